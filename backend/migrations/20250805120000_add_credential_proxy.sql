@@ -0,0 +1,2 @@
+-- Optional SOCKS5 proxy (host:port) used to reach the node, e.g. a Tor daemon
+ALTER TABLE credentials ADD COLUMN proxy TEXT DEFAULT NULL;
@@ -0,0 +1,2 @@
+-- Whether the stored macaroon/rune only grants read access to the node
+ALTER TABLE credentials ADD COLUMN read_only BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- CLN rune for nodes reached over commando instead of gRPC mTLS
+ALTER TABLE credentials ADD COLUMN rune TEXT DEFAULT NULL;
@@ -0,0 +1,2 @@
+-- Supports keyset pagination of an account's events ordered by (timestamp, id)
+CREATE INDEX IF NOT EXISTS idx_events_account_timestamp_id ON events(account_id, timestamp, id);
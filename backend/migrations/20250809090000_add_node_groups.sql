@@ -0,0 +1,44 @@
+-- Node groups tag an account's nodes so members can be limited to a subset of them
+CREATE TABLE IF NOT EXISTS node_groups (
+    id TEXT PRIMARY KEY,
+    account_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT DEFAULT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    is_deleted BOOLEAN NOT NULL DEFAULT 0,
+    deleted_at DATETIME DEFAULT NULL,
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_node_groups_account_id ON node_groups(account_id);
+CREATE UNIQUE INDEX idx_node_groups_account_name ON node_groups(account_id, name) WHERE is_deleted = 0;
+
+CREATE TRIGGER node_groups_updated_at
+    AFTER UPDATE ON node_groups
+    FOR EACH ROW
+    WHEN NEW.updated_at = OLD.updated_at
+BEGIN
+    UPDATE node_groups SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+
+CREATE TABLE IF NOT EXISTS node_group_nodes (
+    group_id TEXT NOT NULL,
+    node_id TEXT NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (group_id, node_id),
+    FOREIGN KEY (group_id) REFERENCES node_groups(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_node_group_nodes_node_id ON node_group_nodes(node_id);
+
+CREATE TABLE IF NOT EXISTS node_group_members (
+    group_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (group_id, user_id),
+    FOREIGN KEY (group_id) REFERENCES node_groups(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_node_group_members_user_id ON node_group_members(user_id);
@@ -0,0 +1,2 @@
+-- Recipient of notifications delivered to a person rather than a URL, e.g. a Nostr npub
+ALTER TABLE notifications ADD COLUMN recipient TEXT DEFAULT NULL;
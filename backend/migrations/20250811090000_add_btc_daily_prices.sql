@@ -0,0 +1,8 @@
+-- Daily BTC closing prices, cached lazily to value historical payments
+CREATE TABLE IF NOT EXISTS btc_daily_prices (
+    date TEXT NOT NULL,           -- UTC day, YYYY-MM-DD
+    currency TEXT NOT NULL,
+    close REAL NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (date, currency)
+);
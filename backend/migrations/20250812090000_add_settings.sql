@@ -0,0 +1,11 @@
+-- Account-scoped settings, one JSON encoded value per key
+CREATE TABLE IF NOT EXISTS settings (
+    account_id TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, key),
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_settings_key ON settings(key);
@@ -0,0 +1,13 @@
+-- Display metadata users attach to an account's nodes
+CREATE TABLE IF NOT EXISTS node_labels (
+    account_id TEXT NOT NULL,
+    node_id TEXT NOT NULL,
+    display_name TEXT DEFAULT NULL,
+    color TEXT DEFAULT NULL,        -- #rrggbb
+    environment TEXT DEFAULT NULL,  -- prod or test
+    notes TEXT DEFAULT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, node_id),
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
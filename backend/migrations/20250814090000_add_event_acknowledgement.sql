@@ -0,0 +1,5 @@
+-- Warning and critical events are acknowledged once someone has handled them
+ALTER TABLE events ADD COLUMN acknowledged_by TEXT DEFAULT NULL REFERENCES users(id);
+ALTER TABLE events ADD COLUMN acknowledged_at DATETIME DEFAULT NULL;
+
+CREATE INDEX idx_events_acknowledged_at ON events(acknowledged_at);
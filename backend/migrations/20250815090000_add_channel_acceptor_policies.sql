@@ -0,0 +1,11 @@
+-- Rules deciding on inbound channel requests to an account's LND nodes
+CREATE TABLE IF NOT EXISTS channel_acceptor_policies (
+    account_id TEXT PRIMARY KEY NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT 0,
+    min_capacity_sat INTEGER DEFAULT NULL,
+    reject_private BOOLEAN NOT NULL DEFAULT 0,
+    blocked_pubkeys TEXT NOT NULL DEFAULT '[]', -- JSON array of hex pubkeys
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
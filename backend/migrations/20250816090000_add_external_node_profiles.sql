@@ -0,0 +1,6 @@
+-- External node metadata (Amboss, 1ML), cached to spare the third-party APIs
+CREATE TABLE IF NOT EXISTS external_node_profiles (
+    pubkey TEXT PRIMARY KEY NOT NULL,
+    profile TEXT NOT NULL,        -- JSON ExternalNodeProfile
+    fetched_at DATETIME NOT NULL
+);
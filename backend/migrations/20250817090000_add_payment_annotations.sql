@@ -0,0 +1,11 @@
+-- Tags and notes users attach to payments and invoices
+CREATE TABLE IF NOT EXISTS payment_annotations (
+    account_id TEXT NOT NULL,
+    payment_hash TEXT NOT NULL,         -- lowercase hex
+    tags TEXT NOT NULL DEFAULT '[]',    -- JSON array of tags
+    notes TEXT DEFAULT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, payment_hash),
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
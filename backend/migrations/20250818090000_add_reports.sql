@@ -0,0 +1,26 @@
+-- Periodic node reports, one per node, frequency and period
+CREATE TABLE IF NOT EXISTS reports (
+    id TEXT PRIMARY KEY NOT NULL,
+    account_id TEXT NOT NULL,
+    node_id TEXT NOT NULL,
+    node_alias TEXT NOT NULL,
+    frequency TEXT NOT NULL,            -- weekly or monthly
+    period_start DATETIME NOT NULL,
+    period_end DATETIME NOT NULL,
+    summary TEXT NOT NULL,              -- JSON encoded report figures
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (account_id, node_id, frequency, period_start),
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_reports_account_period ON reports(account_id, period_start);
+
+-- Hourly reachability probes of each node, for report uptime
+CREATE TABLE IF NOT EXISTS node_availability_samples (
+    account_id TEXT NOT NULL,
+    node_id TEXT NOT NULL,
+    sampled_at DATETIME NOT NULL,
+    reachable BOOLEAN NOT NULL,
+    PRIMARY KEY (account_id, node_id, sampled_at),
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
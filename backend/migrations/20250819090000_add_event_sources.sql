@@ -0,0 +1,15 @@
+-- External systems allowed to push events through /api/events/ingest
+CREATE TABLE IF NOT EXISTS event_sources (
+    id TEXT PRIMARY KEY NOT NULL,
+    account_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    node_id TEXT DEFAULT NULL,
+    created_by TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,    -- hex SHA-256 of the ingest token
+    last_used_at DATETIME DEFAULT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE,
+    FOREIGN KEY (created_by) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_event_sources_account ON event_sources(account_id);
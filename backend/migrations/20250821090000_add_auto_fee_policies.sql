@@ -0,0 +1,34 @@
+-- Balance-driven fee policies for individual channels of an account's nodes
+CREATE TABLE IF NOT EXISTS auto_fee_policies (
+    account_id TEXT NOT NULL,
+    node_id TEXT NOT NULL,
+    channel_id TEXT NOT NULL,           -- numeric short channel id
+    enabled BOOLEAN NOT NULL DEFAULT 0,
+    target_local_ratio REAL NOT NULL,   -- local balance share the policy steers towards
+    base_fee_ppm INTEGER NOT NULL,      -- fee charged at the target
+    min_fee_ppm INTEGER NOT NULL,       -- fee when the channel is full of local balance
+    max_fee_ppm INTEGER NOT NULL,       -- fee when the channel is depleted
+    max_step_ppm INTEGER NOT NULL,      -- largest change made in one run
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, node_id, channel_id),
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+-- Every fee change attempted by the auto-fee scheduler
+CREATE TABLE IF NOT EXISTS auto_fee_adjustments (
+    id TEXT PRIMARY KEY NOT NULL,
+    account_id TEXT NOT NULL,
+    node_id TEXT NOT NULL,
+    channel_id TEXT NOT NULL,
+    local_ratio REAL NOT NULL,
+    old_fee_ppm INTEGER NOT NULL,
+    new_fee_ppm INTEGER NOT NULL,
+    applied BOOLEAN NOT NULL,
+    error TEXT DEFAULT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_auto_fee_adjustments_node
+ON auto_fee_adjustments (account_id, node_id, created_at);
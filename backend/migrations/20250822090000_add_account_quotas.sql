@@ -0,0 +1,19 @@
+-- Limits set by the operator of a hosted instance; NULL means unlimited
+CREATE TABLE IF NOT EXISTS account_quotas (
+    account_id TEXT PRIMARY KEY NOT NULL,
+    max_nodes INTEGER DEFAULT NULL,
+    max_notifications INTEGER DEFAULT NULL,
+    max_events INTEGER DEFAULT NULL,
+    max_api_requests_per_day INTEGER DEFAULT NULL,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+-- Authenticated API requests per account and UTC day
+CREATE TABLE IF NOT EXISTS api_usage (
+    account_id TEXT NOT NULL,
+    day TEXT NOT NULL,
+    request_count INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (account_id, day),
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
@@ -0,0 +1,10 @@
+-- Full node message an event was built from, kept for accounts that enable raw capture
+CREATE TABLE IF NOT EXISTS event_raw (
+    event_id TEXT PRIMARY KEY NOT NULL,
+    source TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    truncated BOOLEAN NOT NULL DEFAULT 0,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (event_id) REFERENCES events(id) ON DELETE CASCADE
+);
@@ -0,0 +1,74 @@
+-- Usernames, emails and account names only need to be unique among rows that
+-- aren't soft-deleted. SQLite can't drop a column constraint, so both tables are
+-- rebuilt with partial unique indexes instead. The migration runner switches
+-- foreign keys off, so dropping the old tables doesn't cascade to the rows
+-- referencing them.
+
+CREATE TABLE accounts_new (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    is_active BOOLEAN NOT NULL DEFAULT 1,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    is_deleted BOOLEAN NOT NULL DEFAULT 0,
+    deleted_at DATETIME DEFAULT NULL
+);
+
+INSERT INTO accounts_new (id, name, is_active, created_at, updated_at, is_deleted, deleted_at)
+SELECT id, name, is_active, created_at, updated_at, is_deleted, deleted_at FROM accounts;
+
+DROP TABLE accounts;
+ALTER TABLE accounts_new RENAME TO accounts;
+
+CREATE UNIQUE INDEX idx_accounts_name ON accounts(name) WHERE is_deleted = 0;
+
+CREATE TRIGGER accounts_updated_at
+    AFTER UPDATE ON accounts
+    FOR EACH ROW
+    WHEN NEW.updated_at = OLD.updated_at
+BEGIN
+    UPDATE accounts SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+
+CREATE TABLE users_new (
+    id TEXT PRIMARY KEY,
+    account_id TEXT NOT NULL,
+    username TEXT NOT NULL,
+    password_hash TEXT NOT NULL,
+    email TEXT NOT NULL,
+    role_id TEXT NOT NULL,
+    role_access_level TEXT NOT NULL DEFAULT 'Read', -- Default access level
+    is_active BOOLEAN NOT NULL DEFAULT 1,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    is_deleted BOOLEAN NOT NULL DEFAULT 0,
+    deleted_at DATETIME DEFAULT NULL,
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE,
+    FOREIGN KEY (role_id) REFERENCES roles(id) ON DELETE SET NULL
+);
+
+INSERT INTO users_new (
+    id, account_id, username, password_hash, email, role_id, role_access_level,
+    is_active, created_at, updated_at, is_deleted, deleted_at
+)
+SELECT
+    id, account_id, username, password_hash, email, role_id, role_access_level,
+    is_active, created_at, updated_at, is_deleted, deleted_at
+FROM users;
+
+DROP TABLE users;
+ALTER TABLE users_new RENAME TO users;
+
+CREATE INDEX idx_users_account_id ON users(account_id);
+CREATE UNIQUE INDEX idx_users_username ON users(username) WHERE is_deleted = 0;
+CREATE UNIQUE INDEX idx_users_email ON users(email) WHERE is_deleted = 0;
+CREATE INDEX idx_users_role_id ON users(role_id);
+CREATE INDEX idx_users_role_access_level ON users(role_access_level);
+
+CREATE TRIGGER users_updated_at
+    AFTER UPDATE ON users
+    FOR EACH ROW
+    WHEN NEW.updated_at = OLD.updated_at
+BEGIN
+    UPDATE users SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
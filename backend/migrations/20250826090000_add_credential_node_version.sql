@@ -0,0 +1,3 @@
+-- Version string the node reported when the credential was stored, e.g.
+-- "0.18.3-beta" for LND or "v24.08.1" for CLN
+ALTER TABLE credentials ADD COLUMN node_version TEXT;
@@ -0,0 +1,8 @@
+-- Escalation steps already run for a critical alert, so each runs once
+CREATE TABLE IF NOT EXISTS event_escalations (
+    event_id TEXT NOT NULL,
+    step INTEGER NOT NULL,
+    escalated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (event_id, step),
+    FOREIGN KEY (event_id) REFERENCES events(id) ON DELETE CASCADE
+);
@@ -0,0 +1,29 @@
+-- Queue and log of topic webhooks such as `invoice.settled`. Deliveries are
+-- retried until they succeed or run out of attempts, and kept afterwards as
+-- the delivery log.
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+    id TEXT PRIMARY KEY NOT NULL,
+    account_id TEXT NOT NULL,
+    node_id TEXT NOT NULL,
+    topic TEXT NOT NULL,
+    idempotency_key TEXT NOT NULL,
+    ordering_key TEXT NOT NULL,
+    url TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_error TEXT,
+    last_status_code INTEGER,
+    delivered_at DATETIME,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+-- An occurrence is queued once per endpoint, however often it is reported
+CREATE UNIQUE INDEX IF NOT EXISTS idx_webhook_deliveries_idempotency
+    ON webhook_deliveries(account_id, url, idempotency_key);
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_due
+    ON webhook_deliveries(status, next_attempt_at);
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_account
+    ON webhook_deliveries(account_id, created_at);
@@ -0,0 +1,17 @@
+-- Last seen routing policy of each side of the channels of an account's nodes
+CREATE TABLE IF NOT EXISTS channel_policy_snapshots (
+    account_id TEXT NOT NULL,
+    node_id TEXT NOT NULL,
+    channel_id TEXT NOT NULL,           -- numeric short channel id
+    side TEXT NOT NULL,                 -- 'local' or 'remote'
+    fee_base_msat INTEGER NOT NULL,
+    fee_rate_ppm INTEGER NOT NULL,
+    min_htlc_msat INTEGER NOT NULL,
+    max_htlc_msat INTEGER DEFAULT NULL,
+    time_lock_delta INTEGER NOT NULL,
+    disabled BOOLEAN NOT NULL,
+    last_update INTEGER DEFAULT NULL,   -- gossip timestamp, unix seconds
+    observed_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, node_id, channel_id, side),
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
@@ -0,0 +1,21 @@
+-- Payment hashes watched for their payment settling or failing, e.g. on behalf
+-- of an external order system
+CREATE TABLE IF NOT EXISTS payment_watches (
+    id TEXT PRIMARY KEY NOT NULL,
+    account_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    payment_hash TEXT NOT NULL,
+    callback_url TEXT DEFAULT NULL,
+    node_ids TEXT DEFAULT NULL,         -- JSON array of the nodes searched, NULL for all
+    status TEXT NOT NULL DEFAULT 'pending',
+    node_id TEXT DEFAULT NULL,          -- node the payment settled or failed on
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expires_at DATETIME NOT NULL,
+    resolved_at DATETIME DEFAULT NULL,
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_payment_watches_hash
+    ON payment_watches(account_id, payment_hash);
+CREATE INDEX IF NOT EXISTS idx_payment_watches_pending
+    ON payment_watches(status, expires_at);
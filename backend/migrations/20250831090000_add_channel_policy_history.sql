@@ -0,0 +1,29 @@
+-- Every routing policy seen on each side of the channels of an account's nodes,
+-- appended when the snapshot of the side changes
+CREATE TABLE IF NOT EXISTS channel_policy_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id TEXT NOT NULL,
+    node_id TEXT NOT NULL,
+    channel_id TEXT NOT NULL,           -- numeric short channel id
+    side TEXT NOT NULL,                 -- 'local' or 'remote'
+    fee_base_msat INTEGER NOT NULL,
+    fee_rate_ppm INTEGER NOT NULL,
+    min_htlc_msat INTEGER NOT NULL,
+    max_htlc_msat INTEGER DEFAULT NULL,
+    time_lock_delta INTEGER NOT NULL,
+    disabled BOOLEAN NOT NULL,
+    last_update INTEGER DEFAULT NULL,   -- gossip timestamp, unix seconds
+    observed_at DATETIME NOT NULL,
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_channel_policy_history_channel
+    ON channel_policy_history(account_id, node_id, channel_id, observed_at);
+
+-- The snapshots taken so far start the history
+INSERT INTO channel_policy_history
+    (account_id, node_id, channel_id, side, fee_base_msat, fee_rate_ppm, min_htlc_msat,
+    max_htlc_msat, time_lock_delta, disabled, last_update, observed_at)
+SELECT account_id, node_id, channel_id, side, fee_base_msat, fee_rate_ppm, min_htlc_msat,
+    max_htlc_msat, time_lock_delta, disabled, last_update, observed_at
+FROM channel_policy_snapshots;
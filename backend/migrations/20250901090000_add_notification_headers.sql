@@ -0,0 +1,2 @@
+-- Extra headers of webhook notifications: a JSON object of header names and encrypted values
+ALTER TABLE notifications ADD COLUMN headers TEXT DEFAULT NULL;
@@ -0,0 +1,19 @@
+-- Channels the nodes of an account opened with both peers contributing, so
+-- listings and analytics can tell them apart from single-funded ones
+CREATE TABLE IF NOT EXISTS dual_funded_channels (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id TEXT NOT NULL,
+    node_id TEXT NOT NULL,
+    peer_pubkey TEXT NOT NULL,
+    channel_point TEXT NOT NULL,            -- funding outpoint as txid:vout
+    channel_id TEXT NOT NULL,               -- hex-encoded channel id
+    local_amount_sat INTEGER NOT NULL,
+    requested_amount_sat INTEGER NOT NULL,  -- leased from the peer
+    compact_lease TEXT DEFAULT NULL,        -- terms of the peer's liquidity ad
+    opened_by TEXT NOT NULL,                -- user id
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_dual_funded_channels_channel_point
+    ON dual_funded_channels(node_id, channel_point);
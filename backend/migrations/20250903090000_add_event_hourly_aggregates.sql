@@ -0,0 +1,16 @@
+-- Hourly counts and sums of compacted events, which replace the raw rows of
+-- high-volume event types once they are older than the account's compaction age
+CREATE TABLE IF NOT EXISTS event_hourly_aggregates (
+    account_id TEXT NOT NULL,
+    node_id TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    hour DATETIME NOT NULL,                 -- start of the UTC hour
+    event_count INTEGER NOT NULL,
+    amount_msat INTEGER NOT NULL DEFAULT 0, -- sum of the events' amounts
+    PRIMARY KEY (account_id, node_id, event_type, severity, hour),
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_event_hourly_aggregates_hour
+    ON event_hourly_aggregates(account_id, hour);
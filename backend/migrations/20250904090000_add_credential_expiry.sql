@@ -0,0 +1,5 @@
+-- When the credential stops working: the earliest notAfter of the TLS
+-- certificates it connects with, and the earliest time-before caveat of its
+-- macaroon. NULL when unknown or never.
+ALTER TABLE credentials ADD COLUMN tls_cert_expires_at DATETIME;
+ALTER TABLE credentials ADD COLUMN macaroon_expires_at DATETIME;
@@ -0,0 +1,16 @@
+-- Ping round trips to the connected peers of an account's nodes, sampled
+-- every minute
+CREATE TABLE IF NOT EXISTS peer_pings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id TEXT NOT NULL,
+    node_id TEXT NOT NULL,
+    peer_pubkey TEXT NOT NULL,
+    rtt_us INTEGER NOT NULL,            -- round trip, microseconds
+    recorded_at DATETIME NOT NULL,
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_peer_pings_peer
+    ON peer_pings(account_id, node_id, peer_pubkey, recorded_at);
+CREATE INDEX IF NOT EXISTS idx_peer_pings_recorded_at
+    ON peer_pings(recorded_at);
@@ -0,0 +1,4 @@
+-- Annotations may also be keyed by the txid of an on-chain transaction, whose
+-- notes are synchronized with the label the node keeps for it. The label as of
+-- the last synchronization tells which side changed since.
+ALTER TABLE payment_annotations ADD COLUMN node_label TEXT DEFAULT NULL;
@@ -0,0 +1,14 @@
+-- Administrative changes made to an account, shown in its activity feed
+CREATE TABLE IF NOT EXISTS audit_log (
+    id TEXT PRIMARY KEY,
+    account_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,              -- user who made the change
+    action TEXT NOT NULL,
+    target TEXT,                        -- ID or name of what was changed
+    details TEXT,                       -- JSON encoded
+    created_at DATETIME NOT NULL,
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_account
+    ON audit_log(account_id, created_at, id);
@@ -0,0 +1,2 @@
+-- API LND nodes are reached over, "grpc" or "rest"; NULL for gRPC
+ALTER TABLE credentials ADD COLUMN transport TEXT DEFAULT NULL;
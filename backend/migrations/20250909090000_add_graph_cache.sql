@@ -0,0 +1,35 @@
+-- Each monitored node's view of the channel graph, synced in full now and
+-- then and kept current from its gossip in between
+CREATE TABLE IF NOT EXISTS graph_nodes (
+    node_id TEXT NOT NULL,              -- monitored node whose view this is
+    pubkey TEXT NOT NULL,
+    alias TEXT NOT NULL,
+    color TEXT,
+    last_update INTEGER,                -- unix time of the announcement
+    addresses TEXT NOT NULL,            -- JSON array of host:port
+    PRIMARY KEY (node_id, pubkey)
+);
+
+CREATE TABLE IF NOT EXISTS graph_edges (
+    node_id TEXT NOT NULL,
+    channel_id INTEGER NOT NULL,        -- numeric short channel id
+    node1 TEXT NOT NULL,                -- endpoint with the smaller pubkey
+    node2 TEXT NOT NULL,
+    capacity_sat INTEGER NOT NULL,
+    node1_base_fee_msat INTEGER,        -- fees are NULL while a side is disabled
+    node1_fee_rate_ppm INTEGER,
+    node1_last_update INTEGER,
+    node2_base_fee_msat INTEGER,
+    node2_fee_rate_ppm INTEGER,
+    node2_last_update INTEGER,
+    PRIMARY KEY (node_id, channel_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_graph_edges_node1 ON graph_edges(node_id, node1);
+CREATE INDEX IF NOT EXISTS idx_graph_edges_node2 ON graph_edges(node_id, node2);
+
+CREATE TABLE IF NOT EXISTS graph_syncs (
+    node_id TEXT PRIMARY KEY,
+    synced_at DATETIME NOT NULL,        -- last full fetch
+    updated_at DATETIME NOT NULL        -- last change applied
+);
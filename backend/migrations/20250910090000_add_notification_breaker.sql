@@ -0,0 +1,5 @@
+-- Circuit breaker of notification endpoints: deliveries failed in a row, the
+-- last error and when deliveries were paused
+ALTER TABLE notifications ADD COLUMN consecutive_failures INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE notifications ADD COLUMN last_error TEXT DEFAULT NULL;
+ALTER TABLE notifications ADD COLUMN paused_at DATETIME DEFAULT NULL;
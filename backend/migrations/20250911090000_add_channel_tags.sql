@@ -0,0 +1,26 @@
+-- Tags grouping the channels of an account's nodes, with the fee policy and
+-- alert thresholds applied to the channels they tag
+CREATE TABLE IF NOT EXISTS channel_tags (
+    account_id TEXT NOT NULL,
+    name TEXT NOT NULL COLLATE NOCASE,
+    fee_policy TEXT DEFAULT NULL,               -- JSON auto-fee policy
+    alert_thresholds TEXT NOT NULL DEFAULT '{}', -- JSON channel alert thresholds
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, name),
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+-- Channels carrying a tag
+CREATE TABLE IF NOT EXISTS channel_tag_assignments (
+    account_id TEXT NOT NULL,
+    tag TEXT NOT NULL COLLATE NOCASE,
+    node_id TEXT NOT NULL,
+    channel_id TEXT NOT NULL,           -- numeric short channel id
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, tag, node_id, channel_id),
+    FOREIGN KEY (account_id, tag) REFERENCES channel_tags(account_id, name) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_channel_tag_assignments_node
+ON channel_tag_assignments (account_id, node_id);
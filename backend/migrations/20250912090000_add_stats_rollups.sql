@@ -0,0 +1,41 @@
+-- Payment figures of each UTC hour of a node, read by the payment statistics
+CREATE TABLE IF NOT EXISTS payment_hourly_rollups (
+    account_id TEXT NOT NULL,
+    node_id TEXT NOT NULL,
+    hour DATETIME NOT NULL,                 -- start of the UTC hour
+    outgoing_count INTEGER NOT NULL,
+    outgoing_sat INTEGER NOT NULL,
+    incoming_count INTEGER NOT NULL,
+    incoming_sat INTEGER NOT NULL,
+    forwarded_count INTEGER NOT NULL,
+    forwarded_sat INTEGER NOT NULL,
+    failed_outgoing INTEGER NOT NULL,
+    fee_revenue_msat INTEGER NOT NULL,
+    PRIMARY KEY (account_id, node_id, hour),
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+-- Event counts of each UTC hour of a node by severity, read by the event statistics
+CREATE TABLE IF NOT EXISTS event_hourly_rollups (
+    account_id TEXT NOT NULL,
+    node_id TEXT NOT NULL,
+    hour DATETIME NOT NULL,                 -- start of the UTC hour
+    severity TEXT NOT NULL,
+    event_count INTEGER NOT NULL,
+    PRIMARY KEY (account_id, node_id, hour, severity),
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+-- When each set of rollups was last brought up to date
+CREATE TABLE IF NOT EXISTS stats_rollup_refreshes (
+    account_id TEXT NOT NULL,
+    kind TEXT NOT NULL,                     -- payments or events
+    node_id TEXT NOT NULL DEFAULT '',       -- empty for the account-wide event rollups
+    refreshed_at DATETIME NOT NULL,
+    PRIMARY KEY (account_id, kind, node_id),
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+-- Finding the events added or deleted since the last refresh
+CREATE INDEX IF NOT EXISTS idx_events_created_at ON events(account_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_events_deleted_at ON events(account_id, deleted_at);
@@ -0,0 +1,3 @@
+-- Bitcoin network the node reported when the credential was stored, e.g.
+-- "bitcoin", "testnet", "signet" or "regtest"
+ALTER TABLE credentials ADD COLUMN network TEXT;
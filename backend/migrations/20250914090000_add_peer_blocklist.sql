@@ -0,0 +1,12 @@
+-- Peers whose events an account doesn't want to be alerted about, such as
+-- probing bots that keep connecting and disconnecting
+CREATE TABLE IF NOT EXISTS peer_blocklist (
+    account_id TEXT NOT NULL,
+    pubkey TEXT NOT NULL,               -- lowercase hex node public key
+    action TEXT NOT NULL DEFAULT 'suppress', -- suppress, downgrade
+    note TEXT DEFAULT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, pubkey),
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
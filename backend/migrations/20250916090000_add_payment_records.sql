@@ -0,0 +1,16 @@
+-- Payments and forwards counted by the payment statistics, read for the parts
+-- of a window that hourly rollups don't cover
+CREATE TABLE IF NOT EXISTS payment_records (
+    account_id TEXT NOT NULL,
+    node_id TEXT NOT NULL,
+    at DATETIME NOT NULL,                   -- completion time, or creation time when never completed
+    kind TEXT NOT NULL,                     -- outgoing, incoming, forwarded or failed_outgoing
+    amount_sat INTEGER NOT NULL,
+    fee_msat INTEGER NOT NULL,              -- fee earned, for forwards
+    FOREIGN KEY (account_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_payment_records_node_at ON payment_records(account_id, node_id, at);
+
+-- Recompute payment rollups from the start so the records are filled in too
+DELETE FROM stats_rollup_refreshes WHERE kind = 'payments';
@@ -3,26 +3,37 @@
 //! These functions process requests for account data, interact with the database
 //! or relevant services, and return account-specific information.
 
-use crate::api::common::{
-    ApiResponse, PaginatedData, PaginationFilter, PaginationMeta, service_error_to_http,
+use crate::api::common::{ApiError, ApiResponse, PaginatedData, PaginationFilter, PaginationMeta};
+use crate::database::models::{
+    Account, AccountSettings, AccountUsage, AuditAction, ChannelAcceptorPolicy, CreateNewAccount,
+    EventCursor, User, UserWithAccount,
 };
-use crate::database::models::{Account, CreateNewAccount, User, UserWithAccount};
 use crate::services::account_service::AccountService;
+use crate::services::activity_feed::{ActivityPage, ActivityService};
+use crate::services::channel_acceptor::ChannelAcceptorService;
+use crate::services::data_aggregator::{AccountDashboard, DataAggregator};
+use crate::services::quota_service::QuotaService;
+use crate::services::settings_service::SettingsService;
 use crate::services::user_service::UserService;
+use crate::utils::handlers_common::require_admin;
 use crate::utils::jwt::Claims;
 use axum::extract::Query;
 use axum::{
     extract::{Extension, Json},
-    http::StatusCode,
     response::Json as ResponseJson,
 };
+use serde::Deserialize;
 use sqlx::SqlitePool;
 
+/// Entries per page of the activity feed when the request doesn't say.
+const DEFAULT_ACTIVITY_PER_PAGE: u32 = 20;
+const MAX_ACTIVITY_PER_PAGE: u32 = 100;
+
 #[axum::debug_handler]
 pub async fn create_account(
     Extension(pool): Extension<SqlitePool>,
     Json(payload): Json<CreateNewAccount>,
-) -> Result<ResponseJson<ApiResponse<UserWithAccount>>, (StatusCode, String)> {
+) -> Result<ResponseJson<ApiResponse<UserWithAccount>>, ApiError> {
     tracing::info!("Creating new account with payload: {:?}", payload);
 
     let service = AccountService::new(&pool);
@@ -35,7 +46,7 @@ pub async fn create_account(
                 "Account created successfully",
             )))
         }
-        Err(error) => Err(service_error_to_http(error)),
+        Err(error) => Err(error.into()),
     }
 }
 
@@ -44,7 +55,7 @@ pub async fn create_account(
 pub async fn get_account(
     Extension(claims): Extension<Claims>,
     Extension(pool): Extension<SqlitePool>,
-) -> Result<Json<ApiResponse<Account>>, (StatusCode, String)> {
+) -> Result<Json<ApiResponse<Account>>, ApiError> {
     let account_id = claims.account_id.as_str().to_string();
 
     tracing::info!("Getting account Details: {}", account_id);
@@ -55,15 +66,7 @@ pub async fn get_account(
         .await
         .map_err(|e| {
             tracing::error!("Account not found for ID {}: {}", account_id, e);
-            let error_response = ApiResponse::<()>::error(
-                "Account not found".to_string(),
-                "account_not_found",
-                None,
-            );
-            (
-                StatusCode::NOT_FOUND,
-                serde_json::to_string(&error_response).unwrap(),
-            )
+            ApiError::not_found("account_not_found", "Account not found")
         })?;
 
     tracing::info!("Account found: {}", account.id);
@@ -79,7 +82,7 @@ pub async fn get_account(
 pub async fn get_account_admin_user(
     Extension(claims): Extension<Claims>,
     Extension(pool): Extension<SqlitePool>,
-) -> Result<Json<ApiResponse<User>>, (StatusCode, String)> {
+) -> Result<Json<ApiResponse<User>>, ApiError> {
     let account_id = claims.account_id.as_str().to_string();
     let user_service = UserService::new(&pool);
 
@@ -90,15 +93,7 @@ pub async fn get_account_admin_user(
         .await
         .map_err(|e| {
             tracing::error!("Admin user not found for account ID {}: {}", account_id, e);
-            let error_response = ApiResponse::<()>::error(
-                "Admin user not found".to_string(),
-                "admin_user_not_found",
-                None,
-            );
-            (
-                StatusCode::NOT_FOUND,
-                serde_json::to_string(&error_response).unwrap(),
-            )
+            ApiError::not_found("admin_user_not_found", "Admin user not found")
         })?;
 
     tracing::info!("Admin user found: {}", user.id);
@@ -115,7 +110,7 @@ pub async fn get_account_users(
     Extension(claims): Extension<Claims>,
     Extension(pool): Extension<SqlitePool>,
     Query(pagination): Query<PaginationFilter>,
-) -> Result<Json<ApiResponse<PaginatedData<User>>>, (StatusCode, String)> {
+) -> Result<Json<ApiResponse<PaginatedData<User>>>, ApiError> {
     let account_id = claims.account_id.as_str().to_string();
     let user_service = UserService::new(&pool);
 
@@ -131,12 +126,7 @@ pub async fn get_account_users(
         .await
         .map_err(|e| {
             tracing::error!("Users not found for account ID {}: {}", account_id, e);
-            let error_response =
-                ApiResponse::<()>::error("Users not found".to_string(), "users_not_found", None);
-            (
-                StatusCode::NOT_FOUND,
-                serde_json::to_string(&error_response).unwrap(),
-            )
+            ApiError::not_found("users_not_found", "Users not found")
         })?;
 
     let paginated_data = PaginatedData::new(users, total_count);
@@ -153,3 +143,167 @@ pub async fn get_account_users(
         pagination_meta,
     )))
 }
+
+/// Reports the account's consumption against each of its quotas.
+#[axum::debug_handler]
+pub async fn get_account_usage(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<SqlitePool>,
+) -> Result<Json<ApiResponse<AccountUsage>>, ApiError> {
+    let usage = QuotaService::new(&pool)
+        .get_usage(&claims.account_id)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        usage,
+        "Account usage retrieved successfully",
+    )))
+}
+
+/// Retrieves the dashboard summary across every node in the account.
+#[axum::debug_handler]
+pub async fn get_account_dashboard(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<SqlitePool>,
+) -> Result<Json<ApiResponse<AccountDashboard>>, ApiError> {
+    tracing::info!("Building dashboard for account: {}", claims.account_id);
+
+    let aggregator = DataAggregator::new(&pool);
+    let dashboard = aggregator
+        .get_account_dashboard(&claims.account_id, &claims.sub)
+        .await?;
+
+    if !dashboard.unavailable_sources.is_empty() {
+        tracing::warn!(
+            "Dashboard for account {} is missing sources: {:?}",
+            claims.account_id,
+            dashboard.unavailable_sources
+        );
+    }
+
+    Ok(Json(ApiResponse::success(
+        dashboard,
+        "Account dashboard retrieved successfully",
+    )))
+}
+
+/// Retrieves the account's settings.
+#[axum::debug_handler]
+pub async fn get_account_settings(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<SqlitePool>,
+) -> Result<Json<ApiResponse<AccountSettings>>, ApiError> {
+    let settings = SettingsService::new(&pool)
+        .get_settings(&claims.account_id)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        settings,
+        "Account settings retrieved successfully",
+    )))
+}
+
+/// Replaces the account's settings; fields left out are reset to their defaults.
+#[axum::debug_handler]
+pub async fn update_account_settings(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<SqlitePool>,
+    Json(payload): Json<AccountSettings>,
+) -> Result<Json<ApiResponse<AccountSettings>>, ApiError> {
+    require_admin(&claims, "change account settings")?;
+
+    tracing::info!("Updating settings for account: {}", claims.account_id);
+
+    let settings = SettingsService::new(&pool)
+        .update_settings(&claims.account_id, payload)
+        .await?;
+    ActivityService::new(&pool)
+        .record(&claims, AuditAction::SettingsUpdated, None, None)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        settings,
+        "Account settings updated successfully",
+    )))
+}
+
+/// Retrieves the rules applied to inbound channel requests on the account's LND nodes.
+#[axum::debug_handler]
+pub async fn get_channel_acceptor_policy(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<SqlitePool>,
+) -> Result<Json<ApiResponse<ChannelAcceptorPolicy>>, ApiError> {
+    let policy = ChannelAcceptorService::new(&pool)
+        .get_policy(&claims.account_id)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        policy,
+        "Channel acceptor policy retrieved successfully",
+    )))
+}
+
+/// Replaces the rules applied to inbound channel requests.
+#[axum::debug_handler]
+pub async fn update_channel_acceptor_policy(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<SqlitePool>,
+    Json(payload): Json<ChannelAcceptorPolicy>,
+) -> Result<Json<ApiResponse<ChannelAcceptorPolicy>>, ApiError> {
+    require_admin(&claims, "change the channel acceptor policy")?;
+
+    tracing::info!(
+        "Updating channel acceptor policy for account: {}",
+        claims.account_id
+    );
+
+    let policy = ChannelAcceptorService::new(&pool)
+        .update_policy(&claims.account_id, payload)
+        .await?;
+    ActivityService::new(&pool)
+        .record(&claims, AuditAction::ChannelAcceptorUpdated, None, None)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        policy,
+        "Channel acceptor policy updated successfully",
+    )))
+}
+
+/// Query parameters for the account activity feed.
+#[derive(Debug, Deserialize)]
+pub struct ActivityQuery {
+    /// `next_cursor` from the previous page; omit for the newest activity
+    pub cursor: Option<String>,
+    /// Number of entries per page
+    pub limit: Option<u32>,
+}
+
+/// Retrieves the account's recent activity, newest first: audit log entries,
+/// critical events and membership changes.
+#[axum::debug_handler]
+pub async fn get_account_activity(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<SqlitePool>,
+    Query(query): Query<ActivityQuery>,
+) -> Result<ResponseJson<ApiResponse<ActivityPage>>, ApiError> {
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(str::parse::<EventCursor>)
+        .transpose()
+        .map_err(|e| ApiError::bad_request("invalid_cursor", e))?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_ACTIVITY_PER_PAGE)
+        .clamp(1, MAX_ACTIVITY_PER_PAGE);
+
+    let page = ActivityService::new(&pool)
+        .get_activity(&claims, cursor, limit as usize)
+        .await?;
+
+    Ok(ResponseJson(ApiResponse::success(
+        page,
+        "Account activity retrieved successfully",
+    )))
+}
@@ -3,7 +3,11 @@
 //! These routes provide endpoints for accessing and updating account-specific
 //! data.
 
-use super::handlers::{create_account, get_account, get_account_admin_user, get_account_users};
+use super::handlers::{
+    create_account, get_account, get_account_activity, get_account_admin_user,
+    get_account_dashboard, get_account_settings, get_account_usage, get_account_users,
+    get_channel_acceptor_policy, update_account_settings, update_channel_acceptor_policy,
+};
 use crate::auth::middleware::jwt_auth;
 use axum::{
     Router, middleware,
@@ -25,4 +29,28 @@ pub async fn account_router() -> Router {
             "/get-account-users",
             get(get_account_users).layer(middleware::from_fn(jwt_auth)),
         )
+        .route(
+            "/dashboard",
+            get(get_account_dashboard).layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/usage",
+            get(get_account_usage).layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/settings",
+            get(get_account_settings)
+                .put(update_account_settings)
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/activity",
+            get(get_account_activity).layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/channel-acceptor",
+            get(get_channel_acceptor_policy)
+                .put(update_channel_acceptor_policy)
+                .layer(middleware::from_fn(jwt_auth)),
+        )
 }
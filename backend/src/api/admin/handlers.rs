@@ -0,0 +1,167 @@
+//! Handler functions for account administration API endpoints.
+//!
+//! These functions check that the caller is an account admin before handing
+//! the request to the relevant service. Endpoints about the whole server
+//! require an operator instead, as account admins only manage their account.
+
+use crate::api::common::{ApiError, ApiResponse};
+use crate::config::AppConfig;
+use crate::database::models::{Credential, User};
+use crate::services::credential_service::CredentialService;
+use crate::services::task_supervisor::{self, TaskStatus};
+use crate::services::user_service::UserService;
+use crate::utils::handlers_common::{require_admin, require_operator};
+use crate::utils::jwt::Claims;
+use crate::utils::logging;
+use axum::extract::{Extension, Json, Path};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// A restored credential, without its secrets.
+#[derive(Debug, Serialize)]
+pub struct RestoredCredential {
+    pub id: String,
+    pub user_id: String,
+    pub node_id: String,
+    pub node_alias: String,
+    pub node_type: Option<String>,
+    pub read_only: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Credential> for RestoredCredential {
+    fn from(credential: Credential) -> Self {
+        Self {
+            id: credential.id,
+            user_id: credential.user_id,
+            node_id: credential.node_id,
+            node_alias: credential.node_alias,
+            node_type: credential.node_type,
+            read_only: credential.read_only,
+            created_at: credential.created_at,
+        }
+    }
+}
+
+/// The tracing filter, as read by `GET` and set by `PUT /api/admin/logging`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoggingFilter {
+    /// `EnvFilter` directives, e.g. `info,services::node_manager=debug`
+    pub filter: String,
+}
+
+/// Restores a soft-deleted user of the caller's account.
+///
+/// Fails with `409` if an active user has taken the username or email since.
+#[axum::debug_handler]
+pub async fn restore_user(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<SqlitePool>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<User>>, ApiError> {
+    require_admin(&claims, "restore deleted records")?;
+
+    let user = UserService::new(&pool)
+        .restore_user(&claims.account_id, &id)
+        .await?;
+
+    tracing::info!("User {} restored by admin {}", user.id, claims.sub);
+    Ok(Json(ApiResponse::success(
+        user,
+        "User restored successfully",
+    )))
+}
+
+/// Restores a soft-deleted credential of the caller's account.
+///
+/// Fails with `409` if its user has connected another node since.
+#[axum::debug_handler]
+pub async fn restore_credential(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<SqlitePool>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<RestoredCredential>>, ApiError> {
+    require_admin(&claims, "restore deleted records")?;
+
+    let credential = CredentialService::new(&pool)
+        .restore_credential(&claims.account_id, &id)
+        .await?;
+
+    tracing::info!(
+        "Credential {} for node {} restored by admin {}",
+        credential.id,
+        credential.node_id,
+        claims.sub
+    );
+    Ok(Json(ApiResponse::success(
+        credential.into(),
+        "Credential restored successfully",
+    )))
+}
+
+/// Shows the server configuration with its secrets redacted.
+#[axum::debug_handler]
+pub async fn get_config(
+    Extension(claims): Extension<Claims>,
+    Extension(config): Extension<AppConfig>,
+) -> Result<Json<ApiResponse<serde_json::Value>>, ApiError> {
+    require_operator(&claims, &config, "view the server configuration")?;
+
+    Ok(Json(ApiResponse::success(
+        config.redacted(),
+        "Configuration retrieved successfully",
+    )))
+}
+
+/// Returns the tracing filter in effect.
+#[axum::debug_handler]
+pub async fn get_logging(
+    Extension(claims): Extension<Claims>,
+    Extension(config): Extension<AppConfig>,
+) -> Result<Json<ApiResponse<LoggingFilter>>, ApiError> {
+    require_operator(&claims, &config, "view the logging filter")?;
+
+    let filter = logging::current_filter()
+        .ok_or_else(|| ApiError::internal("logging_unavailable", "Logging is not initialized"))?;
+    Ok(Json(ApiResponse::success(
+        LoggingFilter { filter },
+        "Logging filter retrieved successfully",
+    )))
+}
+
+/// Replaces the tracing filter until the next change or restart.
+#[axum::debug_handler]
+pub async fn update_logging(
+    Extension(claims): Extension<Claims>,
+    Extension(config): Extension<AppConfig>,
+    Json(payload): Json<LoggingFilter>,
+) -> Result<Json<ApiResponse<LoggingFilter>>, ApiError> {
+    require_operator(&claims, &config, "change the logging filter")?;
+
+    let filter = logging::set_filter(&payload.filter)
+        .map_err(|e| ApiError::bad_request("invalid_filter", e))?;
+    tracing::info!(
+        "Logging filter set to {} by operator {}",
+        filter,
+        claims.sub
+    );
+    Ok(Json(ApiResponse::success(
+        LoggingFilter { filter },
+        "Logging filter updated successfully",
+    )))
+}
+
+/// Lists the background tasks of the instance and of the caller's account.
+#[axum::debug_handler]
+pub async fn list_tasks(
+    Extension(claims): Extension<Claims>,
+    Extension(config): Extension<AppConfig>,
+) -> Result<Json<ApiResponse<Vec<TaskStatus>>>, ApiError> {
+    require_operator(&claims, &config, "view background tasks")?;
+
+    Ok(Json(ApiResponse::success(
+        task_supervisor::tasks(&claims.account_id),
+        "Background tasks retrieved successfully",
+    )))
+}
@@ -0,0 +1,7 @@
+//! Module for account administration API endpoints.
+//!
+//! This module handles functionalities reserved for account admins, such as
+//! restoring soft-deleted users and credentials.
+
+pub mod handlers;
+pub mod routes;
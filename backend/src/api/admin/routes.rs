@@ -0,0 +1,40 @@
+//! Defines the HTTP routes for account administration.
+//!
+//! Restoring records is restricted to admins of the caller's account, while
+//! the configuration, logging and task routes are restricted to the server's
+//! operators (`OPERATOR_USER_IDS`).
+
+use super::handlers::{
+    get_config, get_logging, list_tasks, restore_credential, restore_user, update_logging,
+};
+use crate::auth::middleware::jwt_auth;
+use axum::{
+    Router, middleware,
+    routing::{get, post},
+};
+
+pub async fn admin_router() -> Router {
+    Router::new()
+        .route(
+            "/config",
+            get(get_config).layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/logging",
+            get(get_logging)
+                .put(update_logging)
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/tasks",
+            get(list_tasks).layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/users/{id}/restore",
+            post(restore_user).layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/credentials/{id}/restore",
+            post(restore_credential).layer(middleware::from_fn(jwt_auth)),
+        )
+}
@@ -0,0 +1,190 @@
+//! Handler functions for figures aggregated across nodes.
+//!
+//! Each endpoint covers every node the caller may see, or one node group when
+//! `group` names it by ID or name.
+
+use crate::api::common::{ApiError, ApiResponse};
+use crate::services::node_aggregate::{
+    AggregateChannels, AggregateEventStats, AggregatePaymentStats, AggregateStuckPayments,
+    NodeAggregateService, NodeSelection,
+};
+use crate::services::payment_stats::{StatsBucket, parse_window, stats_span};
+use crate::utils::handlers_common::request_tz;
+use crate::utils::jwt::Claims;
+use crate::utils::time_zone::DateBound;
+use axum::{
+    Json,
+    extract::{Extension, Query},
+};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use validator::Validate;
+
+/// Window used when the statistics are requested without one.
+const DEFAULT_STATS_WINDOW: &str = "30d";
+
+/// Query parameters selecting the nodes to aggregate.
+#[derive(Debug, Deserialize)]
+pub struct AggregateQuery {
+    /// ID or name of a node group; every visible node when absent
+    pub group: Option<String>,
+    /// Combines nodes on different networks, e.g. mainnet and signet
+    #[serde(default)]
+    pub allow_mixed_networks: bool,
+}
+
+/// Query parameters for the stuck payments report.
+#[derive(Debug, Deserialize, Validate)]
+pub struct StuckPaymentsQuery {
+    /// ID or name of a node group; every visible node when absent
+    pub group: Option<String>,
+    /// Combines nodes on different networks, e.g. mainnet and signet
+    #[serde(default)]
+    pub allow_mixed_networks: bool,
+    /// Minutes a payment has to be in flight, the account's threshold when absent
+    #[validate(range(min = 1, max = 10080, message = "Must be 1-10080 minutes"))]
+    pub minutes: Option<u32>,
+}
+
+/// Query parameters for aggregated statistics.
+#[derive(Debug, Deserialize)]
+pub struct AggregateStatsQuery {
+    /// ID or name of a node group; every visible node when absent
+    pub group: Option<String>,
+    /// Combines nodes on different networks, e.g. mainnet and signet
+    #[serde(default)]
+    pub allow_mixed_networks: bool,
+    /// How far back to look, e.g. `24h`, `30d` or `12w`; ignored when `from` is given
+    pub window: Option<String>,
+    /// Start of the statistics, a date or an RFC 3339 time
+    pub from: Option<DateBound>,
+    /// End of the statistics (inclusive), now when absent
+    pub to: Option<DateBound>,
+    /// IANA time zone for dates and bucket boundaries, the account's when absent
+    pub tz: Option<String>,
+    /// Width of each bucket
+    #[serde(default)]
+    pub bucket: StatsBucket,
+}
+
+impl AggregateStatsQuery {
+    /// Resolves the time span and zone of the statistics.
+    async fn span(
+        &self,
+        pool: &SqlitePool,
+        claims: &Claims,
+    ) -> Result<(DateTime<Utc>, DateTime<Utc>, Tz), ApiError> {
+        let window = parse_window(self.window.as_deref().unwrap_or(DEFAULT_STATS_WINDOW))
+            .map_err(|e| ApiError::bad_request("invalid_window", e))?;
+        let tz = request_tz(pool, &claims.account_id, self.tz.as_deref()).await?;
+        let (start, end) = stats_span(window, self.from, self.to, tz, Utc::now())
+            .map_err(|e| ApiError::bad_request("invalid_window", e))?;
+        Ok((start, end, tz))
+    }
+}
+
+/// Lists the channels of several nodes with their combined totals.
+#[axum::debug_handler]
+pub async fn get_aggregate_channels(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<AggregateQuery>,
+) -> Result<Json<ApiResponse<AggregateChannels>>, ApiError> {
+    let channels = NodeAggregateService::new(&pool)
+        .channels(
+            &claims,
+            NodeSelection {
+                group: query.group.as_deref(),
+                allow_mixed_networks: query.allow_mixed_networks,
+            },
+        )
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        channels,
+        "Aggregated channels retrieved successfully",
+    )))
+}
+
+/// Computes payment statistics over several nodes.
+#[axum::debug_handler]
+pub async fn get_aggregate_payment_stats(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<AggregateStatsQuery>,
+) -> Result<Json<ApiResponse<AggregatePaymentStats>>, ApiError> {
+    let (start, end, tz) = query.span(&pool, &claims).await?;
+    let stats = NodeAggregateService::new(&pool)
+        .payment_stats(
+            &claims,
+            NodeSelection {
+                group: query.group.as_deref(),
+                allow_mixed_networks: query.allow_mixed_networks,
+            },
+            start,
+            end,
+            query.bucket,
+            tz,
+        )
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        stats,
+        "Aggregated payment statistics retrieved successfully",
+    )))
+}
+
+/// Counts the events of several nodes by severity over time.
+#[axum::debug_handler]
+pub async fn get_aggregate_event_stats(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<AggregateStatsQuery>,
+) -> Result<Json<ApiResponse<AggregateEventStats>>, ApiError> {
+    let (start, end, tz) = query.span(&pool, &claims).await?;
+    let stats = NodeAggregateService::new(&pool)
+        .event_stats(
+            &claims,
+            NodeSelection {
+                group: query.group.as_deref(),
+                allow_mixed_networks: query.allow_mixed_networks,
+            },
+            start,
+            end,
+            query.bucket,
+            tz,
+        )
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        stats,
+        "Aggregated event statistics retrieved successfully",
+    )))
+}
+
+/// Lists the outgoing payments of several nodes stuck in flight.
+#[axum::debug_handler]
+pub async fn get_aggregate_stuck_payments(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<StuckPaymentsQuery>,
+) -> Result<Json<ApiResponse<AggregateStuckPayments>>, ApiError> {
+    query.validate()?;
+    let payments = NodeAggregateService::new(&pool)
+        .stuck_payments(
+            &claims,
+            NodeSelection {
+                group: query.group.as_deref(),
+                allow_mixed_networks: query.allow_mixed_networks,
+            },
+            query.minutes,
+        )
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        payments,
+        "Stuck payments retrieved successfully",
+    )))
+}
@@ -0,0 +1,17 @@
+//! Defines the HTTP routes for figures aggregated across nodes.
+
+use super::handlers::{
+    get_aggregate_channels, get_aggregate_event_stats, get_aggregate_payment_stats,
+    get_aggregate_stuck_payments,
+};
+use crate::auth::middleware::jwt_auth;
+use axum::{Router, middleware, routing::get};
+
+pub async fn aggregate_router() -> Router {
+    Router::new()
+        .route("/channels", get(get_aggregate_channels))
+        .route("/payments/stats", get(get_aggregate_payment_stats))
+        .route("/payments/stuck", get(get_aggregate_stuck_payments))
+        .route("/events/stats", get(get_aggregate_event_stats))
+        .layer(middleware::from_fn(jwt_auth))
+}
@@ -0,0 +1,146 @@
+//! Handler functions for auto-fee API endpoints.
+//!
+//! These functions manage the per-channel fee policies of the selected node,
+//! preview what the next scheduled run would change and list past adjustments.
+
+use crate::api::common::{ApiError, ApiResponse};
+use crate::database::models::{AutoFeeAdjustment, AutoFeePolicy, UpdateAutoFeePolicyRequest};
+use crate::services::auto_fees::{AutoFeeService, FeeProposal};
+use crate::utils::ShortChannelID;
+use crate::utils::handlers_common::{SelectedNode, require_write_access};
+use crate::utils::jwt::Claims;
+use axum::{
+    Json,
+    extract::{Extension, Path, Query},
+};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use std::str::FromStr;
+use validator::Validate;
+
+/// Adjustments returned when no limit is given.
+const DEFAULT_ADJUSTMENT_LIMIT: i64 = 100;
+
+/// Query parameters for the adjustment log.
+#[derive(Debug, Deserialize, Validate)]
+pub struct AdjustmentQuery {
+    #[validate(range(min = 1, max = 1000, message = "limit must be between 1 and 1000"))]
+    pub limit: Option<i64>,
+}
+
+fn parse_channel_id(channel_id: &str) -> Result<ShortChannelID, ApiError> {
+    ShortChannelID::from_str(channel_id).map_err(|e| {
+        ApiError::bad_request(
+            "invalid_channel_id",
+            format!("Invalid channel ID format: {e}"),
+        )
+    })
+}
+
+/// Lists the auto-fee policies of the selected node.
+#[axum::debug_handler]
+pub async fn list_policies(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
+) -> Result<Json<ApiResponse<Vec<AutoFeePolicy>>>, ApiError> {
+    let policies = AutoFeeService::new(&pool)
+        .get_policies(&claims.account_id, &node.credentials().node_id)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        policies,
+        "Auto-fee policies retrieved successfully",
+    )))
+}
+
+/// Sets the auto-fee policy of a channel.
+#[axum::debug_handler]
+pub async fn update_policy(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
+    Path(channel_id): Path<String>,
+    Json(payload): Json<UpdateAutoFeePolicyRequest>,
+) -> Result<Json<ApiResponse<AutoFeePolicy>>, ApiError> {
+    require_write_access(&claims, "change auto-fee policies")?;
+    let channel_id = parse_channel_id(&channel_id)?;
+
+    let policy = AutoFeeService::new(&pool)
+        .update_policy(
+            &claims.account_id,
+            &node.credentials().node_id,
+            &channel_id,
+            payload,
+        )
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        policy,
+        "Auto-fee policy updated successfully",
+    )))
+}
+
+/// Removes the auto-fee policy of a channel, leaving its current fee in place.
+#[axum::debug_handler]
+pub async fn delete_policy(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
+    Path(channel_id): Path<String>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    require_write_access(&claims, "change auto-fee policies")?;
+    let channel_id = parse_channel_id(&channel_id)?;
+
+    AutoFeeService::new(&pool)
+        .delete_policy(&claims.account_id, &node.credentials().node_id, &channel_id)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        (),
+        "Auto-fee policy deleted successfully",
+    )))
+}
+
+/// Shows the fee each policy would set on the next run without changing anything.
+#[axum::debug_handler]
+pub async fn preview_adjustments(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
+) -> Result<Json<ApiResponse<Vec<FeeProposal>>>, ApiError> {
+    let node_client = node.client().await?;
+
+    let proposals = AutoFeeService::new(&pool)
+        .preview(&claims.account_id, node_client)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        proposals,
+        "Auto-fee preview generated successfully",
+    )))
+}
+
+/// Lists the fee changes attempted on the selected node, newest first.
+#[axum::debug_handler]
+pub async fn list_adjustments(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
+    Query(query): Query<AdjustmentQuery>,
+) -> Result<Json<ApiResponse<Vec<AutoFeeAdjustment>>>, ApiError> {
+    query.validate()?;
+
+    let adjustments = AutoFeeService::new(&pool)
+        .get_adjustments(
+            &claims.account_id,
+            &node.credentials().node_id,
+            query.limit.unwrap_or(DEFAULT_ADJUSTMENT_LIMIT),
+        )
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        adjustments,
+        "Auto-fee adjustments retrieved successfully",
+    )))
+}
@@ -0,0 +1,49 @@
+//! Defines the HTTP routes for auto-fee management.
+//!
+//! Every route acts on the selected node. Changing a policy also needs node
+//! credentials that can update channel fees.
+
+use super::handlers::{
+    delete_policy, list_adjustments, list_policies, preview_adjustments, update_policy,
+};
+use crate::auth::middleware::{
+    jwt_auth, node_group_access_required, node_selection, node_write_access_required,
+};
+use axum::{
+    Router, middleware,
+    routing::{get, put},
+};
+
+pub async fn auto_fee_router() -> Router {
+    Router::new()
+        .route(
+            "/policies",
+            get(list_policies)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/policies/{channel_id}",
+            put(update_policy)
+                .layer(middleware::from_fn(node_write_access_required))
+                .delete(delete_policy)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/preview",
+            get(preview_adjustments)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/adjustments",
+            get(list_adjustments)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+}
@@ -1,65 +1,591 @@
+use crate::database::models::{DualFundedChannel, OpenDualFundedChannelRequest};
+use crate::errors::{LightningError, ServiceError};
+use crate::repositories::dual_funded_channel_repository::DualFundedChannelRepository;
+use crate::services::channel_flow::{
+    ChannelFlow, DEFAULT_FORECAST_DAYS, LONG_FLOW_WINDOW_DAYS, LiquidityForecast, channel_flows,
+    daily_flows, forecast_liquidity,
+};
+use crate::services::channel_tags::ChannelTagService;
+use crate::services::node_capabilities::{self, NodeFeature, NodeImplementation};
+use crate::services::node_manager::{LightningClient, parse_channel_point};
+use crate::services::policy_history::{PolicyHistory, PolicyHistoryService};
+use crate::utils::amount::Amount;
 use crate::utils::handlers_common::{
-    create_node_client, extract_node_credentials, handle_node_error, parse_public_key,
+    SelectedNode, handle_node_error, parse_public_key, require_write_access,
 };
 use crate::utils::jwt::Claims;
+use crate::utils::mempool::mempool;
 use crate::{
     api::common::{
-        ApiResponse, FilterRequest, NumericOperator, PaginatedData, PaginationFilter,
-        PaginationMeta, apply_pagination, validation_error_response,
+        ApiError, ApiResponse, FilterRequest, NumericOperator, PaginatedData, PaginationFilter,
+        PaginationMeta, apply_pagination,
+    },
+    utils::{
+        ChannelDetails, ChannelState, ChannelSummary, DualFundRequest, LiquidityAd, ShortChannelID,
+        close_fee::{CloseFeeEstimate, DEFAULT_CLOSE_TARGET_CONF},
     },
-    utils::{ChannelDetails, ChannelState, ChannelSummary, ShortChannelID},
 };
 use axum::{
     Json,
     extract::{Extension, Path, Query},
-    http::StatusCode,
 };
+use bitcoin::OutPoint;
+use bitcoin::secp256k1::PublicKey;
+use chrono::{Duration, Utc};
+use futures::future::{join_all, try_join_all};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 use validator::Validate;
 
+/// The ways a channel can be addressed in `/api/channels/{id}`.
+enum ChannelLookup {
+    /// Numeric or `BLOCKxTXxOUTPUT` short channel id.
+    ShortChannelId(ShortChannelID),
+    /// Funding outpoint as `txid:vout`.
+    ChannelPoint(OutPoint),
+    /// Every channel with this peer.
+    Peer(PublicKey),
+}
+
+/// A single channel, or all channels with a peer when looked up by pubkey.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum ChannelLookupResult {
+    Channel(Box<ChannelDetails>),
+    PeerChannels(Vec<ChannelDetails>),
+}
+
 #[axum::debug_handler]
 pub async fn get_channel_info(
-    Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
     Path(channel_id): Path<String>,
-) -> Result<Json<ApiResponse<ChannelDetails>>, (StatusCode, String)> {
-    let scid = parse_short_channel_id(&channel_id)?;
-    let node_credentials = extract_node_credentials(&claims)?;
-    let public_key = parse_public_key(&node_credentials.node_id)?;
+) -> Result<Json<ApiResponse<ChannelLookupResult>>, ApiError> {
+    let lookup = parse_channel_lookup(&channel_id)?;
+    let node_client = node.client().await?;
+
+    let result = match lookup {
+        ChannelLookup::ShortChannelId(scid) => node_client
+            .get_channel_info(&scid)
+            .await
+            .map(|details| ChannelLookupResult::Channel(Box::new(details))),
+        ChannelLookup::ChannelPoint(channel_point) => {
+            let channels = node_client
+                .list_channels()
+                .await
+                .map_err(|e| handle_node_error(e, "list channels"))?;
+
+            match channels
+                .iter()
+                .find(|channel| channel.channel_point == Some(channel_point))
+            {
+                Some(channel) => node_client
+                    .get_channel_info(&channel.chan_id)
+                    .await
+                    .map(|details| ChannelLookupResult::Channel(Box::new(details))),
+                None => Err(LightningError::NotFound(format!(
+                    "No channel with channel point {channel_point}"
+                ))),
+            }
+        }
+        ChannelLookup::Peer(peer) => {
+            let channels = node_client
+                .list_channels()
+                .await
+                .map_err(|e| handle_node_error(e, "list channels"))?;
 
-    let node_client = create_node_client(node_credentials, public_key).await?;
+            try_join_all(
+                channels
+                    .iter()
+                    .filter(|channel| channel.remote_pubkey == Some(peer))
+                    .map(|channel| node_client.get_channel_info(&channel.chan_id)),
+            )
+            .await
+            .map(ChannelLookupResult::PeerChannels)
+        }
+    }
+    .map_err(|e| handle_node_error(e, "get channel info"))?;
+
+    let result = match result {
+        ChannelLookupResult::Channel(details) => {
+            ChannelLookupResult::Channel(Box::new(with_funding_tx(*details).await))
+        }
+        ChannelLookupResult::PeerChannels(channels) => ChannelLookupResult::PeerChannels(
+            join_all(channels.into_iter().map(with_funding_tx)).await,
+        ),
+    };
+
+    Ok(Json(ApiResponse::success(
+        result,
+        "Channel details retrieved successfully",
+    )))
+}
 
-    let channel_details = node_client
-        .get_channel_info(&scid)
+/// Request body for fetching several channels' details at once.
+#[derive(Debug, Deserialize, Validate)]
+pub struct ChannelDetailsBatchRequest {
+    /// Short channel ids, numeric or `BLOCKxTXxOUTPUT`
+    #[validate(length(
+        min = 1,
+        max = 200,
+        message = "channel_ids must contain between 1 and 200 ids"
+    ))]
+    pub channel_ids: Vec<String>,
+}
+
+/// The outcome for one requested channel, so a missing channel does not fail the batch.
+#[derive(Debug, Serialize)]
+pub struct ChannelDetailsBatchItem {
+    pub channel_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<ChannelDetails>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Handler for fetching the details of many channels in a single node pass
+#[axum::debug_handler]
+pub async fn get_channels_details(
+    Extension(node): Extension<SelectedNode>,
+    Json(payload): Json<ChannelDetailsBatchRequest>,
+) -> Result<Json<ApiResponse<Vec<ChannelDetailsBatchItem>>>, ApiError> {
+    payload.validate()?;
+
+    let channel_ids = payload
+        .channel_ids
+        .iter()
+        .map(|channel_id| {
+            ShortChannelID::from_str(channel_id).map_err(|e| {
+                ApiError::bad_request(
+                    "invalid_channel_id",
+                    format!("Invalid channel ID format for {channel_id}: {e}"),
+                )
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let node_client = node.client().await?;
+    let results = node_client
+        .get_channels_info(&channel_ids)
         .await
-        .map_err(|e| handle_node_error(e, "get channel info"))?;
+        .map_err(|e| handle_node_error(e, "get channels info"))?;
+
+    let items = join_all(payload.channel_ids.into_iter().zip(results).map(
+        |(channel_id, result)| async move {
+            match result {
+                Ok(details) => ChannelDetailsBatchItem {
+                    channel_id,
+                    details: Some(with_funding_tx(details).await),
+                    error: None,
+                },
+                Err(e) => ChannelDetailsBatchItem {
+                    channel_id,
+                    details: None,
+                    error: Some(e.to_string()),
+                },
+            }
+        },
+    ))
+    .await;
 
     Ok(Json(ApiResponse::success(
-        channel_details,
+        items,
         "Channel details retrieved successfully",
     )))
 }
 
+/// Query parameters for the closing fee estimate.
+#[derive(Debug, Deserialize, Validate)]
+pub struct CloseEstimateQuery {
+    /// Blocks within which the closing transactions should confirm
+    #[validate(range(
+        min = 1,
+        max = 1008,
+        message = "target_conf must be between 1 and 1008"
+    ))]
+    pub target_conf: Option<u32>,
+}
+
+/// Handler for estimating the on-chain cost of closing a channel
+#[axum::debug_handler]
+pub async fn get_close_estimate(
+    Extension(node): Extension<SelectedNode>,
+    Path(channel_id): Path<String>,
+    Query(query): Query<CloseEstimateQuery>,
+) -> Result<Json<ApiResponse<CloseFeeEstimate>>, ApiError> {
+    query.validate()?;
+    let target_conf = query.target_conf.unwrap_or(DEFAULT_CLOSE_TARGET_CONF);
+    let node_client = node.client().await?;
+    let scid = resolve_short_channel_id(node_client, &channel_id, "Close estimates").await?;
+
+    let estimate = node_client
+        .estimate_close_fee(&scid, target_conf)
+        .await
+        .map_err(|e| handle_node_error(e, "estimate close fee"))?;
+
+    Ok(Json(ApiResponse::success(
+        estimate,
+        "Close fee estimate retrieved successfully",
+    )))
+}
+
+/// Query parameters for a channel's liquidity forecast.
+#[derive(Debug, Deserialize, Validate)]
+pub struct ForecastQuery {
+    /// Days of forwards the forecast is based on
+    #[validate(range(min = 3, max = 90, message = "days must be between 3 and 90"))]
+    pub days: Option<u64>,
+}
+
+/// Handler for the days until a channel's local balance runs out or fills up
+/// at its recent flow
+#[axum::debug_handler]
+pub async fn get_liquidity_forecast(
+    Extension(node): Extension<SelectedNode>,
+    Path(channel_id): Path<String>,
+    Query(query): Query<ForecastQuery>,
+) -> Result<Json<ApiResponse<LiquidityForecast>>, ApiError> {
+    query.validate()?;
+    let days = query.days.unwrap_or(DEFAULT_FORECAST_DAYS);
+    let node_client = node.client().await?;
+    let scid = resolve_short_channel_id(node_client, &channel_id, "Forecasts").await?;
+
+    let channel = node_client
+        .list_channels()
+        .await
+        .map_err(|e| handle_node_error(e, "list channels"))?
+        .into_iter()
+        .find(|channel| channel.chan_id.0 == scid.0)
+        .ok_or_else(|| {
+            ApiError::not_found(
+                "channel_not_found",
+                format!("No open channel {}", scid.to_block_format()),
+            )
+        })?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let forwards = node_client
+        .list_forwards(now.saturating_sub(days * 86_400))
+        .await
+        .map_err(|e| handle_node_error(e, "list forwards"))?;
+    let forecast = forecast_liquidity(&channel, &daily_flows(&forwards, scid, now, days));
+
+    Ok(Json(ApiResponse::success(
+        forecast,
+        "Liquidity forecast retrieved successfully",
+    )))
+}
+
+/// Resolves a short channel id or channel point to the channel's short channel id.
+///
+/// `purpose` names what needs it in the error for peer lookups.
+async fn resolve_short_channel_id(
+    node_client: &(dyn LightningClient + Send + Sync),
+    channel_id: &str,
+    purpose: &str,
+) -> Result<ShortChannelID, ApiError> {
+    match parse_channel_lookup(channel_id)? {
+        ChannelLookup::ShortChannelId(scid) => Ok(scid),
+        ChannelLookup::ChannelPoint(channel_point) => node_client
+            .list_channels()
+            .await
+            .map_err(|e| handle_node_error(e, "list channels"))?
+            .iter()
+            .find(|channel| channel.channel_point == Some(channel_point))
+            .map(|channel| channel.chan_id)
+            .ok_or_else(|| {
+                ApiError::not_found(
+                    "channel_not_found",
+                    format!("No channel with channel point {channel_point}"),
+                )
+            }),
+        ChannelLookup::Peer(_) => Err(ApiError::bad_request(
+            "invalid_channel_id",
+            format!("{purpose} need a short channel id or channel point"),
+        )),
+    }
+}
+
+/// Days covered by a policy history when none are given.
+const DEFAULT_POLICY_HISTORY_DAYS: u32 = 90;
+
+/// Query parameters for a channel's policy history.
+#[derive(Debug, Deserialize, Validate)]
+pub struct PolicyHistoryQuery {
+    /// How many days back the timeline reaches
+    #[validate(range(min = 1, max = 365, message = "days must be between 1 and 365"))]
+    pub days: Option<u32>,
+}
+
+/// Handler for a channel's timeline of fee policies and the forwards under each
+#[axum::debug_handler]
+pub async fn get_policy_history(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
+    Path(channel_id): Path<String>,
+    Query(query): Query<PolicyHistoryQuery>,
+) -> Result<Json<ApiResponse<PolicyHistory>>, ApiError> {
+    query.validate()?;
+    let end = Utc::now();
+    let start = end - Duration::days(query.days.unwrap_or(DEFAULT_POLICY_HISTORY_DAYS).into());
+    let node_client = node.client().await?;
+    let scid = resolve_short_channel_id(node_client, &channel_id, "Policy histories").await?;
+
+    let forwards = node_client
+        .list_forwards(start.timestamp() as u64)
+        .await
+        .map_err(|e| handle_node_error(e, "list forwards"))?;
+    let history = PolicyHistoryService::new(&pool)
+        .get_history(
+            &claims.account_id,
+            &node.credentials().node_id,
+            scid,
+            &forwards,
+            start,
+            end,
+        )
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        history,
+        "Policy history retrieved successfully",
+    )))
+}
+
+/// Rejects nodes whose implementation or version can't open dual-funded channels.
+fn require_dual_funding(
+    node: &SelectedNode,
+    node_client: &(dyn LightningClient + Send + Sync),
+) -> Result<(), ApiError> {
+    let supported = NodeImplementation::from_node_type(&node.credentials().node_type).is_some_and(
+        |implementation| {
+            node_capabilities::supports(
+                implementation,
+                node_client.get_info().version.as_deref(),
+                NodeFeature::DualFunding,
+            )
+        },
+    );
+    if !supported {
+        return Err(ApiError::bad_request(
+            "dual_funding_unsupported",
+            "Dual-funded channels need a Core Lightning node with dual funding enabled",
+        ));
+    }
+    Ok(())
+}
+
+/// Handler for listing the liquidity ads in the node's view of the gossip,
+/// cheapest lease first
+#[axum::debug_handler]
+pub async fn list_liquidity_ads(
+    Extension(node): Extension<SelectedNode>,
+) -> Result<Json<ApiResponse<Vec<LiquidityAd>>>, ApiError> {
+    let node_client = node.client().await?;
+    require_dual_funding(&node, node_client)?;
+
+    let mut ads = node_client
+        .list_liquidity_ads()
+        .await
+        .map_err(|e| handle_node_error(e, "list liquidity ads"))?;
+    ads.sort_by_key(|ad| (ad.lease_fee_basis, ad.lease_fee_base_msat));
+
+    Ok(Json(ApiResponse::success(
+        ads,
+        "Liquidity ads retrieved successfully",
+    )))
+}
+
+/// Handler for opening a channel funded by both peers, leasing the peer's
+/// contribution under its liquidity ad
+#[axum::debug_handler]
+pub async fn open_dual_funded_channel(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
+    Json(payload): Json<OpenDualFundedChannelRequest>,
+) -> Result<Json<ApiResponse<DualFundedChannel>>, ApiError> {
+    require_write_access(&claims, "open channels")?;
+    payload.validate()?;
+    let pubkey = parse_public_key(&payload.pubkey)?;
+    let request_amount_sat = payload.request_amount_sat.unwrap_or(0);
+    if request_amount_sat > 0 && payload.compact_lease.is_none() {
+        return Err(ApiError::bad_request(
+            "missing_compact_lease",
+            "Leasing a contribution from the peer needs the compact_lease of its liquidity ad",
+        ));
+    }
+
+    let node_client = node.client().await?;
+    require_dual_funding(&node, node_client)?;
+
+    let opened = node_client
+        .open_dual_funded_channel(&DualFundRequest {
+            pubkey,
+            address: payload.address,
+            amount_sat: payload.amount_sat,
+            request_amount_sat,
+            compact_lease: payload.compact_lease.clone(),
+            sat_per_vbyte: payload.sat_per_vbyte,
+            private: payload.private,
+        })
+        .await
+        .map_err(|e| handle_node_error(e, "open dual-funded channel"))?;
+
+    let channel = DualFundedChannel {
+        node_id: node.credentials().node_id.clone(),
+        peer_pubkey: pubkey.to_string(),
+        channel_point: opened.channel_point.to_string(),
+        channel_id: opened.channel_id,
+        local_amount_sat: payload.amount_sat as i64,
+        requested_amount_sat: request_amount_sat as i64,
+        compact_lease: payload.compact_lease.filter(|_| request_amount_sat > 0),
+        opened_by: claims.sub.clone(),
+        created_at: Utc::now(),
+    };
+    // The channel is already being opened, so a failed write only loses the tag
+    if let Err(e) = DualFundedChannelRepository::new(&pool)
+        .create_channel(&claims.account_id, &channel)
+        .await
+    {
+        tracing::error!(
+            "Failed to record dual-funded channel {}: {}",
+            channel.channel_point,
+            e
+        );
+    }
+
+    Ok(Json(ApiResponse::success(
+        channel,
+        "Dual-funded channel opening",
+    )))
+}
+
+/// Handler for listing the dual-funded channels opened on the node through NodeGaze
+#[axum::debug_handler]
+pub async fn list_dual_funded_channels(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
+) -> Result<Json<ApiResponse<Vec<DualFundedChannel>>>, ApiError> {
+    let channels = DualFundedChannelRepository::new(&pool)
+        .get_channels(&claims.account_id, &node.credentials().node_id)
+        .await
+        .map_err(ServiceError::from)?;
+
+    Ok(Json(ApiResponse::success(
+        channels,
+        "Dual-funded channels retrieved successfully",
+    )))
+}
+
+/// Attaches the funding transaction's on-chain status when the explorer has it.
+async fn with_funding_tx(mut details: ChannelDetails) -> ChannelDetails {
+    if let Some(txid) = details.txid {
+        details.funding_tx = mempool().tx_status(&txid).await;
+    }
+    details
+}
+
+/// Optional extras for channel listings.
+#[derive(Debug, Deserialize)]
+pub struct ChannelIncludeQuery {
+    /// Comma-separated extras; `flow` adds 7 and 30 day forwarding flow
+    pub include: Option<String>,
+}
+
+/// Tag filter for channel listings.
+#[derive(Debug, Deserialize)]
+pub struct ChannelTagQuery {
+    /// Only list channels carrying this tag, compared without regard to case
+    pub tag: Option<String>,
+}
+
+/// A channel in a listing, with its forwarding flow when requested.
+#[derive(Debug, Serialize)]
+pub struct ChannelListItem {
+    #[serde(flatten)]
+    pub channel: ChannelSummary,
+    /// Whether the channel was opened through NodeGaze with both peers contributing
+    pub dual_funded: bool,
+    /// Names of the channel's tags
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flow: Option<ChannelFlow>,
+}
+
 /// Handler for listing all channels with filtering and pagination
 #[axum::debug_handler]
 pub async fn list_channels(
+    Extension(pool): Extension<SqlitePool>,
     Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
     Query(filter): Query<ChannelFilter>,
-) -> Result<Json<ApiResponse<PaginatedData<ChannelSummary>>>, (StatusCode, String)> {
-    if let Err(validation_errors) = filter.validate() {
-        return Err(validation_error_response(validation_errors));
-    }
+    Query(include): Query<ChannelIncludeQuery>,
+    Query(tag_query): Query<ChannelTagQuery>,
+) -> Result<Json<ApiResponse<PaginatedData<ChannelListItem>>>, ApiError> {
+    filter.validate()?;
 
-    let node_credentials = extract_node_credentials(&claims)?;
-    let public_key = parse_public_key(&node_credentials.node_id)?;
+    let mut include_flow = false;
+    for extra in include.include.as_deref().unwrap_or_default().split(',') {
+        match extra.trim() {
+            "" => {}
+            "flow" => include_flow = true,
+            other => {
+                return Err(ApiError::bad_request(
+                    "invalid_include",
+                    format!("Unknown include '{other}', expected 'flow'"),
+                ));
+            }
+        }
+    }
 
-    let node_client = create_node_client(node_credentials, public_key).await?;
+    let node_client = node.client().await?;
 
-    let channels = node_client
+    let mut channels = node_client
         .list_channels()
         .await
         .map_err(|e| handle_node_error(e, "list channels"))?;
 
-    process_channels_with_filters(channels, &filter).await
+    let tags: HashMap<u64, Vec<String>> = ChannelTagService::new(&pool)
+        .get_node_channel_tags(&claims.account_id, &node.credentials().node_id)
+        .await?
+        .into_iter()
+        .map(|(channel_id, tags)| (channel_id, tags.into_iter().map(|tag| tag.name).collect()))
+        .collect();
+    if let Some(tag) = tag_query.tag.as_deref().map(str::trim) {
+        channels.retain(|channel| {
+            tags.get(&channel.chan_id.0)
+                .is_some_and(|names| names.iter().any(|name| name.eq_ignore_ascii_case(tag)))
+        });
+    }
+
+    let flows = if include_flow {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let forwards = node_client
+            .list_forwards(now.saturating_sub(LONG_FLOW_WINDOW_DAYS * 86_400))
+            .await
+            .map_err(|e| handle_node_error(e, "list forwards"))?;
+        Some(channel_flows(&forwards, now))
+    } else {
+        None
+    };
+
+    let dual_funded = DualFundedChannelRepository::new(&pool)
+        .get_channel_points(&node.credentials().node_id)
+        .await
+        .map_err(ServiceError::from)?;
+
+    process_channels_with_filters(channels, &filter, flows.as_ref(), &dual_funded, &tags).await
 }
 
 pub type ChannelFilter = FilterRequest<ChannelState>;
@@ -91,20 +617,16 @@ fn apply_channel_filters(
     }
 
     // Apply capacity filter
+    // Negative values were rejected when the filter was validated
     if let (Some(operator), Some(filter_value)) = (&filter.operator, filter.value) {
-        if filter_value < 0 {
-            // Negative filter values shouldn't match positive amounts
-            channels.clear();
-        } else {
-            let filter_value_u64 = filter_value as u64;
-            channels.retain(|channel| match operator {
-                NumericOperator::Gte => channel.capacity >= filter_value_u64,
-                NumericOperator::Lte => channel.capacity <= filter_value_u64,
-                NumericOperator::Eq => channel.capacity == filter_value_u64,
-                NumericOperator::Gt => channel.capacity > filter_value_u64,
-                NumericOperator::Lt => channel.capacity < filter_value_u64,
-            });
-        }
+        let filter_value = Amount::from_sat_i64(filter_value);
+        channels.retain(|channel| match operator {
+            NumericOperator::Gte => channel.capacity >= filter_value,
+            NumericOperator::Lte => channel.capacity <= filter_value,
+            NumericOperator::Eq => channel.capacity == filter_value,
+            NumericOperator::Gt => channel.capacity > filter_value,
+            NumericOperator::Lt => channel.capacity < filter_value,
+        });
     }
 
     // Apply date range filter (for channel creation dates)
@@ -139,15 +661,28 @@ fn apply_channel_filters(
     channels
 }
 
-/// Process channels with filters and pagination
+/// Process channels with filters and pagination, attaching flow when it was computed
 async fn process_channels_with_filters(
     all_channels: Vec<ChannelSummary>,
     filter: &ChannelFilter,
-) -> Result<Json<ApiResponse<PaginatedData<ChannelSummary>>>, (StatusCode, String)> {
+    flows: Option<&HashMap<u64, ChannelFlow>>,
+    dual_funded: &HashSet<String>,
+    tags: &HashMap<u64, Vec<String>>,
+) -> Result<Json<ApiResponse<PaginatedData<ChannelListItem>>>, ApiError> {
     let filtered_channels = apply_channel_filters(all_channels, filter);
     let total_filtered_count = filtered_channels.len() as u64;
     let pagination_filter = filter.to_pagination_filter();
-    let paginated_channels = apply_pagination(filtered_channels, &pagination_filter);
+    let paginated_channels = apply_pagination(filtered_channels, &pagination_filter)
+        .into_iter()
+        .map(|channel| ChannelListItem {
+            flow: flows.map(|flows| flows.get(&channel.chan_id.0).cloned().unwrap_or_default()),
+            dual_funded: channel
+                .channel_point
+                .is_some_and(|point| dual_funded.contains(&point.to_string())),
+            tags: tags.get(&channel.chan_id.0).cloned().unwrap_or_default(),
+            channel,
+        })
+        .collect();
     let pagination_meta = PaginationMeta::from_filter(&pagination_filter, total_filtered_count);
     let paginated_data = PaginatedData::new(paginated_channels, total_filtered_count);
 
@@ -157,16 +692,95 @@ async fn process_channels_with_filters(
     )))
 }
 
-fn parse_short_channel_id(channel_id: &str) -> Result<ShortChannelID, (StatusCode, String)> {
-    ShortChannelID::from_str(channel_id).map_err(|e| {
-        let error_response = ApiResponse::<()>::error(
-            format!("Invalid channel ID format: {e}"),
+/// Interprets the path segment as a short channel id, a channel point or a peer pubkey.
+fn parse_channel_lookup(channel_id: &str) -> Result<ChannelLookup, ApiError> {
+    let lookup = if channel_id.contains(':') {
+        parse_channel_point(channel_id).map(ChannelLookup::ChannelPoint)
+    } else if channel_id.len() == 66 {
+        PublicKey::from_str(channel_id)
+            .map(ChannelLookup::Peer)
+            .map_err(|e| LightningError::Parse(e.to_string()))
+    } else {
+        ShortChannelID::from_str(channel_id).map(ChannelLookup::ShortChannelId)
+    };
+
+    lookup.map_err(|e| {
+        ApiError::bad_request(
             "invalid_channel_id",
-            None,
-        );
-        (
-            StatusCode::BAD_REQUEST,
-            serde_json::to_string(&error_response).unwrap(),
+            format!("Invalid channel ID format: {e}"),
         )
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+
+    fn parse_filter(query: &str) -> Result<ChannelFilter, String> {
+        let uri: axum::http::Uri = format!("/channels?{query}").parse().unwrap();
+        let Query(filter) =
+            Query::<ChannelFilter>::try_from_uri(&uri).map_err(|e| e.to_string())?;
+        filter.validate().map_err(|e| e.to_string())?;
+        Ok(filter)
+    }
+
+    #[test]
+    fn capacity_filter_takes_whole_sats_only() {
+        let filter = parse_filter("operator=gte&value=1500").unwrap();
+        assert_eq!(filter.value, Some(1500));
+
+        assert!(parse_filter("operator=gte&value=1.5").is_err());
+        assert!(parse_filter("operator=gte&value=-1").is_err());
+    }
+
+    const PEER: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+    const TXID: &str = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+
+    #[test]
+    fn looks_up_channels_by_short_channel_id() {
+        for channel_id in ["790409x1146x0", "869063886273904640"] {
+            let Ok(ChannelLookup::ShortChannelId(scid)) = parse_channel_lookup(channel_id) else {
+                panic!("{channel_id} is a short channel id");
+            };
+            assert_eq!(scid.0, 869_063_886_273_904_640);
+        }
+    }
+
+    #[test]
+    fn looks_up_channels_by_channel_point_or_peer() {
+        let Ok(ChannelLookup::ChannelPoint(outpoint)) = parse_channel_lookup(&format!("{TXID}:1"))
+        else {
+            panic!("txid:vout is a channel point");
+        };
+        assert_eq!(outpoint.txid.to_string(), TXID);
+        assert_eq!(outpoint.vout, 1);
+
+        let Ok(ChannelLookup::Peer(peer)) = parse_channel_lookup(PEER) else {
+            panic!("a 66 character id is a peer");
+        };
+        assert_eq!(peer.to_string(), PEER);
+    }
+
+    #[test]
+    fn rejects_malformed_channel_ids() {
+        let not_hex = "z".repeat(66);
+        let malformed = [
+            "",
+            "channel",
+            "790409x1146",
+            "790409x1146x70000",
+            "16777216x0x0",
+            "abc:1",
+            &format!("{TXID}:x"),
+            &not_hex,
+        ];
+        for channel_id in malformed {
+            let Err(error) = parse_channel_lookup(channel_id) else {
+                panic!("{channel_id:?} should be rejected");
+            };
+            assert_eq!(error.into_response().status(), StatusCode::BAD_REQUEST);
+        }
+    }
+}
@@ -1,19 +1,77 @@
-use super::handlers::{get_channel_info, list_channels};
-use crate::auth::middleware::{jwt_auth, node_credentials_required};
-use axum::{Router, middleware, routing::get};
-
-pub async fn channel_router() -> Router {
-    Router::new()
-        .route(
-            "/{channel_id}",
-            get(get_channel_info)
-                .layer(middleware::from_fn(node_credentials_required))
-                .layer(middleware::from_fn(jwt_auth)),
-        )
-        .route(
-            "/",
-            get(list_channels)
-                .layer(middleware::from_fn(node_credentials_required))
-                .layer(middleware::from_fn(jwt_auth)),
-        )
-}
+use super::handlers::{
+    get_channel_info, get_channels_details, get_close_estimate, get_liquidity_forecast,
+    get_policy_history, list_channels, list_dual_funded_channels, list_liquidity_ads,
+    open_dual_funded_channel,
+};
+use crate::auth::middleware::{
+    jwt_auth, node_group_access_required, node_selection, node_write_access_required,
+};
+use crate::middleware::response_cache::cached_node_response;
+use axum::{
+    Router, middleware,
+    routing::{get, post},
+};
+
+pub async fn channel_router() -> Router {
+    Router::new()
+        .route(
+            "/{channel_id}",
+            get(get_channel_info)
+                .layer(middleware::from_fn(cached_node_response))
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/details",
+            post(get_channels_details)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/liquidity-ads",
+            get(list_liquidity_ads)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/dual-funded",
+            post(open_dual_funded_channel)
+                .layer(middleware::from_fn(node_write_access_required))
+                .get(list_dual_funded_channels)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/{channel_id}/close-estimate",
+            get(get_close_estimate)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/{channel_id}/forecast",
+            get(get_liquidity_forecast)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/{channel_id}/policy-history",
+            get(get_policy_history)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/",
+            get(list_channels)
+                .layer(middleware::from_fn(cached_node_response))
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+}
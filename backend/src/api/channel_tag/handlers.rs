@@ -0,0 +1,152 @@
+//! Handler functions for channel tag API endpoints.
+//!
+//! These functions manage the account's channel tags, with the fee policy and
+//! alert thresholds of each, and tag or untag channels of the selected node.
+
+use crate::api::common::{ApiError, ApiResponse};
+use crate::database::models::{ChannelTag, CreateChannelTagRequest, UpdateChannelTagRequest};
+use crate::services::channel_tags::ChannelTagService;
+use crate::utils::ShortChannelID;
+use crate::utils::handlers_common::{SelectedNode, require_write_access};
+use crate::utils::jwt::Claims;
+use axum::{
+    Json,
+    extract::{Extension, Path},
+};
+use sqlx::SqlitePool;
+use std::str::FromStr;
+
+fn parse_channel_id(channel_id: &str) -> Result<ShortChannelID, ApiError> {
+    ShortChannelID::from_str(channel_id).map_err(|e| {
+        ApiError::bad_request(
+            "invalid_channel_id",
+            format!("Invalid channel ID format: {e}"),
+        )
+    })
+}
+
+/// Lists the account's channel tags.
+#[axum::debug_handler]
+pub async fn list_tags(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<Vec<ChannelTag>>>, ApiError> {
+    let tags = ChannelTagService::new(&pool)
+        .get_tags(&claims.account_id)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        tags,
+        "Channel tags retrieved successfully",
+    )))
+}
+
+/// Creates a channel tag.
+#[axum::debug_handler]
+pub async fn create_tag(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<CreateChannelTagRequest>,
+) -> Result<Json<ApiResponse<ChannelTag>>, ApiError> {
+    require_write_access(&claims, "change channel tags")?;
+
+    let tag = ChannelTagService::new(&pool)
+        .create_tag(&claims.account_id, payload)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        tag,
+        "Channel tag created successfully",
+    )))
+}
+
+/// Replaces the fee policy and alert thresholds of a channel tag.
+#[axum::debug_handler]
+pub async fn update_tag(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(name): Path<String>,
+    Json(payload): Json<UpdateChannelTagRequest>,
+) -> Result<Json<ApiResponse<ChannelTag>>, ApiError> {
+    require_write_access(&claims, "change channel tags")?;
+
+    let tag = ChannelTagService::new(&pool)
+        .update_tag(&claims.account_id, &name, payload)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        tag,
+        "Channel tag updated successfully",
+    )))
+}
+
+/// Deletes a channel tag, removing it from every channel carrying it.
+#[axum::debug_handler]
+pub async fn delete_tag(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(name): Path<String>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    require_write_access(&claims, "change channel tags")?;
+
+    ChannelTagService::new(&pool)
+        .delete_tag(&claims.account_id, &name)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        (),
+        "Channel tag deleted successfully",
+    )))
+}
+
+/// Tags a channel of the selected node.
+#[axum::debug_handler]
+pub async fn tag_channel(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
+    Path((name, channel_id)): Path<(String, String)>,
+) -> Result<Json<ApiResponse<ChannelTag>>, ApiError> {
+    require_write_access(&claims, "change channel tags")?;
+    let channel_id = parse_channel_id(&channel_id)?;
+
+    let tag = ChannelTagService::new(&pool)
+        .tag_channel(
+            &claims.account_id,
+            &name,
+            &node.credentials().node_id,
+            &channel_id,
+        )
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        tag,
+        "Channel tagged successfully",
+    )))
+}
+
+/// Removes a tag from a channel of the selected node.
+#[axum::debug_handler]
+pub async fn untag_channel(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
+    Path((name, channel_id)): Path<(String, String)>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    require_write_access(&claims, "change channel tags")?;
+    let channel_id = parse_channel_id(&channel_id)?;
+
+    ChannelTagService::new(&pool)
+        .untag_channel(
+            &claims.account_id,
+            &name,
+            &node.credentials().node_id,
+            &channel_id,
+        )
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        (),
+        "Channel untagged successfully",
+    )))
+}
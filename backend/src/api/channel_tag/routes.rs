@@ -0,0 +1,35 @@
+//! Defines the HTTP routes for channel tags.
+//!
+//! Tags belong to the account; tagging and untagging channels acts on the
+//! selected node.
+
+use super::handlers::{create_tag, delete_tag, list_tags, tag_channel, untag_channel, update_tag};
+use crate::auth::middleware::{jwt_auth, node_group_access_required, node_selection};
+use axum::{
+    Router, middleware,
+    routing::{get, put},
+};
+
+pub async fn channel_tag_router() -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(list_tags)
+                .post(create_tag)
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/{name}",
+            put(update_tag)
+                .delete(delete_tag)
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/{name}/channels/{channel_id}",
+            put(tag_channel)
+                .delete(untag_channel)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+}
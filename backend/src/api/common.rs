@@ -19,8 +19,8 @@
 //! - `pagination`: Metadata about current page, total items, etc.
 //!
 //! # Error Handling Flow
-//! 1. Service layer returns domain-specific `ServiceError`
-//! 2. `service_error_to_http` converts to appropriate HTTP response
+//! 1. Service layer returns domain-specific `ServiceError`, node clients `LightningError`
+//! 2. Both convert into `ApiError`, which picks the HTTP status and error type
 //! 3. Validation errors are automatically formatted with field details
 //!
 //! # Filtering System
@@ -31,8 +31,12 @@
 //! - Generic state filtering that works with any enum
 //! - In-memory filtering for collections
 
-use crate::errors::ServiceError;
-use axum::http::StatusCode;
+use crate::errors::{LightningError, ServiceError};
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
 use chrono::{DateTime, Utc};
 use serde::{
     Deserialize, Serialize,
@@ -83,6 +87,9 @@ pub struct PaginationMeta {
     /// Previous page number (if available)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub prev_page: Option<u32>,
+    /// Opaque cursor for the next page of cursor-paginated lists (if available)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 /// Paginated response wrapper containing items and pagination metadata
@@ -158,7 +165,8 @@ where
     /// The comparison operator
     pub operator: Option<NumericOperator>,
 
-    /// The value to compare against
+    /// The value to compare against, in whole sats for amounts
+    #[validate(range(min = 0, message = "Filter value must be a whole number of sats"))]
     pub value: Option<i64>,
 
     /// Start date (inclusive)
@@ -239,6 +247,30 @@ impl PaginationMeta {
             } else {
                 None
             },
+            next_cursor: None,
+        }
+    }
+
+    /// Create pagination metadata for a keyset page.
+    ///
+    /// Page numbers have no meaning for cursors, so the page is always reported as
+    /// the first one and `has_prev` records whether a cursor was supplied.
+    pub fn from_cursor(
+        per_page: u32,
+        total_items: u64,
+        has_prev: bool,
+        next_cursor: Option<String>,
+    ) -> Self {
+        Self {
+            current_page: 1,
+            per_page,
+            total_items,
+            total_pages: Self::new(1, per_page, total_items).total_pages,
+            has_next: next_cursor.is_some(),
+            has_prev,
+            next_page: None,
+            prev_page: None,
+            next_cursor,
         }
     }
 
@@ -335,48 +367,145 @@ impl Default for PaginationFilter {
     }
 }
 
-/// Converts ServiceError to appropriate HTTP response with standard format
-pub fn service_error_to_http(error: ServiceError) -> (StatusCode, String) {
-    let (status, error_type, message) = match error {
-        ServiceError::Validation { message } => {
-            (StatusCode::BAD_REQUEST, "validation_error", message)
-        }
-        ServiceError::NotFound { entity, identifier } => (
-            StatusCode::NOT_FOUND,
-            "not_found",
-            format!("{entity} '{identifier}' not found"),
-        ),
-        ServiceError::AlreadyExists { entity, identifier } => (
-            StatusCode::CONFLICT,
-            "already_exists",
-            format!("{entity} '{identifier}' already exists"),
-        ),
-        ServiceError::InvalidOperation { message } => {
-            (StatusCode::BAD_REQUEST, "invalid_operation", message)
-        }
-        ServiceError::Database { source } => {
-            tracing::error!("Database error: {}", source);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "database_error",
-                "Internal server error".to_string(),
-            )
+/// Error returned by API handlers.
+///
+/// Renders as a failed `ApiResponse` with the status it was built with, so
+/// handlers can propagate service, node and validation errors with `?`.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    error_type: String,
+    message: String,
+    details: Option<Vec<FieldError>>,
+}
+
+impl ApiError {
+    /// Create an error with an explicit status code
+    pub fn new(
+        status: StatusCode,
+        error_type: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            status,
+            error_type: error_type.into(),
+            message: message.into(),
+            details: None,
         }
-        ServiceError::ExternalService { message } => {
-            (StatusCode::BAD_GATEWAY, "external_service_error", message)
+    }
+
+    /// 400 Bad Request
+    pub fn bad_request(error_type: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, error_type, message)
+    }
+
+    /// 401 Unauthorized
+    pub fn unauthorized(error_type: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, error_type, message)
+    }
+
+    /// 403 Forbidden
+    pub fn forbidden(error_type: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, error_type, message)
+    }
+
+    /// 404 Not Found
+    pub fn not_found(error_type: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, error_type, message)
+    }
+
+    /// 500 Internal Server Error
+    pub fn internal(error_type: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, error_type, message)
+    }
+
+    /// Status code for a failed node operation.
+    pub fn lightning_status(error: &LightningError) -> StatusCode {
+        match error {
+            LightningError::ConnectionError(_) | LightningError::NetworkError(_) => {
+                StatusCode::BAD_GATEWAY
+            }
+            LightningError::NotFound(_) => StatusCode::NOT_FOUND,
+            LightningError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            LightningError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            LightningError::GetInfoError(_)
+            | LightningError::PaymentError(_)
+            | LightningError::InvoiceError(_)
+            | LightningError::GetGraphError(_)
+            | LightningError::StreamingError(_)
+            | LightningError::ChannelError(_)
+            | LightningError::Parse(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
-        ServiceError::InternalError { message } => {
-            tracing::error!("Internal error: {}", message);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "internal_error",
-                "Internal server error".to_string(),
-            )
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ApiResponse::<()>::error(self.message, self.error_type, self.details);
+        (self.status, Json(body)).into_response()
+    }
+}
+
+impl From<ServiceError> for ApiError {
+    fn from(error: ServiceError) -> Self {
+        match error {
+            ServiceError::Validation { message } => Self::bad_request("validation_error", message),
+            ServiceError::NotFound { entity, identifier } => {
+                Self::not_found("not_found", format!("{entity} '{identifier}' not found"))
+            }
+            ServiceError::AlreadyExists { entity, identifier } => Self::new(
+                StatusCode::CONFLICT,
+                "already_exists",
+                format!("{entity} '{identifier}' already exists"),
+            ),
+            ServiceError::InvalidOperation { message } => {
+                Self::bad_request("invalid_operation", message)
+            }
+            ServiceError::QuotaExceeded { resource, limit } => Self::forbidden(
+                "quota_exceeded",
+                format!("{resource} quota of {limit} reached for this account"),
+            ),
+            ServiceError::Database { source } => {
+                tracing::error!("Database error: {}", source);
+                Self::internal("database_error", "Internal server error")
+            }
+            ServiceError::ExternalService { message } => {
+                Self::new(StatusCode::BAD_GATEWAY, "external_service_error", message)
+            }
+            ServiceError::InternalError { message } => {
+                tracing::error!("Internal error: {}", message);
+                Self::internal("internal_error", "Internal server error")
+            }
         }
-    };
+    }
+}
 
-    let error_response = ApiResponse::<()>::error(message, error_type, None);
-    (status, serde_json::to_string(&error_response).unwrap())
+impl From<LightningError> for ApiError {
+    fn from(error: LightningError) -> Self {
+        let error_type = match error {
+            LightningError::ConnectionError(_) | LightningError::NetworkError(_) => {
+                "node_unreachable"
+            }
+            LightningError::NotFound(_) => "not_found",
+            LightningError::ValidationError(_) => "validation_error",
+            LightningError::RateLimited(_) => "node_rate_limited",
+            _ => "node_error",
+        };
+        Self::new(
+            Self::lightning_status(&error),
+            error_type,
+            error.to_string(),
+        )
+    }
+}
+
+impl From<validator::ValidationErrors> for ApiError {
+    fn from(errors: validator::ValidationErrors) -> Self {
+        Self {
+            details: Some(validation_errors_to_field_errors(errors)),
+            ..Self::bad_request("validation_error", "Validation failed")
+        }
+    }
 }
 
 /// Formats validator::ValidationErrors into field-specific error details
@@ -397,17 +526,6 @@ pub fn validation_errors_to_field_errors(errors: validator::ValidationErrors) ->
         .collect()
 }
 
-/// Helper to create validation error response
-pub fn validation_error_response(errors: validator::ValidationErrors) -> (StatusCode, String) {
-    let field_errors = validation_errors_to_field_errors(errors);
-    let error_response =
-        ApiResponse::<()>::error("Validation failed", "validation_error", Some(field_errors));
-    (
-        StatusCode::BAD_REQUEST,
-        serde_json::to_string(&error_response).unwrap(),
-    )
-}
-
 /// Apply pagination to a collection
 pub fn apply_pagination<T>(items: Vec<T>, pagination: &PaginationFilter) -> Vec<T> {
     let offset = pagination.offset() as usize;
@@ -454,6 +572,20 @@ mod tests {
         assert!(!meta.has_prev);
     }
 
+    #[test]
+    fn test_cursor_pagination_meta() {
+        let meta = PaginationMeta::from_cursor(10, 25, false, Some("abc".to_string()));
+        assert_eq!(meta.total_pages, 3);
+        assert!(meta.has_next);
+        assert!(!meta.has_prev);
+        assert_eq!(meta.next_cursor.as_deref(), Some("abc"));
+        assert_eq!(meta.next_page, None);
+
+        let meta = PaginationMeta::from_cursor(10, 25, true, None);
+        assert!(!meta.has_next);
+        assert!(meta.has_prev);
+    }
+
     #[test]
     fn test_pagination_filter() {
         let filter = PaginationFilter {
@@ -477,4 +609,32 @@ mod tests {
         let paginated = apply_pagination(items, &pagination);
         assert_eq!(paginated, vec![4, 5, 6]); // Skip 3, take 3
     }
+
+    #[test]
+    fn test_api_error_status_mapping() {
+        let error = ApiError::from(ServiceError::not_found("Notification", "abc"));
+        assert_eq!(error.status, StatusCode::NOT_FOUND);
+        assert_eq!(error.message, "Notification 'abc' not found");
+
+        let error = ApiError::from(ServiceError::already_exists("User", "bob"));
+        assert_eq!(error.status, StatusCode::CONFLICT);
+
+        let error = ApiError::from(LightningError::ConnectionError("timed out".to_string()));
+        assert_eq!(error.status, StatusCode::BAD_GATEWAY);
+        assert_eq!(error.error_type, "node_unreachable");
+
+        let error = ApiError::from(LightningError::NotFound("invoice".to_string()));
+        assert_eq!(error.status, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_api_error_validation_details() {
+        let filter = PaginationFilter {
+            page: Some(0),
+            per_page: Some(500),
+        };
+        let error = ApiError::from(filter.validate().unwrap_err());
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.details.map(|details| details.len()), Some(2));
+    }
 }
@@ -3,10 +3,24 @@
 //! These functions process requests for credential data, interact with the database
 //! or relevant services, and return credential-specific information.
 
-use crate::api::common::ApiResponse;
+use crate::api::common::{ApiError, ApiResponse};
+use crate::api::node::handlers::{
+    check_lnd_macaroon, generate_new_token_with_credentials, is_read_only,
+};
+use crate::database::models::{AuditAction, NodeLabel, UpdateNodeLabelRequest};
 use crate::repositories::credential_repository::CredentialRepository;
+use crate::repositories::node_label_repository::NodeLabelRepository;
+use crate::services::activity_feed::ActivityService;
+use crate::services::credential_service::CredentialService;
+use crate::services::node_label_service::{LabeledNode, NodeLabelService};
+use crate::services::node_manager::ConnectionRequest;
+use crate::utils::handlers_common::require_write_access;
 use crate::utils::jwt::Claims;
-use axum::{Json, extract::Extension, http::StatusCode};
+use axum::{
+    Json,
+    extract::{Extension, Path},
+};
+use chrono::{DateTime, Utc};
 use sqlx::SqlitePool;
 
 /// Response structure for credential status
@@ -15,6 +29,9 @@ pub struct CredentialStatus {
     pub has_credential: bool,
     pub node_id: Option<String>,
     pub node_alias: Option<String>,
+    pub label: Option<NodeLabel>,
+    pub tls_cert_expires_at: Option<DateTime<Utc>>,
+    pub macaroon_expires_at: Option<DateTime<Utc>>,
 }
 
 /// Get the credential status for the authenticated user
@@ -22,15 +39,25 @@ pub struct CredentialStatus {
 pub async fn get_user_credential_status(
     Extension(pool): Extension<SqlitePool>,
     Extension(claims): Extension<Claims>,
-) -> Result<Json<ApiResponse<CredentialStatus>>, (StatusCode, String)> {
+) -> Result<Json<ApiResponse<CredentialStatus>>, ApiError> {
     let repo = CredentialRepository::new(&pool);
 
     match repo.get_credential_by_user_id(&claims.sub).await {
         Ok(Some(credential)) => {
+            let label = NodeLabelRepository::new(&pool)
+                .get_label(&claims.account_id, &credential.node_id)
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Failed to load node label: {}", e);
+                    None
+                });
             let status = CredentialStatus {
                 has_credential: true,
                 node_id: Some(credential.node_id),
                 node_alias: Some(credential.node_alias),
+                label,
+                tls_cert_expires_at: credential.tls_cert_expires_at,
+                macaroon_expires_at: credential.macaroon_expires_at,
             };
             Ok(Json(ApiResponse::success(
                 status,
@@ -42,6 +69,9 @@ pub async fn get_user_credential_status(
                 has_credential: false,
                 node_id: None,
                 node_alias: None,
+                label: None,
+                tls_cert_expires_at: None,
+                macaroon_expires_at: None,
             };
             Ok(Json(ApiResponse::success(
                 status,
@@ -50,15 +80,147 @@ pub async fn get_user_credential_status(
         }
         Err(e) => {
             tracing::error!("Failed to get credential status: {}", e);
-            let error_response = ApiResponse::<()>::error(
-                "Failed to retrieve credential status".to_string(),
+            Err(ApiError::internal(
                 "database_error",
-                None,
-            );
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                serde_json::to_string(&error_response).unwrap(),
+                "Failed to retrieve credential status",
             ))
         }
     }
 }
+
+/// Lists the account's nodes visible to the caller, with their labels.
+#[axum::debug_handler]
+pub async fn get_nodes(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<Vec<LabeledNode>>>, ApiError> {
+    let nodes = NodeLabelService::new(&pool).get_nodes(&claims).await?;
+
+    Ok(Json(ApiResponse::success(
+        nodes,
+        "Nodes retrieved successfully",
+    )))
+}
+
+/// Sets the display name, color, environment and notes of a node.
+#[axum::debug_handler]
+pub async fn update_node_label(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(node_id): Path<String>,
+    Json(payload): Json<UpdateNodeLabelRequest>,
+) -> Result<Json<ApiResponse<NodeLabel>>, ApiError> {
+    require_write_access(&claims, "change node labels")?;
+
+    let label = NodeLabelService::new(&pool)
+        .update_label(&claims.account_id, &node_id, payload)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        label,
+        "Node label updated successfully",
+    )))
+}
+
+/// Removes the label of a node.
+#[axum::debug_handler]
+pub async fn delete_node_label(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(node_id): Path<String>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    require_write_access(&claims, "change node labels")?;
+
+    NodeLabelService::new(&pool)
+        .delete_label(&claims.account_id, &node_id)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        (),
+        "Node label removed successfully",
+    )))
+}
+
+/// Response structure for a credential rotation
+#[derive(Debug, serde::Serialize)]
+pub struct CredentialRotationResponse {
+    pub credential_id: String,
+    pub node_id: String,
+    pub node_alias: String,
+    pub read_only: bool,
+    pub tls_cert_expires_at: Option<DateTime<Utc>>,
+    pub macaroon_expires_at: Option<DateTime<Utc>>,
+    pub rotated_at: DateTime<Utc>,
+    /// Token carrying the new credentials, when the caller's token carried the old ones
+    pub new_access_token: Option<String>,
+}
+
+/// Replaces the stored credentials of a node with new ones, taking the same
+/// body as `/api/node/auth`.
+///
+/// The new credentials must reach the same node; running event collectors
+/// are left as they are.
+#[axum::debug_handler]
+pub async fn rotate_credential(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(payload): Json<ConnectionRequest>,
+) -> Result<Json<ApiResponse<CredentialRotationResponse>>, ApiError> {
+    require_write_access(&claims, "rotate credentials")?;
+
+    let macaroon_permissions = match &payload {
+        ConnectionRequest::Lnd(lnd_conn) => check_lnd_macaroon(&lnd_conn.macaroon).await?,
+        _ => None,
+    };
+    let read_only = is_read_only(&payload, macaroon_permissions.as_ref());
+
+    let (credential, node_info) = CredentialService::new(&pool)
+        .rotate_credential(&claims, &id, &payload, read_only)
+        .await?;
+    tracing::info!(
+        "Credential {} of node {} rotated by user {}",
+        credential.id,
+        credential.node_id,
+        claims.sub
+    );
+    ActivityService::new(&pool)
+        .record(
+            &claims,
+            AuditAction::CredentialRotated,
+            Some(&credential.node_id),
+            Some(serde_json::json!({
+                "credential_id": credential.id,
+                "node_type": credential.node_type,
+                "read_only": credential.read_only,
+            })),
+        )
+        .await?;
+
+    let carries_old_credentials = credential.user_id == claims.sub
+        && claims
+            .node_credentials
+            .as_ref()
+            .is_some_and(|node| node.node_id == credential.node_id);
+    let new_access_token = if carries_old_credentials {
+        generate_new_token_with_credentials(&claims, &payload, &node_info, read_only)
+            .map_err(|e| tracing::warn!("Failed to issue a token with the new credentials: {}", e))
+            .ok()
+    } else {
+        None
+    };
+
+    Ok(Json(ApiResponse::success(
+        CredentialRotationResponse {
+            credential_id: credential.id,
+            node_id: credential.node_id,
+            node_alias: credential.node_alias,
+            read_only: credential.read_only,
+            tls_cert_expires_at: credential.tls_cert_expires_at,
+            macaroon_expires_at: credential.macaroon_expires_at,
+            rotated_at: credential.updated_at,
+            new_access_token,
+        },
+        "Credential rotated successfully",
+    )))
+}
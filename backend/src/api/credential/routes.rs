@@ -5,12 +5,30 @@
 
 use crate::api::credential::handlers;
 use crate::auth::middleware::jwt_auth;
-use axum::{Router, middleware, routing::get};
+use axum::{
+    Router, middleware,
+    routing::{get, put},
+};
 
 /// Creates and returns the credential routes
 pub fn credential_routes() -> Router {
-    Router::new().route(
-        "/status",
-        get(handlers::get_user_credential_status).layer(middleware::from_fn(jwt_auth)),
-    )
+    Router::new()
+        .route(
+            "/status",
+            get(handlers::get_user_credential_status).layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/nodes",
+            get(handlers::get_nodes).layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/{id}",
+            put(handlers::rotate_credential).layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/nodes/{node_id}/label",
+            put(handlers::update_node_label)
+                .delete(handlers::delete_node_label)
+                .layer(middleware::from_fn(jwt_auth)),
+        )
 }
@@ -1,69 +1,506 @@
 //! Handler functions for event management API endpoints.
 
-use crate::api::common::{ApiResponse, PaginatedData, service_error_to_http};
-use crate::database::models::EventResponse;
+use crate::api::common::{ApiError, ApiResponse, PaginatedData, PaginationMeta};
+use crate::database::models::{
+    CreateEventSourceRequest, Event, EventCursor, EventFilters, EventHourlyAggregate,
+    EventResponse, EventSource, IngestEventRequest, RawEventResponse,
+};
 use crate::services::event_service::EventService;
+use crate::services::event_source_service::{CreatedEventSource, EventSourceService};
+use crate::services::event_stats::EventStats;
+use crate::services::node_group_service::{NodeGroupService, NodeScope};
+use crate::services::payment_stats::{StatsBucket, parse_window, stats_span};
+use crate::utils::handlers_common::{request_tz, require_admin};
 use crate::utils::jwt::Claims;
+use crate::utils::time_zone::{DateBound, DateRange};
 use axum::{
-    extract::{Extension, Path},
-    http::StatusCode,
+    extract::{Extension, Path, Query},
+    http::{HeaderMap, header::AUTHORIZATION},
     response::Json as ResponseJson,
 };
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
+use std::str::FromStr;
+
+/// Default number of events returned per page.
+const DEFAULT_EVENTS_PER_PAGE: u32 = 20;
+/// Largest page a client may request.
+const MAX_EVENTS_PER_PAGE: u32 = 100;
+
+/// Query parameters for keyset pagination over events.
+#[derive(Debug, Deserialize)]
+pub struct EventPageQuery {
+    /// `next_cursor` from the previous page; omit for the newest events
+    pub cursor: Option<String>,
+    /// Number of events per page
+    pub limit: Option<u32>,
+    /// Comma-separated event types, e.g. `channel_opened,channel_closed`
+    #[serde(rename = "type")]
+    pub event_type: Option<String>,
+    /// Comma-separated severities, e.g. `warning,critical`
+    pub severity: Option<String>,
+    /// Only events of this node
+    pub node_id: Option<String>,
+    /// Only warning and critical events nobody has acknowledged
+    #[serde(default)]
+    pub unacknowledged: bool,
+    /// Earliest event time, a date or an RFC 3339 time
+    pub from: Option<DateBound>,
+    /// Latest event time (inclusive), a date or an RFC 3339 time
+    pub to: Option<DateBound>,
+    /// IANA time zone dates are read in, the account's when absent
+    pub tz: Option<String>,
+}
+
+/// Parses a comma-separated list of values.
+fn parse_list<T: FromStr<Err = String>>(
+    value: Option<&str>,
+    error_type: &str,
+) -> Result<Option<Vec<T>>, ApiError> {
+    value
+        .map(|value| value.split(',').map(|item| item.trim().parse()).collect())
+        .transpose()
+        .map_err(|e| ApiError::bad_request(error_type, e))
+}
+
+/// Resolves `from`/`to` in the request's time zone, looking it up only when needed.
+async fn date_range(
+    pool: &SqlitePool,
+    account_id: &str,
+    from: Option<DateBound>,
+    to: Option<DateBound>,
+    tz: Option<&str>,
+) -> Result<DateRange, ApiError> {
+    match (from, to) {
+        (None, None) => Ok(DateRange::default()),
+        (from, to) => {
+            let tz = request_tz(pool, account_id, tz).await?;
+            Ok(DateRange::resolve(from, to, tz))
+        }
+    }
+}
 
-/// Retrieves events for the user's account.
+/// Builds the filters shared by listing and bulk deletion, kept within the
+/// caller's node scope.
+fn event_filters(
+    scope: &NodeScope,
+    node_id: Option<String>,
+    event_type: Option<&str>,
+    severity: Option<&str>,
+    unacknowledged_only: bool,
+    range: DateRange,
+) -> Result<EventFilters, ApiError> {
+    let node_ids = match node_id {
+        // A node outside the scope matches no events
+        Some(node_id) if scope.allows(&node_id) => Some(vec![node_id]),
+        Some(_) => Some(Vec::new()),
+        None => scope.node_ids().map(<[String]>::to_vec),
+    };
+
+    Ok(EventFilters {
+        event_types: parse_list(event_type, "invalid_event_type")?,
+        severities: parse_list(severity, "invalid_severity")?,
+        node_ids,
+        start_date: range.start,
+        end_date: range.end,
+        unacknowledged_only,
+        ..Default::default()
+    })
+}
+
+/// Retrieves events for the user's account, newest first.
 #[axum::debug_handler]
 pub async fn get_events(
     Extension(pool): Extension<SqlitePool>,
     Extension(claims): Extension<Claims>,
-) -> Result<ResponseJson<ApiResponse<PaginatedData<EventResponse>>>, (StatusCode, String)> {
+    Query(query): Query<EventPageQuery>,
+) -> Result<ResponseJson<ApiResponse<PaginatedData<EventResponse>>>, ApiError> {
     let account_id = claims.account_id();
 
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(str::parse::<EventCursor>)
+        .transpose()
+        .map_err(|e| ApiError::bad_request("invalid_cursor", e))?;
+    let has_prev = cursor.is_some();
+    let per_page = query
+        .limit
+        .unwrap_or(DEFAULT_EVENTS_PER_PAGE)
+        .clamp(1, MAX_EVENTS_PER_PAGE);
+
+    // Members of node groups only see events of their groups' nodes
+    let scope = NodeGroupService::new(&pool)
+        .node_scope_for_claims(&claims)
+        .await?;
+
+    let range = date_range(&pool, account_id, query.from, query.to, query.tz.as_deref()).await?;
+    let filters = event_filters(
+        &scope,
+        query.node_id,
+        query.event_type.as_deref(),
+        query.severity.as_deref(),
+        query.unacknowledged,
+        range,
+    )?;
+
     let service = EventService::new(&pool);
 
-    // Get all events for the account
-    let events = service
-        .get_events_for_account(&pool, account_id, None)
-        .await
-        .map_err(service_error_to_http)?;
+    let total = service
+        .count_events_for_account(account_id, &filters)
+        .await?;
 
-    let total = events.len() as u64;
-    let response = PaginatedData::new(events, total);
+    let (events, next_cursor) = service
+        .get_events_page(account_id, filters, cursor, per_page as i64)
+        .await?;
 
-    Ok(ResponseJson(ApiResponse::success(
-        response,
+    let pagination = PaginationMeta::from_cursor(
+        per_page,
+        total,
+        has_prev,
+        next_cursor.map(|cursor| cursor.to_string()),
+    );
+
+    Ok(ResponseJson(ApiResponse::paginated(
+        PaginatedData::new(events, total),
+        pagination,
         "Events retrieved successfully",
     )))
 }
 
+/// Query parameters for bulk event deletion, the filters of `EventPageQuery`.
+#[derive(Debug, Deserialize)]
+pub struct EventDeleteQuery {
+    /// Comma-separated event types, e.g. `channel_opened,channel_closed`
+    #[serde(rename = "type")]
+    pub event_type: Option<String>,
+    /// Comma-separated severities, e.g. `warning,critical`
+    pub severity: Option<String>,
+    /// Only events of this node
+    pub node_id: Option<String>,
+    /// Only warning and critical events nobody has acknowledged
+    #[serde(default)]
+    pub unacknowledged: bool,
+    /// Earliest event time, a date or an RFC 3339 time
+    pub from: Option<DateBound>,
+    /// Latest event time (inclusive), a date or an RFC 3339 time
+    pub to: Option<DateBound>,
+    /// IANA time zone dates are read in, the account's when absent
+    pub tz: Option<String>,
+}
+
+/// Outcome of a bulk event deletion.
+#[derive(Debug, Serialize)]
+pub struct DeletedEvents {
+    pub deleted: u64,
+}
+
+/// Soft deletes every event of the account matching the filters, e.g. to clear
+/// the noise of a misconfigured alert.
+///
+/// At least one filter is required so a bare request can't wipe the account's history.
+#[axum::debug_handler]
+pub async fn delete_events(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<EventDeleteQuery>,
+) -> Result<ResponseJson<ApiResponse<DeletedEvents>>, ApiError> {
+    require_admin(&claims, "delete events")?;
+    let unfiltered = query.event_type.is_none()
+        && query.severity.is_none()
+        && query.node_id.is_none()
+        && query.from.is_none()
+        && query.to.is_none()
+        && !query.unacknowledged;
+    if unfiltered {
+        return Err(ApiError::bad_request(
+            "missing_filter",
+            "Pass at least one of type, severity, node_id, unacknowledged, from or to",
+        ));
+    }
+
+    let account_id = claims.account_id();
+    let scope = NodeGroupService::new(&pool)
+        .node_scope_for_claims(&claims)
+        .await?;
+    let range = date_range(&pool, account_id, query.from, query.to, query.tz.as_deref()).await?;
+    let filters = event_filters(
+        &scope,
+        query.node_id,
+        query.event_type.as_deref(),
+        query.severity.as_deref(),
+        query.unacknowledged,
+        range,
+    )?;
+
+    let deleted = EventService::new(&pool)
+        .delete_events(account_id, &filters)
+        .await?;
+    tracing::info!(
+        "User {} deleted {} events of account {}",
+        claims.sub,
+        deleted,
+        account_id
+    );
+
+    Ok(ResponseJson(ApiResponse::success(
+        DeletedEvents { deleted },
+        "Events deleted successfully",
+    )))
+}
+
+/// Window used when `GET /api/events/stats` is called without one.
+const DEFAULT_STATS_WINDOW: &str = "30d";
+
+/// Query parameters for event statistics.
+#[derive(Debug, Deserialize)]
+pub struct EventStatsQuery {
+    /// How far back to look, e.g. `24h`, `30d` or `12w`; ignored when `from` is given
+    pub window: Option<String>,
+    /// Start of the statistics, a date or an RFC 3339 time
+    pub from: Option<DateBound>,
+    /// End of the statistics (inclusive), now when absent
+    pub to: Option<DateBound>,
+    /// IANA time zone for dates and bucket boundaries, the account's when absent
+    pub tz: Option<String>,
+    /// Width of each bucket
+    #[serde(default)]
+    pub bucket: StatsBucket,
+}
+
+/// Counts the account's events by severity over time.
+#[axum::debug_handler]
+pub async fn get_event_stats(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<EventStatsQuery>,
+) -> Result<ResponseJson<ApiResponse<EventStats>>, ApiError> {
+    let window = parse_window(query.window.as_deref().unwrap_or(DEFAULT_STATS_WINDOW))
+        .map_err(|e| ApiError::bad_request("invalid_window", e))?;
+    let tz = request_tz(&pool, claims.account_id(), query.tz.as_deref()).await?;
+    let (start, end) = stats_span(window, query.from, query.to, tz, Utc::now())
+        .map_err(|e| ApiError::bad_request("invalid_window", e))?;
+
+    let scope = NodeGroupService::new(&pool)
+        .node_scope_for_claims(&claims)
+        .await?;
+
+    let stats = EventService::new(&pool)
+        .get_event_stats(
+            claims.account_id(),
+            scope.node_ids(),
+            start,
+            end,
+            query.bucket,
+            tz,
+        )
+        .await?;
+
+    Ok(ResponseJson(ApiResponse::success(
+        stats,
+        "Event statistics retrieved successfully",
+    )))
+}
+
+/// Window used when `GET /api/events/aggregates` is called without one.
+const DEFAULT_AGGREGATES_WINDOW: &str = "90d";
+
+/// Query parameters for the hourly aggregates of compacted events.
+#[derive(Debug, Deserialize)]
+pub struct EventAggregatesQuery {
+    /// How far back to look, e.g. `30d` or `12w`; ignored when `from` is given
+    pub window: Option<String>,
+    /// Start of the aggregates, a date or an RFC 3339 time
+    pub from: Option<DateBound>,
+    /// End of the aggregates (inclusive), now when absent
+    pub to: Option<DateBound>,
+    /// IANA time zone for dates, the account's when absent
+    pub tz: Option<String>,
+}
+
+/// Lists the hourly counts and amount sums of the account's compacted events.
+#[axum::debug_handler]
+pub async fn get_event_aggregates(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<EventAggregatesQuery>,
+) -> Result<ResponseJson<ApiResponse<Vec<EventHourlyAggregate>>>, ApiError> {
+    let window = parse_window(query.window.as_deref().unwrap_or(DEFAULT_AGGREGATES_WINDOW))
+        .map_err(|e| ApiError::bad_request("invalid_window", e))?;
+    let tz = request_tz(&pool, claims.account_id(), query.tz.as_deref()).await?;
+    let (start, end) = stats_span(window, query.from, query.to, tz, Utc::now())
+        .map_err(|e| ApiError::bad_request("invalid_window", e))?;
+
+    let scope = NodeGroupService::new(&pool)
+        .node_scope_for_claims(&claims)
+        .await?;
+
+    let aggregates = EventService::new(&pool)
+        .get_hourly_aggregates(claims.account_id(), scope.node_ids(), start, end)
+        .await?;
+
+    Ok(ResponseJson(ApiResponse::success(
+        aggregates,
+        "Event aggregates retrieved successfully",
+    )))
+}
+
 /// Retrieves a specific event by ID.
 #[axum::debug_handler]
 pub async fn get_event_by_id(
     Extension(pool): Extension<SqlitePool>,
     Extension(claims): Extension<Claims>,
     Path(id): Path<String>,
-) -> Result<ResponseJson<ApiResponse<EventResponse>>, (StatusCode, String)> {
+) -> Result<ResponseJson<ApiResponse<EventResponse>>, ApiError> {
     let account_id = claims.account_id();
 
+    let scope = NodeGroupService::new(&pool)
+        .node_scope_for_claims(&claims)
+        .await?;
+    let filters = EventFilters {
+        node_ids: scope.node_ids().map(<[String]>::to_vec),
+        ..Default::default()
+    };
+
     let service = EventService::new(&pool);
 
     // Get all events for the account
     let events = service
-        .get_events_for_account(&pool, account_id, None)
-        .await
-        .map_err(service_error_to_http)?;
+        .get_events_for_account(&pool, account_id, Some(filters))
+        .await?;
 
     // Find the specific event by ID
-    let event = events.into_iter().find(|e| e.id == id).ok_or_else(|| {
-        let error_response = ApiResponse::<()>::error("Event not found", "not_found", None);
-        (
-            StatusCode::NOT_FOUND,
-            serde_json::to_string(&error_response).unwrap(),
-        )
-    })?;
+    let event = events
+        .into_iter()
+        .find(|e| e.id == id)
+        .ok_or_else(|| ApiError::not_found("not_found", "Event not found"))?;
 
     Ok(ResponseJson(ApiResponse::success(
         event,
         "Event retrieved successfully",
     )))
 }
+
+/// Acknowledges a warning or critical event.
+#[axum::debug_handler]
+pub async fn acknowledge_event(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<EventResponse>>, ApiError> {
+    let event = EventService::new(&pool)
+        .acknowledge_event(&claims, &id)
+        .await?;
+
+    Ok(ResponseJson(ApiResponse::success(
+        event,
+        "Event acknowledged successfully",
+    )))
+}
+
+/// Retrieves the full node message an event was built from.
+///
+/// Only events recorded while the account had `capture_raw_events` on have one.
+#[axum::debug_handler]
+pub async fn get_raw_event(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<RawEventResponse>>, ApiError> {
+    let raw = EventService::new(&pool).get_raw_event(&claims, &id).await?;
+
+    Ok(ResponseJson(ApiResponse::success(
+        raw,
+        "Raw event retrieved successfully",
+    )))
+}
+
+/// Lists the external systems allowed to push events into the account.
+#[axum::debug_handler]
+pub async fn list_event_sources(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+) -> Result<ResponseJson<ApiResponse<Vec<EventSource>>>, ApiError> {
+    require_admin(&claims, "manage event sources")?;
+
+    let sources = EventSourceService::new(&pool)
+        .list_sources(&claims.account_id)
+        .await?;
+
+    Ok(ResponseJson(ApiResponse::success(
+        sources,
+        "Event sources retrieved successfully",
+    )))
+}
+
+/// Registers an external event source and returns its ingest token.
+#[axum::debug_handler]
+pub async fn create_event_source(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    ResponseJson(payload): ResponseJson<CreateEventSourceRequest>,
+) -> Result<ResponseJson<ApiResponse<CreatedEventSource>>, ApiError> {
+    require_admin(&claims, "manage event sources")?;
+
+    let source = EventSourceService::new(&pool)
+        .create_source(&claims, payload)
+        .await?;
+
+    Ok(ResponseJson(ApiResponse::success(
+        source,
+        "Event source created; store the token now, it won't be shown again",
+    )))
+}
+
+/// Deletes an event source, revoking its token.
+#[axum::debug_handler]
+pub async fn delete_event_source(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<()>>, ApiError> {
+    require_admin(&claims, "manage event sources")?;
+
+    EventSourceService::new(&pool)
+        .delete_source(&claims.account_id, &id)
+        .await?;
+
+    Ok(ResponseJson(ApiResponse::success(
+        (),
+        "Event source deleted successfully",
+    )))
+}
+
+/// Accepts an event pushed by an external system.
+///
+/// Authenticated with the source's ingest token as a bearer token, not a user JWT.
+#[axum::debug_handler]
+pub async fn ingest_event(
+    Extension(pool): Extension<SqlitePool>,
+    headers: HeaderMap,
+    ResponseJson(payload): ResponseJson<IngestEventRequest>,
+) -> Result<ResponseJson<ApiResponse<Event>>, ApiError> {
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .ok_or_else(|| {
+            ApiError::unauthorized(
+                "authentication_error",
+                "Expected an ingest token as Authorization: Bearer <token>",
+            )
+        })?;
+
+    let service = EventSourceService::new(&pool);
+    let source = service
+        .authenticate(token.trim())
+        .await?
+        .ok_or_else(|| ApiError::unauthorized("authentication_error", "Invalid ingest token"))?;
+    let event = service.ingest(&source, payload).await?;
+
+    Ok(ResponseJson(ApiResponse::success(
+        event,
+        "Event ingested successfully",
+    )))
+}
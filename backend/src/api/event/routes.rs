@@ -1,12 +1,30 @@
 //! Defines the HTTP routes for event management.
 
-use super::handlers::{get_event_by_id, get_events};
+use super::handlers::{
+    acknowledge_event, create_event_source, delete_event_source, delete_events,
+    get_event_aggregates, get_event_by_id, get_event_stats, get_events, get_raw_event,
+    ingest_event, list_event_sources,
+};
 use crate::auth::middleware::jwt_auth;
-use axum::{Router, middleware, routing::get};
+use axum::{
+    Router, middleware,
+    routing::{delete, get, post},
+};
 
 pub async fn event_router() -> Router {
     Router::new()
-        .route("/", get(get_events))
+        .route("/", get(get_events).delete(delete_events))
+        .route("/stats", get(get_event_stats))
+        .route("/aggregates", get(get_event_aggregates))
+        .route(
+            "/sources",
+            get(list_event_sources).post(create_event_source),
+        )
+        .route("/sources/{id}", delete(delete_event_source))
         .route("/{id}", get(get_event_by_id))
+        .route("/{id}/ack", post(acknowledge_event))
+        .route("/{id}/raw", get(get_raw_event))
         .layer(middleware::from_fn(jwt_auth))
+        // Authenticated with per-source ingest tokens instead of user JWTs
+        .route("/ingest", post(ingest_event))
 }
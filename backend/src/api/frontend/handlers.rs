@@ -0,0 +1,97 @@
+//! Middleware around the frontend's static export.
+//!
+//! Unknown `/api/` and `/auth/` paths answer with a JSON 404 rather than the
+//! frontend's page, and every file is sent with the caching it allows.
+
+use crate::api::common::ApiError;
+use axum::{
+    extract::Request,
+    http::{HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+/// Files under `_next/static/` have content hashes in their names, so they
+/// never change; pages must be revalidated to pick up a new build. A missing
+/// asset is answered with the root page, which must not be cached in its place.
+fn cache_control(request_path: &str, status: StatusCode) -> &'static str {
+    if request_path.starts_with("/_next/static/") && status.is_success() {
+        "public, max-age=31536000, immutable"
+    } else {
+        "no-cache"
+    }
+}
+
+/// Keeps API paths out of the frontend and sets the caching of its files.
+pub async fn frontend_files(request: Request, next: Next) -> Response {
+    let path = request.uri().path().to_string();
+    if path.starts_with("/api/") || path.starts_with("/auth/") {
+        return ApiError::not_found("not_found", format!("No route for {path}")).into_response();
+    }
+
+    let mut response = next.run(request).await;
+    let cache_control = cache_control(&path, response.status());
+    response.headers_mut().insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(cache_control),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::frontend::routes::frontend_router;
+    use axum::body::Body;
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    async fn get(dir: &std::path::Path, path: &str) -> (StatusCode, String) {
+        let response = frontend_router(dir.to_path_buf())
+            .oneshot(Request::get(path).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    #[tokio::test]
+    async fn serves_pages_and_client_routes() {
+        let dir = std::env::temp_dir().join(format!("nodegaze-frontend-{}", Uuid::now_v7()));
+        std::fs::create_dir_all(dir.join("channels")).unwrap();
+        std::fs::write(dir.join("index.html"), "index").unwrap();
+        std::fs::write(dir.join("settings.html"), "settings").unwrap();
+        std::fs::write(dir.join("channels/index.html"), "channels").unwrap();
+        std::fs::write(dir.join("a b.txt"), "spaced").unwrap();
+
+        assert_eq!(get(&dir, "/").await, (StatusCode::OK, "index".to_string()));
+        assert_eq!(get(&dir, "/settings").await.1, "settings");
+        assert_eq!(get(&dir, "/channels/").await.1, "channels");
+        assert_eq!(get(&dir, "/a%20b.txt").await.1, "spaced");
+        assert_eq!(get(&dir, "/nodes/abc").await.1, "index");
+        assert_eq!(get(&dir, "/../index.html").await.1, "index");
+
+        let (status, body) = get(&dir, "/api/unknown").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(body.contains("not_found"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn caches_only_hashed_assets() {
+        assert_eq!(
+            cache_control("/_next/static/chunks/main.js", StatusCode::OK),
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(
+            cache_control("/_next/static/chunks/gone.js", StatusCode::NOT_FOUND),
+            "no-cache"
+        );
+        assert_eq!(cache_control("/index.html", StatusCode::OK), "no-cache");
+        assert_eq!(cache_control("/settings", StatusCode::OK), "no-cache");
+    }
+}
@@ -0,0 +1,7 @@
+//! Module serving a static export of the frontend from the backend.
+//!
+//! Enabled by setting `FRONTEND_DIR`, so a deployment can run the single
+//! backend binary and its database without a separate web server.
+
+pub mod handlers;
+pub mod routes;
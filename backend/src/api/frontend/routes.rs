@@ -0,0 +1,21 @@
+//! Defines the fallback route serving the frontend's files.
+
+use super::handlers::frontend_files;
+use axum::{Router, middleware};
+use std::path::PathBuf;
+use tower_http::services::{ServeDir, ServeFile};
+
+/// Serves the files of `dir` for every path no other route matches.
+///
+/// Pages are found by path, their `.html` file or a directory's `index.html`,
+/// as laid out by `next build` with `output: "export"`. Other paths get the
+/// root `index.html`, so the frontend's client-side routes load on a refresh.
+pub fn frontend_router(dir: PathBuf) -> Router {
+    let files = ServeDir::new(&dir)
+        .html_as_default_extension(true)
+        .not_found_service(ServeFile::new(dir.join("index.html")));
+
+    Router::new()
+        .fallback_service(files)
+        .layer(middleware::from_fn(frontend_files))
+}
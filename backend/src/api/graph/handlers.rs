@@ -0,0 +1,134 @@
+use crate::api::common::{ApiError, ApiResponse};
+use crate::services::external_profiles::{ExternalNodeProfile, ExternalProfileService};
+use crate::services::graph_cache::{self, GraphFreshness};
+use crate::services::network_stats::{NetworkStats, get_network_stats};
+use crate::services::peer_suggestions::{PeerSuggestion, suggest_peers};
+use crate::utils::GraphNode;
+use crate::utils::handlers_common::{SelectedNode, handle_node_error, parse_public_key};
+use axum::{
+    Json,
+    extract::{Extension, Path, Query},
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use validator::Validate;
+
+/// Suggestions returned when no limit is given.
+const DEFAULT_SUGGESTION_LIMIT: usize = 20;
+
+/// A node from the gossip graph, with third-party metadata when enrichment is on.
+#[derive(Debug, Serialize)]
+pub struct GraphNodeResponse {
+    #[serde(flatten)]
+    pub node: GraphNode,
+    pub external: Option<ExternalNodeProfile>,
+    pub graph: GraphFreshness,
+}
+
+/// Peer suggestions with the freshness of the graph they were ranked from.
+#[derive(Debug, Serialize)]
+pub struct PeerSuggestionsResponse {
+    pub suggestions: Vec<PeerSuggestion>,
+    pub graph: GraphFreshness,
+}
+
+/// Query parameters for peer suggestions.
+#[derive(Debug, Deserialize, Validate)]
+pub struct PeerSuggestionQuery {
+    /// Size of the channel to open, in satoshis.
+    #[validate(range(min = 1, message = "capacity must be positive"))]
+    pub capacity: u64,
+    #[validate(range(min = 1, max = 100, message = "limit must be between 1 and 100"))]
+    pub limit: Option<usize>,
+}
+
+#[axum::debug_handler]
+pub async fn get_graph_node(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(node): Extension<SelectedNode>,
+    Path(pubkey): Path<String>,
+) -> Result<Json<ApiResponse<GraphNodeResponse>>, ApiError> {
+    let pubkey = parse_public_key(&pubkey)?;
+    let node_client = node.client().await?;
+
+    let (graph_node, graph) = graph_cache::graph_node(&pool, node_client, &pubkey)
+        .await
+        .map_err(|e| handle_node_error(e, "get graph node"))?;
+    let external = ExternalProfileService::new(&pool)
+        .get_profile(&pubkey)
+        .await;
+
+    Ok(Json(ApiResponse::success(
+        GraphNodeResponse {
+            node: graph_node,
+            external,
+            graph,
+        },
+        "Graph node retrieved successfully",
+    )))
+}
+
+/// Network-wide stats from the selected node's graph, refreshed every few minutes.
+#[axum::debug_handler]
+pub async fn get_graph_stats(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(node): Extension<SelectedNode>,
+) -> Result<Json<ApiResponse<NetworkStats>>, ApiError> {
+    let node_client = node.client().await?;
+
+    let stats = get_network_stats(&pool, node_client)
+        .await
+        .map_err(|e| handle_node_error(e, "get graph stats"))?;
+
+    Ok(Json(ApiResponse::success(
+        NetworkStats::clone(&stats),
+        "Graph stats retrieved successfully",
+    )))
+}
+
+/// Ranks graph nodes the selected node could open a channel of `capacity` to.
+#[axum::debug_handler]
+pub async fn get_peer_suggestions(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(node): Extension<SelectedNode>,
+    Query(query): Query<PeerSuggestionQuery>,
+) -> Result<Json<ApiResponse<PeerSuggestionsResponse>>, ApiError> {
+    query.validate()?;
+    let node_client = node.client().await?;
+
+    let my_pubkey = node_client.get_info().pubkey;
+    let my_peers: HashSet<_> = node_client
+        .list_channels()
+        .await
+        .map_err(|e| handle_node_error(e, "list channels"))?
+        .into_iter()
+        .filter_map(|channel| channel.remote_pubkey)
+        .collect();
+    let (edges, graph) = graph_cache::graph_edges(&pool, node_client)
+        .await
+        .map_err(|e| handle_node_error(e, "list graph edges"))?;
+
+    let limit = query.limit.unwrap_or(DEFAULT_SUGGESTION_LIMIT);
+    let suggestions = tokio::task::spawn_blocking(move || {
+        suggest_peers(
+            &edges,
+            &my_pubkey,
+            &my_peers,
+            query.capacity,
+            limit,
+            Utc::now(),
+        )
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("Peer scoring failed: {}", e);
+        ApiError::internal("internal_error", "Failed to score peers")
+    })?;
+
+    Ok(Json(ApiResponse::success(
+        PeerSuggestionsResponse { suggestions, graph },
+        "Peer suggestions retrieved successfully",
+    )))
+}
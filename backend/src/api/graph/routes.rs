@@ -0,0 +1,28 @@
+use super::handlers::{get_graph_node, get_graph_stats, get_peer_suggestions};
+use crate::auth::middleware::{jwt_auth, node_group_access_required, node_selection};
+use axum::{Router, middleware, routing::get};
+
+pub async fn graph_router() -> Router {
+    Router::new()
+        .route(
+            "/node/{pubkey}",
+            get(get_graph_node)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/peer-suggestions",
+            get(get_peer_suggestions)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/stats",
+            get(get_graph_stats)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+}
@@ -0,0 +1,39 @@
+//! Handler functions for health probe endpoints.
+
+use crate::api::common::ApiResponse;
+use crate::services::health::{self, ReadinessReport};
+use axum::{
+    extract::{Extension, Json},
+    http::StatusCode,
+};
+use sqlx::SqlitePool;
+
+/// Liveness probe: the process is up and serving requests.
+#[axum::debug_handler]
+pub async fn liveness() -> Json<ApiResponse<serde_json::Value>> {
+    Json(ApiResponse::success(
+        serde_json::json!({ "status": "ok" }),
+        "Service is alive",
+    ))
+}
+
+/// Readiness probe: answers 503 while a required dependency is down.
+#[axum::debug_handler]
+pub async fn readiness(
+    Extension(pool): Extension<SqlitePool>,
+) -> (StatusCode, Json<ApiResponse<ReadinessReport>>) {
+    let report = health::readiness(&pool).await;
+
+    if report.ready {
+        (
+            StatusCode::OK,
+            Json(ApiResponse::success(report, "Service is ready")),
+        )
+    } else {
+        tracing::warn!("Readiness check failed: {:?}", report.components);
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ApiResponse::success(report, "Service is not ready")),
+        )
+    }
+}
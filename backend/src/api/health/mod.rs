@@ -0,0 +1,7 @@
+//! Module for liveness and readiness probe endpoints.
+//!
+//! These endpoints are unauthenticated so orchestrators such as Kubernetes can
+//! poll them.
+
+pub mod handlers;
+pub mod routes;
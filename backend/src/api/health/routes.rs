@@ -0,0 +1,10 @@
+//! Defines the HTTP routes for health probes.
+
+use super::handlers::{liveness, readiness};
+use axum::{Router, routing::get};
+
+pub fn health_router() -> Router {
+    Router::new()
+        .route("/healthz", get(liveness))
+        .route("/readyz", get(readiness))
+}
@@ -3,26 +3,23 @@
 //! These functions process requests for invite data, interact with the database
 //! or relevant services, and return invite-specific information.
 
-use crate::api::common::ApiResponse;
-use crate::config::Config;
+use crate::api::common::{ApiError, ApiResponse};
+use crate::config::AppConfig;
 use crate::database::models::{AcceptInviteRequest, CreateInviteRequest, Invite, User};
 use crate::services::invite_service::InviteService;
 use crate::services::user_service::UserService;
 use crate::utils::jwt::Claims;
-use axum::{
-    extract::{Extension, Json, Path},
-    http::StatusCode,
-};
+use axum::extract::{Extension, Json, Path};
 use sqlx::SqlitePool;
 
 /// Handle invite creation request
 #[axum::debug_handler]
 pub async fn create_invite(
     Extension(pool): Extension<SqlitePool>,
+    Extension(config): Extension<AppConfig>,
     Extension(claims): Extension<Claims>,
     Json(payload): Json<CreateInviteRequest>,
-) -> Result<Json<ApiResponse<Invite>>, (StatusCode, String)> {
-    let config = Config::from_env().unwrap();
+) -> Result<Json<ApiResponse<Invite>>, ApiError> {
     let user_id = claims.sub.as_str().to_string();
 
     tracing::info!("Creating invite for user: {}", user_id);
@@ -33,26 +30,16 @@ pub async fn create_invite(
         .await
         .map_err(|e| {
             tracing::error!("User not found for ID {}: {}", user_id, e);
-            let error_response =
-                ApiResponse::<()>::error("User not found".to_string(), "user_not_found", None);
-            (
-                StatusCode::NOT_FOUND,
-                serde_json::to_string(&error_response).unwrap(),
-            )
+            ApiError::not_found("user_not_found", "User not found")
         })?;
 
     let service = InviteService::new(&pool, &config);
 
     let invite = service.create_invite(payload, user).await.map_err(|e| {
         tracing::error!("Failed to create invite for user {}: {}", user_id, e);
-        let error_response = ApiResponse::<()>::error(
-            format!("Failed to create invite: {e}"),
+        ApiError::internal(
             "invite_creation_error",
-            None,
-        );
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            serde_json::to_string(&error_response).unwrap(),
+            format!("Failed to create invite: {e}"),
         )
     })?;
 
@@ -68,9 +55,9 @@ pub async fn create_invite(
 pub async fn get_invite_by_id(
     Extension(claims): Extension<Claims>,
     Extension(pool): Extension<SqlitePool>,
+    Extension(config): Extension<AppConfig>,
     Path(id): Path<String>,
-) -> Result<Json<ApiResponse<Invite>>, (StatusCode, String)> {
-    let config = Config::from_env().unwrap();
+) -> Result<Json<ApiResponse<Invite>>, ApiError> {
     let user_id = claims.sub.as_str().to_string();
 
     tracing::info!("Getting invite by ID: {} for user: {}", id, user_id);
@@ -81,12 +68,7 @@ pub async fn get_invite_by_id(
         .await
         .map_err(|e| {
             tracing::error!("User not found for ID {}: {}", user_id, e);
-            let error_response =
-                ApiResponse::<()>::error("User not found".to_string(), "user_not_found", None);
-            (
-                StatusCode::NOT_FOUND,
-                serde_json::to_string(&error_response).unwrap(),
-            )
+            ApiError::not_found("user_not_found", "User not found")
         })?;
 
     let service = InviteService::new(&pool, &config);
@@ -95,15 +77,7 @@ pub async fn get_invite_by_id(
         .await
         .map_err(|e| {
             tracing::error!("Failed to find invite {}: {}", id, e);
-            let error_response = ApiResponse::<()>::error(
-                format!("Failed to find invite: {e}"),
-                "invite_not_found",
-                None,
-            );
-            (
-                StatusCode::NOT_FOUND,
-                serde_json::to_string(&error_response).unwrap(),
-            )
+            ApiError::not_found("invite_not_found", format!("Failed to find invite: {e}"))
         })?;
 
     tracing::info!("Invite found: {}", invite.id);
@@ -118,8 +92,8 @@ pub async fn get_invite_by_id(
 pub async fn get_invites(
     Extension(claims): Extension<Claims>,
     Extension(pool): Extension<SqlitePool>,
-) -> Result<Json<ApiResponse<Vec<Invite>>>, (StatusCode, String)> {
-    let config = Config::from_env().unwrap();
+    Extension(config): Extension<AppConfig>,
+) -> Result<Json<ApiResponse<Vec<Invite>>>, ApiError> {
     let user_id = claims.sub.as_str().to_string();
 
     tracing::info!("Getting all invites for user: {}", user_id);
@@ -130,12 +104,7 @@ pub async fn get_invites(
         .await
         .map_err(|e| {
             tracing::error!("User not found for ID {}: {}", user_id, e);
-            let error_response =
-                ApiResponse::<()>::error("User not found".to_string(), "user_not_found", None);
-            (
-                StatusCode::NOT_FOUND,
-                serde_json::to_string(&error_response).unwrap(),
-            )
+            ApiError::not_found("user_not_found", "User not found")
         })?;
 
     let service = InviteService::new(&pool, &config);
@@ -144,15 +113,7 @@ pub async fn get_invites(
         .await
         .map_err(|e| {
             tracing::error!("No invites found for account {}: {}", user.account_id, e);
-            let error_response = ApiResponse::<()>::error(
-                format!("No invites found: {e}"),
-                "invites_not_found",
-                None,
-            );
-            (
-                StatusCode::NOT_FOUND,
-                serde_json::to_string(&error_response).unwrap(),
-            )
+            ApiError::not_found("invites_not_found", format!("No invites found: {e}"))
         })?;
 
     tracing::info!("Found {} invites for user: {}", invites.len(), user_id);
@@ -166,10 +127,10 @@ pub async fn get_invites(
 #[axum::debug_handler]
 pub async fn resend_invite(
     Extension(pool): Extension<SqlitePool>,
+    Extension(config): Extension<AppConfig>,
     Extension(claims): Extension<Claims>,
     Path(id): Path<String>,
-) -> Result<Json<ApiResponse<Invite>>, (StatusCode, String)> {
-    let config = Config::from_env().unwrap();
+) -> Result<Json<ApiResponse<Invite>>, ApiError> {
     let user_id = claims.sub.as_str().to_string();
 
     tracing::info!("Resending invite {} for user: {}", id, user_id);
@@ -180,25 +141,15 @@ pub async fn resend_invite(
         .await
         .map_err(|e| {
             tracing::error!("User not found for ID {}: {}", user_id, e);
-            let error_response =
-                ApiResponse::<()>::error("User not found".to_string(), "user_not_found", None);
-            (
-                StatusCode::NOT_FOUND,
-                serde_json::to_string(&error_response).unwrap(),
-            )
+            ApiError::not_found("user_not_found", "User not found")
         })?;
 
     let service = InviteService::new(&pool, &config);
     let invite = service.resend_invite(&id, &user).await.map_err(|e| {
         tracing::error!("Failed to resend invite {} for user {}: {}", id, user_id, e);
-        let error_response = ApiResponse::<()>::error(
-            format!("Failed to resend invite: {e}"),
+        ApiError::internal(
             "invite_resend_error",
-            None,
-        );
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            serde_json::to_string(&error_response).unwrap(),
+            format!("Failed to resend invite: {e}"),
         )
     })?;
 
@@ -213,10 +164,9 @@ pub async fn resend_invite(
 #[axum::debug_handler]
 pub async fn accept_invite(
     Extension(pool): Extension<SqlitePool>,
+    Extension(config): Extension<AppConfig>,
     Json(accept_invite): Json<AcceptInviteRequest>,
-) -> Result<Json<ApiResponse<User>>, (StatusCode, String)> {
-    let config = Config::from_env().unwrap();
-
+) -> Result<Json<ApiResponse<User>>, ApiError> {
     tracing::info!("Accepting invite for token: {}", accept_invite.token);
 
     let service = InviteService::new(&pool, &config);
@@ -226,14 +176,9 @@ pub async fn accept_invite(
             accept_invite.token,
             e
         );
-        let error_response = ApiResponse::<()>::error(
-            format!("Failed to accept invitation: {e}"),
+        ApiError::internal(
             "invite_accept_error",
-            None,
-        );
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            serde_json::to_string(&error_response).unwrap(),
+            format!("Failed to accept invitation: {e}"),
         )
     })?;
 
@@ -1,151 +1,257 @@
-use crate::utils::handlers_common::{
-    create_node_client, extract_node_credentials, handle_node_error, parse_payment_hash,
-    parse_public_key,
-};
-use crate::utils::jwt::Claims;
-use crate::{
-    api::common::{
-        ApiResponse, FilterRequest, NumericOperator, PaginatedData, PaginationFilter,
-        PaginationMeta, apply_pagination, validation_error_response,
-    },
-    utils::{CustomInvoice, InvoiceStatus},
-};
-use axum::{
-    Json,
-    extract::{Extension, Path, Query},
-    http::StatusCode,
-};
-use validator::Validate;
-
-/// Handler for getting invoice details
-#[axum::debug_handler]
-pub async fn get_invoice_details(
-    Extension(claims): Extension<Claims>,
-    Path(payment_hash): Path<String>,
-) -> Result<Json<ApiResponse<CustomInvoice>>, (StatusCode, String)> {
-    let payment_hash = parse_payment_hash(&payment_hash)?;
-    let node_credentials = extract_node_credentials(&claims)?;
-    let public_key = parse_public_key(&node_credentials.node_id)?;
-
-    let node_client = create_node_client(node_credentials, public_key).await?;
-
-    let invoice_details = node_client
-        .get_invoice_details(&payment_hash)
-        .await
-        .map_err(|e| handle_node_error(e, "get invoice details"))?;
-
-    Ok(Json(ApiResponse::success(
-        invoice_details,
-        "Invoice details retrieved successfully",
-    )))
-}
-
-/// Handler for listing all invoices with filtering and pagination
-#[axum::debug_handler]
-pub async fn list_invoices(
-    Extension(claims): Extension<Claims>,
-    Query(filter): Query<InvoiceFilter>,
-) -> Result<Json<ApiResponse<PaginatedData<CustomInvoice>>>, (StatusCode, String)> {
-    if let Err(validation_errors) = filter.validate() {
-        return Err(validation_error_response(validation_errors));
-    }
-
-    let node_credentials = extract_node_credentials(&claims)?;
-    let public_key = parse_public_key(&node_credentials.node_id)?;
-
-    let node_client = create_node_client(node_credentials, public_key).await?;
-
-    let invoices = node_client
-        .list_invoices()
-        .await
-        .map_err(|e| handle_node_error(e, "list invoices"))?;
-
-    process_invoices_with_filters(invoices, &filter).await
-}
-
-pub type InvoiceFilter = FilterRequest<InvoiceStatus>;
-
-impl FilterRequest<InvoiceStatus> {
-    pub fn to_pagination_filter(&self) -> PaginationFilter {
-        PaginationFilter {
-            page: self.page,
-            per_page: self.per_page,
-        }
-    }
-}
-
-/// Apply all filters to a collection of invoices
-fn apply_invoice_filters(
-    mut invoices: Vec<CustomInvoice>,
-    filter: &InvoiceFilter,
-) -> Vec<CustomInvoice> {
-    // Apply state filter
-    if let Some(filter_states) = &filter.states {
-        let normalized_filter_states: std::collections::HashSet<String> = filter_states
-            .iter()
-            .map(|state| state.to_string().to_lowercase())
-            .collect();
-
-        invoices.retain(|invoice| {
-            normalized_filter_states.contains(&invoice.state.to_string().to_lowercase())
-        });
-    }
-
-    // Apply amount filter (using value field)
-    if let (Some(operator), Some(filter_value)) = (&filter.operator, filter.value) {
-        if filter_value < 0 {
-            // Negative filter values shouldn't match positive amounts
-            invoices.clear();
-        } else {
-            let filter_value_u64 = filter_value as u64;
-            invoices.retain(|invoice| match operator {
-                NumericOperator::Gte => invoice.value >= filter_value_u64,
-                NumericOperator::Lte => invoice.value <= filter_value_u64,
-                NumericOperator::Eq => invoice.value == filter_value_u64,
-                NumericOperator::Gt => invoice.value > filter_value_u64,
-                NumericOperator::Lt => invoice.value < filter_value_u64,
-            });
-        }
-    }
-
-    // Apply date range filter (for invoice creation dates)
-    if filter.from.is_some() || filter.to.is_some() {
-        if let Some(from_date) = filter.from {
-            invoices.retain(|invoice| {
-                invoice
-                    .creation_date
-                    .map(|creation_date| creation_date >= from_date.timestamp())
-                    .unwrap_or(false)
-            });
-        }
-
-        if let Some(to_date) = filter.to {
-            invoices.retain(|invoice| {
-                invoice
-                    .creation_date
-                    .map(|creation_date| creation_date <= to_date.timestamp())
-                    .unwrap_or(false)
-            });
-        }
-    }
-
-    invoices
-}
-
-/// Process invoices with filters and pagination
-async fn process_invoices_with_filters(
-    all_invoices: Vec<CustomInvoice>,
-    filter: &InvoiceFilter,
-) -> Result<Json<ApiResponse<PaginatedData<CustomInvoice>>>, (StatusCode, String)> {
-    let filtered_invoices = apply_invoice_filters(all_invoices, filter);
-    let total_filtered_count = filtered_invoices.len() as u64;
-    let pagination_filter = filter.to_pagination_filter();
-    let paginated_invoices = apply_pagination(filtered_invoices, &pagination_filter);
-    let pagination_meta = PaginationMeta::from_filter(&pagination_filter, total_filtered_count);
-    let paginated_data = PaginatedData::new(paginated_invoices, total_filtered_count);
-
-    Ok(Json(ApiResponse::ok_paginated(
-        paginated_data,
-        pagination_meta,
-    )))
-}
+use crate::database::models::{DecodeInvoiceRequest, ReconcileInvoicesRequest};
+use crate::services::donations::{DonationStats, donation_stats};
+use crate::services::invoice_decoder::{DecodedInvoice, decode_invoice};
+use crate::services::invoice_reconciliation::{
+    InvoiceReconciliationReport, InvoiceReconciliationService,
+};
+use crate::services::payment_stats::{parse_window, stats_span};
+use crate::services::settings_service::SettingsService;
+use crate::utils::amount::Amount;
+use crate::utils::handlers_common::{SelectedNode, handle_node_error, parse_payment_hash};
+use crate::utils::jwt::Claims;
+use crate::utils::time_zone::{DateBound, parse_tz};
+use crate::{
+    api::common::{
+        ApiError, ApiResponse, FilterRequest, NumericOperator, PaginatedData, PaginationFilter,
+        PaginationMeta, apply_pagination,
+    },
+    utils::{CustomInvoice, InvoiceStatus},
+};
+use axum::{
+    Json,
+    extract::{Extension, Path, Query},
+};
+use chrono::Utc;
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use validator::Validate;
+
+/// Handler for getting invoice details
+#[axum::debug_handler]
+pub async fn get_invoice_details(
+    Extension(node): Extension<SelectedNode>,
+    Path(payment_hash): Path<String>,
+) -> Result<Json<ApiResponse<CustomInvoice>>, ApiError> {
+    let payment_hash = parse_payment_hash(&payment_hash)?;
+    let node_client = node.client().await?;
+
+    let invoice_details = node_client
+        .get_invoice_details(&payment_hash)
+        .await
+        .map_err(|e| handle_node_error(e, "get invoice details"))?;
+
+    Ok(Json(ApiResponse::success(
+        invoice_details,
+        "Invoice details retrieved successfully",
+    )))
+}
+
+/// Handler for listing all invoices with filtering and pagination
+#[axum::debug_handler]
+pub async fn list_invoices(
+    Extension(node): Extension<SelectedNode>,
+    Query(filter): Query<InvoiceFilter>,
+) -> Result<Json<ApiResponse<PaginatedData<CustomInvoice>>>, ApiError> {
+    filter.validate()?;
+
+    let node_client = node.client().await?;
+
+    let invoices = node_client
+        .list_invoices()
+        .await
+        .map_err(|e| handle_node_error(e, "list invoices"))?;
+
+    process_invoices_with_filters(invoices, &filter).await
+}
+
+/// Handler for reconciling invoice states with the node
+///
+/// All invoices are read from the node in one call and compared with the
+/// state NodeGaze recorded for each hash.
+#[axum::debug_handler]
+pub async fn reconcile_invoices(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
+    Json(payload): Json<ReconcileInvoicesRequest>,
+) -> Result<Json<ApiResponse<InvoiceReconciliationReport>>, ApiError> {
+    payload.validate()?;
+    let mut seen = HashSet::new();
+    let payment_hashes: Vec<String> = payload
+        .payment_hashes
+        .into_iter()
+        .map(|hash| hash.to_lowercase())
+        .filter(|hash| seen.insert(hash.clone()))
+        .collect();
+
+    let node_client = node.client().await?;
+    let invoices = node_client
+        .list_invoices()
+        .await
+        .map_err(|e| handle_node_error(e, "list invoices"))?;
+
+    let report = InvoiceReconciliationService::new(&pool)
+        .reconcile(
+            &claims.account_id,
+            &node.credentials().node_id,
+            &payment_hashes,
+            &invoices,
+        )
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        report,
+        "Invoices reconciled successfully",
+    )))
+}
+
+/// Handler for decoding a BOLT11 payment request
+///
+/// Works without a node, so the invoice can be shown for confirmation before
+/// any node is asked to pay it.
+#[axum::debug_handler]
+pub async fn decode_payment_request(
+    Json(payload): Json<DecodeInvoiceRequest>,
+) -> Result<Json<ApiResponse<DecodedInvoice>>, ApiError> {
+    payload.validate()?;
+    let invoice = decode_invoice(&payload.payment_request)?;
+
+    Ok(Json(ApiResponse::success(
+        invoice,
+        "Payment request decoded successfully",
+    )))
+}
+
+/// Window used when `GET /api/invoices/donations/stats` is called without one.
+const DEFAULT_DONATIONS_WINDOW: &str = "30d";
+
+/// Query parameters for donation statistics.
+#[derive(Debug, Deserialize)]
+pub struct DonationStatsQuery {
+    /// How far back to look, e.g. `24h`, `30d` or `12w`; ignored when `from` is given
+    pub window: Option<String>,
+    /// Start of the statistics, a date or an RFC 3339 time
+    pub from: Option<DateBound>,
+    /// End of the statistics (inclusive), now when absent
+    pub to: Option<DateBound>,
+    /// IANA time zone dates are read in, the account's when absent
+    pub tz: Option<String>,
+}
+
+/// Handler for the donations received through zero-amount invoices, summed
+/// per donation tag of the account
+#[axum::debug_handler]
+pub async fn get_donation_stats(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
+    Query(query): Query<DonationStatsQuery>,
+) -> Result<Json<ApiResponse<DonationStats>>, ApiError> {
+    let window = parse_window(query.window.as_deref().unwrap_or(DEFAULT_DONATIONS_WINDOW))
+        .map_err(|e| ApiError::bad_request("invalid_window", e))?;
+
+    let settings = SettingsService::new(&pool)
+        .get_settings(&claims.account_id)
+        .await?;
+    let tz = match query.tz.as_deref() {
+        Some(tz) => parse_tz(tz).map_err(|e| ApiError::bad_request("invalid_timezone", e))?,
+        None => settings.tz(),
+    };
+    let (start, end) = stats_span(window, query.from, query.to, tz, Utc::now())
+        .map_err(|e| ApiError::bad_request("invalid_window", e))?;
+
+    let node_client = node.client().await?;
+    let invoices = node_client
+        .list_invoices()
+        .await
+        .map_err(|e| handle_node_error(e, "list invoices"))?;
+
+    Ok(Json(ApiResponse::success(
+        donation_stats(&invoices, &settings.donation_tags, start, end),
+        "Donation statistics retrieved successfully",
+    )))
+}
+
+pub type InvoiceFilter = FilterRequest<InvoiceStatus>;
+
+impl FilterRequest<InvoiceStatus> {
+    pub fn to_pagination_filter(&self) -> PaginationFilter {
+        PaginationFilter {
+            page: self.page,
+            per_page: self.per_page,
+        }
+    }
+}
+
+/// Apply all filters to a collection of invoices
+fn apply_invoice_filters(
+    mut invoices: Vec<CustomInvoice>,
+    filter: &InvoiceFilter,
+) -> Vec<CustomInvoice> {
+    // Apply state filter
+    if let Some(filter_states) = &filter.states {
+        let normalized_filter_states: std::collections::HashSet<String> = filter_states
+            .iter()
+            .map(|state| state.to_string().to_lowercase())
+            .collect();
+
+        invoices.retain(|invoice| {
+            normalized_filter_states.contains(&invoice.state.to_string().to_lowercase())
+        });
+    }
+
+    // Apply amount filter (using value field); negative values were rejected
+    // when the filter was validated
+    if let (Some(operator), Some(filter_value)) = (&filter.operator, filter.value) {
+        let filter_value = Amount::from_sat_i64(filter_value);
+        invoices.retain(|invoice| match operator {
+            NumericOperator::Gte => invoice.value >= filter_value,
+            NumericOperator::Lte => invoice.value <= filter_value,
+            NumericOperator::Eq => invoice.value == filter_value,
+            NumericOperator::Gt => invoice.value > filter_value,
+            NumericOperator::Lt => invoice.value < filter_value,
+        });
+    }
+
+    // Apply date range filter (for invoice creation dates)
+    if filter.from.is_some() || filter.to.is_some() {
+        if let Some(from_date) = filter.from {
+            invoices.retain(|invoice| {
+                invoice
+                    .creation_date
+                    .map(|creation_date| creation_date >= from_date.timestamp())
+                    .unwrap_or(false)
+            });
+        }
+
+        if let Some(to_date) = filter.to {
+            invoices.retain(|invoice| {
+                invoice
+                    .creation_date
+                    .map(|creation_date| creation_date <= to_date.timestamp())
+                    .unwrap_or(false)
+            });
+        }
+    }
+
+    invoices
+}
+
+/// Process invoices with filters and pagination
+async fn process_invoices_with_filters(
+    all_invoices: Vec<CustomInvoice>,
+    filter: &InvoiceFilter,
+) -> Result<Json<ApiResponse<PaginatedData<CustomInvoice>>>, ApiError> {
+    let filtered_invoices = apply_invoice_filters(all_invoices, filter);
+    let total_filtered_count = filtered_invoices.len() as u64;
+    let pagination_filter = filter.to_pagination_filter();
+    let paginated_invoices = apply_pagination(filtered_invoices, &pagination_filter);
+    let pagination_meta = PaginationMeta::from_filter(&pagination_filter, total_filtered_count);
+    let paginated_data = PaginatedData::new(paginated_invoices, total_filtered_count);
+
+    Ok(Json(ApiResponse::ok_paginated(
+        paginated_data,
+        pagination_meta,
+    )))
+}
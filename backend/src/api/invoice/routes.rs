@@ -1,19 +1,48 @@
-use super::handlers::{get_invoice_details, list_invoices};
-use crate::auth::middleware::{jwt_auth, node_credentials_required};
-use axum::{Router, middleware, routing::get};
+use super::handlers::{
+    decode_payment_request, get_donation_stats, get_invoice_details, list_invoices,
+    reconcile_invoices,
+};
+use crate::auth::middleware::{jwt_auth, node_group_access_required, node_selection};
+use crate::middleware::response_cache::cached_node_response;
+use axum::{
+    Router, middleware,
+    routing::{get, post},
+};
 
 pub async fn invoice_router() -> Router {
     Router::new()
         .route(
             "/{payment_hash}",
             get(get_invoice_details)
-                .layer(middleware::from_fn(node_credentials_required))
+                .layer(middleware::from_fn(cached_node_response))
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/donations/stats",
+            get(get_donation_stats)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/decode",
+            post(decode_payment_request).layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/reconcile",
+            post(reconcile_invoices)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
                 .layer(middleware::from_fn(jwt_auth)),
         )
         .route(
             "/",
             get(list_invoices)
-                .layer(middleware::from_fn(node_credentials_required))
+                .layer(middleware::from_fn(cached_node_response))
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
                 .layer(middleware::from_fn(jwt_auth)),
         )
 }
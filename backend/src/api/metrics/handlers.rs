@@ -0,0 +1,67 @@
+//! Handler functions for the Prometheus metrics endpoint.
+
+use crate::api::common::ApiError;
+use crate::config::AppConfig;
+use crate::middleware::request_metrics::request_metrics;
+use crate::services::metrics::{render_metrics, render_rpc_metrics, render_server_metrics};
+use crate::services::rpc_budget::rpc_counts;
+use crate::services::settings_service::SettingsService;
+use crate::utils::handlers_common::{SelectedNode, handle_node_error};
+use crate::utils::jwt::Claims;
+use crate::utils::logging;
+use axum::{
+    extract::Extension,
+    http::{HeaderValue, header::CONTENT_TYPE},
+    response::{IntoResponse, Response},
+};
+use sqlx::SqlitePool;
+
+/// Content type of the Prometheus text exposition format.
+const EXPOSITION_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Exports the selected node's channel gauges for Prometheus to scrape.
+///
+/// Gauges per channel are added when the account has `channel_metrics` on,
+/// followed by the node's RPC budget counters and the server's request and
+/// slow statement metrics.
+#[axum::debug_handler]
+pub async fn get_metrics(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(config): Extension<AppConfig>,
+    Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
+) -> Result<Response, ApiError> {
+    let per_channel = SettingsService::new(&pool)
+        .get_settings(&claims.account_id)
+        .await?
+        .channel_metrics;
+
+    let node_client = node.client().await?;
+    let channels = node_client
+        .list_channels()
+        .await
+        .map_err(|e| handle_node_error(e, "list channels"))?;
+
+    let mut body = render_metrics(
+        node_client.get_info(),
+        &channels,
+        per_channel,
+        config.metrics_max_channels,
+    );
+    body.push_str(&render_rpc_metrics(
+        node_client.get_info(),
+        &rpc_counts(&node.credentials().node_id),
+    ));
+    body.push_str(&render_server_metrics(
+        &request_metrics(),
+        logging::slow_statements(),
+    ));
+    Ok((
+        [(
+            CONTENT_TYPE,
+            HeaderValue::from_static(EXPOSITION_CONTENT_TYPE),
+        )],
+        body,
+    )
+        .into_response())
+}
@@ -0,0 +1,13 @@
+use super::handlers::get_metrics;
+use crate::auth::middleware::{jwt_auth, node_group_access_required, node_selection};
+use axum::{Router, middleware, routing::get};
+
+pub async fn metrics_router() -> Router {
+    Router::new().route(
+        "/",
+        get(get_metrics)
+            .layer(middleware::from_fn(node_group_access_required))
+            .layer(middleware::from_fn(node_selection))
+            .layer(middleware::from_fn(jwt_auth)),
+    )
+}
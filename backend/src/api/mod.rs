@@ -5,13 +5,26 @@
 //! authentication routes which are handled separately.
 
 pub mod account;
+pub mod admin;
+pub mod aggregate;
+pub mod auto_fee;
 pub mod channel;
+pub mod channel_tag;
 pub mod common;
 pub mod credential;
 pub mod event;
+pub mod frontend;
+pub mod graph;
+pub mod health;
 pub mod invite;
 pub mod invoice;
+pub mod metrics;
 pub mod node;
+pub mod node_group;
 pub mod notification;
 pub mod payment;
+pub mod peer_blocklist;
+pub mod report;
+pub mod search;
 pub mod user;
+pub mod webhook;
@@ -1,25 +1,38 @@
 //! Handler functions for the node observability API.
-use crate::api::common::ApiResponse;
-use crate::database::models::CreateCredential;
-use crate::errors::LightningError;
+use crate::api::common::{ApiError, ApiResponse};
+use crate::database::models::{CreateCredential, PeerPing};
 use crate::repositories::credential_repository::CredentialRepository;
-use crate::services::event_manager::{EventCollector, EventHandler, NodeSpecificEvent};
+use crate::services::channel_acceptor::ChannelAcceptor;
+use crate::services::credential_expiry;
+use crate::services::event_manager::{CapturedEvent, EventCollector, EventHandler};
+use crate::services::lnurl_monitor::LnurlMonitor;
+use crate::services::node_capabilities::{self, CapabilityMatrix, NodeFeature, NodeImplementation};
+use crate::services::node_discovery::{self, DiscoveredNode};
+use crate::services::node_manager::ClnCommandoNode;
 use crate::services::node_manager::LightningClient;
 use crate::services::node_manager::{
-    ClnConnection, ClnNode, ConnectionRequest, LndConnection, LndNode,
+    ClnNode, ConnectionRequest, LndNode, LndTransport, connect_lightning,
+};
+use crate::services::peer_pings::{PeerPingService, PeerRttStats};
+use crate::services::public_profile::{self, SignedPublicProfile};
+use crate::services::quota_service::QuotaService;
+use crate::services::transaction_labels::{self, LabelledTransaction};
+use crate::utils::handlers_common::{
+    SelectedNode, create_node_client, handle_node_error, parse_public_key,
 };
 use crate::utils::jwt::{Claims, JwtUtils, NodeCredentials};
-use crate::utils::{NodeId, NodeInfo};
-use axum::{
-    extract::{Extension, Json},
-    http::StatusCode,
+use crate::utils::macaroon::{
+    self, MacaroonBakeCommand, MacaroonFeature, MacaroonPermissionReport,
 };
+use crate::utils::{NodeInfo, PeerSummary, rune};
+use axum::extract::{Extension, Json, Path, Query};
 use sqlx::SqlitePool;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::sync::mpsc;
 
 use uuid::Uuid;
+use validator::Validate;
 
 /// Node authentication response with stored credential info
 #[derive(Debug, serde::Serialize)]
@@ -28,6 +41,10 @@ pub struct NodeAuthResponse {
     pub credential_stored: bool,
     pub credential_id: Option<String>,
     pub new_access_token: Option<String>,
+    /// Features the LND macaroon grants; absent for CLN or if the macaroon could not be decoded.
+    pub macaroon_permissions: Option<MacaroonPermissionReport>,
+    /// True when the credential cannot change node state; write endpoints are disabled.
+    pub read_only: bool,
 }
 
 #[axum::debug_handler]
@@ -35,31 +52,62 @@ pub async fn authenticate_node(
     Extension(pool): Extension<SqlitePool>,
     Extension(claims): Extension<Option<Claims>>,
     Json(payload): Json<ConnectionRequest>,
-) -> Result<Json<ApiResponse<NodeAuthResponse>>, (StatusCode, String)> {
+) -> Result<Json<ApiResponse<NodeAuthResponse>>, ApiError> {
+    let mut macaroon_permissions = None;
+
     // First authenticate with the node
     let node_info = match &payload {
         ConnectionRequest::Lnd(lnd_conn) => {
             tracing::info!("Attempting to authenticate LND node: {:?}", lnd_conn.id);
+
+            macaroon_permissions = check_lnd_macaroon(&lnd_conn.macaroon).await?;
+
             match LndNode::new(lnd_conn.clone()).await {
                 Ok(lnd_node) => {
                     tracing::info!("LND node authenticated: {:?}", lnd_node.info);
 
                     let info = lnd_node.info.clone();
+                    // Taken before the node is boxed, the acceptor needs LND's own API,
+                    // which the REST proxy can't stream.
+                    let acceptor_client = lnd_node.get_lightning_stub().await;
 
-                    let (sender, receiver) = mpsc::channel::<NodeSpecificEvent>(32);
-
-                    let collector = EventCollector::new(sender);
+                    let (sender, receiver) = mpsc::channel::<CapturedEvent>(32);
+                    let collector =
+                        EventCollector::new(sender, claims.as_ref().map(|c| c.account_id.clone()));
                     let lnd_node_: Arc<Mutex<Box<dyn LightningClient + Send + Sync + 'static>>> =
                         Arc::new(Mutex::new(Box::new(lnd_node)));
 
-                    collector.start_sending(info.pubkey, lnd_node_).await;
-
                     // Start processing events with database context
                     let handler = if let Some(user_claims) = &claims {
                         tracing::info!(
                             "Creating handler with database context for user: {}",
                             user_claims.sub
                         );
+                        LnurlMonitor::start_for_node(
+                            pool.clone(),
+                            user_claims.account_id.clone(),
+                            user_claims.sub.clone(),
+                            info.clone(),
+                            lnd_node_.clone(),
+                        );
+                        // Without the permission LND would reject the acceptor stream anyway
+                        let acceptor_allowed = macaroon_permissions.as_ref().is_none_or(|report| {
+                            report.is_granted(MacaroonFeature::ChannelAcceptor)
+                        }) && node_capabilities::supports(
+                            NodeImplementation::Lnd,
+                            info.version.as_deref(),
+                            NodeFeature::ChannelAcceptor,
+                        );
+                        if let Some(acceptor_client) = acceptor_client.filter(|_| acceptor_allowed)
+                        {
+                            ChannelAcceptor::start_for_node(
+                                pool.clone(),
+                                user_claims.account_id.clone(),
+                                user_claims.sub.clone(),
+                                info.clone(),
+                                acceptor_client,
+                            );
+                        }
                         EventHandler::with_context(
                             pool.clone(),
                             user_claims.account_id.clone(),
@@ -71,20 +119,22 @@ pub async fn authenticate_node(
                         tracing::info!("Creating handler without database context");
                         EventHandler::new()
                     };
-                    handler.start_receiving(receiver);
+                    // The REST proxy has no event subscriptions, so no collector is
+                    // started over it.
+                    if lnd_conn.transport == LndTransport::Grpc {
+                        handler.watch_open_invoices(NodeImplementation::Lnd, lnd_node_.clone());
+                        handler.start_receiving(receiver);
+
+                        collector.start_sending(info.pubkey, lnd_node_).await;
+                    }
 
                     info
                 }
                 Err(e) => {
                     tracing::error!("Failed to authenticate LND node: {}", e);
-                    let error_response = ApiResponse::<()>::error(
-                        format!("LND authentication failed: {e}"),
+                    return Err(ApiError::internal(
                         "node_authentication_error",
-                        None,
-                    );
-                    return Err((
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        serde_json::to_string(&error_response).unwrap(),
+                        format!("LND authentication failed: {e}"),
                     ));
                 }
             }
@@ -97,20 +147,25 @@ pub async fn authenticate_node(
 
                     let info = cln_node.info.clone();
 
-                    let (sender, receiver) = mpsc::channel::<NodeSpecificEvent>(32);
-
-                    let collector = EventCollector::new(sender);
+                    let (sender, receiver) = mpsc::channel::<CapturedEvent>(32);
+                    let collector =
+                        EventCollector::new(sender, claims.as_ref().map(|c| c.account_id.clone()));
                     let cln_node_: Arc<Mutex<Box<dyn LightningClient + Send + Sync + 'static>>> =
                         Arc::new(Mutex::new(Box::new(cln_node)));
 
-                    collector.start_sending(info.pubkey, cln_node_).await;
-
                     // Start processing events with database context
                     let handler = if let Some(user_claims) = &claims {
                         tracing::info!(
                             "Creating CLN handler with database context for user: {}",
                             user_claims.sub
                         );
+                        LnurlMonitor::start_for_node(
+                            pool.clone(),
+                            user_claims.account_id.clone(),
+                            user_claims.sub.clone(),
+                            info.clone(),
+                            cln_node_.clone(),
+                        );
                         EventHandler::with_context(
                             pool.clone(),
                             user_claims.account_id.clone(),
@@ -122,39 +177,80 @@ pub async fn authenticate_node(
                         tracing::info!("Creating CLN handler without database context");
                         EventHandler::new()
                     };
-
+                    handler.watch_open_invoices(NodeImplementation::Cln, cln_node_.clone());
                     handler.start_receiving(receiver);
 
+                    collector
+                        .start_sending(info.pubkey, cln_node_.clone())
+                        .await;
+                    // Releases without splicing can't change a channel's funding output
+                    if node_capabilities::supports(
+                        NodeImplementation::Cln,
+                        info.version.as_deref(),
+                        NodeFeature::Splicing,
+                    ) {
+                        collector.start_splice_watch(info.pubkey, cln_node_);
+                    }
+
                     info
                 }
                 Err(e) => {
                     tracing::error!("Failed to authenticate CLN node: {}", e);
-                    let error_response = ApiResponse::<()>::error(
+                    return Err(ApiError::internal(
+                        "node_authentication_error",
                         format!("CLN authentication failed: {e}"),
+                    ));
+                }
+            }
+        }
+        ConnectionRequest::ClnRune(rune_conn) => {
+            tracing::info!(
+                "Attempting to authenticate CLN node over commando: {:?}",
+                rune_conn.id
+            );
+            // Commando has no event subscriptions, so no collector is started for it.
+            match ClnCommandoNode::new(rune_conn.clone()).await {
+                Ok(cln_node) => {
+                    tracing::info!("CLN node authenticated over commando: {:?}", cln_node.info);
+                    cln_node.info.clone()
+                }
+                Err(e) => {
+                    tracing::error!("Failed to authenticate CLN node over commando: {}", e);
+                    return Err(ApiError::internal(
                         "node_authentication_error",
-                        None,
-                    );
-                    return Err((
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        serde_json::to_string(&error_response).unwrap(),
+                        format!("CLN commando authentication failed: {e}"),
                     ));
                 }
             }
         }
     };
 
+    let read_only = is_read_only(&payload, macaroon_permissions.as_ref());
+
+    if let Some(user_claims) = &claims {
+        QuotaService::new(&pool)
+            .check_node_quota(
+                &user_claims.account_id,
+                &user_claims.sub,
+                &node_info.pubkey.to_string(),
+            )
+            .await?;
+    }
+
     // If user is authenticated (has JWT token), store the credentials
     let (credential_stored, credential_id, new_access_token) = if let Some(user_claims) = claims {
-        match store_node_credentials(&pool, &user_claims, &payload, &node_info).await {
+        match store_node_credentials(&pool, &user_claims, &payload, &node_info, read_only).await {
             Ok(credential_id) => {
                 tracing::info!("Node credentials stored for user: {}", user_claims.sub);
-                
+
                 let new_token = generate_new_token_with_credentials(
                     &user_claims,
                     &payload,
                     &node_info,
-                ).ok();
-                
+                    read_only,
+                )
+                .ok();
+
                 (true, Some(credential_id), new_token)
             }
             Err(e) => {
@@ -172,6 +268,8 @@ pub async fn authenticate_node(
         credential_stored,
         credential_id,
         new_access_token,
+        macaroon_permissions,
+        read_only,
     };
 
     let message = if credential_stored {
@@ -183,12 +281,54 @@ pub async fn authenticate_node(
     Ok(Json(ApiResponse::success(response_data, message)))
 }
 
+/// Inspects an LND macaroon, rejecting it when it can't be used for monitoring.
+///
+/// Returns `None` when the macaroon could not be decoded; the node then enforces
+/// its permissions on its own.
+pub(crate) async fn check_lnd_macaroon(
+    macaroon_path: &str,
+) -> Result<Option<MacaroonPermissionReport>, ApiError> {
+    // Scoped macaroons are welcome, but monitoring needs at least the read-only set.
+    match macaroon::check_macaroon_file(macaroon_path).await {
+        Ok(report) if !report.is_granted(MacaroonFeature::ReadOnly) => {
+            let missing = report.missing_uris(MacaroonFeature::ReadOnly);
+            tracing::warn!("LND macaroon is missing permissions for: {:?}", missing);
+            Err(ApiError::forbidden(
+                "insufficient_macaroon_permissions",
+                format!(
+                    "Macaroon is missing permissions required for monitoring: {}",
+                    missing.join(", ")
+                ),
+            ))
+        }
+        Ok(report) => Ok(Some(report)),
+        Err(e) => {
+            tracing::warn!("Could not inspect LND macaroon permissions: {}", e);
+            Ok(None)
+        }
+    }
+}
+
+/// Whether the credential can't change node state.
+pub(crate) fn is_read_only(
+    payload: &ConnectionRequest,
+    macaroon_permissions: Option<&MacaroonPermissionReport>,
+) -> bool {
+    // Capabilities that cannot be detected are left to the node to enforce.
+    match payload {
+        ConnectionRequest::ClnRune(rune_conn) => rune::is_read_only(&rune_conn.rune),
+        _ => macaroon_permissions
+            .is_some_and(|report| !report.is_granted(MacaroonFeature::ChannelManagement)),
+    }
+}
+
 /// Helper function to store node credentials in database
 async fn store_node_credentials(
     pool: &SqlitePool,
     claims: &Claims,
     connection_request: &ConnectionRequest,
     node_info: &NodeInfo,
+    read_only: bool,
 ) -> Result<String, String> {
     let credential_repo = CredentialRepository::new(pool);
 
@@ -226,8 +366,19 @@ async fn store_node_credentials(
                 Some(cln_conn.client_key.clone()),
                 Some(cln_conn.ca_cert.clone()),
             ),
+            ConnectionRequest::ClnRune(rune_conn) => (
+                Some("cln".to_string()),
+                "".to_string(), // The rune is stored in its own column
+                "".to_string(), // Commando runs over the peer transport, not TLS
+                rune_conn.address.clone(),
+                None,
+                None,
+                None,
+            ),
         };
 
+    let expiry = credential_expiry::read_expiry(connection_request).await;
+
     // Create new credential record with all required fields
     let create_credential = CreateCredential {
         id: Uuid::now_v7().to_string(),
@@ -242,6 +393,14 @@ async fn store_node_credentials(
         client_cert,
         client_key,
         ca_cert,
+        proxy: connection_request.proxy(),
+        rune: connection_request.rune(),
+        transport: connection_request.transport(),
+        read_only,
+        node_version: node_info.version.clone(),
+        network: node_info.network.clone(),
+        tls_cert_expires_at: expiry.tls_cert_expires_at,
+        macaroon_expires_at: expiry.macaroon_expires_at,
     };
 
     let credential = credential_repo
@@ -253,13 +412,13 @@ async fn store_node_credentials(
 }
 
 /// Generate new JWT token with node credentials included
-fn generate_new_token_with_credentials(
+pub(crate) fn generate_new_token_with_credentials(
     claims: &Claims,
     connection_request: &ConnectionRequest,
     node_info: &NodeInfo,
+    read_only: bool,
 ) -> Result<String, String> {
-    let jwt_utils = JwtUtils::new()
-        .map_err(|e| format!("Failed to create JWT utils: {e}"))?;
+    let jwt_utils = JwtUtils::new().map_err(|e| format!("Failed to create JWT utils: {e}"))?;
 
     let (node_type, macaroon, tls_cert, address, client_cert, client_key, ca_cert) =
         match connection_request {
@@ -281,6 +440,15 @@ fn generate_new_token_with_credentials(
                 Some(cln_conn.client_key.clone()),
                 Some(cln_conn.ca_cert.clone()),
             ),
+            ConnectionRequest::ClnRune(rune_conn) => (
+                "cln".to_string(),
+                "".to_string(),
+                "".to_string(),
+                rune_conn.address.clone(),
+                None,
+                None,
+                None,
+            ),
         };
 
     let node_credentials = NodeCredentials {
@@ -293,6 +461,10 @@ fn generate_new_token_with_credentials(
         client_cert,
         client_key,
         ca_cert,
+        proxy: connection_request.proxy(),
+        rune: connection_request.rune(),
+        transport: connection_request.transport(),
+        read_only,
     };
 
     jwt_utils
@@ -302,6 +474,7 @@ fn generate_new_token_with_credentials(
             claims.role.clone(),
             claims.role_access_level.clone(),
             Some(node_credentials),
+            claims.sid.clone(),
         )
         .map_err(|e| format!("Failed to generate token: {e}"))
 }
@@ -310,115 +483,122 @@ fn generate_new_token_with_credentials(
 #[axum::debug_handler]
 pub async fn get_node_info_jwt(
     Extension(claims): Extension<Claims>,
-) -> Result<Json<NodeInfo>, (StatusCode, String)> {
+) -> Result<Json<NodeInfo>, ApiError> {
     let node_credentials = claims.node_credentials().ok_or_else(|| {
-        (
-            StatusCode::BAD_REQUEST,
-            "No node credentials found in token. Please authenticate your node first.".to_string(),
+        ApiError::bad_request(
+            "missing_credentials",
+            "No node credentials found in token. Please authenticate your node first.",
         )
     })?;
 
-    // Create connection request based on node type
-    match node_credentials.node_type.as_str() {
-        "lnd" => {
-            let lnd_conn = LndConnection {
-                id: NodeId::PublicKey(
-                    node_credentials
-                        .node_id
-                        .parse()
-                        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid node ID: {e}")))?,
-                ),
-                address: node_credentials.address.clone(),
-                macaroon: node_credentials.macaroon.clone(),
-                cert: node_credentials.tls_cert.clone(),
-            };
+    let client = create_node_client(node_credentials).await?;
+    Ok(Json(client.get_info().clone()))
+}
 
-            match LndNode::new(lnd_conn).await {
-                Ok(lnd_node) => Ok(Json(lnd_node.info)),
-                Err(e) => {
-                    tracing::error!("Failed to connect to LND node: {}", e);
-                    Err((
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        format!("LND connection failed: {e}"),
-                    ))
-                }
-            }
-        }
-        "cln" => {
-            let client_cert = node_credentials.client_cert.as_ref().ok_or_else(|| {
-                (
-                    StatusCode::BAD_REQUEST,
-                    "Missing client certificate for CLN".to_string(),
-                )
-            })?;
+/// Query parameters for the bake command helper.
+#[derive(Debug, serde::Deserialize)]
+pub struct BakeMacaroonQuery {
+    #[serde(default)]
+    pub feature: MacaroonFeature,
+}
 
-            let client_key = node_credentials.client_key.as_ref().ok_or_else(|| {
-                (
-                    StatusCode::BAD_REQUEST,
-                    "Missing client key for CLN".to_string(),
-                )
-            })?;
+/// Returns the `lncli bakemacaroon` command for the minimal permissions a feature needs.
+#[axum::debug_handler]
+pub async fn get_bake_macaroon_command(
+    Query(query): Query<BakeMacaroonQuery>,
+) -> Result<Json<ApiResponse<MacaroonBakeCommand>>, ApiError> {
+    Ok(Json(ApiResponse::success(
+        macaroon::bake_command(query.feature),
+        "Bake command generated successfully",
+    )))
+}
 
-            let ca_cert = node_credentials.ca_cert.as_ref().ok_or_else(|| {
-                (
-                    StatusCode::BAD_REQUEST,
-                    "Missing CA certificate for CLN".to_string(),
-                )
-            })?;
-
-            let cln_conn = ClnConnection {
-                id: NodeId::PublicKey(
-                    node_credentials
-                        .node_id
-                        .parse()
-                        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid node ID: {e}")))?,
-                ),
-                address: node_credentials.address.clone(),
-                ca_cert: ca_cert.clone(),
-                client_cert: client_cert.clone(),
-                client_key: client_key.clone(),
-            };
-
-            match ClnNode::new(cln_conn).await {
-                Ok(cln_node) => Ok(Json(cln_node.info)),
-                Err(e) => {
-                    tracing::error!("Failed to connect to CLN node: {}", e);
-                    Err((
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        format!("CLN connection failed: {e}"),
-                    ))
-                }
-            }
-        }
-        _ => Err((StatusCode::BAD_REQUEST, "Unsupported node type".to_string())),
-    }
+/// Capabilities detected while testing a connection.
+#[derive(Debug, serde::Serialize)]
+pub struct NodeCapabilities {
+    /// Whether live node events can be collected; commando and LND's REST proxy have
+    /// no subscriptions.
+    pub event_streaming: bool,
+    /// True when the credential cannot change node state.
+    pub read_only: bool,
+    /// Features the LND macaroon grants; absent for CLN or if the macaroon could not be decoded.
+    pub macaroon_permissions: Option<MacaroonPermissionReport>,
 }
 
-// Keep existing functions...
-pub async fn connect_lightning(
-    conn: ConnectionRequest,
-) -> Result<Box<dyn LightningClient + Send>, LightningError> {
-    match conn {
-        ConnectionRequest::Lnd(lnd_conn) => {
-            let node = LndNode::new(lnd_conn).await?;
-            Ok(Box::new(node))
-        }
-        ConnectionRequest::Cln(cln_conn) => {
-            let node = ClnNode::new(cln_conn).await?;
-            Ok(Box::new(node))
-        }
-    }
+/// Outcome of a successful connection test.
+#[derive(Debug, serde::Serialize)]
+pub struct TestConnectionResponse {
+    pub node_info: NodeInfo,
+    pub node_type: String,
+    pub capabilities: NodeCapabilities,
+    /// Time taken to connect and fetch the node info, in milliseconds
+    pub latency_ms: u64,
+}
+
+/// Connects with the given credentials and reports what the node allows.
+///
+/// Unlike `/auth`, nothing is stored and no event collectors are started, so a
+/// connection wizard can check credentials before committing to them.
+#[axum::debug_handler]
+pub async fn test_connection(
+    Json(payload): Json<ConnectionRequest>,
+) -> Result<Json<ApiResponse<TestConnectionResponse>>, ApiError> {
+    let macaroon_permissions = match &payload {
+        ConnectionRequest::Lnd(lnd_conn) => check_lnd_macaroon(&lnd_conn.macaroon).await?,
+        _ => None,
+    };
+    let read_only = is_read_only(&payload, macaroon_permissions.as_ref());
+    let (node_type, event_streaming) = match &payload {
+        ConnectionRequest::Lnd(lnd_conn) => ("lnd", lnd_conn.transport == LndTransport::Grpc),
+        ConnectionRequest::Cln(_) => ("cln", true),
+        ConnectionRequest::ClnRune(_) => ("cln", false),
+    };
+
+    let started = std::time::Instant::now();
+    let client = connect_lightning(payload).await.map_err(|e| {
+        tracing::warn!("Connection test failed: {}", e);
+        ApiError::new(
+            ApiError::lightning_status(&e),
+            "node_connection_error",
+            format!("Connection test failed: {e}"),
+        )
+    })?;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    Ok(Json(ApiResponse::success(
+        TestConnectionResponse {
+            node_info: client.get_info().clone(),
+            node_type: node_type.to_string(),
+            capabilities: NodeCapabilities {
+                event_streaming,
+                read_only,
+                macaroon_permissions,
+            },
+            latency_ms,
+        },
+        "Connection test succeeded",
+    )))
+}
+
+/// Lists the reachable default gRPC endpoints of local node setups, to pre-fill
+/// the connection form.
+#[axum::debug_handler]
+pub async fn discover_nodes() -> Result<Json<ApiResponse<Vec<DiscoveredNode>>>, ApiError> {
+    Ok(Json(ApiResponse::success(
+        node_discovery::discover_nodes().await,
+        "Node discovery completed",
+    )))
 }
 
 #[axum::debug_handler]
 pub async fn get_node_info(
     Json(payload): Json<ConnectionRequest>,
-) -> Result<Json<NodeInfo>, (StatusCode, String)> {
+) -> Result<Json<NodeInfo>, ApiError> {
     match connect_lightning(payload).await {
         Ok(client) => Ok(Json(client.get_info().clone())),
         Err(e) => {
             tracing::error!("Failed to get node info: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+            Err(e.into())
         }
     }
 }
@@ -432,14 +612,9 @@ pub struct WalletBalanceResponse {
 
 #[axum::debug_handler]
 pub async fn get_wallet_balance(
-    Extension(claims): Extension<Claims>,
-) -> Result<Json<ApiResponse<WalletBalanceResponse>>, (StatusCode, String)> {
-    use crate::utils::handlers_common::{create_node_client, extract_node_credentials, handle_node_error, parse_public_key};
-    
-    let node_credentials = extract_node_credentials(&claims)?;
-    let public_key = parse_public_key(&node_credentials.node_id)?;
-    
-    let node_client = create_node_client(node_credentials, public_key).await?;
+    Extension(node): Extension<SelectedNode>,
+) -> Result<Json<ApiResponse<WalletBalanceResponse>>, ApiError> {
+    let node_client = node.client().await?;
 
     let balance = node_client
         .get_wallet_balance()
@@ -453,3 +628,191 @@ pub async fn get_wallet_balance(
         "Wallet balance retrieved successfully",
     )))
 }
+
+/// Lists which optional features the selected node's implementation and version support.
+///
+/// The version is read from the node on each request, and stored with the
+/// credential so upgrades are picked up.
+#[axum::debug_handler]
+pub async fn get_node_capabilities(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
+) -> Result<Json<ApiResponse<CapabilityMatrix>>, ApiError> {
+    let credentials = node.credentials();
+    let implementation = NodeImplementation::from_node_type(&credentials.node_type)
+        .ok_or_else(|| ApiError::bad_request("unsupported_node_type", "Unsupported node type"))?;
+
+    let node_client = node.client().await?;
+    let version = node_client.get_info().version.clone();
+
+    if let Some(version) = &version {
+        let stored = CredentialRepository::new(&pool)
+            .update_node_version(&claims.account_id, &credentials.node_id, version)
+            .await;
+        if let Err(e) = stored {
+            tracing::warn!("Failed to store node version: {}", e);
+        }
+    }
+
+    Ok(Json(ApiResponse::success(
+        node_capabilities::capability_matrix(implementation, version.as_deref()),
+        "Node capabilities retrieved successfully",
+    )))
+}
+
+/// Window of the round trip summary of each peer when none is given.
+const DEFAULT_PEER_RTT_WINDOW_MINUTES: u32 = 60;
+/// Hours of ping samples returned when none are given.
+const DEFAULT_PEER_PING_HOURS: u32 = 24;
+
+/// Query parameters for the peer list.
+#[derive(Debug, serde::Deserialize, validator::Validate)]
+pub struct PeerListQuery {
+    /// Minutes of ping samples each peer's round trips are summarised over
+    #[validate(range(min = 1, max = 10080, message = "minutes must be between 1 and 10080"))]
+    pub minutes: Option<u32>,
+}
+
+/// Query parameters for a peer's ping samples.
+#[derive(Debug, serde::Deserialize, validator::Validate)]
+pub struct PeerPingQuery {
+    /// How many hours back the samples reach
+    #[validate(range(min = 1, max = 720, message = "hours must be between 1 and 720"))]
+    pub hours: Option<u32>,
+}
+
+/// A peer of the selected node with its recent ping round trips.
+#[derive(Debug, serde::Serialize)]
+pub struct PeerResponse {
+    #[serde(flatten)]
+    pub peer: PeerSummary,
+    pub rtt: PeerRttStats,
+}
+
+/// Lists the selected node's peers with a summary of their sampled round trips.
+#[axum::debug_handler]
+pub async fn list_peers(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
+    Query(query): Query<PeerListQuery>,
+) -> Result<Json<ApiResponse<Vec<PeerResponse>>>, ApiError> {
+    query.validate()?;
+    let node_client = node.client().await?;
+    let peers = node_client
+        .list_peers()
+        .await
+        .map_err(|e| handle_node_error(e, "list peers"))?;
+
+    let minutes = query.minutes.unwrap_or(DEFAULT_PEER_RTT_WINDOW_MINUTES);
+    let since = chrono::Utc::now() - chrono::Duration::minutes(minutes.into());
+    let mut stats = PeerPingService::new(&pool)
+        .get_rtt_stats(&claims.account_id, &node.credentials().node_id, since)
+        .await?;
+
+    let peers = peers
+        .into_iter()
+        .map(|peer| PeerResponse {
+            rtt: stats.remove(&peer.pubkey.to_string()).unwrap_or_default(),
+            peer,
+        })
+        .collect();
+
+    Ok(Json(ApiResponse::success(
+        peers,
+        "Peers retrieved successfully",
+    )))
+}
+
+/// Lists the ping round trips sampled to one of the selected node's peers, oldest first.
+#[axum::debug_handler]
+pub async fn get_peer_pings(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
+    Path(pubkey): Path<String>,
+    Query(query): Query<PeerPingQuery>,
+) -> Result<Json<ApiResponse<Vec<PeerPing>>>, ApiError> {
+    query.validate()?;
+    let pubkey = parse_public_key(&pubkey)?;
+    let hours = query.hours.unwrap_or(DEFAULT_PEER_PING_HOURS);
+    let since = chrono::Utc::now() - chrono::Duration::hours(hours.into());
+
+    let pings = PeerPingService::new(&pool)
+        .get_pings(
+            &claims.account_id,
+            &node.credentials().node_id,
+            &pubkey.to_string(),
+            since,
+        )
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        pings,
+        "Peer pings retrieved successfully",
+    )))
+}
+
+/// Lists the selected node's on-chain transactions that carry a label on the
+/// node or an annotation in NodeGaze, side by side.
+#[axum::debug_handler]
+pub async fn list_transaction_labels(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
+) -> Result<Json<ApiResponse<Vec<LabelledTransaction>>>, ApiError> {
+    let node_client = node.client().await?;
+
+    let transactions =
+        transaction_labels::list_labelled_transactions(&pool, &claims.account_id, node_client)
+            .await?;
+
+    Ok(Json(ApiResponse::success(
+        transactions,
+        "Transaction labels retrieved successfully",
+    )))
+}
+
+/// Builds a shareable profile of the selected node from its public data:
+/// alias, pubkey, public channels and their fee policies.
+#[axum::debug_handler]
+pub async fn get_public_profile(
+    Extension(node): Extension<SelectedNode>,
+) -> Result<Json<ApiResponse<SignedPublicProfile>>, ApiError> {
+    public_profile_response(&node, false).await
+}
+
+/// Builds the selected node's public profile and has the node sign its JSON,
+/// which needs a writable credential allowed to sign messages.
+#[axum::debug_handler]
+pub async fn get_signed_public_profile(
+    Extension(node): Extension<SelectedNode>,
+) -> Result<Json<ApiResponse<SignedPublicProfile>>, ApiError> {
+    public_profile_response(&node, true).await
+}
+
+async fn public_profile_response(
+    node: &SelectedNode,
+    signed: bool,
+) -> Result<Json<ApiResponse<SignedPublicProfile>>, ApiError> {
+    let node_client = node.client().await?;
+
+    let profile = public_profile::build_profile(node_client)
+        .await
+        .map_err(|e| handle_node_error(e, "build public profile"))?;
+    let signature = if signed {
+        Some(
+            public_profile::sign_profile(node_client, &profile)
+                .await
+                .map_err(|e| handle_node_error(e, "sign public profile"))?,
+        )
+    } else {
+        None
+    };
+
+    Ok(Json(ApiResponse::success(
+        SignedPublicProfile { profile, signature },
+        "Public profile built successfully",
+    )))
+}
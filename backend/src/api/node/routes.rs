@@ -3,8 +3,16 @@
 //! These routes map specific API paths to handler functions responsible for
 //! serving channel statistics, node events, and other lightning-related information.
 
-use super::handlers::{authenticate_node, get_node_info, get_node_info_jwt, get_wallet_balance};
-use crate::auth::middleware::{jwt_auth, node_credentials_required, optional_jwt_auth};
+use super::handlers::{
+    authenticate_node, discover_nodes, get_bake_macaroon_command, get_node_capabilities,
+    get_node_info, get_node_info_jwt, get_peer_pings, get_public_profile,
+    get_signed_public_profile, get_wallet_balance, list_peers, list_transaction_labels,
+    test_connection,
+};
+use crate::auth::middleware::{
+    jwt_auth, node_group_access_required, node_selection, node_write_access_required,
+    optional_jwt_auth,
+};
 use axum::{
     Router, middleware,
     routing::{get, post},
@@ -19,17 +27,74 @@ pub async fn node_router() -> Router {
         )
         // Public route (no authentication required)
         .route("/info", post(get_node_info))
+        .route("/macaroon/bake-command", get(get_bake_macaroon_command))
+        // Checks credentials without storing them or starting collectors
+        .route(
+            "/test-connection",
+            post(test_connection).layer(middleware::from_fn(jwt_auth)),
+        )
+        // Probes the default endpoints of local node setups
+        .route(
+            "/discover",
+            get(discover_nodes).layer(middleware::from_fn(jwt_auth)),
+        )
         // Protected routes (require JWT token with node credentials)
         .route(
             "/info/jwt",
             get(get_node_info_jwt)
-                .layer(middleware::from_fn(node_credentials_required))
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
                 .layer(middleware::from_fn(jwt_auth)),
         )
         .route(
             "/wallet/balance",
             get(get_wallet_balance)
-                .layer(middleware::from_fn(node_credentials_required))
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/capabilities",
+            get(get_node_capabilities)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/peers",
+            get(list_peers)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/peers/{pubkey}/pings",
+            get(get_peer_pings)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/public-profile",
+            get(get_public_profile)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        // Signing uses the node's key, so read-only credentials can't
+        .route(
+            "/public-profile/signed",
+            get(get_signed_public_profile)
+                .layer(middleware::from_fn(node_write_access_required))
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/transaction-labels",
+            get(list_transaction_labels)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
                 .layer(middleware::from_fn(jwt_auth)),
         )
 }
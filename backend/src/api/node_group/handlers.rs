@@ -0,0 +1,161 @@
+//! Handler functions for node group management API endpoints.
+//!
+//! Any user may list the groups visible to them; only admins may change groups,
+//! their nodes or their members.
+
+use crate::api::common::{ApiError, ApiResponse};
+use crate::database::models::{AuditAction, CreateNodeGroupRequest, NodeGroupWithMembers};
+use crate::services::activity_feed::ActivityService;
+use crate::services::node_group_service::NodeGroupService;
+use crate::utils::handlers_common::require_admin;
+use crate::utils::jwt::Claims;
+use axum::{
+    extract::{Extension, Json, Path},
+    response::Json as ResponseJson,
+};
+use sqlx::SqlitePool;
+
+/// Lists node groups: every group for admins, the caller's own groups otherwise.
+#[axum::debug_handler]
+pub async fn get_node_groups(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+) -> Result<ResponseJson<ApiResponse<Vec<NodeGroupWithMembers>>>, ApiError> {
+    let service = NodeGroupService::new(&pool);
+    match service.get_groups(&claims).await {
+        Ok(groups) => Ok(ResponseJson(ApiResponse::success(
+            groups,
+            "Node groups retrieved successfully",
+        ))),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Creates a node group.
+#[axum::debug_handler]
+pub async fn create_node_group(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<CreateNodeGroupRequest>,
+) -> Result<ResponseJson<ApiResponse<NodeGroupWithMembers>>, ApiError> {
+    require_admin(&claims, "manage node groups")?;
+
+    let service = NodeGroupService::new(&pool);
+    let group = service.create_group(claims.account_id(), payload).await?;
+    ActivityService::new(&pool)
+        .record(
+            &claims,
+            AuditAction::NodeGroupCreated,
+            Some(&group.group.name),
+            None,
+        )
+        .await?;
+
+    Ok(ResponseJson(ApiResponse::success(
+        group,
+        "Node group created successfully",
+    )))
+}
+
+/// Deletes a node group.
+#[axum::debug_handler]
+pub async fn delete_node_group(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<ResponseJson<ApiResponse<()>>, ApiError> {
+    require_admin(&claims, "manage node groups")?;
+
+    let service = NodeGroupService::new(&pool);
+    service.delete_group(&id, claims.account_id()).await?;
+    ActivityService::new(&pool)
+        .record(&claims, AuditAction::NodeGroupDeleted, Some(&id), None)
+        .await?;
+
+    Ok(ResponseJson(ApiResponse::success(
+        (),
+        "Node group deleted successfully",
+    )))
+}
+
+/// Tags a node with a group.
+#[axum::debug_handler]
+pub async fn add_group_node(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path((id, node_id)): Path<(String, String)>,
+) -> Result<ResponseJson<ApiResponse<()>>, ApiError> {
+    require_admin(&claims, "manage node groups")?;
+
+    let service = NodeGroupService::new(&pool);
+    match service.add_node(&id, &node_id, claims.account_id()).await {
+        Ok(_) => Ok(ResponseJson(ApiResponse::success(
+            (),
+            "Node added to group successfully",
+        ))),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Removes a node from a group.
+#[axum::debug_handler]
+pub async fn remove_group_node(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path((id, node_id)): Path<(String, String)>,
+) -> Result<ResponseJson<ApiResponse<()>>, ApiError> {
+    require_admin(&claims, "manage node groups")?;
+
+    let service = NodeGroupService::new(&pool);
+    match service
+        .remove_node(&id, &node_id, claims.account_id())
+        .await
+    {
+        Ok(_) => Ok(ResponseJson(ApiResponse::success(
+            (),
+            "Node removed from group successfully",
+        ))),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Assigns a user to a group.
+#[axum::debug_handler]
+pub async fn add_group_member(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path((id, user_id)): Path<(String, String)>,
+) -> Result<ResponseJson<ApiResponse<()>>, ApiError> {
+    require_admin(&claims, "manage node groups")?;
+
+    let service = NodeGroupService::new(&pool);
+    match service.add_member(&id, &user_id, claims.account_id()).await {
+        Ok(_) => Ok(ResponseJson(ApiResponse::success(
+            (),
+            "User added to group successfully",
+        ))),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Removes a user from a group.
+#[axum::debug_handler]
+pub async fn remove_group_member(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path((id, user_id)): Path<(String, String)>,
+) -> Result<ResponseJson<ApiResponse<()>>, ApiError> {
+    require_admin(&claims, "manage node groups")?;
+
+    let service = NodeGroupService::new(&pool);
+    match service
+        .remove_member(&id, &user_id, claims.account_id())
+        .await
+    {
+        Ok(_) => Ok(ResponseJson(ApiResponse::success(
+            (),
+            "User removed from group successfully",
+        ))),
+        Err(error) => Err(error.into()),
+    }
+}
@@ -0,0 +1,23 @@
+//! Defines the HTTP routes for node group management.
+
+use super::handlers::{
+    add_group_member, add_group_node, create_node_group, delete_node_group, get_node_groups,
+    remove_group_member, remove_group_node,
+};
+use crate::auth::middleware::jwt_auth;
+use axum::{
+    Router, middleware,
+    routing::{delete, get, post, put},
+};
+
+pub async fn node_group_router() -> Router {
+    Router::new()
+        .route("/", get(get_node_groups))
+        .route("/", post(create_node_group))
+        .route("/{id}", delete(delete_node_group))
+        .route("/{id}/nodes/{node_id}", put(add_group_node))
+        .route("/{id}/nodes/{node_id}", delete(remove_group_node))
+        .route("/{id}/members/{user_id}", put(add_group_member))
+        .route("/{id}/members/{user_id}", delete(remove_group_member))
+        .layer(middleware::from_fn(jwt_auth))
+}
@@ -1,17 +1,17 @@
 //! Handler functions for notification management API endpoints.
 
-use crate::api::common::{
-    ApiResponse, PaginatedData, PaginationFilter, PaginationMeta, service_error_to_http,
-};
+use crate::api::common::{ApiError, ApiResponse, PaginatedData, PaginationFilter, PaginationMeta};
 use crate::database::models::{
-    CreateNotificationRequest, EventResponse, Notification, UpdateNotificationRequest,
+    AuditAction, CreateNotificationRequest, EventResponse, Notification, ReplayNotificationRequest,
+    UpdateNotificationRequest,
 };
+use crate::services::activity_feed::ActivityService;
+use crate::services::notification_replay::{self, ReplayStarted};
 use crate::services::notification_service::NotificationService;
 use crate::services::user_service::UserService;
 use crate::utils::jwt::Claims;
 use axum::{
     extract::{Extension, Json, Path, Query},
-    http::StatusCode,
     response::Json as ResponseJson,
 };
 use sqlx::SqlitePool;
@@ -22,28 +22,31 @@ pub async fn create_notification(
     Extension(pool): Extension<SqlitePool>,
     Extension(claims): Extension<Claims>,
     Json(payload): Json<CreateNotificationRequest>,
-) -> Result<ResponseJson<ApiResponse<Notification>>, (StatusCode, String)> {
+) -> Result<ResponseJson<ApiResponse<Notification>>, ApiError> {
     let user_id = claims.sub.as_str();
 
     // Get user details
     let user_service = UserService::new(&pool);
     let user = user_service.get_user_required(user_id).await.map_err(|e| {
         tracing::error!("User not found for ID {}: {}", user_id, e);
-        let error_response = ApiResponse::<()>::error("User not found", "user_not_found", None);
-        (
-            StatusCode::NOT_FOUND,
-            serde_json::to_string(&error_response).unwrap(),
-        )
+        ApiError::not_found("user_not_found", "User not found")
     })?;
 
     let service = NotificationService::new(&pool);
-    match service.create_notification(payload, &user).await {
-        Ok(notification) => Ok(ResponseJson(ApiResponse::success(
-            notification,
-            "Notification created successfully",
-        ))),
-        Err(error) => Err(service_error_to_http(error)),
-    }
+    let notification = service.create_notification(payload, &user).await?;
+    ActivityService::new(&pool)
+        .record(
+            &claims,
+            AuditAction::NotificationCreated,
+            Some(&notification.name),
+            Some(serde_json::json!({ "notification_id": notification.id })),
+        )
+        .await?;
+
+    Ok(ResponseJson(ApiResponse::success(
+        notification,
+        "Notification created successfully",
+    )))
 }
 
 /// Retrieves all notifications for the user's account.
@@ -51,7 +54,7 @@ pub async fn create_notification(
 pub async fn get_notifications(
     Extension(pool): Extension<SqlitePool>,
     Extension(claims): Extension<Claims>,
-) -> Result<ResponseJson<ApiResponse<Vec<Notification>>>, (StatusCode, String)> {
+) -> Result<ResponseJson<ApiResponse<Vec<Notification>>>, ApiError> {
     let account_id = claims.account_id();
 
     let service = NotificationService::new(&pool);
@@ -60,7 +63,7 @@ pub async fn get_notifications(
             notifications,
             "Notifications retrieved successfully",
         ))),
-        Err(error) => Err(service_error_to_http(error)),
+        Err(error) => Err(error.into()),
     }
 }
 
@@ -70,7 +73,7 @@ pub async fn get_notification_by_id(
     Extension(pool): Extension<SqlitePool>,
     Extension(claims): Extension<Claims>,
     Path(id): Path<String>,
-) -> Result<ResponseJson<ApiResponse<Notification>>, (StatusCode, String)> {
+) -> Result<ResponseJson<ApiResponse<Notification>>, ApiError> {
     let account_id = claims.account_id();
 
     let service = NotificationService::new(&pool);
@@ -79,7 +82,7 @@ pub async fn get_notification_by_id(
             notification,
             "Notification retrieved successfully",
         ))),
-        Err(error) => Err(service_error_to_http(error)),
+        Err(error) => Err(error.into()),
     }
 }
 
@@ -90,7 +93,7 @@ pub async fn update_notification(
     Extension(claims): Extension<Claims>,
     Path(id): Path<String>,
     Json(payload): Json<UpdateNotificationRequest>,
-) -> Result<ResponseJson<ApiResponse<Notification>>, (StatusCode, String)> {
+) -> Result<ResponseJson<ApiResponse<Notification>>, ApiError> {
     let account_id = claims.account_id();
 
     let service = NotificationService::new(&pool);
@@ -99,7 +102,7 @@ pub async fn update_notification(
             notification,
             "Notification updated successfully",
         ))),
-        Err(error) => Err(service_error_to_http(error)),
+        Err(error) => Err(error.into()),
     }
 }
 
@@ -109,17 +112,19 @@ pub async fn delete_notification(
     Extension(pool): Extension<SqlitePool>,
     Extension(claims): Extension<Claims>,
     Path(id): Path<String>,
-) -> Result<ResponseJson<ApiResponse<()>>, (StatusCode, String)> {
+) -> Result<ResponseJson<ApiResponse<()>>, ApiError> {
     let account_id = claims.account_id();
 
     let service = NotificationService::new(&pool);
-    match service.delete_notification(&id, account_id).await {
-        Ok(_) => Ok(ResponseJson(ApiResponse::success(
-            (),
-            "Notification deleted successfully",
-        ))),
-        Err(error) => Err(service_error_to_http(error)),
-    }
+    service.delete_notification(&id, account_id).await?;
+    ActivityService::new(&pool)
+        .record(&claims, AuditAction::NotificationDeleted, Some(&id), None)
+        .await?;
+
+    Ok(ResponseJson(ApiResponse::success(
+        (),
+        "Notification deleted successfully",
+    )))
 }
 
 /// Retrieves events for a specific notification endpoint.
@@ -129,7 +134,7 @@ pub async fn get_notification_events(
     Extension(claims): Extension<Claims>,
     Path(id): Path<String>,
     Query(pagination): Query<PaginationFilter>,
-) -> Result<ResponseJson<ApiResponse<PaginatedData<EventResponse>>>, (StatusCode, String)> {
+) -> Result<ResponseJson<ApiResponse<PaginatedData<EventResponse>>>, ApiError> {
     let account_id = claims.account_id();
 
     let service = NotificationService::new(&pool);
@@ -148,8 +153,7 @@ pub async fn get_notification_events(
             // Get total count
             let total_count = service
                 .count_events_for_notification(&id, account_id)
-                .await
-                .map_err(service_error_to_http)?;
+                .await?;
 
             let paginated_data = PaginatedData::new(events, total_count as u64);
             let pagination_meta = PaginationMeta::from_filter(&pagination, total_count as u64);
@@ -159,6 +163,24 @@ pub async fn get_notification_events(
                 pagination_meta,
             )))
         }
-        Err(error) => Err(service_error_to_http(error)),
+        Err(error) => Err(error.into()),
     }
 }
+
+/// Sends the events stored for a notification endpoint within a time range
+/// to it again, e.g. after its receiver was down.
+#[axum::debug_handler]
+pub async fn replay_notification_events(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Json(payload): Json<ReplayNotificationRequest>,
+) -> Result<ResponseJson<ApiResponse<ReplayStarted>>, ApiError> {
+    let replay =
+        notification_replay::start_replay(&pool, claims.account_id(), &id, payload).await?;
+
+    Ok(ResponseJson(ApiResponse::success(
+        replay,
+        "Notification replay started",
+    )))
+}
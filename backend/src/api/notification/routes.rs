@@ -2,7 +2,7 @@
 
 use super::handlers::{
     create_notification, delete_notification, get_notification_by_id, get_notification_events,
-    get_notifications, update_notification,
+    get_notifications, replay_notification_events, update_notification,
 };
 use crate::auth::middleware::jwt_auth;
 use axum::{
@@ -24,4 +24,6 @@ pub async fn notification_router() -> Router {
         .layer(middleware::from_fn(jwt_auth))
         .route("/{id}/events", get(get_notification_events))
         .layer(middleware::from_fn(jwt_auth))
+        .route("/{id}/replay", post(replay_notification_events))
+        .layer(middleware::from_fn(jwt_auth))
 }
@@ -2,71 +2,367 @@
 //!
 //! These functions process requests for payment data and return payment-specific information.
 
+use crate::database::models::{
+    PaymentAnnotation, PaymentWatch, UpdatePaymentAnnotationRequest, WatchPaymentsRequest,
+    WatchStatus,
+};
+use crate::services::payment_annotations::PaymentAnnotationService;
+use crate::services::payment_stats::{PaymentStats, StatsBucket, parse_window, stats_span};
+use crate::services::payment_tracker::PaymentTracker;
+use crate::services::payment_watches::PaymentWatchService;
+use crate::services::price_history::PriceHistoryService;
+use crate::services::stats_rollups::StatsRollupService;
 use crate::utils::handlers_common::{
-    create_node_client, extract_node_credentials, handle_node_error, parse_payment_hash,
-    parse_public_key,
+    SelectedNode, handle_node_error, parse_payment_hash, request_tz, require_write_access,
 };
 use crate::utils::jwt::Claims;
+use crate::utils::time_zone::{DateBound, DateRange};
 use crate::{
     api::common::{
-        ApiResponse, NumericOperator, PaginatedData, PaginationFilter, PaginationMeta,
-        apply_pagination, deserialize_states, validation_error_response,
+        ApiError, ApiResponse, NumericOperator, PaginatedData, PaginationFilter, PaginationMeta,
+        apply_pagination, deserialize_states,
+    },
+    utils::{
+        PaymentCursor, PaymentDetails, PaymentState, PaymentSummary, PaymentType,
+        deserialize_payment_types, sort_payments,
     },
-    utils::{PaymentDetails, PaymentState, PaymentSummary, PaymentType, deserialize_payment_types},
 };
+use async_stream::stream;
 use axum::{
     Json,
     extract::{Extension, Path, Query},
-    http::StatusCode,
+    response::sse::{Event, KeepAlive, Sse},
 };
 use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::convert::Infallible;
 use validator::Validate;
 
+/// A payment with the tags and notes the account attached to it.
+#[derive(Debug, Serialize)]
+pub struct AnnotatedPayment<T> {
+    #[serde(flatten)]
+    pub payment: T,
+    pub annotation: Option<PaymentAnnotation>,
+}
+
 /// Handler for getting payment details
 #[axum::debug_handler]
 pub async fn get_payment_details(
+    Extension(pool): Extension<SqlitePool>,
     Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
     Path(payment_hash): Path<String>,
-) -> Result<Json<ApiResponse<PaymentDetails>>, (StatusCode, String)> {
+) -> Result<Json<ApiResponse<AnnotatedPayment<PaymentDetails>>>, ApiError> {
+    let annotation = PaymentAnnotationService::new(&pool)
+        .get_annotation(&claims.account_id, &payment_hash)
+        .await?;
     let payment_hash = parse_payment_hash(&payment_hash)?;
-    let node_credentials = extract_node_credentials(&claims)?;
-    let public_key = parse_public_key(&node_credentials.node_id)?;
+    let node_client = node.client().await?;
 
-    let node_client = create_node_client(node_credentials, public_key).await?;
-
-    let payment_details = node_client
+    let mut payment_details = node_client
         .get_payment_details(&payment_hash)
         .await
         .map_err(|e| handle_node_error(e, "get payment details"))?;
 
+    PriceHistoryService::new(&pool)
+        .reprice_payment(&mut payment_details)
+        .await;
+
     Ok(Json(ApiResponse::success(
-        payment_details,
+        AnnotatedPayment {
+            payment: payment_details,
+            annotation,
+        },
         "Payment details retrieved successfully",
     )))
 }
 
-/// Handler for listing all payments
+/// Streams the progress of an outgoing payment as server-sent events.
+///
+/// Each HTLC attempt produces `attempt_started` and then `attempt_failed` or
+/// `attempt_succeeded`; the stream ends with `settled` or `failed`, or with an
+/// `error` event when the payment can't be tracked.
+#[axum::debug_handler]
+pub async fn track_payment(
+    Extension(node): Extension<SelectedNode>,
+    Path(payment_hash): Path<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let payment_hash = parse_payment_hash(&payment_hash)?;
+    // Connect before the response starts so connection errors get a status code
+    node.client().await?;
+
+    let events = stream! {
+        // Already connected above, so this returns the same client
+        let Ok(client) = node.client().await else {
+            return;
+        };
+        let mut updates = match client.track_payment(&payment_hash).await {
+            Ok(updates) => updates,
+            Err(e) => {
+                yield Ok(error_event(&e.to_string()));
+                return;
+            }
+        };
+
+        let mut tracker = PaymentTracker::new();
+        while let Some(update) = updates.next().await {
+            let payment = match update {
+                Ok(payment) => payment,
+                Err(e) => {
+                    yield Ok(error_event(&e.to_string()));
+                    return;
+                }
+            };
+            for progress in tracker.update(payment) {
+                let finished = progress.is_final();
+                match Event::default().event(progress.event_name()).json_data(&progress) {
+                    Ok(event) => yield Ok(event),
+                    Err(e) => tracing::warn!("Failed to encode payment progress: {}", e),
+                }
+                if finished {
+                    return;
+                }
+            }
+        }
+    };
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+fn error_event(message: &str) -> Event {
+    Event::default()
+        .event("error")
+        .data(json!({ "message": message }).to_string())
+}
+
+/// Handler for listing all payments, newest first.
+///
+/// Pages are picked by `page` or, for pages that stay put while new payments
+/// arrive, by the `next_cursor` of the previous page.
 #[axum::debug_handler]
 pub async fn list_payments(
+    Extension(pool): Extension<SqlitePool>,
     Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
     Query(filter): Query<PaymentFilter>,
-) -> Result<Json<ApiResponse<PaginatedData<PaymentSummary>>>, (StatusCode, String)> {
-    if let Err(validation_errors) = filter.validate() {
-        return Err(validation_error_response(validation_errors));
-    }
-
-    let node_credentials = extract_node_credentials(&claims)?;
-    let public_key = parse_public_key(&node_credentials.node_id)?;
+) -> Result<Json<ApiResponse<PaginatedData<AnnotatedPayment<PaymentSummary>>>>, ApiError> {
+    filter.validate()?;
+    let cursor = filter
+        .cursor
+        .as_deref()
+        .map(str::parse::<PaymentCursor>)
+        .transpose()
+        .map_err(|e| ApiError::bad_request("invalid_cursor", e))?;
 
-    let node_client = create_node_client(node_credentials, public_key).await?;
+    let node_client = node.client().await?;
 
-    let all_payments = node_client
+    let mut all_payments = node_client
         .list_payments()
         .await
         .map_err(|e| handle_node_error(e, "list payments"))?;
+    // Backends already sort, but pages must not depend on them getting it right
+    sort_payments(&mut all_payments);
+
+    PriceHistoryService::new(&pool)
+        .reprice_payments(&mut all_payments)
+        .await;
 
-    process_payments_with_filters(all_payments, &filter).await
+    let annotations = PaymentAnnotationService::new(&pool)
+        .get_annotations(&claims.account_id)
+        .await?;
+
+    let tz = request_tz(&pool, &claims.account_id, filter.tz.as_deref()).await?;
+    let range = DateRange::resolve(filter.from, filter.to, tz);
+
+    process_payments_with_filters(all_payments, annotations, &filter, &range, cursor).await
+}
+
+/// Sets the tags and notes of a payment, invoice or on-chain transaction (by txid).
+#[axum::debug_handler]
+pub async fn update_payment_annotation(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(payment_hash): Path<String>,
+    Json(payload): Json<UpdatePaymentAnnotationRequest>,
+) -> Result<Json<ApiResponse<PaymentAnnotation>>, ApiError> {
+    require_write_access(&claims, "annotate payments")?;
+    parse_payment_hash(&payment_hash)?;
+
+    let annotation = PaymentAnnotationService::new(&pool)
+        .update_annotation(&claims.account_id, &payment_hash, payload)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        annotation,
+        "Payment annotation updated successfully",
+    )))
+}
+
+/// Removes the tags and notes of a payment, invoice or on-chain transaction.
+#[axum::debug_handler]
+pub async fn delete_payment_annotation(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(payment_hash): Path<String>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    require_write_access(&claims, "annotate payments")?;
+    parse_payment_hash(&payment_hash)?;
+
+    PaymentAnnotationService::new(&pool)
+        .delete_annotation(&claims.account_id, &payment_hash)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        (),
+        "Payment annotation removed successfully",
+    )))
+}
+
+/// Default number of watches returned.
+const DEFAULT_WATCHES_LIMIT: u32 = 50;
+/// Largest number of watches a client may request.
+const MAX_WATCHES_LIMIT: u32 = 200;
+
+/// Query parameters for listing payment watches.
+#[derive(Debug, Deserialize)]
+pub struct WatchListQuery {
+    /// `pending`, `settled`, `failed` or `expired`
+    pub status: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// Watches payment hashes for their payment settling or failing.
+#[axum::debug_handler]
+pub async fn watch_payments(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<WatchPaymentsRequest>,
+) -> Result<Json<ApiResponse<Vec<PaymentWatch>>>, ApiError> {
+    require_write_access(&claims, "watch payments")?;
+
+    let watches = PaymentWatchService::new(&pool)
+        .watch_payments(&claims, payload)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        watches,
+        "Payment hashes watched successfully",
+    )))
+}
+
+/// Lists the account's payment watches, newest first.
+#[axum::debug_handler]
+pub async fn list_payment_watches(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<WatchListQuery>,
+) -> Result<Json<ApiResponse<Vec<PaymentWatch>>>, ApiError> {
+    let status = query
+        .status
+        .as_deref()
+        .map(str::parse::<WatchStatus>)
+        .transpose()
+        .map_err(|e| ApiError::bad_request("invalid_status", e))?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_WATCHES_LIMIT)
+        .clamp(1, MAX_WATCHES_LIMIT);
+
+    let watches = PaymentWatchService::new(&pool)
+        .get_watches(
+            &claims.account_id,
+            status,
+            limit.into(),
+            query.offset.unwrap_or(0).into(),
+        )
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        watches,
+        "Payment watches retrieved successfully",
+    )))
+}
+
+/// Stops watching a payment hash.
+#[axum::debug_handler]
+pub async fn delete_payment_watch(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(payment_hash): Path<String>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    require_write_access(&claims, "watch payments")?;
+    parse_payment_hash(&payment_hash)?;
+
+    PaymentWatchService::new(&pool)
+        .unwatch(&claims.account_id, &payment_hash)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        (),
+        "Payment watch removed successfully",
+    )))
+}
+
+/// Window used when `GET /api/payments/stats` is called without one.
+const DEFAULT_STATS_WINDOW: &str = "30d";
+
+/// Query parameters for payment statistics.
+#[derive(Debug, Deserialize)]
+pub struct PaymentStatsQuery {
+    /// How far back to look, e.g. `24h`, `30d` or `12w`; ignored when `from` is given
+    pub window: Option<String>,
+    /// Start of the statistics, a date or an RFC 3339 time
+    pub from: Option<DateBound>,
+    /// End of the statistics (inclusive), now when absent
+    pub to: Option<DateBound>,
+    /// IANA time zone for dates and bucket boundaries, the account's when absent
+    pub tz: Option<String>,
+    /// Width of each bucket
+    #[serde(default)]
+    pub bucket: StatsBucket,
+}
+
+/// Handler for payment volume and success-rate statistics
+#[axum::debug_handler]
+pub async fn get_payment_stats(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
+    Query(query): Query<PaymentStatsQuery>,
+) -> Result<Json<ApiResponse<PaymentStats>>, ApiError> {
+    let window = parse_window(query.window.as_deref().unwrap_or(DEFAULT_STATS_WINDOW))
+        .map_err(|e| ApiError::bad_request("invalid_window", e))?;
+
+    let tz = request_tz(&pool, &claims.account_id, query.tz.as_deref()).await?;
+    let (start, end) = stats_span(window, query.from, query.to, tz, Utc::now())
+        .map_err(|e| ApiError::bad_request("invalid_window", e))?;
+
+    let rollups = StatsRollupService::new(&pool);
+    let node_id = &node.credentials().node_id;
+    rollups
+        .refresh_stale_payment_rollups(&claims.account_id, node_id, async || node.client().await)
+        .await?;
+
+    let stats = rollups
+        .get_payment_stats(
+            &claims.account_id,
+            std::slice::from_ref(node_id),
+            start,
+            end,
+            query.bucket,
+            tz,
+        )
+        .await?;
+    Ok(Json(ApiResponse::success(
+        stats,
+        "Payment statistics retrieved successfully",
+    )))
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
@@ -85,11 +381,14 @@ pub struct PaymentFilterRequest {
     /// The value to compare against
     pub value: Option<i64>,
 
-    /// Start date (inclusive)
-    pub from: Option<DateTime<Utc>>,
+    /// Start date (inclusive), a date or an RFC 3339 time
+    pub from: Option<DateBound>,
 
-    /// End date (inclusive)
-    pub to: Option<DateTime<Utc>>,
+    /// End date (inclusive), a date or an RFC 3339 time
+    pub to: Option<DateBound>,
+
+    /// IANA time zone dates are read in, the account's when absent
+    pub tz: Option<String>,
 
     /// Payment states filter
     #[serde(default, deserialize_with = "deserialize_states")]
@@ -98,6 +397,12 @@ pub struct PaymentFilterRequest {
     /// Payment type filter (NEW - only for payments)
     #[serde(default, deserialize_with = "deserialize_payment_types")]
     pub payment_types: Option<Vec<PaymentType>>,
+
+    /// Only payments annotated with this tag
+    pub tag: Option<String>,
+
+    /// `next_cursor` from the previous page; takes precedence over `page`
+    pub cursor: Option<String>,
 }
 
 pub type PaymentFilter = PaymentFilterRequest;
@@ -115,6 +420,7 @@ impl PaymentFilterRequest {
 fn apply_payment_filters(
     mut payments: Vec<PaymentSummary>,
     filter: &PaymentFilter,
+    range: &DateRange,
 ) -> Vec<PaymentSummary> {
     // Apply state filter
     if let Some(filter_states) = &filter.states {
@@ -141,48 +447,104 @@ fn apply_payment_filters(
         } else {
             let filter_value_u64 = filter_value as u64;
             payments.retain(|payment| match operator {
-                NumericOperator::Gte => payment.amount_sat >= filter_value_u64,
-                NumericOperator::Lte => payment.amount_sat <= filter_value_u64,
-                NumericOperator::Eq => payment.amount_sat == filter_value_u64,
-                NumericOperator::Gt => payment.amount_sat > filter_value_u64,
-                NumericOperator::Lt => payment.amount_sat < filter_value_u64,
+                NumericOperator::Gte => payment.amount.sat() >= filter_value_u64,
+                NumericOperator::Lte => payment.amount.sat() <= filter_value_u64,
+                NumericOperator::Eq => payment.amount.sat() == filter_value_u64,
+                NumericOperator::Gt => payment.amount.sat() > filter_value_u64,
+                NumericOperator::Lt => payment.amount.sat() < filter_value_u64,
             });
         }
     }
 
-    // Apply date range filter
-    if filter.from.is_some() || filter.to.is_some() {
-        if let Some(from_date) = filter.from {
-            payments.retain(|payment| {
-                payment
-                    .completed_at
-                    .map(|completed_at| (completed_at as i64) >= from_date.timestamp())
-                    .unwrap_or(false)
-            });
-        }
-
-        if let Some(to_date) = filter.to {
-            payments.retain(|payment| {
-                payment
-                    .completed_at
-                    .map(|completed_at| (completed_at as i64) <= to_date.timestamp())
-                    .unwrap_or(false)
-            });
-        }
+    // Apply date range filter, which payments that never completed don't match
+    if *range != DateRange::default() {
+        payments.retain(|payment| {
+            payment
+                .completed_at
+                .and_then(|completed_at| DateTime::from_timestamp(completed_at as i64, 0))
+                .is_some_and(|completed_at| range.contains(completed_at))
+        });
     }
     payments
 }
 
+/// Picks the payments after `cursor` in list order, returning at most
+/// `per_page` of them and the cursor of the page after, if there is one.
+fn page_after_cursor(
+    payments: Vec<AnnotatedPayment<PaymentSummary>>,
+    cursor: &PaymentCursor,
+    per_page: usize,
+) -> (Vec<AnnotatedPayment<PaymentSummary>>, Option<PaymentCursor>) {
+    let mut page: Vec<_> = payments
+        .into_iter()
+        .skip_while(|payment| PaymentCursor::from(&payment.payment) >= *cursor)
+        .take(per_page + 1)
+        .collect();
+    if page.len() <= per_page {
+        return (page, None);
+    }
+    page.truncate(per_page);
+    let next_cursor = page
+        .last()
+        .map(|payment| PaymentCursor::from(&payment.payment));
+    (page, next_cursor)
+}
+
 /// Process payments with filters and pagination
 async fn process_payments_with_filters(
     all_payments: Vec<PaymentSummary>,
+    mut annotations: HashMap<String, PaymentAnnotation>,
     filter: &PaymentFilter,
-) -> Result<Json<ApiResponse<PaginatedData<PaymentSummary>>>, (StatusCode, String)> {
-    let filtered_payments = apply_payment_filters(all_payments, filter);
+    range: &DateRange,
+    cursor: Option<PaymentCursor>,
+) -> Result<Json<ApiResponse<PaginatedData<AnnotatedPayment<PaymentSummary>>>>, ApiError> {
+    let mut filtered_payments: Vec<AnnotatedPayment<PaymentSummary>> =
+        apply_payment_filters(all_payments, filter, range)
+            .into_iter()
+            .map(|payment| AnnotatedPayment {
+                annotation: annotations.remove(&payment.payment_hash.to_lowercase()),
+                payment,
+            })
+            .collect();
+
+    // Apply tag filter
+    if let Some(tag) = filter.tag.as_deref().map(str::trim) {
+        filtered_payments.retain(|payment| {
+            payment
+                .annotation
+                .as_ref()
+                .is_some_and(|annotation| annotation.has_tag(tag))
+        });
+    }
+
     let total_filtered_count = filtered_payments.len() as u64;
     let pagination_filter = filter.to_pagination_filter();
-    let paginated_payments = apply_pagination(filtered_payments, &pagination_filter);
-    let pagination_meta = PaginationMeta::from_filter(&pagination_filter, total_filtered_count);
+
+    let (paginated_payments, pagination_meta) = match cursor {
+        Some(cursor) => {
+            let per_page = pagination_filter.per_page();
+            let (page, next_cursor) =
+                page_after_cursor(filtered_payments, &cursor, per_page as usize);
+            let meta = PaginationMeta::from_cursor(
+                per_page,
+                total_filtered_count,
+                true,
+                next_cursor.map(|cursor| cursor.to_string()),
+            );
+            (page, meta)
+        }
+        None => {
+            let page = apply_pagination(filtered_payments, &pagination_filter);
+            let mut meta = PaginationMeta::from_filter(&pagination_filter, total_filtered_count);
+            // Lets clients switch to cursors after the first page
+            if meta.has_next {
+                meta.next_cursor = page
+                    .last()
+                    .map(|payment| PaymentCursor::from(&payment.payment).to_string());
+            }
+            (page, meta)
+        }
+    };
     let paginated_data = PaginatedData::new(paginated_payments, total_filtered_count);
 
     Ok(Json(ApiResponse::ok_paginated(
@@ -3,22 +3,60 @@
 //! These routes provide endpoints for accessing and updating payment-specific
 //! data.
 
-use super::handlers::{get_payment_details, list_payments};
-use crate::auth::middleware::{jwt_auth, node_credentials_required};
-use axum::{Router, middleware, routing::get};
+use super::handlers::{
+    delete_payment_annotation, delete_payment_watch, get_payment_details, get_payment_stats,
+    list_payment_watches, list_payments, track_payment, update_payment_annotation, watch_payments,
+};
+use crate::auth::middleware::{jwt_auth, node_group_access_required, node_selection};
+use axum::{
+    Router, middleware,
+    routing::{delete, get, put},
+};
 
 pub async fn payment_router() -> Router {
     Router::new()
+        .route(
+            "/stats",
+            get(get_payment_stats)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/watch",
+            get(list_payment_watches)
+                .post(watch_payments)
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/watch/{payment_hash}",
+            delete(delete_payment_watch).layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/{payment_hash}/annotation",
+            put(update_payment_annotation)
+                .delete(delete_payment_annotation)
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/{payment_hash}/track",
+            get(track_payment)
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
+                .layer(middleware::from_fn(jwt_auth)),
+        )
         .route(
             "/{payment_hash}",
             get(get_payment_details)
-                .layer(middleware::from_fn(node_credentials_required))
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
                 .layer(middleware::from_fn(jwt_auth)),
         )
         .route(
             "/",
             get(list_payments)
-                .layer(middleware::from_fn(node_credentials_required))
+                .layer(middleware::from_fn(node_group_access_required))
+                .layer(middleware::from_fn(node_selection))
                 .layer(middleware::from_fn(jwt_auth)),
         )
 }
@@ -0,0 +1,70 @@
+//! Handler functions for peer blocklist API endpoints.
+//!
+//! These functions list the peers whose events the account suppresses or
+//! downgrades, and add, change or remove them.
+
+use crate::api::common::{ApiError, ApiResponse};
+use crate::database::models::{BlockPeerRequest, BlockedPeer};
+use crate::services::peer_blocklist::PeerBlocklistService;
+use crate::utils::handlers_common::require_write_access;
+use crate::utils::jwt::Claims;
+use axum::{
+    Json,
+    extract::{Extension, Path},
+};
+use sqlx::SqlitePool;
+
+/// Lists the account's blocked peers.
+#[axum::debug_handler]
+pub async fn list_blocked_peers(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<ApiResponse<Vec<BlockedPeer>>>, ApiError> {
+    let peers = PeerBlocklistService::new(&pool)
+        .get_blocked_peers(&claims.account_id)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        peers,
+        "Blocked peers retrieved successfully",
+    )))
+}
+
+/// Blocks a peer, or changes what happens to its events.
+#[axum::debug_handler]
+pub async fn block_peer(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(pubkey): Path<String>,
+    Json(payload): Json<BlockPeerRequest>,
+) -> Result<Json<ApiResponse<BlockedPeer>>, ApiError> {
+    require_write_access(&claims, "change the peer blocklist")?;
+
+    let peer = PeerBlocklistService::new(&pool)
+        .block_peer(&claims.account_id, &pubkey, payload)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        peer,
+        "Peer blocked successfully",
+    )))
+}
+
+/// Removes a peer from the blocklist.
+#[axum::debug_handler]
+pub async fn unblock_peer(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(pubkey): Path<String>,
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    require_write_access(&claims, "change the peer blocklist")?;
+
+    PeerBlocklistService::new(&pool)
+        .unblock_peer(&claims.account_id, &pubkey)
+        .await?;
+
+    Ok(Json(ApiResponse::success(
+        (),
+        "Peer unblocked successfully",
+    )))
+}
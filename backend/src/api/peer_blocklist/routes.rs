@@ -0,0 +1,22 @@
+//! Defines the HTTP routes for the account's peer blocklist.
+
+use super::handlers::{block_peer, list_blocked_peers, unblock_peer};
+use crate::auth::middleware::jwt_auth;
+use axum::{
+    Router, middleware,
+    routing::{get, put},
+};
+
+pub async fn peer_blocklist_router() -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(list_blocked_peers).layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/{pubkey}",
+            put(block_peer)
+                .delete(unblock_peer)
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+}
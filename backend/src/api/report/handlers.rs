@@ -0,0 +1,197 @@
+//! Handler functions for node report API endpoints.
+
+use crate::api::common::{ApiError, ApiResponse};
+use crate::database::models::{Report, ReportFrequency};
+use crate::errors::ServiceError;
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::repositories::report_repository::ReportRepository;
+use crate::services::node_group_service::NodeGroupService;
+use crate::services::reports::{generate_report, last_complete_period, render_html, render_pdf};
+use crate::services::settings_service::SettingsService;
+use crate::utils::handlers_common::require_write_access;
+use crate::utils::jwt::Claims;
+use axum::{
+    Json,
+    extract::{Extension, Path, Query},
+    http::{
+        HeaderValue,
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    },
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use serde::Deserialize;
+use sqlx::SqlitePool;
+
+/// Default number of reports returned.
+const DEFAULT_REPORTS_LIMIT: u32 = 20;
+/// Largest number of reports a client may request.
+const MAX_REPORTS_LIMIT: u32 = 100;
+
+/// Query parameters for listing reports.
+#[derive(Debug, Deserialize)]
+pub struct ReportListQuery {
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// Format a report is downloaded in.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    Html,
+    Pdf,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReportDownloadQuery {
+    #[serde(default)]
+    pub format: ReportFormat,
+}
+
+/// Body of `POST /api/reports`.
+#[derive(Debug, Deserialize)]
+pub struct GenerateReportRequest {
+    pub node_id: String,
+    pub frequency: ReportFrequency,
+}
+
+/// Lists the reports of the nodes visible to the caller, newest period first.
+#[axum::debug_handler]
+pub async fn list_reports(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ReportListQuery>,
+) -> Result<Json<ApiResponse<Vec<Report>>>, ApiError> {
+    let scope = NodeGroupService::new(&pool)
+        .node_scope_for_claims(&claims)
+        .await?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_REPORTS_LIMIT)
+        .clamp(1, MAX_REPORTS_LIMIT);
+
+    let reports = ReportRepository::new(&pool)
+        .get_reports_by_account_id(
+            &claims.account_id,
+            scope.node_ids(),
+            limit.into(),
+            query.offset.unwrap_or(0).into(),
+        )
+        .await
+        .map_err(ServiceError::from)?;
+
+    Ok(Json(ApiResponse::success(
+        reports,
+        "Reports retrieved successfully",
+    )))
+}
+
+/// Downloads a report as JSON, HTML or PDF.
+#[axum::debug_handler]
+pub async fn get_report(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+    Query(query): Query<ReportDownloadQuery>,
+) -> Result<Response, ApiError> {
+    let report = ReportRepository::new(&pool)
+        .get_report(&claims.account_id, &id)
+        .await
+        .map_err(ServiceError::from)?;
+    let scope = NodeGroupService::new(&pool)
+        .node_scope_for_claims(&claims)
+        .await?;
+    let Some(report) = report.filter(|report| scope.allows(&report.node_id)) else {
+        return Err(ApiError::not_found(
+            "report_not_found",
+            format!("Report {id} not found"),
+        ));
+    };
+
+    let file_name = format!(
+        "nodegaze-{}-report-{}",
+        report.frequency,
+        report.period_start.format("%Y-%m-%d")
+    );
+    let branding = SettingsService::new(&pool)
+        .get_settings(&claims.account_id)
+        .await?
+        .branding;
+    let (content_type, extension, body) = match query.format {
+        ReportFormat::Json => {
+            return Ok(Json(ApiResponse::success(
+                report,
+                "Report retrieved successfully",
+            ))
+            .into_response());
+        }
+        ReportFormat::Html => (
+            "text/html; charset=utf-8",
+            "html",
+            render_html(&report, &branding)?.into_bytes(),
+        ),
+        ReportFormat::Pdf => ("application/pdf", "pdf", render_pdf(&report, &branding)?),
+    };
+
+    let disposition =
+        HeaderValue::from_str(&format!("attachment; filename=\"{file_name}.{extension}\""))
+            .map_err(|e| {
+                ApiError::internal("report_error", format!("Invalid report file name: {e}"))
+            })?;
+    Ok((
+        [
+            (CONTENT_TYPE, HeaderValue::from_static(content_type)),
+            (CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Generates the report of a node's last complete week or month now.
+///
+/// A report already stored for that period is replaced. Nothing is emailed.
+#[axum::debug_handler]
+pub async fn create_report(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<GenerateReportRequest>,
+) -> Result<Json<ApiResponse<Report>>, ApiError> {
+    require_write_access(&claims, "generate reports")?;
+
+    let scope = NodeGroupService::new(&pool)
+        .node_scope_for_claims(&claims)
+        .await?;
+    let credentials = CredentialRepository::new(&pool)
+        .get_node_credentials(Some(&claims.account_id), Some(&claims.sub))
+        .await
+        .map_err(ServiceError::from)?;
+    let credential = credentials
+        .iter()
+        .filter(|credential| scope.allows(&credential.node_id))
+        .find(|credential| credential.node_id == payload.node_id)
+        .ok_or_else(|| {
+            ApiError::not_found(
+                "node_not_found",
+                format!("Node {} not found", payload.node_id),
+            )
+        })?;
+
+    let settings = SettingsService::new(&pool)
+        .get_settings(&claims.account_id)
+        .await?;
+    let (start, end) = last_complete_period(payload.frequency, Utc::now(), settings.tz())
+        .ok_or_else(|| {
+            ApiError::bad_request("invalid_frequency", "Frequency must be weekly or monthly")
+        })?;
+
+    let report = generate_report(&pool, credential, payload.frequency, start, end).await?;
+
+    Ok(Json(ApiResponse::success(
+        report,
+        "Report generated successfully",
+    )))
+}
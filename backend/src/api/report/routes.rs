@@ -0,0 +1,12 @@
+//! Defines the HTTP routes for node reports.
+
+use super::handlers::{create_report, get_report, list_reports};
+use crate::auth::middleware::jwt_auth;
+use axum::{Router, middleware, routing::get};
+
+pub async fn report_router() -> Router {
+    Router::new()
+        .route("/", get(list_reports).post(create_report))
+        .route("/{id}", get(get_report))
+        .layer(middleware::from_fn(jwt_auth))
+}
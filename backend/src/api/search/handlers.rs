@@ -0,0 +1,101 @@
+use crate::api::common::{ApiError, ApiResponse};
+use crate::database::models::EventFilters;
+use crate::services::event_service::EventService;
+use crate::services::node_group_service::NodeGroupService;
+use crate::services::search::{SearchResults, channel_matches, invoice_matches, payment_matches};
+use crate::utils::handlers_common::SelectedNode;
+use crate::utils::jwt::Claims;
+use axum::{
+    Json,
+    extract::{Extension, Query},
+};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+use validator::Validate;
+
+/// Results returned per group when no limit is given.
+const DEFAULT_SEARCH_LIMIT: usize = 10;
+
+/// Query parameters for the account-wide search.
+#[derive(Debug, Deserialize, Validate)]
+pub struct SearchQuery {
+    /// Text to look for
+    #[validate(length(min = 2, max = 200, message = "q must be between 2 and 200 characters"))]
+    pub q: String,
+    /// Most results returned per group
+    #[validate(range(min = 1, max = 50, message = "limit must be between 1 and 50"))]
+    pub limit: Option<usize>,
+}
+
+/// Handler for searching channels, payments, invoices and events at once
+#[axum::debug_handler]
+pub async fn search(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Extension(node): Extension<SelectedNode>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<ApiResponse<SearchResults>>, ApiError> {
+    query.validate()?;
+    let q = query.q.trim().to_string();
+    let limit = query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+
+    let node_client = node.client().await?;
+
+    // Members of node groups only see events of their groups' nodes
+    let scope = NodeGroupService::new(&pool)
+        .node_scope_for_claims(&claims)
+        .await?;
+    let filters = EventFilters {
+        node_ids: scope.node_ids().map(<[String]>::to_vec),
+        search: Some(q.clone()),
+        limit: Some(limit as i64),
+        ..Default::default()
+    };
+
+    let event_service = EventService::new(&pool);
+    let (channels, payments, invoices, events) = tokio::join!(
+        node_client.list_channels(),
+        node_client.list_payments(),
+        node_client.list_invoices(),
+        event_service.get_events_for_account(&pool, claims.account_id(), Some(filters)),
+    );
+
+    // A node that cannot answer leaves its groups empty rather than failing the search
+    let channels = channels.unwrap_or_else(|e| {
+        tracing::warn!("Search could not list channels: {e}");
+        Vec::new()
+    });
+    let payments = payments.unwrap_or_else(|e| {
+        tracing::warn!("Search could not list payments: {e}");
+        Vec::new()
+    });
+    let invoices = invoices.unwrap_or_else(|e| {
+        tracing::warn!("Search could not list invoices: {e}");
+        Vec::new()
+    });
+
+    let results = SearchResults {
+        channels: channels
+            .into_iter()
+            .filter(|channel| channel_matches(channel, &q))
+            .take(limit)
+            .collect(),
+        payments: payments
+            .into_iter()
+            .filter(|payment| payment_matches(payment, &q))
+            .take(limit)
+            .collect(),
+        invoices: invoices
+            .into_iter()
+            .filter(|invoice| invoice_matches(invoice, &q))
+            .take(limit)
+            .collect(),
+        events: events?,
+        query: q,
+    };
+
+    Ok(Json(ApiResponse::success(
+        results,
+        "Search results retrieved successfully",
+    )))
+}
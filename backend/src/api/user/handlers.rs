@@ -3,14 +3,14 @@
 //! These functions process requests for user data, interact with the database
 //! or relevant services, and return user-specific information.
 
-use crate::api::common::ApiResponse;
-use crate::database::models::User;
+use crate::api::common::{ApiError, ApiResponse};
+use crate::database::models::{AuditAction, User};
+use crate::services::activity_feed::ActivityService;
+use crate::services::session_service::{SessionInfo, SessionService};
 use crate::services::user_service::UserService;
 use crate::utils::jwt::Claims;
-use axum::{
-    extract::{Extension, Json, Path},
-    http::StatusCode,
-};
+use axum::extract::{Extension, Json, Path};
+use serde_json::{Value, json};
 use sqlx::SqlitePool;
 
 /// Retrieves a user by its ID.
@@ -19,7 +19,7 @@ pub async fn get_user_by_id(
     Extension(claims): Extension<Claims>,
     Extension(pool): Extension<SqlitePool>,
     Path(id): Path<String>,
-) -> Result<Json<ApiResponse<User>>, (StatusCode, String)> {
+) -> Result<Json<ApiResponse<User>>, ApiError> {
     let user_id = claims.sub.as_str().to_string();
 
     tracing::info!("Getting user by ID: {} for user: {}", id, user_id);
@@ -30,12 +30,7 @@ pub async fn get_user_by_id(
         .await
         .map_err(|e| {
             tracing::error!("User not found for ID {}: {}", id, e);
-            let error_response =
-                ApiResponse::<()>::error("User not found".to_string(), "user_not_found", None);
-            (
-                StatusCode::NOT_FOUND,
-                serde_json::to_string(&error_response).unwrap(),
-            )
+            ApiError::not_found("user_not_found", "User not found")
         })?;
 
     tracing::info!("User found: {}", user.id);
@@ -51,12 +46,12 @@ pub async fn change_user_role_access_level(
     Extension(claims): Extension<Claims>,
     Extension(pool): Extension<SqlitePool>,
     Path(id): Path<String>,
-) -> Result<Json<ApiResponse<User>>, (StatusCode, String)> {
+) -> Result<Json<ApiResponse<User>>, ApiError> {
     let user_role = claims.role.as_str().to_string();
     if user_role != "Admin" {
-        return Err((
-            StatusCode::FORBIDDEN,
-            "Only Admin users can change role access levels".to_string(),
+        return Err(ApiError::forbidden(
+            "forbidden",
+            "Only Admin users can change role access levels",
         ));
     }
 
@@ -74,19 +69,69 @@ pub async fn change_user_role_access_level(
         .await
         .map_err(|e| {
             tracing::error!("Failed to change role access level for ID {}: {}", id, e);
-            let error_response = ApiResponse::<()>::error(
-                "Failed to change role access level".to_string(),
-                "role_change_failed",
-                None,
-            );
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                serde_json::to_string(&error_response).unwrap(),
-            )
+            ApiError::internal("role_change_failed", "Failed to change role access level")
         })?;
+    ActivityService::new(&pool)
+        .record(
+            &claims,
+            AuditAction::AccessLevelChanged,
+            Some(&user.username),
+            Some(json!({ "user_id": user.id, "role_access_level": user.role_access_level })),
+        )
+        .await?;
 
     Ok(Json(ApiResponse::success(
         user,
         "User role access level changed successfully",
     )))
 }
+
+/// Lists the caller's active login sessions.
+#[axum::debug_handler]
+pub async fn list_sessions(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<SqlitePool>,
+) -> Result<Json<ApiResponse<Vec<SessionInfo>>>, ApiError> {
+    let sessions = SessionService::new(&pool).list_sessions(&claims).await?;
+
+    Ok(Json(ApiResponse::success(
+        sessions,
+        "Sessions retrieved successfully",
+    )))
+}
+
+/// Revokes one of the caller's sessions, logging that device out.
+#[axum::debug_handler]
+pub async fn revoke_session(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<SqlitePool>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<Value>>, ApiError> {
+    SessionService::new(&pool)
+        .revoke_session(&claims.sub, &id)
+        .await?;
+
+    tracing::info!("User {} revoked session {}", claims.sub, id);
+    Ok(Json(ApiResponse::success(
+        json!({ "revoked": true }),
+        "Session revoked successfully",
+    )))
+}
+
+/// Revokes all of the caller's sessions, including the current one, so every
+/// token issued to them stops working.
+#[axum::debug_handler]
+pub async fn revoke_all_sessions(
+    Extension(claims): Extension<Claims>,
+    Extension(pool): Extension<SqlitePool>,
+) -> Result<Json<ApiResponse<Value>>, ApiError> {
+    let revoked = SessionService::new(&pool)
+        .revoke_all_sessions(&claims.sub)
+        .await?;
+
+    tracing::info!("User {} logged out of {} sessions", claims.sub, revoked);
+    Ok(Json(ApiResponse::success(
+        json!({ "revoked": revoked }),
+        "Logged out of all sessions",
+    )))
+}
@@ -3,11 +3,14 @@
 //! These routes provide endpoints for accessing and updating user-specific
 //! data beyond authentication credentials.
 
-use super::handlers::{change_user_role_access_level, get_user_by_id};
+use super::handlers::{
+    change_user_role_access_level, get_user_by_id, list_sessions, revoke_all_sessions,
+    revoke_session,
+};
 use crate::auth::middleware::jwt_auth;
 use axum::{
     Router, middleware,
-    routing::{get, post},
+    routing::{delete, get, post},
 };
 
 pub async fn user_router() -> Router {
@@ -20,4 +23,14 @@ pub async fn user_router() -> Router {
             "/change-user-role-access-level/{id}",
             post(change_user_role_access_level).layer(middleware::from_fn(jwt_auth)),
         )
+        .route(
+            "/sessions",
+            get(list_sessions)
+                .delete(revoke_all_sessions)
+                .layer(middleware::from_fn(jwt_auth)),
+        )
+        .route(
+            "/sessions/{id}",
+            delete(revoke_session).layer(middleware::from_fn(jwt_auth)),
+        )
 }
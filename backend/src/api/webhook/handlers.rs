@@ -0,0 +1,126 @@
+//! Handler functions for webhook delivery log API endpoints.
+
+use crate::api::common::{ApiError, ApiResponse};
+use crate::database::models::{DeliveryStatus, WebhookDelivery};
+use crate::errors::ServiceError;
+use crate::repositories::webhook_delivery_repository::WebhookDeliveryRepository;
+use crate::services::node_group_service::NodeGroupService;
+use crate::utils::handlers_common::require_write_access;
+use crate::utils::jwt::Claims;
+use axum::{
+    Json,
+    extract::{Extension, Path, Query},
+};
+use serde::Deserialize;
+use sqlx::SqlitePool;
+
+/// Default number of deliveries returned.
+const DEFAULT_DELIVERIES_LIMIT: u32 = 50;
+/// Largest number of deliveries a client may request.
+const MAX_DELIVERIES_LIMIT: u32 = 200;
+
+/// Query parameters for listing deliveries.
+#[derive(Debug, Deserialize)]
+pub struct DeliveryListQuery {
+    /// `pending`, `delivered` or `failed`
+    pub status: Option<String>,
+    /// e.g. `invoice.settled`
+    pub topic: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// Lists the webhook deliveries about nodes visible to the caller, newest first.
+#[axum::debug_handler]
+pub async fn list_deliveries(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<DeliveryListQuery>,
+) -> Result<Json<ApiResponse<Vec<WebhookDelivery>>>, ApiError> {
+    let status = query
+        .status
+        .as_deref()
+        .map(str::parse::<DeliveryStatus>)
+        .transpose()
+        .map_err(|e| ApiError::bad_request("invalid_status", e))?;
+    let scope = NodeGroupService::new(&pool)
+        .node_scope_for_claims(&claims)
+        .await?;
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_DELIVERIES_LIMIT)
+        .clamp(1, MAX_DELIVERIES_LIMIT);
+
+    let deliveries = WebhookDeliveryRepository::new(&pool)
+        .get_deliveries_by_account_id(
+            &claims.account_id,
+            scope.node_ids(),
+            status,
+            query.topic.as_deref(),
+            limit.into(),
+            query.offset.unwrap_or(0).into(),
+        )
+        .await
+        .map_err(ServiceError::from)?;
+
+    Ok(Json(ApiResponse::success(
+        deliveries,
+        "Deliveries retrieved successfully",
+    )))
+}
+
+async fn find_delivery(
+    pool: &SqlitePool,
+    claims: &Claims,
+    id: &str,
+) -> Result<WebhookDelivery, ApiError> {
+    let delivery = WebhookDeliveryRepository::new(pool)
+        .get_delivery(&claims.account_id, id)
+        .await
+        .map_err(ServiceError::from)?;
+    let scope = NodeGroupService::new(pool)
+        .node_scope_for_claims(claims)
+        .await?;
+    delivery
+        .filter(|delivery| scope.allows(&delivery.node_id))
+        .ok_or_else(|| {
+            ApiError::not_found("delivery_not_found", format!("Delivery {id} not found"))
+        })
+}
+
+/// Retrieves one delivery with its payload and last attempt.
+#[axum::debug_handler]
+pub async fn get_delivery(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<WebhookDelivery>>, ApiError> {
+    let delivery = find_delivery(&pool, &claims, &id).await?;
+
+    Ok(Json(ApiResponse::success(
+        delivery,
+        "Delivery retrieved successfully",
+    )))
+}
+
+/// Queues a delivery again with fresh attempts and the same idempotency key.
+#[axum::debug_handler]
+pub async fn redeliver(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Claims>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<WebhookDelivery>>, ApiError> {
+    require_write_access(&claims, "redeliver webhooks")?;
+
+    find_delivery(&pool, &claims, &id).await?;
+    WebhookDeliveryRepository::new(&pool)
+        .requeue(&claims.account_id, &id)
+        .await
+        .map_err(ServiceError::from)?;
+    let delivery = find_delivery(&pool, &claims, &id).await?;
+
+    Ok(Json(ApiResponse::success(
+        delivery,
+        "Delivery queued again",
+    )))
+}
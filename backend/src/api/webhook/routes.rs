@@ -0,0 +1,16 @@
+//! Defines the HTTP routes for the webhook delivery log.
+
+use super::handlers::{get_delivery, list_deliveries, redeliver};
+use crate::auth::middleware::jwt_auth;
+use axum::{
+    Router, middleware,
+    routing::{get, post},
+};
+
+pub async fn webhook_router() -> Router {
+    Router::new()
+        .route("/deliveries", get(list_deliveries))
+        .route("/deliveries/{id}", get(get_delivery))
+        .route("/deliveries/{id}/redeliver", post(redeliver))
+        .layer(middleware::from_fn(jwt_auth))
+}
@@ -4,35 +4,66 @@
 //! token refresh), parse request data, validate input, and interact with the
 //! `auth::service` for core business logic.
 
-use crate::api::common::{ApiResponse, service_error_to_http};
+use crate::api::common::{ApiError, ApiResponse};
 use crate::auth::models::*;
 use crate::auth::service::AuthService;
+use crate::errors::ServiceError;
 use crate::repositories::credential_repository::CredentialRepository;
+use crate::services::session_service::SessionService;
 use crate::utils::jwt::Claims;
 use axum::{
-    extract::{Extension, Json},
-    http::StatusCode,
+    extract::{ConnectInfo, Extension, Json},
+    http::{HeaderMap, header::USER_AGENT},
     response::Json as ResponseJson,
 };
 use sqlx::SqlitePool;
+use std::net::SocketAddr;
+
+/// Reads the device details recorded on a new session.
+///
+/// The first `X-Forwarded-For` address is preferred over the peer address so
+/// deployments behind a reverse proxy record the client rather than the proxy.
+fn client_info(headers: &HeaderMap, peer: Option<SocketAddr>) -> ClientInfo {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+
+    ClientInfo {
+        user_agent: header(USER_AGENT.as_str()).map(str::to_string),
+        ip_address: header("x-forwarded-for")
+            .and_then(|value| value.split(',').next())
+            .map(|value| value.trim().to_string())
+            .or_else(|| peer.map(|peer| peer.ip().to_string())),
+    }
+}
 
 /// Handle user login request
 #[axum::debug_handler]
 pub async fn login(
     Extension(pool): Extension<SqlitePool>,
+    connect_info: Option<Extension<ConnectInfo<SocketAddr>>>,
+    headers: HeaderMap,
     Json(payload): Json<LoginRequest>,
-) -> Result<ResponseJson<ApiResponse<LoginResponse>>, (StatusCode, String)> {
+) -> Result<ResponseJson<ApiResponse<LoginResponse>>, ApiError> {
     let auth_service = match AuthService::new(&pool) {
         Ok(service) => service,
-        Err(error) => return Err(service_error_to_http(error)),
+        Err(error) => return Err(error.into()),
     };
 
-    match auth_service.login(payload).await {
+    let peer = connect_info.map(|Extension(ConnectInfo(addr))| addr);
+    match auth_service
+        .login(payload, client_info(&headers, peer))
+        .await
+    {
         Ok(response) => Ok(ResponseJson(ApiResponse::success(
             response,
             "Login successful",
         ))),
-        Err(error) => Err(service_error_to_http(error)),
+        Err(error) => Err(error.into()),
     }
 }
 
@@ -41,10 +72,10 @@ pub async fn login(
 pub async fn refresh_token(
     Extension(pool): Extension<SqlitePool>,
     Json(payload): Json<RefreshTokenRequest>,
-) -> Result<ResponseJson<ApiResponse<RefreshTokenResponse>>, (StatusCode, String)> {
+) -> Result<ResponseJson<ApiResponse<RefreshTokenResponse>>, ApiError> {
     let auth_service = match AuthService::new(&pool) {
         Ok(service) => service,
-        Err(error) => return Err(service_error_to_http(error)),
+        Err(error) => return Err(error.into()),
     };
 
     match auth_service.refresh_token(payload).await {
@@ -52,14 +83,32 @@ pub async fn refresh_token(
             response,
             "Token refreshed successfully",
         ))),
-        Err(error) => Err(service_error_to_http(error)),
+        Err(error) => Err(error.into()),
     }
 }
 
-/// Handle logout request (client-side token invalidation)
+/// Handle logout request, revoking the session of the presented token
 #[axum::debug_handler]
-pub async fn logout() -> Result<ResponseJson<ApiResponse<serde_json::Value>>, (StatusCode, String)>
-{
+pub async fn logout(
+    Extension(pool): Extension<SqlitePool>,
+    Extension(claims): Extension<Option<Claims>>,
+) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, ApiError> {
+    if let Some(Claims {
+        sub,
+        sid: Some(session_id),
+        ..
+    }) = claims
+    {
+        // Already revoked or expired sessions need no revoking
+        match SessionService::new(&pool)
+            .revoke_session(&sub, &session_id)
+            .await
+        {
+            Ok(()) | Err(ServiceError::NotFound { .. }) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
     Ok(ResponseJson(ApiResponse::success(
         serde_json::json!({ "logged_out": true }),
         "Logged out successfully",
@@ -71,7 +120,7 @@ pub async fn logout() -> Result<ResponseJson<ApiResponse<serde_json::Value>>, (S
 pub async fn me(
     Extension(pool): Extension<SqlitePool>,
     Extension(claims): Extension<Claims>,
-) -> Result<ResponseJson<ApiResponse<UserInfo>>, (StatusCode, String)> {
+) -> Result<ResponseJson<ApiResponse<UserInfo>>, ApiError> {
     // Get user information from database using claims
     let user = match sqlx::query!(
         r#"
@@ -88,18 +137,10 @@ pub async fn me(
     {
         Ok(Some(user)) => user,
         Ok(None) => {
-            let error_response = ApiResponse::<()>::error("User not found", "not_found", None);
-            return Err((
-                StatusCode::NOT_FOUND,
-                serde_json::to_string(&error_response).unwrap(),
-            ));
+            return Err(ApiError::not_found("not_found", "User not found"));
         }
         Err(_e) => {
-            let error_response = ApiResponse::<()>::error("Database error", "database_error", None);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                serde_json::to_string(&error_response).unwrap(),
-            ));
+            return Err(ApiError::internal("database_error", "Database error"));
         }
     };
 
@@ -124,36 +165,28 @@ pub async fn me(
 pub async fn revoke_node_credentials(
     Extension(pool): Extension<SqlitePool>,
     Extension(claims): Extension<Claims>,
-) -> Result<ResponseJson<ApiResponse<RevokeNodeCredentialsResponse>>, (StatusCode, String)> {
+) -> Result<ResponseJson<ApiResponse<RevokeNodeCredentialsResponse>>, ApiError> {
     let credential_repo = CredentialRepository::new(&pool);
 
     // Check if user has credentials to revoke
     let credential = match credential_repo.get_credential_by_user_id(&claims.sub).await {
         Ok(Some(cred)) => cred,
         Ok(None) => {
-            let error_response =
-                ApiResponse::<()>::error("No node credentials found", "not_found", None);
-            return Err((
-                StatusCode::NOT_FOUND,
-                serde_json::to_string(&error_response).unwrap(),
+            return Err(ApiError::not_found(
+                "not_found",
+                "No node credentials found",
             ));
         }
         Err(_e) => {
-            let error_response = ApiResponse::<()>::error("Database error", "database_error", None);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                serde_json::to_string(&error_response).unwrap(),
-            ));
+            return Err(ApiError::internal("database_error", "Database error"));
         }
     };
 
     // Soft delete the credential
     if let Err(_e) = credential_repo.delete_credential(&credential.id).await {
-        let error_response =
-            ApiResponse::<()>::error("Failed to revoke credentials", "database_error", None);
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            serde_json::to_string(&error_response).unwrap(),
+        return Err(ApiError::internal(
+            "database_error",
+            "Failed to revoke credentials",
         ));
     }
 
@@ -161,11 +194,9 @@ pub async fn revoke_node_credentials(
     let jwt_utils = match crate::utils::jwt::JwtUtils::new() {
         Ok(utils) => utils,
         Err(_e) => {
-            let error_response =
-                ApiResponse::<()>::error("JWT configuration error", "configuration_error", None);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                serde_json::to_string(&error_response).unwrap(),
+            return Err(ApiError::internal(
+                "configuration_error",
+                "JWT configuration error",
             ));
         }
     };
@@ -176,15 +207,11 @@ pub async fn revoke_node_credentials(
         claims.role,
         claims.role_access_level,
         None, // No node credentials
+        claims.sid,
     ) {
         Ok(token) => token,
         Err(_e) => {
-            let error_response =
-                ApiResponse::<()>::error("Token generation failed", "token_error", None);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                serde_json::to_string(&error_response).unwrap(),
-            ));
+            return Err(ApiError::internal("token_error", "Token generation failed"));
         }
     };
 
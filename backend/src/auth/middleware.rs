@@ -4,16 +4,25 @@
 //! and enforcing user permissions across the API endpoints.
 
 use crate::api::common::ApiResponse;
-use crate::utils::jwt::JwtUtils;
+use crate::middleware::idempotency::{idempotent_request, not_handled};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::services::node_group_service::NodeGroupService;
+use crate::services::quota_service::QuotaService;
+use crate::services::session_service::SessionService;
+use crate::utils::handlers_common::SelectedNode;
+use crate::utils::jwt::{JwtUtils, NodeCredentials};
 use axum::response::IntoResponse;
 use axum::{
-    extract::Request,
-    http::{StatusCode, header::AUTHORIZATION},
+    extract::{Query, Request},
+    http::{Extensions, StatusCode, header::AUTHORIZATION},
     middleware::Next,
     response::{Json, Response},
 };
 
 /// JWT authentication middleware
+///
+/// Also rejects tokens whose login session has been revoked, and handles
+/// `Idempotency-Key` headers on POST requests.
 pub async fn jwt_auth(mut request: Request, next: Next) -> Result<Response, Response> {
     // Extract Authorization header
     let auth_header = request
@@ -55,21 +64,62 @@ pub async fn jwt_auth(mut request: Request, next: Next) -> Result<Response, Resp
         }
     };
 
-    match jwt_utils.validate_token(token) {
-        Ok(claims) => {
-            // Add claims to request extensions for use in handlers
-            request.extensions_mut().insert(claims);
-            Ok(next.run(request).await)
-        }
+    let claims = match jwt_utils.validate_token(token) {
+        Ok(claims) => claims,
         Err(e) => {
             let error_response = ApiResponse::<()>::error(
                 format!("Invalid or expired token: {e}"),
                 "authentication_error",
                 None,
             );
-            Err((StatusCode::UNAUTHORIZED, Json(error_response)).into_response())
+            return Err((StatusCode::UNAUTHORIZED, Json(error_response)).into_response());
         }
+    };
+
+    let Some(pool) = request.extensions().get::<sqlx::SqlitePool>().cloned() else {
+        let error_response =
+            ApiResponse::<()>::error("Internal server error", "server_error", None);
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(error_response)).into_response());
+    };
+
+    let active = SessionService::new(&pool)
+        .is_session_active(&claims)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to check session: {}", e);
+            let error_response =
+                ApiResponse::<()>::error("Internal server error", "server_error", None);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response)).into_response()
+        })?;
+    if !active {
+        let error_response = ApiResponse::<()>::error(
+            "Session has been revoked. Please log in again.",
+            "authentication_error",
+            None,
+        );
+        return Err((StatusCode::UNAUTHORIZED, Json(error_response)).into_response());
     }
+
+    // Metering must not take the API down, so a failed count lets the request through
+    match QuotaService::new(&pool)
+        .meter_api_request(&claims.account_id)
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            let error_response = ApiResponse::<()>::error(
+                "Daily API request quota reached for this account. Check GET /api/account/usage for when it resets.",
+                "quota_exceeded",
+                None,
+            );
+            return Err((StatusCode::TOO_MANY_REQUESTS, Json(error_response)).into_response());
+        }
+        Err(e) => tracing::error!("Failed to meter API request: {}", e),
+    }
+
+    // Add claims to request extensions for use in handlers
+    request.extensions_mut().insert(claims);
+    Ok(idempotent_request(request, next).await)
 }
 
 /// Optional JWT authentication middleware (doesn't fail if no token)
@@ -79,8 +129,7 @@ pub async fn optional_jwt_auth(mut request: Request, next: Next) -> Result<Respo
         .get(AUTHORIZATION)
         .and_then(|header| header.to_str().ok())
     {
-        if auth_header.starts_with("Bearer ") {
-            let token = &auth_header[7..];
+        if let Some(token) = auth_header.strip_prefix("Bearer ") {
             let jwt_utils = match JwtUtils::new() {
                 Ok(utils) => utils,
                 Err(_) => {
@@ -92,10 +141,7 @@ pub async fn optional_jwt_auth(mut request: Request, next: Next) -> Result<Respo
                 }
             };
 
-            match jwt_utils.validate_token(token) {
-                Ok(claims) => Some(claims),
-                Err(_) => None,
-            }
+            jwt_utils.validate_token(token).ok()
         } else {
             None
         }
@@ -103,34 +149,318 @@ pub async fn optional_jwt_auth(mut request: Request, next: Next) -> Result<Respo
         None
     };
 
+    // Tokens of revoked sessions count as no token at all
+    let claims = match (claims, request.extensions().get::<sqlx::SqlitePool>()) {
+        (Some(claims), Some(pool)) => SessionService::new(pool)
+            .is_session_active(&claims)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::error!("Failed to check session: {}", e);
+                false
+            })
+            .then_some(claims),
+        _ => None,
+    };
+
     // Always insert the Option<Claims>, even if it's None
     request.extensions_mut().insert(claims);
     Ok(next.run(request).await)
 }
 
-/// Node credentials required middleware
-pub async fn node_credentials_required(request: Request, next: Next) -> Result<Response, Response> {
-    // Get claims from request extensions
-    let claims = request.extensions().get::<crate::utils::jwt::Claims>();
+/// Header naming the node a request is for.
+pub const NODE_ID_HEADER: &str = "x-node-id";
 
-    let claims = match claims {
-        Some(claims) => claims,
-        None => {
-            let error_response =
-                ApiResponse::<()>::error("Authentication required", "authentication_error", None);
-            return Err((StatusCode::UNAUTHORIZED, Json(error_response)).into_response());
-        }
+#[derive(serde::Deserialize)]
+struct NodeSelectionQuery {
+    node_id: Option<String>,
+}
+
+/// Resolves the node a request operates on and adds it to the extensions as a
+/// `SelectedNode`.
+///
+/// The node is taken from the `X-Node-Id` header, the `node_id` query parameter
+/// or, when neither is given, the credentials in the token. A named node must
+/// have credentials stored in the caller's account; those replace the token's
+/// credentials in the claims, so later layers check access to the selected
+/// node. Must be layered after `jwt_auth`.
+pub async fn node_selection(request: Request, next: Next) -> Result<Response, Response> {
+    let request = select_node(request).await.map_err(not_handled)?;
+    Ok(next.run(request).await)
+}
+
+async fn select_node(mut request: Request) -> Result<Request, Response> {
+    let (Some(mut claims), Some(pool)) = (
+        request
+            .extensions()
+            .get::<crate::utils::jwt::Claims>()
+            .cloned(),
+        request.extensions().get::<sqlx::SqlitePool>().cloned(),
+    ) else {
+        let error_response =
+            ApiResponse::<()>::error("Authentication required", "authentication_error", None);
+        return Err((StatusCode::UNAUTHORIZED, Json(error_response)).into_response());
     };
 
-    // Check if user has node credentials
-    if !claims.has_node_credentials() {
+    let requested = request
+        .headers()
+        .get(NODE_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| {
+            Query::<NodeSelectionQuery>::try_from_uri(request.uri())
+                .ok()
+                .and_then(|query| query.0.node_id)
+        })
+        .map(|node_id| node_id.trim().to_lowercase())
+        .filter(|node_id| !node_id.is_empty());
+
+    if let Some(node_id) = requested {
+        let credentials = CredentialRepository::new(&pool)
+            .get_node_credentials(Some(&claims.account_id), Some(&claims.sub))
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to load node credentials: {}", e);
+                let error_response =
+                    ApiResponse::<()>::error("Internal server error", "server_error", None);
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response)).into_response()
+            })?;
+
+        let Some(credential) = credentials
+            .iter()
+            .find(|credential| credential.node_id == node_id)
+        else {
+            let error_response = ApiResponse::<()>::error(
+                format!("No credentials for node {node_id} are stored in this account."),
+                "node_not_found",
+                None,
+            );
+            return Err((StatusCode::NOT_FOUND, Json(error_response)).into_response());
+        };
+
+        claims.node_credentials = Some(NodeCredentials::from(credential));
+    }
+
+    let Some(credentials) = claims.node_credentials.clone() else {
         let error_response = ApiResponse::<()>::error(
-            "Node credentials required. Please authenticate your node first.",
+            "Node credentials required. Select a node with the X-Node-Id header or authenticate your node first.",
             "node_credentials_required",
             None,
         );
         return Err((StatusCode::BAD_REQUEST, Json(error_response)).into_response());
+    };
+
+    request
+        .extensions_mut()
+        .insert(SelectedNode::new(credentials));
+    request.extensions_mut().insert(claims);
+    Ok(request)
+}
+
+/// Rejects requests that would change node state when the stored credentials are read-only.
+///
+/// Must be layered after `jwt_auth` so the claims are available.
+pub async fn node_write_access_required(
+    request: Request,
+    next: Next,
+) -> Result<Response, Response> {
+    let read_only = request
+        .extensions()
+        .get::<crate::utils::jwt::Claims>()
+        .and_then(|claims| claims.node_credentials.as_ref())
+        .is_some_and(|credentials| credentials.read_only);
+
+    if read_only {
+        let error_response = ApiResponse::<()>::error(
+            "The connected node credentials are read-only. Provide a macaroon or rune with write permissions to use this endpoint.",
+            "read_only_node",
+            None,
+        );
+        return Err((StatusCode::FORBIDDEN, Json(error_response)).into_response());
     }
 
     Ok(next.run(request).await)
 }
+
+/// Rejects requests for a node outside the user's node groups.
+///
+/// Must be layered after `jwt_auth` so the claims are available. Tokens are checked
+/// on every request so group changes apply without waiting for a new token.
+pub async fn node_group_access_required(
+    request: Request,
+    next: Next,
+) -> Result<Response, Response> {
+    check_node_group_access(request.extensions())
+        .await
+        .map_err(not_handled)?;
+    Ok(next.run(request).await)
+}
+
+async fn check_node_group_access(extensions: &Extensions) -> Result<(), Response> {
+    let (Some(claims), Some(pool)) = (
+        extensions.get::<crate::utils::jwt::Claims>().cloned(),
+        extensions.get::<sqlx::SqlitePool>().cloned(),
+    ) else {
+        let error_response =
+            ApiResponse::<()>::error("Authentication required", "authentication_error", None);
+        return Err((StatusCode::UNAUTHORIZED, Json(error_response)).into_response());
+    };
+
+    let Some(node_id) = claims
+        .node_credentials
+        .as_ref()
+        .map(|credentials| credentials.node_id.as_str())
+    else {
+        return Ok(());
+    };
+
+    let scope = NodeGroupService::new(&pool)
+        .node_scope_for_claims(&claims)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to resolve node groups: {}", e);
+            let error_response =
+                ApiResponse::<()>::error("Internal server error", "server_error", None);
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response)).into_response()
+        })?;
+
+    if !scope.allows(node_id) {
+        let error_response = ApiResponse::<()>::error(
+            "This node is not in any of your node groups.",
+            "node_access_denied",
+            None,
+        );
+        return Err((StatusCode::FORBIDDEN, Json(error_response)).into_response());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::CreateNodeGroup;
+    use crate::database::models::RoleAccessLevel;
+    use crate::repositories::node_group_repository::NodeGroupRepository;
+    use crate::utils::jwt::Claims;
+    use axum::{
+        Extension, Router,
+        body::Body,
+        middleware,
+        routing::{get, post},
+    };
+    use tower::ServiceExt;
+
+    fn claims(read_only: bool) -> Claims {
+        Claims {
+            sub: "user".to_string(),
+            account_id: "account".to_string(),
+            role: "Admin".to_string(),
+            role_access_level: RoleAccessLevel::ReadWrite,
+            node_credentials: Some(NodeCredentials {
+                node_id: "node".to_string(),
+                node_alias: "gaze".to_string(),
+                node_type: "cln".to_string(),
+                macaroon: String::new(),
+                tls_cert: String::new(),
+                client_cert: None,
+                client_key: None,
+                ca_cert: None,
+                address: "127.0.0.1:9735".to_string(),
+                proxy: None,
+                rune: Some("rune".to_string()),
+                transport: None,
+                read_only,
+            }),
+            exp: 0,
+            iat: 0,
+            sid: None,
+        }
+    }
+
+    async fn write_to_node(read_only: bool) -> StatusCode {
+        Router::new()
+            .route(
+                "/",
+                post(|| async { "written" }).layer(middleware::from_fn(node_write_access_required)),
+            )
+            .layer(Extension(claims(read_only)))
+            .oneshot(Request::post("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn read_only_credentials_cannot_write_to_the_node() {
+        assert_eq!(write_to_node(true).await, StatusCode::FORBIDDEN);
+        assert_eq!(write_to_node(false).await, StatusCode::OK);
+    }
+
+    async fn grouped_account() -> sqlx::SqlitePool {
+        let pool = crate::database::test_pool().await;
+        sqlx::query(
+            r#"
+            INSERT INTO accounts (id, name) VALUES ('account', 'Account');
+            INSERT INTO users (id, account_id, username, password_hash, email, role_id)
+            VALUES
+                ('member', 'account', 'member', '', 'member@example.com',
+                 '01932f4e-8b2b-7a3c-9d5f-2a3b4c5d6e7f'),
+                ('outsider', 'account', 'outsider', '', 'outsider@example.com',
+                 '01932f4e-8b2b-7a3c-9d5f-2a3b4c5d6e7f');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let repo = NodeGroupRepository::new(&pool);
+        repo.create_group(CreateNodeGroup {
+            id: "group".to_string(),
+            account_id: "account".to_string(),
+            name: "Operators".to_string(),
+            description: None,
+        })
+        .await
+        .unwrap();
+        repo.add_node("group", "node").await.unwrap();
+        repo.add_member("group", "member").await.unwrap();
+        pool
+    }
+
+    async fn read_node_as(pool: &sqlx::SqlitePool, user: &str, role: &str) -> StatusCode {
+        let claims = Claims {
+            sub: user.to_string(),
+            role: role.to_string(),
+            ..claims(false)
+        };
+        Router::new()
+            .route(
+                "/",
+                get(|| async { "node" }).layer(middleware::from_fn(node_group_access_required)),
+            )
+            .layer(Extension(claims))
+            .layer(Extension(pool.clone()))
+            .oneshot(Request::get("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn only_group_members_reach_grouped_nodes() {
+        let pool = grouped_account().await;
+
+        assert_eq!(
+            read_node_as(&pool, "outsider", "Member").await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            read_node_as(&pool, "member", "Member").await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            read_node_as(&pool, "outsider", "Admin").await,
+            StatusCode::OK
+        );
+    }
+}
@@ -17,6 +17,13 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+/// Details of the client logging in, recorded on the session it opens
+#[derive(Debug, Default, Clone)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
 /// Login response containing tokens and user info
 #[derive(Debug, Serialize)]
 pub struct LoginResponse {
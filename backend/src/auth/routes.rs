@@ -15,7 +15,10 @@ pub fn auth_router() -> Router {
     Router::new()
         .route("/login", post(login))
         .route("/refresh", post(refresh_token))
-        .route("/logout", post(logout))
+        .route(
+            "/logout",
+            post(logout).layer(middleware::from_fn(optional_jwt_auth)),
+        )
         .route("/me", get(me).layer(middleware::from_fn(jwt_auth)))
         .route(
             "/revoke-node-credentials",
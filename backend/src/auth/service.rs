@@ -1,10 +1,12 @@
 //! Core business logic for the authentication system.
 
 use crate::auth::models::*;
-use crate::config::Config;
+use crate::config::{self, AppConfig};
 use crate::errors::{ServiceError, ServiceResult};
 use crate::repositories::account_repository::AccountRepository;
 use crate::repositories::credential_repository::CredentialRepository;
+use crate::services::node_group_service::NodeGroupService;
+use crate::services::session_service::SessionService;
 use crate::services::user_service::UserService;
 use crate::utils::jwt::{JwtUtils, NodeCredentials};
 use sqlx::SqlitePool;
@@ -15,7 +17,7 @@ pub struct AuthService<'a> {
     pool: &'a SqlitePool,
     jwt_utils: JwtUtils,
     user_service: UserService<'a>,
-    config: Config,
+    config: AppConfig,
 }
 
 impl<'a> AuthService<'a> {
@@ -23,7 +25,7 @@ impl<'a> AuthService<'a> {
     pub fn new(pool: &'a SqlitePool) -> ServiceResult<Self> {
         let jwt_utils = JwtUtils::new()?;
         let user_service = UserService::new(pool);
-        let config = Config::from_env()?;
+        let config = config::shared()?;
 
         Ok(AuthService {
             pool,
@@ -34,7 +36,11 @@ impl<'a> AuthService<'a> {
     }
 
     /// Authenticate user and generate JWT tokens with node credentials if available
-    pub async fn login(&self, login_request: LoginRequest) -> ServiceResult<LoginResponse> {
+    pub async fn login(
+        &self,
+        login_request: LoginRequest,
+        client: ClientInfo,
+    ) -> ServiceResult<LoginResponse> {
         // Validate input
         if let Err(validation_errors) = login_request.validate() {
             let error_messages: Vec<String> = validation_errors
@@ -78,28 +84,43 @@ impl<'a> AuthService<'a> {
         let user_role_id = user.role_id.clone();
         let role_access_level = user.role_access_level.clone();
 
-        // Check for existing node credentials and convert them to JWT format
-        let credential_repo = CredentialRepository::new(self.pool);
-        let node_credentials =
-            if let Some(credential) = credential_repo.get_credential_by_account_id(&account_id).await? {
-                Some(NodeCredentials {
-                    node_id: credential.node_id,
-                    node_alias: credential.node_alias,
-                    node_type: credential.node_type.unwrap_or_else(|| "lnd".to_string()),
-                    macaroon: credential.macaroon,
-                    tls_cert: credential.tls_cert,
-                    client_cert: credential.client_cert,
-                    client_key: credential.client_key,
-                    ca_cert: credential.ca_cert,
-                    address: credential.address,
-                })
-            } else {
-                None
-            };
-
         // Get user role name
         let role_name = self.get_user_role_name(&user_role_id).await?;
 
+        // Select the first of the account's nodes the user's node groups allow
+        let scope = NodeGroupService::new(self.pool)
+            .node_scope(&account_id, &user_id, &role_name)
+            .await?;
+        let credential_repo = CredentialRepository::new(self.pool);
+        let node_credentials = if let Some(credential) = credential_repo
+            .get_node_credentials(Some(&account_id), Some(&user_id))
+            .await?
+            .into_iter()
+            .find(|credential| scope.allows(&credential.node_id))
+        {
+            Some(NodeCredentials {
+                node_id: credential.node_id,
+                node_alias: credential.node_alias,
+                node_type: credential.node_type.unwrap_or_else(|| "lnd".to_string()),
+                macaroon: credential.macaroon,
+                tls_cert: credential.tls_cert,
+                client_cert: credential.client_cert,
+                client_key: credential.client_key,
+                ca_cert: credential.ca_cert,
+                address: credential.address,
+                proxy: credential.proxy,
+                rune: credential.rune,
+                transport: credential.transport,
+                read_only: credential.read_only,
+            })
+        } else {
+            None
+        };
+
+        let session = SessionService::new(self.pool)
+            .create_session(&user_id, &account_id, client.user_agent, client.ip_address)
+            .await?;
+
         // Generate tokens with node credentials if available
         let access_token = self.jwt_utils.generate_token(
             user_id.clone(),
@@ -107,11 +128,14 @@ impl<'a> AuthService<'a> {
             role_name.clone(),
             role_access_level.clone(),
             node_credentials,
+            Some(session.id.clone()),
         )?;
 
-        let refresh_token = self
-            .jwt_utils
-            .generate_refresh_token(user_id.clone(), role_access_level.clone())?;
+        let refresh_token = self.jwt_utils.generate_refresh_token(
+            user_id.clone(),
+            role_access_level.clone(),
+            session.id,
+        )?;
 
         // Check if user has credentials for the response
         let has_node_credentials = credential_repo
@@ -148,6 +172,16 @@ impl<'a> AuthService<'a> {
         // Validate refresh token
         let claims = self.jwt_utils.validate_token(&request.refresh_token)?;
 
+        // Refuse refresh tokens whose session was revoked, e.g. by logging out
+        if !SessionService::new(self.pool)
+            .is_session_active(&claims)
+            .await?
+        {
+            return Err(ServiceError::validation(
+                "Session has been revoked. Please log in again.".to_string(),
+            ));
+        }
+
         // Get user to ensure they still exist and are active
         let user = self.user_service.get_user_required(&claims.sub).await?;
 
@@ -163,32 +197,45 @@ impl<'a> AuthService<'a> {
         let user_role_id = user.role_id.clone();
         let role_access_level = user.role_access_level.clone();
 
-        // Check for existing node credentials
+        let role_name = self.get_user_role_name(&user_role_id).await?;
+        let scope = NodeGroupService::new(self.pool)
+            .node_scope(&user_account_id, &user_id, &role_name)
+            .await?;
+
+        // Check for existing node credentials the user's node groups still allow
         let credential_repo = CredentialRepository::new(self.pool);
-        let node_credentials =
-            if let Some(credential) = credential_repo.get_credential_by_user_id(&user_id).await? {
-                Some(NodeCredentials {
-                    node_id: credential.node_id,
-                    node_alias: credential.node_alias,
-                    node_type: credential.node_type.unwrap_or_else(|| "lnd".to_string()),
-                    macaroon: credential.macaroon,
-                    tls_cert: credential.tls_cert,
-                    client_cert: credential.client_cert,
-                    client_key: credential.client_key,
-                    ca_cert: credential.ca_cert,
-                    address: credential.address,
-                })
-            } else {
-                None
-            };
+        let node_credentials = if let Some(credential) = credential_repo
+            .get_credential_by_user_id(&user_id)
+            .await?
+            .filter(|credential| scope.allows(&credential.node_id))
+        {
+            Some(NodeCredentials {
+                node_id: credential.node_id,
+                node_alias: credential.node_alias,
+                node_type: credential.node_type.unwrap_or_else(|| "lnd".to_string()),
+                macaroon: credential.macaroon,
+                tls_cert: credential.tls_cert,
+                client_cert: credential.client_cert,
+                client_key: credential.client_key,
+                ca_cert: credential.ca_cert,
+                address: credential.address,
+                proxy: credential.proxy,
+                rune: credential.rune,
+                transport: credential.transport,
+                read_only: credential.read_only,
+            })
+        } else {
+            None
+        };
 
         // Generate new access token with node credentials if available
         let access_token = self.jwt_utils.generate_token(
             user_id,
             user_account_id,
-            self.get_user_role_name(&user_role_id).await?,
+            role_name,
             role_access_level,
             node_credentials,
+            claims.sid,
         )?;
 
         Ok(RefreshTokenResponse {
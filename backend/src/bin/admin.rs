@@ -0,0 +1,265 @@
+//! `nodegaze-admin`: operations tool working directly on the database.
+//!
+//! Meant for when the HTTP API is down or every admin is locked out. It reads
+//! the same environment as the server. Passwords are read from standard input
+//! so they never show up in the process list or shell history.
+
+use anyhow::{Context, Result, anyhow, bail};
+use backend::config::{BackupConfig, Config};
+use backend::database::Database;
+use backend::database::models::CreateNewAccount;
+use backend::repositories::credential_repository::CredentialRepository;
+use backend::repositories::event_repository::EventRepository;
+use backend::services::account_service::AccountService;
+use backend::services::backup::BackupService;
+use backend::services::event_backfill::EventBackfillService;
+use backend::services::quota_service::{QuotaResource, QuotaService};
+use backend::services::user_service::UserService;
+use chrono::{Duration, NaiveDate, Utc};
+use sqlx::SqlitePool;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+const USAGE: &str = "Usage: nodegaze-admin <command> [arguments]
+
+Commands:
+  create-account <account-name> <username> <email>
+  reset-password <username>
+  list-credentials [--account <account-id>]
+  revoke-credential <credential-id>
+  backfill-events <node-id> [--since <YYYY-MM-DD>]
+  purge-events --older-than-days <days>
+  backup-now
+  list-backups
+  restore-backup --output <path> [<backup-key>]
+  set-quota <account-id> <nodes|notifications|events|api-requests-per-day> <limit|unlimited>";
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some((command, args)) = args.split_first() else {
+        eprintln!("{USAGE}");
+        std::process::exit(2);
+    };
+
+    if let Err(e) = run(command, args).await {
+        eprintln!("Error: {e:#}");
+        std::process::exit(1);
+    }
+}
+
+async fn run(command: &str, args: &[String]) -> Result<()> {
+    let config = Config::from_env()?;
+    let db = Database::new(&config).await?;
+    let pool = db.pool();
+
+    match (command, args) {
+        ("create-account", [name, username, email]) => {
+            create_account(pool, name, username, email).await
+        }
+        ("reset-password", [username]) => reset_password(pool, username).await,
+        ("list-credentials", []) => list_credentials(pool, None).await,
+        ("list-credentials", [flag, account_id]) if flag == "--account" => {
+            list_credentials(pool, Some(account_id)).await
+        }
+        ("revoke-credential", [credential_id]) => revoke_credential(pool, credential_id).await,
+        ("backfill-events", [node_id]) => backfill_events(pool, node_id, None).await,
+        ("backfill-events", [node_id, flag, since]) if flag == "--since" => {
+            backfill_events(pool, node_id, Some(since)).await
+        }
+        ("purge-events", [flag, days]) if flag == "--older-than-days" => {
+            purge_events(pool, days).await
+        }
+        ("backup-now", []) => backup_now(pool, backup_config(&config)?).await,
+        ("list-backups", []) => list_backups(backup_config(&config)?).await,
+        ("restore-backup", [flag, output]) if flag == "--output" => {
+            restore_backup(backup_config(&config)?, output, None).await
+        }
+        ("restore-backup", [flag, output, key]) if flag == "--output" => {
+            restore_backup(backup_config(&config)?, output, Some(key)).await
+        }
+        ("set-quota", [account_id, resource, limit]) => {
+            set_quota(pool, account_id, resource, limit).await
+        }
+        _ => bail!("invalid command or arguments\n\n{USAGE}"),
+    }
+}
+
+/// Reads a password from standard input.
+fn read_password(prompt: &str) -> Result<String> {
+    eprint!("{prompt}: ");
+    io::stderr().flush()?;
+
+    let mut password = String::new();
+    io::stdin().lock().read_line(&mut password)?;
+    let password = password.trim_end_matches(['\r', '\n']).to_string();
+    if password.is_empty() {
+        bail!("no password given");
+    }
+    Ok(password)
+}
+
+async fn create_account(pool: &SqlitePool, name: &str, username: &str, email: &str) -> Result<()> {
+    let password = read_password("Admin password")?;
+    let created = AccountService::new(pool)
+        .create_account(CreateNewAccount {
+            name: name.to_string(),
+            username: username.to_string(),
+            email: email.to_string(),
+            password,
+        })
+        .await?;
+
+    println!(
+        "Created account {} ({}) with admin user {} ({})",
+        created.account.name, created.account.id, created.user.username, created.user.id
+    );
+    Ok(())
+}
+
+async fn reset_password(pool: &SqlitePool, username: &str) -> Result<()> {
+    let password = read_password("New password")?;
+    let user = UserService::new(pool)
+        .reset_password(username, &password)
+        .await?;
+
+    println!("Password reset for {} ({})", user.username, user.id);
+    Ok(())
+}
+
+async fn list_credentials(pool: &SqlitePool, account_id: Option<&str>) -> Result<()> {
+    let repo = CredentialRepository::new(pool);
+    let credentials = match account_id {
+        Some(account_id) => repo.get_credentials_by_account_id(account_id).await?,
+        None => repo.get_active_credentials().await?,
+    };
+
+    println!("ID\tACCOUNT\tNODE\tALIAS\tTYPE\tADDRESS\tUPDATED");
+    for credential in credentials {
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            credential.id,
+            credential.account_id,
+            credential.node_id,
+            credential.node_alias,
+            credential.node_type.as_deref().unwrap_or("lnd"),
+            credential.address,
+            credential.updated_at.to_rfc3339()
+        );
+    }
+    Ok(())
+}
+
+async fn revoke_credential(pool: &SqlitePool, credential_id: &str) -> Result<()> {
+    let repo = CredentialRepository::new(pool);
+    let credential = repo
+        .get_credential_by_id(credential_id)
+        .await?
+        .ok_or_else(|| anyhow!("no active credential with id {credential_id}"))?;
+
+    repo.delete_credential(&credential.id).await?;
+    println!(
+        "Revoked credential {} for node {} ({})",
+        credential.id, credential.node_alias, credential.node_id
+    );
+    Ok(())
+}
+
+async fn backfill_events(pool: &SqlitePool, node_id: &str, since: Option<&str>) -> Result<()> {
+    let since = since
+        .map(|since| {
+            NaiveDate::parse_from_str(since, "%Y-%m-%d")
+                .with_context(|| format!("invalid --since date '{since}', expected YYYY-MM-DD"))
+        })
+        .transpose()?
+        .and_then(|day| day.and_hms_opt(0, 0, 0))
+        .map(|start| start.and_utc());
+
+    let reports = EventBackfillService::new(pool)
+        .backfill_node(node_id, since)
+        .await?;
+    for report in reports {
+        println!(
+            "Account {}: created {} events, {} already recorded",
+            report.account_id, report.created, report.existing
+        );
+    }
+    Ok(())
+}
+
+async fn purge_events(pool: &SqlitePool, days: &str) -> Result<()> {
+    let days: i64 = days
+        .parse()
+        .ok()
+        .filter(|days| *days > 0)
+        .ok_or_else(|| anyhow!("--older-than-days must be a positive number of days"))?;
+
+    let cutoff = Utc::now() - Duration::days(days);
+    let purged = EventRepository::new(pool)
+        .purge_events_before(cutoff)
+        .await?;
+
+    println!("Purged {purged} events from before {}", cutoff.to_rfc3339());
+    Ok(())
+}
+
+fn backup_config(config: &Config) -> Result<&BackupConfig> {
+    config.backup.as_ref().ok_or_else(|| {
+        anyhow!("backups are not configured; set BACKUP_S3_BUCKET and related variables")
+    })
+}
+
+async fn backup_now(pool: &SqlitePool, config: &BackupConfig) -> Result<()> {
+    let key = BackupService::new(config).run_backup(pool).await?;
+    println!("Uploaded backup {key}");
+    Ok(())
+}
+
+async fn list_backups(config: &BackupConfig) -> Result<()> {
+    println!("KEY\tSIZE");
+    for backup in BackupService::new(config).list_backups().await? {
+        println!("{}\t{}", backup.key, backup.size);
+    }
+    Ok(())
+}
+
+async fn restore_backup(config: &BackupConfig, output: &str, key: Option<&str>) -> Result<()> {
+    let key = BackupService::new(config)
+        .restore(key, Path::new(output))
+        .await?;
+    println!("Restored backup {key} to {output}");
+    println!("Stop NodeGaze and point DATABASE_URL at it (or move it into place) to use it.");
+    Ok(())
+}
+
+async fn set_quota(pool: &SqlitePool, account_id: &str, resource: &str, limit: &str) -> Result<()> {
+    let resource: QuotaResource = resource.parse().map_err(|e: String| anyhow!(e))?;
+    let limit = match limit {
+        "unlimited" => None,
+        limit => Some(
+            limit
+                .parse::<i64>()
+                .ok()
+                .filter(|limit| *limit >= 0)
+                .ok_or_else(|| anyhow!("limit must be a non-negative number or 'unlimited'"))?,
+        ),
+    };
+
+    AccountService::new(pool)
+        .get_account_required(account_id)
+        .await?;
+    let quota = QuotaService::new(pool)
+        .set_quota(account_id, resource, limit)
+        .await?;
+
+    let show = |limit: Option<i64>| limit.map_or("unlimited".to_string(), |l| l.to_string());
+    println!("Quotas of account {account_id}:");
+    println!("  nodes: {}", show(quota.max_nodes));
+    println!("  notifications: {}", show(quota.max_notifications));
+    println!("  events: {}", show(quota.max_events));
+    println!(
+        "  api-requests-per-day: {}",
+        show(quota.max_api_requests_per_day)
+    );
+    Ok(())
+}
@@ -0,0 +1,134 @@
+//! Embedded schema migrations and the startup migration runner.
+//!
+//! Migrations are compiled into the binary, so a deployment needs no `sqlx-cli`.
+//! They are applied in the background on startup unless disabled, or on demand
+//! with `nodegaze migrate`. The outcome of the startup run is kept for `/readyz`.
+
+use anyhow::Result;
+use sqlx::SqlitePool;
+use sqlx::migrate::Migrator;
+use std::sync::{LazyLock, RwLock};
+
+/// Migrations from `backend/migrations`, embedded at compile time.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Progress of the startup migration run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MigrationRun {
+    /// Startup migrations are turned off
+    Disabled,
+    Running,
+    Finished,
+    Failed(String),
+}
+
+static STARTUP_RUN: LazyLock<RwLock<MigrationRun>> =
+    LazyLock::new(|| RwLock::new(MigrationRun::Disabled));
+
+/// Returns the state of the startup migration run.
+pub fn startup_run() -> MigrationRun {
+    STARTUP_RUN.read().unwrap().clone()
+}
+
+fn set_startup_run(state: MigrationRun) {
+    *STARTUP_RUN.write().unwrap() = state;
+}
+
+/// Applies all pending migrations.
+///
+/// Foreign keys are switched off meanwhile, as SQLite requires for migrations
+/// that rebuild a table others reference: with them on, dropping the old table
+/// would cascade to the referencing rows. The sqlx SQLite driver runs every
+/// migration in a transaction, inside which the pragma has no effect, so it is
+/// set on the connection beforehand and the references are checked afterwards.
+pub async fn run(pool: &SqlitePool) -> Result<()> {
+    let mut conn = pool.acquire().await?;
+    sqlx::query("PRAGMA foreign_keys = OFF")
+        .execute(&mut *conn)
+        .await?;
+    let result = MIGRATOR.run_direct(&mut *conn).await;
+    let violations: Vec<(String,)> = sqlx::query_as("PRAGMA foreign_key_check")
+        .fetch_all(&mut *conn)
+        .await?;
+    sqlx::query("PRAGMA foreign_keys = ON")
+        .execute(&mut *conn)
+        .await?;
+
+    result?;
+    if !violations.is_empty() {
+        let tables: Vec<String> = violations.into_iter().map(|(table,)| table).collect();
+        anyhow::bail!(
+            "Migrations left broken foreign keys in {}",
+            tables.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Applies pending migrations in a background task, recording the outcome.
+pub fn spawn_startup_run(pool: SqlitePool) {
+    set_startup_run(MigrationRun::Running);
+
+    tokio::spawn(async move {
+        match run(&pool).await {
+            Ok(()) => {
+                tracing::info!("Database migrations are up to date");
+                set_startup_run(MigrationRun::Finished);
+            }
+            Err(e) => {
+                tracing::error!("Database migrations failed: {}", e);
+                set_startup_run(MigrationRun::Failed(e.to_string()));
+            }
+        }
+    });
+}
+
+/// Versions of the migrations applied successfully to the database.
+pub async fn applied_versions(pool: &SqlitePool) -> Result<Vec<i64>> {
+    let versions = sqlx::query_scalar(
+        "SELECT version FROM _sqlx_migrations WHERE success = 1 ORDER BY version",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(versions)
+}
+
+/// Versions known to the binary that are missing from `applied`.
+pub fn pending_versions(applied: &[i64]) -> Vec<i64> {
+    MIGRATOR
+        .iter()
+        .map(|migration| migration.version)
+        .filter(|version| !applied.contains(version))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_unapplied_versions() {
+        let known: Vec<i64> = MIGRATOR.iter().map(|migration| migration.version).collect();
+        assert!(!known.is_empty());
+
+        assert_eq!(pending_versions(&[]), known);
+        assert!(pending_versions(&known).is_empty());
+        assert_eq!(
+            pending_versions(&known[..known.len() - 1]),
+            vec![known[known.len() - 1]]
+        );
+    }
+
+    #[tokio::test]
+    async fn applies_every_migration_with_foreign_keys_restored() {
+        let pool = crate::database::test_pool().await;
+
+        let applied = applied_versions(&pool).await.unwrap();
+        assert!(pending_versions(&applied).is_empty());
+        let foreign_keys: i64 = sqlx::query_scalar("PRAGMA foreign_keys")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(foreign_keys, 1);
+    }
+}
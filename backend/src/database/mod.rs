@@ -2,14 +2,44 @@
 //!
 //! This module is responsible for initializing the database connection pool
 //! and providing a central point for database-related configurations and helpers.
+//!
+//! SQLite allows a single writer at a time. With write-ahead logging, reads no
+//! longer wait for it, and writers wait up to `DB_BUSY_TIMEOUT_MS` for each
+//! other. Writes to hot tables come from many tasks at once, so they also
+//! queue for [`write_permit`] instead of spinning on the database lock: events
+//! from the collectors, the statistics rollups, and the API request counter
+//! `jwt_auth` bumps on every request. Other tables see occasional writes that
+//! the busy timeout covers. The permit is not reentrant, so a write holding it
+//! must not call another.
+//!
+//! Statements taking longer than `DB_SLOW_QUERY_MS` are logged as warnings
+//! with their SQL and duration.
 
 use crate::config::Config;
 use anyhow::Result;
-use sqlx::{SqlitePool, sqlite::SqlitePoolOptions};
+use log::LevelFilter;
+use sqlx::{
+    ConnectOptions, SqlitePool,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous},
+};
+use std::str::FromStr;
 use std::time::Duration;
+use tokio::sync::{Semaphore, SemaphorePermit};
 
+pub mod migrations;
 pub mod models;
 
+/// Writers to hot tables, one at a time.
+static WRITE_PERMITS: Semaphore = Semaphore::const_new(1);
+
+/// Waits for the turn to write to a hot table; the turn ends when the permit drops.
+pub async fn write_permit() -> SemaphorePermit<'static> {
+    WRITE_PERMITS
+        .acquire()
+        .await
+        .expect("the write semaphore is never closed")
+}
+
 pub struct Database {
     pub pool: SqlitePool,
 }
@@ -19,11 +49,30 @@ impl Database {
     pub async fn new(config: &Config) -> Result<Self> {
         let database_url = &config.database_url;
 
+        // Migrations create the schema, so a missing database file is created too
+        let mut options = SqliteConnectOptions::from_str(database_url)?
+            .create_if_missing(true)
+            .busy_timeout(Duration::from_millis(config.db_busy_timeout_ms));
+        if config.db_wal {
+            // Syncing at checkpoints only is safe in WAL mode and much faster
+            options = options
+                .journal_mode(SqliteJournalMode::Wal)
+                .synchronous(SqliteSynchronous::Normal);
+        }
+        options = if config.db_slow_query_ms == 0 {
+            options.log_slow_statements(LevelFilter::Off, Duration::ZERO)
+        } else {
+            options.log_slow_statements(
+                LevelFilter::Warn,
+                Duration::from_millis(config.db_slow_query_ms),
+            )
+        };
+
         // Create a new SqlitePool with a timeout of 30 seconds
         let pool = SqlitePoolOptions::new()
             .max_connections(config.max_connections)
             .acquire_timeout(Duration::from_secs(config.acquire_timeout_seconds))
-            .connect(database_url)
+            .connect_with(options)
             .await?;
 
         Ok(Database { pool })
@@ -35,6 +84,21 @@ impl Database {
     }
 }
 
+/// An empty in-memory database with every migration applied, for tests.
+#[cfg(test)]
+pub async fn test_pool() -> SqlitePool {
+    // Each connection to `:memory:` opens its own database, so keep to one
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .expect("in-memory database opens");
+    migrations::run(&pool)
+        .await
+        .expect("migrations apply to an empty database");
+    pool
+}
+
 impl Clone for Database {
     fn clone(&self) -> Self {
         Database {
@@ -42,3 +106,99 @@ impl Clone for Database {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::{CreateEvent, EventSeverity, EventType};
+    use crate::repositories::event_repository::EventRepository;
+    use crate::repositories::quota_repository::QuotaRepository;
+    use crate::repositories::stats_rollup_repository::StatsRollupRepository;
+    use chrono::{DateTime, Utc};
+    use futures::future::join_all;
+
+    fn event(i: usize) -> CreateEvent {
+        CreateEvent {
+            id: format!("event-{i}"),
+            account_id: "account".to_string(),
+            user_id: "user".to_string(),
+            node_id: "node".to_string(),
+            node_alias: "gaze".to_string(),
+            event_type: EventType::ChannelOpened,
+            severity: EventSeverity::Info,
+            title: "Channel opened".to_string(),
+            description: "A channel was opened".to_string(),
+            data: "{}".to_string(),
+            notifications_id: None,
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// Writers share a file database that gives up on a lock at once, so any
+    /// hot-table write made without a permit fails with `database is locked`.
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn hot_table_writers_never_find_the_database_locked() {
+        let path = std::env::temp_dir().join(format!("nodegaze-{}.db", uuid::Uuid::now_v7()));
+        let config = Config::from_vars(&|name| match name {
+            "DATABASE_URL" => Some(format!("sqlite://{}", path.display())),
+            "JWT_SECRET" => Some("secret".to_string()),
+            "DB_MAX_CONNECTIONS" => Some("8".to_string()),
+            "DB_BUSY_TIMEOUT_MS" => Some("0".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        let pool = Database::new(&config).await.unwrap().pool;
+        migrations::run(&pool).await.unwrap();
+        sqlx::query(
+            r#"
+            INSERT INTO accounts (id, name) VALUES ('account', 'Account');
+            INSERT INTO users (id, account_id, username, password_hash, email, role_id)
+            VALUES ('user', 'account', 'user', '', 'user@example.com',
+                    '01932f4e-8b2b-7a3c-9d5f-2a3b4c5d6e7f');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let writers = (0..400).map(|i| {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                match i % 4 {
+                    0 | 1 => EventRepository::new(&pool)
+                        .create_event(event(i))
+                        .await
+                        .map(drop),
+                    2 => QuotaRepository::new(&pool)
+                        .record_api_request("account", "2025-09-12")
+                        .await
+                        .map(drop),
+                    _ => {
+                        StatsRollupRepository::new(&pool)
+                            .refresh_event_rollups("account", DateTime::UNIX_EPOCH, Utc::now())
+                            .await
+                    }
+                }
+            })
+        });
+        for result in join_all(writers).await {
+            result.unwrap().unwrap();
+        }
+
+        let events: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM events")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(events, 200);
+        let requests = QuotaRepository::new(&pool)
+            .get_api_requests("account", "2025-09-12")
+            .await
+            .unwrap();
+        assert_eq!(requests, 100);
+
+        pool.close().await;
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+    }
+}
@@ -81,6 +81,20 @@ pub struct User {
     pub deleted_at: Option<DateTime<Utc>>,
 }
 
+/// A login session; the access and refresh tokens issued at login carry its id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserSession {
+    pub id: String,
+    pub user_id: String,
+    pub account_id: String,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_activity_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, PartialEq, PartialOrd)]
 #[sqlx(type_name = "TEXT")] // Store as TEXT in SQLite
 pub enum RoleAccessLevel {
@@ -188,10 +202,20 @@ pub struct Credential {
     pub macaroon: String,
     pub tls_cert: String,
     pub address: String,
-    pub node_type: Option<String>,   // "lnd" or "cln"
-    pub client_cert: Option<String>, // For CLN
-    pub client_key: Option<String>,  // For CLN
-    pub ca_cert: Option<String>,     // For CLN
+    pub node_type: Option<String>,    // "lnd" or "cln"
+    pub client_cert: Option<String>,  // For CLN
+    pub client_key: Option<String>,   // For CLN
+    pub ca_cert: Option<String>,      // For CLN
+    pub proxy: Option<String>,        // SOCKS5 proxy, e.g. Tor
+    pub rune: Option<String>,         // For CLN over commando
+    pub transport: Option<String>,    // For LND, "grpc" or "rest"
+    pub read_only: bool,              // Macaroon/rune cannot change node state
+    pub node_version: Option<String>, // Version reported at connect time
+    pub network: Option<String>,      // e.g. "bitcoin" or "signet", reported at connect time
+    /// Earliest `notAfter` of the TLS certificates
+    pub tls_cert_expires_at: Option<DateTime<Utc>>,
+    /// Earliest `time-before` caveat of the macaroon
+    pub macaroon_expires_at: Option<DateTime<Utc>>,
     pub is_active: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -231,6 +255,23 @@ pub struct CreateCredential {
     pub client_cert: Option<String>,
     pub client_key: Option<String>,
     pub ca_cert: Option<String>,
+
+    #[validate(custom(function = "validate_socket_address"))]
+    pub proxy: Option<String>,
+
+    pub rune: Option<String>,
+
+    pub transport: Option<String>,
+
+    pub read_only: bool,
+
+    pub node_version: Option<String>,
+
+    pub network: Option<String>,
+
+    pub tls_cert_expires_at: Option<DateTime<Utc>>,
+
+    pub macaroon_expires_at: Option<DateTime<Utc>>,
 }
 
 // Custom validation function
@@ -355,6 +396,775 @@ pub struct UserWithRoleAndPermissions {
     pub role: Role,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct NodeGroup {
+    pub id: String,
+    pub account_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub is_deleted: bool,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateNodeGroupRequest {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "Group name must be between 1-255 characters"
+    ))]
+    pub name: String,
+    #[validate(length(max = 1000, message = "Description too long"))]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateNodeGroup {
+    pub id: String,
+    pub account_id: String,
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// A node group with the nodes it tags and the users assigned to it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeGroupWithMembers {
+    pub group: NodeGroup,
+    pub node_ids: Vec<String>,
+    pub user_ids: Vec<String>,
+}
+
+/// Display metadata attached to one of an account's nodes.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct NodeLabel {
+    pub account_id: String,
+    pub node_id: String,
+    pub display_name: Option<String>,
+    /// Hex color such as `#f7931a`
+    pub color: Option<String>,
+    pub environment: Option<NodeEnvironment>,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl NodeLabel {
+    /// The label as embedded in event payloads.
+    pub fn payload(&self) -> serde_json::Value {
+        serde_json::json!({
+            "display_name": self.display_name,
+            "color": self.color,
+            "environment": self.environment,
+            "notes": self.notes,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum NodeEnvironment {
+    Prod,
+    Test,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct UpdateNodeLabelRequest {
+    #[validate(length(max = 100, message = "Display name must be at most 100 characters"))]
+    pub display_name: Option<String>,
+    #[validate(custom(function = "validate_hex_color"))]
+    pub color: Option<String>,
+    pub environment: Option<NodeEnvironment>,
+    #[validate(length(max = 2000, message = "Notes must be at most 2000 characters"))]
+    pub notes: Option<String>,
+}
+
+/// Validates a `#rrggbb` color
+fn validate_hex_color(color: &str) -> Result<(), validator::ValidationError> {
+    let valid = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if !valid {
+        return Err(validator::ValidationError::new(
+            "Color must be a hex color such as #f7931a",
+        ));
+    }
+    Ok(())
+}
+
+/// An external system allowed to push events into an account.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventSource {
+    pub id: String,
+    pub account_id: String,
+    pub name: String,
+    /// Node the source's events are attributed to when they don't name one
+    pub node_id: Option<String>,
+    /// User who created the source; ingested events are recorded as theirs
+    pub created_by: String,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateEventSourceRequest {
+    #[validate(length(min = 1, max = 100, message = "Name must be between 1-100 characters"))]
+    pub name: String,
+    pub node_id: Option<String>,
+}
+
+/// Event pushed by an external system to `POST /api/events/ingest`.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct IngestEventRequest {
+    /// Source-defined kind of event, e.g. `bitcoind_reorg`
+    #[validate(custom(function = "validate_external_type"))]
+    #[serde(rename = "type")]
+    pub event_type: String,
+    /// `info`, `warning` or `critical`
+    pub severity: String,
+    #[validate(length(min = 1, max = 255, message = "Title must be between 1-255 characters"))]
+    pub title: String,
+    #[validate(length(
+        min = 1,
+        max = 4000,
+        message = "Description must be between 1-4000 characters"
+    ))]
+    pub description: String,
+    /// Node the event concerns; defaults to the source's node
+    pub node_id: Option<String>,
+    /// When the event happened; defaults to when it was received
+    pub occurred_at: Option<DateTime<Utc>>,
+    /// Additional JSON object stored with the event
+    pub data: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+/// Validates a lowercase `snake_case` event kind of at most 64 characters
+fn validate_external_type(event_type: &str) -> Result<(), validator::ValidationError> {
+    let valid = !event_type.is_empty()
+        && event_type.len() <= 64
+        && event_type
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '.' | '-'));
+    if !valid {
+        return Err(validator::ValidationError::new("invalid_event_type")
+            .with_message("Type must be 1-64 lowercase letters, digits, '_', '.' or '-'".into()));
+    }
+    Ok(())
+}
+
+/// Tags and notes an account attached to a payment, invoice or on-chain transaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentAnnotation {
+    /// Payment hash, or txid of an on-chain transaction
+    pub payment_hash: String,
+    /// Labels such as `rebalance` or `customer refund`
+    pub tags: Vec<String>,
+    pub notes: Option<String>,
+    /// Label the node keeps for the on-chain transaction, as last synchronized with `notes`
+    pub node_label: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PaymentAnnotation {
+    /// Whether the annotation carries a tag, ignoring case.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct UpdatePaymentAnnotationRequest {
+    #[serde(default)]
+    #[validate(
+        length(max = 20, message = "At most 20 tags are allowed"),
+        custom(function = "validate_tags")
+    )]
+    pub tags: Vec<String>,
+    #[validate(length(max = 2000, message = "Notes must be at most 2000 characters"))]
+    pub notes: Option<String>,
+}
+
+/// Validates that every tag is 1 to 50 characters long
+fn validate_tags(tags: &[String]) -> Result<(), validator::ValidationError> {
+    if tags
+        .iter()
+        .any(|tag| tag.trim().is_empty() || tag.trim().chars().count() > 50)
+    {
+        return Err(validator::ValidationError::new("invalid_tag")
+            .with_message("Tags must be between 1 and 50 characters".into()));
+    }
+    Ok(())
+}
+
+/// A payment hash watched for its payment settling or failing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentWatch {
+    pub id: String,
+    pub account_id: String,
+    pub user_id: String,
+    pub payment_hash: String,
+    /// Receives `payment_watch.updated` webhooks
+    pub callback_url: Option<String>,
+    /// Nodes searched for the payment, all of the account's when absent
+    pub node_ids: Option<Vec<String>>,
+    pub status: WatchStatus,
+    /// Node the payment settled or failed on
+    pub node_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum WatchStatus {
+    Pending,
+    Settled,
+    Failed,
+    Expired,
+}
+
+impl std::str::FromStr for WatchStatus {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(WatchStatus::Pending),
+            "settled" => Ok(WatchStatus::Settled),
+            "failed" => Ok(WatchStatus::Failed),
+            "expired" => Ok(WatchStatus::Expired),
+            _ => Err(format!("Invalid watch status: {s}")),
+        }
+    }
+}
+
+/// Request body for watching payment hashes.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct WatchPaymentsRequest {
+    #[validate(
+        length(min = 1, max = 100, message = "Watch 1-100 payment hashes at a time"),
+        custom(function = "validate_payment_hashes")
+    )]
+    pub payment_hashes: Vec<String>,
+    #[validate(custom(function = "validate_webhook_url"))]
+    pub callback_url: Option<String>,
+    /// How long to watch for, 24 hours when absent
+    #[validate(range(min = 1, max = 43200, message = "Watches may last 1-43200 minutes"))]
+    pub expires_in_minutes: Option<u32>,
+}
+
+/// Request body for reconciling invoice states with a node.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct ReconcileInvoicesRequest {
+    #[validate(
+        length(min = 1, max = 500, message = "Reconcile 1-500 invoices at a time"),
+        custom(function = "validate_payment_hashes")
+    )]
+    pub payment_hashes: Vec<String>,
+}
+
+/// Request body for decoding a BOLT11 payment request.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct DecodeInvoiceRequest {
+    /// The payment request, optionally prefixed with `lightning:`
+    #[validate(length(
+        min = 1,
+        max = 7089,
+        message = "Payment requests must be 1-7089 characters"
+    ))]
+    pub payment_request: String,
+}
+
+/// Validates that every payment hash is 64 hex characters
+fn validate_payment_hashes(hashes: &[String]) -> Result<(), validator::ValidationError> {
+    if hashes
+        .iter()
+        .any(|hash| hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()))
+    {
+        return Err(validator::ValidationError::new("invalid_payment_hash")
+            .with_message("Payment hashes must be 64 hex characters".into()));
+    }
+    Ok(())
+}
+
+/// Request body for opening a channel funded by both peers.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct OpenDualFundedChannelRequest {
+    /// Public key of the peer
+    pub pubkey: String,
+    /// `host:port` of the peer, when the node isn't connected to it yet
+    #[validate(custom(function = "validate_socket_address"))]
+    pub address: Option<String>,
+    /// Our contribution
+    #[validate(range(
+        min = 1,
+        max = 2_100_000_000_000_000_u64,
+        message = "Amount must be a positive number of sats"
+    ))]
+    pub amount_sat: u64,
+    /// Contribution to lease from the peer, none when absent
+    #[validate(range(
+        max = 2_100_000_000_000_000_u64,
+        message = "Requested amount is larger than the bitcoin supply"
+    ))]
+    pub request_amount_sat: Option<u64>,
+    /// Terms of the peer's liquidity ad, needed to lease its contribution
+    #[validate(length(min = 1, max = 256, message = "Compact lease must be 1-256 characters"))]
+    pub compact_lease: Option<String>,
+    /// Funding fee rate, the node's estimate when absent
+    #[validate(range(min = 1, max = 10000, message = "Fee rate must be 1-10000 sat/vB"))]
+    pub sat_per_vbyte: Option<u32>,
+    /// Keep the channel out of the gossip
+    #[serde(default)]
+    pub private: bool,
+}
+
+/// A channel one of an account's nodes opened with both peers contributing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DualFundedChannel {
+    pub node_id: String,
+    pub peer_pubkey: String,
+    /// Funding outpoint as `txid:vout`
+    pub channel_point: String,
+    /// Hex-encoded channel id
+    pub channel_id: String,
+    pub local_amount_sat: i64,
+    /// Contribution leased from the peer
+    pub requested_amount_sat: i64,
+    /// Terms of the peer's liquidity ad the contribution was leased under
+    pub compact_lease: Option<String>,
+    /// User who opened the channel
+    pub opened_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Rules applied to inbound channel requests on an account's LND nodes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChannelAcceptorPolicy {
+    /// Every request is accepted while the policy is disabled
+    pub enabled: bool,
+    /// Smallest channel accepted
+    pub min_capacity_sat: Option<u64>,
+    /// Reject channels that won't be announced to the network
+    pub reject_private: bool,
+    /// Peers whose channels are always rejected
+    #[validate(custom(function = "validate_pubkeys"))]
+    pub blocked_pubkeys: Vec<String>,
+}
+
+impl ChannelAcceptorPolicy {
+    /// Decides on an inbound channel request, returning why it is rejected.
+    pub fn evaluate(&self, peer: &str, capacity_sat: u64, private: bool) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self
+            .blocked_pubkeys
+            .iter()
+            .any(|pubkey| pubkey.eq_ignore_ascii_case(peer))
+        {
+            return Err("Peer is blocklisted".to_string());
+        }
+        if let Some(min_capacity_sat) = self.min_capacity_sat.filter(|min| capacity_sat < *min) {
+            return Err(format!(
+                "Capacity of {capacity_sat} sat is below the minimum of {min_capacity_sat} sat"
+            ));
+        }
+        if private && self.reject_private {
+            return Err("Private channels are not accepted".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn validate_pubkeys(pubkeys: &[String]) -> Result<(), validator::ValidationError> {
+    let valid = pubkeys.iter().all(|pubkey| {
+        pubkey.len() == 66
+            && (pubkey.starts_with("02") || pubkey.starts_with("03"))
+            && pubkey.chars().all(|c| c.is_ascii_hexdigit())
+    });
+    if !valid {
+        return Err(validator::ValidationError::new("invalid_pubkey")
+            .with_message("Blocked pubkeys must be 33-byte hex node public keys".into()));
+    }
+    Ok(())
+}
+
+/// Balance-driven fee policy of one channel of an account's node.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoFeePolicy {
+    pub node_id: String,
+    /// Numeric short channel id
+    pub channel_id: String,
+    /// Disabled policies are previewed but never applied
+    pub enabled: bool,
+    /// Share of the capacity the local balance is steered towards
+    pub target_local_ratio: f64,
+    /// Fee charged while the channel sits at the target
+    pub base_fee_ppm: u32,
+    /// Fee charged when the channel is full of local balance
+    pub min_fee_ppm: u32,
+    /// Fee charged when the channel is depleted
+    pub max_fee_ppm: u32,
+    /// Largest change made to the fee in one run
+    pub max_step_ppm: u32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Request body for creating or replacing a channel's auto-fee policy.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+#[validate(schema(function = "validate_auto_fee_policy"))]
+pub struct UpdateAutoFeePolicyRequest {
+    #[serde(default)]
+    pub enabled: bool,
+    #[validate(range(
+        min = 0.0,
+        max = 1.0,
+        message = "target_local_ratio must be between 0 and 1"
+    ))]
+    pub target_local_ratio: f64,
+    pub base_fee_ppm: u32,
+    pub min_fee_ppm: u32,
+    pub max_fee_ppm: u32,
+    #[validate(range(min = 1, message = "max_step_ppm must be at least 1"))]
+    pub max_step_ppm: u32,
+}
+
+fn validate_auto_fee_policy(
+    policy: &UpdateAutoFeePolicyRequest,
+) -> Result<(), validator::ValidationError> {
+    if policy.min_fee_ppm > policy.base_fee_ppm || policy.base_fee_ppm > policy.max_fee_ppm {
+        return Err(validator::ValidationError::new("invalid_fee_bounds")
+            .with_message("Fees must satisfy min_fee_ppm <= base_fee_ppm <= max_fee_ppm".into()));
+    }
+    Ok(())
+}
+
+/// A fee change the auto-fee scheduler attempted on a channel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoFeeAdjustment {
+    pub id: String,
+    pub node_id: String,
+    pub channel_id: String,
+    /// Local balance share when the change was decided
+    pub local_ratio: f64,
+    pub old_fee_ppm: u32,
+    pub new_fee_ppm: u32,
+    /// Whether the node accepted the change
+    pub applied: bool,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A tag grouping channels of an account's nodes, such as `exchange peers`,
+/// with the fee policy and alert thresholds its channels share.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelTag {
+    pub name: String,
+    /// Auto-fee policy of tagged channels without a policy of their own
+    pub fee_policy: Option<UpdateAutoFeePolicyRequest>,
+    pub alert_thresholds: ChannelAlertThresholds,
+    /// Channels carrying the tag, across the account's nodes
+    pub channel_count: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Alert levels of the channels carrying a tag, replacing the account's
+/// `alert_thresholds` for them. Each falls back to the account's when absent.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChannelAlertThresholds {
+    /// Pending HTLCs this many blocks or fewer from their expiry
+    #[validate(range(min = 1, max = 2016, message = "Must be 1-2016 blocks"))]
+    pub htlc_expiry_blocks: Option<u32>,
+}
+
+/// Request body for creating a channel tag.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct CreateChannelTagRequest {
+    #[validate(custom(function = "validate_tag"))]
+    pub name: String,
+    #[validate(nested)]
+    pub fee_policy: Option<UpdateAutoFeePolicyRequest>,
+    #[serde(default)]
+    #[validate(nested)]
+    pub alert_thresholds: ChannelAlertThresholds,
+}
+
+/// Request body for replacing the fee policy and alert thresholds of a channel tag.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateChannelTagRequest {
+    #[validate(nested)]
+    pub fee_policy: Option<UpdateAutoFeePolicyRequest>,
+    #[serde(default)]
+    #[validate(nested)]
+    pub alert_thresholds: ChannelAlertThresholds,
+}
+
+/// Validates that a tag is 1 to 50 characters long
+fn validate_tag(tag: &str) -> Result<(), validator::ValidationError> {
+    validate_tags(&[tag.to_string()])
+}
+
+/// What happens to the events of a blocklisted peer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+#[serde(rename_all = "snake_case")]
+pub enum PeerBlockAction {
+    /// Not recorded at all
+    #[default]
+    Suppress,
+    /// Recorded as Info without notifying anyone
+    Downgrade,
+}
+
+/// A peer on an account's blocklist, such as a probing bot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockedPeer {
+    /// Lowercase hex node public key
+    pub pubkey: String,
+    pub action: PeerBlockAction,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Request body for adding a peer to the blocklist or changing its entry.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+pub struct BlockPeerRequest {
+    pub action: PeerBlockAction,
+    #[validate(length(max = 200, message = "Note must be at most 200 characters"))]
+    pub note: Option<String>,
+}
+
+/// Last seen routing policy of one side of a channel of an account's node.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelPolicySnapshot {
+    pub node_id: String,
+    /// Numeric short channel id
+    pub channel_id: String,
+    /// Whose policy this is, the node's or its peer's
+    pub side: PolicySide,
+    pub fee_base_msat: i64,
+    pub fee_rate_ppm: i64,
+    pub min_htlc_msat: i64,
+    pub max_htlc_msat: Option<i64>,
+    pub time_lock_delta: i64,
+    pub disabled: bool,
+    /// Gossip timestamp of the policy, when the node knows it
+    pub last_update: Option<i64>,
+    /// When the policy was first seen as it is
+    pub observed_at: DateTime<Utc>,
+}
+
+/// A ping round trip from one of an account's nodes to a connected peer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerPing {
+    pub node_id: String,
+    pub peer_pubkey: String,
+    /// Round trip in microseconds
+    pub rtt_us: i64,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// A node in a monitored node's cached view of the channel graph.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphNodeRecord {
+    pub pubkey: String,
+    pub alias: String,
+    pub color: Option<String>,
+    pub last_update: Option<i64>,
+    /// JSON array of `host:port` addresses
+    pub addresses: String,
+}
+
+/// A public channel in a monitored node's cached view of the channel graph.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphEdgeRecord {
+    /// Numeric short channel id
+    pub channel_id: i64,
+    /// The endpoint with the smaller pubkey
+    pub node1: String,
+    pub node2: String,
+    pub capacity_sat: i64,
+    /// Fees of each side, absent while that side is disabled
+    pub node1_base_fee_msat: Option<i64>,
+    pub node1_fee_rate_ppm: Option<i64>,
+    pub node1_last_update: Option<i64>,
+    pub node2_base_fee_msat: Option<i64>,
+    pub node2_fee_rate_ppm: Option<i64>,
+    pub node2_last_update: Option<i64>,
+}
+
+/// Changes to a monitored node's cached graph.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GraphChanges {
+    /// Nodes and channels added or changed
+    pub nodes: Vec<GraphNodeRecord>,
+    pub edges: Vec<GraphEdgeRecord>,
+    /// Nodes and channels gone from the graph
+    pub removed_nodes: Vec<String>,
+    pub closed_channels: Vec<i64>,
+}
+
+impl GraphChanges {
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+            && self.edges.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.closed_channels.is_empty()
+    }
+}
+
+/// When a monitored node's cached graph was fetched and last changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphSync {
+    pub node_id: String,
+    /// Last full fetch of the graph from the node
+    pub synced_at: DateTime<Utc>,
+    /// Latest change applied from the node's gossip
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Administrative change recorded in an account's audit log.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    SettingsUpdated,
+    ChannelAcceptorUpdated,
+    AccessLevelChanged,
+    NodeGroupCreated,
+    NodeGroupDeleted,
+    NotificationCreated,
+    NotificationDeleted,
+    CredentialRotated,
+}
+
+impl std::fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditAction::SettingsUpdated => write!(f, "settings_updated"),
+            AuditAction::ChannelAcceptorUpdated => write!(f, "channel_acceptor_updated"),
+            AuditAction::AccessLevelChanged => write!(f, "access_level_changed"),
+            AuditAction::NodeGroupCreated => write!(f, "node_group_created"),
+            AuditAction::NodeGroupDeleted => write!(f, "node_group_deleted"),
+            AuditAction::NotificationCreated => write!(f, "notification_created"),
+            AuditAction::NotificationDeleted => write!(f, "notification_deleted"),
+            AuditAction::CredentialRotated => write!(f, "credential_rotated"),
+        }
+    }
+}
+
+/// An entry of an account's audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditLogEntry {
+    pub id: String,
+    pub account_id: String,
+    /// User who made the change
+    pub user_id: String,
+    pub action: AuditAction,
+    /// ID or name of what was changed
+    pub target: Option<String>,
+    /// JSON encoded details of the change
+    pub details: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A member joining, leaving or being invited to an account.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MembershipChange {
+    /// Prefixed with `member:` or `invite:`
+    pub id: String,
+    /// `member_joined`, `member_removed` or `member_invited`
+    pub action: String,
+    /// The member, or the inviter of an invite
+    pub user_id: Option<String>,
+    /// Username of the member or email of the invitee
+    pub subject: String,
+    pub occurred_at: DateTime<Utc>,
+}
+
+/// Where an entry of the account activity feed comes from.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ActivityKind {
+    Audit,
+    Event,
+    Membership,
+}
+
+/// An entry of `GET /api/account/activity`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityItem {
+    /// Prefixed with its source, e.g. `audit:…` or `event:…`
+    pub id: String,
+    pub kind: ActivityKind,
+    /// What happened, e.g. `settings_updated`, an event type or `member_joined`
+    pub action: String,
+    /// User who acted, or the member concerned
+    pub user_id: Option<String>,
+    pub node_id: Option<String>,
+    pub summary: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, Hash)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum PolicySide {
+    Local,
+    Remote,
+}
+
+/// Limits on what an account may use. `None` means unlimited.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccountQuota {
+    /// Distinct nodes with stored credentials
+    pub max_nodes: Option<i64>,
+    /// Notification endpoints
+    pub max_notifications: Option<i64>,
+    /// Stored events; the oldest are deleted once exceeded
+    pub max_events: Option<i64>,
+    /// Authenticated API requests per UTC day
+    pub max_api_requests_per_day: Option<i64>,
+}
+
+/// Current consumption of one quota.
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaUsage {
+    pub used: i64,
+    pub limit: Option<i64>,
+}
+
+/// Current consumption of every quota of an account.
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountUsage {
+    pub nodes: QuotaUsage,
+    pub notifications: QuotaUsage,
+    pub events: QuotaUsage,
+    pub api_requests_today: QuotaUsage,
+    /// When the daily API request count starts over
+    pub api_requests_reset_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Notification {
     pub id: String,
@@ -363,11 +1173,34 @@ pub struct Notification {
     pub name: String,
     pub notification_type: NotificationType,
     pub url: String,
+    /// Recipient npub for Nostr notifications, routing key for PagerDuty and
+    /// API key for Opsgenie
+    pub recipient: Option<String>,
+    /// Extra webhook headers with encrypted values; only their names are returned
+    #[serde(serialize_with = "serialize_header_names", skip_deserializing)]
+    pub headers: Option<String>,
     pub is_active: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub is_deleted: bool,
     pub deleted_at: Option<DateTime<Utc>>,
+    /// Deliveries failed in a row since the last successful one
+    pub consecutive_failures: i64,
+    /// Error of the last failed delivery
+    pub last_error: Option<String>,
+    /// When deliveries were paused after too many failures, until a probe succeeds
+    pub paused_at: Option<DateTime<Utc>>,
+}
+
+fn serialize_header_names<S: serde::Serializer>(
+    headers: &Option<String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let names = headers
+        .as_deref()
+        .map(crate::utils::webhook_headers::header_names)
+        .unwrap_or_default();
+    serializer.collect_seq(names)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, PartialEq)]
@@ -375,6 +1208,12 @@ pub struct Notification {
 pub enum NotificationType {
     Webhook,
     Discord,
+    /// Encrypted direct messages published to a Nostr relay
+    Nostr,
+    /// Incidents opened and resolved through PagerDuty's Events API v2
+    PagerDuty,
+    /// Alerts created and closed through Opsgenie's alerts API
+    Opsgenie,
 }
 
 impl std::fmt::Display for NotificationType {
@@ -382,6 +1221,9 @@ impl std::fmt::Display for NotificationType {
         match self {
             NotificationType::Webhook => write!(f, "webhook"),
             NotificationType::Discord => write!(f, "discord"),
+            NotificationType::Nostr => write!(f, "nostr"),
+            NotificationType::PagerDuty => write!(f, "pagerduty"),
+            NotificationType::Opsgenie => write!(f, "opsgenie"),
         }
     }
 }
@@ -392,6 +1234,9 @@ impl std::str::FromStr for NotificationType {
         match s.to_lowercase().as_str() {
             "webhook" => Ok(NotificationType::Webhook),
             "discord" => Ok(NotificationType::Discord),
+            "nostr" => Ok(NotificationType::Nostr),
+            "pagerduty" => Ok(NotificationType::PagerDuty),
+            "opsgenie" => Ok(NotificationType::Opsgenie),
             _ => Err(format!("Invalid notification type: {s}")),
         }
     }
@@ -410,6 +1255,9 @@ pub struct CreateNotification {
     pub notification_type: NotificationType,
     #[validate(url(message = "Must be a valid URL"))]
     pub url: String,
+    pub recipient: Option<String>,
+    /// Webhook headers as sealed by `webhook_headers::seal_headers`
+    pub headers: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
@@ -419,6 +1267,9 @@ pub struct CreateNotificationRequest {
     pub notification_type: NotificationType,
     #[validate(url(message = "Must be a valid URL"))]
     pub url: String,
+    pub recipient: Option<String>,
+    /// Extra headers sent with webhook notifications, e.g. `Authorization`
+    pub headers: Option<std::collections::BTreeMap<String, String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
@@ -427,9 +1278,23 @@ pub struct UpdateNotificationRequest {
     pub name: Option<String>,
     #[validate(url(message = "Must be a valid URL"))]
     pub url: Option<String>,
+    pub recipient: Option<String>,
+    /// Replaces the webhook headers; an empty object removes them
+    pub headers: Option<std::collections::BTreeMap<String, String>>,
     pub is_active: Option<bool>,
 }
 
+/// Events of a notification endpoint to send to it again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayNotificationRequest {
+    pub start_date: DateTime<Utc>,
+    /// Defaults to now
+    pub end_date: Option<DateTime<Utc>>,
+    pub event_types: Option<Vec<EventType>>,
+    pub severities: Option<Vec<EventSeverity>>,
+    pub node_ids: Option<Vec<String>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Event {
     pub id: String,
@@ -444,12 +1309,25 @@ pub struct Event {
     pub data: String, // JSON string
     pub notifications_id: Option<String>,
     pub timestamp: DateTime<Utc>,
+    /// User who acknowledged the event
+    pub acknowledged_by: Option<String>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub is_deleted: bool,
     pub deleted_at: Option<DateTime<Utc>>,
 }
 
+impl Event {
+    /// Whether the event is alert-like and can be acknowledged.
+    pub fn is_alert(&self) -> bool {
+        matches!(
+            self.severity,
+            EventSeverity::Warning | EventSeverity::Critical
+        )
+    }
+}
+
 impl From<Event> for EventResponse {
     fn from(event: Event) -> Self {
         Self {
@@ -465,6 +1343,8 @@ impl From<Event> for EventResponse {
             data: serde_json::from_str(&event.data).unwrap_or(serde_json::Value::Null),
             timestamp: event.timestamp,
             notifications_id: event.notifications_id,
+            acknowledged_by: event.acknowledged_by,
+            acknowledged_at: event.acknowledged_at,
             created_at: event.created_at,
         }
     }
@@ -475,15 +1355,45 @@ impl From<Event> for EventResponse {
 pub enum EventType {
     ChannelOpened,
     ChannelClosed,
+    ChannelSpliced,
     InvoiceCreated,
     InvoiceSettled,
     InvoiceCancelled,
     InvoiceAccepted,
+    InvoiceExpired,
+    LnurlCheckFailed,
+    /// A Lightning Address / LNURL-pay check passes again after failing
+    LnurlCheckRecovered,
     PaymentSent,
     PaymentReceived,
     PaymentFailed,
     NodeConnected,
     NodeDisconnected,
+    PaymentAnomalyDetected,
+    ChannelRequestAccepted,
+    ChannelRequestRejected,
+    /// An HTLC is close enough to its expiry to risk a force close
+    HtlcExpiryRisk,
+    /// A peer changed the fees of a channel or disabled it
+    PolicyChanged,
+    /// A watched payment hash was paid
+    WatchedPaymentSettled,
+    /// A watched payment hash failed
+    WatchedPaymentFailed,
+    /// Pushed by an external system through the ingest endpoint
+    External,
+    /// A TLS certificate or macaroon of a node connection expires soon or has expired
+    CredentialExpiring,
+    /// Pings to a peer have been slow for longer than the account allows
+    PeerHighLatency,
+    /// An outgoing payment has been in flight for longer than the account allows
+    PaymentStuck,
+    /// Deliveries to a notification endpoint were paused after failing in a row
+    NotificationEndpointPaused,
+    /// A paused notification endpoint answered a probe and receives deliveries again
+    NotificationEndpointResumed,
+    /// A peer broadcast a revoked state of a channel
+    ChannelBreachDetected,
 }
 
 impl std::fmt::Display for EventType {
@@ -491,15 +1401,33 @@ impl std::fmt::Display for EventType {
         match self {
             EventType::ChannelOpened => write!(f, "channel_opened"),
             EventType::ChannelClosed => write!(f, "channel_closed"),
+            EventType::ChannelSpliced => write!(f, "channel_spliced"),
             EventType::InvoiceCreated => write!(f, "invoice_created"),
             EventType::InvoiceSettled => write!(f, "invoice_settled"),
             EventType::InvoiceCancelled => write!(f, "invoice_cancelled"),
             EventType::InvoiceAccepted => write!(f, "invoice_accepted"),
+            EventType::InvoiceExpired => write!(f, "invoice_expired"),
+            EventType::LnurlCheckFailed => write!(f, "lnurl_check_failed"),
+            EventType::LnurlCheckRecovered => write!(f, "lnurl_check_recovered"),
             EventType::PaymentSent => write!(f, "payment_sent"),
             EventType::PaymentReceived => write!(f, "payment_received"),
             EventType::PaymentFailed => write!(f, "payment_failed"),
             EventType::NodeConnected => write!(f, "node_connected"),
             EventType::NodeDisconnected => write!(f, "node_disconnected"),
+            EventType::PaymentAnomalyDetected => write!(f, "payment_anomaly_detected"),
+            EventType::ChannelRequestAccepted => write!(f, "channel_request_accepted"),
+            EventType::ChannelRequestRejected => write!(f, "channel_request_rejected"),
+            EventType::HtlcExpiryRisk => write!(f, "htlc_expiry_risk"),
+            EventType::PolicyChanged => write!(f, "policy_changed"),
+            EventType::WatchedPaymentSettled => write!(f, "watched_payment_settled"),
+            EventType::WatchedPaymentFailed => write!(f, "watched_payment_failed"),
+            EventType::External => write!(f, "external"),
+            EventType::CredentialExpiring => write!(f, "credential_expiring"),
+            EventType::PeerHighLatency => write!(f, "peer_high_latency"),
+            EventType::PaymentStuck => write!(f, "payment_stuck"),
+            EventType::NotificationEndpointPaused => write!(f, "notification_endpoint_paused"),
+            EventType::NotificationEndpointResumed => write!(f, "notification_endpoint_resumed"),
+            EventType::ChannelBreachDetected => write!(f, "channel_breach_detected"),
         }
     }
 }
@@ -510,15 +1438,33 @@ impl std::str::FromStr for EventType {
         match s {
             "channel_opened" => Ok(EventType::ChannelOpened),
             "channel_closed" => Ok(EventType::ChannelClosed),
+            "channel_spliced" => Ok(EventType::ChannelSpliced),
             "invoice_created" => Ok(EventType::InvoiceCreated),
             "invoice_settled" => Ok(EventType::InvoiceSettled),
             "invoice_cancelled" => Ok(EventType::InvoiceCancelled),
             "invoice_accepted" => Ok(EventType::InvoiceAccepted),
+            "invoice_expired" => Ok(EventType::InvoiceExpired),
+            "lnurl_check_failed" => Ok(EventType::LnurlCheckFailed),
+            "lnurl_check_recovered" => Ok(EventType::LnurlCheckRecovered),
             "payment_sent" => Ok(EventType::PaymentSent),
             "payment_received" => Ok(EventType::PaymentReceived),
             "payment_failed" => Ok(EventType::PaymentFailed),
             "node_connected" => Ok(EventType::NodeConnected),
             "node_disconnected" => Ok(EventType::NodeDisconnected),
+            "payment_anomaly_detected" => Ok(EventType::PaymentAnomalyDetected),
+            "channel_request_accepted" => Ok(EventType::ChannelRequestAccepted),
+            "channel_request_rejected" => Ok(EventType::ChannelRequestRejected),
+            "htlc_expiry_risk" => Ok(EventType::HtlcExpiryRisk),
+            "policy_changed" => Ok(EventType::PolicyChanged),
+            "watched_payment_settled" => Ok(EventType::WatchedPaymentSettled),
+            "watched_payment_failed" => Ok(EventType::WatchedPaymentFailed),
+            "external" => Ok(EventType::External),
+            "credential_expiring" => Ok(EventType::CredentialExpiring),
+            "peer_high_latency" => Ok(EventType::PeerHighLatency),
+            "payment_stuck" => Ok(EventType::PaymentStuck),
+            "notification_endpoint_paused" => Ok(EventType::NotificationEndpointPaused),
+            "notification_endpoint_resumed" => Ok(EventType::NotificationEndpointResumed),
+            "channel_breach_detected" => Ok(EventType::ChannelBreachDetected),
             _ => Err(format!("Invalid event type: {s}")),
         }
     }
@@ -590,10 +1536,63 @@ pub struct EventResponse {
     pub notifications_id: Option<String>,
     pub data: serde_json::Value, // Parsed JSON
     pub timestamp: DateTime<Utc>,
+    pub acknowledged_by: Option<String>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A stored `Idempotency-Key` request; the response is absent while it is still running.
+#[derive(Debug, Clone, FromRow)]
+pub struct IdempotencyRecord {
+    pub request_hash: String,
+    pub status_code: Option<i64>,
+    pub content_type: Option<String>,
+    pub response_body: Option<Vec<u8>>,
+}
+
+/// The full node message an event was built from.
+#[derive(Debug, Clone, FromRow)]
+pub struct RawEvent {
+    pub event_id: String,
+    /// Kind of node message, e.g. `lnd_invoice`
+    pub source: String,
+    pub payload: String,
+    /// Size of the message before any truncation
+    pub size_bytes: i64,
+    pub truncated: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RawEventResponse {
+    pub event_id: String,
+    pub source: String,
+    /// The message as JSON, or the stored prefix as a string when truncated
+    pub payload: serde_json::Value,
+    pub size_bytes: i64,
+    pub truncated: bool,
+    pub captured_at: DateTime<Utc>,
+}
+
+impl From<RawEvent> for RawEventResponse {
+    fn from(raw: RawEvent) -> Self {
+        let payload = if raw.truncated {
+            serde_json::Value::String(raw.payload)
+        } else {
+            serde_json::from_str(&raw.payload).unwrap_or(serde_json::Value::String(raw.payload))
+        };
+        Self {
+            event_id: raw.event_id,
+            source: raw.source,
+            payload,
+            size_bytes: raw.size_bytes,
+            truncated: raw.truncated,
+            captured_at: raw.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct EventFilters {
     pub event_types: Option<Vec<EventType>>,
     pub severities: Option<Vec<EventSeverity>>,
@@ -602,4 +1601,632 @@ pub struct EventFilters {
     pub end_date: Option<DateTime<Utc>>,
     pub limit: Option<i64>,
     pub offset: Option<i64>,
+    /// Keyset position to continue from; takes precedence over `offset`.
+    #[serde(default)]
+    pub cursor: Option<EventCursor>,
+    /// Only warning and critical events nobody has acknowledged yet.
+    #[serde(default)]
+    pub unacknowledged_only: bool,
+    /// Case-insensitive text the title or description must contain.
+    #[serde(default)]
+    pub search: Option<String>,
+}
+
+/// Position of the last event on a page, ordered by `(timestamp, id)` descending.
+///
+/// Handed to clients as an opaque URL-safe base64 string.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventCursor {
+    pub timestamp: DateTime<Utc>,
+    pub id: String,
+}
+
+impl From<&EventResponse> for EventCursor {
+    fn from(event: &EventResponse) -> Self {
+        Self {
+            timestamp: event.timestamp,
+            id: event.id.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for EventCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use base64::Engine;
+
+        let raw = format!("{}|{}", self.timestamp.to_rfc3339(), self.id);
+        write!(
+            f,
+            "{}",
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(raw)
+        )
+    }
+}
+
+impl std::str::FromStr for EventCursor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use base64::Engine;
+
+        let invalid = || format!("Invalid event cursor: {s}");
+        let raw = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(s)
+            .map_err(|_| invalid())?;
+        let raw = String::from_utf8(raw).map_err(|_| invalid())?;
+        let (timestamp, id) = raw.split_once('|').ok_or_else(invalid)?;
+        let timestamp = DateTime::parse_from_rfc3339(timestamp)
+            .map_err(|_| invalid())?
+            .with_timezone(&Utc);
+
+        Ok(Self {
+            timestamp,
+            id: id.to_string(),
+        })
+    }
+}
+
+/// Fiat currencies the price source quotes.
+pub const SUPPORTED_CURRENCIES: [&str; 7] = ["USD", "EUR", "GBP", "CAD", "CHF", "AUD", "JPY"];
+
+/// Account-wide preferences, stored one top-level field per `settings` row.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccountSettings {
+    #[validate(custom(function = "validate_currency"))]
+    pub default_currency: String,
+    /// IANA time zone used for day boundaries, e.g. `Europe/Berlin`
+    #[validate(custom(function = "validate_timezone"))]
+    pub timezone: String,
+    /// Days events are kept before being purged, kept forever when absent
+    #[validate(range(min = 1, max = 3650, message = "Retention must be 1-3650 days"))]
+    pub event_retention_days: Option<u32>,
+    #[validate(nested)]
+    pub alert_thresholds: AlertThresholds,
+    #[validate(nested)]
+    pub notification_digest: DigestSettings,
+    /// Log stores the account's events are forwarded to
+    #[validate(nested)]
+    pub event_sinks: Vec<EventSinkConfig>,
+    #[validate(nested)]
+    pub reports: ReportSettings,
+    #[validate(nested)]
+    pub branding: BrandingSettings,
+    #[validate(nested)]
+    pub escalation: EscalationSettings,
+    #[validate(nested)]
+    pub invoice_webhook: InvoiceWebhookSettings,
+    /// Keep the full node message of each event, see `GET /api/events/{id}/raw`
+    pub capture_raw_events: bool,
+    /// Export gauges per channel from `GET /api/metrics`, not just per node
+    pub channel_metrics: bool,
+    #[validate(nested)]
+    pub event_compaction: EventCompactionSettings,
+    /// Track stuck payments of LND nodes until they resolve, see `services::stuck_payments`
+    pub track_stuck_payments: bool,
+    /// Tags zero-amount invoices are counted as donations under, see `services::donations`
+    #[validate(
+        length(max = 32, message = "At most 32 donation tags can be set"),
+        nested
+    )]
+    pub donation_tags: Vec<DonationTag>,
+    #[validate(nested)]
+    pub discord_bot: DiscordBotSettings,
+}
+
+impl Default for AccountSettings {
+    fn default() -> Self {
+        Self {
+            default_currency: "USD".to_string(),
+            timezone: "UTC".to_string(),
+            event_retention_days: None,
+            alert_thresholds: AlertThresholds::default(),
+            notification_digest: DigestSettings::default(),
+            event_sinks: Vec::new(),
+            reports: ReportSettings::default(),
+            branding: BrandingSettings::default(),
+            escalation: EscalationSettings::default(),
+            invoice_webhook: InvoiceWebhookSettings::default(),
+            capture_raw_events: false,
+            channel_metrics: false,
+            event_compaction: EventCompactionSettings::default(),
+            track_stuck_payments: false,
+            donation_tags: Vec::new(),
+            discord_bot: DiscordBotSettings::default(),
+        }
+    }
+}
+
+impl AccountSettings {
+    /// The configured time zone, UTC if it is not a known zone.
+    pub fn tz(&self) -> chrono_tz::Tz {
+        self.timezone.parse().unwrap_or(chrono_tz::UTC)
+    }
+}
+
+/// Levels at which alerts are raised, each disabled when absent.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertThresholds {
+    /// Local balance of a channel below this share of its capacity
+    #[validate(range(max = 100, message = "Must be a percentage"))]
+    pub min_local_balance_percent: Option<u8>,
+    /// Outgoing payment success rate below this percentage
+    #[validate(range(max = 100, message = "Must be a percentage"))]
+    pub min_payment_success_percent: Option<u8>,
+    /// On-chain wallet balance below this amount
+    pub min_onchain_balance_sat: Option<u64>,
+    /// Hourly payment figures this many standard deviations above their baseline
+    #[validate(range(min = 1.0, max = 10.0, message = "Must be 1-10 standard deviations"))]
+    pub payment_anomaly_sigma: Option<f64>,
+    /// Pending HTLCs this many blocks or fewer from their expiry
+    #[validate(range(min = 1, max = 2016, message = "Must be 1-2016 blocks"))]
+    pub htlc_expiry_blocks: Option<u32>,
+    /// TLS certificates and macaroons this many days or fewer from their expiry
+    #[validate(range(min = 1, max = 365, message = "Must be 1-365 days"))]
+    pub credential_expiry_days: Option<u32>,
+    /// Ping round trips to a peer above this many milliseconds
+    #[validate(range(min = 1, max = 60000, message = "Must be 1-60000 milliseconds"))]
+    pub max_peer_rtt_ms: Option<u32>,
+    /// Minutes pings have to stay above `max_peer_rtt_ms` before alerting, 10 when absent
+    #[validate(range(min = 1, max = 1440, message = "Must be 1-1440 minutes"))]
+    pub peer_rtt_minutes: Option<u32>,
+    /// Outgoing payments in flight for longer than this many minutes
+    #[validate(range(min = 10, max = 10080, message = "Must be 10-10080 minutes"))]
+    pub stuck_payment_minutes: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DigestFrequency {
+    #[default]
+    Off,
+    Daily,
+    Weekly,
+}
+
+/// Defaults for notification digests summarising recent events.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+pub struct DigestSettings {
+    pub frequency: DigestFrequency,
+    /// Hour of the day the digest is sent, in the account's time zone
+    #[validate(range(max = 23, message = "Hour must be 0-23"))]
+    pub hour: u8,
+    /// Lowest severity included in a digest
+    pub min_severity: EventSeverity,
+}
+
+impl Default for DigestSettings {
+    fn default() -> Self {
+        Self {
+            frequency: DigestFrequency::Off,
+            hour: 8,
+            min_severity: EventSeverity::Info,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFrequency {
+    #[default]
+    Off,
+    Weekly,
+    Monthly,
+}
+
+impl std::fmt::Display for ReportFrequency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReportFrequency::Off => write!(f, "off"),
+            ReportFrequency::Weekly => write!(f, "weekly"),
+            ReportFrequency::Monthly => write!(f, "monthly"),
+        }
+    }
+}
+
+/// Schedule and recipients of the periodic node reports.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReportSettings {
+    pub frequency: ReportFrequency,
+    /// Addresses each report is emailed to
+    #[validate(
+        length(max = 20, message = "At most 20 report recipients are allowed"),
+        custom(function = "validate_recipients")
+    )]
+    pub recipients: Vec<String>,
+}
+
+fn validate_recipients(recipients: &[String]) -> Result<(), validator::ValidationError> {
+    use validator::ValidateEmail;
+
+    if !recipients
+        .iter()
+        .all(|recipient| recipient.validate_email())
+    {
+        return Err(validator::ValidationError::new("invalid_recipient")
+            .with_message("Recipients must be valid email addresses".into()));
+    }
+    Ok(())
+}
+
+/// How the account presents itself in notifications and reports, NodeGaze's
+/// own branding where a field is absent.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+pub struct BrandingSettings {
+    /// Name shown as the sender of Discord embeds and in report headers
+    #[validate(length(min = 1, max = 64, message = "Display name must be 1-64 characters"))]
+    pub display_name: Option<String>,
+    /// Image shown next to the display name
+    #[validate(custom(function = "validate_logo_url"))]
+    pub logo_url: Option<String>,
+    /// Line closing each notification and report
+    #[validate(length(min = 1, max = 200, message = "Footer text must be 1-200 characters"))]
+    pub footer_text: Option<String>,
+}
+
+fn validate_logo_url(logo_url: &str) -> Result<(), validator::ValidationError> {
+    let valid = reqwest::Url::parse(logo_url)
+        .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.host().is_some());
+    if !valid || logo_url.len() > 2048 {
+        return Err(validator::ValidationError::new("invalid_logo_url")
+            .with_message("Logo URL must be an http or https URL".into()));
+    }
+    Ok(())
+}
+
+/// Where critical alerts go while nobody acknowledges them, off without steps.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+pub struct EscalationSettings {
+    /// Steps in the order they run, each at least as late as the one before
+    #[validate(
+        length(max = 10, message = "At most 10 escalation steps are allowed"),
+        nested,
+        custom(function = "validate_escalation_steps")
+    )]
+    pub steps: Vec<EscalationStep>,
+}
+
+impl EscalationSettings {
+    /// Whether a notification endpoint only hears of critical alerts through
+    /// delayed steps, so the alert isn't sent to it right away.
+    pub fn delays_endpoint(&self, notification_id: &str) -> bool {
+        let mut steps = self
+            .steps
+            .iter()
+            .filter(|step| step.notification_id.as_deref() == Some(notification_id))
+            .peekable();
+        steps.peek().is_some() && steps.all(|step| step.delay_minutes > 0)
+    }
+}
+
+/// Endpoint told about every settled invoice, see `services::invoice_webhooks`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+pub struct InvoiceWebhookSettings {
+    /// URL each `invoice.settled` delivery is posted to, none sent when absent
+    #[validate(custom(function = "validate_webhook_url"))]
+    pub url: Option<String>,
+}
+
+/// Discord server the `/nodegaze` slash commands answer for the account in,
+/// see `services::discord_bot`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscordBotSettings {
+    /// ID of the server, commands run anywhere else aren't answered
+    #[validate(custom(function = "validate_discord_id"))]
+    pub guild_id: Option<String>,
+}
+
+fn validate_discord_id(id: &str) -> Result<(), validator::ValidationError> {
+    if !(17..=20).contains(&id.len()) || !id.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(validator::ValidationError::new("invalid_discord_id")
+            .with_message("Server ID must be the 17-20 digit ID Discord shows".into()));
+    }
+    Ok(())
+}
+
+/// A tag under which zero-amount invoices whose memo contains `pattern` are
+/// counted as donations.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+pub struct DonationTag {
+    #[validate(length(min = 1, max = 64, message = "Tag must be between 1-64 characters"))]
+    pub tag: String,
+    /// Text the memo contains, regardless of case
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "Pattern must be between 1-255 characters"
+    ))]
+    pub pattern: String,
+}
+
+/// Compaction of old high-volume events into hourly aggregates, see
+/// `services::event_compaction`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventCompactionSettings {
+    pub enabled: bool,
+    /// Age in days after which events are compacted
+    #[validate(range(min = 1, max = 3650, message = "Compaction age must be 1-3650 days"))]
+    pub after_days: u32,
+    /// Types of the events that are compacted
+    #[validate(length(max = 32, message = "At most 32 event types can be compacted"))]
+    pub event_types: Vec<EventType>,
+}
+
+impl Default for EventCompactionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            after_days: 30,
+            event_types: vec![
+                EventType::InvoiceCreated,
+                EventType::InvoiceSettled,
+                EventType::InvoiceCancelled,
+                EventType::InvoiceAccepted,
+                EventType::InvoiceExpired,
+                EventType::PaymentSent,
+                EventType::PaymentReceived,
+                EventType::PaymentFailed,
+            ],
+        }
+    }
+}
+
+/// Counts and amount sum of an hour of compacted events of one type.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventHourlyAggregate {
+    pub node_id: String,
+    pub event_type: EventType,
+    pub severity: EventSeverity,
+    /// Start of the UTC hour
+    pub hour: DateTime<Utc>,
+    pub event_count: i64,
+    pub amount_msat: i64,
+}
+
+/// Payment figures of one UTC hour of a node, see `services::stats_rollups`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaymentHourlyRollup {
+    pub node_id: String,
+    /// Start of the UTC hour
+    pub hour: DateTime<Utc>,
+    pub outgoing_count: i64,
+    pub outgoing_sat: i64,
+    pub incoming_count: i64,
+    pub incoming_sat: i64,
+    pub forwarded_count: i64,
+    pub forwarded_sat: i64,
+    pub failed_outgoing: i64,
+    pub fee_revenue_msat: i64,
+}
+
+/// What a payment record counts as in the payment statistics.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentRecordKind {
+    /// A settled outgoing payment
+    Outgoing,
+    /// A settled incoming payment
+    Incoming,
+    /// A settled forward
+    Forwarded,
+    /// An outgoing payment that failed
+    FailedOutgoing,
+}
+
+/// A payment or forward of a node counted by the payment statistics, read for
+/// the parts of a window hourly rollups don't cover, see `services::stats_rollups`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaymentRecord {
+    pub node_id: String,
+    /// Completion time, or creation time when the payment never completed
+    pub at: DateTime<Utc>,
+    pub kind: PaymentRecordKind,
+    pub amount_sat: i64,
+    /// Fee earned, for forwards
+    pub fee_msat: i64,
+}
+
+/// Number of events of one severity in one UTC hour of a node, see
+/// `services::stats_rollups`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventHourlyRollup {
+    pub node_id: String,
+    /// Start of the UTC hour
+    pub hour: DateTime<Utc>,
+    pub severity: EventSeverity,
+    pub event_count: i64,
+}
+
+fn validate_webhook_url(url: &str) -> Result<(), validator::ValidationError> {
+    let valid = reqwest::Url::parse(url)
+        .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.host().is_some());
+    if !valid || url.len() > 2048 {
+        return Err(validator::ValidationError::new("invalid_webhook_url")
+            .with_message("Webhook URL must be an http or https URL".into()));
+    }
+    Ok(())
+}
+
+/// One step of an escalation, reaching a notification endpoint or email addresses.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(default, deny_unknown_fields)]
+pub struct EscalationStep {
+    /// Minutes after the alert the step runs if it is still unacknowledged
+    #[validate(range(max = 1440, message = "Delay must be 0-1440 minutes"))]
+    pub delay_minutes: u32,
+    /// Notification endpoint the alert is sent to
+    pub notification_id: Option<String>,
+    /// Addresses the alert is emailed to
+    #[validate(
+        length(max = 20, message = "At most 20 escalation recipients are allowed"),
+        custom(function = "validate_recipients")
+    )]
+    pub email_recipients: Vec<String>,
+}
+
+fn validate_escalation_steps(steps: &[EscalationStep]) -> Result<(), validator::ValidationError> {
+    if steps
+        .iter()
+        .any(|step| step.notification_id.is_some() != step.email_recipients.is_empty())
+    {
+        return Err(validator::ValidationError::new("invalid_step")
+            .with_message("Each step needs a notification or recipients".into()));
+    }
+    if steps
+        .windows(2)
+        .any(|pair| pair[1].delay_minutes < pair[0].delay_minutes)
+    {
+        return Err(validator::ValidationError::new("unordered_steps")
+            .with_message("Steps must be ordered by delay".into()));
+    }
+    Ok(())
+}
+
+/// A stored node report; its figures are kept as JSON in `summary`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Report {
+    pub id: String,
+    pub account_id: String,
+    pub node_id: String,
+    pub node_alias: String,
+    pub frequency: ReportFrequency,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub summary: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Waiting for its first or next attempt
+    Pending,
+    Delivered,
+    /// Given up on after the last attempt
+    Failed,
+}
+
+impl std::str::FromStr for DeliveryStatus {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(DeliveryStatus::Pending),
+            "delivered" => Ok(DeliveryStatus::Delivered),
+            "failed" => Ok(DeliveryStatus::Failed),
+            _ => Err(format!("Invalid delivery status: {s}")),
+        }
+    }
+}
+
+/// A webhook delivery of a topic such as `invoice.settled`, and how it went.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: String,
+    pub account_id: String,
+    pub node_id: String,
+    pub topic: String,
+    /// Sent as the `Idempotency-Key` header; the same for every attempt
+    pub idempotency_key: String,
+    /// Deliveries sharing this key are sent one at a time, oldest first
+    pub ordering_key: String,
+    pub url: String,
+    pub payload: serde_json::Value,
+    pub status: DeliveryStatus,
+    pub attempts: i64,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    /// HTTP status of the last attempt, absent if no response was received
+    pub last_status_code: Option<i64>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventSinkKind {
+    /// Grafana Loki push API, e.g. `http://loki:3100/loki/api/v1/push`
+    Loki,
+    /// Elasticsearch index, e.g. `http://elasticsearch:9200/nodegaze-events`
+    Elasticsearch,
+    /// RFC 5424 syslog receiver, e.g. `udp://logs.example.com:514`
+    Syslog,
+}
+
+/// An external log store persisted events are forwarded to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+#[validate(schema(function = "validate_event_sink"))]
+pub struct EventSinkConfig {
+    pub kind: EventSinkKind,
+    /// Endpoint of the sink; HTTP sinks may carry basic auth credentials
+    pub url: String,
+}
+
+impl std::str::FromStr for EventSinkConfig {
+    type Err = String;
+
+    /// Parses a `kind=url` pair, e.g. `syslog=udp://127.0.0.1:514`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, url) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Expected kind=url, got '{s}'"))?;
+        let kind = serde_json::from_value(serde_json::Value::String(kind.trim().to_string()))
+            .map_err(|_| format!("Unknown event sink kind '{}'", kind.trim()))?;
+        let sink = Self {
+            kind,
+            url: url.trim().to_string(),
+        };
+        sink.validate().map_err(|e| e.to_string())?;
+        Ok(sink)
+    }
+}
+
+fn validate_event_sink(sink: &EventSinkConfig) -> Result<(), validator::ValidationError> {
+    let invalid = |message: &str| {
+        Err(validator::ValidationError::new("invalid_event_sink")
+            .with_message(message.to_string().into()))
+    };
+    let Ok(url) = reqwest::Url::parse(&sink.url) else {
+        return invalid("Event sink URL is not a valid URL");
+    };
+
+    match sink.kind {
+        EventSinkKind::Loki | EventSinkKind::Elasticsearch
+            if !matches!(url.scheme(), "http" | "https") =>
+        {
+            invalid("HTTP event sinks need an http or https URL")
+        }
+        EventSinkKind::Syslog if !matches!(url.scheme(), "udp" | "tcp") || url.host().is_none() => {
+            invalid("Syslog sinks need a udp:// or tcp:// URL with a host")
+        }
+        _ => Ok(()),
+    }
+}
+
+fn validate_currency(currency: &str) -> Result<(), validator::ValidationError> {
+    if !SUPPORTED_CURRENCIES.contains(&currency) {
+        return Err(validator::ValidationError::new("unsupported_currency")
+            .with_message(format!("Currency must be one of {SUPPORTED_CURRENCIES:?}").into()));
+    }
+    Ok(())
+}
+
+fn validate_timezone(timezone: &str) -> Result<(), validator::ValidationError> {
+    if timezone.parse::<chrono_tz::Tz>().is_err() {
+        return Err(validator::ValidationError::new("unknown_timezone")
+            .with_message("Time zone must be an IANA name such as Europe/Berlin".into()));
+    }
+    Ok(())
 }
@@ -4,46 +4,9 @@
 //! backend application and provides mechanisms for consistent error handling
 //! and response formatting.
 
+pub use nodegaze_lightning::LightningError;
 use thiserror::Error;
 
-/// Represents errors that can occur during Lightning Network operations.
-#[derive(Debug, Error)]
-pub enum LightningError {
-    /// Error that occurred while connecting to a Lightning node.
-    #[error("Node connection error: {0}")]
-    ConnectionError(String),
-    /// Error that occurred while retrieving node information.
-    #[error("Get info error: {0}")]
-    GetInfoError(String),
-    /// Error that occurred while retrieving payments.
-    #[error("Error while retrieving payments: {0}")]
-    PaymentError(String),
-    /// Error that occurred while retrieving invoices.
-    #[error("Error while retrieving invoices: {0}")]
-    InvoiceError(String),
-    /// Error that occurred during configuration validation.
-    #[error("Config validation failed: {0}")]
-    ValidationError(String),
-    /// Error that occurred while getting graph.
-    #[error("Get graph error: {0}")]
-    GetGraphError(String),
-    /// Error that occurred while streaming events.
-    #[error("Streaming error: {0}")]
-    StreamingError(String),
-    /// Channel-related error.
-    #[error("Channel error: {0}")]
-    ChannelError(String),
-    /// Generic not found error.
-    #[error("Not found: {0}")]
-    NotFound(String),
-    /// Parse error for things like pubkeys or strings.
-    #[error("Parse error: {0}")]
-    Parse(String),
-    #[error("Network error: {0}")]
-    /// Network error.
-    NetworkError(String),
-}
-
 /// Generic service error that can be used across all entities
 #[derive(Debug, Error)]
 pub enum ServiceError {
@@ -59,6 +22,9 @@ pub enum ServiceError {
     #[error("Invalid operation: {message}")]
     InvalidOperation { message: String },
 
+    #[error("{resource} quota of {limit} reached")]
+    QuotaExceeded { resource: String, limit: i64 },
+
     #[error("Database error: {source}")]
     Database {
         #[from]
@@ -100,4 +66,11 @@ impl ServiceError {
             message: message.into(),
         }
     }
+
+    pub fn quota_exceeded(resource: impl Into<String>, limit: i64) -> Self {
+        Self::QuotaExceeded {
+            resource: resource.into(),
+            limit,
+        }
+    }
 }
@@ -0,0 +1,14 @@
+//! Library side of the NodeGaze backend.
+//!
+//! Holds everything the binaries share: the `nodegaze` server in `main.rs` and
+//! the `nodegaze-admin` operations tool in `bin/admin.rs`.
+
+pub mod api;
+pub mod auth;
+pub mod config;
+pub mod database;
+pub mod errors;
+pub mod middleware;
+pub mod repositories;
+pub mod services;
+pub mod utils;
@@ -4,35 +4,91 @@
 //! and registers all API routes and middleware.
 //! It orchestrates the application's startup and defines its overall structure.
 
-mod api;
-mod auth;
-mod config;
-mod database;
-mod errors;
-mod repositories;
-mod services;
-mod utils;
-
-use crate::api::common::ApiResponse;
-use axum::{Extension, Router, response::Json, routing::get};
-use config::Config;
-use database::Database;
+use axum::{Extension, Router, middleware, response::Json, routing::get};
+use backend::api::common::ApiResponse;
+use backend::config::{self, Config};
+use backend::database::{self, Database};
+use backend::middleware::request_metrics::track_requests;
+use backend::{api, auth, services};
+use std::net::SocketAddr;
 use tracing::info;
-use tracing_subscriber::fmt::init;
 
 #[tokio::main]
 async fn main() {
-    init();
+    backend::utils::logging::init();
 
-    let config = Config::from_env().unwrap();
+    let config = match Config::from_env() {
+        Ok(config) => config::install(config),
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!("See .env.example for the available settings.");
+            std::process::exit(1);
+        }
+    };
     let db = Database::new(&config).await.unwrap();
     let pool = db.pool().clone();
 
+    match std::env::args().nth(1).as_deref() {
+        None => {}
+        Some("migrate") => {
+            let migrated = async {
+                database::migrations::run(&pool).await?;
+                database::migrations::applied_versions(&pool).await
+            }
+            .await;
+            match migrated {
+                Ok(applied) => info!(
+                    "Database migrated to version {}",
+                    applied.last().copied().unwrap_or_default()
+                ),
+                Err(e) => {
+                    eprintln!("Migration failed: {e:#}");
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Some(command) => {
+            eprintln!("Unknown command '{command}'. Usage: nodegaze [migrate]");
+            std::process::exit(2);
+        }
+    }
+
+    if config.run_migrations_on_startup {
+        database::migrations::spawn_startup_run(pool.clone());
+    }
+    services::settings_service::spawn_retention_job(pool.clone());
+    services::event_compaction::spawn_compaction_job(pool.clone());
+    services::anomaly_detector::spawn_anomaly_detector(pool.clone());
+    services::htlc_expiry::spawn_htlc_expiry_checker(pool.clone());
+    services::peer_pings::spawn_peer_ping_sampler(pool.clone());
+    services::stuck_payments::spawn_stuck_payment_checker(pool.clone());
+    services::credential_expiry::spawn_credential_expiry_checker(pool.clone());
+    services::breach_watch::spawn_breach_watcher(pool.clone());
+    services::auto_fees::spawn_auto_fee_scheduler(pool.clone());
+    services::graph_cache::spawn_graph_cache_refresher(pool.clone());
+    services::network_stats::spawn_network_stats_refresher(pool.clone());
+    services::stats_rollups::spawn_stats_rollup_refresher(pool.clone());
+    services::event_sinks::spawn_event_sinks(pool.clone(), config.event_sinks.clone());
+    services::reports::spawn_report_scheduler(pool.clone(), config.email_config());
+    services::alert_escalation::spawn_escalation_scheduler(pool.clone(), config.email_config());
+    services::notification_breaker::spawn_breaker_prober(pool.clone());
+    services::invoice_webhooks::spawn_delivery_worker(pool.clone());
+    services::payment_watches::spawn_payment_watcher(pool.clone());
+    services::policy_watcher::spawn_policy_watcher(pool.clone());
+    services::transaction_labels::spawn_label_sync(pool.clone());
+    services::backup::spawn_backup_scheduler(pool.clone(), config.backup.clone());
+    services::discord_bot::spawn_discord_bot(pool.clone(), config.discord_bot_token.clone());
+
     let app = Router::new()
-        .route("/", get(root_handler))
+        .merge(api::health::routes::health_router())
         .nest("/api/node", api::node::routes::node_router().await)
         .nest("/api/account", api::account::routes::account_router().await)
-        .nest("/api/credential", api::credential::routes::credential_routes())
+        .nest("/api/admin", api::admin::routes::admin_router().await)
+        .nest(
+            "/api/credential",
+            api::credential::routes::credential_routes(),
+        )
         .nest("/auth", auth::routes::auth_router())
         .nest("/api/invite", api::invite::routes::invite_router().await)
         .nest(
@@ -40,26 +96,70 @@ async fn main() {
             api::notification::routes::notification_router().await,
         )
         .nest("/api/events", api::event::routes::event_router().await)
+        .nest(
+            "/api/node-groups",
+            api::node_group::routes::node_group_router().await,
+        )
         .nest(
             "/api/channels",
             api::channel::routes::channel_router().await,
         )
+        .nest(
+            "/api/channel-tags",
+            api::channel_tag::routes::channel_tag_router().await,
+        )
         .nest(
             "/api/payments",
             api::payment::routes::payment_router().await,
         )
+        .nest(
+            "/api/peer-blocklist",
+            api::peer_blocklist::routes::peer_blocklist_router().await,
+        )
         .nest(
             "/api/invoices",
             api::invoice::routes::invoice_router().await,
         )
         .nest("/api/user", api::user::routes::user_router().await)
-        .layer(Extension(pool));
+        .nest("/api/graph", api::graph::routes::graph_router().await)
+        .nest("/api/reports", api::report::routes::report_router().await)
+        .nest("/api/search", api::search::routes::search_router().await)
+        .nest(
+            "/api/aggregate",
+            api::aggregate::routes::aggregate_router().await,
+        )
+        .nest(
+            "/api/auto-fees",
+            api::auto_fee::routes::auto_fee_router().await,
+        )
+        .nest(
+            "/api/webhooks",
+            api::webhook::routes::webhook_router().await,
+        )
+        .nest("/api/metrics", api::metrics::routes::metrics_router().await);
+    // The frontend takes over `/` when the backend serves it
+    let app = match &config.frontend_dir {
+        Some(dir) => {
+            info!("Serving the frontend from {}", dir.display());
+            app.merge(api::frontend::routes::frontend_router(dir.clone()))
+        }
+        None => app.route("/", get(root_handler)),
+    };
+    let app = app
+        .layer(middleware::from_fn(track_requests))
+        .layer(Extension(pool))
+        .layer(Extension(config.clone()));
 
     let bind_address = format!("0.0.0.0:{}", config.server_port);
     let listener = tokio::net::TcpListener::bind(&bind_address).await.unwrap();
 
     info!("Started NodeGaze server on port {}", config.server_port);
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
 
 async fn root_handler() -> Json<ApiResponse<serde_json::Value>> {
@@ -0,0 +1,420 @@
+//! `Idempotency-Key` support for POST requests.
+//!
+//! A client that retries a POST after a timeout cannot tell whether the first
+//! attempt went through. Sending the same `Idempotency-Key` header on every
+//! attempt makes the request run once: its response is stored for
+//! `IDEMPOTENCY_TTL_HOURS` and returned again, marked with
+//! `Idempotent-Replayed: true`, to retries with the same key. Every response is
+//! stored, server errors included, since the node may have acted before the
+//! error; only requests rejected before their handler ran, such as rate limited
+//! ones or those whose node could not be selected, release the key so a retry
+//! runs them. Keys are scoped to the user; reusing one for a different request
+//! is rejected, as is a retry that arrives while the first attempt is still
+//! running.
+
+use crate::api::common::ApiResponse;
+use crate::auth::middleware::NODE_ID_HEADER;
+use crate::database::models::IdempotencyRecord;
+use crate::repositories::idempotency_repository::IdempotencyRepository;
+use crate::utils::jwt::Claims;
+use axum::{
+    body::{Body, Bytes, to_bytes},
+    extract::{OriginalUri, Request},
+    http::{HeaderValue, Method, StatusCode, header::CONTENT_TYPE, request, response},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use bitcoin::hashes::{Hash, HashEngine, sha256};
+use chrono::{Duration, Utc};
+use sqlx::SqlitePool;
+
+/// Header carrying the client's key.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// Header added to replayed responses.
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// How long a completed response is replayed.
+const IDEMPOTENCY_TTL_HOURS: i64 = 24;
+/// How long a key stays locked by a request that never completed, e.g. because
+/// the server stopped while handling it.
+const IN_PROGRESS_TIMEOUT_MINUTES: i64 = 5;
+/// Longest accepted key.
+const MAX_KEY_LEN: usize = 255;
+/// Largest request body accepted with a key.
+const MAX_REQUEST_BODY_BYTES: usize = 1024 * 1024;
+/// Largest response body that is buffered for storing.
+const MAX_RESPONSE_BODY_BYTES: usize = 8 * 1024 * 1024;
+
+/// Marks a response rejected before its handler ran, so its key is released.
+#[derive(Debug, Clone, Copy)]
+pub struct NotHandled;
+
+/// Marks a rejection made before the handler ran, see [`NotHandled`].
+pub fn not_handled(mut response: Response) -> Response {
+    response.extensions_mut().insert(NotHandled);
+    response
+}
+
+/// Runs a keyed POST request at most once per key and replays its response.
+///
+/// Called by `jwt_auth` once the claims are known; requests that are not POST
+/// or carry no `Idempotency-Key` pass through untouched.
+pub async fn idempotent_request(request: Request, next: Next) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    let Some(key) = request.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return next.run(request).await;
+    };
+    let key = match key.to_str() {
+        Ok(key) if is_valid_key(key) => key.to_string(),
+        _ => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                "Idempotency-Key must be 1 to 255 visible ASCII characters",
+                "validation_error",
+            );
+        }
+    };
+    let (Some(user_id), Some(pool)) = (
+        request.extensions().get::<Claims>().map(|c| c.sub.clone()),
+        request.extensions().get::<SqlitePool>().cloned(),
+    ) else {
+        return next.run(request).await;
+    };
+
+    let (parts, body) = request.into_parts();
+    let body = match to_bytes(body, MAX_REQUEST_BODY_BYTES).await {
+        Ok(body) => body,
+        Err(_) => {
+            return error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "Request body is too large",
+                "validation_error",
+            );
+        }
+    };
+    let request_hash = request_hash(&parts, &body);
+
+    let repo = IdempotencyRepository::new(&pool);
+    let now = Utc::now();
+    if let Err(e) = repo.purge_expired(now).await {
+        tracing::warn!("Failed to purge expired idempotency keys: {}", e);
+    }
+
+    let in_progress_until = now + Duration::minutes(IN_PROGRESS_TIMEOUT_MINUTES);
+    match repo
+        .reserve(&user_id, &key, &request_hash, in_progress_until)
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            return match repo.get(&user_id, &key).await {
+                Ok(record) => replay(record, &request_hash),
+                Err(e) => {
+                    tracing::error!("Failed to load idempotency key: {}", e);
+                    server_error()
+                }
+            };
+        }
+        Err(e) => {
+            tracing::error!("Failed to reserve idempotency key: {}", e);
+            return server_error();
+        }
+    }
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    if !handler_ran(&response) {
+        if let Err(e) = repo.release(&user_id, &key).await {
+            tracing::error!("Failed to release idempotency key: {}", e);
+        }
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    match to_bytes(body, MAX_RESPONSE_BODY_BYTES).await {
+        Ok(body) => complete(&repo, &user_id, &key, parts, body).await,
+        Err(e) => {
+            // The handler ran, so retries get this error instead of running it again
+            tracing::error!("Failed to buffer response for idempotency key: {}", e);
+            let (parts, body) = server_error().into_parts();
+            let body = to_bytes(body, MAX_RESPONSE_BODY_BYTES)
+                .await
+                .unwrap_or_default();
+            complete(&repo, &user_id, &key, parts, body).await
+        }
+    }
+}
+
+/// Stores the response of a request that ran under a key and returns it.
+async fn complete(
+    repo: &IdempotencyRepository<'_>,
+    user_id: &str,
+    key: &str,
+    parts: response::Parts,
+    body: Bytes,
+) -> Response {
+    let content_type = parts
+        .headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    let expires_at = Utc::now() + Duration::hours(IDEMPOTENCY_TTL_HOURS);
+    if let Err(e) = repo
+        .complete(
+            user_id,
+            key,
+            i64::from(parts.status.as_u16()),
+            content_type,
+            &body,
+            expires_at,
+        )
+        .await
+    {
+        // The request already ran, so its response is still returned; the key
+        // stays reserved and a retry only runs it again once the reservation
+        // times out
+        tracing::error!("Failed to store idempotent response: {}", e);
+    }
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Answers a request whose key was already used.
+fn replay(record: Option<IdempotencyRecord>, request_hash: &str) -> Response {
+    // The key expired between reserving and loading it; the client can retry
+    let Some(record) = record else {
+        return in_progress();
+    };
+    if record.request_hash != request_hash {
+        return error_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Idempotency-Key was already used for a different request",
+            "idempotency_key_reused",
+        );
+    }
+    let Some(status) = record
+        .status_code
+        .and_then(|code| u16::try_from(code).ok())
+        .and_then(|code| StatusCode::from_u16(code).ok())
+    else {
+        return in_progress();
+    };
+
+    let mut response = Response::new(Body::from(Bytes::from(
+        record.response_body.unwrap_or_default(),
+    )));
+    *response.status_mut() = status;
+    let headers = response.headers_mut();
+    if let Some(content_type) = record
+        .content_type
+        .and_then(|value| HeaderValue::from_str(&value).ok())
+    {
+        headers.insert(CONTENT_TYPE, content_type);
+    }
+    headers.insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+/// Whether a response may come from a handler that ran, and so is replayed to
+/// retries.
+///
+/// Rate limited node calls are shed before reaching the node, and [`NotHandled`]
+/// responses were rejected before the handler.
+fn handler_ran(response: &Response) -> bool {
+    response.status() != StatusCode::TOO_MANY_REQUESTS
+        && response.extensions().get::<NotHandled>().is_none()
+}
+
+/// Keys are opaque strings such as UUIDs; anything printable is accepted.
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty() && key.len() <= MAX_KEY_LEN && key.bytes().all(|b| b.is_ascii_graphic())
+}
+
+/// Hex SHA-256 over everything that identifies a request: method, full path and
+/// query, selected node and body.
+fn request_hash(parts: &request::Parts, body: &[u8]) -> String {
+    // Nested routers strip their prefix from the URI
+    let uri = parts
+        .extensions
+        .get::<OriginalUri>()
+        .map_or(&parts.uri, |original| &original.0);
+    let node_id = parts
+        .headers
+        .get(NODE_ID_HEADER)
+        .map(HeaderValue::as_bytes)
+        .unwrap_or_default();
+
+    let mut engine = sha256::Hash::engine();
+    for field in [
+        parts.method.as_str().as_bytes(),
+        uri.path_and_query()
+            .map_or(uri.path(), |path| path.as_str())
+            .as_bytes(),
+        node_id,
+        body,
+    ] {
+        // Length prefixes keep field boundaries from shifting between requests
+        engine.input(&(field.len() as u64).to_be_bytes());
+        engine.input(field);
+    }
+    sha256::Hash::from_engine(engine).to_string()
+}
+
+fn in_progress() -> Response {
+    error_response(
+        StatusCode::CONFLICT,
+        "A request with this Idempotency-Key is still in progress",
+        "idempotency_key_in_progress",
+    )
+}
+
+fn server_error() -> Response {
+    error_response(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Internal server error",
+        "server_error",
+    )
+}
+
+fn error_response(status: StatusCode, message: &str, error_type: &str) -> Response {
+    let error_response = ApiResponse::<()>::error(message, error_type, None);
+    (status, Json(error_response)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::RoleAccessLevel;
+    use axum::{Extension, Router, middleware, routing::post};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tower::ServiceExt;
+
+    fn parts(method: Method, uri: &str, node_id: Option<&str>) -> request::Parts {
+        let mut builder = Request::builder().method(method).uri(uri);
+        if let Some(node_id) = node_id {
+            builder = builder.header(NODE_ID_HEADER, node_id);
+        }
+        builder.body(()).unwrap().into_parts().0
+    }
+
+    #[test]
+    fn accepts_visible_ascii_keys() {
+        assert!(is_valid_key("3f1c2a9e-7b4d-4c55-9a0e-2f6d8b1c0e7a"));
+        assert!(!is_valid_key(""));
+        assert!(!is_valid_key("has space"));
+        assert!(!is_valid_key(&"k".repeat(MAX_KEY_LEN + 1)));
+    }
+
+    #[test]
+    fn replays_everything_but_requests_that_never_ran() {
+        let response = |status: StatusCode| status.into_response();
+        assert!(handler_ran(&response(StatusCode::CREATED)));
+        assert!(handler_ran(&response(StatusCode::UNPROCESSABLE_ENTITY)));
+        assert!(handler_ran(&response(StatusCode::BAD_GATEWAY)));
+        assert!(handler_ran(&response(StatusCode::INTERNAL_SERVER_ERROR)));
+        assert!(!handler_ran(&response(StatusCode::TOO_MANY_REQUESTS)));
+        assert!(!handler_ran(&not_handled(response(StatusCode::NOT_FOUND))));
+    }
+
+    /// Sends the same keyed POST twice to a handler answering `status`, returning
+    /// the second response and how often the handler ran.
+    async fn retry(status: StatusCode) -> (Response, usize) {
+        let pool = crate::database::test_pool().await;
+        sqlx::query(
+            r#"
+            INSERT INTO accounts (id, name) VALUES ('account', 'Account');
+            INSERT INTO users (id, account_id, username, password_hash, email, role_id)
+            VALUES ('user', 'account', 'user', '', 'user@example.com',
+                    '01932f4e-8b2b-7a3c-9d5f-2a3b4c5d6e7f');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let claims = Claims {
+            sub: "user".to_string(),
+            account_id: "account".to_string(),
+            role: "Member".to_string(),
+            role_access_level: RoleAccessLevel::ReadWrite,
+            node_credentials: None,
+            exp: 0,
+            iat: 0,
+            sid: None,
+        };
+
+        let runs = Arc::new(AtomicUsize::new(0));
+        let handler_runs = runs.clone();
+        let app = Router::new()
+            .route(
+                "/payments",
+                post(move || async move {
+                    handler_runs.fetch_add(1, Ordering::SeqCst);
+                    status
+                }),
+            )
+            .layer(middleware::from_fn(idempotent_request))
+            .layer(Extension(claims))
+            .layer(Extension(pool));
+        let send = || {
+            app.clone().oneshot(
+                Request::post("/payments")
+                    .header(IDEMPOTENCY_KEY_HEADER, "pay-1")
+                    .body(Body::from("{}"))
+                    .unwrap(),
+            )
+        };
+
+        send().await.unwrap();
+        let response = send().await.unwrap();
+        (response, runs.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn replays_server_errors_instead_of_running_again() {
+        let (response, runs) = retry(StatusCode::BAD_GATEWAY).await;
+        assert_eq!(runs, 1);
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(response.headers()[IDEMPOTENT_REPLAYED_HEADER], "true");
+    }
+
+    #[tokio::test]
+    async fn runs_rate_limited_requests_again() {
+        let (response, runs) = retry(StatusCode::TOO_MANY_REQUESTS).await;
+        assert_eq!(runs, 2);
+        assert!(!response.headers().contains_key(IDEMPOTENT_REPLAYED_HEADER));
+    }
+
+    #[test]
+    fn hash_covers_path_node_and_body() {
+        let base = request_hash(&parts(Method::POST, "/api/a?x=1", Some("n1")), b"{}");
+        assert_eq!(
+            base,
+            request_hash(&parts(Method::POST, "/api/a?x=1", Some("n1")), b"{}")
+        );
+        assert_ne!(
+            base,
+            request_hash(&parts(Method::POST, "/api/a?x=2", Some("n1")), b"{}")
+        );
+        assert_ne!(
+            base,
+            request_hash(&parts(Method::POST, "/api/a?x=1", Some("n2")), b"{}")
+        );
+        assert_ne!(
+            base,
+            request_hash(&parts(Method::POST, "/api/a?x=1", Some("n1")), b"{ }")
+        );
+    }
+
+    #[test]
+    fn hash_prefers_the_original_uri() {
+        let mut nested = parts(Method::POST, "/a", None);
+        nested
+            .extensions
+            .insert(OriginalUri("/api/a".parse().unwrap()));
+        assert_eq!(
+            request_hash(&nested, b""),
+            request_hash(&parts(Method::POST, "/api/a", None), b"")
+        );
+    }
+}
@@ -2,4 +2,8 @@
 //!
 //! This module contains reusable middleware components (e.g., for logging,
 //! CORS, or rate limiting) that can be applied to different parts of the
-//! Axum router.
\ No newline at end of file
+//! Axum router.
+
+pub mod idempotency;
+pub mod request_metrics;
+pub mod response_cache;
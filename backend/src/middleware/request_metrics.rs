@@ -0,0 +1,132 @@
+//! Latency and status code metrics per API route.
+//!
+//! Every request is timed and counted under its method and route template,
+//! e.g. `GET /api/node/{id}`, so slow or failing endpoints stand out in
+//! `GET /api/metrics` without a series per node or id. Requests matching no
+//! route are counted under `unmatched`.
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use std::collections::BTreeMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// Upper bounds of the latency histogram buckets, in seconds.
+pub const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Route label of requests that matched no route.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Latency histogram of a route.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RouteLatency {
+    /// Requests that took at most each of `LATENCY_BUCKETS`, cumulative
+    pub buckets: [u64; LATENCY_BUCKETS.len()],
+    pub count: u64,
+    pub sum_seconds: f64,
+}
+
+impl RouteLatency {
+    fn observe(&mut self, seconds: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum_seconds += seconds;
+    }
+}
+
+/// Requests served since the server started.
+#[derive(Debug, Clone, Default)]
+pub struct RequestMetrics {
+    /// Latency per method and route
+    pub latency: BTreeMap<(String, String), RouteLatency>,
+    /// Responses per method, route and status code
+    pub responses: BTreeMap<(String, String, u16), u64>,
+}
+
+impl RequestMetrics {
+    /// Records a request that was answered with `status` after `elapsed`.
+    pub fn record(&mut self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        self.latency
+            .entry((method.to_string(), route.to_string()))
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+        *self
+            .responses
+            .entry((method.to_string(), route.to_string(), status))
+            .or_default() += 1;
+    }
+}
+
+static REQUEST_METRICS: LazyLock<Mutex<RequestMetrics>> =
+    LazyLock::new(|| Mutex::new(RequestMetrics::default()));
+
+/// Times each request and counts its response status under its route.
+///
+/// Must be layered on the router after all routes are added, so the matched
+/// route template is known.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    if let Ok(mut metrics) = REQUEST_METRICS.lock() {
+        metrics.record(
+            &method,
+            &route,
+            response.status().as_u16(),
+            started.elapsed(),
+        );
+    }
+    response
+}
+
+/// Copy of the requests recorded so far.
+pub fn request_metrics() -> RequestMetrics {
+    REQUEST_METRICS
+        .lock()
+        .map(|metrics| metrics.clone())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_are_cumulative_per_route() {
+        let mut metrics = RequestMetrics::default();
+        metrics.record("GET", "/api/node/{id}", 200, Duration::from_millis(3));
+        metrics.record("GET", "/api/node/{id}", 500, Duration::from_millis(300));
+        metrics.record("POST", "/auth/login", 401, Duration::from_secs(20));
+
+        let node = &metrics.latency[&("GET".to_string(), "/api/node/{id}".to_string())];
+        assert_eq!(node.count, 2);
+        assert_eq!(node.buckets[0], 1);
+        assert_eq!(node.buckets[5], 1);
+        assert_eq!(node.buckets[6], 2);
+        assert_eq!(node.buckets[10], 2);
+
+        let login = &metrics.latency[&("POST".to_string(), "/auth/login".to_string())];
+        assert_eq!(login.buckets, [0; LATENCY_BUCKETS.len()]);
+        assert_eq!(login.count, 1);
+        assert_eq!(
+            metrics.responses[&("GET".to_string(), "/api/node/{id}".to_string(), 500)],
+            1
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! Response caching for endpoints that proxy node RPC calls.
+//!
+//! Dashboards poll channel and invoice lists far more often than they change.
+//! Successful responses are kept in memory for a few seconds, keyed by account,
+//! node, endpoint and a hash of the query string, so repeated polls don't reach
+//! the node. Responses carry account data such as channel tags, so accounts
+//! sharing a node never see each other's. Every cached response also carries
+//! an `ETag` derived from its body; a client sending it back in `If-None-Match`
+//! gets an empty `304 Not Modified`.
+
+use crate::utils::jwt::Claims;
+use axum::{
+    body::{Body, Bytes, to_bytes},
+    extract::Request,
+    http::{
+        HeaderMap, HeaderValue, StatusCode,
+        header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use bitcoin::hashes::{Hash, sha256};
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a node response is served from memory.
+const RESPONSE_CACHE_TTL: Duration = Duration::from_secs(10);
+/// Largest response body that is buffered for caching.
+const MAX_CACHED_BODY_BYTES: usize = 8 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    account_id: String,
+    node_id: String,
+    path: String,
+    filter_hash: u64,
+}
+
+#[derive(Clone)]
+struct CachedResponse {
+    stored_at: Instant,
+    etag: HeaderValue,
+    content_type: Option<HeaderValue>,
+    body: Bytes,
+}
+
+static RESPONSE_CACHE: LazyLock<Mutex<HashMap<CacheKey, CachedResponse>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Serves repeated node proxy requests from memory and answers `If-None-Match`.
+///
+/// Must be layered after `jwt_auth` and the node access checks, so only
+/// requests that were allowed to reach the node are answered from the cache.
+/// Requests without node credentials pass through untouched.
+pub async fn cached_node_response(request: Request, next: Next) -> Response {
+    let Some(key) = cache_key(&request) else {
+        return next.run(request).await;
+    };
+    let if_none_match = request.headers().get(IF_NONE_MATCH).cloned();
+
+    let cached = RESPONSE_CACHE.lock().ok().and_then(|cache| {
+        cache
+            .get(&key)
+            .filter(|entry| entry.stored_at.elapsed() < RESPONSE_CACHE_TTL)
+            .cloned()
+    });
+
+    let entry = match cached {
+        Some(entry) => entry,
+        None => {
+            let response = next.run(request).await;
+            if response.status() != StatusCode::OK {
+                return response;
+            }
+
+            let (parts, body) = response.into_parts();
+            let body = match to_bytes(body, MAX_CACHED_BODY_BYTES).await {
+                Ok(body) => body,
+                Err(e) => {
+                    tracing::error!("Failed to buffer node response for caching: {}", e);
+                    return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                }
+            };
+
+            let entry = CachedResponse {
+                stored_at: Instant::now(),
+                etag: etag_for(&body),
+                content_type: parts.headers.get(CONTENT_TYPE).cloned(),
+                body,
+            };
+            if let Ok(mut cache) = RESPONSE_CACHE.lock() {
+                cache.retain(|_, entry| entry.stored_at.elapsed() < RESPONSE_CACHE_TTL);
+                cache.insert(key, entry.clone());
+            }
+            entry
+        }
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(ETAG, entry.etag.clone());
+    // Browsers may keep the body, but must revalidate it before every use
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static("private, no-cache"));
+
+    if if_none_match.is_some_and(|value| etag_matches(&value, &entry.etag)) {
+        return (StatusCode::NOT_MODIFIED, headers).into_response();
+    }
+
+    if let Some(content_type) = entry.content_type {
+        headers.insert(CONTENT_TYPE, content_type);
+    }
+    (StatusCode::OK, headers, Body::from(entry.body)).into_response()
+}
+
+fn cache_key(request: &Request) -> Option<CacheKey> {
+    let claims = request.extensions().get::<Claims>()?;
+    let node_id = claims.node_credentials()?.node_id.clone();
+
+    let mut hasher = DefaultHasher::new();
+    hasher.write(request.uri().query().unwrap_or_default().as_bytes());
+
+    Some(CacheKey {
+        account_id: claims.account_id.clone(),
+        node_id,
+        path: request.uri().path().to_string(),
+        filter_hash: hasher.finish(),
+    })
+}
+
+/// Strong validator built from the first 16 bytes of the body's SHA-256.
+fn etag_for(body: &[u8]) -> HeaderValue {
+    let digest = sha256::Hash::hash(body);
+    let etag = format!("\"{}\"", hex::encode(&digest.as_byte_array()[..16]));
+    HeaderValue::from_str(&etag).expect("hex ETag is a valid header value")
+}
+
+/// Whether an `If-None-Match` header names the current representation.
+///
+/// Uses weak comparison as RFC 9110 requires for `If-None-Match`.
+fn etag_matches(if_none_match: &HeaderValue, etag: &HeaderValue) -> bool {
+    let Ok(value) = if_none_match.to_str() else {
+        return false;
+    };
+    let etag = etag.to_str().unwrap_or_default();
+
+    value.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.strip_prefix("W/").unwrap_or(candidate) == etag
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_if_none_match_candidates() {
+        let etag = etag_for(b"{\"channels\":[]}");
+        assert_eq!(etag, etag_for(b"{\"channels\":[]}"));
+        assert_ne!(etag, etag_for(b"{\"channels\":[1]}"));
+
+        let tag = etag.to_str().unwrap();
+        let header = |value: &str| HeaderValue::from_str(value).unwrap();
+        assert!(etag_matches(&header(tag), &etag));
+        assert!(etag_matches(&header(&format!("\"other\", W/{tag}")), &etag));
+        assert!(etag_matches(&header("*"), &etag));
+        assert!(!etag_matches(&header("\"other\""), &etag));
+    }
+}
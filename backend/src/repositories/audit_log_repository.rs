@@ -0,0 +1,78 @@
+//! Database repository for the audit log of administrative changes.
+//!
+//! Entries are only ever appended; they are removed along with their account.
+
+use crate::database::models::{AuditAction, AuditLogEntry};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// Repository for audit log database operations.
+pub struct AuditLogRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> AuditLogRepository<'a> {
+    /// Creates a new AuditLogRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Appends an entry to the audit log.
+    pub async fn insert_entry(&self, entry: &AuditLogEntry) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO audit_log (id, account_id, user_id, action, target, details, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+            entry.id,
+            entry.account_id,
+            entry.user_id,
+            entry.action,
+            entry.target,
+            entry.details,
+            entry.created_at
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Retrieves up to `limit` of an account's entries, newest first, only
+    /// those made at or before `until` when given.
+    pub async fn get_entries(
+        &self,
+        account_id: &str,
+        until: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<AuditLogEntry>> {
+        let entries = sqlx::query_as!(
+            AuditLogEntry,
+            r#"
+            SELECT
+            id as "id!",
+            account_id as "account_id!",
+            user_id as "user_id!",
+            action as "action!: AuditAction",
+            target as "target?",
+            details as "details?",
+            created_at as "created_at!: DateTime<Utc>"
+            FROM audit_log
+            WHERE account_id = ?
+            AND (? IS NULL OR created_at <= ?)
+            ORDER BY created_at DESC, id DESC
+            LIMIT ?
+            "#,
+            account_id,
+            until,
+            until,
+            limit
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+}
@@ -0,0 +1,217 @@
+//! Database repository for auto-fee policies and their adjustment log.
+//!
+//! Policies are keyed by account, node and channel. Every fee change the
+//! scheduler attempts is appended to the adjustment log, whether or not the node
+//! accepted it.
+
+use crate::database::models::{AutoFeeAdjustment, AutoFeePolicy, UpdateAutoFeePolicyRequest};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// Repository for auto-fee database operations.
+pub struct AutoFeeRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> AutoFeeRepository<'a> {
+    /// Creates a new AutoFeeRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves the policies of one of an account's nodes.
+    pub async fn get_policies(
+        &self,
+        account_id: &str,
+        node_id: &str,
+    ) -> Result<Vec<AutoFeePolicy>> {
+        let policies = sqlx::query_as!(
+            AutoFeePolicy,
+            r#"
+            SELECT
+            node_id as "node_id!",
+            channel_id as "channel_id!",
+            enabled as "enabled!: bool",
+            target_local_ratio as "target_local_ratio!: f64",
+            base_fee_ppm as "base_fee_ppm!: u32",
+            min_fee_ppm as "min_fee_ppm!: u32",
+            max_fee_ppm as "max_fee_ppm!: u32",
+            max_step_ppm as "max_step_ppm!: u32",
+            created_at as "created_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>"
+            FROM auto_fee_policies
+            WHERE account_id = ? AND node_id = ?
+            ORDER BY channel_id
+            "#,
+            account_id,
+            node_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(policies)
+    }
+
+    /// Lists the accounts and nodes with at least one enabled policy, their own
+    /// or one of a tag of their channels.
+    pub async fn get_nodes_with_enabled_policies(&self) -> Result<Vec<(String, String)>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT account_id as "account_id!", node_id as "node_id!"
+            FROM auto_fee_policies
+            WHERE enabled = 1
+            UNION
+            SELECT a.account_id, a.node_id
+            FROM channel_tag_assignments a
+            JOIN channel_tags t ON t.account_id = a.account_id AND t.name = a.tag
+            WHERE json_extract(t.fee_policy, '$.enabled') = 1
+            "#
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.account_id, row.node_id))
+            .collect())
+    }
+
+    /// Creates or replaces the policy of a channel.
+    pub async fn upsert_policy(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        channel_id: &str,
+        policy: &UpdateAutoFeePolicyRequest,
+    ) -> Result<AutoFeePolicy> {
+        let policy = sqlx::query_as!(
+            AutoFeePolicy,
+            r#"
+            INSERT INTO auto_fee_policies
+            (account_id, node_id, channel_id, enabled, target_local_ratio, base_fee_ppm,
+             min_fee_ppm, max_fee_ppm, max_step_ppm)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (account_id, node_id, channel_id) DO UPDATE SET
+            enabled = excluded.enabled,
+            target_local_ratio = excluded.target_local_ratio,
+            base_fee_ppm = excluded.base_fee_ppm,
+            min_fee_ppm = excluded.min_fee_ppm,
+            max_fee_ppm = excluded.max_fee_ppm,
+            max_step_ppm = excluded.max_step_ppm,
+            updated_at = CURRENT_TIMESTAMP
+            RETURNING
+            node_id as "node_id!",
+            channel_id as "channel_id!",
+            enabled as "enabled!: bool",
+            target_local_ratio as "target_local_ratio!: f64",
+            base_fee_ppm as "base_fee_ppm!: u32",
+            min_fee_ppm as "min_fee_ppm!: u32",
+            max_fee_ppm as "max_fee_ppm!: u32",
+            max_step_ppm as "max_step_ppm!: u32",
+            created_at as "created_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            account_id,
+            node_id,
+            channel_id,
+            policy.enabled,
+            policy.target_local_ratio,
+            policy.base_fee_ppm,
+            policy.min_fee_ppm,
+            policy.max_fee_ppm,
+            policy.max_step_ppm
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(policy)
+    }
+
+    /// Removes the policy of a channel.
+    ///
+    /// Returns whether a policy existed.
+    pub async fn delete_policy(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        channel_id: &str,
+    ) -> Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM auto_fee_policies WHERE account_id = ? AND node_id = ? AND channel_id = ?",
+            account_id,
+            node_id,
+            channel_id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Appends an attempted fee change to the adjustment log.
+    pub async fn record_adjustment(
+        &self,
+        account_id: &str,
+        adjustment: &AutoFeeAdjustment,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO auto_fee_adjustments
+            (id, account_id, node_id, channel_id, local_ratio, old_fee_ppm, new_fee_ppm,
+             applied, error, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+            adjustment.id,
+            account_id,
+            adjustment.node_id,
+            adjustment.channel_id,
+            adjustment.local_ratio,
+            adjustment.old_fee_ppm,
+            adjustment.new_fee_ppm,
+            adjustment.applied,
+            adjustment.error,
+            adjustment.created_at
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Retrieves the most recent adjustments made on one of an account's nodes, newest first.
+    pub async fn get_adjustments(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        limit: i64,
+    ) -> Result<Vec<AutoFeeAdjustment>> {
+        let adjustments = sqlx::query_as!(
+            AutoFeeAdjustment,
+            r#"
+            SELECT
+            id as "id!",
+            node_id as "node_id!",
+            channel_id as "channel_id!",
+            local_ratio as "local_ratio!: f64",
+            old_fee_ppm as "old_fee_ppm!: u32",
+            new_fee_ppm as "new_fee_ppm!: u32",
+            applied as "applied!: bool",
+            error as "error?",
+            created_at as "created_at!: DateTime<Utc>"
+            FROM auto_fee_adjustments
+            WHERE account_id = ? AND node_id = ?
+            ORDER BY created_at DESC
+            LIMIT ?
+            "#,
+            account_id,
+            node_id,
+            limit
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(adjustments)
+    }
+}
@@ -0,0 +1,85 @@
+//! Database repository for channel acceptor policies.
+//!
+//! An account has at most one policy, shared by all of its LND nodes. Blocked
+//! pubkeys are kept as a JSON array in the policy row.
+
+use crate::database::models::ChannelAcceptorPolicy;
+use anyhow::Result;
+use chrono::Utc;
+use sqlx::SqlitePool;
+
+/// Repository for channel acceptor policy database operations.
+pub struct ChannelAcceptorRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> ChannelAcceptorRepository<'a> {
+    /// Creates a new ChannelAcceptorRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves the account's policy, if one was ever saved.
+    pub async fn get_policy(&self, account_id: &str) -> Result<Option<ChannelAcceptorPolicy>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+            enabled as "enabled!: bool",
+            min_capacity_sat as "min_capacity_sat?: i64",
+            reject_private as "reject_private!: bool",
+            blocked_pubkeys as "blocked_pubkeys!"
+            FROM channel_acceptor_policies
+            WHERE account_id = ?
+            "#,
+            account_id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        Ok(Some(ChannelAcceptorPolicy {
+            enabled: row.enabled,
+            min_capacity_sat: row.min_capacity_sat.map(|sat| sat.max(0) as u64),
+            reject_private: row.reject_private,
+            blocked_pubkeys: serde_json::from_str(&row.blocked_pubkeys)?,
+        }))
+    }
+
+    /// Creates or replaces the account's policy.
+    pub async fn save_policy(
+        &self,
+        account_id: &str,
+        policy: &ChannelAcceptorPolicy,
+    ) -> Result<()> {
+        let min_capacity_sat = policy.min_capacity_sat.map(|sat| sat as i64);
+        let blocked_pubkeys = serde_json::to_string(&policy.blocked_pubkeys)?;
+        let now = Utc::now();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO channel_acceptor_policies
+            (account_id, enabled, min_capacity_sat, reject_private, blocked_pubkeys, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT (account_id) DO UPDATE SET
+            enabled = excluded.enabled,
+            min_capacity_sat = excluded.min_capacity_sat,
+            reject_private = excluded.reject_private,
+            blocked_pubkeys = excluded.blocked_pubkeys,
+            updated_at = excluded.updated_at
+            "#,
+            account_id,
+            policy.enabled,
+            min_capacity_sat,
+            policy.reject_private,
+            blocked_pubkeys,
+            now
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
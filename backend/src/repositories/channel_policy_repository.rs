@@ -0,0 +1,185 @@
+//! Database repository for the last seen routing policies of channels.
+//!
+//! Snapshots are keyed by account, node, channel and side and replaced when a
+//! check finds the policy changed, so they hold the policies as last seen.
+//! Every replacement is also appended to the channel's policy history.
+
+use crate::database::models::{ChannelPolicySnapshot, PolicySide};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// Repository for channel policy snapshot database operations.
+pub struct ChannelPolicyRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> ChannelPolicyRepository<'a> {
+    /// Creates a new ChannelPolicyRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves the snapshots of one of an account's nodes.
+    pub async fn get_snapshots(
+        &self,
+        account_id: &str,
+        node_id: &str,
+    ) -> Result<Vec<ChannelPolicySnapshot>> {
+        let snapshots = sqlx::query_as!(
+            ChannelPolicySnapshot,
+            r#"
+            SELECT
+            node_id as "node_id!",
+            channel_id as "channel_id!",
+            side as "side!: PolicySide",
+            fee_base_msat as "fee_base_msat!",
+            fee_rate_ppm as "fee_rate_ppm!",
+            min_htlc_msat as "min_htlc_msat!",
+            max_htlc_msat as "max_htlc_msat?",
+            time_lock_delta as "time_lock_delta!",
+            disabled as "disabled!: bool",
+            last_update as "last_update?",
+            observed_at as "observed_at!: DateTime<Utc>"
+            FROM channel_policy_snapshots
+            WHERE account_id = ? AND node_id = ?
+            ORDER BY channel_id, side
+            "#,
+            account_id,
+            node_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(snapshots)
+    }
+
+    /// Creates or replaces the snapshot of one side of a channel and appends
+    /// it to the channel's history.
+    pub async fn upsert_snapshot(
+        &self,
+        account_id: &str,
+        snapshot: &ChannelPolicySnapshot,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO channel_policy_snapshots
+            (account_id, node_id, channel_id, side, fee_base_msat, fee_rate_ppm, min_htlc_msat,
+            max_htlc_msat, time_lock_delta, disabled, last_update, observed_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (account_id, node_id, channel_id, side) DO UPDATE SET
+            fee_base_msat = excluded.fee_base_msat,
+            fee_rate_ppm = excluded.fee_rate_ppm,
+            min_htlc_msat = excluded.min_htlc_msat,
+            max_htlc_msat = excluded.max_htlc_msat,
+            time_lock_delta = excluded.time_lock_delta,
+            disabled = excluded.disabled,
+            last_update = excluded.last_update,
+            observed_at = excluded.observed_at
+            "#,
+            account_id,
+            snapshot.node_id,
+            snapshot.channel_id,
+            snapshot.side,
+            snapshot.fee_base_msat,
+            snapshot.fee_rate_ppm,
+            snapshot.min_htlc_msat,
+            snapshot.max_htlc_msat,
+            snapshot.time_lock_delta,
+            snapshot.disabled,
+            snapshot.last_update,
+            snapshot.observed_at
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO channel_policy_history
+            (account_id, node_id, channel_id, side, fee_base_msat, fee_rate_ppm, min_htlc_msat,
+            max_htlc_msat, time_lock_delta, disabled, last_update, observed_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+            account_id,
+            snapshot.node_id,
+            snapshot.channel_id,
+            snapshot.side,
+            snapshot.fee_base_msat,
+            snapshot.fee_rate_ppm,
+            snapshot.min_htlc_msat,
+            snapshot.max_htlc_msat,
+            snapshot.time_lock_delta,
+            snapshot.disabled,
+            snapshot.last_update,
+            snapshot.observed_at
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Retrieves every policy seen on both sides of a channel, oldest first.
+    pub async fn get_history(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        channel_id: &str,
+    ) -> Result<Vec<ChannelPolicySnapshot>> {
+        let history = sqlx::query_as!(
+            ChannelPolicySnapshot,
+            r#"
+            SELECT
+            node_id as "node_id!",
+            channel_id as "channel_id!",
+            side as "side!: PolicySide",
+            fee_base_msat as "fee_base_msat!",
+            fee_rate_ppm as "fee_rate_ppm!",
+            min_htlc_msat as "min_htlc_msat!",
+            max_htlc_msat as "max_htlc_msat?",
+            time_lock_delta as "time_lock_delta!",
+            disabled as "disabled!: bool",
+            last_update as "last_update?",
+            observed_at as "observed_at!: DateTime<Utc>"
+            FROM channel_policy_history
+            WHERE account_id = ? AND node_id = ? AND channel_id = ?
+            ORDER BY observed_at, id
+            "#,
+            account_id,
+            node_id,
+            channel_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(history)
+    }
+
+    /// Deletes the snapshots of a node's channels other than `channel_ids`,
+    /// once those channels are closed.
+    pub async fn delete_other_snapshots(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        channel_ids: &[String],
+    ) -> Result<u64> {
+        let channel_ids = serde_json::to_string(channel_ids)?;
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM channel_policy_snapshots
+            WHERE account_id = ? AND node_id = ?
+            AND channel_id NOT IN (SELECT value FROM json_each(?))
+            "#,
+            account_id,
+            node_id,
+            channel_ids
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,274 @@
+//! Database repository for channel tags and the channels carrying them.
+//!
+//! Tags are keyed by account and name, compared without regard to case. The fee
+//! policy and alert thresholds of a tag are kept as JSON in its row; removing a
+//! tag removes it from its channels.
+
+use crate::database::models::{ChannelAlertThresholds, ChannelTag, UpdateAutoFeePolicyRequest};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// A `channel_tags` row with its JSON columns still encoded.
+struct ChannelTagRow {
+    name: String,
+    fee_policy: Option<String>,
+    alert_thresholds: String,
+    channel_count: i64,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl TryFrom<ChannelTagRow> for ChannelTag {
+    type Error = serde_json::Error;
+
+    fn try_from(row: ChannelTagRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            name: row.name,
+            fee_policy: row
+                .fee_policy
+                .as_deref()
+                .map(serde_json::from_str)
+                .transpose()?,
+            alert_thresholds: serde_json::from_str(&row.alert_thresholds)?,
+            channel_count: row.channel_count,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+/// Repository for channel tag database operations.
+pub struct ChannelTagRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> ChannelTagRepository<'a> {
+    /// Creates a new ChannelTagRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves every tag of an account, by name.
+    pub async fn get_tags(&self, account_id: &str) -> Result<Vec<ChannelTag>> {
+        let rows = sqlx::query_as!(
+            ChannelTagRow,
+            r#"
+            SELECT
+            t.name as "name!",
+            t.fee_policy as "fee_policy?",
+            t.alert_thresholds as "alert_thresholds!",
+            (SELECT COUNT(*) FROM channel_tag_assignments a
+             WHERE a.account_id = t.account_id AND a.tag = t.name) as "channel_count!: i64",
+            t.created_at as "created_at!: DateTime<Utc>",
+            t.updated_at as "updated_at!: DateTime<Utc>"
+            FROM channel_tags t
+            WHERE t.account_id = ?
+            ORDER BY t.name
+            "#,
+            account_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(ChannelTag::try_from)
+            .collect::<Result<_, _>>()?)
+    }
+
+    /// Retrieves one of an account's tags.
+    pub async fn get_tag(&self, account_id: &str, name: &str) -> Result<Option<ChannelTag>> {
+        let row = sqlx::query_as!(
+            ChannelTagRow,
+            r#"
+            SELECT
+            t.name as "name!",
+            t.fee_policy as "fee_policy?",
+            t.alert_thresholds as "alert_thresholds!",
+            (SELECT COUNT(*) FROM channel_tag_assignments a
+             WHERE a.account_id = t.account_id AND a.tag = t.name) as "channel_count!: i64",
+            t.created_at as "created_at!: DateTime<Utc>",
+            t.updated_at as "updated_at!: DateTime<Utc>"
+            FROM channel_tags t
+            WHERE t.account_id = ? AND t.name = ?
+            "#,
+            account_id,
+            name
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(row.map(ChannelTag::try_from).transpose()?)
+    }
+
+    /// Creates a tag.
+    ///
+    /// Returns whether it was created, i.e. the account had no tag of that name.
+    pub async fn create_tag(
+        &self,
+        account_id: &str,
+        name: &str,
+        fee_policy: Option<&UpdateAutoFeePolicyRequest>,
+        alert_thresholds: &ChannelAlertThresholds,
+    ) -> Result<bool> {
+        let fee_policy = fee_policy.map(serde_json::to_string).transpose()?;
+        let alert_thresholds = serde_json::to_string(alert_thresholds)?;
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO channel_tags (account_id, name, fee_policy, alert_thresholds)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT (account_id, name) DO NOTHING
+            "#,
+            account_id,
+            name,
+            fee_policy,
+            alert_thresholds
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Replaces the fee policy and alert thresholds of a tag.
+    ///
+    /// Returns whether the tag exists.
+    pub async fn update_tag(
+        &self,
+        account_id: &str,
+        name: &str,
+        fee_policy: Option<&UpdateAutoFeePolicyRequest>,
+        alert_thresholds: &ChannelAlertThresholds,
+    ) -> Result<bool> {
+        let fee_policy = fee_policy.map(serde_json::to_string).transpose()?;
+        let alert_thresholds = serde_json::to_string(alert_thresholds)?;
+        let result = sqlx::query!(
+            r#"
+            UPDATE channel_tags
+            SET fee_policy = ?, alert_thresholds = ?, updated_at = CURRENT_TIMESTAMP
+            WHERE account_id = ? AND name = ?
+            "#,
+            fee_policy,
+            alert_thresholds,
+            account_id,
+            name
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Removes a tag from the account and its channels.
+    ///
+    /// Returns whether the tag existed.
+    pub async fn delete_tag(&self, account_id: &str, name: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM channel_tags WHERE account_id = ? AND name = ?",
+            account_id,
+            name
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Tags a channel of one of the account's nodes; tagging it again does nothing.
+    pub async fn tag_channel(
+        &self,
+        account_id: &str,
+        tag: &str,
+        node_id: &str,
+        channel_id: &str,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO channel_tag_assignments (account_id, tag, node_id, channel_id)
+            SELECT account_id, name, ?, ? FROM channel_tags
+            WHERE account_id = ? AND name = ?
+            ON CONFLICT DO NOTHING
+            "#,
+            node_id,
+            channel_id,
+            account_id,
+            tag
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Removes a tag from a channel.
+    ///
+    /// Returns whether the channel carried it.
+    pub async fn untag_channel(
+        &self,
+        account_id: &str,
+        tag: &str,
+        node_id: &str,
+        channel_id: &str,
+    ) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM channel_tag_assignments
+            WHERE account_id = ? AND tag = ? AND node_id = ? AND channel_id = ?
+            "#,
+            account_id,
+            tag,
+            node_id,
+            channel_id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Retrieves the tags of every tagged channel of one of an account's nodes,
+    /// as pairs of numeric channel id and tag, by channel and then tag name.
+    pub async fn get_node_channel_tags(
+        &self,
+        account_id: &str,
+        node_id: &str,
+    ) -> Result<Vec<(String, ChannelTag)>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+            a.channel_id as "channel_id!",
+            t.name as "name!",
+            t.fee_policy as "fee_policy?",
+            t.alert_thresholds as "alert_thresholds!",
+            (SELECT COUNT(*) FROM channel_tag_assignments c
+             WHERE c.account_id = t.account_id AND c.tag = t.name) as "channel_count!: i64",
+            t.created_at as "created_at!: DateTime<Utc>",
+            t.updated_at as "updated_at!: DateTime<Utc>"
+            FROM channel_tag_assignments a
+            JOIN channel_tags t ON t.account_id = a.account_id AND t.name = a.tag
+            WHERE a.account_id = ? AND a.node_id = ?
+            ORDER BY a.channel_id, t.name
+            "#,
+            account_id,
+            node_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let tag = ChannelTag::try_from(ChannelTagRow {
+                    name: row.name,
+                    fee_policy: row.fee_policy,
+                    alert_thresholds: row.alert_thresholds,
+                    channel_count: row.channel_count,
+                    created_at: row.created_at,
+                    updated_at: row.updated_at,
+                })?;
+                Ok((row.channel_id, tag))
+            })
+            .collect()
+    }
+}
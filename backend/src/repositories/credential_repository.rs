@@ -42,8 +42,8 @@ impl<'a> CredentialRepository<'a> {
         let credential = sqlx::query_as!(
             Credential,
             r#"
-            INSERT INTO credentials (id, user_id, account_id, node_id, node_alias, macaroon, tls_cert, address, node_type, client_cert, client_key, ca_cert, is_active)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO credentials (id, user_id, account_id, node_id, node_alias, macaroon, tls_cert, address, node_type, client_cert, client_key, ca_cert, proxy, rune, transport, read_only, node_version, network, tls_cert_expires_at, macaroon_expires_at, is_active)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             RETURNING
             id as "id!",
             user_id as "user_id!",
@@ -57,6 +57,14 @@ impl<'a> CredentialRepository<'a> {
             client_cert as "client_cert?",
             client_key as "client_key?",
             ca_cert as "ca_cert?",
+            proxy as "proxy?",
+            rune as "rune?",
+            transport as "transport?",
+            read_only as "read_only!",
+            node_version as "node_version?",
+            network as "network?",
+            tls_cert_expires_at as "tls_cert_expires_at?: DateTime<Utc>",
+            macaroon_expires_at as "macaroon_expires_at?: DateTime<Utc>",
             is_active as "is_active!",
             created_at as "created_at!: DateTime<Utc>",
             updated_at as "updated_at!: DateTime<Utc>",
@@ -75,6 +83,14 @@ impl<'a> CredentialRepository<'a> {
             credential.client_cert,
             credential.client_key,
             credential.ca_cert,
+            credential.proxy,
+            credential.rune,
+            credential.transport,
+            credential.read_only,
+            credential.node_version,
+            credential.network,
+            credential.tls_cert_expires_at,
+            credential.macaroon_expires_at,
             true
         )
         .fetch_one(self.pool)
@@ -90,8 +106,99 @@ impl<'a> CredentialRepository<'a> {
     ///
     /// # Returns
     /// `Some(Credential)` if found and not deleted, `None` otherwise
+    pub async fn get_credential_by_id(&self, id: &str) -> Result<Option<Credential>> {
+        let credential = sqlx::query_as!(
+            Credential,
+            r#"
+                SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                node_id as "node_id!",
+                node_alias as "node_alias!",
+                macaroon as "macaroon!",
+                tls_cert as "tls_cert!",
+                address as "address!",
+                node_type as "node_type?",
+                client_cert as "client_cert?",
+                client_key as "client_key?",
+                ca_cert as "ca_cert?",
+                proxy as "proxy?",
+                rune as "rune?",
+                transport as "transport?",
+                read_only as "read_only!",
+                node_version as "node_version?",
+                network as "network?",
+                tls_cert_expires_at as "tls_cert_expires_at?: DateTime<Utc>",
+                macaroon_expires_at as "macaroon_expires_at?: DateTime<Utc>",
+                is_active as "is_active!",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>",
+                is_deleted as "is_deleted!",
+                deleted_at as "deleted_at?: DateTime<Utc>"
+                FROM credentials WHERE id = ? AND is_deleted = 0
+                "#,
+            id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(credential)
+    }
+
+    /// Retrieves a soft-deleted credential of an account.
     ///
-    /// # Security
+    /// # Arguments
+    /// * `account_id` - Account the credential belongs to
+    /// * `id` - Credential ID (UUID format)
+    ///
+    /// # Returns
+    /// `Some(Credential)` if the credential exists and is deleted, `None` otherwise
+    pub async fn get_deleted_credential(
+        &self,
+        account_id: &str,
+        id: &str,
+    ) -> Result<Option<Credential>> {
+        let credential = sqlx::query_as!(
+            Credential,
+            r#"
+                SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                node_id as "node_id!",
+                node_alias as "node_alias!",
+                macaroon as "macaroon!",
+                tls_cert as "tls_cert!",
+                address as "address!",
+                node_type as "node_type?",
+                client_cert as "client_cert?",
+                client_key as "client_key?",
+                ca_cert as "ca_cert?",
+                proxy as "proxy?",
+                rune as "rune?",
+                transport as "transport?",
+                read_only as "read_only!",
+                node_version as "node_version?",
+                network as "network?",
+                tls_cert_expires_at as "tls_cert_expires_at?: DateTime<Utc>",
+                macaroon_expires_at as "macaroon_expires_at?: DateTime<Utc>",
+                is_active as "is_active!",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>",
+                is_deleted as "is_deleted!",
+                deleted_at as "deleted_at?: DateTime<Utc>"
+                FROM credentials WHERE id = ? AND account_id = ? AND is_deleted = 1
+                "#,
+            id,
+            account_id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(credential)
+    }
+
     /// Retrieves credentials associated with a specific user.
     ///
     /// # Arguments
@@ -116,6 +223,14 @@ impl<'a> CredentialRepository<'a> {
                 client_cert as "client_cert?",
                 client_key as "client_key?",
                 ca_cert as "ca_cert?",
+                proxy as "proxy?",
+                rune as "rune?",
+                transport as "transport?",
+                read_only as "read_only!",
+                node_version as "node_version?",
+                network as "network?",
+                tls_cert_expires_at as "tls_cert_expires_at?: DateTime<Utc>",
+                macaroon_expires_at as "macaroon_expires_at?: DateTime<Utc>",
                 is_active as "is_active!",
                 created_at as "created_at!: DateTime<Utc>",
                 updated_at as "updated_at!: DateTime<Utc>",
@@ -131,15 +246,17 @@ impl<'a> CredentialRepository<'a> {
         Ok(credential)
     }
 
-    /// Retrieves credentials associated with a specific account.
+    /// Retrieves every active credential belonging to an account, for listing
+    /// them; connecting to the account's nodes goes through
+    /// [`Self::get_node_credentials`].
     ///
     /// # Arguments
     /// * `account_id` - Account ID (UUID format)
     ///
     /// # Returns
-    /// `Some(Credential)` if found and not deleted, `None` otherwise
-    pub async fn get_credential_by_account_id(&self, account_id: &str) -> Result<Option<Credential>> {
-        let credential = sqlx::query_as!(
+    /// All non-deleted credentials for the account, oldest first
+    pub async fn get_credentials_by_account_id(&self, account_id: &str) -> Result<Vec<Credential>> {
+        let credentials = sqlx::query_as!(
             Credential,
             r#"
                 SELECT
@@ -155,19 +272,145 @@ impl<'a> CredentialRepository<'a> {
                 client_cert as "client_cert?",
                 client_key as "client_key?",
                 ca_cert as "ca_cert?",
+                proxy as "proxy?",
+                rune as "rune?",
+                transport as "transport?",
+                read_only as "read_only!",
+                node_version as "node_version?",
+                network as "network?",
+                tls_cert_expires_at as "tls_cert_expires_at?: DateTime<Utc>",
+                macaroon_expires_at as "macaroon_expires_at?: DateTime<Utc>",
                 is_active as "is_active!",
                 created_at as "created_at!: DateTime<Utc>",
                 updated_at as "updated_at!: DateTime<Utc>",
                 is_deleted as "is_deleted!",
                 deleted_at as "deleted_at?: DateTime<Utc>"
                 FROM credentials WHERE account_id = ? AND is_deleted = 0
+                ORDER BY created_at ASC
                 "#,
             account_id
         )
-        .fetch_optional(self.pool)
+        .fetch_all(self.pool)
         .await?;
 
-        Ok(credential)
+        Ok(credentials)
+    }
+
+    /// Retrieves every active credential across all accounts, for listing
+    /// them; connecting to nodes goes through [`Self::get_node_credentials`].
+    ///
+    /// # Returns
+    /// All non-deleted credentials, most recently updated first
+    pub async fn get_active_credentials(&self) -> Result<Vec<Credential>> {
+        let credentials = sqlx::query_as!(
+            Credential,
+            r#"
+                SELECT
+                id as "id!",
+                user_id as "user_id!",
+                account_id as "account_id!",
+                node_id as "node_id!",
+                node_alias as "node_alias!",
+                macaroon as "macaroon!",
+                tls_cert as "tls_cert!",
+                address as "address!",
+                node_type as "node_type?",
+                client_cert as "client_cert?",
+                client_key as "client_key?",
+                ca_cert as "ca_cert?",
+                proxy as "proxy?",
+                rune as "rune?",
+                transport as "transport?",
+                read_only as "read_only!",
+                node_version as "node_version?",
+                network as "network?",
+                tls_cert_expires_at as "tls_cert_expires_at?: DateTime<Utc>",
+                macaroon_expires_at as "macaroon_expires_at?: DateTime<Utc>",
+                is_active as "is_active!",
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>",
+                is_deleted as "is_deleted!",
+                deleted_at as "deleted_at?: DateTime<Utc>"
+                FROM credentials WHERE is_deleted = 0
+                ORDER BY updated_at DESC
+                "#
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(credentials)
+    }
+
+    /// Retrieves one active credential per account and node, as several users
+    /// of an account may store credentials for the same node.
+    ///
+    /// This is the lookup every node connection and node membership check goes
+    /// through. Of a node's credentials, the one stored by `user_id` is used if
+    /// there is one, then a writable one before a read-only one, then the most
+    /// recently updated.
+    ///
+    /// # Arguments
+    /// * `account_id` - Account whose nodes to list, or `None` for every account
+    /// * `user_id` - User acting on the nodes, or `None` for background jobs
+    ///
+    /// # Returns
+    /// A credential for each node of the account(s), oldest node first
+    pub async fn get_node_credentials(
+        &self,
+        account_id: Option<&str>,
+        user_id: Option<&str>,
+    ) -> Result<Vec<Credential>> {
+        let credentials = sqlx::query_as!(
+            Credential,
+            r#"
+                SELECT
+                c.id as "id!",
+                c.user_id as "user_id!",
+                c.account_id as "account_id!",
+                c.node_id as "node_id!",
+                c.node_alias as "node_alias!",
+                c.macaroon as "macaroon!",
+                c.tls_cert as "tls_cert!",
+                c.address as "address!",
+                c.node_type as "node_type?",
+                c.client_cert as "client_cert?",
+                c.client_key as "client_key?",
+                c.ca_cert as "ca_cert?",
+                c.proxy as "proxy?",
+                c.rune as "rune?",
+                c.transport as "transport?",
+                c.read_only as "read_only!",
+                c.node_version as "node_version?",
+                c.network as "network?",
+                c.tls_cert_expires_at as "tls_cert_expires_at?: DateTime<Utc>",
+                c.macaroon_expires_at as "macaroon_expires_at?: DateTime<Utc>",
+                c.is_active as "is_active!",
+                c.created_at as "created_at!: DateTime<Utc>",
+                c.updated_at as "updated_at!: DateTime<Utc>",
+                c.is_deleted as "is_deleted!",
+                c.deleted_at as "deleted_at?: DateTime<Utc>"
+                FROM credentials c
+                WHERE c.is_deleted = 0
+                AND (? IS NULL OR c.account_id = ?)
+                AND c.id = (
+                    SELECT latest.id FROM credentials latest
+                    WHERE latest.account_id = c.account_id
+                    AND latest.node_id = c.node_id
+                    AND latest.is_deleted = 0
+                    ORDER BY latest.user_id IS ? DESC, latest.read_only ASC,
+                    latest.updated_at DESC
+                    LIMIT 1
+                )
+                ORDER BY c.created_at ASC
+                "#,
+            account_id,
+            account_id,
+            user_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(credentials)
     }
 
     /// Marks a credential as deleted (soft deletion).
@@ -196,4 +439,151 @@ impl<'a> CredentialRepository<'a> {
 
         Ok(())
     }
+
+    /// Records the version a node reports on the account's active credentials for it.
+    pub async fn update_node_version(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        node_version: &str,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE credentials
+            SET node_version = ?
+            WHERE account_id = ? AND node_id = ? AND is_deleted = 0
+            "#,
+            node_version,
+            account_id,
+            node_id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Records the network a node runs on with the account's active credentials for it.
+    pub async fn update_network(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        network: &str,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE credentials
+            SET network = ?
+            WHERE account_id = ? AND node_id = ? AND is_deleted = 0
+            "#,
+            network,
+            account_id,
+            node_id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Retrieves the network an account's node runs on, `None` when unknown.
+    pub async fn get_node_network(
+        &self,
+        account_id: &str,
+        node_id: &str,
+    ) -> Result<Option<String>> {
+        let network = sqlx::query_scalar!(
+            r#"
+            SELECT network as "network!"
+            FROM credentials
+            WHERE account_id = ? AND node_id = ? AND is_deleted = 0 AND network IS NOT NULL
+            ORDER BY updated_at DESC
+            LIMIT 1
+            "#,
+            account_id,
+            node_id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(network)
+    }
+
+    /// Counts the nodes of an account that users other than `user_id` store
+    /// credentials for.
+    pub async fn count_nodes_of_other_users(&self, account_id: &str, user_id: &str) -> Result<i64> {
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(DISTINCT node_id) as "count!: i64"
+            FROM credentials
+            WHERE account_id = ? AND user_id != ? AND is_deleted = 0
+            "#,
+            account_id,
+            user_id
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Replaces the connection details of an active credential in one update.
+    ///
+    /// # Returns
+    /// `true` if the credential was still active and has been updated
+    pub async fn rotate_credential(&self, credential: &Credential) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE credentials
+            SET node_alias = ?, macaroon = ?, tls_cert = ?, address = ?, node_type = ?,
+                client_cert = ?, client_key = ?, ca_cert = ?, proxy = ?, rune = ?, transport = ?,
+                read_only = ?, node_version = ?, network = ?, tls_cert_expires_at = ?,
+                macaroon_expires_at = ?, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ? AND is_deleted = 0
+            "#,
+            credential.node_alias,
+            credential.macaroon,
+            credential.tls_cert,
+            credential.address,
+            credential.node_type,
+            credential.client_cert,
+            credential.client_key,
+            credential.ca_cert,
+            credential.proxy,
+            credential.rune,
+            credential.transport,
+            credential.read_only,
+            credential.node_version,
+            credential.network,
+            credential.tls_cert_expires_at,
+            credential.macaroon_expires_at,
+            credential.id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Clears the deletion of a soft-deleted credential.
+    ///
+    /// # Returns
+    /// `true` if a deleted credential was restored
+    ///
+    /// # Errors
+    /// Fails with a unique constraint error if its user has an active credential.
+    pub async fn restore_credential(&self, id: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE credentials
+            SET is_deleted = 0, deleted_at = NULL
+            WHERE id = ? AND is_deleted = 1
+            "#,
+            id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
 }
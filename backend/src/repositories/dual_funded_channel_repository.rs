@@ -0,0 +1,106 @@
+//! Database repository for the dual-funded channels opened through NodeGaze.
+//!
+//! Node listings don't say how a channel was funded, so each channel opened
+//! with a contribution asked of the peer is recorded by its funding outpoint,
+//! which is known as soon as the funding transaction is broadcast.
+
+use crate::database::models::DualFundedChannel;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+
+/// Repository for dual-funded channel database operations.
+pub struct DualFundedChannelRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> DualFundedChannelRepository<'a> {
+    /// Creates a new DualFundedChannelRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Records a channel an account opened with both peers contributing.
+    pub async fn create_channel(
+        &self,
+        account_id: &str,
+        channel: &DualFundedChannel,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO dual_funded_channels
+            (account_id, node_id, peer_pubkey, channel_point, channel_id, local_amount_sat,
+            requested_amount_sat, compact_lease, opened_by, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (node_id, channel_point) DO NOTHING
+            "#,
+            account_id,
+            channel.node_id,
+            channel.peer_pubkey,
+            channel.channel_point,
+            channel.channel_id,
+            channel.local_amount_sat,
+            channel.requested_amount_sat,
+            channel.compact_lease,
+            channel.opened_by,
+            channel.created_at
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Retrieves the dual-funded channels of one of an account's nodes, newest first.
+    pub async fn get_channels(
+        &self,
+        account_id: &str,
+        node_id: &str,
+    ) -> Result<Vec<DualFundedChannel>> {
+        let channels = sqlx::query_as!(
+            DualFundedChannel,
+            r#"
+            SELECT
+            node_id as "node_id!",
+            peer_pubkey as "peer_pubkey!",
+            channel_point as "channel_point!",
+            channel_id as "channel_id!",
+            local_amount_sat as "local_amount_sat!",
+            requested_amount_sat as "requested_amount_sat!",
+            compact_lease as "compact_lease?",
+            opened_by as "opened_by!",
+            created_at as "created_at!: DateTime<Utc>"
+            FROM dual_funded_channels
+            WHERE account_id = ? AND node_id = ?
+            ORDER BY created_at DESC
+            "#,
+            account_id,
+            node_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(channels)
+    }
+
+    /// Funding outpoints of a node's dual-funded channels, whichever account opened them.
+    ///
+    /// How a channel was funded is a fact about the node, and channel listings
+    /// are cached per node, so the tag is shared by every account watching it.
+    pub async fn get_channel_points(&self, node_id: &str) -> Result<HashSet<String>> {
+        let channel_points = sqlx::query_scalar!(
+            r#"
+            SELECT channel_point as "channel_point!"
+            FROM dual_funded_channels
+            WHERE node_id = ?
+            "#,
+            node_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(channel_points.into_iter().collect())
+    }
+}
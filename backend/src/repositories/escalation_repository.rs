@@ -0,0 +1,47 @@
+//! Database repository for the escalation steps run for critical alerts.
+//!
+//! A step is recorded against the first stored copy of an alert before it
+//! runs, so a step that fails to send is not retried.
+
+use anyhow::Result;
+use sqlx::SqlitePool;
+
+/// Repository for escalation database operations.
+pub struct EscalationRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> EscalationRepository<'a> {
+    /// Creates a new EscalationRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Indexes of the steps already run for an event.
+    pub async fn get_run_steps(&self, event_id: &str) -> Result<Vec<i64>> {
+        let steps = sqlx::query_scalar!(
+            r#"SELECT step as "step!" FROM event_escalations WHERE event_id = ?"#,
+            event_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(steps)
+    }
+
+    /// Claims a step of an event's escalation.
+    ///
+    /// Returns false if the step was already run.
+    pub async fn record_step(&self, event_id: &str, step: i64) -> Result<bool> {
+        let result = sqlx::query!(
+            "INSERT OR IGNORE INTO event_escalations (event_id, step) VALUES (?, ?)",
+            event_id,
+            step
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
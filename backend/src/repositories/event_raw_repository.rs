@@ -0,0 +1,68 @@
+//! Database repository for raw node messages captured with events.
+
+use crate::database::models::RawEvent;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// Repository for raw event database operations.
+pub struct EventRawRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> EventRawRepository<'a> {
+    /// Creates a new EventRawRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Stores the raw message of an event.
+    pub async fn create_raw_event(
+        &self,
+        event_id: &str,
+        source: &str,
+        payload: &str,
+        size_bytes: i64,
+        truncated: bool,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO event_raw (event_id, source, payload, size_bytes, truncated)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+            event_id,
+            source,
+            payload,
+            size_bytes,
+            truncated
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Retrieves the raw message of an event.
+    pub async fn get_raw_event(&self, event_id: &str) -> Result<Option<RawEvent>> {
+        let raw = sqlx::query_as!(
+            RawEvent,
+            r#"
+            SELECT
+            event_id as "event_id!",
+            source,
+            payload,
+            size_bytes as "size_bytes!: i64",
+            truncated as "truncated!: bool",
+            created_at as "created_at!: DateTime<Utc>"
+            FROM event_raw
+            WHERE event_id = ?
+            "#,
+            event_id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(raw)
+    }
+}
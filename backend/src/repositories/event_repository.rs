@@ -1,11 +1,13 @@
 //! Database repository for event management operations.
 
 use crate::database::models::{
-    CreateEvent, Event, EventFilters, EventResponse, EventSeverity, EventType,
+    CreateEvent, Event, EventFilters, EventHourlyAggregate, EventResponse, EventSeverity, EventType,
 };
+use crate::database::write_permit;
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
 
 /// Repository for event database operations.
 pub struct EventRepository<'a> {
@@ -21,6 +23,7 @@ impl<'a> EventRepository<'a> {
 
     /// Creates a new event in the database.
     pub async fn create_event(&self, event: CreateEvent) -> Result<Event> {
+        let _permit = write_permit().await;
         let event = sqlx::query_as!(
             Event,
             r#"
@@ -39,6 +42,8 @@ impl<'a> EventRepository<'a> {
             data as "data!",
             notifications_id as "notifications_id!",
             timestamp as "timestamp!: DateTime<Utc>",
+            acknowledged_by as "acknowledged_by?",
+            acknowledged_at as "acknowledged_at?: DateTime<Utc>",
             created_at as "created_at!: DateTime<Utc>",
             updated_at as "updated_at!: DateTime<Utc>",
             is_deleted as "is_deleted!",
@@ -64,24 +69,38 @@ impl<'a> EventRepository<'a> {
     }
 
     /// Retrieves events by account ID with basic filtering.
+    ///
+    /// Events are ordered by `(timestamp, id)` descending. When a cursor is given only
+    /// events strictly after it in that order are returned and `offset` is ignored.
+    /// `node_ids`, `event_types` and `severities` restrict the result to events of
+    /// those nodes, types and severities.
+    /// `unacknowledged_only` keeps the warning and critical events nobody acknowledged.
+    /// `search` keeps events whose title or description contains the text.
+    /// `start_date` and `end_date` bound the event time, both inclusive.
     pub async fn get_events_by_account_id(
         &self,
         account_id: &str,
         filters: Option<EventFilters>,
     ) -> Result<Vec<Event>> {
-        let filters = filters.unwrap_or(EventFilters {
-            limit: None,
-            offset: None,
-            node_ids: None,
-            event_types: None,
-            severities: None,
-            start_date: None,
-            end_date: None,
-        });
+        let filters = filters.unwrap_or_default();
 
         // Simple implementation without complex dynamic queries
         let limit = filters.limit.unwrap_or(50).min(1000);
-        let offset = filters.offset.unwrap_or(0);
+        let (cursor_timestamp, cursor_id, offset) = match &filters.cursor {
+            Some(cursor) => (Some(cursor.timestamp), Some(cursor.id.clone()), 0),
+            None => (None, None, filters.offset.unwrap_or(0)),
+        };
+        let (node_ids, event_types, severities) = json_filters(&filters)?;
+        let unacknowledged_only = filters.unacknowledged_only;
+        // LIKE wildcards in the search text are matched literally.
+        let search = filters.search.map(|search| {
+            let escaped = search
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            format!("%{escaped}%")
+        });
+        let (start_date, end_date) = (filters.start_date, filters.end_date);
 
         let events = sqlx::query_as!(
             Event,
@@ -99,16 +118,46 @@ impl<'a> EventRepository<'a> {
             notifications_id as "notifications_id!",
             data as "data!",
             timestamp as "timestamp!: DateTime<Utc>",
+            acknowledged_by as "acknowledged_by?",
+            acknowledged_at as "acknowledged_at?: DateTime<Utc>",
             created_at as "created_at!: DateTime<Utc>",
             updated_at as "updated_at!: DateTime<Utc>",
             is_deleted as "is_deleted!",
             deleted_at as "deleted_at?: DateTime<Utc>"
             FROM events
             WHERE account_id = ? AND is_deleted = 0
-            ORDER BY timestamp DESC
+            AND (? IS NULL OR node_id IN (SELECT value FROM json_each(?)))
+            AND (? IS NULL OR event_type IN (SELECT value FROM json_each(?)))
+            AND (? IS NULL OR severity IN (SELECT value FROM json_each(?)))
+            AND (? IS NULL OR timestamp < ? OR (timestamp = ? AND id < ?))
+            AND (? = 0 OR (acknowledged_at IS NULL AND severity IN (?, ?)))
+            AND (? IS NULL OR title LIKE ? ESCAPE '\' OR description LIKE ? ESCAPE '\')
+            AND (? IS NULL OR timestamp >= ?)
+            AND (? IS NULL OR timestamp <= ?)
+            ORDER BY timestamp DESC, id DESC
             LIMIT ? OFFSET ?
             "#,
             account_id,
+            node_ids,
+            node_ids,
+            event_types,
+            event_types,
+            severities,
+            severities,
+            cursor_timestamp,
+            cursor_timestamp,
+            cursor_timestamp,
+            cursor_id,
+            unacknowledged_only,
+            EventSeverity::Warning,
+            EventSeverity::Critical,
+            search,
+            search,
+            search,
+            start_date,
+            start_date,
+            end_date,
+            end_date,
             limit,
             offset
         )
@@ -118,6 +167,361 @@ impl<'a> EventRepository<'a> {
         Ok(events)
     }
 
+    /// Counts the non-deleted events recorded for an account that match the node,
+    /// type, severity, acknowledgement and time filters; paging and search are ignored.
+    pub async fn count_events_by_account_id(
+        &self,
+        account_id: &str,
+        filters: &EventFilters,
+    ) -> Result<i64> {
+        let (node_ids, event_types, severities) = json_filters(filters)?;
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count FROM events
+            WHERE account_id = ? AND is_deleted = 0
+            AND (? IS NULL OR node_id IN (SELECT value FROM json_each(?)))
+            AND (? IS NULL OR event_type IN (SELECT value FROM json_each(?)))
+            AND (? IS NULL OR severity IN (SELECT value FROM json_each(?)))
+            AND (? = 0 OR (acknowledged_at IS NULL AND severity IN (?, ?)))
+            AND (? IS NULL OR timestamp >= ?)
+            AND (? IS NULL OR timestamp <= ?)
+            "#,
+            account_id,
+            node_ids,
+            node_ids,
+            event_types,
+            event_types,
+            severities,
+            severities,
+            filters.unacknowledged_only,
+            EventSeverity::Warning,
+            EventSeverity::Critical,
+            filters.start_date,
+            filters.start_date,
+            filters.end_date,
+            filters.end_date
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(result.count)
+    }
+
+    /// Soft deletes an account's events that match the same filters as
+    /// `count_events_by_account_id`.
+    ///
+    /// Returns the number of deleted events.
+    pub async fn soft_delete_events(
+        &self,
+        account_id: &str,
+        filters: &EventFilters,
+    ) -> Result<u64> {
+        let (node_ids, event_types, severities) = json_filters(filters)?;
+        let _permit = write_permit().await;
+        let result = sqlx::query!(
+            r#"
+            UPDATE events
+            SET is_deleted = 1, deleted_at = CURRENT_TIMESTAMP
+            WHERE account_id = ? AND is_deleted = 0
+            AND (? IS NULL OR node_id IN (SELECT value FROM json_each(?)))
+            AND (? IS NULL OR event_type IN (SELECT value FROM json_each(?)))
+            AND (? IS NULL OR severity IN (SELECT value FROM json_each(?)))
+            AND (? = 0 OR (acknowledged_at IS NULL AND severity IN (?, ?)))
+            AND (? IS NULL OR timestamp >= ?)
+            AND (? IS NULL OR timestamp <= ?)
+            "#,
+            account_id,
+            node_ids,
+            node_ids,
+            event_types,
+            event_types,
+            severities,
+            severities,
+            filters.unacknowledged_only,
+            EventSeverity::Warning,
+            EventSeverity::Critical,
+            filters.start_date,
+            filters.start_date,
+            filters.end_date,
+            filters.end_date
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Retrieves the time and severity of an account's events in `[start, end]`,
+    /// optionally limited to some nodes.
+    pub async fn get_event_severities_between(
+        &self,
+        account_id: &str,
+        node_ids: Option<&[String]>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<(DateTime<Utc>, EventSeverity)>> {
+        let node_ids = node_ids.map(serde_json::to_string).transpose()?;
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+            timestamp as "timestamp!: DateTime<Utc>",
+            severity as "severity: EventSeverity"
+            FROM events
+            WHERE account_id = ? AND is_deleted = 0
+            AND (? IS NULL OR node_id IN (SELECT value FROM json_each(?)))
+            AND timestamp >= ? AND timestamp <= ?
+            "#,
+            account_id,
+            node_ids,
+            node_ids,
+            start,
+            end
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.timestamp, row.severity))
+            .collect())
+    }
+
+    /// Retrieves the most recent events of a given severity for an account.
+    pub async fn get_recent_events_by_severity(
+        &self,
+        account_id: &str,
+        severity: EventSeverity,
+        limit: i64,
+    ) -> Result<Vec<Event>> {
+        let events = sqlx::query_as!(
+            Event,
+            r#"
+            SELECT
+            id as "id!",
+            account_id as "account_id!",
+            user_id as "user_id!",
+            node_id as "node_id!",
+            node_alias as "node_alias!",
+            event_type as "event_type: EventType",
+            severity as "severity: EventSeverity",
+            title as "title!",
+            description as "description!",
+            notifications_id as "notifications_id?",
+            data as "data!",
+            timestamp as "timestamp!: DateTime<Utc>",
+            acknowledged_by as "acknowledged_by?",
+            acknowledged_at as "acknowledged_at?: DateTime<Utc>",
+            created_at as "created_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>",
+            is_deleted as "is_deleted!",
+            deleted_at as "deleted_at?: DateTime<Utc>"
+            FROM events
+            WHERE account_id = ? AND severity = ? AND is_deleted = 0
+            ORDER BY timestamp DESC
+            LIMIT ?
+            "#,
+            account_id,
+            severity,
+            limit
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(events)
+    }
+
+    /// Retrieves the events of a given severity across all accounts since
+    /// `since`, acknowledged or not, oldest first.
+    pub async fn get_events_by_severity_since(
+        &self,
+        severity: EventSeverity,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<Event>> {
+        let events = sqlx::query_as!(
+            Event,
+            r#"
+            SELECT
+            id as "id!",
+            account_id as "account_id!",
+            user_id as "user_id!",
+            node_id as "node_id!",
+            node_alias as "node_alias!",
+            event_type as "event_type: EventType",
+            severity as "severity: EventSeverity",
+            title as "title!",
+            description as "description!",
+            notifications_id as "notifications_id?",
+            data as "data!",
+            timestamp as "timestamp!: DateTime<Utc>",
+            acknowledged_by as "acknowledged_by?",
+            acknowledged_at as "acknowledged_at?: DateTime<Utc>",
+            created_at as "created_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>",
+            is_deleted as "is_deleted!",
+            deleted_at as "deleted_at?: DateTime<Utc>"
+            FROM events
+            WHERE severity = ? AND timestamp >= ? AND is_deleted = 0
+            ORDER BY timestamp ASC, id ASC
+            "#,
+            severity,
+            since
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(events)
+    }
+
+    /// Retrieves a node's events of the given types recorded in `[start, end)`, oldest first.
+    pub async fn get_node_events_between(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        event_types: &[EventType],
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Event>> {
+        // Types are bound as a JSON array of their stored names and expanded with json_each.
+        let event_types = serde_json::to_string(event_types)?;
+
+        let events = sqlx::query_as!(
+            Event,
+            r#"
+            SELECT
+            id as "id!",
+            account_id as "account_id!",
+            user_id as "user_id!",
+            node_id as "node_id!",
+            node_alias as "node_alias!",
+            event_type as "event_type: EventType",
+            severity as "severity: EventSeverity",
+            title as "title!",
+            description as "description!",
+            notifications_id as "notifications_id?",
+            data as "data!",
+            timestamp as "timestamp!: DateTime<Utc>",
+            acknowledged_by as "acknowledged_by?",
+            acknowledged_at as "acknowledged_at?: DateTime<Utc>",
+            created_at as "created_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>",
+            is_deleted as "is_deleted!",
+            deleted_at as "deleted_at?: DateTime<Utc>"
+            FROM events
+            WHERE account_id = ? AND node_id = ? AND is_deleted = 0
+            AND event_type IN (SELECT value FROM json_each(?))
+            AND timestamp >= ? AND timestamp < ?
+            ORDER BY timestamp ASC
+            "#,
+            account_id,
+            node_id,
+            event_types,
+            start,
+            end
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(events)
+    }
+
+    /// Counts warning and critical events recorded for an account since a point in time.
+    pub async fn count_alerts_since(&self, account_id: &str, since: DateTime<Utc>) -> Result<i64> {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count FROM events
+            WHERE account_id = ? AND severity IN (?, ?) AND timestamp >= ? AND is_deleted = 0
+            "#,
+            account_id,
+            EventSeverity::Warning,
+            EventSeverity::Critical,
+            since
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(result.count)
+    }
+
+    /// Counts the warning and critical events of an account nobody has acknowledged.
+    pub async fn count_unacknowledged_alerts(&self, account_id: &str) -> Result<i64> {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count FROM events
+            WHERE account_id = ? AND severity IN (?, ?) AND acknowledged_at IS NULL
+            AND is_deleted = 0
+            "#,
+            account_id,
+            EventSeverity::Warning,
+            EventSeverity::Critical
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(result.count)
+    }
+
+    /// Retrieves a non-deleted event of an account by ID.
+    pub async fn get_event_by_id(&self, account_id: &str, id: &str) -> Result<Option<Event>> {
+        let event = sqlx::query_as!(
+            Event,
+            r#"
+            SELECT
+            id as "id!",
+            account_id as "account_id!",
+            user_id as "user_id!",
+            node_id as "node_id!",
+            node_alias as "node_alias!",
+            event_type as "event_type: EventType",
+            severity as "severity: EventSeverity",
+            title as "title!",
+            description as "description!",
+            notifications_id as "notifications_id?",
+            data as "data!",
+            timestamp as "timestamp!: DateTime<Utc>",
+            acknowledged_by as "acknowledged_by?",
+            acknowledged_at as "acknowledged_at?: DateTime<Utc>",
+            created_at as "created_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>",
+            is_deleted as "is_deleted!",
+            deleted_at as "deleted_at?: DateTime<Utc>"
+            FROM events
+            WHERE id = ? AND account_id = ? AND is_deleted = 0
+            "#,
+            id,
+            account_id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(event)
+    }
+
+    /// Acknowledges every endpoint's copy of an event, unless already acknowledged.
+    ///
+    /// Returns the number of copies updated.
+    pub async fn acknowledge_event_copies(&self, event: &Event, user_id: &str) -> Result<u64> {
+        let now = Utc::now();
+        let _permit = write_permit().await;
+        let result = sqlx::query!(
+            r#"
+            UPDATE events SET acknowledged_by = ?, acknowledged_at = ?
+            WHERE account_id = ? AND node_id = ? AND event_type = ? AND title = ?
+            AND timestamp = ? AND acknowledged_at IS NULL AND is_deleted = 0
+            "#,
+            user_id,
+            now,
+            event.account_id,
+            event.node_id,
+            event.event_type,
+            event.title,
+            event.timestamp
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
     /// Gets events by notification ID.
     pub async fn get_events_by_notification_id(
         &self,
@@ -140,6 +544,8 @@ impl<'a> EventRepository<'a> {
               description as "description!",
               data as "data!",
               timestamp as "timestamp!: DateTime<Utc>",
+              acknowledged_by as "acknowledged_by?",
+              acknowledged_at as "acknowledged_at?: DateTime<Utc>",
               notifications_id as "notifications_id?",
               created_at as "created_at!: DateTime<Utc>",
               updated_at as "updated_at!: DateTime<Utc>",
@@ -158,14 +564,59 @@ impl<'a> EventRepository<'a> {
         .await?;
 
         // Convert to EventResponse
-        let event_responses = events
-            .into_iter()
-            .map(|event| EventResponse::from(event))
-            .collect();
+        let event_responses = events.into_iter().map(EventResponse::from).collect();
 
         Ok(event_responses)
     }
 
+    /// Gets the events stored for a notification endpoint between `start` and
+    /// `end`, oldest first.
+    pub async fn get_notification_events_between(
+        &self,
+        notifications_id: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<Event>> {
+        let events = sqlx::query_as!(
+            Event,
+            r#"
+              SELECT
+              id as "id!",
+              account_id as "account_id!",
+              user_id as "user_id!",
+              node_id as "node_id!",
+              node_alias as "node_alias!",
+              event_type as "event_type: EventType",
+              severity as "severity: EventSeverity",
+              title as "title!",
+              description as "description!",
+              data as "data!",
+              timestamp as "timestamp!: DateTime<Utc>",
+              acknowledged_by as "acknowledged_by?",
+              acknowledged_at as "acknowledged_at?: DateTime<Utc>",
+              notifications_id as "notifications_id?",
+              created_at as "created_at!: DateTime<Utc>",
+              updated_at as "updated_at!: DateTime<Utc>",
+              is_deleted as "is_deleted!",
+              deleted_at as "deleted_at?: DateTime<Utc>"
+              FROM events
+              WHERE notifications_id = ? AND is_deleted = 0
+              AND timestamp >= ? AND timestamp <= ?
+              ORDER BY timestamp ASC, id ASC
+              LIMIT ?
+              "#,
+            notifications_id,
+            start,
+            end,
+            limit
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(events)
+    }
+
     /// Gets event count by notification ID.
     pub async fn count_events_by_notification_id(&self, notifications_id: &str) -> Result<i64> {
         let result = sqlx::query!(
@@ -177,4 +628,256 @@ impl<'a> EventRepository<'a> {
 
         Ok(result.count)
     }
+
+    /// Returns the payment hashes of the node's recorded `invoice_settled` events.
+    pub async fn get_settled_invoice_hashes(
+        &self,
+        account_id: &str,
+        node_id: &str,
+    ) -> Result<HashSet<String>> {
+        let event_type = EventType::InvoiceSettled;
+        let hashes = sqlx::query_scalar!(
+            r#"
+            SELECT json_extract(data, '$.hash') as "hash!: String"
+            FROM events
+            WHERE account_id = ? AND node_id = ? AND event_type = ?
+            AND json_extract(data, '$.hash') IS NOT NULL
+            "#,
+            account_id,
+            node_id,
+            event_type
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(hashes.into_iter().collect())
+    }
+
+    /// Returns the channel points of the node's recorded events of a type.
+    pub async fn get_event_channel_points(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        event_type: EventType,
+    ) -> Result<HashSet<String>> {
+        let channel_points = sqlx::query_scalar!(
+            r#"
+            SELECT json_extract(data, '$.channel_point') as "channel_point!: String"
+            FROM events
+            WHERE account_id = ? AND node_id = ? AND event_type = ?
+            AND json_extract(data, '$.channel_point') IS NOT NULL
+            "#,
+            account_id,
+            node_id,
+            event_type
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(channel_points.into_iter().collect())
+    }
+
+    /// Returns the latest recorded invoice event of each of the given hashes on a
+    /// node, with when it occurred.
+    pub async fn get_latest_invoice_events(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        payment_hashes: &[String],
+    ) -> Result<HashMap<String, (EventType, DateTime<Utc>)>> {
+        let hashes = serde_json::to_string(payment_hashes)?;
+        let event_types = serde_json::to_string(&[
+            EventType::InvoiceCreated,
+            EventType::InvoiceAccepted,
+            EventType::InvoiceSettled,
+            EventType::InvoiceCancelled,
+            EventType::InvoiceExpired,
+        ])?;
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+            json_extract(data, '$.hash') as "hash!: String",
+            event_type as "event_type!: EventType",
+            timestamp as "timestamp!: DateTime<Utc>"
+            FROM events
+            WHERE account_id = ? AND node_id = ? AND is_deleted = 0
+            AND event_type IN (SELECT value FROM json_each(?))
+            AND json_extract(data, '$.hash') IN (SELECT value FROM json_each(?))
+            ORDER BY timestamp, id
+            "#,
+            account_id,
+            node_id,
+            event_types,
+            hashes
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        // Later events of a hash replace earlier ones
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.hash, (row.event_type, row.timestamp)))
+            .collect())
+    }
+
+    /// Permanently deletes events that occurred before `cutoff`.
+    ///
+    /// Returns the number of deleted events.
+    pub async fn purge_events_before(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        let _permit = write_permit().await;
+        let result = sqlx::query!("DELETE FROM events WHERE timestamp < ?", cutoff)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Permanently deletes an account's events that occurred before `cutoff`.
+    ///
+    /// Returns the number of deleted events.
+    pub async fn purge_account_events_before(
+        &self,
+        account_id: &str,
+        cutoff: DateTime<Utc>,
+    ) -> Result<u64> {
+        let _permit = write_permit().await;
+        let result = sqlx::query!(
+            "DELETE FROM events WHERE account_id = ? AND timestamp < ?",
+            account_id,
+            cutoff
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Folds an account's events of the given types that occurred before
+    /// `cutoff` into hourly aggregates and deletes them.
+    ///
+    /// Amounts are taken from the events' `value_msat` or `amount_msat` data.
+    /// Returns the number of compacted events.
+    pub async fn compact_account_events_before(
+        &self,
+        account_id: &str,
+        event_types: &[EventType],
+        cutoff: DateTime<Utc>,
+    ) -> Result<u64> {
+        let event_types = serde_json::to_string(event_types)?;
+        let _permit = write_permit().await;
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO event_hourly_aggregates
+            (account_id, node_id, event_type, severity, hour, event_count, amount_msat)
+            SELECT
+            account_id,
+            node_id,
+            event_type,
+            severity,
+            strftime('%Y-%m-%dT%H:00:00+00:00', timestamp),
+            COUNT(*),
+            COALESCE(SUM(COALESCE(
+                json_extract(data, '$.value_msat'),
+                json_extract(data, '$.amount_msat'),
+                0
+            )), 0)
+            FROM events
+            WHERE account_id = ? AND is_deleted = 0 AND timestamp < ?
+            AND event_type IN (SELECT value FROM json_each(?))
+            GROUP BY account_id, node_id, event_type, severity,
+            strftime('%Y-%m-%dT%H:00:00+00:00', timestamp)
+            ON CONFLICT (account_id, node_id, event_type, severity, hour) DO UPDATE SET
+            event_count = event_count + excluded.event_count,
+            amount_msat = amount_msat + excluded.amount_msat
+            "#,
+            account_id,
+            cutoff,
+            event_types
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM events
+            WHERE account_id = ? AND timestamp < ?
+            AND event_type IN (SELECT value FROM json_each(?))
+            "#,
+            account_id,
+            cutoff,
+            event_types
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Retrieves an account's hourly aggregates of compacted events in
+    /// `[start, end]`, optionally limited to some nodes, oldest first.
+    pub async fn get_hourly_aggregates_between(
+        &self,
+        account_id: &str,
+        node_ids: Option<&[String]>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<EventHourlyAggregate>> {
+        let node_ids = node_ids.map(serde_json::to_string).transpose()?;
+        let aggregates = sqlx::query_as!(
+            EventHourlyAggregate,
+            r#"
+            SELECT
+            node_id as "node_id!",
+            event_type as "event_type!: EventType",
+            severity as "severity!: EventSeverity",
+            hour as "hour!: DateTime<Utc>",
+            event_count as "event_count!",
+            amount_msat as "amount_msat!"
+            FROM event_hourly_aggregates
+            WHERE account_id = ?
+            AND (? IS NULL OR node_id IN (SELECT value FROM json_each(?)))
+            AND hour >= ? AND hour <= ?
+            ORDER BY hour, node_id, event_type
+            "#,
+            account_id,
+            node_ids,
+            node_ids,
+            start,
+            end
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(aggregates)
+    }
+}
+
+/// Binds the node, type and severity restrictions of `filters` as JSON arrays,
+/// which the queries expand with json_each.
+///
+/// Types and severities serialize to their variant names, the text sqlx stores
+/// them as.
+fn json_filters(
+    filters: &EventFilters,
+) -> Result<(Option<String>, Option<String>, Option<String>)> {
+    Ok((
+        filters
+            .node_ids
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?,
+        filters
+            .event_types
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?,
+        filters
+            .severities
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?,
+    ))
 }
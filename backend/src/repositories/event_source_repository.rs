@@ -0,0 +1,122 @@
+//! Database repository for external event sources.
+//!
+//! Only the SHA-256 of a source's ingest token is stored; the token itself is
+//! shown once when the source is created.
+
+use crate::database::models::EventSource;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// Repository for event source database operations.
+pub struct EventSourceRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> EventSourceRepository<'a> {
+    /// Creates a new EventSourceRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Stores a new event source.
+    pub async fn create_source(&self, source: &EventSource) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO event_sources (id, account_id, name, node_id, created_by, token_hash, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+            source.id,
+            source.account_id,
+            source.name,
+            source.node_id,
+            source.created_by,
+            source.token_hash,
+            source.created_at
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Retrieves the event sources of an account.
+    pub async fn get_sources_by_account_id(&self, account_id: &str) -> Result<Vec<EventSource>> {
+        let sources = sqlx::query_as!(
+            EventSource,
+            r#"
+            SELECT
+            id as "id!",
+            account_id as "account_id!",
+            name as "name!",
+            node_id as "node_id?",
+            created_by as "created_by!",
+            token_hash as "token_hash!",
+            last_used_at as "last_used_at?: DateTime<Utc>",
+            created_at as "created_at!: DateTime<Utc>"
+            FROM event_sources
+            WHERE account_id = ?
+            ORDER BY created_at
+            "#,
+            account_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(sources)
+    }
+
+    /// Retrieves the event source an ingest token belongs to.
+    pub async fn get_source_by_token_hash(&self, token_hash: &str) -> Result<Option<EventSource>> {
+        let source = sqlx::query_as!(
+            EventSource,
+            r#"
+            SELECT
+            id as "id!",
+            account_id as "account_id!",
+            name as "name!",
+            node_id as "node_id?",
+            created_by as "created_by!",
+            token_hash as "token_hash!",
+            last_used_at as "last_used_at?: DateTime<Utc>",
+            created_at as "created_at!: DateTime<Utc>"
+            FROM event_sources
+            WHERE token_hash = ?
+            "#,
+            token_hash
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(source)
+    }
+
+    /// Records that a source just pushed an event.
+    pub async fn touch_source(&self, id: &str, used_at: DateTime<Utc>) -> Result<()> {
+        sqlx::query!(
+            "UPDATE event_sources SET last_used_at = ? WHERE id = ?",
+            used_at,
+            id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Removes one of an account's event sources, revoking its token.
+    ///
+    /// Returns whether the source existed.
+    pub async fn delete_source(&self, account_id: &str, id: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM event_sources WHERE account_id = ? AND id = ?",
+            account_id,
+            id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,53 @@
+//! Database repository for cached external node profiles.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// Repository for the external node profile cache.
+pub struct ExternalProfileRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> ExternalProfileRepository<'a> {
+    /// Creates a new ExternalProfileRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves the cached profile JSON of a node and when it was fetched.
+    pub async fn get_profile(&self, pubkey: &str) -> Result<Option<(String, DateTime<Utc>)>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT profile as "profile!", fetched_at as "fetched_at!: DateTime<Utc>"
+            FROM external_node_profiles
+            WHERE pubkey = ?
+            "#,
+            pubkey
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(row.map(|row| (row.profile, row.fetched_at)))
+    }
+
+    /// Stores a node's profile JSON, replacing any previous one.
+    pub async fn save_profile(
+        &self,
+        pubkey: &str,
+        profile: &str,
+        fetched_at: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query!(
+            "INSERT OR REPLACE INTO external_node_profiles (pubkey, profile, fetched_at) VALUES (?, ?, ?)",
+            pubkey,
+            profile,
+            fetched_at
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,330 @@
+//! Database repository for the cached channel graphs of monitored nodes.
+//!
+//! Each node's view of the graph is kept apart under its `node_id`, since
+//! nodes see gossip at different times. A full sync replaces the whole view,
+//! gossip updates in between only touch the rows they change.
+
+use crate::database::models::{GraphChanges, GraphEdgeRecord, GraphNodeRecord, GraphSync};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// Repository for cached graph database operations.
+pub struct GraphRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> GraphRepository<'a> {
+    /// Creates a new GraphRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves when a node's graph was synced, if it is cached.
+    pub async fn get_sync(&self, node_id: &str) -> Result<Option<GraphSync>> {
+        let sync = sqlx::query_as!(
+            GraphSync,
+            r#"
+            SELECT
+            node_id as "node_id!",
+            synced_at as "synced_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>"
+            FROM graph_syncs
+            WHERE node_id = ?
+            "#,
+            node_id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(sync)
+    }
+
+    /// Retrieves the syncs of every cached graph.
+    pub async fn get_syncs(&self) -> Result<Vec<GraphSync>> {
+        let syncs = sqlx::query_as!(
+            GraphSync,
+            r#"
+            SELECT
+            node_id as "node_id!",
+            synced_at as "synced_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>"
+            FROM graph_syncs
+            ORDER BY node_id
+            "#
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(syncs)
+    }
+
+    /// Retrieves every channel of a node's cached graph.
+    pub async fn get_edges(&self, node_id: &str) -> Result<Vec<GraphEdgeRecord>> {
+        let edges = sqlx::query_as!(
+            GraphEdgeRecord,
+            r#"
+            SELECT
+            channel_id as "channel_id!",
+            node1 as "node1!",
+            node2 as "node2!",
+            capacity_sat as "capacity_sat!",
+            node1_base_fee_msat as "node1_base_fee_msat?",
+            node1_fee_rate_ppm as "node1_fee_rate_ppm?",
+            node1_last_update as "node1_last_update?",
+            node2_base_fee_msat as "node2_base_fee_msat?",
+            node2_fee_rate_ppm as "node2_fee_rate_ppm?",
+            node2_last_update as "node2_last_update?"
+            FROM graph_edges
+            WHERE node_id = ?
+            ORDER BY channel_id
+            "#,
+            node_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(edges)
+    }
+
+    /// Retrieves the cached channels among `channel_ids`.
+    pub async fn get_edges_by_id(
+        &self,
+        node_id: &str,
+        channel_ids: &[i64],
+    ) -> Result<Vec<GraphEdgeRecord>> {
+        let channel_ids = serde_json::to_string(channel_ids)?;
+        let edges = sqlx::query_as!(
+            GraphEdgeRecord,
+            r#"
+            SELECT
+            channel_id as "channel_id!",
+            node1 as "node1!",
+            node2 as "node2!",
+            capacity_sat as "capacity_sat!",
+            node1_base_fee_msat as "node1_base_fee_msat?",
+            node1_fee_rate_ppm as "node1_fee_rate_ppm?",
+            node1_last_update as "node1_last_update?",
+            node2_base_fee_msat as "node2_base_fee_msat?",
+            node2_fee_rate_ppm as "node2_fee_rate_ppm?",
+            node2_last_update as "node2_last_update?"
+            FROM graph_edges
+            WHERE node_id = ? AND channel_id IN (SELECT value FROM json_each(?))
+            "#,
+            node_id,
+            channel_ids
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(edges)
+    }
+
+    /// Retrieves every node of a node's cached graph.
+    pub async fn get_nodes(&self, node_id: &str) -> Result<Vec<GraphNodeRecord>> {
+        let nodes = sqlx::query_as!(
+            GraphNodeRecord,
+            r#"
+            SELECT
+            pubkey as "pubkey!",
+            alias as "alias!",
+            color as "color?",
+            last_update as "last_update?",
+            addresses as "addresses!"
+            FROM graph_nodes
+            WHERE node_id = ?
+            ORDER BY pubkey
+            "#,
+            node_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(nodes)
+    }
+
+    /// Retrieves a node of the cached graph with the count and total capacity
+    /// of its cached channels.
+    pub async fn get_node(
+        &self,
+        node_id: &str,
+        pubkey: &str,
+    ) -> Result<Option<(GraphNodeRecord, i64, i64)>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+            pubkey as "pubkey!",
+            alias as "alias!",
+            color as "color?",
+            last_update as "last_update?",
+            addresses as "addresses!",
+            (SELECT COUNT(*) FROM graph_edges e
+                WHERE e.node_id = n.node_id AND (e.node1 = n.pubkey OR e.node2 = n.pubkey))
+                as "num_channels!: i64",
+            (SELECT COALESCE(SUM(capacity_sat), 0) FROM graph_edges e
+                WHERE e.node_id = n.node_id AND (e.node1 = n.pubkey OR e.node2 = n.pubkey))
+                as "total_capacity_sat!: i64"
+            FROM graph_nodes n
+            WHERE node_id = ? AND pubkey = ?
+            "#,
+            node_id,
+            pubkey
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(row.map(|row| {
+            (
+                GraphNodeRecord {
+                    pubkey: row.pubkey,
+                    alias: row.alias,
+                    color: row.color,
+                    last_update: row.last_update,
+                    addresses: row.addresses,
+                },
+                row.num_channels,
+                row.total_capacity_sat,
+            )
+        }))
+    }
+
+    /// Applies changes to a node's cached graph in one transaction. A full
+    /// sync first drops the whole cached graph, so `changes` is all of it.
+    pub async fn apply_changes(
+        &self,
+        node_id: &str,
+        changes: &GraphChanges,
+        full_sync: bool,
+        at: DateTime<Utc>,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        if full_sync {
+            sqlx::query!("DELETE FROM graph_nodes WHERE node_id = ?", node_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query!("DELETE FROM graph_edges WHERE node_id = ?", node_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        for node in &changes.nodes {
+            sqlx::query!(
+                r#"
+                INSERT OR REPLACE INTO graph_nodes
+                (node_id, pubkey, alias, color, last_update, addresses)
+                VALUES (?, ?, ?, ?, ?, ?)
+                "#,
+                node_id,
+                node.pubkey,
+                node.alias,
+                node.color,
+                node.last_update,
+                node.addresses
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        for edge in &changes.edges {
+            sqlx::query!(
+                r#"
+                INSERT OR REPLACE INTO graph_edges
+                (node_id, channel_id, node1, node2, capacity_sat,
+                node1_base_fee_msat, node1_fee_rate_ppm, node1_last_update,
+                node2_base_fee_msat, node2_fee_rate_ppm, node2_last_update)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+                node_id,
+                edge.channel_id,
+                edge.node1,
+                edge.node2,
+                edge.capacity_sat,
+                edge.node1_base_fee_msat,
+                edge.node1_fee_rate_ppm,
+                edge.node1_last_update,
+                edge.node2_base_fee_msat,
+                edge.node2_fee_rate_ppm,
+                edge.node2_last_update
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let removed_nodes = serde_json::to_string(&changes.removed_nodes)?;
+        sqlx::query!(
+            "DELETE FROM graph_nodes WHERE node_id = ? AND pubkey IN (SELECT value FROM json_each(?))",
+            node_id,
+            removed_nodes
+        )
+        .execute(&mut *tx)
+        .await?;
+        let closed_channels = serde_json::to_string(&changes.closed_channels)?;
+        sqlx::query!(
+            "DELETE FROM graph_edges WHERE node_id = ? AND channel_id IN (SELECT value FROM json_each(?))",
+            node_id,
+            closed_channels
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if full_sync {
+            sqlx::query!(
+                r#"
+                INSERT INTO graph_syncs (node_id, synced_at, updated_at)
+                VALUES (?, ?, ?)
+                ON CONFLICT (node_id) DO UPDATE SET
+                synced_at = excluded.synced_at,
+                updated_at = excluded.updated_at
+                "#,
+                node_id,
+                at,
+                at
+            )
+            .execute(&mut *tx)
+            .await?;
+        } else {
+            sqlx::query!(
+                "UPDATE graph_syncs SET updated_at = ? WHERE node_id = ?",
+                at,
+                node_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Records a fetch of the whole graph that found nothing else to change.
+    pub async fn mark_synced(&self, node_id: &str, synced_at: DateTime<Utc>) -> Result<()> {
+        sqlx::query!(
+            "UPDATE graph_syncs SET synced_at = ? WHERE node_id = ?",
+            synced_at,
+            node_id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Deletes a node's cached graph.
+    pub async fn delete_graph(&self, node_id: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!("DELETE FROM graph_nodes WHERE node_id = ?", node_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM graph_edges WHERE node_id = ?", node_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM graph_syncs WHERE node_id = ?", node_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
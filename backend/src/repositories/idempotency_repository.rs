@@ -0,0 +1,119 @@
+//! Database repository for idempotency keys.
+
+use crate::database::models::IdempotencyRecord;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// Repository for idempotency key database operations.
+pub struct IdempotencyRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> IdempotencyRepository<'a> {
+    /// Creates a new IdempotencyRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Claims a key for a request that is about to run.
+    ///
+    /// Returns false if the user already used the key.
+    pub async fn reserve(
+        &self,
+        user_id: &str,
+        key: &str,
+        request_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO idempotency_keys (user_id, idempotency_key, request_hash, expires_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT (user_id, idempotency_key) DO NOTHING
+            "#,
+            user_id,
+            key,
+            request_hash,
+            expires_at
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Retrieves a user's key.
+    pub async fn get(&self, user_id: &str, key: &str) -> Result<Option<IdempotencyRecord>> {
+        let record = sqlx::query_as!(
+            IdempotencyRecord,
+            r#"
+            SELECT
+            request_hash,
+            status_code as "status_code?: i64",
+            content_type,
+            response_body
+            FROM idempotency_keys
+            WHERE user_id = ? AND idempotency_key = ?
+            "#,
+            user_id,
+            key
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(record)
+    }
+
+    /// Stores the response of a reserved key.
+    pub async fn complete(
+        &self,
+        user_id: &str,
+        key: &str,
+        status_code: i64,
+        content_type: Option<&str>,
+        response_body: &[u8],
+        expires_at: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE idempotency_keys
+            SET status_code = ?, content_type = ?, response_body = ?, expires_at = ?
+            WHERE user_id = ? AND idempotency_key = ?
+            "#,
+            status_code,
+            content_type,
+            response_body,
+            expires_at,
+            user_id,
+            key
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Frees a reserved key whose response could not be stored.
+    pub async fn release(&self, user_id: &str, key: &str) -> Result<()> {
+        sqlx::query!(
+            "DELETE FROM idempotency_keys WHERE user_id = ? AND idempotency_key = ?",
+            user_id,
+            key
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Deletes keys past their expiry.
+    pub async fn purge_expired(&self, now: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query!("DELETE FROM idempotency_keys WHERE expires_at <= ?", now)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
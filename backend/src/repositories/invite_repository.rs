@@ -67,10 +67,6 @@ impl<'a> InviteRepository<'a> {
         Ok(invite)
     }
 
-    /// Updates an existing invite status in the database.
-    ///
-    /// # Arguments
-    /// * `invite_id` - Unique identifier of the invite to update
     /// Retrieves a invite by their unique identifier.
     ///
     /// # Arguments
@@ -1,7 +1,32 @@
 pub mod account_repository;
+pub mod audit_log_repository;
+pub mod auto_fee_repository;
+pub mod channel_acceptor_repository;
+pub mod channel_policy_repository;
+pub mod channel_tag_repository;
 pub mod credential_repository;
+pub mod dual_funded_channel_repository;
+pub mod escalation_repository;
+pub mod event_raw_repository;
 pub mod event_repository;
+pub mod event_source_repository;
+pub mod external_profile_repository;
+pub mod graph_repository;
+pub mod idempotency_repository;
 pub mod invite_repository;
+pub mod node_group_repository;
+pub mod node_label_repository;
 pub mod notification_repository;
+pub mod payment_annotation_repository;
+pub mod payment_watch_repository;
+pub mod peer_blocklist_repository;
+pub mod peer_ping_repository;
+pub mod price_repository;
+pub mod quota_repository;
+pub mod report_repository;
 pub mod role_repository;
+pub mod session_repository;
+pub mod settings_repository;
+pub mod stats_rollup_repository;
 pub mod user_repository;
+pub mod webhook_delivery_repository;
@@ -0,0 +1,282 @@
+//! Database repository for node group operations.
+//!
+//! Node groups tag an account's nodes and assign users to them, limiting which
+//! nodes non-admin members can see.
+
+use crate::database::models::{CreateNodeGroup, NodeGroup};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// Repository for node group database operations.
+pub struct NodeGroupRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> NodeGroupRepository<'a> {
+    /// Creates a new NodeGroupRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Creates a new node group in the database.
+    pub async fn create_group(&self, group: CreateNodeGroup) -> Result<NodeGroup> {
+        let group = sqlx::query_as!(
+            NodeGroup,
+            r#"
+            INSERT INTO node_groups (id, account_id, name, description)
+            VALUES (?, ?, ?, ?)
+            RETURNING
+            id as "id!",
+            account_id as "account_id!",
+            name as "name!",
+            description as "description?",
+            created_at as "created_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>",
+            is_deleted as "is_deleted!",
+            deleted_at as "deleted_at?: DateTime<Utc>"
+            "#,
+            group.id,
+            group.account_id,
+            group.name,
+            group.description
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(group)
+    }
+
+    /// Retrieves a node group by its ID.
+    pub async fn get_group_by_id(&self, id: &str) -> Result<Option<NodeGroup>> {
+        let group = sqlx::query_as!(
+            NodeGroup,
+            r#"
+            SELECT
+            id as "id!",
+            account_id as "account_id!",
+            name as "name!",
+            description as "description?",
+            created_at as "created_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>",
+            is_deleted as "is_deleted!",
+            deleted_at as "deleted_at?: DateTime<Utc>"
+            FROM node_groups WHERE id = ? AND is_deleted = 0
+            "#,
+            id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(group)
+    }
+
+    /// Retrieves a node group by name within an account.
+    pub async fn get_group_by_name(
+        &self,
+        account_id: &str,
+        name: &str,
+    ) -> Result<Option<NodeGroup>> {
+        let group = sqlx::query_as!(
+            NodeGroup,
+            r#"
+            SELECT
+            id as "id!",
+            account_id as "account_id!",
+            name as "name!",
+            description as "description?",
+            created_at as "created_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>",
+            is_deleted as "is_deleted!",
+            deleted_at as "deleted_at?: DateTime<Utc>"
+            FROM node_groups WHERE account_id = ? AND name = ? AND is_deleted = 0
+            "#,
+            account_id,
+            name
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(group)
+    }
+
+    /// Retrieves all node groups of an account.
+    pub async fn get_groups_by_account_id(&self, account_id: &str) -> Result<Vec<NodeGroup>> {
+        let groups = sqlx::query_as!(
+            NodeGroup,
+            r#"
+            SELECT
+            id as "id!",
+            account_id as "account_id!",
+            name as "name!",
+            description as "description?",
+            created_at as "created_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>",
+            is_deleted as "is_deleted!",
+            deleted_at as "deleted_at?: DateTime<Utc>"
+            FROM node_groups WHERE account_id = ? AND is_deleted = 0
+            ORDER BY name ASC
+            "#,
+            account_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(groups)
+    }
+
+    /// Retrieves the node groups a user is assigned to.
+    pub async fn get_groups_by_user_id(&self, user_id: &str) -> Result<Vec<NodeGroup>> {
+        let groups = sqlx::query_as!(
+            NodeGroup,
+            r#"
+            SELECT
+            g.id as "id!",
+            g.account_id as "account_id!",
+            g.name as "name!",
+            g.description as "description?",
+            g.created_at as "created_at!: DateTime<Utc>",
+            g.updated_at as "updated_at!: DateTime<Utc>",
+            g.is_deleted as "is_deleted!",
+            g.deleted_at as "deleted_at?: DateTime<Utc>"
+            FROM node_groups g
+            INNER JOIN node_group_members m ON m.group_id = g.id
+            WHERE m.user_id = ? AND g.is_deleted = 0
+            ORDER BY g.name ASC
+            "#,
+            user_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(groups)
+    }
+
+    /// Counts the node groups defined for an account.
+    pub async fn count_groups_by_account_id(&self, account_id: &str) -> Result<i64> {
+        let result = sqlx::query!(
+            "SELECT COUNT(*) as count FROM node_groups WHERE account_id = ? AND is_deleted = 0",
+            account_id
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(result.count)
+    }
+
+    /// Marks a node group as deleted and drops its node and member assignments.
+    pub async fn delete_group(&self, id: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!(
+            "UPDATE node_groups SET is_deleted = 1, deleted_at = CURRENT_TIMESTAMP WHERE id = ?",
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!("DELETE FROM node_group_nodes WHERE group_id = ?", id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!("DELETE FROM node_group_members WHERE group_id = ?", id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Tags a node with a group. Adding a node twice is a no-op.
+    pub async fn add_node(&self, group_id: &str, node_id: &str) -> Result<()> {
+        sqlx::query!(
+            "INSERT OR IGNORE INTO node_group_nodes (group_id, node_id) VALUES (?, ?)",
+            group_id,
+            node_id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Removes a node from a group.
+    pub async fn remove_node(&self, group_id: &str, node_id: &str) -> Result<()> {
+        sqlx::query!(
+            "DELETE FROM node_group_nodes WHERE group_id = ? AND node_id = ?",
+            group_id,
+            node_id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Assigns a user to a group. Assigning a user twice is a no-op.
+    pub async fn add_member(&self, group_id: &str, user_id: &str) -> Result<()> {
+        sqlx::query!(
+            "INSERT OR IGNORE INTO node_group_members (group_id, user_id) VALUES (?, ?)",
+            group_id,
+            user_id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Removes a user from a group.
+    pub async fn remove_member(&self, group_id: &str, user_id: &str) -> Result<()> {
+        sqlx::query!(
+            "DELETE FROM node_group_members WHERE group_id = ? AND user_id = ?",
+            group_id,
+            user_id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Lists the node IDs tagged with a group.
+    pub async fn get_group_node_ids(&self, group_id: &str) -> Result<Vec<String>> {
+        let rows = sqlx::query!(
+            r#"SELECT node_id as "node_id!" FROM node_group_nodes WHERE group_id = ? ORDER BY node_id"#,
+            group_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| row.node_id).collect())
+    }
+
+    /// Lists the user IDs assigned to a group.
+    pub async fn get_group_user_ids(&self, group_id: &str) -> Result<Vec<String>> {
+        let rows = sqlx::query!(
+            r#"SELECT user_id as "user_id!" FROM node_group_members WHERE group_id = ? ORDER BY user_id"#,
+            group_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| row.user_id).collect())
+    }
+
+    /// Lists every node ID a user can reach through their group assignments.
+    pub async fn get_node_ids_for_user(&self, user_id: &str) -> Result<Vec<String>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT DISTINCT n.node_id as "node_id!"
+            FROM node_group_nodes n
+            INNER JOIN node_group_members m ON m.group_id = n.group_id
+            INNER JOIN node_groups g ON g.id = n.group_id
+            WHERE m.user_id = ? AND g.is_deleted = 0
+            "#,
+            user_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| row.node_id).collect())
+    }
+}
@@ -0,0 +1,132 @@
+//! Database repository for node labels.
+//!
+//! Labels are keyed by account and node, so every user of an account sees the
+//! same name, color and notes for a node.
+
+use crate::database::models::{NodeEnvironment, NodeLabel};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// Repository for node label database operations.
+pub struct NodeLabelRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> NodeLabelRepository<'a> {
+    /// Creates a new NodeLabelRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves the label of one of an account's nodes.
+    pub async fn get_label(&self, account_id: &str, node_id: &str) -> Result<Option<NodeLabel>> {
+        let label = sqlx::query_as!(
+            NodeLabel,
+            r#"
+            SELECT
+            account_id as "account_id!",
+            node_id as "node_id!",
+            display_name as "display_name?",
+            color as "color?",
+            environment as "environment?: NodeEnvironment",
+            notes as "notes?",
+            created_at as "created_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>"
+            FROM node_labels
+            WHERE account_id = ? AND node_id = ?
+            "#,
+            account_id,
+            node_id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(label)
+    }
+
+    /// Retrieves the labels of every node of an account.
+    pub async fn get_labels_by_account_id(&self, account_id: &str) -> Result<Vec<NodeLabel>> {
+        let labels = sqlx::query_as!(
+            NodeLabel,
+            r#"
+            SELECT
+            account_id as "account_id!",
+            node_id as "node_id!",
+            display_name as "display_name?",
+            color as "color?",
+            environment as "environment?: NodeEnvironment",
+            notes as "notes?",
+            created_at as "created_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>"
+            FROM node_labels
+            WHERE account_id = ?
+            "#,
+            account_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(labels)
+    }
+
+    /// Creates or replaces the label of one of an account's nodes.
+    pub async fn upsert_label(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        display_name: Option<&str>,
+        color: Option<&str>,
+        environment: Option<NodeEnvironment>,
+        notes: Option<&str>,
+    ) -> Result<NodeLabel> {
+        let label = sqlx::query_as!(
+            NodeLabel,
+            r#"
+            INSERT INTO node_labels (account_id, node_id, display_name, color, environment, notes)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT (account_id, node_id) DO UPDATE SET
+            display_name = excluded.display_name,
+            color = excluded.color,
+            environment = excluded.environment,
+            notes = excluded.notes,
+            updated_at = CURRENT_TIMESTAMP
+            RETURNING
+            account_id as "account_id!",
+            node_id as "node_id!",
+            display_name as "display_name?",
+            color as "color?",
+            environment as "environment?: NodeEnvironment",
+            notes as "notes?",
+            created_at as "created_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            account_id,
+            node_id,
+            display_name,
+            color,
+            environment,
+            notes
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(label)
+    }
+
+    /// Removes the label of one of an account's nodes.
+    ///
+    /// Returns whether a label existed.
+    pub async fn delete_label(&self, account_id: &str, node_id: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM node_labels WHERE account_id = ? AND node_id = ?",
+            account_id,
+            node_id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -1,6 +1,6 @@
 //! Database repository for notification management operations.
 //!
-//! Provides CRUD operations for webhook and Discord notifications.
+//! Provides CRUD operations for webhook, Discord and Nostr notifications.
 
 use crate::database::models::{CreateNotification, Notification};
 use anyhow::Result;
@@ -27,8 +27,8 @@ impl<'a> NotificationRepository<'a> {
         let notification = sqlx::query_as!(
             Notification,
             r#"
-            INSERT INTO notifications (id, account_id, user_id, name, notification_type, url, is_active)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO notifications (id, account_id, user_id, name, notification_type, url, recipient, headers, is_active)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
             RETURNING
             id as "id!",
             account_id as "account_id!",
@@ -36,11 +36,16 @@ impl<'a> NotificationRepository<'a> {
             name as "name!",
             notification_type as "notification_type: crate::database::models::NotificationType",
             url as "url!",
+            recipient as "recipient?",
+            headers as "headers?",
             is_active as "is_active!",
             created_at as "created_at!: DateTime<Utc>",
             updated_at as "updated_at!: DateTime<Utc>",
             is_deleted as "is_deleted!",
-            deleted_at as "deleted_at?: DateTime<Utc>"
+            deleted_at as "deleted_at?: DateTime<Utc>",
+            consecutive_failures as "consecutive_failures!",
+            last_error as "last_error?",
+            paused_at as "paused_at?: DateTime<Utc>"
             "#,
             notification.id,
             notification.account_id,
@@ -48,6 +53,8 @@ impl<'a> NotificationRepository<'a> {
             notification.name,
             notification.notification_type,
             notification.url,
+            notification.recipient,
+            notification.headers,
             true
         )
         .fetch_one(self.pool)
@@ -68,11 +75,16 @@ impl<'a> NotificationRepository<'a> {
             name as "name!",
             notification_type as "notification_type: crate::database::models::NotificationType",
             url as "url!",
+            recipient as "recipient?",
+            headers as "headers?",
             is_active as "is_active!",
             created_at as "created_at!: DateTime<Utc>",
             updated_at as "updated_at!: DateTime<Utc>",
             is_deleted as "is_deleted!",
-            deleted_at as "deleted_at?: DateTime<Utc>"
+            deleted_at as "deleted_at?: DateTime<Utc>",
+            consecutive_failures as "consecutive_failures!",
+            last_error as "last_error?",
+            paused_at as "paused_at?: DateTime<Utc>"
             FROM notifications WHERE id = ? AND is_deleted = 0
             "#,
             id
@@ -98,11 +110,16 @@ impl<'a> NotificationRepository<'a> {
             name as "name!",
             notification_type as "notification_type: crate::database::models::NotificationType",
             url as "url!",
+            recipient as "recipient?",
+            headers as "headers?",
             is_active as "is_active!",
             created_at as "created_at!: DateTime<Utc>",
             updated_at as "updated_at!: DateTime<Utc>",
             is_deleted as "is_deleted!",
-            deleted_at as "deleted_at?: DateTime<Utc>"
+            deleted_at as "deleted_at?: DateTime<Utc>",
+            consecutive_failures as "consecutive_failures!",
+            last_error as "last_error?",
+            paused_at as "paused_at?: DateTime<Utc>"
             FROM notifications
             WHERE account_id = ? AND is_deleted = 0
             ORDER BY created_at DESC
@@ -116,11 +133,15 @@ impl<'a> NotificationRepository<'a> {
     }
 
     /// Updates a notification.
+    ///
+    /// `headers` replaces the sealed webhook headers when given; `Some(None)` removes them.
     pub async fn update_notification(
         &self,
         id: &str,
         name: Option<&str>,
         url: Option<&str>,
+        recipient: Option<&str>,
+        headers: Option<Option<&str>>,
         is_active: Option<bool>,
     ) -> Result<bool> {
         // Build the query dynamically based on provided fields
@@ -135,10 +156,23 @@ impl<'a> NotificationRepository<'a> {
             param_count += 1;
             set_clauses.push(format!("url = ?{param_count}"));
         }
+        if recipient.is_some() {
+            param_count += 1;
+            set_clauses.push(format!("recipient = ?{param_count}"));
+        }
+        if headers.is_some() {
+            param_count += 1;
+            set_clauses.push(format!("headers = ?{param_count}"));
+        }
         if is_active.is_some() {
             param_count += 1;
             set_clauses.push(format!("is_active = ?{param_count}"));
         }
+        // A new address or credentials may well fix a paused endpoint
+        if url.is_some() || recipient.is_some() || headers.is_some() {
+            set_clauses
+                .push("consecutive_failures = 0, last_error = NULL, paused_at = NULL".to_string());
+        }
 
         if set_clauses.is_empty() {
             return Ok(false);
@@ -160,6 +194,12 @@ impl<'a> NotificationRepository<'a> {
         if let Some(url) = url {
             query_builder = query_builder.bind(url);
         }
+        if let Some(recipient) = recipient {
+            query_builder = query_builder.bind(recipient);
+        }
+        if let Some(headers) = headers {
+            query_builder = query_builder.bind(headers);
+        }
         if let Some(is_active) = is_active {
             query_builder = query_builder.bind(is_active);
         }
@@ -170,6 +210,108 @@ impl<'a> NotificationRepository<'a> {
         Ok(rows_affected > 0)
     }
 
+    /// Retrieves the active notifications whose deliveries are paused.
+    pub async fn get_paused_notifications(&self) -> Result<Vec<Notification>> {
+        let notifications = sqlx::query_as!(
+            Notification,
+            r#"
+            SELECT
+            id as "id!",
+            account_id as "account_id!",
+            user_id as "user_id!",
+            name as "name!",
+            notification_type as "notification_type: crate::database::models::NotificationType",
+            url as "url!",
+            recipient as "recipient?",
+            headers as "headers?",
+            is_active as "is_active!",
+            created_at as "created_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>",
+            is_deleted as "is_deleted!",
+            deleted_at as "deleted_at?: DateTime<Utc>",
+            consecutive_failures as "consecutive_failures!",
+            last_error as "last_error?",
+            paused_at as "paused_at?: DateTime<Utc>"
+            FROM notifications
+            WHERE paused_at IS NOT NULL AND is_active = 1 AND is_deleted = 0
+            ORDER BY paused_at
+            "#
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(notifications)
+    }
+
+    /// Records a failed delivery, returning how many failed in a row.
+    pub async fn record_failure(&self, id: &str, error: &str) -> Result<i64> {
+        let failures = sqlx::query_scalar!(
+            r#"
+            UPDATE notifications
+            SET consecutive_failures = consecutive_failures + 1, last_error = ?
+            WHERE id = ?
+            RETURNING consecutive_failures as "consecutive_failures!"
+            "#,
+            error,
+            id
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(failures)
+    }
+
+    /// Records a successful delivery, clearing the failures before it.
+    pub async fn record_success(&self, id: &str) -> Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE notifications
+            SET consecutive_failures = 0, last_error = NULL
+            WHERE id = ? AND consecutive_failures > 0
+            "#,
+            id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Pauses deliveries to a notification, returning false if already paused.
+    pub async fn pause_notification(&self, id: &str, paused_at: DateTime<Utc>) -> Result<bool> {
+        let rows_affected = sqlx::query!(
+            r#"
+            UPDATE notifications
+            SET paused_at = ?
+            WHERE id = ? AND paused_at IS NULL AND is_deleted = 0
+            "#,
+            paused_at,
+            id
+        )
+        .execute(self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Resumes deliveries to a paused notification, returning false if it wasn't paused.
+    pub async fn resume_notification(&self, id: &str) -> Result<bool> {
+        let rows_affected = sqlx::query!(
+            r#"
+            UPDATE notifications
+            SET consecutive_failures = 0, last_error = NULL, paused_at = NULL
+            WHERE id = ? AND paused_at IS NOT NULL
+            "#,
+            id
+        )
+        .execute(self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
     /// Soft deletes a notification.
     pub async fn delete_notification(&self, id: &str) -> Result<()> {
         sqlx::query!(
@@ -0,0 +1,178 @@
+//! Database repository for payment annotations.
+//!
+//! Annotations are keyed by account and payment hash, so the tags and notes on
+//! a payment are shared by every user of the account and survive reconnecting
+//! the node. Tags are kept as a JSON array in the annotation row.
+
+use crate::database::models::PaymentAnnotation;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// Repository for payment annotation database operations.
+pub struct PaymentAnnotationRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> PaymentAnnotationRepository<'a> {
+    /// Creates a new PaymentAnnotationRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves the annotation of a payment.
+    pub async fn get_annotation(
+        &self,
+        account_id: &str,
+        payment_hash: &str,
+    ) -> Result<Option<PaymentAnnotation>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+            payment_hash as "payment_hash!",
+            tags as "tags!",
+            notes as "notes?",
+            node_label as "node_label?",
+            created_at as "created_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>"
+            FROM payment_annotations
+            WHERE account_id = ? AND payment_hash = ?
+            "#,
+            account_id,
+            payment_hash
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        Ok(Some(PaymentAnnotation {
+            payment_hash: row.payment_hash,
+            tags: serde_json::from_str(&row.tags)?,
+            notes: row.notes,
+            node_label: row.node_label,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }))
+    }
+
+    /// Retrieves every annotation of an account.
+    pub async fn get_annotations_by_account_id(
+        &self,
+        account_id: &str,
+    ) -> Result<Vec<PaymentAnnotation>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+            payment_hash as "payment_hash!",
+            tags as "tags!",
+            notes as "notes?",
+            node_label as "node_label?",
+            created_at as "created_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>"
+            FROM payment_annotations
+            WHERE account_id = ?
+            "#,
+            account_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(PaymentAnnotation {
+                    payment_hash: row.payment_hash,
+                    tags: serde_json::from_str(&row.tags)?,
+                    notes: row.notes,
+                    node_label: row.node_label,
+                    created_at: row.created_at,
+                    updated_at: row.updated_at,
+                })
+            })
+            .collect()
+    }
+
+    /// Creates or replaces the annotation of a payment.
+    pub async fn upsert_annotation(
+        &self,
+        account_id: &str,
+        payment_hash: &str,
+        tags: &[String],
+        notes: Option<&str>,
+    ) -> Result<()> {
+        let tags = serde_json::to_string(tags)?;
+        let now = Utc::now();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO payment_annotations (account_id, payment_hash, tags, notes, updated_at)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT (account_id, payment_hash) DO UPDATE SET
+            tags = excluded.tags,
+            notes = excluded.notes,
+            updated_at = excluded.updated_at
+            "#,
+            account_id,
+            payment_hash,
+            tags,
+            notes,
+            now
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Records the node's label of an on-chain transaction as synchronized,
+    /// setting the notes to it when `import` is set and creating the annotation
+    /// if there is none.
+    pub async fn record_node_label(
+        &self,
+        account_id: &str,
+        txid: &str,
+        node_label: &str,
+        import: bool,
+    ) -> Result<()> {
+        let now = Utc::now();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO payment_annotations (account_id, payment_hash, notes, node_label, updated_at)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT (account_id, payment_hash) DO UPDATE SET
+            notes = CASE WHEN ? THEN excluded.notes ELSE notes END,
+            node_label = excluded.node_label,
+            updated_at = CASE WHEN ? THEN excluded.updated_at ELSE updated_at END
+            "#,
+            account_id,
+            txid,
+            node_label,
+            node_label,
+            now,
+            import,
+            import
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Removes the annotation of a payment.
+    ///
+    /// Returns whether an annotation existed.
+    pub async fn delete_annotation(&self, account_id: &str, payment_hash: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM payment_annotations WHERE account_id = ? AND payment_hash = ?",
+            account_id,
+            payment_hash
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,244 @@
+//! Database repository for watched payment hashes.
+//!
+//! A hash is watched once per account. Watching it again while the watch is
+//! pending or expired starts it over; settled and failed watches are kept as
+//! they are.
+
+use crate::database::models::{PaymentWatch, WatchStatus};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// A watch as stored, with its node IDs still encoded.
+struct WatchRow {
+    id: String,
+    account_id: String,
+    user_id: String,
+    payment_hash: String,
+    callback_url: Option<String>,
+    node_ids: Option<String>,
+    status: WatchStatus,
+    node_id: Option<String>,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    resolved_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<WatchRow> for PaymentWatch {
+    type Error = anyhow::Error;
+
+    fn try_from(row: WatchRow) -> Result<Self> {
+        Ok(Self {
+            id: row.id,
+            account_id: row.account_id,
+            user_id: row.user_id,
+            payment_hash: row.payment_hash,
+            callback_url: row.callback_url,
+            node_ids: row
+                .node_ids
+                .as_deref()
+                .map(serde_json::from_str)
+                .transpose()?,
+            status: row.status,
+            node_id: row.node_id,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+            resolved_at: row.resolved_at,
+        })
+    }
+}
+
+/// Repository for payment watch database operations.
+pub struct PaymentWatchRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> PaymentWatchRepository<'a> {
+    /// Creates a new PaymentWatchRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Starts watching a hash, or starts its pending or expired watch over.
+    pub async fn upsert_watch(&self, watch: &PaymentWatch) -> Result<()> {
+        let node_ids = watch
+            .node_ids
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO payment_watches
+            (id, account_id, user_id, payment_hash, callback_url, node_ids, status, created_at, expires_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (account_id, payment_hash) DO UPDATE SET
+            user_id = excluded.user_id,
+            callback_url = excluded.callback_url,
+            node_ids = excluded.node_ids,
+            status = 'pending',
+            node_id = NULL,
+            expires_at = excluded.expires_at,
+            resolved_at = NULL
+            WHERE payment_watches.status IN ('pending', 'expired')
+            "#,
+            watch.id,
+            watch.account_id,
+            watch.user_id,
+            watch.payment_hash,
+            watch.callback_url,
+            node_ids,
+            watch.status,
+            watch.created_at,
+            watch.expires_at
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Retrieves the watch of one of an account's hashes.
+    pub async fn get_watch(
+        &self,
+        account_id: &str,
+        payment_hash: &str,
+    ) -> Result<Option<PaymentWatch>> {
+        let row = sqlx::query_as!(
+            WatchRow,
+            r#"
+            SELECT
+            id as "id!",
+            account_id as "account_id!",
+            user_id as "user_id!",
+            payment_hash as "payment_hash!",
+            callback_url as "callback_url?",
+            node_ids as "node_ids?",
+            status as "status!: WatchStatus",
+            node_id as "node_id?",
+            created_at as "created_at!: DateTime<Utc>",
+            expires_at as "expires_at!: DateTime<Utc>",
+            resolved_at as "resolved_at?: DateTime<Utc>"
+            FROM payment_watches
+            WHERE account_id = ? AND payment_hash = ?
+            "#,
+            account_id,
+            payment_hash
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        row.map(PaymentWatch::try_from).transpose()
+    }
+
+    /// Retrieves an account's watches, newest first.
+    pub async fn get_watches_by_account_id(
+        &self,
+        account_id: &str,
+        status: Option<WatchStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<PaymentWatch>> {
+        let rows = sqlx::query_as!(
+            WatchRow,
+            r#"
+            SELECT
+            id as "id!",
+            account_id as "account_id!",
+            user_id as "user_id!",
+            payment_hash as "payment_hash!",
+            callback_url as "callback_url?",
+            node_ids as "node_ids?",
+            status as "status!: WatchStatus",
+            node_id as "node_id?",
+            created_at as "created_at!: DateTime<Utc>",
+            expires_at as "expires_at!: DateTime<Utc>",
+            resolved_at as "resolved_at?: DateTime<Utc>"
+            FROM payment_watches
+            WHERE account_id = ? AND (? IS NULL OR status = ?)
+            ORDER BY created_at DESC, id DESC
+            LIMIT ? OFFSET ?
+            "#,
+            account_id,
+            status,
+            status,
+            limit,
+            offset
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        rows.into_iter().map(PaymentWatch::try_from).collect()
+    }
+
+    /// Retrieves the pending watches of every account.
+    pub async fn get_pending_watches(&self) -> Result<Vec<PaymentWatch>> {
+        let rows = sqlx::query_as!(
+            WatchRow,
+            r#"
+            SELECT
+            id as "id!",
+            account_id as "account_id!",
+            user_id as "user_id!",
+            payment_hash as "payment_hash!",
+            callback_url as "callback_url?",
+            node_ids as "node_ids?",
+            status as "status!: WatchStatus",
+            node_id as "node_id?",
+            created_at as "created_at!: DateTime<Utc>",
+            expires_at as "expires_at!: DateTime<Utc>",
+            resolved_at as "resolved_at?: DateTime<Utc>"
+            FROM payment_watches
+            WHERE status = 'pending'
+            ORDER BY account_id, created_at
+            "#
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        rows.into_iter().map(PaymentWatch::try_from).collect()
+    }
+
+    /// Ends a pending watch with its outcome.
+    ///
+    /// Returns false if the watch was no longer pending.
+    pub async fn resolve_watch(
+        &self,
+        id: &str,
+        status: WatchStatus,
+        node_id: Option<&str>,
+    ) -> Result<bool> {
+        let now = Utc::now();
+        let result = sqlx::query!(
+            r#"
+            UPDATE payment_watches
+            SET status = ?, node_id = ?, resolved_at = ?
+            WHERE id = ? AND status = 'pending'
+            "#,
+            status,
+            node_id,
+            now,
+            id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Stops watching one of an account's hashes.
+    ///
+    /// Returns whether the watch existed.
+    pub async fn delete_watch(&self, account_id: &str, payment_hash: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM payment_watches WHERE account_id = ? AND payment_hash = ?",
+            account_id,
+            payment_hash
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,115 @@
+//! Database repository for an account's blocklist of peers.
+//!
+//! Entries are keyed by account and the peer's lowercase hex public key.
+
+use crate::database::models::{BlockedPeer, PeerBlockAction};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// Repository for peer blocklist database operations.
+pub struct PeerBlocklistRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> PeerBlocklistRepository<'a> {
+    /// Creates a new PeerBlocklistRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves every blocked peer of an account, by pubkey.
+    pub async fn get_blocked_peers(&self, account_id: &str) -> Result<Vec<BlockedPeer>> {
+        let peers = sqlx::query_as!(
+            BlockedPeer,
+            r#"
+            SELECT
+            pubkey as "pubkey!",
+            action as "action!: PeerBlockAction",
+            note as "note?",
+            created_at as "created_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>"
+            FROM peer_blocklist
+            WHERE account_id = ?
+            ORDER BY pubkey
+            "#,
+            account_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(peers)
+    }
+
+    /// Retrieves one blocked peer of an account.
+    pub async fn get_blocked_peer(
+        &self,
+        account_id: &str,
+        pubkey: &str,
+    ) -> Result<Option<BlockedPeer>> {
+        let peer = sqlx::query_as!(
+            BlockedPeer,
+            r#"
+            SELECT
+            pubkey as "pubkey!",
+            action as "action!: PeerBlockAction",
+            note as "note?",
+            created_at as "created_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>"
+            FROM peer_blocklist
+            WHERE account_id = ? AND pubkey = ?
+            "#,
+            account_id,
+            pubkey
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(peer)
+    }
+
+    /// Adds a peer to the blocklist, replacing the action and note of an
+    /// existing entry.
+    pub async fn upsert_blocked_peer(
+        &self,
+        account_id: &str,
+        pubkey: &str,
+        action: PeerBlockAction,
+        note: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO peer_blocklist (account_id, pubkey, action, note)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT (account_id, pubkey) DO UPDATE SET
+                action = excluded.action,
+                note = excluded.note,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+            account_id,
+            pubkey,
+            action,
+            note
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Removes a peer from the blocklist.
+    ///
+    /// Returns whether it was on it.
+    pub async fn delete_blocked_peer(&self, account_id: &str, pubkey: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            "DELETE FROM peer_blocklist WHERE account_id = ? AND pubkey = ?",
+            account_id,
+            pubkey
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
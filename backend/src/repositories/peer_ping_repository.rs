@@ -0,0 +1,90 @@
+//! Database repository for the ping round trips sampled to nodes' peers.
+//!
+//! Samples are appended per account, node and peer and kept for a limited
+//! time, so they form a time series of each peer's connection quality.
+
+use crate::database::models::PeerPing;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// Repository for peer ping database operations.
+pub struct PeerPingRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> PeerPingRepository<'a> {
+    /// Creates a new PeerPingRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Appends the round trips sampled in one pass over a node's peers.
+    pub async fn insert_pings(&self, account_id: &str, pings: &[PeerPing]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for ping in pings {
+            sqlx::query!(
+                r#"
+                INSERT INTO peer_pings (account_id, node_id, peer_pubkey, rtt_us, recorded_at)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+                account_id,
+                ping.node_id,
+                ping.peer_pubkey,
+                ping.rtt_us,
+                ping.recorded_at
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Retrieves the round trips to a node's peers since `since`, oldest first,
+    /// only those to `peer_pubkey` when given.
+    pub async fn get_pings(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        peer_pubkey: Option<&str>,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<PeerPing>> {
+        let pings = sqlx::query_as!(
+            PeerPing,
+            r#"
+            SELECT
+            node_id as "node_id!",
+            peer_pubkey as "peer_pubkey!",
+            rtt_us as "rtt_us!",
+            recorded_at as "recorded_at!: DateTime<Utc>"
+            FROM peer_pings
+            WHERE account_id = ? AND node_id = ?
+            AND (? IS NULL OR peer_pubkey = ?)
+            AND recorded_at >= ?
+            ORDER BY recorded_at, id
+            "#,
+            account_id,
+            node_id,
+            peer_pubkey,
+            peer_pubkey,
+            since
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(pings)
+    }
+
+    /// Deletes the round trips sampled before `cutoff`.
+    pub async fn delete_before(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query!("DELETE FROM peer_pings WHERE recorded_at < ?", cutoff)
+            .execute(self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
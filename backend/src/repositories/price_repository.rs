@@ -0,0 +1,54 @@
+//! Database repository for cached historical BTC prices.
+
+use anyhow::Result;
+use sqlx::SqlitePool;
+
+/// Repository for the daily BTC price cache.
+pub struct PriceRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> PriceRepository<'a> {
+    /// Creates a new PriceRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves the cached closing prices for the given `YYYY-MM-DD` dates.
+    pub async fn get_daily_closes(
+        &self,
+        currency: &str,
+        dates: &[String],
+    ) -> Result<Vec<(String, f64)>> {
+        // Dates are bound as a JSON array and expanded with json_each.
+        let dates = serde_json::to_string(dates)?;
+        let rows = sqlx::query!(
+            r#"
+            SELECT date as "date!", close as "close!: f64"
+            FROM btc_daily_prices
+            WHERE currency = ? AND date IN (SELECT value FROM json_each(?))
+            "#,
+            currency,
+            dates
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| (row.date, row.close)).collect())
+    }
+
+    /// Stores the closing price of a day, replacing any previous value.
+    pub async fn save_daily_close(&self, date: &str, currency: &str, close: f64) -> Result<()> {
+        sqlx::query!(
+            "INSERT OR REPLACE INTO btc_daily_prices (date, currency, close) VALUES (?, ?, ?)",
+            date,
+            currency,
+            close
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
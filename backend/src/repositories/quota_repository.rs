@@ -0,0 +1,172 @@
+//! Database repository for account quotas and API usage metering.
+
+use crate::database::models::AccountQuota;
+use crate::database::write_permit;
+use anyhow::Result;
+use sqlx::SqlitePool;
+
+/// Repository for quota database operations.
+pub struct QuotaRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> QuotaRepository<'a> {
+    /// Creates a new QuotaRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves the quotas of an account. Accounts without a row are unlimited.
+    pub async fn get_quota(&self, account_id: &str) -> Result<AccountQuota> {
+        let quota = sqlx::query_as!(
+            AccountQuota,
+            r#"
+            SELECT
+            max_nodes as "max_nodes?: i64",
+            max_notifications as "max_notifications?: i64",
+            max_events as "max_events?: i64",
+            max_api_requests_per_day as "max_api_requests_per_day?: i64"
+            FROM account_quotas
+            WHERE account_id = ?
+            "#,
+            account_id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(quota.unwrap_or_default())
+    }
+
+    /// Replaces the quotas of an account.
+    pub async fn set_quota(&self, account_id: &str, quota: &AccountQuota) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO account_quotas
+            (account_id, max_nodes, max_notifications, max_events, max_api_requests_per_day)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT (account_id) DO UPDATE SET
+            max_nodes = excluded.max_nodes,
+            max_notifications = excluded.max_notifications,
+            max_events = excluded.max_events,
+            max_api_requests_per_day = excluded.max_api_requests_per_day,
+            updated_at = CURRENT_TIMESTAMP
+            "#,
+            account_id,
+            quota.max_nodes,
+            quota.max_notifications,
+            quota.max_events,
+            quota.max_api_requests_per_day
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Counts one API request of an account on a day (`YYYY-MM-DD`).
+    ///
+    /// Returns the day's count including this request.
+    pub async fn record_api_request(&self, account_id: &str, day: &str) -> Result<i64> {
+        // Every authenticated request writes here
+        let _permit = write_permit().await;
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO api_usage (account_id, day, request_count)
+            VALUES (?, ?, 1)
+            ON CONFLICT (account_id, day) DO UPDATE SET
+            request_count = request_count + 1
+            RETURNING request_count as "request_count!: i64"
+            "#,
+            account_id,
+            day
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(row.request_count)
+    }
+
+    /// Retrieves the number of API requests an account made on a day (`YYYY-MM-DD`).
+    pub async fn get_api_requests(&self, account_id: &str, day: &str) -> Result<i64> {
+        let row = sqlx::query!(
+            r#"
+            SELECT request_count as "request_count!: i64"
+            FROM api_usage
+            WHERE account_id = ? AND day = ?
+            "#,
+            account_id,
+            day
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(row.map_or(0, |row| row.request_count))
+    }
+
+    /// Counts the distinct nodes an account has stored credentials for.
+    pub async fn count_nodes(&self, account_id: &str) -> Result<i64> {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(DISTINCT node_id) as "count!: i64"
+            FROM credentials
+            WHERE account_id = ? AND is_deleted = 0
+            "#,
+            account_id
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(result.count)
+    }
+
+    /// Counts the notification endpoints of an account.
+    pub async fn count_notifications(&self, account_id: &str) -> Result<i64> {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as "count!: i64"
+            FROM notifications
+            WHERE account_id = ? AND is_deleted = 0
+            "#,
+            account_id
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(result.count)
+    }
+
+    /// Counts the event rows an account stores, including soft-deleted ones.
+    pub async fn count_stored_events(&self, account_id: &str) -> Result<i64> {
+        let result = sqlx::query!(
+            r#"SELECT COUNT(*) as "count!: i64" FROM events WHERE account_id = ?"#,
+            account_id
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(result.count)
+    }
+
+    /// Permanently deletes an account's oldest `count` events.
+    ///
+    /// Returns the number of deleted events.
+    pub async fn purge_oldest_events(&self, account_id: &str, count: i64) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM events WHERE id IN (
+                SELECT id FROM events
+                WHERE account_id = ?
+                ORDER BY timestamp ASC, id ASC
+                LIMIT ?
+            )
+            "#,
+            account_id,
+            count
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,235 @@
+//! Database repository for node reports and the availability samples they use.
+//!
+//! A report is unique per account, node, frequency and period start, so
+//! regenerating a period replaces the stored report.
+
+use crate::database::models::{Report, ReportFrequency};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// Repository for report database operations.
+pub struct ReportRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> ReportRepository<'a> {
+    /// Creates a new ReportRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Stores a report, replacing any earlier one for the same period.
+    pub async fn save_report(&self, report: &Report) -> Result<()> {
+        let summary = report.summary.to_string();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO reports
+            (id, account_id, node_id, node_alias, frequency, period_start, period_end, summary, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (account_id, node_id, frequency, period_start) DO UPDATE SET
+            id = excluded.id,
+            node_alias = excluded.node_alias,
+            period_end = excluded.period_end,
+            summary = excluded.summary,
+            created_at = excluded.created_at
+            "#,
+            report.id,
+            report.account_id,
+            report.node_id,
+            report.node_alias,
+            report.frequency,
+            report.period_start,
+            report.period_end,
+            summary,
+            report.created_at
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Whether a report of the node already covers the period.
+    pub async fn report_exists(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        frequency: ReportFrequency,
+        period_start: DateTime<Utc>,
+    ) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as count FROM reports
+            WHERE account_id = ? AND node_id = ? AND frequency = ? AND period_start = ?
+            "#,
+            account_id,
+            node_id,
+            frequency,
+            period_start
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(result.count > 0)
+    }
+
+    /// Retrieves an account's reports, newest period first.
+    ///
+    /// `node_ids` restricts the result to reports of those nodes.
+    pub async fn get_reports_by_account_id(
+        &self,
+        account_id: &str,
+        node_ids: Option<&[String]>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Report>> {
+        let node_ids = node_ids.map(serde_json::to_string).transpose()?;
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+            id as "id!",
+            account_id as "account_id!",
+            node_id as "node_id!",
+            node_alias as "node_alias!",
+            frequency as "frequency!: ReportFrequency",
+            period_start as "period_start!: DateTime<Utc>",
+            period_end as "period_end!: DateTime<Utc>",
+            summary as "summary!",
+            created_at as "created_at!: DateTime<Utc>"
+            FROM reports
+            WHERE account_id = ?
+            AND (? IS NULL OR node_id IN (SELECT value FROM json_each(?)))
+            ORDER BY period_start DESC, node_id
+            LIMIT ? OFFSET ?
+            "#,
+            account_id,
+            node_ids,
+            node_ids,
+            limit,
+            offset
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(Report {
+                    id: row.id,
+                    account_id: row.account_id,
+                    node_id: row.node_id,
+                    node_alias: row.node_alias,
+                    frequency: row.frequency,
+                    period_start: row.period_start,
+                    period_end: row.period_end,
+                    summary: serde_json::from_str(&row.summary)?,
+                    created_at: row.created_at,
+                })
+            })
+            .collect()
+    }
+
+    /// Retrieves one of an account's reports.
+    pub async fn get_report(&self, account_id: &str, id: &str) -> Result<Option<Report>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+            id as "id!",
+            account_id as "account_id!",
+            node_id as "node_id!",
+            node_alias as "node_alias!",
+            frequency as "frequency!: ReportFrequency",
+            period_start as "period_start!: DateTime<Utc>",
+            period_end as "period_end!: DateTime<Utc>",
+            summary as "summary!",
+            created_at as "created_at!: DateTime<Utc>"
+            FROM reports
+            WHERE account_id = ? AND id = ?
+            "#,
+            account_id,
+            id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        Ok(Some(Report {
+            id: row.id,
+            account_id: row.account_id,
+            node_id: row.node_id,
+            node_alias: row.node_alias,
+            frequency: row.frequency,
+            period_start: row.period_start,
+            period_end: row.period_end,
+            summary: serde_json::from_str(&row.summary)?,
+            created_at: row.created_at,
+        }))
+    }
+
+    /// Records whether a node answered a reachability probe.
+    pub async fn record_availability(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        sampled_at: DateTime<Utc>,
+        reachable: bool,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT OR REPLACE INTO node_availability_samples (account_id, node_id, sampled_at, reachable)
+            VALUES (?, ?, ?, ?)
+            "#,
+            account_id,
+            node_id,
+            sampled_at,
+            reachable
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Counts the reachable and total probes of a node in `[start, end)`.
+    pub async fn count_availability(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<(i64, i64)> {
+        let result = sqlx::query!(
+            r#"
+            SELECT
+            COALESCE(SUM(reachable), 0) as "reachable!: i64",
+            COUNT(*) as "total!: i64"
+            FROM node_availability_samples
+            WHERE account_id = ? AND node_id = ? AND sampled_at >= ? AND sampled_at < ?
+            "#,
+            account_id,
+            node_id,
+            start,
+            end
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok((result.reachable, result.total))
+    }
+
+    /// Deletes availability probes older than the cutoff.
+    pub async fn purge_availability_before(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query!(
+            "DELETE FROM node_availability_samples WHERE sampled_at < ?",
+            cutoff
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,152 @@
+//! Database repository for user login sessions.
+//!
+//! Sessions are never deleted when revoked; `revoked_at` is set so the tokens
+//! carrying the session id are rejected until they expire.
+
+use crate::database::models::UserSession;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// Repository for user session database operations.
+pub struct SessionRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> SessionRepository<'a> {
+    /// Creates a new SessionRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Stores a new session.
+    pub async fn create_session(&self, session: &UserSession) -> Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO user_sessions (id, user_id, account_id, user_agent, ip_address, created_at, last_activity_at, expires_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+            session.id,
+            session.user_id,
+            session.account_id,
+            session.user_agent,
+            session.ip_address,
+            session.created_at,
+            session.last_activity_at,
+            session.expires_at
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Retrieves a session by its ID.
+    pub async fn get_session(&self, id: &str) -> Result<Option<UserSession>> {
+        let session = sqlx::query_as!(
+            UserSession,
+            r#"
+            SELECT
+            id as "id!",
+            user_id as "user_id!",
+            account_id as "account_id!",
+            user_agent as "user_agent?",
+            ip_address as "ip_address?",
+            created_at as "created_at!: DateTime<Utc>",
+            last_activity_at as "last_activity_at!: DateTime<Utc>",
+            expires_at as "expires_at!: DateTime<Utc>",
+            revoked_at as "revoked_at?: DateTime<Utc>"
+            FROM user_sessions
+            WHERE id = ?
+            "#,
+            id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(session)
+    }
+
+    /// Retrieves a user's sessions that are neither revoked nor expired, most
+    /// recently used first.
+    pub async fn get_active_sessions_by_user_id(
+        &self,
+        user_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<UserSession>> {
+        let sessions = sqlx::query_as!(
+            UserSession,
+            r#"
+            SELECT
+            id as "id!",
+            user_id as "user_id!",
+            account_id as "account_id!",
+            user_agent as "user_agent?",
+            ip_address as "ip_address?",
+            created_at as "created_at!: DateTime<Utc>",
+            last_activity_at as "last_activity_at!: DateTime<Utc>",
+            expires_at as "expires_at!: DateTime<Utc>",
+            revoked_at as "revoked_at?: DateTime<Utc>"
+            FROM user_sessions
+            WHERE user_id = ? AND revoked_at IS NULL AND expires_at > ?
+            ORDER BY last_activity_at DESC
+            "#,
+            user_id,
+            now
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    /// Records activity on a session.
+    pub async fn touch_session(&self, id: &str, at: DateTime<Utc>) -> Result<()> {
+        sqlx::query!(
+            "UPDATE user_sessions SET last_activity_at = ? WHERE id = ?",
+            at,
+            id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Revokes one of a user's sessions.
+    ///
+    /// Returns whether an unrevoked session was found.
+    pub async fn revoke_session(&self, user_id: &str, id: &str, at: DateTime<Utc>) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE user_sessions SET revoked_at = ?
+            WHERE user_id = ? AND id = ? AND revoked_at IS NULL
+            "#,
+            at,
+            user_id,
+            id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Revokes all of a user's sessions, returning how many were revoked.
+    pub async fn revoke_sessions_by_user_id(
+        &self,
+        user_id: &str,
+        at: DateTime<Utc>,
+    ) -> Result<u64> {
+        let result = sqlx::query!(
+            "UPDATE user_sessions SET revoked_at = ? WHERE user_id = ? AND revoked_at IS NULL",
+            at,
+            user_id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
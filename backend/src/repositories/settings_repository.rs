@@ -0,0 +1,75 @@
+//! Database repository for account-scoped settings.
+//!
+//! Settings are stored as key/value rows with JSON encoded values; the typed
+//! model lives in the settings service.
+
+use anyhow::Result;
+use sqlx::SqlitePool;
+
+/// Repository for the `settings` table.
+pub struct SettingsRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> SettingsRepository<'a> {
+    /// Creates a new SettingsRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves every stored setting of an account as `(key, value)` pairs.
+    pub async fn get_settings(&self, account_id: &str) -> Result<Vec<(String, String)>> {
+        let rows = sqlx::query!(
+            r#"SELECT key as "key!", value as "value!" FROM settings WHERE account_id = ?"#,
+            account_id
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| (row.key, row.value)).collect())
+    }
+
+    /// Retrieves one setting for every account that stored it, as `(account_id, value)` pairs.
+    pub async fn get_setting_for_all_accounts(&self, key: &str) -> Result<Vec<(String, String)>> {
+        let rows = sqlx::query!(
+            r#"SELECT account_id as "account_id!", value as "value!" FROM settings WHERE key = ?"#,
+            key
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.account_id, row.value))
+            .collect())
+    }
+
+    /// Stores the given settings of an account, replacing previous values.
+    pub async fn save_settings(
+        &self,
+        account_id: &str,
+        settings: &[(String, String)],
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for (key, value) in settings {
+            sqlx::query!(
+                r#"
+                INSERT INTO settings (account_id, key, value)
+                VALUES (?, ?, ?)
+                ON CONFLICT (account_id, key)
+                DO UPDATE SET value = excluded.value, updated_at = CURRENT_TIMESTAMP
+                "#,
+                account_id,
+                key,
+                value
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
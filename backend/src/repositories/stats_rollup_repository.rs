@@ -0,0 +1,353 @@
+//! Database repository for the hourly rollups behind the statistics endpoints.
+//!
+//! A refresh replaces the rollups from some hour on and records when it ran in
+//! `stats_rollup_refreshes`, in one transaction. Payment rollups are kept per
+//! node, along with the payment records they were folded from; event rollups
+//! are refreshed for a whole account at once and recorded with an empty node id.
+
+use crate::database::models::{
+    EventHourlyRollup, EventSeverity, PaymentHourlyRollup, PaymentRecord, PaymentRecordKind,
+};
+use crate::database::write_permit;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// `kind` of the refreshes of payment rollups.
+pub const PAYMENT_ROLLUPS: &str = "payments";
+/// `kind` of the refreshes of event rollups.
+pub const EVENT_ROLLUPS: &str = "events";
+
+/// Repository for statistics rollup database operations.
+pub struct StatsRollupRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> StatsRollupRepository<'a> {
+    /// Creates a new StatsRollupRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves when a set of rollups was last refreshed, `None` if never.
+    pub async fn get_refreshed_at(
+        &self,
+        account_id: &str,
+        kind: &str,
+        node_id: &str,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let refreshed_at = sqlx::query_scalar!(
+            r#"
+            SELECT refreshed_at as "refreshed_at!: DateTime<Utc>"
+            FROM stats_rollup_refreshes
+            WHERE account_id = ? AND kind = ? AND node_id = ?
+            "#,
+            account_id,
+            kind,
+            node_id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(refreshed_at)
+    }
+
+    /// Replaces a node's payment rollups and records from the hour `since` on
+    /// with `rollups` and `records`, recording the refresh as of `refreshed_at`.
+    pub async fn replace_payment_rollups(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        since: DateTime<Utc>,
+        rollups: &[PaymentHourlyRollup],
+        records: &[PaymentRecord],
+        refreshed_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let _permit = write_permit().await;
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM payment_hourly_rollups
+            WHERE account_id = ? AND node_id = ? AND hour >= ?
+            "#,
+            account_id,
+            node_id,
+            since
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        for rollup in rollups {
+            sqlx::query!(
+                r#"
+                INSERT INTO payment_hourly_rollups
+                (account_id, node_id, hour, outgoing_count, outgoing_sat, incoming_count,
+                 incoming_sat, forwarded_count, forwarded_sat, failed_outgoing, fee_revenue_msat)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+                account_id,
+                node_id,
+                rollup.hour,
+                rollup.outgoing_count,
+                rollup.outgoing_sat,
+                rollup.incoming_count,
+                rollup.incoming_sat,
+                rollup.forwarded_count,
+                rollup.forwarded_sat,
+                rollup.failed_outgoing,
+                rollup.fee_revenue_msat
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        sqlx::query!(
+            r#"
+            DELETE FROM payment_records
+            WHERE account_id = ? AND node_id = ? AND at >= ?
+            "#,
+            account_id,
+            node_id,
+            since
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        for record in records {
+            sqlx::query!(
+                r#"
+                INSERT INTO payment_records (account_id, node_id, at, kind, amount_sat, fee_msat)
+                VALUES (?, ?, ?, ?, ?, ?)
+                "#,
+                account_id,
+                node_id,
+                record.at,
+                record.kind,
+                record.amount_sat,
+                record.fee_msat
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        record_refresh(&mut tx, account_id, PAYMENT_ROLLUPS, node_id, refreshed_at).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Retrieves the payment rollups of some of an account's nodes for the
+    /// hours starting in `[start, end]`, oldest first.
+    pub async fn get_payment_rollups(
+        &self,
+        account_id: &str,
+        node_ids: &[String],
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<PaymentHourlyRollup>> {
+        let node_ids = serde_json::to_string(node_ids)?;
+        let rollups = sqlx::query_as!(
+            PaymentHourlyRollup,
+            r#"
+            SELECT
+            node_id as "node_id!",
+            hour as "hour!: DateTime<Utc>",
+            outgoing_count as "outgoing_count!",
+            outgoing_sat as "outgoing_sat!",
+            incoming_count as "incoming_count!",
+            incoming_sat as "incoming_sat!",
+            forwarded_count as "forwarded_count!",
+            forwarded_sat as "forwarded_sat!",
+            failed_outgoing as "failed_outgoing!",
+            fee_revenue_msat as "fee_revenue_msat!"
+            FROM payment_hourly_rollups
+            WHERE account_id = ?
+            AND node_id IN (SELECT value FROM json_each(?))
+            AND hour >= ? AND hour <= ?
+            ORDER BY hour, node_id
+            "#,
+            account_id,
+            node_ids,
+            start,
+            end
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rollups)
+    }
+
+    /// Retrieves the payment records of some of an account's nodes between
+    /// `start` and `end`, oldest first.
+    pub async fn get_payment_records(
+        &self,
+        account_id: &str,
+        node_ids: &[String],
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<PaymentRecord>> {
+        let node_ids = serde_json::to_string(node_ids)?;
+        let records = sqlx::query_as!(
+            PaymentRecord,
+            r#"
+            SELECT
+            node_id as "node_id!",
+            at as "at!: DateTime<Utc>",
+            kind as "kind!: PaymentRecordKind",
+            amount_sat as "amount_sat!",
+            fee_msat as "fee_msat!"
+            FROM payment_records
+            WHERE account_id = ?
+            AND node_id IN (SELECT value FROM json_each(?))
+            AND at >= ? AND at <= ?
+            ORDER BY at, node_id
+            "#,
+            account_id,
+            node_ids,
+            start,
+            end
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    /// Earliest time of the account's events added or deleted at or after
+    /// `since`, `None` when there are none.
+    pub async fn earliest_event_changed_since(
+        &self,
+        account_id: &str,
+        since: DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>> {
+        // created_at and deleted_at hold CURRENT_TIMESTAMP, which datetime() matches
+        let earliest = sqlx::query_scalar!(
+            r#"
+            SELECT MIN(timestamp) as "timestamp?: DateTime<Utc>"
+            FROM events
+            WHERE account_id = ?
+            AND (created_at >= datetime(?) OR deleted_at >= datetime(?))
+            "#,
+            account_id,
+            since,
+            since
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(earliest)
+    }
+
+    /// Recounts an account's event rollups from the hour `since` on, from its
+    /// events and their compacted aggregates, recording the refresh as of
+    /// `refreshed_at`.
+    pub async fn refresh_event_rollups(
+        &self,
+        account_id: &str,
+        since: DateTime<Utc>,
+        refreshed_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let _permit = write_permit().await;
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!(
+            "DELETE FROM event_hourly_rollups WHERE account_id = ? AND hour >= ?",
+            account_id,
+            since
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO event_hourly_rollups (account_id, node_id, hour, severity, event_count)
+            SELECT account_id, node_id, hour, severity, SUM(event_count)
+            FROM (
+                SELECT account_id, node_id,
+                strftime('%Y-%m-%dT%H:00:00+00:00', timestamp) as hour,
+                severity, 1 as event_count
+                FROM events
+                WHERE account_id = ? AND is_deleted = 0 AND timestamp >= ?
+                UNION ALL
+                SELECT account_id, node_id, hour, severity, event_count
+                FROM event_hourly_aggregates
+                WHERE account_id = ? AND hour >= ?
+            )
+            GROUP BY account_id, node_id, hour, severity
+            "#,
+            account_id,
+            since,
+            account_id,
+            since
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        record_refresh(&mut tx, account_id, EVENT_ROLLUPS, "", refreshed_at).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Retrieves an account's event rollups for the hours starting in
+    /// `[start, end]`, optionally limited to some nodes, oldest first.
+    pub async fn get_event_rollups(
+        &self,
+        account_id: &str,
+        node_ids: Option<&[String]>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<EventHourlyRollup>> {
+        let node_ids = node_ids.map(serde_json::to_string).transpose()?;
+        let rollups = sqlx::query_as!(
+            EventHourlyRollup,
+            r#"
+            SELECT
+            node_id as "node_id!",
+            hour as "hour!: DateTime<Utc>",
+            severity as "severity!: EventSeverity",
+            event_count as "event_count!"
+            FROM event_hourly_rollups
+            WHERE account_id = ?
+            AND (? IS NULL OR node_id IN (SELECT value FROM json_each(?)))
+            AND hour >= ? AND hour <= ?
+            ORDER BY hour, node_id
+            "#,
+            account_id,
+            node_ids,
+            node_ids,
+            start,
+            end
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(rollups)
+    }
+}
+
+async fn record_refresh(
+    tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+    account_id: &str,
+    kind: &str,
+    node_id: &str,
+    refreshed_at: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO stats_rollup_refreshes (account_id, kind, node_id, refreshed_at)
+        VALUES (?, ?, ?, ?)
+        ON CONFLICT (account_id, kind, node_id) DO UPDATE SET refreshed_at = excluded.refreshed_at
+        "#,
+        account_id,
+        kind,
+        node_id,
+        refreshed_at
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
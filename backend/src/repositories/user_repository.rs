@@ -4,7 +4,7 @@
 
 use crate::{
     api::common::PaginationFilter,
-    database::models::{RoleAccessLevel, User},
+    database::models::{MembershipChange, RoleAccessLevel, User},
 };
 use anyhow::Result;
 use chrono::{DateTime, Utc};
@@ -28,10 +28,6 @@ impl<'a> UserRepository<'a> {
         Self { pool }
     }
 
-    /// Creates a new user in the database.
-    ///
-    /// # Arguments
-    /// * `user` - CreateUser DTO containing user details
     /// Retrieves a user by their unique identifier.
     ///
     /// # Arguments
@@ -218,6 +214,65 @@ impl<'a> UserRepository<'a> {
         Ok(users)
     }
 
+    /// Retrieves a soft-deleted user of an account.
+    ///
+    /// # Arguments
+    /// * `account_id` - Account the user belongs to
+    /// * `id` - User ID (UUID format)
+    ///
+    /// # Returns
+    /// `Some(User)` if the user exists and is deleted, `None` otherwise
+    pub async fn get_deleted_user(&self, account_id: &str, id: &str) -> Result<Option<User>> {
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            SELECT
+            id as "id!",
+            account_id as "account_id!",
+            role_id as "role_id!",
+            role_access_level as "role_access_level: RoleAccessLevel",
+            username as "username!",
+            password_hash as "password_hash!",
+            email as "email!",
+            is_active as "is_active!",
+            created_at as "created_at!: DateTime<Utc>",
+            updated_at as "updated_at!: DateTime<Utc>",
+            is_deleted as "is_deleted!",
+            deleted_at as "deleted_at?: DateTime<Utc>"
+            FROM users WHERE id = ? AND account_id = ? AND is_deleted = 1
+            "#,
+            id,
+            account_id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(user)
+    }
+
+    /// Clears the deletion of a soft-deleted user.
+    ///
+    /// # Returns
+    /// `true` if a deleted user was restored
+    ///
+    /// # Errors
+    /// Fails with a unique constraint error if an active user took the
+    /// username or email in the meantime.
+    pub async fn restore_user(&self, id: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET is_deleted = 0, deleted_at = NULL
+            WHERE id = ? AND is_deleted = 1
+            "#,
+            id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     /// Get total count of users for an account
     pub async fn get_users_count_by_account_id(&self, account_id: &str) -> Result<u64> {
         let count = sqlx::query_scalar!(
@@ -229,4 +284,77 @@ impl<'a> UserRepository<'a> {
 
         Ok(count as u64)
     }
+
+    /// Retrieves up to `limit` of an account's membership changes, newest
+    /// first: members joining and being removed, and invites sent. Only those
+    /// at or before `until` when given.
+    pub async fn get_membership_changes(
+        &self,
+        account_id: &str,
+        until: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<MembershipChange>> {
+        // Times set by CURRENT_TIMESTAMP are compared in its format
+        let changes = sqlx::query_as!(
+            MembershipChange,
+            r#"
+            SELECT
+            id as "id!: String",
+            action as "action!: String",
+            user_id as "user_id?",
+            subject as "subject!",
+            occurred_at as "occurred_at!: DateTime<Utc>"
+            FROM (
+                SELECT 'member:' || id || ':joined' AS id, 'member_joined' AS action,
+                id AS user_id, username AS subject, datetime(created_at) AS occurred_at
+                FROM users WHERE account_id = ?
+                UNION ALL
+                SELECT 'member:' || id || ':removed', 'member_removed',
+                id, username, datetime(deleted_at)
+                FROM users WHERE account_id = ? AND is_deleted = 1 AND deleted_at IS NOT NULL
+                UNION ALL
+                SELECT 'invite:' || id, 'member_invited',
+                inviter_id, invitee_email, datetime(created_at)
+                FROM invites WHERE account_id = ?
+            )
+            WHERE (? IS NULL OR occurred_at <= datetime(?))
+            ORDER BY occurred_at DESC, id DESC
+            LIMIT ?
+            "#,
+            account_id,
+            account_id,
+            account_id,
+            until,
+            until,
+            limit
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(changes)
+    }
+
+    /// Replaces a user's password hash.
+    ///
+    /// # Arguments
+    /// * `id` - User ID
+    /// * `password_hash` - Bcrypt hash of the new password
+    ///
+    /// # Returns
+    /// `true` if an active user was updated
+    pub async fn update_password(&self, id: &str, password_hash: &str) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_hash = ?, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ? AND is_deleted = 0
+            "#,
+            password_hash,
+            id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
 }
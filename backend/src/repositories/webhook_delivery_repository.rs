@@ -0,0 +1,314 @@
+//! Database repository for topic webhook deliveries.
+//!
+//! The table is both the retry queue and the delivery log: a delivery stays
+//! `pending` until it succeeds or runs out of attempts and is kept afterwards.
+
+use crate::database::models::{DeliveryStatus, WebhookDelivery};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+/// A delivery as stored, with its payload still encoded.
+struct DeliveryRow {
+    id: String,
+    account_id: String,
+    node_id: String,
+    topic: String,
+    idempotency_key: String,
+    ordering_key: String,
+    url: String,
+    payload: String,
+    status: DeliveryStatus,
+    attempts: i64,
+    next_attempt_at: DateTime<Utc>,
+    last_error: Option<String>,
+    last_status_code: Option<i64>,
+    delivered_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<DeliveryRow> for WebhookDelivery {
+    type Error = anyhow::Error;
+
+    fn try_from(row: DeliveryRow) -> Result<Self> {
+        Ok(Self {
+            id: row.id,
+            account_id: row.account_id,
+            node_id: row.node_id,
+            topic: row.topic,
+            idempotency_key: row.idempotency_key,
+            ordering_key: row.ordering_key,
+            url: row.url,
+            payload: serde_json::from_str(&row.payload)?,
+            status: row.status,
+            attempts: row.attempts,
+            next_attempt_at: row.next_attempt_at,
+            last_error: row.last_error,
+            last_status_code: row.last_status_code,
+            delivered_at: row.delivered_at,
+            created_at: row.created_at,
+        })
+    }
+}
+
+/// Repository for webhook delivery database operations.
+pub struct WebhookDeliveryRepository<'a> {
+    /// Shared SQLite connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> WebhookDeliveryRepository<'a> {
+    /// Creates a new WebhookDeliveryRepository instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Queues a delivery unless one with its idempotency key is already
+    /// queued for the endpoint.
+    ///
+    /// Returns whether the delivery was queued.
+    pub async fn enqueue(&self, delivery: &WebhookDelivery) -> Result<bool> {
+        let payload = delivery.payload.to_string();
+
+        let result = sqlx::query!(
+            r#"
+            INSERT OR IGNORE INTO webhook_deliveries
+            (id, account_id, node_id, topic, idempotency_key, ordering_key, url, payload, status, next_attempt_at, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+            delivery.id,
+            delivery.account_id,
+            delivery.node_id,
+            delivery.topic,
+            delivery.idempotency_key,
+            delivery.ordering_key,
+            delivery.url,
+            payload,
+            delivery.status,
+            delivery.next_attempt_at,
+            delivery.created_at
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Retrieves pending deliveries due by `now`, oldest first.
+    ///
+    /// A delivery waits while an older one with its ordering key and endpoint
+    /// is still pending, so each ordering key is delivered in order.
+    pub async fn get_due_deliveries(
+        &self,
+        now: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<WebhookDelivery>> {
+        let rows = sqlx::query_as!(
+            DeliveryRow,
+            r#"
+            SELECT
+            d.id as "id!",
+            d.account_id as "account_id!",
+            d.node_id as "node_id!",
+            d.topic as "topic!",
+            d.idempotency_key as "idempotency_key!",
+            d.ordering_key as "ordering_key!",
+            d.url as "url!",
+            d.payload as "payload!",
+            d.status as "status!: DeliveryStatus",
+            d.attempts as "attempts!",
+            d.next_attempt_at as "next_attempt_at!: DateTime<Utc>",
+            d.last_error as "last_error?",
+            d.last_status_code as "last_status_code?",
+            d.delivered_at as "delivered_at?: DateTime<Utc>",
+            d.created_at as "created_at!: DateTime<Utc>"
+            FROM webhook_deliveries d
+            WHERE d.status = 'pending' AND d.next_attempt_at <= ?
+            AND NOT EXISTS (
+                SELECT 1 FROM webhook_deliveries earlier
+                WHERE earlier.status = 'pending'
+                AND earlier.account_id = d.account_id
+                AND earlier.url = d.url
+                AND earlier.ordering_key = d.ordering_key
+                AND (earlier.created_at < d.created_at
+                    OR (earlier.created_at = d.created_at AND earlier.id < d.id))
+            )
+            ORDER BY d.created_at, d.id
+            LIMIT ?
+            "#,
+            now,
+            limit
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        rows.into_iter().map(WebhookDelivery::try_from).collect()
+    }
+
+    /// Records a successful attempt.
+    pub async fn mark_delivered(&self, id: &str, status_code: i64) -> Result<()> {
+        let now = Utc::now();
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = 'delivered', attempts = attempts + 1, delivered_at = ?,
+            last_status_code = ?, last_error = NULL
+            WHERE id = ?
+            "#,
+            now,
+            status_code,
+            id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Records a failed attempt, retrying at `retry_at` or giving up without one.
+    pub async fn mark_attempt_failed(
+        &self,
+        id: &str,
+        error: &str,
+        status_code: Option<i64>,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        let status = if retry_at.is_some() {
+            DeliveryStatus::Pending
+        } else {
+            DeliveryStatus::Failed
+        };
+        let next_attempt_at = retry_at.unwrap_or_else(Utc::now);
+
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = ?, attempts = attempts + 1, next_attempt_at = ?,
+            last_error = ?, last_status_code = ?
+            WHERE id = ?
+            "#,
+            status,
+            next_attempt_at,
+            error,
+            status_code,
+            id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Retrieves an account's deliveries, newest first.
+    ///
+    /// `node_ids` restricts the result to deliveries about those nodes.
+    pub async fn get_deliveries_by_account_id(
+        &self,
+        account_id: &str,
+        node_ids: Option<&[String]>,
+        status: Option<DeliveryStatus>,
+        topic: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<WebhookDelivery>> {
+        let node_ids = node_ids.map(serde_json::to_string).transpose()?;
+        let rows = sqlx::query_as!(
+            DeliveryRow,
+            r#"
+            SELECT
+            id as "id!",
+            account_id as "account_id!",
+            node_id as "node_id!",
+            topic as "topic!",
+            idempotency_key as "idempotency_key!",
+            ordering_key as "ordering_key!",
+            url as "url!",
+            payload as "payload!",
+            status as "status!: DeliveryStatus",
+            attempts as "attempts!",
+            next_attempt_at as "next_attempt_at!: DateTime<Utc>",
+            last_error as "last_error?",
+            last_status_code as "last_status_code?",
+            delivered_at as "delivered_at?: DateTime<Utc>",
+            created_at as "created_at!: DateTime<Utc>"
+            FROM webhook_deliveries
+            WHERE account_id = ?
+            AND (? IS NULL OR node_id IN (SELECT value FROM json_each(?)))
+            AND (? IS NULL OR status = ?) AND (? IS NULL OR topic = ?)
+            ORDER BY created_at DESC, id DESC
+            LIMIT ? OFFSET ?
+            "#,
+            account_id,
+            node_ids,
+            node_ids,
+            status,
+            status,
+            topic,
+            topic,
+            limit,
+            offset
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        rows.into_iter().map(WebhookDelivery::try_from).collect()
+    }
+
+    /// Retrieves one of an account's deliveries.
+    pub async fn get_delivery(
+        &self,
+        account_id: &str,
+        id: &str,
+    ) -> Result<Option<WebhookDelivery>> {
+        let row = sqlx::query_as!(
+            DeliveryRow,
+            r#"
+            SELECT
+            id as "id!",
+            account_id as "account_id!",
+            node_id as "node_id!",
+            topic as "topic!",
+            idempotency_key as "idempotency_key!",
+            ordering_key as "ordering_key!",
+            url as "url!",
+            payload as "payload!",
+            status as "status!: DeliveryStatus",
+            attempts as "attempts!",
+            next_attempt_at as "next_attempt_at!: DateTime<Utc>",
+            last_error as "last_error?",
+            last_status_code as "last_status_code?",
+            delivered_at as "delivered_at?: DateTime<Utc>",
+            created_at as "created_at!: DateTime<Utc>"
+            FROM webhook_deliveries
+            WHERE account_id = ? AND id = ?
+            "#,
+            account_id,
+            id
+        )
+        .fetch_optional(self.pool)
+        .await?;
+
+        row.map(WebhookDelivery::try_from).transpose()
+    }
+
+    /// Queues a delivery again with fresh attempts, keeping its idempotency key.
+    ///
+    /// Returns whether the delivery exists.
+    pub async fn requeue(&self, account_id: &str, id: &str) -> Result<bool> {
+        let now = Utc::now();
+        let result = sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = 'pending', attempts = 0, next_attempt_at = ?, last_error = NULL
+            WHERE account_id = ? AND id = ?
+            "#,
+            now,
+            account_id,
+            id
+        )
+        .execute(self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
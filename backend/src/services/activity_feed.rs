@@ -0,0 +1,261 @@
+//! The recent activity of an account, as shown on its settings page.
+//!
+//! Three sources are merged into one feed, newest first: the audit log of
+//! administrative changes, critical events of the nodes the user may see, and
+//! membership changes, i.e. members joining or being removed and invites sent.
+//! Pages are continued from the `(timestamp, id)` of their last entry, with the
+//! same opaque cursor as the event list.
+
+use crate::database::models::{
+    ActivityItem, ActivityKind, AuditAction, AuditLogEntry, EventCursor, EventFilters,
+    EventResponse, EventSeverity, MembershipChange,
+};
+use crate::errors::ServiceResult;
+use crate::repositories::audit_log_repository::AuditLogRepository;
+use crate::repositories::user_repository::UserRepository;
+use crate::services::event_service::EventService;
+use crate::services::node_group_service::NodeGroupService;
+use crate::utils::jwt::Claims;
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+/// One page of `GET /api/account/activity`.
+#[derive(Debug, Serialize)]
+pub struct ActivityPage {
+    pub items: Vec<ActivityItem>,
+    /// Cursor of the next page, absent on the last one
+    pub next_cursor: Option<String>,
+}
+
+fn audit_summary(action: AuditAction, target: Option<&str>) -> String {
+    let target = target.unwrap_or("unknown");
+    match action {
+        AuditAction::SettingsUpdated => "Account settings updated".to_string(),
+        AuditAction::ChannelAcceptorUpdated => "Channel acceptor policy updated".to_string(),
+        AuditAction::AccessLevelChanged => format!("Access level of user {target} changed"),
+        AuditAction::NodeGroupCreated => format!("Node group {target} created"),
+        AuditAction::NodeGroupDeleted => format!("Node group {target} deleted"),
+        AuditAction::NotificationCreated => format!("Notification endpoint {target} created"),
+        AuditAction::NotificationDeleted => format!("Notification endpoint {target} deleted"),
+        AuditAction::CredentialRotated => format!("Credentials of node {target} rotated"),
+    }
+}
+
+impl From<AuditLogEntry> for ActivityItem {
+    fn from(entry: AuditLogEntry) -> Self {
+        Self {
+            id: format!("audit:{}", entry.id),
+            kind: ActivityKind::Audit,
+            action: entry.action.to_string(),
+            summary: audit_summary(entry.action, entry.target.as_deref()),
+            user_id: Some(entry.user_id),
+            node_id: None,
+            timestamp: entry.created_at,
+        }
+    }
+}
+
+impl From<EventResponse> for ActivityItem {
+    fn from(event: EventResponse) -> Self {
+        Self {
+            id: format!("event:{}", event.id),
+            kind: ActivityKind::Event,
+            action: event.event_type.to_string(),
+            summary: format!("{} on {}", event.title, event.node_alias),
+            user_id: Some(event.user_id),
+            node_id: Some(event.node_id),
+            timestamp: event.timestamp,
+        }
+    }
+}
+
+impl From<MembershipChange> for ActivityItem {
+    fn from(change: MembershipChange) -> Self {
+        let summary = match change.action.as_str() {
+            "member_joined" => format!("{} joined the account", change.subject),
+            "member_removed" => format!("{} was removed from the account", change.subject),
+            _ => format!("{} was invited to the account", change.subject),
+        };
+        Self {
+            id: change.id,
+            kind: ActivityKind::Membership,
+            action: change.action,
+            summary,
+            user_id: change.user_id,
+            node_id: None,
+            timestamp: change.occurred_at,
+        }
+    }
+}
+
+/// Orders the entries of all sources newest first, keeps those after `cursor`
+/// and cuts the page at `limit`.
+///
+/// Returns the page together with the cursor of the next page, or `None` when
+/// no more entries follow.
+pub fn merge_page(
+    mut items: Vec<ActivityItem>,
+    cursor: Option<&EventCursor>,
+    limit: usize,
+) -> (Vec<ActivityItem>, Option<EventCursor>) {
+    if let Some(cursor) = cursor {
+        items.retain(|item| (item.timestamp, &item.id) < (cursor.timestamp, &cursor.id));
+    }
+    items.sort_by(|a, b| (b.timestamp, &b.id).cmp(&(a.timestamp, &a.id)));
+
+    if items.len() > limit {
+        items.truncate(limit);
+        let next = items.last().map(|item| EventCursor {
+            timestamp: item.timestamp,
+            id: item.id.clone(),
+        });
+        (items, next)
+    } else {
+        (items, None)
+    }
+}
+
+/// Service recording and reading account activity.
+pub struct ActivityService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> ActivityService<'a> {
+    /// Creates a new ActivityService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Records an administrative change made by the user of `claims`.
+    pub async fn record(
+        &self,
+        claims: &Claims,
+        action: AuditAction,
+        target: Option<&str>,
+        details: Option<Value>,
+    ) -> ServiceResult<()> {
+        let entry = AuditLogEntry {
+            id: Uuid::now_v7().to_string(),
+            account_id: claims.account_id.clone(),
+            user_id: claims.sub.clone(),
+            action,
+            target: target.map(str::to_string),
+            details: details.map(|details| details.to_string()),
+            created_at: Utc::now(),
+        };
+        AuditLogRepository::new(self.pool)
+            .insert_entry(&entry)
+            .await?;
+        Ok(())
+    }
+
+    /// Retrieves one page of the account's activity, newest first.
+    pub async fn get_activity(
+        &self,
+        claims: &Claims,
+        cursor: Option<EventCursor>,
+        limit: usize,
+    ) -> ServiceResult<ActivityPage> {
+        let account_id = &claims.account_id;
+        let until = cursor.as_ref().map(|cursor| cursor.timestamp);
+        // Each source may fill the whole page, and one more entry tells
+        // whether another page follows.
+        let fetch = limit as i64 + 1;
+
+        let audit = AuditLogRepository::new(self.pool)
+            .get_entries(account_id, until, fetch)
+            .await?;
+        let membership = UserRepository::new(self.pool)
+            .get_membership_changes(account_id, until, fetch)
+            .await?;
+
+        // Members of node groups only see events of their groups' nodes
+        let scope = NodeGroupService::new(self.pool)
+            .node_scope_for_claims(claims)
+            .await?;
+        let filters = EventFilters {
+            severities: Some(vec![EventSeverity::Critical]),
+            node_ids: scope.node_ids().map(<[String]>::to_vec),
+            end_date: until,
+            limit: Some(fetch),
+            ..Default::default()
+        };
+        let events = EventService::new(self.pool)
+            .get_events_for_account(self.pool, account_id, Some(filters))
+            .await?;
+
+        let items: Vec<ActivityItem> = audit
+            .into_iter()
+            .map(ActivityItem::from)
+            .chain(events.into_iter().map(ActivityItem::from))
+            .chain(membership.into_iter().map(ActivityItem::from))
+            .collect();
+        let (items, next) = merge_page(items, cursor.as_ref(), limit);
+
+        Ok(ActivityPage {
+            items,
+            next_cursor: next.map(|cursor| cursor.to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Duration};
+
+    fn item(id: &str, timestamp: DateTime<Utc>) -> ActivityItem {
+        ActivityItem {
+            id: id.to_string(),
+            kind: ActivityKind::Audit,
+            action: "settings_updated".to_string(),
+            user_id: None,
+            node_id: None,
+            summary: String::new(),
+            timestamp,
+        }
+    }
+
+    fn ids(items: &[ActivityItem]) -> Vec<&str> {
+        items.iter().map(|item| item.id.as_str()).collect()
+    }
+
+    #[test]
+    fn merges_sources_newest_first() {
+        let now = Utc::now();
+        let items = vec![
+            item("audit:a", now - Duration::minutes(3)),
+            item("event:b", now - Duration::minutes(1)),
+            item("member:c:joined", now - Duration::minutes(2)),
+            item("audit:d", now - Duration::minutes(1)),
+        ];
+
+        let (page, next) = merge_page(items.clone(), None, 3);
+        assert_eq!(ids(&page), ["event:b", "audit:d", "member:c:joined"]);
+        let next = next.unwrap();
+        assert_eq!(next.id, "member:c:joined");
+
+        let (page, next) = merge_page(items, Some(&next), 3);
+        assert_eq!(ids(&page), ["audit:a"]);
+        assert!(next.is_none());
+    }
+
+    #[test]
+    fn continues_after_entries_of_the_same_time() {
+        let now = Utc::now();
+        let items = vec![
+            item("audit:a", now),
+            item("invite:b", now),
+            item("event:c", now),
+        ];
+
+        let (page, next) = merge_page(items.clone(), None, 1);
+        assert_eq!(ids(&page), ["invite:b"]);
+        let (page, _) = merge_page(items, next.as_ref(), 5);
+        assert_eq!(ids(&page), ["event:c", "audit:a"]);
+    }
+}
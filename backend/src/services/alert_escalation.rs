@@ -0,0 +1,314 @@
+//! Escalation of critical alerts nobody acknowledges.
+//!
+//! An account's `escalation.steps` name where a critical alert goes next and
+//! how many minutes after it was raised, e.g. Discord right away, email after
+//! ten minutes and a second webhook after thirty. Every minute the critical
+//! alerts of the last day are checked and the due steps of those still
+//! unacknowledged are run. Acknowledging any endpoint's copy of an alert stops
+//! its escalation.
+//!
+//! Each step runs at most once per alert. A step more than an hour overdue,
+//! e.g. after downtime or for alerts raised before the policy was set up, is
+//! skipped rather than sent late.
+
+use crate::config::EmailConfig;
+use crate::database::models::{AccountSettings, EscalationStep, Event, EventSeverity};
+use crate::errors::ServiceResult;
+use crate::repositories::escalation_repository::EscalationRepository;
+use crate::repositories::event_repository::EventRepository;
+use crate::repositories::notification_repository::NotificationRepository;
+use crate::services::email_service::EmailService;
+use crate::services::notification_dispatcher::NotificationDispatcher;
+use crate::services::reports::escape_html;
+use crate::services::settings_service::SettingsService;
+use crate::services::task_supervisor;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Longest a due step may wait before it is skipped.
+const STEP_GRACE: Duration = Duration::hours(1);
+
+/// Longest delay a step may have, see `EscalationStep::delay_minutes`.
+const MAX_STEP_DELAY: Duration = Duration::minutes(1440);
+
+/// Copies of one alert share these; they only differ by endpoint.
+type AlertKey = (String, String, String, String, DateTime<Utc>);
+
+/// Groups the stored copies of each alert, keeping the order of `events`.
+pub fn group_copies(events: Vec<Event>) -> Vec<Vec<Event>> {
+    let mut groups: Vec<Vec<Event>> = Vec::new();
+    let mut index: HashMap<AlertKey, usize> = HashMap::new();
+    for event in events {
+        let key = (
+            event.account_id.clone(),
+            event.node_id.clone(),
+            event.event_type.to_string(),
+            event.title.clone(),
+            event.timestamp,
+        );
+        match index.get(&key) {
+            Some(&i) => groups[i].push(event),
+            None => {
+                index.insert(key, groups.len());
+                groups.push(vec![event]);
+            }
+        }
+    }
+    groups
+}
+
+/// Indexes of the steps due for an alert raised at `raised_at`, leaving out
+/// those already `run` and those more than `STEP_GRACE` overdue.
+pub fn due_steps(
+    steps: &[EscalationStep],
+    raised_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+    run: &[i64],
+) -> Vec<usize> {
+    steps
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !run.contains(&(*i as i64)))
+        .filter(|(_, step)| {
+            let due_at = raised_at + Duration::minutes(i64::from(step.delay_minutes));
+            due_at <= now && now - due_at <= STEP_GRACE
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Runs the due escalation steps of every unacknowledged critical alert.
+pub async fn run_escalations(pool: &SqlitePool, email: Option<&EmailService>) -> ServiceResult<()> {
+    let now = Utc::now();
+    let events = EventRepository::new(pool)
+        .get_events_by_severity_since(EventSeverity::Critical, now - MAX_STEP_DELAY - STEP_GRACE)
+        .await?;
+    let settings_service = SettingsService::new(pool);
+    let repo = EscalationRepository::new(pool);
+    let mut settings: HashMap<String, AccountSettings> = HashMap::new();
+
+    for copies in group_copies(events) {
+        if copies.iter().any(|copy| copy.acknowledged_at.is_some()) {
+            continue;
+        }
+        // Steps are recorded against the first copy stored
+        let alert = &copies[0];
+        if !settings.contains_key(&alert.account_id) {
+            let account_settings = settings_service.get_settings(&alert.account_id).await?;
+            settings.insert(alert.account_id.clone(), account_settings);
+        }
+        let account_settings = &settings[&alert.account_id];
+        let steps = &account_settings.escalation.steps;
+        if steps.is_empty() {
+            continue;
+        }
+
+        let run = repo.get_run_steps(&alert.id).await?;
+        for i in due_steps(steps, alert.timestamp, now, &run) {
+            if !repo.record_step(&alert.id, i as i64).await? {
+                continue;
+            }
+            tracing::info!("Escalating alert {} to step {}", alert.id, i + 1);
+            run_step(pool, email, &copies, &steps[i], account_settings).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Sends an alert to the endpoint or recipients of a step, logging failures.
+async fn run_step(
+    pool: &SqlitePool,
+    email: Option<&EmailService>,
+    copies: &[Event],
+    step: &EscalationStep,
+    settings: &AccountSettings,
+) {
+    let alert = &copies[0];
+
+    if let Some(notification_id) = &step.notification_id {
+        let notification = match NotificationRepository::new(pool)
+            .get_notification_by_id(notification_id)
+            .await
+        {
+            Ok(Some(notification))
+                if notification.account_id == alert.account_id
+                    && notification.is_active
+                    && notification.paused_at.is_none() =>
+            {
+                notification
+            }
+            Ok(_) => {
+                tracing::warn!(
+                    "Escalation of alert {} skipped inactive, paused or unknown notification {}",
+                    alert.id,
+                    notification_id
+                );
+                return;
+            }
+            Err(e) => {
+                tracing::error!("Failed to load notification {}: {}", notification_id, e);
+                return;
+            }
+        };
+        // The endpoint's own copy, if the alert was stored for it
+        let event = copies
+            .iter()
+            .find(|copy| copy.notifications_id.as_ref() == Some(notification_id))
+            .unwrap_or(alert);
+        let kind = notification.notification_type.clone();
+        if let Err(e) = NotificationDispatcher::new()
+            .send_to_endpoint(event, notification, &settings.branding)
+            .await
+        {
+            tracing::error!(
+                "Failed to escalate alert {} to {} notification {}: {}",
+                alert.id,
+                kind,
+                notification_id,
+                e
+            );
+        }
+        return;
+    }
+
+    let Some(email) = email else {
+        tracing::warn!(
+            "Escalation of alert {} can't be emailed, email isn't configured",
+            alert.id
+        );
+        return;
+    };
+    let sender = settings
+        .branding
+        .display_name
+        .as_deref()
+        .unwrap_or("NodeGaze");
+    let subject = format!(
+        "[{}] Unacknowledged critical alert: {}",
+        sender, alert.title
+    );
+    let text = format!(
+        "{}\n\nNode: {}\nRaised: {}\n\nThis alert hasn't been acknowledged yet.",
+        alert.description,
+        alert.node_alias,
+        alert.timestamp.to_rfc3339()
+    );
+    let html = format!(
+        "<h2>{}</h2><p>{}</p><p>Node: {}<br>Raised: {}</p><p>This alert hasn't been acknowledged yet.</p>",
+        escape_html(&alert.title),
+        escape_html(&alert.description),
+        escape_html(&alert.node_alias),
+        alert.timestamp.to_rfc3339()
+    );
+    for recipient in &step.email_recipients {
+        if let Err(e) = email.send_email(recipient, &subject, &html, &text).await {
+            tracing::warn!(
+                "Failed to email escalation of alert {} to {}: {}",
+                alert.id,
+                recipient,
+                e
+            );
+        }
+    }
+}
+
+/// Runs due escalation steps every minute.
+pub fn spawn_escalation_scheduler(pool: SqlitePool, email_config: Option<EmailConfig>) {
+    let email = email_config.and_then(|config| match EmailService::new(config) {
+        Ok(email) => Some(email),
+        Err(e) => {
+            tracing::warn!("Escalations won't be emailed: {}", e);
+            None
+        }
+    });
+
+    task_supervisor::supervise("alert_escalation", move |task| {
+        let pool = pool.clone();
+        let email = email.clone();
+        async move {
+            // The first run waits for startup migrations
+            let mut interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + CHECK_INTERVAL,
+                CHECK_INTERVAL,
+            );
+            loop {
+                interval.tick().await;
+                let result = run_escalations(&pool, email.as_ref()).await;
+                if let Err(e) = &result {
+                    tracing::error!("Alert escalation failed: {}", e);
+                }
+                task.record_run(&result);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::EventType;
+
+    fn step(delay_minutes: u32) -> EscalationStep {
+        EscalationStep {
+            delay_minutes,
+            notification_id: Some("discord".to_string()),
+            email_recipients: Vec::new(),
+        }
+    }
+
+    fn copy(id: &str, notifications_id: &str, timestamp: DateTime<Utc>) -> Event {
+        Event {
+            id: id.to_string(),
+            account_id: "account".to_string(),
+            user_id: "user".to_string(),
+            node_id: "node".to_string(),
+            node_alias: "alias".to_string(),
+            event_type: EventType::NodeDisconnected,
+            severity: EventSeverity::Critical,
+            title: "Node Disconnected".to_string(),
+            description: "Node went offline".to_string(),
+            data: "{}".to_string(),
+            timestamp,
+            acknowledged_by: None,
+            acknowledged_at: None,
+            notifications_id: Some(notifications_id.to_string()),
+            created_at: timestamp,
+            updated_at: timestamp,
+            is_deleted: false,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn runs_steps_once_they_are_due() {
+        let steps = vec![step(0), step(10), step(30)];
+        let raised_at = Utc::now() - Duration::minutes(12);
+
+        assert_eq!(due_steps(&steps, raised_at, Utc::now(), &[]), vec![0, 1]);
+        assert_eq!(due_steps(&steps, raised_at, Utc::now(), &[0]), vec![1]);
+        assert!(due_steps(&steps, raised_at, Utc::now(), &[0, 1]).is_empty());
+
+        // Steps long overdue are skipped rather than sent late
+        let raised_at = Utc::now() - Duration::hours(3);
+        assert!(due_steps(&steps, raised_at, Utc::now(), &[]).is_empty());
+    }
+
+    #[test]
+    fn groups_the_copies_of_an_alert() {
+        let now = Utc::now();
+        let groups = group_copies(vec![
+            copy("a", "discord", now),
+            copy("b", "webhook", now),
+            copy("c", "discord", now + Duration::seconds(5)),
+        ]);
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].len(), 2);
+        assert_eq!(groups[0][0].id, "a");
+        assert_eq!(groups[1][0].id, "c");
+    }
+}
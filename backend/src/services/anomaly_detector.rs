@@ -0,0 +1,306 @@
+//! Detection of unusual payment activity on connected nodes.
+//!
+//! Every hour, each node's last complete hour is compared with the hourly
+//! figures of the week before it. A figure more standard deviations above its
+//! baseline than the account's `alert_thresholds.payment_anomaly_sigma` raises a
+//! `PaymentAnomalyDetected` warning carrying the statistics. Accounts without a
+//! threshold are not checked.
+
+use crate::database::models::{CreateEvent, Credential, EventSeverity, EventType};
+use crate::errors::{LightningError, ServiceError, ServiceResult};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::services::event_service::EventService;
+use crate::services::node_manager::connect_node;
+use crate::services::payment_stats::{
+    PaymentFlowStats, PaymentStatsBucket, StatsBucket, payment_stats,
+};
+use crate::services::settings_service::SettingsService;
+use crate::services::task_supervisor;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+const HOUR_SECS: i64 = 3600;
+/// Hours before the checked hour that form the baseline.
+const BASELINE_HOURS: i64 = 168;
+/// Baseline hours with a value needed before a metric is judged.
+const MIN_BASELINE_SAMPLES: usize = 24;
+/// Finished outgoing payments an hour needs for its failure rate to count.
+const MIN_FAILURE_RATE_ATTEMPTS: u64 = 5;
+
+/// A payment figure tracked per hour.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyMetric {
+    /// Settled incoming, outgoing and forwarded sats
+    PaymentVolume,
+    FailedPayments,
+    /// Share of finished outgoing payments that failed
+    FailureRate,
+}
+
+const METRICS: [AnomalyMetric; 3] = [
+    AnomalyMetric::PaymentVolume,
+    AnomalyMetric::FailedPayments,
+    AnomalyMetric::FailureRate,
+];
+
+impl AnomalyMetric {
+    fn value(self, stats: &PaymentFlowStats) -> Option<f64> {
+        match self {
+            AnomalyMetric::PaymentVolume => Some(
+                (stats.outgoing.volume_sat + stats.incoming.volume_sat + stats.forwarded.volume_sat)
+                    as f64,
+            ),
+            AnomalyMetric::FailedPayments => Some(stats.failed_outgoing as f64),
+            AnomalyMetric::FailureRate => {
+                let finished = stats.outgoing.count + stats.failed_outgoing;
+                (finished >= MIN_FAILURE_RATE_ATTEMPTS)
+                    .then(|| stats.failed_outgoing as f64 / finished as f64)
+            }
+        }
+    }
+
+    /// Smallest deviation used, so a flat baseline doesn't turn noise into alerts.
+    fn min_std_dev(self) -> f64 {
+        match self {
+            AnomalyMetric::PaymentVolume => 1_000.0,
+            AnomalyMetric::FailedPayments => 1.0,
+            AnomalyMetric::FailureRate => 0.05,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            AnomalyMetric::PaymentVolume => "Payment volume",
+            AnomalyMetric::FailedPayments => "Failed payments",
+            AnomalyMetric::FailureRate => "Payment failure rate",
+        }
+    }
+}
+
+/// A metric of the checked hour that deviates from its baseline.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Anomaly {
+    pub metric: AnomalyMetric,
+    pub current: f64,
+    pub baseline_mean: f64,
+    pub baseline_std_dev: f64,
+    /// Standard deviations the current value lies above the mean
+    pub sigma: f64,
+    pub baseline_samples: usize,
+}
+
+/// Compares the last bucket against the ones before it.
+///
+/// Returns the metrics more than `threshold` standard deviations above their
+/// baseline. Drops are not reported.
+pub fn detect_anomalies(buckets: &[PaymentStatsBucket], threshold: f64) -> Vec<Anomaly> {
+    let Some((current, baseline)) = buckets.split_last() else {
+        return Vec::new();
+    };
+
+    METRICS
+        .iter()
+        .filter_map(|&metric| {
+            let current = metric.value(&current.stats)?;
+            let samples: Vec<f64> = baseline
+                .iter()
+                .filter_map(|bucket| metric.value(&bucket.stats))
+                .collect();
+            if samples.len() < MIN_BASELINE_SAMPLES {
+                return None;
+            }
+
+            let count = samples.len() as f64;
+            let mean = samples.iter().sum::<f64>() / count;
+            let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / count;
+            let std_dev = variance.sqrt();
+            let sigma = (current - mean) / std_dev.max(metric.min_std_dev());
+
+            (sigma > threshold).then_some(Anomaly {
+                metric,
+                current,
+                baseline_mean: mean,
+                baseline_std_dev: std_dev,
+                sigma,
+                baseline_samples: samples.len(),
+            })
+        })
+        .collect()
+}
+
+/// Checks every node of the accounts with an anomaly threshold.
+pub async fn check_all_nodes(pool: &SqlitePool) -> ServiceResult<()> {
+    let credentials = CredentialRepository::new(pool)
+        .get_node_credentials(None, None)
+        .await?;
+    let settings = SettingsService::new(pool);
+    let mut thresholds: HashMap<String, Option<f64>> = HashMap::new();
+
+    for credential in &credentials {
+        if !thresholds.contains_key(&credential.account_id) {
+            let threshold = settings
+                .get_settings(&credential.account_id)
+                .await?
+                .alert_thresholds
+                .payment_anomaly_sigma;
+            thresholds.insert(credential.account_id.clone(), threshold);
+        }
+        let Some(threshold) = thresholds[&credential.account_id] else {
+            continue;
+        };
+
+        if let Err(e) = check_node(pool, credential, threshold).await {
+            tracing::warn!(
+                "Payment anomaly check of node {} failed: {}",
+                credential.node_id,
+                e
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks a node's last complete hour and records the anomalies found.
+async fn check_node(
+    pool: &SqlitePool,
+    credential: &Credential,
+    threshold: f64,
+) -> ServiceResult<()> {
+    let now = Utc::now().timestamp();
+    let hour_start = now - now.rem_euclid(HOUR_SECS);
+    let window = |timestamp| {
+        DateTime::from_timestamp(timestamp, 0).ok_or_else(|| ServiceError::InternalError {
+            message: format!("Timestamp {timestamp} is out of range"),
+        })
+    };
+    let start = window(hour_start - (BASELINE_HOURS + 1) * HOUR_SECS)?;
+    let end = window(hour_start - 1)?;
+
+    let external = |e: LightningError| ServiceError::ExternalService {
+        message: e.to_string(),
+    };
+    let client = connect_node(credential).await.map_err(external)?;
+    let payments = client.list_payments().await.map_err(external)?;
+    let forwards = client
+        .list_forwards(start.timestamp() as u64)
+        .await
+        .map_err(external)?;
+
+    let stats = payment_stats(
+        &payments,
+        &forwards,
+        start,
+        end,
+        StatsBucket::Hour,
+        chrono_tz::UTC,
+    );
+    let Some(checked) = stats.buckets.last() else {
+        return Ok(());
+    };
+
+    for anomaly in detect_anomalies(&stats.buckets, threshold) {
+        EventService::new(pool)
+            .create_and_dispatch_event(CreateEvent {
+                id: Uuid::now_v7().to_string(),
+                account_id: credential.account_id.clone(),
+                user_id: credential.user_id.clone(),
+                node_id: credential.node_id.clone(),
+                node_alias: credential.node_alias.clone(),
+                event_type: EventType::PaymentAnomalyDetected,
+                severity: EventSeverity::Warning,
+                title: "Payment Anomaly Detected".to_string(),
+                description: format!(
+                    "{} is {:.1} standard deviations above its baseline",
+                    anomaly.metric.label(),
+                    anomaly.sigma
+                ),
+                data: json!({
+                    "metric": anomaly.metric,
+                    "current": anomaly.current,
+                    "baseline_mean": anomaly.baseline_mean,
+                    "baseline_std_dev": anomaly.baseline_std_dev,
+                    "sigma": anomaly.sigma,
+                    "threshold": threshold,
+                    "baseline_samples": anomaly.baseline_samples,
+                    "window_start": checked.start,
+                    "window_end": end,
+                })
+                .to_string(),
+                notifications_id: None,
+                timestamp: Utc::now(),
+            })
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Checks for payment anomalies every hour.
+pub fn spawn_anomaly_detector(pool: SqlitePool) {
+    task_supervisor::supervise("anomaly_detector", move |task| {
+        let pool = pool.clone();
+        async move {
+            // Fixed ticks check each hour once; the first waits for startup migrations
+            let mut interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + CHECK_INTERVAL,
+                CHECK_INTERVAL,
+            );
+            loop {
+                interval.tick().await;
+                let result = check_all_nodes(&pool).await;
+                if let Err(e) = &result {
+                    tracing::error!("Payment anomaly detection failed: {}", e);
+                }
+                task.record_run(&result);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hour(failed_outgoing: u64, settled: u64) -> PaymentStatsBucket {
+        let mut stats = PaymentFlowStats {
+            failed_outgoing,
+            ..Default::default()
+        };
+        stats.outgoing.count = settled;
+        stats.outgoing.volume_sat = settled * 1_000;
+        PaymentStatsBucket {
+            start: Utc::now(),
+            stats,
+        }
+    }
+
+    #[test]
+    fn flags_spikes_above_the_baseline() {
+        let mut buckets: Vec<_> = (0..48).map(|i| hour(i % 2, 10)).collect();
+        buckets.push(hour(9, 10));
+
+        let anomalies = detect_anomalies(&buckets, 3.0);
+        let metrics: Vec<_> = anomalies.iter().map(|a| a.metric).collect();
+        assert_eq!(
+            metrics,
+            vec![AnomalyMetric::FailedPayments, AnomalyMetric::FailureRate]
+        );
+        assert_eq!(anomalies[0].current, 9.0);
+        assert_eq!(anomalies[0].baseline_mean, 0.5);
+        assert_eq!(anomalies[0].baseline_samples, 48);
+
+        // A quiet hour is not an anomaly, and a short history isn't judged
+        buckets.pop();
+        buckets.push(hour(0, 2));
+        assert!(detect_anomalies(&buckets, 3.0).is_empty());
+        let short = vec![hour(0, 10), hour(0, 10), hour(9, 10)];
+        assert!(detect_anomalies(&short, 3.0).is_empty());
+    }
+}
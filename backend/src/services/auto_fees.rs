@@ -0,0 +1,464 @@
+//! Balance-driven fee adjustment for individual channels.
+//!
+//! A channel's policy names the local balance share to steer towards and the fee
+//! charged there. Below the target the fee rises linearly to `max_fee_ppm` at an
+//! empty channel; above it the fee falls to `min_fee_ppm` at a full one. Each run
+//! moves the current fee at most `max_step_ppm` towards that figure.
+//!
+//! Channels without a policy of their own follow the policy of their tags, see
+//! `services::channel_tags`.
+//!
+//! Every hour, the enabled policies of each node are evaluated and the changed
+//! fees applied. Each attempted change is written to the adjustment log.
+
+use crate::database::models::{AutoFeeAdjustment, AutoFeePolicy, UpdateAutoFeePolicyRequest};
+use crate::errors::{LightningError, ServiceError, ServiceResult};
+use crate::repositories::auto_fee_repository::AutoFeeRepository;
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::services::channel_tags::{ChannelTagService, tag_fee_policy};
+use crate::services::node_manager::LightningClient;
+use crate::services::node_manager::connect_node;
+use crate::services::task_supervisor;
+use crate::utils::amount::Amount;
+use crate::utils::{ChannelDetails, ShortChannelID};
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::str::FromStr;
+use uuid::Uuid;
+use validator::Validate;
+
+const RUN_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// What a policy would do to its channel's fee right now.
+#[derive(Debug, Clone, Serialize)]
+pub struct FeeProposal {
+    pub channel_id: String,
+    /// Tag the policy comes from, absent for the channel's own policy
+    pub tag: Option<String>,
+    pub enabled: bool,
+    /// Local balance share of the capacity
+    pub local_ratio: Option<f64>,
+    pub current_fee_ppm: Option<u32>,
+    /// Fee the policy steers towards at this balance
+    pub target_fee_ppm: Option<u32>,
+    /// Fee after this run's step
+    pub new_fee_ppm: Option<u32>,
+    /// Why the channel can't be evaluated
+    pub error: Option<String>,
+}
+
+impl FeeProposal {
+    /// Whether applying the proposal changes the fee.
+    pub fn changes_fee(&self) -> bool {
+        matches!(
+            (self.current_fee_ppm, self.new_fee_ppm),
+            (Some(current), Some(new)) if current != new
+        )
+    }
+
+    fn failed(policy: &AutoFeePolicy, error: impl Into<String>) -> Self {
+        Self {
+            channel_id: policy.channel_id.clone(),
+            tag: None,
+            enabled: policy.enabled,
+            local_ratio: None,
+            current_fee_ppm: None,
+            target_fee_ppm: None,
+            new_fee_ppm: None,
+            error: Some(error.into()),
+        }
+    }
+}
+
+/// Fee the policy charges at a local balance share.
+pub fn target_fee_ppm(policy: &AutoFeePolicy, local_ratio: f64) -> u32 {
+    let ratio = local_ratio.clamp(0.0, 1.0);
+    let target = policy.target_local_ratio;
+    let base = policy.base_fee_ppm as f64;
+
+    let fee = if ratio < target {
+        let depletion = (target - ratio) / target;
+        base + (policy.max_fee_ppm as f64 - base) * depletion
+    } else if ratio > target {
+        let fullness = (ratio - target) / (1.0 - target);
+        base - (base - policy.min_fee_ppm as f64) * fullness
+    } else {
+        base
+    };
+
+    fee.round() as u32
+}
+
+/// Moves the current fee towards the target by at most the policy's step.
+pub fn next_fee_ppm(policy: &AutoFeePolicy, current_fee_ppm: u32, target_fee_ppm: u32) -> u32 {
+    if target_fee_ppm > current_fee_ppm {
+        current_fee_ppm + (target_fee_ppm - current_fee_ppm).min(policy.max_step_ppm)
+    } else {
+        current_fee_ppm - (current_fee_ppm - target_fee_ppm).min(policy.max_step_ppm)
+    }
+}
+
+/// Evaluates a policy against its channel's current balance and fee.
+fn propose(
+    policy: &AutoFeePolicy,
+    details: &ChannelDetails,
+    local_pubkey: &bitcoin::secp256k1::PublicKey,
+) -> FeeProposal {
+    if details.capacity == Amount::ZERO {
+        return FeeProposal::failed(policy, "Channel has no capacity");
+    }
+    let Some(local_policy) = [&details.node1_policy, &details.node2_policy]
+        .into_iter()
+        .flatten()
+        .find(|node_policy| node_policy.pubkey == *local_pubkey)
+    else {
+        return FeeProposal::failed(policy, "Channel has no local routing policy");
+    };
+
+    let local_ratio = details.local_balance.msat() as f64 / details.capacity.msat() as f64;
+    let current = local_policy.fee_rate_milli_msat.min(u32::MAX as u64) as u32;
+    let target = target_fee_ppm(policy, local_ratio);
+
+    FeeProposal {
+        channel_id: policy.channel_id.clone(),
+        tag: None,
+        enabled: policy.enabled,
+        local_ratio: Some(local_ratio),
+        current_fee_ppm: Some(current),
+        target_fee_ppm: Some(target),
+        new_fee_ppm: Some(next_fee_ppm(policy, current, target)),
+        error: None,
+    }
+}
+
+/// Service layer for auto-fee policies.
+pub struct AutoFeeService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> AutoFeeService<'a> {
+    /// Creates a new AutoFeeService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Lists the policies of one of the account's nodes.
+    pub async fn get_policies(
+        &self,
+        account_id: &str,
+        node_id: &str,
+    ) -> ServiceResult<Vec<AutoFeePolicy>> {
+        Ok(AutoFeeRepository::new(self.pool)
+            .get_policies(account_id, node_id)
+            .await?)
+    }
+
+    /// Sets the policy of a channel, replacing any previous one.
+    pub async fn update_policy(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        channel_id: &ShortChannelID,
+        request: UpdateAutoFeePolicyRequest,
+    ) -> ServiceResult<AutoFeePolicy> {
+        request
+            .validate()
+            .map_err(|e| ServiceError::validation(e.to_string()))?;
+
+        let policy = AutoFeeRepository::new(self.pool)
+            .upsert_policy(account_id, node_id, &channel_id.to_string(), &request)
+            .await?;
+
+        Ok(policy)
+    }
+
+    /// Removes the policy of a channel.
+    pub async fn delete_policy(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        channel_id: &ShortChannelID,
+    ) -> ServiceResult<()> {
+        if !AutoFeeRepository::new(self.pool)
+            .delete_policy(account_id, node_id, &channel_id.to_string())
+            .await?
+        {
+            return Err(ServiceError::not_found(
+                "Auto-fee policy",
+                channel_id.to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Retrieves the most recent fee changes attempted on a node, newest first.
+    pub async fn get_adjustments(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        limit: i64,
+    ) -> ServiceResult<Vec<AutoFeeAdjustment>> {
+        Ok(AutoFeeRepository::new(self.pool)
+            .get_adjustments(account_id, node_id, limit)
+            .await?)
+    }
+
+    /// The policies of a node's channels: their own, else their tags', each
+    /// with the tag it comes from.
+    async fn effective_policies(
+        &self,
+        account_id: &str,
+        node_id: &str,
+    ) -> ServiceResult<Vec<(AutoFeePolicy, Option<String>)>> {
+        let mut policies: Vec<(AutoFeePolicy, Option<String>)> = self
+            .get_policies(account_id, node_id)
+            .await?
+            .into_iter()
+            .map(|policy| (policy, None))
+            .collect();
+
+        let mut tagged: Vec<_> = ChannelTagService::new(self.pool)
+            .get_node_channel_tags(account_id, node_id)
+            .await?
+            .into_iter()
+            .collect();
+        tagged.sort_by_key(|(channel_id, _)| *channel_id);
+        for (channel_id, tags) in tagged {
+            let channel_id = channel_id.to_string();
+            if policies
+                .iter()
+                .any(|(policy, _)| policy.channel_id == channel_id)
+            {
+                continue;
+            }
+            let Some((tag, policy)) = tag_fee_policy(&tags) else {
+                continue;
+            };
+            policies.push((
+                AutoFeePolicy {
+                    node_id: node_id.to_string(),
+                    channel_id,
+                    enabled: policy.enabled,
+                    target_local_ratio: policy.target_local_ratio,
+                    base_fee_ppm: policy.base_fee_ppm,
+                    min_fee_ppm: policy.min_fee_ppm,
+                    max_fee_ppm: policy.max_fee_ppm,
+                    max_step_ppm: policy.max_step_ppm,
+                    created_at: tag.created_at,
+                    updated_at: tag.updated_at,
+                },
+                Some(tag.name.clone()),
+            ));
+        }
+
+        Ok(policies)
+    }
+
+    /// Evaluates every policy of a node without changing any fee.
+    pub async fn preview(
+        &self,
+        account_id: &str,
+        client: &(dyn LightningClient + Send + Sync),
+    ) -> ServiceResult<Vec<FeeProposal>> {
+        let local_pubkey = client.get_info().pubkey;
+        let policies = self
+            .effective_policies(account_id, &local_pubkey.to_string())
+            .await?;
+
+        let channel_ids: Vec<ShortChannelID> = policies
+            .iter()
+            .filter_map(|(policy, _)| ShortChannelID::from_str(&policy.channel_id).ok())
+            .collect();
+        let mut details = client
+            .get_channels_info(&channel_ids)
+            .await
+            .map_err(|e| ServiceError::ExternalService {
+                message: e.to_string(),
+            })?
+            .into_iter();
+
+        Ok(policies
+            .iter()
+            .map(|(policy, tag)| {
+                let proposal = if ShortChannelID::from_str(&policy.channel_id).is_err() {
+                    FeeProposal::failed(policy, "Invalid channel id")
+                } else {
+                    match details.next() {
+                        Some(Ok(details)) => propose(policy, &details, &local_pubkey),
+                        Some(Err(e)) => FeeProposal::failed(policy, e.to_string()),
+                        None => FeeProposal::failed(policy, "Channel not found"),
+                    }
+                };
+                FeeProposal {
+                    tag: tag.clone(),
+                    ..proposal
+                }
+            })
+            .collect())
+    }
+
+    /// Applies the changed fees of a node's enabled policies and logs each attempt.
+    pub async fn apply(
+        &self,
+        account_id: &str,
+        client: &(dyn LightningClient + Send + Sync),
+    ) -> ServiceResult<Vec<AutoFeeAdjustment>> {
+        let node_id = client.get_info().pubkey.to_string();
+        let repository = AutoFeeRepository::new(self.pool);
+        let mut adjustments = Vec::new();
+
+        for proposal in self.preview(account_id, client).await? {
+            if !proposal.enabled || !proposal.changes_fee() {
+                continue;
+            }
+            let (Some(local_ratio), Some(old_fee_ppm), Some(new_fee_ppm)) = (
+                proposal.local_ratio,
+                proposal.current_fee_ppm,
+                proposal.new_fee_ppm,
+            ) else {
+                continue;
+            };
+            let Ok(channel_id) = ShortChannelID::from_str(&proposal.channel_id) else {
+                continue;
+            };
+
+            let result = client.set_channel_fee_rate(&channel_id, new_fee_ppm).await;
+            let adjustment = AutoFeeAdjustment {
+                id: Uuid::now_v7().to_string(),
+                node_id: node_id.clone(),
+                channel_id: proposal.channel_id,
+                local_ratio,
+                old_fee_ppm,
+                new_fee_ppm,
+                applied: result.is_ok(),
+                error: result.err().map(|e| e.to_string()),
+                created_at: Utc::now(),
+            };
+
+            match &adjustment.error {
+                None => tracing::info!(
+                    "Auto-fee changed channel {} of node {} from {} to {} ppm",
+                    adjustment.channel_id,
+                    node_id,
+                    old_fee_ppm,
+                    new_fee_ppm
+                ),
+                Some(error) => tracing::warn!(
+                    "Auto-fee could not change channel {} of node {}: {}",
+                    adjustment.channel_id,
+                    node_id,
+                    error
+                ),
+            }
+            repository
+                .record_adjustment(account_id, &adjustment)
+                .await?;
+            adjustments.push(adjustment);
+        }
+
+        Ok(adjustments)
+    }
+}
+
+/// Applies the enabled policies of every node that has any.
+pub async fn run_all_nodes(pool: &SqlitePool) -> ServiceResult<()> {
+    let nodes = AutoFeeRepository::new(pool)
+        .get_nodes_with_enabled_policies()
+        .await?;
+    if nodes.is_empty() {
+        return Ok(());
+    }
+    let credentials = CredentialRepository::new(pool)
+        .get_node_credentials(None, None)
+        .await?;
+
+    for (account_id, node_id) in nodes {
+        // Read-only credentials can't change fees, and are only picked without a writable one
+        let Some(credential) = credentials.iter().find(|credential| {
+            credential.account_id == account_id
+                && credential.node_id == node_id
+                && !credential.read_only
+        }) else {
+            tracing::warn!("Auto-fee skipped node {node_id}: no writable credentials stored");
+            continue;
+        };
+
+        let result = async {
+            let client = connect_node(credential)
+                .await
+                .map_err(|e: LightningError| ServiceError::ExternalService {
+                    message: e.to_string(),
+                })?;
+            AutoFeeService::new(pool)
+                .apply(&account_id, client.as_ref())
+                .await
+        }
+        .await;
+        if let Err(e) = result {
+            tracing::warn!("Auto-fee run of node {} failed: {}", node_id, e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Applies auto-fee policies every hour.
+pub fn spawn_auto_fee_scheduler(pool: SqlitePool) {
+    task_supervisor::supervise("auto_fees", move |task| {
+        let pool = pool.clone();
+        async move {
+            // The first run waits a full interval so startup migrations can finish
+            let mut interval =
+                tokio::time::interval_at(tokio::time::Instant::now() + RUN_INTERVAL, RUN_INTERVAL);
+            loop {
+                interval.tick().await;
+                let result = run_all_nodes(&pool).await;
+                if let Err(e) = &result {
+                    tracing::error!("Auto-fee run failed: {}", e);
+                }
+                task.record_run(&result);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> AutoFeePolicy {
+        AutoFeePolicy {
+            node_id: String::new(),
+            channel_id: "1".to_string(),
+            enabled: true,
+            target_local_ratio: 0.5,
+            base_fee_ppm: 100,
+            min_fee_ppm: 10,
+            max_fee_ppm: 500,
+            max_step_ppm: 50,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn raises_fees_when_depleted_and_lowers_when_full() {
+        let policy = policy();
+        assert_eq!(target_fee_ppm(&policy, 0.5), 100);
+        assert_eq!(target_fee_ppm(&policy, 0.0), 500);
+        assert_eq!(target_fee_ppm(&policy, 0.25), 300);
+        assert_eq!(target_fee_ppm(&policy, 1.0), 10);
+        assert_eq!(target_fee_ppm(&policy, 0.75), 55);
+    }
+
+    #[test]
+    fn limits_each_change_to_the_step() {
+        let policy = policy();
+        assert_eq!(next_fee_ppm(&policy, 100, 300), 150);
+        assert_eq!(next_fee_ppm(&policy, 100, 80), 80);
+        assert_eq!(next_fee_ppm(&policy, 100, 10), 50);
+        assert_eq!(next_fee_ppm(&policy, 100, 100), 100);
+    }
+}
@@ -0,0 +1,385 @@
+//! Encrypted off-site backups of the NodeGaze database.
+//!
+//! A consistent copy of the live database is taken with `VACUUM INTO`, encrypted
+//! with AES-256-GCM under `BACKUP_ENCRYPTION_KEY` and uploaded to the configured
+//! S3-compatible bucket. The unencrypted copy holds every stored secret, so it is
+//! written owner-only into a private directory next to the database and removed
+//! once read; copies left behind by a crash are removed at startup. Only the newest `BACKUP_RETENTION_COUNT` backups are
+//! kept. `nodegaze-admin restore-backup` downloads and decrypts one again.
+
+use crate::config::BackupConfig;
+use crate::services::task_supervisor;
+use crate::utils::s3::{S3Client, S3Object};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Identifies the backup format; also authenticated with the ciphertext.
+const BACKUP_MAGIC: &[u8; 8] = b"NGZBAK1\0";
+const NONCE_LEN: usize = 12;
+const BACKUP_SUFFIX: &str = ".db.enc";
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+/// Directory next to the database holding snapshots while they are read.
+const SNAPSHOT_DIR: &str = ".nodegaze-backups";
+const SNAPSHOT_PREFIX: &str = "nodegaze-backup-";
+
+/// Encrypts a database snapshot.
+///
+/// The output is the format marker, a random nonce and the ciphertext with its tag.
+pub fn encrypt_backup(key: &[u8; 32], snapshot: &[u8]) -> Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new(&(*key).into());
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: snapshot,
+                aad: BACKUP_MAGIC,
+            },
+        )
+        .map_err(|_| anyhow!("failed to encrypt backup"))?;
+
+    let mut backup = Vec::with_capacity(BACKUP_MAGIC.len() + NONCE_LEN + ciphertext.len());
+    backup.extend_from_slice(BACKUP_MAGIC);
+    backup.extend_from_slice(&nonce);
+    backup.extend_from_slice(&ciphertext);
+    Ok(backup)
+}
+
+/// Decrypts a backup made by [`encrypt_backup`].
+pub fn decrypt_backup(key: &[u8; 32], backup: &[u8]) -> Result<Vec<u8>> {
+    let Some(rest) = backup.strip_prefix(BACKUP_MAGIC.as_slice()) else {
+        bail!("not a NodeGaze backup");
+    };
+    if rest.len() < NONCE_LEN {
+        bail!("backup is truncated");
+    }
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    Aes256Gcm::new(&(*key).into())
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: BACKUP_MAGIC,
+            },
+        )
+        .map_err(|_| anyhow!("backup could not be decrypted; wrong key or corrupted file"))
+}
+
+/// Object key of a backup taken at `at`. Keys sort in the order backups were taken.
+pub fn backup_object_key(prefix: &str, at: DateTime<Utc>) -> String {
+    format!(
+        "{prefix}nodegaze-{}{BACKUP_SUFFIX}",
+        at.format("%Y%m%dT%H%M%SZ")
+    )
+}
+
+/// Keys of the backups beyond the newest `retention_count`, oldest first.
+pub fn expired_backups(objects: &[S3Object], retention_count: usize) -> Vec<String> {
+    let mut keys: Vec<&str> = objects
+        .iter()
+        .map(|object| object.key.as_str())
+        .filter(|key| key.ends_with(BACKUP_SUFFIX))
+        .collect();
+    keys.sort_unstable();
+
+    let expired = keys.len().saturating_sub(retention_count);
+    keys[..expired].iter().map(|key| key.to_string()).collect()
+}
+
+/// Takes, uploads, lists and restores backups.
+pub struct BackupService<'a> {
+    config: &'a BackupConfig,
+    s3: S3Client,
+}
+
+impl<'a> BackupService<'a> {
+    /// Creates a new BackupService instance.
+    pub fn new(config: &'a BackupConfig) -> Self {
+        Self {
+            config,
+            s3: S3Client::new(config),
+        }
+    }
+
+    /// Snapshots, encrypts and uploads the database, then deletes expired backups.
+    ///
+    /// Returns the object key of the new backup.
+    pub async fn run_backup(&self, pool: &SqlitePool) -> Result<String> {
+        let snapshot = snapshot_database(pool).await?;
+        let size = snapshot.len();
+        let encrypted = encrypt_backup(&self.config.encryption_key, &snapshot)?;
+        drop(snapshot);
+
+        let key = backup_object_key(&self.config.s3_prefix, Utc::now());
+        self.s3
+            .put_object(&key, encrypted)
+            .await
+            .context("failed to upload backup")?;
+        tracing::info!("Uploaded database backup {} ({} bytes)", key, size);
+
+        // A failed rotation leaves extra backups behind but doesn't fail the backup
+        if let Err(e) = self.rotate().await {
+            tracing::warn!("Could not delete expired backups: {:#}", e);
+        }
+
+        Ok(key)
+    }
+
+    /// Lists the stored backups, oldest first.
+    pub async fn list_backups(&self) -> Result<Vec<S3Object>> {
+        Ok(self
+            .s3
+            .list_objects(&self.config.s3_prefix)
+            .await?
+            .into_iter()
+            .filter(|object| object.key.ends_with(BACKUP_SUFFIX))
+            .collect())
+    }
+
+    /// Downloads and decrypts a backup, the newest when no key is given, into a new
+    /// database file at `output`.
+    ///
+    /// Returns the key of the restored backup.
+    pub async fn restore(&self, key: Option<&str>, output: &Path) -> Result<String> {
+        if output.exists() {
+            bail!("{} already exists", output.display());
+        }
+
+        let key = match key {
+            Some(key) => key.to_string(),
+            None => {
+                self.list_backups()
+                    .await?
+                    .pop()
+                    .ok_or_else(|| anyhow!("no backups stored under {}", self.config.s3_prefix))?
+                    .key
+            }
+        };
+
+        let encrypted = self
+            .s3
+            .get_object(&key)
+            .await
+            .with_context(|| format!("failed to download backup {key}"))?;
+        let snapshot = decrypt_backup(&self.config.encryption_key, &encrypted)?;
+        if !snapshot.starts_with(SQLITE_HEADER) {
+            bail!("backup {key} does not contain a SQLite database");
+        }
+
+        tokio::fs::write(output, snapshot)
+            .await
+            .with_context(|| format!("failed to write {}", output.display()))?;
+        Ok(key)
+    }
+
+    async fn rotate(&self) -> Result<()> {
+        let objects = self.s3.list_objects(&self.config.s3_prefix).await?;
+        for key in expired_backups(&objects, self.config.retention_count) {
+            self.s3.delete_object(&key).await?;
+            tracing::info!("Deleted expired database backup {}", key);
+        }
+        Ok(())
+    }
+}
+
+/// The private snapshot directory of the database behind the pool.
+async fn snapshot_dir(pool: &SqlitePool) -> Result<PathBuf> {
+    let file: String =
+        sqlx::query_scalar("SELECT file FROM pragma_database_list WHERE name = 'main'")
+            .fetch_one(pool)
+            .await
+            .context("failed to locate the database file")?;
+    if file.is_empty() {
+        bail!("only databases stored in a file can be backed up");
+    }
+    let database_dir = Path::new(&file).parent().unwrap_or(Path::new("."));
+    Ok(database_dir.join(SNAPSHOT_DIR))
+}
+
+/// Reads a consistent copy of the live database into memory.
+async fn snapshot_database(pool: &SqlitePool) -> Result<Vec<u8>> {
+    let dir = snapshot_dir(pool).await?;
+    tokio::fs::DirBuilder::new()
+        .mode(0o700)
+        .recursive(true)
+        .create(&dir)
+        .await
+        .with_context(|| format!("failed to create {}", dir.display()))?;
+    // An existing directory keeps its mode, so restrict it as well
+    tokio::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700)).await?;
+
+    let path = dir.join(format!("{SNAPSHOT_PREFIX}{}.db", Uuid::now_v7()));
+    let path_str = path
+        .to_str()
+        .ok_or_else(|| anyhow!("snapshot path is not valid UTF-8"))?;
+
+    let result = async {
+        // VACUUM INTO fills an existing empty file, keeping its owner-only mode
+        tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+            .await
+            .with_context(|| format!("failed to create {}", path.display()))?;
+        sqlx::query("VACUUM INTO ?")
+            .bind(path_str)
+            .execute(pool)
+            .await
+            .context("failed to snapshot the database")?;
+        Ok::<_, anyhow::Error>(tokio::fs::read(&path).await?)
+    }
+    .await;
+
+    // The copy is unencrypted, so it is removed as soon as it has been read
+    let _ = tokio::fs::remove_file(&path).await;
+    result
+}
+
+/// Removes snapshots left behind by a server that stopped while taking a backup,
+/// including ones earlier versions wrote to the temporary directory.
+async fn remove_stale_snapshots(pool: &SqlitePool) {
+    let mut dirs = vec![std::env::temp_dir()];
+    if let Ok(dir) = snapshot_dir(pool).await {
+        dirs.push(dir);
+    }
+
+    for dir in dirs {
+        let Ok(mut entries) = tokio::fs::read_dir(&dir).await else {
+            continue;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if !(name.starts_with(SNAPSHOT_PREFIX) && name.ends_with(".db")) {
+                continue;
+            }
+            match tokio::fs::remove_file(entry.path()).await {
+                Ok(()) => {
+                    tracing::info!("Removed stale backup snapshot {}", entry.path().display())
+                }
+                Err(e) => tracing::warn!(
+                    "Failed to remove stale backup snapshot {}: {}",
+                    entry.path().display(),
+                    e
+                ),
+            }
+        }
+    }
+}
+
+/// Backs the database up every `BACKUP_INTERVAL_HOURS` when backups are configured.
+pub fn spawn_backup_scheduler(pool: SqlitePool, config: Option<BackupConfig>) {
+    // Runs whether or not backups are still configured, as a crash may have left
+    // a snapshot behind while they were
+    tokio::spawn({
+        let pool = pool.clone();
+        async move { remove_stale_snapshots(&pool).await }
+    });
+
+    let Some(config) = config else {
+        return;
+    };
+
+    task_supervisor::supervise("backup", move |task| {
+        let pool = pool.clone();
+        let config = config.clone();
+        async move {
+            let period = Duration::from_secs(config.interval_hours * 3600);
+            // The first backup waits a full interval so startup migrations can finish
+            let mut interval =
+                tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            let service = BackupService::new(&config);
+            loop {
+                interval.tick().await;
+                let result = service.run_backup(&pool).await;
+                if let Err(e) = &result {
+                    tracing::error!("Database backup failed: {:#}", e);
+                }
+                task.record_run(&result);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn encrypted_backups_round_trip_and_reject_tampering() {
+        let key = [7u8; 32];
+        let snapshot = b"SQLite format 3\0rest of the database".to_vec();
+
+        let mut backup = encrypt_backup(&key, &snapshot).unwrap();
+        assert!(backup.starts_with(BACKUP_MAGIC));
+        assert_eq!(decrypt_backup(&key, &backup).unwrap(), snapshot);
+        assert!(decrypt_backup(&[8u8; 32], &backup).is_err());
+
+        let last = backup.len() - 1;
+        backup[last] ^= 1;
+        assert!(decrypt_backup(&key, &backup).is_err());
+    }
+
+    #[test]
+    fn expires_all_but_the_newest_backups() {
+        let object = |at| S3Object {
+            key: backup_object_key("nodegaze/", Utc.timestamp_opt(at, 0).unwrap()),
+            size: 1,
+        };
+        let mut objects = vec![object(300), object(100), object(200)];
+        objects.push(S3Object {
+            key: "nodegaze/notes.txt".to_string(),
+            size: 1,
+        });
+
+        assert_eq!(
+            expired_backups(&objects, 2),
+            vec!["nodegaze/nodegaze-19700101T000140Z.db.enc".to_string()]
+        );
+        assert!(expired_backups(&objects, 5).is_empty());
+    }
+
+    #[tokio::test]
+    async fn snapshots_stay_private_and_stale_ones_are_removed() {
+        let dir = std::env::temp_dir().join(format!("nodegaze-backup-test-{}", Uuid::now_v7()));
+        std::fs::create_dir(&dir).unwrap();
+        let pool = SqlitePool::connect(&format!("sqlite://{}/nodegaze.db?mode=rwc", dir.display()))
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE secrets (value TEXT)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let snapshot = snapshot_database(&pool).await.unwrap();
+        assert!(snapshot.starts_with(SQLITE_HEADER));
+        let snapshot_dir = dir.join(SNAPSHOT_DIR);
+        let mode = std::fs::metadata(&snapshot_dir)
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o700);
+        assert_eq!(std::fs::read_dir(&snapshot_dir).unwrap().count(), 0);
+
+        let stale = snapshot_dir.join(format!("{SNAPSHOT_PREFIX}crashed.db"));
+        let unrelated = snapshot_dir.join("notes.txt");
+        std::fs::write(&stale, b"secrets").unwrap();
+        std::fs::write(&unrelated, b"notes").unwrap();
+        remove_stale_snapshots(&pool).await;
+        assert!(!stale.exists());
+        assert!(unrelated.exists());
+
+        pool.close().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
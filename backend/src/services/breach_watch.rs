@@ -0,0 +1,172 @@
+//! Alerts about peers broadcasting revoked channel states.
+//!
+//! A peer that broadcasts an old commitment tries to take back funds it has
+//! already paid out, and the node has until the commitment's timelock expires
+//! to sweep them with justice transactions. LND reports such a close with a
+//! `BREACH_CLOSE` close type, which the channel event subscription raises as a
+//! critical `ChannelBreachDetected` event. Breaches that happen while the
+//! subscription is down are caught by listing the breach closes of each LND
+//! node every ten minutes, with the justice transactions and the funds still in
+//! limbo from its pending channels. A breach is raised once per channel point;
+//! CLN doesn't report which closes were breaches and isn't checked.
+
+use crate::database::models::{CreateEvent, Credential, EventSeverity, EventType};
+use crate::errors::{LightningError, ServiceError, ServiceResult};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::repositories::event_repository::EventRepository;
+use crate::services::event_service::EventService;
+use crate::services::node_capabilities::NodeImplementation;
+use crate::services::node_manager::connect_node;
+use crate::services::task_supervisor;
+use crate::utils::ChannelBreach;
+use chrono::Utc;
+use serde_json::{Value, json};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(600);
+
+/// Data of the `ChannelBreachDetected` event of a breach, keyed like the
+/// channel events of the node's subscription.
+pub fn breach_data(breach: &ChannelBreach) -> Value {
+    json!({
+        "chan_id": breach.channel_id.0,
+        "remote_pubkey": breach.remote_pubkey,
+        "channel_point": breach.channel_point,
+        "closing_tx_hash": breach.breach_txid,
+        "breach_txid": breach.breach_txid,
+        "justice_txids": breach.justice_txids,
+        "capacity": breach.capacity_sat,
+        "close_height": breach.close_height,
+        "settled_balance": breach.settled_balance_sat,
+        "time_locked_balance": breach.time_locked_balance_sat,
+        "limbo_balance": breach.limbo_balance_sat,
+    })
+}
+
+/// Checks every LND node for breaches not yet raised.
+pub async fn check_all_nodes(pool: &SqlitePool) -> ServiceResult<()> {
+    let credentials = CredentialRepository::new(pool)
+        .get_node_credentials(None, None)
+        .await?;
+
+    for credential in &credentials {
+        let implementation = credential.node_type.as_deref().unwrap_or("lnd");
+        if NodeImplementation::from_node_type(implementation) != Some(NodeImplementation::Lnd) {
+            continue;
+        }
+
+        if let Err(e) = check_node(pool, credential).await {
+            tracing::warn!("Breach check of node {} failed: {}", credential.node_id, e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Raises the breaches of a node whose channel points have no
+/// `ChannelBreachDetected` event yet.
+async fn check_node(pool: &SqlitePool, credential: &Credential) -> ServiceResult<()> {
+    let external = |e: LightningError| ServiceError::ExternalService {
+        message: e.to_string(),
+    };
+    let client = connect_node(credential).await.map_err(external)?;
+    let breaches = client.list_channel_breaches().await.map_err(external)?;
+    if breaches.is_empty() {
+        return Ok(());
+    }
+
+    let raised = EventRepository::new(pool)
+        .get_event_channel_points(
+            &credential.account_id,
+            &credential.node_id,
+            EventType::ChannelBreachDetected,
+        )
+        .await?;
+    let events = EventService::new(pool);
+
+    for breach in breaches
+        .iter()
+        .filter(|breach| !raised.contains(&breach.channel_point))
+    {
+        let justice = if breach.justice_txids.is_empty() {
+            "no justice transaction reported yet".to_string()
+        } else {
+            format!("justice in {}", breach.justice_txids.join(", "))
+        };
+        events
+            .create_and_dispatch_event(CreateEvent {
+                id: Uuid::now_v7().to_string(),
+                account_id: credential.account_id.clone(),
+                user_id: credential.user_id.clone(),
+                node_id: credential.node_id.clone(),
+                node_alias: credential.node_alias.clone(),
+                event_type: EventType::ChannelBreachDetected,
+                severity: EventSeverity::Critical,
+                title: "Channel Breach Detected".to_string(),
+                description: format!(
+                    "{} broadcast a revoked state of channel {} in {}, {}",
+                    breach.remote_pubkey, breach.channel_id.0, breach.breach_txid, justice
+                ),
+                data: breach_data(breach).to_string(),
+                notifications_id: None,
+                timestamp: Utc::now(),
+            })
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Checks LND nodes for breached channels every ten minutes.
+pub fn spawn_breach_watcher(pool: SqlitePool) {
+    task_supervisor::supervise("breach_watch", move |task| {
+        let pool = pool.clone();
+        async move {
+            // The first check waits for startup migrations
+            let mut interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + CHECK_INTERVAL,
+                CHECK_INTERVAL,
+            );
+            loop {
+                interval.tick().await;
+                let result = check_all_nodes(&pool).await;
+                if let Err(e) = &result {
+                    tracing::error!("Breach check failed: {}", e);
+                }
+                task.record_run(&result);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::ShortChannelID;
+
+    #[test]
+    fn keys_breach_data_like_channel_events() {
+        let breach = ChannelBreach {
+            channel_id: ShortChannelID(870000 << 40 | 12 << 16 | 1),
+            channel_point: "aa:1".to_string(),
+            remote_pubkey: "02ff".to_string(),
+            capacity_sat: 1_000_000,
+            breach_txid: "bb".to_string(),
+            justice_txids: vec!["cc".to_string()],
+            close_height: 870_100,
+            settled_balance_sat: 0,
+            time_locked_balance_sat: 0,
+            limbo_balance_sat: 400_000,
+        };
+        let data = breach_data(&breach);
+
+        assert_eq!(data["chan_id"], json!(breach.channel_id.0));
+        assert_eq!(data["channel_point"], "aa:1");
+        assert_eq!(data["remote_pubkey"], "02ff");
+        assert_eq!(data["closing_tx_hash"], "bb");
+        assert_eq!(data["breach_txid"], "bb");
+        assert_eq!(data["justice_txids"], json!(["cc"]));
+        assert_eq!(data["limbo_balance"], 400_000);
+    }
+}
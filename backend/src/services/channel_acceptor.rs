@@ -0,0 +1,283 @@
+//! Channel acceptor for inbound channel requests to LND nodes.
+//!
+//! An acceptor stream is registered with each LND node when it connects. LND
+//! then holds every inbound channel request until the acceptor answers, so the
+//! account's policy is read from the database for each request and decides on
+//! the spot. Decisions made under an enabled policy are recorded as events.
+
+use crate::database::models::{ChannelAcceptorPolicy, CreateEvent, EventSeverity, EventType};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::channel_acceptor_repository::ChannelAcceptorRepository;
+use crate::services::event_service::EventService;
+use crate::services::task_supervisor;
+use crate::utils::NodeInfo;
+use chrono::Utc;
+use serde_json::json;
+use sqlx::SqlitePool;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic_lnd::lnrpc::{ChannelAcceptRequest, ChannelAcceptResponse};
+use tonic_lnd::tonic::Code;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Wait before registering again after the acceptor stream broke.
+const RECONNECT_DELAY: Duration = Duration::from_secs(30);
+/// Bit of `channel_flags` set when the channel will be announced.
+const ANNOUNCE_CHANNEL_FLAG: u32 = 1;
+
+/// Service layer for channel acceptor policies.
+pub struct ChannelAcceptorService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> ChannelAcceptorService<'a> {
+    /// Creates a new ChannelAcceptorService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Returns the account's policy, disabled if none was saved.
+    pub async fn get_policy(&self, account_id: &str) -> ServiceResult<ChannelAcceptorPolicy> {
+        let policy = ChannelAcceptorRepository::new(self.pool)
+            .get_policy(account_id)
+            .await?;
+        Ok(policy.unwrap_or_default())
+    }
+
+    /// Validates and stores the account's policy.
+    pub async fn update_policy(
+        &self,
+        account_id: &str,
+        mut policy: ChannelAcceptorPolicy,
+    ) -> ServiceResult<ChannelAcceptorPolicy> {
+        policy
+            .validate()
+            .map_err(|e| ServiceError::validation(e.to_string()))?;
+
+        for pubkey in &mut policy.blocked_pubkeys {
+            pubkey.make_ascii_lowercase();
+        }
+        policy.blocked_pubkeys.sort();
+        policy.blocked_pubkeys.dedup();
+
+        ChannelAcceptorRepository::new(self.pool)
+            .save_policy(account_id, &policy)
+            .await?;
+        Ok(policy)
+    }
+}
+
+/// Answers an LND node's inbound channel requests under its account's policy.
+pub struct ChannelAcceptor {
+    pool: SqlitePool,
+    account_id: String,
+    user_id: String,
+    info: NodeInfo,
+    client: tonic_lnd::LightningClient,
+}
+
+impl ChannelAcceptor {
+    /// Registers the acceptor with the node and keeps it registered.
+    pub fn start_for_node(
+        pool: SqlitePool,
+        account_id: String,
+        user_id: String,
+        info: NodeInfo,
+        client: tonic_lnd::LightningClient,
+    ) {
+        let acceptor = ChannelAcceptor {
+            pool,
+            account_id,
+            user_id,
+            info,
+            client,
+        };
+        tokio::spawn(acceptor.run());
+    }
+
+    async fn run(mut self) {
+        let task = task_supervisor::track(
+            format!("channel_acceptor:{}", self.info.pubkey),
+            Some(&self.account_id),
+        );
+        loop {
+            match self.serve().await {
+                Ok(()) => {
+                    tracing::info!(
+                        "Channel acceptor stream of {} ended, registering again",
+                        self.info.pubkey
+                    );
+                    task.backoff("Acceptor stream ended");
+                }
+                Err(status) if status.code() == Code::PermissionDenied => {
+                    tracing::warn!(
+                        "Macaroon of {} does not allow a channel acceptor: {}",
+                        self.info.pubkey,
+                        status.message()
+                    );
+                    task.fail(status.message());
+                    return;
+                }
+                Err(status) => {
+                    tracing::warn!(
+                        "Channel acceptor of {} failed: {}",
+                        self.info.pubkey,
+                        status
+                    );
+                    task.backoff(&status);
+                }
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+            task.resume();
+        }
+    }
+
+    /// Answers requests until the stream ends.
+    async fn serve(&mut self) -> Result<(), tonic_lnd::tonic::Status> {
+        let (responses, receiver) = mpsc::channel(16);
+        let mut requests = self
+            .client
+            .channel_acceptor(ReceiverStream::new(receiver))
+            .await?
+            .into_inner();
+
+        while let Some(request) = requests.message().await? {
+            let policy = self.policy().await;
+            let peer = hex::encode(&request.node_pubkey);
+            let private = request.channel_flags & ANNOUNCE_CHANNEL_FLAG == 0;
+            let decision = policy.evaluate(&peer, request.funding_amt, private);
+
+            let response = ChannelAcceptResponse {
+                accept: decision.is_ok(),
+                pending_chan_id: request.pending_chan_id.clone(),
+                // The detailed reason stays in the event, the peer only learns of the rejection
+                error: if decision.is_ok() {
+                    String::new()
+                } else {
+                    "Channel rejected by node policy".to_string()
+                },
+                ..Default::default()
+            };
+            if responses.send(response).await.is_err() {
+                return Ok(());
+            }
+
+            if policy.enabled {
+                self.record_decision(&request, &peer, private, decision)
+                    .await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The account's current policy; requests are accepted when it can't be read.
+    async fn policy(&self) -> ChannelAcceptorPolicy {
+        ChannelAcceptorService::new(&self.pool)
+            .get_policy(&self.account_id)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::error!("Failed to load channel acceptor policy: {}", e);
+                ChannelAcceptorPolicy::default()
+            })
+    }
+
+    async fn record_decision(
+        &self,
+        request: &ChannelAcceptRequest,
+        peer: &str,
+        private: bool,
+        decision: Result<(), String>,
+    ) {
+        let (event_type, title, description, reason) = match decision {
+            Ok(()) => (
+                EventType::ChannelRequestAccepted,
+                "Channel Request Accepted",
+                format!("Accepted {} sat channel from {peer}", request.funding_amt),
+                None,
+            ),
+            Err(reason) => (
+                EventType::ChannelRequestRejected,
+                "Channel Request Rejected",
+                format!(
+                    "Rejected {} sat channel from {peer}: {reason}",
+                    request.funding_amt
+                ),
+                Some(reason),
+            ),
+        };
+
+        let result = EventService::new(&self.pool)
+            .create_and_dispatch_event(CreateEvent {
+                id: Uuid::now_v7().to_string(),
+                account_id: self.account_id.clone(),
+                user_id: self.user_id.clone(),
+                node_id: self.info.pubkey.to_string(),
+                node_alias: self.info.alias.clone(),
+                event_type,
+                severity: EventSeverity::Info,
+                title: title.to_string(),
+                description,
+                data: json!({
+                    "remote_pubkey": peer,
+                    "pending_chan_id": hex::encode(&request.pending_chan_id),
+                    "capacity": request.funding_amt,
+                    "push_amt": request.push_amt,
+                    "private": private,
+                    "reason": reason,
+                })
+                .to_string(),
+                notifications_id: None,
+                timestamp: Utc::now(),
+            })
+            .await;
+
+        if let Err(e) = result {
+            tracing::error!("Failed to record channel request decision: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PEER: &str = "02eec7245d6b7d2ccb30380bfbe2a3648cd7a942653f5aa340edcea1f283686619";
+
+    #[test]
+    fn applies_policy_rules() {
+        let policy = ChannelAcceptorPolicy {
+            enabled: true,
+            min_capacity_sat: Some(1_000_000),
+            reject_private: true,
+            blocked_pubkeys: vec![PEER.to_string()],
+        };
+
+        assert_eq!(
+            policy.evaluate(&PEER.to_uppercase(), 5_000_000, false),
+            Err("Peer is blocklisted".to_string())
+        );
+        let other = PEER.replace("02ee", "03ee");
+        assert!(policy.evaluate(&other, 500_000, false).is_err());
+        assert!(policy.evaluate(&other, 5_000_000, true).is_err());
+        assert!(policy.evaluate(&other, 5_000_000, false).is_ok());
+
+        let disabled = ChannelAcceptorPolicy {
+            enabled: false,
+            ..policy
+        };
+        assert!(disabled.evaluate(PEER, 1, true).is_ok());
+    }
+
+    #[test]
+    fn rejects_malformed_blocked_pubkeys() {
+        let policy = ChannelAcceptorPolicy {
+            blocked_pubkeys: vec!["not-a-pubkey".to_string()],
+            ..Default::default()
+        };
+        assert!(policy.validate().is_err());
+    }
+}
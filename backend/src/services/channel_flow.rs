@@ -0,0 +1,335 @@
+//! Per-channel routing flow derived from the node's forwarding history.
+//!
+//! A channel that mostly carries forwards out of the node drains its local
+//! balance (a sink); one that mostly carries them in refills it (a source).
+//! Forecasts extrapolate a channel's mean daily net flow to the day its local
+//! balance runs out or fills up, with bounds from the 95% confidence interval
+//! of that mean, so rebalances can be scheduled before the channel is stuck.
+
+use crate::utils::{ChannelSummary, ForwardSummary, ShortChannelID};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Shortest window flow is reported for, in days.
+pub const SHORT_FLOW_WINDOW_DAYS: u64 = 7;
+/// Longest window flow is reported for, in days.
+pub const LONG_FLOW_WINDOW_DAYS: u64 = 30;
+
+/// Days of flow a forecast is based on when none are given.
+pub const DEFAULT_FORECAST_DAYS: u64 = 30;
+
+/// z-score of the 95% confidence interval of the mean daily flow.
+const FORECAST_Z: f64 = 1.96;
+
+/// Share of the total volume the net flow must exceed before a channel counts as a
+/// sink or source.
+const DIRECTION_THRESHOLD: f64 = 0.1;
+
+/// Which way forwards mostly travel through a channel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlowDirection {
+    /// Forwards mostly leave through the channel, depleting local balance
+    Sink,
+    /// Forwards mostly arrive through the channel, depleting remote balance
+    Source,
+    /// Roughly equal in both directions
+    Balanced,
+    /// No forwards in the window
+    #[default]
+    Idle,
+}
+
+/// Forwarded volume through a channel over one window.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FlowWindow {
+    /// Amount of forwards that arrived on the channel
+    pub inbound_sat: u64,
+    /// Amount of forwards that left on the channel
+    pub outbound_sat: u64,
+    /// `inbound_sat - outbound_sat`
+    pub net_sat: i64,
+    pub direction: FlowDirection,
+}
+
+impl FlowWindow {
+    fn finish(&mut self) {
+        self.net_sat = self.inbound_sat as i64 - self.outbound_sat as i64;
+        let total = self.inbound_sat + self.outbound_sat;
+        self.direction = if total == 0 {
+            FlowDirection::Idle
+        } else if (self.net_sat.unsigned_abs() as f64) <= total as f64 * DIRECTION_THRESHOLD {
+            FlowDirection::Balanced
+        } else if self.net_sat > 0 {
+            FlowDirection::Source
+        } else {
+            FlowDirection::Sink
+        };
+    }
+}
+
+/// Forwarded volume through a channel over the last 7 and 30 days.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChannelFlow {
+    #[serde(rename = "7d")]
+    pub last_7d: FlowWindow,
+    #[serde(rename = "30d")]
+    pub last_30d: FlowWindow,
+}
+
+/// Sums the forwards settled up to `now` into per-channel flow, keyed by numeric
+/// short channel id. Channels without forwards are absent.
+pub fn channel_flows(forwards: &[ForwardSummary], now: u64) -> HashMap<u64, ChannelFlow> {
+    let short_start = now.saturating_sub(SHORT_FLOW_WINDOW_DAYS * 86_400);
+    let long_start = now.saturating_sub(LONG_FLOW_WINDOW_DAYS * 86_400);
+
+    let mut flows: HashMap<u64, ChannelFlow> = HashMap::new();
+    for forward in forwards {
+        if forward.resolved_at < long_start || forward.resolved_at > now {
+            continue;
+        }
+        let recent = forward.resolved_at >= short_start;
+
+        if let Some(chan_id) = forward.chan_id_in {
+            let flow = flows.entry(chan_id.0).or_default();
+            let amount_sat = forward.amount_in_msat / 1000;
+            flow.last_30d.inbound_sat += amount_sat;
+            if recent {
+                flow.last_7d.inbound_sat += amount_sat;
+            }
+        }
+        if let Some(chan_id) = forward.chan_id_out {
+            let flow = flows.entry(chan_id.0).or_default();
+            let amount_sat = forward.amount_out_msat / 1000;
+            flow.last_30d.outbound_sat += amount_sat;
+            if recent {
+                flow.last_7d.outbound_sat += amount_sat;
+            }
+        }
+    }
+
+    for flow in flows.values_mut() {
+        flow.last_7d.finish();
+        flow.last_30d.finish();
+    }
+
+    flows
+}
+
+/// Days until a channel's local balance runs out or fills up.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DaysEstimate {
+    /// At the mean daily flow
+    pub expected: f64,
+    /// At the fastest flow within the confidence bounds
+    pub earliest: f64,
+    /// At the slowest flow within the bounds; absent when the flow may stop or turn
+    pub latest: Option<f64>,
+}
+
+/// Outlook of a channel's local balance at its recent flow.
+#[derive(Debug, Clone, Serialize)]
+pub struct LiquidityForecast {
+    pub channel_id: ShortChannelID,
+    pub local_balance_sat: u64,
+    pub remote_balance_sat: u64,
+    /// Days of forwards the forecast is based on
+    pub window_days: u64,
+    #[serde(flatten)]
+    pub flow: FlowWindow,
+    /// Mean daily change of the local balance through forwards
+    pub daily_net_flow_sat: f64,
+    /// Lower and upper 95% confidence bound of the mean daily change
+    pub daily_net_flow_bounds_sat: (f64, f64),
+    /// Set while the local balance shrinks
+    pub days_until_depleted: Option<DaysEstimate>,
+    /// Set while the local balance grows
+    pub days_until_full: Option<DaysEstimate>,
+}
+
+/// Sums a channel's forwards settled in each of the `days` days up to `now`,
+/// oldest day first.
+pub fn daily_flows(
+    forwards: &[ForwardSummary],
+    chan_id: ShortChannelID,
+    now: u64,
+    days: u64,
+) -> Vec<FlowWindow> {
+    let start = now.saturating_sub(days * 86_400);
+    let mut flows = vec![FlowWindow::default(); days as usize];
+    for forward in forwards {
+        if forward.resolved_at < start || forward.resolved_at >= now {
+            continue;
+        }
+        let day = ((forward.resolved_at - start) / 86_400) as usize;
+        if forward.chan_id_in.is_some_and(|id| id.0 == chan_id.0) {
+            flows[day].inbound_sat += forward.amount_in_msat / 1000;
+        }
+        if forward.chan_id_out.is_some_and(|id| id.0 == chan_id.0) {
+            flows[day].outbound_sat += forward.amount_out_msat / 1000;
+        }
+    }
+    for flow in &mut flows {
+        flow.finish();
+    }
+    flows
+}
+
+/// Days until `balance_sat` is used up at `rate` sat a day and the slower and
+/// faster rates of the bounds, all of the same sign.
+fn days_until(balance_sat: u64, rate: f64, slow: f64, fast: f64) -> DaysEstimate {
+    let days = |rate: f64| (balance_sat as f64 / rate * 10.0).round() / 10.0;
+    DaysEstimate {
+        expected: days(rate),
+        earliest: days(fast),
+        latest: (slow > 0.0).then(|| days(slow)),
+    }
+}
+
+/// Forecasts a channel's local balance from its daily flows, as returned by
+/// [`daily_flows`].
+pub fn forecast_liquidity(channel: &ChannelSummary, daily: &[FlowWindow]) -> LiquidityForecast {
+    let mut flow = FlowWindow::default();
+    for day in daily {
+        flow.inbound_sat += day.inbound_sat;
+        flow.outbound_sat += day.outbound_sat;
+    }
+    flow.finish();
+
+    let n = daily.len().max(1) as f64;
+    let mean = daily.iter().map(|day| day.net_sat as f64).sum::<f64>() / n;
+    let variance = if daily.len() > 1 {
+        daily
+            .iter()
+            .map(|day| (day.net_sat as f64 - mean).powi(2))
+            .sum::<f64>()
+            / (n - 1.0)
+    } else {
+        0.0
+    };
+    let margin = FORECAST_Z * (variance / n).sqrt();
+    let (low, high) = (mean - margin, mean + margin);
+
+    let (days_until_depleted, days_until_full) = if mean < 0.0 {
+        let depleted = days_until(channel.local_balance.sat(), -mean, -high, -low);
+        (Some(depleted), None)
+    } else if mean > 0.0 {
+        let full = days_until(channel.remote_balance.sat(), mean, low, high);
+        (None, Some(full))
+    } else {
+        (None, None)
+    };
+
+    LiquidityForecast {
+        channel_id: channel.chan_id,
+        local_balance_sat: channel.local_balance.sat(),
+        remote_balance_sat: channel.remote_balance.sat(),
+        window_days: daily.len() as u64,
+        flow,
+        daily_net_flow_sat: mean,
+        daily_net_flow_bounds_sat: (low, high),
+        days_until_depleted,
+        days_until_full,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::ChannelState;
+    use crate::utils::amount::Amount;
+
+    const DAY: u64 = 86_400;
+
+    fn forward(chan_in: u64, chan_out: u64, amount_sat: u64, resolved_at: u64) -> ForwardSummary {
+        ForwardSummary {
+            chan_id_in: Some(ShortChannelID(chan_in)),
+            chan_id_out: Some(ShortChannelID(chan_out)),
+            amount_in_msat: amount_sat * 1000 + 1000,
+            amount_out_msat: amount_sat * 1000,
+            fee_msat: 1000,
+            resolved_at,
+        }
+    }
+
+    #[test]
+    fn classifies_sinks_and_sources_per_window() {
+        let now = 40 * DAY;
+        let forwards = vec![
+            // Recent: 1 -> 2
+            forward(1, 2, 10_000, now - DAY),
+            // Older: 2 -> 1, only in the 30 day window
+            forward(2, 1, 50_000, now - 20 * DAY),
+            // Outside both windows
+            forward(1, 2, 1_000_000, now - 35 * DAY),
+        ];
+
+        let flows = channel_flows(&forwards, now);
+
+        let first = &flows[&1];
+        assert_eq!(first.last_7d.inbound_sat, 10_001);
+        assert_eq!(first.last_7d.direction, FlowDirection::Source);
+        assert_eq!(first.last_30d.outbound_sat, 50_000);
+        assert_eq!(first.last_30d.net_sat, 10_001 - 50_000);
+        assert_eq!(first.last_30d.direction, FlowDirection::Sink);
+
+        let second = &flows[&2];
+        assert_eq!(second.last_7d.direction, FlowDirection::Sink);
+        assert_eq!(second.last_30d.direction, FlowDirection::Source);
+    }
+
+    #[test]
+    fn near_equal_flow_is_balanced() {
+        let now = 10 * DAY;
+        let forwards = vec![
+            forward(1, 2, 1_000, now - DAY),
+            forward(2, 1, 1_050, now - DAY),
+        ];
+
+        let flows = channel_flows(&forwards, now);
+
+        assert_eq!(flows[&1].last_7d.direction, FlowDirection::Balanced);
+        assert_eq!(flows[&2].last_7d.direction, FlowDirection::Balanced);
+    }
+
+    #[test]
+    fn forecasts_days_until_depleted_with_bounds() {
+        let now = 40 * DAY;
+        let mut forwards = Vec::new();
+        // Channel 1 sends out 9_000 to 11_000 sat a day
+        for day in 0..10 {
+            let amount = if day % 2 == 0 { 9_000 } else { 11_000 };
+            forwards.push(forward(2, 1, amount, now - day * DAY - 1));
+        }
+        let daily = daily_flows(&forwards, ShortChannelID(1), now, 10);
+        assert_eq!(daily.len(), 10);
+        assert!(daily.iter().all(|day| day.direction == FlowDirection::Sink));
+
+        let channel = ChannelSummary {
+            chan_id: ShortChannelID(1),
+            alias: None,
+            channel_state: ChannelState::Active,
+            private: false,
+            remote_balance: Amount::from_sat(500_000),
+            local_balance: Amount::from_sat(200_000),
+            capacity: Amount::from_sat(700_000),
+            last_update: None,
+            uptime: None,
+            remote_pubkey: None,
+            channel_point: None,
+        };
+        let forecast = forecast_liquidity(&channel, &daily);
+        assert_eq!(forecast.daily_net_flow_sat, -10_000.0);
+        assert_eq!(forecast.flow.direction, FlowDirection::Sink);
+        assert!(forecast.days_until_full.is_none());
+        let depleted = forecast.days_until_depleted.unwrap();
+        assert_eq!(depleted.expected, 20.0);
+        assert!(depleted.earliest < 20.0);
+        assert!(depleted.latest.unwrap() > 20.0);
+
+        // Flow on a single day can't rule out the channel going quiet
+        let daily = daily_flows(&forwards[..1], ShortChannelID(1), now, 10);
+        let forecast = forecast_liquidity(&channel, &daily);
+        assert_eq!(forecast.days_until_depleted.unwrap().latest, None);
+    }
+}
@@ -0,0 +1,219 @@
+//! Tags grouping channels, such as `exchange peers` or `plebnet`.
+//!
+//! A tag can carry an auto-fee policy and alert thresholds, so channels that
+//! should be treated alike are configured once. Auto-fee applies a tag's policy
+//! to each tagged channel without a policy of its own, taking the first tag by
+//! name when several have one. The HTLC expiry check warns about the HTLCs of a
+//! tagged channel at the largest threshold of its tags, and at the account's
+//! when none has one. Channel listings show each channel's tags and can be
+//! filtered by tag.
+
+use crate::database::models::{
+    ChannelTag, CreateChannelTagRequest, UpdateAutoFeePolicyRequest, UpdateChannelTagRequest,
+};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::channel_tag_repository::ChannelTagRepository;
+use crate::utils::ShortChannelID;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use validator::Validate;
+
+/// The fee policy a channel gets from its tags, with the tag it comes from.
+pub fn tag_fee_policy(tags: &[ChannelTag]) -> Option<(&ChannelTag, &UpdateAutoFeePolicyRequest)> {
+    tags.iter()
+        .find_map(|tag| tag.fee_policy.as_ref().map(|policy| (tag, policy)))
+}
+
+/// The HTLC expiry threshold a channel gets from its tags.
+pub fn tag_htlc_expiry_blocks(tags: &[ChannelTag]) -> Option<u32> {
+    tags.iter()
+        .filter_map(|tag| tag.alert_thresholds.htlc_expiry_blocks)
+        .max()
+}
+
+/// Service layer for channel tags.
+pub struct ChannelTagService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> ChannelTagService<'a> {
+    /// Creates a new ChannelTagService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Lists the account's tags, by name.
+    pub async fn get_tags(&self, account_id: &str) -> ServiceResult<Vec<ChannelTag>> {
+        Ok(ChannelTagRepository::new(self.pool)
+            .get_tags(account_id)
+            .await?)
+    }
+
+    /// Creates a tag.
+    pub async fn create_tag(
+        &self,
+        account_id: &str,
+        request: CreateChannelTagRequest,
+    ) -> ServiceResult<ChannelTag> {
+        request
+            .validate()
+            .map_err(|e| ServiceError::validation(e.to_string()))?;
+
+        let name = request.name.trim();
+        let repository = ChannelTagRepository::new(self.pool);
+        if !repository
+            .create_tag(
+                account_id,
+                name,
+                request.fee_policy.as_ref(),
+                &request.alert_thresholds,
+            )
+            .await?
+        {
+            return Err(ServiceError::already_exists("Channel tag", name));
+        }
+
+        self.get_tag(account_id, name).await
+    }
+
+    /// Replaces the fee policy and alert thresholds of a tag.
+    pub async fn update_tag(
+        &self,
+        account_id: &str,
+        name: &str,
+        request: UpdateChannelTagRequest,
+    ) -> ServiceResult<ChannelTag> {
+        request
+            .validate()
+            .map_err(|e| ServiceError::validation(e.to_string()))?;
+
+        if !ChannelTagRepository::new(self.pool)
+            .update_tag(
+                account_id,
+                name,
+                request.fee_policy.as_ref(),
+                &request.alert_thresholds,
+            )
+            .await?
+        {
+            return Err(ServiceError::not_found("Channel tag", name));
+        }
+
+        self.get_tag(account_id, name).await
+    }
+
+    /// Removes a tag from the account and every channel carrying it.
+    pub async fn delete_tag(&self, account_id: &str, name: &str) -> ServiceResult<()> {
+        if !ChannelTagRepository::new(self.pool)
+            .delete_tag(account_id, name)
+            .await?
+        {
+            return Err(ServiceError::not_found("Channel tag", name));
+        }
+        Ok(())
+    }
+
+    /// Tags a channel of one of the account's nodes.
+    pub async fn tag_channel(
+        &self,
+        account_id: &str,
+        name: &str,
+        node_id: &str,
+        channel_id: &ShortChannelID,
+    ) -> ServiceResult<ChannelTag> {
+        self.get_tag(account_id, name).await?;
+        ChannelTagRepository::new(self.pool)
+            .tag_channel(account_id, name, node_id, &channel_id.to_string())
+            .await?;
+
+        self.get_tag(account_id, name).await
+    }
+
+    /// Removes a tag from a channel of one of the account's nodes.
+    pub async fn untag_channel(
+        &self,
+        account_id: &str,
+        name: &str,
+        node_id: &str,
+        channel_id: &ShortChannelID,
+    ) -> ServiceResult<()> {
+        if !ChannelTagRepository::new(self.pool)
+            .untag_channel(account_id, name, node_id, &channel_id.to_string())
+            .await?
+        {
+            return Err(ServiceError::not_found(
+                "Channel tag",
+                format!("{name} on channel {channel_id}"),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The tags of each tagged channel of one of the account's nodes, keyed by
+    /// numeric channel id and ordered by name.
+    pub async fn get_node_channel_tags(
+        &self,
+        account_id: &str,
+        node_id: &str,
+    ) -> ServiceResult<HashMap<u64, Vec<ChannelTag>>> {
+        let mut channels: HashMap<u64, Vec<ChannelTag>> = HashMap::new();
+        for (channel_id, tag) in ChannelTagRepository::new(self.pool)
+            .get_node_channel_tags(account_id, node_id)
+            .await?
+        {
+            if let Ok(channel_id) = channel_id.parse() {
+                channels.entry(channel_id).or_default().push(tag);
+            }
+        }
+        Ok(channels)
+    }
+
+    async fn get_tag(&self, account_id: &str, name: &str) -> ServiceResult<ChannelTag> {
+        ChannelTagRepository::new(self.pool)
+            .get_tag(account_id, name)
+            .await?
+            .ok_or_else(|| ServiceError::not_found("Channel tag", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::ChannelAlertThresholds;
+    use chrono::Utc;
+
+    fn tag(name: &str, fee_policy: Option<u32>, htlc_expiry_blocks: Option<u32>) -> ChannelTag {
+        ChannelTag {
+            name: name.to_string(),
+            fee_policy: fee_policy.map(|base_fee_ppm| UpdateAutoFeePolicyRequest {
+                enabled: true,
+                target_local_ratio: 0.5,
+                base_fee_ppm,
+                min_fee_ppm: 0,
+                max_fee_ppm: 1000,
+                max_step_ppm: 50,
+            }),
+            alert_thresholds: ChannelAlertThresholds { htlc_expiry_blocks },
+            channel_count: 1,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn resolves_the_settings_a_channel_gets_from_its_tags() {
+        let tags = [
+            tag("exchange peers", None, Some(40)),
+            tag("plebnet", Some(200), Some(144)),
+            tag("sinks", Some(900), None),
+        ];
+        let (tag, policy) = tag_fee_policy(&tags).unwrap();
+        assert_eq!(tag.name, "plebnet");
+        assert_eq!(policy.base_fee_ppm, 200);
+        assert_eq!(tag_htlc_expiry_blocks(&tags), Some(144));
+
+        assert!(tag_fee_policy(&tags[..1]).is_none());
+        assert_eq!(tag_htlc_expiry_blocks(&tags[2..]), None);
+    }
+}
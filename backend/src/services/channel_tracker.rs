@@ -0,0 +1,176 @@
+//! Detects channels whose funding output changed between polls of the channel list.
+//!
+//! A splice replaces a channel's funding transaction, so once it confirms the
+//! channel shows up with a new channel point, short channel ID and capacity while
+//! the old entry disappears. Nodes don't announce this on their event streams, so
+//! the tracker keeps the last seen channel list and pairs vanished channels with
+//! new ones to the same peer.
+
+use crate::utils::{ChannelState, ChannelSummary};
+use bitcoin::OutPoint;
+use bitcoin::secp256k1::PublicKey;
+use std::collections::HashMap;
+
+/// A channel whose funding output was replaced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelSplice {
+    pub remote_pubkey: PublicKey,
+    pub previous_chan_id: u64,
+    pub chan_id: u64,
+    pub previous_channel_point: Option<OutPoint>,
+    pub channel_point: Option<OutPoint>,
+    pub previous_capacity: u64,
+    pub capacity: u64,
+}
+
+#[derive(Debug, Clone)]
+struct TrackedChannel {
+    remote_pubkey: Option<PublicKey>,
+    channel_point: Option<OutPoint>,
+    capacity: u64,
+    closing: bool,
+}
+
+/// Last known channel list of a node, keyed by short channel ID.
+#[derive(Debug, Default)]
+pub struct ChannelTracker {
+    channels: HashMap<u64, TrackedChannel>,
+    seeded: bool,
+}
+
+impl ChannelTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the cached channel list and returns the channels spliced since the
+    /// previous call. The first call only seeds the cache.
+    pub fn observe(&mut self, channels: &[ChannelSummary]) -> Vec<ChannelSplice> {
+        let current: HashMap<u64, TrackedChannel> = channels
+            .iter()
+            .map(|channel| {
+                (
+                    channel.chan_id.0,
+                    TrackedChannel {
+                        remote_pubkey: channel.remote_pubkey,
+                        channel_point: channel.channel_point,
+                        // Funding outputs are whole satoshis
+                        capacity: channel.capacity.sat(),
+                        closing: matches!(
+                            channel.channel_state,
+                            ChannelState::Closing | ChannelState::Closed | ChannelState::Failed
+                        ),
+                    },
+                )
+            })
+            .collect();
+
+        let mut splices = Vec::new();
+        if self.seeded {
+            // Channels that were open last time and are gone now may have been spliced.
+            let mut vanished: Vec<(u64, &TrackedChannel)> = self
+                .channels
+                .iter()
+                .filter(|(chan_id, channel)| !channel.closing && !current.contains_key(chan_id))
+                .map(|(chan_id, channel)| (*chan_id, channel))
+                .collect();
+
+            let mut appeared: Vec<(&u64, &TrackedChannel)> = current
+                .iter()
+                .filter(|(chan_id, channel)| {
+                    !channel.closing && !self.channels.contains_key(chan_id)
+                })
+                .collect();
+            appeared.sort_by_key(|(chan_id, _)| **chan_id);
+
+            for (chan_id, channel) in appeared {
+                let Some(remote_pubkey) = channel.remote_pubkey else {
+                    continue;
+                };
+                let Some(position) = vanished
+                    .iter()
+                    .position(|(_, old)| old.remote_pubkey == Some(remote_pubkey))
+                else {
+                    continue;
+                };
+                let (previous_chan_id, previous) = vanished.swap_remove(position);
+                splices.push(ChannelSplice {
+                    remote_pubkey,
+                    previous_chan_id,
+                    chan_id: *chan_id,
+                    previous_channel_point: previous.channel_point,
+                    channel_point: channel.channel_point,
+                    previous_capacity: previous.capacity,
+                    capacity: channel.capacity,
+                });
+            }
+        }
+
+        self.channels = current;
+        self.seeded = true;
+        splices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::ShortChannelID;
+    use crate::utils::amount::Amount;
+    use std::str::FromStr;
+
+    fn channel(chan_id: u64, txid_byte: u8, capacity: u64, state: ChannelState) -> ChannelSummary {
+        let remote_pubkey = PublicKey::from_str(
+            "02eec7245d6b7d2ccb30380bfbe2a3648cd7a942653f5aa340edcea1f283686619",
+        )
+        .unwrap();
+        let channel_point =
+            OutPoint::from_str(&format!("{}:0", format!("{txid_byte:02x}").repeat(32))).unwrap();
+
+        ChannelSummary {
+            chan_id: ShortChannelID(chan_id),
+            alias: None,
+            channel_state: state,
+            private: false,
+            remote_balance: Amount::ZERO,
+            local_balance: Amount::from_sat(capacity),
+            capacity: Amount::from_sat(capacity),
+            last_update: None,
+            uptime: None,
+            remote_pubkey: Some(remote_pubkey),
+            channel_point: Some(channel_point),
+        }
+    }
+
+    #[test]
+    fn reports_replaced_funding_output_as_splice() {
+        let mut tracker = ChannelTracker::new();
+        assert!(
+            tracker
+                .observe(&[channel(1, 0xaa, 100_000, ChannelState::Active)])
+                .is_empty()
+        );
+
+        let splices = tracker.observe(&[channel(2, 0xbb, 150_000, ChannelState::Active)]);
+        assert_eq!(splices.len(), 1);
+        assert_eq!(splices[0].previous_chan_id, 1);
+        assert_eq!(splices[0].chan_id, 2);
+        assert_eq!(splices[0].previous_capacity, 100_000);
+        assert_eq!(splices[0].capacity, 150_000);
+
+        assert!(
+            tracker
+                .observe(&[channel(2, 0xbb, 150_000, ChannelState::Active)])
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn ignores_reopen_after_close() {
+        let mut tracker = ChannelTracker::new();
+        tracker.observe(&[channel(1, 0xaa, 100_000, ChannelState::Closing)]);
+
+        let splices = tracker.observe(&[channel(2, 0xbb, 150_000, ChannelState::Active)]);
+        assert!(splices.is_empty());
+    }
+}
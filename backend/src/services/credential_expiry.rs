@@ -0,0 +1,249 @@
+//! Expiry tracking of the TLS certificates and macaroons nodes are connected with.
+//!
+//! An expired certificate or macaroon breaks the connection to a node with
+//! nothing but a handshake error to go on. When credentials are stored, the
+//! earliest `notAfter` of their certificates and the earliest `time-before`
+//! caveat of their macaroon are recorded. Every six hours the credentials of
+//! accounts with an `alert_thresholds.credential_expiry_days` threshold are
+//! checked, raising a `CredentialExpiring` warning once a credential is within
+//! the threshold and a critical event once it has expired.
+
+use crate::database::models::{CreateEvent, Credential, EventSeverity, EventType};
+use crate::errors::ServiceResult;
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::services::event_service::EventService;
+use crate::services::node_manager::ConnectionRequest;
+use crate::services::settings_service::SettingsService;
+use crate::services::task_supervisor;
+use crate::utils::{macaroon, tls_cert};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(6 * 3600);
+
+/// When a connection's credentials stop working, each absent when unknown or never.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct CredentialExpiry {
+    /// Earliest expiry of the TLS certificates
+    pub tls_cert_expires_at: Option<DateTime<Utc>>,
+    /// Earliest `time-before` caveat of the macaroon
+    pub macaroon_expires_at: Option<DateTime<Utc>>,
+}
+
+/// Part of a credential that expires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExpiringPart {
+    TlsCert,
+    Macaroon,
+}
+
+impl ExpiringPart {
+    fn name(&self) -> &'static str {
+        match self {
+            ExpiringPart::TlsCert => "TLS certificate",
+            ExpiringPart::Macaroon => "macaroon",
+        }
+    }
+
+    fn key(&self) -> &'static str {
+        match self {
+            ExpiringPart::TlsCert => "tls_cert",
+            ExpiringPart::Macaroon => "macaroon",
+        }
+    }
+}
+
+/// Identifies a reported expiry: credential, part, expiry and whether it had passed.
+type AlertKey = (String, ExpiringPart, DateTime<Utc>, bool);
+
+/// Reads the expiry of a connection's certificates and macaroon from their files.
+///
+/// Files that can't be read or parsed leave the expiry unknown rather than
+/// failing the connection, which is checked by connecting.
+pub async fn read_expiry(connection: &ConnectionRequest) -> CredentialExpiry {
+    let cert_paths: Vec<&str> = match connection {
+        ConnectionRequest::Lnd(lnd_conn) => vec![lnd_conn.cert.as_str()],
+        ConnectionRequest::Cln(cln_conn) => {
+            vec![cln_conn.ca_cert.as_str(), cln_conn.client_cert.as_str()]
+        }
+        ConnectionRequest::ClnRune(_) => Vec::new(),
+    };
+
+    let mut tls_cert_expires_at: Option<DateTime<Utc>> = None;
+    for path in cert_paths {
+        match tls_cert::read_certificate_expiry(path).await {
+            Ok(expiry) => {
+                tls_cert_expires_at =
+                    Some(tls_cert_expires_at.map_or(expiry, |earliest| earliest.min(expiry)));
+            }
+            Err(e) => tracing::warn!("Could not read the expiry of certificate {}: {}", path, e),
+        }
+    }
+
+    let macaroon_expires_at = match connection {
+        ConnectionRequest::Lnd(lnd_conn) => macaroon::read_macaroon_expiry(&lnd_conn.macaroon)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("Could not read the expiry of the LND macaroon: {}", e);
+                None
+            }),
+        _ => None,
+    };
+
+    CredentialExpiry {
+        tls_cert_expires_at,
+        macaroon_expires_at,
+    }
+}
+
+/// Parts of a credential expiring within `threshold_days` of `now`, or already expired.
+pub fn expiring_parts(
+    credential: &Credential,
+    threshold_days: u32,
+    now: DateTime<Utc>,
+) -> Vec<(ExpiringPart, DateTime<Utc>)> {
+    let horizon = now + Duration::days(threshold_days.into());
+    [
+        (ExpiringPart::TlsCert, credential.tls_cert_expires_at),
+        (ExpiringPart::Macaroon, credential.macaroon_expires_at),
+    ]
+    .into_iter()
+    .filter_map(|(part, expiry)| expiry.map(|expiry| (part, expiry)))
+    .filter(|(_, expiry)| *expiry <= horizon)
+    .collect()
+}
+
+/// Checks the credentials the nodes of the accounts with a credential expiry
+/// threshold are reached with, see `CredentialRepository::get_node_credentials`.
+///
+/// `alerted` holds the expiries already reported and is pruned to those still pending.
+pub async fn check_all_credentials(
+    pool: &SqlitePool,
+    alerted: &mut HashSet<AlertKey>,
+) -> ServiceResult<()> {
+    let credentials = CredentialRepository::new(pool)
+        .get_node_credentials(None, None)
+        .await?;
+    let settings = SettingsService::new(pool);
+    let mut thresholds: HashMap<String, Option<u32>> = HashMap::new();
+    let mut still_expiring = HashSet::new();
+    let now = Utc::now();
+
+    for credential in &credentials {
+        if !thresholds.contains_key(&credential.account_id) {
+            let threshold = settings
+                .get_settings(&credential.account_id)
+                .await?
+                .alert_thresholds
+                .credential_expiry_days;
+            thresholds.insert(credential.account_id.clone(), threshold);
+        }
+        let Some(threshold) = thresholds[&credential.account_id] else {
+            continue;
+        };
+
+        for (part, expires_at) in expiring_parts(credential, threshold, now) {
+            let expired = expires_at <= now;
+            let key = (credential.id.clone(), part, expires_at, expired);
+            still_expiring.insert(key.clone());
+            if alerted.contains(&key) {
+                continue;
+            }
+
+            raise_expiry_event(pool, credential, part, expires_at, now, threshold).await?;
+            alerted.insert(key);
+        }
+    }
+
+    alerted.retain(|key| still_expiring.contains(key));
+    Ok(())
+}
+
+/// Records a warning about an expiring credential, critical once it has expired.
+async fn raise_expiry_event(
+    pool: &SqlitePool,
+    credential: &Credential,
+    part: ExpiringPart,
+    expires_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+    threshold: u32,
+) -> ServiceResult<()> {
+    let days_remaining = (expires_at - now).num_days();
+    let (severity, title, description) = if expires_at <= now {
+        (
+            EventSeverity::Critical,
+            "Credential Expired",
+            format!(
+                "The {} used to connect to {} expired on {}; replace it to restore the connection",
+                part.name(),
+                credential.node_alias,
+                expires_at.format("%Y-%m-%d")
+            ),
+        )
+    } else {
+        (
+            EventSeverity::Warning,
+            "Credential Expiring",
+            format!(
+                "The {} used to connect to {} expires on {}, in {} days",
+                part.name(),
+                credential.node_alias,
+                expires_at.format("%Y-%m-%d"),
+                days_remaining
+            ),
+        )
+    };
+
+    EventService::new(pool)
+        .create_and_dispatch_event(CreateEvent {
+            id: Uuid::now_v7().to_string(),
+            account_id: credential.account_id.clone(),
+            user_id: credential.user_id.clone(),
+            node_id: credential.node_id.clone(),
+            node_alias: credential.node_alias.clone(),
+            event_type: EventType::CredentialExpiring,
+            severity,
+            title: title.to_string(),
+            description,
+            data: json!({
+                "credential_id": credential.id,
+                "credential_part": part.key(),
+                "expires_at": expires_at,
+                "days_remaining": days_remaining,
+                "threshold_days": threshold,
+            })
+            .to_string(),
+            notifications_id: None,
+            timestamp: now,
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Checks credentials for nearing expiries every six hours.
+pub fn spawn_credential_expiry_checker(pool: SqlitePool) {
+    task_supervisor::supervise("credential_expiry", move |task| {
+        let pool = pool.clone();
+        async move {
+            let mut alerted = HashSet::new();
+            // The first check waits for startup migrations
+            let mut interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + CHECK_INTERVAL,
+                CHECK_INTERVAL,
+            );
+            loop {
+                interval.tick().await;
+                let result = check_all_credentials(&pool, &mut alerted).await;
+                if let Err(e) = &result {
+                    tracing::error!("Credential expiry check failed: {}", e);
+                }
+                task.record_run(&result);
+            }
+        }
+    });
+}
@@ -3,13 +3,67 @@
 //! Handles all credential-related business operations
 
 use crate::database::models::{CreateCredential, Credential};
-use crate::errors::{ServiceError, ServiceResult};
+use crate::errors::{LightningError, ServiceError, ServiceResult};
 use crate::repositories::account_repository::AccountRepository;
 use crate::repositories::credential_repository::CredentialRepository;
 use crate::repositories::user_repository::UserRepository;
+use crate::services::credential_expiry::{self, CredentialExpiry};
+use crate::services::node_manager::ConnectionRequest;
+use crate::services::node_manager::connect_node;
+use crate::services::quota_service::QuotaService;
+use crate::utils::NodeInfo;
+use crate::utils::jwt::Claims;
 use sqlx::SqlitePool;
 use validator::Validate;
 
+/// Copy of `current` connecting with the credentials of `connection`.
+///
+/// Identity, ownership and creation time are kept; every connection detail is
+/// taken from `connection`, clearing those its kind of connection doesn't use.
+pub fn rotated_credential(
+    current: &Credential,
+    connection: &ConnectionRequest,
+    read_only: bool,
+    expiry: CredentialExpiry,
+) -> Credential {
+    let mut rotated = Credential {
+        proxy: connection.proxy(),
+        rune: connection.rune(),
+        transport: connection.transport(),
+        read_only,
+        tls_cert_expires_at: expiry.tls_cert_expires_at,
+        macaroon_expires_at: expiry.macaroon_expires_at,
+        client_cert: None,
+        client_key: None,
+        ca_cert: None,
+        ..current.clone()
+    };
+    match connection {
+        ConnectionRequest::Lnd(lnd_conn) => {
+            rotated.node_type = Some("lnd".to_string());
+            rotated.address = lnd_conn.address.clone();
+            rotated.macaroon = lnd_conn.macaroon.clone();
+            rotated.tls_cert = lnd_conn.cert.clone();
+        }
+        ConnectionRequest::Cln(cln_conn) => {
+            rotated.node_type = Some("cln".to_string());
+            rotated.address = cln_conn.address.clone();
+            rotated.macaroon = String::new();
+            rotated.tls_cert = String::new();
+            rotated.client_cert = Some(cln_conn.client_cert.clone());
+            rotated.client_key = Some(cln_conn.client_key.clone());
+            rotated.ca_cert = Some(cln_conn.ca_cert.clone());
+        }
+        ConnectionRequest::ClnRune(rune_conn) => {
+            rotated.node_type = Some("cln".to_string());
+            rotated.address = rune_conn.address.clone();
+            rotated.macaroon = String::new();
+            rotated.tls_cert = String::new();
+        }
+    }
+    rotated
+}
+
 pub struct CredentialService<'a> {
     /// Shared database connection pool
     pool: &'a SqlitePool,
@@ -126,4 +180,230 @@ impl<'a> CredentialService<'a> {
         let credential = repo.get_credential_by_user_id(user_id).await?;
         Ok(credential)
     }
+
+    /// Restores a soft-deleted credential of an account.
+    ///
+    /// Each user has at most one active credential, so this fails while the
+    /// credential's user has connected another node since.
+    ///
+    /// # Errors
+    /// Returns `ServiceError` for:
+    /// - Credentials that don't exist in the account or aren't deleted
+    /// - Credentials of deleted users
+    /// - Users that already have an active credential
+    /// - The account's node quota being reached
+    /// - Database errors
+    pub async fn restore_credential(
+        &self,
+        account_id: &str,
+        id: &str,
+    ) -> ServiceResult<Credential> {
+        let repo = CredentialRepository::new(self.pool);
+        let credential = repo
+            .get_deleted_credential(account_id, id)
+            .await?
+            .ok_or_else(|| ServiceError::not_found("Deleted credential", id))?;
+
+        if UserRepository::new(self.pool)
+            .get_user_by_id(&credential.user_id)
+            .await?
+            .is_none()
+        {
+            return Err(ServiceError::invalid_operation(format!(
+                "User {} is deleted; restore the user first",
+                credential.user_id
+            )));
+        }
+        if repo
+            .get_credential_by_user_id(&credential.user_id)
+            .await?
+            .is_some()
+        {
+            return Err(ServiceError::already_exists(
+                "Active credential for user",
+                &credential.user_id,
+            ));
+        }
+        QuotaService::new(self.pool)
+            .check_node_quota(account_id, &credential.user_id, &credential.node_id)
+            .await?;
+
+        let restored = repo.restore_credential(id).await.map_err(|e| {
+            if e.to_string()
+                .contains("UNIQUE constraint failed: credentials.user_id")
+            {
+                ServiceError::already_exists("Active credential for user", &credential.user_id)
+            } else {
+                ServiceError::Database { source: e }
+            }
+        })?;
+        if !restored {
+            return Err(ServiceError::not_found("Deleted credential", id));
+        }
+
+        self.get_credential_required(id).await
+    }
+
+    /// Replaces the connection details of a credential of the caller's account,
+    /// e.g. after a macaroon was baked again or a TLS certificate renewed.
+    ///
+    /// The new credentials are only stored once a connection with them succeeds
+    /// and the node answers with the credential's pubkey, and then replace the
+    /// old ones in a single update. Nothing holds on to stored credentials: API
+    /// requests and background jobs use whatever is stored when they connect.
+    /// Running event collectors keep streaming over the connections they
+    /// opened, as neither LND nor CLN checks a subscription's credentials again
+    /// once it started, so no events are missed while rotating.
+    ///
+    /// # Returns
+    /// The updated credential and the node info reported with the new credentials
+    ///
+    /// # Errors
+    /// Returns `ServiceError` for:
+    /// - Credentials that don't exist in the account
+    /// - Callers that neither own the credential nor are admins
+    /// - New credentials that can't connect or belong to another node
+    /// - Database errors
+    pub async fn rotate_credential(
+        &self,
+        claims: &Claims,
+        id: &str,
+        connection: &ConnectionRequest,
+        read_only: bool,
+    ) -> ServiceResult<(Credential, NodeInfo)> {
+        let repo = CredentialRepository::new(self.pool);
+        let current = repo
+            .get_credential_by_id(id)
+            .await?
+            .filter(|credential| credential.account_id == claims.account_id)
+            .ok_or_else(|| ServiceError::not_found("Credential", id))?;
+        if current.user_id != claims.sub && claims.role != "Admin" {
+            return Err(ServiceError::invalid_operation(
+                "Only the owner of a credential or an admin can rotate it",
+            ));
+        }
+
+        let expiry = credential_expiry::read_expiry(connection).await;
+        let mut rotated = rotated_credential(&current, connection, read_only, expiry);
+
+        // Connecting checks the pubkey the node answers with against the credential's
+        let client = connect_node(&rotated).await.map_err(|e| match e {
+            LightningError::ValidationError(message) => ServiceError::validation(message),
+            e => ServiceError::ExternalService {
+                message: format!("Failed to connect with the new credentials: {e}"),
+            },
+        })?;
+        let info = client.get_info().clone();
+        rotated.node_alias = info.alias.clone();
+        rotated.node_version = info.version.clone();
+        rotated.network = info.network.clone();
+
+        if !repo.rotate_credential(&rotated).await? {
+            // Deleted while the new credentials were checked
+            return Err(ServiceError::not_found("Credential", id));
+        }
+
+        Ok((self.get_credential_required(id).await?, info))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::node_manager::{
+        ClnConnection, ClnRuneConnection, LndConnection, LndTransport,
+    };
+    use crate::utils::NodeId;
+    use bitcoin::secp256k1::PublicKey;
+    use chrono::Utc;
+    use std::str::FromStr;
+
+    const PUBKEY: &str = "02eadbd9e7557375161df8b646776a547c5cbc2e95b3071ec81553f8ec2cea3b8c";
+
+    fn node_id() -> NodeId {
+        NodeId::PublicKey(PublicKey::from_str(PUBKEY).unwrap())
+    }
+
+    fn stored_cln_credential() -> Credential {
+        Credential {
+            id: "credential".to_string(),
+            user_id: "user".to_string(),
+            account_id: "account".to_string(),
+            node_id: PUBKEY.to_string(),
+            node_alias: "alias".to_string(),
+            macaroon: String::new(),
+            tls_cert: String::new(),
+            address: "https://10.0.0.1:9736".to_string(),
+            node_type: Some("cln".to_string()),
+            client_cert: Some("client cert".to_string()),
+            client_key: Some("client key".to_string()),
+            ca_cert: Some("ca cert".to_string()),
+            proxy: Some("127.0.0.1:9050".to_string()),
+            rune: None,
+            transport: None,
+            read_only: false,
+            node_version: Some("v24.02".to_string()),
+            network: Some("bitcoin".to_string()),
+            tls_cert_expires_at: Some(Utc::now()),
+            macaroon_expires_at: None,
+            is_active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            is_deleted: false,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn rotation_replaces_connection_details_only() {
+        let current = stored_cln_credential();
+
+        let renewed = ConnectionRequest::Cln(ClnConnection {
+            id: node_id(),
+            address: "https://10.0.0.2:9736".to_string(),
+            ca_cert: "new ca cert".to_string(),
+            client_cert: "new client cert".to_string(),
+            client_key: "new client key".to_string(),
+            proxy: None,
+        });
+        let rotated = rotated_credential(&current, &renewed, false, CredentialExpiry::default());
+        assert_eq!(
+            (rotated.id.as_str(), rotated.user_id.as_str()),
+            ("credential", "user")
+        );
+        assert_eq!(rotated.node_id, PUBKEY);
+        assert_eq!(rotated.created_at, current.created_at);
+        assert_eq!(rotated.address, "https://10.0.0.2:9736");
+        assert_eq!(rotated.client_key.as_deref(), Some("new client key"));
+        assert_eq!(rotated.proxy, None);
+        assert_eq!(rotated.tls_cert_expires_at, None);
+
+        let rune = ConnectionRequest::ClnRune(ClnRuneConnection {
+            id: node_id(),
+            address: "10.0.0.1:9735".to_string(),
+            rune: "rune".to_string(),
+            proxy: None,
+        });
+        let rotated = rotated_credential(&current, &rune, true, CredentialExpiry::default());
+        assert_eq!(rotated.rune.as_deref(), Some("rune"));
+        assert!(rotated.read_only);
+        assert_eq!(
+            (rotated.client_cert, rotated.client_key, rotated.ca_cert),
+            (None, None, None)
+        );
+
+        let lnd = ConnectionRequest::Lnd(LndConnection {
+            id: node_id(),
+            address: "https://10.0.0.1:10009".to_string(),
+            macaroon: "macaroon".to_string(),
+            cert: "cert".to_string(),
+            proxy: None,
+            transport: LndTransport::Rest,
+        });
+        let rotated = rotated_credential(&current, &lnd, false, CredentialExpiry::default());
+        assert_eq!(rotated.node_type.as_deref(), Some("lnd"));
+        assert_eq!(rotated.transport.as_deref(), Some("rest"));
+        assert_eq!(rotated.macaroon, "macaroon");
+        assert_eq!(rotated.rune, None);
+    }
 }
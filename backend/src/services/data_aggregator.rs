@@ -3,3 +3,340 @@
 //! This module is responsible for gathering raw data from various sources (e.g.,
 //! connected Lightning nodes), performing any necessary transformations or
 //! aggregations, and preparing it for storage or API consumption.
+
+use crate::database::models::{Credential, EventResponse, EventSeverity, NodeLabel};
+use crate::errors::ServiceResult;
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::repositories::event_repository::EventRepository;
+use crate::repositories::node_label_repository::NodeLabelRepository;
+use crate::services::node_manager::connect_node;
+use crate::utils::amount::Amount;
+use crate::utils::{ChannelState, PaymentState, PaymentType};
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::future::Future;
+use tokio::time::{Duration, timeout};
+
+/// Time budget for connecting to a single node.
+pub const NODE_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time budget for each RPC issued against a connected node.
+pub const NODE_QUERY_TIMEOUT: Duration = Duration::from_secs(15);
+/// Time budget for each database-backed dashboard section.
+const DATABASE_TIMEOUT: Duration = Duration::from_secs(3);
+/// Length of the rolling window used for payment volume and fee revenue.
+const DASHBOARD_WINDOW: chrono::Duration = chrono::Duration::hours(24);
+/// Maximum number of critical events included in the dashboard.
+const RECENT_CRITICAL_EVENTS_LIMIT: i64 = 10;
+
+/// Reachability of a node at the time the dashboard was assembled.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeStatus {
+    Online,
+    Unreachable,
+    Timeout,
+}
+
+/// Per-node figures shown on the account dashboard.
+#[derive(Debug, Serialize)]
+pub struct NodeDashboardSummary {
+    pub node_id: String,
+    pub node_alias: String,
+    pub node_type: String,
+    pub status: NodeStatus,
+    pub error: Option<String>,
+    pub active_channels: usize,
+    pub inactive_channels: usize,
+    pub capacity_sat: u64,
+    pub local_balance_sat: u64,
+    pub remote_balance_sat: u64,
+    pub payments_in_24h_sat: u64,
+    pub payments_out_24h_sat: u64,
+    pub fee_revenue_24h_msat: u64,
+    pub label: Option<NodeLabel>,
+}
+
+/// Sums of the per-node figures across every node in the account.
+#[derive(Debug, Default, Serialize)]
+pub struct DashboardTotals {
+    pub nodes_online: usize,
+    pub capacity_sat: u64,
+    pub local_balance_sat: u64,
+    pub remote_balance_sat: u64,
+    pub payments_in_24h_sat: u64,
+    pub payments_out_24h_sat: u64,
+    pub fee_revenue_24h_msat: u64,
+}
+
+/// Account-wide summary returned by `GET /api/account/dashboard`.
+#[derive(Debug, Serialize)]
+pub struct AccountDashboard {
+    pub generated_at: DateTime<Utc>,
+    pub nodes: Vec<NodeDashboardSummary>,
+    pub totals: DashboardTotals,
+    pub recent_critical_events: Vec<EventResponse>,
+    /// Warning and critical events raised within the dashboard window.
+    pub pending_alerts: i64,
+    /// Warning and critical events nobody has acknowledged yet.
+    pub unacknowledged_alerts: i64,
+    /// Sources that failed or exceeded their time budget; their figures are omitted.
+    pub unavailable_sources: Vec<String>,
+}
+
+/// Assembles cross-source summaries for an account.
+pub struct DataAggregator<'a> {
+    pool: &'a SqlitePool,
+}
+
+impl<'a> DataAggregator<'a> {
+    /// Creates a new DataAggregator instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Builds the account dashboard for a user, querying every node and the event
+    /// store concurrently.
+    pub async fn get_account_dashboard(
+        &self,
+        account_id: &str,
+        user_id: &str,
+    ) -> ServiceResult<AccountDashboard> {
+        let credentials = CredentialRepository::new(self.pool)
+            .get_node_credentials(Some(account_id), Some(user_id))
+            .await?;
+
+        let now = Utc::now();
+        let since = now - DASHBOARD_WINDOW;
+        let since_unix = since.timestamp().max(0) as u64;
+
+        let event_repo = EventRepository::new(self.pool);
+        // Labels only decorate the summaries, so the dashboard is served without them
+        let mut labels: HashMap<String, NodeLabel> = NodeLabelRepository::new(self.pool)
+            .get_labels_by_account_id(account_id)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to load node labels: {}", e);
+                Vec::new()
+            })
+            .into_iter()
+            .map(|label| (label.node_id.clone(), label))
+            .collect();
+
+        let (node_results, critical_events, pending_alerts, unacknowledged_alerts) = tokio::join!(
+            join_all(
+                credentials
+                    .iter()
+                    .map(|credential| summarize_node(credential, since_unix))
+            ),
+            bounded(
+                DATABASE_TIMEOUT,
+                "recent_critical_events",
+                event_repo.get_recent_events_by_severity(
+                    account_id,
+                    EventSeverity::Critical,
+                    RECENT_CRITICAL_EVENTS_LIMIT,
+                ),
+            ),
+            bounded(
+                DATABASE_TIMEOUT,
+                "pending_alerts",
+                event_repo.count_alerts_since(account_id, since),
+            ),
+            bounded(
+                DATABASE_TIMEOUT,
+                "unacknowledged_alerts",
+                event_repo.count_unacknowledged_alerts(account_id),
+            ),
+        );
+
+        let mut unavailable_sources = Vec::new();
+        let mut totals = DashboardTotals::default();
+        let mut nodes = Vec::with_capacity(node_results.len());
+
+        for (mut summary, failures) in node_results {
+            summary.label = labels.remove(&summary.node_id);
+            if summary.status == NodeStatus::Online {
+                totals.nodes_online += 1;
+            }
+            totals.capacity_sat += summary.capacity_sat;
+            totals.local_balance_sat += summary.local_balance_sat;
+            totals.remote_balance_sat += summary.remote_balance_sat;
+            totals.payments_in_24h_sat += summary.payments_in_24h_sat;
+            totals.payments_out_24h_sat += summary.payments_out_24h_sat;
+            totals.fee_revenue_24h_msat += summary.fee_revenue_24h_msat;
+
+            unavailable_sources.extend(failures);
+            nodes.push(summary);
+        }
+
+        let recent_critical_events = match critical_events {
+            Ok(events) => events.into_iter().map(EventResponse::from).collect(),
+            Err(failure) => {
+                unavailable_sources.push(failure);
+                Vec::new()
+            }
+        };
+
+        let pending_alerts = pending_alerts.unwrap_or_else(|failure| {
+            unavailable_sources.push(failure);
+            0
+        });
+        let unacknowledged_alerts = unacknowledged_alerts.unwrap_or_else(|failure| {
+            unavailable_sources.push(failure);
+            0
+        });
+
+        Ok(AccountDashboard {
+            generated_at: now,
+            nodes,
+            totals,
+            recent_critical_events,
+            pending_alerts,
+            unacknowledged_alerts,
+            unavailable_sources,
+        })
+    }
+}
+
+/// Connects to a stored node and collects its channel, payment and fee figures.
+///
+/// Returns the summary together with the names of any sources that did not respond.
+async fn summarize_node(
+    credential: &Credential,
+    since: u64,
+) -> (NodeDashboardSummary, Vec<String>) {
+    let mut summary = NodeDashboardSummary {
+        node_id: credential.node_id.clone(),
+        node_alias: credential.node_alias.clone(),
+        node_type: credential
+            .node_type
+            .clone()
+            .unwrap_or_else(|| "lnd".to_string()),
+        status: NodeStatus::Online,
+        error: None,
+        active_channels: 0,
+        inactive_channels: 0,
+        capacity_sat: 0,
+        local_balance_sat: 0,
+        remote_balance_sat: 0,
+        payments_in_24h_sat: 0,
+        payments_out_24h_sat: 0,
+        fee_revenue_24h_msat: 0,
+        label: None,
+    };
+    let mut failures = Vec::new();
+
+    let client = match timeout(NODE_CONNECT_TIMEOUT, connect_node(credential)).await {
+        Ok(Ok(client)) => client,
+        Ok(Err(e)) => {
+            tracing::warn!(
+                "Dashboard could not reach node {}: {}",
+                credential.node_id,
+                e
+            );
+            summary.status = NodeStatus::Unreachable;
+            summary.error = Some(e.to_string());
+            failures.push(format!("node:{}", credential.node_id));
+            return (summary, failures);
+        }
+        Err(_) => {
+            tracing::warn!(
+                "Dashboard timed out connecting to node {}",
+                credential.node_id
+            );
+            summary.status = NodeStatus::Timeout;
+            summary.error = Some(format!(
+                "Connection timed out after {}s",
+                NODE_CONNECT_TIMEOUT.as_secs()
+            ));
+            failures.push(format!("node:{}", credential.node_id));
+            return (summary, failures);
+        }
+    };
+
+    let channels_source = format!("node:{}:channels", credential.node_id);
+    let payments_source = format!("node:{}:payments", credential.node_id);
+    let forwards_source = format!("node:{}:forwards", credential.node_id);
+
+    let (channels, payments, fees) = tokio::join!(
+        bounded(NODE_QUERY_TIMEOUT, &channels_source, client.list_channels()),
+        bounded(NODE_QUERY_TIMEOUT, &payments_source, client.list_payments()),
+        bounded(
+            NODE_QUERY_TIMEOUT,
+            &forwards_source,
+            client.get_forwarding_fees(since)
+        ),
+    );
+
+    match channels {
+        Ok(channels) => {
+            // Summed in msat so sub-satoshi balances add up
+            let (mut capacity, mut local_balance, mut remote_balance) =
+                (Amount::ZERO, Amount::ZERO, Amount::ZERO);
+            for channel in channels {
+                match channel.channel_state {
+                    ChannelState::Active | ChannelState::Splicing => summary.active_channels += 1,
+                    ChannelState::Closed | ChannelState::Failed => continue,
+                    _ => summary.inactive_channels += 1,
+                }
+                capacity += channel.capacity;
+                local_balance += channel.local_balance;
+                remote_balance += channel.remote_balance;
+            }
+            summary.capacity_sat = capacity.sat();
+            summary.local_balance_sat = local_balance.sat();
+            summary.remote_balance_sat = remote_balance.sat();
+        }
+        Err(failure) => failures.push(failure),
+    }
+
+    match payments {
+        Ok(payments) => {
+            for payment in payments {
+                let settled_at = payment.completed_at.or(payment.creation_time).unwrap_or(0);
+                if !matches!(payment.state, PaymentState::Settled) || settled_at < since {
+                    continue;
+                }
+                match payment.payment_type {
+                    PaymentType::Incoming => summary.payments_in_24h_sat += payment.amount.sat(),
+                    PaymentType::Outgoing => summary.payments_out_24h_sat += payment.amount.sat(),
+                    PaymentType::Forwarded => {}
+                }
+            }
+        }
+        Err(failure) => failures.push(failure),
+    }
+
+    match fees {
+        Ok(fees) => summary.fee_revenue_24h_msat = fees,
+        Err(failure) => failures.push(failure),
+    }
+
+    (summary, failures)
+}
+
+/// Runs a dashboard source under a deadline, folding timeouts and errors into a source label.
+async fn bounded<T, E, F>(limit: Duration, source: &str, fut: F) -> Result<T, String>
+where
+    F: Future<Output = Result<T, E>>,
+    E: std::fmt::Display,
+{
+    match timeout(limit, fut).await {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(e)) => {
+            tracing::warn!("Dashboard source {} failed: {}", source, e);
+            Err(source.to_string())
+        }
+        Err(_) => {
+            tracing::warn!(
+                "Dashboard source {} timed out after {}s",
+                source,
+                limit.as_secs()
+            );
+            Err(source.to_string())
+        }
+    }
+}
@@ -0,0 +1,586 @@
+//! Discord bot answering `/nodegaze` slash commands with live node data.
+//!
+//! Discord notifications go out through webhooks, which can only post. With
+//! `DISCORD_BOT_TOKEN` set, NodeGaze also logs a bot into the Discord gateway,
+//! registers `/nodegaze balance` and `/nodegaze channels` for it and answers
+//! them from the nodes of the account whose `discord_bot.guild_id` setting
+//! names the server the command was run in. Replies are only shown to the
+//! member who ran the command.
+
+use crate::database::models::{Credential, DiscordBotSettings};
+use crate::errors::ServiceResult;
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::repositories::settings_repository::SettingsRepository;
+use crate::services::node_aggregate::{ChannelTotals, NodeChannel, NodeFailure, query_nodes};
+use crate::services::notification_dispatcher::brand_discord_embed;
+use crate::services::settings_service::SettingsService;
+use crate::services::task_supervisor::{self, TaskHandle};
+use crate::utils::{ChannelState, ChannelSummary};
+use futures::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+const GATEWAY_URL: &str = "wss://gateway.discord.gg/?v=10&encoding=json";
+const API_URL: &str = "https://discord.com/api/v10";
+
+// Gateway opcodes
+const OP_DISPATCH: u64 = 0;
+const OP_HEARTBEAT: u64 = 1;
+const OP_IDENTIFY: u64 = 2;
+const OP_RECONNECT: u64 = 7;
+const OP_INVALID_SESSION: u64 = 9;
+const OP_HELLO: u64 = 10;
+const OP_HEARTBEAT_ACK: u64 = 11;
+
+/// Interaction type of a slash command.
+const APPLICATION_COMMAND: u64 = 2;
+/// Interaction response acknowledging a command whose reply follows.
+const DEFERRED_CHANNEL_MESSAGE: u64 = 5;
+/// Message flag showing a reply only to the member who ran the command.
+const EPHEMERAL: u64 = 1 << 6;
+
+/// A session that lasted this long starts the reconnect backoff over.
+const STABLE_SESSION: Duration = Duration::from_secs(600);
+/// Inactive channels named in a `/nodegaze channels` reply, per node.
+const LISTED_CHANNELS: usize = 10;
+/// Discord shows at most 25 fields in an embed; one is kept for failures.
+const MAX_NODE_FIELDS: usize = 24;
+const EMBED_COLOR: u64 = 0xf7931a;
+
+type GatewaySocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// A `/nodegaze` subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BotCommand {
+    Balance,
+    Channels,
+}
+
+/// The `/nodegaze` command as registered with Discord.
+fn command_definition() -> Value {
+    json!({
+        "name": "nodegaze",
+        "description": "Live figures of the nodes NodeGaze monitors",
+        "dm_permission": false,
+        "options": [
+            {
+                "type": 1,
+                "name": "balance",
+                "description": "On-chain and channel balances of each node"
+            },
+            {
+                "type": 1,
+                "name": "channels",
+                "description": "Channel counts and capacity of each node, with its inactive channels"
+            }
+        ]
+    })
+}
+
+/// A `/nodegaze` command run in a server.
+#[derive(Debug, PartialEq)]
+struct CommandInteraction {
+    id: String,
+    token: String,
+    guild_id: String,
+    /// `None` for subcommands this version doesn't know
+    command: Option<BotCommand>,
+}
+
+/// Reads the `/nodegaze` command out of an `INTERACTION_CREATE` payload.
+///
+/// Other interactions and commands run outside a server give `None`.
+fn parse_interaction(interaction: &Value) -> Option<CommandInteraction> {
+    if interaction["type"].as_u64() != Some(APPLICATION_COMMAND)
+        || interaction["data"]["name"].as_str() != Some("nodegaze")
+    {
+        return None;
+    }
+    let command = match interaction["data"]["options"][0]["name"].as_str() {
+        Some("balance") => Some(BotCommand::Balance),
+        Some("channels") => Some(BotCommand::Channels),
+        _ => None,
+    };
+
+    Some(CommandInteraction {
+        id: interaction["id"].as_str()?.to_string(),
+        token: interaction["token"].as_str()?.to_string(),
+        guild_id: interaction["guild_id"].as_str()?.to_string(),
+        command,
+    })
+}
+
+/// Picks the account linking `guild_id` from the stored `discord_bot`
+/// settings of all accounts.
+///
+/// A server linked by several accounts is answered for none of them.
+fn linked_account(links: Vec<(String, String)>, guild_id: &str) -> Option<String> {
+    let mut accounts = links.into_iter().filter(|(_, value)| {
+        serde_json::from_str::<DiscordBotSettings>(value)
+            .is_ok_and(|settings| settings.guild_id.as_deref() == Some(guild_id))
+    });
+    match (accounts.next(), accounts.next()) {
+        (Some((account_id, _)), None) => Some(account_id),
+        _ => None,
+    }
+}
+
+/// Formats sats with thousands separators, e.g. `1,250,000 sat`.
+fn sats(amount: u64) -> String {
+    let digits = amount.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3 + 4);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped + " sat"
+}
+
+/// Lists the nodes that couldn't be queried, as the last field of a reply.
+fn failures_field(errors: &[NodeFailure]) -> Option<Value> {
+    if errors.is_empty() {
+        return None;
+    }
+    let value = errors
+        .iter()
+        .map(|failure| format!("{}: {}", failure.node_alias, failure.error))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(json!({ "name": "Unreachable", "value": truncate(&value, 1024) }))
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars.saturating_sub(1)) {
+        Some((end, _)) if text.chars().count() > max_chars => format!("{}…", &text[..end]),
+        _ => text.to_string(),
+    }
+}
+
+/// Builds the `/nodegaze balance` reply from each node's on-chain balance and
+/// channels.
+fn balance_embed(
+    nodes: &[(&Credential, (u64, Vec<ChannelSummary>))],
+    errors: &[NodeFailure],
+) -> Value {
+    let mut total = 0;
+    let mut fields: Vec<Value> = Vec::new();
+    for (credential, (onchain, channels)) in nodes {
+        let totals = ChannelTotals::of(&node_channels(credential, channels));
+        total += onchain + totals.local_balance_sat;
+        fields.push(json!({
+            "name": credential.node_alias,
+            "value": format!(
+                "On-chain: {}\nLocal: {}\nRemote: {}",
+                sats(*onchain),
+                sats(totals.local_balance_sat),
+                sats(totals.remote_balance_sat)
+            ),
+            "inline": true
+        }));
+    }
+    fields.truncate(MAX_NODE_FIELDS);
+    fields.extend(failures_field(errors));
+
+    json!({
+        "title": "Balance",
+        "description": format!(
+            "{} on-chain and in channels across {} node{}",
+            sats(total),
+            nodes.len(),
+            if nodes.len() == 1 { "" } else { "s" }
+        ),
+        "color": EMBED_COLOR,
+        "fields": fields
+    })
+}
+
+/// Builds the `/nodegaze channels` reply from each node's channels.
+fn channels_embed(nodes: &[(&Credential, Vec<ChannelSummary>)], errors: &[NodeFailure]) -> Value {
+    let mut fields: Vec<Value> = Vec::new();
+    for (credential, channels) in nodes {
+        let totals = ChannelTotals::of(&node_channels(credential, channels));
+        let mut value = format!(
+            "{} channels, {} active\nCapacity: {}",
+            totals.channels,
+            totals.active_channels,
+            sats(totals.capacity_sat)
+        );
+        let inactive: Vec<&ChannelSummary> = channels
+            .iter()
+            .filter(|channel| {
+                matches!(
+                    channel.channel_state,
+                    ChannelState::Opening | ChannelState::Disabled | ChannelState::Closing
+                )
+            })
+            .collect();
+        for channel in inactive.iter().take(LISTED_CHANNELS) {
+            value.push_str(&format!(
+                "\n{:?}: {} ({})",
+                channel.channel_state,
+                channel.alias.as_deref().unwrap_or("unknown peer"),
+                channel.chan_id
+            ));
+        }
+        if inactive.len() > LISTED_CHANNELS {
+            value.push_str(&format!("\n…and {} more", inactive.len() - LISTED_CHANNELS));
+        }
+        fields.push(json!({
+            "name": credential.node_alias,
+            "value": truncate(&value, 1024)
+        }));
+    }
+    fields.truncate(MAX_NODE_FIELDS);
+    fields.extend(failures_field(errors));
+
+    json!({
+        "title": "Channels",
+        "color": EMBED_COLOR,
+        "fields": fields
+    })
+}
+
+fn node_channels(credential: &Credential, channels: &[ChannelSummary]) -> Vec<NodeChannel> {
+    channels
+        .iter()
+        .map(|channel| NodeChannel {
+            node_id: credential.node_id.clone(),
+            node_alias: credential.node_alias.clone(),
+            channel: channel.clone(),
+        })
+        .collect()
+}
+
+/// The bot's access to the database and to Discord's HTTP API.
+struct DiscordBot {
+    pool: SqlitePool,
+    token: String,
+    http_client: reqwest::Client,
+}
+
+impl DiscordBot {
+    /// Runs one gateway session, answering commands until Discord asks for a
+    /// reconnect (`Ok`) or the connection fails (`Err`).
+    async fn run_session(self: &Arc<Self>) -> Result<(), String> {
+        let (mut socket, _) = tokio_tungstenite::connect_async(GATEWAY_URL)
+            .await
+            .map_err(|e| format!("Could not connect to the Discord gateway: {e}"))?;
+
+        let hello = next_payload(&mut socket).await?;
+        if hello["op"].as_u64() != Some(OP_HELLO) {
+            return Err(format!(
+                "Expected hello from the Discord gateway, got {hello}"
+            ));
+        }
+        let period = hello["d"]["heartbeat_interval"]
+            .as_u64()
+            .map(Duration::from_millis)
+            .ok_or("Discord gateway sent no heartbeat interval")?;
+        send(
+            &mut socket,
+            json!({
+                "op": OP_IDENTIFY,
+                "d": {
+                    "token": self.token,
+                    // Slash commands arrive without any intents
+                    "intents": 0,
+                    "properties": {
+                        "os": std::env::consts::OS,
+                        "browser": "nodegaze",
+                        "device": "nodegaze"
+                    }
+                }
+            }),
+        )
+        .await?;
+
+        let mut heartbeat = tokio::time::interval(period);
+        let mut acknowledged = true;
+        let mut sequence: Option<u64> = None;
+        let mut application_id: Option<String> = None;
+        loop {
+            let payload = tokio::select! {
+                payload = next_payload(&mut socket) => payload?,
+                _ = heartbeat.tick() => {
+                    // A connection that stopped acknowledging is dead, even if still open
+                    if !acknowledged {
+                        return Err("Discord gateway stopped acknowledging heartbeats".to_string());
+                    }
+                    acknowledged = false;
+                    send(&mut socket, json!({ "op": OP_HEARTBEAT, "d": sequence })).await?;
+                    continue;
+                }
+            };
+            if let Some(seq) = payload["s"].as_u64() {
+                sequence = Some(seq);
+            }
+
+            match payload["op"].as_u64() {
+                Some(OP_HEARTBEAT_ACK) => acknowledged = true,
+                Some(OP_HEARTBEAT) => {
+                    send(&mut socket, json!({ "op": OP_HEARTBEAT, "d": sequence })).await?
+                }
+                Some(OP_RECONNECT) | Some(OP_INVALID_SESSION) => return Ok(()),
+                Some(OP_DISPATCH) => match payload["t"].as_str() {
+                    Some("READY") => {
+                        let id = payload["d"]["application"]["id"]
+                            .as_str()
+                            .ok_or("Discord READY carries no application ID")?;
+                        self.register_commands(id).await?;
+                        application_id = Some(id.to_string());
+                    }
+                    Some("INTERACTION_CREATE") => {
+                        let (Some(interaction), Some(application_id)) =
+                            (parse_interaction(&payload["d"]), application_id.clone())
+                        else {
+                            continue;
+                        };
+                        let bot = Arc::clone(self);
+                        tokio::spawn(async move { bot.answer(&application_id, interaction).await });
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+    }
+
+    /// Registers `/nodegaze`, replacing the bot's earlier global commands.
+    async fn register_commands(&self, application_id: &str) -> Result<(), String> {
+        self.http_client
+            .put(format!("{API_URL}/applications/{application_id}/commands"))
+            .header("Authorization", format!("Bot {}", self.token))
+            .json(&json!([command_definition()]))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| format!("Could not register the slash commands: {e}"))?;
+        Ok(())
+    }
+
+    /// Acknowledges a command at once and follows up with the reply.
+    async fn answer(&self, application_id: &str, interaction: CommandInteraction) {
+        // Discord drops commands not acknowledged within 3 seconds, nodes can take longer
+        let deferred = self
+            .http_client
+            .post(format!(
+                "{API_URL}/interactions/{}/{}/callback",
+                interaction.id, interaction.token
+            ))
+            .json(&json!({ "type": DEFERRED_CHANNEL_MESSAGE, "data": { "flags": EPHEMERAL } }))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status);
+        if let Err(e) = deferred {
+            tracing::warn!("Could not acknowledge Discord command: {}", e);
+            return;
+        }
+
+        let reply = match self.reply(&interaction).await {
+            Ok(reply) => reply,
+            Err(e) => {
+                tracing::error!(
+                    "Discord command in server {} failed: {}",
+                    interaction.guild_id,
+                    e
+                );
+                json!({ "content": "The node data could not be loaded, please try again later." })
+            }
+        };
+        let sent = self
+            .http_client
+            .patch(format!(
+                "{API_URL}/webhooks/{application_id}/{}/messages/@original",
+                interaction.token
+            ))
+            .json(&reply)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status);
+        if let Err(e) = sent {
+            tracing::warn!("Could not send Discord command reply: {}", e);
+        }
+    }
+
+    /// Builds the message answering a command.
+    async fn reply(&self, interaction: &CommandInteraction) -> ServiceResult<Value> {
+        let links = SettingsRepository::new(&self.pool)
+            .get_setting_for_all_accounts("discord_bot")
+            .await?;
+        let Some(account_id) = linked_account(links, &interaction.guild_id) else {
+            return Ok(json!({
+                "content": format!(
+                    "This server isn't linked to a NodeGaze account. Set `discord_bot.guild_id` \
+                     in the account settings to `{}` to link it.",
+                    interaction.guild_id
+                )
+            }));
+        };
+        let Some(command) = interaction.command else {
+            return Ok(json!({ "content": "Unknown command." }));
+        };
+
+        let credentials = CredentialRepository::new(&self.pool)
+            .get_node_credentials(Some(&account_id), None)
+            .await?;
+
+        let mut embed = match command {
+            BotCommand::Balance => {
+                let (nodes, errors) = query_nodes(&credentials, |client| async move {
+                    tokio::try_join!(client.get_wallet_balance(), client.list_channels())
+                })
+                .await;
+                balance_embed(&nodes, &errors)
+            }
+            BotCommand::Channels => {
+                let (nodes, errors) = query_nodes(&credentials, |client| async move {
+                    client.list_channels().await
+                })
+                .await;
+                channels_embed(&nodes, &errors)
+            }
+        };
+        let branding = SettingsService::new(&self.pool)
+            .get_settings(&account_id)
+            .await?
+            .branding;
+        brand_discord_embed(&mut embed, &branding);
+
+        Ok(json!({ "embeds": [embed] }))
+    }
+}
+
+/// Reads the next JSON payload from the gateway.
+async fn next_payload(socket: &mut GatewaySocket) -> Result<Value, String> {
+    while let Some(message) = socket.next().await {
+        match message.map_err(|e| format!("Discord gateway connection failed: {e}"))? {
+            WsMessage::Text(text) => {
+                return serde_json::from_str(&text)
+                    .map_err(|e| format!("Unreadable Discord gateway payload: {e}"));
+            }
+            WsMessage::Close(frame) => {
+                return Err(match frame {
+                    Some(frame) => format!(
+                        "Discord closed the gateway ({}): {}",
+                        u16::from(frame.code),
+                        frame.reason
+                    ),
+                    None => "Discord closed the gateway".to_string(),
+                });
+            }
+            _ => {}
+        }
+    }
+    Err("Discord gateway connection ended".to_string())
+}
+
+async fn send(socket: &mut GatewaySocket, payload: Value) -> Result<(), String> {
+    socket
+        .send(WsMessage::Text(payload.to_string()))
+        .await
+        .map_err(|e| format!("Could not send to the Discord gateway: {e}"))
+}
+
+/// Keeps the bot connected to the Discord gateway when a token is configured.
+pub fn spawn_discord_bot(pool: SqlitePool, token: Option<String>) {
+    let Some(token) = token else {
+        return;
+    };
+
+    task_supervisor::supervise("discord_bot", move |task: TaskHandle| {
+        let bot = Arc::new(DiscordBot {
+            pool: pool.clone(),
+            token: token.clone(),
+            http_client: reqwest::Client::new(),
+        });
+        async move {
+            let mut quick_failures = 0;
+            loop {
+                let started_at = Instant::now();
+                match bot.run_session().await {
+                    Ok(()) => tracing::info!("Discord asked the bot to reconnect"),
+                    Err(e) => {
+                        if started_at.elapsed() >= STABLE_SESSION {
+                            quick_failures = 0;
+                        }
+                        quick_failures += 1;
+                        let delay = task_supervisor::restart_delay(quick_failures);
+                        tracing::warn!(
+                            "Discord bot disconnected, reconnecting in {}s: {}",
+                            delay.as_secs(),
+                            e
+                        );
+                        task.backoff(e);
+                        tokio::time::sleep(delay).await;
+                        task.resume();
+                    }
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_nodegaze_commands_from_linked_servers() {
+        let interaction = json!({
+            "id": "1234",
+            "token": "interaction-token",
+            "type": 2,
+            "guild_id": "613425648685547541",
+            "data": {
+                "name": "nodegaze",
+                "options": [{ "type": 1, "name": "channels" }]
+            }
+        });
+        assert_eq!(
+            parse_interaction(&interaction),
+            Some(CommandInteraction {
+                id: "1234".to_string(),
+                token: "interaction-token".to_string(),
+                guild_id: "613425648685547541".to_string(),
+                command: Some(BotCommand::Channels),
+            })
+        );
+
+        let mut in_dm = interaction.clone();
+        in_dm.as_object_mut().unwrap().remove("guild_id");
+        assert_eq!(parse_interaction(&in_dm), None);
+
+        let linked = |guild: &str| format!(r#"{{"guild_id":"{guild}"}}"#);
+        let links = vec![
+            ("a".to_string(), linked("613425648685547541")),
+            ("b".to_string(), linked("813425648685547541")),
+            ("c".to_string(), "{}".to_string()),
+        ];
+        assert_eq!(
+            linked_account(links.clone(), "613425648685547541").as_deref(),
+            Some("a")
+        );
+        assert_eq!(linked_account(links.clone(), "999"), None);
+
+        let mut shared = links;
+        shared.push(("d".to_string(), linked("613425648685547541")));
+        assert_eq!(linked_account(shared, "613425648685547541"), None);
+    }
+
+    #[test]
+    fn groups_sat_digits() {
+        assert_eq!(sats(0), "0 sat");
+        assert_eq!(sats(999), "999 sat");
+        assert_eq!(sats(1_250_000), "1,250,000 sat");
+        assert_eq!(truncate("abcdef", 4), "abc…");
+        assert_eq!(truncate("abc", 4), "abc");
+    }
+}
@@ -0,0 +1,189 @@
+//! Donations received through zero-amount invoices.
+//!
+//! Tip jars hand out invoices without an amount and let the payer choose it, so
+//! the requested amounts say nothing about what came in. Settled zero-amount
+//! invoices are counted by the amount actually received instead, under each
+//! of the account's `donation_tags` whose pattern the invoice's memo contains.
+//! An invoice matching several tags is counted under each of them but once in
+//! the totals, and those matching none are counted as untagged.
+
+use crate::database::models::DonationTag;
+use crate::utils::amount::Amount;
+use crate::utils::{CustomInvoice, InvoiceStatus};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Number and sum of the donations received under one tag.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct DonationTotals {
+    pub count: u64,
+    pub received: Amount,
+    /// Largest single donation
+    pub largest: Amount,
+    pub last_received_at: Option<DateTime<Utc>>,
+}
+
+impl DonationTotals {
+    fn add(&mut self, received: Amount, received_at: DateTime<Utc>) {
+        self.count += 1;
+        self.received += received;
+        self.largest = self.largest.max(received);
+        self.last_received_at = self.last_received_at.max(Some(received_at));
+    }
+}
+
+/// Donations received under a configured tag.
+#[derive(Debug, Clone, Serialize)]
+pub struct TagDonations {
+    pub tag: String,
+    pub pattern: String,
+    #[serde(flatten)]
+    pub totals: DonationTotals,
+}
+
+/// Response of `GET /api/invoices/donations/stats`.
+#[derive(Debug, Serialize)]
+pub struct DonationStats {
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    /// One entry per configured tag, in the order of the settings
+    pub tags: Vec<TagDonations>,
+    /// Donations whose memo matches no tag
+    pub untagged: DonationTotals,
+    pub totals: DonationTotals,
+}
+
+/// Whether `memo` contains the tag's pattern, regardless of case.
+pub fn matches_tag(memo: &str, tag: &DonationTag) -> bool {
+    memo.to_lowercase()
+        .contains(&tag.pattern.trim().to_lowercase())
+}
+
+/// Amount and time a zero-amount invoice was paid with, `None` for invoices
+/// that ask for an amount or weren't settled.
+fn donation(invoice: &CustomInvoice) -> Option<(Amount, DateTime<Utc>)> {
+    if invoice.value != Amount::ZERO || invoice.state != InvoiceStatus::Settled {
+        return None;
+    }
+    let received_at = DateTime::from_timestamp(invoice.settle_date?, 0)?;
+    Some((invoice.amount_paid.unwrap_or_default(), received_at))
+}
+
+/// Sums the donations settled between `start` and `end` per tag.
+pub fn donation_stats(
+    invoices: &[CustomInvoice],
+    tags: &[DonationTag],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> DonationStats {
+    let mut tag_donations: Vec<TagDonations> = tags
+        .iter()
+        .map(|tag| TagDonations {
+            tag: tag.tag.clone(),
+            pattern: tag.pattern.clone(),
+            totals: DonationTotals::default(),
+        })
+        .collect();
+    let mut untagged = DonationTotals::default();
+    let mut totals = DonationTotals::default();
+
+    for invoice in invoices {
+        let Some((received, received_at)) = donation(invoice) else {
+            continue;
+        };
+        if received_at < start || received_at > end {
+            continue;
+        }
+
+        let mut tagged = false;
+        for (tag, donations) in tags.iter().zip(&mut tag_donations) {
+            if matches_tag(&invoice.memo, tag) {
+                donations.totals.add(received, received_at);
+                tagged = true;
+            }
+        }
+        if !tagged {
+            untagged.add(received, received_at);
+        }
+        totals.add(received, received_at);
+    }
+
+    DonationStats {
+        window_start: start,
+        window_end: end,
+        tags: tag_donations,
+        untagged,
+        totals,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn tag(tag: &str, pattern: &str) -> DonationTag {
+        DonationTag {
+            tag: tag.to_string(),
+            pattern: pattern.to_string(),
+        }
+    }
+
+    fn invoice(
+        memo: &str,
+        value_msat: u64,
+        paid_msat: u64,
+        settled: DateTime<Utc>,
+    ) -> CustomInvoice {
+        CustomInvoice {
+            memo: memo.to_string(),
+            value: Amount::from_msat(value_msat),
+            amount_paid: Some(Amount::from_msat(paid_msat)),
+            settle_date: Some(settled.timestamp()),
+            state: InvoiceStatus::Settled,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn sums_zero_amount_invoices_per_tag() {
+        let now = Utc::now();
+        let start = now - Duration::days(30);
+        let tags = [tag("podcast", "#podcast"), tag("blog", "Blog tip")];
+        let invoices = [
+            invoice("Thanks! #Podcast", 0, 21_000, now - Duration::days(1)),
+            invoice("blog tip #podcast", 0, 5_000_500, now - Duration::days(2)),
+            invoice("coffee", 0, 1_000_000, now - Duration::days(3)),
+            // Fixed amount, not a donation
+            invoice("#podcast merch", 50_000_000, 50_000_000, now),
+            // Settled before the window
+            invoice("#podcast", 0, 9_000_000, now - Duration::days(40)),
+            CustomInvoice {
+                state: InvoiceStatus::Open,
+                ..invoice("#podcast", 0, 0, now)
+            },
+        ];
+
+        let stats = donation_stats(&invoices, &tags, start, now);
+        let podcast = &stats.tags[0].totals;
+        assert_eq!(
+            (podcast.count, podcast.received),
+            (2, Amount::from_msat(5_021_500))
+        );
+        assert_eq!(podcast.largest, Amount::from_msat(5_000_500));
+        assert_eq!(
+            podcast.last_received_at.map(|at| at.timestamp()),
+            Some((now - Duration::days(1)).timestamp())
+        );
+        let blog = &stats.tags[1].totals;
+        assert_eq!((blog.count, blog.received.sat()), (1, 5_000));
+        assert_eq!(
+            (stats.untagged.count, stats.untagged.received),
+            (1, Amount::from_sat(1_000))
+        );
+        assert_eq!(
+            (stats.totals.count, stats.totals.received),
+            (3, Amount::from_msat(6_021_500))
+        );
+    }
+}
@@ -17,7 +17,7 @@ impl EmailService {
         let creds = Credentials::new(config.smtp_username.clone(), config.smtp_password.clone());
 
         let mailer = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)
-            .map_err(|e| ServiceError::validation(&format!("Invalid SMTP host: {}", e)))?
+            .map_err(|e| ServiceError::validation(format!("Invalid SMTP host: {}", e)))?
             .port(config.smtp_port)
             .credentials(creds)
             .timeout(Some(std::time::Duration::from_secs(30)))
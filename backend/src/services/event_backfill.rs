@@ -0,0 +1,174 @@
+//! Recreates events missed while a node's event stream was not running.
+//!
+//! Settled invoices are the only events a node keeps a history of, so the
+//! backfill covers `invoice_settled` events. Recovered events are stored without
+//! dispatching notifications and are marked with `"backfilled": true`.
+
+use crate::database::models::{CreateEvent, Credential, EventSeverity, EventType};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::repositories::event_repository::EventRepository;
+use crate::services::node_manager::connect_node;
+use crate::utils::{CustomInvoice, InvoiceStatus};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+/// LND's numeric `SETTLED` invoice state, as stored by the live event stream.
+const SETTLED_INVOICE_STATE: i32 = 1;
+
+/// Outcome of backfilling one account's view of a node.
+#[derive(Debug)]
+pub struct BackfillReport {
+    pub account_id: String,
+    pub created: usize,
+    /// Settled invoices that already had an event
+    pub existing: usize,
+}
+
+/// Settlement time of an invoice, or `None` if it isn't settled.
+fn settled_at(state: &InvoiceStatus, settle_date: Option<i64>) -> Option<DateTime<Utc>> {
+    match state {
+        InvoiceStatus::Settled => {
+            DateTime::from_timestamp(settle_date.filter(|date| *date > 0)?, 0)
+        }
+        _ => None,
+    }
+}
+
+/// Service for recovering missed node events.
+pub struct EventBackfillService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> EventBackfillService<'a> {
+    /// Creates a new EventBackfillService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Backfills settled invoice events for every account connected to the node.
+    ///
+    /// Only invoices settled at or after `since` are considered when it is given.
+    pub async fn backfill_node(
+        &self,
+        node_id: &str,
+        since: Option<DateTime<Utc>>,
+    ) -> ServiceResult<Vec<BackfillReport>> {
+        let credentials: Vec<Credential> = CredentialRepository::new(self.pool)
+            .get_node_credentials(None, None)
+            .await?
+            .into_iter()
+            .filter(|credential| credential.node_id == node_id)
+            .collect();
+        if credentials.is_empty() {
+            return Err(ServiceError::not_found("Node", node_id));
+        }
+
+        let mut reports = Vec::with_capacity(credentials.len());
+        for credential in &credentials {
+            reports.push(self.backfill_credential(credential, since).await?);
+        }
+
+        Ok(reports)
+    }
+
+    async fn backfill_credential(
+        &self,
+        credential: &Credential,
+        since: Option<DateTime<Utc>>,
+    ) -> ServiceResult<BackfillReport> {
+        let client = connect_node(credential)
+            .await
+            .map_err(|e| ServiceError::ExternalService {
+                message: format!("Failed to connect to node {}: {e}", credential.node_id),
+            })?;
+        let invoices = client
+            .list_invoices()
+            .await
+            .map_err(|e| ServiceError::ExternalService {
+                message: format!("Failed to list invoices: {e}"),
+            })?;
+
+        let repo = EventRepository::new(self.pool);
+        let recorded = repo
+            .get_settled_invoice_hashes(&credential.account_id, &credential.node_id)
+            .await?;
+
+        let mut report = BackfillReport {
+            account_id: credential.account_id.clone(),
+            created: 0,
+            existing: 0,
+        };
+
+        for invoice in &invoices {
+            let Some(settled_at) = settled_at(&invoice.state, invoice.settle_date) else {
+                continue;
+            };
+            if since.is_some_and(|since| settled_at < since) {
+                continue;
+            }
+            if recorded.contains(&invoice.payment_hash) {
+                report.existing += 1;
+                continue;
+            }
+
+            repo.create_event(settled_invoice_event(credential, invoice, settled_at))
+                .await?;
+            report.created += 1;
+        }
+
+        Ok(report)
+    }
+}
+
+/// Builds an `invoice_settled` event shaped like the ones from the live stream.
+fn settled_invoice_event(
+    credential: &Credential,
+    invoice: &CustomInvoice,
+    settled_at: DateTime<Utc>,
+) -> CreateEvent {
+    CreateEvent {
+        id: Uuid::now_v7().to_string(),
+        account_id: credential.account_id.clone(),
+        user_id: credential.user_id.clone(),
+        node_id: credential.node_id.clone(),
+        node_alias: credential.node_alias.clone(),
+        event_type: EventType::InvoiceSettled,
+        severity: EventSeverity::Info,
+        title: "Invoice Settled".to_string(),
+        description: format!("Invoice settled for {}", invoice.value),
+        data: json!({
+            "preimage": invoice.payment_preimage,
+            "hash": invoice.payment_hash,
+            "value_msat": invoice.value.msat(),
+            "value_sat": invoice.value.sat(),
+            "state": SETTLED_INVOICE_STATE,
+            "memo": invoice.memo,
+            "creation_date": invoice.creation_date,
+            "payment_request": invoice.payment_request,
+            "backfilled": true,
+        })
+        .to_string(),
+        notifications_id: None,
+        timestamp: settled_at,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_settled_invoices_are_backfilled() {
+        assert_eq!(
+            settled_at(&InvoiceStatus::Settled, Some(1_754_827_200)),
+            DateTime::from_timestamp(1_754_827_200, 0)
+        );
+        // LND reports a zero settle date for invoices that never settled
+        assert_eq!(settled_at(&InvoiceStatus::Settled, Some(0)), None);
+        assert_eq!(settled_at(&InvoiceStatus::Open, Some(1_754_827_200)), None);
+    }
+}
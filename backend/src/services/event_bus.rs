@@ -0,0 +1,143 @@
+//! Process-wide bus the event pipeline publishes on.
+//!
+//! Messages are split into typed topics: lifecycle notices about the node
+//! streams, and alerts once they are persisted. Each topic is a broadcast
+//! channel, so log sinks and any other consumer subscribe on their own without
+//! the producers knowing about them. A subscriber that falls more than the
+//! topic's capacity behind skips the oldest messages instead of holding up the
+//! producers, which is why raw node events reach their handler for persistence
+//! on a channel of their own rather than through the bus, see
+//! `services::event_manager`.
+
+use crate::database::models::Event;
+use serde::Serialize;
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+const SYSTEM_TOPIC_CAPACITY: usize = 64;
+const ALERT_TOPIC_CAPACITY: usize = 1024;
+
+static BUS: LazyLock<EventBus> = LazyLock::new(EventBus::new);
+static NEXT_SOURCE_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Identifies one node stream; a node connected twice has two sources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct SourceId(u64);
+
+impl SourceId {
+    /// A source id no other stream has.
+    pub fn next() -> Self {
+        Self(NEXT_SOURCE_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Lifecycle of the node streams, published on the system topic.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SystemEvent {
+    StreamStarted {
+        source: SourceId,
+        node_id: String,
+    },
+    StreamFailed {
+        source: SourceId,
+        node_id: String,
+        error: String,
+    },
+    StreamEnded {
+        source: SourceId,
+        node_id: String,
+    },
+}
+
+impl SystemEvent {
+    /// Source whose stream this notice reports as stopped, if any.
+    pub fn stopped_source(&self) -> Option<SourceId> {
+        match self {
+            SystemEvent::StreamStarted { .. } => None,
+            SystemEvent::StreamFailed { source, .. } | SystemEvent::StreamEnded { source, .. } => {
+                Some(*source)
+            }
+        }
+    }
+}
+
+/// The topics of the bus; messages published while a topic has no
+/// subscribers are dropped.
+pub struct EventBus {
+    system: Sender<SystemEvent>,
+    alerts: Sender<Event>,
+}
+
+impl EventBus {
+    fn new() -> Self {
+        Self {
+            system: broadcast::channel(SYSTEM_TOPIC_CAPACITY).0,
+            alerts: broadcast::channel(ALERT_TOPIC_CAPACITY).0,
+        }
+    }
+
+    pub fn publish_system_event(&self, event: SystemEvent) {
+        tracing::debug!("System event: {:?}", event);
+        let _ = self.system.send(event);
+    }
+
+    pub fn subscribe_system_events(&self) -> Receiver<SystemEvent> {
+        self.system.subscribe()
+    }
+
+    /// Publishes an event once it has been persisted.
+    pub fn publish_alert(&self, event: Event) {
+        let _ = self.alerts.send(event);
+    }
+
+    pub fn subscribe_alerts(&self) -> Receiver<Event> {
+        self.alerts.subscribe()
+    }
+}
+
+/// The process-wide bus.
+pub fn bus() -> &'static EventBus {
+    &BUS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::broadcast::error::RecvError;
+
+    fn stream_ended(source: SourceId) -> SystemEvent {
+        SystemEvent::StreamEnded {
+            source,
+            node_id: "node".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn subscribers_receive_independently() {
+        let bus = EventBus::new();
+        let mut splices = bus.subscribe_system_events();
+        let mut logs = bus.subscribe_system_events();
+        let source = SourceId::next();
+
+        bus.publish_system_event(stream_ended(source));
+
+        assert_eq!(splices.recv().await.unwrap().stopped_source(), Some(source));
+        assert_eq!(logs.recv().await.unwrap().stopped_source(), Some(source));
+        assert_ne!(SourceId::next(), source);
+    }
+
+    #[tokio::test]
+    async fn slow_subscribers_skip_the_oldest_messages() {
+        let bus = EventBus::new();
+        let mut slow = bus.subscribe_system_events();
+
+        for _ in 0..SYSTEM_TOPIC_CAPACITY + 3 {
+            bus.publish_system_event(stream_ended(SourceId::next()));
+        }
+
+        assert!(matches!(slow.recv().await, Err(RecvError::Lagged(3))));
+        assert!(slow.recv().await.is_ok());
+    }
+}
@@ -0,0 +1,105 @@
+//! Compaction of old high-volume events into hourly aggregates.
+//!
+//! Invoice and payment events pile up fast on busy nodes while long-term
+//! analytics only need how many there were and what they added up to. Accounts
+//! that enable compaction have events of the chosen types older than
+//! `after_days` folded into one row per node, type, severity and UTC hour in
+//! `event_hourly_aggregates`, and the raw rows deleted. Event statistics count
+//! the aggregates alongside the remaining events.
+
+use crate::database::models::EventCompactionSettings;
+use crate::errors::ServiceResult;
+use crate::repositories::event_repository::EventRepository;
+use crate::repositories::settings_repository::SettingsRepository;
+use crate::services::task_supervisor;
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use sqlx::SqlitePool;
+
+/// How often events past their account's compaction age are compacted.
+const COMPACTION_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// Time before which events are compacted, rounded down to the hour so each
+/// run only compacts whole hours.
+pub fn compaction_cutoff(now: DateTime<Utc>, after_days: u32) -> DateTime<Utc> {
+    let cutoff = now - Duration::days(after_days.into());
+    cutoff.duration_trunc(Duration::hours(1)).unwrap_or(cutoff)
+}
+
+/// Service compacting events.
+pub struct EventCompactionService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> EventCompactionService<'a> {
+    /// Creates a new EventCompactionService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Compacts the events past their compaction age of every account that
+    /// enabled compaction.
+    ///
+    /// Returns the number of compacted events.
+    pub async fn compact_expired_events(&self) -> ServiceResult<u64> {
+        let settings = SettingsRepository::new(self.pool)
+            .get_setting_for_all_accounts("event_compaction")
+            .await?;
+        let repo = EventRepository::new(self.pool);
+
+        let mut compacted = 0;
+        for (account_id, value) in settings {
+            let Ok(settings) = serde_json::from_str::<EventCompactionSettings>(&value) else {
+                continue;
+            };
+            if !settings.enabled || settings.event_types.is_empty() {
+                continue;
+            }
+            let cutoff = compaction_cutoff(Utc::now(), settings.after_days);
+            compacted += repo
+                .compact_account_events_before(&account_id, &settings.event_types, cutoff)
+                .await?;
+        }
+
+        Ok(compacted)
+    }
+}
+
+/// Compacts events past their account's compaction age every hour.
+pub fn spawn_compaction_job(pool: SqlitePool) {
+    task_supervisor::supervise("event_compaction", move |task| {
+        let pool = pool.clone();
+        async move {
+            loop {
+                // The first run waits too, giving startup migrations time to finish
+                tokio::time::sleep(COMPACTION_INTERVAL).await;
+                let result = EventCompactionService::new(&pool)
+                    .compact_expired_events()
+                    .await;
+                match &result {
+                    Ok(0) => {}
+                    Ok(compacted) => tracing::info!("Compacted {} events", compacted),
+                    Err(e) => tracing::error!("Event compaction failed: {}", e),
+                }
+                task.record_run(&result);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cutoff_is_rounded_down_to_the_hour() {
+        let now = DateTime::parse_from_rfc3339("2025-09-03T14:42:17Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(
+            compaction_cutoff(now, 30).to_rfc3339(),
+            "2025-08-04T14:00:00+00:00"
+        );
+    }
+}
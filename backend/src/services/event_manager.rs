@@ -1,102 +1,64 @@
 //! Manages Events occuring on a lightning node.
 //!
 //! This module collects, aggregates and dispatches events occuring on a lightning node
-//! in order to provide timely notifications for critical events.
+//! in order to provide timely notifications for critical events. Collectors
+//! queue a node's events for its handler on a channel of their own, which
+//! holds them back rather than dropping any while the handler is busy, and
+//! report their stream's lifecycle on the event bus.
 
+use crate::services::channel_tracker::{ChannelSplice, ChannelTracker};
+use crate::services::event_bus::{SourceId, SystemEvent, bus};
+use crate::services::invoice_watcher::InvoiceExpiryWatcher;
+use crate::services::node_capabilities::NodeImplementation;
 use crate::services::node_manager::LightningClient;
+use crate::services::raw_events::RawPayload;
+use crate::services::task_supervisor;
+use crate::utils::{CustomInvoice, InvoiceStatus};
 use bitcoin::secp256k1::PublicKey;
-use serde::{Deserialize, Serialize};
+use lightning::ln::PaymentHash;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio;
+use tokio::sync::broadcast::error::TryRecvError;
 use tokio::sync::{Mutex, mpsc};
 use tokio_stream::Stream;
 use tokio_stream::StreamExt;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum LNDEvent {
-    ChannelOpened {
-        active: bool,
-        remote_pubkey: String,
-        channel_point: String,
-        chan_id: u64,
-        capacity: i64,
-        local_balance: i64,
-        remote_balance: i64,
-        total_satoshis_sent: i64,
-        total_satoshis_received: i64,
-    },
-    ChannelClosed {
-        channel_point: String,
-        chan_id: u64,
-        chain_hash: String,
-        closing_tx_hash: String,
-        remote_pubkey: String,
-        capacity: i64,
-        close_height: u32,
-        settled_balance: i64,
-        time_locked_balance: i64,
-        close_type: i32,
-        open_initiator: i32,
-        close_initiator: i32,
-    },
-    InvoiceCreated {
-        preimage: Vec<u8>,
-        hash: Vec<u8>,
-        value_msat: i64,
-        state: i32,
-        memo: String,
-        creation_date: i64,
-        payment_request: String,
-    },
-    InvoiceSettled {
-        preimage: Vec<u8>,
-        hash: Vec<u8>,
-        value_msat: i64,
-        state: i32,
-        memo: String,
-        creation_date: i64,
-        payment_request: String,
-    },
-    InvoiceCancelled {
-        preimage: Vec<u8>,
-        hash: Vec<u8>,
-        value_msat: i64,
-        state: i32,
-        memo: String,
-        creation_date: i64,
-        payment_request: String,
-    },
-    InvoiceAccepted {
-        preimage: Vec<u8>,
-        hash: Vec<u8>,
-        value_msat: i64,
-        state: i32,
-        memo: String,
-        creation_date: i64,
-        payment_request: String,
-    },
-}
+pub use nodegaze_lightning::events::{CLNEvent, CapturedEvent, LNDEvent, NodeSpecificEvent};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum CLNEvent {
-    ChannelOpened {},
-}
+/// How often the channel list is polled for splices.
+const SPLICE_POLL_INTERVAL: Duration = Duration::from_secs(60);
 
-#[derive(Debug, Clone)]
-pub enum NodeSpecificEvent {
-    LND(LNDEvent),
-    CLN(CLNEvent),
+impl From<ChannelSplice> for CLNEvent {
+    fn from(splice: ChannelSplice) -> Self {
+        CLNEvent::ChannelSpliced {
+            remote_pubkey: splice.remote_pubkey.to_string(),
+            previous_chan_id: splice.previous_chan_id,
+            chan_id: splice.chan_id,
+            previous_channel_point: splice.previous_channel_point.map(|point| point.to_string()),
+            channel_point: splice.channel_point.map(|point| point.to_string()),
+            previous_capacity: splice.previous_capacity,
+            capacity: splice.capacity,
+        }
+    }
 }
 
+/// Sends a node's events to its handler, and reports its stream on the event
+/// bus under its own source.
 pub struct EventCollector {
-    raw_event_sender: mpsc::Sender<NodeSpecificEvent>,
+    raw_event_sender: mpsc::Sender<CapturedEvent>,
+    source: SourceId,
+    /// Absent for nodes connected without a logged in user
+    account_id: Option<String>,
 }
 
 impl EventCollector {
-    pub fn new(sender: mpsc::Sender<NodeSpecificEvent>) -> Self {
+    pub fn new(sender: mpsc::Sender<CapturedEvent>, account_id: Option<String>) -> Self {
         EventCollector {
             raw_event_sender: sender,
+            source: SourceId::next(),
+            account_id,
         }
     }
 
@@ -106,13 +68,18 @@ impl EventCollector {
         lnd_node_: Arc<Mutex<Box<dyn LightningClient + Send + Sync + 'static>>>,
     ) {
         let sender = self.raw_event_sender.clone();
-        let node_id_for_task = node_id.clone();
+        let source = self.source;
+        let node_id_for_task = node_id;
+        let task = task_supervisor::track(
+            format!("event_stream:{node_id}"),
+            self.account_id.as_deref(),
+        );
 
         tokio::spawn(async move {
-            let mut lnd_node_guard = lnd_node_.lock().await;
-            let event_stream_result = lnd_node_guard.stream_events().await;
+            // Release the node once subscribed so pollers can still query it.
+            let event_stream_result = lnd_node_.lock().await.stream_events().await;
 
-            let mut event_stream: Pin<Box<dyn Stream<Item = NodeSpecificEvent> + Send>> =
+            let mut event_stream: Pin<Box<dyn Stream<Item = CapturedEvent> + Send>> =
                 match event_stream_result {
                     Ok(stream) => stream,
                     Err(e) => {
@@ -121,9 +88,19 @@ impl EventCollector {
                             node_id_for_task,
                             e
                         );
+                        task.fail(format!("Failed to start event stream: {e:?}"));
+                        bus().publish_system_event(SystemEvent::StreamFailed {
+                            source,
+                            node_id: node_id_for_task.to_string(),
+                            error: format!("{e:?}"),
+                        });
                         return;
                     }
                 };
+            bus().publish_system_event(SystemEvent::StreamStarted {
+                source,
+                node_id: node_id_for_task.to_string(),
+            });
 
             while let Some(event) = event_stream.next().await {
                 if sender.send(event).await.is_err() {
@@ -135,8 +112,69 @@ impl EventCollector {
                 }
             }
             tracing::info!("Event stream for node {} ended.", node_id_for_task);
+            task.finish();
+            bus().publish_system_event(SystemEvent::StreamEnded {
+                source,
+                node_id: node_id_for_task.to_string(),
+            });
         });
     }
+
+    /// Polls the node's channel list and reports spliced channels as CLN events,
+    /// until the collector's event stream ends.
+    pub fn start_splice_watch(
+        &self,
+        node_id: PublicKey,
+        node: Arc<Mutex<Box<dyn LightningClient + Send + Sync + 'static>>>,
+    ) {
+        let sender = self.raw_event_sender.clone();
+        let source = self.source;
+        let mut notices = bus().subscribe_system_events();
+
+        tokio::spawn(async move {
+            let mut tracker = ChannelTracker::new();
+            let mut interval = tokio::time::interval(SPLICE_POLL_INTERVAL);
+
+            loop {
+                interval.tick().await;
+                if stream_stopped(&mut notices, source) {
+                    tracing::info!("Splice watch for node {} stopped.", node_id);
+                    return;
+                }
+                let channels = match node.lock().await.list_channels().await {
+                    Ok(channels) => channels,
+                    Err(e) => {
+                        tracing::warn!("Failed to poll channels of node {}: {:?}", node_id, e);
+                        continue;
+                    }
+                };
+
+                for splice in tracker.observe(&channels) {
+                    let event = NodeSpecificEvent::CLN(splice.into()).into();
+                    if sender.send(event).await.is_err() {
+                        tracing::info!("Splice watch for node {} stopped.", node_id);
+                        return;
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Whether the stream of `source` has ended or failed, going by the notices
+/// received so far.
+fn stream_stopped(
+    notices: &mut tokio::sync::broadcast::Receiver<SystemEvent>,
+    source: SourceId,
+) -> bool {
+    loop {
+        match notices.try_recv() {
+            Ok(notice) if notice.stopped_source() == Some(source) => return true,
+            Ok(_) | Err(TryRecvError::Lagged(_)) => {}
+            Err(TryRecvError::Empty) => return false,
+            Err(TryRecvError::Closed) => return true,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -146,6 +184,7 @@ pub struct EventHandler {
     user_id: Option<String>,
     node_id: Option<String>,
     node_alias: Option<String>,
+    invoice_watcher: InvoiceExpiryWatcher,
 }
 
 impl EventHandler {
@@ -156,14 +195,15 @@ impl EventHandler {
             user_id: None,
             node_id: None,
             node_alias: None,
+            invoice_watcher: InvoiceExpiryWatcher::new(),
         }
     }
 
-    pub fn start_receiving(self, mut receiver: mpsc::Receiver<NodeSpecificEvent>) {
-        let handler = self.clone();
+    /// Handles the events a collector sends until it stops sending.
+    pub fn start_receiving(self, mut receiver: mpsc::Receiver<CapturedEvent>) {
         tokio::spawn(async move {
-            while let Some(raw_event) = receiver.recv().await {
-                handler.dispatch_event(raw_event).await;
+            while let Some(captured) = receiver.recv().await {
+                self.dispatch_event(captured).await;
             }
         });
     }
@@ -181,10 +221,152 @@ impl EventHandler {
             user_id: Some(user_id),
             node_id: Some(node_id),
             node_alias: Some(node_alias),
+            invoice_watcher: InvoiceExpiryWatcher::new(),
         }
     }
 
-    pub async fn dispatch_event(&self, raw_event: NodeSpecificEvent) {
+    pub async fn dispatch_event(&self, captured: CapturedEvent) {
+        self.track_invoice_expiry(&captured.event);
+        self.record_event(captured.event, captured.raw).await;
+    }
+
+    /// Watches the node's open invoices for expiry, so invoices created before
+    /// its collector started are reported too.
+    ///
+    /// Not every settlement arrives as an event (CLN reports none), so each
+    /// invoice is looked up again at its expiry and only reported if unpaid.
+    pub fn watch_open_invoices(
+        &self,
+        implementation: NodeImplementation,
+        node: Arc<Mutex<Box<dyn LightningClient + Send + Sync + 'static>>>,
+    ) {
+        // Expiry events are only worth scheduling if they can be recorded
+        if self.pool.is_none() {
+            return;
+        }
+
+        let handler = self.clone();
+        tokio::spawn(async move {
+            let invoices = match node.lock().await.list_invoices().await {
+                Ok(invoices) => invoices,
+                Err(e) => {
+                    tracing::warn!("Failed to list invoices to watch for expiry: {:?}", e);
+                    return;
+                }
+            };
+            for invoice in invoices {
+                if invoice.state == InvoiceStatus::Open {
+                    handler.watch_listed_invoice(implementation, node.clone(), invoice);
+                }
+            }
+        });
+    }
+
+    fn watch_listed_invoice(
+        &self,
+        implementation: NodeImplementation,
+        node: Arc<Mutex<Box<dyn LightningClient + Send + Sync + 'static>>>,
+        invoice: CustomInvoice,
+    ) {
+        let Some(expires_at) = invoice.expires_at.map(|expires_at| expires_at as i64) else {
+            return;
+        };
+        let Ok(hash) = hex::decode(&invoice.payment_hash) else {
+            return;
+        };
+        let Ok(payment_hash) = <[u8; 32]>::try_from(hash.as_slice()) else {
+            return;
+        };
+        let creation_date = invoice.creation_date.unwrap_or_default();
+        let expiry = invoice
+            .expiry
+            .map_or(expires_at - creation_date, |expiry| expiry as i64);
+        let expired = match implementation {
+            NodeImplementation::Lnd => NodeSpecificEvent::LND(LNDEvent::InvoiceExpired {
+                hash: hash.clone(),
+                value: invoice.value,
+                memo: invoice.memo,
+                creation_date,
+                expiry,
+                payment_request: invoice.payment_request,
+            }),
+            NodeImplementation::Cln => NodeSpecificEvent::CLN(CLNEvent::InvoiceExpired {
+                hash: hash.clone(),
+                value: invoice.value,
+                memo: invoice.memo,
+                creation_date,
+                expiry,
+                payment_request: invoice.payment_request,
+            }),
+        };
+
+        let handler = self.clone();
+        self.invoice_watcher.watch(hash, expires_at, async move {
+            let current = node
+                .lock()
+                .await
+                .get_invoice_details(&PaymentHash(payment_hash))
+                .await;
+            match current {
+                Ok(current)
+                    if matches!(
+                        current.state,
+                        InvoiceStatus::Settled | InvoiceStatus::Failed
+                    ) => {}
+                Ok(_) => handler.record_event(expired, None).await,
+                Err(e) => {
+                    tracing::warn!("Failed to look up expiring invoice: {:?}", e);
+                }
+            }
+        });
+    }
+
+    /// Starts or stops watching invoices for expiry as their state changes.
+    fn track_invoice_expiry(&self, raw_event: &NodeSpecificEvent) {
+        // Expiry events are only worth scheduling if they can be recorded
+        if self.pool.is_none() {
+            return;
+        }
+
+        match raw_event {
+            NodeSpecificEvent::LND(LNDEvent::InvoiceCreated {
+                hash,
+                value,
+                memo,
+                creation_date,
+                expiry,
+                payment_request,
+                ..
+            }) if *expiry > 0 => {
+                let handler = self.clone();
+                let expired = LNDEvent::InvoiceExpired {
+                    hash: hash.clone(),
+                    value: *value,
+                    memo: memo.clone(),
+                    creation_date: *creation_date,
+                    expiry: *expiry,
+                    payment_request: payment_request.clone(),
+                };
+                self.invoice_watcher
+                    .watch(hash.clone(), creation_date + expiry, async move {
+                        handler
+                            .record_event(NodeSpecificEvent::LND(expired), None)
+                            .await;
+                    });
+            }
+            NodeSpecificEvent::LND(LNDEvent::InvoiceSettled { hash, .. })
+            | NodeSpecificEvent::LND(LNDEvent::InvoiceAccepted { hash, .. }) => {
+                self.invoice_watcher.unwatch(hash);
+            }
+            NodeSpecificEvent::LND(LNDEvent::InvoiceCancelled { hash, .. }) => {
+                self.invoice_watcher
+                    .unwatch_before_expiry(hash, chrono::Utc::now().timestamp());
+            }
+            _ => {}
+        }
+    }
+
+    async fn record_event(&self, raw_event: NodeSpecificEvent, raw: Option<RawPayload>) {
         // Only process if we have database context
         if let (Some(pool), Some(account_id), Some(user_id), Some(node_id), Some(node_alias)) = (
             &self.pool,
@@ -197,12 +379,12 @@ impl EventHandler {
 
             if let Err(e) = event_service
                 .process_lightning_event(
-                    pool,
                     account_id.clone(),
                     user_id.clone(),
                     node_id.clone(),
                     node_alias.clone(),
                     &raw_event,
+                    raw,
                 )
                 .await
             {
@@ -218,3 +400,9 @@ impl EventHandler {
         }
     }
 }
+
+impl Default for EventHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
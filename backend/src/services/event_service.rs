@@ -1,19 +1,56 @@
 //! Event business logic service.
 
 use crate::database::models::{
-    CreateEvent, Event, EventFilters, EventResponse, EventSeverity, EventType,
+    CreateEvent, Event, EventCursor, EventFilters, EventHourlyAggregate, EventResponse,
+    EventSeverity, EventType, NodeLabel, PeerBlockAction, RawEventResponse,
 };
 use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::repositories::event_raw_repository::EventRawRepository;
 use crate::repositories::event_repository::EventRepository;
+use crate::repositories::node_label_repository::NodeLabelRepository;
 use crate::repositories::notification_repository::NotificationRepository;
+use crate::services::event_bus::bus;
+use crate::services::event_stats::EventStats;
+use crate::services::invoice_webhooks;
+use crate::services::node_group_service::NodeGroupService;
 use crate::services::notification_dispatcher::NotificationDispatcher;
-use chrono::Utc;
+use crate::services::payment_stats::StatsBucket;
+use crate::services::peer_blocklist::PeerBlocklistService;
+use crate::services::quota_service::QuotaService;
+use crate::services::raw_events::{self, RawPayload};
+use crate::services::settings_service::SettingsService;
+use crate::services::stats_rollups::StatsRollupService;
+use crate::utils::amount::Amount;
+use crate::utils::jwt::Claims;
+use crate::utils::mempool::mempool;
+use bitcoin::Txid;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 use serde_json;
 use serde_json::Value;
 use sqlx::SqlitePool;
 use std::collections::HashMap;
+use std::str::FromStr;
+use tonic_lnd::lnrpc::channel_close_summary::ClosureType;
 use uuid::Uuid;
 
+/// Adds the node label to an event's JSON data under `node_label`.
+fn attach_node_label(data: &str, label: &NodeLabel) -> String {
+    attach_field(data, "node_label", label.payload())
+}
+
+/// Adds a field to an event's JSON data, leaving data that isn't an object as is.
+fn attach_field(data: &str, key: &str, value: Value) -> String {
+    match serde_json::from_str::<Value>(data) {
+        Ok(Value::Object(mut fields)) => {
+            fields.insert(key.to_string(), value);
+            Value::Object(fields).to_string()
+        }
+        _ => data.to_string(),
+    }
+}
+
 /// Service layer for event operations.
 pub struct EventService<'a> {
     pool: &'a SqlitePool,
@@ -30,19 +67,76 @@ impl<'a> EventService<'a> {
     }
 
     /// Creates and dispatches a new event.
+    ///
+    /// Returns `None` when the account's peer blocklist suppresses it.
     pub async fn create_and_dispatch_event(
+        &self,
+        create_event: CreateEvent,
+    ) -> ServiceResult<Option<Event>> {
+        Ok(self
+            .create_and_dispatch_events(create_event, None)
+            .await?
+            .into_iter()
+            .next())
+    }
+
+    /// Creates an event, one copy per active notification endpoint, and
+    /// dispatches it. The node message it was built from is stored with every
+    /// copy when the account captures raw events.
+    ///
+    /// Events of peers on the account's blocklist aren't stored when it
+    /// suppresses them, and are stored once as Info without notifications when
+    /// it downgrades them.
+    async fn create_and_dispatch_events(
         &self,
         mut create_event: CreateEvent,
-    ) -> ServiceResult<Event> {
+        raw: Option<RawPayload>,
+    ) -> ServiceResult<Vec<Event>> {
         let event_repo = EventRepository::new(self.pool);
         let notification_repo = NotificationRepository::new(self.pool);
 
+        let block_action = PeerBlocklistService::new(self.pool)
+            .event_action(&create_event)
+            .await;
+        if block_action == Some(PeerBlockAction::Suppress) {
+            return Ok(Vec::new());
+        }
+        let notify = block_action.is_none();
+        if !notify {
+            create_event.severity = EventSeverity::Info;
+        }
+
+        // Receivers get the node's label to tell apart alerts from similar nodes
+        match NodeLabelRepository::new(self.pool)
+            .get_label(&create_event.account_id, &create_event.node_id)
+            .await
+        {
+            Ok(Some(label)) => create_event.data = attach_node_label(&create_event.data, &label),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to load node label for event: {}", e),
+        }
+        match CredentialRepository::new(self.pool)
+            .get_node_network(&create_event.account_id, &create_event.node_id)
+            .await
+        {
+            Ok(Some(network)) => {
+                create_event.data =
+                    attach_field(&create_event.data, "network", Value::String(network))
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to load node network for event: {}", e),
+        }
+
         // Get all active notifications for this account
         let notifications = notification_repo
             .get_notifications_by_account_id(&create_event.account_id)
             .await?;
 
-        let active_notifications: Vec<_> = notifications.iter().filter(|n| n.is_active).collect();
+        // Downgraded events are stored once, without an endpoint
+        let active_notifications: Vec<_> = notifications
+            .iter()
+            .filter(|n| n.is_active && notify)
+            .collect();
 
         let mut created_events = Vec::new();
 
@@ -58,24 +152,86 @@ impl<'a> EventService<'a> {
         // If no notifications, create event without notification_id
         if active_notifications.is_empty() {
             create_event.notifications_id = None;
-            let event = event_repo.create_event(create_event).await?;
+            let event = event_repo.create_event(create_event.clone()).await?;
             created_events.push(event);
         }
 
+        // Accounts with an event quota keep only their newest events
+        if let Err(e) = QuotaService::new(self.pool)
+            .enforce_event_quota(&create_event.account_id)
+            .await
+        {
+            tracing::warn!("Failed to enforce event quota: {}", e);
+        }
+
+        if let Some(raw) = raw {
+            self.capture_raw_event(&create_event.account_id, &created_events, &raw)
+                .await;
+        }
+
+        // The copies only differ by endpoint, so webhooks and subscribers get the first one
+        if let Some(event) = created_events.first() {
+            if let Err(e) = invoice_webhooks::enqueue_settled_invoice(self.pool, event).await {
+                tracing::error!("Failed to queue invoice webhook: {}", e);
+            }
+            bus().publish_alert(event.clone());
+        }
+
         // Dispatch notifications for all created events
-        for event in &created_events {
+        for event in created_events.iter().filter(|_| notify) {
             if let Err(e) = self.dispatcher.dispatch_event(self.pool, event).await {
                 tracing::error!("Failed to dispatch event notifications: {}", e);
             }
         }
 
-        // Return the first event, or an error if none were created
-        created_events
-            .into_iter()
-            .next()
-            .ok_or_else(|| ServiceError::InternalError {
-                message: "No events were created".to_string(),
-            })
+        Ok(created_events)
+    }
+
+    /// Stores an event's node message if the account captures raw events.
+    ///
+    /// Capture is best effort; the event stands either way.
+    async fn capture_raw_event(&self, account_id: &str, events: &[Event], raw: &RawPayload) {
+        let enabled = match SettingsService::new(self.pool)
+            .get_settings(account_id)
+            .await
+        {
+            Ok(settings) => settings.capture_raw_events,
+            Err(e) => {
+                tracing::warn!("Failed to load settings for raw event capture: {}", e);
+                return;
+            }
+        };
+        if !enabled {
+            return;
+        }
+
+        let event_ids: Vec<String> = events.iter().map(|event| event.id.clone()).collect();
+        if let Err(e) = raw_events::store_raw_event(self.pool, &event_ids, raw).await {
+            tracing::warn!("Failed to store raw event: {}", e);
+        }
+    }
+
+    /// Retrieves the node message an event was built from.
+    pub async fn get_raw_event(
+        &self,
+        claims: &Claims,
+        event_id: &str,
+    ) -> ServiceResult<RawEventResponse> {
+        let scope = NodeGroupService::new(self.pool)
+            .node_scope_for_claims(claims)
+            .await?;
+
+        EventRepository::new(self.pool)
+            .get_event_by_id(&claims.account_id, event_id)
+            .await?
+            .filter(|event| scope.allows(&event.node_id))
+            .ok_or_else(|| ServiceError::not_found("Event", event_id))?;
+
+        let raw = EventRawRepository::new(self.pool)
+            .get_raw_event(event_id)
+            .await?
+            .ok_or_else(|| ServiceError::not_found("Raw event", event_id))?;
+        Ok(raw.into())
     }
 
     /// Retrieves events for an account with optional filters.
@@ -90,7 +246,7 @@ impl<'a> EventService<'a> {
 
         let event_responses: Vec<EventResponse> = events
             .into_iter()
-            .filter_map(|event| {
+            .map(|event| {
                 // Parse JSON data
                 let data = match serde_json::from_str::<Value>(&event.data) {
                     Ok(data) => data,
@@ -100,7 +256,7 @@ impl<'a> EventService<'a> {
                     }
                 };
 
-                Some(EventResponse {
+                EventResponse {
                     id: event.id,
                     account_id: event.account_id,
                     user_id: event.user_id,
@@ -113,25 +269,156 @@ impl<'a> EventService<'a> {
                     notifications_id: event.notifications_id,
                     data,
                     timestamp: event.timestamp,
+                    acknowledged_by: event.acknowledged_by,
+                    acknowledged_at: event.acknowledged_at,
                     created_at: event.created_at,
-                })
+                }
             })
             .collect();
 
         Ok(event_responses)
     }
 
+    /// Retrieves one keyset page of events for an account, newest first.
+    ///
+    /// Returns the page together with the cursor of the next page, or `None` when
+    /// this page reaches the oldest event.
+    pub async fn get_events_page(
+        &self,
+        account_id: &str,
+        filters: EventFilters,
+        cursor: Option<EventCursor>,
+        limit: i64,
+    ) -> ServiceResult<(Vec<EventResponse>, Option<EventCursor>)> {
+        // Fetch one extra row to learn whether another page follows.
+        let filters = EventFilters {
+            limit: Some(limit + 1),
+            offset: None,
+            cursor,
+            ..filters
+        };
+        let mut events = self
+            .get_events_for_account(self.pool, account_id, Some(filters))
+            .await?;
+
+        let next_cursor = if events.len() as i64 > limit {
+            events.truncate(limit as usize);
+            events.last().map(EventCursor::from)
+        } else {
+            None
+        };
+
+        Ok((events, next_cursor))
+    }
+
+    /// Counts the events recorded for an account that match the node, type,
+    /// severity, acknowledgement and time filters.
+    pub async fn count_events_for_account(
+        &self,
+        account_id: &str,
+        filters: &EventFilters,
+    ) -> ServiceResult<u64> {
+        let repo = EventRepository::new(self.pool);
+        let count = repo.count_events_by_account_id(account_id, filters).await?;
+        Ok(count as u64)
+    }
+
+    /// Soft deletes the events of an account that match the same filters as
+    /// `count_events_for_account`, returning how many were deleted.
+    pub async fn delete_events(
+        &self,
+        account_id: &str,
+        filters: &EventFilters,
+    ) -> ServiceResult<u64> {
+        let deleted = EventRepository::new(self.pool)
+            .soft_delete_events(account_id, filters)
+            .await?;
+        Ok(deleted)
+    }
+
+    /// Counts an account's events between `start` and `end` by severity, in
+    /// buckets aligned to the time zone `tz`, from the hourly event rollups.
+    pub async fn get_event_stats(
+        &self,
+        account_id: &str,
+        node_ids: Option<&[String]>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        bucket: StatsBucket,
+        tz: Tz,
+    ) -> ServiceResult<EventStats> {
+        StatsRollupService::new(self.pool)
+            .get_event_stats(account_id, node_ids, start, end, bucket, tz)
+            .await
+    }
+
+    /// Retrieves the hourly aggregates of an account's compacted events
+    /// between `start` and `end`.
+    pub async fn get_hourly_aggregates(
+        &self,
+        account_id: &str,
+        node_ids: Option<&[String]>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> ServiceResult<Vec<EventHourlyAggregate>> {
+        let aggregates = EventRepository::new(self.pool)
+            .get_hourly_aggregates_between(account_id, node_ids, start, end)
+            .await?;
+        Ok(aggregates)
+    }
+
+    /// Acknowledges a warning or critical event on behalf of the caller.
+    ///
+    /// Acknowledging an event twice keeps the first acknowledgement.
+    pub async fn acknowledge_event(
+        &self,
+        claims: &Claims,
+        event_id: &str,
+    ) -> ServiceResult<EventResponse> {
+        let scope = NodeGroupService::new(self.pool)
+            .node_scope_for_claims(claims)
+            .await?;
+        let repo = EventRepository::new(self.pool);
+
+        let event = repo
+            .get_event_by_id(&claims.account_id, event_id)
+            .await?
+            .filter(|event| scope.allows(&event.node_id))
+            .ok_or_else(|| ServiceError::not_found("Event", event_id))?;
+        if !event.is_alert() {
+            return Err(ServiceError::invalid_operation(
+                "Only warning and critical events can be acknowledged",
+            ));
+        }
+
+        if event.acknowledged_at.is_some() {
+            return Ok(event.into());
+        }
+
+        // Acknowledging one endpoint's copy settles the alert, which also stops
+        // its escalation. A concurrent acknowledgement may win; the stored one
+        // is returned either way.
+        repo.acknowledge_event_copies(&event, &claims.sub).await?;
+        let event = repo
+            .get_event_by_id(&claims.account_id, event_id)
+            .await?
+            .ok_or_else(|| ServiceError::not_found("Event", event_id))?;
+        Ok(event.into())
+    }
+
     /// Processes a Lightning node event and creates a standardized event.
+    ///
+    /// Returns `None` when the account's peer blocklist suppresses it.
     pub async fn process_lightning_event(
         &self,
-        _pool: &SqlitePool,
         account_id: String,
         user_id: String,
         node_id: String,
         node_alias: String,
         lightning_event: &crate::services::event_manager::NodeSpecificEvent,
-    ) -> ServiceResult<Event> {
-        let (event_type, severity, title, description, data) = match lightning_event {
+        raw: Option<RawPayload>,
+    ) -> ServiceResult<Option<Event>> {
+        let (event_type, severity, title, description, mut data) = match lightning_event {
             crate::services::event_manager::NodeSpecificEvent::LND(lnd_event) => {
                 self.process_lnd_event(lnd_event)
             }
@@ -140,7 +427,19 @@ impl<'a> EventService<'a> {
             }
         };
 
-        self.create_and_dispatch_event(CreateEvent {
+        // On-chain context is best effort; the event is recorded either way
+        let onchain = match Self::onchain_transaction(lightning_event) {
+            Some((key, txid)) => mempool().tx_status(&txid).await.map(|status| (key, status)),
+            None => None,
+        };
+        if let Some((key, status)) = onchain {
+            data.insert(
+                key.to_string(),
+                serde_json::to_value(status).unwrap_or(Value::Null),
+            );
+        }
+
+        let create_event = CreateEvent {
             id: Uuid::now_v7().to_string(),
             account_id,
             user_id,
@@ -153,8 +452,37 @@ impl<'a> EventService<'a> {
             data: serde_json::to_string(&data).unwrap_or_else(|_| "{}".to_string()),
             notifications_id: None,
             timestamp: Utc::now(),
-        })
-        .await
+        };
+
+        Ok(self
+            .create_and_dispatch_events(create_event, raw)
+            .await?
+            .into_iter()
+            .next())
+    }
+
+    /// The transaction an event is about, keyed by the data field its on-chain
+    /// status is stored under.
+    fn onchain_transaction(
+        lightning_event: &crate::services::event_manager::NodeSpecificEvent,
+    ) -> Option<(&'static str, Txid)> {
+        use crate::services::event_manager::{CLNEvent, LNDEvent, NodeSpecificEvent};
+
+        let (key, txid) = match lightning_event {
+            NodeSpecificEvent::LND(LNDEvent::ChannelOpened { channel_point, .. }) => {
+                ("funding_tx", channel_point.split(':').next()?)
+            }
+            NodeSpecificEvent::LND(LNDEvent::ChannelClosed {
+                closing_tx_hash, ..
+            }) => ("closing_tx", closing_tx_hash.as_str()),
+            NodeSpecificEvent::CLN(CLNEvent::ChannelSpliced {
+                channel_point: Some(channel_point),
+                ..
+            }) => ("funding_tx", channel_point.split(':').next()?),
+            _ => return None,
+        };
+
+        Txid::from_str(txid).ok().map(|txid| (key, txid))
     }
 
     /// Processes LND-specific events.v4
@@ -227,12 +555,8 @@ impl<'a> EventService<'a> {
                 close_type,
                 open_initiator,
                 close_initiator,
-            } => (
-                EventType::ChannelClosed,
-                EventSeverity::Warning,
-                "Channel Closed".to_string(),
-                format!("Channel closed with {remote_pubkey}"),
-                HashMap::from([
+            } => {
+                let mut data = HashMap::from([
                     ("chan_id".to_string(), Value::Number((*chan_id).into())),
                     (
                         "remote_pubkey".to_string(),
@@ -272,34 +596,57 @@ impl<'a> EventService<'a> {
                         "close_initiator".to_string(),
                         Value::Number((*close_initiator).into()),
                     ),
-                ]),
-            ),
+                ]);
+                if *close_type == ClosureType::BreachClose as i32 {
+                    data.insert(
+                        "breach_txid".to_string(),
+                        Value::String(closing_tx_hash.clone()),
+                    );
+                    (
+                        EventType::ChannelBreachDetected,
+                        EventSeverity::Critical,
+                        "Channel Breach Detected".to_string(),
+                        format!(
+                            "{remote_pubkey} broadcast a revoked state of channel {chan_id} in {closing_tx_hash}"
+                        ),
+                        data,
+                    )
+                } else {
+                    (
+                        EventType::ChannelClosed,
+                        EventSeverity::Warning,
+                        "Channel Closed".to_string(),
+                        format!("Channel closed with {remote_pubkey}"),
+                        data,
+                    )
+                }
+            }
             crate::services::event_manager::LNDEvent::InvoiceCreated {
                 preimage,
                 hash,
-                value_msat,
+                value,
                 state,
                 memo,
                 creation_date,
+                expiry,
                 payment_request,
             } => (
                 EventType::InvoiceCreated,
                 EventSeverity::Info,
                 "Invoice Created".to_string(),
-                format!("New invoice created for {value_msat} msat"),
+                format!("New invoice created for {value}"),
                 HashMap::from([
                     ("preimage".to_string(), Value::String(hex::encode(preimage))),
                     ("hash".to_string(), Value::String(hex::encode(hash))),
-                    (
-                        "value_msat".to_string(),
-                        Value::Number((*value_msat).into()),
-                    ),
+                    ("value_msat".to_string(), Value::Number(value.msat().into())),
+                    ("value_sat".to_string(), Value::Number(value.sat().into())),
                     ("state".to_string(), Value::Number((*state).into())),
                     ("memo".to_string(), Value::String(memo.clone())),
                     (
                         "creation_date".to_string(),
                         Value::Number((*creation_date).into()),
                     ),
+                    ("expiry".to_string(), Value::Number((*expiry).into())),
                     (
                         "payment_request".to_string(),
                         Value::String(payment_request.clone()),
@@ -309,7 +656,7 @@ impl<'a> EventService<'a> {
             crate::services::event_manager::LNDEvent::InvoiceSettled {
                 preimage,
                 hash,
-                value_msat,
+                value,
                 state,
                 memo,
                 creation_date,
@@ -318,14 +665,12 @@ impl<'a> EventService<'a> {
                 EventType::InvoiceSettled,
                 EventSeverity::Info,
                 "Invoice Settled".to_string(),
-                format!("Invoice settled for {value_msat} msat"),
+                format!("Invoice settled for {value}"),
                 HashMap::from([
                     ("preimage".to_string(), Value::String(hex::encode(preimage))),
                     ("hash".to_string(), Value::String(hex::encode(hash))),
-                    (
-                        "value_msat".to_string(),
-                        Value::Number((*value_msat).into()),
-                    ),
+                    ("value_msat".to_string(), Value::Number(value.msat().into())),
+                    ("value_sat".to_string(), Value::Number(value.sat().into())),
                     ("state".to_string(), Value::Number((*state).into())),
                     ("memo".to_string(), Value::String(memo.clone())),
                     (
@@ -341,7 +686,7 @@ impl<'a> EventService<'a> {
             crate::services::event_manager::LNDEvent::InvoiceCancelled {
                 preimage,
                 hash,
-                value_msat,
+                value,
                 state,
                 memo,
                 creation_date,
@@ -350,14 +695,12 @@ impl<'a> EventService<'a> {
                 EventType::InvoiceCancelled,
                 EventSeverity::Warning,
                 "Invoice Cancelled".to_string(),
-                format!("Invoice cancelled for {value_msat} msat"),
+                format!("Invoice cancelled for {value}"),
                 HashMap::from([
                     ("preimage".to_string(), Value::String(hex::encode(preimage))),
                     ("hash".to_string(), Value::String(hex::encode(hash))),
-                    (
-                        "value_msat".to_string(),
-                        Value::Number((*value_msat).into()),
-                    ),
+                    ("value_msat".to_string(), Value::Number(value.msat().into())),
+                    ("value_sat".to_string(), Value::Number(value.sat().into())),
                     ("state".to_string(), Value::Number((*state).into())),
                     ("memo".to_string(), Value::String(memo.clone())),
                     (
@@ -373,7 +716,7 @@ impl<'a> EventService<'a> {
             crate::services::event_manager::LNDEvent::InvoiceAccepted {
                 preimage,
                 hash,
-                value_msat,
+                value,
                 state,
                 memo,
                 creation_date,
@@ -382,14 +725,12 @@ impl<'a> EventService<'a> {
                 EventType::InvoiceAccepted,
                 EventSeverity::Info,
                 "Invoice Accepted".to_string(),
-                format!("Invoice accepted for {value_msat} msat"),
+                format!("Invoice accepted for {value}"),
                 HashMap::from([
                     ("preimage".to_string(), Value::String(hex::encode(preimage))),
                     ("hash".to_string(), Value::String(hex::encode(hash))),
-                    (
-                        "value_msat".to_string(),
-                        Value::Number((*value_msat).into()),
-                    ),
+                    ("value_msat".to_string(), Value::Number(value.msat().into())),
+                    ("value_sat".to_string(), Value::Number(value.sat().into())),
                     ("state".to_string(), Value::Number((*state).into())),
                     ("memo".to_string(), Value::String(memo.clone())),
                     (
@@ -402,9 +743,60 @@ impl<'a> EventService<'a> {
                     ),
                 ]),
             ),
+            crate::services::event_manager::LNDEvent::InvoiceExpired {
+                hash,
+                value,
+                memo,
+                creation_date,
+                expiry,
+                payment_request,
+            } => Self::invoice_expired(hash, value, memo, *creation_date, *expiry, payment_request),
         }
     }
 
+    /// Builds the event for an invoice that reached its expiry unpaid, which
+    /// the expiry watcher raises the same way for every implementation.
+    fn invoice_expired(
+        hash: &[u8],
+        value: &Amount,
+        memo: &str,
+        creation_date: i64,
+        expiry: i64,
+        payment_request: &str,
+    ) -> (
+        EventType,
+        EventSeverity,
+        String,
+        String,
+        HashMap<String, Value>,
+    ) {
+        (
+            EventType::InvoiceExpired,
+            EventSeverity::Warning,
+            "Invoice Expired".to_string(),
+            if memo.is_empty() {
+                format!("Invoice for {value} expired unpaid")
+            } else {
+                format!("Invoice \"{memo}\" for {value} expired unpaid")
+            },
+            HashMap::from([
+                ("hash".to_string(), Value::String(hex::encode(hash))),
+                ("value_msat".to_string(), Value::Number(value.msat().into())),
+                ("value_sat".to_string(), Value::Number(value.sat().into())),
+                ("memo".to_string(), Value::String(memo.to_string())),
+                (
+                    "creation_date".to_string(),
+                    Value::Number(creation_date.into()),
+                ),
+                ("expiry".to_string(), Value::Number(expiry.into())),
+                (
+                    "payment_request".to_string(),
+                    Value::String(payment_request.to_string()),
+                ),
+            ]),
+        )
+    }
+
     /// Processes CLN-specific events.
     fn process_cln_event(
         &self,
@@ -424,6 +816,56 @@ impl<'a> EventService<'a> {
                 "New channel opened".to_string(),
                 HashMap::new(),
             ),
+            crate::services::event_manager::CLNEvent::ChannelSpliced {
+                remote_pubkey,
+                previous_chan_id,
+                chan_id,
+                previous_channel_point,
+                channel_point,
+                previous_capacity,
+                capacity,
+            } => (
+                EventType::ChannelSpliced,
+                EventSeverity::Info,
+                "Channel Spliced".to_string(),
+                format!(
+                    "Channel with {remote_pubkey} resized from {previous_capacity} to {capacity} sat"
+                ),
+                HashMap::from([
+                    (
+                        "previous_channel_id".to_string(),
+                        Value::Number((*previous_chan_id).into()),
+                    ),
+                    ("channel_id".to_string(), Value::Number((*chan_id).into())),
+                    (
+                        "counterparty_node_id".to_string(),
+                        Value::String(remote_pubkey.clone()),
+                    ),
+                    (
+                        "previous_channel_point".to_string(),
+                        previous_channel_point
+                            .clone()
+                            .map_or(Value::Null, Value::String),
+                    ),
+                    (
+                        "channel_point".to_string(),
+                        channel_point.clone().map_or(Value::Null, Value::String),
+                    ),
+                    (
+                        "previous_capacity".to_string(),
+                        Value::Number((*previous_capacity).into()),
+                    ),
+                    ("capacity".to_string(), Value::Number((*capacity).into())),
+                ]),
+            ),
+            crate::services::event_manager::CLNEvent::InvoiceExpired {
+                hash,
+                value,
+                memo,
+                creation_date,
+                expiry,
+                payment_request,
+            } => Self::invoice_expired(hash, value, memo, *creation_date, *expiry, payment_request),
             // crate::services::event_manager::CLNEvent::ChannelClosed {} => (
             //     EventType::ChannelClosed,
             //     EventSeverity::Warning,
@@ -462,3 +904,32 @@ impl<'a> EventService<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::NodeEnvironment;
+
+    #[test]
+    fn embeds_node_label_in_event_data() {
+        let label = NodeLabel {
+            account_id: "account".to_string(),
+            node_id: "node".to_string(),
+            display_name: Some("Routing node".to_string()),
+            color: Some("#f7931a".to_string()),
+            environment: Some(NodeEnvironment::Prod),
+            notes: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let data: Value =
+            serde_json::from_str(&attach_node_label(r#"{"chan_id":1}"#, &label)).unwrap();
+        assert_eq!(data["chan_id"], 1);
+        assert_eq!(data["node_label"]["display_name"], "Routing node");
+        assert_eq!(data["node_label"]["environment"], "prod");
+
+        // Data that is not an object is left alone
+        assert_eq!(attach_node_label("[]", &label), "[]");
+    }
+}
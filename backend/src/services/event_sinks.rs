@@ -0,0 +1,425 @@
+//! Forwards persisted events to external log stores.
+//!
+//! Sinks are configured globally with `EVENT_SINKS` and per account with the
+//! `event_sinks` setting. Persisted events are taken from the alert topic of the
+//! event bus, queued on a bounded channel and shipped in batches by a single
+//! worker. While a sink is retried the queue fills up, and
+//! once it is full new events are dropped instead of holding up event handling.
+
+use crate::database::models::{
+    Event, EventResponse, EventSeverity, EventSinkConfig, EventSinkKind,
+};
+use crate::services::event_bus::bus;
+use crate::services::settings_service::SettingsService;
+use anyhow::{Context, Result, anyhow, bail};
+use reqwest::{Client, Url};
+use serde_json::{Value, json};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpStream, UdpSocket, lookup_host};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::{self, Receiver, Sender, error::TrySendError};
+use tokio::time::Instant;
+
+/// Events waiting to be shipped before new ones are dropped.
+const QUEUE_CAPACITY: usize = 10_000;
+/// Largest number of events sent to a sink in one request.
+const BATCH_SIZE: usize = 500;
+/// How long a partial batch waits for more events.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_ATTEMPTS: u32 = 5;
+/// Delay before the first retry, doubled for each further one.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+const DEFAULT_SYSLOG_PORT: u16 = 514;
+/// Syslog facility `local0`.
+const SYSLOG_FACILITY: u8 = 16;
+
+static STARTED: AtomicBool = AtomicBool::new(false);
+static DROPPED_EVENTS: AtomicU64 = AtomicU64::new(0);
+
+/// Queues a persisted event for the configured sinks.
+fn forward(queue: &Sender<Event>, event: Event) {
+    if let Err(TrySendError::Full(_)) = queue.try_send(event) {
+        count_dropped(1);
+    }
+}
+
+fn count_dropped(events: u64) {
+    let before = DROPPED_EVENTS.fetch_add(events, Ordering::Relaxed);
+    let dropped = before + events;
+    // Log at growing intervals so a stuck sink doesn't flood the log
+    if before.leading_zeros() != dropped.leading_zeros() {
+        tracing::warn!(
+            "Event sink queue is full, {} events dropped so far",
+            dropped
+        );
+    }
+}
+
+/// Starts the worker shipping persisted events to their sinks.
+pub fn spawn_event_sinks(pool: SqlitePool, global_sinks: Vec<EventSinkConfig>) {
+    if STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+
+    let mut alerts = bus().subscribe_alerts();
+    tokio::spawn(async move {
+        loop {
+            match alerts.recv().await {
+                Ok(event) => forward(&sender, event),
+                Err(RecvError::Lagged(skipped)) => count_dropped(skipped),
+                Err(RecvError::Closed) => return,
+            }
+        }
+    });
+    tokio::spawn(run(pool, global_sinks, receiver));
+}
+
+async fn run(pool: SqlitePool, global_sinks: Vec<EventSinkConfig>, mut receiver: Receiver<Event>) {
+    let client = Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .expect("Failed to create HTTP client");
+
+    while let Some(event) = receiver.recv().await {
+        let mut batch = vec![event];
+        let deadline = Instant::now() + FLUSH_INTERVAL;
+        while batch.len() < BATCH_SIZE {
+            match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                Ok(Some(event)) => batch.push(event),
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        let routes = route_events(&pool, &global_sinks, &batch).await;
+        for (sink, events) in routes {
+            send_with_retry(&client, &sink, &events).await;
+        }
+    }
+}
+
+/// Groups a batch by the sinks each event goes to.
+async fn route_events<'e>(
+    pool: &SqlitePool,
+    global_sinks: &[EventSinkConfig],
+    batch: &'e [Event],
+) -> HashMap<EventSinkConfig, Vec<&'e Event>> {
+    let settings = SettingsService::new(pool);
+    let mut account_sinks: HashMap<&str, Vec<EventSinkConfig>> = HashMap::new();
+    let mut routes: HashMap<EventSinkConfig, Vec<&Event>> = HashMap::new();
+
+    for event in batch {
+        if !account_sinks.contains_key(event.account_id.as_str()) {
+            let sinks = match settings.get_settings(&event.account_id).await {
+                Ok(settings) => settings.event_sinks,
+                Err(e) => {
+                    tracing::warn!(
+                        "Failed to load event sinks of account {}: {}",
+                        event.account_id,
+                        e
+                    );
+                    Vec::new()
+                }
+            };
+            account_sinks.insert(event.account_id.as_str(), sinks);
+        }
+
+        let mut sinks: Vec<&EventSinkConfig> = Vec::new();
+        for sink in global_sinks
+            .iter()
+            .chain(&account_sinks[event.account_id.as_str()])
+        {
+            // An account may list a sink that is also configured globally
+            if !sinks.contains(&sink) {
+                sinks.push(sink);
+            }
+        }
+        for sink in sinks {
+            routes.entry(sink.clone()).or_default().push(event);
+        }
+    }
+
+    routes
+}
+
+async fn send_with_retry(client: &Client, sink: &EventSinkConfig, events: &[&Event]) {
+    let mut delay = RETRY_DELAY;
+    for attempt in 1..=MAX_ATTEMPTS {
+        let result = match sink.kind {
+            EventSinkKind::Loki => send_loki(client, &sink.url, events).await,
+            EventSinkKind::Elasticsearch => send_elasticsearch(client, &sink.url, events).await,
+            EventSinkKind::Syslog => send_syslog(&sink.url, events).await,
+        };
+
+        match result {
+            Ok(()) => return,
+            Err(e) if attempt < MAX_ATTEMPTS => {
+                tracing::warn!(
+                    "Event sink {} failed (attempt {}/{}): {:#}",
+                    sink_name(sink),
+                    attempt,
+                    MAX_ATTEMPTS,
+                    e
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            Err(e) => tracing::error!(
+                "Dropping {} events for event sink {}: {:#}",
+                events.len(),
+                sink_name(sink),
+                e
+            ),
+        }
+    }
+}
+
+/// Names a sink in logs without the credentials its URL may carry.
+fn sink_name(sink: &EventSinkConfig) -> String {
+    match Url::parse(&sink.url) {
+        Ok(mut url) => {
+            let _ = url.set_username("");
+            let _ = url.set_password(None);
+            url.to_string()
+        }
+        Err(_) => sink.url.clone(),
+    }
+}
+
+/// The event as shipped to sinks, shaped like the events API returns it.
+fn event_document(event: &Event) -> Value {
+    serde_json::to_value(EventResponse::from(event.clone())).unwrap_or_default()
+}
+
+/// Builds a Loki push request, one stream per account, node, type and severity.
+fn loki_payload(events: &[&Event]) -> Value {
+    let mut streams: HashMap<(&str, &str, String, String), Vec<Value>> = HashMap::new();
+    for event in events {
+        let labels = (
+            event.account_id.as_str(),
+            event.node_id.as_str(),
+            event.event_type.to_string(),
+            event.severity.to_string(),
+        );
+        let nanos = event.timestamp.timestamp_nanos_opt().unwrap_or_default();
+        streams.entry(labels).or_default().push(json!([
+            nanos.to_string(),
+            event_document(event).to_string()
+        ]));
+    }
+
+    let streams: Vec<Value> = streams
+        .into_iter()
+        .map(|((account_id, node_id, event_type, severity), values)| {
+            json!({
+                "stream": {
+                    "service": "nodegaze",
+                    "account_id": account_id,
+                    "node_id": node_id,
+                    "event_type": event_type,
+                    "severity": severity,
+                },
+                "values": values,
+            })
+        })
+        .collect();
+
+    json!({ "streams": streams })
+}
+
+async fn send_loki(client: &Client, url: &str, events: &[&Event]) -> Result<()> {
+    let response = client
+        .post(url)
+        .header("User-Agent", "NodeGaze/1.0")
+        .json(&loki_payload(events))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        bail!("Loki responded with status {}", response.status());
+    }
+    Ok(())
+}
+
+/// Builds an Elasticsearch bulk request body.
+///
+/// Documents are indexed under the event ID, so a retried batch doesn't
+/// duplicate events that were stored before the failure.
+fn elasticsearch_bulk_body(events: &[&Event]) -> String {
+    let mut body = String::new();
+    for event in events {
+        body.push_str(&json!({ "index": { "_id": event.id } }).to_string());
+        body.push('\n');
+        body.push_str(&event_document(event).to_string());
+        body.push('\n');
+    }
+    body
+}
+
+async fn send_elasticsearch(client: &Client, index_url: &str, events: &[&Event]) -> Result<()> {
+    let url = format!("{}/_bulk", index_url.trim_end_matches('/'));
+    let response = client
+        .post(url)
+        .header("Content-Type", "application/x-ndjson")
+        .header("User-Agent", "NodeGaze/1.0")
+        .body(elasticsearch_bulk_body(events))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        bail!("Elasticsearch responded with status {}", response.status());
+    }
+    // The bulk API answers 200 even when some documents were rejected
+    let result: Value = response.json().await?;
+    if result["errors"].as_bool() == Some(true) {
+        bail!("Elasticsearch rejected some of the events");
+    }
+    Ok(())
+}
+
+/// Formats an event as an RFC 5424 syslog message with a JSON body.
+fn syslog_message(event: &Event) -> String {
+    let severity = match event.severity {
+        EventSeverity::Critical => 2,
+        EventSeverity::Warning => 4,
+        EventSeverity::Info => 6,
+    };
+
+    format!(
+        "<{}>1 {} - nodegaze - {} - {}",
+        SYSLOG_FACILITY * 8 + severity,
+        event.timestamp.to_rfc3339(),
+        event.event_type,
+        event_document(event)
+    )
+}
+
+async fn send_syslog(url: &str, events: &[&Event]) -> Result<()> {
+    let url = Url::parse(url)?;
+    let host = url.host_str().context("Syslog URL has no host")?;
+    let address = lookup_host((host, url.port().unwrap_or(DEFAULT_SYSLOG_PORT)))
+        .await?
+        .next()
+        .ok_or_else(|| anyhow!("Could not resolve {host}"))?;
+
+    match url.scheme() {
+        "udp" => {
+            let local = if address.is_ipv4() {
+                "0.0.0.0:0"
+            } else {
+                "[::]:0"
+            };
+            let socket = UdpSocket::bind(local).await?;
+            socket.connect(address).await?;
+            for event in events {
+                socket.send(syslog_message(event).as_bytes()).await?;
+            }
+        }
+        "tcp" => {
+            let mut stream = TcpStream::connect(address).await?;
+            for event in events {
+                // Octet-counting framing from RFC 6587
+                let message = syslog_message(event);
+                stream
+                    .write_all(format!("{} {}", message.len(), message).as_bytes())
+                    .await?;
+            }
+            stream.flush().await?;
+        }
+        scheme => bail!("Unsupported syslog scheme {scheme}"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::EventType;
+    use chrono::{TimeZone, Utc};
+
+    fn event(id: &str, severity: EventSeverity) -> Event {
+        let timestamp = Utc.with_ymd_and_hms(2025, 8, 14, 9, 0, 0).unwrap();
+        Event {
+            id: id.to_string(),
+            account_id: "account".to_string(),
+            user_id: "user".to_string(),
+            node_id: "node".to_string(),
+            node_alias: "alias".to_string(),
+            event_type: EventType::ChannelClosed,
+            severity,
+            title: "Channel Closed".to_string(),
+            description: "Channel closed with peer".to_string(),
+            data: json!({ "chan_id": 42 }).to_string(),
+            notifications_id: None,
+            timestamp,
+            acknowledged_by: None,
+            acknowledged_at: None,
+            created_at: timestamp,
+            updated_at: timestamp,
+            is_deleted: false,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn formats_events_for_each_sink() {
+        let first = event("first", EventSeverity::Warning);
+        let second = event("second", EventSeverity::Warning);
+        let events = [&first, &second];
+
+        let payload = loki_payload(&events);
+        let streams = payload["streams"].as_array().unwrap();
+        assert_eq!(streams.len(), 1);
+        assert_eq!(streams[0]["stream"]["event_type"], "channel_closed");
+        assert_eq!(streams[0]["values"][0][0], "1755162000000000000");
+
+        let body = elasticsearch_bulk_body(&events);
+        let lines: Vec<&str> = body.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[2], r#"{"index":{"_id":"second"}}"#);
+        let document: Value = serde_json::from_str(lines[3]).unwrap();
+        assert_eq!(document["data"]["chan_id"], 42);
+
+        assert!(
+            syslog_message(&first)
+                .starts_with("<132>1 2025-08-14T09:00:00+00:00 - nodegaze - channel_closed - {")
+        );
+    }
+
+    #[test]
+    fn parses_and_validates_sink_configs() {
+        let sink: EventSinkConfig = "loki=http://loki:3100/loki/api/v1/push".parse().unwrap();
+        assert_eq!(sink.kind, EventSinkKind::Loki);
+
+        assert!(
+            "syslog=udp://127.0.0.1:514"
+                .parse::<EventSinkConfig>()
+                .is_ok()
+        );
+        assert!(
+            "syslog=http://127.0.0.1:514"
+                .parse::<EventSinkConfig>()
+                .is_err()
+        );
+        assert!(
+            "elasticsearch=udp://es:9200"
+                .parse::<EventSinkConfig>()
+                .is_err()
+        );
+        assert!(
+            "splunk=http://splunk:8088"
+                .parse::<EventSinkConfig>()
+                .is_err()
+        );
+        assert!("http://loki:3100".parse::<EventSinkConfig>().is_err());
+
+        let sink: EventSinkConfig = "elasticsearch=https://user:secret@es:9200/events"
+            .parse()
+            .unwrap();
+        assert_eq!(sink_name(&sink), "https://es:9200/events");
+    }
+}
@@ -0,0 +1,253 @@
+//! External event source business logic service.
+//!
+//! Admins register the systems allowed to push events into their account, such
+//! as a bitcoind watcher, and hand each one its own ingest token. Ingested
+//! events are stored as `External` events and go through the same notification
+//! and event sink pipeline as the ones collected from nodes.
+
+use crate::database::models::{
+    CreateEvent, CreateEventSourceRequest, Event, EventSeverity, EventSource, EventType,
+    IngestEventRequest,
+};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::repositories::event_source_repository::EventSourceRepository;
+use crate::services::event_service::EventService;
+use crate::utils::generate_random_string::generate_random_string;
+use crate::utils::jwt::Claims;
+use bitcoin::hashes::{Hash, sha256};
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::{Value, json};
+use sqlx::SqlitePool;
+use std::str::FromStr;
+use uuid::Uuid;
+use validator::Validate;
+
+/// Prefix that makes ingest tokens recognisable, e.g. in secret scanners.
+const TOKEN_PREFIX: &str = "ngz_";
+const TOKEN_LENGTH: usize = 40;
+/// Node id recorded for events that concern no particular node.
+const EXTERNAL_NODE_ID: &str = "external";
+
+/// A newly created source with its token, which is only shown once.
+#[derive(Debug, Serialize)]
+pub struct CreatedEventSource {
+    #[serde(flatten)]
+    pub source: EventSource,
+    pub token: String,
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(sha256::Hash::hash(token.as_bytes()).as_byte_array())
+}
+
+/// Service layer for external event sources.
+pub struct EventSourceService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> EventSourceService<'a> {
+    /// Creates a new EventSourceService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Registers a source and issues its ingest token.
+    pub async fn create_source(
+        &self,
+        claims: &Claims,
+        request: CreateEventSourceRequest,
+    ) -> ServiceResult<CreatedEventSource> {
+        request
+            .validate()
+            .map_err(|e| ServiceError::validation(e.to_string()))?;
+        if let Some(node_id) = &request.node_id {
+            self.ensure_account_node(&claims.account_id, node_id)
+                .await?;
+        }
+
+        let token = format!("{TOKEN_PREFIX}{}", generate_random_string(TOKEN_LENGTH));
+        let source = EventSource {
+            id: Uuid::now_v7().to_string(),
+            account_id: claims.account_id.clone(),
+            name: request.name.trim().to_string(),
+            node_id: request.node_id,
+            created_by: claims.sub.clone(),
+            token_hash: hash_token(&token),
+            last_used_at: None,
+            created_at: Utc::now(),
+        };
+        EventSourceRepository::new(self.pool)
+            .create_source(&source)
+            .await?;
+
+        Ok(CreatedEventSource { source, token })
+    }
+
+    /// Lists the account's sources.
+    pub async fn list_sources(&self, account_id: &str) -> ServiceResult<Vec<EventSource>> {
+        Ok(EventSourceRepository::new(self.pool)
+            .get_sources_by_account_id(account_id)
+            .await?)
+    }
+
+    /// Deletes a source, revoking its token.
+    pub async fn delete_source(&self, account_id: &str, id: &str) -> ServiceResult<()> {
+        if !EventSourceRepository::new(self.pool)
+            .delete_source(account_id, id)
+            .await?
+        {
+            return Err(ServiceError::not_found("Event source", id));
+        }
+        Ok(())
+    }
+
+    /// Finds the source an ingest token was issued to.
+    pub async fn authenticate(&self, token: &str) -> ServiceResult<Option<EventSource>> {
+        if !token.starts_with(TOKEN_PREFIX) {
+            return Ok(None);
+        }
+        Ok(EventSourceRepository::new(self.pool)
+            .get_source_by_token_hash(&hash_token(token))
+            .await?)
+    }
+
+    /// Validates an event pushed by a source and dispatches it like any other event.
+    pub async fn ingest(
+        &self,
+        source: &EventSource,
+        request: IngestEventRequest,
+    ) -> ServiceResult<Event> {
+        request
+            .validate()
+            .map_err(|e| ServiceError::validation(e.to_string()))?;
+        let severity = EventSeverity::from_str(&request.severity.to_lowercase())
+            .map_err(ServiceError::validation)?;
+
+        let node_id = request.node_id.or_else(|| source.node_id.clone());
+        let node_alias = match &node_id {
+            Some(node_id) => {
+                self.ensure_account_node(&source.account_id, node_id)
+                    .await?
+            }
+            None => source.name.clone(),
+        };
+
+        let mut data = request.data.unwrap_or_default();
+        data.insert(
+            "source".to_string(),
+            json!({ "id": source.id, "name": source.name }),
+        );
+        data.insert("external_type".to_string(), json!(request.event_type));
+
+        let now = Utc::now();
+        let event = EventService::new(self.pool)
+            .create_and_dispatch_event(CreateEvent {
+                id: Uuid::now_v7().to_string(),
+                account_id: source.account_id.clone(),
+                user_id: source.created_by.clone(),
+                node_id: node_id.unwrap_or_else(|| EXTERNAL_NODE_ID.to_string()),
+                node_alias,
+                event_type: EventType::External,
+                severity,
+                title: request.title,
+                description: request.description,
+                data: Value::Object(data).to_string(),
+                notifications_id: None,
+                timestamp: request.occurred_at.unwrap_or(now),
+            })
+            .await?
+            // External events are never blocklisted
+            .ok_or_else(|| ServiceError::InternalError {
+                message: "No events were created".to_string(),
+            })?;
+
+        if let Err(e) = EventSourceRepository::new(self.pool)
+            .touch_source(&source.id, now)
+            .await
+        {
+            tracing::warn!("Failed to record use of event source {}: {}", source.id, e);
+        }
+
+        Ok(event)
+    }
+
+    /// Checks that a node belongs to the account, returning its alias.
+    async fn ensure_account_node(&self, account_id: &str, node_id: &str) -> ServiceResult<String> {
+        CredentialRepository::new(self.pool)
+            .get_node_credentials(Some(account_id), None)
+            .await?
+            .into_iter()
+            .find(|credential| credential.node_id == node_id)
+            .map(|credential| credential.node_alias)
+            .ok_or_else(|| ServiceError::not_found("Node", node_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::RoleAccessLevel;
+
+    #[tokio::test]
+    async fn authenticates_only_live_tokens() {
+        let pool = crate::database::test_pool().await;
+        sqlx::query(
+            r#"
+            INSERT INTO accounts (id, name) VALUES ('account', 'Account');
+            INSERT INTO users (id, account_id, username, password_hash, email, role_id)
+            VALUES ('admin', 'account', 'admin', '', 'admin@example.com',
+                    '01932f4e-8b2a-7a3c-9d5e-1f2a3b4c5d6e');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let claims = Claims {
+            sub: "admin".to_string(),
+            account_id: "account".to_string(),
+            role: "Admin".to_string(),
+            role_access_level: RoleAccessLevel::ReadWrite,
+            node_credentials: None,
+            exp: 0,
+            iat: 0,
+            sid: None,
+        };
+        let service = EventSourceService::new(&pool);
+
+        let created = service
+            .create_source(
+                &claims,
+                CreateEventSourceRequest {
+                    name: "bitcoind".to_string(),
+                    node_id: None,
+                },
+            )
+            .await
+            .unwrap();
+        let token = created.token;
+        assert!(token.starts_with(TOKEN_PREFIX));
+        assert_eq!(token.len(), TOKEN_PREFIX.len() + TOKEN_LENGTH);
+        // Only the SHA-256 of the token is stored
+        assert_eq!(created.source.token_hash, hash_token(&token));
+        assert_eq!(
+            hash_token("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        let source = service.authenticate(&token).await.unwrap().unwrap();
+        assert_eq!(source.id, created.source.id);
+
+        let unprefixed = &token[TOKEN_PREFIX.len()..];
+        assert!(service.authenticate(unprefixed).await.unwrap().is_none());
+        let wrong_prefix = format!("ngx_{unprefixed}");
+        assert!(service.authenticate(&wrong_prefix).await.unwrap().is_none());
+        let unknown = format!("{TOKEN_PREFIX}{}", "0".repeat(TOKEN_LENGTH));
+        assert!(service.authenticate(&unknown).await.unwrap().is_none());
+
+        service.delete_source("account", &source.id).await.unwrap();
+        assert!(service.authenticate(&token).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,182 @@
+//! Time-bucketed event counts by severity.
+//!
+//! Buckets are aligned to the request's time zone like the payment statistics,
+//! so a day bucket counts the alerts of one local day. Compacted events count
+//! in the bucket their hour starts in. The statistics endpoints read the
+//! hourly rollups of `services::stats_rollups` for the hours they cover.
+
+use crate::database::models::{EventHourlyAggregate, EventHourlyRollup, EventSeverity};
+use crate::services::payment_stats::StatsBucket;
+use crate::utils::time_zone::LocalBuckets;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+
+/// Number of events of each severity.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct SeverityCounts {
+    pub total: u64,
+    pub info: u64,
+    pub warning: u64,
+    pub critical: u64,
+}
+
+impl SeverityCounts {
+    fn add(&mut self, severity: &EventSeverity, count: u64) {
+        self.total += count;
+        match severity {
+            EventSeverity::Info => self.info += count,
+            EventSeverity::Warning => self.warning += count,
+            EventSeverity::Critical => self.critical += count,
+        }
+    }
+}
+
+/// Event counts for one bucket.
+#[derive(Debug, Clone, Serialize)]
+pub struct EventStatsBucket {
+    pub start: DateTime<Utc>,
+    #[serde(flatten)]
+    pub counts: SeverityCounts,
+}
+
+/// Response of `GET /api/events/stats`.
+#[derive(Debug, Serialize)]
+pub struct EventStats {
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub bucket: StatsBucket,
+    /// Time zone the buckets are aligned to
+    pub tz: String,
+    pub buckets: Vec<EventStatsBucket>,
+    pub totals: SeverityCounts,
+}
+
+/// Counts events between `start` and `end` per bucket, aligned in `tz`,
+/// including those compacted into `aggregates`.
+pub fn event_stats(
+    events: &[(DateTime<Utc>, EventSeverity)],
+    aggregates: &[EventHourlyAggregate],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    bucket: StatsBucket,
+    tz: Tz,
+) -> EventStats {
+    let counted = events
+        .iter()
+        .map(|(timestamp, severity)| (*timestamp, severity, 1))
+        .chain(aggregates.iter().map(|aggregate| {
+            (
+                aggregate.hour,
+                &aggregate.severity,
+                aggregate.event_count.max(0) as u64,
+            )
+        }));
+    count_in_buckets(counted, start, end, bucket, tz)
+}
+
+/// Counts the events of hourly rollups between `start` and `end` per bucket,
+/// aligned in `tz`.
+///
+/// A rollup only counts when its whole hour lies in the window and in one
+/// bucket, see `LocalBuckets::covers_hour`. `events` and `aggregates` hold
+/// those of the other hours, which count as in `event_stats`.
+pub fn rollup_event_stats(
+    rollups: &[EventHourlyRollup],
+    events: &[(DateTime<Utc>, EventSeverity)],
+    aggregates: &[EventHourlyAggregate],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    bucket: StatsBucket,
+    tz: Tz,
+) -> EventStats {
+    let local_buckets = LocalBuckets::new(start, end, bucket.seconds(), tz);
+    let mut stats = event_stats(events, aggregates, start, end, bucket, tz);
+
+    for rollup in rollups {
+        if !local_buckets.covers_hour(rollup.hour) {
+            continue;
+        }
+        if let Some(index) = local_buckets.index(rollup.hour) {
+            let count = rollup.event_count.max(0) as u64;
+            stats.buckets[index].counts.add(&rollup.severity, count);
+            stats.totals.add(&rollup.severity, count);
+        }
+    }
+
+    stats
+}
+
+fn count_in_buckets<'a>(
+    counted: impl Iterator<Item = (DateTime<Utc>, &'a EventSeverity, u64)>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    bucket: StatsBucket,
+    tz: Tz,
+) -> EventStats {
+    let local_buckets = LocalBuckets::new(start, end, bucket.seconds(), tz);
+    let mut buckets: Vec<EventStatsBucket> = local_buckets
+        .starts()
+        .into_iter()
+        .map(|start| EventStatsBucket {
+            start,
+            counts: SeverityCounts::default(),
+        })
+        .collect();
+    let mut totals = SeverityCounts::default();
+
+    for (timestamp, severity, count) in counted {
+        if let Some(index) = local_buckets.index(timestamp) {
+            buckets[index].counts.add(severity, count);
+            totals.add(severity, count);
+        }
+    }
+
+    EventStats {
+        window_start: start,
+        window_end: end,
+        bucket,
+        tz: tz.name().to_string(),
+        buckets,
+        totals,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn counts_events_per_local_day() {
+        let events = vec![
+            // 23:30 on 1 August in Berlin
+            (utc("2025-08-01T21:30:00Z"), EventSeverity::Critical),
+            // 00:30 on 2 August in Berlin, still 1 August in UTC
+            (utc("2025-08-01T22:30:00Z"), EventSeverity::Warning),
+            (utc("2025-08-02T10:00:00Z"), EventSeverity::Info),
+            (utc("2025-08-05T10:00:00Z"), EventSeverity::Info),
+        ];
+
+        let stats = event_stats(
+            &events,
+            &[],
+            utc("2025-07-31T22:00:00Z"),
+            utc("2025-08-02T21:59:59Z"),
+            StatsBucket::Day,
+            chrono_tz::Europe::Berlin,
+        );
+
+        assert_eq!(stats.buckets.len(), 2);
+        assert_eq!(stats.buckets[0].counts.critical, 1);
+        assert_eq!(stats.buckets[0].counts.total, 1);
+        assert_eq!(stats.buckets[1].start, utc("2025-08-01T22:00:00Z"));
+        assert_eq!(stats.buckets[1].counts.warning, 1);
+        assert_eq!(stats.buckets[1].counts.info, 1);
+        assert_eq!(stats.totals.total, 3);
+        assert_eq!(stats.tz, "Europe/Berlin");
+    }
+}
@@ -0,0 +1,258 @@
+//! External metadata for graph nodes from Amboss and 1ML.
+//!
+//! Enrichment is off unless `EXTERNAL_ENRICHMENT_ENABLED` is set, since every
+//! lookup tells the third party which node is being looked at. Amboss community
+//! tags and socials need `AMBOSS_API_KEY`; 1ML's public rankings need no key.
+//! Profiles are cached in the `external_node_profiles` table for
+//! `EXTERNAL_ENRICHMENT_TTL_SECONDS`, and a stale profile is served when both
+//! providers are unreachable.
+
+use crate::config;
+use crate::repositories::external_profile_repository::ExternalProfileRepository;
+use bitcoin::secp256k1::PublicKey;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sqlx::SqlitePool;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+const AMBOSS_API_URL: &str = "https://api.amboss.space/graphql";
+const ONE_ML_API_URL: &str = "https://1ml.com";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+const AMBOSS_NODE_QUERY: &str = r#"
+query NodeProfile($pubkey: String!) {
+  getNode(pubkey: $pubkey) {
+    tags { name }
+    socials { info { website twitter telegram } }
+  }
+}
+"#;
+
+static HTTP_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .unwrap_or_default()
+});
+
+/// Third-party metadata about a node.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalNodeProfile {
+    pub amboss: Option<AmbossProfile>,
+    pub one_ml: Option<OneMlProfile>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// Community tags and socials from Amboss.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AmbossProfile {
+    pub tags: Vec<String>,
+    pub website: Option<String>,
+    pub twitter: Option<String>,
+    pub telegram: Option<String>,
+}
+
+/// Network totals and rankings from 1ML.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OneMlProfile {
+    #[serde(alias = "capacity")]
+    pub capacity_sat: Option<u64>,
+    #[serde(alias = "channelcount")]
+    pub channel_count: Option<u32>,
+    #[serde(alias = "noderank")]
+    pub rank: Option<OneMlRank>,
+}
+
+/// Position of the node in 1ML's rankings, 1 being the top.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OneMlRank {
+    pub capacity: Option<u32>,
+    #[serde(alias = "channelcount")]
+    pub channel_count: Option<u32>,
+    pub age: Option<u32>,
+    pub growth: Option<u32>,
+    pub availability: Option<u32>,
+}
+
+/// Reads the tags and socials out of an Amboss `getNode` response.
+fn parse_amboss_profile(response: &Value) -> Option<AmbossProfile> {
+    let node = response.pointer("/data/getNode")?;
+    if node.is_null() {
+        return None;
+    }
+
+    let social = |name: &str| {
+        node.pointer(&format!("/socials/info/{name}"))
+            .and_then(Value::as_str)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+
+    Some(AmbossProfile {
+        tags: node
+            .get("tags")
+            .and_then(Value::as_array)
+            .map(|tags| {
+                tags.iter()
+                    .filter_map(|tag| tag.get("name")?.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        website: social("website"),
+        twitter: social("twitter"),
+        telegram: social("telegram"),
+    })
+}
+
+/// Service layer for external node profiles.
+pub struct ExternalProfileService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> ExternalProfileService<'a> {
+    /// Creates a new ExternalProfileService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Returns the node's external profile, or `None` when enrichment is disabled
+    /// or nothing is known about the node.
+    pub async fn get_profile(&self, pubkey: &PublicKey) -> Option<ExternalNodeProfile> {
+        let config = config::shared().ok()?;
+        if !config.external_enrichment_enabled {
+            return None;
+        }
+
+        let repo = ExternalProfileRepository::new(self.pool);
+        let key = pubkey.to_string();
+        let cached: Option<ExternalNodeProfile> = match repo.get_profile(&key).await {
+            Ok(row) => row.and_then(|(profile, _)| serde_json::from_str(&profile).ok()),
+            Err(e) => {
+                tracing::warn!("Failed to read cached profile of {}: {}", key, e);
+                None
+            }
+        };
+
+        let ttl = chrono::Duration::seconds(config.external_enrichment_ttl_seconds as i64);
+        if let Some(profile) = cached
+            .as_ref()
+            .filter(|profile| Utc::now() - profile.fetched_at < ttl)
+        {
+            return Some(profile.clone());
+        }
+
+        let Some(profile) = fetch_profile(pubkey, config.amboss_api_key.as_deref()).await else {
+            return cached;
+        };
+
+        match serde_json::to_string(&profile) {
+            Ok(json) => {
+                if let Err(e) = repo.save_profile(&key, &json, profile.fetched_at).await {
+                    tracing::warn!("Failed to cache profile of {}: {}", key, e);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to serialize profile of {}: {}", key, e),
+        }
+        Some(profile)
+    }
+}
+
+/// Queries both providers, returning `None` when neither answered.
+async fn fetch_profile(
+    pubkey: &PublicKey,
+    amboss_api_key: Option<&str>,
+) -> Option<ExternalNodeProfile> {
+    let (amboss, one_ml) = tokio::join!(
+        async {
+            match amboss_api_key {
+                Some(api_key) => fetch_amboss(pubkey, api_key).await,
+                None => None,
+            }
+        },
+        fetch_one_ml(pubkey),
+    );
+
+    (amboss.is_some() || one_ml.is_some()).then(|| ExternalNodeProfile {
+        amboss,
+        one_ml,
+        fetched_at: Utc::now(),
+    })
+}
+
+async fn fetch_amboss(pubkey: &PublicKey, api_key: &str) -> Option<AmbossProfile> {
+    let response = HTTP_CLIENT
+        .post(AMBOSS_API_URL)
+        .bearer_auth(api_key)
+        .json(&json!({
+            "query": AMBOSS_NODE_QUERY,
+            "variables": { "pubkey": pubkey.to_string() },
+        }))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+
+    match response {
+        Ok(response) => parse_amboss_profile(&response.json::<Value>().await.ok()?),
+        Err(e) => {
+            tracing::warn!("Amboss lookup of {} failed: {}", pubkey, e);
+            None
+        }
+    }
+}
+
+async fn fetch_one_ml(pubkey: &PublicKey) -> Option<OneMlProfile> {
+    let response = HTTP_CLIENT
+        .get(format!("{ONE_ML_API_URL}/node/{pubkey}/json"))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+
+    match response {
+        Ok(response) => response.json().await.ok(),
+        Err(e) => {
+            tracing::warn!("1ML lookup of {} failed: {}", pubkey, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_provider_responses() {
+        let amboss = json!({
+            "data": { "getNode": {
+                "tags": [{ "name": "Routing" }, { "name": "Exchange" }],
+                "socials": { "info": { "website": "https://example.com", "twitter": "" } }
+            } }
+        });
+        assert_eq!(
+            parse_amboss_profile(&amboss),
+            Some(AmbossProfile {
+                tags: vec!["Routing".to_string(), "Exchange".to_string()],
+                website: Some("https://example.com".to_string()),
+                twitter: None,
+                telegram: None,
+            })
+        );
+        assert_eq!(
+            parse_amboss_profile(&json!({ "data": { "getNode": null } })),
+            None
+        );
+
+        let one_ml: OneMlProfile = serde_json::from_value(json!({
+            "pub_key": "02aa",
+            "capacity": 150_000_000,
+            "channelcount": 42,
+            "noderank": { "capacity": 120, "channelcount": 300, "age": 900 }
+        }))
+        .unwrap();
+        assert_eq!(one_ml.capacity_sat, Some(150_000_000));
+        assert_eq!(one_ml.rank.unwrap().channel_count, Some(300));
+    }
+}
@@ -0,0 +1,606 @@
+//! Local copy of each monitored node's view of the channel graph.
+//!
+//! `describe_graph` returns tens of thousands of channels on mainnet, far too
+//! much to fetch for every graph request. A node's graph is fetched in full the
+//! first time it is asked for and kept current from then on: LND nodes stream
+//! their gossip through `SubscribeChannelGraph` and each update is applied as it
+//! arrives, while CLN nodes and LND nodes behind the REST proxy, which can't
+//! stream gossip, are polled every `POLL_INTERVAL` and only the nodes and
+//! channels that changed are written. Each cached graph is fetched in full
+//! again once a day to make up for anything missed. Graph endpoints read the
+//! cache and report how fresh it is.
+
+use crate::database::models::{
+    Credential, GraphChanges, GraphEdgeRecord, GraphNodeRecord, GraphSync,
+};
+use crate::errors::{LightningError, ServiceResult};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::repositories::graph_repository::GraphRepository;
+use crate::services::node_manager::connect_node;
+use crate::services::node_manager::{GraphUpdates, LightningClient};
+use crate::services::task_supervisor;
+use crate::utils::{EdgeFee, GraphEdge, GraphNode, GraphUpdate, NetworkGraph, ShortChannelID};
+use bitcoin::secp256k1::PublicKey;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use std::str::FromStr;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+/// Time between polls of graphs that aren't streamed.
+const POLL_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Age at which a cached graph is fetched in full again.
+const FULL_SYNC_INTERVAL: chrono::Duration = chrono::Duration::hours(24);
+
+/// A full sync this recent isn't repeated when the node's gossip starts being
+/// streamed; the seconds in between are made up for by the daily sync.
+const RECENT_SYNC: chrono::Duration = chrono::Duration::minutes(1);
+
+/// Nodes whose gossip is being streamed into their cached graph.
+static FOLLOWED: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(Default::default);
+
+/// How current a cached graph is.
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphFreshness {
+    /// When the whole graph was last fetched from the node
+    pub synced_at: DateTime<Utc>,
+    /// When the latest change from the node's gossip was applied
+    pub updated_at: DateTime<Utc>,
+    /// Whether gossip is applied as it arrives rather than polled
+    pub live: bool,
+}
+
+impl From<&GraphSync> for GraphFreshness {
+    fn from(sync: &GraphSync) -> Self {
+        Self {
+            synced_at: sync.synced_at,
+            updated_at: sync.updated_at,
+            live: is_followed(&sync.node_id),
+        }
+    }
+}
+
+impl From<&GraphNode> for GraphNodeRecord {
+    fn from(node: &GraphNode) -> Self {
+        Self {
+            pubkey: node.pubkey.to_string(),
+            alias: node.alias.clone(),
+            color: node.color.clone(),
+            last_update: node.last_update.map(|timestamp| timestamp as i64),
+            addresses: serde_json::to_string(&node.addresses).unwrap_or_else(|_| "[]".into()),
+        }
+    }
+}
+
+impl From<&GraphEdge> for GraphEdgeRecord {
+    fn from(edge: &GraphEdge) -> Self {
+        Self {
+            channel_id: edge.channel_id.0 as i64,
+            node1: edge.node1.to_string(),
+            node2: edge.node2.to_string(),
+            capacity_sat: edge.capacity_sat as i64,
+            node1_base_fee_msat: edge.node1_fee.map(|fee| fee.base_fee_msat as i64),
+            node1_fee_rate_ppm: edge.node1_fee.map(|fee| fee.fee_rate_ppm as i64),
+            node1_last_update: edge.node1_last_update.map(|timestamp| timestamp as i64),
+            node2_base_fee_msat: edge.node2_fee.map(|fee| fee.base_fee_msat as i64),
+            node2_fee_rate_ppm: edge.node2_fee.map(|fee| fee.fee_rate_ppm as i64),
+            node2_last_update: edge.node2_last_update.map(|timestamp| timestamp as i64),
+        }
+    }
+}
+
+/// Reads a cached node; its channel totals are left to the caller.
+fn cached_node(record: GraphNodeRecord) -> Option<GraphNode> {
+    Some(GraphNode {
+        pubkey: PublicKey::from_str(&record.pubkey).ok()?,
+        alias: record.alias,
+        color: record.color,
+        last_update: record.last_update.map(|timestamp| timestamp as u64),
+        addresses: serde_json::from_str(&record.addresses).unwrap_or_default(),
+        num_channels: None,
+        total_capacity_sat: None,
+    })
+}
+
+/// Reads a cached channel.
+fn cached_edge(record: GraphEdgeRecord) -> Option<GraphEdge> {
+    let fee = |base_fee_msat: Option<i64>, fee_rate_ppm: Option<i64>| {
+        Some(EdgeFee {
+            base_fee_msat: base_fee_msat? as u64,
+            fee_rate_ppm: fee_rate_ppm? as u64,
+        })
+    };
+    Some(GraphEdge {
+        channel_id: ShortChannelID(record.channel_id as u64),
+        node1: PublicKey::from_str(&record.node1).ok()?,
+        node2: PublicKey::from_str(&record.node2).ok()?,
+        capacity_sat: record.capacity_sat as u64,
+        node1_fee: fee(record.node1_base_fee_msat, record.node1_fee_rate_ppm),
+        node2_fee: fee(record.node2_base_fee_msat, record.node2_fee_rate_ppm),
+        node1_last_update: record.node1_last_update.map(|timestamp| timestamp as u64),
+        node2_last_update: record.node2_last_update.map(|timestamp| timestamp as u64),
+    })
+}
+
+fn cache_error(e: anyhow::Error) -> LightningError {
+    LightningError::GetGraphError(format!("Graph cache error: {e}"))
+}
+
+fn is_followed(node_id: &str) -> bool {
+    FOLLOWED.lock().unwrap().contains(node_id)
+}
+
+/// Splits `fetched` into the items that differ from `cached` and the keys of
+/// the cached items no longer fetched.
+fn diff<T: PartialEq, K: Eq + Hash>(
+    cached: Vec<T>,
+    fetched: Vec<T>,
+    key: impl Fn(&T) -> K,
+) -> (Vec<T>, Vec<K>) {
+    let mut cached: HashMap<K, T> = cached.into_iter().map(|item| (key(&item), item)).collect();
+    let changed = fetched
+        .into_iter()
+        .filter(|item| cached.remove(&key(item)).as_ref() != Some(item))
+        .collect();
+    (changed, cached.into_keys().collect())
+}
+
+/// Changes that turn a cached graph into one fetched again from the node.
+pub fn graph_changes(
+    cached_nodes: Vec<GraphNodeRecord>,
+    cached_edges: Vec<GraphEdgeRecord>,
+    fetched: &NetworkGraph,
+) -> GraphChanges {
+    let (nodes, removed_nodes) = diff(
+        cached_nodes,
+        fetched.nodes.iter().map(GraphNodeRecord::from).collect(),
+        |node| node.pubkey.clone(),
+    );
+    let (edges, closed_channels) = diff(
+        cached_edges,
+        fetched.edges.iter().map(GraphEdgeRecord::from).collect(),
+        |edge| edge.channel_id,
+    );
+    GraphChanges {
+        nodes,
+        edges,
+        removed_nodes,
+        closed_channels,
+    }
+}
+
+/// Changes a batch of gossip updates makes, given the cached channels they touch.
+pub fn gossip_changes(updates: Vec<GraphUpdate>, cached: Vec<GraphEdge>) -> GraphChanges {
+    let mut edges: HashMap<u64, GraphEdge> = cached
+        .into_iter()
+        .map(|edge| (edge.channel_id.0, edge))
+        .collect();
+    let mut nodes: HashMap<PublicKey, GraphNode> = HashMap::new();
+    let mut touched = HashSet::new();
+    let mut closed = HashSet::new();
+
+    for update in updates {
+        match update {
+            GraphUpdate::Node(node) => {
+                nodes.insert(node.pubkey, node);
+            }
+            GraphUpdate::Channel(direction) => {
+                let id = direction.channel_id.0;
+                edges
+                    .entry(id)
+                    .and_modify(|edge| edge.set_direction(&direction))
+                    .or_insert_with(|| GraphEdge::from_direction(&direction));
+                touched.insert(id);
+                closed.remove(&id);
+            }
+            GraphUpdate::Closed(channel_id) => {
+                edges.remove(&channel_id.0);
+                touched.remove(&channel_id.0);
+                closed.insert(channel_id.0);
+            }
+        }
+    }
+
+    GraphChanges {
+        nodes: nodes.values().map(GraphNodeRecord::from).collect(),
+        edges: touched
+            .iter()
+            .filter_map(|id| edges.get(id))
+            .map(GraphEdgeRecord::from)
+            .collect(),
+        removed_nodes: vec![],
+        closed_channels: closed.into_iter().map(|id| id as i64).collect(),
+    }
+}
+
+/// Fetches the client's whole graph into the cache, replacing what was there.
+async fn full_sync(
+    pool: &SqlitePool,
+    client: &(dyn LightningClient + Send + Sync),
+) -> Result<GraphSync, LightningError> {
+    let node_id = client.get_info().pubkey.to_string();
+    let graph = client.describe_graph().await?;
+    let changes = GraphChanges {
+        nodes: graph.nodes.iter().map(GraphNodeRecord::from).collect(),
+        edges: graph.edges.iter().map(GraphEdgeRecord::from).collect(),
+        ..Default::default()
+    };
+
+    let now = Utc::now();
+    GraphRepository::new(pool)
+        .apply_changes(&node_id, &changes, true, now)
+        .await
+        .map_err(cache_error)?;
+    tracing::info!(
+        "Cached graph of node {}: {} nodes, {} channels",
+        node_id,
+        changes.nodes.len(),
+        changes.edges.len()
+    );
+
+    Ok(GraphSync {
+        node_id,
+        synced_at: now,
+        updated_at: now,
+    })
+}
+
+/// Fetches the client's graph and writes only what changed since the cache was filled.
+async fn poll_changes(
+    pool: &SqlitePool,
+    client: &(dyn LightningClient + Send + Sync),
+) -> Result<(), LightningError> {
+    let node_id = client.get_info().pubkey.to_string();
+    let graph = client.describe_graph().await?;
+    let repository = GraphRepository::new(pool);
+    let cached_nodes = repository.get_nodes(&node_id).await.map_err(cache_error)?;
+    let cached_edges = repository.get_edges(&node_id).await.map_err(cache_error)?;
+
+    let changes = graph_changes(cached_nodes, cached_edges, &graph);
+    let now = Utc::now();
+    if !changes.is_empty() {
+        repository
+            .apply_changes(&node_id, &changes, false, now)
+            .await
+            .map_err(cache_error)?;
+    }
+    repository
+        .mark_synced(&node_id, now)
+        .await
+        .map_err(cache_error)
+}
+
+/// Applies a batch of gossip updates to a node's cached graph.
+async fn apply_gossip(
+    pool: &SqlitePool,
+    node_id: &str,
+    updates: Vec<GraphUpdate>,
+) -> Result<(), LightningError> {
+    let repository = GraphRepository::new(pool);
+    let channel_ids: Vec<i64> = updates
+        .iter()
+        .filter_map(|update| match update {
+            GraphUpdate::Channel(direction) => Some(direction.channel_id.0 as i64),
+            _ => None,
+        })
+        .collect();
+    let cached = repository
+        .get_edges_by_id(node_id, &channel_ids)
+        .await
+        .map_err(cache_error)?
+        .into_iter()
+        .filter_map(cached_edge)
+        .collect();
+
+    let changes = gossip_changes(updates, cached);
+    if changes.is_empty() {
+        return Ok(());
+    }
+    repository
+        .apply_changes(node_id, &changes, false, Utc::now())
+        .await
+        .map_err(cache_error)
+}
+
+/// Applies the node's gossip to its cached graph until the stream ends or the
+/// node stops being followed.
+fn spawn_follower(
+    pool: SqlitePool,
+    node_id: String,
+    client: Box<dyn LightningClient + Send + Sync>,
+    mut updates: GraphUpdates,
+) {
+    FOLLOWED.lock().unwrap().insert(node_id.clone());
+    tokio::spawn(async move {
+        // The client holds the connection the stream runs over
+        let _client = client;
+        while let Some(batch) = updates.next().await {
+            if !is_followed(&node_id) {
+                return;
+            }
+            match batch {
+                Ok(batch) => {
+                    if let Err(e) = apply_gossip(&pool, &node_id, batch).await {
+                        tracing::warn!("Failed to apply gossip of node {}: {}", node_id, e);
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Graph updates of node {} failed: {}", node_id, e);
+                    break;
+                }
+            }
+        }
+        tracing::info!(
+            "Graph updates of node {} ended, polling until resubscribed",
+            node_id
+        );
+        FOLLOWED.lock().unwrap().remove(&node_id);
+    });
+}
+
+/// Brings a cached graph up to date, starting to follow the node's gossip if
+/// it can stream it.
+async fn refresh_graph(
+    pool: &SqlitePool,
+    credential: &Credential,
+    sync: &GraphSync,
+) -> Result<(), LightningError> {
+    let client = connect_node(credential).await?;
+    let age = Utc::now() - sync.synced_at;
+
+    if !is_followed(&sync.node_id) {
+        match client.subscribe_graph().await {
+            Ok(updates) => {
+                // Gossip from here on is applied, so a sync now leaves no gap
+                if age > RECENT_SYNC {
+                    full_sync(pool, client.as_ref()).await?;
+                }
+                spawn_follower(pool.clone(), sync.node_id.clone(), client, updates);
+                return Ok(());
+            }
+            Err(e) => tracing::debug!("Polling graph of node {}: {}", sync.node_id, e),
+        }
+    }
+
+    if age >= FULL_SYNC_INTERVAL {
+        full_sync(pool, client.as_ref()).await?;
+    } else if !is_followed(&sync.node_id) {
+        poll_changes(pool, client.as_ref()).await?;
+    }
+    Ok(())
+}
+
+/// Refreshes one node's graph in the background after its first sync.
+fn spawn_refresh(pool: SqlitePool, sync: GraphSync) {
+    tokio::spawn(async move {
+        let credential = match CredentialRepository::new(&pool)
+            .get_node_credentials(None, None)
+            .await
+        {
+            Ok(credentials) => credentials
+                .into_iter()
+                .find(|credential| credential.node_id == sync.node_id),
+            Err(e) => {
+                tracing::warn!("Failed to load credentials for graph cache: {}", e);
+                return;
+            }
+        };
+        let Some(credential) = credential else {
+            return;
+        };
+        if let Err(e) = refresh_graph(&pool, &credential, &sync).await {
+            tracing::warn!("Graph refresh of node {} failed: {}", sync.node_id, e);
+        }
+    });
+}
+
+/// Returns the sync of the client's cached graph, filling the cache first if
+/// the graph was never asked for.
+async fn ensure_synced(
+    pool: &SqlitePool,
+    client: &(dyn LightningClient + Send + Sync),
+) -> Result<GraphSync, LightningError> {
+    let node_id = client.get_info().pubkey.to_string();
+    if let Some(sync) = GraphRepository::new(pool)
+        .get_sync(&node_id)
+        .await
+        .map_err(cache_error)?
+    {
+        return Ok(sync);
+    }
+
+    let sync = full_sync(pool, client).await?;
+    spawn_refresh(pool.clone(), sync.clone());
+    Ok(sync)
+}
+
+/// Returns every public channel of the client's graph from the cache.
+pub async fn graph_edges(
+    pool: &SqlitePool,
+    client: &(dyn LightningClient + Send + Sync),
+) -> Result<(Vec<GraphEdge>, GraphFreshness), LightningError> {
+    let sync = ensure_synced(pool, client).await?;
+    let edges = GraphRepository::new(pool)
+        .get_edges(&sync.node_id)
+        .await
+        .map_err(cache_error)?
+        .into_iter()
+        .filter_map(cached_edge)
+        .collect();
+    Ok((edges, GraphFreshness::from(&sync)))
+}
+
+/// Looks up a node of the client's graph in the cache, asking the node itself
+/// for nodes that announced themselves since the cache was last updated.
+pub async fn graph_node(
+    pool: &SqlitePool,
+    client: &(dyn LightningClient + Send + Sync),
+    pubkey: &PublicKey,
+) -> Result<(GraphNode, GraphFreshness), LightningError> {
+    let sync = ensure_synced(pool, client).await?;
+    let cached = GraphRepository::new(pool)
+        .get_node(&sync.node_id, &pubkey.to_string())
+        .await
+        .map_err(cache_error)?;
+
+    let node = match cached.and_then(|(record, num_channels, total_capacity_sat)| {
+        Some(GraphNode {
+            num_channels: Some(num_channels as u32),
+            total_capacity_sat: Some(total_capacity_sat as u64),
+            ..cached_node(record)?
+        })
+    }) {
+        Some(node) => node,
+        None => client.get_graph_node(pubkey).await?,
+    };
+    Ok((node, GraphFreshness::from(&sync)))
+}
+
+/// Keeps every cached graph current, dropping those of nodes no longer monitored.
+async fn refresh_cached_graphs(pool: &SqlitePool) -> ServiceResult<()> {
+    let repository = GraphRepository::new(pool);
+    let syncs = repository.get_syncs().await?;
+    if syncs.is_empty() {
+        return Ok(());
+    }
+    let credentials = CredentialRepository::new(pool)
+        .get_node_credentials(None, None)
+        .await?;
+
+    for sync in syncs {
+        let Some(credential) = credentials
+            .iter()
+            .find(|credential| credential.node_id == sync.node_id)
+        else {
+            FOLLOWED.lock().unwrap().remove(&sync.node_id);
+            repository.delete_graph(&sync.node_id).await?;
+            continue;
+        };
+        if is_followed(&sync.node_id) && Utc::now() - sync.synced_at < FULL_SYNC_INTERVAL {
+            continue;
+        }
+
+        if let Err(e) = refresh_graph(pool, credential, &sync).await {
+            tracing::warn!("Graph refresh of node {} failed: {}", sync.node_id, e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Polls the cached graphs that aren't streamed and restarts lost streams.
+pub fn spawn_graph_cache_refresher(pool: SqlitePool) {
+    task_supervisor::supervise("graph_cache", move |task| {
+        let pool = pool.clone();
+        async move {
+            let mut interval = tokio::time::interval(POLL_INTERVAL);
+            loop {
+                interval.tick().await;
+                let result = refresh_cached_graphs(&pool).await;
+                if let Err(e) = &result {
+                    tracing::error!("Graph cache refresh failed: {}", e);
+                }
+                task.record_run(&result);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::GraphDirection;
+    use bitcoin::secp256k1::{Secp256k1, SecretKey};
+
+    fn pubkey(n: u8) -> PublicKey {
+        PublicKey::from_secret_key(&Secp256k1::new(), &SecretKey::from_slice(&[n; 32]).unwrap())
+    }
+
+    fn direction(id: u64, source: u8, destination: u8, fee_rate_ppm: u64) -> GraphDirection {
+        GraphDirection {
+            channel_id: ShortChannelID(id),
+            source: pubkey(source),
+            destination: pubkey(destination),
+            capacity_sat: 1_000_000,
+            fee: Some(EdgeFee {
+                base_fee_msat: 1000,
+                fee_rate_ppm,
+            }),
+            last_update: 1_700_000_000 + fee_rate_ppm,
+        }
+    }
+
+    fn node(n: u8, alias: &str) -> GraphNode {
+        GraphNode {
+            pubkey: pubkey(n),
+            alias: alias.to_string(),
+            color: None,
+            last_update: Some(1_700_000_000),
+            addresses: vec![],
+            num_channels: None,
+            total_capacity_sat: None,
+        }
+    }
+
+    #[test]
+    fn gossip_updates_one_side_of_cached_channels() {
+        let cached = GraphEdge::from_directions([direction(1, 1, 2, 100), direction(1, 2, 1, 200)]);
+        let changes = gossip_changes(
+            vec![
+                GraphUpdate::Channel(direction(1, 2, 1, 300)),
+                GraphUpdate::Channel(direction(2, 1, 3, 50)),
+                GraphUpdate::Closed(ShortChannelID(2)),
+                GraphUpdate::Node(node(3, "new")),
+            ],
+            cached,
+        );
+
+        assert_eq!(changes.edges.len(), 1);
+        let edge = cached_edge(changes.edges[0].clone()).unwrap();
+        let (ours, theirs) = if edge.node1 == pubkey(1) {
+            (edge.node1_fee, edge.node2_fee)
+        } else {
+            (edge.node2_fee, edge.node1_fee)
+        };
+        assert_eq!(ours.unwrap().fee_rate_ppm, 100);
+        assert_eq!(theirs.unwrap().fee_rate_ppm, 300);
+        assert_eq!(changes.closed_channels, vec![2]);
+        assert_eq!(changes.nodes[0].alias, "new");
+    }
+
+    #[test]
+    fn polling_writes_only_what_changed() {
+        let cached = NetworkGraph {
+            nodes: vec![node(1, "one"), node(2, "two")],
+            edges: GraphEdge::from_directions([direction(1, 1, 2, 100), direction(2, 2, 3, 100)]),
+        };
+        let fetched = NetworkGraph {
+            nodes: vec![node(1, "one"), node(2, "renamed")],
+            edges: GraphEdge::from_directions([direction(1, 1, 2, 100), direction(3, 1, 3, 10)]),
+        };
+        let changes = graph_changes(
+            cached.nodes.iter().map(GraphNodeRecord::from).collect(),
+            cached.edges.iter().map(GraphEdgeRecord::from).collect(),
+            &fetched,
+        );
+
+        assert_eq!(changes.nodes.len(), 1);
+        assert_eq!(changes.nodes[0].alias, "renamed");
+        assert_eq!(changes.edges.len(), 1);
+        assert_eq!(changes.edges[0].channel_id, 3);
+        assert_eq!(changes.closed_channels, vec![2]);
+        assert!(changes.removed_nodes.is_empty());
+
+        let unchanged = graph_changes(
+            fetched.nodes.iter().map(GraphNodeRecord::from).collect(),
+            fetched.edges.iter().map(GraphEdgeRecord::from).collect(),
+            &fetched,
+        );
+        assert!(unchanged.is_empty());
+    }
+}
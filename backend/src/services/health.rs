@@ -0,0 +1,193 @@
+//! Dependency checks behind the readiness probe.
+//!
+//! Each check reports a component status instead of failing outright, so the
+//! probe response shows which dependency is holding the instance out of rotation.
+
+use crate::config;
+use crate::database::migrations::{self, MigrationRun};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::services::node_manager::connect_node;
+use futures::future::select_ok;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::future::Future;
+use std::time::Instant;
+use tokio::time::{Duration, timeout};
+
+/// Time budget for acquiring a database connection.
+const DATABASE_TIMEOUT: Duration = Duration::from_secs(2);
+/// Time budget for finding one reachable node.
+const NODE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ComponentState {
+    Up,
+    Down,
+    /// The check is disabled by configuration
+    Skipped,
+}
+
+/// Outcome of a single dependency check.
+#[derive(Debug, Serialize)]
+pub struct ComponentStatus {
+    pub name: &'static str,
+    pub status: ComponentState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub latency_ms: u64,
+}
+
+/// Combined readiness of the instance.
+#[derive(Debug, Serialize)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    pub components: Vec<ComponentStatus>,
+}
+
+impl ReadinessReport {
+    fn new(components: Vec<ComponentStatus>) -> Self {
+        let ready = components
+            .iter()
+            .all(|component| component.status != ComponentState::Down);
+        Self { ready, components }
+    }
+}
+
+/// Runs a check and records how long it took.
+async fn timed<F>(name: &'static str, check: F) -> ComponentStatus
+where
+    F: Future<Output = Result<Option<String>, String>>,
+{
+    let started = Instant::now();
+    let (status, detail) = match check.await {
+        Ok(detail) => (ComponentState::Up, detail),
+        Err(error) => (ComponentState::Down, Some(error)),
+    };
+    ComponentStatus {
+        name,
+        status,
+        detail,
+        latency_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+/// Checks the database, its migrations and optionally node connectivity.
+pub async fn readiness(pool: &SqlitePool) -> ReadinessReport {
+    let require_node = config::shared()
+        .map(|config| config.readiness_require_node)
+        .unwrap_or(false);
+
+    let mut components = vec![
+        timed("database", check_database(pool)).await,
+        timed("migrations", check_migrations(pool)).await,
+    ];
+
+    components.push(if require_node {
+        timed("nodes", check_nodes(pool)).await
+    } else {
+        ComponentStatus {
+            name: "nodes",
+            status: ComponentState::Skipped,
+            detail: Some("READINESS_REQUIRE_NODE is not enabled".to_string()),
+            latency_ms: 0,
+        }
+    });
+
+    ReadinessReport::new(components)
+}
+
+async fn check_database(pool: &SqlitePool) -> Result<Option<String>, String> {
+    let mut conn = timeout(DATABASE_TIMEOUT, pool.acquire())
+        .await
+        .map_err(|_| "Timed out acquiring a connection".to_string())?
+        .map_err(|e| format!("Could not acquire a connection: {e}"))?;
+
+    sqlx::query("SELECT 1")
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Query failed: {e}"))?;
+
+    Ok(None)
+}
+
+async fn check_migrations(pool: &SqlitePool) -> Result<Option<String>, String> {
+    match migrations::startup_run() {
+        MigrationRun::Running => return Err("Migrations are still running".to_string()),
+        MigrationRun::Failed(e) => return Err(format!("Migrations failed: {e}")),
+        MigrationRun::Disabled | MigrationRun::Finished => {}
+    }
+
+    let applied = migrations::applied_versions(pool)
+        .await
+        .map_err(|e| format!("Could not read applied migrations: {e}"))?;
+    let version = applied
+        .last()
+        .map_or_else(|| "none".to_string(), i64::to_string);
+
+    let pending = migrations::pending_versions(&applied);
+    if pending.is_empty() {
+        Ok(Some(format!("Schema version {version}")))
+    } else {
+        Err(format!(
+            "Schema version {version}, pending migrations: {pending:?}"
+        ))
+    }
+}
+
+async fn check_nodes(pool: &SqlitePool) -> Result<Option<String>, String> {
+    let credentials = CredentialRepository::new(pool)
+        .get_node_credentials(None, None)
+        .await
+        .map_err(|e| format!("Could not load node credentials: {e}"))?;
+    if credentials.is_empty() {
+        return Err("No nodes are connected".to_string());
+    }
+
+    let attempts = credentials.iter().map(|credential| {
+        Box::pin(async move {
+            connect_node(credential)
+                .await
+                .map(|_| credential.node_id.clone())
+                .map_err(|e| e.to_string())
+        })
+    });
+
+    match timeout(NODE_TIMEOUT, select_ok(attempts)).await {
+        Ok(Ok((node_id, _))) => Ok(Some(format!("Node {node_id} is reachable"))),
+        Ok(Err(e)) => Err(format!("No node is reachable, last error: {e}")),
+        Err(_) => Err(format!(
+            "No node responded within {}s",
+            NODE_TIMEOUT.as_secs()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn component(status: ComponentState) -> ComponentStatus {
+        ComponentStatus {
+            name: "test",
+            status,
+            detail: None,
+            latency_ms: 0,
+        }
+    }
+
+    #[test]
+    fn skipped_components_do_not_block_readiness() {
+        let report = ReadinessReport::new(vec![
+            component(ComponentState::Up),
+            component(ComponentState::Skipped),
+        ]);
+        assert!(report.ready);
+
+        let report = ReadinessReport::new(vec![
+            component(ComponentState::Up),
+            component(ComponentState::Down),
+        ]);
+        assert!(!report.ready);
+    }
+}
@@ -0,0 +1,254 @@
+//! Warnings about pending HTLCs nearing their expiry.
+//!
+//! An HTLC that isn't resolved before its expiry height has to be settled on
+//! chain, so the channel is force closed. Every ten minutes, roughly once per
+//! block, the pending HTLCs of each node are compared with the chain tip and
+//! those within the account's `alert_thresholds.htlc_expiry_blocks` raise an
+//! `HtlcExpiryRisk` warning. Channels whose tags set `htlc_expiry_blocks` use
+//! the largest of those instead. Each HTLC is reported once; nodes without a
+//! threshold, their account's or their channels' tags', are not checked.
+
+use crate::database::models::{CreateEvent, Credential, EventSeverity, EventType};
+use crate::errors::{LightningError, ServiceError, ServiceResult};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::services::channel_tags::{ChannelTagService, tag_htlc_expiry_blocks};
+use crate::services::event_service::EventService;
+use crate::services::node_manager::connect_node;
+use crate::services::settings_service::SettingsService;
+use crate::services::task_supervisor;
+use crate::utils::{PendingHtlc, PendingHtlcs, ShortChannelID};
+use chrono::Utc;
+use serde_json::json;
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(600);
+
+/// An HTLC within the threshold of its expiry.
+#[derive(Debug, Clone)]
+pub struct ExpiringHtlc {
+    pub htlc: PendingHtlc,
+    /// Blocks left until the HTLC expires, negative once it has
+    pub blocks_remaining: i64,
+}
+
+/// Identifies an HTLC that was already reported: node, channel, payment hash,
+/// direction and expiry.
+type AlertKey = (String, u64, String, bool, u32);
+
+/// Picks the HTLCs `threshold` blocks or fewer from their expiry, closest first.
+pub fn expiring_htlcs(pending: &PendingHtlcs, threshold: u32) -> Vec<ExpiringHtlc> {
+    let mut expiring: Vec<ExpiringHtlc> = pending
+        .htlcs
+        .iter()
+        .map(|htlc| ExpiringHtlc {
+            htlc: htlc.clone(),
+            blocks_remaining: i64::from(htlc.expiry_height) - i64::from(pending.block_height),
+        })
+        .filter(|expiring| expiring.blocks_remaining <= i64::from(threshold))
+        .collect();
+    expiring.sort_by_key(|expiring| expiring.blocks_remaining);
+    expiring
+}
+
+/// Checks every node with an HTLC expiry threshold.
+///
+/// `alerted` holds the HTLCs already reported and is pruned to those still pending.
+pub async fn check_all_nodes(
+    pool: &SqlitePool,
+    alerted: &mut HashSet<AlertKey>,
+) -> ServiceResult<()> {
+    let credentials = CredentialRepository::new(pool)
+        .get_node_credentials(None, None)
+        .await?;
+    let settings = SettingsService::new(pool);
+    let mut thresholds: HashMap<String, Option<u32>> = HashMap::new();
+    let mut still_pending = HashSet::new();
+
+    for credential in &credentials {
+        if !thresholds.contains_key(&credential.account_id) {
+            let threshold = settings
+                .get_settings(&credential.account_id)
+                .await?
+                .alert_thresholds
+                .htlc_expiry_blocks;
+            thresholds.insert(credential.account_id.clone(), threshold);
+        }
+        let threshold = thresholds[&credential.account_id];
+        let tag_thresholds: HashMap<u64, u32> = ChannelTagService::new(pool)
+            .get_node_channel_tags(&credential.account_id, &credential.node_id)
+            .await?
+            .into_iter()
+            .filter_map(|(channel_id, tags)| {
+                tag_htlc_expiry_blocks(&tags).map(|blocks| (channel_id, blocks))
+            })
+            .collect();
+        if threshold.is_none() && tag_thresholds.is_empty() {
+            continue;
+        }
+
+        match check_node(pool, credential, threshold, &tag_thresholds, alerted).await {
+            Ok(keys) => still_pending.extend(keys),
+            Err(e) => {
+                tracing::warn!(
+                    "HTLC expiry check of node {} failed: {}",
+                    credential.node_id,
+                    e
+                );
+                // Keep the node's alerts so a failed check doesn't repeat them
+                still_pending.extend(
+                    alerted
+                        .iter()
+                        .filter(|key| key.0 == credential.node_id)
+                        .cloned(),
+                );
+            }
+        }
+    }
+
+    alerted.retain(|key| still_pending.contains(key));
+    Ok(())
+}
+
+/// Checks a node's pending HTLCs, warning about the new ones near expiry.
+///
+/// `threshold` is the account's and `tag_thresholds` those of tagged channels,
+/// by numeric channel id. Returns the keys of the node's expiring HTLCs.
+async fn check_node(
+    pool: &SqlitePool,
+    credential: &Credential,
+    threshold: Option<u32>,
+    tag_thresholds: &HashMap<u64, u32>,
+    alerted: &mut HashSet<AlertKey>,
+) -> ServiceResult<Vec<AlertKey>> {
+    let external = |e: LightningError| ServiceError::ExternalService {
+        message: e.to_string(),
+    };
+    let client = connect_node(credential).await.map_err(external)?;
+    let pending = client.list_pending_htlcs().await.map_err(external)?;
+
+    let widest = tag_thresholds.values().copied().chain(threshold).max();
+    let mut keys = Vec::new();
+    for expiring in expiring_htlcs(&pending, widest.unwrap_or_default()) {
+        let htlc = &expiring.htlc;
+        let ShortChannelID(channel_id) = htlc.channel_id;
+        let Some(threshold) = tag_thresholds.get(&channel_id).copied().or(threshold) else {
+            continue;
+        };
+        if expiring.blocks_remaining > i64::from(threshold) {
+            continue;
+        }
+        let key = (
+            credential.node_id.clone(),
+            channel_id,
+            htlc.payment_hash.clone(),
+            htlc.incoming,
+            htlc.expiry_height,
+        );
+        keys.push(key.clone());
+        if alerted.contains(&key) {
+            continue;
+        }
+
+        EventService::new(pool)
+            .create_and_dispatch_event(CreateEvent {
+                id: Uuid::now_v7().to_string(),
+                account_id: credential.account_id.clone(),
+                user_id: credential.user_id.clone(),
+                node_id: credential.node_id.clone(),
+                node_alias: credential.node_alias.clone(),
+                event_type: EventType::HtlcExpiryRisk,
+                severity: EventSeverity::Warning,
+                title: "HTLC Near Expiry".to_string(),
+                description: format!(
+                    "{} HTLC of {} sats on channel {} expires in {} blocks, risking a force close",
+                    if htlc.incoming {
+                        "Incoming"
+                    } else {
+                        "Outgoing"
+                    },
+                    htlc.amount_msat / 1000,
+                    htlc.channel_id,
+                    expiring.blocks_remaining
+                ),
+                data: json!({
+                    "channel_id": htlc.channel_id,
+                    "remote_pubkey": htlc.remote_pubkey,
+                    "direction": if htlc.incoming { "incoming" } else { "outgoing" },
+                    "amount_msat": htlc.amount_msat,
+                    "amount_sat": htlc.amount_msat / 1000,
+                    "payment_hash": htlc.payment_hash,
+                    "expiry_height": htlc.expiry_height,
+                    "block_height": pending.block_height,
+                    "blocks_remaining": expiring.blocks_remaining,
+                    "threshold_blocks": threshold,
+                })
+                .to_string(),
+                notifications_id: None,
+                timestamp: Utc::now(),
+            })
+            .await?;
+        alerted.insert(key);
+    }
+
+    Ok(keys)
+}
+
+/// Checks pending HTLCs for nearing expiries every ten minutes.
+pub fn spawn_htlc_expiry_checker(pool: SqlitePool) {
+    task_supervisor::supervise("htlc_expiry", move |task| {
+        let pool = pool.clone();
+        async move {
+            let mut alerted = HashSet::new();
+            // The first check waits for startup migrations
+            let mut interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + CHECK_INTERVAL,
+                CHECK_INTERVAL,
+            );
+            loop {
+                interval.tick().await;
+                let result = check_all_nodes(&pool, &mut alerted).await;
+                if let Err(e) = &result {
+                    tracing::error!("HTLC expiry check failed: {}", e);
+                }
+                task.record_run(&result);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn htlc(channel_id: u64, expiry_height: u32) -> PendingHtlc {
+        PendingHtlc {
+            channel_id: ShortChannelID(channel_id),
+            remote_pubkey: None,
+            incoming: true,
+            amount_msat: 50_000_000,
+            payment_hash: "ab".repeat(32),
+            expiry_height,
+        }
+    }
+
+    #[test]
+    fn picks_htlcs_within_the_threshold() {
+        let pending = PendingHtlcs {
+            block_height: 900_000,
+            htlcs: vec![
+                htlc(1, 900_100),
+                htlc(2, 900_012),
+                htlc(3, 900_003),
+                htlc(4, 899_999),
+            ],
+        };
+
+        let expiring = expiring_htlcs(&pending, 12);
+        let remaining: Vec<i64> = expiring.iter().map(|e| e.blocks_remaining).collect();
+        assert_eq!(remaining, vec![-1, 3, 12]);
+        assert_eq!(expiring[1].htlc.channel_id.0, 3);
+        assert_eq!(expiring_htlcs(&pending, 0).len(), 1);
+    }
+}
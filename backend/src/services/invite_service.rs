@@ -255,7 +255,7 @@ impl<'a> InviteService<'a> {
             return Err(ServiceError::not_found("Invitation not resent", &invite.id));
         }
 
-        self.try_send_invite_email(&invite, &user, &account.name);
+        self.try_send_invite_email(&invite, user, &account.name);
         Ok(invite)
     }
 
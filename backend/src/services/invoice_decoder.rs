@@ -0,0 +1,220 @@
+//! Decoding BOLT11 payment requests without asking a node.
+//!
+//! `POST /api/invoices/decode` reads everything a confirmation screen needs
+//! before paying from the payment request itself: amount, description, expiry,
+//! destination, route hints and feature bits. The signature is checked while
+//! parsing, so the destination is the key that signed the invoice.
+
+use crate::errors::{ServiceError, ServiceResult};
+use crate::utils::{Feature, ShortChannelID};
+use chrono::{DateTime, Utc};
+use lightning_invoice::{Bolt11Invoice, Bolt11InvoiceDescription};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Names of the feature bits BOLT 9 allows in invoices, by their even bit.
+const INVOICE_FEATURES: [(u32, &str); 5] = [
+    (8, "var_onion_optin"),
+    (14, "payment_secret"),
+    (16, "basic_mpp"),
+    (24, "option_route_blinding"),
+    (48, "option_payment_metadata"),
+];
+
+/// A BOLT11 invoice as read from its payment request.
+#[derive(Debug, Serialize)]
+pub struct DecodedInvoice {
+    pub payment_hash: String,
+    pub payment_secret: String,
+    /// Amount to pay, absent for invoices leaving it to the payer
+    pub amount_msat: Option<u64>,
+    /// Description, absent when the invoice only commits to its hash
+    pub description: Option<String>,
+    pub description_hash: Option<String>,
+    /// Node the payment goes to
+    pub destination: String,
+    /// `bitcoin`, `testnet`, `regtest`, `simnet` or `signet`
+    pub network: String,
+    pub created_at: DateTime<Utc>,
+    /// Seconds after creation the invoice can be paid for
+    pub expiry: u64,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub is_expired: bool,
+    pub min_final_cltv_expiry_delta: u64,
+    pub fallback_addresses: Vec<String>,
+    /// Private routes to the destination, each from the first hop on
+    pub route_hints: Vec<Vec<RouteHintHop>>,
+    /// Feature bits set in the invoice
+    pub features: HashMap<u32, Feature>,
+}
+
+/// One hop of a route hint, i.e. a private channel towards the destination.
+#[derive(Debug, Serialize)]
+pub struct RouteHintHop {
+    /// Node forwarding over the channel
+    pub node_id: String,
+    pub channel_id: ShortChannelID,
+    pub base_fee_msat: u32,
+    pub fee_rate_ppm: u32,
+    pub cltv_expiry_delta: u16,
+    pub htlc_minimum_msat: Option<u64>,
+    pub htlc_maximum_msat: Option<u64>,
+}
+
+/// Little-endian feature flags of an invoice. `lightning` keeps them to itself
+/// apart from their `Debug` output, which lists the bytes.
+fn feature_flags(features: &impl std::fmt::Debug) -> Vec<u8> {
+    format!("{features:?}")
+        .trim_matches(['[', ']'])
+        .split(", ")
+        .filter_map(|byte| byte.parse().ok())
+        .collect()
+}
+
+/// Names the feature bits set in little-endian `flags`.
+fn invoice_features(flags: &[u8]) -> HashMap<u32, Feature> {
+    flags
+        .iter()
+        .enumerate()
+        .flat_map(|(byte, bits)| {
+            (0..8)
+                .filter(move |bit| bits & (1 << bit) != 0)
+                .map(move |bit| byte as u32 * 8 + bit)
+        })
+        .map(|bit| {
+            let name = INVOICE_FEATURES
+                .iter()
+                .find(|(even, _)| bit & !1 == *even)
+                .map(|(_, name)| name.to_string());
+            let feature = Feature {
+                is_known: Some(name.is_some()),
+                name,
+                is_required: Some(bit % 2 == 0),
+            };
+            (bit, feature)
+        })
+        .collect()
+}
+
+/// Decodes a BOLT11 payment request.
+///
+/// # Errors
+/// Returns `ServiceError::Validation` for anything that isn't a validly
+/// signed BOLT11 invoice.
+pub fn decode_invoice(payment_request: &str) -> ServiceResult<DecodedInvoice> {
+    let payment_request = payment_request.trim();
+    let payment_request = payment_request
+        .get(..10)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("lightning:"))
+        .map_or(payment_request, |_| &payment_request[10..]);
+    let invoice = Bolt11Invoice::from_str(payment_request)
+        .map_err(|e| ServiceError::validation(format!("Invalid payment request: {e}")))?;
+
+    let (description, description_hash) = match invoice.description() {
+        Bolt11InvoiceDescription::Direct(description) => (Some(description.to_string()), None),
+        Bolt11InvoiceDescription::Hash(hash) => (None, Some(hex::encode(hash.0))),
+    };
+    let created_at = i64::try_from(invoice.duration_since_epoch().as_secs())
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .ok_or_else(|| ServiceError::validation("Invoice creation time is out of range"))?;
+    let expires_at = invoice
+        .expires_at()
+        .and_then(|at| i64::try_from(at.as_secs()).ok())
+        .and_then(|secs| DateTime::from_timestamp(secs, 0));
+
+    let route_hints = invoice
+        .route_hints()
+        .into_iter()
+        .map(|hint| {
+            hint.0
+                .into_iter()
+                .map(|hop| RouteHintHop {
+                    node_id: hop.src_node_id.to_string(),
+                    channel_id: ShortChannelID(hop.short_channel_id),
+                    base_fee_msat: hop.fees.base_msat,
+                    fee_rate_ppm: hop.fees.proportional_millionths,
+                    cltv_expiry_delta: hop.cltv_expiry_delta,
+                    htlc_minimum_msat: hop.htlc_minimum_msat,
+                    htlc_maximum_msat: hop.htlc_maximum_msat,
+                })
+                .collect()
+        })
+        .collect();
+
+    Ok(DecodedInvoice {
+        payment_hash: hex::encode(invoice.payment_hash()),
+        payment_secret: hex::encode(invoice.payment_secret().0),
+        amount_msat: invoice.amount_milli_satoshis(),
+        description,
+        description_hash,
+        destination: invoice
+            .payee_pub_key()
+            .copied()
+            .unwrap_or_else(|| invoice.recover_payee_pub_key())
+            .to_string(),
+        network: invoice.network().to_string(),
+        created_at,
+        expiry: invoice.expiry_time().as_secs(),
+        expires_at,
+        is_expired: invoice.is_expired(),
+        min_final_cltv_expiry_delta: invoice.min_final_cltv_expiry_delta(),
+        fallback_addresses: invoice
+            .fallback_addresses()
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+        route_hints,
+        features: invoice
+            .features()
+            .map(|features| invoice_features(&feature_flags(features)))
+            .unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_the_fields_of_a_payment_request() {
+        // BOLT11 test vector with a fallback address and a route hint of two hops
+        let decoded = decode_invoice(
+            "LIGHTNING:lnbc20m1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqhp58yjmdan79s6qqdhdzgynm4zwqd5d7xmw5fk98klysy043l2ahrqsfpp3qjmp7lwpagxun9pygexvgpjdc4jdj85fr9yq20q82gphp2nflc7jtzrcazrra7wwgzxqc8u7754cdlpfrmccae92qgzqvzq2ps8pqqqqqqpqqqqq9qqqvpeuqafqxu92d8lr6fvg0r5gv0heeeqgcrqlnm6jhphu9y00rrhy4grqszsvpcgpy9qqqqqqgqqqqq7qqzq9qrsgqdfjcdk6w3ak5pca9hwfwfh63zrrz06wwfya0ydlzpgzxkn5xagsqz7x9j4jwe7yj7vaf2k9lqsdk45kts2fd0fkr28am0u4w95tt2nsq76cqw0",
+        )
+        .unwrap();
+
+        assert_eq!(decoded.amount_msat, Some(2_000_000_000));
+        assert_eq!(decoded.description, None);
+        assert_eq!(
+            decoded.destination,
+            "03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad"
+        );
+        assert_eq!(decoded.network, "bitcoin");
+        assert!(decoded.is_expired);
+        assert_eq!(
+            decoded.fallback_addresses,
+            ["1RustyRX2oai4EYYDpQGWvEL62BBGqN9T"]
+        );
+        assert_eq!(decoded.route_hints.len(), 1);
+        assert_eq!(decoded.route_hints[0].len(), 2);
+        assert_eq!(decoded.route_hints[0][1].cltv_expiry_delta, 4);
+        assert_eq!(
+            decoded.features[&14].name.as_deref(),
+            Some("payment_secret")
+        );
+        assert!(decode_invoice("lnbc1invalid").is_err());
+    }
+
+    #[test]
+    fn names_known_feature_bits() {
+        let features = invoice_features(&[0, 0b0100_0010, 0, 0, 0, 0, 0, 0b1000_0000]);
+        assert_eq!(features.len(), 3);
+        assert_eq!(features[&9].name.as_deref(), Some("var_onion_optin"));
+        assert_eq!(features[&9].is_required, Some(false));
+        assert_eq!(features[&14].is_required, Some(true));
+        assert_eq!(features[&63].is_known, Some(false));
+        assert_eq!(feature_flags(&vec![0u8, 0b0100_0010]), [0, 0b0100_0010]);
+    }
+}
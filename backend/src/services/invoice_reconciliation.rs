@@ -0,0 +1,207 @@
+//! Reconciliation of invoice states with a node after downtime.
+//!
+//! Shops tracking invoices through NodeGaze's events can miss settlements or
+//! expiries while NodeGaze or the node was down. Reconciling a list of hashes
+//! reads the node's invoices in one call and sets each invoice's current state
+//! beside the state NodeGaze last recorded for it, i.e. that of its latest
+//! invoice event, flagging the invoices whose states differ.
+
+use crate::database::models::EventType;
+use crate::errors::ServiceResult;
+use crate::repositories::event_repository::EventRepository;
+use crate::utils::amount::Amount;
+use crate::utils::{CustomInvoice, InvoiceStatus};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+/// How NodeGaze's record of an invoice differs from the node's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvoiceDrift {
+    /// The node has no invoice with the hash
+    MissingOnNode,
+    /// No event was recorded for the invoice
+    NotRecorded,
+    /// The recorded state is no longer the node's
+    StatusChanged,
+}
+
+/// One invoice's state on the node and as NodeGaze recorded it.
+#[derive(Debug, Clone, Serialize)]
+pub struct InvoiceReconciliation {
+    pub payment_hash: String,
+    /// Authoritative state, absent when the node doesn't know the invoice
+    pub status: Option<InvoiceStatus>,
+    pub value: Option<Amount>,
+    pub settle_date: Option<i64>,
+    pub expires_at: Option<u64>,
+    /// State implied by the latest recorded invoice event
+    pub recorded_status: Option<InvoiceStatus>,
+    pub recorded_event: Option<EventType>,
+    pub recorded_at: Option<DateTime<Utc>>,
+    /// Absent when the states agree
+    pub drift: Option<InvoiceDrift>,
+}
+
+/// Response of `POST /api/invoices/reconcile`.
+#[derive(Debug, Serialize)]
+pub struct InvoiceReconciliationReport {
+    pub checked: usize,
+    pub in_sync: usize,
+    pub drifted: usize,
+    pub invoices: Vec<InvoiceReconciliation>,
+}
+
+/// The state an invoice event leaves an invoice in, `None` for other events.
+pub fn recorded_status(event_type: &EventType) -> Option<InvoiceStatus> {
+    match event_type {
+        // Held HTLCs still await settlement, so the invoice counts as open
+        EventType::InvoiceCreated | EventType::InvoiceAccepted => Some(InvoiceStatus::Open),
+        EventType::InvoiceSettled => Some(InvoiceStatus::Settled),
+        EventType::InvoiceCancelled => Some(InvoiceStatus::Failed),
+        EventType::InvoiceExpired => Some(InvoiceStatus::Expired),
+        _ => None,
+    }
+}
+
+/// Sets the node's invoices beside the recorded events, in the order of `payment_hashes`.
+pub fn reconcile(
+    payment_hashes: &[String],
+    node_invoices: &HashMap<String, &CustomInvoice>,
+    recorded: &HashMap<String, (EventType, DateTime<Utc>)>,
+) -> InvoiceReconciliationReport {
+    let invoices: Vec<InvoiceReconciliation> = payment_hashes
+        .iter()
+        .map(|hash| {
+            let invoice = node_invoices.get(hash);
+            let recorded = recorded.get(hash);
+            let recorded_status = recorded.and_then(|(event_type, _)| recorded_status(event_type));
+            let status = invoice.map(|invoice| invoice.state);
+
+            let drift = match (status, recorded_status) {
+                (None, _) => Some(InvoiceDrift::MissingOnNode),
+                (Some(_), None) => Some(InvoiceDrift::NotRecorded),
+                (Some(status), Some(recorded)) if status != recorded => {
+                    Some(InvoiceDrift::StatusChanged)
+                }
+                _ => None,
+            };
+
+            InvoiceReconciliation {
+                payment_hash: hash.clone(),
+                status,
+                value: invoice.map(|invoice| invoice.value),
+                settle_date: invoice
+                    .and_then(|invoice| invoice.settle_date)
+                    .filter(|date| *date > 0),
+                expires_at: invoice.and_then(|invoice| invoice.expires_at),
+                recorded_status,
+                recorded_event: recorded.map(|(event_type, _)| event_type.clone()),
+                recorded_at: recorded.map(|(_, at)| *at),
+                drift,
+            }
+        })
+        .collect();
+
+    let drifted = invoices
+        .iter()
+        .filter(|invoice| invoice.drift.is_some())
+        .count();
+    InvoiceReconciliationReport {
+        checked: invoices.len(),
+        in_sync: invoices.len() - drifted,
+        drifted,
+        invoices,
+    }
+}
+
+/// Service reconciling invoice states.
+pub struct InvoiceReconciliationService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> InvoiceReconciliationService<'a> {
+    /// Creates a new InvoiceReconciliationService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Reconciles the given hashes against the node's invoices.
+    ///
+    /// Hashes are expected lowercase and without duplicates.
+    pub async fn reconcile(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        payment_hashes: &[String],
+        node_invoices: &[CustomInvoice],
+    ) -> ServiceResult<InvoiceReconciliationReport> {
+        let recorded = EventRepository::new(self.pool)
+            .get_latest_invoice_events(account_id, node_id, payment_hashes)
+            .await?;
+        let node_invoices: HashMap<String, &CustomInvoice> = node_invoices
+            .iter()
+            .map(|invoice| (invoice.payment_hash.to_lowercase(), invoice))
+            .collect();
+
+        Ok(reconcile(payment_hashes, &node_invoices, &recorded))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invoice(hash: &str, state: InvoiceStatus) -> CustomInvoice {
+        CustomInvoice {
+            payment_hash: hash.to_string(),
+            state,
+            value: Amount::from_msat(5000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn flags_invoices_whose_states_drifted() {
+        let hashes: Vec<String> = ["aa", "bb", "cc", "dd"].map(String::from).to_vec();
+        let settled = invoice("aa", InvoiceStatus::Settled);
+        let open = invoice("bb", InvoiceStatus::Open);
+        let expired = invoice("cc", InvoiceStatus::Expired);
+        let node_invoices = HashMap::from([
+            ("aa".to_string(), &settled),
+            ("bb".to_string(), &open),
+            ("cc".to_string(), &expired),
+        ]);
+        let now = Utc::now();
+        let recorded = HashMap::from([
+            ("aa".to_string(), (EventType::InvoiceCreated, now)),
+            ("bb".to_string(), (EventType::InvoiceAccepted, now)),
+            ("dd".to_string(), (EventType::InvoiceSettled, now)),
+        ]);
+
+        let report = reconcile(&hashes, &node_invoices, &recorded);
+        let drifts: Vec<Option<InvoiceDrift>> = report
+            .invoices
+            .iter()
+            .map(|invoice| invoice.drift)
+            .collect();
+        assert_eq!(
+            drifts,
+            vec![
+                Some(InvoiceDrift::StatusChanged),
+                None,
+                Some(InvoiceDrift::NotRecorded),
+                Some(InvoiceDrift::MissingOnNode),
+            ]
+        );
+        assert_eq!((report.checked, report.in_sync, report.drifted), (4, 1, 3));
+        assert_eq!(report.invoices[0].status, Some(InvoiceStatus::Settled));
+        assert_eq!(
+            report.invoices[0].recorded_status,
+            Some(InvoiceStatus::Open)
+        );
+    }
+}
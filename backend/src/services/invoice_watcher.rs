@@ -0,0 +1,142 @@
+//! Tracks open invoices and fires a callback when they reach expiry unpaid.
+//!
+//! Each watched invoice gets a sleeping task keyed by its payment hash. Settling
+//! or cancelling the invoice aborts the task, so only invoices that are still
+//! open at their expiry time trigger the callback.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::AbortHandle;
+
+/// How long past expiry a cancellation is still treated as the expiry itself.
+///
+/// LND cancels expired invoices on its own, and that cancellation can arrive
+/// slightly before the watcher wakes up.
+const EXPIRY_GRACE_SECS: i64 = 5;
+
+struct PendingInvoice {
+    expires_at: i64,
+    task: AbortHandle,
+}
+
+/// Schedules expiry callbacks for open invoices.
+#[derive(Clone, Default)]
+pub struct InvoiceExpiryWatcher {
+    pending: Arc<Mutex<HashMap<Vec<u8>, PendingInvoice>>>,
+}
+
+impl InvoiceExpiryWatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `on_expiry` at `expires_at` (Unix seconds) unless the invoice is
+    /// unwatched first. Watching a hash again replaces its previous schedule.
+    pub fn watch<F>(&self, hash: Vec<u8>, expires_at: i64, on_expiry: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let delay = (expires_at - chrono::Utc::now().timestamp()).max(0) as u64;
+        let pending = self.pending.clone();
+        let key = hash.clone();
+
+        let mut map = self.pending.lock().unwrap();
+        let task = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(delay)).await;
+            if take_due(&mut pending.lock().unwrap(), &key, expires_at) {
+                on_expiry.await;
+            }
+        })
+        .abort_handle();
+
+        if let Some(previous) = map.insert(hash, PendingInvoice { expires_at, task }) {
+            previous.task.abort();
+        }
+    }
+
+    /// Stops watching an invoice, e.g. once it has been paid.
+    pub fn unwatch(&self, hash: &[u8]) {
+        if let Some(pending) = self.pending.lock().unwrap().remove(hash) {
+            pending.task.abort();
+        }
+    }
+
+    /// Stops watching an invoice cancelled before it expired.
+    ///
+    /// A cancellation at (or just before) the expiry time is the node expiring
+    /// the invoice itself, so the watcher is left to report it.
+    pub fn unwatch_before_expiry(&self, hash: &[u8], now: i64) {
+        let mut map = self.pending.lock().unwrap();
+        let expired = map
+            .get(hash)
+            .is_some_and(|pending| now + EXPIRY_GRACE_SECS >= pending.expires_at);
+        if expired {
+            return;
+        }
+        if let Some(pending) = map.remove(hash) {
+            pending.task.abort();
+        }
+    }
+}
+
+/// Removes the schedule of `hash` if it is still the one due at `expires_at`.
+///
+/// A replaced task can wake up before it is aborted, and must leave the schedule
+/// that replaced it in place.
+fn take_due(pending: &mut HashMap<Vec<u8>, PendingInvoice>, hash: &[u8], expires_at: i64) -> bool {
+    if pending
+        .get(hash)
+        .is_some_and(|invoice| invoice.expires_at == expires_at)
+    {
+        pending.remove(hash);
+        true
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn fires_only_for_invoices_left_open() {
+        let watcher = InvoiceExpiryWatcher::new();
+        let fired = Arc::new(AtomicUsize::new(0));
+        let now = chrono::Utc::now().timestamp();
+
+        for hash in [b"a".to_vec(), b"b".to_vec()] {
+            let fired = fired.clone();
+            watcher.watch(hash, now, async move {
+                fired.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        let later = fired.clone();
+        watcher.watch(b"c".to_vec(), now + 3_600, async move {
+            later.fetch_add(1, Ordering::SeqCst);
+        });
+
+        watcher.unwatch(b"a");
+        watcher.unwatch_before_expiry(b"c", now);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(fired.load(Ordering::SeqCst), 1);
+        assert!(watcher.pending.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn replaced_schedules_survive_the_old_task() {
+        let watcher = InvoiceExpiryWatcher::new();
+        let now = chrono::Utc::now().timestamp();
+        watcher.watch(b"a".to_vec(), now + 3_600, async {});
+
+        let mut pending = watcher.pending.lock().unwrap();
+        assert!(!take_due(&mut pending, b"a", now));
+        assert!(pending.contains_key(b"a".as_slice()));
+        assert!(take_due(&mut pending, b"a", now + 3_600));
+        assert!(pending.is_empty());
+    }
+}
@@ -0,0 +1,270 @@
+//! `invoice.settled` webhooks for shops and other payment receivers.
+//!
+//! When a node reports an invoice settled, a delivery carrying the preimage,
+//! amount, memo and the payment's annotation is queued for the account's
+//! `invoice_webhook.url`. Deliveries are stored before they are sent and
+//! retried with growing delays until the endpoint answers with a 2xx status,
+//! so each settlement arrives at least once. Every attempt carries the same
+//! `Idempotency-Key`, and deliveries of one invoice are sent in order.
+//! Past deliveries are listed under `GET /api/webhooks/deliveries`.
+
+use crate::database::models::{DeliveryStatus, Event, EventType, WebhookDelivery};
+use crate::errors::ServiceResult;
+use crate::repositories::payment_annotation_repository::PaymentAnnotationRepository;
+use crate::repositories::webhook_delivery_repository::WebhookDeliveryRepository;
+use crate::services::settings_service::SettingsService;
+use crate::services::task_supervisor;
+use chrono::{DateTime, Duration, Utc};
+use reqwest::Client;
+use serde_json::{Value, json};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+pub const INVOICE_SETTLED_TOPIC: &str = "invoice.settled";
+
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// Deliveries sent per check.
+const BATCH_SIZE: i64 = 50;
+const MAX_ATTEMPTS: i64 = 12;
+/// Delay before the first retry, doubled for each further one.
+const RETRY_DELAY: Duration = Duration::seconds(30);
+const MAX_RETRY_DELAY: Duration = Duration::hours(2);
+
+/// When to retry a delivery that failed its `attempts`th attempt, `None` once
+/// it is out of attempts.
+pub fn retry_at(attempts: i64, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if attempts >= MAX_ATTEMPTS {
+        return None;
+    }
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    let delay = RETRY_DELAY * 2_i32.pow(exponent);
+    Some(now + delay.min(MAX_RETRY_DELAY))
+}
+
+/// Body of an `invoice.settled` delivery.
+pub fn settled_payload(event: &Event, idempotency_key: &str, metadata: Value) -> Value {
+    let data = serde_json::from_str::<Value>(&event.data).unwrap_or_default();
+    json!({
+        "topic": INVOICE_SETTLED_TOPIC,
+        "idempotency_key": idempotency_key,
+        "created_at": event.timestamp,
+        "data": {
+            "node_id": event.node_id,
+            "node_alias": event.node_alias,
+            "payment_hash": data["hash"],
+            "preimage": data["preimage"],
+            "amount_msat": data["value_msat"],
+            "memo": data["memo"],
+            "payment_request": data["payment_request"],
+            "creation_date": data["creation_date"],
+            "settled_at": event.timestamp,
+            "metadata": metadata,
+        },
+    })
+}
+
+/// Queues the `invoice.settled` delivery of a settled invoice event, if the
+/// account has an invoice webhook.
+pub async fn enqueue_settled_invoice(pool: &SqlitePool, event: &Event) -> ServiceResult<()> {
+    if event.event_type != EventType::InvoiceSettled {
+        return Ok(());
+    }
+    let Some(url) = SettingsService::new(pool)
+        .get_settings(&event.account_id)
+        .await?
+        .invoice_webhook
+        .url
+    else {
+        return Ok(());
+    };
+
+    let data = serde_json::from_str::<Value>(&event.data).unwrap_or_default();
+    let Some(payment_hash) = data["hash"].as_str() else {
+        tracing::warn!("Settled invoice event {} has no payment hash", event.id);
+        return Ok(());
+    };
+    let metadata = match PaymentAnnotationRepository::new(pool)
+        .get_annotation(&event.account_id, payment_hash)
+        .await?
+    {
+        Some(annotation) => json!({ "tags": annotation.tags, "notes": annotation.notes }),
+        None => json!({}),
+    };
+
+    // The node reporting a settlement twice must not pay out twice
+    let idempotency_key = format!(
+        "{}:{}:{}",
+        INVOICE_SETTLED_TOPIC, event.node_id, payment_hash
+    );
+    let payload = settled_payload(event, &idempotency_key, metadata);
+    let queued = WebhookDeliveryRepository::new(pool)
+        .enqueue(&pending_delivery(
+            &event.account_id,
+            &event.node_id,
+            INVOICE_SETTLED_TOPIC,
+            idempotency_key,
+            payment_hash,
+            url,
+            payload,
+        ))
+        .await?;
+    if !queued {
+        tracing::debug!("Settlement of invoice {} is already queued", payment_hash);
+    }
+    Ok(())
+}
+
+/// A delivery of any topic, due now.
+pub fn pending_delivery(
+    account_id: &str,
+    node_id: &str,
+    topic: &str,
+    idempotency_key: String,
+    ordering_key: &str,
+    url: String,
+    payload: Value,
+) -> WebhookDelivery {
+    let now = Utc::now();
+    WebhookDelivery {
+        id: Uuid::now_v7().to_string(),
+        account_id: account_id.to_string(),
+        node_id: node_id.to_string(),
+        topic: topic.to_string(),
+        idempotency_key,
+        ordering_key: ordering_key.to_string(),
+        url,
+        payload,
+        status: DeliveryStatus::Pending,
+        attempts: 0,
+        next_attempt_at: now,
+        last_error: None,
+        last_status_code: None,
+        delivered_at: None,
+        created_at: now,
+    }
+}
+
+/// Sends the due deliveries, recording each attempt.
+pub async fn deliver_due(pool: &SqlitePool, client: &Client) -> ServiceResult<()> {
+    let repo = WebhookDeliveryRepository::new(pool);
+    for delivery in repo.get_due_deliveries(Utc::now(), BATCH_SIZE).await? {
+        let result = client
+            .post(&delivery.url)
+            .header("User-Agent", "NodeGaze/1.0")
+            .header("Idempotency-Key", &delivery.idempotency_key)
+            .header("X-NodeGaze-Topic", &delivery.topic)
+            .header("X-NodeGaze-Delivery", &delivery.id)
+            .json(&delivery.payload)
+            .send()
+            .await;
+
+        let (error, status_code) = match result {
+            Ok(response) if response.status().is_success() => {
+                repo.mark_delivered(&delivery.id, response.status().as_u16().into())
+                    .await?;
+                continue;
+            }
+            Ok(response) => (
+                format!("Endpoint answered with status {}", response.status()),
+                Some(response.status().as_u16().into()),
+            ),
+            Err(e) => (e.to_string(), None),
+        };
+
+        let retry_at = retry_at(delivery.attempts + 1, Utc::now());
+        if retry_at.is_none() {
+            tracing::warn!(
+                "Giving up on {} delivery {} to {}: {}",
+                delivery.topic,
+                delivery.id,
+                delivery.url,
+                error
+            );
+        }
+        repo.mark_attempt_failed(&delivery.id, &error, status_code, retry_at)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Sends due webhook deliveries every five seconds.
+pub fn spawn_delivery_worker(pool: SqlitePool) {
+    let client = Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .expect("Failed to create HTTP client");
+
+    task_supervisor::supervise("webhook_delivery", move |task| {
+        let pool = pool.clone();
+        let client = client.clone();
+        async move {
+            // The first run waits for startup migrations
+            let mut interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + CHECK_INTERVAL,
+                CHECK_INTERVAL,
+            );
+            loop {
+                interval.tick().await;
+                let result = deliver_due(&pool, &client).await;
+                if let Err(e) = &result {
+                    tracing::error!("Webhook delivery failed: {}", e);
+                }
+                task.record_run(&result);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::EventSeverity;
+
+    #[test]
+    fn retries_back_off_until_attempts_run_out() {
+        let now = Utc::now();
+        assert_eq!(retry_at(1, now), Some(now + Duration::seconds(30)));
+        assert_eq!(retry_at(3, now), Some(now + Duration::minutes(2)));
+        assert_eq!(retry_at(11, now), Some(now + MAX_RETRY_DELAY));
+        assert_eq!(retry_at(MAX_ATTEMPTS, now), None);
+    }
+
+    #[test]
+    fn settled_payload_carries_the_preimage() {
+        let now = Utc::now();
+        let event = Event {
+            id: "event".to_string(),
+            account_id: "account".to_string(),
+            user_id: "user".to_string(),
+            node_id: "node".to_string(),
+            node_alias: "alias".to_string(),
+            event_type: EventType::InvoiceSettled,
+            severity: EventSeverity::Info,
+            title: "Invoice Settled".to_string(),
+            description: "Invoice settled for 5000 msat".to_string(),
+            data: json!({
+                "hash": "ab".repeat(32),
+                "preimage": "cd".repeat(32),
+                "value_msat": 5000,
+                "memo": "order 42",
+            })
+            .to_string(),
+            notifications_id: None,
+            timestamp: now,
+            acknowledged_by: None,
+            acknowledged_at: None,
+            created_at: now,
+            updated_at: now,
+            is_deleted: false,
+            deleted_at: None,
+        };
+
+        let payload = settled_payload(&event, "key", json!({ "tags": ["shop"] }));
+        assert_eq!(payload["topic"], INVOICE_SETTLED_TOPIC);
+        assert_eq!(payload["data"]["preimage"], "cd".repeat(32));
+        assert_eq!(payload["data"]["amount_msat"], 5000);
+        assert_eq!(payload["data"]["memo"], "order 42");
+        assert_eq!(payload["data"]["metadata"]["tags"][0], "shop");
+    }
+}
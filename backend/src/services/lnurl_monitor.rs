@@ -0,0 +1,380 @@
+//! Periodic checks of Lightning Address / LNURL-pay endpoints served by a node.
+//!
+//! Each check resolves the endpoint, validates its LUD-06 pay parameters and
+//! requests an invoice for the minimum amount. The invoice must pay the connected
+//! node, commit to the endpoint's metadata and be known to the node, which shows
+//! the address server is creating invoices on it. Nothing is paid. A target that
+//! starts failing is recorded as an `LnurlCheckFailed` event and one that
+//! passes again as an `LnurlCheckRecovered` event; a failure is reported again
+//! only after it recovers and fails anew.
+
+use crate::config;
+use crate::database::models::{CreateEvent, Event, EventSeverity, EventType};
+use crate::errors::ServiceResult;
+use crate::services::event_service::EventService;
+use crate::services::node_manager::LightningClient;
+use crate::services::task_supervisor;
+use crate::utils::NodeInfo;
+use bitcoin::bech32;
+use bitcoin::hashes::{Hash, sha256};
+use chrono::Utc;
+use lightning::ln::PaymentHash;
+use lightning_invoice::{Bolt11Invoice, Bolt11InvoiceDescription};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+/// LUD-06 `payRequest` response.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PayRequest {
+    tag: String,
+    callback: String,
+    min_sendable: u64,
+    max_sendable: u64,
+    metadata: String,
+}
+
+/// LUD-06 callback response.
+#[derive(Debug, Deserialize)]
+struct PayRequestInvoice {
+    pr: String,
+}
+
+/// Validated pay parameters of an LNURL-pay endpoint.
+#[derive(Debug, PartialEq)]
+pub struct PayParams {
+    pub callback: String,
+    pub min_sendable_msat: u64,
+    pub metadata: String,
+}
+
+/// Turns a lightning address, bech32 `lnurl` or URL into the URL to query.
+pub fn resolve_target(target: &str) -> Result<String, String> {
+    let target = target.trim();
+    if target.starts_with("https://") || target.starts_with("http://") {
+        return Ok(target.to_string());
+    }
+
+    let lowercase = target.to_lowercase();
+    let encoded = lowercase.strip_prefix("lightning:").unwrap_or(&lowercase);
+    if encoded.starts_with("lnurl1") {
+        let (_, data) = bech32::decode(encoded).map_err(|err| format!("Invalid LNURL: {err}"))?;
+        return String::from_utf8(data).map_err(|_| "LNURL does not encode a URL".to_string());
+    }
+
+    match target.split_once('@') {
+        Some((user, domain)) if !user.is_empty() && domain.contains('.') => {
+            let scheme = if domain.ends_with(".onion") {
+                "http"
+            } else {
+                "https"
+            };
+            Ok(format!("{scheme}://{domain}/.well-known/lnurlp/{user}"))
+        }
+        _ => Err(format!(
+            "'{target}' is not a lightning address, LNURL or URL"
+        )),
+    }
+}
+
+/// Parses and validates an LNURL-pay response.
+pub fn parse_pay_request(body: &Value) -> Result<PayParams, String> {
+    if body.get("status").and_then(Value::as_str) == Some("ERROR") {
+        return Err(format!(
+            "Endpoint returned an error: {}",
+            body.get("reason")
+                .and_then(Value::as_str)
+                .unwrap_or("no reason given")
+        ));
+    }
+
+    let request: PayRequest = serde_json::from_value(body.clone())
+        .map_err(|err| format!("Malformed payRequest response: {err}"))?;
+    if request.tag != "payRequest" {
+        return Err(format!("Unexpected tag '{}'", request.tag));
+    }
+    if request.min_sendable == 0 || request.min_sendable > request.max_sendable {
+        return Err(format!(
+            "Invalid sendable range {}..{} msat",
+            request.min_sendable, request.max_sendable
+        ));
+    }
+
+    let metadata: Vec<Value> = serde_json::from_str(&request.metadata)
+        .map_err(|_| "Metadata is not a JSON array".to_string())?;
+    let has_description = metadata.iter().any(|entry| {
+        entry.get(0).and_then(Value::as_str) == Some("text/plain")
+            && entry.get(1).is_some_and(Value::is_string)
+    });
+    if !has_description {
+        return Err("Metadata has no text/plain description".to_string());
+    }
+
+    Ok(PayParams {
+        callback: request.callback,
+        min_sendable_msat: request.min_sendable,
+        metadata: request.metadata,
+    })
+}
+
+/// Checks a node's LNURL-pay targets and records failures as events.
+pub struct LnurlMonitor {
+    http_client: Client,
+    pool: SqlitePool,
+    account_id: String,
+    user_id: String,
+    info: NodeInfo,
+    node: Arc<Mutex<Box<dyn LightningClient + Send + Sync + 'static>>>,
+    targets: Vec<String>,
+    interval: Duration,
+}
+
+impl LnurlMonitor {
+    /// Starts monitoring the configured targets against a node, if any are configured.
+    pub fn start_for_node(
+        pool: SqlitePool,
+        account_id: String,
+        user_id: String,
+        info: NodeInfo,
+        node: Arc<Mutex<Box<dyn LightningClient + Send + Sync + 'static>>>,
+    ) {
+        let config = match config::shared() {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::warn!("LNURL monitor not started: {}", e);
+                return;
+            }
+        };
+        if config.lnurl_monitor_targets.is_empty() {
+            return;
+        }
+
+        let http_client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        let monitor = LnurlMonitor {
+            http_client,
+            pool,
+            account_id,
+            user_id,
+            info,
+            node,
+            targets: config.lnurl_monitor_targets.clone(),
+            interval: Duration::from_secs(config.lnurl_monitor_interval_seconds.max(60)),
+        };
+        tokio::spawn(monitor.run());
+    }
+
+    async fn run(self) {
+        let task = task_supervisor::track(
+            format!("lnurl_monitor:{}", self.info.pubkey),
+            Some(&self.account_id),
+        );
+        let mut failing = HashSet::new();
+        let mut interval = tokio::time::interval(self.interval);
+
+        loop {
+            interval.tick().await;
+            let mut result = Ok(());
+            for target in &self.targets {
+                match self.check(target).await {
+                    Ok(()) => {
+                        if failing.remove(target) {
+                            self.record_recovery(target).await;
+                        }
+                    }
+                    Err(reason) => {
+                        tracing::warn!("LNURL check of {} failed: {}", target, reason);
+                        if failing.insert(target.clone()) {
+                            self.record_failure(target, &reason).await;
+                        }
+                        result = Err(format!("{target}: {reason}"));
+                    }
+                }
+            }
+            task.record_run(&result);
+        }
+    }
+
+    /// Runs one end-to-end check of a target.
+    async fn check(&self, target: &str) -> Result<(), String> {
+        let url = resolve_target(target)?;
+        let body: Value = self
+            .http_client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|err| format!("Could not reach {url}: {err}"))?
+            .json()
+            .await
+            .map_err(|err| format!("Endpoint did not return JSON: {err}"))?;
+        let params = parse_pay_request(&body)?;
+
+        let amount_msat = params.min_sendable_msat;
+        let invoice: PayRequestInvoice = self
+            .http_client
+            .get(&params.callback)
+            .query(&[("amount", amount_msat)])
+            .send()
+            .await
+            .map_err(|err| format!("Could not reach callback: {err}"))?
+            .json()
+            .await
+            .map_err(|err| format!("Callback did not return an invoice: {err}"))?;
+
+        let invoice = Bolt11Invoice::from_str(&invoice.pr)
+            .map_err(|err| format!("Callback returned an invalid invoice: {err}"))?;
+        if invoice.amount_milli_satoshis() != Some(amount_msat) {
+            return Err(format!(
+                "Invoice amount {:?} msat does not match the requested {amount_msat} msat",
+                invoice.amount_milli_satoshis()
+            ));
+        }
+        let payee = invoice
+            .payee_pub_key()
+            .copied()
+            .unwrap_or_else(|| invoice.recover_payee_pub_key());
+        if payee.serialize() != self.info.pubkey.serialize() {
+            return Err(format!("Invoice pays {payee} instead of this node"));
+        }
+
+        let metadata_hash = sha256::Hash::hash(params.metadata.as_bytes()).to_byte_array();
+        let commits_to_metadata = match invoice.description() {
+            Bolt11InvoiceDescription::Hash(hash) => {
+                let hash: &[u8] = hash.0.as_ref();
+                hash == metadata_hash.as_slice()
+            }
+            Bolt11InvoiceDescription::Direct(_) => false,
+        };
+        if !commits_to_metadata {
+            return Err("Invoice description hash does not match the metadata".to_string());
+        }
+
+        let payment_hash: &[u8] = invoice.payment_hash().as_ref();
+        let payment_hash: [u8; 32] = payment_hash
+            .try_into()
+            .map_err(|_| "Invoice has a malformed payment hash".to_string())?;
+        self.node
+            .lock()
+            .await
+            .get_invoice_details(&PaymentHash(payment_hash))
+            .await
+            .map_err(|err| format!("Node does not know the generated invoice: {err}"))?;
+
+        Ok(())
+    }
+
+    async fn record_failure(&self, target: &str, reason: &str) {
+        let result = self
+            .record_event(
+                EventType::LnurlCheckFailed,
+                EventSeverity::Critical,
+                "LNURL Check Failed",
+                format!("Payments to {target} may fail: {reason}"),
+                json!({ "target": target, "reason": reason }),
+            )
+            .await;
+
+        if let Err(e) = result {
+            tracing::error!("Failed to record LNURL check failure for {}: {}", target, e);
+        }
+    }
+
+    async fn record_recovery(&self, target: &str) {
+        let result = self
+            .record_event(
+                EventType::LnurlCheckRecovered,
+                EventSeverity::Info,
+                "LNURL Check Recovered",
+                format!("Payments to {target} work again"),
+                json!({ "target": target }),
+            )
+            .await;
+
+        if let Err(e) = result {
+            tracing::error!(
+                "Failed to record LNURL check recovery for {}: {}",
+                target,
+                e
+            );
+        }
+    }
+
+    async fn record_event(
+        &self,
+        event_type: EventType,
+        severity: EventSeverity,
+        title: &str,
+        description: String,
+        data: Value,
+    ) -> ServiceResult<Option<Event>> {
+        EventService::new(&self.pool)
+            .create_and_dispatch_event(CreateEvent {
+                id: Uuid::now_v7().to_string(),
+                account_id: self.account_id.clone(),
+                user_id: self.user_id.clone(),
+                node_id: self.info.pubkey.to_string(),
+                node_alias: self.info.alias.clone(),
+                event_type,
+                severity,
+                title: title.to_string(),
+                description,
+                data: data.to_string(),
+                notifications_id: None,
+                timestamp: Utc::now(),
+            })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_targets() {
+        assert_eq!(
+            resolve_target("tips@example.com").unwrap(),
+            "https://example.com/.well-known/lnurlp/tips"
+        );
+        assert_eq!(
+            resolve_target("https://example.com/lnurlp/tips").unwrap(),
+            "https://example.com/lnurlp/tips"
+        );
+        assert!(resolve_target("not-an-address").is_err());
+    }
+
+    #[test]
+    fn validates_pay_request() {
+        let body = json!({
+            "tag": "payRequest",
+            "callback": "https://example.com/callback",
+            "minSendable": 1000,
+            "maxSendable": 100000000,
+            "metadata": "[[\"text/plain\",\"Tips\"]]"
+        });
+        let params = parse_pay_request(&body).unwrap();
+        assert_eq!(params.min_sendable_msat, 1000);
+
+        let mut bad_metadata = body.clone();
+        bad_metadata["metadata"] = json!("[]");
+        assert!(parse_pay_request(&bad_metadata).is_err());
+
+        let error = json!({ "status": "ERROR", "reason": "user not found" });
+        assert!(
+            parse_pay_request(&error)
+                .unwrap_err()
+                .contains("user not found")
+        );
+    }
+}
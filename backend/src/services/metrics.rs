@@ -0,0 +1,376 @@
+//! Prometheus text exposition of a node's channels, for `GET /api/metrics`.
+//!
+//! Node-level gauges sum the node's channels. Accounts that turn on
+//! `channel_metrics` also get gauges per channel, labeled by short channel id
+//! and peer, for channel-level heatmaps. Each channel adds a series per gauge,
+//! so only the largest `METRICS_MAX_CHANNELS` channels are exported and
+//! `nodegaze_channel_series_dropped` counts the rest.
+//!
+//! The calls made to the node under its RPC budget follow, and the server's
+//! own request latencies, response codes and slow database statements are
+//! appended to every node's exposition.
+
+use crate::middleware::request_metrics::{LATENCY_BUCKETS, RequestMetrics};
+use crate::services::rpc_budget::RpcCounts;
+use crate::utils::amount::Amount;
+use crate::utils::{ChannelState, ChannelSummary, NodeInfo};
+use std::fmt::{Display, Write};
+
+/// Name, help text and value of a gauge with one series per channel.
+type ChannelGauge = (&'static str, &'static str, fn(&ChannelSummary) -> f64);
+
+/// Satoshis of an amount, keeping the msat as the fraction.
+fn sat(amount: Amount) -> f64 {
+    amount.msat() as f64 / 1000.0
+}
+
+/// Escapes a label value as the text format requires.
+pub fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Builds an exposition one metric family at a time.
+struct Exposition {
+    text: String,
+}
+
+impl Exposition {
+    fn family(&mut self, name: &str, help: &str, kind: &str) {
+        let _ = writeln!(self.text, "# HELP {name} {help}");
+        let _ = writeln!(self.text, "# TYPE {name} {kind}");
+    }
+
+    fn gauge(&mut self, name: &str, help: &str) {
+        self.family(name, help, "gauge");
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(label, value)| format!("{label}=\"{}\"", escape_label(value)))
+            .collect();
+        if labels.is_empty() {
+            let _ = writeln!(self.text, "{name} {value}");
+        } else {
+            let _ = writeln!(self.text, "{name}{{{}}} {value}", labels.join(","));
+        }
+    }
+}
+
+/// Renders the gauges of a node's channels, with one series per channel for at
+/// most `max_channels` of them when `per_channel` is set.
+pub fn render_metrics(
+    node: &NodeInfo,
+    channels: &[ChannelSummary],
+    per_channel: bool,
+    max_channels: usize,
+) -> String {
+    let pubkey = node.pubkey.to_string();
+    let node_labels = [("node", pubkey.as_str()), ("alias", node.alias.as_str())];
+    let active = |channel: &&ChannelSummary| matches!(channel.channel_state, ChannelState::Active);
+    let mut out = Exposition {
+        text: String::new(),
+    };
+
+    let node_gauges: [(&str, &str, f64); 5] = [
+        (
+            "nodegaze_node_channels",
+            "Channels of the node.",
+            channels.len() as f64,
+        ),
+        (
+            "nodegaze_node_active_channels",
+            "Active channels of the node.",
+            channels.iter().filter(active).count() as f64,
+        ),
+        (
+            "nodegaze_node_local_balance_sat",
+            "Local balance across the node's channels, in satoshis.",
+            sat(channels.iter().map(|channel| channel.local_balance).sum()),
+        ),
+        (
+            "nodegaze_node_remote_balance_sat",
+            "Remote balance across the node's channels, in satoshis.",
+            sat(channels.iter().map(|channel| channel.remote_balance).sum()),
+        ),
+        (
+            "nodegaze_node_capacity_sat",
+            "Capacity of the node's channels, in satoshis.",
+            sat(channels.iter().map(|channel| channel.capacity).sum()),
+        ),
+    ];
+    for (name, help, value) in node_gauges {
+        out.gauge(name, help);
+        out.sample(name, &node_labels, value);
+    }
+
+    if !per_channel {
+        return out.text;
+    }
+
+    // The largest channels are kept when there are too many to export
+    let mut exported: Vec<&ChannelSummary> = channels.iter().collect();
+    exported.sort_by(|a, b| {
+        b.capacity
+            .cmp(&a.capacity)
+            .then(a.chan_id.0.cmp(&b.chan_id.0))
+    });
+    exported.truncate(max_channels);
+
+    let channel_gauges: [ChannelGauge; 4] = [
+        (
+            "nodegaze_channel_local_balance_sat",
+            "Local balance of the channel, in satoshis.",
+            |channel| sat(channel.local_balance),
+        ),
+        (
+            "nodegaze_channel_remote_balance_sat",
+            "Remote balance of the channel, in satoshis.",
+            |channel| sat(channel.remote_balance),
+        ),
+        (
+            "nodegaze_channel_capacity_sat",
+            "Capacity of the channel, in satoshis.",
+            |channel| sat(channel.capacity),
+        ),
+        (
+            "nodegaze_channel_active",
+            "Whether the channel is active.",
+            |channel| {
+                f64::from(u8::from(matches!(
+                    channel.channel_state,
+                    ChannelState::Active
+                )))
+            },
+        ),
+    ];
+    for (name, help, value) in channel_gauges {
+        out.gauge(name, help);
+        for channel in &exported {
+            let scid = channel.chan_id.to_block_format();
+            let peer = channel
+                .remote_pubkey
+                .map(|pubkey| pubkey.to_string())
+                .unwrap_or_default();
+            let labels = [
+                ("node", pubkey.as_str()),
+                ("scid", scid.as_str()),
+                ("peer", peer.as_str()),
+                ("peer_alias", channel.alias.as_deref().unwrap_or_default()),
+            ];
+            out.sample(name, &labels, value(channel));
+        }
+    }
+
+    out.gauge(
+        "nodegaze_channel_series_dropped",
+        "Channels left out of the per-channel gauges by METRICS_MAX_CHANNELS.",
+    );
+    out.sample(
+        "nodegaze_channel_series_dropped",
+        &node_labels,
+        (channels.len() - exported.len()) as u64,
+    );
+    out.text
+}
+
+/// Renders the counters of the calls made to a node under its RPC budget.
+pub fn render_rpc_metrics(node: &NodeInfo, counts: &RpcCounts) -> String {
+    let pubkey = node.pubkey.to_string();
+    let node_labels = [("node", pubkey.as_str()), ("alias", node.alias.as_str())];
+    let mut out = Exposition {
+        text: String::new(),
+    };
+
+    let counters: [(&str, &str, u64); 3] = [
+        (
+            "nodegaze_node_rpc_calls_total",
+            "RPC calls made to the node.",
+            counts.calls,
+        ),
+        (
+            "nodegaze_node_rpc_calls_delayed_total",
+            "RPC calls that waited for the node's budget.",
+            counts.delayed,
+        ),
+        (
+            "nodegaze_node_rpc_calls_shed_total",
+            "RPC calls refused because too many were waiting for the node's budget.",
+            counts.shed,
+        ),
+    ];
+    for (name, help, value) in counters {
+        out.family(name, help, "counter");
+        out.sample(name, &node_labels, value);
+    }
+    out.text
+}
+
+/// Renders the request latency histograms and response counters per route,
+/// and the number of database statements slower than `DB_SLOW_QUERY_MS`.
+pub fn render_server_metrics(requests: &RequestMetrics, slow_statements: u64) -> String {
+    let mut out = Exposition {
+        text: String::new(),
+    };
+
+    let name = "nodegaze_http_request_duration_seconds";
+    out.family(
+        name,
+        "Time taken to answer API requests, per route.",
+        "histogram",
+    );
+    for ((method, route), latency) in &requests.latency {
+        let labels = [("method", method.as_str()), ("route", route.as_str())];
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(latency.buckets) {
+            let le = bound.to_string();
+            let bucket_labels = [labels[0], labels[1], ("le", le.as_str())];
+            out.sample(&format!("{name}_bucket"), &bucket_labels, count);
+        }
+        let bucket_labels = [labels[0], labels[1], ("le", "+Inf")];
+        out.sample(&format!("{name}_bucket"), &bucket_labels, latency.count);
+        out.sample(&format!("{name}_sum"), &labels, latency.sum_seconds);
+        out.sample(&format!("{name}_count"), &labels, latency.count);
+    }
+
+    let name = "nodegaze_http_responses_total";
+    out.family(name, "API responses, per route and status code.", "counter");
+    for ((method, route, status), count) in &requests.responses {
+        let status = status.to_string();
+        let labels = [
+            ("method", method.as_str()),
+            ("route", route.as_str()),
+            ("status", status.as_str()),
+        ];
+        out.sample(name, &labels, count);
+    }
+
+    let name = "nodegaze_db_slow_statements_total";
+    out.family(
+        name,
+        "Database statements that took longer than DB_SLOW_QUERY_MS.",
+        "counter",
+    );
+    out.sample(name, &[], slow_statements);
+    out.text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::ShortChannelID;
+    use bitcoin::secp256k1::PublicKey;
+    use lightning::ln::features::NodeFeatures;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    fn channel(scid: &str, capacity: u64, state: ChannelState) -> ChannelSummary {
+        ChannelSummary {
+            chan_id: ShortChannelID::from_str(scid).unwrap(),
+            alias: Some("peer \"one\"".to_string()),
+            channel_state: state,
+            private: false,
+            remote_balance: Amount::from_sat(capacity / 4),
+            local_balance: Amount::from_sat(capacity / 2),
+            capacity: Amount::from_sat(capacity),
+            last_update: None,
+            uptime: None,
+            remote_pubkey: None,
+            channel_point: None,
+        }
+    }
+
+    #[test]
+    fn caps_per_channel_series_at_the_largest_channels() {
+        let node = NodeInfo {
+            pubkey: PublicKey::from_str(
+                "02eadbd9e7557375161df8b646776a547c5cbc2e95b3071ec81553f8ec2cea3b8c",
+            )
+            .unwrap(),
+            alias: "gaze".to_string(),
+            features: NodeFeatures::empty(),
+            version: None,
+            network: None,
+        };
+        let channels = vec![
+            channel("800000x1x0", 1_000_000, ChannelState::Active),
+            channel("800001x2x1", 5_000_000, ChannelState::Disabled),
+            channel("800002x3x0", 2_000_000, ChannelState::Active),
+        ];
+
+        let text = render_metrics(&node, &channels, false, 2);
+        assert!(text.contains("alias=\"gaze\"} 8000000"));
+        assert!(text.contains("nodegaze_node_active_channels{"));
+        assert!(!text.contains("nodegaze_channel_"));
+
+        let text = render_metrics(&node, &channels, true, 2);
+        assert!(text.contains("scid=\"800001x2x1\""));
+        assert!(text.contains("scid=\"800002x3x0\""));
+        assert!(!text.contains("scid=\"800000x1x0\""));
+        assert!(text.contains("peer_alias=\"peer \\\"one\\\"\"} 1250000"));
+        assert!(text.contains("nodegaze_channel_series_dropped{"));
+        assert!(
+            text.lines()
+                .any(|line| line.starts_with("nodegaze_channel_active{")
+                    && line.contains("800001x2x1")
+                    && line.ends_with(" 0"))
+        );
+
+        // Sub-satoshi balances are kept as the fraction
+        let mut channel = channel("800003x4x0", 10, ChannelState::Active);
+        channel.local_balance = Amount::from_msat(1_500);
+        let text = render_metrics(&node, &[channel], false, 1);
+        assert!(text.contains("alias=\"gaze\"} 1.5\n"));
+    }
+
+    #[test]
+    fn renders_rpc_budget_counters() {
+        let node = NodeInfo {
+            pubkey: PublicKey::from_str(
+                "02eadbd9e7557375161df8b646776a547c5cbc2e95b3071ec81553f8ec2cea3b8c",
+            )
+            .unwrap(),
+            alias: "gaze".to_string(),
+            features: NodeFeatures::empty(),
+            version: None,
+            network: None,
+        };
+        let counts = RpcCounts {
+            calls: 120,
+            delayed: 7,
+            shed: 2,
+        };
+
+        let text = render_rpc_metrics(&node, &counts);
+        assert!(text.contains("# TYPE nodegaze_node_rpc_calls_shed_total counter"));
+        assert!(text.contains("nodegaze_node_rpc_calls_total{node=\"02eadbd9"));
+        assert!(text.contains("alias=\"gaze\"} 7\n"));
+        assert!(text.contains("alias=\"gaze\"} 2\n"));
+    }
+
+    #[test]
+    fn renders_request_histograms_and_slow_statements() {
+        let mut requests = RequestMetrics::default();
+        requests.record("GET", "/api/events", 200, Duration::from_millis(40));
+        requests.record("GET", "/api/events", 503, Duration::from_secs(3));
+
+        let text = render_server_metrics(&requests, 2);
+        let labels = "method=\"GET\",route=\"/api/events\"";
+        assert!(text.contains("# TYPE nodegaze_http_request_duration_seconds histogram"));
+        assert!(text.contains(&format!(
+            "nodegaze_http_request_duration_seconds_bucket{{{labels},le=\"0.05\"}} 1"
+        )));
+        assert!(text.contains(&format!(
+            "nodegaze_http_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} 2"
+        )));
+        assert!(text.contains(&format!(
+            "nodegaze_http_request_duration_seconds_count{{{labels}}} 2"
+        )));
+        assert!(text.contains(&format!(
+            "nodegaze_http_responses_total{{{labels},status=\"503\"}} 1"
+        )));
+        assert!(text.contains("\nnodegaze_db_slow_statements_total 2\n"));
+    }
+}
@@ -5,13 +5,73 @@
 //! such as managing node connections or aggregating data.
 
 pub mod account_service;
-// pub mod credential_service; // Removed - unused service
+pub mod activity_feed;
+pub mod alert_escalation;
+pub mod anomaly_detector;
+pub mod auto_fees;
+pub mod backup;
+pub mod breach_watch;
+pub mod channel_acceptor;
+pub mod channel_flow;
+pub mod channel_tags;
+pub mod channel_tracker;
+pub mod credential_expiry;
+pub mod credential_service;
 pub mod data_aggregator;
+pub mod discord_bot;
+pub mod donations;
 pub mod email_service;
+pub mod event_backfill;
+pub mod event_bus;
+pub mod event_compaction;
 pub mod event_manager;
 pub mod event_service;
+pub mod event_sinks;
+pub mod event_source_service;
+pub mod event_stats;
+pub mod external_profiles;
+pub mod graph_cache;
+pub mod health;
+pub mod htlc_expiry;
 pub mod invite_service;
+pub mod invoice_decoder;
+pub mod invoice_reconciliation;
+pub mod invoice_watcher;
+pub mod invoice_webhooks;
+pub mod lnurl_monitor;
+pub mod metrics;
+pub mod network_stats;
+pub mod node_aggregate;
+pub mod node_capabilities;
+pub mod node_discovery;
+pub mod node_group_service;
+pub mod node_label_service;
 pub mod node_manager;
+pub mod node_networks;
+pub mod notification_breaker;
 pub mod notification_dispatcher;
+pub mod notification_replay;
 pub mod notification_service;
+pub mod payment_annotations;
+pub mod payment_stats;
+pub mod payment_tracker;
+pub mod payment_watches;
+pub mod peer_blocklist;
+pub mod peer_pings;
+pub mod peer_suggestions;
+pub mod policy_history;
+pub mod policy_watcher;
+pub mod price_history;
+pub mod public_profile;
+pub mod quota_service;
+pub mod raw_events;
+pub mod reports;
+pub mod rpc_budget;
+pub mod search;
+pub mod session_service;
+pub mod settings_service;
+pub mod stats_rollups;
+pub mod stuck_payments;
+pub mod task_supervisor;
+pub mod transaction_labels;
 pub mod user_service;
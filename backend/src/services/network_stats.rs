@@ -0,0 +1,367 @@
+//! Network-wide statistics computed from a node's view of the channel graph.
+//!
+//! The graph is read from the graph cache, but walking all of it still takes a
+//! while on mainnet, so results are cached per node for `STATS_CACHE_TTL` and
+//! refreshed in the background for every node whose stats were asked for. The node's own closeness rank is an estimate:
+//! computing the closeness of every node would take one graph walk per node, so
+//! it is compared against a fixed sample of nodes instead.
+
+use crate::errors::{LightningError, ServiceError, ServiceResult};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::services::graph_cache::{self, GraphFreshness};
+use crate::services::node_manager::LightningClient;
+use crate::services::node_manager::connect_node;
+use crate::services::task_supervisor;
+use crate::utils::GraphEdge;
+use bitcoin::secp256k1::PublicKey;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long computed stats are served before they are computed again.
+const STATS_CACHE_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// How often cached stats are recomputed in the background.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Number of nodes the closeness rank is estimated from.
+const CLOSENESS_SAMPLE_SIZE: usize = 200;
+
+/// Stats of the whole public graph.
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkStats {
+    /// Nodes with at least one public channel.
+    pub node_count: usize,
+    pub channel_count: usize,
+    pub total_capacity_sat: u64,
+    pub avg_channel_size_sat: u64,
+    /// Averages over the enabled channel directions.
+    pub avg_fee_rate_ppm: f64,
+    pub median_fee_rate_ppm: u64,
+    pub avg_base_fee_msat: f64,
+    /// Where the node stands in the graph, `None` if it has no public channels.
+    pub my_node: Option<NodeRank>,
+    pub computed_at: DateTime<Utc>,
+    /// Freshness of the cached graph the stats were computed from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub graph: Option<GraphFreshness>,
+}
+
+/// A node's position in the graph. Ranks start at 1.
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeRank {
+    pub channels: usize,
+    pub capacity_sat: u64,
+    pub channel_rank: usize,
+    pub capacity_rank: usize,
+    /// Inverse of the average hop count to every reachable node.
+    pub closeness: f64,
+    /// Approximate rank by closeness.
+    pub closeness_rank_estimate: usize,
+}
+
+/// Stats of a node and when they were computed.
+type CachedStats = (Instant, Arc<NetworkStats>);
+
+/// Computed stats per node pubkey.
+static STATS_CACHE: LazyLock<Mutex<HashMap<PublicKey, CachedStats>>> =
+    LazyLock::new(Default::default);
+
+/// Computes the stats of a graph as seen by `my_pubkey`.
+pub fn compute_network_stats(
+    edges: &[GraphEdge],
+    my_pubkey: &PublicKey,
+    now: DateTime<Utc>,
+) -> NetworkStats {
+    // Each node gets a dense index so the graph walks can use plain vectors
+    let mut index: HashMap<PublicKey, usize> = HashMap::new();
+    let mut adjacency: Vec<Vec<usize>> = Vec::new();
+    let mut capacities: Vec<u64> = Vec::new();
+    let mut node_index = |pubkey: PublicKey, adjacency: &mut Vec<Vec<usize>>| {
+        *index.entry(pubkey).or_insert_with(|| {
+            adjacency.push(Vec::new());
+            adjacency.len() - 1
+        })
+    };
+
+    let mut fee_rates = Vec::new();
+    let mut base_fees = Vec::new();
+    for edge in edges {
+        let a = node_index(edge.node1, &mut adjacency);
+        let b = node_index(edge.node2, &mut adjacency);
+        capacities.resize(adjacency.len(), 0);
+        adjacency[a].push(b);
+        adjacency[b].push(a);
+        capacities[a] += edge.capacity_sat;
+        capacities[b] += edge.capacity_sat;
+
+        for fee in [edge.node1_fee, edge.node2_fee].into_iter().flatten() {
+            fee_rates.push(fee.fee_rate_ppm);
+            base_fees.push(fee.base_fee_msat);
+        }
+    }
+    fee_rates.sort_unstable();
+
+    let node_count = adjacency.len();
+    let channel_count = edges.len();
+    let total_capacity_sat: u64 = edges.iter().map(|edge| edge.capacity_sat).sum();
+
+    let my_node = index.get(my_pubkey).map(|&me| {
+        let channels = adjacency[me].len();
+        let my_closeness = closeness(&adjacency, me);
+        let sample = sample_nodes(node_count, CLOSENESS_SAMPLE_SIZE);
+        let closer = sample
+            .iter()
+            .filter(|&&node| node != me && closeness(&adjacency, node) > my_closeness)
+            .count();
+
+        NodeRank {
+            channels,
+            capacity_sat: capacities[me],
+            channel_rank: 1 + adjacency.iter().filter(|n| n.len() > channels).count(),
+            capacity_rank: 1 + capacities.iter().filter(|&&c| c > capacities[me]).count(),
+            closeness: my_closeness,
+            closeness_rank_estimate: 1 + closer * node_count / sample.len().max(1),
+        }
+    });
+
+    NetworkStats {
+        node_count,
+        channel_count,
+        total_capacity_sat,
+        avg_channel_size_sat: total_capacity_sat / channel_count.max(1) as u64,
+        avg_fee_rate_ppm: average(&fee_rates),
+        median_fee_rate_ppm: fee_rates.get(fee_rates.len() / 2).copied().unwrap_or(0),
+        avg_base_fee_msat: average(&base_fees),
+        my_node,
+        computed_at: now,
+        graph: None,
+    }
+}
+
+fn average(values: &[u64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<u64>() as f64 / values.len() as f64
+}
+
+/// Evenly spaced node indexes, so repeated runs compare against the same nodes.
+fn sample_nodes(node_count: usize, sample_size: usize) -> Vec<usize> {
+    let step = node_count.div_ceil(sample_size).max(1);
+    (0..node_count).step_by(step).collect()
+}
+
+/// Closeness centrality of `node`: reachable nodes divided by the sum of their
+/// hop distances. Isolated nodes score zero.
+fn closeness(adjacency: &[Vec<usize>], node: usize) -> f64 {
+    let mut distance = vec![usize::MAX; adjacency.len()];
+    let mut queue = VecDeque::from([node]);
+    distance[node] = 0;
+
+    let (mut reached, mut total) = (0usize, 0usize);
+    while let Some(current) = queue.pop_front() {
+        for &next in &adjacency[current] {
+            if distance[next] == usize::MAX {
+                distance[next] = distance[current] + 1;
+                reached += 1;
+                total += distance[next];
+                queue.push_back(next);
+            }
+        }
+    }
+
+    if total == 0 {
+        0.0
+    } else {
+        reached as f64 / total as f64
+    }
+}
+
+/// Reads the graph and computes the stats of a node, replacing any cached ones.
+async fn refresh_stats(
+    pool: &SqlitePool,
+    client: &(dyn LightningClient + Send + Sync),
+) -> Result<Arc<NetworkStats>, LightningError> {
+    let pubkey = client.get_info().pubkey;
+    let (edges, graph) = graph_cache::graph_edges(pool, client).await?;
+
+    let stats =
+        tokio::task::spawn_blocking(move || compute_network_stats(&edges, &pubkey, Utc::now()))
+            .await
+            .map_err(|e| LightningError::GetGraphError(format!("Graph stats task failed: {e}")))?;
+
+    let stats = Arc::new(NetworkStats {
+        graph: Some(graph),
+        ..stats
+    });
+    if let Ok(mut cache) = STATS_CACHE.lock() {
+        cache.insert(pubkey, (Instant::now(), stats.clone()));
+    }
+    Ok(stats)
+}
+
+/// Returns the network stats seen by a node, computing them if none are cached.
+pub async fn get_network_stats(
+    pool: &SqlitePool,
+    client: &(dyn LightningClient + Send + Sync),
+) -> Result<Arc<NetworkStats>, LightningError> {
+    let pubkey = client.get_info().pubkey;
+    let cached = STATS_CACHE.lock().ok().and_then(|cache| {
+        cache
+            .get(&pubkey)
+            .filter(|(computed_at, _)| computed_at.elapsed() < STATS_CACHE_TTL)
+            .map(|(_, stats)| stats.clone())
+    });
+    if let Some(stats) = cached {
+        return Ok(stats);
+    }
+
+    refresh_stats(pool, client).await
+}
+
+/// Recomputes the stats of every node that has them cached.
+async fn refresh_cached_nodes(pool: &SqlitePool) -> ServiceResult<()> {
+    let pubkeys: Vec<PublicKey> = match STATS_CACHE.lock() {
+        Ok(cache) => cache.keys().copied().collect(),
+        Err(_) => return Ok(()),
+    };
+    if pubkeys.is_empty() {
+        return Ok(());
+    }
+    let credentials = CredentialRepository::new(pool)
+        .get_node_credentials(None, None)
+        .await?;
+
+    for pubkey in pubkeys {
+        let Some(credential) = credentials
+            .iter()
+            .find(|credential| PublicKey::from_str(&credential.node_id).ok() == Some(pubkey))
+        else {
+            // The node is no longer monitored, so its stats stop being served
+            if let Ok(mut cache) = STATS_CACHE.lock() {
+                cache.remove(&pubkey);
+            }
+            continue;
+        };
+
+        let result = async {
+            let client = connect_node(credential).await?;
+            refresh_stats(pool, client.as_ref()).await
+        }
+        .await
+        .map_err(|e: LightningError| ServiceError::ExternalService {
+            message: e.to_string(),
+        });
+        if let Err(e) = result {
+            tracing::warn!("Graph stats refresh of node {} failed: {}", pubkey, e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Keeps cached network stats fresh so requests rarely wait for a graph walk.
+pub fn spawn_network_stats_refresher(pool: SqlitePool) {
+    task_supervisor::supervise("network_stats", move |task| {
+        let pool = pool.clone();
+        async move {
+            let mut interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + REFRESH_INTERVAL,
+                REFRESH_INTERVAL,
+            );
+            loop {
+                interval.tick().await;
+                let result = refresh_cached_nodes(&pool).await;
+                if let Err(e) = &result {
+                    tracing::error!("Graph stats refresh failed: {}", e);
+                }
+                task.record_run(&result);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{EdgeFee, ShortChannelID};
+    use bitcoin::secp256k1::{Secp256k1, SecretKey};
+
+    fn pubkey(n: u8) -> PublicKey {
+        PublicKey::from_secret_key(&Secp256k1::new(), &SecretKey::from_slice(&[n; 32]).unwrap())
+    }
+
+    fn edge(id: u64, a: u8, b: u8, capacity_sat: u64, fee_rate_ppm: u64) -> GraphEdge {
+        let fee = EdgeFee {
+            base_fee_msat: 1000,
+            fee_rate_ppm,
+        };
+        GraphEdge {
+            channel_id: ShortChannelID(id),
+            node1: pubkey(a),
+            node2: pubkey(b),
+            capacity_sat,
+            node1_fee: Some(fee),
+            node2_fee: None,
+            node1_last_update: Some(1_700_000_000),
+            node2_last_update: None,
+        }
+    }
+
+    #[test]
+    fn computes_graph_totals_and_fees() {
+        // A hub (1) with three spokes, and one spoke linked to a fifth node
+        let edges = vec![
+            edge(1, 1, 2, 1_000_000, 100),
+            edge(2, 1, 3, 2_000_000, 200),
+            edge(3, 1, 4, 3_000_000, 300),
+            edge(4, 4, 5, 500_000, 1000),
+        ];
+
+        let stats = compute_network_stats(&edges, &pubkey(1), Utc::now());
+
+        assert_eq!(stats.node_count, 5);
+        assert_eq!(stats.channel_count, 4);
+        assert_eq!(stats.total_capacity_sat, 6_500_000);
+        assert_eq!(stats.avg_channel_size_sat, 1_625_000);
+        assert_eq!(stats.avg_fee_rate_ppm, 400.0);
+        assert_eq!(stats.median_fee_rate_ppm, 300);
+        assert_eq!(stats.avg_base_fee_msat, 1000.0);
+
+        let me = stats.my_node.unwrap();
+        assert_eq!(me.channels, 3);
+        assert_eq!(me.capacity_sat, 6_000_000);
+        assert_eq!(me.channel_rank, 1);
+        assert_eq!(me.capacity_rank, 1);
+        // Three nodes one hop away and one two hops away
+        assert_eq!(me.closeness, 4.0 / 5.0);
+        assert_eq!(me.closeness_rank_estimate, 1);
+    }
+
+    #[test]
+    fn nodes_outside_the_graph_have_no_rank() {
+        let edges = vec![edge(1, 1, 2, 1_000_000, 100)];
+
+        let stats = compute_network_stats(&edges, &pubkey(9), Utc::now());
+
+        assert!(stats.my_node.is_none());
+        assert_eq!(
+            compute_network_stats(&[], &pubkey(1), Utc::now()).node_count,
+            0
+        );
+    }
+
+    #[test]
+    fn samples_are_evenly_spread() {
+        assert_eq!(sample_nodes(10, 200).len(), 10);
+        assert_eq!(
+            sample_nodes(1000, 200),
+            (0..1000).step_by(5).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,466 @@
+//! Channels, payments and events combined across several of an account's nodes.
+//!
+//! An aggregate covers every node the user may see, or only those of one node
+//! group, and the nodes must run on one network unless mixing networks is
+//! allowed, see `node_networks`. Nodes are queried concurrently under the dashboard's time budgets; a
+//! node that can't be reached is listed under `errors` and the figures of the
+//! others are still returned.
+
+use crate::database::models::Credential;
+use crate::errors::{LightningError, ServiceError, ServiceResult};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::repositories::node_group_repository::NodeGroupRepository;
+use crate::services::data_aggregator::{NODE_CONNECT_TIMEOUT, NODE_QUERY_TIMEOUT};
+use crate::services::event_service::EventService;
+use crate::services::event_stats::EventStats;
+use crate::services::node_group_service::NodeGroupService;
+use crate::services::node_manager::LightningClient;
+use crate::services::node_manager::connect_node;
+use crate::services::node_networks::{check_single_network, fill_unknown_networks};
+use crate::services::payment_stats::{PaymentStats, StatsBucket};
+use crate::services::settings_service::SettingsService;
+use crate::services::stats_rollups::{StatsRollupService, is_stale, payment_refresh_since};
+use crate::services::stuck_payments::{
+    DEFAULT_STUCK_PAYMENT_MINUTES, StuckPayment, stuck_payments,
+};
+use crate::utils::amount::Amount;
+use crate::utils::jwt::Claims;
+use crate::utils::{ChannelState, ChannelSummary};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use futures::future::join_all;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::future::Future;
+use tokio::time::timeout;
+
+/// Which of the nodes the user may see an aggregate covers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NodeSelection<'a> {
+    /// ID or name of a node group; every visible node when absent
+    pub group: Option<&'a str>,
+    /// Whether nodes on different networks may be combined
+    pub allow_mixed_networks: bool,
+}
+
+/// A node left out of an aggregate.
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeFailure {
+    pub node_id: String,
+    pub node_alias: String,
+    pub error: String,
+}
+
+/// A channel and the node it belongs to.
+#[derive(Debug, Serialize)]
+pub struct NodeChannel {
+    pub node_id: String,
+    pub node_alias: String,
+    #[serde(flatten)]
+    pub channel: ChannelSummary,
+}
+
+/// Sums over the open channels of an aggregate.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct ChannelTotals {
+    pub channels: usize,
+    pub active_channels: usize,
+    pub capacity_sat: u64,
+    pub local_balance_sat: u64,
+    pub remote_balance_sat: u64,
+}
+
+impl ChannelTotals {
+    /// Sums the channels, leaving out closed ones.
+    pub fn of(channels: &[NodeChannel]) -> Self {
+        let mut totals = Self::default();
+        // Summed in msat so sub-satoshi balances add up
+        let (mut capacity, mut local_balance, mut remote_balance) =
+            (Amount::ZERO, Amount::ZERO, Amount::ZERO);
+        for NodeChannel { channel, .. } in channels {
+            match channel.channel_state {
+                ChannelState::Closed | ChannelState::Failed => continue,
+                ChannelState::Active | ChannelState::Splicing => totals.active_channels += 1,
+                _ => {}
+            }
+            totals.channels += 1;
+            capacity += channel.capacity;
+            local_balance += channel.local_balance;
+            remote_balance += channel.remote_balance;
+        }
+        totals.capacity_sat = capacity.sat();
+        totals.local_balance_sat = local_balance.sat();
+        totals.remote_balance_sat = remote_balance.sat();
+        totals
+    }
+}
+
+/// Response of `GET /api/aggregate/channels`.
+#[derive(Debug, Serialize)]
+pub struct AggregateChannels {
+    /// Nodes whose channels are included
+    pub node_ids: Vec<String>,
+    pub channels: Vec<NodeChannel>,
+    pub totals: ChannelTotals,
+    pub errors: Vec<NodeFailure>,
+}
+
+/// Response of `GET /api/aggregate/payments/stats`.
+#[derive(Debug, Serialize)]
+pub struct AggregatePaymentStats {
+    /// Nodes whose payments and forwards are included
+    pub node_ids: Vec<String>,
+    #[serde(flatten)]
+    pub stats: PaymentStats,
+    pub errors: Vec<NodeFailure>,
+}
+
+/// Response of `GET /api/aggregate/events/stats`.
+#[derive(Debug, Serialize)]
+pub struct AggregateEventStats {
+    /// Nodes whose events are counted
+    pub node_ids: Vec<String>,
+    #[serde(flatten)]
+    pub stats: EventStats,
+}
+
+/// Response of `GET /api/aggregate/payments/stuck`.
+#[derive(Debug, Serialize)]
+pub struct AggregateStuckPayments {
+    /// Nodes whose payments are included
+    pub node_ids: Vec<String>,
+    /// Minutes a payment has to be in flight to be listed
+    pub threshold_minutes: u32,
+    /// Longest stuck first
+    pub payments: Vec<StuckPayment>,
+    pub errors: Vec<NodeFailure>,
+}
+
+/// Service combining figures across nodes.
+pub struct NodeAggregateService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> NodeAggregateService<'a> {
+    /// Creates a new NodeAggregateService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Picks one credential per node the user may see, restricted to a node
+    /// group given by ID or name, rejecting nodes on different networks unless
+    /// mixing them is allowed.
+    async fn select_nodes(
+        &self,
+        claims: &Claims,
+        nodes: NodeSelection<'_>,
+    ) -> ServiceResult<Vec<Credential>> {
+        let group_node_ids = match nodes.group {
+            Some(group) => Some(self.group_node_ids(&claims.account_id, group).await?),
+            None => None,
+        };
+        let scope = NodeGroupService::new(self.pool)
+            .node_scope_for_claims(claims)
+            .await?;
+        let credentials = CredentialRepository::new(self.pool)
+            .get_node_credentials(Some(&claims.account_id), Some(&claims.sub))
+            .await?;
+
+        let mut selected: Vec<Credential> = credentials
+            .into_iter()
+            .filter(|credential| scope.allows(&credential.node_id))
+            .filter(|credential| {
+                group_node_ids
+                    .as_ref()
+                    .is_none_or(|node_ids| node_ids.contains(&credential.node_id))
+            })
+            .collect();
+
+        fill_unknown_networks(self.pool, &mut selected).await;
+        check_single_network(&selected, nodes.allow_mixed_networks)?;
+        Ok(selected)
+    }
+
+    async fn group_node_ids(&self, account_id: &str, group: &str) -> ServiceResult<Vec<String>> {
+        let repo = NodeGroupRepository::new(self.pool);
+        let by_id = repo
+            .get_group_by_id(group)
+            .await?
+            .filter(|found| found.account_id == account_id);
+        let found = match by_id {
+            Some(found) => found,
+            None => repo
+                .get_group_by_name(account_id, group)
+                .await?
+                .ok_or_else(|| ServiceError::not_found("Node group", group))?,
+        };
+        Ok(repo.get_group_node_ids(&found.id).await?)
+    }
+
+    /// Lists the channels of every selected node.
+    pub async fn channels(
+        &self,
+        claims: &Claims,
+        nodes: NodeSelection<'_>,
+    ) -> ServiceResult<AggregateChannels> {
+        let credentials = self.select_nodes(claims, nodes).await?;
+        let (results, errors) = query_nodes(&credentials, |client| async move {
+            client.list_channels().await
+        })
+        .await;
+
+        let channels: Vec<NodeChannel> = results
+            .into_iter()
+            .flat_map(|(credential, channels)| {
+                channels.into_iter().map(move |channel| NodeChannel {
+                    node_id: credential.node_id.clone(),
+                    node_alias: credential.node_alias.clone(),
+                    channel,
+                })
+            })
+            .collect();
+
+        Ok(AggregateChannels {
+            node_ids: node_ids(&credentials),
+            totals: ChannelTotals::of(&channels),
+            channels,
+            errors,
+        })
+    }
+
+    /// Computes payment statistics over the payments and forwards of every
+    /// selected node, from their hourly payment rollups and payment records.
+    ///
+    /// Only nodes whose rollups are stale are asked for their payments, to
+    /// refresh them; one that can't be reached is listed under `errors` and
+    /// counted with its last rollups.
+    pub async fn payment_stats(
+        &self,
+        claims: &Claims,
+        nodes: NodeSelection<'_>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        bucket: StatsBucket,
+        tz: Tz,
+    ) -> ServiceResult<AggregatePaymentStats> {
+        let credentials = self.select_nodes(claims, nodes).await?;
+        let rollups = StatsRollupService::new(self.pool);
+        let now = Utc::now();
+
+        let mut stale = Vec::new();
+        let mut refresh_since = HashMap::new();
+        for credential in &credentials {
+            let refreshed_at = rollups
+                .payment_refreshed_at(&claims.account_id, &credential.node_id)
+                .await?;
+            if is_stale(refreshed_at, now) {
+                stale.push(credential.clone());
+                refresh_since.insert(&credential.node_id, payment_refresh_since(refreshed_at));
+            }
+        }
+
+        let mut errors = Vec::new();
+        if let Some(earliest) = refresh_since.values().min() {
+            let since = earliest.timestamp().max(0) as u64;
+            let (results, failures) = query_nodes(&stale, |client| async move {
+                tokio::try_join!(client.list_payments(), client.list_forwards(since))
+            })
+            .await;
+            errors = failures;
+            for (credential, (payments, forwards)) in results {
+                rollups
+                    .store_payment_rollups(
+                        &claims.account_id,
+                        &credential.node_id,
+                        refresh_since[&credential.node_id],
+                        &payments,
+                        &forwards,
+                        now,
+                    )
+                    .await?;
+            }
+        }
+
+        let node_ids = node_ids(&credentials);
+        let stats = rollups
+            .get_payment_stats(&claims.account_id, &node_ids, start, end, bucket, tz)
+            .await?;
+        Ok(AggregatePaymentStats {
+            node_ids,
+            stats,
+            errors,
+        })
+    }
+
+    /// Lists the payments of every selected node that have been in flight for
+    /// `minutes` or longer, the account's `stuck_payment_minutes` when absent.
+    pub async fn stuck_payments(
+        &self,
+        claims: &Claims,
+        nodes: NodeSelection<'_>,
+        minutes: Option<u32>,
+    ) -> ServiceResult<AggregateStuckPayments> {
+        let threshold_minutes = match minutes {
+            Some(minutes) => minutes,
+            None => SettingsService::new(self.pool)
+                .get_settings(&claims.account_id)
+                .await?
+                .alert_thresholds
+                .stuck_payment_minutes
+                .unwrap_or(DEFAULT_STUCK_PAYMENT_MINUTES),
+        };
+        let credentials = self.select_nodes(claims, nodes).await?;
+        let (results, errors) = query_nodes(&credentials, |client| async move {
+            client.list_inflight_payments().await
+        })
+        .await;
+
+        let now = Utc::now();
+        let mut payments: Vec<StuckPayment> = results
+            .into_iter()
+            .flat_map(|(credential, node_payments)| {
+                stuck_payments(credential, node_payments, threshold_minutes, now)
+            })
+            .collect();
+        payments.sort_by_key(|stuck| std::cmp::Reverse(stuck.stuck_minutes));
+
+        Ok(AggregateStuckPayments {
+            node_ids: node_ids(&credentials),
+            threshold_minutes,
+            payments,
+            errors,
+        })
+    }
+
+    /// Counts the events of every selected node by severity over time.
+    pub async fn event_stats(
+        &self,
+        claims: &Claims,
+        nodes: NodeSelection<'_>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        bucket: StatsBucket,
+        tz: Tz,
+    ) -> ServiceResult<AggregateEventStats> {
+        let node_ids = node_ids(&self.select_nodes(claims, nodes).await?);
+        let stats = EventService::new(self.pool)
+            .get_event_stats(&claims.account_id, Some(&node_ids), start, end, bucket, tz)
+            .await?;
+        Ok(AggregateEventStats { node_ids, stats })
+    }
+}
+
+fn node_ids(credentials: &[Credential]) -> Vec<String> {
+    credentials
+        .iter()
+        .map(|credential| credential.node_id.clone())
+        .collect()
+}
+
+/// Connects to each node and runs `query` against it, all concurrently.
+///
+/// Returns the results of the nodes that answered in time and the failures of
+/// the others.
+pub async fn query_nodes<T, F, Fut>(
+    credentials: &[Credential],
+    query: F,
+) -> (Vec<(&Credential, T)>, Vec<NodeFailure>)
+where
+    F: Fn(Box<dyn LightningClient + Send + Sync>) -> Fut,
+    Fut: Future<Output = Result<T, LightningError>>,
+{
+    let outcomes = join_all(credentials.iter().map(|credential| {
+        let query = &query;
+        async move {
+            let client = match timeout(NODE_CONNECT_TIMEOUT, connect_node(credential)).await {
+                Ok(Ok(client)) => client,
+                Ok(Err(e)) => return (credential, Err(e.to_string())),
+                Err(_) => {
+                    let error = format!(
+                        "Connection timed out after {}s",
+                        NODE_CONNECT_TIMEOUT.as_secs()
+                    );
+                    return (credential, Err(error));
+                }
+            };
+            match timeout(NODE_QUERY_TIMEOUT, query(client)).await {
+                Ok(Ok(value)) => (credential, Ok(value)),
+                Ok(Err(e)) => (credential, Err(e.to_string())),
+                Err(_) => {
+                    let error = format!("Query timed out after {}s", NODE_QUERY_TIMEOUT.as_secs());
+                    (credential, Err(error))
+                }
+            }
+        }
+    }))
+    .await;
+
+    let mut results = Vec::new();
+    let mut failures = Vec::new();
+    for (credential, outcome) in outcomes {
+        match outcome {
+            Ok(value) => results.push((credential, value)),
+            Err(error) => {
+                tracing::warn!(
+                    "Aggregate query of node {} failed: {}",
+                    credential.node_id,
+                    error
+                );
+                failures.push(NodeFailure {
+                    node_id: credential.node_id.clone(),
+                    node_alias: credential.node_alias.clone(),
+                    error,
+                });
+            }
+        }
+    }
+    (results, failures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::ShortChannelID;
+
+    fn channel(node_id: &str, state: ChannelState, capacity: u64) -> NodeChannel {
+        NodeChannel {
+            node_id: node_id.to_string(),
+            node_alias: node_id.to_string(),
+            channel: ChannelSummary {
+                chan_id: ShortChannelID(1),
+                alias: None,
+                channel_state: state,
+                private: false,
+                remote_balance: Amount::from_sat(capacity / 4),
+                local_balance: Amount::from_sat(capacity / 2),
+                capacity: Amount::from_sat(capacity),
+                last_update: None,
+                uptime: None,
+                remote_pubkey: None,
+                channel_point: None,
+            },
+        }
+    }
+
+    #[test]
+    fn totals_span_nodes_and_skip_closed_channels() {
+        let channels = vec![
+            channel("a", ChannelState::Active, 1_000_000),
+            channel("b", ChannelState::Disabled, 400_000),
+            channel("b", ChannelState::Closed, 2_000_000),
+        ];
+
+        assert_eq!(
+            ChannelTotals::of(&channels),
+            ChannelTotals {
+                channels: 2,
+                active_channels: 1,
+                capacity_sat: 1_400_000,
+                local_balance_sat: 700_000,
+                remote_balance_sat: 350_000,
+            }
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//! Which optional features a node's implementation and version support.
+//!
+//! Older LND and CLN releases lack some of the RPCs nodegaze relies on, and
+//! calling them fails with errors that don't say why. The version a node reports
+//! at connect time is checked against `MIN_VERSIONS` instead, so unsupported
+//! features are skipped up front and can be listed for the user.
+//!
+//! Nodes whose version can't be read are assumed to be recent; the node still
+//! rejects whatever it can't do.
+
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+/// Lightning node implementation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeImplementation {
+    Lnd,
+    Cln,
+}
+
+impl NodeImplementation {
+    /// Reads a credential's `node_type`.
+    pub fn from_node_type(node_type: &str) -> Option<Self> {
+        match node_type {
+            "lnd" => Some(Self::Lnd),
+            "cln" => Some(Self::Cln),
+            _ => None,
+        }
+    }
+}
+
+/// Optional node features gated on the node version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeFeature {
+    /// Accepting or rejecting incoming channel requests.
+    ChannelAcceptor,
+    /// Holding forwarded HTLCs for inspection.
+    HtlcInterception,
+    /// Streaming the progress of an outgoing payment.
+    PaymentTracking,
+    /// Listing channels separately from peers, which CLN's channel endpoints use.
+    PeerChannels,
+    /// Resizing channels in place.
+    Splicing,
+    /// Opening channels funded by both peers.
+    DualFunding,
+    /// Labelling on-chain transactions of the node's wallet.
+    TransactionLabels,
+}
+
+impl NodeFeature {
+    pub const ALL: [NodeFeature; 7] = [
+        NodeFeature::ChannelAcceptor,
+        NodeFeature::HtlcInterception,
+        NodeFeature::PaymentTracking,
+        NodeFeature::PeerChannels,
+        NodeFeature::Splicing,
+        NodeFeature::DualFunding,
+        NodeFeature::TransactionLabels,
+    ];
+}
+
+/// Release a feature first appeared in, per implementation; features missing
+/// here aren't available through the APIs nodegaze uses.
+const MIN_VERSIONS: &[(NodeImplementation, NodeFeature, NodeVersion)] = &[
+    (
+        NodeImplementation::Lnd,
+        NodeFeature::ChannelAcceptor,
+        NodeVersion::new(0, 9, 0),
+    ),
+    (
+        NodeImplementation::Lnd,
+        NodeFeature::HtlcInterception,
+        NodeVersion::new(0, 11, 0),
+    ),
+    (
+        NodeImplementation::Lnd,
+        NodeFeature::PaymentTracking,
+        NodeVersion::new(0, 10, 0),
+    ),
+    (
+        NodeImplementation::Lnd,
+        NodeFeature::PeerChannels,
+        NodeVersion::new(0, 0, 0),
+    ),
+    (
+        NodeImplementation::Cln,
+        NodeFeature::PeerChannels,
+        NodeVersion::new(23, 2, 0),
+    ),
+    (
+        NodeImplementation::Cln,
+        NodeFeature::Splicing,
+        NodeVersion::new(23, 8, 0),
+    ),
+    (
+        NodeImplementation::Cln,
+        NodeFeature::DualFunding,
+        NodeVersion::new(0, 10, 1),
+    ),
+    (
+        NodeImplementation::Lnd,
+        NodeFeature::TransactionLabels,
+        NodeVersion::new(0, 11, 0),
+    ),
+];
+
+/// A release number such as `0.18.3` or, for CLN's year-based releases, `24.8.1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NodeVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl NodeVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Reads the release number from a version string as the node reports it,
+    /// e.g. `0.18.3-beta commit=v0.18.3-beta` for LND or `v24.08.1-modded` for CLN.
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.trim().trim_start_matches('v');
+        let end = version
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(version.len());
+        let mut parts = version[..end].split('.').map(str::parse::<u32>);
+
+        let major = parts.next()?.ok()?;
+        let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+        let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+        Some(Self::new(major, minor, patch))
+    }
+}
+
+impl fmt::Display for NodeVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl Serialize for NodeVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Release a feature first appeared in, `None` if the implementation lacks it.
+pub fn min_version(
+    implementation: NodeImplementation,
+    feature: NodeFeature,
+) -> Option<NodeVersion> {
+    MIN_VERSIONS
+        .iter()
+        .find(|(i, f, _)| *i == implementation && *f == feature)
+        .map(|(_, _, version)| *version)
+}
+
+/// Whether a node can use a feature, given the version string it reports.
+pub fn supports(
+    implementation: NodeImplementation,
+    version: Option<&str>,
+    feature: NodeFeature,
+) -> bool {
+    let Some(min_version) = min_version(implementation, feature) else {
+        return false;
+    };
+    version
+        .and_then(NodeVersion::parse)
+        .is_none_or(|version| version >= min_version)
+}
+
+/// Support for one feature.
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureSupport {
+    pub feature: NodeFeature,
+    pub supported: bool,
+    /// Release the feature first appeared in, absent if the implementation lacks it
+    pub min_version: Option<NodeVersion>,
+}
+
+/// Response of `GET /api/node/capabilities`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CapabilityMatrix {
+    pub implementation: NodeImplementation,
+    /// Version string as the node reports it
+    pub version: Option<String>,
+    /// Release number read from `version`, absent if it couldn't be parsed
+    pub release: Option<NodeVersion>,
+    pub features: Vec<FeatureSupport>,
+}
+
+/// Lists which optional features a node supports.
+pub fn capability_matrix(
+    implementation: NodeImplementation,
+    version: Option<&str>,
+) -> CapabilityMatrix {
+    CapabilityMatrix {
+        implementation,
+        version: version.map(str::to_string),
+        release: version.and_then(NodeVersion::parse),
+        features: NodeFeature::ALL
+            .into_iter()
+            .map(|feature| FeatureSupport {
+                feature,
+                supported: supports(implementation, version, feature),
+                min_version: min_version(implementation, feature),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_reported_versions() {
+        assert_eq!(
+            NodeVersion::parse("0.18.3-beta commit=v0.18.3-beta"),
+            Some(NodeVersion::new(0, 18, 3))
+        );
+        assert_eq!(
+            NodeVersion::parse("v24.08.1-modded"),
+            Some(NodeVersion::new(24, 8, 1))
+        );
+        assert_eq!(
+            NodeVersion::parse("v23.11"),
+            Some(NodeVersion::new(23, 11, 0))
+        );
+        assert_eq!(NodeVersion::parse("unknown"), None);
+        assert_eq!(NodeVersion::new(24, 8, 1).to_string(), "24.8.1");
+    }
+
+    #[test]
+    fn gates_features_on_version() {
+        let cln = NodeImplementation::Cln;
+        assert!(supports(cln, Some("v24.02"), NodeFeature::Splicing));
+        assert!(!supports(cln, Some("v23.05.2"), NodeFeature::Splicing));
+        assert!(!supports(cln, Some("v22.11.1"), NodeFeature::PeerChannels));
+        assert!(!supports(cln, Some("v24.08"), NodeFeature::ChannelAcceptor));
+
+        let lnd = NodeImplementation::Lnd;
+        assert!(supports(
+            lnd,
+            Some("0.17.0-beta"),
+            NodeFeature::HtlcInterception
+        ));
+        assert!(!supports(
+            lnd,
+            Some("0.10.4-beta"),
+            NodeFeature::HtlcInterception
+        ));
+        assert!(!supports(lnd, Some("0.18.0-beta"), NodeFeature::Splicing));
+    }
+
+    #[test]
+    fn unknown_versions_are_assumed_recent() {
+        let matrix = capability_matrix(NodeImplementation::Lnd, None);
+        assert!(matrix.release.is_none());
+        let acceptor = matrix
+            .features
+            .iter()
+            .find(|support| support.feature == NodeFeature::ChannelAcceptor)
+            .unwrap();
+        assert!(acceptor.supported);
+        assert_eq!(acceptor.min_version, Some(NodeVersion::new(0, 9, 0)));
+    }
+}
@@ -0,0 +1,142 @@
+//! Discovery of Lightning nodes on the local machine or network.
+//!
+//! Home users often run NodeGaze next to their node, on the same host or on an
+//! Umbrel or Start9 box in the LAN. Probing the default gRPC endpoints of those
+//! setups finds the reachable ones so the connection form can be pre-filled.
+//! Only this fixed list is ever probed, never addresses taken from a request.
+//!
+//! Nodes are told apart by the port their implementation listens on by
+//! default, so a candidate is a hint rather than a verified node: credentials
+//! are still checked with `POST /api/node/test-connection`.
+
+use crate::services::node_capabilities::NodeImplementation;
+use futures::future::join_all;
+use serde::Serialize;
+use std::time::Duration;
+use tokio::net::TcpStream;
+
+/// How long a single endpoint may take to accept a connection.
+const PROBE_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Default gRPC port of LND.
+const LND_GRPC_PORT: u16 = 10009;
+/// Port CLN's gRPC plugin is conventionally given with `grpc-port`.
+const CLN_GRPC_PORT: u16 = 9736;
+
+/// Endpoint a node is commonly reachable on.
+struct KnownEndpoint {
+    host: &'static str,
+    port: u16,
+    implementation: NodeImplementation,
+    /// Setup the endpoint is the default of
+    source: &'static str,
+}
+
+const KNOWN_ENDPOINTS: &[KnownEndpoint] = &[
+    KnownEndpoint {
+        host: "localhost",
+        port: LND_GRPC_PORT,
+        implementation: NodeImplementation::Lnd,
+        source: "localhost",
+    },
+    KnownEndpoint {
+        host: "localhost",
+        port: CLN_GRPC_PORT,
+        implementation: NodeImplementation::Cln,
+        source: "localhost",
+    },
+    KnownEndpoint {
+        host: "host.docker.internal",
+        port: LND_GRPC_PORT,
+        implementation: NodeImplementation::Lnd,
+        source: "docker",
+    },
+    KnownEndpoint {
+        host: "host.docker.internal",
+        port: CLN_GRPC_PORT,
+        implementation: NodeImplementation::Cln,
+        source: "docker",
+    },
+    KnownEndpoint {
+        host: "umbrel.local",
+        port: LND_GRPC_PORT,
+        implementation: NodeImplementation::Lnd,
+        source: "umbrel",
+    },
+    KnownEndpoint {
+        host: "umbrel.local",
+        port: CLN_GRPC_PORT,
+        implementation: NodeImplementation::Cln,
+        source: "umbrel",
+    },
+    KnownEndpoint {
+        host: "lnd.embassy",
+        port: LND_GRPC_PORT,
+        implementation: NodeImplementation::Lnd,
+        source: "start9",
+    },
+    KnownEndpoint {
+        host: "c-lightning.embassy",
+        port: CLN_GRPC_PORT,
+        implementation: NodeImplementation::Cln,
+        source: "start9",
+    },
+];
+
+/// A reachable endpoint that likely serves a node's gRPC interface.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveredNode {
+    /// Address to enter in the connection form
+    pub address: String,
+    pub host: String,
+    pub port: u16,
+    /// Implementation listening on the port by default
+    pub implementation: NodeImplementation,
+    /// Setup the endpoint is the default of, e.g. `umbrel`
+    pub source: String,
+    /// Time taken to accept the connection, in milliseconds
+    pub latency_ms: u64,
+}
+
+/// Connects to `host:port`, returning how long it took when the port accepts
+/// connections within the probe timeout.
+pub async fn probe(host: &str, port: u16) -> Option<Duration> {
+    let started = std::time::Instant::now();
+    match tokio::time::timeout(PROBE_TIMEOUT, TcpStream::connect((host, port))).await {
+        Ok(Ok(_)) => Some(started.elapsed()),
+        _ => None,
+    }
+}
+
+/// Probes the known endpoints concurrently, returning the reachable ones.
+pub async fn discover_nodes() -> Vec<DiscoveredNode> {
+    let probes = KNOWN_ENDPOINTS.iter().map(|endpoint| async move {
+        let latency = probe(endpoint.host, endpoint.port).await?;
+        Some(DiscoveredNode {
+            address: format!("https://{}:{}", endpoint.host, endpoint.port),
+            host: endpoint.host.to_string(),
+            port: endpoint.port,
+            implementation: endpoint.implementation,
+            source: endpoint.source.to_string(),
+            latency_ms: latency.as_millis() as u64,
+        })
+    });
+
+    join_all(probes).await.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn only_listening_ports_are_reachable() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap().port();
+        assert!(probe("127.0.0.1", open).await.is_some());
+
+        drop(listener);
+        assert!(probe("127.0.0.1", open).await.is_none());
+    }
+}
@@ -0,0 +1,231 @@
+//! Node group business logic service.
+//!
+//! Groups tag an account's nodes and users are assigned to them. Once an account
+//! defines a group, members only see nodes tagged with one of their groups;
+//! admins always see every node of the account.
+
+use crate::database::models::{CreateNodeGroup, CreateNodeGroupRequest, NodeGroupWithMembers};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::repositories::node_group_repository::NodeGroupRepository;
+use crate::services::user_service::UserService;
+use crate::utils::jwt::Claims;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+use validator::Validate;
+
+/// The nodes of an account a user may see.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeScope {
+    /// Every node of the account.
+    All,
+    /// Only the listed node IDs.
+    Nodes(Vec<String>),
+}
+
+impl NodeScope {
+    /// Whether the scope includes the given node.
+    pub fn allows(&self, node_id: &str) -> bool {
+        match self {
+            NodeScope::All => true,
+            NodeScope::Nodes(node_ids) => node_ids.iter().any(|id| id == node_id),
+        }
+    }
+
+    /// The node IDs to filter queries by, or `None` when unrestricted.
+    pub fn node_ids(&self) -> Option<&[String]> {
+        match self {
+            NodeScope::All => None,
+            NodeScope::Nodes(node_ids) => Some(node_ids),
+        }
+    }
+}
+
+/// Service layer for node group operations.
+pub struct NodeGroupService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> NodeGroupService<'a> {
+    /// Creates a new NodeGroupService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Resolves which of the account's nodes a user may see.
+    pub async fn node_scope(
+        &self,
+        account_id: &str,
+        user_id: &str,
+        role: &str,
+    ) -> ServiceResult<NodeScope> {
+        if role == "Admin" {
+            return Ok(NodeScope::All);
+        }
+
+        let repo = NodeGroupRepository::new(self.pool);
+        if repo.count_groups_by_account_id(account_id).await? == 0 {
+            return Ok(NodeScope::All);
+        }
+
+        let node_ids = repo.get_node_ids_for_user(user_id).await?;
+        Ok(NodeScope::Nodes(node_ids))
+    }
+
+    /// Resolves the node scope of the user a token was issued to.
+    pub async fn node_scope_for_claims(&self, claims: &Claims) -> ServiceResult<NodeScope> {
+        self.node_scope(&claims.account_id, &claims.sub, &claims.role)
+            .await
+    }
+
+    /// Creates a new node group for an account.
+    pub async fn create_group(
+        &self,
+        account_id: &str,
+        request: CreateNodeGroupRequest,
+    ) -> ServiceResult<NodeGroupWithMembers> {
+        if let Err(validation_errors) = request.validate() {
+            let error_messages: Vec<String> = validation_errors
+                .field_errors()
+                .into_iter()
+                .flat_map(|(field, errors)| {
+                    errors.iter().map(move |error| {
+                        format!(
+                            "{}: {}",
+                            field,
+                            error.message.as_ref().unwrap_or(&"Invalid value".into())
+                        )
+                    })
+                })
+                .collect();
+
+            return Err(ServiceError::validation(error_messages.join(", ")));
+        }
+
+        let repo = NodeGroupRepository::new(self.pool);
+        let name = request.name.trim().to_string();
+        if repo.get_group_by_name(account_id, &name).await?.is_some() {
+            return Err(ServiceError::already_exists("Node group", name));
+        }
+
+        let group = repo
+            .create_group(CreateNodeGroup {
+                id: Uuid::now_v7().to_string(),
+                account_id: account_id.to_string(),
+                name,
+                description: request.description,
+            })
+            .await?;
+
+        Ok(NodeGroupWithMembers {
+            group,
+            node_ids: Vec::new(),
+            user_ids: Vec::new(),
+        })
+    }
+
+    /// Lists the groups visible to a user: every group for admins, their own otherwise.
+    pub async fn get_groups(&self, claims: &Claims) -> ServiceResult<Vec<NodeGroupWithMembers>> {
+        let repo = NodeGroupRepository::new(self.pool);
+        let groups = if claims.role == "Admin" {
+            repo.get_groups_by_account_id(&claims.account_id).await?
+        } else {
+            repo.get_groups_by_user_id(&claims.sub).await?
+        };
+
+        let mut result = Vec::with_capacity(groups.len());
+        for group in groups {
+            let node_ids = repo.get_group_node_ids(&group.id).await?;
+            let user_ids = repo.get_group_user_ids(&group.id).await?;
+            result.push(NodeGroupWithMembers {
+                group,
+                node_ids,
+                user_ids,
+            });
+        }
+
+        Ok(result)
+    }
+
+    /// Deletes a node group, releasing its nodes and members.
+    pub async fn delete_group(&self, id: &str, account_id: &str) -> ServiceResult<()> {
+        self.ensure_group(id, account_id).await?;
+
+        let repo = NodeGroupRepository::new(self.pool);
+        repo.delete_group(id).await?;
+        Ok(())
+    }
+
+    /// Tags one of the account's nodes with a group.
+    pub async fn add_node(&self, id: &str, node_id: &str, account_id: &str) -> ServiceResult<()> {
+        self.ensure_group(id, account_id).await?;
+
+        let credential_repo = CredentialRepository::new(self.pool);
+        let known = credential_repo
+            .get_node_credentials(Some(account_id), None)
+            .await?
+            .iter()
+            .any(|credential| credential.node_id == node_id);
+        if !known {
+            return Err(ServiceError::not_found("Node", node_id));
+        }
+
+        let repo = NodeGroupRepository::new(self.pool);
+        repo.add_node(id, node_id).await?;
+        Ok(())
+    }
+
+    /// Removes a node from a group.
+    pub async fn remove_node(
+        &self,
+        id: &str,
+        node_id: &str,
+        account_id: &str,
+    ) -> ServiceResult<()> {
+        self.ensure_group(id, account_id).await?;
+
+        let repo = NodeGroupRepository::new(self.pool);
+        repo.remove_node(id, node_id).await?;
+        Ok(())
+    }
+
+    /// Assigns one of the account's users to a group.
+    pub async fn add_member(&self, id: &str, user_id: &str, account_id: &str) -> ServiceResult<()> {
+        self.ensure_group(id, account_id).await?;
+
+        let user = UserService::new(self.pool)
+            .get_user_required(user_id)
+            .await?;
+        if user.account_id != account_id {
+            return Err(ServiceError::not_found("User", user_id));
+        }
+
+        let repo = NodeGroupRepository::new(self.pool);
+        repo.add_member(id, user_id).await?;
+        Ok(())
+    }
+
+    /// Removes a user from a group.
+    pub async fn remove_member(
+        &self,
+        id: &str,
+        user_id: &str,
+        account_id: &str,
+    ) -> ServiceResult<()> {
+        self.ensure_group(id, account_id).await?;
+
+        let repo = NodeGroupRepository::new(self.pool);
+        repo.remove_member(id, user_id).await?;
+        Ok(())
+    }
+
+    /// Verifies a group exists and belongs to the account.
+    async fn ensure_group(&self, id: &str, account_id: &str) -> ServiceResult<()> {
+        let repo = NodeGroupRepository::new(self.pool);
+        match repo.get_group_by_id(id).await? {
+            Some(group) if group.account_id == account_id => Ok(()),
+            _ => Err(ServiceError::not_found("Node group", id)),
+        }
+    }
+}
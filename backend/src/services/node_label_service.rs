@@ -0,0 +1,125 @@
+//! Node label business logic service.
+//!
+//! Labels give an account's nodes a display name, color, environment tag and
+//! notes. They are shown in node listings and embedded in event payloads, so
+//! notification receivers can tell which node an alert came from.
+
+use crate::database::models::{NodeLabel, UpdateNodeLabelRequest};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::repositories::node_label_repository::NodeLabelRepository;
+use crate::services::node_group_service::NodeGroupService;
+use crate::utils::jwt::Claims;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use validator::Validate;
+
+/// A node connected to the account, with its label if one was set.
+#[derive(Debug, Serialize)]
+pub struct LabeledNode {
+    pub node_id: String,
+    pub node_alias: String,
+    pub node_type: String,
+    /// Bitcoin network the node runs on, e.g. `bitcoin` or `signet`, if known
+    pub network: Option<String>,
+    pub label: Option<NodeLabel>,
+    /// When the stored TLS certificate expires, if known
+    pub tls_cert_expires_at: Option<DateTime<Utc>>,
+    /// When the stored macaroon expires, absent when it never does
+    pub macaroon_expires_at: Option<DateTime<Utc>>,
+}
+
+/// Trims a text field, treating blank values as unset.
+fn non_blank(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|value| !value.is_empty())
+}
+
+/// Service layer for node label operations.
+pub struct NodeLabelService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> NodeLabelService<'a> {
+    /// Creates a new NodeLabelService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Lists the account's nodes visible to the caller, with their labels.
+    pub async fn get_nodes(&self, claims: &Claims) -> ServiceResult<Vec<LabeledNode>> {
+        let scope = NodeGroupService::new(self.pool)
+            .node_scope_for_claims(claims)
+            .await?;
+        let credentials = CredentialRepository::new(self.pool)
+            .get_node_credentials(Some(&claims.account_id), Some(&claims.sub))
+            .await?;
+        let mut labels: HashMap<String, NodeLabel> = NodeLabelRepository::new(self.pool)
+            .get_labels_by_account_id(&claims.account_id)
+            .await?
+            .into_iter()
+            .map(|label| (label.node_id.clone(), label))
+            .collect();
+
+        Ok(credentials
+            .into_iter()
+            .filter(|credential| scope.allows(&credential.node_id))
+            .map(|credential| LabeledNode {
+                label: labels.remove(&credential.node_id),
+                node_id: credential.node_id,
+                node_alias: credential.node_alias,
+                node_type: credential.node_type.unwrap_or_else(|| "lnd".to_string()),
+                network: credential.network,
+                tls_cert_expires_at: credential.tls_cert_expires_at,
+                macaroon_expires_at: credential.macaroon_expires_at,
+            })
+            .collect())
+    }
+
+    /// Sets the label of one of the account's nodes, replacing any previous one.
+    pub async fn update_label(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        request: UpdateNodeLabelRequest,
+    ) -> ServiceResult<NodeLabel> {
+        request
+            .validate()
+            .map_err(|e| ServiceError::validation(e.to_string()))?;
+
+        let connected = CredentialRepository::new(self.pool)
+            .get_node_credentials(Some(account_id), None)
+            .await?
+            .iter()
+            .any(|credential| credential.node_id == node_id);
+        if !connected {
+            return Err(ServiceError::not_found("Node", node_id));
+        }
+
+        let label = NodeLabelRepository::new(self.pool)
+            .upsert_label(
+                account_id,
+                node_id,
+                non_blank(request.display_name.as_deref()),
+                non_blank(request.color.as_deref()),
+                request.environment,
+                non_blank(request.notes.as_deref()),
+            )
+            .await?;
+
+        Ok(label)
+    }
+
+    /// Removes the label of one of the account's nodes.
+    pub async fn delete_label(&self, account_id: &str, node_id: &str) -> ServiceResult<()> {
+        if !NodeLabelRepository::new(self.pool)
+            .delete_label(account_id, node_id)
+            .await?
+        {
+            return Err(ServiceError::not_found("Node label", node_id));
+        }
+        Ok(())
+    }
+}
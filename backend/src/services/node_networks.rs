@@ -0,0 +1,134 @@
+//! The Bitcoin network each of an account's nodes runs on.
+//!
+//! Nodes report their network when they are connected, e.g. `bitcoin`,
+//! `testnet`, `signet` or `regtest`, and it is stored with their credentials.
+//! Node listings show it and events carry it in their data as `network`.
+//! Credentials stored before networks were recorded get theirs the first time
+//! their node is aggregated.
+//!
+//! Coins of different networks aren't worth the same, so aggregates refuse to
+//! add up nodes on more than one network unless the request passes
+//! `allow_mixed_networks=true`; nodes whose network is still unknown don't
+//! count. Amounts of nodes off mainnet have no fiat value, and their
+//! `amount_usd` is `null`.
+
+use crate::database::models::Credential;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::services::node_aggregate::query_nodes;
+use sqlx::SqlitePool;
+use std::collections::BTreeSet;
+
+/// The known networks of some nodes, by name.
+pub fn networks(credentials: &[Credential]) -> BTreeSet<&str> {
+    credentials
+        .iter()
+        .filter_map(|credential| credential.network.as_deref())
+        .collect()
+}
+
+/// Rejects combining nodes on different networks unless `allow_mixed` is set.
+pub fn check_single_network(credentials: &[Credential], allow_mixed: bool) -> ServiceResult<()> {
+    let networks = networks(credentials);
+    if networks.len() > 1 && !allow_mixed {
+        return Err(ServiceError::invalid_operation(format!(
+            "The selected nodes run on different networks ({}); select one network's nodes \
+             with a node group or pass allow_mixed_networks=true",
+            networks.into_iter().collect::<Vec<_>>().join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Reads the network of the nodes whose network isn't known yet from the
+/// nodes themselves, storing it with their credentials.
+///
+/// Nodes that can't be reached keep an unknown network.
+pub async fn fill_unknown_networks(pool: &SqlitePool, credentials: &mut [Credential]) {
+    let unknown: Vec<Credential> = credentials
+        .iter()
+        .filter(|credential| credential.network.is_none())
+        .cloned()
+        .collect();
+    if unknown.is_empty() {
+        return;
+    }
+
+    let (results, _) = query_nodes(&unknown, |client| async move {
+        Ok(client.get_info().network.clone())
+    })
+    .await;
+
+    let repo = CredentialRepository::new(pool);
+    for (found, network) in results {
+        let Some(network) = network else {
+            continue;
+        };
+        if let Err(e) = repo
+            .update_network(&found.account_id, &found.node_id, &network)
+            .await
+        {
+            tracing::warn!("Failed to store network of node {}: {}", found.node_id, e);
+        }
+        for credential in credentials
+            .iter_mut()
+            .filter(|credential| credential.node_id == found.node_id)
+        {
+            credential.network = Some(network.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn credential(node_id: &str, network: Option<&str>) -> Credential {
+        Credential {
+            id: node_id.to_string(),
+            user_id: "user".to_string(),
+            account_id: "account".to_string(),
+            node_id: node_id.to_string(),
+            node_alias: node_id.to_string(),
+            macaroon: String::new(),
+            tls_cert: String::new(),
+            address: "127.0.0.1:10009".to_string(),
+            node_type: Some("lnd".to_string()),
+            client_cert: None,
+            client_key: None,
+            ca_cert: None,
+            proxy: None,
+            rune: None,
+            transport: None,
+            read_only: false,
+            node_version: None,
+            network: network.map(str::to_string),
+            tls_cert_expires_at: None,
+            macaroon_expires_at: None,
+            is_active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            is_deleted: false,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn rejects_mixing_networks_unless_allowed() {
+        let mainnet = [
+            credential("a", Some("bitcoin")),
+            credential("b", Some("bitcoin")),
+            credential("c", None),
+        ];
+        assert!(check_single_network(&mainnet, false).is_ok());
+
+        let mixed = [
+            credential("a", Some("bitcoin")),
+            credential("b", Some("signet")),
+        ];
+        let error = check_single_network(&mixed, false).unwrap_err();
+        assert!(error.to_string().contains("bitcoin, signet"));
+        assert!(check_single_network(&mixed, true).is_ok());
+    }
+}
@@ -0,0 +1,277 @@
+//! Circuit breaker of notification endpoints.
+//!
+//! An endpoint that is down or rejects NodeGaze keeps failing every delivery
+//! until someone notices. Failed deliveries in a row are counted per endpoint,
+//! and once `NOTIFICATION_BREAKER_THRESHOLD` of them failed the endpoint is
+//! paused: events are still stored for it, so they can be replayed, but no
+//! longer sent. A `notification_endpoint_paused` warning goes to the account's
+//! other endpoints and its admin is emailed when email is configured.
+//!
+//! Every fifteen minutes paused endpoints are probed without sending them an
+//! event. One that answers is resumed with a `notification_endpoint_resumed`
+//! event. Updating an endpoint's URL, recipient or headers resumes it as well.
+
+use crate::config;
+use crate::database::models::{CreateEvent, EventSeverity, EventType, Notification};
+use crate::errors::ServiceResult;
+use crate::repositories::notification_repository::NotificationRepository;
+use crate::repositories::user_repository::UserRepository;
+use crate::services::email_service::EmailService;
+use crate::services::event_service::EventService;
+use crate::services::notification_dispatcher::NotificationDispatcher;
+use crate::services::reports::escape_html;
+use crate::services::task_supervisor;
+use chrono::Utc;
+use serde_json::json;
+use sqlx::SqlitePool;
+use std::future::Future;
+use std::pin::Pin;
+use uuid::Uuid;
+
+const PROBE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+
+/// Failures in a row that pause an endpoint when the configuration can't be loaded.
+const DEFAULT_THRESHOLD: u32 = 10;
+
+/// Node id of the events the breaker raises, which concern no node.
+const SYSTEM_NODE_ID: &str = "nodegaze";
+
+/// Whether `failures` in a row pause an endpoint; a threshold of 0 never does.
+fn trips(failures: i64, threshold: u32) -> bool {
+    threshold > 0 && failures >= i64::from(threshold)
+}
+
+/// Clears the failures of an endpoint that accepted a delivery.
+pub async fn record_success(pool: &SqlitePool, notification: &Notification) {
+    if notification.consecutive_failures == 0 {
+        return;
+    }
+    if let Err(e) = NotificationRepository::new(pool)
+        .record_success(&notification.id)
+        .await
+    {
+        tracing::warn!(
+            "Failed to record delivery to notification {}: {}",
+            notification.id,
+            e
+        );
+    }
+}
+
+/// Counts a failed delivery, pausing the endpoint once too many failed in a row.
+pub async fn record_failure(pool: &SqlitePool, notification: &Notification, error: &str) {
+    if let Err(e) = count_failure(pool, notification, error).await {
+        tracing::warn!(
+            "Failed to record failed delivery to notification {}: {}",
+            notification.id,
+            e
+        );
+    }
+}
+
+async fn count_failure(
+    pool: &SqlitePool,
+    notification: &Notification,
+    error: &str,
+) -> ServiceResult<()> {
+    let repo = NotificationRepository::new(pool);
+    let failures = repo.record_failure(&notification.id, error).await?;
+
+    let threshold = config::shared()
+        .map(|config| config.notification_breaker_threshold)
+        .unwrap_or(DEFAULT_THRESHOLD);
+    if !trips(failures, threshold) {
+        return Ok(());
+    }
+    // Concurrent failures only pause, and announce, once
+    if !repo
+        .pause_notification(&notification.id, Utc::now())
+        .await?
+    {
+        return Ok(());
+    }
+
+    tracing::warn!(
+        "Paused notification {} after {} failed deliveries in a row: {}",
+        notification.id,
+        failures,
+        error
+    );
+    let description = format!(
+        "{} failed {} deliveries in a row and is paused. Last error: {}. \
+         It is probed every {} minutes and resumed once it answers; events \
+         raised meanwhile can be replayed.",
+        notification.name,
+        failures,
+        error,
+        PROBE_INTERVAL.as_secs() / 60
+    );
+    raise_event(
+        pool,
+        notification,
+        EventType::NotificationEndpointPaused,
+        EventSeverity::Warning,
+        "Notification Endpoint Paused",
+        description.clone(),
+        failures,
+    )
+    .await?;
+    email_admin(pool, notification, &description).await;
+
+    Ok(())
+}
+
+/// Records an event about an endpoint, dispatched to the account's others.
+///
+/// Boxed since dispatching it records deliveries, which may raise another.
+fn raise_event<'a>(
+    pool: &'a SqlitePool,
+    notification: &'a Notification,
+    event_type: EventType,
+    severity: EventSeverity,
+    title: &'a str,
+    description: String,
+    failures: i64,
+) -> Pin<Box<dyn Future<Output = ServiceResult<()>> + Send + 'a>> {
+    Box::pin(async move {
+        EventService::new(pool)
+            .create_and_dispatch_event(CreateEvent {
+                id: Uuid::now_v7().to_string(),
+                account_id: notification.account_id.clone(),
+                user_id: notification.user_id.clone(),
+                node_id: SYSTEM_NODE_ID.to_string(),
+                node_alias: "NodeGaze".to_string(),
+                event_type,
+                severity,
+                title: title.to_string(),
+                description,
+                data: json!({
+                    "notification_id": notification.id,
+                    "notification_name": notification.name,
+                    "notification_type": notification.notification_type,
+                    "consecutive_failures": failures,
+                })
+                .to_string(),
+                notifications_id: None,
+                timestamp: Utc::now(),
+            })
+            .await?;
+
+        Ok(())
+    })
+}
+
+/// Emails the account's admin about a paused endpoint, if email is configured.
+async fn email_admin(pool: &SqlitePool, notification: &Notification, description: &str) {
+    let Some(email_config) = config::shared()
+        .ok()
+        .and_then(|config| config.email_config())
+    else {
+        return;
+    };
+    let admin = match UserRepository::new(pool)
+        .get_admin_user_by_account_id(&notification.account_id)
+        .await
+    {
+        Ok(Some(admin)) => admin,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to load admin of account {}: {}",
+                notification.account_id,
+                e
+            );
+            return;
+        }
+    };
+
+    let subject = format!("[NodeGaze] Notification paused: {}", notification.name);
+    let html = format!(
+        "<h2>Notification paused</h2><p>{}</p>",
+        escape_html(description)
+    );
+    let result = match EmailService::new(email_config) {
+        Ok(email) => {
+            email
+                .send_email(&admin.email, &subject, &html, description)
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        tracing::warn!(
+            "Failed to email admin of account {} about paused notification {}: {}",
+            notification.account_id,
+            notification.id,
+            e
+        );
+    }
+}
+
+/// Probes the paused endpoints, resuming those that answer.
+pub async fn probe_paused_endpoints(pool: &SqlitePool) -> ServiceResult<()> {
+    let repo = NotificationRepository::new(pool);
+    let dispatcher = NotificationDispatcher::new();
+
+    for notification in repo.get_paused_notifications().await? {
+        if let Err(e) = dispatcher.probe_endpoint(&notification).await {
+            tracing::info!("Paused notification {} still fails: {}", notification.id, e);
+            continue;
+        }
+        if !repo.resume_notification(&notification.id).await? {
+            continue;
+        }
+
+        tracing::info!("Resumed notification {}", notification.id);
+        raise_event(
+            pool,
+            &notification,
+            EventType::NotificationEndpointResumed,
+            EventSeverity::Info,
+            "Notification Endpoint Resumed",
+            format!(
+                "{} answers again and receives notifications.",
+                notification.name
+            ),
+            notification.consecutive_failures,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Probes paused notification endpoints every fifteen minutes.
+pub fn spawn_breaker_prober(pool: SqlitePool) {
+    task_supervisor::supervise("notification_breaker", move |task| {
+        let pool = pool.clone();
+        async move {
+            // The first probe waits for startup migrations
+            let mut interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + PROBE_INTERVAL,
+                PROBE_INTERVAL,
+            );
+            loop {
+                interval.tick().await;
+                let result = probe_paused_endpoints(&pool).await;
+                if let Err(e) = &result {
+                    tracing::error!("Probing paused notifications failed: {}", e);
+                }
+                task.record_run(&result);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trips_at_the_threshold() {
+        assert!(!trips(9, 10));
+        assert!(trips(10, 10));
+        assert!(trips(11, 10));
+        assert!(!trips(1000, 0));
+    }
+}
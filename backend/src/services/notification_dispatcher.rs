@@ -1,17 +1,175 @@
 //! Service for dispatching events to notification endpoints.
 
-use crate::database::models::{Event, Notification, NotificationType};
+use crate::config;
+use crate::database::models::{
+    BrandingSettings, EscalationSettings, Event, EventSeverity, Notification, NotificationType,
+};
 use crate::repositories::notification_repository::NotificationRepository;
+use crate::services::notification_breaker;
+use crate::services::settings_service::SettingsService;
+use crate::utils::nostr;
+use crate::utils::on_call::{self, OnCallAction};
+use crate::utils::webhook_headers;
 use reqwest::Client;
 use serde_json::json;
 use sqlx::SqlitePool;
-use std::time::Duration;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
 use tracing::{error, info, warn};
 
+/// De-duplication window used when the configuration can't be loaded.
+const DEFAULT_DEDUP_WINDOW: Duration = Duration::from_secs(300);
+
+/// Footer of Discord embeds for accounts without their own footer text.
+const DEFAULT_FOOTER_TEXT: &str = "NodeGaze Lightning Monitor";
+
+/// Event data fields that tell apart otherwise identical events.
+const DEDUP_KEY_FIELDS: &[&str] = &[
+    "chan_id",
+    "channel_id",
+    "channel_point",
+    "remote_pubkey",
+    "counterparty_node_id",
+    "hash",
+    "target",
+];
+
+/// Identifies notifications that are considered identical.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct DedupKey {
+    notification_id: String,
+    event_type: String,
+    node_id: String,
+    /// `name=value` pairs of the event's key fields
+    fields: String,
+}
+
+impl DedupKey {
+    fn new(event: &Event, notification: &Notification) -> Self {
+        let data = serde_json::from_str::<serde_json::Value>(&event.data).unwrap_or_default();
+        let fields = DEDUP_KEY_FIELDS
+            .iter()
+            .filter_map(|field| data.get(field).map(|value| format!("{field}={value}")))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        Self {
+            notification_id: notification.id.clone(),
+            event_type: event.event_type.to_string(),
+            node_id: event.node_id.clone(),
+            fields,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct DedupEntry {
+    last_seen: Instant,
+    /// Occurrences not sent since the first one
+    suppressed: u32,
+    /// Latest suppressed event, the base of the summary
+    latest: Option<Event>,
+}
+
+/// What to do with an occurrence of a notification.
+#[derive(Debug, PartialEq)]
+enum Occurrence {
+    /// Send it, it hasn't been seen within the window
+    Send,
+    /// Suppress it; the first suppression schedules the summary
+    Suppress { first: bool },
+}
+
+/// Recently sent notifications, shared by all dispatchers.
+#[derive(Debug, Default)]
+struct DedupState {
+    entries: HashMap<DedupKey, DedupEntry>,
+}
+
+impl DedupState {
+    /// Records an occurrence, extending the window of a repeated notification.
+    fn record(
+        &mut self,
+        key: DedupKey,
+        event: &Event,
+        window: Duration,
+        now: Instant,
+    ) -> Occurrence {
+        // Entries without suppressions have no summary task to remove them
+        self.entries.retain(|_, entry| {
+            entry.suppressed > 0 || now.duration_since(entry.last_seen) < window
+        });
+
+        match self.entries.get_mut(&key) {
+            Some(entry) if now.duration_since(entry.last_seen) < window => {
+                entry.last_seen = now;
+                entry.suppressed += 1;
+                entry.latest = Some(event.clone());
+                Occurrence::Suppress {
+                    first: entry.suppressed == 1,
+                }
+            }
+            _ => {
+                self.entries.insert(
+                    key,
+                    DedupEntry {
+                        last_seen: now,
+                        suppressed: 0,
+                        latest: None,
+                    },
+                );
+                Occurrence::Send
+            }
+        }
+    }
+
+    /// Ends the window once it has been quiet for `window`.
+    ///
+    /// Returns the suppressed count and latest event, or when to check again.
+    fn close_if_quiet(
+        &mut self,
+        key: &DedupKey,
+        window: Duration,
+        now: Instant,
+    ) -> Result<Option<(u32, Event)>, Instant> {
+        let Some(entry) = self.entries.get(key) else {
+            return Ok(None);
+        };
+        let quiet_at = entry.last_seen + window;
+        if now < quiet_at {
+            return Err(quiet_at);
+        }
+
+        Ok(self
+            .entries
+            .remove(key)
+            .and_then(|entry| entry.latest.map(|event| (entry.suppressed, event))))
+    }
+}
+
+static DEDUP_STATE: LazyLock<Mutex<DedupState>> =
+    LazyLock::new(|| Mutex::new(DedupState::default()));
+
+/// Builds the follow-up summarizing the occurrences of a suppressed notification.
+fn summary_event(mut event: Event, suppressed: u32, window: Duration) -> Event {
+    let occurrences = suppressed + 1;
+    event.title = format!("{} (occurred {} times)", event.title, occurrences);
+    event.description = format!(
+        "{}\n{} identical notifications were suppressed; none followed within {}s of the last.",
+        event.description,
+        suppressed,
+        window.as_secs()
+    );
+    event
+}
+
 /// Service for dispatching events to notification endpoints.
 #[derive(Debug, Clone)]
 pub struct NotificationDispatcher {
     http_client: Client,
+    /// Identical notifications within this window of each other are collapsed
+    dedup_window: Duration,
 }
 
 impl NotificationDispatcher {
@@ -22,7 +180,14 @@ impl NotificationDispatcher {
             .build()
             .expect("Failed to create HTTP client");
 
-        Self { http_client }
+        let dedup_window = config::shared()
+            .map(|config| Duration::from_secs(config.notification_dedup_window_seconds))
+            .unwrap_or(DEFAULT_DEDUP_WINDOW);
+
+        Self {
+            http_client,
+            dedup_window,
+        }
     }
 
     /// Dispatches an event to all active notifications for the account.
@@ -36,8 +201,39 @@ impl NotificationDispatcher {
             .get_notifications_by_account_id(&event.account_id)
             .await?;
 
-        let active_notifications: Vec<_> =
-            notifications.into_iter().filter(|n| n.is_active).collect();
+        // Unbranded notifications without escalation beat none at all
+        let (branding, escalation) = match SettingsService::new(pool)
+            .get_settings(&event.account_id)
+            .await
+        {
+            Ok(settings) => (settings.branding, settings.escalation),
+            Err(e) => {
+                warn!(
+                    "Failed to load settings of account {}: {}",
+                    event.account_id, e
+                );
+                (BrandingSettings::default(), EscalationSettings::default())
+            }
+        };
+
+        // Each stored copy of an event belongs to one notification endpoint.
+        // Endpoints reached only by later escalation steps wait for them.
+        let (paused_notifications, active_notifications): (Vec<_>, Vec<_>) = notifications
+            .into_iter()
+            .filter(|n| n.is_active)
+            .filter(|n| event.notifications_id.as_ref().is_none_or(|id| *id == n.id))
+            .filter(|n| {
+                event.severity != EventSeverity::Critical || !escalation.delays_endpoint(&n.id)
+            })
+            .partition(|n| n.paused_at.is_some());
+
+        // The stored copy can be replayed once the endpoint is back
+        for notification in &paused_notifications {
+            info!(
+                "Not sending event {} to paused notification {}",
+                event.id, notification.id
+            );
+        }
 
         if active_notifications.is_empty() {
             info!(
@@ -55,48 +251,209 @@ impl NotificationDispatcher {
 
         // Dispatch to all active notifications concurrently
         let dispatch_futures: Vec<_> = active_notifications
-            .into_iter()
-            .map(|notification| self.send_to_endpoint(event, notification))
+            .iter()
+            .map(|notification| self.send_deduplicated(event, notification.clone(), &branding))
             .collect();
 
         // Wait for all dispatches to complete
         let results = futures::future::join_all(dispatch_futures).await;
 
-        // Log results
-        for (i, result) in results.into_iter().enumerate() {
+        // Log results and keep count of each endpoint's failures in a row
+        for (notification, result) in active_notifications.iter().zip(results) {
             match result {
-                Ok(_) => info!(
-                    "Successfully dispatched event {} to endpoint {}",
-                    event.id, i
-                ),
-                Err(e) => error!(
-                    "Failed to dispatch event {} to endpoint {}: {}",
-                    event.id, i, e
-                ),
+                Ok(sent) => {
+                    info!(
+                        "Successfully dispatched event {} to notification {}",
+                        event.id, notification.id
+                    );
+                    if sent {
+                        notification_breaker::record_success(pool, notification).await;
+                    }
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to dispatch event {} to notification {}: {}",
+                        event.id, notification.id, e
+                    );
+                    notification_breaker::record_failure(pool, notification, &e.to_string()).await;
+                }
             }
         }
 
         Ok(())
     }
 
-    /// Sends an event to a specific notification endpoint.
-    async fn send_to_endpoint(
+    /// Sends an event unless an identical one was sent to the endpoint within the window.
+    ///
+    /// Suppressed repeats are summarized in one follow-up once the window passes
+    /// without another occurrence. Returns whether the event was sent.
+    async fn send_deduplicated(
         &self,
         event: &Event,
         notification: Notification,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        branding: &BrandingSettings,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        if self.dedup_window.is_zero() {
+            return self.send_to_endpoint(event, notification, branding).await;
+        }
+
+        let key = DedupKey::new(event, &notification);
+        let occurrence = DEDUP_STATE.lock().unwrap().record(
+            key.clone(),
+            event,
+            self.dedup_window,
+            Instant::now(),
+        );
+
+        match occurrence {
+            Occurrence::Send => self.send_to_endpoint(event, notification, branding).await,
+            Occurrence::Suppress { first } => {
+                info!(
+                    "Suppressed repeated {} notification for node {}",
+                    event.event_type, event.node_id
+                );
+                if first {
+                    self.spawn_summary(key, notification, branding.clone());
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    /// Sends the summary of a suppressed notification once its window closes.
+    fn spawn_summary(&self, key: DedupKey, notification: Notification, branding: BrandingSettings) {
+        let dispatcher = self.clone();
+
+        tokio::spawn(async move {
+            let window = dispatcher.dedup_window;
+            let summary = loop {
+                let closed =
+                    DEDUP_STATE
+                        .lock()
+                        .unwrap()
+                        .close_if_quiet(&key, window, Instant::now());
+                match closed {
+                    Ok(summary) => break summary,
+                    Err(quiet_at) => tokio::time::sleep_until(quiet_at.into()).await,
+                }
+            };
+
+            let Some((suppressed, event)) = summary else {
+                return;
+            };
+            let event = summary_event(event, suppressed, window);
+            if let Err(e) = dispatcher
+                .send_to_endpoint(&event, notification, &branding)
+                .await
+            {
+                error!(
+                    "Failed to send notification summary for event {}: {}",
+                    event.id, e
+                );
+            }
+        });
+    }
+
+    /// Sends an event to a specific notification endpoint, bypassing de-duplication.
+    ///
+    /// Returns whether anything was sent; Nostr, PagerDuty and Opsgenie
+    /// endpoints skip events they don't alert on. Requests the endpoint
+    /// doesn't accept are errors.
+    pub async fn send_to_endpoint(
+        &self,
+        event: &Event,
+        notification: Notification,
+        branding: &BrandingSettings,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
         match notification.notification_type {
-            NotificationType::Webhook => self.send_webhook(event, &notification).await,
-            NotificationType::Discord => self.send_discord(event, &notification).await,
+            NotificationType::Webhook => self.send_webhook(event, &notification, false).await,
+            NotificationType::Discord => self.send_discord(event, &notification, branding).await,
+            NotificationType::Nostr => self.send_nostr(event, &notification).await,
+            NotificationType::PagerDuty => self.send_pagerduty(event, &notification).await,
+            NotificationType::Opsgenie => self.send_opsgenie(event, &notification).await,
         }
     }
 
-    /// Sends event to a webhook endpoint.
-    async fn send_webhook(
+    /// Sends a stored event to an endpoint again, bypassing de-duplication.
+    ///
+    /// Webhook payloads carry `"replay": true`; other endpoints get the title
+    /// prefixed with `[Replay]`.
+    pub async fn replay_to_endpoint(
         &self,
         event: &Event,
+        notification: Notification,
+        branding: &BrandingSettings,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        if notification.notification_type == NotificationType::Webhook {
+            return self.send_webhook(event, &notification, true).await;
+        }
+        let mut event = event.clone();
+        event.title = format!("[Replay] {}", event.title);
+        self.send_to_endpoint(&event, notification, branding).await
+    }
+
+    /// Checks whether a paused endpoint accepts requests again without sending it an event.
+    ///
+    /// Webhooks get the ping they were tested with when created and Discord
+    /// webhooks are looked up, both needing a successful answer. Nostr relays
+    /// need to accept a connection. A test alert would page whoever is on
+    /// call, so PagerDuty and Opsgenie only need to answer without a server error.
+    pub async fn probe_endpoint(
+        &self,
         notification: &Notification,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let response = match notification.notification_type {
+            NotificationType::Webhook => {
+                let headers = webhook_headers::stored_headers(notification.headers.as_deref())?;
+                self.http_client
+                    .post(&notification.url)
+                    .headers(headers)
+                    .header("User-Agent", "NodeGaze/1.0")
+                    .json(&json!({
+                        "event": "Ping",
+                        "timestamp": chrono::Utc::now().to_rfc3339()
+                    }))
+                    .send()
+                    .await?
+            }
+            NotificationType::Discord => {
+                self.http_client
+                    .get(&notification.url)
+                    .header("User-Agent", "NodeGaze/1.0")
+                    .send()
+                    .await?
+            }
+            NotificationType::Nostr => {
+                nostr::check_relay(&notification.url).await?;
+                return Ok(());
+            }
+            NotificationType::PagerDuty | NotificationType::Opsgenie => {
+                let response = self
+                    .http_client
+                    .get(&notification.url)
+                    .header("User-Agent", "NodeGaze/1.0")
+                    .send()
+                    .await?;
+                if response.status().is_server_error() {
+                    return Err(format!("Probe failed with status {}", response.status()).into());
+                }
+                return Ok(());
+            }
+        };
+
+        if !response.status().is_success() {
+            return Err(format!("Probe failed with status {}", response.status()).into());
+        }
+        Ok(())
+    }
+
+    /// Sends event to a webhook endpoint, marked as a replay when `replay` is set.
+    async fn send_webhook(
+        &self,
+        event: &Event,
+        notification: &Notification,
+        replay: bool,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
         let payload = json!({
             "event_id": event.id,
             "timestamp": event.timestamp,
@@ -106,32 +463,35 @@ impl NotificationDispatcher {
             "description": event.description,
             "node_id": event.node_id,
             "node_alias": event.node_alias,
-            "data": serde_json::from_str::<serde_json::Value>(&event.data).unwrap_or(json!({}))
+            "data": serde_json::from_str::<serde_json::Value>(&event.data).unwrap_or(json!({})),
+            "replay": replay
         });
+        let headers = webhook_headers::stored_headers(notification.headers.as_deref())?;
 
         let response = self
             .http_client
             .post(&notification.url)
+            .headers(headers)
             .header("Content-Type", "application/json")
             .header("User-Agent", "NodeGaze/1.0")
             .json(&payload)
             .send()
             .await?;
 
-        if response.status().is_success() {
-            info!(
-                "Webhook notification sent successfully to {}",
-                notification.url
-            );
-        } else {
-            warn!(
+        if !response.status().is_success() {
+            return Err(format!(
                 "Webhook notification failed with status {}: {}",
                 response.status(),
                 notification.url
-            );
+            )
+            .into());
         }
+        info!(
+            "Webhook notification sent successfully to {}",
+            notification.url
+        );
 
-        Ok(())
+        Ok(true)
     }
 
     /// Sends event to a Discord webhook.
@@ -139,43 +499,9 @@ impl NotificationDispatcher {
         &self,
         event: &Event,
         notification: &Notification,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let color = match event.severity {
-            crate::database::models::EventSeverity::Info => 0x00ff00, // Green
-            crate::database::models::EventSeverity::Warning => 0xffff00, // Yellow
-            crate::database::models::EventSeverity::Critical => 0xff0000, // Red
-        };
-
-        let embed = json!({
-            "title": event.title,
-            "description": event.description,
-            "color": color,
-            "timestamp": event.timestamp,
-            "fields": [
-                {
-                    "name": "Event Type",
-                    "value": event.event_type.to_string(),
-                    "inline": true
-                },
-                {
-                    "name": "Severity",
-                    "value": event.severity.to_string(),
-                    "inline": true
-                },
-                {
-                    "name": "Node",
-                    "value": if event.node_alias.is_empty() {
-                        event.node_id.clone()
-                    } else {
-                        format!("{} ({})", event.node_alias, &event.node_id[..8])
-                    },
-                    "inline": true
-                }
-            ],
-            "footer": {
-                "text": "NodeGaze Lightning Monitor"
-            }
-        });
+        branding: &BrandingSettings,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let embed = discord_embed(event, branding);
 
         let payload = json!({
             "embeds": [embed]
@@ -190,19 +516,320 @@ impl NotificationDispatcher {
             .send()
             .await?;
 
-        if response.status().is_success() {
-            info!(
-                "Discord notification sent successfully to {}",
-                notification.url
-            );
-        } else {
-            warn!(
+        if !response.status().is_success() {
+            return Err(format!(
                 "Discord notification failed with status {}: {}",
                 response.status(),
                 notification.url
-            );
+            )
+            .into());
         }
+        info!(
+            "Discord notification sent successfully to {}",
+            notification.url
+        );
 
-        Ok(())
+        Ok(true)
+    }
+
+    /// Opens a PagerDuty incident for a critical event, or resolves the
+    /// incident of the condition an event reports as cleared.
+    async fn send_pagerduty(
+        &self,
+        event: &Event,
+        notification: &Notification,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let Some(action) = on_call::on_call_action(event) else {
+            return Ok(false);
+        };
+        let routing_key = notification
+            .recipient
+            .as_deref()
+            .ok_or("PagerDuty notification has no routing key")?;
+
+        let response = self
+            .http_client
+            .post(&notification.url)
+            .header("User-Agent", "NodeGaze/1.0")
+            .json(&on_call::pagerduty_event(routing_key, event, &action))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!(
+                "PagerDuty event failed with status {}: {}",
+                response.status(),
+                notification.url
+            )
+            .into());
+        }
+        info!("PagerDuty event sent successfully to {}", notification.url);
+
+        Ok(true)
+    }
+
+    /// Creates an Opsgenie alert for a critical event, or closes the alert of
+    /// the condition an event reports as cleared.
+    async fn send_opsgenie(
+        &self,
+        event: &Event,
+        notification: &Notification,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let Some(action) = on_call::on_call_action(event) else {
+            return Ok(false);
+        };
+        let api_key = notification
+            .recipient
+            .as_deref()
+            .ok_or("Opsgenie notification has no API key")?;
+
+        let request = match &action {
+            OnCallAction::Trigger(alias) => self
+                .http_client
+                .post(&notification.url)
+                .json(&on_call::opsgenie_alert(event, alias)),
+            OnCallAction::Resolve(alias) => self
+                .http_client
+                .post(on_call::opsgenie_close_url(&notification.url, alias)?)
+                .json(&on_call::opsgenie_close(event)),
+        };
+        let response = request
+            .header("Authorization", format!("GenieKey {api_key}"))
+            .header("User-Agent", "NodeGaze/1.0")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!(
+                "Opsgenie request failed with status {}: {}",
+                response.status(),
+                notification.url
+            )
+            .into());
+        }
+        info!("Opsgenie request sent successfully to {}", notification.url);
+
+        Ok(true)
+    }
+
+    /// Sends a critical event as an encrypted Nostr direct message.
+    async fn send_nostr(
+        &self,
+        event: &Event,
+        notification: &Notification,
+    ) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        // DMs land on people's phones, so only critical alerts are sent
+        if event.severity != EventSeverity::Critical {
+            return Ok(false);
+        }
+
+        let secret_key = config::shared()?
+            .nostr_secret_key
+            .clone()
+            .ok_or("NOSTR_SECRET_KEY is not configured")?;
+        let secret_key = nostr::parse_secret_key(&secret_key)?;
+        let recipient = nostr::parse_public_key(
+            notification
+                .recipient
+                .as_deref()
+                .ok_or("Nostr notification has no recipient")?,
+        )?;
+
+        let node = if event.node_alias.is_empty() {
+            event.node_id.clone()
+        } else {
+            format!("{} ({})", event.node_alias, event.node_id)
+        };
+        let message = format!(
+            "[{}] {}\n{}\nNode: {}\n{}",
+            event.severity,
+            event.title,
+            event.description,
+            node,
+            event.timestamp.to_rfc3339()
+        );
+
+        let dm = nostr::encrypted_dm(&secret_key, &recipient, &message);
+        nostr::publish(&notification.url, &dm).await?;
+        info!("Nostr notification published to {}", notification.url);
+
+        Ok(true)
+    }
+}
+
+impl Default for NotificationDispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds the Discord embed of an event, signed with the account's branding.
+fn discord_embed(event: &Event, branding: &BrandingSettings) -> serde_json::Value {
+    let color = match event.severity {
+        EventSeverity::Info => 0x00ff00,     // Green
+        EventSeverity::Warning => 0xffff00,  // Yellow
+        EventSeverity::Critical => 0xff0000, // Red
+    };
+
+    let mut embed = json!({
+        "title": event.title,
+        "description": event.description,
+        "color": color,
+        "timestamp": event.timestamp,
+        "fields": [
+            {
+                "name": "Event Type",
+                "value": event.event_type.to_string(),
+                "inline": true
+            },
+            {
+                "name": "Severity",
+                "value": event.severity.to_string(),
+                "inline": true
+            },
+            {
+                "name": "Node",
+                "value": node_display_name(event),
+                "inline": true
+            }
+        ]
+    });
+    brand_discord_embed(&mut embed, branding);
+
+    embed
+}
+
+/// Signs a Discord embed with the account's branding, NodeGaze's where unset.
+pub fn brand_discord_embed(embed: &mut serde_json::Value, branding: &BrandingSettings) {
+    embed["footer"] = json!({
+        "text": branding.footer_text.as_deref().unwrap_or(DEFAULT_FOOTER_TEXT)
+    });
+    if let Some(name) = &branding.display_name {
+        embed["author"] = json!({ "name": name });
+        if let Some(logo_url) = &branding.logo_url {
+            embed["author"]["icon_url"] = json!(logo_url);
+        }
+    }
+    if let Some(logo_url) = &branding.logo_url {
+        embed["footer"]["icon_url"] = json!(logo_url);
+    }
+}
+
+/// Names the event's node, preferring the label's display name and environment.
+fn node_display_name(event: &Event) -> String {
+    let label = serde_json::from_str::<serde_json::Value>(&event.data)
+        .ok()
+        .and_then(|data| data.get("node_label").cloned())
+        .unwrap_or_default();
+
+    let name = match label["display_name"].as_str() {
+        Some(display_name) => display_name.to_string(),
+        None if event.node_alias.is_empty() => event.node_id.clone(),
+        None => event.node_alias.clone(),
+    };
+    let short_id = event.node_id.get(..8).unwrap_or(&event.node_id);
+
+    match label["environment"].as_str() {
+        Some(environment) => format!("{name} ({short_id}) [{environment}]"),
+        None if name == event.node_id => name,
+        None => format!("{name} ({short_id})"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::EventType;
+    use chrono::Utc;
+
+    fn disconnected_event() -> Event {
+        Event {
+            id: "event".to_string(),
+            account_id: "account".to_string(),
+            user_id: "user".to_string(),
+            node_id: "node".to_string(),
+            node_alias: "alias".to_string(),
+            event_type: EventType::NodeDisconnected,
+            severity: EventSeverity::Warning,
+            title: "Node Disconnected".to_string(),
+            description: "Peer went offline".to_string(),
+            data: json!({ "remote_pubkey": "peer" }).to_string(),
+            notifications_id: None,
+            timestamp: Utc::now(),
+            acknowledged_by: None,
+            acknowledged_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            is_deleted: false,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn collapses_repeats_within_the_window() {
+        let window = Duration::from_secs(60);
+        let start = Instant::now();
+        let event = disconnected_event();
+        let key = DedupKey {
+            notification_id: "webhook".to_string(),
+            event_type: event.event_type.to_string(),
+            node_id: event.node_id.clone(),
+            fields: "remote_pubkey=\"peer\"".to_string(),
+        };
+        let mut state = DedupState::default();
+
+        assert_eq!(
+            state.record(key.clone(), &event, window, start),
+            Occurrence::Send
+        );
+        assert_eq!(
+            state.record(key.clone(), &event, window, start + Duration::from_secs(30)),
+            Occurrence::Suppress { first: true }
+        );
+        // Each repeat slides the window
+        assert_eq!(
+            state.record(key.clone(), &event, window, start + Duration::from_secs(80)),
+            Occurrence::Suppress { first: false }
+        );
+        assert_eq!(
+            state
+                .close_if_quiet(&key, window, start + Duration::from_secs(100))
+                .unwrap_err(),
+            start + Duration::from_secs(140)
+        );
+
+        let (suppressed, latest) = state
+            .close_if_quiet(&key, window, start + Duration::from_secs(140))
+            .unwrap()
+            .unwrap();
+        assert_eq!(suppressed, 2);
+        assert_eq!(
+            summary_event(latest, suppressed, window).title,
+            "Node Disconnected (occurred 3 times)"
+        );
+        assert_eq!(
+            state.record(key, &event, window, start + Duration::from_secs(150)),
+            Occurrence::Send
+        );
+    }
+
+    #[test]
+    fn discord_embeds_carry_the_account_branding() {
+        let event = disconnected_event();
+
+        let embed = discord_embed(&event, &BrandingSettings::default());
+        assert_eq!(embed["footer"]["text"], DEFAULT_FOOTER_TEXT);
+        assert!(embed.get("author").is_none());
+
+        let branding = BrandingSettings {
+            display_name: Some("Acme Routing".to_string()),
+            logo_url: Some("https://acme.example/logo.png".to_string()),
+            footer_text: Some("Acme node operations".to_string()),
+        };
+        let embed = discord_embed(&event, &branding);
+        assert_eq!(embed["author"]["name"], "Acme Routing");
+        assert_eq!(embed["author"]["icon_url"], "https://acme.example/logo.png");
+        assert_eq!(embed["footer"]["text"], "Acme node operations");
+        assert_eq!(embed["footer"]["icon_url"], "https://acme.example/logo.png");
     }
 }
@@ -0,0 +1,214 @@
+//! Sending the stored events of a notification endpoint to it again.
+//!
+//! After an outage of a receiver, `POST /api/notification/{id}/replay` sends
+//! the events recorded for the endpoint within a time range once more, oldest
+//! first, optionally narrowed to some event types, severities or nodes. Events
+//! are sent at most `REPLAY_EVENTS_PER_SECOND` a second in the background so
+//! the receiver isn't flooded, and are marked as replays: webhook payloads
+//! carry `"replay": true` and other endpoints see a `[Replay]` title. PagerDuty
+//! and Opsgenie endpoints can't be replayed to, as that would reopen incidents
+//! long resolved. Each endpoint runs one replay at a time.
+
+use crate::database::models::{Event, NotificationType, ReplayNotificationRequest};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::event_repository::EventRepository;
+use crate::services::notification_dispatcher::NotificationDispatcher;
+use crate::services::notification_service::NotificationService;
+use crate::services::settings_service::SettingsService;
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+/// Most events one replay sends.
+pub const MAX_REPLAY_EVENTS: usize = 1000;
+/// Most stored events looked at before filtering.
+const MAX_SCANNED_EVENTS: usize = 10 * MAX_REPLAY_EVENTS;
+/// Pace at which replayed events are sent.
+const REPLAY_EVENTS_PER_SECOND: u64 = 5;
+
+/// Endpoints with a replay in progress.
+static REPLAYING: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(Default::default);
+
+/// Response of `POST /api/notification/{id}/replay`.
+#[derive(Debug, Serialize)]
+pub struct ReplayStarted {
+    pub notification_id: String,
+    /// Events that will be sent
+    pub event_count: usize,
+    /// Seconds until the last of them is sent
+    pub estimated_seconds: u64,
+}
+
+/// Whether an event passes the type, severity and node filters of a replay.
+pub fn matches_replay(event: &Event, request: &ReplayNotificationRequest) -> bool {
+    fn allows<T: PartialEq>(filter: &Option<Vec<T>>, value: &T) -> bool {
+        filter
+            .as_ref()
+            .is_none_or(|values| values.is_empty() || values.contains(value))
+    }
+    allows(&request.event_types, &event.event_type)
+        && allows(&request.severities, &event.severity)
+        && allows(&request.node_ids, &event.node_id)
+}
+
+/// Starts sending the endpoint's events matching `request` to it again.
+///
+/// # Errors
+/// Returns `ServiceError` for:
+/// - Endpoints that don't exist in the account, are inactive or on-call services
+/// - Invalid time ranges and ranges with more than `MAX_REPLAY_EVENTS` events
+/// - Endpoints with a replay already in progress
+/// - Database errors
+pub async fn start_replay(
+    pool: &SqlitePool,
+    account_id: &str,
+    notification_id: &str,
+    request: ReplayNotificationRequest,
+) -> ServiceResult<ReplayStarted> {
+    let notification = NotificationService::new(pool)
+        .get_notification_required(notification_id, account_id)
+        .await?;
+    if !notification.is_active {
+        return Err(ServiceError::invalid_operation(
+            "Inactive notification endpoints can't be replayed to",
+        ));
+    }
+    if matches!(
+        notification.notification_type,
+        NotificationType::PagerDuty | NotificationType::Opsgenie
+    ) {
+        return Err(ServiceError::invalid_operation(
+            "Replaying to PagerDuty or Opsgenie would reopen resolved incidents",
+        ));
+    }
+
+    let end = request.end_date.unwrap_or_else(Utc::now);
+    if request.start_date > end {
+        return Err(ServiceError::validation(
+            "start_date must not be after end_date",
+        ));
+    }
+    let stored = EventRepository::new(pool)
+        .get_notification_events_between(
+            notification_id,
+            request.start_date,
+            end,
+            MAX_SCANNED_EVENTS as i64 + 1,
+        )
+        .await?;
+    let scanned = stored.len();
+    let events: Vec<Event> = stored
+        .into_iter()
+        .filter(|event| matches_replay(event, &request))
+        .collect();
+    if scanned > MAX_SCANNED_EVENTS || events.len() > MAX_REPLAY_EVENTS {
+        return Err(ServiceError::validation(format!(
+            "More than {MAX_REPLAY_EVENTS} events match; narrow the time range or filters"
+        )));
+    }
+
+    if !REPLAYING
+        .lock()
+        .unwrap()
+        .insert(notification_id.to_string())
+    {
+        return Err(ServiceError::already_exists(
+            "Replay for notification",
+            notification_id,
+        ));
+    }
+
+    let branding = match SettingsService::new(pool).get_settings(account_id).await {
+        Ok(settings) => settings.branding,
+        Err(e) => {
+            tracing::warn!("Failed to load settings of account {}: {}", account_id, e);
+            Default::default()
+        }
+    };
+    let started = ReplayStarted {
+        notification_id: notification_id.to_string(),
+        event_count: events.len(),
+        estimated_seconds: events.len() as u64 / REPLAY_EVENTS_PER_SECOND,
+    };
+
+    tokio::spawn(async move {
+        let dispatcher = NotificationDispatcher::new();
+        let mut interval =
+            tokio::time::interval(Duration::from_millis(1000 / REPLAY_EVENTS_PER_SECOND));
+        let mut failed = 0;
+        for event in &events {
+            interval.tick().await;
+            if let Err(e) = dispatcher
+                .replay_to_endpoint(event, notification.clone(), &branding)
+                .await
+            {
+                tracing::warn!(
+                    "Failed to replay event {} to notification {}: {}",
+                    event.id,
+                    notification.id,
+                    e
+                );
+                failed += 1;
+            }
+        }
+        tracing::info!(
+            "Replayed {} event(s) to notification {}, {} failed",
+            events.len(),
+            notification.id,
+            failed
+        );
+        REPLAYING.lock().unwrap().remove(&notification.id);
+    });
+
+    Ok(started)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::{EventSeverity, EventType};
+
+    fn event(event_type: EventType, severity: EventSeverity, node_id: &str) -> Event {
+        Event {
+            id: "event".to_string(),
+            account_id: "account".to_string(),
+            user_id: "user".to_string(),
+            node_id: node_id.to_string(),
+            node_alias: String::new(),
+            event_type,
+            severity,
+            title: String::new(),
+            description: String::new(),
+            data: "{}".to_string(),
+            notifications_id: Some("notification".to_string()),
+            timestamp: Utc::now(),
+            acknowledged_by: None,
+            acknowledged_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            is_deleted: false,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn replays_events_passing_every_filter() {
+        let mut request = ReplayNotificationRequest {
+            start_date: Utc::now(),
+            end_date: None,
+            event_types: None,
+            severities: Some(vec![EventSeverity::Critical]),
+            node_ids: Some(vec![]),
+        };
+        let closed = event(EventType::ChannelClosed, EventSeverity::Critical, "node1");
+        let opened = event(EventType::ChannelOpened, EventSeverity::Info, "node1");
+        assert!(matches_replay(&closed, &request));
+        assert!(!matches_replay(&opened, &request));
+
+        request.node_ids = Some(vec!["node2".to_string()]);
+        assert!(!matches_replay(&closed, &request));
+    }
+}
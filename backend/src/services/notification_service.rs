@@ -2,17 +2,22 @@
 //!
 //! Handles all notification-related business operations
 
+use crate::config;
 use crate::database::models::{
-    CreateNotification, CreateNotificationRequest, EventResponse, Notification,
+    CreateNotification, CreateNotificationRequest, EventResponse, Notification, NotificationType,
     UpdateNotificationRequest, User,
 };
 use crate::errors::{ServiceError, ServiceResult};
 use crate::repositories::event_repository::EventRepository;
 use crate::repositories::notification_repository::NotificationRepository;
+use crate::services::quota_service::QuotaService;
+use crate::utils::{nostr, on_call, webhook_headers};
 use chrono::Utc;
 use reqwest::Client;
+use reqwest::header::HeaderMap;
 use serde_json::json;
 use sqlx::SqlitePool;
+use std::collections::BTreeMap;
 use std::time::Duration;
 use uuid::Uuid;
 use validator::Validate;
@@ -53,8 +58,21 @@ impl<'a> NotificationService<'a> {
             return Err(ServiceError::validation(error_messages.join(", ")));
         }
 
+        let headers = create_request.headers.unwrap_or_default();
+        let parsed_headers = self.validate_headers(&headers, &create_request.notification_type)?;
         // Validate URL based on notification type
-        self.validate_url(&create_request.url, &create_request.notification_type)
+        self.validate_url(
+            &create_request.url,
+            &create_request.notification_type,
+            &parsed_headers,
+        )
+        .await?;
+        self.validate_recipient(
+            create_request.recipient.as_deref(),
+            &create_request.notification_type,
+        )?;
+        QuotaService::new(self.pool)
+            .check_notification_quota(&user.account_id)
             .await?;
 
         let create_notification = CreateNotification {
@@ -64,6 +82,8 @@ impl<'a> NotificationService<'a> {
             name: create_request.name,
             notification_type: create_request.notification_type,
             url: create_request.url,
+            recipient: create_request.recipient,
+            headers: self.seal_headers(&headers)?,
         };
 
         let repo = NotificationRepository::new(self.pool);
@@ -131,9 +151,34 @@ impl<'a> NotificationService<'a> {
         // First verify the notification exists and belongs to the account
         let existing = self.get_notification_required(id, account_id).await?;
 
-        // Validate URL if provided
-        if let Some(ref url) = update_request.url {
-            self.validate_url(url, &existing.notification_type).await?;
+        let parsed_headers = update_request
+            .headers
+            .as_ref()
+            .map(|headers| self.validate_headers(headers, &existing.notification_type))
+            .transpose()?;
+        // Validate the URL if it or the headers sent to it change
+        if update_request.url.is_some() || parsed_headers.is_some() {
+            let headers = match parsed_headers {
+                Some(headers) => headers,
+                None => webhook_headers::stored_headers(existing.headers.as_deref())?,
+            };
+            self.validate_url(
+                update_request.url.as_deref().unwrap_or(&existing.url),
+                &existing.notification_type,
+                &headers,
+            )
+            .await?;
+        }
+        let sealed_headers = update_request
+            .headers
+            .as_ref()
+            .map(|headers| self.seal_headers(headers))
+            .transpose()?;
+        if update_request.recipient.is_some() {
+            self.validate_recipient(
+                update_request.recipient.as_deref(),
+                &existing.notification_type,
+            )?;
         }
 
         let repo = NotificationRepository::new(self.pool);
@@ -142,6 +187,8 @@ impl<'a> NotificationService<'a> {
                 id,
                 update_request.name.as_deref(),
                 update_request.url.as_deref(),
+                update_request.recipient.as_deref(),
+                sealed_headers.as_ref().map(Option::as_deref),
                 update_request.is_active,
             )
             .await?;
@@ -207,10 +254,13 @@ impl<'a> NotificationService<'a> {
     }
 
     /// Validates URL based on notification type.
+    ///
+    /// Webhooks are sent a test request carrying their custom headers.
     async fn validate_url(
         &self,
         url: &str,
         notification_type: &crate::database::models::NotificationType,
+        headers: &HeaderMap,
     ) -> ServiceResult<()> {
         match notification_type {
             crate::database::models::NotificationType::Discord => {
@@ -221,13 +271,106 @@ impl<'a> NotificationService<'a> {
                 }
             }
             crate::database::models::NotificationType::Webhook => {
-                self.test_webhook_connection(url).await?;
+                self.test_webhook_connection(url, headers).await?;
+            }
+            crate::database::models::NotificationType::Nostr => {
+                if !url.starts_with("wss://") && !url.starts_with("ws://") {
+                    return Err(ServiceError::validation(
+                        "Nostr URLs must be relay websocket URLs (wss://)",
+                    ));
+                }
+            }
+            // Test requests would page whoever is on call, so only the host is checked
+            crate::database::models::NotificationType::PagerDuty => {
+                if !is_https_url_on(url, "pagerduty.com") {
+                    return Err(ServiceError::validation(
+                        "PagerDuty URLs must be Events API URLs, e.g. https://events.pagerduty.com/v2/enqueue",
+                    ));
+                }
+            }
+            crate::database::models::NotificationType::Opsgenie => {
+                if !is_https_url_on(url, "opsgenie.com") {
+                    return Err(ServiceError::validation(
+                        "Opsgenie URLs must be alerts API URLs, e.g. https://api.opsgenie.com/v2/alerts",
+                    ));
+                }
             }
         }
         Ok(())
     }
 
-    async fn test_webhook_connection(&self, url: &str) -> ServiceResult<()> {
+    /// Validates the recipient, which Nostr, PagerDuty and Opsgenie
+    /// notifications require and others don't take.
+    fn validate_recipient(
+        &self,
+        recipient: Option<&str>,
+        notification_type: &crate::database::models::NotificationType,
+    ) -> ServiceResult<()> {
+        match (notification_type, recipient) {
+            (crate::database::models::NotificationType::Nostr, Some(recipient)) => {
+                nostr::parse_public_key(recipient).map_err(ServiceError::validation)?;
+            }
+            (crate::database::models::NotificationType::Nostr, None) => {
+                return Err(ServiceError::validation(
+                    "Nostr notifications require a recipient npub",
+                ));
+            }
+            (
+                crate::database::models::NotificationType::PagerDuty
+                | crate::database::models::NotificationType::Opsgenie,
+                Some(key),
+            ) => {
+                on_call::validate_integration_key(key).map_err(ServiceError::validation)?;
+            }
+            (
+                crate::database::models::NotificationType::PagerDuty
+                | crate::database::models::NotificationType::Opsgenie,
+                None,
+            ) => {
+                return Err(ServiceError::validation(
+                    "PagerDuty and Opsgenie notifications require their integration key as recipient",
+                ));
+            }
+            (_, Some(_)) => {
+                return Err(ServiceError::validation(
+                    "Only Nostr, PagerDuty and Opsgenie notifications take a recipient",
+                ));
+            }
+            (_, None) => {}
+        }
+        Ok(())
+    }
+
+    /// Validates custom headers, which only webhook notifications take.
+    fn validate_headers(
+        &self,
+        headers: &BTreeMap<String, String>,
+        notification_type: &NotificationType,
+    ) -> ServiceResult<HeaderMap> {
+        if !headers.is_empty() && *notification_type != NotificationType::Webhook {
+            return Err(ServiceError::validation(
+                "Only webhook notifications take custom headers",
+            ));
+        }
+        webhook_headers::parse_headers(headers).map_err(ServiceError::validation)
+    }
+
+    /// Encrypts custom headers for storage with the configured key.
+    fn seal_headers(&self, headers: &BTreeMap<String, String>) -> ServiceResult<Option<String>> {
+        if headers.is_empty() {
+            return Ok(None);
+        }
+        let key = config::shared()?
+            .notification_encryption_key
+            .ok_or_else(|| {
+                ServiceError::validation(
+                    "Custom headers require NOTIFICATION_ENCRYPTION_KEY to be configured",
+                )
+            })?;
+        Ok(webhook_headers::seal_headers(&key, headers)?)
+    }
+
+    async fn test_webhook_connection(&self, url: &str, headers: &HeaderMap) -> ServiceResult<()> {
         let client = Client::builder()
             .timeout(Duration::from_secs(5))
             .build()
@@ -237,6 +380,7 @@ impl<'a> NotificationService<'a> {
 
         let response = client
             .post(url)
+            .headers(headers.clone())
             .json(&json!({
                 "event": "Ping",
                 "timestamp": Utc::now().to_rfc3339()
@@ -260,3 +404,13 @@ impl<'a> NotificationService<'a> {
         Ok(())
     }
 }
+
+/// Whether a URL is an https URL on `domain` or one of its subdomains.
+fn is_https_url_on(url: &str, domain: &str) -> bool {
+    reqwest::Url::parse(url).is_ok_and(|url| {
+        url.scheme() == "https"
+            && url
+                .host_str()
+                .is_some_and(|host| host == domain || host.ends_with(&format!(".{domain}")))
+    })
+}
@@ -0,0 +1,132 @@
+//! Payment annotation business logic service.
+//!
+//! Users tag payments and invoices (`rebalance`, `customer refund`, …) and add
+//! notes to them. Annotations are stored per account by payment hash, which
+//! payments and invoices share, and are merged into payment listings. On-chain
+//! transactions are annotated by txid, and their notes are kept in sync with
+//! the node's transaction labels by `transaction_labels`.
+
+use crate::database::models::{PaymentAnnotation, UpdatePaymentAnnotationRequest};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::payment_annotation_repository::PaymentAnnotationRepository;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use validator::Validate;
+
+/// Trims tags and drops repeats, comparing without regard to case.
+fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags.iter().map(|tag| tag.trim()) {
+        if !normalized.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            normalized.push(tag.to_string());
+        }
+    }
+    normalized
+}
+
+/// Service layer for payment annotation operations.
+pub struct PaymentAnnotationService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> PaymentAnnotationService<'a> {
+    /// Creates a new PaymentAnnotationService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Returns the account's annotations keyed by payment hash.
+    pub async fn get_annotations(
+        &self,
+        account_id: &str,
+    ) -> ServiceResult<HashMap<String, PaymentAnnotation>> {
+        let annotations = PaymentAnnotationRepository::new(self.pool)
+            .get_annotations_by_account_id(account_id)
+            .await?;
+
+        Ok(annotations
+            .into_iter()
+            .map(|annotation| (annotation.payment_hash.clone(), annotation))
+            .collect())
+    }
+
+    /// Returns the annotation of a single payment.
+    pub async fn get_annotation(
+        &self,
+        account_id: &str,
+        payment_hash: &str,
+    ) -> ServiceResult<Option<PaymentAnnotation>> {
+        Ok(PaymentAnnotationRepository::new(self.pool)
+            .get_annotation(account_id, &payment_hash.to_lowercase())
+            .await?)
+    }
+
+    /// Sets the tags and notes of a payment, replacing any previous ones.
+    pub async fn update_annotation(
+        &self,
+        account_id: &str,
+        payment_hash: &str,
+        request: UpdatePaymentAnnotationRequest,
+    ) -> ServiceResult<PaymentAnnotation> {
+        request
+            .validate()
+            .map_err(|e| ServiceError::validation(e.to_string()))?;
+
+        let payment_hash = payment_hash.to_lowercase();
+        let notes = request
+            .notes
+            .as_deref()
+            .map(str::trim)
+            .filter(|notes| !notes.is_empty());
+
+        let repo = PaymentAnnotationRepository::new(self.pool);
+        repo.upsert_annotation(
+            account_id,
+            &payment_hash,
+            &normalize_tags(&request.tags),
+            notes,
+        )
+        .await?;
+
+        repo.get_annotation(account_id, &payment_hash)
+            .await?
+            .ok_or_else(|| ServiceError::not_found("Payment annotation", &payment_hash))
+    }
+
+    /// Removes the annotation of a payment.
+    pub async fn delete_annotation(
+        &self,
+        account_id: &str,
+        payment_hash: &str,
+    ) -> ServiceResult<()> {
+        let payment_hash = payment_hash.to_lowercase();
+        if !PaymentAnnotationRepository::new(self.pool)
+            .delete_annotation(account_id, &payment_hash)
+            .await?
+        {
+            return Err(ServiceError::not_found("Payment annotation", &payment_hash));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_tags() {
+        let tags = [
+            "rebalance ",
+            "Customer refund",
+            "REBALANCE",
+            "customer refund",
+        ]
+        .map(str::to_string);
+        assert_eq!(
+            normalize_tags(&tags),
+            vec!["rebalance".to_string(), "Customer refund".to_string()]
+        );
+    }
+}
@@ -0,0 +1,518 @@
+//! Time-bucketed payment volume and success-rate statistics.
+//!
+//! Figures are computed from the node's payment list and forwarding history and
+//! grouped into fixed-size buckets for dashboard charts, aligned to the
+//! account's time zone. The statistics endpoints read them from the hourly
+//! rollups of `services::stats_rollups`, and the hours those can't be split
+//! into buckets from the stored payment records.
+
+use crate::database::models::{PaymentHourlyRollup, PaymentRecord, PaymentRecordKind};
+use crate::utils::time_zone::{DateBound, LocalBuckets};
+use crate::utils::{ForwardSummary, PaymentState, PaymentSummary, PaymentType};
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+/// Longest window the statistics can cover.
+pub const MAX_STATS_WINDOW: Duration = Duration::days(365);
+
+/// Width of each statistics bucket.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsBucket {
+    Hour,
+    #[default]
+    Day,
+    Week,
+}
+
+impl StatsBucket {
+    /// Bucket width in seconds.
+    pub fn seconds(self) -> i64 {
+        match self {
+            StatsBucket::Hour => 3_600,
+            StatsBucket::Day => 86_400,
+            StatsBucket::Week => 604_800,
+        }
+    }
+}
+
+/// Number and total amount of payments in one direction.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct FlowStats {
+    pub count: u64,
+    pub volume_sat: u64,
+}
+
+impl FlowStats {
+    fn add(&mut self, amount_sat: u64) {
+        self.count += 1;
+        self.volume_sat += amount_sat;
+    }
+
+    fn merge(&mut self, count: i64, volume_sat: i64) {
+        self.count += count.max(0) as u64;
+        self.volume_sat += volume_sat.max(0) as u64;
+    }
+}
+
+/// Payment figures for a span of time.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct PaymentFlowStats {
+    /// Settled outgoing payments
+    pub outgoing: FlowStats,
+    /// Settled incoming payments
+    pub incoming: FlowStats,
+    /// Settled forwards, by amount forwarded
+    pub forwarded: FlowStats,
+    /// Outgoing payments that failed
+    pub failed_outgoing: u64,
+    /// Fees earned by settled forwards
+    pub fee_revenue_msat: u64,
+    /// Share of finished outgoing payments that settled, absent when none finished
+    pub success_rate: Option<f64>,
+}
+
+impl PaymentFlowStats {
+    /// Counts a payment that settled, or an outgoing one that failed.
+    pub fn add_payment(&mut self, payment: &PaymentSummary) {
+        match (payment.state, &payment.payment_type) {
+            (PaymentState::Settled, PaymentType::Outgoing) => {
+                self.outgoing.add(payment.amount.sat())
+            }
+            (PaymentState::Settled, PaymentType::Incoming) => {
+                self.incoming.add(payment.amount.sat())
+            }
+            (PaymentState::Settled, PaymentType::Forwarded) => {
+                self.forwarded.add(payment.amount.sat())
+            }
+            (PaymentState::Failed, PaymentType::Outgoing) => self.failed_outgoing += 1,
+            _ => {}
+        }
+    }
+
+    /// Counts a settled forward and its fee.
+    pub fn add_forward(&mut self, forward: &ForwardSummary) {
+        self.forwarded.add(forward.amount_out_msat / 1000);
+        self.fee_revenue_msat += forward.fee_msat;
+    }
+
+    /// Counts a stored payment record.
+    pub fn add_record(&mut self, record: &PaymentRecord) {
+        let amount_sat = record.amount_sat.max(0) as u64;
+        match record.kind {
+            PaymentRecordKind::Outgoing => self.outgoing.add(amount_sat),
+            PaymentRecordKind::Incoming => self.incoming.add(amount_sat),
+            PaymentRecordKind::Forwarded => {
+                self.forwarded.add(amount_sat);
+                self.fee_revenue_msat += record.fee_msat.max(0) as u64;
+            }
+            PaymentRecordKind::FailedOutgoing => self.failed_outgoing += 1,
+        }
+    }
+
+    /// Adds the figures of an hourly rollup.
+    pub fn add_rollup(&mut self, rollup: &PaymentHourlyRollup) {
+        self.outgoing
+            .merge(rollup.outgoing_count, rollup.outgoing_sat);
+        self.incoming
+            .merge(rollup.incoming_count, rollup.incoming_sat);
+        self.forwarded
+            .merge(rollup.forwarded_count, rollup.forwarded_sat);
+        self.failed_outgoing += rollup.failed_outgoing.max(0) as u64;
+        self.fee_revenue_msat += rollup.fee_revenue_msat.max(0) as u64;
+    }
+
+    fn finish(&mut self) {
+        let finished = self.outgoing.count + self.failed_outgoing;
+        self.success_rate = (finished > 0).then(|| self.outgoing.count as f64 / finished as f64);
+    }
+}
+
+/// Payment figures for one bucket.
+#[derive(Debug, Clone, Serialize)]
+pub struct PaymentStatsBucket {
+    pub start: DateTime<Utc>,
+    #[serde(flatten)]
+    pub stats: PaymentFlowStats,
+}
+
+/// Response of `GET /api/payments/stats`.
+#[derive(Debug, Serialize)]
+pub struct PaymentStats {
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub bucket: StatsBucket,
+    /// Time zone the buckets are aligned to
+    pub tz: String,
+    pub buckets: Vec<PaymentStatsBucket>,
+    pub totals: PaymentFlowStats,
+}
+
+/// Parses a window such as `24h`, `30d` or `12w`.
+pub fn parse_window(window: &str) -> Result<Duration, String> {
+    let window = window.trim();
+    let invalid = || format!("Invalid window '{window}', expected e.g. 24h, 30d or 12w");
+
+    let unit = window.chars().last().ok_or_else(invalid)?;
+    let amount: i64 = window[..window.len() - unit.len_utf8()]
+        .parse()
+        .map_err(|_| invalid())?;
+    if amount <= 0 || amount > MAX_STATS_WINDOW.num_hours() {
+        return Err(invalid());
+    }
+
+    let duration = match unit {
+        'h' => Duration::hours(amount),
+        'd' => Duration::days(amount),
+        'w' => Duration::weeks(amount),
+        _ => return Err(invalid()),
+    };
+
+    if duration > MAX_STATS_WINDOW {
+        return Err(format!(
+            "Window '{window}' exceeds the maximum of {} days",
+            MAX_STATS_WINDOW.num_days()
+        ));
+    }
+
+    Ok(duration)
+}
+
+/// Resolves the span statistics cover: `from` to `to` when given, otherwise the
+/// `window` before `to`, which defaults to now.
+pub fn stats_span(
+    window: Duration,
+    from: Option<DateBound>,
+    to: Option<DateBound>,
+    tz: Tz,
+    now: DateTime<Utc>,
+) -> Result<(DateTime<Utc>, DateTime<Utc>), String> {
+    let end = to.map_or(now, |to| to.end(tz));
+    let start = from.map_or(end - window, |from| from.start(tz));
+
+    if start > end {
+        return Err("`from` must not be after `to`".to_string());
+    }
+    if end - start > MAX_STATS_WINDOW {
+        return Err(format!(
+            "Statistics can cover at most {} days",
+            MAX_STATS_WINDOW.num_days()
+        ));
+    }
+    Ok((start, end))
+}
+
+/// Groups payments and forwards between `start` and `end` into buckets.
+///
+/// Buckets are aligned in the time zone `tz`, see `LocalBuckets`. Payments are
+/// placed by completion time, or creation time when they never completed.
+pub fn payment_stats(
+    payments: &[PaymentSummary],
+    forwards: &[ForwardSummary],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    bucket: StatsBucket,
+    tz: Tz,
+) -> PaymentStats {
+    let local_buckets = LocalBuckets::new(start, end, bucket.seconds(), tz);
+    let mut stats = empty_stats(&local_buckets, start, end, bucket, tz);
+    stats.add_reported(&local_buckets, payments, forwards);
+    stats.finish()
+}
+
+/// Groups hourly rollups between `start` and `end` into buckets aligned in `tz`.
+///
+/// A rollup only counts when its whole hour lies in the window and in one
+/// bucket, see `LocalBuckets::covers_hour`. The other hours are counted from
+/// `records`, which need only span them.
+pub fn rollup_payment_stats(
+    rollups: &[PaymentHourlyRollup],
+    records: &[PaymentRecord],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    bucket: StatsBucket,
+    tz: Tz,
+) -> PaymentStats {
+    let local_buckets = LocalBuckets::new(start, end, bucket.seconds(), tz);
+    let mut stats = empty_stats(&local_buckets, start, end, bucket, tz);
+
+    for rollup in rollups {
+        if let Some(index) = local_buckets
+            .covers_hour(rollup.hour)
+            .then(|| local_buckets.index(rollup.hour))
+            .flatten()
+        {
+            stats.buckets[index].stats.add_rollup(rollup);
+            stats.totals.add_rollup(rollup);
+        }
+    }
+
+    for record in records {
+        if let Some(index) = (!local_buckets.covers_hour(record.at))
+            .then(|| local_buckets.index(record.at))
+            .flatten()
+        {
+            stats.buckets[index].stats.add_record(record);
+            stats.totals.add_record(record);
+        }
+    }
+
+    stats.finish()
+}
+
+fn empty_stats(
+    local_buckets: &LocalBuckets,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    bucket: StatsBucket,
+    tz: Tz,
+) -> PaymentStats {
+    PaymentStats {
+        window_start: start,
+        window_end: end,
+        bucket,
+        tz: tz.name().to_string(),
+        buckets: local_buckets
+            .starts()
+            .into_iter()
+            .map(|start| PaymentStatsBucket {
+                start,
+                stats: PaymentFlowStats::default(),
+            })
+            .collect(),
+        totals: PaymentFlowStats::default(),
+    }
+}
+
+impl PaymentStats {
+    /// Counts the payments and forwards in the window.
+    fn add_reported(
+        &mut self,
+        local_buckets: &LocalBuckets,
+        payments: &[PaymentSummary],
+        forwards: &[ForwardSummary],
+    ) {
+        let bucket_index = |timestamp: u64| {
+            let at = DateTime::from_timestamp(i64::try_from(timestamp).ok()?, 0)?;
+            local_buckets.index(at)
+        };
+
+        for payment in payments {
+            if let Some(index) = payment
+                .completed_at
+                .or(payment.creation_time)
+                .and_then(bucket_index)
+            {
+                self.buckets[index].stats.add_payment(payment);
+                self.totals.add_payment(payment);
+            }
+        }
+
+        for forward in forwards {
+            if let Some(index) = bucket_index(forward.resolved_at) {
+                self.buckets[index].stats.add_forward(forward);
+                self.totals.add_forward(forward);
+            }
+        }
+    }
+
+    fn finish(mut self) -> Self {
+        for bucket in &mut self.buckets {
+            bucket.stats.finish();
+        }
+        self.totals.finish();
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::amount::Amount;
+
+    #[test]
+    fn parses_windows() {
+        assert_eq!(parse_window("24h"), Ok(Duration::hours(24)));
+        assert_eq!(parse_window("30d"), Ok(Duration::days(30)));
+        assert_eq!(parse_window("2w"), Ok(Duration::weeks(2)));
+        assert!(parse_window("0d").is_err());
+        assert!(parse_window("30").is_err());
+        assert!(parse_window("").is_err());
+        assert!(parse_window("400d").is_err());
+    }
+
+    #[test]
+    fn resolves_spans_in_the_time_zone() {
+        let now = DateTime::from_timestamp(1_754_000_000, 0).unwrap();
+        let berlin = chrono_tz::Europe::Berlin;
+        let date = |s: &str| Some(s.parse::<DateBound>().unwrap());
+
+        assert_eq!(
+            stats_span(Duration::days(30), None, None, berlin, now),
+            Ok((now - Duration::days(30), now))
+        );
+        let (start, end) = stats_span(
+            Duration::days(30),
+            date("2025-08-01"),
+            date("2025-08-01"),
+            berlin,
+            now,
+        )
+        .unwrap();
+        assert_eq!(start.to_rfc3339(), "2025-07-31T22:00:00+00:00");
+        assert_eq!(end - start, Duration::days(1) - Duration::nanoseconds(1));
+        assert!(
+            stats_span(
+                Duration::days(1),
+                date("2025-08-02"),
+                date("2025-08-01"),
+                berlin,
+                now
+            )
+            .is_err()
+        );
+        assert!(
+            stats_span(
+                Duration::days(1),
+                date("2023-01-01"),
+                date("2025-08-01"),
+                berlin,
+                now
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn buckets_settled_and_failed_payments() {
+        let start = DateTime::from_timestamp(0, 0).unwrap();
+        let end = DateTime::from_timestamp(2 * 86_400 - 1, 0).unwrap();
+        let payment = |state, payment_type, amount_sat, completed_at| PaymentSummary {
+            state,
+            payment_type,
+            amount: Amount::from_sat(amount_sat),
+            amount_usd: Some(0.0),
+            routing_fee: None,
+            creation_time: Some(completed_at),
+            invoice: None,
+            payment_hash: String::new(),
+            completed_at: Some(completed_at),
+        };
+        let payments = vec![
+            payment(PaymentState::Settled, PaymentType::Outgoing, 1_000, 10),
+            payment(PaymentState::Failed, PaymentType::Outgoing, 500, 20),
+            payment(PaymentState::Settled, PaymentType::Incoming, 2_000, 86_400),
+        ];
+        let forwards = vec![ForwardSummary {
+            chan_id_in: None,
+            chan_id_out: None,
+            amount_in_msat: 0,
+            amount_out_msat: 5_000_000,
+            fee_msat: 1_000,
+            resolved_at: 86_500,
+        }];
+
+        let stats = payment_stats(
+            &payments,
+            &forwards,
+            start,
+            end,
+            StatsBucket::Day,
+            chrono_tz::UTC,
+        );
+
+        assert_eq!(stats.buckets.len(), 2);
+        assert_eq!(stats.buckets[0].stats.outgoing.volume_sat, 1_000);
+        assert_eq!(stats.buckets[0].stats.success_rate, Some(0.5));
+        assert_eq!(stats.buckets[1].stats.incoming.count, 1);
+        assert_eq!(stats.buckets[1].stats.forwarded.volume_sat, 5_000);
+        assert_eq!(stats.buckets[1].stats.success_rate, None);
+        assert_eq!(stats.totals.failed_outgoing, 1);
+    }
+
+    #[test]
+    fn aligns_day_buckets_to_local_midnight() {
+        // 22:00 UTC is already the next day at UTC+3
+        let start = DateTime::from_timestamp(0, 0).unwrap();
+        let end = DateTime::from_timestamp(86_399, 0).unwrap();
+        let forwards = vec![ForwardSummary {
+            chan_id_in: None,
+            chan_id_out: None,
+            amount_in_msat: 0,
+            amount_out_msat: 1_000_000,
+            fee_msat: 0,
+            resolved_at: 22 * 3_600,
+        }];
+
+        let stats = payment_stats(
+            &[],
+            &forwards,
+            start,
+            end,
+            StatsBucket::Day,
+            chrono_tz::Etc::GMTMinus3,
+        );
+
+        assert_eq!(stats.buckets.len(), 2);
+        assert_eq!(
+            stats.buckets[1].start,
+            DateTime::from_timestamp(21 * 3_600, 0).unwrap()
+        );
+        assert_eq!(stats.buckets[1].stats.forwarded.count, 1);
+    }
+
+    #[test]
+    fn counts_split_hours_from_records() {
+        // Local midnight in India is half past a UTC hour
+        let start = DateTime::parse_from_rfc3339("2025-07-31T18:30:00Z")
+            .unwrap()
+            .to_utc();
+        let end = start + Duration::days(1) - Duration::nanoseconds(1);
+        let hour = |h: i64| start - Duration::minutes(30) + Duration::hours(h);
+        let rollup = |hour, forwarded_count| PaymentHourlyRollup {
+            node_id: "node".to_string(),
+            hour,
+            outgoing_count: 0,
+            outgoing_sat: 0,
+            incoming_count: 0,
+            incoming_sat: 0,
+            forwarded_count,
+            forwarded_sat: 0,
+            failed_outgoing: 0,
+            fee_revenue_msat: 0,
+        };
+        let record = |at: DateTime<Utc>| PaymentRecord {
+            node_id: "node".to_string(),
+            at,
+            kind: PaymentRecordKind::Forwarded,
+            amount_sat: 1_000,
+            fee_msat: 10,
+        };
+        let rollups = vec![
+            // Both forwards of the hour the window starts within, one before it
+            rollup(hour(0), 2),
+            rollup(hour(10), 3),
+        ];
+        let records = vec![
+            record(start - Duration::minutes(20)),
+            record(start + Duration::minutes(15)),
+            // Within an hour the rollups cover
+            record(hour(10) + Duration::minutes(5)),
+        ];
+
+        let stats = rollup_payment_stats(
+            &rollups,
+            &records,
+            start,
+            end,
+            StatsBucket::Day,
+            chrono_tz::Asia::Kolkata,
+        );
+
+        assert_eq!(stats.buckets.len(), 1);
+        assert_eq!(stats.buckets[0].stats.forwarded.count, 4);
+        assert_eq!(stats.totals.forwarded.volume_sat, 1_000);
+        assert_eq!(stats.totals.fee_revenue_msat, 10);
+    }
+}
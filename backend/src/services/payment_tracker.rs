@@ -0,0 +1,217 @@
+//! Progress reporting for payments tracked with `GET /api/payments/{hash}/track`.
+//!
+//! Nodes report a tracked payment as a series of full snapshots. The tracker
+//! compares each snapshot with what it has already reported and emits only
+//! what changed: attempts sent along a route, attempts that failed or
+//! succeeded, and finally whether the payment settled or failed.
+
+use crate::utils::amount::Amount;
+use crate::utils::{PaymentDetails, PaymentHtlc, PaymentState, Route};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// One step in the progress of a tracked payment.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PaymentProgress {
+    /// An HTLC attempt was sent along a route; attempts after a failed one are retries
+    AttemptStarted {
+        attempt_id: u64,
+        route: Option<Route>,
+        attempt_time: Option<u64>,
+    },
+    /// An HTLC attempt failed
+    AttemptFailed {
+        attempt_id: u64,
+        failure_reason: Option<String>,
+        failure_code: Option<u16>,
+        resolve_time: Option<u64>,
+    },
+    /// An HTLC attempt reached the destination
+    AttemptSucceeded {
+        attempt_id: u64,
+        resolve_time: Option<u64>,
+    },
+    /// The payment was completed
+    Settled {
+        payment_hash: String,
+        amount: Amount,
+        routing_fee: Option<Amount>,
+        completed_at: Option<u64>,
+    },
+    /// The payment failed after exhausting its attempts
+    Failed {
+        payment_hash: String,
+        attempts: usize,
+    },
+}
+
+impl PaymentProgress {
+    /// Name of the server-sent event carrying this step.
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            Self::AttemptStarted { .. } => "attempt_started",
+            Self::AttemptFailed { .. } => "attempt_failed",
+            Self::AttemptSucceeded { .. } => "attempt_succeeded",
+            Self::Settled { .. } => "settled",
+            Self::Failed { .. } => "failed",
+        }
+    }
+
+    /// Whether this is the last step of the payment.
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::Settled { .. } | Self::Failed { .. })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AttemptStatus {
+    InFlight,
+    Resolved,
+}
+
+/// Remembers what has been reported about a payment so far.
+#[derive(Debug, Default)]
+pub struct PaymentTracker {
+    attempts: HashMap<u64, AttemptStatus>,
+    finished: bool,
+}
+
+impl PaymentTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the progress made since the previous snapshot.
+    pub fn update(&mut self, payment: PaymentDetails) -> Vec<PaymentProgress> {
+        let mut progress = Vec::new();
+        if self.finished {
+            return progress;
+        }
+
+        let attempts = payment.htlcs.len();
+        for htlc in payment.htlcs {
+            let PaymentHtlc {
+                mut routes,
+                attempt_id,
+                attempt_time,
+                resolve_time,
+                failure_reason,
+                failure_code,
+            } = htlc;
+
+            let status = self.attempts.get(&attempt_id).copied();
+            if status.is_none() {
+                progress.push(PaymentProgress::AttemptStarted {
+                    attempt_id,
+                    route: routes.pop(),
+                    attempt_time,
+                });
+                self.attempts.insert(attempt_id, AttemptStatus::InFlight);
+            }
+            if status == Some(AttemptStatus::Resolved) {
+                continue;
+            }
+
+            if failure_reason.is_some() || failure_code.is_some() {
+                progress.push(PaymentProgress::AttemptFailed {
+                    attempt_id,
+                    failure_reason,
+                    failure_code,
+                    resolve_time,
+                });
+            } else if resolve_time.is_some() {
+                progress.push(PaymentProgress::AttemptSucceeded {
+                    attempt_id,
+                    resolve_time,
+                });
+            } else {
+                // Still in flight: no failure and no resolve time yet
+                continue;
+            }
+            self.attempts.insert(attempt_id, AttemptStatus::Resolved);
+        }
+
+        match payment.state {
+            PaymentState::Settled => progress.push(PaymentProgress::Settled {
+                payment_hash: payment.payment_hash,
+                amount: payment.amount,
+                routing_fee: payment.routing_fee,
+                completed_at: payment.completed_at,
+            }),
+            PaymentState::Failed => progress.push(PaymentProgress::Failed {
+                payment_hash: payment.payment_hash,
+                attempts,
+            }),
+            PaymentState::Inflight => {}
+        }
+        self.finished = progress.iter().any(PaymentProgress::is_final);
+
+        progress
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::PaymentType;
+
+    fn htlc(attempt_id: u64, resolve_time: Option<u64>, failure_code: Option<u16>) -> PaymentHtlc {
+        PaymentHtlc {
+            routes: Vec::new(),
+            attempt_id,
+            attempt_time: Some(100),
+            resolve_time,
+            failure_reason: failure_code.map(|code| format!("code {code}")),
+            failure_code,
+        }
+    }
+
+    fn payment(state: PaymentState, htlcs: Vec<PaymentHtlc>) -> PaymentDetails {
+        PaymentDetails {
+            state,
+            payment_type: PaymentType::Outgoing,
+            amount: Amount::from_sat(1_000),
+            amount_usd: Some(0.0),
+            routing_fee: Some(Amount::from_msat(2_500)),
+            network: None,
+            description: None,
+            creation_time: Some(100),
+            invoice: None,
+            payment_hash: "ab".repeat(32),
+            destination_pubkey: None,
+            completed_at: None,
+            htlcs,
+        }
+    }
+
+    fn names(progress: &[PaymentProgress]) -> Vec<&'static str> {
+        progress.iter().map(PaymentProgress::event_name).collect()
+    }
+
+    #[test]
+    fn reports_only_new_progress() {
+        let mut tracker = PaymentTracker::new();
+
+        let first = tracker.update(payment(PaymentState::Inflight, vec![htlc(1, None, None)]));
+        assert_eq!(names(&first), ["attempt_started"]);
+
+        let retry = tracker.update(payment(
+            PaymentState::Inflight,
+            vec![htlc(1, Some(110), Some(15)), htlc(2, None, None)],
+        ));
+        assert_eq!(names(&retry), ["attempt_failed", "attempt_started"]);
+
+        let settled = tracker.update(payment(
+            PaymentState::Settled,
+            vec![htlc(1, Some(110), Some(15)), htlc(2, Some(120), None)],
+        ));
+        assert_eq!(names(&settled), ["attempt_succeeded", "settled"]);
+
+        assert!(
+            tracker
+                .update(payment(PaymentState::Settled, Vec::new()))
+                .is_empty()
+        );
+    }
+}
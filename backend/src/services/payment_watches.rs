@@ -0,0 +1,460 @@
+//! Watch list of payment hashes, e.g. the invoices of an external order system.
+//!
+//! `POST /api/payments/watch` registers hashes. Every 30 seconds the payments
+//! of the nodes a watch covers are searched for its hash: a settled payment or
+//! paid invoice settles the watch and a failed payment fails it, raising a
+//! `WatchedPaymentSettled` or `WatchedPaymentFailed` event on the node. A watch
+//! that finds neither before it expires ends as expired. Each change is also
+//! sent to the watch's `callback_url` as a `payment_watch.updated` webhook
+//! through the webhook delivery queue. A failed payment ends its watch; watch
+//! the hash again to follow a retry.
+
+use crate::database::models::{
+    CreateEvent, Credential, EventSeverity, EventType, PaymentWatch, WatchPaymentsRequest,
+    WatchStatus,
+};
+use crate::errors::{LightningError, ServiceError, ServiceResult};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::repositories::payment_watch_repository::PaymentWatchRepository;
+use crate::repositories::webhook_delivery_repository::WebhookDeliveryRepository;
+use crate::services::data_aggregator::{NODE_CONNECT_TIMEOUT, NODE_QUERY_TIMEOUT};
+use crate::services::event_service::EventService;
+use crate::services::invoice_webhooks::pending_delivery;
+use crate::services::node_group_service::NodeGroupService;
+use crate::services::node_manager::connect_node;
+use crate::services::task_supervisor;
+use crate::utils::jwt::Claims;
+use crate::utils::{PaymentState, PaymentSummary};
+use chrono::{Duration, Utc};
+use serde_json::{Value, json};
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use tokio::time::timeout;
+use uuid::Uuid;
+use validator::Validate;
+
+pub const PAYMENT_WATCH_TOPIC: &str = "payment_watch.updated";
+
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+const DEFAULT_WATCH_MINUTES: u32 = 24 * 60;
+
+/// What a watch's payment came to on one node.
+#[derive(Debug, Clone)]
+pub struct WatchOutcome<'a> {
+    pub status: WatchStatus,
+    pub node_id: &'a str,
+    pub payment: &'a PaymentSummary,
+}
+
+/// Picks the outcome of a watch from its hash's payments on the nodes it
+/// covers. A settlement on any node wins over failures on others.
+pub fn watch_outcome<'a>(payments: &[(&'a str, &'a PaymentSummary)]) -> Option<WatchOutcome<'a>> {
+    let find = |state: PaymentState| {
+        payments
+            .iter()
+            .find(|(_, payment)| payment.state == state)
+            .copied()
+    };
+    if let Some((node_id, payment)) = find(PaymentState::Settled) {
+        return Some(WatchOutcome {
+            status: WatchStatus::Settled,
+            node_id,
+            payment,
+        });
+    }
+    find(PaymentState::Failed).map(|(node_id, payment)| WatchOutcome {
+        status: WatchStatus::Failed,
+        node_id,
+        payment,
+    })
+}
+
+/// Body of a `payment_watch.updated` delivery.
+pub fn watch_payload(
+    watch: &PaymentWatch,
+    status: WatchStatus,
+    outcome: Option<&WatchOutcome>,
+    idempotency_key: &str,
+) -> Value {
+    json!({
+        "topic": PAYMENT_WATCH_TOPIC,
+        "idempotency_key": idempotency_key,
+        "created_at": Utc::now(),
+        "data": {
+            "watch_id": watch.id,
+            "payment_hash": watch.payment_hash,
+            "status": status,
+            "node_id": outcome.map(|outcome| outcome.node_id),
+            "payment_type": outcome.map(|outcome| &outcome.payment.payment_type),
+            "amount_sat": outcome.map(|outcome| outcome.payment.amount.sat()),
+            "amount_msat": outcome.map(|outcome| outcome.payment.amount.msat()),
+            "routing_fee": outcome.and_then(|outcome| outcome.payment.routing_fee),
+            "completed_at": outcome.and_then(|outcome| outcome.payment.completed_at),
+            "expires_at": watch.expires_at,
+        },
+    })
+}
+
+/// Service layer for payment watch operations.
+pub struct PaymentWatchService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> PaymentWatchService<'a> {
+    /// Creates a new PaymentWatchService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Watches payment hashes on the nodes the user may see.
+    ///
+    /// Hashes whose payment already settled or failed keep their watch.
+    pub async fn watch_payments(
+        &self,
+        claims: &Claims,
+        request: WatchPaymentsRequest,
+    ) -> ServiceResult<Vec<PaymentWatch>> {
+        request
+            .validate()
+            .map_err(|e| ServiceError::validation(e.to_string()))?;
+
+        let scope = NodeGroupService::new(self.pool)
+            .node_scope_for_claims(claims)
+            .await?;
+        let now = Utc::now();
+        let expires_at = now
+            + Duration::minutes(
+                request
+                    .expires_in_minutes
+                    .unwrap_or(DEFAULT_WATCH_MINUTES)
+                    .into(),
+            );
+
+        let repo = PaymentWatchRepository::new(self.pool);
+        let mut hashes: Vec<String> = request
+            .payment_hashes
+            .iter()
+            .map(|hash| hash.to_lowercase())
+            .collect();
+        let mut seen = HashSet::new();
+        hashes.retain(|hash| seen.insert(hash.clone()));
+        let mut watches = Vec::with_capacity(hashes.len());
+        for payment_hash in hashes {
+            repo.upsert_watch(&PaymentWatch {
+                id: Uuid::now_v7().to_string(),
+                account_id: claims.account_id.clone(),
+                user_id: claims.sub.clone(),
+                payment_hash: payment_hash.clone(),
+                callback_url: request.callback_url.clone(),
+                node_ids: scope.node_ids().map(<[String]>::to_vec),
+                status: WatchStatus::Pending,
+                node_id: None,
+                created_at: now,
+                expires_at,
+                resolved_at: None,
+            })
+            .await?;
+            if let Some(watch) = repo.get_watch(&claims.account_id, &payment_hash).await? {
+                watches.push(watch);
+            }
+        }
+        Ok(watches)
+    }
+
+    /// Lists an account's watches, newest first.
+    pub async fn get_watches(
+        &self,
+        account_id: &str,
+        status: Option<WatchStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> ServiceResult<Vec<PaymentWatch>> {
+        Ok(PaymentWatchRepository::new(self.pool)
+            .get_watches_by_account_id(account_id, status, limit, offset)
+            .await?)
+    }
+
+    /// Stops watching a hash.
+    pub async fn unwatch(&self, account_id: &str, payment_hash: &str) -> ServiceResult<()> {
+        let payment_hash = payment_hash.to_lowercase();
+        let deleted = PaymentWatchRepository::new(self.pool)
+            .delete_watch(account_id, &payment_hash)
+            .await?;
+        if !deleted {
+            return Err(ServiceError::not_found("Payment watch", &payment_hash));
+        }
+        Ok(())
+    }
+}
+
+/// Expires overdue watches and resolves those whose payment settled or failed.
+pub async fn check_watches(pool: &SqlitePool) -> ServiceResult<()> {
+    let repo = PaymentWatchRepository::new(pool);
+    let now = Utc::now();
+    let mut by_account: HashMap<String, Vec<PaymentWatch>> = HashMap::new();
+
+    for watch in repo.get_pending_watches().await? {
+        if watch.expires_at <= now {
+            if repo
+                .resolve_watch(&watch.id, WatchStatus::Expired, None)
+                .await?
+            {
+                notify_callback(pool, &watch, WatchStatus::Expired, None).await?;
+            }
+        } else {
+            by_account
+                .entry(watch.account_id.clone())
+                .or_default()
+                .push(watch);
+        }
+    }
+
+    for (account_id, watches) in by_account {
+        if let Err(e) = check_account(pool, &account_id, &watches).await {
+            tracing::warn!(
+                "Payment watch check of account {} failed: {}",
+                account_id,
+                e
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Searches the payments of the account's nodes for its watched hashes.
+async fn check_account(
+    pool: &SqlitePool,
+    account_id: &str,
+    watches: &[PaymentWatch],
+) -> ServiceResult<()> {
+    let watched: HashSet<&str> = watches
+        .iter()
+        .map(|watch| watch.payment_hash.as_str())
+        .collect();
+    let covered = |node_id: &str| {
+        watches.iter().any(|watch| {
+            watch
+                .node_ids
+                .as_ref()
+                .is_none_or(|node_ids| node_ids.iter().any(|id| id == node_id))
+        })
+    };
+
+    let credentials: Vec<Credential> = CredentialRepository::new(pool)
+        .get_node_credentials(Some(account_id), None)
+        .await?
+        .into_iter()
+        .filter(|credential| covered(&credential.node_id))
+        .collect();
+
+    let mut payments: HashMap<String, Vec<(&Credential, PaymentSummary)>> = HashMap::new();
+    for credential in &credentials {
+        match list_payments(credential).await {
+            Ok(node_payments) => {
+                for payment in node_payments {
+                    if watched.contains(payment.payment_hash.as_str()) {
+                        payments
+                            .entry(payment.payment_hash.clone())
+                            .or_default()
+                            .push((credential, payment));
+                    }
+                }
+            }
+            Err(e) => tracing::warn!(
+                "Listing payments of node {} for watches failed: {}",
+                credential.node_id,
+                e
+            ),
+        }
+    }
+
+    for watch in watches {
+        let Some(found) = payments.get(&watch.payment_hash) else {
+            continue;
+        };
+        let in_scope: Vec<(&str, &PaymentSummary)> = found
+            .iter()
+            .filter(|(credential, _)| {
+                watch
+                    .node_ids
+                    .as_ref()
+                    .is_none_or(|node_ids| node_ids.contains(&credential.node_id))
+            })
+            .map(|(credential, payment)| (credential.node_id.as_str(), payment))
+            .collect();
+        let Some(outcome) = watch_outcome(&in_scope) else {
+            continue;
+        };
+        let repo = PaymentWatchRepository::new(pool);
+        if !repo
+            .resolve_watch(&watch.id, outcome.status, Some(outcome.node_id))
+            .await?
+        {
+            continue;
+        }
+
+        let credential = credentials
+            .iter()
+            .find(|credential| credential.node_id == outcome.node_id)
+            .expect("payments are listed from the account's credentials");
+        raise_event(pool, credential, watch, &outcome).await?;
+        notify_callback(pool, watch, outcome.status, Some(&outcome)).await?;
+    }
+    Ok(())
+}
+
+async fn list_payments(credential: &Credential) -> Result<Vec<PaymentSummary>, LightningError> {
+    let client = timeout(NODE_CONNECT_TIMEOUT, connect_node(credential))
+        .await
+        .map_err(|_| LightningError::ConnectionError("Connection timed out".to_string()))??;
+    timeout(NODE_QUERY_TIMEOUT, client.list_payments())
+        .await
+        .map_err(|_| LightningError::PaymentError("Listing payments timed out".to_string()))?
+}
+
+async fn raise_event(
+    pool: &SqlitePool,
+    credential: &Credential,
+    watch: &PaymentWatch,
+    outcome: &WatchOutcome<'_>,
+) -> ServiceResult<()> {
+    let payment = outcome.payment;
+    let (event_type, severity, title, verb) = match outcome.status {
+        WatchStatus::Settled => (
+            EventType::WatchedPaymentSettled,
+            EventSeverity::Info,
+            "Watched Payment Settled",
+            "settled",
+        ),
+        _ => (
+            EventType::WatchedPaymentFailed,
+            EventSeverity::Warning,
+            "Watched Payment Failed",
+            "failed",
+        ),
+    };
+
+    EventService::new(pool)
+        .create_and_dispatch_event(CreateEvent {
+            id: Uuid::now_v7().to_string(),
+            account_id: credential.account_id.clone(),
+            user_id: credential.user_id.clone(),
+            node_id: credential.node_id.clone(),
+            node_alias: credential.node_alias.clone(),
+            event_type,
+            severity,
+            title: title.to_string(),
+            description: format!(
+                "Watched payment {} of {} {}",
+                watch.payment_hash, payment.amount, verb
+            ),
+            data: json!({
+                "watch_id": watch.id,
+                "payment_hash": watch.payment_hash,
+                "payment_type": payment.payment_type,
+                "amount_sat": payment.amount.sat(),
+                "amount_msat": payment.amount.msat(),
+                "routing_fee": payment.routing_fee,
+                "completed_at": payment.completed_at,
+            })
+            .to_string(),
+            notifications_id: None,
+            timestamp: Utc::now(),
+        })
+        .await?;
+    Ok(())
+}
+
+/// Queues the webhook of a watch's change for its callback URL, if it has one.
+async fn notify_callback(
+    pool: &SqlitePool,
+    watch: &PaymentWatch,
+    status: WatchStatus,
+    outcome: Option<&WatchOutcome<'_>>,
+) -> ServiceResult<()> {
+    let Some(url) = watch.callback_url.clone() else {
+        return Ok(());
+    };
+    // Watching a hash again moves its expiry, so a later outcome gets its own key
+    let idempotency_key = format!(
+        "{}:{}:{}:{}",
+        PAYMENT_WATCH_TOPIC,
+        watch.id,
+        watch.expires_at.timestamp(),
+        json!(status).as_str().unwrap_or_default()
+    );
+    let payload = watch_payload(watch, status, outcome, &idempotency_key);
+    WebhookDeliveryRepository::new(pool)
+        .enqueue(&pending_delivery(
+            &watch.account_id,
+            outcome.map_or("", |outcome| outcome.node_id),
+            PAYMENT_WATCH_TOPIC,
+            idempotency_key,
+            &watch.payment_hash,
+            url,
+            payload,
+        ))
+        .await?;
+    Ok(())
+}
+
+/// Checks the watched payment hashes every 30 seconds.
+pub fn spawn_payment_watcher(pool: SqlitePool) {
+    task_supervisor::supervise("payment_watcher", move |task| {
+        let pool = pool.clone();
+        async move {
+            // The first check waits for startup migrations
+            let mut interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + CHECK_INTERVAL,
+                CHECK_INTERVAL,
+            );
+            loop {
+                interval.tick().await;
+                let result = check_watches(&pool).await;
+                if let Err(e) = &result {
+                    tracing::error!("Payment watch check failed: {}", e);
+                }
+                task.record_run(&result);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::PaymentType;
+    use crate::utils::amount::Amount;
+
+    fn payment(state: PaymentState) -> PaymentSummary {
+        PaymentSummary {
+            state,
+            payment_type: PaymentType::Incoming,
+            amount: Amount::from_sat(21_000),
+            amount_usd: Some(0.0),
+            routing_fee: None,
+            creation_time: None,
+            invoice: None,
+            payment_hash: "ab".repeat(32),
+            completed_at: None,
+        }
+    }
+
+    #[test]
+    fn settlements_win_over_failures() {
+        let failed = payment(PaymentState::Failed);
+        let settled = payment(PaymentState::Settled);
+        let inflight = payment(PaymentState::Inflight);
+
+        let outcome = watch_outcome(&[("a", &failed), ("b", &settled)]).unwrap();
+        assert_eq!(outcome.status, WatchStatus::Settled);
+        assert_eq!(outcome.node_id, "b");
+
+        let outcome = watch_outcome(&[("a", &failed), ("b", &inflight)]).unwrap();
+        assert_eq!(outcome.status, WatchStatus::Failed);
+
+        assert!(watch_outcome(&[("b", &inflight)]).is_none());
+        assert!(watch_outcome(&[]).is_none());
+    }
+}
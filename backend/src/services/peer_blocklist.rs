@@ -0,0 +1,180 @@
+//! Blocklists of peers whose events only add noise, such as probing bots that
+//! keep connecting and disconnecting.
+//!
+//! An event is a peer's when its data names one as `remote_pubkey`,
+//! `peer_pubkey` or `counterparty_node_id`, as channel, channel request, HTLC
+//! expiry, policy, latency and splice events do. Before an event is stored,
+//! those of a blocked peer are dropped when its entry suppresses them, or
+//! stored once as Info without notifying any endpoint when it downgrades them.
+//! Critical events, such as breaches, and external events always go through.
+
+use crate::database::models::{
+    BlockPeerRequest, BlockedPeer, CreateEvent, EventSeverity, EventType, PeerBlockAction,
+};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::peer_blocklist_repository::PeerBlocklistRepository;
+use bitcoin::secp256k1::PublicKey;
+use serde_json::Value;
+use sqlx::SqlitePool;
+use std::str::FromStr;
+use validator::Validate;
+
+/// Keys of the event data naming the peer an event is about.
+const PEER_KEYS: [&str; 3] = ["remote_pubkey", "peer_pubkey", "counterparty_node_id"];
+
+/// The lowercase pubkey of the peer an event is about, if the blocklist
+/// applies to it.
+pub fn event_peer(event: &CreateEvent) -> Option<String> {
+    if event.severity == EventSeverity::Critical || event.event_type == EventType::External {
+        return None;
+    }
+    let data = serde_json::from_str::<Value>(&event.data).ok()?;
+    PEER_KEYS
+        .iter()
+        .find_map(|key| data.get(key)?.as_str())
+        .map(str::to_ascii_lowercase)
+}
+
+/// Service layer for peer blocklists.
+pub struct PeerBlocklistService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> PeerBlocklistService<'a> {
+    /// Creates a new PeerBlocklistService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Lists the account's blocked peers, by pubkey.
+    pub async fn get_blocked_peers(&self, account_id: &str) -> ServiceResult<Vec<BlockedPeer>> {
+        Ok(PeerBlocklistRepository::new(self.pool)
+            .get_blocked_peers(account_id)
+            .await?)
+    }
+
+    /// Blocks a peer, or changes what happens to the events of a blocked one.
+    pub async fn block_peer(
+        &self,
+        account_id: &str,
+        pubkey: &str,
+        request: BlockPeerRequest,
+    ) -> ServiceResult<BlockedPeer> {
+        request
+            .validate()
+            .map_err(|e| ServiceError::validation(e.to_string()))?;
+        let pubkey = PublicKey::from_str(pubkey)
+            .map_err(|_| ServiceError::validation("Pubkey must be a 33-byte hex node public key"))?
+            .to_string();
+        let note = request
+            .note
+            .as_deref()
+            .map(str::trim)
+            .filter(|note| !note.is_empty());
+
+        let repository = PeerBlocklistRepository::new(self.pool);
+        repository
+            .upsert_blocked_peer(account_id, &pubkey, request.action, note)
+            .await?;
+        repository
+            .get_blocked_peer(account_id, &pubkey)
+            .await?
+            .ok_or_else(|| ServiceError::not_found("Blocked peer", &pubkey))
+    }
+
+    /// Removes a peer from the blocklist.
+    pub async fn unblock_peer(&self, account_id: &str, pubkey: &str) -> ServiceResult<()> {
+        let pubkey = pubkey.to_ascii_lowercase();
+        if !PeerBlocklistRepository::new(self.pool)
+            .delete_blocked_peer(account_id, &pubkey)
+            .await?
+        {
+            return Err(ServiceError::not_found("Blocked peer", &pubkey));
+        }
+        Ok(())
+    }
+
+    /// What happens to an event because of the account's blocklist; `None`
+    /// lets it through as is.
+    ///
+    /// The blocklist is best effort; when it can't be read, the event goes
+    /// through.
+    pub async fn event_action(&self, event: &CreateEvent) -> Option<PeerBlockAction> {
+        let pubkey = event_peer(event)?;
+        match PeerBlocklistRepository::new(self.pool)
+            .get_blocked_peer(&event.account_id, &pubkey)
+            .await
+        {
+            Ok(peer) => peer.map(|peer| peer.action),
+            Err(e) => {
+                tracing::warn!("Failed to load peer blocklist for event: {}", e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+
+    const PEER: &str = "02eec7245d6b7d2ccb30380bfbe2a3648cd7a942653f5aa340edcea1f283686619";
+
+    fn event(event_type: EventType, severity: EventSeverity, data: Value) -> CreateEvent {
+        CreateEvent {
+            id: "event".to_string(),
+            account_id: "account".to_string(),
+            user_id: "user".to_string(),
+            node_id: "node".to_string(),
+            node_alias: "node".to_string(),
+            event_type,
+            severity,
+            title: "title".to_string(),
+            description: "description".to_string(),
+            data: data.to_string(),
+            notifications_id: None,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn finds_the_peer_of_non_critical_events() {
+        let opened = event(
+            EventType::ChannelOpened,
+            EventSeverity::Info,
+            json!({ "remote_pubkey": PEER.to_uppercase() }),
+        );
+        assert_eq!(event_peer(&opened).as_deref(), Some(PEER));
+
+        let latency = event(
+            EventType::PeerHighLatency,
+            EventSeverity::Warning,
+            json!({ "peer_pubkey": PEER }),
+        );
+        assert_eq!(event_peer(&latency).as_deref(), Some(PEER));
+
+        let breach = event(
+            EventType::ChannelBreachDetected,
+            EventSeverity::Critical,
+            json!({ "remote_pubkey": PEER }),
+        );
+        assert_eq!(event_peer(&breach), None);
+
+        let external = event(
+            EventType::External,
+            EventSeverity::Info,
+            json!({ "remote_pubkey": PEER }),
+        );
+        assert_eq!(event_peer(&external), None);
+
+        let invoice = event(
+            EventType::InvoiceSettled,
+            EventSeverity::Info,
+            json!({ "amount": 1000 }),
+        );
+        assert_eq!(event_peer(&invoice), None);
+    }
+}
@@ -0,0 +1,432 @@
+//! Ping round trips to nodes' peers, as a measure of connection quality.
+//!
+//! Every minute the connected peers of each node are listed and the round trip
+//! to each is stored. LND keeps the time of its own last ping with each peer
+//! and reports it with the peer list; CLN nodes are asked to ping each peer and
+//! the call is timed. Samples are kept for 30 days. Accounts with an
+//! `alert_thresholds.max_peer_rtt_ms` threshold get a `PeerHighLatency` warning
+//! once every sample to a peer has stayed above it for
+//! `alert_thresholds.peer_rtt_minutes`, repeated only after the pings recover.
+
+use crate::database::models::{CreateEvent, Credential, EventSeverity, EventType, PeerPing};
+use crate::errors::{LightningError, ServiceError, ServiceResult};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::repositories::peer_ping_repository::PeerPingRepository;
+use crate::services::event_service::EventService;
+use crate::services::node_manager::connect_node;
+use crate::services::settings_service::SettingsService;
+use crate::services::task_supervisor;
+use crate::utils::PeerSummary;
+use chrono::{DateTime, Duration, Utc};
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use uuid::Uuid;
+
+const SAMPLE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+/// Longest a CLN ping may take before the peer is skipped for this round.
+const PING_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// Pings sent to one node's peers at a time.
+const PING_CONCURRENCY: usize = 8;
+const RETENTION_DAYS: i64 = 30;
+/// How long pings must stay slow when the account doesn't say.
+pub const DEFAULT_PEER_RTT_MINUTES: u32 = 10;
+
+/// Round trips to a peer over a window.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PeerRttStats {
+    pub samples: usize,
+    pub latest_ms: Option<f64>,
+    pub avg_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    pub latest_at: Option<DateTime<Utc>>,
+}
+
+/// Identifies a peer already reported as slow: account, node and peer.
+type AlertKey = (String, String, String);
+
+/// Summarises one peer's samples, oldest first.
+pub fn rtt_stats(pings: &[&PeerPing]) -> PeerRttStats {
+    let Some(latest) = pings.last() else {
+        return PeerRttStats::default();
+    };
+    let total_us: i64 = pings.iter().map(|ping| ping.rtt_us).sum();
+    let max_us = pings.iter().map(|ping| ping.rtt_us).max().unwrap_or(0);
+
+    PeerRttStats {
+        samples: pings.len(),
+        latest_ms: Some(latest.rtt_us as f64 / 1000.0),
+        avg_ms: Some(total_us as f64 / pings.len() as f64 / 1000.0),
+        max_ms: Some(max_us as f64 / 1000.0),
+        latest_at: Some(latest.recorded_at),
+    }
+}
+
+/// Whether every sample of one peer in the `window` before `now` is above
+/// `threshold_ms`, with samples reaching back to the start of the window and
+/// up to `now`, so a gap in sampling doesn't count as slow.
+pub fn sustained_above(
+    pings: &[&PeerPing],
+    threshold_ms: u32,
+    window: Duration,
+    now: DateTime<Utc>,
+) -> bool {
+    let start = now - window;
+    let slack = Duration::from_std(SAMPLE_INTERVAL * 2).unwrap_or_else(|_| Duration::minutes(2));
+    let in_window: Vec<&&PeerPing> = pings
+        .iter()
+        .filter(|ping| ping.recorded_at >= start && ping.recorded_at <= now)
+        .collect();
+    let (Some(first), Some(last)) = (in_window.first(), in_window.last()) else {
+        return false;
+    };
+
+    first.recorded_at <= start + slack
+        && last.recorded_at >= now - slack
+        && in_window
+            .iter()
+            .all(|ping| ping.rtt_us > i64::from(threshold_ms) * 1000)
+}
+
+/// Groups samples by peer, keeping each peer's oldest first.
+fn by_peer(pings: &[PeerPing]) -> BTreeMap<&str, Vec<&PeerPing>> {
+    let mut grouped: BTreeMap<&str, Vec<&PeerPing>> = BTreeMap::new();
+    for ping in pings {
+        grouped.entry(&ping.peer_pubkey).or_default().push(ping);
+    }
+    grouped
+}
+
+/// Service reading the ping samples of an account's nodes.
+pub struct PeerPingService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> PeerPingService<'a> {
+    /// Creates a new PeerPingService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Summarises the round trips to each of a node's peers since `since`.
+    pub async fn get_rtt_stats(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        since: DateTime<Utc>,
+    ) -> ServiceResult<HashMap<String, PeerRttStats>> {
+        let pings = PeerPingRepository::new(self.pool)
+            .get_pings(account_id, node_id, None, since)
+            .await?;
+
+        Ok(by_peer(&pings)
+            .into_iter()
+            .map(|(peer, pings)| (peer.to_string(), rtt_stats(&pings)))
+            .collect())
+    }
+
+    /// Retrieves the round trips to one of a node's peers since `since`, oldest first.
+    pub async fn get_pings(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        peer_pubkey: &str,
+        since: DateTime<Utc>,
+    ) -> ServiceResult<Vec<PeerPing>> {
+        Ok(PeerPingRepository::new(self.pool)
+            .get_pings(account_id, node_id, Some(peer_pubkey), since)
+            .await?)
+    }
+}
+
+/// Samples the peers of every node and checks the accounts' latency thresholds.
+///
+/// `alerted` holds the peers already reported and is pruned to those still slow.
+pub async fn sample_all_nodes(
+    pool: &SqlitePool,
+    alerted: &mut HashSet<AlertKey>,
+) -> ServiceResult<()> {
+    let credentials = CredentialRepository::new(pool)
+        .get_node_credentials(None, None)
+        .await?;
+    let settings = SettingsService::new(pool);
+    let mut thresholds: HashMap<String, (Option<u32>, Option<u32>)> = HashMap::new();
+    let mut still_slow = HashSet::new();
+
+    for credential in &credentials {
+        if !thresholds.contains_key(&credential.account_id) {
+            let alert_thresholds = settings
+                .get_settings(&credential.account_id)
+                .await?
+                .alert_thresholds;
+            thresholds.insert(
+                credential.account_id.clone(),
+                (
+                    alert_thresholds.max_peer_rtt_ms,
+                    alert_thresholds.peer_rtt_minutes,
+                ),
+            );
+        }
+
+        if let Err(e) = sample_node(pool, credential).await {
+            tracing::warn!(
+                "Peer ping sampling of node {} failed: {}",
+                credential.node_id,
+                e
+            );
+        }
+
+        let (Some(threshold_ms), minutes) = thresholds[&credential.account_id] else {
+            continue;
+        };
+        let minutes = minutes.unwrap_or(DEFAULT_PEER_RTT_MINUTES);
+        match check_node(pool, credential, threshold_ms, minutes, alerted).await {
+            Ok(keys) => still_slow.extend(keys),
+            Err(e) => {
+                tracing::warn!(
+                    "Peer latency check of node {} failed: {}",
+                    credential.node_id,
+                    e
+                );
+                still_slow.extend(
+                    alerted
+                        .iter()
+                        .filter(|key| key.0 == credential.account_id && key.1 == credential.node_id)
+                        .cloned(),
+                );
+            }
+        }
+    }
+
+    alerted.retain(|key| still_slow.contains(key));
+
+    let removed = PeerPingRepository::new(pool)
+        .delete_before(Utc::now() - Duration::days(RETENTION_DAYS))
+        .await?;
+    if removed > 0 {
+        tracing::debug!("Removed {} peer ping samples", removed);
+    }
+    Ok(())
+}
+
+/// Stores the round trip to each of a node's connected peers.
+async fn sample_node(pool: &SqlitePool, credential: &Credential) -> ServiceResult<()> {
+    let external = |e: LightningError| ServiceError::ExternalService {
+        message: e.to_string(),
+    };
+    let client = connect_node(credential).await.map_err(external)?;
+    let peers: Vec<PeerSummary> = client
+        .list_peers()
+        .await
+        .map_err(external)?
+        .into_iter()
+        .filter(|peer| peer.connected)
+        .collect();
+
+    let client = client.as_ref();
+    let pings: Vec<PeerPing> = stream::iter(peers)
+        .map(|peer| async move {
+            let rtt_us = match peer.ping_time_us {
+                Some(ping_time_us) => ping_time_us,
+                None => {
+                    match tokio::time::timeout(PING_TIMEOUT, client.ping_peer(&peer.pubkey)).await {
+                        Ok(Ok(rtt)) => rtt.as_micros() as u64,
+                        Ok(Err(e)) => {
+                            tracing::debug!("Ping to peer {} failed: {}", peer.pubkey, e);
+                            return None;
+                        }
+                        Err(_) => {
+                            tracing::debug!("Ping to peer {} timed out", peer.pubkey);
+                            return None;
+                        }
+                    }
+                }
+            };
+            Some(PeerPing {
+                node_id: credential.node_id.clone(),
+                peer_pubkey: peer.pubkey.to_string(),
+                rtt_us: rtt_us as i64,
+                recorded_at: Utc::now(),
+            })
+        })
+        .buffer_unordered(PING_CONCURRENCY)
+        .filter_map(|ping| async move { ping })
+        .collect()
+        .await;
+
+    PeerPingRepository::new(pool)
+        .insert_pings(&credential.account_id, &pings)
+        .await?;
+    Ok(())
+}
+
+/// Warns about the peers of a node whose pings have been slow for `minutes`.
+///
+/// Returns the keys of the node's slow peers.
+async fn check_node(
+    pool: &SqlitePool,
+    credential: &Credential,
+    threshold_ms: u32,
+    minutes: u32,
+    alerted: &mut HashSet<AlertKey>,
+) -> ServiceResult<Vec<AlertKey>> {
+    let now = Utc::now();
+    let window = Duration::minutes(minutes.into());
+    let pings = PeerPingRepository::new(pool)
+        .get_pings(
+            &credential.account_id,
+            &credential.node_id,
+            None,
+            now - window,
+        )
+        .await?;
+
+    let mut keys = Vec::new();
+    for (peer, pings) in by_peer(&pings) {
+        if !sustained_above(&pings, threshold_ms, window, now) {
+            continue;
+        }
+        let key = (
+            credential.account_id.clone(),
+            credential.node_id.clone(),
+            peer.to_string(),
+        );
+        keys.push(key.clone());
+        if alerted.contains(&key) {
+            continue;
+        }
+
+        let stats = rtt_stats(&pings);
+        EventService::new(pool)
+            .create_and_dispatch_event(CreateEvent {
+                id: Uuid::now_v7().to_string(),
+                account_id: credential.account_id.clone(),
+                user_id: credential.user_id.clone(),
+                node_id: credential.node_id.clone(),
+                node_alias: credential.node_alias.clone(),
+                event_type: EventType::PeerHighLatency,
+                severity: EventSeverity::Warning,
+                title: "Peer High Latency".to_string(),
+                description: format!(
+                    "Pings from {} to peer {} have taken over {} ms for {} minutes, {:.0} ms on average",
+                    credential.node_alias,
+                    peer,
+                    threshold_ms,
+                    minutes,
+                    stats.avg_ms.unwrap_or_default()
+                ),
+                data: json!({
+                    "peer_pubkey": peer,
+                    "threshold_ms": threshold_ms,
+                    "minutes": minutes,
+                    "latest_ms": stats.latest_ms,
+                    "avg_ms": stats.avg_ms,
+                    "max_ms": stats.max_ms,
+                    "samples": stats.samples,
+                })
+                .to_string(),
+                notifications_id: None,
+                timestamp: now,
+            })
+            .await?;
+        alerted.insert(key);
+    }
+
+    Ok(keys)
+}
+
+/// Samples peer round trips every minute.
+pub fn spawn_peer_ping_sampler(pool: SqlitePool) {
+    task_supervisor::supervise("peer_pings", move |task| {
+        let pool = pool.clone();
+        async move {
+            let mut alerted = HashSet::new();
+            // The first run waits for startup migrations
+            let mut interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + SAMPLE_INTERVAL,
+                SAMPLE_INTERVAL,
+            );
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let result = sample_all_nodes(&pool, &mut alerted).await;
+                if let Err(e) = &result {
+                    tracing::error!("Peer ping sampling failed: {}", e);
+                }
+                task.record_run(&result);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ping(rtt_ms: i64, recorded_at: DateTime<Utc>) -> PeerPing {
+        PeerPing {
+            node_id: "node".to_string(),
+            peer_pubkey: "peer".to_string(),
+            rtt_us: rtt_ms * 1000,
+            recorded_at,
+        }
+    }
+
+    fn every_minute(rtts_ms: &[i64], now: DateTime<Utc>) -> Vec<PeerPing> {
+        rtts_ms
+            .iter()
+            .enumerate()
+            .map(|(i, rtt)| {
+                ping(
+                    *rtt,
+                    now - Duration::minutes((rtts_ms.len() - 1 - i) as i64),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn summarises_round_trips() {
+        let now = Utc::now();
+        let pings = every_minute(&[100, 300, 200], now);
+        let refs: Vec<&PeerPing> = pings.iter().collect();
+
+        let stats = rtt_stats(&refs);
+        assert_eq!(stats.samples, 3);
+        assert_eq!(stats.latest_ms, Some(200.0));
+        assert_eq!(stats.avg_ms, Some(200.0));
+        assert_eq!(stats.max_ms, Some(300.0));
+        assert_eq!(stats.latest_at, Some(now));
+        assert_eq!(rtt_stats(&[]), PeerRttStats::default());
+    }
+
+    #[test]
+    fn alerts_only_when_slow_for_the_whole_window() {
+        let now = Utc::now();
+        let window = Duration::minutes(10);
+
+        let slow = every_minute(&[2500; 11], now);
+        let refs: Vec<&PeerPing> = slow.iter().collect();
+        assert!(sustained_above(&refs, 2000, window, now));
+
+        // One quick pong in the window resets it
+        let mut mixed = [2500; 11];
+        mixed[5] = 150;
+        let mixed = every_minute(&mixed, now);
+        let refs: Vec<&PeerPing> = mixed.iter().collect();
+        assert!(!sustained_above(&refs, 2000, window, now));
+
+        // Slow for only the last three minutes
+        let recent = every_minute(&[2500; 3], now);
+        let refs: Vec<&PeerPing> = recent.iter().collect();
+        assert!(!sustained_above(&refs, 2000, window, now));
+
+        // No samples lately, e.g. the peer disconnected
+        let stale: Vec<PeerPing> = every_minute(&[2500; 11], now - Duration::minutes(5));
+        let refs: Vec<&PeerPing> = stale.iter().collect();
+        assert!(!sustained_above(&refs, 2000, window, now));
+    }
+}
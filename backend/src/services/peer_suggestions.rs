@@ -0,0 +1,300 @@
+//! Ranks nodes of the public graph as peers for a new channel.
+//!
+//! Each candidate gets four scores between 0 and 1, combined with `WEIGHTS`:
+//!
+//! - connectivity: its channel count relative to the best connected candidate,
+//!   on a log scale so a few huge routing nodes don't flatten everyone else
+//! - fees: how cheap its median outgoing fee rate is, reaching 0 at `MAX_FEE_PPM`
+//! - uptime: estimated from gossip, as nodes that go offline stop refreshing
+//!   their channel updates and have their channels disabled
+//! - diversity: the share of its neighbours we are not connected to yet, so
+//!   peers that reach new parts of the graph rank higher
+//!
+//! Nodes we already have channels with, nodes with fewer than `MIN_CHANNELS`
+//! public channels and nodes whose total capacity is below the channel being
+//! opened are not suggested.
+
+use crate::utils::GraphEdge;
+use bitcoin::secp256k1::PublicKey;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Fewest public channels a candidate needs.
+const MIN_CHANNELS: usize = 5;
+
+/// Median fee rate at which the fee score reaches zero.
+const MAX_FEE_PPM: f64 = 2000.0;
+
+/// Gossip age up to which a side counts as fully fresh.
+const FRESH_UPDATE_SECS: u64 = 24 * 3600;
+
+/// Gossip age at which a side counts as offline. Nodes refresh their channel
+/// updates at least every two weeks.
+const STALE_UPDATE_SECS: u64 = 14 * 24 * 3600;
+
+/// Weights of the connectivity, fees, uptime and diversity scores.
+const WEIGHTS: [f64; 4] = [0.35, 0.2, 0.25, 0.2];
+
+/// The individual scores of a candidate, each between 0 and 1.
+#[derive(Debug, Clone, Serialize)]
+pub struct PeerScores {
+    pub connectivity: f64,
+    pub fees: f64,
+    pub uptime: f64,
+    pub diversity: f64,
+}
+
+/// A suggested peer.
+#[derive(Debug, Clone, Serialize)]
+pub struct PeerSuggestion {
+    pub pubkey: PublicKey,
+    /// Weighted total of `scores`, between 0 and 1.
+    pub score: f64,
+    pub scores: PeerScores,
+    pub channels: usize,
+    pub capacity_sat: u64,
+    pub median_fee_rate_ppm: Option<u64>,
+    /// Latest channel update the node sent.
+    pub last_update: Option<DateTime<Utc>>,
+    /// Neighbours it shares with us.
+    pub shared_peers: usize,
+}
+
+/// What the graph tells about one node.
+#[derive(Default)]
+struct Candidate {
+    neighbours: HashSet<PublicKey>,
+    capacity_sat: u64,
+    fee_rates: Vec<u64>,
+    /// Freshness of each of its channel sides, between 0 and 1.
+    freshness: Vec<f64>,
+    last_update: Option<u64>,
+}
+
+/// How fresh a side is given the age of its latest update; disabled sides and
+/// sides that never sent an update count as offline.
+fn freshness(last_update: Option<u64>, enabled: bool, now: u64) -> f64 {
+    let Some(last_update) = last_update.filter(|_| enabled) else {
+        return 0.0;
+    };
+    let age = now.saturating_sub(last_update);
+    if age <= FRESH_UPDATE_SECS {
+        1.0
+    } else if age >= STALE_UPDATE_SECS {
+        0.0
+    } else {
+        (STALE_UPDATE_SECS - age) as f64 / (STALE_UPDATE_SECS - FRESH_UPDATE_SECS) as f64
+    }
+}
+
+/// Ranks candidate peers for a channel of `capacity_sat`, best first.
+pub fn suggest_peers(
+    edges: &[GraphEdge],
+    my_pubkey: &PublicKey,
+    my_peers: &HashSet<PublicKey>,
+    capacity_sat: u64,
+    limit: usize,
+    now: DateTime<Utc>,
+) -> Vec<PeerSuggestion> {
+    let now_secs = now.timestamp().max(0) as u64;
+    let mut candidates: HashMap<PublicKey, Candidate> = HashMap::new();
+
+    for edge in edges {
+        let sides = [
+            (
+                edge.node1,
+                edge.node2,
+                edge.node1_fee,
+                edge.node1_last_update,
+            ),
+            (
+                edge.node2,
+                edge.node1,
+                edge.node2_fee,
+                edge.node2_last_update,
+            ),
+        ];
+        for (node, neighbour, fee, last_update) in sides {
+            let candidate = candidates.entry(node).or_default();
+            candidate.neighbours.insert(neighbour);
+            candidate.capacity_sat += edge.capacity_sat;
+            if let Some(fee) = fee {
+                candidate.fee_rates.push(fee.fee_rate_ppm);
+            }
+            candidate
+                .freshness
+                .push(freshness(last_update, fee.is_some(), now_secs));
+            candidate.last_update = candidate.last_update.max(last_update);
+        }
+    }
+
+    let eligible: Vec<(PublicKey, Candidate)> = candidates
+        .into_iter()
+        .filter(|(pubkey, candidate)| {
+            pubkey != my_pubkey
+                && !my_peers.contains(pubkey)
+                && candidate.neighbours.len() >= MIN_CHANNELS
+                && candidate.capacity_sat >= capacity_sat
+        })
+        .collect();
+    let most_channels = eligible
+        .iter()
+        .map(|(_, candidate)| candidate.neighbours.len())
+        .max()
+        .unwrap_or(1);
+
+    let mut suggestions: Vec<PeerSuggestion> = eligible
+        .into_iter()
+        .map(|(pubkey, mut candidate)| {
+            let channels = candidate.neighbours.len();
+            candidate.fee_rates.sort_unstable();
+            let median_fee_rate_ppm = candidate
+                .fee_rates
+                .get(candidate.fee_rates.len() / 2)
+                .copied();
+            let shared_peers = candidate
+                .neighbours
+                .iter()
+                .filter(|neighbour| my_peers.contains(neighbour))
+                .count();
+
+            let scores = PeerScores {
+                connectivity: (channels as f64 + 1.0).ln() / (most_channels as f64 + 1.0).ln(),
+                fees: median_fee_rate_ppm
+                    .map_or(0.0, |ppm| 1.0 - (ppm as f64 / MAX_FEE_PPM).min(1.0)),
+                uptime: candidate.freshness.iter().sum::<f64>()
+                    / candidate.freshness.len().max(1) as f64,
+                diversity: 1.0 - shared_peers as f64 / channels as f64,
+            };
+            let score = [
+                scores.connectivity,
+                scores.fees,
+                scores.uptime,
+                scores.diversity,
+            ]
+            .iter()
+            .zip(WEIGHTS)
+            .map(|(score, weight)| score * weight)
+            .sum::<f64>();
+
+            PeerSuggestion {
+                pubkey,
+                score,
+                scores,
+                channels,
+                capacity_sat: candidate.capacity_sat,
+                median_fee_rate_ppm,
+                last_update: candidate
+                    .last_update
+                    .and_then(|secs| DateTime::from_timestamp(secs as i64, 0)),
+                shared_peers,
+            }
+        })
+        .collect();
+
+    // Ties are broken by pubkey so the order is stable between requests
+    suggestions.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.pubkey.cmp(&b.pubkey))
+    });
+    suggestions.truncate(limit);
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{EdgeFee, ShortChannelID};
+    use bitcoin::secp256k1::{Secp256k1, SecretKey};
+
+    const NOW: i64 = 1_750_000_000;
+
+    fn pubkey(n: u8) -> PublicKey {
+        PublicKey::from_secret_key(&Secp256k1::new(), &SecretKey::from_slice(&[n; 32]).unwrap())
+    }
+
+    fn edge(id: u64, a: u8, b: u8, fee_rate_ppm: u64, age_secs: i64) -> GraphEdge {
+        let fee = Some(EdgeFee {
+            base_fee_msat: 1000,
+            fee_rate_ppm,
+        });
+        let last_update = Some((NOW - age_secs) as u64);
+        GraphEdge {
+            channel_id: ShortChannelID(id),
+            node1: pubkey(a),
+            node2: pubkey(b),
+            capacity_sat: 2_000_000,
+            node1_fee: fee,
+            node2_fee: fee,
+            node1_last_update: last_update,
+            node2_last_update: last_update,
+        }
+    }
+
+    /// Hub `hub` with channels to each of `spokes`.
+    fn star(hub: u8, spokes: &[u8], fee_rate_ppm: u64, age_secs: i64) -> Vec<GraphEdge> {
+        spokes
+            .iter()
+            .map(|&spoke| {
+                edge(
+                    u64::from(hub) << 8 | u64::from(spoke),
+                    hub,
+                    spoke,
+                    fee_rate_ppm,
+                    age_secs,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn prefers_cheap_fresh_and_well_connected_peers() {
+        let mut edges = Vec::new();
+        // A cheap, active node and an expensive one whose gossip is ten days old
+        edges.extend(star(10, &[20, 21, 22, 23, 24, 25], 100, 3600));
+        edges.extend(star(11, &[30, 31, 32, 33, 34, 35], 1500, 10 * 24 * 3600));
+        // A node too small for the channel
+        edges.extend(star(12, &[40, 41], 100, 3600));
+
+        let now = DateTime::from_timestamp(NOW, 0).unwrap();
+        let suggestions = suggest_peers(&edges, &pubkey(1), &HashSet::new(), 1_000_000, 10, now);
+
+        let ranked: Vec<PublicKey> = suggestions.iter().map(|s| s.pubkey).collect();
+        assert_eq!(ranked, vec![pubkey(10), pubkey(11)]);
+        assert_eq!(suggestions[0].scores.uptime, 1.0);
+        assert_eq!(suggestions[0].median_fee_rate_ppm, Some(100));
+        assert!(suggestions[1].scores.uptime < 0.5);
+    }
+
+    #[test]
+    fn skips_existing_peers_and_penalises_overlap() {
+        let mut edges = star(10, &[20, 21, 22, 23, 24], 100, 3600);
+        edges.extend(star(11, &[30, 31, 32, 33, 34], 100, 3600));
+        edges.extend(star(12, &[20, 21, 22, 23, 24], 100, 3600));
+        edges.push(edge(1, 1, 20, 100, 3600));
+        edges.push(edge(2, 1, 21, 100, 3600));
+        edges.push(edge(3, 1, 12, 100, 3600));
+        let my_peers = HashSet::from([pubkey(20), pubkey(21), pubkey(12)]);
+
+        let now = DateTime::from_timestamp(NOW, 0).unwrap();
+        let suggestions = suggest_peers(&edges, &pubkey(1), &my_peers, 1_000_000, 10, now);
+
+        let ranked: Vec<PublicKey> = suggestions.iter().map(|s| s.pubkey).collect();
+        assert_eq!(ranked, vec![pubkey(11), pubkey(10)]);
+        assert_eq!(suggestions[1].shared_peers, 2);
+        assert_eq!(
+            suggest_peers(&edges, &pubkey(1), &my_peers, 1_000_000, 1, now).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn disabled_and_stale_sides_count_as_offline() {
+        assert_eq!(freshness(Some(100), true, 200), 1.0);
+        assert_eq!(freshness(Some(100), false, 200), 0.0);
+        assert_eq!(freshness(None, true, 200), 0.0);
+        assert_eq!(freshness(Some(0), true, STALE_UPDATE_SECS), 0.0);
+    }
+}
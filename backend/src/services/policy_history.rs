@@ -0,0 +1,251 @@
+//! Timeline of a channel's fee policies next to the traffic each one saw.
+//!
+//! The policy watcher appends every policy it sees on either side of a channel
+//! to the channel's history. The timeline splits that history into periods,
+//! one per policy, and sums the forwards that fell into each: those leaving
+//! through the channel for our policies, which set what they earned, and those
+//! arriving through it for the peer's, which set what senders paid to reach
+//! us. Daily rates make periods of different lengths comparable, so one can see
+//! whether a fee change helped or hurt traffic. The timeline only reaches back
+//! to the first check that saw the channel.
+
+use crate::database::models::{ChannelPolicySnapshot, PolicySide};
+use crate::errors::ServiceResult;
+use crate::repositories::channel_policy_repository::ChannelPolicyRepository;
+use crate::utils::{ForwardSummary, ShortChannelID};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+/// One policy and the forwards made while it was in effect.
+#[derive(Debug, Clone, Serialize)]
+pub struct PolicyPeriod {
+    pub fee_base_msat: i64,
+    pub fee_rate_ppm: i64,
+    pub min_htlc_msat: i64,
+    pub max_htlc_msat: Option<i64>,
+    pub time_lock_delta: i64,
+    pub disabled: bool,
+    /// Start of the period, clipped to the start of the timeline
+    pub from: DateTime<Utc>,
+    /// End of the period, absent for the policy in effect now
+    pub until: Option<DateTime<Utc>>,
+    /// Forwards through the channel in the direction the policy prices
+    pub forwards: u64,
+    pub volume_msat: u64,
+    pub fee_msat: u64,
+    pub volume_msat_per_day: u64,
+    pub forwards_per_day: f64,
+}
+
+/// Response of `GET /api/channels/{id}/policy-history`.
+#[derive(Debug, Serialize)]
+pub struct PolicyHistory {
+    pub channel_id: ShortChannelID,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Our policies, aligned with forwards leaving through the channel
+    pub local: Vec<PolicyPeriod>,
+    /// The peer's policies, aligned with forwards arriving through the channel
+    pub remote: Vec<PolicyPeriod>,
+}
+
+/// Splits one side's history into periods between `start` and `end`, summing
+/// the channel's forwards in the direction that side prices.
+pub fn policy_periods(
+    history: &[ChannelPolicySnapshot],
+    side: PolicySide,
+    channel_id: ShortChannelID,
+    forwards: &[ForwardSummary],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Vec<PolicyPeriod> {
+    let policies: Vec<&ChannelPolicySnapshot> = history
+        .iter()
+        .filter(|snapshot| snapshot.side == side)
+        .collect();
+    let channel = |id: Option<ShortChannelID>| id.is_some_and(|id| id.0 == channel_id.0);
+
+    let mut periods = Vec::new();
+    for (i, policy) in policies.iter().enumerate() {
+        let until = policies.get(i + 1).map(|next| next.observed_at);
+        // Policies replaced before the timeline starts are left out
+        if until.is_some_and(|until| until <= start) || policy.observed_at >= end {
+            continue;
+        }
+        let from = policy.observed_at.max(start);
+        let period_end = until.unwrap_or(end).min(end);
+
+        let mut period = PolicyPeriod {
+            fee_base_msat: policy.fee_base_msat,
+            fee_rate_ppm: policy.fee_rate_ppm,
+            min_htlc_msat: policy.min_htlc_msat,
+            max_htlc_msat: policy.max_htlc_msat,
+            time_lock_delta: policy.time_lock_delta,
+            disabled: policy.disabled,
+            from,
+            until,
+            forwards: 0,
+            volume_msat: 0,
+            fee_msat: 0,
+            volume_msat_per_day: 0,
+            forwards_per_day: 0.0,
+        };
+        for forward in forwards {
+            let resolved_at = forward.resolved_at as i64;
+            if resolved_at < from.timestamp() || resolved_at >= period_end.timestamp() {
+                continue;
+            }
+            let volume_msat = match side {
+                PolicySide::Local if channel(forward.chan_id_out) => forward.amount_out_msat,
+                PolicySide::Remote if channel(forward.chan_id_in) => forward.amount_in_msat,
+                _ => continue,
+            };
+            period.forwards += 1;
+            period.volume_msat += volume_msat;
+            period.fee_msat += forward.fee_msat;
+        }
+
+        let days = (period_end - from).num_seconds() as f64 / 86_400.0;
+        if days > 0.0 {
+            period.volume_msat_per_day = (period.volume_msat as f64 / days) as u64;
+            period.forwards_per_day = period.forwards as f64 / days;
+        }
+        periods.push(period);
+    }
+    periods
+}
+
+/// Service building policy timelines.
+pub struct PolicyHistoryService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> PolicyHistoryService<'a> {
+    /// Creates a new PolicyHistoryService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Builds the timeline of a channel of one of an account's nodes from the
+    /// node's forwards since `start`.
+    pub async fn get_history(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        channel_id: ShortChannelID,
+        forwards: &[ForwardSummary],
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> ServiceResult<PolicyHistory> {
+        let history = ChannelPolicyRepository::new(self.pool)
+            .get_history(account_id, node_id, &channel_id.0.to_string())
+            .await?;
+
+        Ok(PolicyHistory {
+            channel_id,
+            from: start,
+            to: end,
+            local: policy_periods(
+                &history,
+                PolicySide::Local,
+                channel_id,
+                forwards,
+                start,
+                end,
+            ),
+            remote: policy_periods(
+                &history,
+                PolicySide::Remote,
+                channel_id,
+                forwards,
+                start,
+                end,
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn policy(
+        side: PolicySide,
+        fee_rate_ppm: i64,
+        observed_at: DateTime<Utc>,
+    ) -> ChannelPolicySnapshot {
+        ChannelPolicySnapshot {
+            node_id: "node".to_string(),
+            channel_id: "7".to_string(),
+            side,
+            fee_base_msat: 0,
+            fee_rate_ppm,
+            min_htlc_msat: 1000,
+            max_htlc_msat: None,
+            time_lock_delta: 40,
+            disabled: false,
+            last_update: None,
+            observed_at,
+        }
+    }
+
+    fn forward(chan_in: u64, chan_out: u64, resolved_at: DateTime<Utc>) -> ForwardSummary {
+        ForwardSummary {
+            chan_id_in: Some(ShortChannelID(chan_in)),
+            chan_id_out: Some(ShortChannelID(chan_out)),
+            amount_in_msat: 1_001_000,
+            amount_out_msat: 1_000_000,
+            fee_msat: 1000,
+            resolved_at: resolved_at.timestamp() as u64,
+        }
+    }
+
+    #[test]
+    fn forwards_fall_into_the_policy_in_effect() {
+        let start = Utc::now() - Duration::days(10);
+        let end = start + Duration::days(10);
+        let history = vec![
+            policy(PolicySide::Local, 100, start - Duration::days(5)),
+            policy(PolicySide::Remote, 50, start - Duration::days(5)),
+            policy(PolicySide::Local, 500, start + Duration::days(6)),
+        ];
+        let forwards = vec![
+            forward(1, 7, start + Duration::days(1)),
+            forward(1, 7, start + Duration::days(2)),
+            forward(1, 7, start + Duration::days(7)),
+            forward(7, 2, start + Duration::days(3)),
+            forward(1, 2, start + Duration::days(3)),
+        ];
+
+        let local = policy_periods(
+            &history,
+            PolicySide::Local,
+            ShortChannelID(7),
+            &forwards,
+            start,
+            end,
+        );
+        assert_eq!(local.len(), 2);
+        assert_eq!((local[0].fee_rate_ppm, local[0].forwards), (100, 2));
+        assert_eq!(local[0].from, start);
+        assert_eq!(local[0].until, Some(start + Duration::days(6)));
+        assert_eq!(local[0].volume_msat_per_day, 2_000_000 / 6);
+        assert_eq!((local[1].fee_rate_ppm, local[1].forwards), (500, 1));
+        assert_eq!(local[1].until, None);
+
+        let remote = policy_periods(
+            &history,
+            PolicySide::Remote,
+            ShortChannelID(7),
+            &forwards,
+            start,
+            end,
+        );
+        assert_eq!(remote.len(), 1);
+        assert_eq!(remote[0].forwards, 1);
+        assert_eq!(remote[0].volume_msat, 1_001_000);
+    }
+}
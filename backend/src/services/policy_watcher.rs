@@ -0,0 +1,349 @@
+//! Events for peers changing the routing policy of their side of a channel.
+//!
+//! A peer raising its fees or disabling its side of a channel takes routing
+//! revenue from us without any notice on the node's event streams. Every ten
+//! minutes both policies of each channel are read from the node's gossip and
+//! compared with the snapshot stored at the previous check. A change to the
+//! peer's fees or disabled flag raises a `PolicyChanged` event carrying the old
+//! and new policies; raised fees and disabled channels are warnings. The first
+//! check of a channel only stores its snapshot.
+
+use crate::database::models::{
+    ChannelPolicySnapshot, CreateEvent, Credential, EventSeverity, EventType, PolicySide,
+};
+use crate::errors::{LightningError, ServiceError, ServiceResult};
+use crate::repositories::channel_policy_repository::ChannelPolicyRepository;
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::services::event_service::EventService;
+use crate::services::node_manager::connect_node;
+use crate::services::task_supervisor;
+use crate::utils::{ChannelDetails, ChannelState, NodePolicy, ShortChannelID};
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(600);
+
+/// A change to a peer's policy worth an event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicyChange {
+    /// Names of the fields that changed
+    pub changed: Vec<&'static str>,
+    /// Whether the change costs us, i.e. fees went up or the channel was disabled
+    pub adverse: bool,
+}
+
+/// Snapshots of both policies of a channel, as far as the node knows them.
+pub fn channel_snapshots(
+    node_id: &str,
+    details: &ChannelDetails,
+    now: DateTime<Utc>,
+) -> Vec<ChannelPolicySnapshot> {
+    [&details.node1_policy, &details.node2_policy]
+        .into_iter()
+        .flatten()
+        .map(|policy| snapshot(node_id, details, policy, now))
+        .collect()
+}
+
+fn snapshot(
+    node_id: &str,
+    details: &ChannelDetails,
+    policy: &NodePolicy,
+    now: DateTime<Utc>,
+) -> ChannelPolicySnapshot {
+    let ShortChannelID(channel_id) = details.channel_id;
+    ChannelPolicySnapshot {
+        node_id: node_id.to_string(),
+        channel_id: channel_id.to_string(),
+        side: if policy.pubkey == details.remote_pubkey {
+            PolicySide::Remote
+        } else {
+            PolicySide::Local
+        },
+        fee_base_msat: policy.fee_base_msat as i64,
+        fee_rate_ppm: policy.fee_rate_milli_msat as i64,
+        min_htlc_msat: policy.min_htlc_msat as i64,
+        max_htlc_msat: policy.max_htlc_msat.map(|max| max as i64),
+        time_lock_delta: i64::from(policy.time_lock_delta),
+        disabled: policy.disabled,
+        last_update: policy.last_update.map(|update| update as i64),
+        observed_at: now,
+    }
+}
+
+/// Whether two snapshots hold the same policy, whenever they were taken.
+pub fn same_policy(old: &ChannelPolicySnapshot, new: &ChannelPolicySnapshot) -> bool {
+    old.fee_base_msat == new.fee_base_msat
+        && old.fee_rate_ppm == new.fee_rate_ppm
+        && old.min_htlc_msat == new.min_htlc_msat
+        && old.max_htlc_msat == new.max_htlc_msat
+        && old.time_lock_delta == new.time_lock_delta
+        && old.disabled == new.disabled
+}
+
+/// The change from `old` to `new`, if the fees or the disabled flag changed.
+///
+/// Other fields that changed alongside are listed too.
+pub fn policy_change(
+    old: &ChannelPolicySnapshot,
+    new: &ChannelPolicySnapshot,
+) -> Option<PolicyChange> {
+    if old.fee_base_msat == new.fee_base_msat
+        && old.fee_rate_ppm == new.fee_rate_ppm
+        && old.disabled == new.disabled
+    {
+        return None;
+    }
+
+    let fields = [
+        ("fee_base_msat", old.fee_base_msat != new.fee_base_msat),
+        ("fee_rate_ppm", old.fee_rate_ppm != new.fee_rate_ppm),
+        ("min_htlc_msat", old.min_htlc_msat != new.min_htlc_msat),
+        ("max_htlc_msat", old.max_htlc_msat != new.max_htlc_msat),
+        (
+            "time_lock_delta",
+            old.time_lock_delta != new.time_lock_delta,
+        ),
+        ("disabled", old.disabled != new.disabled),
+    ];
+    Some(PolicyChange {
+        changed: fields
+            .into_iter()
+            .filter(|(_, changed)| *changed)
+            .map(|(name, _)| name)
+            .collect(),
+        adverse: new.fee_base_msat > old.fee_base_msat
+            || new.fee_rate_ppm > old.fee_rate_ppm
+            || (new.disabled && !old.disabled),
+    })
+}
+
+/// One line summing up a change, e.g. "fee rate 100 -> 500 ppm, disabled".
+pub fn describe_change(old: &ChannelPolicySnapshot, new: &ChannelPolicySnapshot) -> String {
+    let mut parts = Vec::new();
+    if old.fee_rate_ppm != new.fee_rate_ppm {
+        parts.push(format!(
+            "fee rate {} -> {} ppm",
+            old.fee_rate_ppm, new.fee_rate_ppm
+        ));
+    }
+    if old.fee_base_msat != new.fee_base_msat {
+        parts.push(format!(
+            "base fee {} -> {} msat",
+            old.fee_base_msat, new.fee_base_msat
+        ));
+    }
+    if old.disabled != new.disabled {
+        parts.push(if new.disabled { "disabled" } else { "enabled" }.to_string());
+    }
+    parts.join(", ")
+}
+
+/// Checks the channel policies of every node with stored credentials.
+pub async fn check_all_nodes(pool: &SqlitePool) -> ServiceResult<()> {
+    let credentials = CredentialRepository::new(pool)
+        .get_node_credentials(None, None)
+        .await?;
+
+    for credential in &credentials {
+        if let Err(e) = check_node(pool, credential).await {
+            tracing::warn!("Policy check of node {} failed: {}", credential.node_id, e);
+        }
+    }
+    Ok(())
+}
+
+/// Compares a node's channel policies with their snapshots, raising events for
+/// the peers' changes and storing the new snapshots.
+async fn check_node(pool: &SqlitePool, credential: &Credential) -> ServiceResult<()> {
+    let external = |e: LightningError| ServiceError::ExternalService {
+        message: e.to_string(),
+    };
+    let client = connect_node(credential).await.map_err(external)?;
+    let channel_ids: Vec<ShortChannelID> = client
+        .list_channels()
+        .await
+        .map_err(external)?
+        .into_iter()
+        .filter(|channel| {
+            !matches!(
+                channel.channel_state,
+                ChannelState::Opening | ChannelState::Closed | ChannelState::Failed
+            )
+        })
+        .map(|channel| channel.chan_id)
+        .collect();
+    let details = client
+        .get_channels_info(&channel_ids)
+        .await
+        .map_err(external)?;
+
+    let repo = ChannelPolicyRepository::new(pool);
+    let mut previous: HashMap<(String, PolicySide), ChannelPolicySnapshot> = repo
+        .get_snapshots(&credential.account_id, &credential.node_id)
+        .await?
+        .into_iter()
+        .map(|snapshot| ((snapshot.channel_id.clone(), snapshot.side), snapshot))
+        .collect();
+    let now = Utc::now();
+
+    // Channels whose gossip the node doesn't have keep their snapshots
+    for details in details.into_iter().filter_map(Result::ok) {
+        for current in channel_snapshots(&credential.node_id, &details, now) {
+            let old = previous.remove(&(current.channel_id.clone(), current.side));
+            if old.as_ref().is_some_and(|old| same_policy(old, &current)) {
+                continue;
+            }
+            let change = old
+                .as_ref()
+                .filter(|_| current.side == PolicySide::Remote)
+                .and_then(|old| policy_change(old, &current));
+            if let (Some(old), Some(change)) = (&old, change) {
+                raise_event(pool, credential, &details, old, &current, change).await?;
+            }
+            repo.upsert_snapshot(&credential.account_id, &current)
+                .await?;
+        }
+    }
+
+    let open_channel_ids: Vec<String> = channel_ids.iter().map(|id| id.0.to_string()).collect();
+    repo.delete_other_snapshots(
+        &credential.account_id,
+        &credential.node_id,
+        &open_channel_ids,
+    )
+    .await?;
+    Ok(())
+}
+
+async fn raise_event(
+    pool: &SqlitePool,
+    credential: &Credential,
+    details: &ChannelDetails,
+    old: &ChannelPolicySnapshot,
+    new: &ChannelPolicySnapshot,
+    change: PolicyChange,
+) -> ServiceResult<()> {
+    let title = if new.disabled && !old.disabled {
+        "Peer Disabled Channel"
+    } else {
+        "Peer Changed Channel Fees"
+    };
+
+    EventService::new(pool)
+        .create_and_dispatch_event(CreateEvent {
+            id: Uuid::now_v7().to_string(),
+            account_id: credential.account_id.clone(),
+            user_id: credential.user_id.clone(),
+            node_id: credential.node_id.clone(),
+            node_alias: credential.node_alias.clone(),
+            event_type: EventType::PolicyChanged,
+            severity: if change.adverse {
+                EventSeverity::Warning
+            } else {
+                EventSeverity::Info
+            },
+            title: title.to_string(),
+            description: format!(
+                "Peer {} updated its policy on channel {}: {}",
+                details.remote_pubkey,
+                details.channel_id,
+                describe_change(old, new)
+            ),
+            data: json!({
+                "channel_id": details.channel_id,
+                "remote_pubkey": details.remote_pubkey.to_string(),
+                "changed": change.changed,
+                "old": old,
+                "new": new,
+            })
+            .to_string(),
+            notifications_id: None,
+            timestamp: Utc::now(),
+        })
+        .await?;
+    Ok(())
+}
+
+/// Checks the channel policies of every node every ten minutes.
+pub fn spawn_policy_watcher(pool: SqlitePool) {
+    task_supervisor::supervise("policy_watcher", move |task| {
+        let pool = pool.clone();
+        async move {
+            // The first check waits for startup migrations
+            let mut interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + CHECK_INTERVAL,
+                CHECK_INTERVAL,
+            );
+            loop {
+                interval.tick().await;
+                let result = check_all_nodes(&pool).await;
+                if let Err(e) = &result {
+                    tracing::error!("Channel policy check failed: {}", e);
+                }
+                task.record_run(&result);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote(fee_rate_ppm: i64, disabled: bool) -> ChannelPolicySnapshot {
+        ChannelPolicySnapshot {
+            node_id: "node".to_string(),
+            channel_id: "1".to_string(),
+            side: PolicySide::Remote,
+            fee_base_msat: 1000,
+            fee_rate_ppm,
+            min_htlc_msat: 1000,
+            max_htlc_msat: Some(990_000_000),
+            time_lock_delta: 80,
+            disabled,
+            last_update: None,
+            observed_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn fee_rises_and_disabling_are_adverse() {
+        let old = remote(100, false);
+
+        let raised = policy_change(&old, &remote(500, false)).unwrap();
+        assert_eq!(raised.changed, vec!["fee_rate_ppm"]);
+        assert!(raised.adverse);
+        assert_eq!(
+            describe_change(&old, &remote(500, false)),
+            "fee rate 100 -> 500 ppm"
+        );
+
+        let cut = policy_change(&old, &remote(50, false)).unwrap();
+        assert!(!cut.adverse);
+
+        let disabled = policy_change(&old, &remote(100, true)).unwrap();
+        assert_eq!(disabled.changed, vec!["disabled"]);
+        assert!(disabled.adverse);
+        assert!(!policy_change(&remote(100, true), &old).unwrap().adverse);
+    }
+
+    #[test]
+    fn htlc_limit_changes_alone_raise_nothing() {
+        let old = remote(100, false);
+        let mut new = remote(100, false);
+        new.max_htlc_msat = Some(500_000_000);
+
+        assert!(!same_policy(&old, &new));
+        assert_eq!(policy_change(&old, &new), None);
+
+        new.fee_rate_ppm = 200;
+        assert_eq!(
+            policy_change(&old, &new).unwrap().changed,
+            vec!["fee_rate_ppm", "max_htlc_msat"]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+//! Historical BTC/USD prices for valuing past payments.
+//!
+//! Payments from earlier days are valued at that day's closing price instead of
+//! today's. Closes are cached in the `btc_daily_prices` table and fetched from the
+//! explorer's price API the first time a day is needed; until a day is cached its
+//! payments keep the current-price value. Payments of nodes off mainnet have
+//! no fiat value and are left without one.
+
+use crate::errors::ServiceResult;
+use crate::repositories::price_repository::PriceRepository;
+use crate::utils::mempool::mempool;
+use crate::utils::sats_to_usd::PriceConverter;
+use crate::utils::{PaymentDetails, PaymentSummary};
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::future::join_all;
+use sqlx::SqlitePool;
+use std::collections::{BTreeSet, HashMap};
+
+const PRICE_CURRENCY: &str = "USD";
+
+/// Most missing days fetched from the price API per call, so the first listing of
+/// a long payment history doesn't wait on hundreds of lookups.
+const MAX_PRICE_FETCHES: usize = 31;
+
+/// The UTC day a payment is valued at, or `None` if it happened today or has no time.
+fn valuation_day(
+    completed_at: Option<u64>,
+    creation_time: Option<u64>,
+    today: NaiveDate,
+) -> Option<NaiveDate> {
+    let timestamp = i64::try_from(completed_at.or(creation_time)?).ok()?;
+    let day = DateTime::from_timestamp(timestamp, 0)?.date_naive();
+    (day < today).then_some(day)
+}
+
+/// Service layer for historical price lookups.
+pub struct PriceHistoryService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> PriceHistoryService<'a> {
+    /// Creates a new PriceHistoryService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Returns the closing prices known for the given days, fetching missing ones.
+    pub async fn daily_closes(
+        &self,
+        days: &BTreeSet<NaiveDate>,
+    ) -> ServiceResult<HashMap<NaiveDate, f64>> {
+        let repo = PriceRepository::new(self.pool);
+        let keys: Vec<String> = days.iter().map(|day| day.to_string()).collect();
+
+        let mut closes: HashMap<NaiveDate, f64> = repo
+            .get_daily_closes(PRICE_CURRENCY, &keys)
+            .await?
+            .into_iter()
+            .filter_map(|(date, close)| Some((date.parse().ok()?, close)))
+            .collect();
+
+        // Newest days first, as recent payments are the ones most often viewed
+        let missing: Vec<NaiveDate> = days
+            .iter()
+            .rev()
+            .filter(|day| !closes.contains_key(day))
+            .take(MAX_PRICE_FETCHES)
+            .copied()
+            .collect();
+
+        let fetched = join_all(missing.into_iter().map(|day| async move {
+            // Last second of the day, so the lookup returns that day's close
+            let end_of_day = day.and_hms_opt(23, 59, 59)?.and_utc().timestamp();
+            let close = mempool().historical_usd_price(end_of_day).await?;
+            Some((day, close))
+        }))
+        .await;
+
+        for (day, close) in fetched.into_iter().flatten() {
+            repo.save_daily_close(&day.to_string(), PRICE_CURRENCY, close)
+                .await?;
+            closes.insert(day, close);
+        }
+
+        Ok(closes)
+    }
+
+    /// Revalues payments from earlier days at their day's closing price.
+    pub async fn reprice_payments(&self, payments: &mut [PaymentSummary]) {
+        let today = Utc::now().date_naive();
+        let days: BTreeSet<NaiveDate> = payments
+            .iter()
+            .filter(|payment| payment.amount_usd.is_some())
+            .filter_map(|payment| valuation_day(payment.completed_at, payment.creation_time, today))
+            .collect();
+        if days.is_empty() {
+            return;
+        }
+
+        let closes = match self.daily_closes(&days).await {
+            Ok(closes) => closes,
+            Err(e) => {
+                tracing::warn!("Historical prices unavailable, using current price: {}", e);
+                return;
+            }
+        };
+
+        for payment in payments.iter_mut().filter(|p| p.amount_usd.is_some()) {
+            if let Some(close) = valuation_day(payment.completed_at, payment.creation_time, today)
+                .and_then(|day| closes.get(&day))
+            {
+                payment.amount_usd = Some(PriceConverter::sats_to_usd_with_price(
+                    payment.amount.sat(),
+                    *close,
+                ));
+            }
+        }
+    }
+
+    /// Revalues a payment from an earlier day at that day's closing price.
+    pub async fn reprice_payment(&self, payment: &mut PaymentDetails) {
+        if payment.amount_usd.is_none() {
+            return;
+        }
+        let today = Utc::now().date_naive();
+        let Some(day) = valuation_day(payment.completed_at, payment.creation_time, today) else {
+            return;
+        };
+
+        match self.daily_closes(&BTreeSet::from([day])).await {
+            Ok(closes) => {
+                if let Some(close) = closes.get(&day) {
+                    payment.amount_usd = Some(PriceConverter::sats_to_usd_with_price(
+                        payment.amount.sat(),
+                        *close,
+                    ));
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Historical price unavailable, using current price: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_past_payments_at_their_day() {
+        let today = NaiveDate::from_ymd_opt(2025, 8, 11).unwrap();
+        let yesterday_noon = 1_754_827_200; // 2025-08-10 12:00:00 UTC
+
+        assert_eq!(
+            valuation_day(Some(yesterday_noon), None, today),
+            NaiveDate::from_ymd_opt(2025, 8, 10)
+        );
+        // Completion time wins over creation time
+        assert_eq!(
+            valuation_day(Some(yesterday_noon + 86_400), Some(yesterday_noon), today),
+            None
+        );
+        assert_eq!(valuation_day(None, None, today), None);
+    }
+}
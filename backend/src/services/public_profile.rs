@@ -0,0 +1,210 @@
+//! Shareable profile of a routing node, built from its public data only.
+//!
+//! The profile lists what the node already announces to the network: its
+//! alias and pubkey, its public channels with their capacities, and the fee
+//! policies of both sides of each. Balances, private channels and anything
+//! else only NodeGaze sees are left out. A signed profile carries the node's
+//! signature over the profile's JSON, which anyone can check with
+//! `verifymessage` on LND or CLN against the node's pubkey.
+
+use crate::errors::LightningError;
+use crate::services::node_manager::LightningClient;
+use crate::utils::{ChannelDetails, NodePolicy, ShortChannelID};
+use bitcoin::secp256k1::PublicKey;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// A public channel of the node.
+#[derive(Debug, Serialize)]
+pub struct PublicChannel {
+    pub channel_id: ShortChannelID,
+    pub peer: PublicKey,
+    pub capacity_sat: u64,
+    /// Policy the node charges for forwarding out of the channel
+    pub local_policy: Option<NodePolicy>,
+    /// Policy the peer charges for forwarding into the node
+    pub remote_policy: Option<NodePolicy>,
+}
+
+/// Response of `GET /api/node/public-profile`.
+#[derive(Debug, Serialize)]
+pub struct PublicProfile {
+    pub pubkey: PublicKey,
+    pub alias: String,
+    pub channel_count: usize,
+    pub capacity_sat: u64,
+    /// Largest channels first
+    pub channels: Vec<PublicChannel>,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// The node's signature over a profile.
+#[derive(Debug, Serialize)]
+pub struct ProfileSignature {
+    /// The profile's JSON exactly as signed
+    pub message: String,
+    /// zbase32 signature, as `signmessage` returns it
+    pub signature: String,
+}
+
+/// A profile, signed when the request asked for it.
+#[derive(Debug, Serialize)]
+pub struct SignedPublicProfile {
+    #[serde(flatten)]
+    pub profile: PublicProfile,
+    pub signature: Option<ProfileSignature>,
+}
+
+/// Keeps the public channels of `pubkey`'s node, largest first, with each
+/// side's policy.
+pub fn public_channels(pubkey: &PublicKey, details: Vec<ChannelDetails>) -> Vec<PublicChannel> {
+    let mut channels: Vec<PublicChannel> = details
+        .into_iter()
+        .filter(|channel| !channel.private)
+        .map(|channel| {
+            let (local_policy, remote_policy) = match channel.node1_policy {
+                Some(policy) if policy.pubkey == *pubkey => (Some(policy), channel.node2_policy),
+                policy => (channel.node2_policy, policy),
+            };
+            PublicChannel {
+                channel_id: channel.channel_id,
+                peer: channel.remote_pubkey,
+                capacity_sat: channel.capacity.sat(),
+                local_policy,
+                remote_policy,
+            }
+        })
+        .collect();
+    channels.sort_by(|a, b| {
+        b.capacity_sat
+            .cmp(&a.capacity_sat)
+            .then(a.channel_id.0.cmp(&b.channel_id.0))
+    });
+    channels
+}
+
+/// Builds the profile of the client's node.
+pub async fn build_profile(
+    client: &(dyn LightningClient + Send + Sync),
+) -> Result<PublicProfile, LightningError> {
+    let info = client.get_info();
+    let channel_ids: Vec<ShortChannelID> = client
+        .list_channels()
+        .await?
+        .into_iter()
+        .filter(|channel| !channel.private)
+        .map(|channel| channel.chan_id)
+        .collect();
+
+    let mut details = Vec::new();
+    for result in client.get_channels_info(&channel_ids).await? {
+        match result {
+            Ok(channel) => details.push(channel),
+            // Left out rather than failing the whole profile
+            Err(e) => tracing::warn!("Public profile of {} skips a channel: {}", info.pubkey, e),
+        }
+    }
+    let channels = public_channels(&info.pubkey, details);
+
+    Ok(PublicProfile {
+        pubkey: info.pubkey,
+        alias: info.alias.clone(),
+        channel_count: channels.len(),
+        capacity_sat: channels.iter().map(|channel| channel.capacity_sat).sum(),
+        channels,
+        generated_at: Utc::now(),
+    })
+}
+
+/// Signs the profile's JSON with the node's key.
+pub async fn sign_profile(
+    client: &(dyn LightningClient + Send + Sync),
+    profile: &PublicProfile,
+) -> Result<ProfileSignature, LightningError> {
+    let message = serde_json::to_string(profile)
+        .map_err(|e| LightningError::Parse(format!("Failed to serialize profile: {e}")))?;
+    let signature = client.sign_message(&message).await?;
+    Ok(ProfileSignature { message, signature })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::amount::Amount;
+    use std::str::FromStr;
+
+    const OURS: &str = "02eadbd9e7557375161df8b646776a547c5cbc2e95b3071ec81553f8ec2cea3b8c";
+    const PEER: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+    fn policy(pubkey: &str, fee_rate_milli_msat: u64) -> NodePolicy {
+        NodePolicy {
+            pubkey: PublicKey::from_str(pubkey).unwrap(),
+            fee_base_msat: 1000,
+            fee_rate_milli_msat,
+            min_htlc_msat: 1000,
+            max_htlc_msat: None,
+            time_lock_delta: 80,
+            disabled: false,
+            last_update: None,
+        }
+    }
+
+    fn channel(scid: &str, capacity_sat: u64, private: bool, ours_first: bool) -> ChannelDetails {
+        let (node1_policy, node2_policy) = if ours_first {
+            (Some(policy(OURS, 100)), Some(policy(PEER, 500)))
+        } else {
+            (Some(policy(PEER, 500)), Some(policy(OURS, 100)))
+        };
+        ChannelDetails {
+            channel_id: ShortChannelID::from_str(scid).unwrap(),
+            local_balance: Amount::from_sat(123),
+            remote_balance: Amount::from_sat(456),
+            capacity: Amount::from_sat(capacity_sat),
+            active: Some(true),
+            private,
+            remote_pubkey: PublicKey::from_str(PEER).unwrap(),
+            commit_fee: None,
+            local_chan_reserve: None,
+            remote_chan_reserve: None,
+            num_updates: None,
+            total_sent: None,
+            total_received: None,
+            channel_age_blocks: None,
+            opening_cost: None,
+            initiator: None,
+            txid: None,
+            vout: None,
+            node1_policy,
+            node2_policy,
+            funding_tx: None,
+        }
+    }
+
+    #[test]
+    fn keeps_public_channels_with_each_sides_policy() {
+        let ours = PublicKey::from_str(OURS).unwrap();
+        let channels = public_channels(
+            &ours,
+            vec![
+                channel("800000x1x0", 1_000_000, false, true),
+                channel("800001x2x0", 9_000_000, true, true),
+                channel("800002x3x1", 5_000_000, false, false),
+            ],
+        );
+
+        assert_eq!(channels.len(), 2);
+        assert_eq!(channels[0].channel_id.to_block_format(), "800002x3x1");
+        for channel in &channels {
+            assert_eq!(
+                channel.local_policy.as_ref().unwrap().fee_rate_milli_msat,
+                100
+            );
+            assert_eq!(
+                channel.remote_policy.as_ref().unwrap().fee_rate_milli_msat,
+                500
+            );
+        }
+        let json = serde_json::to_string(&channels).unwrap();
+        assert!(!json.contains("balance"));
+    }
+}
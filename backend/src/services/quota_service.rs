@@ -0,0 +1,243 @@
+//! Per-account quotas for hosted deployments.
+//!
+//! Operators set limits with `nodegaze-admin set-quota`; accounts without any are
+//! unlimited. Node and notification limits reject new ones once reached, the
+//! event limit deletes the oldest events as new ones arrive, and the daily API
+//! request limit is enforced by `jwt_auth`.
+
+use crate::database::models::{AccountQuota, AccountUsage, QuotaUsage};
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::repositories::quota_repository::QuotaRepository;
+use chrono::{DateTime, Days, Utc};
+use sqlx::SqlitePool;
+use std::str::FromStr;
+
+/// A limit that can be set on an account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaResource {
+    Nodes,
+    Notifications,
+    Events,
+    ApiRequestsPerDay,
+}
+
+impl FromStr for QuotaResource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nodes" => Ok(Self::Nodes),
+            "notifications" => Ok(Self::Notifications),
+            "events" => Ok(Self::Events),
+            "api-requests-per-day" => Ok(Self::ApiRequestsPerDay),
+            _ => Err(format!(
+                "unknown quota '{s}', expected nodes, notifications, events or api-requests-per-day"
+            )),
+        }
+    }
+}
+
+impl AccountQuota {
+    /// Sets one limit, `None` for unlimited.
+    pub fn set(&mut self, resource: QuotaResource, limit: Option<i64>) {
+        match resource {
+            QuotaResource::Nodes => self.max_nodes = limit,
+            QuotaResource::Notifications => self.max_notifications = limit,
+            QuotaResource::Events => self.max_events = limit,
+            QuotaResource::ApiRequestsPerDay => self.max_api_requests_per_day = limit,
+        }
+    }
+}
+
+/// Day key API requests are counted under.
+fn usage_day(now: DateTime<Utc>) -> String {
+    now.format("%Y-%m-%d").to_string()
+}
+
+/// Start of the next UTC day, when the daily API request count starts over.
+fn next_usage_reset(now: DateTime<Utc>) -> DateTime<Utc> {
+    let tomorrow = now.date_naive() + Days::new(1);
+    tomorrow.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc()
+}
+
+/// Number of oldest events to delete to bring `stored` back to `limit`.
+fn events_over_limit(stored: i64, limit: Option<i64>) -> i64 {
+    limit.map_or(0, |limit| (stored - limit).max(0))
+}
+
+/// Service layer for account quotas.
+pub struct QuotaService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> QuotaService<'a> {
+    /// Creates a new QuotaService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Retrieves the quotas of an account.
+    pub async fn get_quota(&self, account_id: &str) -> ServiceResult<AccountQuota> {
+        Ok(QuotaRepository::new(self.pool)
+            .get_quota(account_id)
+            .await?)
+    }
+
+    /// Sets one quota of an account, `None` for unlimited.
+    pub async fn set_quota(
+        &self,
+        account_id: &str,
+        resource: QuotaResource,
+        limit: Option<i64>,
+    ) -> ServiceResult<AccountQuota> {
+        if limit.is_some_and(|limit| limit < 0) {
+            return Err(ServiceError::validation("Quota limits cannot be negative"));
+        }
+
+        let repo = QuotaRepository::new(self.pool);
+        let mut quota = repo.get_quota(account_id).await?;
+        quota.set(resource, limit);
+        repo.set_quota(account_id, &quota).await?;
+
+        Ok(quota)
+    }
+
+    /// Reports what an account uses against each of its quotas.
+    pub async fn get_usage(&self, account_id: &str) -> ServiceResult<AccountUsage> {
+        let repo = QuotaRepository::new(self.pool);
+        let quota = repo.get_quota(account_id).await?;
+        let now = Utc::now();
+
+        Ok(AccountUsage {
+            nodes: QuotaUsage {
+                used: repo.count_nodes(account_id).await?,
+                limit: quota.max_nodes,
+            },
+            notifications: QuotaUsage {
+                used: repo.count_notifications(account_id).await?,
+                limit: quota.max_notifications,
+            },
+            events: QuotaUsage {
+                used: repo.count_stored_events(account_id).await?,
+                limit: quota.max_events,
+            },
+            api_requests_today: QuotaUsage {
+                used: repo.get_api_requests(account_id, &usage_day(now)).await?,
+                limit: quota.max_api_requests_per_day,
+            },
+            api_requests_reset_at: next_usage_reset(now),
+        })
+    }
+
+    /// Rejects connecting a node that would take the account over its node quota.
+    ///
+    /// The user's own stored credential is replaced by the new one, so its node only
+    /// counts while another user of the account also monitors it.
+    pub async fn check_node_quota(
+        &self,
+        account_id: &str,
+        user_id: &str,
+        node_id: &str,
+    ) -> ServiceResult<()> {
+        let Some(limit) = self.get_quota(account_id).await?.max_nodes else {
+            return Ok(());
+        };
+
+        let repo = CredentialRepository::new(self.pool);
+        if repo
+            .get_node_credentials(Some(account_id), None)
+            .await?
+            .iter()
+            .any(|credential| credential.node_id == node_id)
+        {
+            return Ok(());
+        }
+
+        let nodes = repo.count_nodes_of_other_users(account_id, user_id).await? + 1;
+        if nodes > limit {
+            return Err(ServiceError::quota_exceeded("Node", limit));
+        }
+        Ok(())
+    }
+
+    /// Rejects creating a notification endpoint beyond the account's quota.
+    pub async fn check_notification_quota(&self, account_id: &str) -> ServiceResult<()> {
+        let repo = QuotaRepository::new(self.pool);
+        let Some(limit) = repo.get_quota(account_id).await?.max_notifications else {
+            return Ok(());
+        };
+
+        if repo.count_notifications(account_id).await? >= limit {
+            return Err(ServiceError::quota_exceeded("Notification", limit));
+        }
+        Ok(())
+    }
+
+    /// Deletes an account's oldest events beyond its event quota.
+    ///
+    /// Returns the number of deleted events.
+    pub async fn enforce_event_quota(&self, account_id: &str) -> ServiceResult<u64> {
+        let repo = QuotaRepository::new(self.pool);
+        let limit = repo.get_quota(account_id).await?.max_events;
+        if limit.is_none() {
+            return Ok(0);
+        }
+
+        let excess = events_over_limit(repo.count_stored_events(account_id).await?, limit);
+        if excess == 0 {
+            return Ok(0);
+        }
+        Ok(repo.purge_oldest_events(account_id, excess).await?)
+    }
+
+    /// Counts an API request of an account.
+    ///
+    /// Returns whether the request is within the account's daily limit.
+    pub async fn meter_api_request(&self, account_id: &str) -> ServiceResult<bool> {
+        let repo = QuotaRepository::new(self.pool);
+        let count = repo
+            .record_api_request(account_id, &usage_day(Utc::now()))
+            .await?;
+        let limit = repo.get_quota(account_id).await?.max_api_requests_per_day;
+
+        Ok(limit.is_none_or(|limit| count <= limit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn api_usage_resets_at_utc_midnight() {
+        let now = Utc.with_ymd_and_hms(2025, 12, 31, 23, 59, 59).unwrap();
+        assert_eq!(usage_day(now), "2025-12-31");
+        assert_eq!(
+            next_usage_reset(now),
+            Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn only_events_beyond_the_limit_are_purged() {
+        assert_eq!(events_over_limit(120, Some(100)), 20);
+        assert_eq!(events_over_limit(80, Some(100)), 0);
+        assert_eq!(events_over_limit(1_000_000, None), 0);
+    }
+
+    #[test]
+    fn parses_quota_names() {
+        assert_eq!(
+            "api-requests-per-day".parse::<QuotaResource>(),
+            Ok(QuotaResource::ApiRequestsPerDay)
+        );
+        assert!("storage".parse::<QuotaResource>().is_err());
+
+        let mut quota = AccountQuota::default();
+        quota.set(QuotaResource::Nodes, Some(3));
+        assert_eq!(quota.max_nodes, Some(3));
+    }
+}
@@ -0,0 +1,71 @@
+//! Capture of the full node messages events are built from.
+//!
+//! Event `data` only keeps selected fields. Accounts that turn on
+//! `capture_raw_events` also get the whole message each event came from, stored
+//! in `event_raw` and served by `GET /api/events/{id}/raw`, as the node client
+//! converted it to JSON. Messages larger than `MAX_RAW_EVENT_BYTES` are cut
+//! short.
+
+use crate::errors::ServiceResult;
+use crate::repositories::event_raw_repository::EventRawRepository;
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+pub use nodegaze_lightning::events::RawPayload;
+
+/// Largest message stored in full.
+pub const MAX_RAW_EVENT_BYTES: usize = 64 * 1024;
+
+/// Serializes a message, cutting it at `max_bytes` on a character boundary.
+///
+/// Returns the stored text, the full size and whether it was cut.
+fn limit_payload(message: &Value, max_bytes: usize) -> (String, usize, bool) {
+    let mut text = message.to_string();
+    let size = text.len();
+    if size <= max_bytes {
+        return (text, size, false);
+    }
+
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    (text, size, true)
+}
+
+/// Stores the raw message of each of an event's copies.
+pub async fn store_raw_event(
+    pool: &SqlitePool,
+    event_ids: &[String],
+    raw: &RawPayload,
+) -> ServiceResult<()> {
+    let (payload, size, truncated) = limit_payload(&raw.message, MAX_RAW_EVENT_BYTES);
+    let repo = EventRawRepository::new(pool);
+    for event_id in event_ids {
+        repo.create_raw_event(event_id, raw.source, &payload, size as i64, truncated)
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn cuts_large_payloads_on_character_boundaries() {
+        let message = json!({ "memo": "é".repeat(10) });
+
+        let (text, size, truncated) = limit_payload(&message, 12);
+
+        assert!(truncated);
+        assert_eq!(size, message.to_string().len());
+        assert!(text.len() <= 12);
+        assert!(text.starts_with(r#"{"memo":""#));
+
+        let (_, _, truncated) = limit_payload(&message, MAX_RAW_EVENT_BYTES);
+        assert!(!truncated);
+    }
+}
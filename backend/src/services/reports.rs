@@ -0,0 +1,569 @@
+//! Weekly and monthly node reports.
+//!
+//! Accounts that set `reports.frequency` get a report per node once each week
+//! (Monday to Monday) or calendar month has ended in the account's time zone.
+//! A report sums the period's forwarding fees and payment volume, lists the
+//! channels opened and closed, and gives the node's uptime from hourly
+//! reachability probes. Reports are stored for download as HTML or PDF and
+//! emailed to the configured recipients, carrying the account's `branding`.
+
+use crate::config::EmailConfig;
+use crate::database::models::{
+    BrandingSettings, Credential, Event, EventType, Report, ReportFrequency,
+};
+use crate::errors::{LightningError, ServiceError, ServiceResult};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::repositories::event_repository::EventRepository;
+use crate::repositories::report_repository::ReportRepository;
+use crate::services::email_service::EmailService;
+use crate::services::node_manager::connect_node;
+use crate::services::payment_stats::{StatsBucket, payment_stats};
+use crate::services::settings_service::SettingsService;
+use crate::services::task_supervisor;
+use crate::utils::ChannelState;
+use crate::utils::amount::Amount;
+use crate::utils::pdf::render_text_pdf;
+use crate::utils::time_zone::local_midnight;
+use chrono::{DateTime, Datelike, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+/// Availability probes are kept long enough to cover any monthly report.
+const AVAILABILITY_RETENTION: Duration = Duration::days(62);
+
+/// A channel opened or closed during the period.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelChange {
+    pub timestamp: DateTime<Utc>,
+    pub description: String,
+}
+
+/// Figures of a node over a report period, stored as the report's summary.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReportSummary {
+    pub fee_revenue_sat: u64,
+    pub forwards: u64,
+    pub forwarded_volume_sat: u64,
+    pub payments_sent: u64,
+    pub sent_volume_sat: u64,
+    pub payments_received: u64,
+    pub received_volume_sat: u64,
+    pub failed_payments: u64,
+    /// Share of finished outgoing payments that settled
+    pub success_rate: Option<f64>,
+    pub channels_opened: Vec<ChannelChange>,
+    pub channels_closed: Vec<ChannelChange>,
+    /// Channel counts and capacity when the report was generated
+    pub active_channels: u64,
+    pub total_channels: u64,
+    pub total_capacity_sat: u64,
+    /// Share of hourly probes the node answered, absent without probes
+    pub uptime_percent: Option<f64>,
+    pub uptime_samples: i64,
+}
+
+/// The most recent week or month that has fully passed in the time zone.
+pub fn last_complete_period(
+    frequency: ReportFrequency,
+    now: DateTime<Utc>,
+    tz: chrono_tz::Tz,
+) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let today = now.with_timezone(&tz).date_naive();
+
+    let (start, end) = match frequency {
+        ReportFrequency::Off => return None,
+        ReportFrequency::Weekly => {
+            let monday = today - Duration::days(today.weekday().num_days_from_monday().into());
+            (monday - Duration::days(7), monday)
+        }
+        ReportFrequency::Monthly => {
+            let first = today.with_day(1)?;
+            let previous = (first - Duration::days(1)).with_day(1)?;
+            (previous, first)
+        }
+    };
+
+    Some((local_midnight(tz, start)?, local_midnight(tz, end)?))
+}
+
+/// Collects a node's figures for the period and stores them as a report.
+pub async fn generate_report(
+    pool: &SqlitePool,
+    credential: &Credential,
+    frequency: ReportFrequency,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> ServiceResult<Report> {
+    let external = |e: LightningError| ServiceError::ExternalService {
+        message: e.to_string(),
+    };
+    let client = connect_node(credential).await.map_err(external)?;
+    let (payments, forwards, channels) = tokio::join!(
+        client.list_payments(),
+        client.list_forwards(start.timestamp().max(0) as u64),
+        client.list_channels(),
+    );
+    let payments = payments.map_err(external)?;
+    let forwards = forwards.map_err(external)?;
+    let channels = channels.map_err(external)?;
+
+    let forwards: Vec<_> = forwards
+        .into_iter()
+        .filter(|forward| (forward.resolved_at as i64) < end.timestamp())
+        .collect();
+    // The statistics window is inclusive of its end
+    let totals = payment_stats(
+        &payments,
+        &forwards,
+        start,
+        end - Duration::seconds(1),
+        StatsBucket::Day,
+        chrono_tz::UTC,
+    )
+    .totals;
+
+    let events = EventRepository::new(pool)
+        .get_node_events_between(
+            &credential.account_id,
+            &credential.node_id,
+            &[
+                EventType::ChannelOpened,
+                EventType::ChannelClosed,
+                EventType::ChannelBreachDetected,
+            ],
+            start,
+            end,
+        )
+        .await?;
+    let (opened, closed): (Vec<_>, Vec<_>) = events
+        .into_iter()
+        .partition(|event| event.event_type == EventType::ChannelOpened);
+    let changes = |events: Vec<Event>| {
+        events
+            .into_iter()
+            .map(|event| ChannelChange {
+                timestamp: event.timestamp,
+                description: event.description,
+            })
+            .collect()
+    };
+
+    let (reachable, samples) = ReportRepository::new(pool)
+        .count_availability(&credential.account_id, &credential.node_id, start, end)
+        .await?;
+
+    let summary = ReportSummary {
+        fee_revenue_sat: forwards.iter().map(|forward| forward.fee_msat).sum::<u64>() / 1000,
+        forwards: forwards.len() as u64,
+        forwarded_volume_sat: totals.forwarded.volume_sat,
+        payments_sent: totals.outgoing.count,
+        sent_volume_sat: totals.outgoing.volume_sat,
+        payments_received: totals.incoming.count,
+        received_volume_sat: totals.incoming.volume_sat,
+        failed_payments: totals.failed_outgoing,
+        success_rate: totals.success_rate,
+        channels_opened: changes(opened),
+        channels_closed: changes(closed),
+        active_channels: channels
+            .iter()
+            .filter(|channel| matches!(channel.channel_state, ChannelState::Active))
+            .count() as u64,
+        total_channels: channels.len() as u64,
+        total_capacity_sat: channels
+            .iter()
+            .map(|channel| channel.capacity)
+            .sum::<Amount>()
+            .sat(),
+        uptime_percent: (samples > 0).then(|| reachable as f64 * 100.0 / samples as f64),
+        uptime_samples: samples,
+    };
+
+    let report = Report {
+        id: Uuid::now_v7().to_string(),
+        account_id: credential.account_id.clone(),
+        node_id: credential.node_id.clone(),
+        node_alias: credential.node_alias.clone(),
+        frequency,
+        period_start: start,
+        period_end: end,
+        summary: serde_json::to_value(&summary).map_err(|e| ServiceError::InternalError {
+            message: format!("Failed to encode report: {e}"),
+        })?,
+        created_at: Utc::now(),
+    };
+    ReportRepository::new(pool).save_report(&report).await?;
+
+    Ok(report)
+}
+
+fn summary_of(report: &Report) -> ServiceResult<ReportSummary> {
+    serde_json::from_value(report.summary.clone()).map_err(|e| ServiceError::InternalError {
+        message: format!("Stored report {} is unreadable: {e}", report.id),
+    })
+}
+
+/// Title naming the node and the days covered.
+pub fn report_title(report: &Report) -> String {
+    let kind = match report.frequency {
+        ReportFrequency::Monthly => "Monthly",
+        _ => "Weekly",
+    };
+    let name = if report.node_alias.is_empty() {
+        &report.node_id
+    } else {
+        &report.node_alias
+    };
+    format!(
+        "{kind} report for {name}, {} to {}",
+        report.period_start.format("%Y-%m-%d"),
+        (report.period_end - Duration::seconds(1)).format("%Y-%m-%d")
+    )
+}
+
+/// The report as lines of plain text, for emails and PDFs.
+pub fn report_lines(report: &Report) -> ServiceResult<Vec<String>> {
+    let summary = summary_of(report)?;
+    let mut lines = vec![
+        format!("Node: {}", report.node_id),
+        String::new(),
+        "Revenue and volume".to_string(),
+        format!(
+            "  Fee revenue: {} sat from {} forwards",
+            summary.fee_revenue_sat, summary.forwards
+        ),
+        format!("  Forwarded: {} sat", summary.forwarded_volume_sat),
+        format!(
+            "  Sent: {} sat in {} payments",
+            summary.sent_volume_sat, summary.payments_sent
+        ),
+        format!(
+            "  Received: {} sat in {} payments",
+            summary.received_volume_sat, summary.payments_received
+        ),
+        format!(
+            "  Failed payments: {}{}",
+            summary.failed_payments,
+            summary
+                .success_rate
+                .map(|rate| format!(" (success rate {:.1}%)", rate * 100.0))
+                .unwrap_or_default()
+        ),
+        String::new(),
+        "Channels".to_string(),
+        format!(
+            "  {} of {} channels active, {} sat capacity",
+            summary.active_channels, summary.total_channels, summary.total_capacity_sat
+        ),
+        format!(
+            "  Opened: {}, closed: {}",
+            summary.channels_opened.len(),
+            summary.channels_closed.len()
+        ),
+    ];
+    for (label, changes) in [
+        ("Opened", &summary.channels_opened),
+        ("Closed", &summary.channels_closed),
+    ] {
+        lines.extend(changes.iter().map(|change| {
+            format!(
+                "  - {label} {}: {}",
+                change.timestamp.format("%Y-%m-%d %H:%M UTC"),
+                change.description
+            )
+        }));
+    }
+    lines.push(String::new());
+    lines.push("Uptime".to_string());
+    lines.push(match summary.uptime_percent {
+        Some(percent) => format!(
+            "  {percent:.1}% of {} hourly checks answered",
+            summary.uptime_samples
+        ),
+        None => "  No checks were recorded in this period".to_string(),
+    });
+
+    Ok(lines)
+}
+
+/// Report title led by the account's display name, if it has one.
+pub fn branded_title(report: &Report, branding: &BrandingSettings) -> String {
+    match &branding.display_name {
+        Some(name) => format!("{name}: {}", report_title(report)),
+        None => report_title(report),
+    }
+}
+
+/// Report lines closed by the account's footer text, if it has one.
+fn branded_lines(report: &Report, branding: &BrandingSettings) -> ServiceResult<Vec<String>> {
+    let mut lines = report_lines(report)?;
+    if let Some(footer) = &branding.footer_text {
+        lines.push(String::new());
+        lines.push(footer.clone());
+    }
+    Ok(lines)
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Renders the report as a standalone HTML page.
+pub fn render_html(report: &Report, branding: &BrandingSettings) -> ServiceResult<String> {
+    let page_title = escape_html(&branded_title(report, branding));
+    let title = escape_html(&report_title(report));
+    let mut header = Vec::new();
+    if let Some(logo_url) = &branding.logo_url {
+        header.push(format!(
+            r#"<img src="{}" alt="" style="max-height: 48px;">"#,
+            escape_html(logo_url)
+        ));
+    }
+    if let Some(name) = &branding.display_name {
+        header.push(format!(
+            r#"<p style="color: #7f8c8d; margin: 0;">{}</p>"#,
+            escape_html(name)
+        ));
+    }
+    let mut footer = Vec::new();
+    if let Some(footer_text) = &branding.footer_text {
+        footer.push(
+            r#"<hr style="border: none; border-top: 1px solid #ecf0f1; margin: 30px 0;">"#
+                .to_string(),
+        );
+        footer.push(format!(
+            r#"<p style="font-size: 12px; color: #7f8c8d;">{}</p>"#,
+            escape_html(footer_text)
+        ));
+    }
+    let lines = report_lines(report)?
+        .into_iter()
+        .map(|line| match line.strip_prefix("  ") {
+            Some(item) => format!("<li>{}</li>", escape_html(item.trim_start_matches("- "))),
+            None if line.is_empty() => String::new(),
+            None => format!("<h3 style=\"color: #2c3e50;\">{}</h3>", escape_html(&line)),
+        });
+    let body: String = header
+        .into_iter()
+        .chain([format!(r#"<h2 style="color: #2c3e50;">{title}</h2>"#)])
+        .chain(lines)
+        .chain(footer)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok(format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <title>{page_title}</title>
+</head>
+<body style="font-family: Arial, sans-serif; line-height: 1.6; color: #333;">
+    <div style="max-width: 600px; margin: 0 auto; padding: 20px;">
+        {body}
+    </div>
+</body>
+</html>"#
+    ))
+}
+
+/// Renders the report as a PDF document.
+///
+/// PDFs are text only, so the logo is left out.
+pub fn render_pdf(report: &Report, branding: &BrandingSettings) -> ServiceResult<Vec<u8>> {
+    Ok(render_text_pdf(
+        &branded_title(report, branding),
+        &branded_lines(report, branding)?,
+    ))
+}
+
+/// Emails a report to each recipient, logging failed deliveries.
+async fn email_report(
+    email: &EmailService,
+    report: &Report,
+    recipients: &[String],
+    branding: &BrandingSettings,
+) {
+    let (html, text) = match (
+        render_html(report, branding),
+        branded_lines(report, branding),
+    ) {
+        (Ok(html), Ok(lines)) => (html, lines.join("\n")),
+        (Err(e), _) | (_, Err(e)) => {
+            tracing::error!("Failed to render report {}: {}", report.id, e);
+            return;
+        }
+    };
+    let subject = branded_title(report, branding);
+
+    for recipient in recipients {
+        if let Err(e) = email.send_email(recipient, &subject, &html, &text).await {
+            tracing::warn!(
+                "Failed to email report {} to {}: {}",
+                report.id,
+                recipient,
+                e
+            );
+        }
+    }
+}
+
+/// Probes the nodes of accounts with reports enabled and writes the reports
+/// of periods that ended since the last run.
+pub async fn run_scheduled_reports(
+    pool: &SqlitePool,
+    email: Option<&EmailService>,
+) -> ServiceResult<()> {
+    let credentials = CredentialRepository::new(pool)
+        .get_node_credentials(None, None)
+        .await?;
+    let settings_service = SettingsService::new(pool);
+    let repo = ReportRepository::new(pool);
+    let now = Utc::now();
+    let hour = now - Duration::seconds(now.timestamp().rem_euclid(3600));
+
+    for credential in &credentials {
+        let settings = settings_service
+            .get_settings(&credential.account_id)
+            .await?;
+        let frequency = settings.reports.frequency;
+        let Some((start, end)) = last_complete_period(frequency, now, settings.tz()) else {
+            continue;
+        };
+
+        let reachable = connect_node(credential).await.is_ok();
+        repo.record_availability(&credential.account_id, &credential.node_id, hour, reachable)
+            .await?;
+
+        if repo
+            .report_exists(
+                &credential.account_id,
+                &credential.node_id,
+                frequency,
+                start,
+            )
+            .await?
+        {
+            continue;
+        }
+        match generate_report(pool, credential, frequency, start, end).await {
+            Ok(report) => {
+                if let Some(email) = email {
+                    email_report(
+                        email,
+                        &report,
+                        &settings.reports.recipients,
+                        &settings.branding,
+                    )
+                    .await;
+                }
+            }
+            Err(e) => tracing::warn!(
+                "Report of node {} for {} failed: {}",
+                credential.node_id,
+                start,
+                e
+            ),
+        }
+    }
+
+    repo.purge_availability_before(now - AVAILABILITY_RETENTION)
+        .await?;
+    Ok(())
+}
+
+/// Checks for due reports every hour.
+pub fn spawn_report_scheduler(pool: SqlitePool, email_config: Option<EmailConfig>) {
+    let email = email_config.and_then(|config| match EmailService::new(config) {
+        Ok(email) => Some(email),
+        Err(e) => {
+            tracing::warn!("Reports won't be emailed: {}", e);
+            None
+        }
+    });
+
+    task_supervisor::supervise("reports", move |task| {
+        let pool = pool.clone();
+        let email = email.clone();
+        async move {
+            // The first run waits for startup migrations
+            let mut interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + CHECK_INTERVAL,
+                CHECK_INTERVAL,
+            );
+            loop {
+                interval.tick().await;
+                let result = run_scheduled_reports(&pool, email.as_ref()).await;
+                if let Err(e) = &result {
+                    tracing::error!("Scheduled reports failed: {}", e);
+                }
+                task.record_run(&result);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn finds_last_complete_period() {
+        // Wednesday 2025-08-13, 01:30 in Berlin
+        let now = Utc.with_ymd_and_hms(2025, 8, 12, 23, 30, 0).unwrap();
+        let berlin = chrono_tz::Europe::Berlin;
+
+        let (start, end) = last_complete_period(ReportFrequency::Weekly, now, berlin).unwrap();
+        assert_eq!(start, Utc.with_ymd_and_hms(2025, 8, 3, 22, 0, 0).unwrap());
+        assert_eq!(end, Utc.with_ymd_and_hms(2025, 8, 10, 22, 0, 0).unwrap());
+
+        let (start, end) = last_complete_period(ReportFrequency::Monthly, now, berlin).unwrap();
+        assert_eq!(start, Utc.with_ymd_and_hms(2025, 6, 30, 22, 0, 0).unwrap());
+        assert_eq!(end, Utc.with_ymd_and_hms(2025, 7, 31, 22, 0, 0).unwrap());
+
+        assert!(last_complete_period(ReportFrequency::Off, now, berlin).is_none());
+    }
+
+    #[test]
+    fn renders_the_account_branding() {
+        let report = Report {
+            id: "report".to_string(),
+            account_id: "account".to_string(),
+            node_id: "node".to_string(),
+            node_alias: "alias".to_string(),
+            frequency: ReportFrequency::Weekly,
+            period_start: Utc.with_ymd_and_hms(2025, 8, 4, 0, 0, 0).unwrap(),
+            period_end: Utc.with_ymd_and_hms(2025, 8, 11, 0, 0, 0).unwrap(),
+            summary: serde_json::to_value(ReportSummary::default()).unwrap(),
+            created_at: Utc::now(),
+        };
+        let branding = BrandingSettings {
+            display_name: Some("Acme & Co".to_string()),
+            logo_url: Some("https://acme.example/logo.png".to_string()),
+            footer_text: Some("Questions? ops@acme.example".to_string()),
+        };
+
+        assert_eq!(
+            branded_title(&report, &branding),
+            "Acme & Co: Weekly report for alias, 2025-08-04 to 2025-08-10"
+        );
+        let html = render_html(&report, &branding).unwrap();
+        assert!(html.contains(r#"<img src="https://acme.example/logo.png""#));
+        assert!(html.contains("Acme &amp; Co"));
+        assert!(html.contains("Questions? ops@acme.example"));
+        let lines = branded_lines(&report, &branding).unwrap();
+        assert_eq!(lines.last().unwrap(), "Questions? ops@acme.example");
+
+        let plain = render_html(&report, &BrandingSettings::default()).unwrap();
+        assert!(!plain.contains("<img"));
+        assert_eq!(
+            branded_title(&report, &BrandingSettings::default()),
+            report_title(&report)
+        );
+    }
+}
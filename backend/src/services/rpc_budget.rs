@@ -0,0 +1,406 @@
+//! Per-node budget of RPC calls, protecting nodes from floods of requests.
+//!
+//! Response caching keeps polling dashboards away from the node, but a client
+//! looping over uncached endpoints can still send it a call for every request.
+//! Every client opened for a node therefore draws from a token bucket shared
+//! by all requests and background tasks reaching that node, refilled at
+//! `NODE_RPC_CALLS_PER_SECOND`. A call finding the bucket empty is queued
+//! until its turn comes, and once `NODE_RPC_MAX_QUEUED` calls wait, further
+//! ones are shed with `LightningError::RateLimited` instead of piling up. Each
+//! method of `LightningClient` counts as one call, however many RPCs it makes
+//! and however long a stream it opens.
+
+use crate::errors::LightningError;
+use crate::services::event_manager::CapturedEvent;
+use crate::services::node_manager::{GraphUpdates, LightningClient, PaymentUpdates};
+use crate::utils::close_fee::CloseFeeEstimate;
+use crate::utils::{
+    ChannelBreach, ChannelDetails, ChannelSummary, CustomInvoice, DualFundRequest, ForwardSummary,
+    GraphNode, LiquidityAd, NetworkGraph, NodeInfo, OpenedChannel, PaymentDetails, PaymentSummary,
+    PeerSummary, PendingHtlcs, ShortChannelID, TransactionLabel,
+};
+use async_trait::async_trait;
+use bitcoin::{Network, Txid, secp256k1::PublicKey};
+use lightning::ln::PaymentHash;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tokio_stream::Stream;
+
+/// Calls made to a node through its budget since the server started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RpcCounts {
+    /// Calls let through, right away or after waiting
+    pub calls: u64,
+    /// Calls that waited for their turn
+    pub delayed: u64,
+    /// Calls refused because too many were waiting
+    pub shed: u64,
+}
+
+/// Token bucket holding up to a second's worth of calls.
+///
+/// Tokens go negative while calls wait, each waiting call having reserved the
+/// token refilled at its turn, so waiting calls are let through in order.
+#[derive(Debug)]
+pub struct TokenBucket {
+    calls_per_second: f64,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(calls_per_second: u32, now: Instant) -> Self {
+        Self {
+            calls_per_second: f64::from(calls_per_second),
+            tokens: f64::from(calls_per_second),
+            refilled_at: now,
+        }
+    }
+
+    /// Reserves a call at `now`, returning how long it has to wait for its turn,
+    /// or `None` when `max_queued` calls are waiting already.
+    pub fn reserve(&mut self, now: Instant, max_queued: usize) -> Option<Duration> {
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.calls_per_second)
+            .min(self.calls_per_second);
+        self.refilled_at = now;
+
+        let waiting = (-self.tokens).max(0.0).ceil();
+        if self.tokens < 1.0 && waiting >= max_queued as f64 {
+            return None;
+        }
+        self.tokens -= 1.0;
+        Some(Duration::from_secs_f64(
+            (-self.tokens).max(0.0) / self.calls_per_second,
+        ))
+    }
+}
+
+/// The budget of one node.
+struct NodeBudget {
+    node_id: String,
+    bucket: Mutex<TokenBucket>,
+    max_queued: usize,
+    calls: AtomicU64,
+    delayed: AtomicU64,
+    shed: AtomicU64,
+}
+
+impl NodeBudget {
+    /// Waits for the next call's turn, or fails when too many calls wait.
+    async fn acquire(&self) -> Result<(), LightningError> {
+        let wait = self
+            .bucket
+            .lock()
+            .map_err(|_| LightningError::RateLimited("RPC budget unavailable".to_string()))?
+            .reserve(Instant::now(), self.max_queued);
+
+        match wait {
+            None => {
+                self.shed.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!(
+                    "Shed an RPC call to node {}, too many waiting",
+                    self.node_id
+                );
+                Err(LightningError::RateLimited(format!(
+                    "Too many calls to node {} are waiting, try again shortly",
+                    self.node_id
+                )))
+            }
+            Some(wait) => {
+                if !wait.is_zero() {
+                    self.delayed.fetch_add(1, Ordering::Relaxed);
+                    tokio::time::sleep(wait).await;
+                }
+                self.calls.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+        }
+    }
+
+    fn counts(&self) -> RpcCounts {
+        RpcCounts {
+            calls: self.calls.load(Ordering::Relaxed),
+            delayed: self.delayed.load(Ordering::Relaxed),
+            shed: self.shed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Rate of `NODE_RPC_CALLS_PER_SECOND` when unset.
+pub const DEFAULT_CALLS_PER_SECOND: u32 = 20;
+/// Calls queued per node with `NODE_RPC_MAX_QUEUED` unset.
+pub const DEFAULT_MAX_QUEUED: usize = 50;
+
+static BUDGETS: LazyLock<Mutex<HashMap<String, Arc<NodeBudget>>>> = LazyLock::new(Default::default);
+
+/// Puts a node's client under the node's RPC budget, unless the budget is
+/// turned off with `NODE_RPC_CALLS_PER_SECOND=0`.
+///
+/// Without a loaded configuration the default budget applies.
+pub fn budgeted(
+    node_id: &str,
+    client: Box<dyn LightningClient + Send + Sync>,
+) -> Box<dyn LightningClient + Send + Sync> {
+    let (calls_per_second, max_queued) = crate::config::shared()
+        .map_or((DEFAULT_CALLS_PER_SECOND, DEFAULT_MAX_QUEUED), |config| {
+            (config.node_rpc_calls_per_second, config.node_rpc_max_queued)
+        });
+    if calls_per_second == 0 {
+        return client;
+    }
+    let Ok(mut budgets) = BUDGETS.lock() else {
+        return client;
+    };
+    let budget = budgets
+        .entry(node_id.to_string())
+        .or_insert_with(|| {
+            Arc::new(NodeBudget {
+                node_id: node_id.to_string(),
+                bucket: Mutex::new(TokenBucket::new(calls_per_second, Instant::now())),
+                max_queued,
+                calls: AtomicU64::new(0),
+                delayed: AtomicU64::new(0),
+                shed: AtomicU64::new(0),
+            })
+        })
+        .clone();
+
+    Box::new(BudgetedClient {
+        inner: client,
+        budget,
+    })
+}
+
+/// Calls made to a node through its budget, all zero before its first client.
+pub fn rpc_counts(node_id: &str) -> RpcCounts {
+    BUDGETS
+        .lock()
+        .ok()
+        .and_then(|budgets| budgets.get(node_id).map(|budget| budget.counts()))
+        .unwrap_or_default()
+}
+
+/// A client whose calls draw from its node's budget.
+struct BudgetedClient {
+    inner: Box<dyn LightningClient + Send + Sync>,
+    budget: Arc<NodeBudget>,
+}
+
+#[async_trait]
+impl LightningClient for BudgetedClient {
+    fn get_info(&self) -> &NodeInfo {
+        // Read when the client connected
+        self.inner.get_info()
+    }
+
+    async fn get_network(&self) -> Result<Network, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.get_network().await
+    }
+
+    async fn list_channels(&self) -> Result<Vec<ChannelSummary>, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.list_channels().await
+    }
+
+    async fn get_channel_info(
+        &self,
+        channel_id: &ShortChannelID,
+    ) -> Result<ChannelDetails, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.get_channel_info(channel_id).await
+    }
+
+    async fn get_channels_info(
+        &self,
+        channel_ids: &[ShortChannelID],
+    ) -> Result<Vec<Result<ChannelDetails, LightningError>>, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.get_channels_info(channel_ids).await
+    }
+
+    async fn estimate_close_fee(
+        &self,
+        channel_id: &ShortChannelID,
+        target_conf: u32,
+    ) -> Result<CloseFeeEstimate, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.estimate_close_fee(channel_id, target_conf).await
+    }
+
+    async fn get_graph_node(&self, pubkey: &PublicKey) -> Result<GraphNode, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.get_graph_node(pubkey).await
+    }
+
+    async fn describe_graph(&self) -> Result<NetworkGraph, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.describe_graph().await
+    }
+
+    async fn subscribe_graph(&self) -> Result<GraphUpdates, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.subscribe_graph().await
+    }
+
+    async fn get_payment_details(
+        &self,
+        payment_hash: &PaymentHash,
+    ) -> Result<PaymentDetails, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.get_payment_details(payment_hash).await
+    }
+
+    async fn list_payments(&self) -> Result<Vec<PaymentSummary>, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.list_payments().await
+    }
+
+    async fn list_inflight_payments(&self) -> Result<Vec<PaymentSummary>, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.list_inflight_payments().await
+    }
+
+    async fn track_payment<'a>(
+        &'a self,
+        payment_hash: &PaymentHash,
+    ) -> Result<PaymentUpdates<'a>, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.track_payment(payment_hash).await
+    }
+
+    async fn stream_events(
+        &mut self,
+    ) -> Result<Pin<Box<dyn Stream<Item = CapturedEvent> + Send>>, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.stream_events().await
+    }
+
+    async fn list_invoices(&self) -> Result<Vec<CustomInvoice>, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.list_invoices().await
+    }
+
+    async fn get_invoice_details(
+        &self,
+        payment_hash: &PaymentHash,
+    ) -> Result<CustomInvoice, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.get_invoice_details(payment_hash).await
+    }
+
+    async fn get_wallet_balance(&self) -> Result<u64, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.get_wallet_balance().await
+    }
+
+    async fn get_forwarding_fees(&self, since: u64) -> Result<u64, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.get_forwarding_fees(since).await
+    }
+
+    async fn list_forwards(&self, since: u64) -> Result<Vec<ForwardSummary>, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.list_forwards(since).await
+    }
+
+    async fn set_channel_fee_rate(
+        &self,
+        channel_id: &ShortChannelID,
+        fee_rate_ppm: u32,
+    ) -> Result<(), LightningError> {
+        self.budget.acquire().await?;
+        self.inner
+            .set_channel_fee_rate(channel_id, fee_rate_ppm)
+            .await
+    }
+
+    async fn list_pending_htlcs(&self) -> Result<PendingHtlcs, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.list_pending_htlcs().await
+    }
+
+    async fn list_channel_breaches(&self) -> Result<Vec<ChannelBreach>, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.list_channel_breaches().await
+    }
+
+    async fn list_liquidity_ads(&self) -> Result<Vec<LiquidityAd>, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.list_liquidity_ads().await
+    }
+
+    async fn open_dual_funded_channel(
+        &self,
+        request: &DualFundRequest,
+    ) -> Result<OpenedChannel, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.open_dual_funded_channel(request).await
+    }
+
+    async fn list_peers(&self) -> Result<Vec<PeerSummary>, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.list_peers().await
+    }
+
+    async fn ping_peer(&self, pubkey: &PublicKey) -> Result<Duration, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.ping_peer(pubkey).await
+    }
+
+    async fn list_transaction_labels(&self) -> Result<Vec<TransactionLabel>, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.list_transaction_labels().await
+    }
+
+    async fn label_transaction(&self, txid: &Txid, label: &str) -> Result<(), LightningError> {
+        self.budget.acquire().await?;
+        self.inner.label_transaction(txid, label).await
+    }
+
+    async fn sign_message(&self, message: &str) -> Result<String, LightningError> {
+        self.budget.acquire().await?;
+        self.inner.sign_message(message).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queues_calls_beyond_the_rate_and_sheds_past_the_queue() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2, start);
+
+        // A second's worth passes right away
+        assert_eq!(bucket.reserve(start, 2), Some(Duration::ZERO));
+        assert_eq!(bucket.reserve(start, 2), Some(Duration::ZERO));
+        // The next ones wait for the refill, in order
+        assert_eq!(bucket.reserve(start, 2), Some(Duration::from_millis(500)));
+        assert_eq!(bucket.reserve(start, 2), Some(Duration::from_secs(1)));
+        assert_eq!(bucket.reserve(start, 2), None);
+
+        // Half a second later the first waiting call had its turn
+        let later = start + Duration::from_millis(500);
+        assert_eq!(bucket.reserve(later, 2), Some(Duration::from_secs(1)));
+        assert_eq!(bucket.reserve(later, 2), None);
+    }
+
+    #[test]
+    fn refills_up_to_a_second_of_calls() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(3, start);
+        let idle = start + Duration::from_secs(60);
+
+        for _ in 0..3 {
+            assert_eq!(bucket.reserve(idle, 0), Some(Duration::ZERO));
+        }
+        assert_eq!(bucket.reserve(idle, 0), None);
+    }
+}
@@ -0,0 +1,109 @@
+//! Matching for the account-wide search box.
+//!
+//! Channels, payments and invoices come from the selected node and are matched in
+//! memory; events are matched in SQL by the event repository.
+
+use crate::database::models::EventResponse;
+use crate::utils::{ChannelSummary, CustomInvoice, PaymentSummary};
+use serde::Serialize;
+
+/// Search results grouped by what they are.
+#[derive(Debug, Serialize)]
+pub struct SearchResults {
+    pub query: String,
+    pub channels: Vec<ChannelSummary>,
+    pub payments: Vec<PaymentSummary>,
+    pub invoices: Vec<CustomInvoice>,
+    pub events: Vec<EventResponse>,
+}
+
+/// Whether a channel matches by short channel id (numeric or `BLOCKxTXxOUTPUT`),
+/// peer alias or peer pubkey prefix.
+pub fn channel_matches(channel: &ChannelSummary, query: &str) -> bool {
+    let query = query.to_lowercase();
+
+    channel.chan_id.to_string() == query
+        || channel.chan_id.to_block_format() == query
+        || channel
+            .alias
+            .as_ref()
+            .is_some_and(|alias| alias.to_lowercase().contains(&query))
+        || channel
+            .remote_pubkey
+            .is_some_and(|pubkey| pubkey.to_string().starts_with(&query))
+}
+
+/// Whether a payment's hash starts with the query.
+pub fn payment_matches(payment: &PaymentSummary, query: &str) -> bool {
+    payment.payment_hash.starts_with(&query.to_lowercase())
+}
+
+/// Whether an invoice matches by payment hash prefix or memo.
+pub fn invoice_matches(invoice: &CustomInvoice, query: &str) -> bool {
+    let query = query.to_lowercase();
+
+    invoice.payment_hash.starts_with(&query) || invoice.memo.to_lowercase().contains(&query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::amount::Amount;
+    use crate::utils::{ChannelState, InvoiceStatus, ShortChannelID};
+    use bitcoin::secp256k1::PublicKey;
+    use std::str::FromStr;
+
+    const PUBKEY: &str = "02eec7245d6b7d2ccb30380bfbe2a3648cd7a942653f5aa340edcea1f283686619";
+
+    fn channel() -> ChannelSummary {
+        ChannelSummary {
+            chan_id: ShortChannelID::from_str("800000x1x0").unwrap(),
+            alias: Some("ACINQ".to_string()),
+            channel_state: ChannelState::Active,
+            private: false,
+            remote_balance: Amount::ZERO,
+            local_balance: Amount::ZERO,
+            capacity: Amount::from_sat(1_000_000),
+            last_update: None,
+            uptime: None,
+            remote_pubkey: Some(PublicKey::from_str(PUBKEY).unwrap()),
+            channel_point: None,
+        }
+    }
+
+    #[test]
+    fn matches_channels_by_scid_alias_and_pubkey_prefix() {
+        let channel = channel();
+        assert!(channel_matches(&channel, "800000x1x0"));
+        assert!(channel_matches(&channel, &channel.chan_id.to_string()));
+        assert!(channel_matches(&channel, "acin"));
+        assert!(channel_matches(&channel, "02EEC7245D"));
+        assert!(!channel_matches(&channel, "800000x1"));
+        assert!(!channel_matches(&channel, "eec7245d"));
+    }
+
+    #[test]
+    fn matches_invoices_by_hash_prefix_and_memo() {
+        let invoice = CustomInvoice {
+            memo: "Coffee for Bob".to_string(),
+            payment_hash: "ab12cd".to_string(),
+            payment_preimage: String::new(),
+            value: Amount::ZERO,
+            amount_paid: None,
+            creation_date: None,
+            settle_date: None,
+            payment_request: String::new(),
+            expiry: None,
+            expires_at: None,
+            state: InvoiceStatus::Open,
+            is_keysend: None,
+            is_amp: None,
+            payment_addr: None,
+            htlcs: None,
+            features: None,
+        };
+        assert!(invoice_matches(&invoice, "AB12"));
+        assert!(invoice_matches(&invoice, "coffee"));
+        assert!(!invoice_matches(&invoice, "12cd"));
+    }
+}
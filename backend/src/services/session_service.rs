@@ -0,0 +1,270 @@
+//! Login session business logic service.
+//!
+//! Every login opens a session whose id is carried by the access and refresh
+//! tokens issued for it. `jwt_auth` and token refresh check the session on each
+//! use, so revoking it logs that device out without waiting for the tokens to
+//! expire.
+
+use crate::database::models::UserSession;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::session_repository::SessionRepository;
+use crate::utils::jwt::{Claims, REFRESH_TOKEN_LIFETIME_DAYS};
+use chrono::{Duration, Utc};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+/// How stale `last_activity_at` may get before a request updates it, so busy
+/// sessions don't write on every request.
+const ACTIVITY_RESOLUTION: Duration = Duration::seconds(60);
+
+/// Longest user agent kept for a session.
+const MAX_USER_AGENT_LENGTH: usize = 255;
+
+/// A session as listed to its user.
+#[derive(Debug, Serialize)]
+pub struct SessionInfo {
+    #[serde(flatten)]
+    pub session: UserSession,
+    /// Whether the request listing the sessions was made with this session
+    pub current: bool,
+}
+
+/// Service layer for login sessions.
+pub struct SessionService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> SessionService<'a> {
+    /// Creates a new SessionService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Opens a session for a user who just logged in.
+    pub async fn create_session(
+        &self,
+        user_id: &str,
+        account_id: &str,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+    ) -> ServiceResult<UserSession> {
+        let now = Utc::now();
+        let session = UserSession {
+            id: Uuid::now_v7().to_string(),
+            user_id: user_id.to_string(),
+            account_id: account_id.to_string(),
+            user_agent: user_agent.map(|agent| agent.chars().take(MAX_USER_AGENT_LENGTH).collect()),
+            ip_address,
+            created_at: now,
+            last_activity_at: now,
+            expires_at: now + Duration::days(REFRESH_TOKEN_LIFETIME_DAYS),
+            revoked_at: None,
+        };
+        SessionRepository::new(self.pool)
+            .create_session(&session)
+            .await?;
+
+        Ok(session)
+    }
+
+    /// Checks that the session a token was issued for is still active, and
+    /// records the activity.
+    ///
+    /// Tokens issued before sessions were tracked carry no session id and are
+    /// treated as revoked.
+    pub async fn is_session_active(&self, claims: &Claims) -> ServiceResult<bool> {
+        let Some(session_id) = &claims.sid else {
+            return Ok(false);
+        };
+
+        let repo = SessionRepository::new(self.pool);
+        let Some(session) = repo.get_session(session_id).await? else {
+            return Ok(false);
+        };
+
+        let now = Utc::now();
+        if session.user_id != claims.sub
+            || session.revoked_at.is_some()
+            || session.expires_at <= now
+        {
+            return Ok(false);
+        }
+
+        if now - session.last_activity_at >= ACTIVITY_RESOLUTION {
+            let touched = repo.touch_session(&session.id, now).await;
+            if let Err(e) = touched {
+                tracing::warn!("Failed to record activity of session {}: {}", session.id, e);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Lists the caller's active sessions, flagging the one the request used.
+    pub async fn list_sessions(&self, claims: &Claims) -> ServiceResult<Vec<SessionInfo>> {
+        let sessions = SessionRepository::new(self.pool)
+            .get_active_sessions_by_user_id(&claims.sub, Utc::now())
+            .await?;
+
+        Ok(sessions
+            .into_iter()
+            .map(|session| SessionInfo {
+                current: claims.sid.as_deref() == Some(session.id.as_str()),
+                session,
+            })
+            .collect())
+    }
+
+    /// Revokes one of the user's sessions.
+    pub async fn revoke_session(&self, user_id: &str, session_id: &str) -> ServiceResult<()> {
+        if !SessionRepository::new(self.pool)
+            .revoke_session(user_id, session_id, Utc::now())
+            .await?
+        {
+            return Err(ServiceError::not_found("Session", session_id));
+        }
+        Ok(())
+    }
+
+    /// Revokes every session of the user, returning how many were revoked.
+    pub async fn revoke_all_sessions(&self, user_id: &str) -> ServiceResult<u64> {
+        Ok(SessionRepository::new(self.pool)
+            .revoke_sessions_by_user_id(user_id, Utc::now())
+            .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::middleware::jwt_auth;
+    use crate::auth::models::RefreshTokenRequest;
+    use crate::auth::service::AuthService;
+    use crate::config::{self, Config};
+    use crate::database::models::RoleAccessLevel;
+    use crate::utils::jwt::JwtUtils;
+    use axum::http::{Request, StatusCode, header::AUTHORIZATION};
+    use axum::{Extension, Router, body::Body, middleware, routing::get};
+    use tower::ServiceExt;
+
+    async fn logged_in_user() -> (SqlitePool, UserSession) {
+        config::install(
+            Config::from_vars(&|name| match name {
+                "DATABASE_URL" => Some("sqlite::memory:".to_string()),
+                "JWT_SECRET" => Some("session-test-secret".to_string()),
+                _ => None,
+            })
+            .unwrap(),
+        );
+        let pool = crate::database::test_pool().await;
+        sqlx::query(
+            r#"
+            INSERT INTO accounts (id, name) VALUES ('account', 'Account');
+            INSERT INTO users (id, account_id, username, password_hash, email, role_id)
+            VALUES ('user', 'account', 'user', '', 'user@example.com',
+                    '01932f4e-8b2b-7a3c-9d5f-2a3b4c5d6e7f');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let session = SessionService::new(&pool)
+            .create_session("user", "account", None, None)
+            .await
+            .unwrap();
+        (pool, session)
+    }
+
+    fn access_token(session_id: Option<String>) -> String {
+        JwtUtils::new()
+            .unwrap()
+            .generate_token(
+                "user".to_string(),
+                "account".to_string(),
+                "Member".to_string(),
+                RoleAccessLevel::Read,
+                None,
+                session_id,
+            )
+            .unwrap()
+    }
+
+    async fn authenticate(pool: &SqlitePool, token: &str) -> StatusCode {
+        Router::new()
+            .route("/", get(|| async { "authenticated" }))
+            .layer(middleware::from_fn(jwt_auth))
+            .layer(Extension(pool.clone()))
+            .oneshot(
+                Request::get("/")
+                    .header(AUTHORIZATION, format!("Bearer {token}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn rejects_tokens_of_revoked_sessions() {
+        let (pool, session) = logged_in_user().await;
+        let token = access_token(Some(session.id.clone()));
+        assert_eq!(authenticate(&pool, &token).await, StatusCode::OK);
+
+        SessionService::new(&pool)
+            .revoke_session("user", &session.id)
+            .await
+            .unwrap();
+        assert_eq!(authenticate(&pool, &token).await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn rejects_tokens_without_a_session() {
+        let (pool, _) = logged_in_user().await;
+        assert_eq!(
+            authenticate(&pool, &access_token(None)).await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[tokio::test]
+    async fn refreshed_tokens_keep_their_session() {
+        let (pool, session) = logged_in_user().await;
+        let jwt_utils = JwtUtils::new().unwrap();
+        let refresh_token = jwt_utils
+            .generate_refresh_token(
+                "user".to_string(),
+                RoleAccessLevel::Read,
+                session.id.clone(),
+            )
+            .unwrap();
+        let auth_service = AuthService::new(&pool).unwrap();
+
+        let refreshed = auth_service
+            .refresh_token(RefreshTokenRequest {
+                refresh_token: refresh_token.clone(),
+            })
+            .await
+            .unwrap();
+        let claims = jwt_utils.validate_token(&refreshed.access_token).unwrap();
+        assert_eq!(claims.sid, Some(session.id.clone()));
+
+        // Revoking the session also stops its refresh token
+        SessionService::new(&pool)
+            .revoke_session("user", &session.id)
+            .await
+            .unwrap();
+        assert!(
+            auth_service
+                .refresh_token(RefreshTokenRequest { refresh_token })
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            authenticate(&pool, &refreshed.access_token).await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
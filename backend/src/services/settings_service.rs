@@ -0,0 +1,212 @@
+//! Account settings business logic service.
+//!
+//! Settings are validated through the typed `AccountSettings` model and stored
+//! one top-level field per row, so adding a field needs no migration. Fields an
+//! account never saved take their default value.
+
+use crate::database::models::AccountSettings;
+use crate::errors::{ServiceError, ServiceResult};
+use crate::repositories::event_repository::EventRepository;
+use crate::repositories::settings_repository::SettingsRepository;
+use crate::services::task_supervisor;
+use chrono::{Duration, Utc};
+use serde_json::{Map, Value};
+use sqlx::SqlitePool;
+use validator::Validate;
+
+/// How often events past their account's retention are purged.
+const RETENTION_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+/// Top-level fields of the settings, keyed by their stored name.
+fn settings_fields(settings: &AccountSettings) -> Map<String, Value> {
+    match serde_json::to_value(settings) {
+        Ok(Value::Object(fields)) => fields,
+        _ => Map::new(),
+    }
+}
+
+/// Overlays stored `(key, value)` pairs on the default settings.
+///
+/// Keys the model no longer knows are ignored.
+fn merge_settings(stored: Vec<(String, String)>) -> ServiceResult<AccountSettings> {
+    let mut fields = settings_fields(&AccountSettings::default());
+
+    for (key, value) in stored {
+        if !fields.contains_key(&key) {
+            continue;
+        }
+        match serde_json::from_str(&value) {
+            Ok(value) => {
+                fields.insert(key, value);
+            }
+            Err(e) => tracing::warn!("Ignoring unreadable setting {}: {}", key, e),
+        }
+    }
+
+    serde_json::from_value(Value::Object(fields)).map_err(|e| ServiceError::InternalError {
+        message: format!("Stored settings are invalid: {e}"),
+    })
+}
+
+/// Splits settings into the `(key, value)` rows they are stored as.
+fn settings_rows(settings: &AccountSettings) -> Vec<(String, String)> {
+    settings_fields(settings)
+        .into_iter()
+        .map(|(key, value)| (key, value.to_string()))
+        .collect()
+}
+
+/// Service layer for account settings.
+pub struct SettingsService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> SettingsService<'a> {
+    /// Creates a new SettingsService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Returns the account's settings, with defaults for fields never saved.
+    pub async fn get_settings(&self, account_id: &str) -> ServiceResult<AccountSettings> {
+        let stored = SettingsRepository::new(self.pool)
+            .get_settings(account_id)
+            .await?;
+        merge_settings(stored)
+    }
+
+    /// Validates and stores the account's settings.
+    pub async fn update_settings(
+        &self,
+        account_id: &str,
+        settings: AccountSettings,
+    ) -> ServiceResult<AccountSettings> {
+        settings
+            .validate()
+            .map_err(|e| ServiceError::validation(e.to_string()))?;
+
+        SettingsRepository::new(self.pool)
+            .save_settings(account_id, &settings_rows(&settings))
+            .await?;
+
+        Ok(settings)
+    }
+
+    /// Deletes events older than their account's retention period.
+    ///
+    /// Returns the number of deleted events.
+    pub async fn purge_expired_events(&self) -> ServiceResult<u64> {
+        let retentions = SettingsRepository::new(self.pool)
+            .get_setting_for_all_accounts("event_retention_days")
+            .await?;
+        let repo = EventRepository::new(self.pool);
+
+        let mut purged = 0;
+        for (account_id, value) in retentions {
+            let Ok(Some(days)) = serde_json::from_str::<Option<u32>>(&value) else {
+                continue;
+            };
+            let cutoff = Utc::now() - Duration::days(days.into());
+            purged += repo
+                .purge_account_events_before(&account_id, cutoff)
+                .await?;
+        }
+
+        Ok(purged)
+    }
+}
+
+/// Purges events past their account's retention period every hour.
+pub fn spawn_retention_job(pool: SqlitePool) {
+    task_supervisor::supervise("event_retention", move |task| {
+        let pool = pool.clone();
+        async move {
+            loop {
+                // The first run waits too, giving startup migrations time to finish
+                tokio::time::sleep(RETENTION_INTERVAL).await;
+                let result = SettingsService::new(&pool).purge_expired_events().await;
+                match &result {
+                    Ok(0) => {}
+                    Ok(purged) => tracing::info!("Purged {} events past their retention", purged),
+                    Err(e) => tracing::error!("Event retention purge failed: {}", e),
+                }
+                task.record_run(&result);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::models::{EscalationSettings, EscalationStep};
+
+    #[test]
+    fn stored_fields_override_defaults() {
+        let stored = vec![
+            ("timezone".to_string(), "\"Europe/Berlin\"".to_string()),
+            ("event_retention_days".to_string(), "30".to_string()),
+            ("removed_setting".to_string(), "true".to_string()),
+        ];
+
+        let settings = merge_settings(stored).unwrap();
+        assert_eq!(settings.timezone, "Europe/Berlin");
+        assert_eq!(settings.event_retention_days, Some(30));
+        assert_eq!(settings.default_currency, "USD");
+
+        let rows = settings_rows(&settings);
+        assert_eq!(merge_settings(rows).unwrap(), settings);
+    }
+
+    #[test]
+    fn rejects_unknown_zones_and_currencies() {
+        let settings = AccountSettings {
+            timezone: "Mars/Olympus".to_string(),
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+
+        let settings = AccountSettings {
+            default_currency: "XYZ".to_string(),
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+        assert!(AccountSettings::default().validate().is_ok());
+    }
+
+    #[test]
+    fn escalation_steps_need_one_target_in_delay_order() {
+        let step =
+            |delay_minutes, notification_id: Option<&str>, recipients: &[&str]| EscalationStep {
+                delay_minutes,
+                notification_id: notification_id.map(str::to_string),
+                email_recipients: recipients.iter().map(|r| r.to_string()).collect(),
+            };
+        let settings = |steps| AccountSettings {
+            escalation: EscalationSettings { steps },
+            ..Default::default()
+        };
+
+        let policy = settings(vec![
+            step(0, Some("discord"), &[]),
+            step(10, None, &["ops@example.com"]),
+            step(30, Some("webhook"), &[]),
+        ]);
+        assert!(policy.validate().is_ok());
+        assert!(!policy.escalation.delays_endpoint("discord"));
+        assert!(policy.escalation.delays_endpoint("webhook"));
+
+        assert!(
+            settings(vec![step(10, Some("a"), &[]), step(0, Some("b"), &[])])
+                .validate()
+                .is_err()
+        );
+        assert!(settings(vec![step(0, None, &[])]).validate().is_err());
+        assert!(
+            settings(vec![step(0, Some("a"), &["ops@example.com"])])
+                .validate()
+                .is_err()
+        );
+    }
+}
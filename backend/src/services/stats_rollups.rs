@@ -0,0 +1,547 @@
+//! Hourly rollups behind the payment and event statistics.
+//!
+//! Computing statistics from a node's full payment list or from every event in
+//! the window gets slow on busy accounts, so a background job folds payment
+//! volume, fee revenue and event counts into one row per node and UTC hour
+//! every five minutes. The statistics endpoints sum those rows into hour, day
+//! or week buckets of the request's time zone, refreshing rollups that are more
+//! than ten minutes old first. A node that can't be reached then has its last
+//! rollups served instead.
+//!
+//! Payment rollups are recomputed from a day before the last refresh on, which
+//! picks up payments settling late. Event rollups are recounted from the
+//! earliest event added or deleted since the last refresh. Hours whose events
+//! retention has purged keep their counts.
+//!
+//! Rollups only count for hours wholly within the window and one bucket. The
+//! hours the window starts and ends within, and those a bucket starts within
+//! in time zones offset by a half or three quarter hour, are counted from the
+//! stored payment records and events instead, read for just those spans.
+//! Payment records are replaced along with the rollups, so the node is only
+//! asked when the rollups are stale. Compacted events, which no longer have an
+//! exact time, count there in the bucket their hour starts in.
+
+use crate::database::models::{Credential, PaymentHourlyRollup, PaymentRecord, PaymentRecordKind};
+use crate::errors::{LightningError, ServiceError, ServiceResult};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::repositories::event_repository::EventRepository;
+use crate::repositories::stats_rollup_repository::{
+    EVENT_ROLLUPS, PAYMENT_ROLLUPS, StatsRollupRepository,
+};
+use crate::services::event_stats::{EventStats, rollup_event_stats};
+use crate::services::node_manager::LightningClient;
+use crate::services::node_manager::connect_node;
+use crate::services::payment_stats::{
+    PaymentFlowStats, PaymentStats, StatsBucket, rollup_payment_stats,
+};
+use crate::services::task_supervisor;
+use crate::utils::time_zone::LocalBuckets;
+use crate::utils::{ForwardSummary, PaymentState, PaymentSummary, PaymentType};
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use chrono_tz::Tz;
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, HashSet};
+
+/// How often the background job refreshes every rollup.
+const REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// Age past which the statistics endpoints refresh rollups before reading them.
+const MAX_ROLLUP_AGE: Duration = Duration::minutes(10);
+
+/// How far before the last refresh payment rollups are recomputed.
+const PAYMENT_LOOKBACK: Duration = Duration::days(1);
+
+/// Start of the UTC hour `at` falls in.
+pub fn hour_start(at: DateTime<Utc>) -> DateTime<Utc> {
+    at.duration_trunc(Duration::hours(1)).unwrap_or(at)
+}
+
+/// Whether rollups last refreshed at `refreshed_at`, if ever, should be
+/// refreshed before reading them.
+pub fn is_stale(refreshed_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+    refreshed_at.is_none_or(|refreshed_at| now - refreshed_at > MAX_ROLLUP_AGE)
+}
+
+/// Hour from which payment rollups last refreshed at `refreshed_at` are
+/// recomputed, all of them when never refreshed.
+pub fn payment_refresh_since(refreshed_at: Option<DateTime<Utc>>) -> DateTime<Utc> {
+    refreshed_at.map_or(DateTime::UNIX_EPOCH, |refreshed_at| {
+        hour_start(refreshed_at - PAYMENT_LOOKBACK)
+    })
+}
+
+/// Records a node's payments and forwards from the hour `since` on that the
+/// statistics count, oldest first.
+///
+/// Payments are placed at their completion time, or creation time when they
+/// never completed.
+pub fn payment_records(
+    node_id: &str,
+    payments: &[PaymentSummary],
+    forwards: &[ForwardSummary],
+    since: DateTime<Utc>,
+) -> Vec<PaymentRecord> {
+    let at = |timestamp: u64| {
+        DateTime::from_timestamp(i64::try_from(timestamp).ok()?, 0).filter(|at| *at >= since)
+    };
+    let mut records = Vec::new();
+
+    for payment in payments {
+        let kind = match (payment.state, &payment.payment_type) {
+            (PaymentState::Settled, PaymentType::Outgoing) => PaymentRecordKind::Outgoing,
+            (PaymentState::Settled, PaymentType::Incoming) => PaymentRecordKind::Incoming,
+            (PaymentState::Settled, PaymentType::Forwarded) => PaymentRecordKind::Forwarded,
+            (PaymentState::Failed, PaymentType::Outgoing) => PaymentRecordKind::FailedOutgoing,
+            _ => continue,
+        };
+        if let Some(at) = payment.completed_at.or(payment.creation_time).and_then(at) {
+            records.push(PaymentRecord {
+                node_id: node_id.to_string(),
+                at,
+                kind,
+                amount_sat: payment.amount.sat() as i64,
+                fee_msat: 0,
+            });
+        }
+    }
+    for forward in forwards {
+        if let Some(at) = at(forward.resolved_at) {
+            records.push(PaymentRecord {
+                node_id: node_id.to_string(),
+                at,
+                kind: PaymentRecordKind::Forwarded,
+                amount_sat: (forward.amount_out_msat / 1000) as i64,
+                fee_msat: forward.fee_msat as i64,
+            });
+        }
+    }
+
+    records.sort_by_key(|record| record.at);
+    records
+}
+
+/// Folds a node's payment records into hourly rollups, oldest first.
+pub fn payment_rollups(node_id: &str, records: &[PaymentRecord]) -> Vec<PaymentHourlyRollup> {
+    let mut hours: BTreeMap<DateTime<Utc>, PaymentFlowStats> = BTreeMap::new();
+    for record in records {
+        hours
+            .entry(hour_start(record.at))
+            .or_default()
+            .add_record(record);
+    }
+
+    hours
+        .into_iter()
+        .map(|(hour, stats)| PaymentHourlyRollup {
+            node_id: node_id.to_string(),
+            hour,
+            outgoing_count: stats.outgoing.count as i64,
+            outgoing_sat: stats.outgoing.volume_sat as i64,
+            incoming_count: stats.incoming.count as i64,
+            incoming_sat: stats.incoming.volume_sat as i64,
+            forwarded_count: stats.forwarded.count as i64,
+            forwarded_sat: stats.forwarded.volume_sat as i64,
+            failed_outgoing: stats.failed_outgoing as i64,
+            fee_revenue_msat: stats.fee_revenue_msat as i64,
+        })
+        .collect()
+}
+
+/// Lists a node's payments, and its forwards from `since` on.
+pub async fn fetch_payments(
+    client: &(dyn LightningClient + Send + Sync),
+    since: DateTime<Utc>,
+) -> ServiceResult<(Vec<PaymentSummary>, Vec<ForwardSummary>)> {
+    tokio::try_join!(
+        client.list_payments(),
+        client.list_forwards(since.timestamp().max(0) as u64),
+    )
+    .map_err(|e: LightningError| ServiceError::ExternalService {
+        message: e.to_string(),
+    })
+}
+
+/// Service maintaining and reading statistics rollups.
+pub struct StatsRollupService<'a> {
+    /// Shared database connection pool
+    pool: &'a SqlitePool,
+}
+
+impl<'a> StatsRollupService<'a> {
+    /// Creates a new StatsRollupService instance.
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// When a node's payment rollups were last refreshed, `None` if never.
+    pub async fn payment_refreshed_at(
+        &self,
+        account_id: &str,
+        node_id: &str,
+    ) -> ServiceResult<Option<DateTime<Utc>>> {
+        Ok(StatsRollupRepository::new(self.pool)
+            .get_refreshed_at(account_id, PAYMENT_ROLLUPS, node_id)
+            .await?)
+    }
+
+    /// Refreshes a node's payment rollups when they are stale, calling
+    /// `connect` for the node's client only then.
+    ///
+    /// A failed refresh is logged and the last rollups are served, unless the
+    /// node has none yet.
+    pub async fn refresh_stale_payment_rollups<'c, E>(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        connect: impl AsyncFnOnce() -> Result<&'c (dyn LightningClient + Send + Sync), E>,
+    ) -> Result<(), E>
+    where
+        E: From<ServiceError> + std::fmt::Debug,
+    {
+        let refreshed_at = self.payment_refreshed_at(account_id, node_id).await?;
+        if !is_stale(refreshed_at, Utc::now()) {
+            return Ok(());
+        }
+
+        let refreshed = match connect().await {
+            Ok(client) => self
+                .refresh_payment_rollups(account_id, node_id, client)
+                .await
+                .map_err(E::from),
+            Err(e) => Err(e),
+        };
+        match (refreshed, refreshed_at) {
+            (Ok(()), _) => Ok(()),
+            (Err(e), None) => Err(e),
+            (Err(e), Some(refreshed_at)) => {
+                tracing::warn!(
+                    "Serving payment rollups of node {} from {}: {:?}",
+                    node_id,
+                    refreshed_at,
+                    e
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Recomputes a node's payment rollups with what `client` reports.
+    pub async fn refresh_payment_rollups(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        client: &(dyn LightningClient + Send + Sync),
+    ) -> ServiceResult<()> {
+        let refreshed_at = Utc::now();
+        let since = payment_refresh_since(self.payment_refreshed_at(account_id, node_id).await?);
+        let (payments, forwards) = fetch_payments(client, since).await?;
+
+        self.store_payment_rollups(
+            account_id,
+            node_id,
+            since,
+            &payments,
+            &forwards,
+            refreshed_at,
+        )
+        .await
+    }
+
+    /// Replaces a node's payment rollups and records from the hour `since` on
+    /// with those of payments and forwards fetched at `refreshed_at`.
+    pub async fn store_payment_rollups(
+        &self,
+        account_id: &str,
+        node_id: &str,
+        since: DateTime<Utc>,
+        payments: &[PaymentSummary],
+        forwards: &[ForwardSummary],
+        refreshed_at: DateTime<Utc>,
+    ) -> ServiceResult<()> {
+        let records = payment_records(node_id, payments, forwards, since);
+        let rollups = payment_rollups(node_id, &records);
+        StatsRollupRepository::new(self.pool)
+            .replace_payment_rollups(account_id, node_id, since, &rollups, &records, refreshed_at)
+            .await?;
+        Ok(())
+    }
+
+    /// Sums the payment rollups of some of an account's nodes between `start`
+    /// and `end` into buckets aligned in `tz`.
+    ///
+    /// The hours rollups can't be split into those buckets are counted from
+    /// the nodes' payment records, see `rollup_payment_stats`.
+    pub async fn get_payment_stats(
+        &self,
+        account_id: &str,
+        node_ids: &[String],
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        bucket: StatsBucket,
+        tz: Tz,
+    ) -> ServiceResult<PaymentStats> {
+        let repository = StatsRollupRepository::new(self.pool);
+        let rollups = repository
+            .get_payment_rollups(account_id, node_ids, hour_start(start), end)
+            .await?;
+
+        let mut records = Vec::new();
+        for (from, to) in LocalBuckets::new(start, end, bucket.seconds(), tz).split_hours() {
+            records.extend(
+                repository
+                    .get_payment_records(account_id, node_ids, from, to)
+                    .await?,
+            );
+        }
+        Ok(rollup_payment_stats(
+            &rollups, &records, start, end, bucket, tz,
+        ))
+    }
+
+    /// Recounts an account's event rollups from the earliest event added or
+    /// deleted since the last refresh.
+    pub async fn refresh_event_rollups(&self, account_id: &str) -> ServiceResult<()> {
+        let repository = StatsRollupRepository::new(self.pool);
+        let refreshed_at = Utc::now();
+        let since = match repository
+            .get_refreshed_at(account_id, EVENT_ROLLUPS, "")
+            .await?
+        {
+            Some(last) => repository
+                .earliest_event_changed_since(account_id, last)
+                .await?
+                .map_or(refreshed_at, |earliest| earliest.min(refreshed_at)),
+            None => DateTime::UNIX_EPOCH,
+        };
+
+        repository
+            .refresh_event_rollups(account_id, hour_start(since), refreshed_at)
+            .await?;
+        Ok(())
+    }
+
+    /// Counts an account's events between `start` and `end` by severity, in
+    /// buckets aligned to `tz`, refreshing its event rollups first when stale.
+    pub async fn get_event_stats(
+        &self,
+        account_id: &str,
+        node_ids: Option<&[String]>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        bucket: StatsBucket,
+        tz: Tz,
+    ) -> ServiceResult<EventStats> {
+        let repository = StatsRollupRepository::new(self.pool);
+        let refreshed_at = repository
+            .get_refreshed_at(account_id, EVENT_ROLLUPS, "")
+            .await?;
+        if is_stale(refreshed_at, Utc::now()) {
+            self.refresh_event_rollups(account_id).await?;
+        }
+
+        let rollups = repository
+            .get_event_rollups(account_id, node_ids, hour_start(start), end)
+            .await?;
+
+        let events = EventRepository::new(self.pool);
+        let (mut raw_events, mut aggregates) = (Vec::new(), Vec::new());
+        for (from, to) in LocalBuckets::new(start, end, bucket.seconds(), tz).split_hours() {
+            raw_events.extend(
+                events
+                    .get_event_severities_between(account_id, node_ids, from, to)
+                    .await?,
+            );
+            aggregates.extend(
+                events
+                    .get_hourly_aggregates_between(account_id, node_ids, from, to)
+                    .await?,
+            );
+        }
+        Ok(rollup_event_stats(
+            &rollups,
+            &raw_events,
+            &aggregates,
+            start,
+            end,
+            bucket,
+            tz,
+        ))
+    }
+
+    /// Refreshes the rollups of every active node and of the accounts they
+    /// belong to.
+    ///
+    /// Returns the number of nodes refreshed; a node that can't be reached is
+    /// logged and retried on the next run.
+    pub async fn refresh_all(&self) -> ServiceResult<usize> {
+        let credentials = CredentialRepository::new(self.pool)
+            .get_node_credentials(None, None)
+            .await?;
+        let mut accounts = HashSet::new();
+        let mut refreshed = 0;
+
+        for credential in &credentials {
+            if accounts.insert(&credential.account_id) {
+                self.refresh_event_rollups(&credential.account_id).await?;
+            }
+            match self.refresh_node(credential).await {
+                Ok(()) => refreshed += 1,
+                Err(e) => tracing::warn!(
+                    "Refreshing the payment rollups of node {} failed: {}",
+                    credential.node_id,
+                    e
+                ),
+            }
+        }
+
+        Ok(refreshed)
+    }
+
+    async fn refresh_node(&self, credential: &Credential) -> ServiceResult<()> {
+        let client = connect_node(credential)
+            .await
+            .map_err(|e| ServiceError::ExternalService {
+                message: e.to_string(),
+            })?;
+        self.refresh_payment_rollups(&credential.account_id, &credential.node_id, &*client)
+            .await
+    }
+}
+
+/// Refreshes the statistics rollups every five minutes.
+pub fn spawn_stats_rollup_refresher(pool: SqlitePool) {
+    task_supervisor::supervise("stats_rollups", move |task| {
+        let pool = pool.clone();
+        async move {
+            // The first refresh waits for startup migrations
+            let mut interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + REFRESH_INTERVAL,
+                REFRESH_INTERVAL,
+            );
+            loop {
+                interval.tick().await;
+                let result = StatsRollupService::new(&pool).refresh_all().await;
+                if let Err(e) = &result {
+                    tracing::error!("Statistics rollup refresh failed: {}", e);
+                }
+                task.record_run(&result);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::amount::Amount;
+    use crate::utils::{PaymentState, PaymentType};
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn payment(state: PaymentState, sat: u64, at: &str) -> PaymentSummary {
+        PaymentSummary {
+            state,
+            payment_type: PaymentType::Outgoing,
+            amount: Amount::from_sat(sat),
+            amount_usd: Some(0.0),
+            routing_fee: None,
+            creation_time: Some(utc(at).timestamp() as u64),
+            invoice: None,
+            payment_hash: String::new(),
+            completed_at: None,
+        }
+    }
+
+    #[test]
+    fn folds_payments_and_forwards_into_utc_hours() {
+        let payments = vec![
+            payment(PaymentState::Settled, 1_000, "2025-09-12T09:59:00Z"),
+            payment(PaymentState::Settled, 2_000, "2025-09-12T10:05:00Z"),
+            payment(PaymentState::Failed, 500, "2025-09-12T10:40:00Z"),
+            // Before the refresh window
+            payment(PaymentState::Settled, 9_000, "2025-09-12T08:30:00Z"),
+        ];
+        let forwards = vec![ForwardSummary {
+            chan_id_in: None,
+            chan_id_out: None,
+            amount_in_msat: 3_001_000,
+            amount_out_msat: 3_000_000,
+            fee_msat: 1_000,
+            resolved_at: utc("2025-09-12T10:20:00Z").timestamp() as u64,
+        }];
+
+        let records = payment_records(
+            "node",
+            &payments,
+            &forwards,
+            payment_refresh_since(Some(utc("2025-09-13T09:10:00Z"))),
+        );
+        let rollups = payment_rollups("node", &records);
+
+        assert_eq!(rollups.len(), 2);
+        assert_eq!(rollups[0].hour, utc("2025-09-12T09:00:00Z"));
+        assert_eq!(rollups[0].outgoing_sat, 1_000);
+        assert_eq!(rollups[1].outgoing_count, 1);
+        assert_eq!(rollups[1].failed_outgoing, 1);
+        assert_eq!(rollups[1].forwarded_sat, 3_000);
+        assert_eq!(rollups[1].fee_revenue_msat, 1_000);
+
+        let now = utc("2025-09-13T09:10:00Z");
+        assert!(is_stale(None, now));
+        assert!(!is_stale(Some(now - Duration::minutes(10)), now));
+        assert!(is_stale(Some(now - Duration::minutes(11)), now));
+    }
+
+    #[tokio::test]
+    async fn serves_fresh_rollups_without_the_node() {
+        let pool = crate::database::test_pool().await;
+        sqlx::query("INSERT INTO accounts (id, name) VALUES ('account', 'Account')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let service = StatsRollupService::new(&pool);
+        let now = Utc::now();
+        let payments = vec![
+            payment(PaymentState::Settled, 1_000, "2025-09-12T09:10:00Z"),
+            payment(PaymentState::Settled, 2_000, "2025-09-12T09:50:00Z"),
+            payment(PaymentState::Settled, 4_000, "2025-09-12T10:30:00Z"),
+        ];
+        service
+            .store_payment_rollups("account", "node", DateTime::UNIX_EPOCH, &payments, &[], now)
+            .await
+            .unwrap();
+
+        let unreachable = async || -> Result<&(dyn LightningClient + Send + Sync), ServiceError> {
+            panic!("fresh rollups are served without asking the node")
+        };
+        service
+            .refresh_stale_payment_rollups("account", "node", unreachable)
+            .await
+            .unwrap();
+
+        let nodes = ["node".to_string()];
+        let stats = |start: &str, end: &str| {
+            service.get_payment_stats(
+                "account",
+                &nodes,
+                utc(start),
+                utc(end),
+                StatsBucket::Hour,
+                chrono_tz::UTC,
+            )
+        };
+        // Whole hours come from the rollups alone
+        let aligned = stats("2025-09-12T09:00:00Z", "2025-09-12T10:59:59Z")
+            .await
+            .unwrap();
+        assert_eq!(aligned.totals.outgoing.volume_sat, 7_000);
+        // The hour the window starts within is counted from the records
+        let unaligned = stats("2025-09-12T09:30:00Z", "2025-09-12T10:59:59Z")
+            .await
+            .unwrap();
+        assert_eq!(unaligned.buckets[0].stats.outgoing.volume_sat, 2_000);
+        assert_eq!(unaligned.totals.outgoing.volume_sat, 6_000);
+    }
+}
@@ -0,0 +1,343 @@
+//! Warnings about outgoing payments stuck in flight.
+//!
+//! A payment stays in flight while any of its HTLCs is unresolved somewhere
+//! along its route, which can take until the HTLCs expire, hours or days later.
+//! Neither LND nor CLN can abandon a payment whose HTLCs are out, so such
+//! payments can only be reported. Every ten minutes the in-flight payments of
+//! each node are listed and those sent longer than the account's
+//! `alert_thresholds.stuck_payment_minutes` ago raise a `PaymentStuck` warning,
+//! once per payment. With `track_stuck_payments` on, the outcome of each
+//! reported payment is looked up once it leaves the flight and raised as an
+//! informational `PaymentStuck` event. Accounts without a threshold are not
+//! checked; `GET /api/aggregate/payments/stuck` lists the stuck payments of
+//! any account's nodes.
+
+use crate::database::models::{CreateEvent, Credential, EventSeverity, EventType};
+use crate::errors::{LightningError, ServiceError, ServiceResult};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::services::event_service::EventService;
+use crate::services::node_manager::LightningClient;
+use crate::services::node_manager::connect_node;
+use crate::services::settings_service::SettingsService;
+use crate::services::task_supervisor;
+use crate::utils::{PaymentState, PaymentSummary, PaymentType};
+use chrono::{DateTime, Utc};
+use lightning::ln::PaymentHash;
+use serde::Serialize;
+use serde_json::json;
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(600);
+/// Age after which a payment is listed as stuck when neither the request nor
+/// the account says.
+pub const DEFAULT_STUCK_PAYMENT_MINUTES: u32 = 60;
+
+/// An outgoing payment in flight for longer than a threshold.
+#[derive(Debug, Clone, Serialize)]
+pub struct StuckPayment {
+    pub node_id: String,
+    pub node_alias: String,
+    #[serde(flatten)]
+    pub payment: PaymentSummary,
+    /// Minutes since the payment was sent
+    pub stuck_minutes: i64,
+}
+
+/// Identifies a payment already reported as stuck: account, node and payment hash.
+type AlertKey = (String, String, String);
+
+/// Minutes an outgoing payment has been in flight at `now`, if at least
+/// `min_minutes`. Payments without a creation time are never stuck.
+pub fn stuck_minutes(
+    payment: &PaymentSummary,
+    min_minutes: u32,
+    now: DateTime<Utc>,
+) -> Option<i64> {
+    if payment.state != PaymentState::Inflight
+        || !matches!(payment.payment_type, PaymentType::Outgoing)
+    {
+        return None;
+    }
+    let created = DateTime::from_timestamp(payment.creation_time? as i64, 0)?;
+    let minutes = (now - created).num_minutes();
+    (minutes >= i64::from(min_minutes)).then_some(minutes)
+}
+
+/// Picks a node's payments in flight for `min_minutes` or longer, longest first.
+pub fn stuck_payments(
+    credential: &Credential,
+    payments: Vec<PaymentSummary>,
+    min_minutes: u32,
+    now: DateTime<Utc>,
+) -> Vec<StuckPayment> {
+    let mut stuck: Vec<StuckPayment> = payments
+        .into_iter()
+        .filter_map(|payment| {
+            let stuck_minutes = stuck_minutes(&payment, min_minutes, now)?;
+            Some(StuckPayment {
+                node_id: credential.node_id.clone(),
+                node_alias: credential.node_alias.clone(),
+                payment,
+                stuck_minutes,
+            })
+        })
+        .collect();
+    stuck.sort_by_key(|stuck| std::cmp::Reverse(stuck.stuck_minutes));
+    stuck
+}
+
+/// Checks every node of the accounts with a stuck payment threshold.
+///
+/// `alerted` holds the payments already reported and is pruned to those still stuck.
+pub async fn check_all_nodes(
+    pool: &SqlitePool,
+    alerted: &mut HashSet<AlertKey>,
+) -> ServiceResult<()> {
+    let credentials = CredentialRepository::new(pool)
+        .get_node_credentials(None, None)
+        .await?;
+    let settings = SettingsService::new(pool);
+    let mut thresholds: HashMap<String, (Option<u32>, bool)> = HashMap::new();
+    let mut still_stuck = HashSet::new();
+
+    for credential in &credentials {
+        if !thresholds.contains_key(&credential.account_id) {
+            let account_settings = settings.get_settings(&credential.account_id).await?;
+            thresholds.insert(
+                credential.account_id.clone(),
+                (
+                    account_settings.alert_thresholds.stuck_payment_minutes,
+                    account_settings.track_stuck_payments,
+                ),
+            );
+        }
+        let (Some(minutes), track) = thresholds[&credential.account_id] else {
+            continue;
+        };
+
+        match check_node(pool, credential, minutes, track, alerted).await {
+            Ok(keys) => still_stuck.extend(keys),
+            Err(e) => {
+                tracing::warn!(
+                    "Stuck payment check of node {} failed: {}",
+                    credential.node_id,
+                    e
+                );
+                still_stuck.extend(
+                    alerted
+                        .iter()
+                        .filter(|key| key.0 == credential.account_id && key.1 == credential.node_id)
+                        .cloned(),
+                );
+            }
+        }
+    }
+
+    alerted.retain(|key| still_stuck.contains(key));
+    Ok(())
+}
+
+/// Warns about a node's payments in flight for `minutes`, and reports the
+/// outcome of those that resolved since when `track` is on.
+///
+/// Returns the keys of the node's stuck payments.
+async fn check_node(
+    pool: &SqlitePool,
+    credential: &Credential,
+    minutes: u32,
+    track: bool,
+    alerted: &mut HashSet<AlertKey>,
+) -> ServiceResult<Vec<AlertKey>> {
+    let external = |e: LightningError| ServiceError::ExternalService {
+        message: e.to_string(),
+    };
+    let client = connect_node(credential).await.map_err(external)?;
+    let payments = client.list_inflight_payments().await.map_err(external)?;
+    let now = Utc::now();
+    let events = EventService::new(pool);
+
+    let mut keys = Vec::new();
+    for stuck in stuck_payments(credential, payments, minutes, now) {
+        let key = (
+            credential.account_id.clone(),
+            credential.node_id.clone(),
+            stuck.payment.payment_hash.clone(),
+        );
+        keys.push(key.clone());
+        if alerted.contains(&key) {
+            continue;
+        }
+
+        events
+            .create_and_dispatch_event(CreateEvent {
+                id: Uuid::now_v7().to_string(),
+                account_id: credential.account_id.clone(),
+                user_id: credential.user_id.clone(),
+                node_id: credential.node_id.clone(),
+                node_alias: credential.node_alias.clone(),
+                event_type: EventType::PaymentStuck,
+                severity: EventSeverity::Warning,
+                title: "Payment Stuck".to_string(),
+                description: format!(
+                    "Payment {} of {} from {} has been in flight for {} minutes",
+                    stuck.payment.payment_hash,
+                    stuck.payment.amount,
+                    credential.node_alias,
+                    stuck.stuck_minutes
+                ),
+                data: json!({
+                    "payment_hash": stuck.payment.payment_hash,
+                    "amount_sat": stuck.payment.amount.sat(),
+                    "amount_msat": stuck.payment.amount.msat(),
+                    "invoice": stuck.payment.invoice,
+                    "creation_time": stuck.payment.creation_time,
+                    "stuck_minutes": stuck.stuck_minutes,
+                    "threshold_minutes": minutes,
+                })
+                .to_string(),
+                notifications_id: None,
+                timestamp: now,
+            })
+            .await?;
+        alerted.insert(key);
+    }
+
+    if track {
+        let resolved = alerted.iter().filter(|key| {
+            key.0 == credential.account_id && key.1 == credential.node_id && !keys.contains(key)
+        });
+        for (_, _, payment_hash) in resolved {
+            if let Err(e) = report_outcome(pool, credential, client.as_ref(), payment_hash).await {
+                tracing::warn!(
+                    "Outcome of stuck payment {} on node {} unknown: {}",
+                    payment_hash,
+                    credential.node_id,
+                    e
+                );
+            }
+        }
+    }
+
+    Ok(keys)
+}
+
+/// Raises an event with the outcome of a stuck payment that left the flight.
+async fn report_outcome(
+    pool: &SqlitePool,
+    credential: &Credential,
+    client: &(dyn LightningClient + Send + Sync),
+    payment_hash: &str,
+) -> ServiceResult<()> {
+    let hash = hex::decode(payment_hash)
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .map(PaymentHash)
+        .ok_or_else(|| ServiceError::validation(format!("Invalid payment hash {payment_hash}")))?;
+    let details =
+        client
+            .get_payment_details(&hash)
+            .await
+            .map_err(|e| ServiceError::ExternalService {
+                message: e.to_string(),
+            })?;
+    let outcome = match details.state {
+        PaymentState::Settled => "settled",
+        PaymentState::Failed => "failed",
+        // Below a raised threshold again
+        PaymentState::Inflight => return Ok(()),
+    };
+
+    EventService::new(pool)
+        .create_and_dispatch_event(CreateEvent {
+            id: Uuid::now_v7().to_string(),
+            account_id: credential.account_id.clone(),
+            user_id: credential.user_id.clone(),
+            node_id: credential.node_id.clone(),
+            node_alias: credential.node_alias.clone(),
+            event_type: EventType::PaymentStuck,
+            severity: EventSeverity::Info,
+            title: "Stuck Payment Resolved".to_string(),
+            description: format!(
+                "Stuck payment {} from {} has {}",
+                payment_hash, credential.node_alias, outcome
+            ),
+            data: json!({
+                "payment_hash": payment_hash,
+                "amount_sat": details.amount.sat(),
+                "amount_msat": details.amount.msat(),
+                "outcome": outcome,
+                "completed_at": details.completed_at,
+            })
+            .to_string(),
+            notifications_id: None,
+            timestamp: Utc::now(),
+        })
+        .await?;
+    Ok(())
+}
+
+/// Checks for stuck payments every ten minutes.
+pub fn spawn_stuck_payment_checker(pool: SqlitePool) {
+    task_supervisor::supervise("stuck_payments", move |task| {
+        let pool = pool.clone();
+        async move {
+            let mut alerted = HashSet::new();
+            // The first check waits for startup migrations
+            let mut interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + CHECK_INTERVAL,
+                CHECK_INTERVAL,
+            );
+            loop {
+                interval.tick().await;
+                let result = check_all_nodes(&pool, &mut alerted).await;
+                if let Err(e) = &result {
+                    tracing::error!("Stuck payment check failed: {}", e);
+                }
+                task.record_run(&result);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::amount::Amount;
+
+    fn payment(state: PaymentState, payment_type: PaymentType, age_minutes: i64) -> PaymentSummary {
+        PaymentSummary {
+            state,
+            payment_type,
+            amount: Amount::from_sat(1000),
+            amount_usd: Some(0.5),
+            routing_fee: None,
+            creation_time: Some(
+                (Utc::now() - chrono::Duration::minutes(age_minutes)).timestamp() as u64,
+            ),
+            invoice: None,
+            payment_hash: format!("{age_minutes:064}"),
+            completed_at: None,
+        }
+    }
+
+    #[test]
+    fn only_old_outgoing_payments_in_flight_are_stuck() {
+        let now = Utc::now();
+        let old = payment(PaymentState::Inflight, PaymentType::Outgoing, 90);
+        assert_eq!(stuck_minutes(&old, 60, now), Some(90));
+        assert_eq!(stuck_minutes(&old, 120, now), None);
+
+        let settled = payment(PaymentState::Settled, PaymentType::Outgoing, 90);
+        assert_eq!(stuck_minutes(&settled, 60, now), None);
+        let incoming = payment(PaymentState::Inflight, PaymentType::Incoming, 90);
+        assert_eq!(stuck_minutes(&incoming, 60, now), None);
+
+        let undated = PaymentSummary {
+            creation_time: None,
+            ..old
+        };
+        assert_eq!(stuck_minutes(&undated, 60, now), None);
+    }
+}
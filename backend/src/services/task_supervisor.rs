@@ -0,0 +1,329 @@
+//! Registry of the background tasks, for `GET /api/admin/tasks`.
+//!
+//! Scheduled jobs run under [`supervise`], which restarts a job that panics or
+//! returns, waiting longer after each quick failure and giving up after
+//! `MAX_QUICK_FAILURES` of them in a row. Per-node tasks such as event streams
+//! and channel acceptors aren't restarted from here but register with
+//! [`track`] under their account, so their state is visible too but only
+//! alongside that account's. Tasks report each run or attempt through their
+//! [`TaskHandle`].
+
+use chrono::{DateTime, Utc};
+use futures::FutureExt;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+/// Delay before the first restart, doubled after each quick failure.
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(300);
+/// A task that ran at least this long before failing starts its backoff over.
+const STABLE_UPTIME: chrono::Duration = chrono::Duration::minutes(10);
+const MAX_QUICK_FAILURES: u32 = 10;
+
+/// Tasks by account, if they belong to one, and name.
+type TaskKey = (Option<String>, String);
+
+static REGISTRY: LazyLock<Mutex<BTreeMap<TaskKey, TaskRecord>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskState {
+    Running,
+    /// Waiting to be restarted or to retry
+    Backoff,
+    /// Stopped for good after an error
+    Failed,
+}
+
+#[derive(Debug, Clone)]
+struct TaskRecord {
+    /// Tells a task apart from a later one registered under its name
+    generation: u64,
+    state: TaskState,
+    started_at: DateTime<Utc>,
+    restart_count: u32,
+    runs: u64,
+    last_run_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    last_error_at: Option<DateTime<Utc>>,
+}
+
+/// A task as listed by `GET /api/admin/tasks`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskStatus {
+    pub name: String,
+    pub state: TaskState,
+    /// When the task last (re)started
+    pub started_at: DateTime<Utc>,
+    pub uptime_secs: i64,
+    pub restart_count: u32,
+    /// Completed runs of a scheduled job
+    pub runs: u64,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+}
+
+/// Lets a task report on itself.
+#[derive(Debug, Clone)]
+pub struct TaskHandle {
+    key: TaskKey,
+    generation: u64,
+}
+
+impl TaskHandle {
+    fn update(&self, f: impl FnOnce(&mut TaskRecord)) {
+        let mut registry = REGISTRY.lock().expect("task registry poisoned");
+        if let Some(record) = registry
+            .get_mut(&self.key)
+            .filter(|record| record.generation == self.generation)
+        {
+            f(record);
+        }
+    }
+
+    /// Records a completed run of a scheduled job, keeping its error if it failed.
+    pub fn record_run<T, E: Display>(&self, result: &Result<T, E>) {
+        let now = Utc::now();
+        self.update(|record| {
+            record.runs += 1;
+            record.last_run_at = Some(now);
+            if let Err(e) = result {
+                record.last_error = Some(e.to_string());
+                record.last_error_at = Some(now);
+            }
+        });
+    }
+
+    /// Marks the task as waiting to retry after an error.
+    pub fn backoff(&self, error: impl Display) {
+        let now = Utc::now();
+        self.update(|record| {
+            record.state = TaskState::Backoff;
+            record.last_error = Some(error.to_string());
+            record.last_error_at = Some(now);
+        });
+    }
+
+    /// Marks the task as running again after a backoff.
+    pub fn resume(&self) {
+        self.update(|record| {
+            record.state = TaskState::Running;
+            record.restart_count += 1;
+            record.started_at = Utc::now();
+        });
+    }
+
+    /// Marks the task as stopped for good.
+    pub fn fail(&self, error: impl Display) {
+        let now = Utc::now();
+        self.update(|record| {
+            record.state = TaskState::Failed;
+            record.last_error = Some(error.to_string());
+            record.last_error_at = Some(now);
+        });
+    }
+
+    /// Removes a task that ended normally from the registry.
+    pub fn finish(&self) {
+        let mut registry = REGISTRY.lock().expect("task registry poisoned");
+        if registry
+            .get(&self.key)
+            .is_some_and(|record| record.generation == self.generation)
+        {
+            registry.remove(&self.key);
+        }
+    }
+}
+
+/// Registers a running task of an account, or of the whole instance, replacing
+/// any earlier one of the same name.
+pub fn track(name: impl Into<String>, account_id: Option<&str>) -> TaskHandle {
+    let handle = TaskHandle {
+        key: (account_id.map(str::to_string), name.into()),
+        generation: NEXT_GENERATION.fetch_add(1, Ordering::Relaxed),
+    };
+    REGISTRY.lock().expect("task registry poisoned").insert(
+        handle.key.clone(),
+        TaskRecord {
+            generation: handle.generation,
+            state: TaskState::Running,
+            started_at: Utc::now(),
+            restart_count: 0,
+            runs: 0,
+            last_run_at: None,
+            last_error: None,
+            last_error_at: None,
+        },
+    );
+    handle
+}
+
+/// Delay before restarting a task after `quick_failures` quick failures in a row.
+pub fn restart_delay(quick_failures: u32) -> Duration {
+    RESTART_DELAY
+        .saturating_mul(2_u32.saturating_pow(quick_failures.saturating_sub(1)))
+        .min(MAX_RESTART_DELAY)
+}
+
+fn panic_message(panic: Box<dyn std::any::Any + Send>) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// Runs a long-lived task, restarting it whenever it panics or returns.
+///
+/// `task` builds the task's future afresh for every start.
+pub fn supervise<F, Fut>(name: &str, task: F)
+where
+    F: Fn(TaskHandle) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let handle = track(name, None);
+    tokio::spawn(async move {
+        let mut quick_failures = 0;
+        loop {
+            let started_at = Utc::now();
+            let error = match AssertUnwindSafe(task(handle.clone())).catch_unwind().await {
+                Ok(()) => "Task returned".to_string(),
+                Err(panic) => format!("Task panicked: {}", panic_message(panic)),
+            };
+
+            if Utc::now() - started_at >= STABLE_UPTIME {
+                quick_failures = 0;
+            }
+            quick_failures += 1;
+            if quick_failures > MAX_QUICK_FAILURES {
+                tracing::error!("Background task {} gave up: {}", handle.key.1, error);
+                handle.fail(error);
+                return;
+            }
+
+            let delay = restart_delay(quick_failures);
+            tracing::error!(
+                "Background task {} stopped, restarting in {}s: {}",
+                handle.key.1,
+                delay.as_secs(),
+                error
+            );
+            handle.backoff(error);
+            tokio::time::sleep(delay).await;
+            handle.resume();
+        }
+    });
+}
+
+/// Lists the instance's tasks and those of an account, by name.
+pub fn tasks(account_id: &str) -> Vec<TaskStatus> {
+    let now = Utc::now();
+    let mut tasks: Vec<TaskStatus> = REGISTRY
+        .lock()
+        .expect("task registry poisoned")
+        .iter()
+        .filter(|((account, _), _)| account.as_deref().is_none_or(|id| id == account_id))
+        .map(|((_, name), record)| TaskStatus {
+            name: name.clone(),
+            state: record.state,
+            started_at: record.started_at,
+            uptime_secs: match record.state {
+                TaskState::Running => (now - record.started_at).num_seconds(),
+                TaskState::Backoff | TaskState::Failed => 0,
+            },
+            restart_count: record.restart_count,
+            runs: record.runs,
+            last_run_at: record.last_run_at,
+            last_error: record.last_error.clone(),
+            last_error_at: record.last_error_at,
+        })
+        .collect();
+    tasks.sort_by(|a, b| a.name.cmp(&b.name));
+    tasks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(name: &str) -> Option<TaskStatus> {
+        tasks("account").into_iter().find(|task| task.name == name)
+    }
+
+    #[test]
+    fn restart_delays_double_up_to_the_cap() {
+        assert_eq!(restart_delay(1), Duration::from_secs(1));
+        assert_eq!(restart_delay(4), Duration::from_secs(8));
+        assert_eq!(restart_delay(30), MAX_RESTART_DELAY);
+    }
+
+    #[test]
+    fn handles_only_update_their_own_registration() {
+        let old = track("test_task", Some("account"));
+        old.record_run(&Err::<(), _>("boom"));
+        let task = status("test_task").unwrap();
+        assert_eq!(task.runs, 1);
+        assert_eq!(task.last_error.as_deref(), Some("boom"));
+
+        let new = track("test_task", Some("account"));
+        old.fail("stale");
+        assert_eq!(status("test_task").unwrap().state, TaskState::Running);
+
+        new.backoff("retrying");
+        new.resume();
+        let task = status("test_task").unwrap();
+        assert_eq!(task.restart_count, 1);
+        assert_eq!(task.last_error.as_deref(), Some("retrying"));
+
+        old.finish();
+        assert!(status("test_task").is_some());
+        new.finish();
+        assert!(status("test_task").is_none());
+    }
+
+    #[test]
+    fn accounts_only_see_their_own_tasks() {
+        let task = track("test_other_account", Some("other"));
+        assert!(status("test_other_account").is_none());
+        assert!(
+            tasks("other")
+                .iter()
+                .any(|task| task.name == "test_other_account")
+        );
+        task.finish();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn supervised_tasks_restart_after_panics() {
+        let starts = std::sync::Arc::new(AtomicU64::new(0));
+        let counter = starts.clone();
+        supervise("test_panicking", move |_| {
+            let counter = counter.clone();
+            async move {
+                if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                    panic!("first start fails");
+                }
+                std::future::pending::<()>().await;
+            }
+        });
+
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(starts.load(Ordering::SeqCst), 2);
+        let task = status("test_panicking").unwrap();
+        assert_eq!(task.state, TaskState::Running);
+        assert_eq!(task.restart_count, 1);
+        assert_eq!(
+            task.last_error.as_deref(),
+            Some("Task panicked: first start fails")
+        );
+    }
+}
@@ -0,0 +1,261 @@
+//! Two-way sync of on-chain transaction labels with annotation notes.
+//!
+//! LND keeps a label for each transaction of its wallet, which tools such as
+//! ThunderHub and RTL let operators edit. Every ten minutes the labels of each
+//! LND node's transactions are compared with the notes of the account's
+//! annotations keyed by the same txid. The label as of the last sync is kept
+//! with the annotation, so whichever side changed since wins: a new label on
+//! the node replaces the notes, and new notes are written to the node as its
+//! label, unless the node's credentials are read-only. LND's own labels for
+//! channel opens, closes and sweeps aren't imported. CLN keeps no transaction
+//! labels, so its nodes are skipped.
+
+use crate::database::models::{Credential, PaymentAnnotation};
+use crate::errors::{LightningError, ServiceError, ServiceResult};
+use crate::repositories::credential_repository::CredentialRepository;
+use crate::repositories::payment_annotation_repository::PaymentAnnotationRepository;
+use crate::services::node_capabilities::{self, NodeFeature, NodeImplementation};
+use crate::services::node_manager::LightningClient;
+use crate::services::node_manager::connect_node;
+use crate::services::payment_annotations::PaymentAnnotationService;
+use crate::services::task_supervisor;
+use crate::utils::TransactionLabel;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+const SYNC_INTERVAL: std::time::Duration = std::time::Duration::from_secs(600);
+/// Longest label LND accepts.
+const MAX_LABEL_CHARS: usize = 500;
+
+/// What a sync does for one transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LabelSync {
+    /// Set the annotation's notes to the node's label
+    Import(String),
+    /// Write the annotation's notes to the node as its label
+    Push(String),
+}
+
+/// A labelled or annotated transaction of the selected node, as listed by
+/// `GET /api/node/transaction-labels`.
+#[derive(Debug, Clone, Serialize)]
+pub struct LabelledTransaction {
+    pub txid: String,
+    /// Label the node keeps for the transaction
+    pub node_label: Option<String>,
+    pub annotation: Option<PaymentAnnotation>,
+}
+
+/// The label an operator gave a transaction, leaving out those LND generates
+/// itself, such as `0:openchannel:shortchanid-…`.
+pub fn user_label(label: &str) -> Option<&str> {
+    let label = label.trim();
+    let generated = label.split_once(':').is_some_and(|(version, _)| {
+        !version.is_empty() && version.chars().all(|c| c.is_ascii_digit())
+    });
+    (!label.is_empty() && !generated).then_some(label)
+}
+
+/// Notes as the node can hold them as a label.
+fn label_for_notes(notes: &str) -> String {
+    notes.trim().chars().take(MAX_LABEL_CHARS).collect()
+}
+
+/// Decides how to bring a transaction's node label and annotation notes back
+/// in line, given the label as of the last sync. A label changed on the node
+/// wins over notes changed in NodeGaze since.
+pub fn sync_action(
+    node_label: Option<&str>,
+    notes: Option<&str>,
+    last_synced: Option<&str>,
+) -> Option<LabelSync> {
+    if let Some(node_label) = node_label.filter(|label| Some(*label) != last_synced) {
+        return Some(LabelSync::Import(node_label.to_string()));
+    }
+    let pushed = notes
+        .map(label_for_notes)
+        .filter(|label| !label.is_empty())?;
+    (Some(pushed.as_str()) != last_synced && Some(pushed.as_str()) != node_label)
+        .then_some(LabelSync::Push(pushed))
+}
+
+/// Lists the selected node's labelled transactions and those annotated in NodeGaze.
+pub async fn list_labelled_transactions(
+    pool: &SqlitePool,
+    account_id: &str,
+    client: &(dyn LightningClient + Send + Sync),
+) -> ServiceResult<Vec<LabelledTransaction>> {
+    let external = |e: LightningError| ServiceError::ExternalService {
+        message: e.to_string(),
+    };
+    let transactions = client.list_transaction_labels().await.map_err(external)?;
+    let mut annotations = PaymentAnnotationService::new(pool)
+        .get_annotations(account_id)
+        .await?;
+
+    Ok(transactions
+        .into_iter()
+        .filter_map(|transaction| {
+            let txid = transaction.txid.to_string();
+            let annotation = annotations.remove(&txid);
+            (transaction.label.is_some() || annotation.is_some()).then_some(LabelledTransaction {
+                txid,
+                node_label: transaction.label,
+                annotation,
+            })
+        })
+        .collect())
+}
+
+/// Syncs the transaction labels of every LND node that supports them.
+pub async fn sync_all_nodes(pool: &SqlitePool) -> ServiceResult<()> {
+    let credentials = CredentialRepository::new(pool)
+        .get_node_credentials(None, None)
+        .await?;
+
+    for credential in &credentials {
+        let supported =
+            NodeImplementation::from_node_type(credential.node_type.as_deref().unwrap_or("lnd"))
+                .is_some_and(|implementation| {
+                    node_capabilities::supports(
+                        implementation,
+                        credential.node_version.as_deref(),
+                        NodeFeature::TransactionLabels,
+                    )
+                });
+        if !supported {
+            continue;
+        }
+
+        if let Err(e) = sync_node(pool, credential).await {
+            tracing::warn!(
+                "Transaction label sync of node {} failed: {}",
+                credential.node_id,
+                e
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Syncs the labels of a node's transactions with the account's annotations.
+async fn sync_node(pool: &SqlitePool, credential: &Credential) -> ServiceResult<()> {
+    let external = |e: LightningError| ServiceError::ExternalService {
+        message: e.to_string(),
+    };
+    let client = connect_node(credential).await.map_err(external)?;
+    let transactions: Vec<TransactionLabel> =
+        client.list_transaction_labels().await.map_err(external)?;
+    let annotations: HashMap<String, PaymentAnnotation> = PaymentAnnotationService::new(pool)
+        .get_annotations(&credential.account_id)
+        .await?;
+    let repo = PaymentAnnotationRepository::new(pool);
+
+    for transaction in transactions {
+        let txid = transaction.txid.to_string();
+        let annotation = annotations.get(&txid);
+        let action = sync_action(
+            transaction.label.as_deref().and_then(user_label),
+            annotation.and_then(|annotation| annotation.notes.as_deref()),
+            annotation.and_then(|annotation| annotation.node_label.as_deref()),
+        );
+
+        match action {
+            Some(LabelSync::Import(label)) => {
+                repo.record_node_label(&credential.account_id, &txid, &label, true)
+                    .await?;
+            }
+            // Read-only credentials can't label the node's transactions
+            Some(LabelSync::Push(label)) if !credential.read_only => {
+                client
+                    .label_transaction(&transaction.txid, &label)
+                    .await
+                    .map_err(external)?;
+                repo.record_node_label(&credential.account_id, &txid, &label, false)
+                    .await?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Syncs transaction labels every ten minutes.
+pub fn spawn_label_sync(pool: SqlitePool) {
+    task_supervisor::supervise("transaction_labels", move |task| {
+        let pool = pool.clone();
+        async move {
+            // The first sync waits for startup migrations
+            let mut interval = tokio::time::interval_at(
+                tokio::time::Instant::now() + SYNC_INTERVAL,
+                SYNC_INTERVAL,
+            );
+            loop {
+                interval.tick().await;
+                let result = sync_all_nodes(&pool).await;
+                if let Err(e) = &result {
+                    tracing::error!("Transaction label sync failed: {}", e);
+                }
+                task.record_run(&result);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_generated_labels() {
+        assert_eq!(user_label("0:openchannel:shortchanid-123"), None);
+        assert_eq!(user_label("0:sweep"), None);
+        assert_eq!(user_label("  "), None);
+        assert_eq!(user_label(" cold storage "), Some("cold storage"));
+        assert_eq!(user_label("refund: order 42"), Some("refund: order 42"));
+    }
+
+    #[test]
+    fn changed_side_wins() {
+        // New label on the node
+        assert_eq!(
+            sync_action(Some("exchange"), None, None),
+            Some(LabelSync::Import("exchange".to_string()))
+        );
+        assert_eq!(
+            sync_action(Some("exchange"), Some("old"), Some("old")),
+            Some(LabelSync::Import("exchange".to_string()))
+        );
+        // New notes in NodeGaze
+        assert_eq!(
+            sync_action(Some("old"), Some("exchange"), Some("old")),
+            Some(LabelSync::Push("exchange".to_string()))
+        );
+        assert_eq!(
+            sync_action(None, Some("exchange"), None),
+            Some(LabelSync::Push("exchange".to_string()))
+        );
+        // Both changed: the node wins
+        assert_eq!(
+            sync_action(Some("node"), Some("notes"), Some("old")),
+            Some(LabelSync::Import("node".to_string()))
+        );
+    }
+
+    #[test]
+    fn leaves_synced_transactions_alone() {
+        assert_eq!(sync_action(Some("same"), Some("same"), Some("same")), None);
+        assert_eq!(sync_action(None, None, None), None);
+        // Cleared notes don't clear the node's label
+        assert_eq!(sync_action(Some("same"), None, Some("same")), None);
+        // Long notes are pushed truncated, and not again
+        let notes = "n".repeat(600);
+        let label = "n".repeat(MAX_LABEL_CHARS);
+        assert_eq!(
+            sync_action(None, Some(&notes), None),
+            Some(LabelSync::Push(label.clone()))
+        );
+        assert_eq!(sync_action(Some(&label), Some(&notes), Some(&label)), None);
+    }
+}
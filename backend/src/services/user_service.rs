@@ -208,4 +208,74 @@ impl<'a> UserService<'a> {
 
         Ok(user)
     }
+
+    /// Sets a new password for the user with the given username.
+    ///
+    /// # Errors
+    /// Returns `ServiceError` for:
+    /// - Empty passwords
+    /// - Unknown usernames
+    /// - Database errors
+    pub async fn reset_password(&self, username: &str, new_password: &str) -> ServiceResult<User> {
+        if new_password.is_empty() {
+            return Err(ServiceError::validation("Password is required"));
+        }
+
+        let repo = UserRepository::new(self.pool);
+        let user = repo
+            .get_user_by_username(username)
+            .await?
+            .ok_or_else(|| ServiceError::not_found("User", username))?;
+
+        let password_hash = bcrypt::hash(new_password, bcrypt::DEFAULT_COST)
+            .map_err(|e| ServiceError::validation(format!("Password hashing failed: {e}")))?;
+        if !repo.update_password(&user.id, &password_hash).await? {
+            return Err(ServiceError::not_found("User", username));
+        }
+
+        Ok(user)
+    }
+
+    /// Restores a soft-deleted user of an account.
+    ///
+    /// # Errors
+    /// Returns `ServiceError` for:
+    /// - Users that don't exist in the account or aren't deleted
+    /// - An active user having taken the username or email since
+    /// - Database errors
+    pub async fn restore_user(&self, account_id: &str, id: &str) -> ServiceResult<User> {
+        let repo = UserRepository::new(self.pool);
+        let user = repo
+            .get_deleted_user(account_id, id)
+            .await?
+            .ok_or_else(|| ServiceError::not_found("Deleted user", id))?;
+
+        if repo.username_exists(&user.username).await? {
+            return Err(ServiceError::already_exists(
+                "User with username",
+                &user.username,
+            ));
+        }
+        if repo.email_exists(&user.email).await? {
+            return Err(ServiceError::already_exists("User with email", &user.email));
+        }
+
+        // An active user may still take the username or email between the checks
+        // and the update
+        let restored = repo.restore_user(id).await.map_err(|e| {
+            let error_msg = e.to_string();
+            if error_msg.contains("UNIQUE constraint failed: users.username") {
+                ServiceError::already_exists("User with username", &user.username)
+            } else if error_msg.contains("UNIQUE constraint failed: users.email") {
+                ServiceError::already_exists("User with email", &user.email)
+            } else {
+                ServiceError::Database { source: e }
+            }
+        })?;
+        if !restored {
+            return Err(ServiceError::not_found("Deleted user", id));
+        }
+
+        self.get_user_required(id).await
+    }
 }
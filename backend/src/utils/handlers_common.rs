@@ -1,100 +1,119 @@
-use crate::api::common::ApiResponse;
+use crate::api::common::ApiError;
+use crate::config::Config;
+use crate::database::models::RoleAccessLevel;
 use crate::errors::LightningError;
-use crate::services::node_manager::{
-    ClnConnection, ClnNode, LightningClient, LndConnection, LndNode,
-};
-use crate::utils::NodeId;
+use crate::services::node_manager::{LightningClient, connect_node};
+use crate::services::settings_service::SettingsService;
 use crate::utils::jwt::{Claims, NodeCredentials};
-use axum::http::StatusCode;
+use crate::utils::time_zone::parse_tz;
 use bitcoin::secp256k1::PublicKey;
+use chrono_tz::Tz;
 use lightning::ln::PaymentHash;
+use sqlx::SqlitePool;
 use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+/// The node a request operates on, placed in the request extensions by the
+/// `node_selection` middleware.
+///
+/// The client connects on first use, so responses served from the cache never
+/// reach the node.
+#[derive(Clone)]
+pub struct SelectedNode {
+    credentials: NodeCredentials,
+    client: Arc<OnceCell<Box<dyn LightningClient + Send + Sync>>>,
+}
+
+impl SelectedNode {
+    pub fn new(credentials: NodeCredentials) -> Self {
+        Self {
+            credentials,
+            client: Arc::new(OnceCell::new()),
+        }
+    }
+
+    pub fn credentials(&self) -> &NodeCredentials {
+        &self.credentials
+    }
+
+    /// Returns the node's client, connecting if this request hasn't yet.
+    pub async fn client(&self) -> Result<&(dyn LightningClient + Send + Sync), ApiError> {
+        let client = self
+            .client
+            .get_or_try_init(|| create_node_client(&self.credentials))
+            .await?;
+        Ok(client.as_ref())
+    }
+}
+
+/// Rejects callers with read-only access; `action` names what they were
+/// refused, e.g. "open channels".
+pub fn require_write_access(claims: &Claims, action: &str) -> Result<(), ApiError> {
+    if claims.role != "Admin" && claims.role_access_level != RoleAccessLevel::ReadWrite {
+        return Err(ApiError::forbidden(
+            "forbidden",
+            format!("Read-only users cannot {action}"),
+        ));
+    }
+    Ok(())
+}
+
+/// Rejects callers who aren't admins of their account; `action` names what
+/// they were refused.
+pub fn require_admin(claims: &Claims, action: &str) -> Result<(), ApiError> {
+    if claims.role != "Admin" {
+        return Err(ApiError::forbidden(
+            "forbidden",
+            format!("Only Admin users can {action}"),
+        ));
+    }
+    Ok(())
+}
+
+/// Rejects callers who aren't operators of the server.
+///
+/// Account admins only manage their own account; settings shared by every
+/// account are reserved for the users listed in `OPERATOR_USER_IDS`.
+pub fn require_operator(claims: &Claims, config: &Config, action: &str) -> Result<(), ApiError> {
+    if !config.is_operator(&claims.sub) {
+        return Err(ApiError::forbidden(
+            "forbidden",
+            format!("Only server operators can {action}"),
+        ));
+    }
+    Ok(())
+}
 
 /// Extract credentials from claims
-pub fn extract_node_credentials(claims: &Claims) -> Result<&NodeCredentials, (StatusCode, String)> {
+pub fn extract_node_credentials(claims: &Claims) -> Result<&NodeCredentials, ApiError> {
     claims.node_credentials().ok_or_else(|| {
-        let error_response = ApiResponse::<()>::error(
-            "No node credentials found in token".to_string(),
-            "missing_credentials",
-            None,
-        );
-        (
-            StatusCode::UNAUTHORIZED,
-            serde_json::to_string(&error_response).unwrap(),
-        )
+        ApiError::unauthorized("missing_credentials", "No node credentials found in token")
     })
 }
 
-/// Creates and returns a Lightning client (LND or CLN) based on the provided credentials.
+/// Opens a client for a node's credentials, drawing from the node's RPC budget.
 pub async fn create_node_client(
     node_credentials: &NodeCredentials,
-    public_key: PublicKey,
-) -> Result<Box<dyn LightningClient>, (StatusCode, String)> {
-    match node_credentials.node_type.as_str() {
-        "lnd" => {
-            let lnd_node = LndNode::new(LndConnection {
-                id: NodeId::PublicKey(public_key),
-                address: node_credentials.address.clone(),
-                macaroon: node_credentials.macaroon.clone(),
-                cert: node_credentials.tls_cert.clone(),
-            })
-            .await
-            .map_err(|e| handle_node_error(e, "connect to LND node"))?;
-
-            Ok(Box::new(lnd_node))
-        }
-        "cln" => {
-            let (client_cert, client_key, ca_cert) = extract_cln_tls_components(node_credentials)?;
-
-            let cln_node = ClnNode::new(ClnConnection {
-                id: NodeId::PublicKey(public_key),
-                address: node_credentials.address.clone(),
-                ca_cert,
-                client_cert,
-                client_key,
-            })
-            .await
-            .map_err(|e| handle_node_error(e, "connect to CLN node"))?;
-
-            Ok(Box::new(cln_node))
-        }
-        _ => {
-            let error_response = ApiResponse::<()>::error(
-                "Unsupported node type".to_string(),
-                "unsupported_node_type",
-                None,
-            );
-            Err((
-                StatusCode::BAD_REQUEST,
-                serde_json::to_string(&error_response).unwrap(),
-            ))
-        }
-    }
+) -> Result<Box<dyn LightningClient + Send + Sync>, ApiError> {
+    connect_node(node_credentials.clone())
+        .await
+        .map_err(|e| handle_node_error(e, "connect to node"))
 }
 
 /// Parse hex string into PaymentHash
-pub fn parse_payment_hash(payment_hash: &str) -> Result<PaymentHash, (StatusCode, String)> {
+pub fn parse_payment_hash(payment_hash: &str) -> Result<PaymentHash, ApiError> {
     let payment_hash_bytes = hex::decode(payment_hash).map_err(|e| {
-        let error_response = ApiResponse::<()>::error(
-            format!("Invalid payment hash format: {e}"),
+        ApiError::bad_request(
             "invalid_payment_hash",
-            None,
-        );
-        (
-            StatusCode::BAD_REQUEST,
-            serde_json::to_string(&error_response).unwrap(),
+            format!("Invalid payment hash format: {e}"),
         )
     })?;
 
     if payment_hash_bytes.len() != 32 {
-        let error_response = ApiResponse::<()>::error(
-            "Payment hash must be 32 bytes".to_string(),
+        return Err(ApiError::bad_request(
             "invalid_payment_hash_length",
-            None,
-        );
-        return Err((
-            StatusCode::BAD_REQUEST,
-            serde_json::to_string(&error_response).unwrap(),
+            "Payment hash must be 32 bytes",
         ));
     }
 
@@ -104,73 +123,37 @@ pub fn parse_payment_hash(payment_hash: &str) -> Result<PaymentHash, (StatusCode
 }
 
 /// Parse node_id into PublicKey
-pub fn parse_public_key(node_id: &str) -> Result<PublicKey, (StatusCode, String)> {
+pub fn parse_public_key(node_id: &str) -> Result<PublicKey, ApiError> {
     PublicKey::from_str(node_id).map_err(|e| {
-        let error_response = ApiResponse::<()>::error(
-            format!("Invalid node public key: {e}"),
+        ApiError::bad_request(
             "invalid_public_key",
-            None,
-        );
-        (
-            StatusCode::BAD_REQUEST,
-            serde_json::to_string(&error_response).unwrap(),
+            format!("Invalid node public key: {e}"),
         )
     })
 }
 
-/// Extract TLS fields for CLN
-pub fn extract_cln_tls_components(
-    node_credentials: &NodeCredentials,
-) -> Result<(String, String, String), (StatusCode, String)> {
-    let client_cert = node_credentials.client_cert.as_ref().ok_or_else(|| {
-        let error_response = ApiResponse::<()>::error(
-            "Missing client certificate for CLN".to_string(),
-            "missing_client_cert",
-            None,
-        );
-        (
-            StatusCode::BAD_REQUEST,
-            serde_json::to_string(&error_response).unwrap(),
-        )
-    })?;
-
-    let client_key = node_credentials.client_key.as_ref().ok_or_else(|| {
-        let error_response = ApiResponse::<()>::error(
-            "Missing client key for CLN".to_string(),
-            "missing_client_key",
-            None,
-        );
-        (
-            StatusCode::BAD_REQUEST,
-            serde_json::to_string(&error_response).unwrap(),
-        )
-    })?;
-
-    let ca_cert = node_credentials.ca_cert.as_ref().ok_or_else(|| {
-        let error_response = ApiResponse::<()>::error(
-            "Missing CA certificate for CLN".to_string(),
-            "missing_ca_cert",
-            None,
-        );
-        (
-            StatusCode::BAD_REQUEST,
-            serde_json::to_string(&error_response).unwrap(),
-        )
-    })?;
-
-    Ok((client_cert.clone(), client_key.clone(), ca_cert.clone()))
+/// Time zone a request's dates are read in: the `tz` query parameter when given,
+/// otherwise the account's `timezone` setting.
+pub async fn request_tz(
+    pool: &SqlitePool,
+    account_id: &str,
+    tz: Option<&str>,
+) -> Result<Tz, ApiError> {
+    match tz {
+        Some(tz) => parse_tz(tz).map_err(|e| ApiError::bad_request("invalid_timezone", e)),
+        None => Ok(SettingsService::new(pool)
+            .get_settings(account_id)
+            .await?
+            .tz()),
+    }
 }
 
 /// Handle node operation errors
-pub fn handle_node_error(e: LightningError, operation: &str) -> (StatusCode, String) {
+pub fn handle_node_error(e: LightningError, operation: &str) -> ApiError {
     tracing::error!("{} failed: {}", operation, e);
-    let error_response = ApiResponse::<()>::error(
-        format!("Failed to {operation}: {e}"),
+    ApiError::new(
+        ApiError::lightning_status(&e),
         format!("{}_error", operation.replace(' ', "_")),
-        None,
-    );
-    (
-        StatusCode::INTERNAL_SERVER_ERROR,
-        serde_json::to_string(&error_response).unwrap(),
+        format!("Failed to {operation}: {e}"),
     )
 }
@@ -7,10 +7,13 @@ use chrono::{Duration, Utc};
 use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
 use serde::{Deserialize, Serialize};
 
-use crate::config::Config;
-use crate::database::models::RoleAccessLevel;
+use crate::config;
+use crate::database::models::{Credential, RoleAccessLevel};
 use crate::errors::ServiceError;
 
+/// Lifetime of refresh tokens, and so of the sessions they belong to.
+pub const REFRESH_TOKEN_LIFETIME_DAYS: i64 = 30;
+
 /// JWT Claims structure containing user and node authentication data
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
@@ -28,6 +31,9 @@ pub struct Claims {
     pub exp: usize,
     /// Token issued at timestamp
     pub iat: usize,
+    /// Login session the token was issued for
+    #[serde(default)]
+    pub sid: Option<String>,
 }
 
 /// Node credentials stored in JWT (now unencrypted for simplicity)
@@ -42,6 +48,37 @@ pub struct NodeCredentials {
     pub client_key: Option<String>,  // For CLN
     pub ca_cert: Option<String>,     // For CLN
     pub address: String,
+    #[serde(default)]
+    pub proxy: Option<String>, // SOCKS5 proxy, e.g. Tor
+    #[serde(default)]
+    pub rune: Option<String>, // For CLN over commando
+    #[serde(default)]
+    pub transport: Option<String>, // For LND, "grpc" or "rest"
+    #[serde(default)]
+    pub read_only: bool, // Mutating node endpoints are disabled
+}
+
+impl From<&Credential> for NodeCredentials {
+    fn from(credential: &Credential) -> Self {
+        Self {
+            node_id: credential.node_id.clone(),
+            node_alias: credential.node_alias.clone(),
+            node_type: credential
+                .node_type
+                .clone()
+                .unwrap_or_else(|| "lnd".to_string()),
+            macaroon: credential.macaroon.clone(),
+            tls_cert: credential.tls_cert.clone(),
+            client_cert: credential.client_cert.clone(),
+            client_key: credential.client_key.clone(),
+            ca_cert: credential.ca_cert.clone(),
+            address: credential.address.clone(),
+            proxy: credential.proxy.clone(),
+            rune: credential.rune.clone(),
+            transport: credential.transport.clone(),
+            read_only: credential.read_only,
+        }
+    }
 }
 
 /// JWT token utility for creating and validating tokens
@@ -54,8 +91,8 @@ pub struct JwtUtils {
 impl JwtUtils {
     /// Create a new JwtUtils instance with keys from environment
     pub fn new() -> Result<Self, ServiceError> {
-        let config = crate::config::Config::from_env()
-            .map_err(|e| ServiceError::validation(format!("Config error: {e}")))?;
+        let config =
+            config::shared().map_err(|e| ServiceError::validation(format!("Config error: {e}")))?;
 
         let encoding_key = EncodingKey::from_secret(config.jwt_secret.as_bytes());
         let decoding_key = DecodingKey::from_secret(config.jwt_secret.as_bytes());
@@ -78,10 +115,11 @@ impl JwtUtils {
         role: String,
         role_access_level: RoleAccessLevel,
         node_credentials: Option<NodeCredentials>,
+        session_id: Option<String>,
     ) -> Result<String, ServiceError> {
         // Get expires_in from config
-        let config = Config::from_env()
-            .map_err(|e| ServiceError::validation(format!("Config error: {e}")))?;
+        let config =
+            config::shared().map_err(|e| ServiceError::validation(format!("Config error: {e}")))?;
         let expires_in = config.jwt_expires_in_seconds;
 
         let now = Utc::now();
@@ -95,6 +133,7 @@ impl JwtUtils {
             node_credentials,
             exp: exp.timestamp() as usize,
             iat: now.timestamp() as usize,
+            sid: session_id,
         };
 
         encode(&Header::default(), &claims, &self.encoding_key)
@@ -113,9 +152,10 @@ impl JwtUtils {
         &self,
         user_id: String,
         role_access_level: RoleAccessLevel,
+        session_id: String,
     ) -> Result<String, ServiceError> {
         let now = Utc::now();
-        let exp = now + Duration::days(30); // Refresh token expires in 30 days
+        let exp = now + Duration::days(REFRESH_TOKEN_LIFETIME_DAYS);
 
         let claims = Claims {
             sub: user_id,
@@ -125,6 +165,7 @@ impl JwtUtils {
             node_credentials: None,
             exp: exp.timestamp() as usize,
             iat: now.timestamp() as usize,
+            sid: Some(session_id),
         };
 
         encode(&Header::default(), &claims, &self.encoding_key)
@@ -0,0 +1,129 @@
+//! Tracing setup with a filter that can be changed while the server runs.
+//!
+//! The filter starts from `RUST_LOG`, `info` when unset, and takes the usual
+//! `EnvFilter` directives, e.g. `info,backend::services::node_manager=debug`.
+//! Targets starting with one of the crate's top-level modules, such as
+//! `services::node_manager`, are taken to be inside the crate.
+//!
+//! sqlx warns about statements slower than `DB_SLOW_QUERY_MS`; those that get
+//! past the filter are also counted for `GET /api/metrics`.
+
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::{EnvFilter, Registry, fmt, prelude::*, reload};
+
+const DEFAULT_FILTER: &str = "info";
+
+/// Target sqlx logs executed statements under.
+const SQLX_QUERY_TARGET: &str = "sqlx::query";
+
+/// Top-level modules of the crate, see `lib.rs`.
+const CRATE_MODULES: &[&str] = &[
+    "api",
+    "auth",
+    "config",
+    "database",
+    "errors",
+    "middleware",
+    "repositories",
+    "services",
+    "utils",
+];
+
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+static SLOW_STATEMENTS: AtomicU64 = AtomicU64::new(0);
+
+/// Counts the warnings sqlx logs about slow statements.
+struct SlowStatementCounter;
+
+impl<S: Subscriber> Layer<S> for SlowStatementCounter {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if metadata.target() == SQLX_QUERY_TARGET && *metadata.level() == Level::WARN {
+            SLOW_STATEMENTS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Installs the global subscriber, logging to stdout.
+pub fn init() {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let (filter, handle) = reload::Layer::new(filter);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer())
+        .with(SlowStatementCounter)
+        .init();
+    let _ = FILTER.set(handle);
+}
+
+/// Prefixes the targets of directives naming a crate module with the crate name.
+pub fn qualify_directives(directives: &str) -> String {
+    directives
+        .split(',')
+        .map(str::trim)
+        .filter(|directive| !directive.is_empty())
+        .map(|directive| {
+            let target_end = directive.find(['=', '[']).unwrap_or(directive.len());
+            let root = directive[..target_end]
+                .split("::")
+                .next()
+                .unwrap_or_default();
+            if CRATE_MODULES.contains(&root) {
+                format!("{}::{directive}", env!("CARGO_CRATE_NAME"))
+            } else {
+                directive.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Slow database statements logged since the server started.
+pub fn slow_statements() -> u64 {
+    SLOW_STATEMENTS.load(Ordering::Relaxed)
+}
+
+/// The filter in effect, `None` before `init`.
+pub fn current_filter() -> Option<String> {
+    FILTER.get()?.with_current(|filter| filter.to_string()).ok()
+}
+
+/// Replaces the filter, returning the one now in effect.
+pub fn set_filter(directives: &str) -> Result<String, String> {
+    let handle = FILTER.get().ok_or("Logging is not initialized")?;
+    let directives = qualify_directives(directives);
+    if directives.is_empty() {
+        return Err("The filter needs at least one directive".to_string());
+    }
+    let filter = EnvFilter::builder()
+        .parse(&directives)
+        .map_err(|e| format!("Invalid filter: {e}"))?;
+    handle.reload(filter).map_err(|e| e.to_string())?;
+    Ok(current_filter().unwrap_or(directives))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn qualifies_crate_modules_only() {
+        assert_eq!(
+            qualify_directives("info, services::node_manager=debug,tower_http=warn"),
+            "info,backend::services::node_manager=debug,tower_http=warn"
+        );
+        assert_eq!(
+            qualify_directives("api[request{id=1}]=trace"),
+            "backend::api[request{id=1}]=trace"
+        );
+        assert_eq!(
+            qualify_directives("backend::utils=debug"),
+            "backend::utils=debug"
+        );
+    }
+}
@@ -0,0 +1,587 @@
+//! LND macaroon inspection and permission requirements.
+//!
+//! Decodes the operations baked into an LND macaroon so scoped macaroons can be
+//! validated at connect time, and describes the minimal permission set NodeGaze
+//! needs for each feature. Macaroons constrained with a timeout carry a
+//! `time-before` caveat, which is read to tell when they expire.
+
+use crate::errors::LightningError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A gRPC method NodeGaze calls, with the entity/action pairs LND requires for it.
+struct RequiredMethod {
+    uri: &'static str,
+    permissions: &'static [(&'static str, &'static str)],
+}
+
+/// Methods used for monitoring: node info, channels, payments, invoices and events.
+const READ_ONLY_METHODS: &[RequiredMethod] = &[
+    RequiredMethod {
+        uri: "/lnrpc.Lightning/GetInfo",
+        permissions: &[("info", "read")],
+    },
+    RequiredMethod {
+        uri: "/lnrpc.Lightning/ListChannels",
+        permissions: &[("offchain", "read")],
+    },
+    RequiredMethod {
+        uri: "/lnrpc.Lightning/DescribeGraph",
+        permissions: &[("info", "read")],
+    },
+    RequiredMethod {
+        uri: "/lnrpc.Lightning/GetChanInfo",
+        permissions: &[("info", "read")],
+    },
+    RequiredMethod {
+        uri: "/lnrpc.Lightning/ListPayments",
+        permissions: &[("offchain", "read")],
+    },
+    RequiredMethod {
+        uri: "/lnrpc.Lightning/ListInvoices",
+        permissions: &[("invoices", "read")],
+    },
+    RequiredMethod {
+        uri: "/lnrpc.Lightning/SubscribeInvoices",
+        permissions: &[("invoices", "read")],
+    },
+    RequiredMethod {
+        uri: "/lnrpc.Lightning/SubscribeChannelEvents",
+        permissions: &[("offchain", "read")],
+    },
+    RequiredMethod {
+        uri: "/lnrpc.Lightning/ForwardingHistory",
+        permissions: &[("offchain", "read")],
+    },
+    RequiredMethod {
+        uri: "/lnrpc.Lightning/WalletBalance",
+        permissions: &[("onchain", "read")],
+    },
+    RequiredMethod {
+        uri: "/lnrpc.Lightning/GetTransactions",
+        permissions: &[("onchain", "read")],
+    },
+];
+
+/// Methods used to change channel state and routing policy.
+const CHANNEL_MANAGEMENT_METHODS: &[RequiredMethod] = &[
+    RequiredMethod {
+        uri: "/lnrpc.Lightning/UpdateChannelPolicy",
+        permissions: &[("offchain", "write")],
+    },
+    RequiredMethod {
+        uri: "/lnrpc.Lightning/ConnectPeer",
+        permissions: &[("peers", "write")],
+    },
+    RequiredMethod {
+        uri: "/lnrpc.Lightning/OpenChannelSync",
+        permissions: &[("onchain", "write"), ("offchain", "write")],
+    },
+    RequiredMethod {
+        uri: "/lnrpc.Lightning/CloseChannel",
+        permissions: &[("onchain", "write"), ("offchain", "write")],
+    },
+];
+
+/// Methods used to decide on inbound channel requests.
+const CHANNEL_ACCEPTOR_METHODS: &[RequiredMethod] = &[RequiredMethod {
+    uri: "/lnrpc.Lightning/ChannelAcceptor",
+    permissions: &[("onchain", "write"), ("offchain", "write")],
+}];
+
+/// A NodeGaze feature that needs its own set of macaroon permissions.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MacaroonFeature {
+    /// Monitoring only; never changes node state.
+    #[default]
+    ReadOnly,
+    /// Opening, closing and updating channels on top of monitoring.
+    ChannelManagement,
+    /// Accepting or rejecting inbound channel requests on top of monitoring.
+    ChannelAcceptor,
+}
+
+impl MacaroonFeature {
+    pub const ALL: [MacaroonFeature; 3] = [
+        MacaroonFeature::ReadOnly,
+        MacaroonFeature::ChannelManagement,
+        MacaroonFeature::ChannelAcceptor,
+    ];
+
+    fn required_methods(&self) -> &'static [RequiredMethod] {
+        match self {
+            MacaroonFeature::ReadOnly => READ_ONLY_METHODS,
+            MacaroonFeature::ChannelManagement => CHANNEL_MANAGEMENT_METHODS,
+            MacaroonFeature::ChannelAcceptor => CHANNEL_ACCEPTOR_METHODS,
+        }
+    }
+
+    /// Features that must be granted for this one to be usable.
+    fn with_prerequisites(&self) -> &'static [MacaroonFeature] {
+        match self {
+            MacaroonFeature::ReadOnly => &[MacaroonFeature::ReadOnly],
+            MacaroonFeature::ChannelManagement => &[
+                MacaroonFeature::ReadOnly,
+                MacaroonFeature::ChannelManagement,
+            ],
+            MacaroonFeature::ChannelAcceptor => {
+                &[MacaroonFeature::ReadOnly, MacaroonFeature::ChannelAcceptor]
+            }
+        }
+    }
+}
+
+/// Whether a macaroon grants a feature, and which method URIs it is missing if not.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeaturePermissions {
+    pub feature: MacaroonFeature,
+    pub granted: bool,
+    pub missing_uris: Vec<String>,
+}
+
+/// Per-feature permission summary for a macaroon.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MacaroonPermissionReport {
+    pub features: Vec<FeaturePermissions>,
+}
+
+impl MacaroonPermissionReport {
+    /// Returns true when every method the feature uses is allowed.
+    pub fn is_granted(&self, feature: MacaroonFeature) -> bool {
+        self.features
+            .iter()
+            .any(|entry| entry.feature == feature && entry.granted)
+    }
+
+    /// Method URIs the macaroon is missing for a feature.
+    pub fn missing_uris(&self, feature: MacaroonFeature) -> Vec<String> {
+        self.features
+            .iter()
+            .find(|entry| entry.feature == feature)
+            .map(|entry| entry.missing_uris.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// The `lncli bakemacaroon` invocation for the minimal permission set of a feature.
+#[derive(Debug, Clone, Serialize)]
+pub struct MacaroonBakeCommand {
+    pub feature: MacaroonFeature,
+    pub uris: Vec<String>,
+    pub permissions: Vec<String>,
+    pub command: String,
+}
+
+/// A single operation baked into a macaroon, e.g. `offchain` with `["read"]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MacaroonOp {
+    pub entity: String,
+    pub actions: Vec<String>,
+}
+
+/// Reads a macaroon file and reports which features it grants.
+pub async fn check_macaroon_file(path: &str) -> Result<MacaroonPermissionReport, LightningError> {
+    let raw = tokio::fs::read(path)
+        .await
+        .map_err(|e| LightningError::ValidationError(format!("Cannot read macaroon: {e}")))?;
+    let ops = decode_macaroon_ops(&raw)?;
+    Ok(permission_report(&ops))
+}
+
+/// Reads a macaroon file and returns when its `time-before` caveats expire it.
+pub async fn read_macaroon_expiry(path: &str) -> Result<Option<DateTime<Utc>>, LightningError> {
+    let raw = tokio::fs::read(path)
+        .await
+        .map_err(|e| LightningError::ValidationError(format!("Cannot read macaroon: {e}")))?;
+    macaroon_expiry(&raw)
+}
+
+/// Builds the per-feature permission report for a set of macaroon operations.
+pub fn permission_report(ops: &[MacaroonOp]) -> MacaroonPermissionReport {
+    let features = MacaroonFeature::ALL
+        .iter()
+        .map(|feature| {
+            let missing_uris: Vec<String> = feature
+                .required_methods()
+                .iter()
+                .filter(|method| !allows(ops, method))
+                .map(|method| method.uri.to_string())
+                .collect();
+
+            FeaturePermissions {
+                feature: *feature,
+                granted: missing_uris.is_empty(),
+                missing_uris,
+            }
+        })
+        .collect();
+
+    MacaroonPermissionReport { features }
+}
+
+/// Returns the bake command for a feature, scoped to the exact method URIs it uses.
+pub fn bake_command(feature: MacaroonFeature) -> MacaroonBakeCommand {
+    let methods = feature
+        .with_prerequisites()
+        .iter()
+        .flat_map(|feature| feature.required_methods());
+
+    let mut uris: Vec<String> = Vec::new();
+    let mut permissions: Vec<String> = Vec::new();
+    for method in methods {
+        if !uris.iter().any(|uri| uri == method.uri) {
+            uris.push(method.uri.to_string());
+        }
+        for (entity, action) in method.permissions {
+            let permission = format!("{entity}:{action}");
+            if !permissions.contains(&permission) {
+                permissions.push(permission);
+            }
+        }
+    }
+
+    let command = format!(
+        "lncli bakemacaroon --save_to=nodegaze.macaroon {}",
+        uris.iter()
+            .map(|uri| format!("uri:{uri}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    );
+
+    MacaroonBakeCommand {
+        feature,
+        uris,
+        permissions,
+        command,
+    }
+}
+
+/// A method is allowed either by an explicit `uri` operation or by all of its
+/// entity/action pairs, mirroring LND's own check.
+fn allows(ops: &[MacaroonOp], method: &RequiredMethod) -> bool {
+    let has = |entity: &str, action: &str| {
+        ops.iter()
+            .any(|op| op.entity == entity && op.actions.iter().any(|a| a == action))
+    };
+
+    has("uri", method.uri)
+        || method
+            .permissions
+            .iter()
+            .all(|(entity, action)| has(entity, action))
+}
+
+/// Decodes the operations from a binary (or hex encoded) LND macaroon.
+///
+/// LND macaroons use the v2 binary format, and their identifier is a version 3
+/// bakery id: a `0x03` byte followed by a protobuf `MacaroonId` whose third field
+/// holds the granted operations.
+pub fn decode_macaroon_ops(raw: &[u8]) -> Result<Vec<MacaroonOp>, LightningError> {
+    let bytes = macaroon_bytes(raw)?;
+    let identifier = macaroon_identifier(&bytes)?;
+    let (version, id) = identifier
+        .split_first()
+        .ok_or_else(|| invalid("empty identifier"))?;
+    if *version != 3 {
+        return Err(invalid(&format!(
+            "unsupported identifier version {version}"
+        )));
+    }
+
+    let mut ops = Vec::new();
+    for (field, value) in protobuf_fields(id)? {
+        if field != 3 {
+            continue;
+        }
+        let mut op = MacaroonOp {
+            entity: String::new(),
+            actions: Vec::new(),
+        };
+        for (op_field, op_value) in protobuf_fields(value)? {
+            let text = String::from_utf8_lossy(op_value).into_owned();
+            match op_field {
+                1 => op.entity = text,
+                2 => op.actions.push(text),
+                _ => {}
+            }
+        }
+        ops.push(op);
+    }
+
+    Ok(ops)
+}
+
+/// Returns the earliest time a binary (or hex encoded) macaroon's first-party
+/// `time-before` caveats allow, `None` when it has none.
+pub fn macaroon_expiry(raw: &[u8]) -> Result<Option<DateTime<Utc>>, LightningError> {
+    const FIELD_IDENTIFIER: u64 = 2;
+    const FIELD_VERIFICATION_ID: u64 = 4;
+    const TIME_BEFORE: &str = "time-before ";
+
+    let bytes = macaroon_bytes(raw)?;
+    let (version, rest) = bytes
+        .split_first()
+        .ok_or_else(|| invalid("empty macaroon"))?;
+    if *version != 2 {
+        return Err(invalid("only v2 binary macaroons are supported"));
+    }
+
+    // The header is followed by one section per caveat and an empty section
+    let (_header, mut rest) = read_section(rest)?;
+    let mut expiry: Option<DateTime<Utc>> = None;
+    while rest.first().is_some_and(|byte| *byte != 0) {
+        let (caveat, remaining) = read_section(rest)?;
+        rest = remaining;
+
+        // Third-party caveats carry a verification id and are checked elsewhere
+        if caveat
+            .iter()
+            .any(|(field, _)| *field == FIELD_VERIFICATION_ID)
+        {
+            continue;
+        }
+        let Some((_, id)) = caveat.iter().find(|(field, _)| *field == FIELD_IDENTIFIER) else {
+            continue;
+        };
+        let Some(time) = std::str::from_utf8(id)
+            .ok()
+            .and_then(|id| id.strip_prefix(TIME_BEFORE))
+        else {
+            continue;
+        };
+        let time = DateTime::parse_from_rfc3339(time.trim())
+            .map_err(|_| invalid(&format!("bad time-before caveat {time}")))?
+            .with_timezone(&Utc);
+        expiry = Some(expiry.map_or(time, |expiry| expiry.min(time)));
+    }
+
+    Ok(expiry)
+}
+
+/// Decodes a macaroon given in binary or hex.
+fn macaroon_bytes(raw: &[u8]) -> Result<Vec<u8>, LightningError> {
+    let trimmed = raw.trim_ascii();
+    if trimmed.iter().all(u8::is_ascii_hexdigit) {
+        hex::decode(trimmed).map_err(|e| invalid(&format!("bad hex encoding: {e}")))
+    } else {
+        Ok(trimmed.to_vec())
+    }
+}
+
+/// `(field type, data)` pairs of a v2 macaroon section.
+type SectionFields<'a> = Vec<(u64, &'a [u8])>;
+
+/// Splits the fields of a v2 macaroon section off its bytes, up to the
+/// section's end-of-section marker.
+fn read_section(mut bytes: &[u8]) -> Result<(SectionFields<'_>, &[u8]), LightningError> {
+    let mut fields = Vec::new();
+    loop {
+        let (field_type, after_type) = read_varint(bytes)?;
+        if field_type == 0 {
+            return Ok((fields, after_type));
+        }
+        let (len, after_len) = read_varint(after_type)?;
+        let len = len as usize;
+        if after_len.len() < len {
+            return Err(invalid("truncated field"));
+        }
+        let (data, remaining) = after_len.split_at(len);
+        fields.push((field_type, data));
+        bytes = remaining;
+    }
+}
+
+/// Extracts the identifier field from the header section of a v2 binary macaroon.
+fn macaroon_identifier(bytes: &[u8]) -> Result<&[u8], LightningError> {
+    const VERSION_2: u8 = 2;
+    const FIELD_EOS: u64 = 0;
+    const FIELD_IDENTIFIER: u64 = 2;
+
+    let (version, mut rest) = bytes
+        .split_first()
+        .ok_or_else(|| invalid("empty macaroon"))?;
+    if *version != VERSION_2 {
+        return Err(invalid("only v2 binary macaroons are supported"));
+    }
+
+    loop {
+        let (field_type, after_type) = read_varint(rest)?;
+        if field_type == FIELD_EOS {
+            return Err(invalid("missing identifier"));
+        }
+        let (len, after_len) = read_varint(after_type)?;
+        let len = len as usize;
+        if after_len.len() < len {
+            return Err(invalid("truncated field"));
+        }
+        let (data, remaining) = after_len.split_at(len);
+        if field_type == FIELD_IDENTIFIER {
+            return Ok(data);
+        }
+        rest = remaining;
+    }
+}
+
+/// Splits a protobuf message into `(field number, value)` pairs, keeping only
+/// length-delimited fields and skipping everything else.
+fn protobuf_fields(mut bytes: &[u8]) -> Result<Vec<(u64, &[u8])>, LightningError> {
+    let mut fields = Vec::new();
+    while !bytes.is_empty() {
+        let (key, rest) = read_varint(bytes)?;
+        let (field, wire_type) = (key >> 3, key & 0x7);
+        bytes = match wire_type {
+            0 => read_varint(rest)?.1,
+            1 => rest.get(8..).ok_or_else(|| invalid("truncated fixed64"))?,
+            2 => {
+                let (len, rest) = read_varint(rest)?;
+                let len = len as usize;
+                if rest.len() < len {
+                    return Err(invalid("truncated length-delimited field"));
+                }
+                let (value, rest) = rest.split_at(len);
+                fields.push((field, value));
+                rest
+            }
+            5 => rest.get(4..).ok_or_else(|| invalid("truncated fixed32"))?,
+            _ => return Err(invalid(&format!("unsupported wire type {wire_type}"))),
+        };
+    }
+    Ok(fields)
+}
+
+fn read_varint(bytes: &[u8]) -> Result<(u64, &[u8]), LightningError> {
+    let mut value = 0u64;
+    for (i, byte) in bytes.iter().enumerate().take(10) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, &bytes[i + 1..]));
+        }
+    }
+    Err(invalid("malformed varint"))
+}
+
+fn invalid(reason: &str) -> LightningError {
+    LightningError::Parse(format!("Invalid macaroon: {reason}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varint(out: &mut Vec<u8>, mut value: usize) {
+        while value >= 0x80 {
+            out.push((value as u8 & 0x7f) | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    fn field(out: &mut Vec<u8>, number: u8, value: &[u8]) {
+        out.push((number << 3) | 2);
+        varint(out, value.len());
+        out.extend_from_slice(value);
+    }
+
+    /// Builds a minimal v2 macaroon whose identifier grants the given operations.
+    fn macaroon_with(ops: &[(&str, &[&str])]) -> Vec<u8> {
+        macaroon_with_caveats(ops, &[])
+    }
+
+    /// Builds a minimal v2 macaroon with first-party caveats.
+    fn macaroon_with_caveats(ops: &[(&str, &[&str])], caveats: &[&str]) -> Vec<u8> {
+        let mut id = vec![3u8];
+        field(&mut id, 1, b"nonce");
+        for (entity, actions) in ops {
+            let mut op = Vec::new();
+            field(&mut op, 1, entity.as_bytes());
+            for action in *actions {
+                field(&mut op, 2, action.as_bytes());
+            }
+            field(&mut id, 3, &op);
+        }
+
+        let mut macaroon = vec![2u8, 2];
+        varint(&mut macaroon, id.len());
+        macaroon.extend_from_slice(&id);
+        macaroon.push(0);
+        for caveat in caveats {
+            macaroon.extend_from_slice(&[2, caveat.len() as u8]);
+            macaroon.extend_from_slice(caveat.as_bytes());
+            macaroon.push(0);
+        }
+        macaroon.extend_from_slice(&[0, 6, 1, 0]);
+        macaroon
+    }
+
+    #[test]
+    fn decodes_operations_from_binary_and_hex() {
+        let raw = macaroon_with(&[("offchain", &["read", "write"]), ("info", &["read"])]);
+        let expected = vec![
+            MacaroonOp {
+                entity: "offchain".to_string(),
+                actions: vec!["read".to_string(), "write".to_string()],
+            },
+            MacaroonOp {
+                entity: "info".to_string(),
+                actions: vec!["read".to_string()],
+            },
+        ];
+
+        assert_eq!(decode_macaroon_ops(&raw).unwrap(), expected);
+        assert_eq!(
+            decode_macaroon_ops(hex::encode(&raw).as_bytes()).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn read_only_macaroon_lacks_channel_management() {
+        let raw = macaroon_with(&[
+            ("info", &["read"]),
+            ("offchain", &["read"]),
+            ("onchain", &["read"]),
+            ("invoices", &["read"]),
+        ]);
+        let report = permission_report(&decode_macaroon_ops(&raw).unwrap());
+
+        assert!(report.is_granted(MacaroonFeature::ReadOnly));
+        assert!(!report.is_granted(MacaroonFeature::ChannelManagement));
+        assert!(
+            report
+                .missing_uris(MacaroonFeature::ChannelManagement)
+                .contains(&"/lnrpc.Lightning/UpdateChannelPolicy".to_string())
+        );
+    }
+
+    #[test]
+    fn earliest_time_before_caveat_expires_the_macaroon() {
+        let raw = macaroon_with_caveats(
+            &[("info", &["read"])],
+            &[
+                "time-before 2026-03-01T00:00:00Z",
+                "ipaddr 10.0.0.1",
+                "time-before 2025-12-24T18:30:00.123456789Z",
+            ],
+        );
+
+        assert_eq!(
+            macaroon_expiry(&raw).unwrap().unwrap().to_rfc3339(),
+            "2025-12-24T18:30:00.123456789+00:00"
+        );
+        assert_eq!(
+            macaroon_expiry(&macaroon_with(&[("info", &["read"])])).unwrap(),
+            None
+        );
+        assert_eq!(decode_macaroon_ops(&raw).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn uri_scoped_macaroon_is_accepted() {
+        let uris: Vec<&str> = READ_ONLY_METHODS.iter().map(|method| method.uri).collect();
+        let raw = macaroon_with(&[("uri", &uris)]);
+        let report = permission_report(&decode_macaroon_ops(&raw).unwrap());
+
+        assert!(report.is_granted(MacaroonFeature::ReadOnly));
+    }
+}
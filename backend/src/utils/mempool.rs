@@ -0,0 +1,226 @@
+//! On-chain transaction context from a mempool.space compatible explorer API.
+//!
+//! Lookups are best effort: when the explorer is unreachable the last known
+//! status is served from cache, or `None` is returned so callers can carry on
+//! without on-chain data.
+
+use crate::config;
+use crate::utils::OnchainTxStatus;
+use bitcoin::Txid;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// Default explorer API, used unless `MEMPOOL_API_URL` is set.
+pub const DEFAULT_MEMPOOL_API_URL: &str = "https://mempool.space/api";
+
+/// Confirmed transactions rarely change, unconfirmed ones may confirm any block.
+const CONFIRMED_TX_TTL: Duration = Duration::from_secs(3600);
+const UNCONFIRMED_TX_TTL: Duration = Duration::from_secs(30);
+const TIP_HEIGHT_TTL: Duration = Duration::from_secs(60);
+
+const EXPLORER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Explorer client shared by all requests so its cache outlives node clients.
+static MEMPOOL: LazyLock<MempoolClient> = LazyLock::new(|| {
+    let base_url = config::shared()
+        .map(|config| config.mempool_api_url.clone())
+        .unwrap_or_else(|_| Some(DEFAULT_MEMPOOL_API_URL.to_string()));
+    MempoolClient::new(base_url)
+});
+
+/// Returns the shared explorer client.
+pub fn mempool() -> &'static MempoolClient {
+    &MEMPOOL
+}
+
+#[derive(Debug, Deserialize)]
+struct ExplorerTx {
+    fee: u64,
+    weight: u64,
+    status: ExplorerTxStatus,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExplorerTxStatus {
+    confirmed: bool,
+    block_height: Option<u32>,
+    block_time: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoricalPrices {
+    prices: Vec<HistoricalPrice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoricalPrice {
+    #[serde(rename = "USD")]
+    usd: f64,
+}
+
+impl ExplorerTx {
+    fn status(self, txid: Txid) -> OnchainTxStatus {
+        let vsize = self.weight.div_ceil(4);
+        let fee_rate = if vsize > 0 {
+            (self.fee as f64 / vsize as f64 * 100.0).round() / 100.0
+        } else {
+            0.0
+        };
+
+        OnchainTxStatus {
+            txid,
+            confirmed: self.status.confirmed,
+            block_height: self.status.block_height,
+            block_time: self.status.block_time,
+            confirmations: None,
+            fee_sat: self.fee,
+            vsize,
+            fee_rate_sat_vb: fee_rate,
+        }
+    }
+}
+
+/// Cached client for a mempool.space compatible REST API.
+pub struct MempoolClient {
+    /// API base URL, `None` when explorer lookups are disabled
+    base_url: Option<String>,
+    client: reqwest::Client,
+    transactions: Mutex<HashMap<Txid, (Instant, OnchainTxStatus)>>,
+    tip_height: Mutex<Option<(Instant, u32)>>,
+}
+
+impl MempoolClient {
+    pub fn new(base_url: Option<String>) -> Self {
+        Self {
+            base_url: base_url.map(|url| url.trim_end_matches('/').to_string()),
+            client: reqwest::Client::new(),
+            transactions: Mutex::new(HashMap::new()),
+            tip_height: Mutex::new(None),
+        }
+    }
+
+    /// Looks up a transaction's confirmation status and fee.
+    pub async fn tx_status(&self, txid: &Txid) -> Option<OnchainTxStatus> {
+        let base_url = self.base_url.as_ref()?;
+
+        let cached = self.transactions.lock().unwrap().get(txid).cloned();
+        let fresh = cached.as_ref().filter(|(fetched_at, status)| {
+            let ttl = if status.confirmed {
+                CONFIRMED_TX_TTL
+            } else {
+                UNCONFIRMED_TX_TTL
+            };
+            fetched_at.elapsed() < ttl
+        });
+
+        let status = match fresh {
+            Some((_, status)) => status.clone(),
+            None => match self
+                .get::<ExplorerTx>(&format!("{base_url}/tx/{txid}"))
+                .await
+            {
+                Ok(tx) => {
+                    let status = tx.status(*txid);
+                    self.transactions
+                        .lock()
+                        .unwrap()
+                        .insert(*txid, (Instant::now(), status.clone()));
+                    status
+                }
+                Err(e) => {
+                    tracing::debug!("Explorer lookup of {} failed: {}", txid, e);
+                    cached?.1
+                }
+            },
+        };
+
+        Some(status.with_tip(self.tip_height(base_url).await))
+    }
+
+    /// Looks up the USD price of one bitcoin at a past Unix time.
+    ///
+    /// Not cached here; callers keep the daily closes they need.
+    pub async fn historical_usd_price(&self, timestamp: i64) -> Option<f64> {
+        let base_url = self.base_url.as_ref()?;
+        let url = format!("{base_url}/v1/historical-price?currency=USD&timestamp={timestamp}");
+
+        match self.get::<HistoricalPrices>(&url).await {
+            Ok(history) => history
+                .prices
+                .first()
+                .map(|price| price.usd)
+                .filter(|usd| *usd > 0.0),
+            Err(e) => {
+                tracing::debug!("Explorer price lookup at {} failed: {}", timestamp, e);
+                None
+            }
+        }
+    }
+
+    async fn tip_height(&self, base_url: &str) -> Option<u32> {
+        let cached = *self.tip_height.lock().unwrap();
+        if let Some((_, height)) =
+            cached.filter(|(fetched_at, _)| fetched_at.elapsed() < TIP_HEIGHT_TTL)
+        {
+            return Some(height);
+        }
+
+        match self
+            .get::<u32>(&format!("{base_url}/blocks/tip/height"))
+            .await
+        {
+            Ok(height) => {
+                *self.tip_height.lock().unwrap() = Some((Instant::now(), height));
+                Some(height)
+            }
+            Err(e) => {
+                tracing::debug!("Explorer tip height lookup failed: {}", e);
+                cached.map(|(_, height)| height)
+            }
+        }
+    }
+
+    async fn get<T: for<'de> Deserialize<'de>>(&self, url: &str) -> Result<T, reqwest::Error> {
+        self.client
+            .get(url)
+            .timeout(EXPLORER_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn derives_fee_rate_and_confirmations() {
+        let txid =
+            Txid::from_str("4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b")
+                .unwrap();
+        let tx: ExplorerTx = serde_json::from_str(
+            r#"{"fee":1410,"weight":561,"status":{"confirmed":true,"block_height":800000,"block_time":1690168629}}"#,
+        )
+        .unwrap();
+
+        let status = tx.status(txid).with_tip(Some(800_005));
+        assert_eq!(status.vsize, 141);
+        assert_eq!(status.fee_rate_sat_vb, 10.0);
+        assert_eq!(status.confirmations, Some(6));
+        assert_eq!(status.with_tip(None).confirmations, None);
+    }
+
+    #[tokio::test]
+    async fn disabled_client_returns_nothing() {
+        let txid =
+            Txid::from_str("4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b")
+                .unwrap();
+        assert!(MempoolClient::new(None).tx_status(&txid).await.is_none());
+    }
+}
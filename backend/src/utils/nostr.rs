@@ -0,0 +1,237 @@
+//! Minimal Nostr client for delivering alerts as encrypted direct messages.
+//!
+//! Implements just enough of NIP-01 (signed events and relay publishing),
+//! NIP-04 (encrypted direct messages) and NIP-19 (`npub`/`nsec` keys) to send a
+//! text message from the server's key to one recipient.
+
+use aes::cipher::{BlockEncryptMut, KeyIvInit, block_padding::Pkcs7};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use bitcoin::bech32;
+use bitcoin::hashes::{Hash, sha256};
+use bitcoin::secp256k1::{Keypair, Message, Parity, Secp256k1, SecretKey, XOnlyPublicKey, ecdh};
+use futures::{SinkExt, StreamExt};
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+/// Event kind of NIP-04 encrypted direct messages.
+pub const ENCRYPTED_DM_KIND: u16 = 4;
+
+/// How long to wait for a relay to acknowledge a published event.
+const RELAY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A signed Nostr event as sent to relays.
+#[derive(Debug, Clone, Serialize)]
+pub struct NostrEvent {
+    pub id: String,
+    pub pubkey: String,
+    pub created_at: i64,
+    pub kind: u16,
+    pub tags: Vec<Vec<String>>,
+    pub content: String,
+    pub sig: String,
+}
+
+/// Decodes a bech32 NIP-19 entity with the expected prefix into its payload.
+fn decode_bech32(value: &str, expected_hrp: &str) -> Result<Vec<u8>, String> {
+    let (hrp, data) =
+        bech32::decode(value).map_err(|err| format!("Invalid {expected_hrp}: {err}"))?;
+    if hrp.to_lowercase() != expected_hrp {
+        return Err(format!(
+            "Expected an {expected_hrp}, got {}",
+            hrp.to_lowercase()
+        ));
+    }
+    Ok(data)
+}
+
+/// Parses a public key given as an `npub` or 64 hex characters.
+pub fn parse_public_key(value: &str) -> Result<XOnlyPublicKey, String> {
+    let value = value.trim();
+    let bytes = if value.starts_with("npub1") {
+        decode_bech32(value, "npub")?
+    } else {
+        hex::decode(value).map_err(|_| "Public key must be an npub or hex".to_string())?
+    };
+    XOnlyPublicKey::from_slice(&bytes).map_err(|err| format!("Invalid public key: {err}"))
+}
+
+/// Parses a secret key given as an `nsec` or 64 hex characters.
+pub fn parse_secret_key(value: &str) -> Result<SecretKey, String> {
+    let value = value.trim();
+    let bytes = if value.starts_with("nsec1") {
+        decode_bech32(value, "nsec")?
+    } else {
+        hex::decode(value).map_err(|_| "Secret key must be an nsec or hex".to_string())?
+    };
+    SecretKey::from_slice(&bytes).map_err(|err| format!("Invalid secret key: {err}"))
+}
+
+/// The NIP-04 shared secret: the x coordinate of the ECDH point, unhashed.
+fn shared_secret(secret_key: &SecretKey, public_key: &XOnlyPublicKey) -> [u8; 32] {
+    let point = ecdh::shared_secret_point(&public_key.public_key(Parity::Even), secret_key);
+    let mut secret = [0u8; 32];
+    secret.copy_from_slice(&point[..32]);
+    secret
+}
+
+/// Encrypts a direct message as `<base64 ciphertext>?iv=<base64 iv>`.
+pub fn encrypt_dm(secret_key: &SecretKey, recipient: &XOnlyPublicKey, plaintext: &str) -> String {
+    let key = shared_secret(secret_key, recipient);
+    let iv: [u8; 16] = rand::random();
+    let ciphertext = cbc::Encryptor::<aes::Aes256>::new(&key.into(), &iv.into())
+        .encrypt_padded_vec_mut::<Pkcs7>(plaintext.as_bytes());
+
+    format!("{}?iv={}", STANDARD.encode(ciphertext), STANDARD.encode(iv))
+}
+
+/// Builds and signs an event with the given key.
+pub fn sign_event(
+    secret_key: &SecretKey,
+    kind: u16,
+    tags: Vec<Vec<String>>,
+    content: String,
+    created_at: i64,
+) -> NostrEvent {
+    let secp = Secp256k1::new();
+    let keypair = Keypair::from_secret_key(&secp, secret_key);
+    let pubkey = keypair.x_only_public_key().0.to_string();
+
+    let serialized = json!([0, pubkey, created_at, kind, tags, content]).to_string();
+    let id = sha256::Hash::hash(serialized.as_bytes()).to_byte_array();
+    let aux_rand: [u8; 32] = rand::random();
+    let sig = secp.sign_schnorr_with_aux_rand(&Message::from_digest(id), &keypair, &aux_rand);
+
+    NostrEvent {
+        id: hex::encode(id),
+        pubkey,
+        created_at,
+        kind,
+        tags,
+        content,
+        sig: sig.to_string(),
+    }
+}
+
+/// Builds a signed NIP-04 direct message to `recipient`.
+pub fn encrypted_dm(
+    secret_key: &SecretKey,
+    recipient: &XOnlyPublicKey,
+    message: &str,
+) -> NostrEvent {
+    sign_event(
+        secret_key,
+        ENCRYPTED_DM_KIND,
+        vec![vec!["p".to_string(), recipient.to_string()]],
+        encrypt_dm(secret_key, recipient, message),
+        chrono::Utc::now().timestamp(),
+    )
+}
+
+/// Publishes an event to a relay and waits for it to be accepted.
+pub async fn publish(relay_url: &str, event: &NostrEvent) -> Result<(), String> {
+    let exchange = async {
+        let (mut socket, _) = tokio_tungstenite::connect_async(relay_url)
+            .await
+            .map_err(|err| format!("Could not connect to relay {relay_url}: {err}"))?;
+
+        socket
+            .send(WsMessage::Text(json!(["EVENT", event]).to_string()))
+            .await
+            .map_err(|err| format!("Could not send event to relay: {err}"))?;
+
+        // Relays answer with ["OK", <event id>, <accepted>, <message>]
+        while let Some(message) = socket.next().await {
+            let message = message.map_err(|err| format!("Relay connection failed: {err}"))?;
+            let WsMessage::Text(text) = message else {
+                continue;
+            };
+            let Ok(Value::Array(reply)) = serde_json::from_str::<Value>(&text) else {
+                continue;
+            };
+            if reply.first().and_then(Value::as_str) != Some("OK")
+                || reply.get(1).and_then(Value::as_str) != Some(event.id.as_str())
+            {
+                continue;
+            }
+
+            let _ = socket.close(None).await;
+            return match reply.get(2).and_then(Value::as_bool) {
+                Some(true) => Ok(()),
+                _ => Err(format!(
+                    "Relay rejected event: {}",
+                    reply
+                        .get(3)
+                        .and_then(Value::as_str)
+                        .unwrap_or("no reason given")
+                )),
+            };
+        }
+
+        Err("Relay closed the connection without acknowledging the event".to_string())
+    };
+
+    tokio::time::timeout(RELAY_TIMEOUT, exchange)
+        .await
+        .map_err(|_| format!("Relay {relay_url} did not acknowledge the event in time"))?
+}
+
+/// Checks that a relay accepts websocket connections.
+pub async fn check_relay(relay_url: &str) -> Result<(), String> {
+    let (mut socket, _) =
+        tokio::time::timeout(RELAY_TIMEOUT, tokio_tungstenite::connect_async(relay_url))
+            .await
+            .map_err(|_| format!("Relay {relay_url} did not answer in time"))?
+            .map_err(|err| format!("Could not connect to relay {relay_url}: {err}"))?;
+    let _ = socket.close(None).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes::cipher::BlockDecryptMut;
+    use bitcoin::secp256k1::schnorr::Signature;
+    use std::str::FromStr;
+
+    fn public_key_of(secret_key: &SecretKey) -> XOnlyPublicKey {
+        Keypair::from_secret_key(&Secp256k1::new(), secret_key)
+            .x_only_public_key()
+            .0
+    }
+
+    #[test]
+    fn parses_npub() {
+        let key =
+            parse_public_key("npub180cvv07tjdrrgpa0j7j7tmnyl2yr6yr7l8j4s3evf6u64th6gkwsyjh6w6")
+                .unwrap();
+        assert_eq!(
+            key.to_string(),
+            "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d"
+        );
+        assert!(parse_public_key("nsec1qqqq").is_err());
+    }
+
+    #[test]
+    fn encrypted_dm_decrypts_for_recipient() {
+        let sender = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let recipient = SecretKey::from_slice(&[2u8; 32]).unwrap();
+
+        let event = encrypted_dm(&sender, &public_key_of(&recipient), "channel closed");
+
+        let (ciphertext, iv) = event.content.split_once("?iv=").unwrap();
+        let key = shared_secret(&recipient, &public_key_of(&sender));
+        let iv: [u8; 16] = STANDARD.decode(iv).unwrap().try_into().unwrap();
+        let plaintext = cbc::Decryptor::<aes::Aes256>::new(&key.into(), &iv.into())
+            .decrypt_padded_vec_mut::<Pkcs7>(&STANDARD.decode(ciphertext).unwrap())
+            .unwrap();
+        assert_eq!(plaintext, b"channel closed");
+
+        let id: [u8; 32] = hex::decode(&event.id).unwrap().try_into().unwrap();
+        let sig = Signature::from_str(&event.sig).unwrap();
+        Secp256k1::verification_only()
+            .verify_schnorr(&sig, &Message::from_digest(id), &public_key_of(&sender))
+            .unwrap();
+    }
+}
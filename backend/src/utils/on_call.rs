@@ -0,0 +1,261 @@
+//! Payloads of the on-call integrations, PagerDuty's Events API v2 and
+//! Opsgenie's alerts API.
+//!
+//! Critical events open an incident keyed by the event type and node, plus a
+//! digest of the fields telling apart events about different channels or
+//! targets, so repeats of one condition land on the same incident. An event
+//! reporting that a condition cleared, e.g. `NodeConnected` after
+//! `NodeDisconnected`, resolves the incident under the key of the type it clears.
+
+use crate::database::models::{Event, EventSeverity, EventType};
+use bitcoin::hashes::{Hash, sha256};
+use serde_json::{Map, Value, json};
+
+/// Event data fields that tell apart incidents of one type on the same node.
+const INCIDENT_KEY_FIELDS: &[&str] = &[
+    "chan_id",
+    "channel_id",
+    "channel_point",
+    "remote_pubkey",
+    "target",
+];
+
+/// Longest Opsgenie alert message.
+const OPSGENIE_MESSAGE_CHARS: usize = 130;
+
+/// Longest PagerDuty incident summary.
+const PAGERDUTY_SUMMARY_CHARS: usize = 1024;
+
+/// What an event means for an on-call integration.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OnCallAction {
+    /// Open the incident with this key, or add to it if it is open
+    Trigger(String),
+    /// Resolve the incident with this key
+    Resolve(String),
+}
+
+/// The event type whose condition an event reports as cleared.
+pub fn cleared_event_type(event_type: &EventType) -> Option<EventType> {
+    match event_type {
+        EventType::NodeConnected => Some(EventType::NodeDisconnected),
+        EventType::LnurlCheckRecovered => Some(EventType::LnurlCheckFailed),
+        _ => None,
+    }
+}
+
+/// Key of the incident of an event's condition, reported as `event_type`.
+pub fn incident_key(event_type: &EventType, event: &Event) -> String {
+    let data = serde_json::from_str::<Value>(&event.data).unwrap_or_default();
+    let fields = INCIDENT_KEY_FIELDS
+        .iter()
+        .filter_map(|field| data.get(field).map(|value| format!("{field}={value}")))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let mut key = format!("nodegaze:{}:{}", event.node_id, event_type);
+    if !fields.is_empty() {
+        let digest = sha256::Hash::hash(fields.as_bytes()).to_string();
+        key.push(':');
+        key.push_str(&digest[..16]);
+    }
+    key
+}
+
+/// Triggers for critical events, resolves for events clearing a condition and
+/// nothing for the rest.
+pub fn on_call_action(event: &Event) -> Option<OnCallAction> {
+    if event.severity == EventSeverity::Critical {
+        let key = incident_key(&event.event_type, event);
+        return Some(OnCallAction::Trigger(key));
+    }
+    cleared_event_type(&event.event_type)
+        .map(|cleared| OnCallAction::Resolve(incident_key(&cleared, event)))
+}
+
+/// Checks a PagerDuty routing key or Opsgenie API key.
+pub fn validate_integration_key(key: &str) -> Result<(), String> {
+    if key.is_empty() || key.len() > 128 || key.chars().any(|c| c.is_whitespace()) {
+        return Err("Integration keys must be 1-128 characters without spaces".to_string());
+    }
+    Ok(())
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    text.chars().take(max_chars).collect()
+}
+
+fn node_name(event: &Event) -> &str {
+    if event.node_alias.is_empty() {
+        &event.node_id
+    } else {
+        &event.node_alias
+    }
+}
+
+/// Body of a PagerDuty Events API v2 request.
+pub fn pagerduty_event(routing_key: &str, event: &Event, action: &OnCallAction) -> Value {
+    match action {
+        OnCallAction::Trigger(dedup_key) => json!({
+            "routing_key": routing_key,
+            "event_action": "trigger",
+            "dedup_key": dedup_key,
+            "client": "NodeGaze",
+            "payload": {
+                "summary": truncate(
+                    &format!("{}: {}", event.title, event.description),
+                    PAGERDUTY_SUMMARY_CHARS,
+                ),
+                "source": node_name(event),
+                "severity": "critical",
+                "timestamp": event.timestamp.to_rfc3339(),
+                "component": event.node_id,
+                "class": event.event_type.to_string(),
+                "custom_details": serde_json::from_str::<Value>(&event.data).unwrap_or(json!({})),
+            },
+        }),
+        OnCallAction::Resolve(dedup_key) => json!({
+            "routing_key": routing_key,
+            "event_action": "resolve",
+            "dedup_key": dedup_key,
+        }),
+    }
+}
+
+/// Body of an Opsgenie request creating an alert.
+pub fn opsgenie_alert(event: &Event, alias: &str) -> Value {
+    // Opsgenie only takes string details
+    let mut details = Map::new();
+    if let Ok(Value::Object(data)) = serde_json::from_str::<Value>(&event.data) {
+        for (name, value) in data {
+            let value = match value {
+                Value::String(value) => value,
+                value => value.to_string(),
+            };
+            details.insert(name, Value::String(value));
+        }
+    }
+    details.insert("event_id".to_string(), json!(event.id));
+    details.insert("node_id".to_string(), json!(event.node_id));
+
+    json!({
+        "message": truncate(
+            &format!("{} on {}", event.title, node_name(event)),
+            OPSGENIE_MESSAGE_CHARS,
+        ),
+        "alias": alias,
+        "description": event.description,
+        "entity": event.node_id,
+        "source": "NodeGaze",
+        "priority": "P1",
+        "tags": [event.event_type.to_string()],
+        "details": details,
+    })
+}
+
+/// Body of an Opsgenie request closing an alert.
+pub fn opsgenie_close(event: &Event) -> Value {
+    json!({
+        "source": "NodeGaze",
+        "note": format!("{}: {}", event.title, event.description),
+    })
+}
+
+/// URL closing the Opsgenie alert `alias`, given the alerts URL of the endpoint.
+pub fn opsgenie_close_url(alerts_url: &str, alias: &str) -> Result<reqwest::Url, String> {
+    let mut url = reqwest::Url::parse(alerts_url).map_err(|e| e.to_string())?;
+    url.path_segments_mut()
+        .map_err(|_| "Opsgenie URL can't take a path".to_string())?
+        .pop_if_empty()
+        .push(alias)
+        .push("close");
+    url.query_pairs_mut().append_pair("identifierType", "alias");
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn event(event_type: EventType, severity: EventSeverity, data: &str) -> Event {
+        Event {
+            id: "event".to_string(),
+            account_id: "account".to_string(),
+            user_id: "user".to_string(),
+            node_id: "02abc".to_string(),
+            node_alias: "alias".to_string(),
+            event_type,
+            severity,
+            title: "LNURL Check Failed".to_string(),
+            description: "Payments may fail".to_string(),
+            data: data.to_string(),
+            timestamp: Utc::now(),
+            acknowledged_by: None,
+            acknowledged_at: None,
+            notifications_id: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            is_deleted: false,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn recoveries_resolve_the_incident_of_the_failure() {
+        let failed = event(
+            EventType::LnurlCheckFailed,
+            EventSeverity::Critical,
+            r#"{"target":"me@example.com","reason":"timeout"}"#,
+        );
+        let other_target = event(
+            EventType::LnurlCheckFailed,
+            EventSeverity::Critical,
+            r#"{"target":"you@example.com","reason":"timeout"}"#,
+        );
+        let recovered = event(
+            EventType::LnurlCheckRecovered,
+            EventSeverity::Info,
+            r#"{"target":"me@example.com"}"#,
+        );
+
+        let Some(OnCallAction::Trigger(key)) = on_call_action(&failed) else {
+            panic!("critical events trigger");
+        };
+        assert!(key.starts_with("nodegaze:02abc:lnurl_check_failed:"));
+        assert_ne!(
+            on_call_action(&other_target),
+            Some(OnCallAction::Trigger(key.clone()))
+        );
+        assert_eq!(on_call_action(&recovered), Some(OnCallAction::Resolve(key)));
+
+        let info = event(EventType::InvoiceSettled, EventSeverity::Info, "{}");
+        assert_eq!(on_call_action(&info), None);
+    }
+
+    #[test]
+    fn builds_provider_requests() {
+        let failed = event(
+            EventType::LnurlCheckFailed,
+            EventSeverity::Critical,
+            r#"{"target":"me@example.com","attempts":3}"#,
+        );
+        let trigger = OnCallAction::Trigger("key".to_string());
+
+        let body = pagerduty_event("routing", &failed, &trigger);
+        assert_eq!(body["event_action"], "trigger");
+        assert_eq!(body["dedup_key"], "key");
+        assert_eq!(body["payload"]["custom_details"]["attempts"], 3);
+
+        let body = opsgenie_alert(&failed, "key");
+        assert_eq!(body["details"]["attempts"], "3");
+        assert_eq!(body["message"], "LNURL Check Failed on alias");
+
+        let url =
+            opsgenie_close_url("https://api.opsgenie.com/v2/alerts", "nodegaze:02abc:x").unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://api.opsgenie.com/v2/alerts/nodegaze:02abc:x/close?identifierType=alias"
+        );
+    }
+}
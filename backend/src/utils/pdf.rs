@@ -0,0 +1,136 @@
+//! Minimal PDF writer for plain-text documents.
+//!
+//! Reports only need lines of text, so rather than pulling in a PDF library
+//! this writes PDF 1.4 directly: A4 pages set in the built-in Helvetica font,
+//! which every viewer provides. Characters outside Latin-1 are replaced with
+//! `?`, as the standard fonts can't show them.
+
+const PAGE_WIDTH: u32 = 595;
+const PAGE_HEIGHT: u32 = 842;
+const MARGIN: u32 = 50;
+const TITLE_SIZE: u32 = 16;
+const FONT_SIZE: u32 = 10;
+const LINE_HEIGHT: u32 = 14;
+const LINES_PER_PAGE: usize = ((PAGE_HEIGHT - 2 * MARGIN - 2 * LINE_HEIGHT) / LINE_HEIGHT) as usize;
+
+/// Escapes a line for use in a PDF string literal.
+fn pdf_string(text: &str) -> Vec<u8> {
+    let mut escaped = Vec::with_capacity(text.len() + 2);
+    escaped.push(b'(');
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                escaped.push(b'\\');
+                escaped.push(c as u8);
+            }
+            c if (' '..='\u{ff}').contains(&c) => escaped.push(c as u32 as u8),
+            _ => escaped.push(b'?'),
+        }
+    }
+    escaped.push(b')');
+    escaped
+}
+
+/// Content stream drawing the title and a page of lines.
+fn page_content(title: &str, lines: &[String]) -> Vec<u8> {
+    let top = PAGE_HEIGHT - MARGIN;
+    let mut content = format!("BT /F2 {TITLE_SIZE} Tf {MARGIN} {top} Td ").into_bytes();
+    content.extend(pdf_string(title));
+    content.extend(format!(" Tj /F1 {FONT_SIZE} Tf {LINE_HEIGHT} TL T* ").as_bytes());
+    for line in lines {
+        content.extend(b"T* ");
+        content.extend(pdf_string(line));
+        content.extend(b" Tj ");
+    }
+    content.extend(b"ET");
+    content
+}
+
+/// Renders a title and lines of text as a PDF document.
+pub fn render_text_pdf(title: &str, lines: &[String]) -> Vec<u8> {
+    let pages: Vec<&[String]> = if lines.is_empty() {
+        vec![&[]]
+    } else {
+        lines.chunks(LINES_PER_PAGE).collect()
+    };
+
+    // Objects 1-4 are the catalog, page tree and fonts; each page adds a page
+    // object and its content stream.
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 5 + 2 * i).collect();
+    let kids = page_ids
+        .iter()
+        .map(|id| format!("{id} 0 R"))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!("<< /Type /Pages /Kids [{kids}] /Count {} >>", pages.len()).into_bytes(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+            .to_vec(),
+    ];
+    for (page, id) in pages.iter().zip(&page_ids) {
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PAGE_WIDTH} {PAGE_HEIGHT}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                id + 1
+            )
+            .into_bytes(),
+        );
+        let content = page_content(title, page);
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend(content);
+        stream.extend(b"\nendstream");
+        objects.push(stream);
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n", i + 1).as_bytes());
+        pdf.extend(object);
+        pdf.extend(b"\nendobj\n");
+    }
+
+    let xref = pdf.len();
+    pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        pdf.extend(format!("{offset:010} 00000 n \n").as_bytes());
+    }
+    pdf.extend(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        )
+        .as_bytes(),
+    );
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_paged_documents() {
+        let lines: Vec<String> = (0..LINES_PER_PAGE + 1)
+            .map(|i| format!("Line {i}"))
+            .collect();
+        let pdf = render_text_pdf("Report (weekly)", &lines);
+        let text = String::from_utf8_lossy(&pdf);
+
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.ends_with("%%EOF\n"));
+        assert!(text.contains("/Count 2"));
+        assert!(text.contains("(Report \\(weekly\\)) Tj"));
+
+        // The cross-reference table points at each object
+        let first = text.find("1 0 obj").unwrap();
+        assert!(text.contains(&format!("{first:010} 00000 n")));
+        assert_eq!(pdf_string("₿ 1"), b"(? 1)");
+    }
+}
@@ -0,0 +1,116 @@
+//! Core Lightning rune inspection.
+//!
+//! A rune is base64url of a 32-byte SHA256 state followed by its restrictions, e.g.
+//! `method^list|method^get|method=summary&pnum<2`. Restrictions are joined with `&`,
+//! alternatives within a restriction with `|`, and `\` escapes either separator.
+
+use base64::Engine;
+use base64::alphabet::URL_SAFE;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+
+/// CLN writes runes with padding but some tools strip it; accept both.
+const RUNE_ENGINE: GeneralPurpose = GeneralPurpose::new(
+    &URL_SAFE,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Length of the SHA256 midstate preceding the restrictions.
+const RUNE_HASH_LEN: usize = 32;
+
+/// Methods outside the `list*`/`get*` families that cannot change node state.
+const READ_ONLY_METHODS: &[&str] = &[
+    "summary",
+    "feerates",
+    "decode",
+    "decodepay",
+    "checkmessage",
+    "waitblockheight",
+];
+
+/// Returns true when the rune only permits methods that cannot change node state.
+///
+/// A rune is read-only if any one restriction limits `method` to read-only calls,
+/// since every restriction must hold for a command to be allowed. Undecodable runes
+/// are reported as not read-only and left to the node to enforce.
+pub fn is_read_only(rune: &str) -> bool {
+    restrictions(rune).is_some_and(|restrictions| {
+        restrictions.iter().any(|alternatives| {
+            !alternatives.is_empty()
+                && alternatives
+                    .iter()
+                    .all(|alternative| is_read_only_condition(alternative))
+        })
+    })
+}
+
+/// Decodes a rune into its restrictions, each a list of alternative conditions.
+fn restrictions(rune: &str) -> Option<Vec<Vec<String>>> {
+    let bytes = RUNE_ENGINE.decode(rune.trim()).ok()?;
+    let text = std::str::from_utf8(bytes.get(RUNE_HASH_LEN..)?).ok()?;
+
+    let mut restrictions = Vec::new();
+    let mut alternatives = Vec::new();
+    let mut current = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => current.extend(chars.next()),
+            '|' => alternatives.push(std::mem::take(&mut current)),
+            '&' => {
+                alternatives.push(std::mem::take(&mut current));
+                restrictions.push(std::mem::take(&mut alternatives));
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.is_empty() || !alternatives.is_empty() {
+        alternatives.push(current);
+        restrictions.push(alternatives);
+    }
+
+    Some(restrictions)
+}
+
+/// Whether a single condition only admits read-only methods.
+fn is_read_only_condition(condition: &str) -> bool {
+    let Some(rest) = condition.strip_prefix("method") else {
+        return false;
+    };
+    let mut chars = rest.chars();
+    let operator = chars.next();
+    let value = chars.as_str();
+
+    let is_read_family = value.starts_with("list") || value.starts_with("get");
+    match operator {
+        Some('^') => is_read_family,
+        Some('=') => is_read_family || READ_ONLY_METHODS.contains(&value),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rune_with(restrictions: &str) -> String {
+        let mut bytes = vec![0u8; RUNE_HASH_LEN];
+        bytes.extend_from_slice(restrictions.as_bytes());
+        RUNE_ENGINE.encode(bytes)
+    }
+
+    #[test]
+    fn detects_read_only_runes() {
+        assert!(is_read_only(&rune_with(
+            "=0&method^list|method^get|method=summary"
+        )));
+        assert!(is_read_only(&rune_with("=0&method=getinfo&pnum=0")));
+    }
+
+    #[test]
+    fn unrestricted_and_write_runes_are_not_read_only() {
+        assert!(!is_read_only(&rune_with("=0")));
+        assert!(!is_read_only(&rune_with("=0&method^list|method=pay")));
+        assert!(!is_read_only(&rune_with("=0&method/pay")));
+        assert!(!is_read_only("not a rune"));
+    }
+}
@@ -0,0 +1,308 @@
+//! Time zone handling for date filters and statistics buckets.
+//!
+//! `from`/`to` filters take plain dates (`2025-08-01`) as well as RFC 3339
+//! instants. Dates, like day and week buckets, are read in the request's time
+//! zone: the `tz` query parameter when given, the account's `timezone` setting
+//! otherwise. A day then runs from local midnight to local midnight, however
+//! long daylight saving time makes it.
+
+use chrono::{DateTime, Duration, DurationRound, NaiveDate, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Parses an IANA time zone name such as `Europe/Berlin`.
+pub fn parse_tz(tz: &str) -> Result<Tz, String> {
+    tz.parse()
+        .map_err(|_| format!("Unknown time zone '{tz}', expected e.g. Europe/Berlin"))
+}
+
+/// Midnight at the start of a local date, as UTC.
+pub fn local_midnight(tz: Tz, date: NaiveDate) -> Option<DateTime<Utc>> {
+    tz.from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .earliest()
+        .map(|midnight| midnight.with_timezone(&Utc))
+}
+
+/// One end of a date filter: an exact instant or a whole local day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateBound {
+    Instant(DateTime<Utc>),
+    Date(NaiveDate),
+}
+
+impl DateBound {
+    /// First instant covered when used as a `from` bound.
+    pub fn start(self, tz: Tz) -> DateTime<Utc> {
+        match self {
+            DateBound::Instant(at) => at,
+            DateBound::Date(date) => local_midnight(tz, date)
+                .unwrap_or_else(|| date.and_time(Default::default()).and_utc()),
+        }
+    }
+
+    /// Last instant covered when used as a `to` bound; dates include their whole day.
+    pub fn end(self, tz: Tz) -> DateTime<Utc> {
+        match self {
+            DateBound::Instant(at) => at,
+            DateBound::Date(date) => {
+                let next_day = date.succ_opt().unwrap_or(date);
+                DateBound::Date(next_day).start(tz) - Duration::nanoseconds(1)
+            }
+        }
+    }
+}
+
+impl FromStr for DateBound {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            return Ok(DateBound::Date(date));
+        }
+        DateTime::parse_from_rfc3339(s)
+            .map(|at| DateBound::Instant(at.with_timezone(&Utc)))
+            .map_err(|_| format!("Invalid date '{s}', expected YYYY-MM-DD or an RFC 3339 time"))
+    }
+}
+
+impl fmt::Display for DateBound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateBound::Instant(at) => write!(f, "{}", at.to_rfc3339()),
+            DateBound::Date(date) => write!(f, "{date}"),
+        }
+    }
+}
+
+impl Serialize for DateBound {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DateBound {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// A `from`/`to` filter resolved to UTC, both ends inclusive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DateRange {
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+impl DateRange {
+    /// Resolves the bounds of a filter in the given time zone.
+    pub fn resolve(from: Option<DateBound>, to: Option<DateBound>, tz: Tz) -> Self {
+        Self {
+            start: from.map(|from| from.start(tz)),
+            end: to.map(|to| to.end(tz)),
+        }
+    }
+
+    /// Whether an instant lies within the range.
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        self.start.is_none_or(|start| at >= start) && self.end.is_none_or(|end| at <= end)
+    }
+}
+
+/// Fixed-width buckets between two instants, aligned in local time.
+///
+/// Buckets are whole multiples of the width since the Unix epoch on the local
+/// clock, so day buckets start at local midnight even when the offset from UTC
+/// changes within the window. The first and last bucket may only partly overlap
+/// the window.
+#[derive(Debug, Clone)]
+pub struct LocalBuckets {
+    tz: Tz,
+    width: i64,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    first: i64,
+    last: i64,
+}
+
+impl LocalBuckets {
+    /// Buckets `width_secs` wide covering `start` to `end`, both inclusive.
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>, width_secs: i64, tz: Tz) -> Self {
+        let mut buckets = Self {
+            tz,
+            width: width_secs,
+            start,
+            end,
+            first: 0,
+            last: 0,
+        };
+        buckets.first = buckets.local_index(start);
+        buckets.last = buckets.local_index(end);
+        buckets
+    }
+
+    fn local_index(&self, at: DateTime<Utc>) -> i64 {
+        let offset = self
+            .tz
+            .offset_from_utc_datetime(&at.naive_utc())
+            .fix()
+            .local_minus_utc();
+        (at.timestamp() + i64::from(offset)).div_euclid(self.width)
+    }
+
+    /// Start of each bucket, as UTC.
+    pub fn starts(&self) -> Vec<DateTime<Utc>> {
+        (self.first..=self.last)
+            .map(|index| {
+                let local = DateTime::from_timestamp(index * self.width, 0)
+                    .unwrap_or(self.start)
+                    .naive_utc();
+                // Local times skipped by a clock change fall back to the same time in UTC
+                self.tz
+                    .from_local_datetime(&local)
+                    .earliest()
+                    .map_or_else(|| local.and_utc(), |start| start.with_timezone(&Utc))
+            })
+            .collect()
+    }
+
+    /// Bucket an instant falls into, `None` outside the window.
+    pub fn index(&self, at: DateTime<Utc>) -> Option<usize> {
+        if at < self.start || at > self.end {
+            return None;
+        }
+        usize::try_from(self.local_index(at) - self.first).ok()
+    }
+
+    /// Bucket a Unix timestamp falls into, `None` outside the window.
+    pub fn index_of_timestamp(&self, timestamp: u64) -> Option<usize> {
+        self.index(DateTime::from_timestamp(i64::try_from(timestamp).ok()?, 0)?)
+    }
+
+    /// Whether the whole UTC hour `at` falls in lies in the window and in a
+    /// single bucket, so figures rolled up per hour can be counted for it.
+    ///
+    /// Hours the window starts or ends within aren't, nor are those a bucket
+    /// starts within, as in time zones offset by a half or three quarter hour.
+    pub fn covers_hour(&self, at: DateTime<Utc>) -> bool {
+        let hour = at.duration_trunc(Duration::hours(1)).unwrap_or(at);
+        let last = hour + Duration::hours(1) - Duration::nanoseconds(1);
+        match (self.index(hour), self.index(last)) {
+            (Some(first), Some(last)) => first == last,
+            _ => false,
+        }
+    }
+
+    /// Parts of the window in hours it doesn't cover, see `covers_hour`, as
+    /// inclusive spans of consecutive hours, oldest first.
+    pub fn split_hours(&self) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+        let mut spans: Vec<(DateTime<Utc>, DateTime<Utc>)> = Vec::new();
+        let mut hour = self
+            .start
+            .duration_trunc(Duration::hours(1))
+            .unwrap_or(self.start);
+
+        while hour <= self.end {
+            let next = hour + Duration::hours(1);
+            if !self.covers_hour(hour) {
+                let last = (next - Duration::nanoseconds(1)).min(self.end);
+                match spans.last_mut() {
+                    Some((_, end)) if *end + Duration::nanoseconds(1) == hour => *end = last,
+                    _ => spans.push((hour.max(self.start), last)),
+                }
+            }
+            hour = next;
+        }
+        spans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::Europe::Berlin;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn dates_cover_the_whole_local_day() {
+        let date: DateBound = "2025-08-01".parse().unwrap();
+        let range = DateRange::resolve(Some(date), Some(date), Berlin);
+
+        assert_eq!(range.start, Some(utc("2025-07-31T22:00:00Z")));
+        assert!(range.contains(utc("2025-08-01T21:59:59Z")));
+        assert!(!range.contains(utc("2025-08-01T22:00:00Z")));
+        assert!(!range.contains(utc("2025-07-31T21:59:59Z")));
+    }
+
+    #[test]
+    fn instants_are_taken_as_given() {
+        let at: DateBound = "2025-08-01T12:00:00+02:00".parse().unwrap();
+        assert_eq!(at.start(Berlin), utc("2025-08-01T10:00:00Z"));
+        assert_eq!(at.end(Berlin), utc("2025-08-01T10:00:00Z"));
+        assert!("01/08/2025".parse::<DateBound>().is_err());
+        assert!(parse_tz("Mars/Olympus").is_err());
+    }
+
+    #[test]
+    fn day_buckets_follow_daylight_saving_time() {
+        // Berlin moves from UTC+1 to UTC+2 on 2025-03-30
+        let buckets = LocalBuckets::new(
+            utc("2025-03-28T23:00:00Z"),
+            utc("2025-03-31T21:59:59Z"),
+            86_400,
+            Berlin,
+        );
+
+        assert_eq!(
+            buckets.starts(),
+            vec![
+                utc("2025-03-28T23:00:00Z"),
+                utc("2025-03-29T23:00:00Z"),
+                utc("2025-03-30T22:00:00Z"),
+            ]
+        );
+        assert_eq!(buckets.index(utc("2025-03-30T22:30:00Z")), Some(2));
+        assert_eq!(buckets.index(utc("2025-03-30T21:30:00Z")), Some(1));
+        assert_eq!(buckets.index(utc("2025-04-01T00:00:00Z")), None);
+    }
+
+    #[test]
+    fn splits_partly_covered_hours() {
+        let buckets = LocalBuckets::new(
+            utc("2025-08-01T10:15:00Z"),
+            utc("2025-08-01T13:30:00Z"),
+            3_600,
+            Berlin,
+        );
+        assert!(!buckets.covers_hour(utc("2025-08-01T10:00:00Z")));
+        assert!(buckets.covers_hour(utc("2025-08-01T11:30:00Z")));
+        assert!(!buckets.covers_hour(utc("2025-08-01T13:00:00Z")));
+        let end_of_hour = utc("2025-08-01T11:00:00Z") - Duration::nanoseconds(1);
+        assert_eq!(
+            buckets.split_hours(),
+            vec![
+                (utc("2025-08-01T10:15:00Z"), end_of_hour),
+                (utc("2025-08-01T13:00:00Z"), utc("2025-08-01T13:30:00Z")),
+            ]
+        );
+
+        // Every hour bucket in India starts half past a UTC hour
+        let buckets = LocalBuckets::new(
+            utc("2025-08-01T10:00:00Z"),
+            utc("2025-08-01T12:59:59Z"),
+            3_600,
+            chrono_tz::Asia::Kolkata,
+        );
+        assert!(!buckets.covers_hour(utc("2025-08-01T11:00:00Z")));
+        assert_eq!(
+            buckets.split_hours(),
+            vec![(utc("2025-08-01T10:00:00Z"), utc("2025-08-01T12:59:59Z"))]
+        );
+    }
+}
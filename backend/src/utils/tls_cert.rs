@@ -0,0 +1,160 @@
+//! Expiry of the TLS certificates nodes are connected with.
+//!
+//! LND's self-signed certificate and CLN's gRPC certificates expire, after
+//! which connections fail without a hint as to why. Only the validity period of
+//! the certificate is needed, so the DER encoding is walked just far enough to
+//! read its `notAfter` time.
+
+use crate::errors::LightningError;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_UTC_TIME: u8 = 0x17;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+/// Explicit tag of the optional version field of a certificate
+const TAG_VERSION: u8 = 0xa0;
+
+/// Reads a PEM certificate file and returns when the certificate expires.
+pub async fn read_certificate_expiry(path: &str) -> Result<DateTime<Utc>, LightningError> {
+    let pem = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| LightningError::ValidationError(format!("Cannot read certificate: {e}")))?;
+    certificate_expiry(&pem)
+}
+
+/// Returns when the first certificate of a PEM file expires.
+pub fn certificate_expiry(pem: &str) -> Result<DateTime<Utc>, LightningError> {
+    const BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+    const END: &str = "-----END CERTIFICATE-----";
+
+    let start = pem
+        .find(BEGIN)
+        .ok_or_else(|| invalid("no PEM certificate found"))?
+        + BEGIN.len();
+    let end = pem[start..]
+        .find(END)
+        .ok_or_else(|| invalid("unterminated PEM certificate"))?
+        + start;
+    let body: String = pem[start..end]
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    let der = STANDARD
+        .decode(body)
+        .map_err(|e| invalid(&format!("bad base64 encoding: {e}")))?;
+
+    der_expiry(&der)
+}
+
+/// Reads `tbsCertificate.validity.notAfter` from a DER-encoded certificate.
+fn der_expiry(der: &[u8]) -> Result<DateTime<Utc>, LightningError> {
+    let (certificate, _) = expect(der, TAG_SEQUENCE)?;
+    let (tbs, _) = expect(certificate, TAG_SEQUENCE)?;
+
+    let mut rest = tbs;
+    if rest.first() == Some(&TAG_VERSION) {
+        rest = read_tlv(rest)?.2;
+    }
+    let (_serial, rest) = expect(rest, TAG_INTEGER)?;
+    let (_signature, rest) = expect(rest, TAG_SEQUENCE)?;
+    let (_issuer, rest) = expect(rest, TAG_SEQUENCE)?;
+    let (validity, _) = expect(rest, TAG_SEQUENCE)?;
+
+    let (_, _not_before, rest) = read_tlv(validity)?;
+    let (tag, not_after, _) = read_tlv(rest)?;
+    parse_time(tag, not_after)
+}
+
+/// Parses an ASN.1 UTCTime or GeneralizedTime in UTC.
+fn parse_time(tag: u8, value: &[u8]) -> Result<DateTime<Utc>, LightningError> {
+    let text = std::str::from_utf8(value).map_err(|_| invalid("time is not ASCII"))?;
+    let full = match tag {
+        // Two-digit years stand for 1950-2049
+        TAG_UTC_TIME => {
+            let year: u32 = text
+                .get(..2)
+                .and_then(|year| year.parse().ok())
+                .ok_or_else(|| invalid("malformed UTCTime"))?;
+            let century = if year < 50 { "20" } else { "19" };
+            format!("{century}{text}")
+        }
+        TAG_GENERALIZED_TIME => text.to_string(),
+        _ => return Err(invalid("validity is not a time")),
+    };
+
+    NaiveDateTime::parse_from_str(&full, "%Y%m%d%H%M%SZ")
+        .map(|time| time.and_utc())
+        .map_err(|_| invalid(&format!("unsupported time {text}")))
+}
+
+/// Reads a TLV that must carry `tag`, returning its value and what follows.
+fn expect(bytes: &[u8], tag: u8) -> Result<(&[u8], &[u8]), LightningError> {
+    let (found, value, rest) = read_tlv(bytes)?;
+    if found != tag {
+        return Err(invalid(&format!(
+            "expected tag {tag:#04x}, found {found:#04x}"
+        )));
+    }
+    Ok((value, rest))
+}
+
+/// Splits the next tag, value and remaining bytes off DER data.
+fn read_tlv(bytes: &[u8]) -> Result<(u8, &[u8], &[u8]), LightningError> {
+    let (&tag, rest) = bytes.split_first().ok_or_else(|| invalid("truncated"))?;
+    let (&first, rest) = rest.split_first().ok_or_else(|| invalid("truncated"))?;
+
+    let (len, rest) = if first < 0x80 {
+        (usize::from(first), rest)
+    } else {
+        let count = usize::from(first & 0x7f);
+        if count == 0 || count > 4 || rest.len() < count {
+            return Err(invalid("unsupported length"));
+        }
+        let len = rest[..count]
+            .iter()
+            .fold(0usize, |len, byte| (len << 8) | usize::from(*byte));
+        (len, &rest[count..])
+    };
+
+    if rest.len() < len {
+        return Err(invalid("truncated value"));
+    }
+    let (value, rest) = rest.split_at(len);
+    Ok((tag, value, rest))
+}
+
+fn invalid(reason: &str) -> LightningError {
+    LightningError::Parse(format!("Invalid certificate: {reason}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Self-signed P-256 certificate valid until 2028-02-28 14:31:58 UTC.
+    const CERT: &str = "-----BEGIN CERTIFICATE-----
+MIIBtTCCAVugAwIBAgIUSMN46UoRZ3Bh0hD5IBiWnr7hEGMwCgYIKoZIzj0EAwIw
+MDEfMB0GA1UECgwWbG5kIGF1dG9nZW5lcmF0ZWQgY2VydDENMAsGA1UEAwwEZ2F6
+ZTAeFw0yNjEwMTYxNDMxNThaFw0yODAyMjgxNDMxNThaMDAxHzAdBgNVBAoMFmxu
+ZCBhdXRvZ2VuZXJhdGVkIGNlcnQxDTALBgNVBAMMBGdhemUwWTATBgcqhkjOPQIB
+BggqhkjOPQMBBwNCAATuDZ2VYkmQS6E9Fef+kA1Lvstlj1GdHpI0pKpJcaJgkqtZ
+aLr11ESi0dyHzTOVI2DFd/L5R5+aVFw8X3ALmjTso1MwUTAdBgNVHQ4EFgQUM5q/
+T5YJw13UhcKh6F7Ci5ylBWwwHwYDVR0jBBgwFoAUM5q/T5YJw13UhcKh6F7Ci5yl
+BWwwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNIADBFAiB1TUoIwCWFDoDb
+efpFsXcRMnXcAeexkBQXVS7/4qsBVAIhANxF1WTK2Oi6hSppTKWVh+QMYWfghdHh
+0fscoooQe8jG
+-----END CERTIFICATE-----
+";
+
+    #[test]
+    fn reads_expiry_of_pem_certificate() {
+        assert_eq!(
+            certificate_expiry(CERT).unwrap().to_rfc3339(),
+            "2028-02-28T14:31:58+00:00"
+        );
+        assert!(certificate_expiry("not a certificate").is_err());
+        assert!(certificate_expiry(&CERT.replace("MIIBtT", "MIIBtA")).is_err());
+    }
+}
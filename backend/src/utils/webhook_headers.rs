@@ -0,0 +1,147 @@
+//! Extra HTTP headers sent with webhook notifications.
+//!
+//! Receivers often want an `Authorization` header or a shared secret, so the
+//! values are stored encrypted with AES-256-GCM under
+//! `NOTIFICATION_ENCRYPTION_KEY`, as a JSON object of header names and
+//! base64-encoded nonce and ciphertext. Names stay readable so they can be
+//! listed without the key.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{Result, anyhow, bail};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::collections::BTreeMap;
+
+const NONCE_LEN: usize = 12;
+const MAX_HEADERS: usize = 20;
+const MAX_VALUE_LEN: usize = 4096;
+
+/// Headers the dispatcher sets itself or the HTTP client derives from the request.
+const RESERVED_HEADERS: &[&str] = &[
+    "content-type",
+    "content-length",
+    "host",
+    "transfer-encoding",
+    "connection",
+    "user-agent",
+];
+
+/// Checks that headers can be sent and aren't ones the dispatcher sets itself,
+/// returning them ready to send.
+pub fn parse_headers(headers: &BTreeMap<String, String>) -> Result<HeaderMap, String> {
+    if headers.len() > MAX_HEADERS {
+        return Err(format!("At most {MAX_HEADERS} headers can be set"));
+    }
+    let mut parsed = HeaderMap::new();
+    for (name, value) in headers {
+        let header = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("'{name}' is not a valid header name"))?;
+        if RESERVED_HEADERS.contains(&header.as_str()) {
+            return Err(format!("The {name} header can't be overridden"));
+        }
+        if value.len() > MAX_VALUE_LEN {
+            return Err(format!(
+                "Value of {name} is longer than {MAX_VALUE_LEN} bytes"
+            ));
+        }
+        let value =
+            HeaderValue::from_str(value).map_err(|_| format!("Value of {name} is not valid"))?;
+        parsed.insert(header, value);
+    }
+    Ok(parsed)
+}
+
+/// Encrypts header values for storage, `None` when there are no headers.
+pub fn seal_headers(key: &[u8; 32], headers: &BTreeMap<String, String>) -> Result<Option<String>> {
+    if headers.is_empty() {
+        return Ok(None);
+    }
+    let cipher = Aes256Gcm::new(&(*key).into());
+    let mut sealed = BTreeMap::new();
+    for (name, value) in headers {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, value.as_bytes())
+            .map_err(|_| anyhow!("failed to encrypt header {name}"))?;
+        let mut blob = nonce.to_vec();
+        blob.extend_from_slice(&ciphertext);
+        sealed.insert(name.to_ascii_lowercase(), STANDARD.encode(blob));
+    }
+    Ok(Some(serde_json::to_string(&sealed)?))
+}
+
+/// Decrypts stored headers into a map ready to send.
+pub fn open_headers(key: &[u8; 32], sealed: &str) -> Result<HeaderMap> {
+    let cipher = Aes256Gcm::new(&(*key).into());
+    let sealed: BTreeMap<String, String> = serde_json::from_str(sealed)?;
+    let mut headers = HeaderMap::new();
+    for (name, blob) in sealed {
+        let blob = STANDARD.decode(blob)?;
+        if blob.len() < NONCE_LEN {
+            bail!("stored value of header {name} is truncated");
+        }
+        let (nonce, ciphertext) = blob.split_at(NONCE_LEN);
+        let value = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("header {name} could not be decrypted; wrong key?"))?;
+        headers.insert(
+            HeaderName::from_bytes(name.as_bytes())?,
+            HeaderValue::from_bytes(&value)?,
+        );
+    }
+    Ok(headers)
+}
+
+/// Decrypts a notification's stored headers with the configured key.
+pub fn stored_headers(sealed: Option<&str>) -> Result<HeaderMap> {
+    let Some(sealed) = sealed else {
+        return Ok(HeaderMap::new());
+    };
+    let key = crate::config::shared()?
+        .notification_encryption_key
+        .ok_or_else(|| anyhow!("NOTIFICATION_ENCRYPTION_KEY is not configured"))?;
+    open_headers(&key, sealed)
+}
+
+/// Names of the stored headers, which are kept in the clear.
+pub fn header_names(sealed: &str) -> Vec<String> {
+    serde_json::from_str::<BTreeMap<String, String>>(sealed)
+        .map(|sealed| sealed.into_keys().collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_headers_open_with_the_same_key_only() {
+        let key = [7u8; 32];
+        let headers = BTreeMap::from([
+            ("Authorization".to_string(), "Bearer hunter2".to_string()),
+            ("X-Team".to_string(), "ops".to_string()),
+        ]);
+
+        let sealed = seal_headers(&key, &headers).unwrap().unwrap();
+        assert!(!sealed.contains("hunter2"));
+        assert_eq!(header_names(&sealed), vec!["authorization", "x-team"]);
+
+        let opened = open_headers(&key, &sealed).unwrap();
+        assert_eq!(opened["authorization"], "Bearer hunter2");
+        assert_eq!(opened["x-team"], "ops");
+        assert!(open_headers(&[8u8; 32], &sealed).is_err());
+        assert_eq!(seal_headers(&key, &BTreeMap::new()).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_reserved_and_malformed_headers() {
+        let header =
+            |name: &str, value: &str| BTreeMap::from([(name.to_string(), value.to_string())]);
+
+        assert!(parse_headers(&header("Authorization", "Basic dXNlcg==")).is_ok());
+        assert!(parse_headers(&header("Content-Type", "text/plain")).is_err());
+        assert!(parse_headers(&header("Bad Header", "x")).is_err());
+        assert!(parse_headers(&header("X-Token", "line\nbreak")).is_err());
+    }
+}
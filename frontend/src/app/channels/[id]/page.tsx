@@ -60,13 +60,13 @@ export default function ChannelDetailsPage({
         
         setChannelData({
           channelId: channel.channel_id,
-          inboundBalance: channel.remote_balance_sat,
-          outboundBalance: channel.local_balance_sat,
+          inboundBalance: channel.remote_balance.sat,
+          outboundBalance: channel.local_balance.sat,
           channelAge: `${Math.floor(channel.channel_age_blocks / 3600)} days`, 
           lastUpdated: new Date(json.timestamp).toLocaleString(),
 
-          capacity: channel.capacity_sat,
-          openingCost: channel.opening_cost_sat ?? 0,
+          capacity: channel.capacity.sat,
+          openingCost: channel.opening_cost?.sat ?? 0,
           commitmentTransactionId: channel.txid,
           connectedNodes: [
             {
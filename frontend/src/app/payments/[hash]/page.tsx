@@ -53,9 +53,9 @@ export default function PaymentDetailsPage({ params }: PaymentDetailsPageProps)
       setPaymentData({
         state: payment.state ?? "...",
         payment_type: payment.payment_type ?? "...",
-        amount_sat: payment.amount_sat ?? "...",
-        amount_usd: payment.amount_usd ?? "...",
-        routing_fee: payment.routing_fee ?? "...",
+        amount_sat: payment.amount?.sat ?? "...",
+        amount_usd: payment.amount_usd === null ? "N/A" : (payment.amount_usd ?? "..."),
+        routing_fee: payment.routing_fee?.sat ?? "...",
         network: payment.network ?? "...",
         description: payment.description === "" ? "Null" : (payment.description ?? "Null"),
         invoice: payment.invoice ?? "...",
@@ -179,7 +179,9 @@ export default function PaymentDetailsPage({ params }: PaymentDetailsPageProps)
             <div>
               <div className="text-sm text-grey-accent mb-1">Amount (USD)</div>
               <div className="text-base font-medium text-maya-blue">
-                {paymentData?.amount_usd !== undefined && paymentData?.amount_usd !== "..."
+                {paymentData?.amount_usd === "N/A"
+                  ? "N/A"
+                  : paymentData?.amount_usd !== undefined && paymentData?.amount_usd !== "..."
                   ? new Intl.NumberFormat("en-US", { style: "currency", currency: "USD" }).format(Number(paymentData.amount_usd))
                   : "..."}
               </div>
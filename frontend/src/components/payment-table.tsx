@@ -28,7 +28,7 @@ import {
   SelectTrigger,
   SelectValue,
 } from "@/components/ui/select";
-import { usePayments, type Payment, type PaymentFilters } from "@/hooks/use-payments";
+import { usePayments, type Amount, type Payment, type PaymentFilters } from "@/hooks/use-payments";
 
 export type { Payment };
 
@@ -118,10 +118,10 @@ export function DataTable({
   },
 },
     {
-      accessorKey: "amount_sat",
+      accessorKey: "amount",
       header: "Amount (sats)",
       cell: ({ row }) => {
-        const balance = row.getValue("amount_sat") as number;
+        const balance = (row.getValue("amount") as Amount).sat;
         const formatted = new Intl.NumberFormat("en-US").format(balance);
         return <div className="text-grey-dark">{formatted} sats</div>;
       },
@@ -130,8 +130,11 @@ export function DataTable({
       accessorKey: "amount_usd",
       header: "Amount (USD)",
       cell: ({ row }) => {
-        const balance = row.getValue("amount_usd") as number;
-        const formatted = new Intl.NumberFormat("en-US", { style: "currency", currency: "USD" }).format(balance);
+        const balance = row.getValue("amount_usd") as number | null;
+        // Nodes off mainnet have no fiat value
+        const formatted = balance === null
+          ? "N/A"
+          : new Intl.NumberFormat("en-US", { style: "currency", currency: "USD" }).format(balance);
         return <div className="text-grey-dark">{formatted}</div>;
       },
     },
@@ -139,7 +142,7 @@ export function DataTable({
       accessorKey: "routing_fee",
       header: "Routing Fee (sats)",
       cell: ({ row }) => {
-        const fee = row.getValue("routing_fee") as number;
+        const fee = (row.getValue("routing_fee") as Amount | null)?.sat ?? 0;
         const formatted = new Intl.NumberFormat("en-US").format(fee);
         return <div className="text-grey-dark">{formatted} sats</div>;
       },
@@ -1,14 +1,15 @@
 import { useQuery } from "@tanstack/react-query";
 import { keepPreviousData } from "@tanstack/react-query";
+import type { Amount } from "./use-payments";
 
 type ApiChannel = {
   chan_id: number;
   alias: string | null;
   channel_state: string | null;
   private: boolean;
-  remote_balance: number;
-  local_balance: number;
-  capacity: number;
+  remote_balance: Amount;
+  local_balance: Amount;
+  capacity: Amount;
   last_update: number;
   uptime: number;
 };
@@ -40,8 +41,8 @@ function transformChannel(item: ApiChannel): Channel {
         ? item.alias
         : String(item.chan_id),
     state,
-    inbound_balance: Number(item.remote_balance ?? 0),
-    outbound_balance: Number(item.local_balance ?? 0),
+    inbound_balance: item.remote_balance?.sat ?? 0,
+    outbound_balance: item.local_balance?.sat ?? 0,
     last_updated: new Date((item.last_update ?? 0) * 1000).toLocaleString(),
     uptime: item.uptime,
   };
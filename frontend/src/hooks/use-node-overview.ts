@@ -81,12 +81,12 @@ export function useNodeOverview() {
     );
 
     const totalIncomingVolume = incomingPayments.reduce(
-      (sum, p) => sum + p.amount_sat,
+      (sum, p) => sum + p.amount.sat,
       0
     );
 
     const totalOutgoingVolume = outgoingPayments.reduce(
-      (sum, p) => sum + p.amount_sat,
+      (sum, p) => sum + p.amount.sat,
       0
     );
 
@@ -1,12 +1,17 @@
 import { useQuery } from "@tanstack/react-query";
 import { keepPreviousData } from "@tanstack/react-query";
 
+export type Amount = {
+  msat: number;
+  sat: number;
+};
+
 export type Payment = {
   state: string;
   payment_type: string;
-  amount_sat: number;
-  amount_usd: number;
-  routing_fee: number;
+  amount: Amount;
+  amount_usd: number | null;
+  routing_fee: Amount | null;
   creation_time:
     | {
         secs_since_epoch: number;
@@ -71,11 +76,11 @@ export function usePaymentMetrics(filters?: PaymentFilters) {
       );
 
       const totalIncomingAmount = incomingPayments.reduce(
-        (sum, p) => sum + (p.amount_sat || 0),
+        (sum, p) => sum + (p.amount?.sat || 0),
         0
       );
       const totalOutgoingAmount = outgoingPayments.reduce(
-        (sum, p) => sum + (p.amount_sat || 0),
+        (sum, p) => sum + (p.amount?.sat || 0),
         0
       );
 
@@ -0,0 +1,150 @@
+//! Lightning amounts, kept in millisatoshis.
+//!
+//! Nodes report amounts in msat or sat depending on the implementation and the
+//! call. Converting msat to sat early drops the sub-satoshi part, so fees and
+//! balances summed afterwards are off. `Amount` keeps the msat and only rounds
+//! when sats are asked for. Amounts are serialized as
+//! `{"msat": 1500500, "sat": 1500}`, the sats rounded down.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::{Display, Formatter};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+
+/// An amount of bitcoin in millisatoshis.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Amount(u64);
+
+impl Amount {
+    pub const ZERO: Self = Self(0);
+
+    pub const fn from_msat(msat: u64) -> Self {
+        Self(msat)
+    }
+
+    pub const fn from_sat(sat: u64) -> Self {
+        Self(sat.saturating_mul(1000))
+    }
+
+    /// Amount of a signed msat value as reported by LND, zero if negative.
+    pub fn from_msat_i64(msat: i64) -> Self {
+        Self(msat.max(0) as u64)
+    }
+
+    /// Amount of a signed sat value as reported by LND, zero if negative.
+    pub fn from_sat_i64(sat: i64) -> Self {
+        Self::from_sat(sat.max(0) as u64)
+    }
+
+    pub const fn msat(self) -> u64 {
+        self.0
+    }
+
+    /// Whole satoshis, rounded down.
+    pub const fn sat(self) -> u64 {
+        self.0 / 1000
+    }
+
+    pub fn checked_sub(self, other: Self) -> Option<Self> {
+        self.0.checked_sub(other.0).map(Self)
+    }
+
+    pub fn saturating_sub(self, other: Self) -> Self {
+        Self(self.0.saturating_sub(other.0))
+    }
+}
+
+impl Add for Amount {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self(self.0.saturating_add(other.0))
+    }
+}
+
+impl AddAssign for Amount {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sum for Amount {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, Add::add)
+    }
+}
+
+impl Display for Amount {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_multiple_of(1000) {
+            write!(f, "{} sat", self.sat())
+        } else {
+            write!(f, "{} msat", self.0)
+        }
+    }
+}
+
+/// Wire form of an amount.
+#[derive(Serialize, Deserialize)]
+struct AmountFields {
+    msat: u64,
+    sat: Option<u64>,
+}
+
+/// Forms an amount is read from: its wire form, or a plain number of sats as
+/// amounts were written before they carried msat.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredAmount {
+    Fields(AmountFields),
+    Sat(u64),
+}
+
+impl Serialize for Amount {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        AmountFields {
+            msat: self.msat(),
+            sat: Some(self.sat()),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(match StoredAmount::deserialize(deserializer)? {
+            StoredAmount::Fields(fields) => Self(fields.msat),
+            StoredAmount::Sat(sat) => Self::from_sat(sat),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_sub_satoshi_amounts() {
+        let fees: Amount = [Amount::from_msat(1500), Amount::from_msat(2500)]
+            .into_iter()
+            .sum();
+        assert_eq!(fees, Amount::from_sat(4));
+        assert_eq!(Amount::from_msat(1999).sat(), 1);
+        assert_eq!(Amount::from_msat_i64(-5), Amount::ZERO);
+        assert_eq!(Amount::from_msat(1500).to_string(), "1500 msat");
+        assert_eq!(Amount::from_sat(2).to_string(), "2 sat");
+
+        let json = serde_json::to_value(Amount::from_msat(1_500_500)).unwrap();
+        assert_eq!(json, serde_json::json!({"msat": 1_500_500, "sat": 1500}));
+        let amount: Amount = serde_json::from_value(json).unwrap();
+        assert_eq!(amount.msat(), 1_500_500);
+        let amount: Amount = serde_json::from_value(serde_json::json!(1500)).unwrap();
+        assert_eq!(amount, Amount::from_msat(1_500_000));
+    }
+}
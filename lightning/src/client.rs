@@ -0,0 +1,254 @@
+//! The interface shared by the LND and CLN clients.
+//!
+//! `LightningClient` is what callers hold, whichever node implementation it
+//! talks to. `ConnectionRequest` reads the connection details of any of them.
+
+#[cfg(feature = "cln")]
+use crate::cln::{ClnConnection, ClnRuneConnection};
+#[cfg(feature = "lnd")]
+use crate::lnd::LndConnection;
+use crate::{
+    close_fee::CloseFeeEstimate,
+    error::LightningError,
+    events::CapturedEvent,
+    types::{
+        ChannelBreach, ChannelDetails, ChannelSummary, CustomInvoice, DualFundRequest,
+        ForwardSummary, GraphNode, GraphUpdate, LiquidityAd, NetworkGraph, NodeInfo, OpenedChannel,
+        PaymentDetails, PaymentState, PaymentSummary, PaymentType, PeerSummary, PendingHtlcs,
+        ShortChannelID, TransactionLabel,
+    },
+};
+
+use async_stream::stream;
+use async_trait::async_trait;
+use bitcoin::{Network, OutPoint, Txid, secp256k1::PublicKey};
+use lightning::ln::PaymentHash;
+use serde::Deserialize;
+use std::{future::Future, pin::Pin, str::FromStr, time::Instant};
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, Error},
+    time::{Duration, sleep},
+};
+use tokio_stream::Stream;
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ConnectionRequest {
+    #[cfg(feature = "lnd")]
+    Lnd(LndConnection),
+    #[cfg(feature = "cln")]
+    Cln(ClnConnection),
+    #[cfg(feature = "cln")]
+    ClnRune(ClnRuneConnection),
+}
+
+impl ConnectionRequest {
+    /// Returns the SOCKS5 proxy configured for this connection, if any.
+    pub fn proxy(&self) -> Option<String> {
+        match self {
+            #[cfg(feature = "lnd")]
+            ConnectionRequest::Lnd(lnd_conn) => lnd_conn.proxy.clone(),
+            #[cfg(feature = "cln")]
+            ConnectionRequest::Cln(cln_conn) => cln_conn.proxy.clone(),
+            #[cfg(feature = "cln")]
+            ConnectionRequest::ClnRune(rune_conn) => rune_conn.proxy.clone(),
+        }
+    }
+
+    /// Returns the CLN rune for commando connections.
+    pub fn rune(&self) -> Option<String> {
+        match self {
+            #[cfg(feature = "cln")]
+            ConnectionRequest::ClnRune(rune_conn) => Some(rune_conn.rune.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the API an LND node is reached over.
+    pub fn transport(&self) -> Option<String> {
+        match self {
+            #[cfg(feature = "lnd")]
+            ConnectionRequest::Lnd(lnd_conn) => Some(lnd_conn.transport.as_str().to_string()),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+}
+
+/// Successive snapshots of an outgoing payment, as returned by `track_payment`.
+pub type PaymentUpdates<'a> =
+    Pin<Box<dyn Stream<Item = Result<PaymentDetails, LightningError>> + Send + 'a>>;
+
+/// Batches of changes to the channel graph, as returned by `subscribe_graph`.
+pub type GraphUpdates =
+    Pin<Box<dyn Stream<Item = Result<Vec<GraphUpdate>, LightningError>> + Send>>;
+
+/// Shortest pause between two reads of a payment tracked by polling.
+const PAYMENT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Tracks a payment on nodes without a payment subscription by re-reading it
+/// each time `wait` returns, until it is no longer in flight.
+pub(crate) fn poll_payment_updates<'a, W, F>(
+    client: &'a (dyn LightningClient + Sync),
+    payment_hash: PaymentHash,
+    mut wait: W,
+) -> PaymentUpdates<'a>
+where
+    W: FnMut() -> F + Send + 'a,
+    F: Future<Output = ()> + Send + 'a,
+{
+    Box::pin(stream! {
+        loop {
+            let details = match client.get_payment_details(&payment_hash).await {
+                Ok(details) => details,
+                Err(err) => {
+                    yield Err(err);
+                    break;
+                }
+            };
+            if !matches!(details.payment_type, PaymentType::Outgoing) {
+                yield Err(LightningError::PaymentError(format!(
+                    "Payment {} is not an outgoing payment",
+                    details.payment_hash
+                )));
+                break;
+            }
+
+            let in_flight = details.state == PaymentState::Inflight;
+            yield Ok(details);
+            if !in_flight {
+                break;
+            }
+
+            let started = Instant::now();
+            wait().await;
+            if let Some(rest) = PAYMENT_POLL_INTERVAL.checked_sub(started.elapsed()) {
+                sleep(rest).await;
+            }
+        }
+    })
+}
+
+pub(crate) async fn reader(filename: &str) -> Result<Vec<u8>, Error> {
+    let mut file = File::open(filename).await?;
+    let mut contents = vec![];
+    file.read_to_end(&mut contents).await?;
+    Ok(contents)
+}
+
+/// Unified interface for Lightning Network node operations across different implementations.
+#[async_trait]
+pub trait LightningClient: Send {
+    /// Returns information about the node.
+    fn get_info(&self) -> &NodeInfo;
+    /// Retrieves the Bitcoin network the node is connected to.
+    async fn get_network(&self) -> Result<Network, LightningError>;
+    /// Lists all channels, returning only their capacities in millisatoshis.
+    async fn list_channels(&self) -> Result<Vec<ChannelSummary>, LightningError>;
+    /// Gets detailed information about a specific channel.
+    async fn get_channel_info(
+        &self,
+        channel_id: &ShortChannelID,
+    ) -> Result<ChannelDetails, LightningError>;
+    /// Gets the details of several channels, in the order given, sharing the node
+    /// lookups between them rather than repeating them per channel.
+    async fn get_channels_info(
+        &self,
+        channel_ids: &[ShortChannelID],
+    ) -> Result<Vec<Result<ChannelDetails, LightningError>>, LightningError>;
+    /// Projects the on-chain fees of closing a channel, cooperatively and by force,
+    /// at the node's fee estimate for the confirmation target.
+    async fn estimate_close_fee(
+        &self,
+        channel_id: &ShortChannelID,
+        target_conf: u32,
+    ) -> Result<CloseFeeEstimate, LightningError>;
+    /// Looks up a node in the channel graph.
+    async fn get_graph_node(&self, pubkey: &PublicKey) -> Result<GraphNode, LightningError>;
+    /// Fetches the node's whole view of the channel graph: every node and public channel.
+    async fn describe_graph(&self) -> Result<NetworkGraph, LightningError>;
+    /// Streams changes to the node's view of the channel graph as gossip arrives.
+    async fn subscribe_graph(&self) -> Result<GraphUpdates, LightningError>;
+    /// Gets detailed information about a specific payment by its hash.
+    async fn get_payment_details(
+        &self,
+        payment_hash: &PaymentHash,
+    ) -> Result<PaymentDetails, LightningError>;
+    /// Lists outgoing payments and paid invoices, sorted with `types::sort_payments`.
+    async fn list_payments(&self) -> Result<Vec<PaymentSummary>, LightningError>;
+    /// Lists the outgoing payments still in flight.
+    async fn list_inflight_payments(&self) -> Result<Vec<PaymentSummary>, LightningError>;
+    /// Streams snapshots of an outgoing payment as its HTLC attempts progress,
+    /// ending once the payment settles or fails.
+    async fn track_payment<'a>(
+        &'a self,
+        payment_hash: &PaymentHash,
+    ) -> Result<PaymentUpdates<'a>, LightningError>;
+    /// Returns a stream of raw events from the lightning node.
+    async fn stream_events(
+        &mut self,
+    ) -> Result<Pin<Box<dyn Stream<Item = CapturedEvent> + Send>>, LightningError>;
+    /// Lists all invoices.
+    async fn list_invoices(&self) -> Result<Vec<CustomInvoice>, LightningError>;
+    /// Gets detailed information about a specific invoice by its payment hash.
+    async fn get_invoice_details(
+        &self,
+        payment_hash: &PaymentHash,
+    ) -> Result<CustomInvoice, LightningError>;
+    /// Gets the onchain wallet balance in satoshis.
+    async fn get_wallet_balance(&self) -> Result<u64, LightningError>;
+    /// Sums the fees earned from forwarded payments since the given unix timestamp, in millisatoshis.
+    async fn get_forwarding_fees(&self, since: u64) -> Result<u64, LightningError>;
+    /// Lists the forwards settled since the given unix timestamp.
+    async fn list_forwards(&self, since: u64) -> Result<Vec<ForwardSummary>, LightningError>;
+    /// Sets the proportional fee the node charges for forwarding out of a channel,
+    /// keeping the rest of its routing policy.
+    async fn set_channel_fee_rate(
+        &self,
+        channel_id: &ShortChannelID,
+        fee_rate_ppm: u32,
+    ) -> Result<(), LightningError>;
+    /// Lists the HTLCs outstanding on the node's channels, with the chain tip.
+    async fn list_pending_htlcs(&self) -> Result<PendingHtlcs, LightningError>;
+    /// Lists the channels peers closed by broadcasting a revoked state.
+    async fn list_channel_breaches(&self) -> Result<Vec<ChannelBreach>, LightningError>;
+    /// Lists the liquidity ads in the node's view of the gossip.
+    async fn list_liquidity_ads(&self) -> Result<Vec<LiquidityAd>, LightningError>;
+    /// Opens a channel funded by both peers, leasing the peer's contribution
+    /// under its liquidity ad, and returns once the funding transaction is broadcast.
+    async fn open_dual_funded_channel(
+        &self,
+        request: &DualFundRequest,
+    ) -> Result<OpenedChannel, LightningError>;
+    /// Lists the node's peers, with the round trip of its last ping to each
+    /// connected one where the node keeps it.
+    async fn list_peers(&self) -> Result<Vec<PeerSummary>, LightningError>;
+    /// Pings a connected peer and returns the round trip.
+    async fn ping_peer(&self, pubkey: &PublicKey) -> Result<Duration, LightningError>;
+    /// Lists the wallet's on-chain transactions with the label the node keeps for each.
+    async fn list_transaction_labels(&self) -> Result<Vec<TransactionLabel>, LightningError>;
+    /// Sets the label of one of the wallet's on-chain transactions, replacing any other.
+    async fn label_transaction(&self, txid: &Txid, label: &str) -> Result<(), LightningError>;
+    /// Signs a message with the node's key, returning the zbase32 signature
+    /// that `verifymessage` on LND and CLN checks.
+    async fn sign_message(&self, message: &str) -> Result<String, LightningError>;
+}
+
+pub fn parse_channel_point(channel_point_str: &str) -> Result<OutPoint, LightningError> {
+    let mut parts = channel_point_str.split(':');
+    let txid_str = parts
+        .next()
+        .ok_or_else(|| LightningError::ValidationError("Missing txid".into()))?;
+    let vout_str = parts
+        .next()
+        .ok_or_else(|| LightningError::ValidationError("Missing vout".into()))?;
+
+    let txid = Txid::from_str(txid_str)
+        .map_err(|err| LightningError::ValidationError(format!("Invalid txid: {err}")))?;
+    let vout = vout_str
+        .parse::<u32>()
+        .map_err(|err| LightningError::ValidationError(format!("Invalid vout: {err}")))?;
+
+    Ok(OutPoint { txid, vout })
+}
@@ -0,0 +1,1712 @@
+//! Core Lightning nodes, reached over gRPC with mTLS or over commando with a
+//! rune (see `cln_commando`).
+
+use crate::{
+    amount::Amount,
+    client::{GraphUpdates, LightningClient, PaymentUpdates, poll_payment_updates, reader},
+    close_fee::{CloseFeeEstimate, CommitmentShape, fee_rate_for_target},
+    error::LightningError,
+    events::{CLNEvent, CapturedEvent, NodeSpecificEvent},
+    sats_to_usd::PriceConverter,
+    socks_proxy,
+    types::{
+        self, Bolt11Fields, ChannelBreach, ChannelDetails, ChannelState, ChannelSummary,
+        CustomInvoice, DualFundRequest, EdgeFee, ForwardSummary, GraphDirection, GraphEdge,
+        GraphNode, InvoiceHtlc, InvoiceStatus, LiquidityAd, NetworkGraph, NodeId, NodeInfo,
+        NodePolicy, OpenedChannel, PaymentDetails, PaymentHtlc, PaymentState, PaymentSummary,
+        PaymentType, PeerSummary, PendingHtlc, PendingHtlcs, ReportedInvoiceState, ShortChannelID,
+        TransactionLabel,
+    },
+};
+
+use async_trait::async_trait;
+use bitcoin::{Network, OutPoint, Transaction, Txid, secp256k1::PublicKey};
+use cln_grpc::pb::{
+    ConnectRequest, FeeratesRequest, FundchannelRequest, GetinfoRequest, ListchannelsChannels,
+    ListchannelsRequest, ListfundsRequest, ListinvoicesInvoices, ListnodesRequest,
+    ListpeerchannelsChannels, ListpeerchannelsRequest, ListpeersRequest, ListtransactionsRequest,
+    PingRequest, node_client::NodeClient,
+};
+use hex;
+use lightning::ln::{PaymentHash, features::NodeFeatures};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    pin::Pin,
+    str::FromStr,
+    time::Instant,
+};
+use tokio::sync::Mutex;
+use tokio::time::{Duration, sleep};
+use tokio_stream::Stream;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Identity};
+
+/// How long CLN's `waitsendpay` may block before a tracked payment is re-read.
+pub(crate) const PAYMENT_WAIT_TIMEOUT_SECS: u32 = 10;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClnConnection {
+    #[serde(with = "types::serde_node_id")]
+    pub id: NodeId,
+    #[serde(with = "types::serde_address")]
+    pub address: String,
+    #[serde(deserialize_with = "types::deserialize_path")]
+    pub ca_cert: String,
+    #[serde(deserialize_with = "types::deserialize_path")]
+    pub client_cert: String,
+    #[serde(deserialize_with = "types::deserialize_path")]
+    pub client_key: String,
+    /// Optional SOCKS5 proxy (`host:port`) used to reach the node, e.g. Tor.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+}
+
+/// CLN connection over commando, authorised by a rune instead of gRPC mTLS.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClnRuneConnection {
+    #[serde(with = "types::serde_node_id")]
+    pub id: NodeId,
+    /// Lightning peer address (`host:port`, usually port 9735).
+    pub address: String,
+    pub rune: String,
+    /// Optional SOCKS5 proxy (`host:port`) used to reach the node, e.g. Tor.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+}
+
+pub struct ClnNode {
+    pub client: Mutex<NodeClient<Channel>>,
+    pub info: NodeInfo,
+    price_converter: PriceConverter,
+}
+
+impl ClnNode {
+    pub async fn new(connection: ClnConnection) -> Result<Self, LightningError> {
+        let tls = ClientTlsConfig::new()
+            .domain_name("cln")
+            .identity(Identity::from_pem(
+                reader(&connection.client_cert).await.map_err(|err| {
+                    LightningError::ConnectionError(format!(
+                        "Cannot load client certificate: {err}"
+                    ))
+                })?,
+                reader(&connection.client_key).await.map_err(|err| {
+                    LightningError::ConnectionError(format!("Cannot load client key: {err}"))
+                })?,
+            ))
+            .ca_certificate(Certificate::from_pem(
+                reader(&connection.ca_cert).await.map_err(|err| {
+                    LightningError::ConnectionError(format!("Cannot load CA certificate: {err}"))
+                })?,
+            ));
+
+        let address =
+            socks_proxy::proxied_address(connection.address, connection.proxy.as_deref()).await?;
+
+        let grpc_connection = Channel::from_shared(address)
+            .map_err(|err| LightningError::ConnectionError(err.to_string()))?
+            .tls_config(tls)
+            .map_err(|err| {
+                LightningError::ConnectionError(format!("Cannot establish tls connection: {err}"))
+            })?
+            .connect()
+            .await
+            .map_err(|err| {
+                LightningError::ConnectionError(format!("Cannot connect to gRPC server: {err}"))
+            })?;
+        let client = Mutex::new(NodeClient::new(grpc_connection));
+        let info = client
+            .lock()
+            .await
+            .getinfo(GetinfoRequest {})
+            .await
+            .map_err(|err| LightningError::GetInfoError(err.to_string()))?
+            .into_inner();
+
+        let pubkey = PublicKey::from_slice(&info.id)
+            .map_err(|err| LightningError::GetInfoError(err.to_string()))?;
+        let mut alias = info.alias.unwrap_or_default();
+        connection.id.validate(&pubkey, &mut alias)?;
+
+        let features = match info.our_features {
+            Some(features) => NodeFeatures::from_be_bytes(features.node),
+            None => NodeFeatures::empty(),
+        };
+
+        Ok(Self {
+            client,
+            info: NodeInfo {
+                pubkey,
+                features,
+                alias,
+                version: Some(info.version).filter(|version| !version.is_empty()),
+                network: Network::from_core_arg(&info.network)
+                    .ok()
+                    .map(|network| network.to_string()),
+            },
+            price_converter: PriceConverter::new(),
+        })
+    }
+
+    async fn get_client_stub(&self) -> NodeClient<Channel> {
+        self.client.lock().await.clone()
+    }
+
+    /// Builds a channel's details from its `listpeerchannels` entry, the gossip for
+    /// both of its directions and the chain tip. The opening cost is left for the
+    /// caller, which may be looking up several funding transactions at once.
+    fn channel_details(
+        &self,
+        channel_id: &ShortChannelID,
+        channel: ListpeerchannelsChannels,
+        gossip: &[ListchannelsChannels],
+        block_height: Option<u32>,
+    ) -> Result<ChannelDetails, LightningError> {
+        let remote_pubkey = PublicKey::from_slice(&channel.peer_id).map_err(|err| {
+            LightningError::ChannelError(format!(
+                "Invalid peer pubkey for channel {channel_id}: {err}"
+            ))
+        })?;
+
+        // Extract last_update for both directions
+        let mut local_last_update = None;
+        let mut remote_last_update = None;
+        let mut is_active_option = None;
+
+        for channel in gossip {
+            // Convert Vec<u8> to String before parsing as pubkey
+            if let Ok(source_str) = String::from_utf8(channel.source.clone())
+                && let Ok(pubkey) = PublicKey::from_str(&source_str)
+            {
+                let update_time = Some(channel.last_update as u64);
+                if pubkey == self.info.pubkey {
+                    local_last_update = update_time;
+                    is_active_option = Some(channel.active);
+                } else if pubkey == remote_pubkey {
+                    remote_last_update = update_time;
+                }
+            }
+        }
+
+        let is_active = is_active_option.unwrap_or(false);
+
+        let capacity = Amount::from_msat(
+            channel
+                .total_msat
+                .as_ref()
+                .ok_or(LightningError::ChannelError(format!(
+                    "Missing total_msat for channel {}",
+                    channel_id
+                )))?
+                .msat,
+        );
+
+        let local_balance = Amount::from_msat(
+            channel
+                .to_us_msat
+                .as_ref()
+                .ok_or(LightningError::ChannelError(format!(
+                    "Missing to_us_msat for channel {channel_id}"
+                )))?
+                .msat,
+        );
+
+        let remote_balance =
+            capacity
+                .checked_sub(local_balance)
+                .ok_or(LightningError::ChannelError(format!(
+                    "Invalid balance calculation for channel {channel_id}"
+                )))?;
+
+        let initiator = match channel.opener().as_str_name() {
+            "LOCAL" => Some(true),
+            "REMOTE" => Some(false),
+            _ => None,
+        };
+
+        let updates = channel
+            .updates
+            .as_ref()
+            .ok_or(LightningError::ChannelError(format!(
+                "Missing channel updates for channel {channel_id}"
+            )))?;
+
+        let local_policy = updates
+            .local
+            .as_ref()
+            .ok_or(LightningError::ChannelError(format!(
+                "Missing local policy for channel {channel_id}"
+            )))?;
+
+        let remote_policy =
+            updates
+                .remote
+                .as_ref()
+                .ok_or(LightningError::ChannelError(format!(
+                    "Missing remote policy for channel {channel_id}"
+                )))?;
+
+        // Build policy structs
+        let local_policy_struct = NodePolicy {
+            pubkey: self.info.pubkey,
+            fee_base_msat: local_policy
+                .fee_base_msat
+                .as_ref()
+                .ok_or(LightningError::ChannelError(format!(
+                    "Missing fee_base_msat in local policy for channel {channel_id}"
+                )))?
+                .msat,
+            fee_rate_milli_msat: local_policy.fee_proportional_millionths as u64,
+            min_htlc_msat: local_policy
+                .htlc_minimum_msat
+                .as_ref()
+                .ok_or(LightningError::ChannelError(format!(
+                    "Missing htlc_minimum_msat in local policy for channel {channel_id}"
+                )))?
+                .msat,
+            max_htlc_msat: local_policy.htlc_maximum_msat.as_ref().map(|amt| amt.msat),
+            time_lock_delta: local_policy.cltv_expiry_delta as u16,
+            disabled: !is_active,
+            last_update: local_last_update,
+        };
+
+        let remote_policy_struct = NodePolicy {
+            pubkey: remote_pubkey,
+            fee_base_msat: remote_policy
+                .fee_base_msat
+                .as_ref()
+                .ok_or(LightningError::ChannelError(format!(
+                    "Missing fee_base_msat in remote policy for channel {channel_id}"
+                )))?
+                .msat,
+            fee_rate_milli_msat: remote_policy.fee_proportional_millionths as u64,
+            min_htlc_msat: remote_policy
+                .htlc_minimum_msat
+                .as_ref()
+                .ok_or(LightningError::ChannelError(format!(
+                    "Missing htlc_minimum_msat in remote policy for channel {channel_id}"
+                )))?
+                .msat,
+            max_htlc_msat: remote_policy.htlc_maximum_msat.as_ref().map(|amt| amt.msat),
+            time_lock_delta: remote_policy.cltv_expiry_delta as u16,
+            disabled: !is_active,
+            last_update: remote_last_update,
+        };
+
+        // Determine policy ordering
+        let (node1_policy, node2_policy) = if self.info.pubkey < remote_pubkey {
+            (local_policy_struct, remote_policy_struct)
+        } else {
+            (remote_policy_struct, local_policy_struct)
+        };
+
+        let txid = cln_funding_txid(channel.funding_txid.as_deref());
+        let channel_age_blocks = block_height.and_then(|height| channel_id.age_blocks(height));
+
+        Ok(ChannelDetails {
+            channel_id: *channel_id,
+            local_balance,
+            remote_balance,
+            capacity,
+            active: Some(is_active),
+            private: channel.private.unwrap_or(false),
+            remote_pubkey,
+            commit_fee: cln_amount(channel.last_tx_fee_msat.as_ref()),
+            local_chan_reserve: cln_amount(channel.our_reserve_msat.as_ref()),
+            remote_chan_reserve: cln_amount(channel.their_reserve_msat.as_ref()),
+            num_updates: None,
+            total_sent: cln_amount(channel.out_fulfilled_msat.as_ref()),
+            total_received: cln_amount(channel.in_fulfilled_msat.as_ref()),
+            channel_age_blocks,
+            opening_cost: None,
+            initiator,
+            txid,
+            vout: channel.funding_outnum,
+            node1_policy: Some(node1_policy),
+            node2_policy: Some(node2_policy),
+            funding_tx: None,
+        })
+    }
+
+    /// Derives the fee paid for a funding transaction from the wallet's transaction
+    /// history and spent outputs, estimating from the opening feerate when some
+    /// inputs are not ours.
+    async fn wallet_funding_fee(&self, txid: &Txid) -> Option<u64> {
+        self.wallet_funding_fees(std::slice::from_ref(txid))
+            .await
+            .remove(txid)
+    }
+
+    /// Derives the fees of several funding transactions from a single read of the
+    /// wallet, see `wallet_funding_fee`.
+    async fn wallet_funding_fees(&self, txids: &[Txid]) -> HashMap<Txid, u64> {
+        if txids.is_empty() {
+            return HashMap::new();
+        }
+
+        let mut client = self.get_client_stub().await;
+        let mut funds_client = client.clone();
+        let mut feerates_client = client.clone();
+
+        let (transactions_result, funds_result, feerates_result) = tokio::join!(
+            client.list_transactions(ListtransactionsRequest {}),
+            funds_client.list_funds(ListfundsRequest { spent: Some(true) }),
+            feerates_client.feerates(FeeratesRequest { style: 1 }), // perkw
+        );
+
+        let Ok(transactions) = transactions_result else {
+            return HashMap::new();
+        };
+        let funding_txs: Vec<Transaction> = transactions
+            .into_inner()
+            .transactions
+            .into_iter()
+            .filter_map(|transaction| {
+                bitcoin::consensus::deserialize::<Transaction>(&transaction.rawtx).ok()
+            })
+            .filter(|transaction| txids.contains(&transaction.compute_txid()))
+            .collect();
+
+        let input_values: HashMap<OutPoint, u64> = funds_result
+            .map(|response| {
+                response
+                    .into_inner()
+                    .outputs
+                    .into_iter()
+                    .filter_map(|output| {
+                        let txid = Txid::from_str(&hex::encode(&output.txid)).ok()?;
+                        let amount_sat = output.amount_msat.as_ref()?.msat / 1000;
+                        Some((
+                            OutPoint {
+                                txid,
+                                vout: output.output,
+                            },
+                            amount_sat,
+                        ))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let opening_sat_per_kw = feerates_result
+            .ok()
+            .and_then(|response| response.into_inner().perkw)
+            .and_then(|perkw| perkw.opening)
+            .map(u64::from);
+
+        funding_txs
+            .iter()
+            .filter_map(|transaction| {
+                let fee = funding_tx_fee(transaction, &input_values, opening_sat_per_kw)?;
+                Some((transaction.compute_txid(), fee))
+            })
+            .collect()
+    }
+
+    async fn get_htlcs_for_payment(
+        &self,
+        payment_hash: &str,
+    ) -> Result<Vec<PaymentHtlc>, LightningError> {
+        let mut client = self.get_client_stub().await;
+
+        // Get individual payment attempts (HTLCs) for this payment
+        let sendpays_response = client
+            .list_send_pays(cln_grpc::pb::ListsendpaysRequest {
+                payment_hash: Some(
+                    hex::decode(payment_hash)
+                        .map_err(|_| LightningError::Parse("Invalid payment hash".to_string()))?,
+                ),
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| {
+                LightningError::PaymentError(format!("CLN list_send_pays error: {err}"))
+            })?;
+
+        let htlcs: Vec<PaymentHtlc> = sendpays_response
+            .into_inner()
+            .payments
+            .into_iter()
+            .map(|sendpay| PaymentHtlc {
+                routes: vec![],
+                attempt_id: sendpay.id,
+                attempt_time: Some(sendpay.created_at),
+                resolve_time: sendpay.completed_at,
+                failure_reason: sendpay.erroronion.map(|_| "Payment failed".to_string()),
+                failure_code: None,
+            })
+            .collect();
+
+        Ok(htlcs)
+    }
+
+    async fn process_outgoing_payment(
+        &self,
+        payment: cln_grpc::pb::ListpaysPays,
+    ) -> Result<PaymentDetails, LightningError> {
+        let state = match payment.status {
+            0 => PaymentState::Inflight, // pending
+            1 => PaymentState::Settled,  // complete
+            2 => PaymentState::Failed,   // failed
+            _ => PaymentState::Failed,
+        };
+
+        // Calculate amounts
+        let amount = cln_amount(payment.amount_msat.as_ref()).unwrap_or_default();
+        let sent_amount = cln_amount(payment.amount_sent_msat.as_ref()).unwrap_or_default();
+        let routing_fee = sent_amount.checked_sub(amount);
+
+        // Get destination pubkey
+        let destination_pubkey = match &payment.destination {
+            Some(hex_str) => {
+                let hex_str = String::from_utf8(hex_str.clone()).map_err(|err| {
+                    LightningError::Parse(format!("Invalid destination string: {err}"))
+                })?;
+                let pubkey = PublicKey::from_str(&hex_str).map_err(|err| {
+                    LightningError::Parse(format!("Invalid destination pubkey: {err}"))
+                })?;
+                Some(pubkey)
+            }
+            None => None,
+        };
+
+        let creation_time = (payment.created_at > 0).then_some(payment.created_at);
+
+        let network = self
+            .get_network()
+            .await
+            .map(|network| Some(network.to_string()))
+            .unwrap_or(None);
+
+        let amount_usd = self
+            .price_converter
+            .node_sats_to_usd(&self.info, amount.sat())
+            .await?;
+
+        // Get HTLC details for this payment
+        let payment_hash_hex = hex::encode(&payment.payment_hash);
+        let htlcs = self
+            .get_htlcs_for_payment(&payment_hash_hex)
+            .await
+            .unwrap_or_else(|_| vec![]);
+
+        Ok(PaymentDetails {
+            state,
+            payment_type: PaymentType::Outgoing,
+            amount,
+            amount_usd,
+            routing_fee,
+            network,
+            description: payment.description,
+            creation_time,
+            invoice: payment.bolt11,
+            payment_hash: payment_hash_hex,
+            destination_pubkey,
+            completed_at: payment.completed_at,
+            htlcs,
+        })
+    }
+
+    async fn process_incoming_payment(
+        &self,
+        invoice: cln_grpc::pb::ListinvoicesInvoices,
+    ) -> Result<PaymentDetails, LightningError> {
+        let state = match invoice.status {
+            0 => PaymentState::Inflight, // unpaid
+            1 => PaymentState::Settled,  // paid
+            2 => PaymentState::Failed,   // expired
+            _ => PaymentState::Inflight,
+        };
+
+        let creation_time = (invoice.expires_at > 0).then_some(invoice.expires_at);
+
+        let completed_at = match state {
+            PaymentState::Settled | PaymentState::Failed => {
+                invoice.paid_at.filter(|&paid_at| paid_at > 0)
+            }
+            _ => None,
+        };
+
+        let network = self
+            .get_network()
+            .await
+            .map(|network| Some(network.to_string()))
+            .unwrap_or(None);
+
+        // Use amount_received_msat if available (actual payment), fallback to amount_msat (invoice amount)
+        let amount = cln_amount(
+            invoice
+                .amount_received_msat
+                .as_ref()
+                .or(invoice.amount_msat.as_ref()),
+        )
+        .unwrap_or_default();
+
+        let amount_usd = self
+            .price_converter
+            .node_sats_to_usd(&self.info, amount.sat())
+            .await?;
+
+        let payment_hash_hex = hex::encode(&invoice.payment_hash);
+        let htlcs = self
+            .get_htlcs_for_payment(&payment_hash_hex)
+            .await
+            .unwrap_or_else(|_| vec![]);
+
+        // For incoming payments, destination is our own node
+        let destination_pubkey = Some(self.info.pubkey);
+
+        Ok(PaymentDetails {
+            state,
+            payment_type: PaymentType::Incoming,
+            amount,
+            amount_usd,
+            routing_fee: None,
+            network,
+            description: invoice.description,
+            creation_time,
+            invoice: invoice.bolt11,
+            payment_hash: payment_hash_hex,
+            destination_pubkey,
+            completed_at,
+            htlcs,
+        })
+    }
+}
+
+#[async_trait]
+impl LightningClient for ClnNode {
+    fn get_info(&self) -> &NodeInfo {
+        &self.info
+    }
+
+    async fn get_network(&self) -> Result<Network, LightningError> {
+        let mut client = self.client.lock().await;
+        let info = client
+            .getinfo(GetinfoRequest {})
+            .await
+            .map_err(|err| LightningError::GetInfoError(err.to_string()))?
+            .into_inner();
+
+        Ok(Network::from_core_arg(&info.network)
+            .map_err(|err| LightningError::ValidationError(err.to_string()))?)
+    }
+
+    async fn list_channels(&self) -> Result<Vec<ChannelSummary>, LightningError> {
+        let mut client = self.get_client_stub().await;
+
+        let mut routing_client = client.clone();
+
+        // Basic channel data and routing info come from independent RPCs
+        let (peer_channels_result, routing_channels_result) = tokio::join!(
+            client.list_peer_channels(ListpeerchannelsRequest { id: None }),
+            routing_client.list_channels(ListchannelsRequest::default()),
+        );
+
+        let peer_channels_response = peer_channels_result
+            .map_err(|err| LightningError::ChannelError(err.to_string()))?
+            .into_inner();
+
+        let routing_channels_response = routing_channels_result
+            .map_err(|err| LightningError::ChannelError(format!("Failed to list channels: {err}")))?
+            .into_inner();
+
+        let mut channel_routing_info = HashMap::new();
+        for routing_channel in routing_channels_response.channels {
+            channel_routing_info
+                .entry(routing_channel.short_channel_id)
+                .and_modify(|info: &mut (u64, bool)| {
+                    info.0 = info.0.max(routing_channel.last_update as u64);
+                    info.1 |= routing_channel.public;
+                })
+                .or_insert((routing_channel.last_update as u64, routing_channel.public));
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let channel_summaries = peer_channels_response
+            .channels
+            .into_iter()
+            .filter_map(|peer_channel| {
+                let short_channel_id_str = peer_channel.short_channel_id.as_ref()?;
+                let channel_id = short_channel_id_str.parse().ok()?;
+
+                let capacity = cln_amount(peer_channel.total_msat.as_ref()).unwrap_or_default();
+                let local_balance =
+                    cln_amount(peer_channel.to_us_msat.as_ref()).unwrap_or_default();
+                let remote_balance = capacity.saturating_sub(local_balance);
+
+                let channel_state = match peer_channel.state {
+                    0 | 1 | 9 | 10 => ChannelState::Opening,
+                    2 => ChannelState::Active,
+                    3..=5 => ChannelState::Closing,
+                    8 => ChannelState::Closed,
+                    11 => ChannelState::Splicing,
+                    _ => ChannelState::Disabled,
+                };
+
+                let alias = peer_channel.alias.as_ref().and_then(|a| a.remote.clone());
+
+                let channel_point = cln_funding_txid(peer_channel.funding_txid.as_deref())
+                    .zip(peer_channel.funding_outnum)
+                    .map(|(txid, vout)| OutPoint { txid, vout });
+
+                // Get routing info if available
+                let (last_update_timestamp, is_public) = channel_routing_info
+                    .get(short_channel_id_str)
+                    .copied()
+                    .unwrap_or((0, false));
+
+                // For private channels with no routing update, use current time as fallback
+                let last_update_timestamp = if !is_public && last_update_timestamp == 0 {
+                    now
+                } else {
+                    last_update_timestamp
+                };
+
+                Some(ChannelSummary {
+                    chan_id: channel_id,
+                    alias,
+                    channel_state,
+                    private: !is_public,
+                    remote_balance,
+                    local_balance,
+                    capacity,
+                    last_update: Some(last_update_timestamp),
+                    uptime: None,
+                    remote_pubkey: PublicKey::from_slice(&peer_channel.peer_id).ok(),
+                    channel_point,
+                })
+            })
+            .collect();
+
+        Ok(channel_summaries)
+    }
+
+    async fn get_channel_info(
+        &self,
+        channel_id: &ShortChannelID,
+    ) -> Result<ChannelDetails, LightningError> {
+        let mut client = self.get_client_stub().await;
+        let mut routing_client = client.clone();
+        let mut info_client = client.clone();
+
+        // Peer channel state, gossip for the channel and the chain tip are fetched together
+        let (peer_channels_result, list_channels_result, info_result) = tokio::join!(
+            client.list_peer_channels(ListpeerchannelsRequest { id: None }),
+            routing_client.list_channels(ListchannelsRequest {
+                short_channel_id: Some(channel_id.to_block_format()),
+                ..Default::default()
+            }),
+            info_client.getinfo(GetinfoRequest {}),
+        );
+
+        let channel = peer_channels_result
+            .map_err(|err| {
+                LightningError::ChannelError(format!("Failed to list peer channels: {err}"))
+            })?
+            .into_inner()
+            .channels
+            .into_iter()
+            .find(|channel| {
+                channel.short_channel_id.as_deref() == Some(channel_id.to_block_format().as_str())
+            })
+            .ok_or_else(|| {
+                LightningError::ChannelError(format!("Channel {channel_id} not found"))
+            })?;
+
+        let gossip = list_channels_result
+            .map_err(|err| LightningError::ChannelError(format!("Failed to list channels: {err}")))?
+            .into_inner()
+            .channels;
+        let block_height = info_result.ok().map(|info| info.into_inner().blockheight);
+        let mut details = self.channel_details(channel_id, channel, &gossip, block_height)?;
+
+        // Only the opener pays for the funding transaction
+        details.opening_cost = match (details.initiator, details.txid.as_ref()) {
+            (Some(true), Some(txid)) => self.wallet_funding_fee(txid).await.map(Amount::from_sat),
+            (Some(false), _) => Some(Amount::ZERO),
+            _ => None,
+        };
+
+        Ok(details)
+    }
+
+    async fn get_channels_info(
+        &self,
+        channel_ids: &[ShortChannelID],
+    ) -> Result<Vec<Result<ChannelDetails, LightningError>>, LightningError> {
+        let mut client = self.get_client_stub().await;
+        let mut routing_client = client.clone();
+        let mut info_client = client.clone();
+
+        // A single listpeerchannels and listchannels pass serves every channel
+        let (peer_channels_result, list_channels_result, info_result) = tokio::join!(
+            client.list_peer_channels(ListpeerchannelsRequest { id: None }),
+            routing_client.list_channels(ListchannelsRequest::default()),
+            info_client.getinfo(GetinfoRequest {}),
+        );
+
+        let mut peer_channels: HashMap<String, ListpeerchannelsChannels> = peer_channels_result
+            .map_err(|err| {
+                LightningError::ChannelError(format!("Failed to list peer channels: {err}"))
+            })?
+            .into_inner()
+            .channels
+            .into_iter()
+            .filter_map(|channel| Some((channel.short_channel_id.clone()?, channel)))
+            .collect();
+
+        let requested: HashSet<String> = channel_ids
+            .iter()
+            .map(ShortChannelID::to_block_format)
+            .collect();
+        let mut gossip: HashMap<String, Vec<ListchannelsChannels>> = HashMap::new();
+        for direction in list_channels_result
+            .map_err(|err| LightningError::ChannelError(format!("Failed to list channels: {err}")))?
+            .into_inner()
+            .channels
+        {
+            if requested.contains(&direction.short_channel_id) {
+                gossip
+                    .entry(direction.short_channel_id.clone())
+                    .or_default()
+                    .push(direction);
+            }
+        }
+        let block_height = info_result.ok().map(|info| info.into_inner().blockheight);
+
+        let mut results: Vec<Result<ChannelDetails, LightningError>> = channel_ids
+            .iter()
+            .map(|channel_id| {
+                let scid = channel_id.to_block_format();
+                let channel = peer_channels.remove(&scid).ok_or_else(|| {
+                    LightningError::NotFound(format!("Channel {channel_id} not found"))
+                })?;
+                let gossip = gossip.get(&scid).map(Vec::as_slice).unwrap_or_default();
+                self.channel_details(channel_id, channel, gossip, block_height)
+            })
+            .collect();
+
+        // Only the opener pays for the funding transaction
+        let funding_txids: Vec<Txid> = results
+            .iter()
+            .flatten()
+            .filter(|details| details.initiator == Some(true))
+            .filter_map(|details| details.txid)
+            .collect();
+        let funding_fees = self.wallet_funding_fees(&funding_txids).await;
+        for details in results.iter_mut().flatten() {
+            details.opening_cost = match (details.initiator, details.txid.as_ref()) {
+                (Some(true), Some(txid)) => funding_fees.get(txid).copied().map(Amount::from_sat),
+                (Some(false), _) => Some(Amount::ZERO),
+                _ => None,
+            };
+        }
+
+        Ok(results)
+    }
+
+    async fn estimate_close_fee(
+        &self,
+        channel_id: &ShortChannelID,
+        target_conf: u32,
+    ) -> Result<CloseFeeEstimate, LightningError> {
+        let mut client = self.get_client_stub().await;
+        let mut feerates_client = client.clone();
+
+        let (peer_channels_result, feerates_result) = tokio::join!(
+            client.list_peer_channels(ListpeerchannelsRequest { id: None }),
+            feerates_client.feerates(FeeratesRequest { style: 1 }), // perkw
+        );
+
+        let channel = peer_channels_result
+            .map_err(|err| {
+                LightningError::ChannelError(format!("Failed to list peer channels: {err}"))
+            })?
+            .into_inner()
+            .channels
+            .into_iter()
+            .find(|channel| {
+                channel.short_channel_id.as_deref() == Some(channel_id.to_block_format().as_str())
+            })
+            .ok_or_else(|| LightningError::NotFound(format!("Channel {channel_id} not found")))?;
+
+        let perkw = feerates_result
+            .map_err(|err| LightningError::ChannelError(format!("Failed to get feerates: {err}")))?
+            .into_inner()
+            .perkw
+            .ok_or_else(|| LightningError::ChannelError("Node has no feerates".to_string()))?;
+
+        let estimates = perkw
+            .estimates
+            .iter()
+            .map(|estimate| (estimate.blockcount, u64::from(estimate.feerate)));
+        let sat_per_kw = fee_rate_for_target(estimates, target_conf)
+            .or(perkw.unilateral_close.map(u64::from))
+            .ok_or_else(|| {
+                LightningError::ChannelError("Node has no feerate estimates".to_string())
+            })?;
+
+        let initiator = match channel.opener().as_str_name() {
+            "LOCAL" => Some(true),
+            "REMOTE" => Some(false),
+            _ => None,
+        };
+
+        Ok(CloseFeeEstimate::new(
+            *channel_id,
+            target_conf,
+            sat_per_kw,
+            CommitmentShape {
+                anchors: false,
+                pending_htlcs: channel.htlcs.len() as u32,
+                commit_weight: None,
+                initiator,
+            },
+        ))
+    }
+
+    async fn get_graph_node(&self, pubkey: &PublicKey) -> Result<GraphNode, LightningError> {
+        self.get_client_stub()
+            .await
+            .list_nodes(ListnodesRequest {
+                id: Some(pubkey.serialize().to_vec()),
+            })
+            .await
+            .map_err(|err| LightningError::GetGraphError(format!("Failed to list nodes: {err}")))?
+            .into_inner()
+            .nodes
+            .into_iter()
+            .find_map(cln_graph_node)
+            .ok_or_else(|| LightningError::NotFound(format!("Node {pubkey} is not in the graph")))
+    }
+
+    async fn describe_graph(&self) -> Result<NetworkGraph, LightningError> {
+        let mut client = self.get_client_stub().await;
+        let mut nodes_client = client.clone();
+        let (channels_result, nodes_result) = tokio::join!(
+            client.list_channels(ListchannelsRequest::default()),
+            nodes_client.list_nodes(ListnodesRequest { id: None }),
+        );
+        let channels = channels_result
+            .map_err(|err| {
+                LightningError::GetGraphError(format!("Failed to list channels: {err}"))
+            })?
+            .into_inner()
+            .channels;
+        let nodes = nodes_result
+            .map_err(|err| LightningError::GetGraphError(format!("Failed to list nodes: {err}")))?
+            .into_inner()
+            .nodes;
+
+        let edges = GraphEdge::from_directions(
+            channels
+                .into_iter()
+                .filter(|channel| channel.public)
+                .filter_map(|channel| {
+                    Some(GraphDirection {
+                        channel_id: ShortChannelID::from_str(&channel.short_channel_id).ok()?,
+                        source: PublicKey::from_slice(&channel.source).ok()?,
+                        destination: PublicKey::from_slice(&channel.destination).ok()?,
+                        capacity_sat: channel.amount_msat.as_ref()?.msat / 1000,
+                        fee: channel.active.then_some(EdgeFee {
+                            base_fee_msat: u64::from(channel.base_fee_millisatoshi),
+                            fee_rate_ppm: u64::from(channel.fee_per_millionth),
+                        }),
+                        last_update: u64::from(channel.last_update),
+                    })
+                }),
+        );
+
+        Ok(NetworkGraph {
+            nodes: nodes.into_iter().filter_map(cln_graph_node).collect(),
+            edges,
+        })
+    }
+
+    async fn subscribe_graph(&self) -> Result<GraphUpdates, LightningError> {
+        Err(LightningError::StreamingError(
+            "CLN has no gossip subscription, its graph is polled instead".to_string(),
+        ))
+    }
+
+    async fn get_payment_details(
+        &self,
+        payment_hash: &PaymentHash,
+    ) -> Result<PaymentDetails, LightningError> {
+        let mut client = self.get_client_stub().await;
+        let hex_hash = hex::encode(payment_hash.0);
+
+        // Check if it's an outgoing payment
+        let response = client
+            .list_pays(cln_grpc::pb::ListpaysRequest {
+                payment_hash: Some(payment_hash.0.to_vec()),
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| LightningError::PaymentError(format!("CLN listpays error: {err}")))?
+            .into_inner();
+
+        if let Some(payment) = response.pays.into_iter().last() {
+            return self.process_outgoing_payment(payment).await;
+        }
+
+        // If it's not an outgoing payment, check if it's an incoming payment (invoice)
+        let invoice_response = client
+            .list_invoices(cln_grpc::pb::ListinvoicesRequest::default())
+            .await
+            .map_err(|err| {
+                tracing::error!("list_invoices RPC failed: {}", err);
+                LightningError::InvoiceError(format!("CLN list_invoices error: {err}"))
+            })?
+            .into_inner();
+
+        if let Some(invoice) = invoice_response
+            .invoices
+            .into_iter()
+            .find(|invoice| hex::encode(&invoice.payment_hash) == hex_hash)
+        {
+            return self.process_incoming_payment(invoice).await;
+        }
+
+        Err(LightningError::NotFound(format!(
+            "Payment {hex_hash} not found"
+        )))
+    }
+
+    async fn list_payments(&self) -> Result<Vec<PaymentSummary>, LightningError> {
+        let mut client = self.get_client_stub().await;
+        let btc_price = self.price_converter.fetch_node_price(&self.info).await?;
+
+        // Fetch outgoing payments
+        let pays_response = client
+            .list_pays(cln_grpc::pb::ListpaysRequest::default())
+            .await
+            .map_err(|err| LightningError::PaymentError(err.to_string()))?
+            .into_inner();
+
+        // Fetch incoming invoices
+        let invoices_response = client
+            .list_invoices(cln_grpc::pb::ListinvoicesRequest::default())
+            .await
+            .map_err(|err| LightningError::InvoiceError(err.to_string()))?
+            .into_inner();
+
+        // Process outgoing payments
+        let outgoing_payments: Vec<PaymentSummary> = pays_response
+            .pays
+            .into_iter()
+            .map(|payment| {
+                let state = match payment.status {
+                    0 => PaymentState::Inflight, // pending
+                    1 => PaymentState::Settled,  // complete
+                    2 => PaymentState::Failed,   // failed
+                    _ => PaymentState::Failed,
+                };
+
+                let amount = cln_amount(payment.amount_msat.as_ref()).unwrap_or_default();
+
+                let amount_usd = btc_price
+                    .map(|price| PriceConverter::sats_to_usd_with_price(amount.sat(), price));
+
+                let routing_fee = match (
+                    cln_amount(payment.amount_sent_msat.as_ref()),
+                    cln_amount(payment.amount_msat.as_ref()),
+                ) {
+                    (Some(sent), Some(received)) => sent.checked_sub(received),
+                    _ => None,
+                };
+
+                let creation_time = (payment.created_at > 0).then_some(payment.created_at);
+
+                PaymentSummary {
+                    state,
+                    payment_type: PaymentType::Outgoing,
+                    amount,
+                    amount_usd,
+                    routing_fee,
+                    creation_time,
+                    invoice: payment.bolt11,
+                    payment_hash: hex::encode(&payment.payment_hash),
+                    completed_at: payment.completed_at,
+                }
+            })
+            .collect();
+
+        // Process incoming payments (from invoices)
+        let incoming_payments: Vec<PaymentSummary> = invoices_response
+            .invoices
+            .into_iter()
+            .filter(|invoice| {
+                // Only include invoices with payment attempts
+                invoice.pay_index.is_some()
+            })
+            .filter_map(|invoice| {
+                let state = match invoice.status {
+                    0 => PaymentState::Inflight, // unpaid
+                    1 => PaymentState::Settled,  // paid
+                    2 => PaymentState::Failed,   // expired
+                    _ => return None,
+                };
+
+                // Use amount_received_msat if available (actual payment), fallback to amount_msat (invoice amount)
+                let amount = cln_amount(
+                    invoice
+                        .amount_received_msat
+                        .as_ref()
+                        .or(invoice.amount_msat.as_ref()),
+                )
+                .unwrap_or_default();
+
+                let amount_usd = btc_price
+                    .map(|price| PriceConverter::sats_to_usd_with_price(amount.sat(), price));
+
+                let creation_time = (invoice.expires_at > 0).then_some(invoice.expires_at);
+
+                let completed_at = match state {
+                    PaymentState::Settled | PaymentState::Failed => {
+                        invoice.paid_at.filter(|&paid_at| paid_at > 0)
+                    }
+                    _ => None,
+                };
+
+                Some(PaymentSummary {
+                    state,
+                    payment_type: PaymentType::Incoming,
+                    amount,
+                    amount_usd,
+                    routing_fee: None,
+                    creation_time,
+                    invoice: invoice.bolt11,
+                    payment_hash: hex::encode(&invoice.payment_hash),
+                    completed_at,
+                })
+            })
+            .collect();
+
+        // Combine all with deduplication
+        let mut seen_hashes = HashSet::new();
+        let mut all_payments = Vec::new();
+
+        let mut push_unique = |payment: PaymentSummary| {
+            if seen_hashes.insert(payment.payment_hash.clone()) {
+                all_payments.push(payment);
+            }
+        };
+
+        outgoing_payments.into_iter().for_each(&mut push_unique);
+        incoming_payments.into_iter().for_each(&mut push_unique);
+
+        types::sort_payments(&mut all_payments);
+
+        Ok(all_payments)
+    }
+
+    async fn list_inflight_payments(&self) -> Result<Vec<PaymentSummary>, LightningError> {
+        let mut client = self.get_client_stub().await;
+        let btc_price = self.price_converter.fetch_node_price(&self.info).await?;
+
+        let pays_response = client
+            .list_pays(cln_grpc::pb::ListpaysRequest {
+                status: Some(cln_grpc::pb::listpays_request::ListpaysStatus::Pending as i32),
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| LightningError::PaymentError(err.to_string()))?
+            .into_inner();
+
+        Ok(pays_response
+            .pays
+            .into_iter()
+            .map(|payment| {
+                let amount = cln_amount(payment.amount_msat.as_ref()).unwrap_or_default();
+                PaymentSummary {
+                    state: PaymentState::Inflight,
+                    payment_type: PaymentType::Outgoing,
+                    amount,
+                    amount_usd: btc_price
+                        .map(|price| PriceConverter::sats_to_usd_with_price(amount.sat(), price)),
+                    routing_fee: None,
+                    creation_time: (payment.created_at > 0).then_some(payment.created_at),
+                    invoice: payment.bolt11,
+                    payment_hash: hex::encode(&payment.payment_hash),
+                    completed_at: None,
+                }
+            })
+            .collect())
+    }
+
+    async fn track_payment<'a>(
+        &'a self,
+        payment_hash: &PaymentHash,
+    ) -> Result<PaymentUpdates<'a>, LightningError> {
+        let client = self.get_client_stub().await;
+        let hash = payment_hash.0.to_vec();
+
+        // waitsendpay returns as soon as an attempt resolves, so progress shows up
+        // without waiting out the timeout
+        Ok(poll_payment_updates(self, *payment_hash, move || {
+            let mut client = client.clone();
+            let payment_hash = hash.clone();
+            async move {
+                let _ = client
+                    .wait_send_pay(cln_grpc::pb::WaitsendpayRequest {
+                        payment_hash,
+                        timeout: Some(PAYMENT_WAIT_TIMEOUT_SECS),
+                        ..Default::default()
+                    })
+                    .await;
+            }
+        }))
+    }
+
+    async fn stream_events(
+        &mut self,
+    ) -> Result<Pin<Box<dyn Stream<Item = CapturedEvent> + Send>>, LightningError> {
+        let event_stream = async_stream::stream! {
+            let mut _counter = 0;
+            loop {
+                sleep(Duration::from_millis(60)).await;
+                yield NodeSpecificEvent::CLN(CLNEvent::ChannelOpened {  }).into();
+                _counter += 1;
+            }
+        };
+
+        Ok(Box::pin(event_stream))
+    }
+
+    async fn list_invoices(&self) -> Result<Vec<CustomInvoice>, LightningError> {
+        let mut client = self.get_client_stub().await;
+        let response = client
+            .list_invoices(cln_grpc::pb::ListinvoicesRequest::default())
+            .await
+            .map_err(|err| LightningError::InvoiceError(err.to_string()))?
+            .into_inner();
+
+        let now = chrono::Utc::now().timestamp() as u64;
+
+        let invoices = response
+            .invoices
+            .into_iter()
+            .map(|invoice| cln_custom_invoice(invoice, now))
+            .collect();
+
+        Ok(invoices)
+    }
+
+    async fn get_invoice_details(
+        &self,
+        payment_hash: &PaymentHash,
+    ) -> Result<CustomInvoice, LightningError> {
+        let mut client = self.get_client_stub().await;
+
+        let request = cln_grpc::pb::ListinvoicesRequest {
+            payment_hash: Some(payment_hash.0.to_vec()),
+            ..Default::default()
+        };
+
+        let response = client
+            .list_invoices(request)
+            .await
+            .map_err(|e| LightningError::InvoiceError(format!("CLN listinvoices error: {e}")))?
+            .into_inner();
+
+        let invoice = response
+            .invoices
+            .into_iter()
+            .next()
+            .ok_or_else(|| LightningError::NotFound("Invoice not found".into()))?;
+
+        Ok(cln_custom_invoice(
+            invoice,
+            chrono::Utc::now().timestamp() as u64,
+        ))
+    }
+
+    async fn get_wallet_balance(&self) -> Result<u64, LightningError> {
+        let mut client = self.get_client_stub().await;
+
+        let request = cln_grpc::pb::ListfundsRequest {
+            spent: None, // Only return unspent outputs
+        };
+
+        let response = client
+            .list_funds(request)
+            .await
+            .map_err(|e| {
+                LightningError::GetInfoError(format!("Failed to get wallet balance: {e}"))
+            })?
+            .into_inner();
+
+        // Sum up all confirmed outputs
+        let total_balance: u64 = response
+            .outputs
+            .iter()
+            .filter(|output| output.status == 1) // 1 = confirmed
+            .map(|output| {
+                output
+                    .amount_msat
+                    .as_ref()
+                    .map(|amt| amt.msat / 1000)
+                    .unwrap_or(0)
+            })
+            .sum();
+
+        Ok(total_balance)
+    }
+
+    async fn get_forwarding_fees(&self, since: u64) -> Result<u64, LightningError> {
+        let mut client = self.get_client_stub().await;
+
+        let response = client
+            .list_forwards(cln_grpc::pb::ListforwardsRequest {
+                status: Some(1), // settled
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| LightningError::PaymentError(format!("Failed to list forwards: {e}")))?
+            .into_inner();
+
+        Ok(response
+            .forwards
+            .iter()
+            .filter(|forward| forward.received_time as u64 >= since)
+            .map(|forward| forward.fee_msat.as_ref().map(|amt| amt.msat).unwrap_or(0))
+            .sum())
+    }
+
+    async fn list_forwards(&self, since: u64) -> Result<Vec<ForwardSummary>, LightningError> {
+        let mut client = self.get_client_stub().await;
+
+        let response = client
+            .list_forwards(cln_grpc::pb::ListforwardsRequest {
+                status: Some(1), // settled
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| LightningError::PaymentError(format!("Failed to list forwards: {e}")))?
+            .into_inner();
+
+        Ok(response
+            .forwards
+            .into_iter()
+            .filter_map(|forward| {
+                let resolved_at = forward.resolved_time.unwrap_or(forward.received_time) as u64;
+                (resolved_at >= since).then(|| ForwardSummary {
+                    chan_id_in: ShortChannelID::from_str(&forward.in_channel).ok(),
+                    chan_id_out: forward
+                        .out_channel
+                        .as_deref()
+                        .and_then(|scid| ShortChannelID::from_str(scid).ok()),
+                    amount_in_msat: forward.in_msat.map(|amt| amt.msat).unwrap_or(0),
+                    amount_out_msat: forward.out_msat.map(|amt| amt.msat).unwrap_or(0),
+                    fee_msat: forward.fee_msat.map(|amt| amt.msat).unwrap_or(0),
+                    resolved_at,
+                })
+            })
+            .collect())
+    }
+
+    async fn set_channel_fee_rate(
+        &self,
+        channel_id: &ShortChannelID,
+        fee_rate_ppm: u32,
+    ) -> Result<(), LightningError> {
+        let mut client = self.get_client_stub().await;
+
+        client
+            .set_channel(cln_grpc::pb::SetchannelRequest {
+                id: channel_id.to_block_format(),
+                feeppm: Some(fee_rate_ppm),
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| {
+                LightningError::ChannelError(format!(
+                    "Failed to set fee rate of channel {channel_id}: {err}"
+                ))
+            })?;
+
+        Ok(())
+    }
+
+    async fn list_pending_htlcs(&self) -> Result<PendingHtlcs, LightningError> {
+        let mut client = self.get_client_stub().await;
+        let mut info_client = client.clone();
+
+        let (peer_channels_result, info_result) = tokio::join!(
+            client.list_peer_channels(ListpeerchannelsRequest { id: None }),
+            info_client.getinfo(GetinfoRequest {}),
+        );
+
+        let channels = peer_channels_result
+            .map_err(|err| LightningError::ChannelError(err.to_string()))?
+            .into_inner()
+            .channels;
+        let block_height = info_result
+            .map_err(|err| LightningError::GetInfoError(err.to_string()))?
+            .into_inner()
+            .blockheight;
+
+        let htlcs = channels
+            .into_iter()
+            .filter_map(|channel| {
+                let channel_id =
+                    ShortChannelID::from_str(channel.short_channel_id.as_deref()?).ok()?;
+                let remote_pubkey = PublicKey::from_slice(&channel.peer_id).ok();
+                Some(channel.htlcs.into_iter().map(move |htlc| PendingHtlc {
+                    channel_id,
+                    remote_pubkey,
+                    // Direction 0 is IN
+                    incoming: htlc.direction == 0,
+                    amount_msat: htlc.amount_msat.map(|amt| amt.msat).unwrap_or(0),
+                    payment_hash: hex::encode(htlc.payment_hash),
+                    expiry_height: htlc.expiry,
+                }))
+            })
+            .flatten()
+            .collect();
+
+        Ok(PendingHtlcs {
+            block_height,
+            htlcs,
+        })
+    }
+
+    async fn list_channel_breaches(&self) -> Result<Vec<ChannelBreach>, LightningError> {
+        Err(LightningError::ValidationError(
+            "CLN doesn't report breached channels".to_string(),
+        ))
+    }
+
+    async fn list_liquidity_ads(&self) -> Result<Vec<LiquidityAd>, LightningError> {
+        let nodes = self
+            .get_client_stub()
+            .await
+            .list_nodes(ListnodesRequest { id: None })
+            .await
+            .map_err(|err| LightningError::GetGraphError(format!("Failed to list nodes: {err}")))?
+            .into_inner()
+            .nodes;
+
+        Ok(nodes
+            .into_iter()
+            .filter_map(|node| {
+                let ad = node.option_will_fund?;
+                Some(LiquidityAd {
+                    pubkey: PublicKey::from_slice(&node.nodeid).ok()?,
+                    alias: node.alias,
+                    lease_fee_base_msat: ad.lease_fee_base_msat.map(|amt| amt.msat).unwrap_or(0),
+                    lease_fee_basis: ad.lease_fee_basis,
+                    funding_weight: ad.funding_weight,
+                    channel_fee_max_base_msat: ad
+                        .channel_fee_max_base_msat
+                        .map(|amt| amt.msat)
+                        .unwrap_or(0),
+                    channel_fee_max_proportional_thousandths: ad
+                        .channel_fee_max_proportional_thousandths,
+                    compact_lease: hex::encode(ad.compact_lease),
+                })
+            })
+            .collect())
+    }
+
+    async fn open_dual_funded_channel(
+        &self,
+        request: &DualFundRequest,
+    ) -> Result<OpenedChannel, LightningError> {
+        let mut client = self.get_client_stub().await;
+
+        if let Some(address) = &request.address {
+            client
+                .connect_peer(ConnectRequest {
+                    id: format!("{}@{address}", request.pubkey),
+                    host: None,
+                    port: None,
+                })
+                .await
+                .map_err(|err| {
+                    LightningError::ConnectionError(format!(
+                        "Failed to connect to {}: {err}",
+                        request.pubkey
+                    ))
+                })?;
+        }
+
+        // CLN opens with the v2 protocol whenever the peer supports it, and only
+        // asks the peer to contribute when a lease is requested
+        let response = client
+            .fund_channel(FundchannelRequest {
+                id: request.pubkey.serialize().to_vec(),
+                amount: Some(cln_grpc::pb::AmountOrAll {
+                    value: Some(cln_grpc::pb::amount_or_all::Value::Amount(
+                        cln_grpc::pb::Amount {
+                            msat: request.amount_sat * 1000,
+                        },
+                    )),
+                }),
+                request_amt: (request.request_amount_sat > 0).then(|| cln_grpc::pb::Amount {
+                    msat: request.request_amount_sat * 1000,
+                }),
+                compact_lease: request.compact_lease.clone(),
+                feerate: request.sat_per_vbyte.map(|rate| cln_grpc::pb::Feerate {
+                    style: Some(cln_grpc::pb::feerate::Style::Perkb(rate * 1000)),
+                }),
+                announce: Some(!request.private),
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| {
+                LightningError::ChannelError(format!(
+                    "Failed to open channel with {}: {err}",
+                    request.pubkey
+                ))
+            })?
+            .into_inner();
+
+        let txid = Txid::from_str(&hex::encode(&response.txid))
+            .map_err(|err| LightningError::Parse(format!("Invalid funding txid: {err}")))?;
+        Ok(OpenedChannel {
+            channel_point: OutPoint {
+                txid,
+                vout: response.outnum,
+            },
+            channel_id: hex::encode(response.channel_id),
+        })
+    }
+
+    async fn list_peers(&self) -> Result<Vec<PeerSummary>, LightningError> {
+        let peers = self
+            .get_client_stub()
+            .await
+            .list_peers(ListpeersRequest {
+                id: None,
+                level: None,
+            })
+            .await
+            .map_err(|err| LightningError::ConnectionError(format!("Failed to list peers: {err}")))?
+            .into_inner()
+            .peers;
+
+        Ok(peers
+            .into_iter()
+            .filter_map(|peer| {
+                Some(PeerSummary {
+                    pubkey: PublicKey::from_slice(&peer.id).ok()?,
+                    address: peer.netaddr.into_iter().next(),
+                    connected: peer.connected,
+                    ping_time_us: None,
+                })
+            })
+            .collect())
+    }
+
+    async fn ping_peer(&self, pubkey: &PublicKey) -> Result<Duration, LightningError> {
+        let mut client = self.get_client_stub().await;
+        let started = Instant::now();
+        client
+            .ping(PingRequest {
+                id: pubkey.serialize().to_vec(),
+                len: None,
+                pongbytes: None,
+            })
+            .await
+            .map_err(|err| {
+                LightningError::ConnectionError(format!("Failed to ping {pubkey}: {err}"))
+            })?;
+        Ok(started.elapsed())
+    }
+    async fn list_transaction_labels(&self) -> Result<Vec<TransactionLabel>, LightningError> {
+        Err(LightningError::ValidationError(
+            "CLN doesn't label on-chain transactions".to_string(),
+        ))
+    }
+
+    async fn label_transaction(&self, _txid: &Txid, _label: &str) -> Result<(), LightningError> {
+        Err(LightningError::ValidationError(
+            "CLN doesn't label on-chain transactions".to_string(),
+        ))
+    }
+
+    async fn sign_message(&self, message: &str) -> Result<String, LightningError> {
+        let mut client = self.get_client_stub().await;
+        let response = client
+            .sign_message(cln_grpc::pb::SignmessageRequest {
+                message: message.to_string(),
+            })
+            .await
+            .map_err(|err| {
+                LightningError::ValidationError(format!("Failed to sign message: {err}"))
+            })?
+            .into_inner();
+        Ok(response.zbase)
+    }
+}
+
+/// Converts an optional CLN amount, which CLN always reports in msat.
+fn cln_amount(amount: Option<&cln_grpc::pb::Amount>) -> Option<Amount> {
+    amount.map(|amount| Amount::from_msat(amount.msat))
+}
+
+/// Converts a node from CLN's `listnodes`, which reports no per-node totals.
+fn cln_graph_node(node: cln_grpc::pb::ListnodesNodes) -> Option<GraphNode> {
+    Some(GraphNode {
+        pubkey: PublicKey::from_slice(&node.nodeid).ok()?,
+        alias: node.alias.unwrap_or_default(),
+        color: node.color.map(|color| format!("#{}", hex::encode(color))),
+        last_update: node.last_timestamp.map(u64::from),
+        addresses: node
+            .addresses
+            .into_iter()
+            .filter_map(|address| Some(format!("{}:{}", address.address?, address.port)))
+            .collect(),
+        num_channels: None,
+        total_capacity_sat: None,
+    })
+}
+
+/// Converts a CLN invoice, reading the creation date and expiry CLN doesn't
+/// report from its payment request.
+fn cln_custom_invoice(invoice: ListinvoicesInvoices, now: u64) -> CustomInvoice {
+    let reported = match invoice.status {
+        1 => ReportedInvoiceState::Settled,
+        2 => ReportedInvoiceState::Expired,
+        _ => ReportedInvoiceState::Open,
+    };
+    let payment_request = invoice.bolt11.unwrap_or_default();
+    let bolt11 = Bolt11Fields::parse(&payment_request);
+    let htlcs = invoice
+        .amount_received_msat
+        .as_ref()
+        .map(|received| vec![InvoiceHtlc::cln_paid(received.msat, invoice.paid_at)]);
+
+    CustomInvoice {
+        memo: invoice.description.unwrap_or_default(),
+        payment_hash: hex::encode(invoice.payment_hash),
+        payment_preimage: invoice
+            .payment_preimage
+            .map(hex::encode)
+            .unwrap_or_default(),
+        value: cln_amount(invoice.amount_msat.as_ref()).unwrap_or_default(),
+        amount_paid: cln_amount(invoice.amount_received_msat.as_ref()),
+        creation_date: bolt11.as_ref().map(|fields| fields.creation_date),
+        settle_date: invoice.paid_at.map(|timestamp| timestamp as i64),
+        payment_request,
+        expiry: bolt11.as_ref().map(|fields| fields.expiry),
+        expires_at: Some(invoice.expires_at),
+        state: InvoiceStatus::resolve(reported, Some(invoice.expires_at), now),
+        is_keysend: None,
+        is_amp: None,
+        payment_addr: bolt11.map(|fields| fields.payment_addr),
+        htlcs,
+        features: None,
+    }
+}
+
+/// Reads a CLN funding txid, which arrives either as hex text or as raw bytes.
+fn cln_funding_txid(txid_bytes: Option<&[u8]>) -> Option<Txid> {
+    let txid_bytes = txid_bytes?;
+    std::str::from_utf8(txid_bytes)
+        .ok()
+        .and_then(|txid_str| Txid::from_str(txid_str).ok())
+        .or_else(|| Txid::from_str(&hex::encode(txid_bytes)).ok())
+}
+
+/// Computes the fee paid by a funding transaction.
+///
+/// When the value of every input is known the exact fee is returned; otherwise the fee
+/// is estimated from the transaction weight and the given feerate in sat/kw.
+pub(crate) fn funding_tx_fee(
+    transaction: &Transaction,
+    input_values: &HashMap<OutPoint, u64>,
+    fallback_sat_per_kw: Option<u64>,
+) -> Option<u64> {
+    let total_in: Option<u64> = transaction
+        .input
+        .iter()
+        .map(|input| input_values.get(&input.previous_output).copied())
+        .sum();
+
+    match total_in {
+        Some(total_in) => {
+            let total_out: u64 = transaction
+                .output
+                .iter()
+                .map(|output| output.value.to_sat())
+                .sum();
+            total_in.checked_sub(total_out)
+        }
+        None => fallback_sat_per_kw.map(|rate| transaction.weight().to_wu() * rate / 1000),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::{ScriptBuf, TxIn, TxOut, absolute::LockTime, transaction::Version};
+
+    fn outpoint(vout: u32) -> OutPoint {
+        OutPoint {
+            txid: Txid::from_str(
+                "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b",
+            )
+            .unwrap(),
+            vout,
+        }
+    }
+
+    fn funding_tx() -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: vec![
+                TxIn {
+                    previous_output: outpoint(0),
+                    ..Default::default()
+                },
+                TxIn {
+                    previous_output: outpoint(1),
+                    ..Default::default()
+                },
+            ],
+            output: vec![TxOut {
+                value: bitcoin::Amount::from_sat(149_000),
+                script_pubkey: ScriptBuf::new(),
+            }],
+        }
+    }
+
+    #[test]
+    fn funding_fee_is_exact_when_every_input_is_ours() {
+        let input_values = HashMap::from([(outpoint(0), 100_000), (outpoint(1), 50_000)]);
+        assert_eq!(
+            funding_tx_fee(&funding_tx(), &input_values, Some(2_500)),
+            Some(1_000)
+        );
+    }
+
+    #[test]
+    fn funding_fee_is_estimated_when_inputs_are_unknown() {
+        let input_values = HashMap::from([(outpoint(0), 100_000)]);
+        let transaction = funding_tx();
+        assert_eq!(transaction.weight().to_wu(), 404);
+        assert_eq!(
+            funding_tx_fee(&transaction, &input_values, Some(2_500)),
+            Some(1_010)
+        );
+
+        // Without a feerate there is nothing to estimate from
+        assert_eq!(funding_tx_fee(&transaction, &input_values, None), None);
+        assert_eq!(funding_tx_fee(&transaction, &HashMap::new(), None), None);
+    }
+
+    #[test]
+    fn funding_fee_is_unknown_when_outputs_exceed_inputs() {
+        let input_values = HashMap::from([(outpoint(0), 100_000), (outpoint(1), 40_000)]);
+        assert_eq!(funding_tx_fee(&funding_tx(), &input_values, None), None);
+    }
+}
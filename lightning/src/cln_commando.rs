@@ -0,0 +1,1850 @@
+//! Core Lightning access over commando, for operators who hand out a rune instead of
+//! gRPC mTLS certificates.
+//!
+//! NodeGaze connects to the node's peer port with a throwaway identity, sends each
+//! JSON-RPC call as a commando request carrying the rune, and reassembles the reply
+//! chunks. The LDK peer manager handles the BOLT 8 transport and ping/pong.
+
+use crate::{
+    amount::Amount,
+    client::{GraphUpdates, LightningClient, PaymentUpdates, poll_payment_updates},
+    cln::{ClnRuneConnection, PAYMENT_WAIT_TIMEOUT_SECS, funding_tx_fee},
+    close_fee::{CloseFeeEstimate, CommitmentShape, fee_rate_for_target},
+    error::LightningError,
+    events::CapturedEvent,
+    sats_to_usd::PriceConverter,
+    socks_proxy,
+    types::{
+        Bolt11Fields, ChannelBreach, ChannelDetails, ChannelState, ChannelSummary, CustomInvoice,
+        DualFundRequest, EdgeFee, ForwardSummary, GraphDirection, GraphEdge, GraphNode,
+        InvoiceHtlc, InvoiceStatus, LiquidityAd, NetworkGraph, NodeId, NodeInfo, NodePolicy,
+        OpenedChannel, PaymentDetails, PaymentHtlc, PaymentState, PaymentSummary, PaymentType,
+        PeerSummary, PendingHtlc, PendingHtlcs, ReportedInvoiceState, ShortChannelID,
+        TransactionLabel, sort_payments,
+    },
+};
+
+use async_trait::async_trait;
+use bitcoin::{Network, OutPoint, Transaction, Txid, secp256k1::PublicKey};
+use lightning::ln::PaymentHash;
+use lightning::ln::features::{InitFeatures, NodeFeatures};
+use lightning::ln::msgs::{DecodeError, LightningError as PeerError};
+use lightning::ln::peer_handler::{
+    CustomMessageHandler, ErroringMessageHandler, IgnoringMessageHandler, MessageHandler,
+    PeerManager,
+};
+use lightning::ln::wire::{CustomMessageReader, Type};
+use lightning::sign::KeysManager;
+use lightning::util::logger::{Logger, Record};
+use lightning::util::ser::{Writeable, Writer};
+use lightning_net_tokio::SocketDescriptor;
+use lightning_secp256k1::PublicKey as PeerPublicKey;
+use rand::RngCore;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::{
+    collections::{HashMap, HashSet},
+    pin::Pin,
+    str::FromStr,
+    sync::{
+        Arc, Mutex as StdMutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::{Duration, Instant, sleep, timeout};
+use tokio_stream::Stream;
+
+/// Commando request message type.
+const COMMANDO_REQUEST: u16 = 0x4c4f;
+/// Reply chunk with more to follow.
+const COMMANDO_REPLY_CONTINUES: u16 = 0x594b;
+/// Final reply chunk.
+const COMMANDO_REPLY_TERM: u16 = 0x594d;
+
+/// Time allowed for the transport handshake and `init` exchange.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time allowed for a single commando call to be answered.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// How often the peer manager is ticked to send pings and drop dead connections.
+const TIMER_TICK_INTERVAL: Duration = Duration::from_secs(10);
+
+type CommandoPeerManager = PeerManager<
+    SocketDescriptor,
+    Arc<ErroringMessageHandler>,
+    Arc<IgnoringMessageHandler>,
+    Arc<IgnoringMessageHandler>,
+    Arc<TracingLogger>,
+    Arc<CommandoHandler>,
+    Arc<KeysManager>,
+>;
+
+/// Failure of a single commando call.
+#[derive(Debug, thiserror::Error)]
+pub enum CommandoError {
+    #[error("commando transport error: {0}")]
+    Transport(String),
+    #[error("RPC error {code}: {message}")]
+    Rpc { code: i64, message: String },
+    #[error("unexpected response: {0}")]
+    Decode(String),
+}
+
+#[derive(Debug)]
+enum CommandoMessage {
+    Request { id: u64, json: Vec<u8> },
+    Reply { id: u64, chunk: Vec<u8>, last: bool },
+}
+
+impl Type for CommandoMessage {
+    fn type_id(&self) -> u16 {
+        match self {
+            CommandoMessage::Request { .. } => COMMANDO_REQUEST,
+            CommandoMessage::Reply { last: false, .. } => COMMANDO_REPLY_CONTINUES,
+            CommandoMessage::Reply { last: true, .. } => COMMANDO_REPLY_TERM,
+        }
+    }
+}
+
+impl Writeable for CommandoMessage {
+    fn write<W: Writer>(&self, writer: &mut W) -> Result<(), lightning::io::Error> {
+        let (id, body) = match self {
+            CommandoMessage::Request { id, json } => (id, json),
+            CommandoMessage::Reply { id, chunk, .. } => (id, chunk),
+        };
+        writer.write_all(&id.to_be_bytes())?;
+        writer.write_all(body)
+    }
+}
+
+/// Reply chunks received so far for a request, and where the whole reply goes.
+type PendingReply = (Vec<u8>, oneshot::Sender<Vec<u8>>);
+
+/// Queues outgoing requests for the peer manager and reassembles reply chunks.
+#[derive(Default)]
+struct CommandoHandler {
+    outbound: StdMutex<Vec<(PeerPublicKey, CommandoMessage)>>,
+    replies: StdMutex<HashMap<u64, PendingReply>>,
+}
+
+impl CustomMessageReader for CommandoHandler {
+    type CustomMessage = CommandoMessage;
+
+    fn read<R: lightning::io::Read>(
+        &self,
+        message_type: u16,
+        buffer: &mut R,
+    ) -> Result<Option<CommandoMessage>, DecodeError> {
+        let last = match message_type {
+            COMMANDO_REPLY_CONTINUES => false,
+            COMMANDO_REPLY_TERM => true,
+            _ => return Ok(None),
+        };
+
+        let mut id = [0u8; 8];
+        buffer
+            .read_exact(&mut id)
+            .map_err(|_| DecodeError::ShortRead)?;
+        let mut chunk = Vec::new();
+        buffer
+            .read_to_end(&mut chunk)
+            .map_err(|_| DecodeError::ShortRead)?;
+
+        Ok(Some(CommandoMessage::Reply {
+            id: u64::from_be_bytes(id),
+            chunk,
+            last,
+        }))
+    }
+}
+
+impl CustomMessageHandler for CommandoHandler {
+    fn handle_custom_message(
+        &self,
+        msg: CommandoMessage,
+        _sender_node_id: &PeerPublicKey,
+    ) -> Result<(), PeerError> {
+        let CommandoMessage::Reply { id, chunk, last } = msg else {
+            return Ok(());
+        };
+
+        let Ok(mut replies) = self.replies.lock() else {
+            return Ok(());
+        };
+        if let Some((buffer, _)) = replies.get_mut(&id) {
+            buffer.extend_from_slice(&chunk);
+        }
+        if last && let Some((buffer, sender)) = replies.remove(&id) {
+            let _ = sender.send(buffer);
+        }
+
+        Ok(())
+    }
+
+    fn get_and_clear_pending_msg(&self) -> Vec<(PeerPublicKey, CommandoMessage)> {
+        self.outbound
+            .lock()
+            .map(|mut outbound| std::mem::take(&mut *outbound))
+            .unwrap_or_default()
+    }
+
+    fn provided_node_features(&self) -> NodeFeatures {
+        NodeFeatures::empty()
+    }
+
+    fn provided_init_features(&self, _their_node_id: &PeerPublicKey) -> InitFeatures {
+        InitFeatures::empty()
+    }
+}
+
+/// Forwards LDK's peer-level logging to tracing.
+struct TracingLogger;
+
+impl Logger for TracingLogger {
+    fn log(&self, record: Record) {
+        tracing::trace!("commando peer: {}", record.args);
+    }
+}
+
+/// A connected commando session with a single CLN node.
+pub struct CommandoClient {
+    peer_manager: Arc<CommandoPeerManager>,
+    handler: Arc<CommandoHandler>,
+    node_id: PeerPublicKey,
+    rune: String,
+    next_id: AtomicU64,
+    ticker: JoinHandle<()>,
+}
+
+impl CommandoClient {
+    /// Opens a peer connection to the node and waits until it is ready for requests.
+    pub async fn connect(
+        node_id: &PublicKey,
+        address: String,
+        proxy: Option<&str>,
+        rune: String,
+    ) -> Result<Self, LightningError> {
+        let node_id = PeerPublicKey::from_slice(&node_id.serialize())
+            .map_err(|err| LightningError::Parse(format!("Invalid node public key: {err}")))?;
+
+        let address = socks_proxy::proxied_address(address, proxy).await?;
+        let host_port = address
+            .split_once("://")
+            .map_or(address.as_str(), |(_, rest)| rest);
+        let socket_addr = tokio::net::lookup_host(host_port)
+            .await
+            .map_err(|err| {
+                LightningError::ConnectionError(format!("Cannot resolve {host_port}: {err}"))
+            })?
+            .next()
+            .ok_or_else(|| {
+                LightningError::ConnectionError(format!("No address for {host_port}"))
+            })?;
+
+        let mut seed = [0u8; 32];
+        let mut ephemeral = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut seed);
+        rand::thread_rng().fill_bytes(&mut ephemeral);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let handler = Arc::new(CommandoHandler::default());
+        let peer_manager = Arc::new(PeerManager::new(
+            MessageHandler {
+                chan_handler: Arc::new(ErroringMessageHandler::new()),
+                route_handler: Arc::new(IgnoringMessageHandler {}),
+                onion_message_handler: Arc::new(IgnoringMessageHandler {}),
+                custom_message_handler: handler.clone(),
+            },
+            now.as_secs() as u32,
+            &ephemeral,
+            Arc::new(TracingLogger),
+            Arc::new(KeysManager::new(&seed, now.as_secs(), now.subsec_nanos())),
+        ));
+
+        let connection =
+            lightning_net_tokio::connect_outbound(peer_manager.clone(), node_id, socket_addr)
+                .await
+                .ok_or_else(|| {
+                    LightningError::ConnectionError(format!(
+                        "Cannot open peer connection to {socket_addr}"
+                    ))
+                })?;
+        tokio::spawn(connection);
+
+        // Custom messages are only delivered once both sides have exchanged `init`.
+        timeout(CONNECT_TIMEOUT, async {
+            while peer_manager.peer_by_node_id(&node_id).is_none() {
+                sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .map_err(|_| {
+            peer_manager.disconnect_all_peers();
+            LightningError::ConnectionError("Timed out waiting for peer handshake".to_string())
+        })?;
+
+        let ticker = tokio::spawn({
+            let peer_manager = peer_manager.clone();
+            async move {
+                loop {
+                    sleep(TIMER_TICK_INTERVAL).await;
+                    peer_manager.timer_tick_occurred();
+                }
+            }
+        });
+
+        Ok(Self {
+            peer_manager,
+            handler,
+            node_id,
+            rune,
+            next_id: AtomicU64::new(0),
+            ticker,
+        })
+    }
+
+    /// Runs a JSON-RPC method on the node and decodes its `result`.
+    pub async fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<T, CommandoError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = json!({
+            "method": method,
+            "params": params,
+            "rune": self.rune,
+            "id": format!("nodegaze/{id}"),
+        });
+
+        let (sender, receiver) = oneshot::channel();
+        self.handler
+            .replies
+            .lock()
+            .map_err(|_| CommandoError::Transport("reply table poisoned".to_string()))?
+            .insert(id, (Vec::new(), sender));
+        self.handler
+            .outbound
+            .lock()
+            .map_err(|_| CommandoError::Transport("outbound queue poisoned".to_string()))?
+            .push((
+                self.node_id,
+                CommandoMessage::Request {
+                    id,
+                    json: request.to_string().into_bytes(),
+                },
+            ));
+        self.peer_manager.process_events();
+
+        let reply = match timeout(REQUEST_TIMEOUT, receiver).await {
+            Ok(Ok(reply)) => reply,
+            _ => {
+                if let Ok(mut replies) = self.handler.replies.lock() {
+                    replies.remove(&id);
+                }
+                return Err(CommandoError::Transport(format!(
+                    "no reply to {method} within {}s",
+                    REQUEST_TIMEOUT.as_secs()
+                )));
+            }
+        };
+
+        let response: JsonRpcResponse =
+            serde_json::from_slice(&reply).map_err(|err| CommandoError::Decode(err.to_string()))?;
+        if let Some(error) = response.error {
+            return Err(CommandoError::Rpc {
+                code: error.code,
+                message: error.message,
+            });
+        }
+
+        serde_json::from_value(response.result.unwrap_or(Value::Null))
+            .map_err(|err| CommandoError::Decode(format!("{method}: {err}")))
+    }
+}
+
+impl Drop for CommandoClient {
+    fn drop(&mut self) {
+        self.ticker.abort();
+        self.peer_manager.disconnect_all_peers();
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonRpcResponse {
+    result: Option<Value>,
+    error: Option<JsonRpcError>,
+}
+
+#[derive(Deserialize)]
+struct JsonRpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct GetinfoResponse {
+    id: String,
+    alias: Option<String>,
+    network: String,
+    blockheight: u32,
+    our_features: Option<OurFeatures>,
+    version: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OurFeatures {
+    node: String,
+}
+
+#[derive(Deserialize)]
+struct ListpeerchannelsResponse {
+    channels: Vec<PeerChannel>,
+}
+
+#[derive(Deserialize)]
+struct PeerChannel {
+    peer_id: String,
+    state: String,
+    short_channel_id: Option<String>,
+    total_msat: Option<u64>,
+    to_us_msat: Option<u64>,
+    alias: Option<ChannelAlias>,
+    private: Option<bool>,
+    opener: Option<String>,
+    funding_txid: Option<String>,
+    funding_outnum: Option<u32>,
+    updates: Option<ChannelUpdates>,
+    last_tx_fee_msat: Option<u64>,
+    our_reserve_msat: Option<u64>,
+    their_reserve_msat: Option<u64>,
+    out_fulfilled_msat: Option<u64>,
+    in_fulfilled_msat: Option<u64>,
+    #[serde(default)]
+    htlcs: Vec<PeerHtlc>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct PeerHtlc {
+    direction: String,
+    amount_msat: u64,
+    expiry: u32,
+    payment_hash: String,
+}
+
+#[derive(Deserialize)]
+struct ChannelAlias {
+    remote: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChannelUpdates {
+    local: Option<ChannelUpdate>,
+    remote: Option<ChannelUpdate>,
+}
+
+#[derive(Deserialize)]
+struct ChannelUpdate {
+    htlc_minimum_msat: Option<u64>,
+    htlc_maximum_msat: Option<u64>,
+    cltv_expiry_delta: Option<u32>,
+    fee_base_msat: Option<u64>,
+    fee_proportional_millionths: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ListchannelsResponse {
+    channels: Vec<GossipChannel>,
+}
+
+#[derive(Deserialize)]
+struct GossipChannel {
+    source: String,
+    destination: Option<String>,
+    short_channel_id: String,
+    public: bool,
+    active: bool,
+    last_update: u64,
+    amount_msat: Option<u64>,
+    base_fee_millisatoshi: Option<u64>,
+    fee_per_millionth: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct ListpaysResponse {
+    pays: Vec<Pay>,
+}
+
+#[derive(Deserialize)]
+struct Pay {
+    payment_hash: String,
+    status: String,
+    destination: Option<String>,
+    created_at: u64,
+    completed_at: Option<u64>,
+    amount_msat: Option<u64>,
+    amount_sent_msat: Option<u64>,
+    bolt11: Option<String>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ListsendpaysResponse {
+    payments: Vec<SendPay>,
+}
+
+#[derive(Deserialize)]
+struct SendPay {
+    id: u64,
+    created_at: u64,
+    completed_at: Option<u64>,
+    erroronion: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ListinvoicesResponse {
+    invoices: Vec<ClnInvoice>,
+}
+
+#[derive(Deserialize)]
+struct ClnInvoice {
+    payment_hash: String,
+    status: String,
+    expires_at: u64,
+    description: Option<String>,
+    amount_msat: Option<u64>,
+    amount_received_msat: Option<u64>,
+    bolt11: Option<String>,
+    pay_index: Option<u64>,
+    paid_at: Option<u64>,
+    payment_preimage: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SignmessageResponse {
+    zbase: String,
+}
+
+#[derive(Deserialize)]
+struct ListfundsResponse {
+    outputs: Vec<FundsOutput>,
+}
+
+#[derive(Deserialize)]
+struct FundsOutput {
+    txid: String,
+    output: u32,
+    amount_msat: u64,
+    status: String,
+}
+
+#[derive(Deserialize)]
+struct ListtransactionsResponse {
+    transactions: Vec<WalletTransaction>,
+}
+
+#[derive(Deserialize)]
+struct WalletTransaction {
+    rawtx: String,
+}
+
+#[derive(Deserialize)]
+struct FeeratesResponse {
+    perkw: Option<FeeratesPerkw>,
+}
+
+#[derive(Deserialize)]
+struct FeeratesPerkw {
+    opening: Option<u64>,
+    unilateral_close: Option<u64>,
+    #[serde(default)]
+    estimates: Vec<FeerateEstimate>,
+}
+
+#[derive(Deserialize)]
+struct FeerateEstimate {
+    blockcount: u32,
+    feerate: u64,
+}
+
+#[derive(Deserialize)]
+struct ListnodesResponse {
+    nodes: Vec<GraphNodeEntry>,
+}
+
+#[derive(Deserialize)]
+struct GraphNodeEntry {
+    #[serde(default)]
+    nodeid: String,
+    alias: Option<String>,
+    color: Option<String>,
+    last_timestamp: Option<u64>,
+    #[serde(default)]
+    addresses: Vec<GraphNodeAddress>,
+    option_will_fund: Option<WillFund>,
+}
+
+/// A node's liquidity ad.
+#[derive(Deserialize)]
+struct WillFund {
+    lease_fee_base_msat: u64,
+    lease_fee_basis: u32,
+    funding_weight: u32,
+    channel_fee_max_base_msat: u64,
+    channel_fee_max_proportional_thousandths: u32,
+    compact_lease: String,
+}
+
+#[derive(Deserialize)]
+struct ListpeersResponse {
+    peers: Vec<PeerEntry>,
+}
+
+#[derive(Deserialize)]
+struct PeerEntry {
+    id: String,
+    connected: bool,
+    #[serde(default)]
+    netaddr: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct FundchannelResponse {
+    txid: String,
+    outnum: u32,
+    channel_id: String,
+}
+
+#[derive(Deserialize)]
+struct GraphNodeAddress {
+    address: Option<String>,
+    port: u16,
+}
+
+#[derive(Deserialize)]
+struct ListforwardsResponse {
+    forwards: Vec<Forward>,
+}
+
+#[derive(Deserialize)]
+struct Forward {
+    in_channel: String,
+    out_channel: Option<String>,
+    in_msat: Option<u64>,
+    received_time: f64,
+    resolved_time: Option<f64>,
+    out_msat: Option<u64>,
+    fee_msat: Option<u64>,
+}
+
+fn channel_state(state: &str) -> ChannelState {
+    match state {
+        "CHANNELD_NORMAL" => ChannelState::Active,
+        "CHANNELD_AWAITING_SPLICE" => ChannelState::Splicing,
+        "OPENINGD"
+        | "CHANNELD_AWAITING_LOCKIN"
+        | "DUALOPEND_OPEN_INIT"
+        | "DUALOPEND_OPEN_COMMITTED"
+        | "DUALOPEND_AWAITING_LOCKIN" => ChannelState::Opening,
+        "CHANNELD_SHUTTING_DOWN" | "CLOSINGD_SIGEXCHANGE" | "CLOSINGD_COMPLETE" => {
+            ChannelState::Closing
+        }
+        "ONCHAIN" => ChannelState::Closed,
+        _ => ChannelState::Disabled,
+    }
+}
+
+fn payment_state(status: &str) -> PaymentState {
+    match status {
+        "pending" => PaymentState::Inflight,
+        "complete" => PaymentState::Settled,
+        _ => PaymentState::Failed,
+    }
+}
+
+fn invoice_payment_state(status: &str) -> PaymentState {
+    match status {
+        "paid" => PaymentState::Settled,
+        "expired" => PaymentState::Failed,
+        _ => PaymentState::Inflight,
+    }
+}
+
+fn invoice_status(invoice: &ClnInvoice, now: u64) -> InvoiceStatus {
+    let reported = match invoice.status.as_str() {
+        "paid" => ReportedInvoiceState::Settled,
+        "expired" => ReportedInvoiceState::Expired,
+        _ => ReportedInvoiceState::Open,
+    };
+    InvoiceStatus::resolve(reported, Some(invoice.expires_at), now)
+}
+
+/// Converts a CLN invoice, reading the creation date and expiry CLN doesn't
+/// report from its payment request.
+fn custom_invoice(invoice: ClnInvoice, now: u64) -> CustomInvoice {
+    let state = invoice_status(&invoice, now);
+    let payment_request = invoice.bolt11.unwrap_or_default();
+    let bolt11 = Bolt11Fields::parse(&payment_request);
+    let htlcs = invoice
+        .amount_received_msat
+        .map(|received| vec![InvoiceHtlc::cln_paid(received, invoice.paid_at)]);
+
+    CustomInvoice {
+        memo: invoice.description.unwrap_or_default(),
+        payment_hash: invoice.payment_hash,
+        payment_preimage: invoice.payment_preimage.unwrap_or_default(),
+        value: Amount::from_msat(invoice.amount_msat.unwrap_or(0)),
+        amount_paid: invoice.amount_received_msat.map(Amount::from_msat),
+        creation_date: bolt11.as_ref().map(|fields| fields.creation_date),
+        settle_date: invoice.paid_at.map(|timestamp| timestamp as i64),
+        payment_request,
+        expiry: bolt11.as_ref().map(|fields| fields.expiry),
+        expires_at: Some(invoice.expires_at),
+        state,
+        is_keysend: None,
+        is_amp: None,
+        payment_addr: bolt11.map(|fields| fields.payment_addr),
+        htlcs,
+        features: None,
+    }
+}
+
+/// Converts a node from `listnodes`, which reports no per-node totals.
+fn graph_node(node: GraphNodeEntry) -> Option<GraphNode> {
+    Some(GraphNode {
+        pubkey: PublicKey::from_str(&node.nodeid).ok()?,
+        alias: node.alias.unwrap_or_default(),
+        color: node.color.map(|color| format!("#{color}")),
+        last_update: node.last_timestamp,
+        addresses: node
+            .addresses
+            .into_iter()
+            .filter_map(|address| Some(format!("{}:{}", address.address?, address.port)))
+            .collect(),
+        num_channels: None,
+        total_capacity_sat: None,
+    })
+}
+
+fn node_policy(
+    pubkey: PublicKey,
+    update: &ChannelUpdate,
+    disabled: bool,
+    last_update: Option<u64>,
+) -> NodePolicy {
+    NodePolicy {
+        pubkey,
+        fee_base_msat: update.fee_base_msat.unwrap_or(0),
+        fee_rate_milli_msat: update.fee_proportional_millionths.unwrap_or(0) as u64,
+        min_htlc_msat: update.htlc_minimum_msat.unwrap_or(0),
+        max_htlc_msat: update.htlc_maximum_msat,
+        time_lock_delta: update.cltv_expiry_delta.unwrap_or(0) as u16,
+        disabled,
+        last_update,
+    }
+}
+
+/// A CLN node reached over commando with a rune.
+pub struct ClnCommandoNode {
+    client: CommandoClient,
+    pub info: NodeInfo,
+    price_converter: PriceConverter,
+}
+
+impl ClnCommandoNode {
+    pub async fn new(connection: ClnRuneConnection) -> Result<Self, LightningError> {
+        // The transport handshake is keyed to the node identity, so an alias is not enough.
+        let NodeId::PublicKey(pubkey) = connection.id.clone() else {
+            return Err(LightningError::ValidationError(
+                "A node public key is required to connect over commando".to_string(),
+            ));
+        };
+
+        let client = CommandoClient::connect(
+            &pubkey,
+            connection.address,
+            connection.proxy.as_deref(),
+            connection.rune,
+        )
+        .await?;
+
+        let info: GetinfoResponse = client
+            .call("getinfo", json!({}))
+            .await
+            .map_err(|err| LightningError::GetInfoError(err.to_string()))?;
+
+        let node_pubkey = PublicKey::from_str(&info.id)
+            .map_err(|err| LightningError::GetInfoError(err.to_string()))?;
+        let mut alias = info.alias.unwrap_or_default();
+        connection.id.validate(&node_pubkey, &mut alias)?;
+
+        let features = info
+            .our_features
+            .and_then(|features| hex::decode(features.node).ok())
+            .map(NodeFeatures::from_be_bytes)
+            .unwrap_or_else(NodeFeatures::empty);
+
+        Ok(Self {
+            client,
+            info: NodeInfo {
+                pubkey: node_pubkey,
+                features,
+                alias,
+                version: info.version,
+                network: Network::from_core_arg(&info.network)
+                    .ok()
+                    .map(|network| network.to_string()),
+            },
+            price_converter: PriceConverter::new(),
+        })
+    }
+
+    /// Derives the fee paid for a funding transaction, see `ClnNode::wallet_funding_fee`.
+    async fn wallet_funding_fee(&self, txid: &Txid) -> Option<u64> {
+        self.wallet_funding_fees(std::slice::from_ref(txid))
+            .await
+            .remove(txid)
+    }
+
+    /// Derives the fees of several funding transactions from a single read of the
+    /// wallet, see `wallet_funding_fee`.
+    async fn wallet_funding_fees(&self, txids: &[Txid]) -> HashMap<Txid, u64> {
+        if txids.is_empty() {
+            return HashMap::new();
+        }
+
+        let (transactions, funds, feerates) = tokio::join!(
+            self.client
+                .call::<ListtransactionsResponse>("listtransactions", json!({})),
+            self.client
+                .call::<ListfundsResponse>("listfunds", json!({ "spent": true })),
+            self.client
+                .call::<FeeratesResponse>("feerates", json!({ "style": "perkw" })),
+        );
+
+        let Ok(transactions) = transactions else {
+            return HashMap::new();
+        };
+        let funding_txs: Vec<Transaction> = transactions
+            .transactions
+            .into_iter()
+            .filter_map(|transaction| {
+                let raw = hex::decode(transaction.rawtx).ok()?;
+                bitcoin::consensus::deserialize::<Transaction>(&raw).ok()
+            })
+            .filter(|transaction| txids.contains(&transaction.compute_txid()))
+            .collect();
+
+        let input_values: HashMap<OutPoint, u64> = funds
+            .map(|funds| {
+                funds
+                    .outputs
+                    .into_iter()
+                    .filter_map(|output| {
+                        let txid = Txid::from_str(&output.txid).ok()?;
+                        Some((
+                            OutPoint {
+                                txid,
+                                vout: output.output,
+                            },
+                            output.amount_msat / 1000,
+                        ))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let opening_sat_per_kw = feerates
+            .ok()
+            .and_then(|feerates| feerates.perkw)
+            .and_then(|perkw| perkw.opening);
+
+        funding_txs
+            .iter()
+            .filter_map(|transaction| {
+                let fee = funding_tx_fee(transaction, &input_values, opening_sat_per_kw)?;
+                Some((transaction.compute_txid(), fee))
+            })
+            .collect()
+    }
+
+    /// Builds a channel's details from its `listpeerchannels` entry, the gossip for
+    /// both of its directions and the chain tip, see `ClnNode::channel_details`.
+    fn channel_details(
+        &self,
+        channel_id: &ShortChannelID,
+        channel: PeerChannel,
+        gossip: &[GossipChannel],
+        block_height: Option<u32>,
+    ) -> Result<ChannelDetails, LightningError> {
+        let remote_pubkey = PublicKey::from_str(&channel.peer_id).map_err(|err| {
+            LightningError::ChannelError(format!(
+                "Invalid peer pubkey for channel {channel_id}: {err}"
+            ))
+        })?;
+
+        let mut local_last_update = None;
+        let mut remote_last_update = None;
+        let mut is_active = false;
+        for direction in gossip {
+            match PublicKey::from_str(&direction.source) {
+                Ok(source) if source == self.info.pubkey => {
+                    local_last_update = Some(direction.last_update);
+                    is_active = direction.active;
+                }
+                Ok(source) if source == remote_pubkey => {
+                    remote_last_update = Some(direction.last_update);
+                }
+                _ => {}
+            }
+        }
+
+        let capacity = channel.total_msat.map(Amount::from_msat).ok_or_else(|| {
+            LightningError::ChannelError(format!("Missing total_msat for channel {channel_id}"))
+        })?;
+        let local_balance = channel.to_us_msat.map(Amount::from_msat).ok_or_else(|| {
+            LightningError::ChannelError(format!("Missing to_us_msat for channel {channel_id}"))
+        })?;
+        let remote_balance = capacity.checked_sub(local_balance).ok_or_else(|| {
+            LightningError::ChannelError(format!(
+                "Invalid balance calculation for channel {channel_id}"
+            ))
+        })?;
+
+        let initiator = match channel.opener.as_deref() {
+            Some("local") => Some(true),
+            Some("remote") => Some(false),
+            _ => None,
+        };
+
+        let (local_update, remote_update) = channel
+            .updates
+            .as_ref()
+            .and_then(|updates| Some((updates.local.as_ref()?, updates.remote.as_ref()?)))
+            .ok_or_else(|| {
+                LightningError::ChannelError(format!(
+                    "Missing channel updates for channel {channel_id}"
+                ))
+            })?;
+
+        let local_policy = node_policy(
+            self.info.pubkey,
+            local_update,
+            !is_active,
+            local_last_update,
+        );
+        let remote_policy =
+            node_policy(remote_pubkey, remote_update, !is_active, remote_last_update);
+        let (node1_policy, node2_policy) = if self.info.pubkey < remote_pubkey {
+            (local_policy, remote_policy)
+        } else {
+            (remote_policy, local_policy)
+        };
+
+        let txid = channel
+            .funding_txid
+            .as_deref()
+            .and_then(|txid| Txid::from_str(txid).ok());
+
+        let channel_age_blocks = block_height.and_then(|height| channel_id.age_blocks(height));
+
+        Ok(ChannelDetails {
+            channel_id: *channel_id,
+            local_balance,
+            remote_balance,
+            capacity,
+            active: Some(is_active),
+            private: channel.private.unwrap_or(false),
+            remote_pubkey,
+            commit_fee: channel.last_tx_fee_msat.map(Amount::from_msat),
+            local_chan_reserve: channel.our_reserve_msat.map(Amount::from_msat),
+            remote_chan_reserve: channel.their_reserve_msat.map(Amount::from_msat),
+            num_updates: None,
+            total_sent: channel.out_fulfilled_msat.map(Amount::from_msat),
+            total_received: channel.in_fulfilled_msat.map(Amount::from_msat),
+            channel_age_blocks,
+            opening_cost: None,
+            initiator,
+            txid,
+            vout: channel.funding_outnum,
+            node1_policy: Some(node1_policy),
+            node2_policy: Some(node2_policy),
+            funding_tx: None,
+        })
+    }
+
+    async fn get_htlcs_for_payment(&self, payment_hash: &str) -> Vec<PaymentHtlc> {
+        self.client
+            .call::<ListsendpaysResponse>("listsendpays", json!({ "payment_hash": payment_hash }))
+            .await
+            .map(|response| {
+                response
+                    .payments
+                    .into_iter()
+                    .map(|sendpay| PaymentHtlc {
+                        routes: vec![],
+                        attempt_id: sendpay.id,
+                        attempt_time: Some(sendpay.created_at),
+                        resolve_time: sendpay.completed_at,
+                        failure_reason: sendpay.erroronion.map(|_| "Payment failed".to_string()),
+                        failure_code: None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    async fn network_name(&self) -> Option<String> {
+        self.get_network()
+            .await
+            .map(|network| network.to_string())
+            .ok()
+    }
+}
+
+#[async_trait]
+impl LightningClient for ClnCommandoNode {
+    fn get_info(&self) -> &NodeInfo {
+        &self.info
+    }
+
+    async fn get_network(&self) -> Result<Network, LightningError> {
+        let info: GetinfoResponse = self
+            .client
+            .call("getinfo", json!({}))
+            .await
+            .map_err(|err| LightningError::GetInfoError(err.to_string()))?;
+
+        Network::from_core_arg(&info.network)
+            .map_err(|err| LightningError::ValidationError(err.to_string()))
+    }
+
+    async fn list_channels(&self) -> Result<Vec<ChannelSummary>, LightningError> {
+        let (peer_channels, gossip) = tokio::join!(
+            self.client
+                .call::<ListpeerchannelsResponse>("listpeerchannels", json!({})),
+            self.client
+                .call::<ListchannelsResponse>("listchannels", json!({})),
+        );
+
+        let peer_channels =
+            peer_channels.map_err(|err| LightningError::ChannelError(err.to_string()))?;
+        let gossip = gossip.map_err(|err| {
+            LightningError::ChannelError(format!("Failed to list channels: {err}"))
+        })?;
+
+        let mut routing_info: HashMap<String, (u64, bool)> = HashMap::new();
+        for channel in gossip.channels {
+            routing_info
+                .entry(channel.short_channel_id)
+                .and_modify(|info| {
+                    info.0 = info.0.max(channel.last_update);
+                    info.1 |= channel.public;
+                })
+                .or_insert((channel.last_update, channel.public));
+        }
+
+        let now = chrono::Utc::now().timestamp() as u64;
+
+        Ok(peer_channels
+            .channels
+            .into_iter()
+            .filter_map(|channel| {
+                let scid = channel.short_channel_id.as_deref()?;
+                let chan_id = ShortChannelID::from_str(scid).ok()?;
+
+                let capacity = Amount::from_msat(channel.total_msat.unwrap_or(0));
+                let local_balance = Amount::from_msat(channel.to_us_msat.unwrap_or(0));
+                let (last_update, is_public) =
+                    routing_info.get(scid).copied().unwrap_or((0, false));
+                let channel_point = channel
+                    .funding_txid
+                    .as_deref()
+                    .and_then(|txid| Txid::from_str(txid).ok())
+                    .zip(channel.funding_outnum)
+                    .map(|(txid, vout)| OutPoint { txid, vout });
+
+                // For private channels with no routing update, use current time as fallback
+                let last_update = if !is_public && last_update == 0 {
+                    now
+                } else {
+                    last_update
+                };
+
+                Some(ChannelSummary {
+                    chan_id,
+                    alias: channel.alias.and_then(|alias| alias.remote),
+                    channel_state: channel_state(&channel.state),
+                    private: !is_public,
+                    remote_balance: capacity.saturating_sub(local_balance),
+                    local_balance,
+                    capacity,
+                    last_update: Some(last_update),
+                    uptime: None,
+                    remote_pubkey: PublicKey::from_str(&channel.peer_id).ok(),
+                    channel_point,
+                })
+            })
+            .collect())
+    }
+
+    async fn get_channel_info(
+        &self,
+        channel_id: &ShortChannelID,
+    ) -> Result<ChannelDetails, LightningError> {
+        let scid = channel_id.to_block_format();
+
+        let (peer_channels, gossip, info) = tokio::join!(
+            self.client
+                .call::<ListpeerchannelsResponse>("listpeerchannels", json!({})),
+            self.client
+                .call::<ListchannelsResponse>("listchannels", json!({ "short_channel_id": scid })),
+            self.client.call::<GetinfoResponse>("getinfo", json!({})),
+        );
+
+        let channel = peer_channels
+            .map_err(|err| {
+                LightningError::ChannelError(format!("Failed to list peer channels: {err}"))
+            })?
+            .channels
+            .into_iter()
+            .find(|channel| channel.short_channel_id.as_deref() == Some(scid.as_str()))
+            .ok_or_else(|| {
+                LightningError::ChannelError(format!("Channel {channel_id} not found"))
+            })?;
+
+        let gossip = gossip.map_err(|err| {
+            LightningError::ChannelError(format!("Failed to list channels: {err}"))
+        })?;
+        let block_height = info.ok().map(|info| info.blockheight);
+        let mut details =
+            self.channel_details(channel_id, channel, &gossip.channels, block_height)?;
+
+        // Only the opener pays for the funding transaction
+        details.opening_cost = match (details.initiator, details.txid.as_ref()) {
+            (Some(true), Some(txid)) => self.wallet_funding_fee(txid).await.map(Amount::from_sat),
+            (Some(false), _) => Some(Amount::ZERO),
+            _ => None,
+        };
+
+        Ok(details)
+    }
+
+    async fn get_channels_info(
+        &self,
+        channel_ids: &[ShortChannelID],
+    ) -> Result<Vec<Result<ChannelDetails, LightningError>>, LightningError> {
+        // A single listpeerchannels and listchannels pass serves every channel
+        let (peer_channels, gossip, info) = tokio::join!(
+            self.client
+                .call::<ListpeerchannelsResponse>("listpeerchannels", json!({})),
+            self.client
+                .call::<ListchannelsResponse>("listchannels", json!({})),
+            self.client.call::<GetinfoResponse>("getinfo", json!({})),
+        );
+
+        let mut peer_channels: HashMap<String, PeerChannel> = peer_channels
+            .map_err(|err| {
+                LightningError::ChannelError(format!("Failed to list peer channels: {err}"))
+            })?
+            .channels
+            .into_iter()
+            .filter_map(|channel| Some((channel.short_channel_id.clone()?, channel)))
+            .collect();
+
+        let requested: HashSet<String> = channel_ids
+            .iter()
+            .map(ShortChannelID::to_block_format)
+            .collect();
+        let mut directions: HashMap<String, Vec<GossipChannel>> = HashMap::new();
+        for direction in gossip
+            .map_err(|err| LightningError::ChannelError(format!("Failed to list channels: {err}")))?
+            .channels
+        {
+            if requested.contains(&direction.short_channel_id) {
+                directions
+                    .entry(direction.short_channel_id.clone())
+                    .or_default()
+                    .push(direction);
+            }
+        }
+        let block_height = info.ok().map(|info| info.blockheight);
+
+        let mut results: Vec<Result<ChannelDetails, LightningError>> = channel_ids
+            .iter()
+            .map(|channel_id| {
+                let scid = channel_id.to_block_format();
+                let channel = peer_channels.remove(&scid).ok_or_else(|| {
+                    LightningError::NotFound(format!("Channel {channel_id} not found"))
+                })?;
+                let gossip = directions.get(&scid).map(Vec::as_slice).unwrap_or_default();
+                self.channel_details(channel_id, channel, gossip, block_height)
+            })
+            .collect();
+
+        // Only the opener pays for the funding transaction
+        let funding_txids: Vec<Txid> = results
+            .iter()
+            .flatten()
+            .filter(|details| details.initiator == Some(true))
+            .filter_map(|details| details.txid)
+            .collect();
+        let funding_fees = self.wallet_funding_fees(&funding_txids).await;
+        for details in results.iter_mut().flatten() {
+            details.opening_cost = match (details.initiator, details.txid.as_ref()) {
+                (Some(true), Some(txid)) => funding_fees.get(txid).copied().map(Amount::from_sat),
+                (Some(false), _) => Some(Amount::ZERO),
+                _ => None,
+            };
+        }
+
+        Ok(results)
+    }
+
+    async fn estimate_close_fee(
+        &self,
+        channel_id: &ShortChannelID,
+        target_conf: u32,
+    ) -> Result<CloseFeeEstimate, LightningError> {
+        let scid = channel_id.to_block_format();
+
+        let (peer_channels, feerates) = tokio::join!(
+            self.client
+                .call::<ListpeerchannelsResponse>("listpeerchannels", json!({})),
+            self.client
+                .call::<FeeratesResponse>("feerates", json!({ "style": "perkw" })),
+        );
+
+        let channel = peer_channels
+            .map_err(|err| {
+                LightningError::ChannelError(format!("Failed to list peer channels: {err}"))
+            })?
+            .channels
+            .into_iter()
+            .find(|channel| channel.short_channel_id.as_deref() == Some(scid.as_str()))
+            .ok_or_else(|| LightningError::NotFound(format!("Channel {channel_id} not found")))?;
+
+        let perkw = feerates
+            .map_err(|err| LightningError::ChannelError(format!("Failed to get feerates: {err}")))?
+            .perkw
+            .ok_or_else(|| LightningError::ChannelError("Node has no feerates".to_string()))?;
+
+        let estimates = perkw
+            .estimates
+            .iter()
+            .map(|estimate| (estimate.blockcount, estimate.feerate));
+        let sat_per_kw = fee_rate_for_target(estimates, target_conf)
+            .or(perkw.unilateral_close)
+            .ok_or_else(|| {
+                LightningError::ChannelError("Node has no feerate estimates".to_string())
+            })?;
+
+        let initiator = match channel.opener.as_deref() {
+            Some("local") => Some(true),
+            Some("remote") => Some(false),
+            _ => None,
+        };
+
+        Ok(CloseFeeEstimate::new(
+            *channel_id,
+            target_conf,
+            sat_per_kw,
+            CommitmentShape {
+                anchors: false,
+                pending_htlcs: channel.htlcs.len() as u32,
+                commit_weight: None,
+                initiator,
+            },
+        ))
+    }
+
+    async fn get_graph_node(&self, pubkey: &PublicKey) -> Result<GraphNode, LightningError> {
+        self.client
+            .call::<ListnodesResponse>("listnodes", json!({ "id": pubkey.to_string() }))
+            .await
+            .map_err(|err| LightningError::GetGraphError(format!("Failed to list nodes: {err}")))?
+            .nodes
+            .into_iter()
+            .find_map(graph_node)
+            .ok_or_else(|| LightningError::NotFound(format!("Node {pubkey} is not in the graph")))
+    }
+
+    async fn describe_graph(&self) -> Result<NetworkGraph, LightningError> {
+        let (channels, nodes) = tokio::join!(
+            self.client
+                .call::<ListchannelsResponse>("listchannels", json!({})),
+            self.client
+                .call::<ListnodesResponse>("listnodes", json!({})),
+        );
+        let channels = channels
+            .map_err(|err| {
+                LightningError::GetGraphError(format!("Failed to list channels: {err}"))
+            })?
+            .channels;
+        let nodes = nodes
+            .map_err(|err| LightningError::GetGraphError(format!("Failed to list nodes: {err}")))?
+            .nodes;
+
+        let edges = GraphEdge::from_directions(
+            channels
+                .into_iter()
+                .filter(|channel| channel.public)
+                .filter_map(|channel| {
+                    Some(GraphDirection {
+                        channel_id: ShortChannelID::from_str(&channel.short_channel_id).ok()?,
+                        source: PublicKey::from_str(&channel.source).ok()?,
+                        destination: PublicKey::from_str(channel.destination.as_deref()?).ok()?,
+                        capacity_sat: channel.amount_msat? / 1000,
+                        fee: channel.active.then_some(EdgeFee {
+                            base_fee_msat: channel.base_fee_millisatoshi.unwrap_or(0),
+                            fee_rate_ppm: channel.fee_per_millionth.unwrap_or(0),
+                        }),
+                        last_update: channel.last_update,
+                    })
+                }),
+        );
+
+        Ok(NetworkGraph {
+            nodes: nodes.into_iter().filter_map(graph_node).collect(),
+            edges,
+        })
+    }
+
+    async fn subscribe_graph(&self) -> Result<GraphUpdates, LightningError> {
+        Err(LightningError::StreamingError(
+            "Commando has no gossip subscription, the graph is polled instead".to_string(),
+        ))
+    }
+
+    async fn get_payment_details(
+        &self,
+        payment_hash: &PaymentHash,
+    ) -> Result<PaymentDetails, LightningError> {
+        let hex_hash = hex::encode(payment_hash.0);
+
+        let pays: ListpaysResponse = self
+            .client
+            .call("listpays", json!({ "payment_hash": hex_hash }))
+            .await
+            .map_err(|err| LightningError::PaymentError(format!("CLN listpays error: {err}")))?;
+
+        if let Some(pay) = pays.pays.into_iter().last() {
+            let amount = Amount::from_msat(pay.amount_msat.unwrap_or(0));
+            let sent = Amount::from_msat(pay.amount_sent_msat.unwrap_or(0));
+            let destination_pubkey = pay
+                .destination
+                .as_deref()
+                .map(PublicKey::from_str)
+                .transpose()
+                .map_err(|err| {
+                    LightningError::Parse(format!("Invalid destination pubkey: {err}"))
+                })?;
+
+            return Ok(PaymentDetails {
+                state: payment_state(&pay.status),
+                payment_type: PaymentType::Outgoing,
+                amount,
+                amount_usd: self
+                    .price_converter
+                    .node_sats_to_usd(&self.info, amount.sat())
+                    .await?,
+                routing_fee: sent.checked_sub(amount),
+                network: self.network_name().await,
+                description: pay.description,
+                creation_time: (pay.created_at > 0).then_some(pay.created_at),
+                invoice: pay.bolt11,
+                htlcs: self.get_htlcs_for_payment(&hex_hash).await,
+                payment_hash: hex_hash,
+                destination_pubkey,
+                completed_at: pay.completed_at,
+            });
+        }
+
+        let invoices: ListinvoicesResponse = self
+            .client
+            .call("listinvoices", json!({ "payment_hash": hex_hash }))
+            .await
+            .map_err(|err| {
+                LightningError::InvoiceError(format!("CLN listinvoices error: {err}"))
+            })?;
+
+        let invoice = invoices
+            .invoices
+            .into_iter()
+            .next()
+            .ok_or_else(|| LightningError::NotFound(format!("Payment {hex_hash} not found")))?;
+
+        let state = invoice_payment_state(&invoice.status);
+        let completed_at = match state {
+            PaymentState::Settled | PaymentState::Failed => {
+                invoice.paid_at.filter(|&paid_at| paid_at > 0)
+            }
+            _ => None,
+        };
+        let amount = Amount::from_msat(
+            invoice
+                .amount_received_msat
+                .or(invoice.amount_msat)
+                .unwrap_or(0),
+        );
+
+        Ok(PaymentDetails {
+            state,
+            payment_type: PaymentType::Incoming,
+            amount,
+            amount_usd: self
+                .price_converter
+                .node_sats_to_usd(&self.info, amount.sat())
+                .await?,
+            routing_fee: None,
+            network: self.network_name().await,
+            description: invoice.description,
+            creation_time: (invoice.expires_at > 0).then_some(invoice.expires_at),
+            invoice: invoice.bolt11,
+            htlcs: self.get_htlcs_for_payment(&hex_hash).await,
+            payment_hash: hex_hash,
+            destination_pubkey: Some(self.info.pubkey),
+            completed_at,
+        })
+    }
+
+    async fn list_payments(&self) -> Result<Vec<PaymentSummary>, LightningError> {
+        let btc_price = self.price_converter.fetch_node_price(&self.info).await?;
+
+        let (pays, invoices) = tokio::join!(
+            self.client.call::<ListpaysResponse>("listpays", json!({})),
+            self.client
+                .call::<ListinvoicesResponse>("listinvoices", json!({})),
+        );
+        let pays = pays.map_err(|err| LightningError::PaymentError(err.to_string()))?;
+        let invoices = invoices.map_err(|err| LightningError::InvoiceError(err.to_string()))?;
+
+        let outgoing = pays.pays.into_iter().map(|pay| {
+            let amount = Amount::from_msat(pay.amount_msat.unwrap_or(0));
+            PaymentSummary {
+                state: payment_state(&pay.status),
+                payment_type: PaymentType::Outgoing,
+                amount,
+                amount_usd: btc_price
+                    .map(|price| PriceConverter::sats_to_usd_with_price(amount.sat(), price)),
+                routing_fee: pay
+                    .amount_sent_msat
+                    .zip(pay.amount_msat)
+                    .map(|(sent, received)| Amount::from_msat(sent.saturating_sub(received))),
+                creation_time: (pay.created_at > 0).then_some(pay.created_at),
+                invoice: pay.bolt11,
+                payment_hash: pay.payment_hash,
+                completed_at: pay.completed_at,
+            }
+        });
+
+        let incoming = invoices
+            .invoices
+            .into_iter()
+            .filter(|invoice| invoice.pay_index.is_some())
+            .map(|invoice| {
+                let state = invoice_payment_state(&invoice.status);
+                let amount = Amount::from_msat(
+                    invoice
+                        .amount_received_msat
+                        .or(invoice.amount_msat)
+                        .unwrap_or(0),
+                );
+                let completed_at = match state {
+                    PaymentState::Settled | PaymentState::Failed => {
+                        invoice.paid_at.filter(|&paid_at| paid_at > 0)
+                    }
+                    _ => None,
+                };
+                PaymentSummary {
+                    state,
+                    payment_type: PaymentType::Incoming,
+                    amount,
+                    amount_usd: btc_price
+                        .map(|price| PriceConverter::sats_to_usd_with_price(amount.sat(), price)),
+                    routing_fee: None,
+                    creation_time: (invoice.expires_at > 0).then_some(invoice.expires_at),
+                    invoice: invoice.bolt11,
+                    payment_hash: invoice.payment_hash,
+                    completed_at,
+                }
+            });
+
+        let mut seen_hashes = HashSet::new();
+        let mut all_payments: Vec<PaymentSummary> = outgoing
+            .chain(incoming)
+            .filter(|payment| seen_hashes.insert(payment.payment_hash.clone()))
+            .collect();
+
+        sort_payments(&mut all_payments);
+
+        Ok(all_payments)
+    }
+
+    async fn list_inflight_payments(&self) -> Result<Vec<PaymentSummary>, LightningError> {
+        let btc_price = self.price_converter.fetch_node_price(&self.info).await?;
+        let pays = self
+            .client
+            .call::<ListpaysResponse>("listpays", json!({ "status": "pending" }))
+            .await
+            .map_err(|err| LightningError::PaymentError(err.to_string()))?;
+
+        Ok(pays
+            .pays
+            .into_iter()
+            .map(|pay| {
+                let amount = Amount::from_msat(pay.amount_msat.unwrap_or(0));
+                PaymentSummary {
+                    state: PaymentState::Inflight,
+                    payment_type: PaymentType::Outgoing,
+                    amount,
+                    amount_usd: btc_price
+                        .map(|price| PriceConverter::sats_to_usd_with_price(amount.sat(), price)),
+                    routing_fee: None,
+                    creation_time: (pay.created_at > 0).then_some(pay.created_at),
+                    invoice: pay.bolt11,
+                    payment_hash: pay.payment_hash,
+                    completed_at: None,
+                }
+            })
+            .collect())
+    }
+
+    async fn track_payment<'a>(
+        &'a self,
+        payment_hash: &PaymentHash,
+    ) -> Result<PaymentUpdates<'a>, LightningError> {
+        let hex_hash = hex::encode(payment_hash.0);
+
+        // waitsendpay returns as soon as an attempt resolves; its timeout stays
+        // below the commando request timeout
+        Ok(poll_payment_updates(self, *payment_hash, move || {
+            let params = json!({
+                "payment_hash": hex_hash,
+                "timeout": PAYMENT_WAIT_TIMEOUT_SECS,
+            });
+            async move {
+                let _ = self.client.call::<Value>("waitsendpay", params).await;
+            }
+        }))
+    }
+
+    async fn stream_events(
+        &mut self,
+    ) -> Result<Pin<Box<dyn Stream<Item = CapturedEvent> + Send>>, LightningError> {
+        // Commando has no subscription mechanism; events are not collected for these nodes.
+        Ok(Box::pin(futures::stream::pending()))
+    }
+
+    async fn list_invoices(&self) -> Result<Vec<CustomInvoice>, LightningError> {
+        let response: ListinvoicesResponse = self
+            .client
+            .call("listinvoices", json!({}))
+            .await
+            .map_err(|err| LightningError::InvoiceError(err.to_string()))?;
+
+        let now = chrono::Utc::now().timestamp() as u64;
+        Ok(response
+            .invoices
+            .into_iter()
+            .map(|invoice| custom_invoice(invoice, now))
+            .collect())
+    }
+
+    async fn get_invoice_details(
+        &self,
+        payment_hash: &PaymentHash,
+    ) -> Result<CustomInvoice, LightningError> {
+        let response: ListinvoicesResponse = self
+            .client
+            .call(
+                "listinvoices",
+                json!({ "payment_hash": hex::encode(payment_hash.0) }),
+            )
+            .await
+            .map_err(|err| {
+                LightningError::InvoiceError(format!("CLN listinvoices error: {err}"))
+            })?;
+
+        let invoice = response
+            .invoices
+            .into_iter()
+            .next()
+            .ok_or_else(|| LightningError::NotFound("Invoice not found".into()))?;
+
+        Ok(custom_invoice(
+            invoice,
+            chrono::Utc::now().timestamp() as u64,
+        ))
+    }
+
+    async fn get_wallet_balance(&self) -> Result<u64, LightningError> {
+        let response: ListfundsResponse =
+            self.client
+                .call("listfunds", json!({}))
+                .await
+                .map_err(|err| {
+                    LightningError::GetInfoError(format!("Failed to get wallet balance: {err}"))
+                })?;
+
+        Ok(response
+            .outputs
+            .iter()
+            .filter(|output| output.status == "confirmed")
+            .map(|output| output.amount_msat / 1000)
+            .sum())
+    }
+
+    async fn get_forwarding_fees(&self, since: u64) -> Result<u64, LightningError> {
+        let response: ListforwardsResponse = self
+            .client
+            .call("listforwards", json!({ "status": "settled" }))
+            .await
+            .map_err(|err| {
+                LightningError::PaymentError(format!("Failed to list forwards: {err}"))
+            })?;
+
+        Ok(response
+            .forwards
+            .iter()
+            .filter(|forward| forward.received_time as u64 >= since)
+            .map(|forward| forward.fee_msat.unwrap_or(0))
+            .sum())
+    }
+
+    async fn list_forwards(&self, since: u64) -> Result<Vec<ForwardSummary>, LightningError> {
+        let response: ListforwardsResponse = self
+            .client
+            .call("listforwards", json!({ "status": "settled" }))
+            .await
+            .map_err(|err| {
+                LightningError::PaymentError(format!("Failed to list forwards: {err}"))
+            })?;
+
+        Ok(response
+            .forwards
+            .into_iter()
+            .filter_map(|forward| {
+                let resolved_at = forward.resolved_time.unwrap_or(forward.received_time) as u64;
+                (resolved_at >= since).then(|| ForwardSummary {
+                    chan_id_in: ShortChannelID::from_str(&forward.in_channel).ok(),
+                    chan_id_out: forward
+                        .out_channel
+                        .as_deref()
+                        .and_then(|scid| ShortChannelID::from_str(scid).ok()),
+                    amount_in_msat: forward.in_msat.unwrap_or(0),
+                    amount_out_msat: forward.out_msat.unwrap_or(0),
+                    fee_msat: forward.fee_msat.unwrap_or(0),
+                    resolved_at,
+                })
+            })
+            .collect())
+    }
+
+    async fn set_channel_fee_rate(
+        &self,
+        channel_id: &ShortChannelID,
+        fee_rate_ppm: u32,
+    ) -> Result<(), LightningError> {
+        self.client
+            .call::<Value>(
+                "setchannel",
+                json!({ "id": channel_id.to_block_format(), "feeppm": fee_rate_ppm }),
+            )
+            .await
+            .map_err(|err| {
+                LightningError::ChannelError(format!(
+                    "Failed to set fee rate of channel {channel_id}: {err}"
+                ))
+            })?;
+
+        Ok(())
+    }
+
+    async fn list_pending_htlcs(&self) -> Result<PendingHtlcs, LightningError> {
+        let (peer_channels, info) = tokio::join!(
+            self.client
+                .call::<ListpeerchannelsResponse>("listpeerchannels", json!({})),
+            self.client.call::<GetinfoResponse>("getinfo", json!({})),
+        );
+
+        let peer_channels =
+            peer_channels.map_err(|err| LightningError::ChannelError(err.to_string()))?;
+        let block_height = info
+            .map_err(|err| LightningError::GetInfoError(err.to_string()))?
+            .blockheight;
+
+        let htlcs = peer_channels
+            .channels
+            .into_iter()
+            .filter_map(|channel| {
+                let channel_id =
+                    ShortChannelID::from_str(channel.short_channel_id.as_deref()?).ok()?;
+                let remote_pubkey = PublicKey::from_str(&channel.peer_id).ok();
+                Some(channel.htlcs.into_iter().map(move |htlc| PendingHtlc {
+                    channel_id,
+                    remote_pubkey,
+                    incoming: htlc.direction == "in",
+                    amount_msat: htlc.amount_msat,
+                    payment_hash: htlc.payment_hash,
+                    expiry_height: htlc.expiry,
+                }))
+            })
+            .flatten()
+            .collect();
+
+        Ok(PendingHtlcs {
+            block_height,
+            htlcs,
+        })
+    }
+
+    async fn list_channel_breaches(&self) -> Result<Vec<ChannelBreach>, LightningError> {
+        Err(LightningError::ValidationError(
+            "CLN doesn't report breached channels".to_string(),
+        ))
+    }
+
+    async fn list_liquidity_ads(&self) -> Result<Vec<LiquidityAd>, LightningError> {
+        let nodes = self
+            .client
+            .call::<ListnodesResponse>("listnodes", json!({}))
+            .await
+            .map_err(|err| LightningError::GetGraphError(format!("Failed to list nodes: {err}")))?
+            .nodes;
+
+        Ok(nodes
+            .into_iter()
+            .filter_map(|node| {
+                let ad = node.option_will_fund?;
+                Some(LiquidityAd {
+                    pubkey: PublicKey::from_str(&node.nodeid).ok()?,
+                    alias: node.alias,
+                    lease_fee_base_msat: ad.lease_fee_base_msat,
+                    lease_fee_basis: ad.lease_fee_basis,
+                    funding_weight: ad.funding_weight,
+                    channel_fee_max_base_msat: ad.channel_fee_max_base_msat,
+                    channel_fee_max_proportional_thousandths: ad
+                        .channel_fee_max_proportional_thousandths,
+                    compact_lease: ad.compact_lease,
+                })
+            })
+            .collect())
+    }
+
+    async fn open_dual_funded_channel(
+        &self,
+        request: &DualFundRequest,
+    ) -> Result<OpenedChannel, LightningError> {
+        if let Some(address) = &request.address {
+            self.client
+                .call::<Value>(
+                    "connect",
+                    json!({ "id": format!("{}@{address}", request.pubkey) }),
+                )
+                .await
+                .map_err(|err| {
+                    LightningError::ConnectionError(format!(
+                        "Failed to connect to {}: {err}",
+                        request.pubkey
+                    ))
+                })?;
+        }
+
+        let mut params = json!({
+            "id": request.pubkey.to_string(),
+            "amount": request.amount_sat,
+            "announce": !request.private,
+        });
+        if request.request_amount_sat > 0 {
+            params["request_amt"] = json!(request.request_amount_sat);
+            params["compact_lease"] = json!(request.compact_lease);
+        }
+        if let Some(rate) = request.sat_per_vbyte {
+            params["feerate"] = json!(format!("{}perkb", rate * 1000));
+        }
+
+        let response = self
+            .client
+            .call::<FundchannelResponse>("fundchannel", params)
+            .await
+            .map_err(|err| {
+                LightningError::ChannelError(format!(
+                    "Failed to open channel with {}: {err}",
+                    request.pubkey
+                ))
+            })?;
+
+        let txid = Txid::from_str(&response.txid)
+            .map_err(|err| LightningError::Parse(format!("Invalid funding txid: {err}")))?;
+        Ok(OpenedChannel {
+            channel_point: OutPoint {
+                txid,
+                vout: response.outnum,
+            },
+            channel_id: response.channel_id,
+        })
+    }
+
+    async fn list_peers(&self) -> Result<Vec<PeerSummary>, LightningError> {
+        let peers = self
+            .client
+            .call::<ListpeersResponse>("listpeers", json!({}))
+            .await
+            .map_err(|err| LightningError::ConnectionError(format!("Failed to list peers: {err}")))?
+            .peers;
+
+        Ok(peers
+            .into_iter()
+            .filter_map(|peer| {
+                Some(PeerSummary {
+                    pubkey: PublicKey::from_str(&peer.id).ok()?,
+                    address: peer.netaddr.into_iter().next(),
+                    connected: peer.connected,
+                    ping_time_us: None,
+                })
+            })
+            .collect())
+    }
+
+    /// The round trip includes the commando hop to the node, so it reads
+    /// higher than over gRPC.
+    async fn ping_peer(&self, pubkey: &PublicKey) -> Result<Duration, LightningError> {
+        let started = Instant::now();
+        self.client
+            .call::<Value>("ping", json!({ "id": pubkey.to_string() }))
+            .await
+            .map_err(|err| {
+                LightningError::ConnectionError(format!("Failed to ping {pubkey}: {err}"))
+            })?;
+        Ok(started.elapsed())
+    }
+    async fn list_transaction_labels(&self) -> Result<Vec<TransactionLabel>, LightningError> {
+        Err(LightningError::ValidationError(
+            "CLN doesn't label on-chain transactions".to_string(),
+        ))
+    }
+
+    async fn label_transaction(&self, _txid: &Txid, _label: &str) -> Result<(), LightningError> {
+        Err(LightningError::ValidationError(
+            "CLN doesn't label on-chain transactions".to_string(),
+        ))
+    }
+
+    async fn sign_message(&self, message: &str) -> Result<String, LightningError> {
+        let response: SignmessageResponse = self
+            .client
+            .call("signmessage", json!({ "message": message }))
+            .await
+            .map_err(|err| {
+                LightningError::ValidationError(format!("Failed to sign message: {err}"))
+            })?;
+        Ok(response.zbase)
+    }
+}
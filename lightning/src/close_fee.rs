@@ -0,0 +1,151 @@
+//! Projected on-chain cost of closing a channel.
+//!
+//! Weights follow the BOLT 3 expected weights. A cooperative close is a single
+//! transaction spending the funding output to both parties. A force close
+//! broadcasts the latest commitment transaction, then sweeps our delayed output
+//! and resolves every pending HTLC with a second-stage transaction. All of it is
+//! priced at the node's fee estimate for the requested confirmation target.
+
+use crate::types::ShortChannelID;
+use serde::Serialize;
+
+/// Closing transaction with two P2WPKH outputs.
+const COOPERATIVE_CLOSE_WEIGHT: u64 = 672;
+/// Commitment transaction without HTLC outputs.
+const COMMITMENT_BASE_WEIGHT: u64 = 724;
+/// Commitment transaction without HTLC outputs, including both anchor outputs.
+const ANCHOR_COMMITMENT_BASE_WEIGHT: u64 = 1124;
+/// Each HTLC output added to the commitment transaction.
+const HTLC_OUTPUT_WEIGHT: u64 = 172;
+/// HTLC-success transaction, the heavier of the two second-stage transactions.
+const HTLC_SECOND_STAGE_WEIGHT: u64 = 703;
+/// Sweep of our `to_local` output once its CSV delay has passed.
+const TO_LOCAL_SWEEP_WEIGHT: u64 = 486;
+/// Default confirmation target for estimates.
+pub const DEFAULT_CLOSE_TARGET_CONF: u32 = 6;
+
+/// Commitment details of a channel that determine its force close weight.
+#[derive(Debug, Clone, Copy)]
+pub struct CommitmentShape {
+    /// Whether the commitment has anchor outputs.
+    pub anchors: bool,
+    pub pending_htlcs: u32,
+    /// Weight reported by the node, preferred over the BOLT 3 estimate.
+    pub commit_weight: Option<u64>,
+    /// Whether this node opened the channel; the opener pays closing fees.
+    pub initiator: Option<bool>,
+}
+
+impl CommitmentShape {
+    fn commitment_weight(&self) -> u64 {
+        self.commit_weight.unwrap_or_else(|| {
+            let base = if self.anchors {
+                ANCHOR_COMMITMENT_BASE_WEIGHT
+            } else {
+                COMMITMENT_BASE_WEIGHT
+            };
+            base + u64::from(self.pending_htlcs) * HTLC_OUTPUT_WEIGHT
+        })
+    }
+}
+
+/// Projected fees for closing a channel cooperatively or by force.
+#[derive(Debug, Serialize)]
+pub struct CloseFeeEstimate {
+    pub channel_id: ShortChannelID,
+    pub target_conf: u32,
+    pub fee_rate_sat_per_vbyte: f64,
+    pub anchors: bool,
+    pub pending_htlcs: u32,
+    /// Whether this node opened the channel and so pays the closing fees.
+    pub initiator: Option<bool>,
+    pub cooperative_close_fee_sat: u64,
+    /// Commitment transaction, `to_local` sweep and second-stage HTLC transactions.
+    pub force_close_fee_sat: u64,
+    pub force_close_weight: u64,
+}
+
+impl CloseFeeEstimate {
+    /// Prices both ways of closing at a fee rate in sat/kw.
+    pub fn new(
+        channel_id: ShortChannelID,
+        target_conf: u32,
+        sat_per_kw: u64,
+        shape: CommitmentShape,
+    ) -> Self {
+        let force_close_weight = shape.commitment_weight()
+            + TO_LOCAL_SWEEP_WEIGHT
+            + u64::from(shape.pending_htlcs) * HTLC_SECOND_STAGE_WEIGHT;
+        let fee = |weight: u64| (weight * sat_per_kw).div_ceil(1000);
+
+        Self {
+            channel_id,
+            target_conf,
+            fee_rate_sat_per_vbyte: sat_per_kw as f64 / 250.0,
+            anchors: shape.anchors,
+            pending_htlcs: shape.pending_htlcs,
+            initiator: shape.initiator,
+            cooperative_close_fee_sat: fee(COOPERATIVE_CLOSE_WEIGHT),
+            force_close_fee_sat: fee(force_close_weight),
+            force_close_weight,
+        }
+    }
+}
+
+/// Picks the feerate, in sat/kw, estimated for the largest block count within
+/// the target, falling back to the fastest estimate when all are slower.
+pub fn fee_rate_for_target(
+    estimates: impl IntoIterator<Item = (u32, u64)>,
+    target_conf: u32,
+) -> Option<u64> {
+    let estimates: Vec<(u32, u64)> = estimates.into_iter().collect();
+    estimates
+        .iter()
+        .filter(|(blockcount, _)| *blockcount <= target_conf)
+        .max_by_key(|(blockcount, _)| *blockcount)
+        .or_else(|| estimates.iter().min_by_key(|(blockcount, _)| *blockcount))
+        .map(|(_, sat_per_kw)| *sat_per_kw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prices_closes_by_weight() {
+        let shape = CommitmentShape {
+            anchors: false,
+            pending_htlcs: 2,
+            commit_weight: None,
+            initiator: Some(true),
+        };
+        // 10 sat/vB
+        let estimate = CloseFeeEstimate::new(ShortChannelID(1), 6, 2_500, shape);
+        assert_eq!(estimate.fee_rate_sat_per_vbyte, 10.0);
+        assert_eq!(estimate.cooperative_close_fee_sat, 1_680);
+        assert_eq!(estimate.force_close_weight, 724 + 2 * 172 + 486 + 2 * 703);
+        assert_eq!(estimate.force_close_fee_sat, 7_400);
+
+        let reported = CloseFeeEstimate::new(
+            ShortChannelID(1),
+            6,
+            2_500,
+            CommitmentShape {
+                anchors: true,
+                pending_htlcs: 0,
+                commit_weight: Some(1_124),
+                ..shape
+            },
+        );
+        assert_eq!(reported.force_close_weight, 1_124 + 486);
+    }
+
+    #[test]
+    fn picks_fee_rate_for_target() {
+        let estimates = [(2, 5_000), (6, 2_000), (12, 1_000), (100, 253)];
+        assert_eq!(fee_rate_for_target(estimates, 6), Some(2_000));
+        assert_eq!(fee_rate_for_target(estimates, 50), Some(1_000));
+        assert_eq!(fee_rate_for_target(estimates, 1), Some(5_000));
+        assert_eq!(fee_rate_for_target([], 6), None);
+    }
+}
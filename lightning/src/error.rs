@@ -0,0 +1,44 @@
+//! Errors of node operations.
+
+use thiserror::Error;
+
+/// Represents errors that can occur during Lightning Network operations.
+#[derive(Debug, Error)]
+pub enum LightningError {
+    /// Error that occurred while connecting to a Lightning node.
+    #[error("Node connection error: {0}")]
+    ConnectionError(String),
+    /// Error that occurred while retrieving node information.
+    #[error("Get info error: {0}")]
+    GetInfoError(String),
+    /// Error that occurred while retrieving payments.
+    #[error("Error while retrieving payments: {0}")]
+    PaymentError(String),
+    /// Error that occurred while retrieving invoices.
+    #[error("Error while retrieving invoices: {0}")]
+    InvoiceError(String),
+    /// Error that occurred during configuration validation.
+    #[error("Config validation failed: {0}")]
+    ValidationError(String),
+    /// Error that occurred while getting graph.
+    #[error("Get graph error: {0}")]
+    GetGraphError(String),
+    /// Error that occurred while streaming events.
+    #[error("Streaming error: {0}")]
+    StreamingError(String),
+    /// Channel-related error.
+    #[error("Channel error: {0}")]
+    ChannelError(String),
+    /// Generic not found error.
+    #[error("Not found: {0}")]
+    NotFound(String),
+    /// Parse error for things like pubkeys or strings.
+    #[error("Parse error: {0}")]
+    Parse(String),
+    #[error("Network error: {0}")]
+    /// Network error.
+    NetworkError(String),
+    /// The node's RPC budget has too many calls waiting.
+    #[error("Node rate limited: {0}")]
+    RateLimited(String),
+}
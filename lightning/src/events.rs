@@ -0,0 +1,132 @@
+//! Events read from a node's subscriptions.
+
+use crate::amount::Amount;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LNDEvent {
+    ChannelOpened {
+        active: bool,
+        remote_pubkey: String,
+        channel_point: String,
+        chan_id: u64,
+        capacity: i64,
+        local_balance: i64,
+        remote_balance: i64,
+        total_satoshis_sent: i64,
+        total_satoshis_received: i64,
+    },
+    ChannelClosed {
+        channel_point: String,
+        chan_id: u64,
+        chain_hash: String,
+        closing_tx_hash: String,
+        remote_pubkey: String,
+        capacity: i64,
+        close_height: u32,
+        settled_balance: i64,
+        time_locked_balance: i64,
+        close_type: i32,
+        open_initiator: i32,
+        close_initiator: i32,
+    },
+    InvoiceCreated {
+        preimage: Vec<u8>,
+        hash: Vec<u8>,
+        value: Amount,
+        state: i32,
+        memo: String,
+        creation_date: i64,
+        expiry: i64,
+        payment_request: String,
+    },
+    InvoiceSettled {
+        preimage: Vec<u8>,
+        hash: Vec<u8>,
+        value: Amount,
+        state: i32,
+        memo: String,
+        creation_date: i64,
+        payment_request: String,
+    },
+    InvoiceCancelled {
+        preimage: Vec<u8>,
+        hash: Vec<u8>,
+        value: Amount,
+        state: i32,
+        memo: String,
+        creation_date: i64,
+        payment_request: String,
+    },
+    InvoiceAccepted {
+        preimage: Vec<u8>,
+        hash: Vec<u8>,
+        value: Amount,
+        state: i32,
+        memo: String,
+        creation_date: i64,
+        payment_request: String,
+    },
+    /// Raised by the expiry watcher when an open invoice reaches its expiry unpaid.
+    InvoiceExpired {
+        hash: Vec<u8>,
+        value: Amount,
+        memo: String,
+        creation_date: i64,
+        expiry: i64,
+        payment_request: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CLNEvent {
+    ChannelOpened {},
+    /// A channel's funding output was replaced by a splice, changing its capacity.
+    ChannelSpliced {
+        remote_pubkey: String,
+        previous_chan_id: u64,
+        chan_id: u64,
+        previous_channel_point: Option<String>,
+        channel_point: Option<String>,
+        previous_capacity: u64,
+        capacity: u64,
+    },
+    /// Raised by the expiry watcher when an open invoice reaches its expiry unpaid.
+    InvoiceExpired {
+        hash: Vec<u8>,
+        value: Amount,
+        memo: String,
+        creation_date: i64,
+        expiry: i64,
+        payment_request: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum NodeSpecificEvent {
+    LND(LNDEvent),
+    CLN(CLNEvent),
+}
+
+/// A node event with the full node message it was parsed from, when the node
+/// implementation provides one.
+#[derive(Debug, Clone)]
+pub struct CapturedEvent {
+    pub event: NodeSpecificEvent,
+    pub raw: Option<RawPayload>,
+}
+
+impl From<NodeSpecificEvent> for CapturedEvent {
+    fn from(event: NodeSpecificEvent) -> Self {
+        Self { event, raw: None }
+    }
+}
+
+/// A node message as JSON, with the kind of message it is.
+#[derive(Debug, Clone, Serialize)]
+pub struct RawPayload {
+    /// Kind of node message, e.g. `lnd_invoice`
+    pub source: &'static str,
+    pub message: Value,
+}
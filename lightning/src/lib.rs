@@ -0,0 +1,39 @@
+//! Clients of Lightning Network nodes, LND and Core Lightning alike.
+//!
+//! Connection types (`LndConnection`, `ClnConnection`, `ClnRuneConnection`)
+//! hold what is needed to reach a node. The node types built from them
+//! (`LndNode`, `ClnNode`, `ClnCommandoNode`) implement `LightningClient`, which
+//! maps each implementation's RPC replies to the models in `types`.
+//!
+//! Each backend sits behind a feature, `lnd` and `cln`, both on by default.
+
+#[cfg(not(any(feature = "lnd", feature = "cln")))]
+compile_error!("nodegaze-lightning needs the `lnd` or the `cln` feature");
+
+pub mod amount;
+pub mod client;
+#[cfg(feature = "cln")]
+pub mod cln;
+#[cfg(feature = "cln")]
+pub mod cln_commando;
+pub mod close_fee;
+pub mod error;
+pub mod events;
+#[cfg(feature = "lnd")]
+pub mod lnd;
+#[cfg(feature = "lnd")]
+pub mod lnd_rest;
+#[cfg(feature = "lnd")]
+pub mod raw_events;
+pub mod sats_to_usd;
+pub mod socks_proxy;
+pub mod types;
+
+pub use client::{ConnectionRequest, GraphUpdates, LightningClient, PaymentUpdates};
+#[cfg(feature = "cln")]
+pub use cln::{ClnConnection, ClnNode, ClnRuneConnection};
+#[cfg(feature = "cln")]
+pub use cln_commando::ClnCommandoNode;
+pub use error::LightningError;
+#[cfg(feature = "lnd")]
+pub use lnd::{LndConnection, LndNode, LndTransport};
@@ -0,0 +1,2131 @@
+//! LND nodes, reached over gRPC or, when only port 8080 is exposed, over
+//! the REST proxy (see `lnd_rest`).
+
+use crate::{
+    amount::Amount,
+    client::{
+        GraphUpdates, LightningClient, PaymentUpdates, parse_channel_point, poll_payment_updates,
+    },
+    close_fee::{CloseFeeEstimate, CommitmentShape},
+    error::LightningError,
+    events::{CapturedEvent, LNDEvent, NodeSpecificEvent},
+    lnd_rest::LndRestClient,
+    raw_events,
+    sats_to_usd::PriceConverter,
+    socks_proxy,
+    types::{
+        self, ChannelBreach, ChannelDetails, ChannelState, ChannelSummary, CustomInvoice,
+        DualFundRequest, EdgeFee, Feature, ForwardSummary, GraphDirection, GraphEdge, GraphNode,
+        GraphUpdate, Hop, InvoiceHtlc, InvoiceStatus, LiquidityAd, NetworkGraph, NodeId, NodeInfo,
+        NodePolicy, OpenedChannel, PaymentDetails, PaymentHtlc, PaymentState, PaymentSummary,
+        PaymentType, PeerSummary, PendingHtlc, PendingHtlcs, ReportedInvoiceState, Route,
+        ShortChannelID, TransactionLabel,
+    },
+};
+
+use async_stream::stream;
+use async_trait::async_trait;
+use bitcoin::{Address, CompressedPublicKey, Network, Txid, hashes::Hash, secp256k1::PublicKey};
+use futures::stream::{SelectAll, StreamExt};
+use hex;
+use lightning::ln::{PaymentHash, features::NodeFeatures};
+use lightning_invoice::{Bolt11Invoice, Bolt11InvoiceDescription};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    pin::Pin,
+    str::FromStr,
+};
+use tokio::sync::Mutex;
+use tokio::time::Duration;
+use tokio_stream::Stream;
+use tonic_lnd::{
+    Client,
+    lnrpc::{
+        ChanInfoRequest, ChannelEdge, ChannelEventSubscription, ChannelEventUpdate, ChannelGraph,
+        ChannelGraphRequest, ClosedChannelsRequest, ClosedChannelsResponse, EstimateFeeRequest,
+        EstimateFeeResponse, ForwardingHistoryRequest, ForwardingHistoryResponse, GetInfoRequest,
+        GetInfoResponse, GetTransactionsRequest, GraphTopologySubscription, GraphTopologyUpdate,
+        Invoice, InvoiceSubscription, LightningNode, ListChannelsRequest, ListChannelsResponse,
+        ListInvoiceRequest, ListInvoiceResponse, ListPaymentsRequest, ListPaymentsResponse,
+        ListPeersRequest, ListPeersResponse, NodeInfoRequest, PendingChannelsRequest,
+        PendingChannelsResponse, PolicyUpdateRequest, PolicyUpdateResponse, RoutingPolicy,
+        SignMessageRequest, SignMessageResponse, TransactionDetails, WalletBalanceRequest,
+        WalletBalanceResponse,
+        channel_event_update::{Channel as EventChannel, UpdateType as LndChannelUpdateType},
+        channel_point::FundingTxid,
+        invoice::InvoiceState,
+        payment::PaymentStatus,
+        policy_update_request,
+    },
+    routerrpc::TrackPaymentRequest,
+    tonic::{Code, Status, Streaming},
+    walletrpc::LabelTransactionRequest,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LndConnection {
+    #[serde(with = "types::serde_node_id")]
+    pub id: NodeId,
+    #[serde(with = "types::serde_address")]
+    pub address: String,
+    #[serde(deserialize_with = "types::deserialize_path")]
+    pub macaroon: String,
+    #[serde(deserialize_with = "types::deserialize_path")]
+    pub cert: String,
+    /// Optional SOCKS5 proxy (`host:port`) used to reach the node, e.g. Tor.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// API the node is reached over; gRPC unless only the REST proxy is exposed.
+    #[serde(default)]
+    pub transport: LndTransport,
+}
+
+/// API an LND node is reached over.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LndTransport {
+    #[default]
+    Grpc,
+    /// LND's REST proxy, usually on port 8080. It has no subscriptions, so no
+    /// events are collected and payments are tracked by polling.
+    Rest,
+}
+
+impl LndTransport {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LndTransport::Grpc => "grpc",
+            LndTransport::Rest => "rest",
+        }
+    }
+
+    /// Reads a stored transport, where nodes stored before REST was supported
+    /// have none.
+    pub fn from_stored(transport: Option<&str>) -> Self {
+        match transport {
+            Some("rest") => LndTransport::Rest,
+            _ => LndTransport::Grpc,
+        }
+    }
+}
+
+/// The API an `LndNode` sends its calls over. Both return the `lnrpc` types,
+/// so the node maps them the same way. The gRPC client is boxed as it is far
+/// larger than the REST one.
+enum LndRpc {
+    Grpc(Box<Mutex<Client>>),
+    Rest(LndRestClient),
+}
+
+impl LndRpc {
+    /// Returns a handle to the Lightning service of a gRPC connection.
+    async fn lightning(client: &Mutex<Client>) -> tonic_lnd::LightningClient {
+        client.lock().await.lightning().clone()
+    }
+
+    async fn get_info(&self) -> Result<GetInfoResponse, Status> {
+        match self {
+            LndRpc::Grpc(client) => Self::lightning(client)
+                .await
+                .get_info(GetInfoRequest {})
+                .await
+                .map(|response| response.into_inner()),
+            LndRpc::Rest(client) => client.get_info().await,
+        }
+    }
+
+    async fn list_channels(
+        &self,
+        request: ListChannelsRequest,
+    ) -> Result<ListChannelsResponse, Status> {
+        match self {
+            LndRpc::Grpc(client) => Self::lightning(client)
+                .await
+                .list_channels(request)
+                .await
+                .map(|response| response.into_inner()),
+            LndRpc::Rest(client) => client.list_channels(request).await,
+        }
+    }
+
+    async fn closed_channels(
+        &self,
+        request: ClosedChannelsRequest,
+    ) -> Result<ClosedChannelsResponse, Status> {
+        match self {
+            LndRpc::Grpc(client) => Self::lightning(client)
+                .await
+                .closed_channels(request)
+                .await
+                .map(|response| response.into_inner()),
+            LndRpc::Rest(client) => client.closed_channels(request).await,
+        }
+    }
+
+    async fn pending_channels(&self) -> Result<PendingChannelsResponse, Status> {
+        match self {
+            LndRpc::Grpc(client) => Self::lightning(client)
+                .await
+                .pending_channels(PendingChannelsRequest {})
+                .await
+                .map(|response| response.into_inner()),
+            LndRpc::Rest(client) => client.pending_channels().await,
+        }
+    }
+
+    async fn get_chan_info(&self, request: ChanInfoRequest) -> Result<ChannelEdge, Status> {
+        match self {
+            LndRpc::Grpc(client) => Self::lightning(client)
+                .await
+                .get_chan_info(request)
+                .await
+                .map(|response| response.into_inner()),
+            LndRpc::Rest(client) => client.get_chan_info(request).await,
+        }
+    }
+
+    async fn describe_graph(&self, request: ChannelGraphRequest) -> Result<ChannelGraph, Status> {
+        match self {
+            LndRpc::Grpc(client) => Self::lightning(client)
+                .await
+                .describe_graph(request)
+                .await
+                .map(|response| response.into_inner()),
+            LndRpc::Rest(client) => client.describe_graph(request).await,
+        }
+    }
+
+    async fn get_node_info(
+        &self,
+        request: NodeInfoRequest,
+    ) -> Result<tonic_lnd::lnrpc::NodeInfo, Status> {
+        match self {
+            LndRpc::Grpc(client) => Self::lightning(client)
+                .await
+                .get_node_info(request)
+                .await
+                .map(|response| response.into_inner()),
+            LndRpc::Rest(client) => client.get_node_info(request).await,
+        }
+    }
+
+    async fn get_transactions(
+        &self,
+        request: GetTransactionsRequest,
+    ) -> Result<TransactionDetails, Status> {
+        match self {
+            LndRpc::Grpc(client) => Self::lightning(client)
+                .await
+                .get_transactions(request)
+                .await
+                .map(|response| response.into_inner()),
+            LndRpc::Rest(client) => client.get_transactions(request).await,
+        }
+    }
+
+    async fn estimate_fee(
+        &self,
+        request: EstimateFeeRequest,
+    ) -> Result<EstimateFeeResponse, Status> {
+        match self {
+            LndRpc::Grpc(client) => Self::lightning(client)
+                .await
+                .estimate_fee(request)
+                .await
+                .map(|response| response.into_inner()),
+            LndRpc::Rest(client) => client.estimate_fee(request).await,
+        }
+    }
+
+    async fn list_payments(
+        &self,
+        request: ListPaymentsRequest,
+    ) -> Result<ListPaymentsResponse, Status> {
+        match self {
+            LndRpc::Grpc(client) => Self::lightning(client)
+                .await
+                .list_payments(request)
+                .await
+                .map(|response| response.into_inner()),
+            LndRpc::Rest(client) => client.list_payments(request).await,
+        }
+    }
+
+    async fn list_invoices(
+        &self,
+        request: ListInvoiceRequest,
+    ) -> Result<ListInvoiceResponse, Status> {
+        match self {
+            LndRpc::Grpc(client) => Self::lightning(client)
+                .await
+                .list_invoices(request)
+                .await
+                .map(|response| response.into_inner()),
+            LndRpc::Rest(client) => client.list_invoices(request).await,
+        }
+    }
+
+    async fn lookup_invoice(
+        &self,
+        request: tonic_lnd::lnrpc::PaymentHash,
+    ) -> Result<Invoice, Status> {
+        match self {
+            LndRpc::Grpc(client) => Self::lightning(client)
+                .await
+                .lookup_invoice(request)
+                .await
+                .map(|response| response.into_inner()),
+            LndRpc::Rest(client) => client.lookup_invoice(request).await,
+        }
+    }
+
+    async fn forwarding_history(
+        &self,
+        request: ForwardingHistoryRequest,
+    ) -> Result<ForwardingHistoryResponse, Status> {
+        match self {
+            LndRpc::Grpc(client) => Self::lightning(client)
+                .await
+                .forwarding_history(request)
+                .await
+                .map(|response| response.into_inner()),
+            LndRpc::Rest(client) => client.forwarding_history(request).await,
+        }
+    }
+
+    async fn update_channel_policy(
+        &self,
+        request: PolicyUpdateRequest,
+    ) -> Result<PolicyUpdateResponse, Status> {
+        match self {
+            LndRpc::Grpc(client) => Self::lightning(client)
+                .await
+                .update_channel_policy(request)
+                .await
+                .map(|response| response.into_inner()),
+            LndRpc::Rest(client) => client.update_channel_policy(request).await,
+        }
+    }
+
+    async fn list_peers(&self, request: ListPeersRequest) -> Result<ListPeersResponse, Status> {
+        match self {
+            LndRpc::Grpc(client) => Self::lightning(client)
+                .await
+                .list_peers(request)
+                .await
+                .map(|response| response.into_inner()),
+            LndRpc::Rest(client) => client.list_peers(request).await,
+        }
+    }
+
+    async fn sign_message(
+        &self,
+        request: SignMessageRequest,
+    ) -> Result<SignMessageResponse, Status> {
+        match self {
+            LndRpc::Grpc(client) => Self::lightning(client)
+                .await
+                .sign_message(request)
+                .await
+                .map(|response| response.into_inner()),
+            LndRpc::Rest(client) => client.sign_message(request).await,
+        }
+    }
+
+    async fn wallet_balance(&self) -> Result<WalletBalanceResponse, Status> {
+        match self {
+            LndRpc::Grpc(client) => Self::lightning(client)
+                .await
+                .wallet_balance(WalletBalanceRequest {})
+                .await
+                .map(|response| response.into_inner()),
+            LndRpc::Rest(client) => client.wallet_balance().await,
+        }
+    }
+
+    async fn label_transaction(&self, request: LabelTransactionRequest) -> Result<(), Status> {
+        match self {
+            LndRpc::Grpc(client) => {
+                let mut wallet = client.lock().await.wallet().clone();
+                wallet.label_transaction(request).await.map(|_| ())
+            }
+            LndRpc::Rest(client) => client.label_transaction(request).await,
+        }
+    }
+}
+
+pub struct LndNode {
+    rpc: LndRpc,
+    pub info: NodeInfo,
+    price_converter: PriceConverter,
+}
+
+/// Amount EstimateFee is asked to price; large enough to be a standard output.
+const FEE_ESTIMATE_AMOUNT_SAT: i64 = 10_000;
+
+/// Maps an error from LND's payment tracking, which reports unknown payments as `NOT_FOUND`.
+fn track_payment_error(status: Status, hex_hash: &str) -> LightningError {
+    if status.code() == Code::NotFound {
+        LightningError::NotFound(format!("Payment {hex_hash} not found"))
+    } else {
+        LightningError::PaymentError(format!("LND track_payment_v2 error: {status}"))
+    }
+}
+
+/// Parses the node features from the format returned by LND gRPC to LDK NodeFeatures
+fn parse_node_features(features: HashSet<u32>) -> NodeFeatures {
+    let mut flags = vec![0; 256];
+
+    for f in features.into_iter() {
+        let byte_offset = (f / 8) as usize;
+        let mask = 1 << (f % 8);
+        if flags.len() <= byte_offset {
+            flags.resize(byte_offset + 1, 0u8);
+        }
+
+        flags[byte_offset] |= mask
+    }
+
+    NodeFeatures::from_le_bytes(flags)
+}
+
+/// Parses the network of a chain LND reports, which calls mainnet `mainnet`.
+fn parse_chain_network(network: &str) -> Result<Network, LightningError> {
+    Network::from_str(match network {
+        "mainnet" => "bitcoin",
+        x => x,
+    })
+    .map_err(|err| LightningError::ValidationError(err.to_string()))
+}
+
+/// Converts an LND routing policy into the implementation-agnostic `NodePolicy`.
+fn lnd_node_policy(pubkey: PublicKey, routing_policy: &RoutingPolicy) -> NodePolicy {
+    NodePolicy {
+        pubkey,
+        fee_base_msat: routing_policy.fee_base_msat as u64,
+        fee_rate_milli_msat: routing_policy.fee_rate_milli_msat as u64,
+        min_htlc_msat: routing_policy.min_htlc as u64,
+        max_htlc_msat: if routing_policy.max_htlc_msat > 0 {
+            Some(routing_policy.max_htlc_msat)
+        } else {
+            None
+        },
+        time_lock_delta: routing_policy.time_lock_delta as u16,
+        disabled: routing_policy.disabled,
+        last_update: Some(routing_policy.last_update as u64),
+    }
+}
+
+/// Fees of one side of an LND channel edge, `None` while that side is disabled.
+fn lnd_edge_fee(policy: &RoutingPolicy) -> Option<EdgeFee> {
+    (!policy.disabled).then(|| EdgeFee {
+        base_fee_msat: policy.fee_base_msat.max(0) as u64,
+        fee_rate_ppm: policy.fee_rate_milli_msat.max(0) as u64,
+    })
+}
+
+/// Converts a node of LND's graph; its channel totals are left to the caller.
+fn lnd_graph_node(node: LightningNode) -> Option<GraphNode> {
+    Some(GraphNode {
+        pubkey: PublicKey::from_str(&node.pub_key).ok()?,
+        alias: node.alias,
+        color: Some(node.color).filter(|color| !color.is_empty()),
+        last_update: Some(u64::from(node.last_update)).filter(|&timestamp| timestamp > 0),
+        addresses: node
+            .addresses
+            .into_iter()
+            .map(|address| address.addr)
+            .collect(),
+        num_channels: None,
+        total_capacity_sat: None,
+    })
+}
+
+/// Splits one of LND's graph topology updates into its changes.
+fn lnd_graph_updates(update: GraphTopologyUpdate) -> Vec<GraphUpdate> {
+    // Node updates carry no announcement time, so the time received stands in
+    let received_at = chrono::Utc::now().timestamp() as u64;
+    let nodes = update.node_updates.into_iter().filter_map(|node| {
+        Some(GraphUpdate::Node(GraphNode {
+            pubkey: PublicKey::from_str(&node.identity_key).ok()?,
+            alias: node.alias,
+            color: Some(node.color).filter(|color| !color.is_empty()),
+            last_update: Some(received_at),
+            addresses: node
+                .node_addresses
+                .into_iter()
+                .map(|address| address.addr)
+                .collect(),
+            num_channels: None,
+            total_capacity_sat: None,
+        }))
+    });
+    let channels = update.channel_updates.into_iter().filter_map(|channel| {
+        let policy = channel.routing_policy?;
+        Some(GraphUpdate::Channel(GraphDirection {
+            channel_id: ShortChannelID(channel.chan_id),
+            source: PublicKey::from_str(&channel.advertising_node).ok()?,
+            destination: PublicKey::from_str(&channel.connecting_node).ok()?,
+            capacity_sat: channel.capacity.max(0) as u64,
+            fee: lnd_edge_fee(&policy),
+            last_update: u64::from(policy.last_update),
+        }))
+    });
+    let closed = update
+        .closed_chans
+        .into_iter()
+        .map(|channel| GraphUpdate::Closed(ShortChannelID(channel.chan_id)));
+
+    nodes.chain(channels).chain(closed).collect()
+}
+
+/// Reads an LND invoice state.
+fn lnd_invoice_state(state: i32) -> ReportedInvoiceState {
+    match InvoiceState::try_from(state).unwrap_or(InvoiceState::Open) {
+        InvoiceState::Open => ReportedInvoiceState::Open,
+        InvoiceState::Accepted => ReportedInvoiceState::Accepted,
+        InvoiceState::Settled => ReportedInvoiceState::Settled,
+        InvoiceState::Canceled => ReportedInvoiceState::Canceled,
+    }
+}
+
+/// Unix time an LND invoice expires at, from its creation date and expiry.
+fn lnd_expires_at(creation_date: i64, expiry: i64) -> Option<u64> {
+    if creation_date <= 0 {
+        return None;
+    }
+    u64::try_from(creation_date.saturating_add(expiry)).ok()
+}
+
+impl LndNode {
+    pub async fn new(connection: LndConnection) -> Result<Self, LightningError> {
+        let rpc = match connection.transport {
+            LndTransport::Grpc => {
+                let address =
+                    socks_proxy::proxied_address(connection.address, connection.proxy.as_deref())
+                        .await?;
+                let client = tonic_lnd::connect(address, connection.cert, connection.macaroon)
+                    .await
+                    .map_err(|err| LightningError::ConnectionError(err.to_string()))?;
+                LndRpc::Grpc(Box::new(Mutex::new(client)))
+            }
+            LndTransport::Rest => LndRpc::Rest(
+                LndRestClient::new(
+                    connection.address,
+                    &connection.cert,
+                    &connection.macaroon,
+                    connection.proxy.as_deref(),
+                )
+                .await?,
+            ),
+        };
+
+        let info = rpc
+            .get_info()
+            .await
+            .map_err(|err| LightningError::GetInfoError(err.to_string()))?;
+
+        let mut alias = info.alias;
+        let pubkey = PublicKey::from_str(&info.identity_pubkey)
+            .map_err(|err| LightningError::GetInfoError(err.to_string()))?;
+        connection.id.validate(&pubkey, &mut alias)?;
+
+        Ok(Self {
+            rpc,
+            info: NodeInfo {
+                pubkey,
+                features: parse_node_features(info.features.keys().cloned().collect()),
+                alias,
+                version: Some(info.version).filter(|version| !version.is_empty()),
+                network: match info.chains.as_slice() {
+                    [chain] => parse_chain_network(&chain.network)
+                        .ok()
+                        .map(|network| network.to_string()),
+                    _ => None,
+                },
+            },
+            price_converter: PriceConverter::new(),
+        })
+    }
+
+    async fn stream_channel_events(&self) -> Result<Streaming<ChannelEventUpdate>, LightningError> {
+        println!("Attempting to subscribe to LND channel events...");
+        let channel_event_stream: Streaming<ChannelEventUpdate> = match self
+            .grpc_client()?
+            .lock()
+            .await
+            .lightning()
+            .subscribe_channel_events(ChannelEventSubscription {})
+            .await
+        {
+            Ok(response) => {
+                println!("LND channel events subscription successful: {response:?}");
+                response.into_inner()
+            }
+            Err(e) => {
+                eprintln!("Error subscribing to LND channel events: {e:?}");
+                return Err(LightningError::StreamingError(format!("{e}")));
+            }
+        };
+        println!("Finished channel events subscription block.");
+        Ok(channel_event_stream)
+    }
+
+    async fn stream_invoice_events(&self) -> Result<Streaming<Invoice>, LightningError> {
+        println!("Attempting to subscribe to LND invoice events...");
+        let invoice_event_stream = match self
+            .grpc_client()?
+            .lock()
+            .await
+            .lightning()
+            .subscribe_invoices(InvoiceSubscription {
+                add_index: 0,
+                settle_index: 0,
+            })
+            .await
+        {
+            Ok(response) => response.into_inner(),
+            Err(e) => {
+                eprintln!("Error subscribing to LND invoice events: {e:?}");
+                return Err(LightningError::StreamingError(format!("{e}")));
+            }
+        };
+        println!("Finished invoice events subscription block.");
+        Ok(invoice_event_stream)
+    }
+
+    /// Returns the gRPC connection, which subscriptions need as the REST proxy
+    /// can't stream.
+    fn grpc_client(&self) -> Result<&Mutex<Client>, LightningError> {
+        match &self.rpc {
+            LndRpc::Grpc(client) => Ok(client.as_ref()),
+            LndRpc::Rest(_) => Err(LightningError::StreamingError(
+                "LND's REST proxy has no subscriptions, connect over gRPC for live events"
+                    .to_string(),
+            )),
+        }
+    }
+
+    /// Returns a handle to the node's Lightning service sharing the connection,
+    /// absent over the REST proxy.
+    pub async fn get_lightning_stub(&self) -> Option<tonic_lnd::LightningClient> {
+        match &self.rpc {
+            LndRpc::Grpc(client) => Some(LndRpc::lightning(client).await),
+            LndRpc::Rest(_) => None,
+        }
+    }
+
+    /// Returns the latest policy update per graph edge, keyed by channel id.
+    async fn graph_edge_updates(&self) -> Result<HashMap<u64, u64>, LightningError> {
+        let graph_response = self
+            .rpc
+            .describe_graph(ChannelGraphRequest {
+                include_unannounced: false,
+            })
+            .await
+            .map_err(|err| LightningError::GetGraphError(err.to_string()))?;
+
+        let mut last_updates: HashMap<u64, u64> = HashMap::new();
+
+        for edge in graph_response.edges.into_iter() {
+            let max_last_update = [&edge.node1_policy, &edge.node2_policy]
+                .into_iter()
+                .flatten()
+                .map(|policy| policy.last_update as u64)
+                .max()
+                .unwrap_or(0);
+
+            if max_last_update > 0 {
+                let entry = last_updates.entry(edge.channel_id).or_insert(0);
+                *entry = (*entry).max(max_last_update);
+            }
+        }
+
+        Ok(last_updates)
+    }
+
+    /// Builds a channel's details from its `ListChannels` entry, its graph edge and
+    /// the chain tip. The opening cost is left for the caller, which knows how the
+    /// wallet's transactions were fetched.
+    fn channel_details(
+        &self,
+        channel: tonic_lnd::lnrpc::Channel,
+        edge: Option<&ChannelEdge>,
+        block_height: Option<u32>,
+    ) -> Result<ChannelDetails, LightningError> {
+        let channel_point = parse_channel_point(&channel.channel_point)?;
+        let remote_pubkey = PublicKey::from_str(&channel.remote_pubkey)
+            .map_err(|err| LightningError::ChannelError(format!("Invalid remote pubkey: {err}")))?;
+
+        let (node1_policy, node2_policy) = match edge {
+            Some(channel_edge) => {
+                let node1_pubkey =
+                    PublicKey::from_str(&channel_edge.node1_pub).unwrap_or(remote_pubkey);
+                let node2_pubkey =
+                    PublicKey::from_str(&channel_edge.node2_pub).unwrap_or(self.info.pubkey);
+
+                (
+                    channel_edge
+                        .node1_policy
+                        .as_ref()
+                        .map(|policy| lnd_node_policy(node1_pubkey, policy)),
+                    channel_edge
+                        .node2_policy
+                        .as_ref()
+                        .map(|policy| lnd_node_policy(node2_pubkey, policy)),
+                )
+            }
+            None => (None, None),
+        };
+
+        let channel_age_blocks =
+            block_height.and_then(|height| ShortChannelID(channel.chan_id).age_blocks(height));
+
+        Ok(ChannelDetails {
+            channel_id: ShortChannelID(channel.chan_id),
+            local_balance: Amount::from_sat_i64(channel.local_balance),
+            remote_balance: Amount::from_sat_i64(channel.remote_balance),
+            capacity: Amount::from_sat_i64(channel.capacity),
+            active: Some(channel.active),
+            private: channel.private,
+            remote_pubkey,
+            commit_fee: Some(Amount::from_sat_i64(channel.commit_fee)),
+            local_chan_reserve: Some(Amount::from_sat(
+                channel
+                    .local_constraints
+                    .as_ref()
+                    .map(|local_constraints| local_constraints.chan_reserve_sat)
+                    .unwrap_or(0),
+            )),
+            remote_chan_reserve: Some(Amount::from_sat(
+                channel
+                    .remote_constraints
+                    .as_ref()
+                    .map(|remote_constraints| remote_constraints.chan_reserve_sat)
+                    .unwrap_or(0),
+            )),
+            num_updates: Some(channel.num_updates),
+            total_sent: Some(Amount::from_sat_i64(channel.total_satoshis_sent)),
+            total_received: Some(Amount::from_sat_i64(channel.total_satoshis_received)),
+            channel_age_blocks,
+            opening_cost: None,
+            initiator: Some(channel.initiator),
+            txid: Some(channel_point.txid),
+            vout: Some(channel_point.vout),
+            node1_policy,
+            node2_policy,
+            funding_tx: None,
+        })
+    }
+
+    /// Looks up the on-chain fee the wallet paid for a funding transaction, searching
+    /// only the block it confirmed in.
+    async fn wallet_funding_fee(&self, txid: &Txid, confirmation_height: u32) -> Option<u64> {
+        let transactions = self
+            .rpc
+            .get_transactions(GetTransactionsRequest {
+                start_height: confirmation_height as i32,
+                end_height: confirmation_height as i32,
+                ..Default::default()
+            })
+            .await
+            .ok()?
+            .transactions;
+
+        let txid = txid.to_string();
+        transactions
+            .into_iter()
+            .find(|transaction| transaction.tx_hash == txid)
+            .map(|transaction| transaction.total_fees.max(0) as u64)
+    }
+
+    async fn process_outgoing_payment(
+        &self,
+        payment: tonic_lnd::lnrpc::Payment,
+    ) -> Result<PaymentDetails, LightningError> {
+        let state = match PaymentStatus::try_from(payment.status).unwrap_or(PaymentStatus::Unknown)
+        {
+            PaymentStatus::Unknown | PaymentStatus::InFlight => PaymentState::Inflight,
+            PaymentStatus::Succeeded => PaymentState::Settled,
+            PaymentStatus::Failed => PaymentState::Failed,
+        };
+
+        let creation_time = payment
+            .creation_time_ns
+            .try_into()
+            .ok()
+            .map(|timestamp_ns: u64| timestamp_ns / 1_000_000_000);
+
+        let completed_at = match state {
+            PaymentState::Settled => payment.htlcs.last().and_then(|htlc| {
+                let resolve_time = htlc.resolve_time_ns as u64;
+                if resolve_time > 0 {
+                    Some(resolve_time / 1_000_000_000)
+                } else {
+                    None
+                }
+            }),
+            _ => None,
+        };
+
+        // Process HTLCs and extract destination pubkey from the last hop
+        let (htlcs, destination_pubkey) = {
+            let mut destination_pubkey = None;
+            let htlcs = payment
+                .htlcs
+                .into_iter()
+                .map(|htlc| {
+                    let route = htlc.route.map(|raw_route| {
+                        // Get destination pubkey from last hop if available
+                        if let Some(last_hop) = raw_route.hops.last()
+                            && let Ok(pubkey) = PublicKey::from_str(&last_hop.pub_key)
+                        {
+                            destination_pubkey = Some(pubkey);
+                        }
+
+                        Route {
+                            total_time_lock: raw_route.total_time_lock,
+                            total_fees: Amount::from_msat_i64(raw_route.total_fees_msat),
+                            total_amt: Amount::from_msat_i64(raw_route.total_amt_msat),
+                            hops: raw_route
+                                .hops
+                                .into_iter()
+                                .map(|hop| Hop {
+                                    pubkey: PublicKey::from_str(&hop.pub_key)
+                                        .unwrap_or(self.info.pubkey),
+                                    chan_id: ShortChannelID(hop.chan_id),
+                                    amount_to_forward: Amount::from_msat_i64(
+                                        hop.amt_to_forward_msat,
+                                    ),
+                                    fee: Some(Amount::from_msat_i64(hop.fee_msat)),
+                                    expiry: Some(hop.expiry.into()),
+                                })
+                                .collect(),
+                        }
+                    });
+
+                    PaymentHtlc {
+                        routes: route.map_or_else(Vec::new, |route| vec![route]),
+                        attempt_id: htlc.attempt_id,
+                        attempt_time: {
+                            let attempt_ns = htlc.attempt_time_ns as u64;
+                            (attempt_ns > 0).then_some(attempt_ns / 1_000_000_000)
+                        },
+                        resolve_time: {
+                            let resolve_ns = htlc.resolve_time_ns as u64;
+                            (resolve_ns > 0).then_some(resolve_ns / 1_000_000_000)
+                        },
+                        failure_reason: htlc
+                            .failure
+                            .as_ref()
+                            .map(|failure| format!("{:?}", failure.code())),
+                        failure_code: htlc.failure.as_ref().map(|failure| failure.code() as u16),
+                    }
+                })
+                .collect();
+
+            (htlcs, destination_pubkey)
+        };
+
+        // Parse invoice for description
+        let description = Bolt11Invoice::from_str(&payment.payment_request)
+            .ok()
+            .and_then(|invoice| {
+                if let Bolt11InvoiceDescription::Direct(desc) = invoice.description() {
+                    Some(desc.to_string())
+                } else {
+                    None
+                }
+            });
+
+        let network = self
+            .get_network()
+            .await
+            .map(|network| Some(network.to_string()))
+            .unwrap_or(None);
+
+        let amount = Amount::from_msat_i64(payment.value_msat);
+        let amount_usd = self
+            .price_converter
+            .node_sats_to_usd(&self.info, amount.sat())
+            .await?;
+
+        Ok(PaymentDetails {
+            state,
+            payment_type: PaymentType::Outgoing,
+            amount,
+            amount_usd,
+            routing_fee: Some(Amount::from_msat_i64(payment.fee_msat)),
+            network,
+            description,
+            creation_time,
+            invoice: payment.payment_request.into(),
+            payment_hash: payment.payment_hash,
+            destination_pubkey,
+            completed_at,
+            htlcs,
+        })
+    }
+
+    async fn process_incoming_payment(
+        &self,
+        invoice: tonic_lnd::lnrpc::Invoice,
+    ) -> Result<PaymentDetails, LightningError> {
+        let state = match invoice.state {
+            0 => {
+                // OPEN - check if payment is in progress
+                PaymentState::Inflight
+            }
+            1 => PaymentState::Settled,  // SETTLED
+            2 => PaymentState::Failed,   // CANCELED
+            3 => PaymentState::Inflight, // ACCEPTED (payment in progress)
+            _ => PaymentState::Inflight, // Default to inflight for unknown states
+        };
+
+        let creation_time = Some(invoice.creation_date as u64);
+
+        let completed_at = match state {
+            PaymentState::Settled | PaymentState::Failed => {
+                if invoice.settle_date > 0 {
+                    Some(invoice.settle_date as u64)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+
+        // Process HTLCs for incoming payments
+        let htlcs: Vec<PaymentHtlc> = invoice
+            .htlcs
+            .into_iter()
+            .map(|htlc| PaymentHtlc {
+                routes: Vec::new(),
+                attempt_id: htlc.htlc_index,
+                attempt_time: {
+                    let accept_ns = htlc.accept_time as u64;
+                    (accept_ns > 0).then_some(accept_ns / 1_000_000_000)
+                },
+                resolve_time: {
+                    let resolve_ns = htlc.resolve_time as u64;
+                    (resolve_ns > 0).then_some(resolve_ns / 1_000_000_000)
+                },
+                failure_reason: None,
+                failure_code: None,
+            })
+            .collect();
+
+        // Parse invoice for description
+        let description = if !invoice.memo.is_empty() {
+            Some(invoice.memo.clone())
+        } else {
+            Bolt11Invoice::from_str(&invoice.payment_request)
+                .ok()
+                .and_then(|parsed_invoice| {
+                    if let Bolt11InvoiceDescription::Direct(desc) = parsed_invoice.description() {
+                        Some(desc.to_string())
+                    } else {
+                        None
+                    }
+                })
+        };
+
+        let network = self
+            .get_network()
+            .await
+            .map(|network| Some(network.to_string()))
+            .unwrap_or(None);
+
+        let amount = if invoice.amt_paid_msat > 0 {
+            Amount::from_msat_i64(invoice.amt_paid_msat)
+        } else {
+            Amount::from_msat_i64(invoice.value_msat)
+        };
+
+        let amount_usd = self
+            .price_converter
+            .node_sats_to_usd(&self.info, amount.sat())
+            .await?;
+
+        let destination_pubkey = Some(self.info.pubkey);
+
+        Ok(PaymentDetails {
+            state,
+            payment_type: PaymentType::Incoming,
+            amount,
+            amount_usd,
+            routing_fee: None,
+            network,
+            description,
+            creation_time,
+            invoice: Some(invoice.payment_request),
+            payment_hash: hex::encode(&invoice.r_hash),
+            destination_pubkey,
+            completed_at,
+            htlcs,
+        })
+    }
+}
+
+#[async_trait]
+impl LightningClient for LndNode {
+    /// Returns cached node information (node_id, alias, features) that was retrieved
+    /// during node initialization. This avoids repeated RPC calls for static node data.
+    fn get_info(&self) -> &NodeInfo {
+        &self.info
+    }
+
+    async fn get_network(&self) -> Result<Network, LightningError> {
+        let info = self
+            .rpc
+            .get_info()
+            .await
+            .map_err(|err| LightningError::GetInfoError(err.to_string()))?;
+
+        if info.chains.is_empty() {
+            return Err(LightningError::ValidationError(format!(
+                "{} is not connected any chain",
+                self.get_info()
+            )));
+        } else if info.chains.len() > 1 {
+            return Err(LightningError::ValidationError(format!(
+                "{} is connected to more than one chain: {:?}",
+                self.get_info(),
+                info.chains.iter().map(|c| c.chain.to_string())
+            )));
+        }
+
+        parse_chain_network(&info.chains[0].network)
+    }
+
+    async fn list_channels(&self) -> Result<Vec<ChannelSummary>, LightningError> {
+        let (list_channels_result, last_updates) = tokio::join!(
+            self.rpc.list_channels(ListChannelsRequest::default()),
+            self.graph_edge_updates(),
+        );
+
+        let list_channels_response =
+            list_channels_result.map_err(|err| LightningError::ChannelError(err.to_string()))?;
+        let last_updates = last_updates?;
+
+        let channels: Vec<ChannelSummary> = list_channels_response
+            .channels
+            .into_iter()
+            .map(|channel| {
+                let channel_state = if channel.active {
+                    ChannelState::Active
+                } else {
+                    ChannelState::Disabled
+                };
+
+                let last_update = last_updates.get(&channel.chan_id).copied();
+
+                ChannelSummary {
+                    chan_id: ShortChannelID(channel.chan_id),
+                    alias: None,
+                    channel_state,
+                    private: channel.private,
+                    remote_balance: Amount::from_sat_i64(channel.remote_balance),
+                    local_balance: Amount::from_sat_i64(channel.local_balance),
+                    capacity: Amount::from_sat_i64(channel.capacity),
+                    last_update,
+                    uptime: Some(channel.uptime as u64),
+                    remote_pubkey: PublicKey::from_str(&channel.remote_pubkey).ok(),
+                    channel_point: parse_channel_point(&channel.channel_point).ok(),
+                }
+            })
+            .collect();
+
+        Ok(channels)
+    }
+
+    async fn get_channel_info(
+        &self,
+        channel_id: &ShortChannelID,
+    ) -> Result<ChannelDetails, LightningError> {
+        // Local channel state, the channel's graph edge and the chain tip are independent lookups
+        let (list_channels_result, chan_info_result, info_result) = tokio::join!(
+            self.rpc.list_channels(ListChannelsRequest {
+                active_only: false,
+                ..Default::default()
+            }),
+            self.rpc.get_chan_info(ChanInfoRequest {
+                chan_id: channel_id.0,
+            }),
+            self.rpc.get_info(),
+        );
+
+        let channel = list_channels_result
+            .map_err(|err| LightningError::ChannelError(format!("LND list_channels error: {err}")))?
+            .channels
+            .into_iter()
+            .find(|channel| channel.chan_id == channel_id.0)
+            .ok_or_else(|| LightningError::ChannelError("Channel not found".to_string()))?;
+
+        // Unannounced channels have no graph edge, so policies are optional
+        let edge = chan_info_result.ok();
+        let block_height = info_result.ok().map(|info| info.block_height);
+        let mut details = self.channel_details(channel, edge.as_ref(), block_height)?;
+
+        // Only the initiator pays for the funding transaction
+        details.opening_cost = match (details.initiator, details.txid) {
+            (Some(true), Some(txid)) => self
+                .wallet_funding_fee(&txid, channel_id.block_height())
+                .await
+                .map(Amount::from_sat),
+            _ => Some(Amount::ZERO),
+        };
+
+        Ok(details)
+    }
+
+    async fn get_channels_info(
+        &self,
+        channel_ids: &[ShortChannelID],
+    ) -> Result<Vec<Result<ChannelDetails, LightningError>>, LightningError> {
+        // One pass over the channels, the graph, the chain tip and the wallet serves every channel
+        let (list_channels_result, graph_result, info_result, transactions_result) = tokio::join!(
+            self.rpc.list_channels(ListChannelsRequest {
+                active_only: false,
+                ..Default::default()
+            }),
+            self.rpc.describe_graph(ChannelGraphRequest {
+                include_unannounced: true,
+            }),
+            self.rpc.get_info(),
+            self.rpc.get_transactions(GetTransactionsRequest::default()),
+        );
+
+        let channels: HashMap<u64, tonic_lnd::lnrpc::Channel> = list_channels_result
+            .map_err(|err| LightningError::ChannelError(format!("LND list_channels error: {err}")))?
+            .channels
+            .into_iter()
+            .map(|channel| (channel.chan_id, channel))
+            .collect();
+        let edges: HashMap<u64, ChannelEdge> = graph_result
+            .map(|graph| {
+                graph
+                    .edges
+                    .into_iter()
+                    .map(|edge| (edge.channel_id, edge))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let block_height = info_result.ok().map(|info| info.block_height);
+        let wallet_fees: HashMap<String, u64> = transactions_result
+            .map(|response| {
+                response
+                    .transactions
+                    .into_iter()
+                    .map(|transaction| (transaction.tx_hash, transaction.total_fees.max(0) as u64))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(channel_ids
+            .iter()
+            .map(|channel_id| {
+                let channel = channels.get(&channel_id.0).cloned().ok_or_else(|| {
+                    LightningError::NotFound(format!("Channel {channel_id} not found"))
+                })?;
+                let mut details =
+                    self.channel_details(channel, edges.get(&channel_id.0), block_height)?;
+                details.opening_cost = match (details.initiator, details.txid) {
+                    (Some(true), Some(txid)) => wallet_fees
+                        .get(&txid.to_string())
+                        .copied()
+                        .map(Amount::from_sat),
+                    _ => Some(Amount::ZERO),
+                };
+                Ok(details)
+            })
+            .collect())
+    }
+
+    async fn estimate_close_fee(
+        &self,
+        channel_id: &ShortChannelID,
+        target_conf: u32,
+    ) -> Result<CloseFeeEstimate, LightningError> {
+        let channel = self
+            .rpc
+            .list_channels(ListChannelsRequest::default())
+            .await
+            .map_err(|err| LightningError::ChannelError(format!("LND list_channels error: {err}")))?
+            .channels
+            .into_iter()
+            .find(|channel| channel.chan_id == channel_id.0)
+            .ok_or_else(|| LightningError::NotFound(format!("Channel {channel_id} not found")))?;
+
+        // EstimateFee prices a wallet spend, so a payment to our own key stands in for the close
+        let network = self.get_network().await?;
+        let address = Address::p2wpkh(&CompressedPublicKey(self.info.pubkey), network);
+        let estimate = self
+            .rpc
+            .estimate_fee(EstimateFeeRequest {
+                addr_to_amount: HashMap::from([(address.to_string(), FEE_ESTIMATE_AMOUNT_SAT)]),
+                target_conf: target_conf as i32,
+                spend_unconfirmed: true,
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| {
+                LightningError::ChannelError(format!("LND estimate_fee error: {err}"))
+            })?;
+
+        Ok(CloseFeeEstimate::new(
+            *channel_id,
+            target_conf,
+            estimate.sat_per_vbyte * 250,
+            CommitmentShape {
+                // ANCHORS and the commitment types after it
+                anchors: channel.commitment_type >= 3,
+                pending_htlcs: channel.pending_htlcs.len() as u32,
+                commit_weight: u64::try_from(channel.commit_weight).ok(),
+                initiator: Some(channel.initiator),
+            },
+        ))
+    }
+
+    async fn get_graph_node(&self, pubkey: &PublicKey) -> Result<GraphNode, LightningError> {
+        let node_info = self
+            .rpc
+            .get_node_info(NodeInfoRequest {
+                pub_key: pubkey.to_string(),
+                include_channels: false,
+            })
+            .await
+            .map_err(|err| match err.code() {
+                tonic_lnd::tonic::Code::NotFound => {
+                    LightningError::NotFound(format!("Node {pubkey} is not in the graph"))
+                }
+                _ => LightningError::GetGraphError(format!("LND get_node_info error: {err}")),
+            })?;
+
+        let not_found = || LightningError::NotFound(format!("Node {pubkey} is not in the graph"));
+        let node = lnd_graph_node(node_info.node.ok_or_else(not_found)?).ok_or_else(not_found)?;
+
+        Ok(GraphNode {
+            num_channels: Some(node_info.num_channels),
+            total_capacity_sat: u64::try_from(node_info.total_capacity).ok(),
+            ..node
+        })
+    }
+
+    async fn describe_graph(&self) -> Result<NetworkGraph, LightningError> {
+        let graph = self
+            .rpc
+            .describe_graph(ChannelGraphRequest {
+                include_unannounced: false,
+            })
+            .await
+            .map_err(|err| LightningError::GetGraphError(err.to_string()))?;
+
+        let fee = |policy: &Option<RoutingPolicy>| policy.as_ref().and_then(lnd_edge_fee);
+        let last_update = |policy: &Option<RoutingPolicy>| {
+            policy.as_ref().map(|policy| u64::from(policy.last_update))
+        };
+
+        let edges = graph
+            .edges
+            .into_iter()
+            .filter_map(|edge| {
+                Some(GraphEdge {
+                    channel_id: ShortChannelID(edge.channel_id),
+                    node1: PublicKey::from_str(&edge.node1_pub).ok()?,
+                    node2: PublicKey::from_str(&edge.node2_pub).ok()?,
+                    capacity_sat: edge.capacity.max(0) as u64,
+                    node1_fee: fee(&edge.node1_policy),
+                    node2_fee: fee(&edge.node2_policy),
+                    node1_last_update: last_update(&edge.node1_policy),
+                    node2_last_update: last_update(&edge.node2_policy),
+                })
+            })
+            .collect();
+
+        Ok(NetworkGraph {
+            nodes: graph.nodes.into_iter().filter_map(lnd_graph_node).collect(),
+            edges,
+        })
+    }
+
+    async fn subscribe_graph(&self) -> Result<GraphUpdates, LightningError> {
+        let mut updates = self
+            .grpc_client()?
+            .lock()
+            .await
+            .lightning()
+            .subscribe_channel_graph(GraphTopologySubscription {})
+            .await
+            .map_err(|err| {
+                LightningError::StreamingError(format!("LND subscribe_channel_graph error: {err}"))
+            })?
+            .into_inner();
+
+        Ok(Box::pin(stream! {
+            while let Some(update) = updates.next().await {
+                match update {
+                    Ok(update) => yield Ok(lnd_graph_updates(update)),
+                    Err(status) => {
+                        yield Err(LightningError::StreamingError(format!(
+                            "LND graph subscription error: {status}"
+                        )));
+                        break;
+                    }
+                }
+            }
+        }))
+    }
+
+    async fn get_payment_details(
+        &self,
+        payment_hash: &PaymentHash,
+    ) -> Result<PaymentDetails, LightningError> {
+        let hex_hash = hex::encode(payment_hash.0);
+
+        // Check if it's an outgoing payment
+        let payments_response = self
+            .rpc
+            .list_payments(ListPaymentsRequest {
+                include_incomplete: true,
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| {
+                tracing::error!("list_payments RPC failed: {}", err);
+                LightningError::PaymentError(format!("LND list_payments error: {err}"))
+            })?;
+
+        if let Some(payment) = payments_response
+            .payments
+            .into_iter()
+            .find(|payment| payment.payment_hash == hex_hash)
+        {
+            return self.process_outgoing_payment(payment).await;
+        }
+
+        // If it's not an outgoing payment, check if it's an incoming payment (invoice)
+        let invoices_response = self
+            .rpc
+            .list_invoices(ListInvoiceRequest::default())
+            .await
+            .map_err(|err| {
+                tracing::error!("list_invoices RPC failed: {}", err);
+                LightningError::InvoiceError(format!("LND list_invoices error: {err}"))
+            })?;
+
+        if let Some(invoice) = invoices_response
+            .invoices
+            .into_iter()
+            .find(|invoice| hex::encode(&invoice.r_hash) == hex_hash)
+        {
+            return self.process_incoming_payment(invoice).await;
+        }
+
+        Err(LightningError::NotFound(format!(
+            "Payment {hex_hash} not found"
+        )))
+    }
+
+    async fn list_payments(&self) -> Result<Vec<PaymentSummary>, LightningError> {
+        let btc_price = self.price_converter.fetch_node_price(&self.info).await?;
+
+        // Fetch outgoing payments
+        let payments_response = self
+            .rpc
+            .list_payments(ListPaymentsRequest::default())
+            .await
+            .map_err(|err| LightningError::PaymentError(err.to_string()))?;
+
+        // Fetch incoming invoices
+        let invoices_response = self
+            .rpc
+            .list_invoices(ListInvoiceRequest::default())
+            .await
+            .map_err(|err| LightningError::InvoiceError(err.to_string()))?;
+
+        // Process outgoing payments
+        let outgoing_payments: Vec<PaymentSummary> = payments_response
+            .payments
+            .into_iter()
+            .map(|payment| {
+                let status =
+                    PaymentStatus::try_from(payment.status).unwrap_or(PaymentStatus::Unknown);
+                let state = match status {
+                    PaymentStatus::Unknown | PaymentStatus::InFlight => PaymentState::Inflight,
+                    PaymentStatus::Succeeded => PaymentState::Settled,
+                    PaymentStatus::Failed => PaymentState::Failed,
+                };
+
+                let amount = Amount::from_msat_i64(payment.value_msat);
+                let amount_usd = btc_price
+                    .map(|price| PriceConverter::sats_to_usd_with_price(amount.sat(), price));
+
+                // Only set completed_at if payment succeeded
+                let completed_at = match state {
+                    PaymentState::Settled => payment
+                        .htlcs
+                        .last()
+                        .map(|htlc| (htlc.resolve_time_ns / 1_000_000_000) as u64),
+                    _ => None,
+                };
+
+                // Only set creation_time if timestamp is valid
+                let creation_time = (payment.creation_time_ns > 0).then_some({
+                    let creation_time_ns = payment.creation_time_ns as u64;
+                    creation_time_ns / 1_000_000_000
+                });
+
+                PaymentSummary {
+                    state,
+                    payment_type: PaymentType::Outgoing,
+                    amount,
+                    amount_usd,
+                    routing_fee: if payment.fee_msat > 0 {
+                        Some(Amount::from_msat_i64(payment.fee_msat))
+                    } else {
+                        None
+                    },
+                    creation_time,
+                    invoice: Some(payment.payment_request),
+                    payment_hash: payment.payment_hash,
+                    completed_at,
+                }
+            })
+            .collect();
+
+        // Process incoming payments (from invoices)
+        let incoming_payments: Vec<PaymentSummary> = invoices_response
+            .invoices
+            .into_iter()
+            .filter(|invoice| {
+                // Exclude invoices without payment attempts (HTLCs)
+                !invoice.htlcs.is_empty()
+            })
+            .filter_map(|invoice| {
+                let state = match invoice.state {
+                    0 => PaymentState::Inflight,
+                    1 => PaymentState::Settled,
+                    2 => PaymentState::Failed,
+                    3 => PaymentState::Inflight,
+                    _ => return None,
+                };
+
+                // Use amt_paid_msat if available, fallback to invoice.value_msat for failed attempts
+                let amount = if invoice.amt_paid_msat > 0 {
+                    Amount::from_msat_i64(invoice.amt_paid_msat)
+                } else {
+                    Amount::from_msat_i64(invoice.value_msat)
+                };
+
+                let amount_usd = btc_price
+                    .map(|price| PriceConverter::sats_to_usd_with_price(amount.sat(), price));
+
+                let creation_time =
+                    (invoice.creation_date > 0).then_some(invoice.creation_date as u64);
+
+                let completed_at = match state {
+                    PaymentState::Settled | PaymentState::Failed => {
+                        (invoice.settle_date > 0).then_some(invoice.settle_date as u64)
+                    }
+                    _ => None,
+                };
+
+                Some(PaymentSummary {
+                    state,
+                    payment_type: PaymentType::Incoming,
+                    amount,
+                    amount_usd,
+                    routing_fee: None,
+                    creation_time,
+                    invoice: Some(invoice.payment_request),
+                    payment_hash: hex::encode(invoice.r_hash),
+                    completed_at,
+                })
+            })
+            .collect();
+
+        // Combine all with deduplication
+        let mut seen_hashes = HashSet::new();
+        let mut all_payments = Vec::new();
+
+        let mut push_unique = |payment: PaymentSummary| {
+            if seen_hashes.insert(payment.payment_hash.clone()) {
+                all_payments.push(payment);
+            }
+        };
+
+        outgoing_payments.into_iter().for_each(&mut push_unique);
+        incoming_payments.into_iter().for_each(&mut push_unique);
+
+        types::sort_payments(&mut all_payments);
+
+        Ok(all_payments)
+    }
+
+    async fn list_inflight_payments(&self) -> Result<Vec<PaymentSummary>, LightningError> {
+        let btc_price = self.price_converter.fetch_node_price(&self.info).await?;
+
+        // Payments in flight are only listed along with incomplete ones
+        let payments_response = self
+            .rpc
+            .list_payments(ListPaymentsRequest {
+                include_incomplete: true,
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| LightningError::PaymentError(err.to_string()))?;
+
+        Ok(payments_response
+            .payments
+            .into_iter()
+            .filter(|payment| payment.status == PaymentStatus::InFlight as i32)
+            .map(|payment| {
+                let amount = Amount::from_msat_i64(payment.value_msat);
+                PaymentSummary {
+                    state: PaymentState::Inflight,
+                    payment_type: PaymentType::Outgoing,
+                    amount,
+                    amount_usd: btc_price
+                        .map(|price| PriceConverter::sats_to_usd_with_price(amount.sat(), price)),
+                    routing_fee: (payment.fee_msat > 0)
+                        .then(|| Amount::from_msat_i64(payment.fee_msat)),
+                    creation_time: (payment.creation_time_ns > 0)
+                        .then_some(payment.creation_time_ns as u64 / 1_000_000_000),
+                    invoice: Some(payment.payment_request),
+                    payment_hash: payment.payment_hash,
+                    completed_at: None,
+                }
+            })
+            .collect())
+    }
+
+    async fn track_payment<'a>(
+        &'a self,
+        payment_hash: &PaymentHash,
+    ) -> Result<PaymentUpdates<'a>, LightningError> {
+        let client = match &self.rpc {
+            LndRpc::Grpc(client) => client,
+            // The REST proxy can't stream, so the payment is read again each second
+            LndRpc::Rest(_) => {
+                return Ok(poll_payment_updates(self, *payment_hash, || {
+                    std::future::ready(())
+                }));
+            }
+        };
+        let hex_hash = hex::encode(payment_hash.0);
+        let mut router = client.lock().await.router().clone();
+        let mut updates = router
+            .track_payment_v2(TrackPaymentRequest {
+                payment_hash: payment_hash.0.to_vec(),
+                no_inflight_updates: false,
+            })
+            .await
+            .map_err(|status| track_payment_error(status, &hex_hash))?
+            .into_inner();
+
+        // LND closes the stream once the payment settles or fails
+        Ok(Box::pin(stream! {
+            while let Some(update) = updates.next().await {
+                match update {
+                    Ok(payment) => yield self.process_outgoing_payment(payment).await,
+                    Err(status) => {
+                        yield Err(track_payment_error(status, &hex_hash));
+                        break;
+                    }
+                }
+            }
+        }))
+    }
+
+    async fn stream_events(
+        &mut self,
+    ) -> Result<Pin<Box<dyn Stream<Item = CapturedEvent> + Send>>, LightningError> {
+        let channel_events_stream = self.stream_channel_events().await?;
+        let invoice_events_stream = self.stream_invoice_events().await?;
+
+        let event_stream = stream! {
+            let channel_events_filtered = channel_events_stream.filter_map(|result| {
+                let event_opt = match result {
+                    Ok(update) => {
+                        let raw = raw_events::lnd_channel_event(&update);
+                        let event = match update.r#type() {
+                            LndChannelUpdateType::OpenChannel => {
+                                if let Some(event_channel) = update.channel {
+                                    match event_channel {
+                                        EventChannel::OpenChannel(chan) => {
+                                            Some(NodeSpecificEvent::LND(LNDEvent::ChannelOpened {
+                                                active: chan.active,
+                                                remote_pubkey: chan.remote_pubkey,
+                                                channel_point: chan.channel_point,
+                                                chan_id: chan.chan_id,
+                                                capacity: chan.capacity,
+                                                local_balance: chan.local_balance,
+                                                remote_balance: chan.remote_balance,
+                                                total_satoshis_sent: chan.total_satoshis_sent,
+                                                total_satoshis_received: chan.total_satoshis_received,
+                                            }))
+                                        }
+                                        _ => {
+                                            eprintln!("Unexpected channel variant for OpenChannel event");
+                                            None
+                                        }
+                                    }
+                                } else {
+                                    None
+                                }
+                            },
+                            LndChannelUpdateType::ClosedChannel => {
+                                if let Some(event_channel) = update.channel {
+                                    match event_channel {
+                                        EventChannel::ClosedChannel(chan_close_sum) => {
+                                            Some(NodeSpecificEvent::LND(LNDEvent::ChannelClosed {
+                                                channel_point: chan_close_sum.channel_point,
+                                                chan_id:  chan_close_sum.chan_id,
+                                                chain_hash:  chan_close_sum.chain_hash,
+                                                closing_tx_hash:  chan_close_sum.closing_tx_hash,
+                                                remote_pubkey:  chan_close_sum.remote_pubkey,
+                                                capacity:  chan_close_sum.capacity,
+                                                close_height:  chan_close_sum.close_height,
+                                                settled_balance:  chan_close_sum.settled_balance,
+                                                time_locked_balance:  chan_close_sum.time_locked_balance,
+                                                close_type:  chan_close_sum.close_type,
+                                                open_initiator:  chan_close_sum.open_initiator,
+                                                close_initiator:  chan_close_sum.close_initiator,
+                                            }))
+                                        }
+                                        _ => {
+                                            eprintln!("Unexpected channel variant for ClosedChannel event");
+                                            None
+                                        }
+                                    }
+                                } else {
+                                    None
+                                }
+                            },
+                            _ => None,
+                        };
+                        event.map(|event| CapturedEvent { event, raw: Some(raw) })
+                    }
+                    Err(e) => {
+                        eprintln!("Error receiving LND channel event: {e:?}");
+                        None
+                    }
+                };
+                futures::future::ready(event_opt)
+            });
+
+            let invoice_events_filtered = invoice_events_stream.filter_map(|result| {
+                let event_opt = match result {
+                    Ok(invoice) => {
+                        let raw = raw_events::lnd_invoice(&invoice);
+                        let event = match invoice.state() {
+                            InvoiceState::Open => {
+                                Some(NodeSpecificEvent::LND(LNDEvent::InvoiceCreated {
+                                        preimage: invoice.r_preimage,
+                                        hash: invoice.r_hash,
+                                        value: Amount::from_msat_i64(invoice.value_msat),
+                                        state: invoice.state,
+                                        memo: invoice.memo,
+                                        creation_date: invoice.creation_date,
+                                        expiry: invoice.expiry,
+                                        payment_request: invoice.payment_request,
+                                }))
+                            },
+                            InvoiceState::Settled => {
+                                  Some(NodeSpecificEvent::LND(LNDEvent::InvoiceSettled {
+                                        preimage: invoice.r_preimage,
+                                        hash: invoice.r_hash,
+                                        value: Amount::from_msat_i64(invoice.value_msat),
+                                        state: invoice.state,
+                                        memo: invoice.memo,
+                                        creation_date: invoice.creation_date,
+                                        payment_request: invoice.payment_request,
+                                }))
+                            },
+                            InvoiceState::Canceled => {
+                                  Some(NodeSpecificEvent::LND(LNDEvent::InvoiceCancelled {
+                                        preimage: invoice.r_preimage,
+                                        hash: invoice.r_hash,
+                                        value: Amount::from_msat_i64(invoice.value_msat),
+                                        state: invoice.state,
+                                        memo: invoice.memo,
+                                        creation_date: invoice.creation_date,
+                                        payment_request: invoice.payment_request,
+                                }))
+                            },
+                            InvoiceState::Accepted => {
+                                  Some(NodeSpecificEvent::LND(LNDEvent::InvoiceAccepted {
+                                        preimage: invoice.r_preimage,
+                                        hash: invoice.r_hash,
+                                        value: Amount::from_msat_i64(invoice.value_msat),
+                                        state: invoice.state,
+                                        memo: invoice.memo,
+                                        creation_date: invoice.creation_date,
+                                        payment_request: invoice.payment_request,
+                                }))
+                            }
+                        };
+                        event.map(|event| CapturedEvent { event, raw: Some(raw) })
+                    },
+                    Err(e) => {
+                        eprintln!("Error subscribing to LND channel events: {e:?}");
+                        None
+                    }
+                };
+                futures::future::ready(event_opt)
+            });
+
+            let mut merged_stream = SelectAll::new();
+            merged_stream.push(channel_events_filtered.boxed());
+            merged_stream.push(invoice_events_filtered.boxed());
+
+            while let Some(event) = merged_stream.next().await {
+                yield event;
+            }
+        };
+
+        Ok(Box::pin(event_stream))
+    }
+
+    async fn list_invoices(&self) -> Result<Vec<CustomInvoice>, LightningError> {
+        let request = tonic_lnd::lnrpc::ListInvoiceRequest {
+            pending_only: false,
+            ..Default::default()
+        };
+
+        let response = self
+            .rpc
+            .list_invoices(request)
+            .await
+            .map_err(|err| LightningError::InvoiceError(err.to_string()))?;
+
+        let now = chrono::Utc::now().timestamp() as u64;
+
+        let invoices = response
+            .invoices
+            .into_iter()
+            .map(|invoice| {
+                let expires_at = lnd_expires_at(invoice.creation_date, invoice.expiry);
+                let state =
+                    InvoiceStatus::resolve(lnd_invoice_state(invoice.state), expires_at, now);
+                let htlcs = Some(
+                    invoice
+                        .htlcs
+                        .into_iter()
+                        .map(|htlc| InvoiceHtlc {
+                            chan_id: Some(htlc.chan_id),
+                            htlc_index: Some(htlc.htlc_index),
+                            amt_msat: Some(htlc.amt_msat),
+                            accept_time: Some(htlc.accept_time),
+                            resolve_time: Some(htlc.resolve_time),
+                            expiry_height: htlc.expiry_height.try_into().ok(),
+                            mpp_total_amt_msat: Some(htlc.mpp_total_amt_msat),
+                        })
+                        .collect(),
+                );
+
+                let features = Some(
+                    invoice
+                        .features
+                        .into_iter()
+                        .map(|(feature_bit, feature_entry)| {
+                            (
+                                feature_bit,
+                                Feature {
+                                    name: Some(feature_entry.name),
+                                    is_known: Some(feature_entry.is_known),
+                                    is_required: Some(feature_entry.is_required),
+                                },
+                            )
+                        })
+                        .collect(),
+                );
+
+                CustomInvoice {
+                    memo: invoice.memo,
+                    payment_hash: hex::encode(invoice.r_hash),
+                    payment_preimage: Some(hex::encode(invoice.r_preimage))
+                        .filter(|preimage_hex| !preimage_hex.is_empty())
+                        .unwrap_or_default(),
+                    value: Amount::from_msat_i64(invoice.value_msat),
+                    amount_paid: (invoice.amt_paid_msat > 0)
+                        .then(|| Amount::from_msat_i64(invoice.amt_paid_msat)),
+                    creation_date: Some(invoice.creation_date),
+                    settle_date: Some(invoice.settle_date),
+                    payment_request: invoice.payment_request,
+                    expiry: Some(invoice.expiry as u64),
+                    expires_at,
+                    state,
+                    is_keysend: Some(invoice.is_keysend),
+                    is_amp: Some(invoice.is_amp),
+                    payment_addr: Some(hex::encode(invoice.payment_addr))
+                        .filter(|addr_hex| !addr_hex.is_empty()),
+                    htlcs,
+                    features,
+                }
+            })
+            .collect();
+
+        Ok(invoices)
+    }
+
+    async fn get_invoice_details(
+        &self,
+        payment_hash: &PaymentHash,
+    ) -> Result<CustomInvoice, LightningError> {
+        let request = tonic_lnd::lnrpc::PaymentHash {
+            r_hash: payment_hash.0.to_vec(),
+            ..Default::default()
+        };
+
+        let response = self
+            .rpc
+            .lookup_invoice(request)
+            .await
+            .map_err(|e| LightningError::InvoiceError(e.to_string()))?;
+
+        let expires_at = lnd_expires_at(response.creation_date, response.expiry);
+        let state = InvoiceStatus::resolve(
+            lnd_invoice_state(response.state),
+            expires_at,
+            chrono::Utc::now().timestamp() as u64,
+        );
+
+        Ok(CustomInvoice {
+            memo: response.memo,
+            payment_hash: hex::encode(response.r_hash),
+            payment_preimage: Some(hex::encode(response.r_preimage))
+                .filter(|preimage_hex| !preimage_hex.is_empty())
+                .unwrap_or_default(),
+            value: Amount::from_msat_i64(response.value_msat),
+            amount_paid: (response.amt_paid_msat > 0)
+                .then(|| Amount::from_msat_i64(response.amt_paid_msat)),
+            creation_date: Some(response.creation_date),
+            settle_date: Some(response.settle_date),
+            payment_request: response.payment_request,
+            expiry: Some(response.expiry as u64),
+            expires_at,
+            state,
+            is_keysend: Some(response.is_keysend),
+            is_amp: Some(response.is_amp),
+            payment_addr: Some(hex::encode(response.payment_addr))
+                .filter(|addr_hex| !addr_hex.is_empty()),
+            htlcs: None,
+            features: None,
+        })
+    }
+
+    async fn get_wallet_balance(&self) -> Result<u64, LightningError> {
+        let response = self.rpc.wallet_balance().await.map_err(|e| {
+            LightningError::GetInfoError(format!("Failed to get wallet balance: {e}"))
+        })?;
+
+        // Return confirmed balance in satoshis
+        Ok(response.confirmed_balance as u64)
+    }
+
+    async fn get_forwarding_fees(&self, since: u64) -> Result<u64, LightningError> {
+        let response = self
+            .rpc
+            .forwarding_history(ForwardingHistoryRequest {
+                start_time: since,
+                num_max_events: 50_000,
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| {
+                LightningError::PaymentError(format!("Failed to get forwarding history: {e}"))
+            })?;
+
+        Ok(response
+            .forwarding_events
+            .iter()
+            .map(|event| event.fee_msat)
+            .sum())
+    }
+
+    async fn list_forwards(&self, since: u64) -> Result<Vec<ForwardSummary>, LightningError> {
+        let response = self
+            .rpc
+            .forwarding_history(ForwardingHistoryRequest {
+                start_time: since,
+                num_max_events: 50_000,
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| {
+                LightningError::PaymentError(format!("Failed to get forwarding history: {e}"))
+            })?;
+
+        Ok(response
+            .forwarding_events
+            .into_iter()
+            .map(|event| ForwardSummary {
+                chan_id_in: Some(ShortChannelID(event.chan_id_in)),
+                chan_id_out: Some(ShortChannelID(event.chan_id_out)),
+                amount_in_msat: event.amt_in_msat,
+                amount_out_msat: event.amt_out_msat,
+                fee_msat: event.fee_msat,
+                resolved_at: event.timestamp_ns / 1_000_000_000,
+            })
+            .collect())
+    }
+
+    async fn set_channel_fee_rate(
+        &self,
+        channel_id: &ShortChannelID,
+        fee_rate_ppm: u32,
+    ) -> Result<(), LightningError> {
+        // LND replaces the whole policy, so the fields that aren't changing are carried over
+        let edge = self
+            .rpc
+            .get_chan_info(ChanInfoRequest {
+                chan_id: channel_id.0,
+            })
+            .await
+            .map_err(|err| {
+                LightningError::ChannelError(format!("LND get_chan_info error: {err}"))
+            })?;
+        let policy = if edge.node1_pub == self.info.pubkey.to_string() {
+            edge.node1_policy
+        } else {
+            edge.node2_policy
+        }
+        .ok_or_else(|| {
+            LightningError::ChannelError(format!("Channel {channel_id} has no local policy"))
+        })?;
+        let outpoint = parse_channel_point(&edge.chan_point)?;
+
+        let response = self
+            .rpc
+            .update_channel_policy(PolicyUpdateRequest {
+                scope: Some(policy_update_request::Scope::ChanPoint(
+                    tonic_lnd::lnrpc::ChannelPoint {
+                        funding_txid: Some(FundingTxid::FundingTxidStr(outpoint.txid.to_string())),
+                        output_index: outpoint.vout,
+                    },
+                )),
+                base_fee_msat: policy.fee_base_msat,
+                fee_rate_ppm,
+                time_lock_delta: policy.time_lock_delta,
+                max_htlc_msat: policy.max_htlc_msat,
+                min_htlc_msat: policy.min_htlc as u64,
+                min_htlc_msat_specified: true,
+                ..Default::default()
+            })
+            .await
+            .map_err(|err| {
+                LightningError::ChannelError(format!("LND update_channel_policy error: {err}"))
+            })?;
+
+        match response.failed_updates.first() {
+            Some(failed) => Err(LightningError::ChannelError(format!(
+                "LND rejected the policy update for channel {channel_id}: {}",
+                failed.update_error
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    async fn list_pending_htlcs(&self) -> Result<PendingHtlcs, LightningError> {
+        let (list_channels_result, info_result) = tokio::join!(
+            self.rpc.list_channels(ListChannelsRequest::default()),
+            self.rpc.get_info(),
+        );
+
+        let channels = list_channels_result
+            .map_err(|err| LightningError::ChannelError(format!("LND list_channels error: {err}")))?
+            .channels;
+        let block_height = info_result
+            .map_err(|err| LightningError::GetInfoError(err.to_string()))?
+            .block_height;
+
+        let htlcs = channels
+            .into_iter()
+            .flat_map(|channel| {
+                let channel_id = ShortChannelID(channel.chan_id);
+                let remote_pubkey = PublicKey::from_str(&channel.remote_pubkey).ok();
+                channel
+                    .pending_htlcs
+                    .into_iter()
+                    .map(move |htlc| PendingHtlc {
+                        channel_id,
+                        remote_pubkey,
+                        incoming: htlc.incoming,
+                        amount_msat: htlc.amount.max(0) as u64 * 1000,
+                        payment_hash: hex::encode(htlc.hash_lock),
+                        expiry_height: htlc.expiration_height,
+                    })
+            })
+            .collect();
+
+        Ok(PendingHtlcs {
+            block_height,
+            htlcs,
+        })
+    }
+
+    async fn list_channel_breaches(&self) -> Result<Vec<ChannelBreach>, LightningError> {
+        let (closed_result, pending_result) = tokio::join!(
+            self.rpc.closed_channels(ClosedChannelsRequest {
+                breach: true,
+                ..Default::default()
+            }),
+            self.rpc.pending_channels(),
+        );
+
+        let closed = closed_result
+            .map_err(|err| {
+                LightningError::ChannelError(format!("LND closed_channels error: {err}"))
+            })?
+            .channels;
+        let pending = pending_result.map_err(|err| {
+            LightningError::ChannelError(format!("LND pending_channels error: {err}"))
+        })?;
+
+        // A breached channel stays pending with funds in limbo until its
+        // justice transactions confirm
+        let limbo: HashMap<String, i64> = pending
+            .waiting_close_channels
+            .into_iter()
+            .filter_map(|waiting| Some((waiting.channel?.channel_point, waiting.limbo_balance)))
+            .chain(
+                pending
+                    .pending_force_closing_channels
+                    .into_iter()
+                    .filter_map(|closing| {
+                        Some((closing.channel?.channel_point, closing.limbo_balance))
+                    }),
+            )
+            .collect();
+
+        Ok(closed
+            .into_iter()
+            .map(|close| {
+                let mut justice_txids: Vec<String> = close
+                    .resolutions
+                    .iter()
+                    .map(|resolution| resolution.sweep_txid.clone())
+                    .filter(|txid| !txid.is_empty())
+                    .collect();
+                justice_txids.sort();
+                justice_txids.dedup();
+
+                ChannelBreach {
+                    channel_id: ShortChannelID(close.chan_id),
+                    limbo_balance_sat: limbo
+                        .get(&close.channel_point)
+                        .copied()
+                        .unwrap_or_default()
+                        .max(0) as u64,
+                    channel_point: close.channel_point,
+                    remote_pubkey: close.remote_pubkey,
+                    capacity_sat: close.capacity.max(0) as u64,
+                    breach_txid: close.closing_tx_hash,
+                    justice_txids,
+                    close_height: close.close_height,
+                    settled_balance_sat: close.settled_balance.max(0) as u64,
+                    time_locked_balance_sat: close.time_locked_balance.max(0) as u64,
+                }
+            })
+            .collect())
+    }
+
+    async fn list_liquidity_ads(&self) -> Result<Vec<LiquidityAd>, LightningError> {
+        Err(LightningError::ValidationError(
+            "LND doesn't support liquidity ads".to_string(),
+        ))
+    }
+
+    async fn open_dual_funded_channel(
+        &self,
+        _request: &DualFundRequest,
+    ) -> Result<OpenedChannel, LightningError> {
+        Err(LightningError::ValidationError(
+            "LND doesn't support dual-funded channels".to_string(),
+        ))
+    }
+
+    async fn list_peers(&self) -> Result<Vec<PeerSummary>, LightningError> {
+        let peers = self
+            .rpc
+            .list_peers(ListPeersRequest { latest_error: true })
+            .await
+            .map_err(|err| LightningError::ConnectionError(format!("LND list_peers error: {err}")))?
+            .peers;
+
+        Ok(peers
+            .into_iter()
+            .filter_map(|peer| {
+                Some(PeerSummary {
+                    pubkey: PublicKey::from_str(&peer.pub_key).ok()?,
+                    address: Some(peer.address),
+                    connected: true,
+                    // LND pings its peers itself and reports 0 until the first pong
+                    ping_time_us: (peer.ping_time > 0).then_some(peer.ping_time as u64),
+                })
+            })
+            .collect())
+    }
+
+    async fn ping_peer(&self, _pubkey: &PublicKey) -> Result<Duration, LightningError> {
+        Err(LightningError::ValidationError(
+            "LND doesn't ping peers on request, its ping times come with the peer list".to_string(),
+        ))
+    }
+
+    async fn list_transaction_labels(&self) -> Result<Vec<TransactionLabel>, LightningError> {
+        let transactions = self
+            .rpc
+            .get_transactions(GetTransactionsRequest::default())
+            .await
+            .map_err(|err| {
+                LightningError::ConnectionError(format!("LND get_transactions error: {err}"))
+            })?
+            .transactions;
+
+        Ok(transactions
+            .into_iter()
+            .filter_map(|transaction| {
+                Some(TransactionLabel {
+                    txid: Txid::from_str(&transaction.tx_hash).ok()?,
+                    label: Some(transaction.label).filter(|label| !label.is_empty()),
+                })
+            })
+            .collect())
+    }
+
+    async fn label_transaction(&self, txid: &Txid, label: &str) -> Result<(), LightningError> {
+        self.rpc
+            .label_transaction(LabelTransactionRequest {
+                // LND takes the txid in its internal byte order
+                txid: txid.to_byte_array().to_vec(),
+                label: label.to_string(),
+                overwrite: true,
+            })
+            .await
+            .map_err(|err| {
+                LightningError::ValidationError(format!(
+                    "Failed to label transaction {txid}: {err}"
+                ))
+            })?;
+        Ok(())
+    }
+
+    async fn sign_message(&self, message: &str) -> Result<String, LightningError> {
+        let response = self
+            .rpc
+            .sign_message(SignMessageRequest {
+                msg: message.as_bytes().to_vec(),
+                single_hash: false,
+            })
+            .await
+            .map_err(|err| {
+                LightningError::ValidationError(format!("Failed to sign message: {err}"))
+            })?;
+        Ok(response.signature)
+    }
+}
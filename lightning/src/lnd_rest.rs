@@ -0,0 +1,1336 @@
+//! LND access over its REST proxy, for nodes that only expose port 8080.
+//!
+//! Each call is the REST route of the gRPC method `LndNode` would otherwise
+//! call, authorised by the hex macaroon in the `Grpc-Metadata-macaroon` header.
+//! Replies are read into the same `lnrpc` types gRPC returns, so `LndNode`
+//! maps both transports to NodeGaze's models with one set of code. Errors are
+//! turned back into the gRPC status LND reports in the body. The REST proxy
+//! can't stream, so subscriptions aren't available over it.
+
+use crate::{client::reader, error::LightningError, socks_proxy};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::{Deserialize, Deserializer, de::DeserializeOwned};
+use serde_json::{Value, json};
+use std::{fmt::Display, str::FromStr};
+use tonic_lnd::{
+    lnrpc::{
+        self, ChanInfoRequest, ChannelGraphRequest, ClosedChannelsRequest, EstimateFeeRequest,
+        ForwardingHistoryRequest, GetTransactionsRequest, ListChannelsRequest, ListInvoiceRequest,
+        ListPaymentsRequest, ListPeersRequest, NodeInfoRequest, PolicyUpdateRequest,
+        SignMessageRequest, channel_point::FundingTxid, policy_update_request,
+    },
+    tonic::{Code, Status},
+    walletrpc::LabelTransactionRequest,
+};
+
+/// Header the REST proxy reads the macaroon from.
+const MACAROON_HEADER: &str = "Grpc-Metadata-macaroon";
+
+/// A client for one node's REST proxy.
+pub struct LndRestClient {
+    http: reqwest::Client,
+    base_url: String,
+    macaroon: String,
+}
+
+impl LndRestClient {
+    /// Prepares requests to the REST proxy at `address`, trusting the node's
+    /// own TLS certificate. Nothing is sent until the first call.
+    pub async fn new(
+        address: String,
+        cert: &str,
+        macaroon: &str,
+        proxy: Option<&str>,
+    ) -> Result<Self, LightningError> {
+        let address = socks_proxy::proxied_address(address, proxy).await?;
+        let cert = reader(cert).await.map_err(|err| {
+            LightningError::ConnectionError(format!("Failed to read TLS certificate: {err}"))
+        })?;
+        let macaroon = reader(macaroon).await.map_err(|err| {
+            LightningError::ConnectionError(format!("Failed to read macaroon: {err}"))
+        })?;
+
+        let certificate = reqwest::Certificate::from_pem(&cert).map_err(|err| {
+            LightningError::ConnectionError(format!("Invalid TLS certificate: {err}"))
+        })?;
+        let http = reqwest::Client::builder()
+            .add_root_certificate(certificate)
+            .build()
+            .map_err(|err| LightningError::ConnectionError(err.to_string()))?;
+
+        Ok(Self {
+            http,
+            base_url: address.trim_end_matches('/').to_string(),
+            macaroon: hex::encode(macaroon),
+        })
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, Status> {
+        self.send(
+            self.http
+                .get(format!("{}{path}", self.base_url))
+                .query(query),
+        )
+        .await
+    }
+
+    async fn post<T: DeserializeOwned>(&self, path: &str, body: Value) -> Result<T, Status> {
+        self.send(
+            self.http
+                .post(format!("{}{path}", self.base_url))
+                .json(&body),
+        )
+        .await
+    }
+
+    async fn send<T: DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T, Status> {
+        let response = request
+            .header(MACAROON_HEADER, &self.macaroon)
+            .send()
+            .await
+            .map_err(|err| Status::unavailable(format!("LND REST request failed: {err}")))?;
+
+        let status = response.status();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|err| Status::unavailable(format!("LND REST response failed: {err}")))?;
+        if !status.is_success() {
+            return Err(rest_error(status, &body));
+        }
+        serde_json::from_slice(&body)
+            .map_err(|err| Status::internal(format!("Unexpected LND REST response: {err}")))
+    }
+
+    pub async fn get_info(&self) -> Result<lnrpc::GetInfoResponse, Status> {
+        self.get::<json::GetInfoResponse>("/v1/getinfo", &[])
+            .await
+            .map(Into::into)
+    }
+
+    pub async fn list_channels(
+        &self,
+        request: ListChannelsRequest,
+    ) -> Result<lnrpc::ListChannelsResponse, Status> {
+        let query = [
+            ("active_only", request.active_only.to_string()),
+            ("inactive_only", request.inactive_only.to_string()),
+            ("public_only", request.public_only.to_string()),
+            ("private_only", request.private_only.to_string()),
+        ];
+        self.get::<json::ListChannelsResponse>("/v1/channels", &query)
+            .await
+            .map(Into::into)
+    }
+
+    pub async fn closed_channels(
+        &self,
+        request: ClosedChannelsRequest,
+    ) -> Result<lnrpc::ClosedChannelsResponse, Status> {
+        let query = [
+            ("cooperative", request.cooperative.to_string()),
+            ("local_force", request.local_force.to_string()),
+            ("remote_force", request.remote_force.to_string()),
+            ("breach", request.breach.to_string()),
+            ("funding_canceled", request.funding_canceled.to_string()),
+            ("abandoned", request.abandoned.to_string()),
+        ];
+        self.get::<json::ClosedChannelsResponse>("/v1/channels/closed", &query)
+            .await
+            .map(Into::into)
+    }
+
+    pub async fn pending_channels(&self) -> Result<lnrpc::PendingChannelsResponse, Status> {
+        self.get::<json::PendingChannelsResponse>("/v1/channels/pending", &[])
+            .await
+            .map(Into::into)
+    }
+
+    pub async fn get_chan_info(
+        &self,
+        request: ChanInfoRequest,
+    ) -> Result<lnrpc::ChannelEdge, Status> {
+        self.get::<json::ChannelEdge>(&format!("/v1/graph/edge/{}", request.chan_id), &[])
+            .await
+            .map(Into::into)
+    }
+
+    pub async fn describe_graph(
+        &self,
+        request: ChannelGraphRequest,
+    ) -> Result<lnrpc::ChannelGraph, Status> {
+        let query = [(
+            "include_unannounced",
+            request.include_unannounced.to_string(),
+        )];
+        self.get::<json::ChannelGraph>("/v1/graph", &query)
+            .await
+            .map(Into::into)
+    }
+
+    pub async fn get_node_info(&self, request: NodeInfoRequest) -> Result<lnrpc::NodeInfo, Status> {
+        let query = [("include_channels", request.include_channels.to_string())];
+        self.get::<json::NodeInfo>(&format!("/v1/graph/node/{}", request.pub_key), &query)
+            .await
+            .map(Into::into)
+    }
+
+    pub async fn get_transactions(
+        &self,
+        request: GetTransactionsRequest,
+    ) -> Result<lnrpc::TransactionDetails, Status> {
+        let query = [
+            ("start_height", request.start_height.to_string()),
+            ("end_height", request.end_height.to_string()),
+        ];
+        self.get::<json::TransactionDetails>("/v1/transactions", &query)
+            .await
+            .map(Into::into)
+    }
+
+    pub async fn estimate_fee(
+        &self,
+        request: EstimateFeeRequest,
+    ) -> Result<lnrpc::EstimateFeeResponse, Status> {
+        let mut query: Vec<(&str, String)> = vec![
+            ("target_conf", request.target_conf.to_string()),
+            ("spend_unconfirmed", request.spend_unconfirmed.to_string()),
+        ];
+        let amounts: Vec<(String, String)> = request
+            .addr_to_amount
+            .iter()
+            .map(|(address, amount)| (format!("addr_to_amount[{address}]"), amount.to_string()))
+            .collect();
+        query.extend(
+            amounts
+                .iter()
+                .map(|(key, amount)| (key.as_str(), amount.clone())),
+        );
+        self.get::<json::EstimateFeeResponse>("/v1/transactions/fee", &query)
+            .await
+            .map(Into::into)
+    }
+
+    pub async fn list_payments(
+        &self,
+        request: ListPaymentsRequest,
+    ) -> Result<lnrpc::ListPaymentsResponse, Status> {
+        let query = [
+            ("include_incomplete", request.include_incomplete.to_string()),
+            ("index_offset", request.index_offset.to_string()),
+            ("max_payments", request.max_payments.to_string()),
+            ("reversed", request.reversed.to_string()),
+        ];
+        self.get::<json::ListPaymentsResponse>("/v1/payments", &query)
+            .await
+            .map(Into::into)
+    }
+
+    pub async fn list_invoices(
+        &self,
+        request: ListInvoiceRequest,
+    ) -> Result<lnrpc::ListInvoiceResponse, Status> {
+        let query = [
+            ("pending_only", request.pending_only.to_string()),
+            ("index_offset", request.index_offset.to_string()),
+            ("num_max_invoices", request.num_max_invoices.to_string()),
+            ("reversed", request.reversed.to_string()),
+        ];
+        self.get::<json::ListInvoiceResponse>("/v1/invoices", &query)
+            .await
+            .map(Into::into)
+    }
+
+    pub async fn lookup_invoice(
+        &self,
+        request: lnrpc::PaymentHash,
+    ) -> Result<lnrpc::Invoice, Status> {
+        self.get::<json::Invoice>(&format!("/v1/invoice/{}", hex::encode(request.r_hash)), &[])
+            .await
+            .map(Into::into)
+    }
+
+    pub async fn wallet_balance(&self) -> Result<lnrpc::WalletBalanceResponse, Status> {
+        self.get::<json::WalletBalanceResponse>("/v1/balance/blockchain", &[])
+            .await
+            .map(Into::into)
+    }
+
+    pub async fn forwarding_history(
+        &self,
+        request: ForwardingHistoryRequest,
+    ) -> Result<lnrpc::ForwardingHistoryResponse, Status> {
+        let body = json!({
+            "start_time": request.start_time.to_string(),
+            "end_time": request.end_time.to_string(),
+            "index_offset": request.index_offset,
+            "num_max_events": request.num_max_events,
+        });
+        self.post::<json::ForwardingHistoryResponse>("/v1/switch", body)
+            .await
+            .map(Into::into)
+    }
+
+    pub async fn update_channel_policy(
+        &self,
+        request: PolicyUpdateRequest,
+    ) -> Result<lnrpc::PolicyUpdateResponse, Status> {
+        let mut body = json!({
+            "base_fee_msat": request.base_fee_msat.to_string(),
+            "fee_rate_ppm": request.fee_rate_ppm,
+            "time_lock_delta": request.time_lock_delta,
+            "max_htlc_msat": request.max_htlc_msat.to_string(),
+            "min_htlc_msat": request.min_htlc_msat.to_string(),
+            "min_htlc_msat_specified": request.min_htlc_msat_specified,
+        });
+        match request.scope {
+            Some(policy_update_request::Scope::ChanPoint(chan_point)) => {
+                let funding_txid = match chan_point.funding_txid {
+                    Some(FundingTxid::FundingTxidStr(txid)) => json!({ "funding_txid_str": txid }),
+                    Some(FundingTxid::FundingTxidBytes(txid)) => {
+                        json!({ "funding_txid_bytes": STANDARD.encode(txid) })
+                    }
+                    None => json!({}),
+                };
+                let mut chan_point_json = funding_txid;
+                chan_point_json["output_index"] = json!(chan_point.output_index);
+                body["chan_point"] = chan_point_json;
+            }
+            Some(policy_update_request::Scope::Global(global)) => body["global"] = json!(global),
+            None => {}
+        }
+        self.post::<json::PolicyUpdateResponse>("/v1/chanpolicy", body)
+            .await
+            .map(Into::into)
+    }
+
+    pub async fn list_peers(
+        &self,
+        request: ListPeersRequest,
+    ) -> Result<lnrpc::ListPeersResponse, Status> {
+        let query = [("latest_error", request.latest_error.to_string())];
+        self.get::<json::ListPeersResponse>("/v1/peers", &query)
+            .await
+            .map(Into::into)
+    }
+
+    pub async fn sign_message(
+        &self,
+        request: SignMessageRequest,
+    ) -> Result<lnrpc::SignMessageResponse, Status> {
+        let body = json!({
+            "msg": STANDARD.encode(request.msg),
+            "single_hash": request.single_hash,
+        });
+        self.post::<json::SignMessageResponse>("/v1/signmessage", body)
+            .await
+            .map(|response| lnrpc::SignMessageResponse {
+                signature: response.signature,
+            })
+    }
+
+    pub async fn label_transaction(&self, request: LabelTransactionRequest) -> Result<(), Status> {
+        let body = json!({
+            "txid": STANDARD.encode(request.txid),
+            "label": request.label,
+            "overwrite": request.overwrite,
+        });
+        self.post::<Value>("/v2/wallet/tx/label", body).await?;
+        Ok(())
+    }
+}
+
+/// Turns a failed REST reply into the gRPC status LND put in its body, or one
+/// derived from the HTTP status when the body isn't LND's.
+fn rest_error(status: reqwest::StatusCode, body: &[u8]) -> Status {
+    #[derive(Deserialize)]
+    struct ErrorBody {
+        code: i32,
+        message: String,
+    }
+
+    match serde_json::from_slice::<ErrorBody>(body) {
+        Ok(error) => Status::new(Code::from_i32(error.code), error.message),
+        Err(_) => {
+            let code = match status.as_u16() {
+                401 => Code::Unauthenticated,
+                403 => Code::PermissionDenied,
+                404 => Code::NotFound,
+                503 => Code::Unavailable,
+                _ => Code::Unknown,
+            };
+            Status::new(
+                code,
+                format!("LND REST error {status}: {}", String::from_utf8_lossy(body)),
+            )
+        }
+    }
+}
+
+/// Reads an integer the REST proxy writes either as a number or, for 64-bit
+/// fields, as a string.
+fn number<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr + Deserialize<'de>,
+    T::Err: Display,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Number<T> {
+        Text(String),
+        Value(T),
+    }
+
+    match Number::<T>::deserialize(deserializer)? {
+        Number::Text(text) => text.parse().map_err(serde::de::Error::custom),
+        Number::Value(value) => Ok(value),
+    }
+}
+
+/// Reads a `bytes` field, which the REST proxy writes as base64.
+fn bytes<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let encoded = Option::<String>::deserialize(deserializer)?.unwrap_or_default();
+    STANDARD.decode(encoded).map_err(serde::de::Error::custom)
+}
+
+/// Reads an enum written by name into its protobuf value, the first variant for
+/// names this version of the protocol doesn't know.
+fn enum_value<E: Into<i32>>(name: &str, from_str_name: fn(&str) -> Option<E>) -> i32 {
+    from_str_name(name).map_or(0, Into::into)
+}
+
+/// The REST replies, holding the fields `LndNode` reads. Each converts into the
+/// `lnrpc` type gRPC returns, with the fields left out at their defaults.
+mod json {
+    use super::{bytes, enum_value, number};
+    use serde::Deserialize;
+    use std::collections::HashMap;
+    use tonic_lnd::lnrpc::{
+        self, CommitmentType, channel_close_summary::ClosureType, failure::FailureCode,
+        invoice::InvoiceState, payment::PaymentStatus, pending_channels_response,
+    };
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct Feature {
+        name: String,
+        is_required: bool,
+        is_known: bool,
+    }
+
+    fn features(features: HashMap<u32, Feature>) -> HashMap<u32, lnrpc::Feature> {
+        features
+            .into_iter()
+            .map(|(bit, feature)| {
+                (
+                    bit,
+                    lnrpc::Feature {
+                        name: feature.name,
+                        is_required: feature.is_required,
+                        is_known: feature.is_known,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct Chain {
+        chain: String,
+        network: String,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct GetInfoResponse {
+        version: String,
+        identity_pubkey: String,
+        alias: String,
+        #[serde(deserialize_with = "number")]
+        block_height: u32,
+        chains: Vec<Chain>,
+        features: HashMap<u32, Feature>,
+    }
+
+    impl From<GetInfoResponse> for lnrpc::GetInfoResponse {
+        fn from(info: GetInfoResponse) -> Self {
+            Self {
+                version: info.version,
+                identity_pubkey: info.identity_pubkey,
+                alias: info.alias,
+                block_height: info.block_height,
+                chains: info
+                    .chains
+                    .into_iter()
+                    .map(|chain| lnrpc::Chain {
+                        chain: chain.chain,
+                        network: chain.network,
+                    })
+                    .collect(),
+                features: features(info.features),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct ChannelConstraints {
+        #[serde(deserialize_with = "number")]
+        chan_reserve_sat: u64,
+    }
+
+    impl From<ChannelConstraints> for lnrpc::ChannelConstraints {
+        fn from(constraints: ChannelConstraints) -> Self {
+            Self {
+                chan_reserve_sat: constraints.chan_reserve_sat,
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct Htlc {
+        incoming: bool,
+        #[serde(deserialize_with = "number")]
+        amount: i64,
+        #[serde(deserialize_with = "bytes")]
+        hash_lock: Vec<u8>,
+        #[serde(deserialize_with = "number")]
+        expiration_height: u32,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct Channel {
+        active: bool,
+        remote_pubkey: String,
+        channel_point: String,
+        #[serde(deserialize_with = "number")]
+        chan_id: u64,
+        #[serde(deserialize_with = "number")]
+        capacity: i64,
+        #[serde(deserialize_with = "number")]
+        local_balance: i64,
+        #[serde(deserialize_with = "number")]
+        remote_balance: i64,
+        #[serde(deserialize_with = "number")]
+        commit_fee: i64,
+        #[serde(deserialize_with = "number")]
+        commit_weight: i64,
+        #[serde(deserialize_with = "number")]
+        total_satoshis_sent: i64,
+        #[serde(deserialize_with = "number")]
+        total_satoshis_received: i64,
+        #[serde(deserialize_with = "number")]
+        num_updates: u64,
+        pending_htlcs: Vec<Htlc>,
+        private: bool,
+        initiator: bool,
+        commitment_type: String,
+        #[serde(deserialize_with = "number")]
+        uptime: i64,
+        local_constraints: Option<ChannelConstraints>,
+        remote_constraints: Option<ChannelConstraints>,
+    }
+
+    impl From<Channel> for lnrpc::Channel {
+        fn from(channel: Channel) -> Self {
+            Self {
+                active: channel.active,
+                remote_pubkey: channel.remote_pubkey,
+                channel_point: channel.channel_point,
+                chan_id: channel.chan_id,
+                capacity: channel.capacity,
+                local_balance: channel.local_balance,
+                remote_balance: channel.remote_balance,
+                commit_fee: channel.commit_fee,
+                commit_weight: channel.commit_weight,
+                total_satoshis_sent: channel.total_satoshis_sent,
+                total_satoshis_received: channel.total_satoshis_received,
+                num_updates: channel.num_updates,
+                pending_htlcs: channel
+                    .pending_htlcs
+                    .into_iter()
+                    .map(|htlc| lnrpc::Htlc {
+                        incoming: htlc.incoming,
+                        amount: htlc.amount,
+                        hash_lock: htlc.hash_lock,
+                        expiration_height: htlc.expiration_height,
+                        ..Default::default()
+                    })
+                    .collect(),
+                private: channel.private,
+                initiator: channel.initiator,
+                commitment_type: enum_value(
+                    &channel.commitment_type,
+                    CommitmentType::from_str_name,
+                ),
+                uptime: channel.uptime,
+                local_constraints: channel.local_constraints.map(Into::into),
+                remote_constraints: channel.remote_constraints.map(Into::into),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct ListChannelsResponse {
+        channels: Vec<Channel>,
+    }
+
+    impl From<ListChannelsResponse> for lnrpc::ListChannelsResponse {
+        fn from(response: ListChannelsResponse) -> Self {
+            Self {
+                channels: response.channels.into_iter().map(Into::into).collect(),
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct Resolution {
+        sweep_txid: String,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct ChannelCloseSummary {
+        channel_point: String,
+        #[serde(deserialize_with = "number")]
+        chan_id: u64,
+        closing_tx_hash: String,
+        remote_pubkey: String,
+        #[serde(deserialize_with = "number")]
+        capacity: i64,
+        #[serde(deserialize_with = "number")]
+        close_height: u32,
+        #[serde(deserialize_with = "number")]
+        settled_balance: i64,
+        #[serde(deserialize_with = "number")]
+        time_locked_balance: i64,
+        close_type: String,
+        resolutions: Vec<Resolution>,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct ClosedChannelsResponse {
+        channels: Vec<ChannelCloseSummary>,
+    }
+
+    impl From<ClosedChannelsResponse> for lnrpc::ClosedChannelsResponse {
+        fn from(response: ClosedChannelsResponse) -> Self {
+            Self {
+                channels: response
+                    .channels
+                    .into_iter()
+                    .map(|close| lnrpc::ChannelCloseSummary {
+                        channel_point: close.channel_point,
+                        chan_id: close.chan_id,
+                        closing_tx_hash: close.closing_tx_hash,
+                        remote_pubkey: close.remote_pubkey,
+                        capacity: close.capacity,
+                        close_height: close.close_height,
+                        settled_balance: close.settled_balance,
+                        time_locked_balance: close.time_locked_balance,
+                        close_type: enum_value(&close.close_type, ClosureType::from_str_name),
+                        resolutions: close
+                            .resolutions
+                            .into_iter()
+                            .map(|resolution| lnrpc::Resolution {
+                                sweep_txid: resolution.sweep_txid,
+                                ..Default::default()
+                            })
+                            .collect(),
+                        ..Default::default()
+                    })
+                    .collect(),
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct PendingChannel {
+        channel_point: String,
+    }
+
+    /// A closing channel, as listed under `waiting_close_channels` and
+    /// `pending_force_closing_channels`.
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct ClosingChannel {
+        channel: Option<PendingChannel>,
+        #[serde(deserialize_with = "number")]
+        limbo_balance: i64,
+    }
+
+    impl ClosingChannel {
+        fn channel(self) -> Option<pending_channels_response::PendingChannel> {
+            self.channel
+                .map(|channel| pending_channels_response::PendingChannel {
+                    channel_point: channel.channel_point,
+                    ..Default::default()
+                })
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct PendingChannelsResponse {
+        waiting_close_channels: Vec<ClosingChannel>,
+        pending_force_closing_channels: Vec<ClosingChannel>,
+    }
+
+    impl From<PendingChannelsResponse> for lnrpc::PendingChannelsResponse {
+        fn from(response: PendingChannelsResponse) -> Self {
+            Self {
+                waiting_close_channels: response
+                    .waiting_close_channels
+                    .into_iter()
+                    .map(|waiting| pending_channels_response::WaitingCloseChannel {
+                        limbo_balance: waiting.limbo_balance,
+                        channel: waiting.channel(),
+                        ..Default::default()
+                    })
+                    .collect(),
+                pending_force_closing_channels: response
+                    .pending_force_closing_channels
+                    .into_iter()
+                    .map(|closing| pending_channels_response::ForceClosedChannel {
+                        limbo_balance: closing.limbo_balance,
+                        channel: closing.channel(),
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct RoutingPolicy {
+        #[serde(deserialize_with = "number")]
+        time_lock_delta: u32,
+        #[serde(deserialize_with = "number")]
+        min_htlc: i64,
+        #[serde(deserialize_with = "number")]
+        fee_base_msat: i64,
+        #[serde(deserialize_with = "number")]
+        fee_rate_milli_msat: i64,
+        disabled: bool,
+        #[serde(deserialize_with = "number")]
+        max_htlc_msat: u64,
+        #[serde(deserialize_with = "number")]
+        last_update: u32,
+    }
+
+    impl From<RoutingPolicy> for lnrpc::RoutingPolicy {
+        fn from(policy: RoutingPolicy) -> Self {
+            Self {
+                time_lock_delta: policy.time_lock_delta,
+                min_htlc: policy.min_htlc,
+                fee_base_msat: policy.fee_base_msat,
+                fee_rate_milli_msat: policy.fee_rate_milli_msat,
+                disabled: policy.disabled,
+                max_htlc_msat: policy.max_htlc_msat,
+                last_update: policy.last_update,
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct ChannelEdge {
+        #[serde(deserialize_with = "number")]
+        channel_id: u64,
+        chan_point: String,
+        node1_pub: String,
+        node2_pub: String,
+        #[serde(deserialize_with = "number")]
+        capacity: i64,
+        node1_policy: Option<RoutingPolicy>,
+        node2_policy: Option<RoutingPolicy>,
+    }
+
+    impl From<ChannelEdge> for lnrpc::ChannelEdge {
+        fn from(edge: ChannelEdge) -> Self {
+            Self {
+                channel_id: edge.channel_id,
+                chan_point: edge.chan_point,
+                node1_pub: edge.node1_pub,
+                node2_pub: edge.node2_pub,
+                capacity: edge.capacity,
+                node1_policy: edge.node1_policy.map(Into::into),
+                node2_policy: edge.node2_policy.map(Into::into),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct ChannelGraph {
+        edges: Vec<ChannelEdge>,
+    }
+
+    impl From<ChannelGraph> for lnrpc::ChannelGraph {
+        fn from(graph: ChannelGraph) -> Self {
+            Self {
+                edges: graph.edges.into_iter().map(Into::into).collect(),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct NodeAddress {
+        network: String,
+        addr: String,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct LightningNode {
+        #[serde(deserialize_with = "number")]
+        last_update: u32,
+        pub_key: String,
+        alias: String,
+        addresses: Vec<NodeAddress>,
+        color: String,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct NodeInfo {
+        node: Option<LightningNode>,
+        #[serde(deserialize_with = "number")]
+        num_channels: u32,
+        #[serde(deserialize_with = "number")]
+        total_capacity: i64,
+    }
+
+    impl From<NodeInfo> for lnrpc::NodeInfo {
+        fn from(info: NodeInfo) -> Self {
+            Self {
+                node: info.node.map(|node| lnrpc::LightningNode {
+                    last_update: node.last_update,
+                    pub_key: node.pub_key,
+                    alias: node.alias,
+                    addresses: node
+                        .addresses
+                        .into_iter()
+                        .map(|address| lnrpc::NodeAddress {
+                            network: address.network,
+                            addr: address.addr,
+                        })
+                        .collect(),
+                    color: node.color,
+                    ..Default::default()
+                }),
+                num_channels: info.num_channels,
+                total_capacity: info.total_capacity,
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct Transaction {
+        tx_hash: String,
+        #[serde(deserialize_with = "number")]
+        total_fees: i64,
+        label: String,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct TransactionDetails {
+        transactions: Vec<Transaction>,
+    }
+
+    impl From<TransactionDetails> for lnrpc::TransactionDetails {
+        fn from(details: TransactionDetails) -> Self {
+            Self {
+                transactions: details
+                    .transactions
+                    .into_iter()
+                    .map(|transaction| lnrpc::Transaction {
+                        tx_hash: transaction.tx_hash,
+                        total_fees: transaction.total_fees,
+                        label: transaction.label,
+                        ..Default::default()
+                    })
+                    .collect(),
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct EstimateFeeResponse {
+        #[serde(deserialize_with = "number")]
+        fee_sat: i64,
+        #[serde(deserialize_with = "number")]
+        sat_per_vbyte: u64,
+    }
+
+    impl From<EstimateFeeResponse> for lnrpc::EstimateFeeResponse {
+        fn from(estimate: EstimateFeeResponse) -> Self {
+            Self {
+                fee_sat: estimate.fee_sat,
+                sat_per_vbyte: estimate.sat_per_vbyte,
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct Hop {
+        #[serde(deserialize_with = "number")]
+        chan_id: u64,
+        #[serde(deserialize_with = "number")]
+        expiry: u32,
+        #[serde(deserialize_with = "number")]
+        amt_to_forward_msat: i64,
+        #[serde(deserialize_with = "number")]
+        fee_msat: i64,
+        pub_key: String,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct Route {
+        #[serde(deserialize_with = "number")]
+        total_time_lock: u32,
+        #[serde(deserialize_with = "number")]
+        total_fees_msat: i64,
+        #[serde(deserialize_with = "number")]
+        total_amt_msat: i64,
+        hops: Vec<Hop>,
+    }
+
+    impl From<Route> for lnrpc::Route {
+        fn from(route: Route) -> Self {
+            Self {
+                total_time_lock: route.total_time_lock,
+                total_fees_msat: route.total_fees_msat,
+                total_amt_msat: route.total_amt_msat,
+                hops: route
+                    .hops
+                    .into_iter()
+                    .map(|hop| lnrpc::Hop {
+                        chan_id: hop.chan_id,
+                        expiry: hop.expiry,
+                        amt_to_forward_msat: hop.amt_to_forward_msat,
+                        fee_msat: hop.fee_msat,
+                        pub_key: hop.pub_key,
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct Failure {
+        code: String,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct HtlcAttempt {
+        #[serde(deserialize_with = "number")]
+        attempt_id: u64,
+        route: Option<Route>,
+        #[serde(deserialize_with = "number")]
+        attempt_time_ns: i64,
+        #[serde(deserialize_with = "number")]
+        resolve_time_ns: i64,
+        failure: Option<Failure>,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct Payment {
+        payment_hash: String,
+        #[serde(deserialize_with = "number")]
+        value_sat: i64,
+        #[serde(deserialize_with = "number")]
+        value_msat: i64,
+        #[serde(deserialize_with = "number")]
+        fee_sat: i64,
+        #[serde(deserialize_with = "number")]
+        fee_msat: i64,
+        payment_request: String,
+        status: String,
+        #[serde(deserialize_with = "number")]
+        creation_time_ns: i64,
+        htlcs: Vec<HtlcAttempt>,
+    }
+
+    impl From<Payment> for lnrpc::Payment {
+        fn from(payment: Payment) -> Self {
+            Self {
+                payment_hash: payment.payment_hash,
+                value_sat: payment.value_sat,
+                value_msat: payment.value_msat,
+                fee_sat: payment.fee_sat,
+                fee_msat: payment.fee_msat,
+                payment_request: payment.payment_request,
+                status: enum_value(&payment.status, PaymentStatus::from_str_name),
+                creation_time_ns: payment.creation_time_ns,
+                htlcs: payment
+                    .htlcs
+                    .into_iter()
+                    .map(|htlc| lnrpc::HtlcAttempt {
+                        attempt_id: htlc.attempt_id,
+                        route: htlc.route.map(Into::into),
+                        attempt_time_ns: htlc.attempt_time_ns,
+                        resolve_time_ns: htlc.resolve_time_ns,
+                        failure: htlc.failure.map(|failure| lnrpc::Failure {
+                            code: enum_value(&failure.code, FailureCode::from_str_name),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct ListPaymentsResponse {
+        payments: Vec<Payment>,
+    }
+
+    impl From<ListPaymentsResponse> for lnrpc::ListPaymentsResponse {
+        fn from(response: ListPaymentsResponse) -> Self {
+            Self {
+                payments: response.payments.into_iter().map(Into::into).collect(),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct InvoiceHtlc {
+        #[serde(deserialize_with = "number")]
+        chan_id: u64,
+        #[serde(deserialize_with = "number")]
+        htlc_index: u64,
+        #[serde(deserialize_with = "number")]
+        amt_msat: u64,
+        #[serde(deserialize_with = "number")]
+        accept_time: i64,
+        #[serde(deserialize_with = "number")]
+        resolve_time: i64,
+        #[serde(deserialize_with = "number")]
+        expiry_height: i32,
+        #[serde(deserialize_with = "number")]
+        mpp_total_amt_msat: u64,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct Invoice {
+        memo: String,
+        #[serde(deserialize_with = "bytes")]
+        r_preimage: Vec<u8>,
+        #[serde(deserialize_with = "bytes")]
+        r_hash: Vec<u8>,
+        #[serde(deserialize_with = "number")]
+        value: i64,
+        #[serde(deserialize_with = "number")]
+        value_msat: i64,
+        #[serde(deserialize_with = "number")]
+        creation_date: i64,
+        #[serde(deserialize_with = "number")]
+        settle_date: i64,
+        payment_request: String,
+        #[serde(deserialize_with = "number")]
+        expiry: i64,
+        #[serde(deserialize_with = "number")]
+        amt_paid_sat: i64,
+        #[serde(deserialize_with = "number")]
+        amt_paid_msat: i64,
+        state: String,
+        htlcs: Vec<InvoiceHtlc>,
+        features: HashMap<u32, Feature>,
+        is_keysend: bool,
+        #[serde(deserialize_with = "bytes")]
+        payment_addr: Vec<u8>,
+        is_amp: bool,
+    }
+
+    impl From<Invoice> for lnrpc::Invoice {
+        fn from(invoice: Invoice) -> Self {
+            Self {
+                memo: invoice.memo,
+                r_preimage: invoice.r_preimage,
+                r_hash: invoice.r_hash,
+                value: invoice.value,
+                value_msat: invoice.value_msat,
+                creation_date: invoice.creation_date,
+                settle_date: invoice.settle_date,
+                payment_request: invoice.payment_request,
+                expiry: invoice.expiry,
+                amt_paid_sat: invoice.amt_paid_sat,
+                amt_paid_msat: invoice.amt_paid_msat,
+                state: enum_value(&invoice.state, InvoiceState::from_str_name),
+                htlcs: invoice
+                    .htlcs
+                    .into_iter()
+                    .map(|htlc| lnrpc::InvoiceHtlc {
+                        chan_id: htlc.chan_id,
+                        htlc_index: htlc.htlc_index,
+                        amt_msat: htlc.amt_msat,
+                        accept_time: htlc.accept_time,
+                        resolve_time: htlc.resolve_time,
+                        expiry_height: htlc.expiry_height,
+                        mpp_total_amt_msat: htlc.mpp_total_amt_msat,
+                        ..Default::default()
+                    })
+                    .collect(),
+                features: features(invoice.features),
+                is_keysend: invoice.is_keysend,
+                payment_addr: invoice.payment_addr,
+                is_amp: invoice.is_amp,
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct ListInvoiceResponse {
+        invoices: Vec<Invoice>,
+    }
+
+    impl From<ListInvoiceResponse> for lnrpc::ListInvoiceResponse {
+        fn from(response: ListInvoiceResponse) -> Self {
+            Self {
+                invoices: response.invoices.into_iter().map(Into::into).collect(),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct WalletBalanceResponse {
+        #[serde(deserialize_with = "number")]
+        confirmed_balance: i64,
+    }
+
+    impl From<WalletBalanceResponse> for lnrpc::WalletBalanceResponse {
+        fn from(balance: WalletBalanceResponse) -> Self {
+            Self {
+                confirmed_balance: balance.confirmed_balance,
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct ForwardingEvent {
+        #[serde(deserialize_with = "number")]
+        chan_id_in: u64,
+        #[serde(deserialize_with = "number")]
+        chan_id_out: u64,
+        #[serde(deserialize_with = "number")]
+        amt_in_msat: u64,
+        #[serde(deserialize_with = "number")]
+        amt_out_msat: u64,
+        #[serde(deserialize_with = "number")]
+        fee_msat: u64,
+        #[serde(deserialize_with = "number")]
+        timestamp_ns: u64,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct ForwardingHistoryResponse {
+        forwarding_events: Vec<ForwardingEvent>,
+    }
+
+    impl From<ForwardingHistoryResponse> for lnrpc::ForwardingHistoryResponse {
+        fn from(response: ForwardingHistoryResponse) -> Self {
+            Self {
+                forwarding_events: response
+                    .forwarding_events
+                    .into_iter()
+                    .map(|event| lnrpc::ForwardingEvent {
+                        chan_id_in: event.chan_id_in,
+                        chan_id_out: event.chan_id_out,
+                        amt_in_msat: event.amt_in_msat,
+                        amt_out_msat: event.amt_out_msat,
+                        fee_msat: event.fee_msat,
+                        timestamp_ns: event.timestamp_ns,
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct FailedUpdate {
+        update_error: String,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct PolicyUpdateResponse {
+        failed_updates: Vec<FailedUpdate>,
+    }
+
+    impl From<PolicyUpdateResponse> for lnrpc::PolicyUpdateResponse {
+        fn from(response: PolicyUpdateResponse) -> Self {
+            Self {
+                failed_updates: response
+                    .failed_updates
+                    .into_iter()
+                    .map(|failed| lnrpc::FailedUpdate {
+                        update_error: failed.update_error,
+                        ..Default::default()
+                    })
+                    .collect(),
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct Peer {
+        pub_key: String,
+        address: String,
+        #[serde(deserialize_with = "number")]
+        ping_time: i64,
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct ListPeersResponse {
+        peers: Vec<Peer>,
+    }
+
+    impl From<ListPeersResponse> for lnrpc::ListPeersResponse {
+        fn from(response: ListPeersResponse) -> Self {
+            Self {
+                peers: response
+                    .peers
+                    .into_iter()
+                    .map(|peer| lnrpc::Peer {
+                        pub_key: peer.pub_key,
+                        address: peer.address,
+                        ping_time: peer.ping_time,
+                        ..Default::default()
+                    })
+                    .collect(),
+            }
+        }
+    }
+
+    #[derive(Deserialize, Default)]
+    #[serde(default)]
+    pub struct SignMessageResponse {
+        pub signature: String,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic_lnd::lnrpc::{
+        CommitmentType, channel_close_summary::ClosureType, payment::PaymentStatus,
+    };
+
+    #[test]
+    fn reads_rest_replies_into_grpc_types() {
+        let channels: lnrpc::ListChannelsResponse = serde_json::from_str::<json::ListChannelsResponse>(
+            r#"{"channels": [{
+                "active": true,
+                "chan_id": "880312192405487617",
+                "capacity": "2000000",
+                "local_balance": "1250000",
+                "commit_weight": "1116",
+                "commitment_type": "ANCHORS",
+                "pending_htlcs": [{"incoming": false, "amount": "5000", "hash_lock": "3q2+7w==", "expiration_height": 800144}],
+                "local_constraints": {"csv_delay": 144, "chan_reserve_sat": "20000"},
+                "alias_scids": []
+            }]}"#,
+        )
+        .unwrap()
+        .into();
+        let channel = &channels.channels[0];
+        assert_eq!(channel.chan_id, 880_312_192_405_487_617);
+        assert_eq!(channel.local_balance, 1_250_000);
+        assert_eq!(channel.commitment_type, CommitmentType::Anchors as i32);
+        assert_eq!(channel.pending_htlcs[0].hash_lock, [0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(
+            channel.local_constraints.as_ref().unwrap().chan_reserve_sat,
+            20_000
+        );
+        assert!(channel.remote_constraints.is_none());
+
+        let payment: lnrpc::Payment = serde_json::from_str::<json::Payment>(
+            r#"{"payment_hash": "ab", "value_sat": "1000", "value_msat": "1000500", "status": "SUCCEEDED", "htlcs": []}"#,
+        )
+        .unwrap()
+        .into();
+        assert_eq!(payment.status, PaymentStatus::Succeeded as i32);
+        assert_eq!(payment.value_sat, 1000);
+        assert_eq!(payment.value_msat, 1_000_500);
+
+        let closed: lnrpc::ClosedChannelsResponse =
+            serde_json::from_str::<json::ClosedChannelsResponse>(
+                r#"{"channels": [{
+                    "chan_id": "880312192405487617",
+                    "closing_tx_hash": "ab",
+                    "settled_balance": "0",
+                    "close_type": "BREACH_CLOSE",
+                    "resolutions": [{"resolution_type": "COMMIT", "sweep_txid": "cd"}]
+                }]}"#,
+            )
+            .unwrap()
+            .into();
+        assert_eq!(closed.channels[0].close_type(), ClosureType::BreachClose);
+        assert_eq!(closed.channels[0].resolutions[0].sweep_txid, "cd");
+
+        let error = rest_error(
+            reqwest::StatusCode::NOT_FOUND,
+            br#"{"code": 5, "message": "edge not found", "details": []}"#,
+        );
+        assert_eq!(error.code(), Code::NotFound);
+        assert_eq!(error.message(), "edge not found");
+    }
+}
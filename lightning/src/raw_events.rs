@@ -0,0 +1,268 @@
+//! Conversion of the LND messages events are built from to JSON.
+//!
+//! Messages are converted field by field following LND's REST JSON, except
+//! that byte fields are hex rather than base64 encoded.
+
+use crate::events::RawPayload;
+use serde_json::{Value, json};
+use tonic_lnd::lnrpc::{
+    Channel, ChannelCloseSummary, ChannelConstraints, ChannelEventUpdate, ChannelPoint, Invoice,
+    channel_event_update::Channel as EventChannel, channel_point::FundingTxid,
+};
+
+fn channel_point_json(point: &ChannelPoint) -> Value {
+    let txid = match &point.funding_txid {
+        Some(FundingTxid::FundingTxidStr(txid)) => Value::String(txid.clone()),
+        Some(FundingTxid::FundingTxidBytes(bytes)) => Value::String(hex::encode(bytes)),
+        None => Value::Null,
+    };
+    json!({ "funding_txid": txid, "output_index": point.output_index })
+}
+
+fn constraints_json(constraints: &Option<ChannelConstraints>) -> Value {
+    constraints.as_ref().map_or(Value::Null, |c| {
+        json!({
+            "csv_delay": c.csv_delay,
+            "chan_reserve_sat": c.chan_reserve_sat,
+            "dust_limit_sat": c.dust_limit_sat,
+            "max_pending_amt_msat": c.max_pending_amt_msat,
+            "min_htlc_msat": c.min_htlc_msat,
+            "max_accepted_htlcs": c.max_accepted_htlcs,
+        })
+    })
+}
+
+#[allow(deprecated)]
+fn channel_json(chan: &Channel) -> Value {
+    json!({
+        "active": chan.active,
+        "remote_pubkey": chan.remote_pubkey,
+        "channel_point": chan.channel_point,
+        "chan_id": chan.chan_id,
+        "capacity": chan.capacity,
+        "local_balance": chan.local_balance,
+        "remote_balance": chan.remote_balance,
+        "commit_fee": chan.commit_fee,
+        "commit_weight": chan.commit_weight,
+        "fee_per_kw": chan.fee_per_kw,
+        "unsettled_balance": chan.unsettled_balance,
+        "total_satoshis_sent": chan.total_satoshis_sent,
+        "total_satoshis_received": chan.total_satoshis_received,
+        "num_updates": chan.num_updates,
+        "pending_htlcs": chan.pending_htlcs.iter().map(|htlc| json!({
+            "incoming": htlc.incoming,
+            "amount": htlc.amount,
+            "hash_lock": hex::encode(&htlc.hash_lock),
+            "expiration_height": htlc.expiration_height,
+            "htlc_index": htlc.htlc_index,
+            "forwarding_channel": htlc.forwarding_channel,
+            "forwarding_htlc_index": htlc.forwarding_htlc_index,
+        })).collect::<Vec<_>>(),
+        "csv_delay": chan.csv_delay,
+        "private": chan.private,
+        "initiator": chan.initiator,
+        "chan_status_flags": chan.chan_status_flags,
+        "local_chan_reserve_sat": chan.local_chan_reserve_sat,
+        "remote_chan_reserve_sat": chan.remote_chan_reserve_sat,
+        "static_remote_key": chan.static_remote_key,
+        "commitment_type": chan.commitment_type().as_str_name(),
+        "lifetime": chan.lifetime,
+        "uptime": chan.uptime,
+        "close_address": chan.close_address,
+        "push_amount_sat": chan.push_amount_sat,
+        "thaw_height": chan.thaw_height,
+        "local_constraints": constraints_json(&chan.local_constraints),
+        "remote_constraints": constraints_json(&chan.remote_constraints),
+        "alias_scids": chan.alias_scids,
+        "zero_conf": chan.zero_conf,
+        "zero_conf_confirmed_scid": chan.zero_conf_confirmed_scid,
+    })
+}
+
+fn channel_close_json(close: &ChannelCloseSummary) -> Value {
+    json!({
+        "channel_point": close.channel_point,
+        "chan_id": close.chan_id,
+        "chain_hash": close.chain_hash,
+        "closing_tx_hash": close.closing_tx_hash,
+        "remote_pubkey": close.remote_pubkey,
+        "capacity": close.capacity,
+        "close_height": close.close_height,
+        "settled_balance": close.settled_balance,
+        "time_locked_balance": close.time_locked_balance,
+        "close_type": close.close_type().as_str_name(),
+        "open_initiator": close.open_initiator().as_str_name(),
+        "close_initiator": close.close_initiator().as_str_name(),
+        "resolutions": close.resolutions.iter().map(|resolution| json!({
+            "resolution_type": resolution.resolution_type().as_str_name(),
+            "outcome": resolution.outcome().as_str_name(),
+            "outpoint": resolution.outpoint.as_ref().map(|outpoint| json!({
+                "txid_str": outpoint.txid_str,
+                "output_index": outpoint.output_index,
+            })),
+            "amount_sat": resolution.amount_sat,
+            "sweep_txid": resolution.sweep_txid,
+        })).collect::<Vec<_>>(),
+        "alias_scids": close.alias_scids,
+        "zero_conf_confirmed_scid": close.zero_conf_confirmed_scid,
+    })
+}
+
+/// Converts an update of LND's `SubscribeChannelEvents` stream.
+pub fn lnd_channel_event(update: &ChannelEventUpdate) -> RawPayload {
+    let channel = match &update.channel {
+        Some(EventChannel::OpenChannel(chan)) => json!({ "open_channel": channel_json(chan) }),
+        Some(EventChannel::ClosedChannel(close)) => {
+            json!({ "closed_channel": channel_close_json(close) })
+        }
+        Some(EventChannel::ActiveChannel(point)) => {
+            json!({ "active_channel": channel_point_json(point) })
+        }
+        Some(EventChannel::InactiveChannel(point)) => {
+            json!({ "inactive_channel": channel_point_json(point) })
+        }
+        Some(EventChannel::FullyResolvedChannel(point)) => {
+            json!({ "fully_resolved_channel": channel_point_json(point) })
+        }
+        Some(EventChannel::PendingOpenChannel(pending)) => json!({
+            "pending_open_channel": {
+                "txid": hex::encode(&pending.txid),
+                "output_index": pending.output_index,
+            }
+        }),
+        None => json!({}),
+    };
+
+    let mut message = channel;
+    message["type"] = Value::String(update.r#type().as_str_name().to_string());
+    RawPayload {
+        source: "lnd_channel_event",
+        message,
+    }
+}
+
+/// Converts an invoice of LND's `SubscribeInvoices` stream.
+#[allow(deprecated)]
+pub fn lnd_invoice(invoice: &Invoice) -> RawPayload {
+    let message = json!({
+        "memo": invoice.memo,
+        "r_preimage": hex::encode(&invoice.r_preimage),
+        "r_hash": hex::encode(&invoice.r_hash),
+        "value": invoice.value,
+        "value_msat": invoice.value_msat,
+        "settled": invoice.settled,
+        "creation_date": invoice.creation_date,
+        "settle_date": invoice.settle_date,
+        "payment_request": invoice.payment_request,
+        "description_hash": hex::encode(&invoice.description_hash),
+        "expiry": invoice.expiry,
+        "fallback_addr": invoice.fallback_addr,
+        "cltv_expiry": invoice.cltv_expiry,
+        "route_hints": invoice.route_hints.iter().map(|hint| json!({
+            "hop_hints": hint.hop_hints.iter().map(|hop| json!({
+                "node_id": hop.node_id,
+                "chan_id": hop.chan_id,
+                "fee_base_msat": hop.fee_base_msat,
+                "fee_proportional_millionths": hop.fee_proportional_millionths,
+                "cltv_expiry_delta": hop.cltv_expiry_delta,
+            })).collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+        "private": invoice.private,
+        "add_index": invoice.add_index,
+        "settle_index": invoice.settle_index,
+        "amt_paid": invoice.amt_paid,
+        "amt_paid_sat": invoice.amt_paid_sat,
+        "amt_paid_msat": invoice.amt_paid_msat,
+        "state": invoice.state().as_str_name(),
+        "htlcs": invoice.htlcs.iter().map(|htlc| json!({
+            "chan_id": htlc.chan_id,
+            "htlc_index": htlc.htlc_index,
+            "amt_msat": htlc.amt_msat,
+            "accept_height": htlc.accept_height,
+            "accept_time": htlc.accept_time,
+            "resolve_time": htlc.resolve_time,
+            "expiry_height": htlc.expiry_height,
+            "state": htlc.state().as_str_name(),
+            "custom_records": htlc.custom_records.iter()
+                .map(|(key, value)| (key.to_string(), Value::String(hex::encode(value))))
+                .collect::<serde_json::Map<_, _>>(),
+            "mpp_total_amt_msat": htlc.mpp_total_amt_msat,
+            "amp": htlc.amp.as_ref().map(|amp| json!({
+                "root_share": hex::encode(&amp.root_share),
+                "set_id": hex::encode(&amp.set_id),
+                "child_index": amp.child_index,
+                "hash": hex::encode(&amp.hash),
+                "preimage": hex::encode(&amp.preimage),
+            })),
+        })).collect::<Vec<_>>(),
+        "features": invoice.features.iter()
+            .map(|(bit, feature)| (bit.to_string(), json!({
+                "name": feature.name,
+                "is_required": feature.is_required,
+                "is_known": feature.is_known,
+            })))
+            .collect::<serde_json::Map<_, _>>(),
+        "is_keysend": invoice.is_keysend,
+        "payment_addr": hex::encode(&invoice.payment_addr),
+        "is_amp": invoice.is_amp,
+        "amp_invoice_state": invoice.amp_invoice_state.iter()
+            .map(|(set_id, state)| (set_id.clone(), json!({
+                "state": state.state().as_str_name(),
+                "settle_index": state.settle_index,
+                "settle_time": state.settle_time,
+                "amt_paid_msat": state.amt_paid_msat,
+            })))
+            .collect::<serde_json::Map<_, _>>(),
+    });
+
+    RawPayload {
+        source: "lnd_invoice",
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic_lnd::lnrpc::InvoiceHtlc;
+
+    #[test]
+    fn converts_invoices_with_htlcs() {
+        let invoice = Invoice {
+            memo: "coffee".to_string(),
+            r_hash: vec![0xab; 32],
+            value_msat: 21_000,
+            state: 1,
+            htlcs: vec![InvoiceHtlc {
+                chan_id: 42,
+                amt_msat: 21_000,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let raw = lnd_invoice(&invoice);
+
+        assert_eq!(raw.source, "lnd_invoice");
+        assert_eq!(raw.message["state"], "SETTLED");
+        assert_eq!(raw.message["r_hash"], "ab".repeat(32));
+        assert_eq!(raw.message["htlcs"][0]["chan_id"], 42);
+        assert_eq!(raw.message["htlcs"][0]["state"], "ACCEPTED");
+    }
+
+    #[test]
+    fn converts_channel_updates() {
+        let update = ChannelEventUpdate {
+            channel: Some(EventChannel::InactiveChannel(ChannelPoint {
+                funding_txid: Some(FundingTxid::FundingTxidStr("ff".repeat(32))),
+                output_index: 1,
+            })),
+            r#type: 3,
+        };
+
+        let raw = lnd_channel_event(&update);
+
+        assert_eq!(raw.message["type"], "INACTIVE_CHANNEL");
+        assert_eq!(raw.message["inactive_channel"]["output_index"], 1);
+    }
+}
@@ -1,4 +1,5 @@
-use crate::errors::LightningError;
+use crate::error::LightningError;
+use crate::types::NodeInfo;
 use serde::Deserialize;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
@@ -52,6 +53,27 @@ impl PriceConverter {
         self.get_btc_price().await
     }
 
+    /// BTC price for valuing a node's amounts, `None` when the node isn't on
+    /// mainnet and its coins have no fiat value.
+    pub async fn fetch_node_price(&self, info: &NodeInfo) -> Result<Option<f64>, LightningError> {
+        if !info.has_fiat_value() {
+            return Ok(None);
+        }
+        self.get_btc_price().await.map(Some)
+    }
+
+    /// Convert a node's sats to USD, `None` when the node isn't on mainnet.
+    pub async fn node_sats_to_usd(
+        &self,
+        info: &NodeInfo,
+        sats: u64,
+    ) -> Result<Option<f64>, LightningError> {
+        Ok(self
+            .fetch_node_price(info)
+            .await?
+            .map(|btc_price| Self::sats_to_usd_with_price(sats, btc_price)))
+    }
+
     async fn get_btc_price(&self) -> Result<f64, LightningError> {
         // Check cache first (read lock)
         if let Some(cached_price) = self.check_cache().await {
@@ -107,3 +129,9 @@ impl PriceConverter {
         });
     }
 }
+
+impl Default for PriceConverter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,215 @@
+//! SOCKS5 tunnelling for node connections.
+//!
+//! Neither `tonic_lnd` nor the CLN gRPC channel accept a custom connector, so nodes behind
+//! a proxy (typically Tor hidden services) are reached through a loopback forwarder that
+//! relays every accepted connection over SOCKS5 to the real node address.
+
+use crate::error::LightningError;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{LazyLock, Mutex, OnceLock};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const SOCKS_VERSION: u8 = 0x05;
+const NO_AUTHENTICATION: u8 = 0x00;
+const CONNECT_COMMAND: u8 = 0x01;
+const ADDRESS_TYPE_IPV4: u8 = 0x01;
+const ADDRESS_TYPE_DOMAIN: u8 = 0x03;
+const ADDRESS_TYPE_IPV6: u8 = 0x04;
+
+/// Proxy for `.onion` addresses of connections without their own.
+static DEFAULT_PROXY: OnceLock<String> = OnceLock::new();
+
+/// Loopback forwarders keyed by `(proxy, target)` so repeated connections reuse one listener.
+static FORWARDERS: LazyLock<Mutex<HashMap<(String, String), SocketAddr>>> =
+    LazyLock::new(Default::default);
+
+/// Sets the proxy `.onion` addresses are reached through when their connection
+/// has none, e.g. a local Tor daemon. Later calls keep the first proxy.
+pub fn set_default_proxy(proxy: String) {
+    let _ = DEFAULT_PROXY.set(proxy);
+}
+
+/// Resolves the proxy for a node address and, if one applies, returns the loopback
+/// address to connect to instead.
+pub async fn proxied_address(
+    address: String,
+    explicit: Option<&str>,
+) -> Result<String, LightningError> {
+    match resolve_proxy(&address, explicit) {
+        Some(proxy) => route_through_proxy(&address, &proxy).await,
+        None => Ok(address),
+    }
+}
+
+/// Picks the proxy for a node: the connection's own setting wins, otherwise the
+/// default proxy is used for `.onion` addresses.
+pub fn resolve_proxy(address: &str, explicit: Option<&str>) -> Option<String> {
+    if let Some(proxy) = explicit.filter(|proxy| !proxy.trim().is_empty()) {
+        return Some(proxy.trim().to_string());
+    }
+
+    let (_, host, _) = split_address(address).ok()?;
+    if !host.ends_with(".onion") {
+        return None;
+    }
+
+    DEFAULT_PROXY.get().cloned()
+}
+
+/// Returns an address equivalent to `address` that reaches the node through `proxy`.
+///
+/// The scheme is preserved so TLS is still negotiated end-to-end with the node.
+pub async fn route_through_proxy(address: &str, proxy: &str) -> Result<String, LightningError> {
+    let (scheme, host, port) = split_address(address)?;
+    let target = format!("{host}:{port}");
+    let key = (proxy.to_string(), target.clone());
+
+    let existing = FORWARDERS
+        .lock()
+        .ok()
+        .and_then(|forwarders| forwarders.get(&key).copied());
+
+    let local_addr = match existing {
+        Some(local_addr) => local_addr,
+        None => {
+            let local_addr = spawn_forwarder(proxy.to_string(), host, port).await?;
+            if let Ok(mut forwarders) = FORWARDERS.lock() {
+                forwarders.insert(key, local_addr);
+            }
+            tracing::info!("Routing {} through SOCKS5 proxy {}", target, proxy);
+            local_addr
+        }
+    };
+
+    Ok(format!("{scheme}://{local_addr}"))
+}
+
+/// Splits `scheme://host:port` into its parts, defaulting the scheme to https.
+fn split_address(address: &str) -> Result<(String, String, u16), LightningError> {
+    let (scheme, rest) = address.split_once("://").unwrap_or(("https", address));
+    let authority = rest.split('/').next().unwrap_or(rest);
+    let (host, port) = authority.rsplit_once(':').ok_or_else(|| {
+        LightningError::ValidationError(format!("Node address {address} is missing a port"))
+    })?;
+    let port = port
+        .parse::<u16>()
+        .map_err(|e| LightningError::ValidationError(format!("Invalid port in {address}: {e}")))?;
+
+    Ok((
+        scheme.to_string(),
+        host.trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string(),
+        port,
+    ))
+}
+
+/// Binds a loopback listener and relays every accepted connection through the proxy.
+async fn spawn_forwarder(
+    proxy: String,
+    host: String,
+    port: u16,
+) -> Result<SocketAddr, LightningError> {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).await.map_err(|e| {
+        LightningError::ConnectionError(format!("Cannot start proxy forwarder: {e}"))
+    })?;
+    let local_addr = listener.local_addr().map_err(|e| {
+        LightningError::ConnectionError(format!("Cannot start proxy forwarder: {e}"))
+    })?;
+
+    tokio::spawn(async move {
+        loop {
+            let (mut inbound, _) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    tracing::error!("Proxy forwarder for {}:{} stopped: {}", host, port, e);
+                    break;
+                }
+            };
+
+            let proxy = proxy.clone();
+            let host = host.clone();
+            tokio::spawn(async move {
+                match connect(&proxy, &host, port).await {
+                    Ok(mut outbound) => {
+                        if let Err(e) =
+                            tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await
+                        {
+                            tracing::debug!(
+                                "Proxied connection to {}:{} closed: {}",
+                                host,
+                                port,
+                                e
+                            );
+                        }
+                    }
+                    Err(e) => {
+                        tracing::warn!("SOCKS5 connect to {}:{} failed: {}", host, port, e);
+                    }
+                }
+            });
+        }
+    });
+
+    Ok(local_addr)
+}
+
+/// Opens a TCP stream to `host:port` through an unauthenticated SOCKS5 proxy.
+///
+/// The hostname is forwarded unresolved so `.onion` addresses are resolved by the proxy.
+async fn connect(proxy: &str, host: &str, port: u16) -> std::io::Result<TcpStream> {
+    let mut stream = TcpStream::connect(proxy).await?;
+
+    stream
+        .write_all(&[SOCKS_VERSION, 1, NO_AUTHENTICATION])
+        .await?;
+    let mut method = [0u8; 2];
+    stream.read_exact(&mut method).await?;
+    if method != [SOCKS_VERSION, NO_AUTHENTICATION] {
+        return Err(socks_error(
+            "proxy requires an unsupported authentication method",
+        ));
+    }
+
+    let host_bytes = host.as_bytes();
+    let host_len = u8::try_from(host_bytes.len())
+        .map_err(|_| socks_error("hostname is too long for SOCKS5"))?;
+
+    let mut request = vec![
+        SOCKS_VERSION,
+        CONNECT_COMMAND,
+        0x00,
+        ADDRESS_TYPE_DOMAIN,
+        host_len,
+    ];
+    request.extend_from_slice(host_bytes);
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0x00 {
+        return Err(socks_error(&format!(
+            "proxy refused connection (reply code {})",
+            reply[1]
+        )));
+    }
+
+    // Drain the bound address the proxy reports; it is not needed.
+    let bound_len = match reply[3] {
+        ADDRESS_TYPE_IPV4 => 4,
+        ADDRESS_TYPE_IPV6 => 16,
+        ADDRESS_TYPE_DOMAIN => stream.read_u8().await? as usize,
+        _ => return Err(socks_error("proxy replied with an unknown address type")),
+    };
+    let mut bound = vec![0u8; bound_len + 2];
+    stream.read_exact(&mut bound).await?;
+
+    Ok(stream)
+}
+
+fn socks_error(message: &str) -> std::io::Error {
+    std::io::Error::other(format!("SOCKS5: {message}"))
+}
@@ -0,0 +1,1155 @@
+//! Models of nodes, channels, payments and invoices, shared by both backends.
+//!
+//! Each backend maps its own RPC replies to these types, so callers handle LND
+//! and CLN nodes alike.
+
+use crate::amount::Amount;
+use crate::error::LightningError;
+use bitcoin::secp256k1::PublicKey;
+use bitcoin::{Network, OutPoint, Txid};
+use expanduser::expanduser;
+use lightning::ln::features::NodeFeatures;
+use lightning_invoice::Bolt11Invoice;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Represents a node id, either by its public key or alias.
+#[derive(Serialize, Debug, Clone)]
+pub enum NodeId {
+    /// The node's public key.
+    PublicKey(PublicKey),
+    /// The node's alias (human-readable name).
+    Alias(String),
+}
+
+impl NodeId {
+    /// Validates that the provided node id matches the one returned by the backend.
+    pub fn validate(&self, node_id: &PublicKey, alias: &mut String) -> Result<(), LightningError> {
+        match self {
+            NodeId::PublicKey(pk) => {
+                if pk != node_id {
+                    return Err(LightningError::ValidationError(format!(
+                        "The provided node id does not match the one returned by the backend ({pk} != {node_id})"
+                    )));
+                }
+            }
+            NodeId::Alias(a) => {
+                if a != alias {
+                    return Err(LightningError::ValidationError(format!(
+                        "The provided alias does not match the one returned by the backend ({a} != {alias})"
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for NodeId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                NodeId::PublicKey(pk) => pk.to_string(),
+                NodeId::Alias(a) => a.to_owned(),
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeInfo {
+    /// The node's public key.
+    pub pubkey: PublicKey,
+    /// A human-readable name for the node (may be empty).
+    pub alias: String,
+    /// The node's supported protocol features and capabilities.
+    #[serde(with = "node_features_serde")]
+    pub features: NodeFeatures,
+    /// The version string the node reports, e.g. `0.18.3-beta` or `v24.08.1`.
+    #[serde(default)]
+    pub version: Option<String>,
+    /// The Bitcoin network the node runs on, e.g. `bitcoin`, `testnet` or `signet`.
+    #[serde(default)]
+    pub network: Option<String>,
+}
+
+impl NodeInfo {
+    /// Whether the node's coins have a fiat value, which only mainnet coins do.
+    /// Nodes whose network is unknown are taken to be on mainnet.
+    pub fn has_fiat_value(&self) -> bool {
+        self.network
+            .as_deref()
+            .is_none_or(|network| network == Network::Bitcoin.to_string())
+    }
+}
+
+impl Display for NodeInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let pk = self.pubkey.to_string();
+        let pk_summary = format!("{}...{}", &pk[..6], &pk[pk.len() - 6..]);
+        if self.alias.is_empty() {
+            write!(f, "{pk_summary}")
+        } else {
+            write!(f, "{}({})", self.alias, pk_summary)
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChannelDetails {
+    pub channel_id: ShortChannelID,
+    pub local_balance: Amount,
+    pub remote_balance: Amount,
+    pub capacity: Amount,
+    pub active: Option<bool>,
+    pub private: bool,
+    pub remote_pubkey: PublicKey,
+    pub commit_fee: Option<Amount>,
+    pub local_chan_reserve: Option<Amount>,
+    pub remote_chan_reserve: Option<Amount>,
+    pub num_updates: Option<u64>,
+    pub total_sent: Option<Amount>,
+    pub total_received: Option<Amount>,
+    pub channel_age_blocks: Option<u32>,
+    pub opening_cost: Option<Amount>,
+    pub initiator: Option<bool>,
+    pub txid: Option<Txid>,
+    pub vout: Option<u32>,
+    pub node1_policy: Option<NodePolicy>,
+    pub node2_policy: Option<NodePolicy>,
+    /// Confirmation status and fee of the funding transaction, from the explorer.
+    pub funding_tx: Option<OnchainTxStatus>,
+}
+
+/// Confirmation status and fee data of a transaction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OnchainTxStatus {
+    pub txid: Txid,
+    pub confirmed: bool,
+    pub block_height: Option<u32>,
+    pub block_time: Option<u64>,
+    /// Depth below the chain tip, absent if unconfirmed or the tip is unknown
+    pub confirmations: Option<u32>,
+    pub fee_sat: u64,
+    pub vsize: u64,
+    pub fee_rate_sat_vb: f64,
+}
+
+impl OnchainTxStatus {
+    /// Sets the confirmations from the chain tip, when both are known.
+    pub fn with_tip(mut self, tip_height: Option<u32>) -> Self {
+        self.confirmations = match (self.confirmed, self.block_height, tip_height) {
+            (true, Some(height), Some(tip)) => Some(tip.saturating_sub(height) + 1),
+            _ => None,
+        };
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelSummary {
+    pub chan_id: ShortChannelID,
+    pub alias: Option<String>,
+    pub channel_state: ChannelState,
+    pub private: bool,
+    pub remote_balance: Amount,
+    pub local_balance: Amount,
+    pub capacity: Amount,
+    pub last_update: Option<u64>,
+    pub uptime: Option<u64>,
+    pub remote_pubkey: Option<PublicKey>,
+    /// Funding outpoint, serialized as `txid:vout`.
+    pub channel_point: Option<OutPoint>,
+}
+
+/// A node's offer, gossiped as a liquidity ad, to contribute to dual-funded
+/// channels opened to it in exchange for a lease fee.
+#[derive(Debug, Clone, Serialize)]
+pub struct LiquidityAd {
+    pub pubkey: PublicKey,
+    pub alias: Option<String>,
+    /// Flat part of the lease fee.
+    pub lease_fee_base_msat: u64,
+    /// Part of the lease fee proportional to the leased amount, in basis points.
+    pub lease_fee_basis: u32,
+    /// Weight of the leaser's funding inputs and outputs, whose on-chain fees the opener pays.
+    pub funding_weight: u32,
+    /// Highest routing fees the leaser may charge on the channel during the lease.
+    pub channel_fee_max_base_msat: u64,
+    pub channel_fee_max_proportional_thousandths: u32,
+    /// The terms in the compact, hex-encoded form opening a channel under them takes.
+    pub compact_lease: String,
+}
+
+/// How to open a channel funded by both peers.
+#[derive(Debug, Clone)]
+pub struct DualFundRequest {
+    pub pubkey: PublicKey,
+    /// `host:port` to reach the peer at, if it isn't connected yet.
+    pub address: Option<String>,
+    /// Our contribution to the channel.
+    pub amount_sat: u64,
+    /// Contribution asked of the peer, leased under its liquidity ad.
+    pub request_amount_sat: u64,
+    /// Terms of the peer's ad, required when asking for a contribution.
+    pub compact_lease: Option<String>,
+    /// Funding transaction fee rate, the node's estimate if absent.
+    pub sat_per_vbyte: Option<u32>,
+    pub private: bool,
+}
+
+/// A channel whose funding transaction was broadcast.
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenedChannel {
+    /// Funding outpoint, serialized as `txid:vout`.
+    pub channel_point: OutPoint,
+    /// Hex-encoded channel id, known before the channel has a short channel id.
+    pub channel_id: String,
+}
+
+/// An HTLC still outstanding on one of the node's channels.
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingHtlc {
+    pub channel_id: ShortChannelID,
+    pub remote_pubkey: Option<PublicKey>,
+    /// Whether the peer offered the HTLC to us.
+    pub incoming: bool,
+    pub amount_msat: u64,
+    pub payment_hash: String,
+    /// Block height at which the HTLC times out.
+    pub expiry_height: u32,
+}
+
+/// The node's outstanding HTLCs and the chain tip they were listed at.
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingHtlcs {
+    pub block_height: u32,
+    pub htlcs: Vec<PendingHtlc>,
+}
+
+/// A channel the peer closed by broadcasting a revoked commitment, which the
+/// node answers with justice transactions sweeping the channel's funds.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelBreach {
+    pub channel_id: ShortChannelID,
+    pub channel_point: String,
+    pub remote_pubkey: String,
+    pub capacity_sat: u64,
+    /// The revoked commitment the peer broadcast.
+    pub breach_txid: String,
+    /// Transactions that swept the breached outputs, as far as the node reports them.
+    pub justice_txids: Vec<String>,
+    pub close_height: u32,
+    pub settled_balance_sat: u64,
+    pub time_locked_balance_sat: u64,
+    /// Funds the justice transactions haven't swept back yet.
+    pub limbo_balance_sat: u64,
+}
+
+/// An on-chain transaction of the node's wallet and the label the node keeps for it.
+#[derive(Debug, Clone, Serialize)]
+pub struct TransactionLabel {
+    pub txid: Txid,
+    pub label: Option<String>,
+}
+
+/// A node the node has a connection or channels with.
+#[derive(Debug, Clone, Serialize)]
+pub struct PeerSummary {
+    pub pubkey: PublicKey,
+    /// Address the connection is made over, when connected.
+    pub address: Option<String>,
+    pub connected: bool,
+    /// Round trip of the node's last ping to the peer, for nodes that report it.
+    pub ping_time_us: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CustomInvoice {
+    pub memo: String,
+    pub payment_hash: String,
+    pub payment_preimage: String,
+    pub value: Amount,
+    /// Amount received, which zero-amount invoices leave to the payer
+    pub amount_paid: Option<Amount>,
+    pub creation_date: Option<i64>,
+    pub settle_date: Option<i64>,
+    pub payment_request: String,
+    /// Seconds after `creation_date` the invoice can be paid for
+    pub expiry: Option<u64>,
+    /// Unix time the invoice expires at
+    pub expires_at: Option<u64>,
+    pub state: InvoiceStatus,
+    pub is_keysend: Option<bool>,
+    pub is_amp: Option<bool>,
+    pub payment_addr: Option<String>,
+    pub htlcs: Option<Vec<InvoiceHtlc>>,
+    pub features: Option<HashMap<u32, Feature>>,
+}
+
+/// Represents a node's routing policy for forwarding payments
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NodePolicy {
+    pub pubkey: PublicKey,
+    pub fee_base_msat: u64,
+    pub fee_rate_milli_msat: u64,
+    pub min_htlc_msat: u64,
+    pub max_htlc_msat: Option<u64>,
+    pub time_lock_delta: u16,
+    pub disabled: bool,
+    pub last_update: Option<u64>,
+}
+
+impl Display for NodePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Policy(pubkey: {}, fee: {}+{}ppm, min_htlc: {}msat{})",
+            self.pubkey,
+            self.fee_base_msat,
+            self.fee_rate_milli_msat,
+            self.min_htlc_msat,
+            match self.max_htlc_msat {
+                Some(max) => format!(", max_htlc: {max}msat"),
+                None => String::new(),
+            }
+        )
+    }
+}
+
+/// A node as seen in the node's view of the channel graph.
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphNode {
+    pub pubkey: PublicKey,
+    pub alias: String,
+    /// Hex color, e.g. `#3399ff`
+    pub color: Option<String>,
+    /// Unix timestamp of the node's last announcement
+    pub last_update: Option<u64>,
+    pub addresses: Vec<String>,
+    /// Channel count and capacity, when the implementation reports them
+    pub num_channels: Option<u32>,
+    pub total_capacity_sat: Option<u64>,
+}
+
+/// Fees one side of a channel announces for forwarding through it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct EdgeFee {
+    pub base_fee_msat: u64,
+    pub fee_rate_ppm: u64,
+}
+
+/// A public channel in the node's view of the channel graph.
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphEdge {
+    pub channel_id: ShortChannelID,
+    /// The endpoint with the lexicographically smaller pubkey
+    pub node1: PublicKey,
+    pub node2: PublicKey,
+    pub capacity_sat: u64,
+    /// Fees of each side; `None` while that side has it disabled or never announced it
+    pub node1_fee: Option<EdgeFee>,
+    pub node2_fee: Option<EdgeFee>,
+    /// Unix time of each side's latest channel update; `None` if it never sent one
+    pub node1_last_update: Option<u64>,
+    pub node2_last_update: Option<u64>,
+}
+
+/// One direction of a channel, as CLN's `listchannels` reports them.
+#[derive(Debug, Clone)]
+pub struct GraphDirection {
+    pub channel_id: ShortChannelID,
+    pub source: PublicKey,
+    pub destination: PublicKey,
+    pub capacity_sat: u64,
+    /// `None` when the direction is disabled
+    pub fee: Option<EdgeFee>,
+    pub last_update: u64,
+}
+
+impl GraphEdge {
+    /// Pairs the directions of each channel into one edge, ordered by channel id.
+    pub fn from_directions(directions: impl IntoIterator<Item = GraphDirection>) -> Vec<Self> {
+        let mut edges: HashMap<u64, GraphEdge> = HashMap::new();
+        for direction in directions {
+            let (node1, node2) = if direction.source.serialize() < direction.destination.serialize()
+            {
+                (direction.source, direction.destination)
+            } else {
+                (direction.destination, direction.source)
+            };
+            edges
+                .entry(direction.channel_id.0)
+                .or_insert_with(|| GraphEdge {
+                    channel_id: direction.channel_id,
+                    node1,
+                    node2,
+                    capacity_sat: direction.capacity_sat,
+                    node1_fee: None,
+                    node2_fee: None,
+                    node1_last_update: None,
+                    node2_last_update: None,
+                })
+                .set_direction(&direction);
+        }
+
+        let mut edges: Vec<GraphEdge> = edges.into_values().collect();
+        edges.sort_unstable_by_key(|edge| edge.channel_id.0);
+        edges
+    }
+
+    /// Starts an edge from the first direction seen of its channel.
+    pub fn from_direction(direction: &GraphDirection) -> Self {
+        GraphEdge::from_directions([direction.clone()]).remove(0)
+    }
+
+    /// Replaces the fees and update time of the side `direction` comes from.
+    pub fn set_direction(&mut self, direction: &GraphDirection) {
+        if direction.source == self.node1 {
+            self.node1_fee = direction.fee;
+            self.node1_last_update = Some(direction.last_update);
+        } else {
+            self.node2_fee = direction.fee;
+            self.node2_last_update = Some(direction.last_update);
+        }
+    }
+}
+
+/// A node's whole view of the channel graph.
+#[derive(Debug, Clone, Default)]
+pub struct NetworkGraph {
+    pub nodes: Vec<GraphNode>,
+    /// Public channels only
+    pub edges: Vec<GraphEdge>,
+}
+
+/// A change to a node's view of the channel graph, as its gossip reports it.
+#[derive(Debug, Clone)]
+pub enum GraphUpdate {
+    /// A node announced itself or changed its announcement
+    Node(GraphNode),
+    /// A channel was announced or one of its sides changed its policy
+    Channel(GraphDirection),
+    /// A channel was closed
+    Closed(ShortChannelID),
+}
+
+/// Represents a short channel ID.
+#[derive(Debug, Clone, Serialize, Copy, Deserialize)]
+pub struct ShortChannelID(pub u64);
+
+/// Represents a log entry from the Lightning Network node.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NodeLog {
+    pub timestamp: String,
+    pub level: Option<LogLevel>,
+    pub message: String,
+    pub subsystem: Option<String>,
+}
+
+// Aggregated metrics and statistics about a Lightning Network node.
+///
+/// Provides a comprehensive view of node performance, resource usage,
+/// and operational health for monitoring and alerting purposes.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NodeMetrics {
+    pub num_channels: u32,
+    pub num_active_channels: u32,
+    pub num_peers: u32,
+    pub block_height: u32,
+    pub uptime_seconds: u64,
+    pub total_capacity: u64,
+    pub total_local_balance: u64,
+    pub total_remote_balance: u64,
+    pub memory_usage: Option<u64>,
+    pub cpu_usage: Option<u64>,
+    pub disk_usage: Option<u64>,
+}
+
+/// Represents a Lightning Network payment initiated or received by the node.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaymentDetails {
+    pub state: PaymentState,
+    pub payment_type: PaymentType,
+    pub amount: Amount,
+    /// `None` when the node isn't on mainnet, where amounts have no fiat value
+    pub amount_usd: Option<f64>,
+    pub routing_fee: Option<Amount>,
+    pub network: Option<String>,
+    pub description: Option<String>,
+    pub creation_time: Option<u64>,
+    pub invoice: Option<String>,
+    pub payment_hash: String,
+    pub destination_pubkey: Option<PublicKey>,
+    pub completed_at: Option<u64>,
+    pub htlcs: Vec<PaymentHtlc>,
+}
+
+/// Represents a Lightning Network payment initiated or received by the node.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PaymentSummary {
+    pub state: PaymentState,
+    pub payment_type: PaymentType,
+    pub amount: Amount,
+    /// `None` when the node isn't on mainnet, where amounts have no fiat value
+    pub amount_usd: Option<f64>,
+    pub routing_fee: Option<Amount>,
+    pub creation_time: Option<u64>,
+    pub invoice: Option<String>,
+    pub payment_hash: String,
+    pub completed_at: Option<u64>,
+}
+
+/// Position of a payment in the unified payments list.
+///
+/// Every backend sorts the list by `(creation_time, payment_hash)` descending,
+/// payments without a creation time last, so payments created in the same
+/// second keep their order from one request to the next. Handed to clients as
+/// an opaque URL-safe base64 string.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PaymentCursor {
+    pub creation_time: u64,
+    pub payment_hash: String,
+}
+
+impl From<&PaymentSummary> for PaymentCursor {
+    fn from(payment: &PaymentSummary) -> Self {
+        Self {
+            creation_time: payment.creation_time.unwrap_or(0),
+            payment_hash: payment.payment_hash.to_lowercase(),
+        }
+    }
+}
+
+impl Display for PaymentCursor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        use base64::Engine;
+
+        let raw = format!("{}|{}", self.creation_time, self.payment_hash);
+        write!(
+            f,
+            "{}",
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(raw)
+        )
+    }
+}
+
+impl FromStr for PaymentCursor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use base64::Engine;
+
+        let invalid = || format!("Invalid payment cursor: {s}");
+        let raw = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(s)
+            .map_err(|_| invalid())?;
+        let raw = String::from_utf8(raw).map_err(|_| invalid())?;
+        let (creation_time, payment_hash) = raw.split_once('|').ok_or_else(invalid)?;
+
+        Ok(Self {
+            creation_time: creation_time.parse().map_err(|_| invalid())?,
+            payment_hash: payment_hash.to_string(),
+        })
+    }
+}
+
+/// Sorts payments into the order of the unified payments list, newest first.
+pub fn sort_payments(payments: &mut [PaymentSummary]) {
+    payments.sort_by_cached_key(|payment| Reverse(PaymentCursor::from(payment)));
+}
+
+/// A settled HTLC forwarded through the node.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ForwardSummary {
+    /// Channel the HTLC arrived on.
+    pub chan_id_in: Option<ShortChannelID>,
+    /// Channel the HTLC left on.
+    pub chan_id_out: Option<ShortChannelID>,
+    pub amount_in_msat: u64,
+    pub amount_out_msat: u64,
+    pub fee_msat: u64,
+    /// Unix timestamp (seconds) at which the forward was settled.
+    pub resolved_at: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaymentHtlc {
+    pub routes: Vec<Route>,
+    pub attempt_id: u64,
+    pub attempt_time: Option<u64>,
+    pub resolve_time: Option<u64>,
+    pub failure_reason: Option<String>,
+    pub failure_code: Option<u16>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InvoiceHtlc {
+    pub chan_id: Option<u64>,
+    pub htlc_index: Option<u64>,
+    pub amt_msat: Option<u64>,
+    pub accept_time: Option<i64>,
+    pub resolve_time: Option<i64>,
+    pub expiry_height: Option<u32>,
+    pub mpp_total_amt_msat: Option<u64>,
+}
+
+impl InvoiceHtlc {
+    /// The payment of a paid CLN invoice.
+    ///
+    /// CLN's `listinvoices` doesn't list the HTLCs an invoice was paid with, so
+    /// the parts of a multi-part payment are reported as one HTLC carrying the
+    /// amount received.
+    pub fn cln_paid(amount_received_msat: u64, paid_at: Option<u64>) -> Self {
+        let paid_at = paid_at.and_then(|paid_at| i64::try_from(paid_at).ok());
+        Self {
+            chan_id: None,
+            htlc_index: None,
+            amt_msat: Some(amount_received_msat),
+            accept_time: paid_at,
+            resolve_time: paid_at,
+            expiry_height: None,
+            mpp_total_amt_msat: Some(amount_received_msat),
+        }
+    }
+}
+
+/// Fields of an invoice read from its BOLT11 payment request, for backends
+/// that don't report them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bolt11Fields {
+    /// Unix time the invoice was created at
+    pub creation_date: i64,
+    /// Seconds after creation the invoice can be paid for
+    pub expiry: u64,
+    pub payment_addr: String,
+}
+
+impl Bolt11Fields {
+    /// Decodes a payment request, `None` if it isn't a valid BOLT11 invoice.
+    pub fn parse(payment_request: &str) -> Option<Self> {
+        let invoice = Bolt11Invoice::from_str(payment_request).ok()?;
+        Some(Self {
+            creation_date: i64::try_from(invoice.duration_since_epoch().as_secs()).ok()?,
+            expiry: invoice.expiry_time().as_secs(),
+            payment_addr: hex::encode(invoice.payment_secret().0),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Feature {
+    pub name: Option<String>,
+    pub is_known: Option<bool>,
+    pub is_required: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Route {
+    pub total_time_lock: u32,
+    pub total_fees: Amount,
+    pub total_amt: Amount,
+    pub hops: Vec<Hop>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Hop {
+    pub pubkey: PublicKey,
+    pub chan_id: ShortChannelID,
+    pub amount_to_forward: Amount,
+    pub fee: Option<Amount>,
+    pub expiry: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Copy)]
+pub enum PaymentState {
+    Inflight,
+    Failed,
+    #[default]
+    Settled,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum PaymentType {
+    Outgoing,
+    Incoming,
+    Forwarded,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InvoiceStatus {
+    #[default]
+    Settled,
+    Open,
+    Expired,
+    Failed,
+}
+
+/// An invoice's state as the backend reports it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportedInvoiceState {
+    Open,
+    /// HTLCs paying a hold invoice are held, awaiting settlement
+    Accepted,
+    Settled,
+    Expired,
+    Canceled,
+}
+
+impl InvoiceStatus {
+    /// Resolves a reported state the same way for every backend.
+    ///
+    /// Backends notice expiries at different times: CLN marks invoices expired
+    /// on its next sweep and LND cancels them. An invoice that isn't settled
+    /// or held is therefore expired once `expires_at` has passed, and only
+    /// invoices canceled before that count as failed.
+    pub fn resolve(reported: ReportedInvoiceState, expires_at: Option<u64>, now: u64) -> Self {
+        let expired = expires_at.is_some_and(|expires_at| expires_at <= now);
+        match reported {
+            ReportedInvoiceState::Settled => InvoiceStatus::Settled,
+            ReportedInvoiceState::Accepted => InvoiceStatus::Open,
+            ReportedInvoiceState::Expired => InvoiceStatus::Expired,
+            ReportedInvoiceState::Open | ReportedInvoiceState::Canceled if expired => {
+                InvoiceStatus::Expired
+            }
+            ReportedInvoiceState::Open => InvoiceStatus::Open,
+            ReportedInvoiceState::Canceled => InvoiceStatus::Failed,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub enum ChannelState {
+    Opening, // funding tx not confirmed
+    #[default]
+    Active, // normal / available
+    Disabled, // temporarily disabled
+    Splicing, // capacity being adjusted, splice awaiting confirmation
+    Closing, // cooperative or force close initiated
+    Closed,  // channel is closed
+    Failed,  // failed or on-chain resolved
+}
+
+/// The severity level of a log entry.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum LogLevel {
+    Info,
+    Warn,
+    Error,
+    Unknown,
+}
+
+pub mod serde_node_id {
+    use super::*;
+    use std::str::FromStr;
+
+    use NodeId;
+    use bitcoin::secp256k1::PublicKey;
+
+    pub fn serialize<S>(id: &NodeId, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&match id {
+            NodeId::PublicKey(p) => p.to_string(),
+            NodeId::Alias(s) => s.to_string(),
+        })
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<NodeId, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        if let Ok(pk) = PublicKey::from_str(&s) {
+            Ok(NodeId::PublicKey(pk))
+        } else {
+            Ok(NodeId::Alias(s))
+        }
+    }
+}
+
+pub mod serde_address {
+    use super::*;
+
+    pub fn serialize<S>(address: &str, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(address)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<String, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        if s.starts_with("https://") || s.starts_with("http://") {
+            Ok(s)
+        } else {
+            Ok(format!("https://{s}"))
+        }
+    }
+}
+
+pub fn deserialize_path<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    Ok(expanduser(s)
+        .map_err(serde::de::Error::custom)?
+        .display()
+        .to_string())
+}
+
+mod node_features_serde {
+    use super::*;
+    pub fn serialize<S>(features: &NodeFeatures, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&features.to_string())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<NodeFeatures, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let flags = Vec::deserialize(deserializer)?;
+        Ok(NodeFeatures::from_le_bytes(flags))
+    }
+}
+
+impl ShortChannelID {
+    pub fn to_u64(&self) -> u64 {
+        self.0
+    }
+
+    /// Height of the block that confirmed the funding transaction (upper 3 bytes).
+    pub fn block_height(&self) -> u32 {
+        (self.0 >> 40) as u32
+    }
+
+    /// Number of blocks mined since the funding transaction confirmed, if it has
+    /// confirmed and the given tip is not behind the funding block.
+    pub fn age_blocks(&self, current_height: u32) -> Option<u32> {
+        match self.block_height() {
+            // Unconfirmed channels have no short channel id yet
+            0 => None,
+            funding_height => current_height.checked_sub(funding_height),
+        }
+    }
+
+    /// Formats the id as `BLOCKxTXxOUTPUT`, the notation CLN uses in its RPC.
+    pub fn to_block_format(&self) -> String {
+        format!(
+            "{}x{}x{}",
+            self.block_height(),
+            (self.0 >> 16) & 0xff_ffff,
+            self.0 & 0xffff
+        )
+    }
+}
+
+impl FromStr for ShortChannelID {
+    type Err = LightningError;
+
+    /// Accepts the numeric form used by LND (`869059488053346304`) or the
+    /// `BLOCKxTXxOUTPUT` form used by CLN and most explorers (`790409x1146x0`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || LightningError::Parse(format!("Invalid short channel id: {s}"));
+
+        if !s.contains('x') {
+            return s.parse::<u64>().map(Self).map_err(|_| invalid());
+        }
+
+        let parts: Vec<u64> = s
+            .split('x')
+            .map(|part| part.trim().parse::<u64>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid())?;
+        match parts[..] {
+            [block, tx, output] if block <= 0xff_ffff && tx <= 0xff_ffff && output <= 0xffff => {
+                Ok(Self((block << 40) | (tx << 16) | output))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl Display for ShortChannelID {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<u64> for ShortChannelID {
+    fn from(id: u64) -> Self {
+        Self(id)
+    }
+}
+
+impl From<ShortChannelID> for u64 {
+    fn from(id: ShortChannelID) -> u64 {
+        id.0
+    }
+}
+
+impl FromStr for PaymentState {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.to_lowercase().as_str() {
+            "inflight" => Ok(PaymentState::Inflight),
+            "failed" => Ok(PaymentState::Failed),
+            "settled" => Ok(PaymentState::Settled),
+            _ => Err(format!("Invalid payment state: {input}")),
+        }
+    }
+}
+
+impl Display for PaymentState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let state = match self {
+            PaymentState::Inflight => "inflight",
+            PaymentState::Failed => "failed",
+            PaymentState::Settled => "settled",
+        };
+        write!(f, "{state}")
+    }
+}
+
+impl PaymentState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentState::Inflight => "inflight",
+            PaymentState::Failed => "failed",
+            PaymentState::Settled => "settled",
+        }
+    }
+}
+
+impl FromStr for PaymentType {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.to_lowercase().as_str() {
+            "outgoing" => Ok(PaymentType::Outgoing),
+            "incoming" => Ok(PaymentType::Incoming),
+            "forwarded" => Ok(PaymentType::Forwarded),
+            _ => Err(format!("Invalid payment type: {input}")),
+        }
+    }
+}
+
+impl Display for PaymentType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let payment_type = match self {
+            PaymentType::Outgoing => "outgoing",
+            PaymentType::Incoming => "incoming",
+            PaymentType::Forwarded => "forwarded",
+        };
+        write!(f, "{payment_type}")
+    }
+}
+
+impl PaymentType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentType::Outgoing => "outgoing",
+            PaymentType::Incoming => "incoming",
+            PaymentType::Forwarded => "forwarded",
+        }
+    }
+}
+
+pub fn deserialize_payment_types<'de, D>(
+    deserializer: D,
+) -> Result<Option<Vec<PaymentType>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    let opt_string: Option<String> = Option::deserialize(deserializer)?;
+
+    match opt_string {
+        Some(s) if s.trim().is_empty() => Ok(None),
+        Some(s) => {
+            let payment_types = s
+                .split(',')
+                .map(|payment_type| payment_type.trim())
+                .filter(|payment_type| !payment_type.is_empty())
+                .map(|payment_type| {
+                    PaymentType::from_str(payment_type).map_err(|err| {
+                        Error::custom(format!("Invalid payment type '{payment_type}': {err}"))
+                    })
+                })
+                .collect::<Result<Vec<PaymentType>, _>>()?;
+
+            if payment_types.is_empty() {
+                Ok(None)
+            } else {
+                Ok(Some(payment_types))
+            }
+        }
+        None => Ok(None),
+    }
+}
+
+impl Display for InvoiceStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            InvoiceStatus::Settled => "settled",
+            InvoiceStatus::Open => "open",
+            InvoiceStatus::Expired => "expired",
+            InvoiceStatus::Failed => "failed",
+        };
+        write!(f, "{status}")
+    }
+}
+
+impl FromStr for InvoiceStatus {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.to_lowercase().as_str() {
+            "settled" => Ok(InvoiceStatus::Settled),
+            "open" => Ok(InvoiceStatus::Open),
+            "expired" => Ok(InvoiceStatus::Expired),
+            "failed" => Ok(InvoiceStatus::Failed),
+            _ => Err(format!("Invalid invoice status: {input}")),
+        }
+    }
+}
+
+impl Display for ChannelState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let state = match self {
+            ChannelState::Opening => "opening",
+            ChannelState::Active => "active",
+            ChannelState::Disabled => "disabled",
+            ChannelState::Splicing => "splicing",
+            ChannelState::Closing => "closing",
+            ChannelState::Closed => "closed",
+            ChannelState::Failed => "failed",
+        };
+        write!(f, "{state}")
+    }
+}
+
+impl FromStr for ChannelState {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.to_lowercase().as_str() {
+            "opening" => Ok(ChannelState::Opening),
+            "active" => Ok(ChannelState::Active),
+            "disabled" => Ok(ChannelState::Disabled),
+            "splicing" => Ok(ChannelState::Splicing),
+            "closing" => Ok(ChannelState::Closing),
+            "closed" => Ok(ChannelState::Closed),
+            "failed" => Ok(ChannelState::Failed),
+            _ => Err(format!("Invalid channel state: {input}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_invoice_fields_from_the_payment_request() {
+        // BOLT11 test vector, created at 1496314658 and valid for 60 seconds
+        let fields = Bolt11Fields::parse(
+            "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rh",
+        )
+        .unwrap();
+
+        assert_eq!(fields.creation_date, 1_496_314_658);
+        assert_eq!(fields.expiry, 60);
+        assert_eq!(fields.payment_addr, "11".repeat(32));
+        assert_eq!(Bolt11Fields::parse("lno1qgsq"), None);
+    }
+
+    fn payment(payment_hash: &str, creation_time: Option<u64>) -> PaymentSummary {
+        PaymentSummary {
+            state: PaymentState::Settled,
+            payment_type: PaymentType::Outgoing,
+            amount: Amount::from_sat(1_000),
+            amount_usd: Some(0.0),
+            routing_fee: None,
+            creation_time,
+            invoice: None,
+            payment_hash: payment_hash.to_string(),
+            completed_at: None,
+        }
+    }
+
+    #[test]
+    fn payments_with_equal_times_keep_their_order() {
+        let mut payments = vec![
+            payment("aa", Some(100)),
+            payment("cc", None),
+            payment("bb", Some(100)),
+            payment("dd", Some(50)),
+        ];
+        sort_payments(&mut payments);
+
+        let hashes: Vec<&str> = payments.iter().map(|p| p.payment_hash.as_str()).collect();
+        assert_eq!(hashes, vec!["bb", "aa", "dd", "cc"]);
+
+        let cursor = PaymentCursor::from(&payments[1]);
+        assert_eq!(cursor.to_string().parse::<PaymentCursor>(), Ok(cursor));
+        assert!("not a cursor".parse::<PaymentCursor>().is_err());
+    }
+
+    #[test]
+    fn unsettled_invoices_expire_on_every_backend() {
+        let resolve = |reported| InvoiceStatus::resolve(reported, Some(1_000), 1_000);
+
+        assert!(matches!(
+            resolve(ReportedInvoiceState::Open),
+            InvoiceStatus::Expired
+        ));
+        assert!(matches!(
+            resolve(ReportedInvoiceState::Canceled),
+            InvoiceStatus::Expired
+        ));
+        assert!(matches!(
+            resolve(ReportedInvoiceState::Settled),
+            InvoiceStatus::Settled
+        ));
+        assert!(matches!(
+            resolve(ReportedInvoiceState::Accepted),
+            InvoiceStatus::Open
+        ));
+        assert!(matches!(
+            InvoiceStatus::resolve(ReportedInvoiceState::Canceled, Some(1_001), 1_000),
+            InvoiceStatus::Failed
+        ));
+        assert!(matches!(
+            InvoiceStatus::resolve(ReportedInvoiceState::Open, None, 1_000),
+            InvoiceStatus::Open
+        ));
+    }
+
+    #[test]
+    fn channel_age_counts_blocks_since_funding() {
+        let channel_id: ShortChannelID = "800000x12x1".parse().unwrap();
+        assert_eq!(channel_id.block_height(), 800_000);
+        assert_eq!(channel_id.age_blocks(800_144), Some(144));
+        assert_eq!(channel_id.age_blocks(800_000), Some(0));
+
+        // Zero-conf aliases sit above the tip and unconfirmed channels have no id
+        assert_eq!(channel_id.age_blocks(799_999), None);
+        assert_eq!(ShortChannelID(0).age_blocks(800_000), None);
+    }
+}
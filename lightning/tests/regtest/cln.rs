@@ -0,0 +1,78 @@
+//! `ClnNode` against the regtest CLN node, with the LND node as counterparty.
+
+use crate::fixtures::{
+    AMOUNT_MSAT, cln, cln_invoice, cln_pay, eventually, lnd, lnd_invoice, lnd_pay, unique_label,
+};
+use bitcoin::Network;
+use nodegaze_lightning::LightningClient;
+use nodegaze_lightning::types::{InvoiceStatus, PaymentState};
+
+#[tokio::test]
+async fn reports_regtest_network() {
+    let node = cln().await;
+    assert_eq!(node.get_network().await.unwrap(), Network::Regtest);
+    node.get_wallet_balance().await.unwrap();
+    node.list_forwards(0).await.unwrap();
+}
+
+#[tokio::test]
+async fn lists_channel_with_lnd() {
+    let (node, peer) = (cln().await, lnd().await);
+    let peer_pubkey = peer.get_info().pubkey;
+
+    let channels = node.list_channels().await.unwrap();
+    let channel = channels
+        .iter()
+        .find(|channel| channel.remote_pubkey == Some(peer_pubkey))
+        .expect("CLN has a channel with the LND node");
+
+    let details = node.get_channel_info(&channel.chan_id).await.unwrap();
+    assert_eq!(details.remote_pubkey, peer_pubkey);
+    assert!(details.capacity_sat > 0);
+}
+
+#[tokio::test]
+async fn settles_invoice_paid_by_lnd() {
+    let (node, peer) = (cln().await, lnd().await);
+    let (bolt11, hash) = cln_invoice(&node, AMOUNT_MSAT, &unique_label("cln-invoice")).await;
+    lnd_pay(&peer, &bolt11).await;
+
+    let node = &node;
+    let invoice = eventually(|| async move {
+        let invoice = node.get_invoice_details(&hash).await.ok()?;
+        matches!(invoice.state, InvoiceStatus::Settled).then_some(invoice)
+    })
+    .await;
+    assert_eq!(invoice.value_msat, AMOUNT_MSAT);
+
+    let invoices = node.list_invoices().await.unwrap();
+    assert!(
+        invoices
+            .iter()
+            .any(|invoice| invoice.payment_hash == hex::encode(hash.0))
+    );
+}
+
+#[tokio::test]
+async fn records_payment_to_lnd() {
+    let (node, peer) = (cln().await, lnd().await);
+    let (payment_request, hash) =
+        lnd_invoice(&peer, AMOUNT_MSAT, &unique_label("cln-payment")).await;
+    cln_pay(&node, &payment_request).await;
+
+    let payments = node.list_payments().await.unwrap();
+    let payment = payments
+        .iter()
+        .find(|payment| payment.payment_hash == hex::encode(hash.0))
+        .expect("payment is listed");
+    assert!(matches!(payment.state, PaymentState::Settled));
+
+    let details = node.get_payment_details(&hash).await.unwrap();
+    assert_eq!(details.payment_hash, hex::encode(hash.0));
+}
+
+#[tokio::test]
+async fn opens_event_stream() {
+    let mut node = cln().await;
+    let _events = node.stream_events().await.unwrap();
+}
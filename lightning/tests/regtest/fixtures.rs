@@ -0,0 +1,167 @@
+//! Node connections and helpers shared by the regtest tests.
+//!
+//! The tests need one LND and one CLN node on regtest with a funded channel
+//! between them, both with enough balance to pay the other. A Polar network
+//! works, as does any docker setup exposing the gRPC ports. Every node is
+//! configured through `REGTEST_*` variables holding its public key, RPC address
+//! and the paths to its credential files.
+
+use bitcoin::secp256k1::PublicKey;
+use cln_grpc::pb::{Amount, AmountOrAny, InvoiceRequest, PayRequest, amount_or_any};
+use lightning::ln::PaymentHash;
+use nodegaze_lightning::types::NodeId;
+use nodegaze_lightning::{ClnConnection, ClnNode, LndConnection, LndNode};
+use std::future::Future;
+use std::str::FromStr;
+use std::time::Duration;
+use tonic_lnd::lnrpc::{Invoice, SendRequest};
+use uuid::Uuid;
+
+/// How long to wait for payments to settle and events to arrive.
+pub const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Invoice amount used by the tests, small enough for any funded channel.
+pub const AMOUNT_MSAT: u64 = 21_000;
+
+fn env(name: &str) -> String {
+    std::env::var(name).unwrap_or_else(|_| panic!("{name} must be set for the regtest tests"))
+}
+
+fn node_id(name: &str) -> NodeId {
+    NodeId::PublicKey(
+        PublicKey::from_str(&env(name)).unwrap_or_else(|e| panic!("{name} is invalid: {e}")),
+    )
+}
+
+/// Connects to the LND node.
+///
+/// `REGTEST_LND_ADDRESS` is the gRPC URL, e.g. `https://127.0.0.1:10001`.
+pub async fn lnd() -> LndNode {
+    LndNode::new(LndConnection {
+        id: node_id("REGTEST_LND_PUBKEY"),
+        address: env("REGTEST_LND_ADDRESS"),
+        macaroon: env("REGTEST_LND_MACAROON"),
+        cert: env("REGTEST_LND_TLS_CERT"),
+        proxy: None,
+    })
+    .await
+    .expect("LND node is reachable")
+}
+
+/// Connects to the CLN node.
+///
+/// `REGTEST_CLN_ADDRESS` is the gRPC URL, e.g. `https://127.0.0.1:11001`.
+pub async fn cln() -> ClnNode {
+    ClnNode::new(ClnConnection {
+        id: node_id("REGTEST_CLN_PUBKEY"),
+        address: env("REGTEST_CLN_ADDRESS"),
+        ca_cert: env("REGTEST_CLN_CA_CERT"),
+        client_cert: env("REGTEST_CLN_CLIENT_CERT"),
+        client_key: env("REGTEST_CLN_CLIENT_KEY"),
+        proxy: None,
+    })
+    .await
+    .expect("CLN node is reachable")
+}
+
+/// A label that doesn't collide with earlier runs against the same network.
+pub fn unique_label(prefix: &str) -> String {
+    format!("{prefix}-{}", Uuid::now_v7())
+}
+
+fn payment_hash(bytes: &[u8]) -> PaymentHash {
+    PaymentHash(bytes.try_into().expect("payment hash is 32 bytes"))
+}
+
+/// Creates an invoice on the LND node, returning its payment request and hash.
+pub async fn lnd_invoice(node: &LndNode, amount_msat: u64, memo: &str) -> (String, PaymentHash) {
+    let response = node
+        .client
+        .lock()
+        .await
+        .lightning()
+        .add_invoice(Invoice {
+            memo: memo.to_string(),
+            value_msat: amount_msat as i64,
+            ..Default::default()
+        })
+        .await
+        .expect("LND creates the invoice")
+        .into_inner();
+
+    (response.payment_request, payment_hash(&response.r_hash))
+}
+
+/// Pays an invoice from the LND node and waits for the result.
+pub async fn lnd_pay(node: &LndNode, payment_request: &str) {
+    let response = node
+        .client
+        .lock()
+        .await
+        .lightning()
+        .send_payment_sync(SendRequest {
+            payment_request: payment_request.to_string(),
+            ..Default::default()
+        })
+        .await
+        .expect("LND accepts the payment")
+        .into_inner();
+
+    assert!(
+        response.payment_error.is_empty(),
+        "LND payment failed: {}",
+        response.payment_error
+    );
+}
+
+/// Creates an invoice on the CLN node, returning its payment request and hash.
+pub async fn cln_invoice(node: &ClnNode, amount_msat: u64, label: &str) -> (String, PaymentHash) {
+    let response = node
+        .client
+        .lock()
+        .await
+        .invoice(InvoiceRequest {
+            amount_msat: Some(AmountOrAny {
+                value: Some(amount_or_any::Value::Amount(Amount { msat: amount_msat })),
+            }),
+            label: label.to_string(),
+            description: label.to_string(),
+            ..Default::default()
+        })
+        .await
+        .expect("CLN creates the invoice")
+        .into_inner();
+
+    (response.bolt11, payment_hash(&response.payment_hash))
+}
+
+/// Pays an invoice from the CLN node and waits for the result.
+pub async fn cln_pay(node: &ClnNode, bolt11: &str) {
+    node.client
+        .lock()
+        .await
+        .pay(PayRequest {
+            bolt11: bolt11.to_string(),
+            ..Default::default()
+        })
+        .await
+        .expect("CLN payment succeeds");
+}
+
+/// Polls `check` until it returns a value, failing the test after `TIMEOUT`.
+pub async fn eventually<T, F, Fut>(mut check: F) -> T
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Option<T>>,
+{
+    tokio::time::timeout(TIMEOUT, async {
+        loop {
+            if let Some(value) = check().await {
+                return value;
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    })
+    .await
+    .expect("condition was not met in time")
+}
@@ -0,0 +1,97 @@
+//! `LndNode` against the regtest LND node, with the CLN node as counterparty.
+
+use crate::fixtures::{
+    AMOUNT_MSAT, TIMEOUT, cln, cln_invoice, cln_pay, eventually, lnd, lnd_invoice, lnd_pay,
+    unique_label,
+};
+use bitcoin::Network;
+use futures::StreamExt;
+use nodegaze_lightning::LightningClient;
+use nodegaze_lightning::events::{LNDEvent, NodeSpecificEvent};
+use nodegaze_lightning::types::{InvoiceStatus, PaymentState};
+
+#[tokio::test]
+async fn reports_regtest_network() {
+    let node = lnd().await;
+    assert_eq!(node.get_network().await.unwrap(), Network::Regtest);
+    node.get_wallet_balance().await.unwrap();
+}
+
+#[tokio::test]
+async fn lists_channel_with_cln() {
+    let (node, peer) = (lnd().await, cln().await);
+    let peer_pubkey = peer.get_info().pubkey;
+
+    let channels = node.list_channels().await.unwrap();
+    let channel = channels
+        .iter()
+        .find(|channel| channel.remote_pubkey == Some(peer_pubkey))
+        .expect("LND has a channel with the CLN node");
+
+    let details = node.get_channel_info(&channel.chan_id).await.unwrap();
+    assert_eq!(details.remote_pubkey, peer_pubkey);
+    assert!(details.capacity_sat > 0);
+}
+
+#[tokio::test]
+async fn settles_invoice_paid_by_cln() {
+    let (node, peer) = (lnd().await, cln().await);
+    let (payment_request, hash) =
+        lnd_invoice(&node, AMOUNT_MSAT, &unique_label("lnd-invoice")).await;
+    cln_pay(&peer, &payment_request).await;
+
+    let node = &node;
+    let invoice = eventually(|| async move {
+        let invoice = node.get_invoice_details(&hash).await.ok()?;
+        matches!(invoice.state, InvoiceStatus::Settled).then_some(invoice)
+    })
+    .await;
+    assert_eq!(invoice.value_msat, AMOUNT_MSAT);
+    assert_eq!(invoice.payment_request, payment_request);
+
+    let invoices = node.list_invoices().await.unwrap();
+    assert!(
+        invoices
+            .iter()
+            .any(|invoice| invoice.payment_hash == hex::encode(hash.0))
+    );
+}
+
+#[tokio::test]
+async fn records_payment_to_cln() {
+    let (node, peer) = (lnd().await, cln().await);
+    let (bolt11, hash) = cln_invoice(&peer, AMOUNT_MSAT, &unique_label("lnd-payment")).await;
+    lnd_pay(&node, &bolt11).await;
+
+    let payments = node.list_payments().await.unwrap();
+    let payment = payments
+        .iter()
+        .find(|payment| payment.payment_hash == hex::encode(hash.0))
+        .expect("payment is listed");
+    assert!(matches!(payment.state, PaymentState::Settled));
+
+    let details = node.get_payment_details(&hash).await.unwrap();
+    assert_eq!(details.destination_pubkey, Some(peer.get_info().pubkey));
+}
+
+#[tokio::test]
+async fn streams_invoice_events() {
+    let mut node = lnd().await;
+    let mut events = node.stream_events().await.unwrap();
+    let (_, hash) = lnd_invoice(&node, AMOUNT_MSAT, &unique_label("lnd-event")).await;
+
+    tokio::time::timeout(TIMEOUT, async {
+        while let Some(event) = events.next().await {
+            if matches!(
+                &event,
+                NodeSpecificEvent::LND(LNDEvent::InvoiceCreated { hash: created, .. })
+                    if created[..] == hash.0[..]
+            ) {
+                return;
+            }
+        }
+        panic!("event stream ended");
+    })
+    .await
+    .expect("invoice creation is streamed");
+}
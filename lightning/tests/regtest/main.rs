@@ -0,0 +1,9 @@
+//! End-to-end tests of the node clients against a regtest network.
+//!
+//! Built only with the `regtest` feature:
+//! `cargo test -p nodegaze-lightning --features regtest --test regtest`.
+//! See `fixtures` for the network the tests expect.
+
+mod cln;
+mod fixtures;
+mod lnd;